@@ -0,0 +1,195 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The values of the types defined by the application, e.g. geo points or ip addresses, which are
+//! carried as the bytes encoded by their types along with the tags naming the types, see
+//! `CustomObject`. A type is registered by its tag on startup by `register_custom_type`, before
+//! any value of it is decoded, compared or hashed, and the values of an unregistered tag fail to
+//! be decoded. The predicates on the values beyond the equality and the order, e.g. whether a
+//! point is within a radius of another, are registered by their names for the type by
+//! `register_custom_predicate`.
+
+use crate::stable_hash::xxh64;
+use crate::STABLE_HASH_SEED;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::io;
+use std::sync::{Arc, RwLock};
+
+/// A type defined by the application, whose values are stored and transported as bytes
+pub trait CustomType: Sized + Send + Sync + 'static {
+    /// The tag naming the type, which is unique among the registered types
+    const TAG: &'static str;
+
+    fn encode(&self) -> Vec<u8>;
+
+    fn decode(bytes: &[u8]) -> io::Result<Self>;
+
+    /// The order of the values, where the equal values are `Ordering::Equal`, or `None` if they
+    /// are incomparable
+    fn compare(&self, other: &Self) -> Option<Ordering>;
+
+    /// The hash of the value, which must be the same for the equal values, and be stable across
+    /// platforms and processes if the values are routed among servers, see `Object::stable_hash`
+    fn hash(&self) -> u64;
+}
+
+/// A predicate on two values of a custom type, e.g. whether a point is within a radius of another
+pub type CustomPredicate = Arc<dyn Fn(&CustomObject, &CustomObject) -> bool + Send + Sync>;
+
+// the functions of a registered type on the encoded bytes of its values
+struct CustomTypeFns {
+    check: fn(&[u8]) -> io::Result<()>,
+    compare: fn(&[u8], &[u8]) -> Option<Ordering>,
+    hash: fn(&[u8]) -> Option<u64>,
+}
+
+#[derive(Default)]
+struct CustomRegistry {
+    types: HashMap<String, CustomTypeFns>,
+    // by the tag of the type and the name of the predicate
+    predicates: HashMap<(String, String), CustomPredicate>,
+}
+
+lazy_static! {
+    static ref CUSTOM_REGISTRY: RwLock<CustomRegistry> = RwLock::new(CustomRegistry::default());
+}
+
+fn check_of<T: CustomType>(bytes: &[u8]) -> io::Result<()> {
+    T::decode(bytes).map(|_| ())
+}
+
+fn compare_of<T: CustomType>(left: &[u8], right: &[u8]) -> Option<Ordering> {
+    let left = T::decode(left).ok()?;
+    let right = T::decode(right).ok()?;
+    left.compare(&right)
+}
+
+fn hash_of<T: CustomType>(bytes: &[u8]) -> Option<u64> {
+    T::decode(bytes).ok().map(|value| value.hash())
+}
+
+fn lock_poisoned() -> io::Error {
+    io::Error::other("lock poisoned")
+}
+
+fn not_registered(tag: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, format!("custom type `{}` is not registered", tag))
+}
+
+/// Register the type by its tag, which replaces the type registered before by the same tag
+pub fn register_custom_type<T: CustomType>() -> io::Result<()> {
+    let fns = CustomTypeFns { check: check_of::<T>, compare: compare_of::<T>, hash: hash_of::<T> };
+    let mut registry = CUSTOM_REGISTRY.write().map_err(|_| lock_poisoned())?;
+    registry.types.insert(T::TAG.to_owned(), fns);
+    Ok(())
+}
+
+/// Whether the type of the tag is registered
+pub fn is_custom_type_registered(tag: &str) -> bool {
+    CUSTOM_REGISTRY.read().map(|registry| registry.types.contains_key(tag)).unwrap_or(false)
+}
+
+/// Register a predicate on the values of the type by its name, e.g. `within_radius` of points,
+/// whose values failed to be decoded never satisfy the predicate
+pub fn register_custom_predicate<T, F>(name: &str, func: F) -> io::Result<()>
+where
+    T: CustomType,
+    F: Fn(&T, &T) -> bool + Send + Sync + 'static,
+{
+    let predicate: CustomPredicate =
+        Arc::new(move |left, right| match (left.to_value::<T>(), right.to_value::<T>()) {
+            (Ok(left), Ok(right)) => func(&left, &right),
+            _ => false,
+        });
+    let mut registry = CUSTOM_REGISTRY.write().map_err(|_| lock_poisoned())?;
+    registry.predicates.insert((T::TAG.to_owned(), name.to_owned()), predicate);
+    Ok(())
+}
+
+/// Get the predicate registered by the name for the type of the tag
+pub fn get_custom_predicate(tag: &str, name: &str) -> io::Result<CustomPredicate> {
+    let registry = CUSTOM_REGISTRY.read().map_err(|_| lock_poisoned())?;
+    if !registry.types.contains_key(tag) {
+        return Err(not_registered(tag));
+    }
+    registry.predicates.get(&(tag.to_owned(), name.to_owned())).cloned().ok_or_else(|| {
+        let msg = format!("predicate `{}` of custom type `{}` is not registered", name, tag);
+        io::Error::new(io::ErrorKind::InvalidInput, msg)
+    })
+}
+
+/// A value of a custom type, as the tag of the type and the bytes encoded by the type
+#[derive(Clone, Debug)]
+pub struct CustomObject {
+    tag: String,
+    bytes: Vec<u8>,
+}
+
+impl CustomObject {
+    /// A value by its encoded bytes, whose type may be not registered yet, see `check()`
+    pub fn new<S: Into<String>>(tag: S, bytes: Vec<u8>) -> Self {
+        CustomObject { tag: tag.into(), bytes }
+    }
+
+    pub fn from_value<T: CustomType>(value: &T) -> Self {
+        CustomObject { tag: T::TAG.to_owned(), bytes: value.encode() }
+    }
+
+    pub fn tag(&self) -> &str {
+        self.tag.as_str()
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        self.bytes.as_slice()
+    }
+
+    /// Decode the value, which fails if it is of another type
+    pub fn to_value<T: CustomType>(&self) -> io::Result<T> {
+        if self.tag != T::TAG {
+            let msg = format!("custom type `{}` is not `{}`", self.tag, T::TAG);
+            Err(io::Error::new(io::ErrorKind::InvalidData, msg))
+        } else {
+            T::decode(&self.bytes)
+        }
+    }
+
+    /// Check that the type of the value is registered, and its bytes can be decoded by the type
+    pub fn check(&self) -> io::Result<()> {
+        let registry = CUSTOM_REGISTRY.read().map_err(|_| lock_poisoned())?;
+        let fns = registry.types.get(&self.tag).ok_or_else(|| not_registered(&self.tag))?;
+        (fns.check)(&self.bytes)
+    }
+
+    /// Compare to a value of the same type by the registered type, or `None` if the values are of
+    /// different types, or the type is not registered
+    pub fn compare(&self, other: &CustomObject) -> Option<Ordering> {
+        if self.tag != other.tag {
+            return None;
+        }
+        let registry = CUSTOM_REGISTRY.read().ok()?;
+        let fns = registry.types.get(&self.tag)?;
+        (fns.compare)(&self.bytes, &other.bytes)
+    }
+
+    /// The hash of the value by the registered type, or of the bytes if the type is not
+    /// registered, whose values never equal to any
+    pub fn hash_value(&self) -> u64 {
+        let hash = CUSTOM_REGISTRY.read().ok().and_then(|registry| {
+            registry.types.get(&self.tag).and_then(|fns| (fns.hash)(&self.bytes))
+        });
+        hash.unwrap_or_else(|| xxh64(&self.bytes, STABLE_HASH_SEED))
+    }
+}
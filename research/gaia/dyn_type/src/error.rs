@@ -38,6 +38,7 @@ impl fmt::Display for CastError {
             RawType::Float => write!(f, "can't cast f64 into {}", self.target),
             RawType::Blob(len) => write!(f, "can't cast Blob({}) into {}", len, self.target),
             RawType::String => write!(f, "can't cast String into {}", self.target),
+            RawType::Custom => write!(f, "can't cast custom object into {}", self.target),
             RawType::Unknown => write!(f, "can't cast unknown dyn type into {}", self.target),
         }
     }
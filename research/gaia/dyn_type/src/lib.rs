@@ -19,17 +19,21 @@ extern crate pegasus;
 
 extern crate dyn_clonable;
 
+pub mod custom;
 pub mod error;
 pub mod object;
 pub mod serde_dyn;
 #[macro_use]
 pub mod macros;
 pub mod serde;
+pub mod stable_hash;
 
+pub use custom::{register_custom_predicate, register_custom_type, CustomObject, CustomType};
 use dyn_clonable::*;
 pub use error::CastError;
 pub use object::{BorrowObject, Object, OwnedOrRef, Primitives};
 pub use serde_dyn::{de_dyn_obj, register_type};
+pub use stable_hash::STABLE_HASH_SEED;
 use std::any::Any;
 use std::fmt::Debug;
 use std::io;
@@ -13,7 +13,8 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
-use crate::{try_downcast, try_downcast_ref, CastError, DynType};
+use crate::stable_hash::XxHash64;
+use crate::{try_downcast, try_downcast_ref, CastError, CustomObject, DynType};
 use core::any::TypeId;
 use std::any::Any;
 use std::borrow::Cow;
@@ -31,6 +32,7 @@ pub enum RawType {
     Float,
     String,
     Blob(usize),
+    Custom,
     Unknown,
 }
 
@@ -68,7 +70,49 @@ macro_rules! try_transmute {
     };
 }
 
+/// The tags leading the canonical bytes of the values for their stable hashes, which must never
+/// be changed, see `Object::stable_hash`
+const STABLE_TAG_UNKNOWN: u8 = 0;
+const STABLE_TAG_INTEGER: u8 = 1;
+const STABLE_TAG_FLOAT: u8 = 2;
+const STABLE_TAG_BYTES: u8 = 3;
+const STABLE_TAG_CUSTOM: u8 = 4;
+
+fn write_stable_bytes(hasher: &mut XxHash64, bytes: &[u8]) {
+    hasher.write(&[STABLE_TAG_BYTES]);
+    hasher.write(bytes);
+}
+
 impl Primitives {
+    /// The stable hash of the number, see `Object::stable_hash`
+    pub fn stable_hash(&self) -> u64 {
+        let mut hasher = XxHash64::default();
+        self.write_stable(&mut hasher);
+        hasher.finish()
+    }
+
+    fn write_stable(&self, hasher: &mut XxHash64) {
+        let integer = match *self {
+            Primitives::Byte(v) => v as i64,
+            Primitives::Integer(v) => v as i64,
+            Primitives::Long(v) => v,
+            // the integral floats are equal to the integers, so they are hashed as the integers
+            Primitives::Float(v)
+                if v.fract() == 0.0 && v >= i64::MIN as f64 && v < i64::MAX as f64 =>
+            {
+                v as i64
+            }
+            Primitives::Float(v) => {
+                let v = if v.is_nan() { f64::NAN } else { v };
+                hasher.write(&[STABLE_TAG_FLOAT]);
+                hasher.write(&v.to_bits().to_le_bytes());
+                return;
+            }
+        };
+        hasher.write(&[STABLE_TAG_INTEGER]);
+        hasher.write(&integer.to_le_bytes());
+    }
+
     #[inline]
     pub fn raw_type(&self) -> RawType {
         match self {
@@ -429,6 +473,8 @@ pub enum Object {
     String(String),
     Blob(Box<[u8]>),
     DynOwned(Box<dyn DynType>),
+    /// A value of a type registered by the application, see `crate::custom`
+    Custom(CustomObject),
 }
 
 /// Try to borrow an immutable reference of [crate::Object].
@@ -439,6 +485,7 @@ pub enum BorrowObject<'a> {
     Blob(&'a [u8]),
     /// To borrow from `Object::DynOwned`, and it can be cloned back to `Object::DynOwned`
     DynRef(&'a Box<dyn DynType>),
+    Custom(&'a CustomObject),
 }
 
 impl Object {
@@ -448,7 +495,28 @@ impl Object {
             Object::String(_) => RawType::String,
             Object::Blob(b) => RawType::Blob(b.len()),
             Object::DynOwned(_) => RawType::Unknown,
-        }
+            Object::Custom(_) => RawType::Custom,
+        }
+    }
+
+    /// A hash of the value which is identical across platforms, processes and versions, by which
+    /// the data can be routed consistently among servers, e.g. grouped by the values, unlike the
+    /// `Hash` that is not guaranteed to be stable. It is the XXH64 hash with the fixed seed
+    /// `STABLE_HASH_SEED` of the canonical bytes of the value, which lead by a tag of the kind:
+    /// * an integer, or a float of an integral value within i64, is `1` followed by the value as
+    ///   an i64 in 8 little-endian bytes, so that the equal numbers are hashed the same;
+    /// * any other float is `2` followed by the bits of the value as a u64 in 8 little-endian
+    ///   bytes, where all NaNs are taken as the same;
+    /// * a string is `3` followed by its UTF-8 bytes, and a blob is `3` followed by its bytes;
+    /// * a custom value is `4` followed by the UTF-8 bytes of its tag, a `0` byte, and the hash
+    ///   registered for its type as a u64 in 8 little-endian bytes, see `CustomType::hash`;
+    /// * a dynamic value is hashed as any of the above if it is a number, a string or bytes,
+    ///   or is `0` alone otherwise.
+    ///
+    /// The canonical bytes and the seed must never be changed, which are protected by the
+    /// golden values of the tests.
+    pub fn stable_hash(&self) -> u64 {
+        self.as_borrow().stable_hash()
     }
 
     pub fn as_borrow(&self) -> BorrowObject {
@@ -457,6 +525,7 @@ impl Object {
             Object::String(v) => BorrowObject::String(v.as_str()),
             Object::Blob(v) => BorrowObject::Blob(v.as_ref()),
             Object::DynOwned(v) => BorrowObject::DynRef(v),
+            Object::Custom(v) => BorrowObject::Custom(v),
         }
     }
 
@@ -580,6 +649,7 @@ impl Object {
             Object::Blob(b) => Ok(String::from_utf8_lossy(b)),
             Object::DynOwned(x) => try_downcast!(x, String, as_str).map(|r| Cow::Borrowed(r)),
             Object::Primitive(p) => Err(CastError::new::<String>(p.raw_type())),
+            Object::Custom(_) => Err(CastError::new::<String>(RawType::Custom)),
         }
     }
 
@@ -590,6 +660,7 @@ impl Object {
             Object::String(str) => Ok(str.as_bytes()),
             Object::Blob(v) => Ok(v.as_ref()),
             Object::DynOwned(x) => try_downcast!(x, Vec<u8>, as_slice),
+            Object::Custom(_) => Err(CastError::new::<&[u8]>(RawType::Custom)),
         }
     }
 
@@ -604,6 +675,15 @@ impl Object {
                 try_transmute!(x, T, RawType::Blob(x.len())).map(|v| OwnedOrRef::Ref(v))
             }
             Object::DynOwned(x) => try_downcast_ref!(x, T).map(|v| OwnedOrRef::Ref(v)),
+            Object::Custom(x) => try_transmute!(x, T, RawType::Custom).map(|v| OwnedOrRef::Ref(v)),
+        }
+    }
+
+    #[inline]
+    pub fn as_custom(&self) -> Result<&CustomObject, CastError> {
+        match self {
+            Object::Custom(v) => Ok(v),
+            _ => Err(CastError::new::<CustomObject>(self.raw_type())),
         }
     }
 
@@ -618,18 +698,56 @@ impl Object {
                 }
             }
             Object::Primitive(p) => Err(CastError::new::<String>(p.raw_type())),
+            Object::Custom(_) => Err(CastError::new::<String>(RawType::Custom)),
             Object::Blob(_) => unimplemented!(),
         }
     }
 }
 
 impl<'a> BorrowObject<'a> {
+    /// The stable hash of the value, see `Object::stable_hash`
+    pub fn stable_hash(&self) -> u64 {
+        let mut hasher = XxHash64::default();
+        match self {
+            BorrowObject::Primitive(p) => p.write_stable(&mut hasher),
+            BorrowObject::String(v) => write_stable_bytes(&mut hasher, v.as_bytes()),
+            BorrowObject::Blob(v) => write_stable_bytes(&mut hasher, v),
+            BorrowObject::Custom(v) => {
+                hasher.write(&[STABLE_TAG_CUSTOM]);
+                hasher.write(v.tag().as_bytes());
+                hasher.write(&[0]);
+                hasher.write(&v.hash_value().to_le_bytes());
+            }
+            BorrowObject::DynRef(_) => {
+                if let Ok(p) = self.as_primitive() {
+                    p.write_stable(&mut hasher);
+                } else if let Ok(v) = self.as_str() {
+                    write_stable_bytes(&mut hasher, v.as_bytes());
+                } else if let Ok(v) = self.as_bytes() {
+                    write_stable_bytes(&mut hasher, v);
+                } else {
+                    hasher.write(&[STABLE_TAG_UNKNOWN]);
+                }
+            }
+        }
+        hasher.finish()
+    }
+
     pub fn raw_type(&self) -> RawType {
         match self {
             BorrowObject::Primitive(p) => p.raw_type(),
             BorrowObject::String(_) => RawType::String,
             BorrowObject::Blob(b) => RawType::Blob(b.len()),
             BorrowObject::DynRef(_) => RawType::Unknown,
+            BorrowObject::Custom(_) => RawType::Custom,
+        }
+    }
+
+    #[inline]
+    pub fn as_custom(&self) -> Result<&CustomObject, CastError> {
+        match self {
+            BorrowObject::Custom(v) => Ok(*v),
+            _ => Err(CastError::new::<CustomObject>(self.raw_type())),
         }
     }
 
@@ -753,6 +871,7 @@ impl<'a> BorrowObject<'a> {
             BorrowObject::Blob(b) => Ok(String::from_utf8_lossy(b)),
             BorrowObject::DynRef(x) => try_downcast!(x, String, as_str).map(|r| Cow::Borrowed(r)),
             BorrowObject::Primitive(p) => Err(CastError::new::<String>(p.raw_type())),
+            BorrowObject::Custom(_) => Err(CastError::new::<String>(RawType::Custom)),
         }
     }
 
@@ -763,6 +882,7 @@ impl<'a> BorrowObject<'a> {
             BorrowObject::String(v) => Ok(v.as_bytes()),
             BorrowObject::Blob(v) => Ok(*v),
             BorrowObject::DynRef(v) => try_downcast!(v, Vec<u8>, as_slice),
+            BorrowObject::Custom(_) => Err(CastError::new::<&[u8]>(RawType::Custom)),
         }
     }
 
@@ -772,6 +892,7 @@ impl<'a> BorrowObject<'a> {
             BorrowObject::String(s) => Some(Object::String((*s).to_owned())),
             BorrowObject::Blob(b) => Some(Object::Blob(b.to_vec().into_boxed_slice())),
             BorrowObject::DynRef(d) => Some(Object::DynOwned((*d).clone())),
+            BorrowObject::Custom(v) => Some(Object::Custom((*v).clone())),
         }
     }
 }
@@ -782,6 +903,9 @@ impl PartialEq for Object {
             Object::Primitive(p) => other.as_primitive().map(|o| p == &o).unwrap_or(false),
             Object::Blob(v) => other.as_bytes().map(|o| o.eq(v.as_ref())).unwrap_or(false),
             Object::String(v) => other.as_str().map(|o| o.eq(v.as_str())).unwrap_or(false),
+            Object::Custom(v) => {
+                other.as_custom().map(|o| v.compare(o) == Some(Ordering::Equal)).unwrap_or(false)
+            }
             // TODO(longbin) Should be able to compare a DynType
             Object::DynOwned(_) => false,
         }
@@ -798,6 +922,7 @@ impl PartialOrd for Object {
             Object::String(v) => {
                 other.as_str().map(|o| v.as_str().partial_cmp(o.as_ref())).unwrap_or(None)
             }
+            Object::Custom(v) => other.as_custom().map(|o| v.compare(o)).unwrap_or(None),
             // TODO(longbin) Should be able to compare a DynType
             Object::DynOwned(_) => None,
         }
@@ -810,6 +935,9 @@ impl<'a> PartialEq for BorrowObject<'a> {
             BorrowObject::Primitive(p) => other.as_primitive().map(|o| p == &o).unwrap_or(false),
             BorrowObject::String(v) => other.as_str().map(|o| o.eq(*v)).unwrap_or(false),
             BorrowObject::Blob(v) => other.as_bytes().map(|o| *v == o).unwrap_or(false),
+            BorrowObject::Custom(v) => {
+                other.as_custom().map(|o| v.compare(o) == Some(Ordering::Equal)).unwrap_or(false)
+            }
             // TODO(longbin) Should be able to compare a DynType
             BorrowObject::DynRef(_) => false,
         }
@@ -826,6 +954,7 @@ impl<'a> PartialOrd for BorrowObject<'a> {
                 other.as_str().map(|o| (*v).partial_cmp(o.as_ref())).unwrap_or(None)
             }
             BorrowObject::Blob(v) => other.as_bytes().map(|o| (*v).partial_cmp(o)).unwrap_or(None),
+            BorrowObject::Custom(v) => other.as_custom().map(|o| v.compare(o)).unwrap_or(None),
             // TODO(longbin) Should be able to compare a DynType
             BorrowObject::DynRef(_) => None,
         }
@@ -873,6 +1002,10 @@ impl Hash for Object {
             Object::Blob(b) => {
                 b.hash(state);
             }
+            Object::Custom(v) => {
+                v.tag().hash(state);
+                v.hash_value().hash(state);
+            }
             // TODO(longbin) Should be able to hash a DynType
             Object::DynOwned(_) => {
                 unimplemented!()
@@ -881,6 +1014,14 @@ impl Hash for Object {
     }
 }
 
+/// The objects are exchanged by `exchange_by` with their stable hashes, so the equal objects, e.g.
+/// an integer and a float of the same value, go to the same worker.
+impl pegasus::api::key::StableKey for Object {
+    fn write_stable(&self, hasher: &mut pegasus::api::key::StableHasher) {
+        hasher.write_u64(self.stable_hash());
+    }
+}
+
 impl From<i8> for Object {
     fn from(v: i8) -> Self {
         Object::Primitive(Primitives::Byte(v))
@@ -1001,6 +1142,12 @@ impl From<String> for Object {
     }
 }
 
+impl From<CustomObject> for Object {
+    fn from(v: CustomObject) -> Self {
+        Object::Custom(v)
+    }
+}
+
 pub enum OwnedOrRef<'a, T> {
     Owned(T),
     Ref(&'a T),
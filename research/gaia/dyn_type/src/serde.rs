@@ -13,7 +13,7 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
-use crate::{de_dyn_obj, Object, Primitives};
+use crate::{de_dyn_obj, CustomObject, Object, Primitives};
 use core::any::TypeId;
 use pegasus::codec::{Decode, Encode, ReadExt, WriteExt};
 use std::io;
@@ -93,6 +93,15 @@ impl Encode for Object {
                 bytes.write_to(writer)?;
                 Ok(())
             }
+            Object::Custom(custom) => {
+                writer.write_u8(4)?;
+                let tag = custom.tag().as_bytes();
+                writer.write_u32(tag.len() as u32)?;
+                writer.write_all(tag)?;
+                writer.write_u64(custom.bytes().len() as u64)?;
+                writer.write_all(custom.bytes())?;
+                Ok(())
+            }
         }
     }
 }
@@ -121,11 +130,19 @@ impl Decode for Object {
             3 => {
                 let bytes = <Vec<u8>>::read_from(reader)?;
                 let mut bytes_reader = &bytes[0..];
-                let number = <u64>::read_from(&mut bytes_reader)?;
+                let number = <u128>::read_from(&mut bytes_reader)?;
                 let t: TypeId = unsafe { std::mem::transmute(number) };
                 let obj = de_dyn_obj(&t, &mut bytes_reader)?;
                 Ok(Object::DynOwned(obj))
             }
+            4 => {
+                let tag = <String>::read_from(reader)?;
+                let bytes = <Vec<u8>>::read_from(reader)?;
+                let custom = CustomObject::new(tag, bytes);
+                // fails with the tag if its type is not registered
+                custom.check()?;
+                Ok(Object::Custom(custom))
+            }
             _ => Err(io::Error::new(io::ErrorKind::Other, "not supported")),
         }
     }
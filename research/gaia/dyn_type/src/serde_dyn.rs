@@ -90,7 +90,7 @@ impl<T: Any + Send + Sync + Clone + Debug + Encode> DynType for T {
     fn to_bytes(&self) -> io::Result<Vec<u8>> {
         let mut bytes = vec![];
         let t = TypeId::of::<T>();
-        let number: u64 = unsafe { std::mem::transmute(t) };
+        let number: u128 = unsafe { std::mem::transmute(t) };
         number.write_to(&mut bytes)?;
         self.write_to(&mut bytes)?;
         Ok(bytes)
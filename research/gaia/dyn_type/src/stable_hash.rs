@@ -0,0 +1,174 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+
+//! The XXH64 hash with a fixed seed, which is identical across platforms, processes and
+//! versions, unlike the `DefaultHasher` of std, so that the data can be routed by the hashes of
+//! their values consistently among servers, see `Object::stable_hash`.
+//!
+//! The algorithm follows the specification of xxHash:
+//! https://github.com/Cyan4973/xxHash/blob/dev/doc/xxhash_spec.md
+
+use std::hash::Hasher;
+
+/// The seed of the stable hashes, which must never be changed, or the data routed by the
+/// stable hashes of earlier versions would be routed differently.
+pub const STABLE_HASH_SEED: u64 = 0;
+
+const PRIME64_1: u64 = 0x9E37_79B1_85EB_CA87;
+const PRIME64_2: u64 = 0xC2B2_AE3D_27D4_EB4F;
+const PRIME64_3: u64 = 0x1656_67B1_9E37_79F9;
+const PRIME64_4: u64 = 0x85EB_CA77_C2B2_AE63;
+const PRIME64_5: u64 = 0x27D4_EB2F_1656_67C5;
+
+const STRIPE_LEN: usize = 32;
+
+/// The streaming XXH64 hasher, which gives the same hash however the bytes are split by writes.
+///
+/// Note that the `write_*` methods of `Hasher` for integers write the bytes in native endian,
+/// so the canonical bytes should be written by `write` instead to be platform independent.
+#[derive(Clone, Debug)]
+pub struct XxHash64 {
+    seed: u64,
+    acc: [u64; 4],
+    buffer: [u8; STRIPE_LEN],
+    buffered: usize,
+    total_len: u64,
+}
+
+impl XxHash64 {
+    pub fn with_seed(seed: u64) -> Self {
+        XxHash64 {
+            seed,
+            acc: [
+                seed.wrapping_add(PRIME64_1).wrapping_add(PRIME64_2),
+                seed.wrapping_add(PRIME64_2),
+                seed,
+                seed.wrapping_sub(PRIME64_1),
+            ],
+            buffer: [0; STRIPE_LEN],
+            buffered: 0,
+            total_len: 0,
+        }
+    }
+
+    fn consume_stripe(acc: &mut [u64; 4], stripe: &[u8]) {
+        for (i, lane) in acc.iter_mut().enumerate() {
+            *lane = round(*lane, read_u64(&stripe[i * 8..]));
+        }
+    }
+}
+
+impl Default for XxHash64 {
+    fn default() -> Self {
+        XxHash64::with_seed(STABLE_HASH_SEED)
+    }
+}
+
+impl Hasher for XxHash64 {
+    fn write(&mut self, mut bytes: &[u8]) {
+        self.total_len += bytes.len() as u64;
+        if self.buffered > 0 {
+            let fill = std::cmp::min(STRIPE_LEN - self.buffered, bytes.len());
+            self.buffer[self.buffered..self.buffered + fill].copy_from_slice(&bytes[..fill]);
+            self.buffered += fill;
+            bytes = &bytes[fill..];
+            if self.buffered < STRIPE_LEN {
+                return;
+            }
+            let buffer = self.buffer;
+            XxHash64::consume_stripe(&mut self.acc, &buffer);
+            self.buffered = 0;
+        }
+        while bytes.len() >= STRIPE_LEN {
+            XxHash64::consume_stripe(&mut self.acc, &bytes[..STRIPE_LEN]);
+            bytes = &bytes[STRIPE_LEN..];
+        }
+        self.buffer[..bytes.len()].copy_from_slice(bytes);
+        self.buffered = bytes.len();
+    }
+
+    fn finish(&self) -> u64 {
+        let mut hash = if self.total_len >= STRIPE_LEN as u64 {
+            let [v1, v2, v3, v4] = self.acc;
+            let mut hash = v1
+                .rotate_left(1)
+                .wrapping_add(v2.rotate_left(7))
+                .wrapping_add(v3.rotate_left(12))
+                .wrapping_add(v4.rotate_left(18));
+            for v in self.acc.iter() {
+                hash = merge_round(hash, *v);
+            }
+            hash
+        } else {
+            self.seed.wrapping_add(PRIME64_5)
+        };
+        hash = hash.wrapping_add(self.total_len);
+
+        let mut rest = &self.buffer[..self.buffered];
+        while rest.len() >= 8 {
+            hash ^= round(0, read_u64(rest));
+            hash = hash.rotate_left(27).wrapping_mul(PRIME64_1).wrapping_add(PRIME64_4);
+            rest = &rest[8..];
+        }
+        if rest.len() >= 4 {
+            hash ^= (read_u32(rest) as u64).wrapping_mul(PRIME64_1);
+            hash = hash.rotate_left(23).wrapping_mul(PRIME64_2).wrapping_add(PRIME64_3);
+            rest = &rest[4..];
+        }
+        for byte in rest {
+            hash ^= (*byte as u64).wrapping_mul(PRIME64_5);
+            hash = hash.rotate_left(11).wrapping_mul(PRIME64_1);
+        }
+
+        hash ^= hash >> 33;
+        hash = hash.wrapping_mul(PRIME64_2);
+        hash ^= hash >> 29;
+        hash = hash.wrapping_mul(PRIME64_3);
+        hash ^= hash >> 32;
+        hash
+    }
+}
+
+/// The XXH64 hash of the bytes with the seed
+pub fn xxh64(bytes: &[u8], seed: u64) -> u64 {
+    let mut hasher = XxHash64::with_seed(seed);
+    hasher.write(bytes);
+    hasher.finish()
+}
+
+#[inline]
+fn round(acc: u64, input: u64) -> u64 {
+    acc.wrapping_add(input.wrapping_mul(PRIME64_2)).rotate_left(31).wrapping_mul(PRIME64_1)
+}
+
+#[inline]
+fn merge_round(acc: u64, val: u64) -> u64 {
+    (acc ^ round(0, val)).wrapping_mul(PRIME64_1).wrapping_add(PRIME64_4)
+}
+
+#[inline]
+fn read_u64(bytes: &[u8]) -> u64 {
+    let mut buf = [0; 8];
+    buf.copy_from_slice(&bytes[..8]);
+    u64::from_le_bytes(buf)
+}
+
+#[inline]
+fn read_u32(bytes: &[u8]) -> u32 {
+    let mut buf = [0; 4];
+    buf.copy_from_slice(&bytes[..4]);
+    u32::from_le_bytes(buf)
+}
@@ -0,0 +1,138 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+#[cfg(test)]
+mod tests {
+    use dyn_type::custom::{get_custom_predicate, is_custom_type_registered};
+    use dyn_type::stable_hash::xxh64;
+    use dyn_type::{register_custom_predicate, register_custom_type};
+    use dyn_type::{CustomObject, CustomType, Object, STABLE_HASH_SEED};
+    use pegasus::codec::{Decode, Encode};
+    use std::cmp::Ordering;
+    use std::collections::HashSet;
+    use std::io;
+
+    #[derive(Debug, PartialEq)]
+    struct Point {
+        x: f64,
+        y: f64,
+    }
+
+    impl CustomType for Point {
+        const TAG: &'static str = "point";
+
+        fn encode(&self) -> Vec<u8> {
+            let mut bytes = self.x.to_le_bytes().to_vec();
+            bytes.extend_from_slice(&self.y.to_le_bytes());
+            bytes
+        }
+
+        fn decode(bytes: &[u8]) -> io::Result<Self> {
+            if bytes.len() != 16 {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "a point is of 16 bytes"));
+            }
+            let mut x = [0u8; 8];
+            let mut y = [0u8; 8];
+            x.copy_from_slice(&bytes[0..8]);
+            y.copy_from_slice(&bytes[8..16]);
+            Ok(Point { x: f64::from_le_bytes(x), y: f64::from_le_bytes(y) })
+        }
+
+        fn compare(&self, other: &Self) -> Option<Ordering> {
+            match self.x.partial_cmp(&other.x)? {
+                Ordering::Equal => self.y.partial_cmp(&other.y),
+                ord => Some(ord),
+            }
+        }
+
+        fn hash(&self) -> u64 {
+            xxh64(&self.encode(), STABLE_HASH_SEED)
+        }
+    }
+
+    fn point(x: f64, y: f64) -> Object {
+        Object::Custom(CustomObject::from_value(&Point { x, y }))
+    }
+
+    fn register() {
+        register_custom_type::<Point>().unwrap();
+        register_custom_predicate("within_radius", |p: &Point, center: &Point| {
+            (p.x - center.x).hypot(p.y - center.y) <= 1.5
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn test_custom_codec() {
+        register();
+        assert!(is_custom_type_registered("point"));
+        let obj = point(1.0, 2.0);
+        let mut bytes = vec![];
+        obj.write_to(&mut bytes).unwrap();
+        let mut reader = &bytes[0..];
+        let de = Object::read_from(&mut reader).unwrap();
+        assert_eq!(de.as_custom().unwrap().to_value::<Point>().unwrap(), Point { x: 1.0, y: 2.0 });
+        assert_eq!(de, obj);
+        // decoded by the registered type, which rejects the malformed bytes
+        let malformed = Object::Custom(CustomObject::new("point", vec![0; 3]));
+        let mut bytes = vec![];
+        malformed.write_to(&mut bytes).unwrap();
+        assert!(Object::read_from(&mut &bytes[0..]).is_err());
+    }
+
+    #[test]
+    fn test_unregistered_custom() {
+        let obj = Object::Custom(CustomObject::new("ip_address", vec![127, 0, 0, 1]));
+        let mut bytes = vec![];
+        obj.write_to(&mut bytes).unwrap();
+        let err = Object::read_from(&mut &bytes[0..]).unwrap_err();
+        assert!(err.to_string().contains("`ip_address`"), "{}", err);
+        // never equal to any, even itself
+        assert_ne!(obj, obj.clone());
+        assert_eq!(obj.partial_cmp(&obj), None);
+        assert!(get_custom_predicate("ip_address", "within_radius").is_err());
+    }
+
+    #[test]
+    fn test_custom_compare() {
+        register();
+        assert_eq!(point(1.0, 2.0), point(1.0, 2.0));
+        assert_ne!(point(1.0, 2.0), point(1.0, 3.0));
+        assert!(point(1.0, 2.0) < point(1.0, 3.0));
+        assert!(point(2.0, 0.0) > point(1.0, 3.0));
+        assert_eq!(point(1.0, 2.0).as_borrow(), point(1.0, 2.0).as_borrow());
+        // incomparable to the values of other types
+        assert_ne!(point(1.0, 2.0), Object::from(1));
+        assert_eq!(point(1.0, 2.0).partial_cmp(&Object::from("a")), None);
+        assert_eq!(point(1.0, 2.0).stable_hash(), point(1.0, 2.0).stable_hash());
+        assert_ne!(point(1.0, 2.0).stable_hash(), point(2.0, 1.0).stable_hash());
+        let set: HashSet<Object> =
+            vec![point(1.0, 2.0), point(1.0, 2.0), point(2.0, 1.0)].into_iter().collect();
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn test_custom_predicate() {
+        register();
+        let within = get_custom_predicate("point", "within_radius").unwrap();
+        let center = CustomObject::from_value(&Point { x: 0.0, y: 0.0 });
+        assert!(within(&CustomObject::from_value(&Point { x: 1.0, y: 1.0 }), &center));
+        assert!(!within(&CustomObject::from_value(&Point { x: 2.0, y: 0.0 }), &center));
+        // never satisfied by the values of other types
+        assert!(!within(&CustomObject::new("ip_address", vec![127, 0, 0, 1]), &center));
+        let err = get_custom_predicate("point", "nearest").err().unwrap();
+        assert!(err.to_string().contains("`nearest`"), "{}", err);
+    }
+}
@@ -0,0 +1,90 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+
+//! The golden values of the stable hashes, which must never be changed, as the data routed by
+//! the stable hashes of earlier versions are expected to be routed the same.
+
+#[cfg(test)]
+mod tests {
+    use dyn_type::stable_hash::{xxh64, XxHash64};
+    use dyn_type::{object, BorrowObject, Object, Primitives, STABLE_HASH_SEED};
+    use std::hash::Hasher;
+
+    #[test]
+    fn test_xxh64_golden_values() {
+        assert_eq!(STABLE_HASH_SEED, 0);
+        assert_eq!(xxh64(b"", 0), 0xef46db3751d8e999);
+        assert_eq!(xxh64(b"abc", 0), 0x44bc2cf5ad770999);
+        assert_eq!(xxh64(b"Nobody inspects the spammish repetition", 0), 0xfbcea83c8a378bf1);
+    }
+
+    #[test]
+    fn test_xxh64_streaming() {
+        let data: Vec<u8> = (0..200_u32).map(|i| (i * 7 % 251) as u8).collect();
+        let expected = xxh64(&data, STABLE_HASH_SEED);
+        for split in [1, 3, 31, 32, 33, 64, 100] {
+            let mut hasher = XxHash64::default();
+            for chunk in data.chunks(split) {
+                hasher.write(chunk);
+            }
+            assert_eq!(hasher.finish(), expected);
+        }
+    }
+
+    #[test]
+    fn test_integer_golden_values() {
+        assert_eq!(object!(0).stable_hash(), 0xa2443c71157cb676);
+        assert_eq!(object!(1).stable_hash(), 0x7b2075f90a671183);
+        assert_eq!(object!(-42_i64).stable_hash(), 0x994fe33a6b26d385);
+        assert_eq!(object!(i64::MAX).stable_hash(), 0x0628092f6dc64641);
+        assert_eq!(object!(i64::MIN).stable_hash(), 0x07a7e2fea9675861);
+    }
+
+    #[test]
+    fn test_float_golden_values() {
+        assert_eq!(object!(0.5).stable_hash(), 0xbbcc31a117000b87);
+        // integral, but out of the range of i64
+        assert_eq!(object!(1e20).stable_hash(), 0xae97a1c0e4b90719);
+        assert_eq!(object!(f64::INFINITY).stable_hash(), 0x01f6954811738a08);
+        assert_eq!(object!(f64::NAN).stable_hash(), 0xa6051e6990e442b8);
+        assert_eq!(object!(-f64::NAN).stable_hash(), 0xa6051e6990e442b8);
+    }
+
+    #[test]
+    fn test_bytes_golden_values() {
+        assert_eq!(object!("").stable_hash(), 0x1f25c8d0bc1f4bb6);
+        assert_eq!(object!("marko").stable_hash(), 0x717c689023339e7f);
+        assert_eq!(object!("北京").stable_hash(), 0xf3e0aa3d24d08725);
+        let long = "Nobody inspects the spammish repetition";
+        assert_eq!(object!(long).stable_hash(), 0x18afb328bf4711d3);
+        let blob = Object::Blob(b"marko".to_vec().into_boxed_slice());
+        assert_eq!(blob.stable_hash(), 0x717c689023339e7f);
+    }
+
+    #[test]
+    fn test_equal_objects_hashed_the_same() {
+        let one = object!(1).stable_hash();
+        assert_eq!(Object::Primitive(Primitives::Byte(1)).stable_hash(), one);
+        assert_eq!(object!(1_i64).stable_hash(), one);
+        assert_eq!(object!(1.0).stable_hash(), one);
+        assert_eq!(object!(true).stable_hash(), one);
+        assert_eq!(object!(-0.0).stable_hash(), object!(0).stable_hash());
+        assert_eq!(Primitives::Integer(1).stable_hash(), one);
+        assert_eq!(BorrowObject::String("marko").stable_hash(), object!("marko").stable_hash());
+        assert_ne!(object!("1").stable_hash(), one);
+        assert_ne!(object!(1.5).stable_hash(), one);
+    }
+}
@@ -0,0 +1,101 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Bench opening a graph of wide vertices, whose properties are either read into the heap by
+//! `PropertyTable`, or memory-mapped by `ColumnTable`: cargo +nightly bench --bench bench_open;
+
+#![feature(test)]
+extern crate test;
+#[macro_use]
+extern crate dyn_type;
+
+use graph_store::config::JsonConf;
+use graph_store::prelude::*;
+use std::path::Path;
+use tempdir::TempDir;
+use test::Bencher;
+
+const NUM_VERTICES: usize = 100_000;
+const NUM_PROPERTIES: usize = 20;
+
+fn schema_file(dir: &Path) -> std::path::PathBuf {
+    let mut properties = vec!["[\"id\", \"ID\"]".to_string()];
+    for index in 1..NUM_PROPERTIES {
+        let kind = if index % 2 == 0 { "Long" } else { "String" };
+        properties.push(format!("[\"p{}\", \"{}\"]", index, kind));
+    }
+    let json = format!(
+        "{{\"vertex_type_map\": {{\"ITEM\": 0}}, \"edge_type_map\": {{\"LINK\": 1}}, \
+         \"vertex_prop\": {{\"ITEM\": [{}]}}, \"edge_prop\": {{\"LINK\": []}}}}",
+        properties.join(", ")
+    );
+    let schema = LDBCGraphSchema::from_json(json).expect("Parse schema error");
+    let path = dir.join("schema.json");
+    schema.to_json_file(&path).expect("Write schema error");
+    path
+}
+
+/// Build the graph into `dir`, with its statistics exported, so that they are not recomputed
+/// by reading all the properties while the graph is opened
+fn build<N>(dir: &Path, schema_file: &Path)
+where
+    N: PropertyTableTrait + Send + Sync,
+{
+    let mut graph: MutableGraphDB<DefaultId, InternalId, N, SingleValueTable> =
+        GraphDBConfig::default().root_dir(dir).new();
+    for vertex in 0..NUM_VERTICES {
+        let mut row = Row::from(vec![object!(vertex as u64)]);
+        for index in 1..NUM_PROPERTIES {
+            if index % 2 == 0 {
+                row.push(object!((vertex * index) as i64));
+            } else {
+                row.push(object!(format!("property {} of vertex {}", index, vertex)));
+            }
+        }
+        graph.add_vertex_with_properties(vertex, [0.into(), INVALID_LABEL_ID], row).unwrap();
+    }
+    graph.export().expect("Export error");
+    let schema = LDBCGraphSchema::from_json_file(schema_file).expect("Read schema error");
+    graph.export_statistics(&schema).expect("Export statistics error");
+}
+
+fn bench_open<N>(b: &mut Bencher)
+where
+    N: PropertyTableTrait + Send + Sync + 'static,
+{
+    let temp = TempDir::new("bench_open").expect("Open temp folder error");
+    let schema_file = schema_file(temp.path());
+    build::<N>(temp.path(), &schema_file);
+    b.iter(|| {
+        let graph = GraphDBConfig::default()
+            .root_dir(temp.path())
+            .schema_file(&schema_file)
+            .open::<DefaultId, InternalId, N, SingleValueTable>()
+            .expect("Open graph error");
+        // a query touching one property of a few vertices
+        let vertex = graph.get_vertex(NUM_VERTICES / 2).expect("Vertex not found");
+        test::black_box(vertex.get_property("p2").and_then(|p| p.try_to_owned()))
+    });
+}
+
+#[bench]
+fn bench_open_property_table(b: &mut Bencher) {
+    bench_open::<PropertyTable>(b);
+}
+
+#[bench]
+fn bench_open_column_table(b: &mut Bencher) {
+    bench_open::<ColumnTable>(b);
+}
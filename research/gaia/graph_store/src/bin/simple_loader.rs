@@ -72,6 +72,7 @@ fn main() {
         let raw_dir = raw_data_dir.clone();
         let graph_dir = graph_data_dir.clone();
         let schema_f = schema_file.clone();
+        let schema = schema.clone();
 
         let handle = std::thread::spawn(move || {
             let mut loader: GraphLoader =
@@ -81,6 +82,7 @@ fn main() {
             loader.load().expect("Load error");
             let graph = loader.into_mutable_graph();
             graph.export().expect("Export error!");
+            graph.export_statistics(&schema).expect("Export statistics error!");
         });
 
         handles.push(handle);
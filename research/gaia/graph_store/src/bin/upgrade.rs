@@ -0,0 +1,73 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use clap::{App, Arg};
+use graph_store::config::{DIR_GRAPH_SCHEMA, FILE_SCHEMA};
+use graph_store::prelude::{DefaultId, GraphDBConfig, InternalId, PropertyTable, NAME, VERSION};
+use graph_store::upgrade::{upgrade, UpgradePolicy, LAYOUT_VERSION};
+use std::path::PathBuf;
+
+fn main() {
+    env_logger::init();
+    let matches = App::new(NAME)
+        .version(VERSION)
+        .about("Upgrade the layout of the graph storage to the current version.")
+        .args(&[
+            Arg::with_name("graph_data_dir")
+                .short("g")
+                .long_help("The directory to graph store")
+                .required(true)
+                .takes_value(true)
+                .index(1),
+            Arg::with_name("schema_file")
+                .short("s")
+                .long_help("The schema file, graph_schema/schema.json of the store by default")
+                .takes_value(true),
+            Arg::with_name("copy_to")
+                .short("c")
+                .long_help("Migrate a copy of the store into the directory, leaving it as it is")
+                .takes_value(true),
+            Arg::with_name("check")
+                .long("check")
+                .long_help("Check the store only, which fails if it is of an older version"),
+        ])
+        .get_matches();
+
+    let graph_data_dir = PathBuf::from(matches.value_of("graph_data_dir").unwrap());
+    let schema_file = matches
+        .value_of("schema_file")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| graph_data_dir.join(DIR_GRAPH_SCHEMA).join(FILE_SCHEMA));
+    let policy = if matches.is_present("check") {
+        UpgradePolicy::Refuse
+    } else if let Some(copy_to) = matches.value_of("copy_to") {
+        UpgradePolicy::MigrateCopy(PathBuf::from(copy_to))
+    } else {
+        UpgradePolicy::Migrate
+    };
+
+    let config = GraphDBConfig::default().root_dir(&graph_data_dir).schema_file(&schema_file);
+    let upgraded = upgrade::<DefaultId, InternalId, PropertyTable, _>(&config, &policy, |step| {
+        println!("{:?}: {} ({} -> {})", step.partition_dir, step.name, step.from, step.to)
+    })
+    .expect("Upgrade error");
+    for partition in upgraded.iter() {
+        println!(
+            "{:?} is upgraded from version {} to {}",
+            partition.partition_dir, partition.from, LAYOUT_VERSION
+        );
+    }
+    println!("{} partitions upgraded to version {}", upgraded.len(), LAYOUT_VERSION);
+}
@@ -0,0 +1,1284 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! A parallel bulk loader that builds the graph store from raw files.
+//!
+//! Unlike `GraphLoader`, which reads all the raw files of a partition in one thread, the
+//! `BulkLoader` works in three stages:
+//! * a number of reader threads stream records from the raw files (CSV, or Parquet with the
+//!   `parquet` feature), parse them and route them to the partitions they belong to;
+//! * a number of builder threads, each owning a subset of the partitions, add the routed
+//!   vertices and edges into their `MutableGraphDB`s;
+//! * once all inputs are consumed, every partition is exported in parallel into the on-disk
+//!   layout described in `GraphDBConfig`, together with the graph schema.
+//!
+//! Malformed rows do not abort the loading. Instead, they are collected into the `LoadReport`
+//! with their file and line number, up to `BulkLoader::max_errors`. The dangling edges, of which an
+//! end vertex is in none of the vertex files, are handled by `BulkLoader::dangling_edge_policy`,
+//! see `DanglingEdgePolicy`. In the strict schema mode, a value not of the type of its property
+//! is handled by the coercion of its column instead of failing its row as malformed, see
+//! `crate::strict`.
+
+use crate::common::{DefaultId, InternalId, Label, LabelId, PLACEHOLDER_LABEL_ID};
+use crate::config::{GraphDBConfig, JsonConf, DIR_GRAPH_SCHEMA, FILE_SCHEMA};
+use crate::dangling::{placeholder_label, DanglingEdgeChecker, DanglingEdgePolicy};
+use crate::error::{GDBError, GDBResult};
+use crate::graph_db::GlobalStoreUpdate;
+use crate::graph_db_impl::MutableGraphDB;
+use crate::ldbc::LDBCParser;
+use crate::parser::{DataType, EdgeMeta, ParserTrait};
+use crate::partition::{GraphPartition, HashPartition};
+use crate::schema::{AdjOrder, LDBCGraphSchema, Schema};
+use crate::strict::{SchemaMode, TypeChecker, TypeCoercion};
+use crate::table::Row;
+use csv::{Reader, ReaderBuilder, StringRecord};
+use petgraph::graph::IndexType;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs::{create_dir_all, File};
+use std::hash::Hash;
+use std::io::{BufReader, Read};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// The raw files of one type of vertex
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct VertexInput {
+    /// The vertex type, which must present in the graph schema
+    pub label: String,
+    /// The files that maintain the vertices of this type
+    pub files: Vec<PathBuf>,
+}
+
+/// The raw files of one type of edge
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EdgeInput {
+    /// The full edge type, as "<src_vertex_label>_<edge_label>_<dst_vertex_label>"
+    pub label: String,
+    /// The files that maintain the edges of this type
+    pub files: Vec<PathBuf>,
+}
+
+/// Describe the raw data to load. The property names and data types of each type of
+/// vertex/edge, as well as which columns hold the ids (those typed `ID`), are given by
+/// the graph schema.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct BulkLoadSpec {
+    pub vertices: Vec<VertexInput>,
+    pub edges: Vec<EdgeInput>,
+}
+
+impl JsonConf<BulkLoadSpec> for BulkLoadSpec {}
+
+/// A row that can not be loaded
+#[derive(Clone, Debug, PartialEq)]
+pub struct MalformedRow {
+    /// The file that contains the row
+    pub file: PathBuf,
+    /// The line number (or row number for Parquet files) of the row, starting from 1
+    pub line: u64,
+    /// Why the row can not be loaded
+    pub reason: String,
+}
+
+/// The report of a bulk loading
+#[derive(Clone, Debug, Default)]
+pub struct LoadReport {
+    /// The number of vertices that are loaded
+    pub num_vertices: usize,
+    /// The number of edges that are loaded
+    pub num_edges: usize,
+    /// The rows that are skipped due to malformed data, ordered by file and line number
+    pub errors: Vec<MalformedRow>,
+    /// The number of dangling edges, which are skipped, or loaded with the placeholders
+    pub num_dangling_edges: usize,
+    /// The number of placeholders created for the absent end vertices of the dangling edges
+    pub num_placeholders: usize,
+    /// The number of mistyped values coerced in the strict schema mode
+    pub num_coerced_values: usize,
+}
+
+/// A streaming source of raw records, which reads one record at a time, so that
+/// a file never has to be maintained in memory as a whole.
+pub trait RecordReader {
+    /// Read the next record into `record`. Return `Ok(false)` if there is no more record.
+    fn read_record(&mut self, record: &mut StringRecord) -> GDBResult<bool>;
+
+    /// The line number of the record that was last read (or failed to read)
+    fn line(&self) -> u64;
+}
+
+/// Read records from a csv file
+pub struct CsvRecordReader<R: Read> {
+    inner: Reader<R>,
+    line: u64,
+}
+
+impl CsvRecordReader<BufReader<File>> {
+    pub fn open<P: AsRef<Path>>(path: P, delim: u8) -> GDBResult<Self> {
+        let inner = ReaderBuilder::new()
+            .delimiter(delim)
+            .buffer_capacity(4096)
+            .comment(Some(b'#'))
+            .flexible(true)
+            .has_headers(false)
+            .from_reader(BufReader::new(File::open(path)?));
+
+        Ok(Self { inner, line: 0 })
+    }
+}
+
+impl<R: Read> RecordReader for CsvRecordReader<R> {
+    fn read_record(&mut self, record: &mut StringRecord) -> GDBResult<bool> {
+        match self.inner.read_record(record) {
+            Ok(has_more) => {
+                if let Some(pos) = record.position() {
+                    self.line = pos.line();
+                }
+                Ok(has_more)
+            }
+            Err(err) => {
+                self.line = err.position().map(|pos| pos.line()).unwrap_or(self.line + 1);
+                if err.is_io_error() {
+                    Err(GDBError::IOError(std::io::Error::from(err)))
+                } else {
+                    Err(GDBError::ParseError)
+                }
+            }
+        }
+    }
+
+    fn line(&self) -> u64 {
+        self.line
+    }
+}
+
+/// Read records from a parquet file, row by row
+#[cfg(feature = "parquet")]
+pub struct ParquetRecordReader {
+    rows: parquet::record::reader::RowIter<'static>,
+    line: u64,
+}
+
+#[cfg(feature = "parquet")]
+impl ParquetRecordReader {
+    pub fn open<P: AsRef<Path>>(path: P) -> GDBResult<Self> {
+        let reader = parquet::file::serialized_reader::SerializedFileReader::new(File::open(path)?)
+            .map_err(|_| GDBError::ParseError)?;
+        let rows = parquet::record::reader::RowIter::from_file_into(Box::new(reader));
+
+        Ok(Self { rows, line: 0 })
+    }
+}
+
+#[cfg(feature = "parquet")]
+impl RecordReader for ParquetRecordReader {
+    fn read_record(&mut self, record: &mut StringRecord) -> GDBResult<bool> {
+        use parquet::record::Field;
+
+        if let Some(row) = self.rows.next() {
+            self.line += 1;
+            record.clear();
+            for (_, field) in row.get_column_iter() {
+                match field {
+                    Field::Null => record.push_field(""),
+                    Field::Str(s) => record.push_field(s),
+                    _ => record.push_field(&field.to_string()),
+                }
+            }
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn line(&self) -> u64 {
+        self.line
+    }
+}
+
+/// Open a reader for the given file, according to its extension
+fn open_reader(path: &Path, delim: u8) -> GDBResult<Box<dyn RecordReader>> {
+    #[cfg(feature = "parquet")]
+    {
+        if path.extension().map_or(false, |ext| ext == "parquet") {
+            return Ok(Box::new(ParquetRecordReader::open(path)?));
+        }
+    }
+    Ok(Box::new(CsvRecordReader::open(path, delim)?))
+}
+
+/// Collect the malformed rows from all reader threads, and abort the loading
+/// once there are more than `max_errors` of them.
+#[derive(Clone)]
+struct ErrorCollector {
+    errors: Arc<Mutex<Vec<MalformedRow>>>,
+    max_errors: usize,
+}
+
+impl ErrorCollector {
+    fn report(&self, file: &Path, line: u64, reason: String) -> GDBResult<()> {
+        debug!("Malformed row at {:?}:{}: {}", file, line, reason);
+        let mut errors = self.errors.lock().map_err(|_| GDBError::UnknownError)?;
+        errors.push(MalformedRow { file: file.to_path_buf(), line, reason });
+        if errors.len() > self.max_errors {
+            Err(GDBError::TooManyMalformedRowsError(errors.len()))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// The data sent from the readers to the builders, where the first field is the
+/// partition the data is routed to.
+enum BuildBatch<G> {
+    Vertices(usize, Vec<(G, Label, Row)>),
+    Edges(usize, Vec<(EdgeMeta<G>, Row)>),
+}
+
+/// Route the parsed data to the builder threads in batches
+struct Router<G> {
+    senders: Vec<SyncSender<BuildBatch<G>>>,
+    partition: Arc<dyn GraphPartition>,
+    batch_size: usize,
+    vertex_buffers: Vec<Vec<(G, Label, Row)>>,
+    edge_buffers: Vec<Vec<(EdgeMeta<G>, Row)>>,
+}
+
+impl<G: IndexType> Router<G> {
+    fn new(
+        senders: Vec<SyncSender<BuildBatch<G>>>, partition: Arc<dyn GraphPartition>,
+        partitions: usize, batch_size: usize,
+    ) -> Self {
+        let mut vertex_buffers = Vec::with_capacity(partitions);
+        let mut edge_buffers = Vec::with_capacity(partitions);
+        for _ in 0..partitions {
+            vertex_buffers.push(vec![]);
+            edge_buffers.push(vec![]);
+        }
+        Router { senders, partition, batch_size, vertex_buffers, edge_buffers }
+    }
+
+    #[inline]
+    fn partition_of(&self, id: G) -> usize {
+        self.partition.get_server(id.index(), self.vertex_buffers.len())
+    }
+
+    fn send(&self, batch: BuildBatch<G>, partition: usize) -> GDBResult<()> {
+        self.senders[partition % self.senders.len()].send(batch).map_err(|_| GDBError::UnknownError)
+    }
+
+    fn push_vertex(&mut self, vertex: (G, Label, Row)) -> GDBResult<()> {
+        let partition = self.partition_of(vertex.0);
+        self.vertex_buffers[partition].push(vertex);
+        if self.vertex_buffers[partition].len() >= self.batch_size {
+            let batch = std::mem::take(&mut self.vertex_buffers[partition]);
+            self.send(BuildBatch::Vertices(partition, batch), partition)?;
+        }
+        Ok(())
+    }
+
+    /// An edge is routed to the partitions of both its source and target vertices
+    fn push_edge(&mut self, edge: (EdgeMeta<G>, Row)) -> GDBResult<()> {
+        let src_partition = self.partition_of(edge.0.src_global_id);
+        let dst_partition = self.partition_of(edge.0.dst_global_id);
+        if src_partition != dst_partition {
+            self.push_edge_to(dst_partition, edge.clone())?;
+        }
+        self.push_edge_to(src_partition, edge)
+    }
+
+    fn push_edge_to(&mut self, partition: usize, edge: (EdgeMeta<G>, Row)) -> GDBResult<()> {
+        self.edge_buffers[partition].push(edge);
+        if self.edge_buffers[partition].len() >= self.batch_size {
+            let batch = std::mem::take(&mut self.edge_buffers[partition]);
+            self.send(BuildBatch::Edges(partition, batch), partition)?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> GDBResult<()> {
+        for partition in 0..self.vertex_buffers.len() {
+            if !self.vertex_buffers[partition].is_empty() {
+                let batch = std::mem::take(&mut self.vertex_buffers[partition]);
+                self.send(BuildBatch::Vertices(partition, batch), partition)?;
+            }
+            if !self.edge_buffers[partition].is_empty() {
+                let batch = std::mem::take(&mut self.edge_buffers[partition]);
+                self.send(BuildBatch::Edges(partition, batch), partition)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Verify that the record has as many fields as the header says
+fn check_num_fields(
+    record: &StringRecord, header: Option<&[(String, DataType)]>,
+) -> Result<(), String> {
+    let expected = header.map(|h| h.len()).unwrap_or(0);
+    if expected > 0 && record.len() != expected {
+        Err(format!("expect {} fields, but found {}", expected, record.len()))
+    } else {
+        Ok(())
+    }
+}
+
+/// The parallel bulk loader, see the module-level document for details.
+///
+/// # Example
+/// ```no_run
+/// use graph_store::bulk_loader::{BulkLoadSpec, BulkLoader};
+/// use graph_store::config::JsonConf;
+/// use graph_store::prelude::*;
+///
+/// let spec = BulkLoadSpec::from_json_file("data/bulk_load.json").expect("Read spec error");
+/// let schema = LDBCGraphSchema::from_json_file("data/schema.json").expect("Read schema error");
+/// let report = BulkLoader::<DefaultId, InternalId>::new(spec, schema, "data/graph")
+///     .partitions(4)
+///     .parallelism(8)
+///     .max_errors(100)
+///     .load()
+///     .expect("Bulk load error");
+/// for error in report.errors {
+///     println!("{:?}:{} {}", error.file, error.line, error.reason);
+/// }
+/// ```
+pub struct BulkLoader<G = DefaultId, I = InternalId> {
+    /// The raw data to load
+    spec: BulkLoadSpec,
+    /// The schema of the graph, not trimmed
+    graph_schema: Arc<LDBCGraphSchema>,
+    /// The root directory to export the graph store
+    root_dir: PathBuf,
+    /// The number of partitions to build
+    partitions: usize,
+    /// The strategy of partitioning the vertices among the partitions to build (namely the
+    /// servers), which is `HashPartition` if not specified
+    graph_partition: Option<Arc<dyn GraphPartition>>,
+    /// The number of threads for both reading and building
+    parallelism: usize,
+    /// The number of records sent from a reader to a builder at a time
+    batch_size: usize,
+    /// The maximum number of malformed rows to tolerate before aborting
+    max_errors: usize,
+    /// A delimiter for splitting the fields in the csv files
+    delim: u8,
+    /// The number of vertex labels, used to initialize the `MutableGraphDB`
+    number_vertex_labels: usize,
+    /// How the dangling edges are handled, which fail the loading by default
+    dangling_edge_policy: DanglingEdgePolicy,
+    /// The edge types whose edges are stored sorted in each adjacency, see `sort_adjacency`
+    adj_orders: Vec<(String, AdjOrder)>,
+    /// How the values are checked against the types of their properties, lenient by default
+    schema_mode: SchemaMode,
+    /// The coercions of the mistyped values by (label, property), see `coerce`
+    coercions: Vec<(String, String, TypeCoercion)>,
+    ph: PhantomData<(G, I)>,
+}
+
+impl<G, I> BulkLoader<G, I>
+where
+    G: IndexType
+        + Eq
+        + Hash
+        + FromStr
+        + Default
+        + Serialize
+        + DeserializeOwned
+        + Send
+        + Sync
+        + 'static,
+    I: IndexType + Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+    pub fn new<P: AsRef<Path>>(spec: BulkLoadSpec, schema: LDBCGraphSchema, root_dir: P) -> Self {
+        Self {
+            spec,
+            graph_schema: Arc::new(schema),
+            root_dir: root_dir.as_ref().to_path_buf(),
+            partitions: 1,
+            graph_partition: None,
+            parallelism: 1,
+            batch_size: 10_000,
+            max_errors: 0,
+            delim: b'|',
+            number_vertex_labels: 20,
+            dangling_edge_policy: DanglingEdgePolicy::default(),
+            adj_orders: vec![],
+            schema_mode: SchemaMode::default(),
+            coercions: vec![],
+            ph: PhantomData,
+        }
+    }
+
+    pub fn partitions(mut self, partitions: usize) -> Self {
+        self.partitions = std::cmp::max(partitions, 1);
+        self
+    }
+
+    /// Partition the vertices following the given strategy
+    pub fn graph_partition(mut self, graph_partition: Arc<dyn GraphPartition>) -> Self {
+        self.graph_partition = Some(graph_partition);
+        self
+    }
+
+    pub fn parallelism(mut self, parallelism: usize) -> Self {
+        self.parallelism = std::cmp::max(parallelism, 1);
+        self
+    }
+
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = std::cmp::max(batch_size, 1);
+        self
+    }
+
+    pub fn max_errors(mut self, max_errors: usize) -> Self {
+        self.max_errors = max_errors;
+        self
+    }
+
+    /// For specifying a different delimiter
+    pub fn with_delimiter(mut self, delim: u8) -> Self {
+        self.delim = delim;
+        self
+    }
+
+    pub fn number_vertex_labels(mut self, number_vertex_labels: usize) -> Self {
+        self.number_vertex_labels = number_vertex_labels;
+        self
+    }
+
+    pub fn dangling_edge_policy(mut self, policy: DanglingEdgePolicy) -> Self {
+        self.dangling_edge_policy = policy;
+        self
+    }
+
+    /// Store the edges of the edge type, e.g. "KNOWS", sorted by the property in each adjacency,
+    /// in the descending order if `descending`, which is recorded in the schema exported, so that
+    /// the queries take the top edges of each vertex by the property without sorting them
+    pub fn sort_adjacency(mut self, edge_type: &str, property: &str, descending: bool) -> Self {
+        let order = AdjOrder { property: property.to_string(), descending };
+        self.adj_orders.push((edge_type.to_string(), order));
+        self
+    }
+
+    pub fn schema_mode(mut self, mode: SchemaMode) -> Self {
+        self.schema_mode = mode;
+        self
+    }
+
+    /// Handle the mistyped values of the property of the label, e.g. "PERSON" or "KNOWS", by the
+    /// coercion in the strict schema mode, where they are rejected by default
+    pub fn coerce(mut self, label: &str, property: &str, coercion: TypeCoercion) -> Self {
+        self.coercions.push((label.to_string(), property.to_string(), coercion));
+        self
+    }
+
+    /// Load the raw data and export the graph store. Return the `LoadReport` if the graph store
+    /// is successfully built, or `GDBError::TooManyMalformedRowsError` if more than
+    /// `Self::max_errors` rows are malformed, or `GDBError::DanglingEdgesError` if any edge
+    /// dangles while the policy is `DanglingEdgePolicy::Reject`, or
+    /// `GDBError::TypeViolationError` if any value is rejected in the strict schema mode, in
+    /// which cases nothing is exported. The adjacency orders and the coercions are checked against
+    /// the schema before anything is read.
+    pub fn load(&self) -> GDBResult<LoadReport> {
+        let timer = Instant::now();
+        let mut graph_schema = self.graph_schema.as_ref().clone();
+        for (edge_type, order) in &self.adj_orders {
+            graph_schema.set_adj_order(edge_type, order.clone())?;
+        }
+        let types = TypeChecker::new(self.schema_mode, &self.coercions, &graph_schema)?;
+        graph_schema.set_strict(self.schema_mode == SchemaMode::Strict);
+        let mut vertex_files = Vec::new();
+        for input in &self.spec.vertices {
+            let label_id = self
+                .graph_schema
+                .get_vertex_label_id(&input.label)
+                .ok_or(GDBError::InvalidTypeError)?;
+            for file in &input.files {
+                vertex_files.push((label_id, file.clone()));
+            }
+        }
+        let mut edge_files = Vec::new();
+        for input in &self.spec.edges {
+            let label_tuple = self
+                .graph_schema
+                .get_edge_label_tuple(&input.label)
+                .ok_or(GDBError::InvalidTypeError)?;
+            for file in &input.files {
+                edge_files.push((
+                    (
+                        label_tuple.src_vertex_label,
+                        label_tuple.dst_vertex_label,
+                        label_tuple.edge_label,
+                    ),
+                    file.clone(),
+                ));
+            }
+        }
+
+        let num_builders = std::cmp::min(self.parallelism, self.partitions);
+        let mut senders = Vec::with_capacity(num_builders);
+        let mut builders = Vec::with_capacity(num_builders);
+        for builder_id in 0..num_builders {
+            // bound the channel so that the readers won't run too far ahead of the builders
+            let (tx, rx) = sync_channel(self.parallelism * 2);
+            senders.push(tx);
+            let configs = (builder_id..self.partitions)
+                .step_by(num_builders)
+                .map(|partition| {
+                    GraphDBConfig::default()
+                        .root_dir(&self.root_dir)
+                        .number_vertex_labels(self.number_vertex_labels)
+                        .partition(partition)
+                })
+                .collect::<Vec<_>>();
+            let partition = self.vertex_partition();
+            let partitions = self.partitions;
+            builders.push(std::thread::spawn(move || {
+                build_partitions::<G, I>(configs, num_builders, partition, partitions, rx)
+            }));
+        }
+
+        let collector =
+            ErrorCollector { errors: Arc::new(Mutex::new(vec![])), max_errors: self.max_errors };
+        let dangling = DanglingEdgeChecker::new(self.dangling_edge_policy);
+        // Vertices must be all added before edges, in order to distinguish the corner vertices,
+        // and to tell the dangling edges
+        let load_rst = self
+            .run_readers(vertex_files, &senders, &collector, &dangling, &types, read_vertices::<G>)
+            .and_then(|num_vertices| {
+                self.run_readers(
+                    edge_files,
+                    &senders,
+                    &collector,
+                    &dangling,
+                    &types,
+                    read_edges::<G>,
+                )
+                .map(|num_edges| (num_vertices, num_edges))
+            });
+        drop(senders);
+
+        let mut graphs = Vec::with_capacity(self.partitions);
+        for builder in builders {
+            graphs.extend(builder.join()??);
+        }
+        let (num_vertices, num_edges) = load_rst?;
+        let (num_dangling_edges, num_placeholders) = dangling.finish()?;
+        let num_coerced_values = types.finish()?;
+        info!("Build all partitions, time elapsed: {:?}", timer.elapsed().as_secs_f64());
+
+        let exporters = graphs
+            .into_iter()
+            .map(|graph| {
+                let schema = self.graph_schema.clone();
+                std::thread::spawn(move || {
+                    graph.export().and_then(|_| graph.export_statistics(schema.as_ref()))
+                })
+            })
+            .collect::<Vec<_>>();
+        for exporter in exporters {
+            exporter.join()??;
+        }
+        let schema_dir = self.root_dir.join(DIR_GRAPH_SCHEMA);
+        create_dir_all(&schema_dir)?;
+        graph_schema.to_json_file(schema_dir.join(FILE_SCHEMA))?;
+        info!("Total time: {:?}", timer.elapsed().as_secs_f64());
+
+        let mut errors = Arc::try_unwrap(collector.errors)
+            .map_err(|_| GDBError::UnknownError)?
+            .into_inner()
+            .map_err(|_| GDBError::UnknownError)?;
+        errors.sort_by(|e1, e2| (&e1.file, e1.line).cmp(&(&e2.file, e2.line)));
+
+        Ok(LoadReport {
+            num_vertices,
+            num_edges,
+            errors,
+            num_dangling_edges,
+            num_placeholders,
+            num_coerced_values,
+        })
+    }
+
+    /// Distribute the files to at most `Self::parallelism` reader threads, and wait for all of them
+    /// to finish. Return the number of records that are successfully read.
+    fn run_readers<T, F>(
+        &self, files: Vec<(T, PathBuf)>, senders: &[SyncSender<BuildBatch<G>>],
+        collector: &ErrorCollector, dangling: &DanglingEdgeChecker<G>, types: &TypeChecker,
+        read_fn: F,
+    ) -> GDBResult<usize>
+    where
+        T: Clone + Send + 'static,
+        F: Fn(&T, &Path, &mut ReadContext<G>) -> GDBResult<usize> + Copy + Send + 'static,
+    {
+        let num_readers = std::cmp::min(self.parallelism, files.len());
+        let mut readers = Vec::with_capacity(num_readers);
+        for reader_id in 0..num_readers {
+            let my_files =
+                files.iter().skip(reader_id).step_by(num_readers).cloned().collect::<Vec<_>>();
+            let router = Router::new(
+                senders.to_vec(),
+                self.vertex_partition(),
+                self.partitions,
+                self.batch_size,
+            );
+            let collector = collector.clone();
+            let dangling = dangling.clone();
+            let types = types.clone();
+            let schema = self.graph_schema.clone();
+            let delim = self.delim;
+            readers.push(std::thread::spawn(move || {
+                let mut context = ReadContext { router, collector, dangling, types, schema, delim };
+                let mut count = 0;
+                for (input_type, file) in my_files {
+                    info!("Process file {:?}", file);
+                    count += read_fn(&input_type, file.as_path(), &mut context)?;
+                }
+                context.router.flush()?;
+                Ok(count)
+            }));
+        }
+
+        let mut count = 0;
+        let mut rst = Ok(());
+        for reader in readers {
+            match reader.join() {
+                Ok(Ok(num)) => count += num,
+                Ok(Err(e)) => {
+                    if rst.is_ok() {
+                        rst = Err(e)
+                    }
+                }
+                Err(e) => {
+                    if rst.is_ok() {
+                        rst = Err(GDBError::from(e))
+                    }
+                }
+            }
+        }
+        rst.map(|_| count)
+    }
+
+    fn vertex_partition(&self) -> Arc<dyn GraphPartition> {
+        match self.graph_partition {
+            Some(ref partition) => partition.clone(),
+            None => Arc::new(HashPartition::new(self.partitions)),
+        }
+    }
+}
+
+/// What a reader thread needs while reading files
+struct ReadContext<G> {
+    router: Router<G>,
+    collector: ErrorCollector,
+    dangling: DanglingEdgeChecker<G>,
+    types: TypeChecker,
+    schema: Arc<LDBCGraphSchema>,
+    delim: u8,
+}
+
+fn read_vertices<G>(
+    vertex_type: &LabelId, file: &Path, context: &mut ReadContext<G>,
+) -> GDBResult<usize>
+where
+    G: IndexType + Eq + Hash + FromStr + Default,
+{
+    let parser = LDBCParser::<G>::vertex_parser(*vertex_type, context.schema.clone())?;
+    let header = context.schema.get_vertex_header(*vertex_type);
+    let mut reader = open_reader(file, context.delim)?;
+    let mut record = StringRecord::new();
+    let mut count = 0;
+    let mut ids = vec![];
+    loop {
+        match reader.read_record(&mut record) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(GDBError::IOError(e)) => return Err(GDBError::IOError(e)),
+            Err(e) => {
+                context.collector.report(file, reader.line(), format!("{:?}", e))?;
+                continue;
+            }
+        }
+        let line = reader.line();
+        let parsed = check_num_fields(&record, header).and_then(|_| {
+            parser
+                .parse_vertex_meta(record.iter())
+                .and_then(|meta| {
+                    context
+                        .types
+                        .parse_vertex_properties(*vertex_type, record.iter(), header, file, line)
+                        .map(|ppt| ppt.map(|ppt| (meta, ppt)))
+                })
+                .map_err(|e| format!("{:?}", e))
+        });
+        match parsed {
+            // rejected for the mistyped values in the strict schema mode
+            Ok(None) => {}
+            Ok(Some((meta, properties))) => {
+                ids.push(meta.global_id);
+                context.router.push_vertex((meta.global_id, meta.label, properties))?;
+                count += 1;
+            }
+            Err(reason) => context.collector.report(file, line, reason)?,
+        }
+    }
+    context.dangling.add_vertices(ids)?;
+
+    Ok(count)
+}
+
+fn read_edges<G>(
+    edge_type: &(LabelId, LabelId, LabelId), file: &Path, context: &mut ReadContext<G>,
+) -> GDBResult<usize>
+where
+    G: IndexType + Eq + Hash + FromStr + Default,
+{
+    let (src_vertex_type, dst_vertex_type, edge_type) = *edge_type;
+    let parser = LDBCParser::<G>::edge_parser(
+        src_vertex_type,
+        dst_vertex_type,
+        edge_type,
+        context.schema.clone(),
+    )?;
+    let header = context.schema.get_edge_header(edge_type);
+    let mut reader = open_reader(file, context.delim)?;
+    let mut record = StringRecord::new();
+    let mut count = 0;
+    loop {
+        match reader.read_record(&mut record) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(GDBError::IOError(e)) => return Err(GDBError::IOError(e)),
+            Err(e) => {
+                context.collector.report(file, reader.line(), format!("{:?}", e))?;
+                continue;
+            }
+        }
+        let line = reader.line();
+        let parsed = check_num_fields(&record, header).and_then(|_| {
+            parser
+                .parse_edge_meta(record.iter())
+                .and_then(|meta| {
+                    context
+                        .types
+                        .parse_edge_properties(edge_type, record.iter(), header, file, line)
+                        .map(|ppt| ppt.map(|ppt| (meta, ppt)))
+                })
+                .map_err(|e| format!("{:?}", e))
+        });
+        match parsed {
+            Ok(None) => {}
+            Ok(Some((mut edge_meta, properties))) => {
+                if context.dangling.check(&mut edge_meta, file, line)? {
+                    context.router.push_edge((edge_meta, properties))?;
+                    count += 1;
+                }
+            }
+            Err(reason) => context.collector.report(file, line, reason)?,
+        }
+    }
+
+    Ok(count)
+}
+
+/// The body of a builder thread, which builds the partitions given by `configs`. The partitions
+/// are assigned to the builders in a round-robin manner, so that partition `p` is maintained by
+/// the `(p / num_builders)`th graph of the builder.
+///
+/// The placeholder of an absent end vertex is created by the partition it belongs to, once an edge
+/// to it arrives, or it is a corner vertex of the same label in the other partitions.
+fn build_partitions<G, I>(
+    configs: Vec<GraphDBConfig>, num_builders: usize, vertex_partition: Arc<dyn GraphPartition>,
+    partitions: usize, rx: Receiver<BuildBatch<G>>,
+) -> GDBResult<Vec<MutableGraphDB<G, I>>>
+where
+    G: IndexType + Eq + Send + Sync,
+    I: IndexType + Send + Sync,
+{
+    let mut graphs: Vec<MutableGraphDB<G, I>> = configs.iter().map(|config| config.new()).collect();
+    while let Ok(batch) = rx.recv() {
+        match batch {
+            BuildBatch::Vertices(partition, vertices) => {
+                let graph = &mut graphs[partition / num_builders];
+                graph.add_vertex_batches(vertices.into_iter())?;
+            }
+            BuildBatch::Edges(partition, edges) => {
+                let graph = &mut graphs[partition / num_builders];
+                for (edge_meta, properties) in edges {
+                    let ends = [
+                        (edge_meta.src_global_id, edge_meta.src_label_id),
+                        (edge_meta.dst_global_id, edge_meta.dst_label_id),
+                    ];
+                    for (id, label_id) in ends.iter().cloned() {
+                        if graph.is_vertex_local(id) {
+                            continue;
+                        }
+                        if label_id == PLACEHOLDER_LABEL_ID
+                            && vertex_partition.get_server(id.index(), partitions) == partition
+                        {
+                            graph.add_vertex(id, placeholder_label());
+                        } else {
+                            graph.add_corner_vertex(id, label_id);
+                        }
+                    }
+                    if !properties.is_empty() {
+                        graph.add_edge_with_properties(
+                            edge_meta.src_global_id,
+                            edge_meta.dst_global_id,
+                            edge_meta.label_id,
+                            properties,
+                        )?;
+                    } else {
+                        graph.add_edge(
+                            edge_meta.src_global_id,
+                            edge_meta.dst_global_id,
+                            edge_meta.label_id,
+                        );
+                    }
+                }
+            }
+        }
+    }
+    for graph in graphs.iter_mut() {
+        graph.shrink_to_fit();
+    }
+
+    Ok(graphs)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::common::PLACEHOLDER_LABEL_NAME;
+    use crate::graph_db::GlobalStoreTrait;
+    use crate::graph_db_impl::LargeGraphDB;
+    use crate::ldbc::LABEL_SHIFT_BITS;
+    use petgraph::Direction;
+    use std::io::Write;
+
+    fn write_file(path: &Path, lines: &[&str]) {
+        let mut file = File::create(path).expect("Create file error");
+        for line in lines {
+            writeln!(file, "{}", line).expect("Write file error");
+        }
+    }
+
+    #[test]
+    fn test_bulk_load() {
+        let temp = tempdir::TempDir::new("test_bulk_load").expect("Open temp folder error");
+        let raw_dir = temp.path().join("raw");
+        let root_dir = temp.path().join("graph");
+        create_dir_all(&raw_dir).unwrap();
+
+        let person_1 = raw_dir.join("person_1.csv");
+        let person_2 = raw_dir.join("person_2.csv");
+        let knows = raw_dir.join("person_knows_person.csv");
+        write_file(
+            &person_1,
+            &[
+                "111|Mahinda|Perera|male|19891203|20100214153210447|119.235.7.103|Firefox",
+                "222|Carmen|Lepland|female|19840218|20100128063958781|195.20.151.175|Chrome",
+                "333|Hans|Johansson|male|19840315|20100223210458137|77.245.239.11|Firefox",
+            ],
+        );
+        write_file(
+            &person_2,
+            &[
+                "444|Ali|Abouba|male|19820810|20100227013512491|41.203.141.129|Chrome",
+                // The birthday is not a valid date
+                "555|Bad|Row|male|not-a-date|20100227013512491|41.203.141.129|Chrome",
+                "666|Jun|Wang|female|19800822|20100306104358914|1.4.6.169|Safari",
+            ],
+        );
+        write_file(
+            &knows,
+            &[
+                "111|222|20100313073721718",
+                "111|333|20100920094243187",
+                "222|444|20110102064341955",
+                "444|666|20120812024437291",
+            ],
+        );
+
+        let spec = BulkLoadSpec {
+            vertices: vec![VertexInput {
+                label: "PERSON".to_string(),
+                files: vec![person_1.clone(), person_2.clone()],
+            }],
+            edges: vec![EdgeInput {
+                label: "PERSON_KNOWS_PERSON".to_string(),
+                files: vec![knows.clone()],
+            }],
+        };
+        let schema = LDBCGraphSchema::from_json_file("data/schema.json").expect("Get schema error");
+        let report = BulkLoader::<DefaultId, InternalId>::new(spec, schema, &root_dir)
+            .partitions(2)
+            .parallelism(2)
+            .batch_size(2)
+            .max_errors(1)
+            .load()
+            .expect("Bulk load error");
+
+        assert_eq!(report.num_vertices, 5);
+        assert_eq!(report.num_edges, 4);
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].file, person_2);
+        assert_eq!(report.errors[0].line, 2);
+
+        let schema_file = root_dir.join(DIR_GRAPH_SCHEMA).join(FILE_SCHEMA);
+        let person = |id: usize| (1 << LABEL_SHIFT_BITS) | id;
+        let mut num_vertices = 0;
+        let mut num_edges = 0;
+        for partition in 0..2 {
+            let graph: LargeGraphDB<DefaultId, InternalId> = GraphDBConfig::default()
+                .root_dir(&root_dir)
+                .schema_file(&schema_file)
+                .partition(partition)
+                .open()
+                .expect("Open graph error");
+            num_vertices += graph.count_all_vertices(None);
+            num_edges += graph.count_all_edges(None);
+            if graph.is_vertex_local(person(111)) {
+                let vertex = graph.get_vertex(person(111)).unwrap();
+                assert_eq!(vertex.get_property("firstName").unwrap().as_str().unwrap(), "Mahinda");
+                let mut out_vertices = graph
+                    .get_out_vertices(person(111), None)
+                    .map(|v| v.get_id())
+                    .collect::<Vec<_>>();
+                out_vertices.sort();
+                assert_eq!(out_vertices, vec![person(222), person(333)]);
+            }
+            assert!(graph.get_vertex(person(555)).is_none());
+        }
+        assert_eq!(num_vertices, 5);
+        assert_eq!(num_edges, 4);
+    }
+
+    #[test]
+    fn test_bulk_load_too_many_errors() {
+        let temp = tempdir::TempDir::new("test_bulk_load_errors").expect("Open temp folder error");
+        let person = temp.path().join("person.csv");
+        write_file(
+            &person,
+            &[
+                "abc|Mahinda|Perera|male|19891203|20100214153210447|119.235.7.103|Firefox",
+                "222|Carmen|Lepland|female",
+            ],
+        );
+        let spec = BulkLoadSpec {
+            vertices: vec![VertexInput { label: "PERSON".to_string(), files: vec![person] }],
+            edges: vec![],
+        };
+        let schema = LDBCGraphSchema::from_json_file("data/schema.json").expect("Get schema error");
+        let root_dir = temp.path().join("graph");
+        let rst =
+            BulkLoader::<DefaultId, InternalId>::new(spec, schema, &root_dir).max_errors(1).load();
+
+        match rst {
+            Err(GDBError::TooManyMalformedRowsError(num)) => assert_eq!(num, 2),
+            _ => panic!("expect too many malformed rows"),
+        }
+        assert!(!root_dir.exists());
+    }
+
+    /// Load the persons 111 and 222, with the knows edges of which two dangle, as the persons 888
+    /// and 999 are absent
+    fn load_dangling(temp: &Path, policy: DanglingEdgePolicy) -> GDBResult<LoadReport> {
+        let person = temp.join("person.csv");
+        let knows = temp.join("person_knows_person.csv");
+        write_file(
+            &person,
+            &[
+                "111|Mahinda|Perera|male|19891203|20100214153210447|119.235.7.103|Firefox",
+                "222|Carmen|Lepland|female|19840218|20100128063958781|195.20.151.175|Chrome",
+            ],
+        );
+        write_file(
+            &knows,
+            &[
+                "111|222|20100313073721718",
+                "111|999|20100920094243187",
+                "999|888|20110102064341955",
+            ],
+        );
+        let spec = BulkLoadSpec {
+            vertices: vec![VertexInput { label: "PERSON".to_string(), files: vec![person] }],
+            edges: vec![EdgeInput { label: "PERSON_KNOWS_PERSON".to_string(), files: vec![knows] }],
+        };
+        let schema = LDBCGraphSchema::from_json_file("data/schema.json").expect("Get schema error");
+        BulkLoader::<DefaultId, InternalId>::new(spec, schema, temp.join("graph"))
+            .partitions(2)
+            .dangling_edge_policy(policy)
+            .load()
+    }
+
+    fn open_partitions(root_dir: &Path) -> Vec<LargeGraphDB<DefaultId, InternalId>> {
+        let schema_file = root_dir.join(DIR_GRAPH_SCHEMA).join(FILE_SCHEMA);
+        (0..2)
+            .map(|partition| {
+                GraphDBConfig::default()
+                    .root_dir(root_dir)
+                    .schema_file(&schema_file)
+                    .partition(partition)
+                    .open()
+                    .expect("Open graph error")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_bulk_load_reject_dangling_edges() {
+        let temp = tempdir::TempDir::new("test_reject_dangling").expect("Open temp folder error");
+        let rst = load_dangling(temp.path(), DanglingEdgePolicy::Reject);
+
+        match rst {
+            Err(GDBError::DanglingEdgesError(num, edges)) => {
+                assert_eq!(num, 2);
+                let lines = edges.iter().map(|edge| edge.line).collect::<Vec<_>>();
+                assert_eq!(lines, vec![2, 3]);
+            }
+            _ => panic!("expect dangling edges"),
+        }
+        assert!(!temp.path().join("graph").exists());
+    }
+
+    #[test]
+    fn test_bulk_load_skip_dangling_edges() {
+        let temp = tempdir::TempDir::new("test_skip_dangling").expect("Open temp folder error");
+        let report = load_dangling(temp.path(), DanglingEdgePolicy::Skip).expect("Load error");
+
+        assert_eq!(report.num_vertices, 2);
+        assert_eq!(report.num_edges, 1);
+        assert_eq!(report.num_dangling_edges, 2);
+        assert_eq!(report.num_placeholders, 0);
+        let graphs = open_partitions(&temp.path().join("graph"));
+        assert_eq!(graphs.iter().map(|g| g.count_all_vertices(None)).sum::<usize>(), 2);
+        assert_eq!(graphs.iter().map(|g| g.count_all_edges(None)).sum::<usize>(), 1);
+    }
+
+    #[test]
+    fn test_bulk_load_placeholder_dangling_edges() {
+        let temp = tempdir::TempDir::new("test_placeholder").expect("Open temp folder error");
+        let report =
+            load_dangling(temp.path(), DanglingEdgePolicy::CreatePlaceholder).expect("Load error");
+
+        assert_eq!(report.num_vertices, 2);
+        assert_eq!(report.num_edges, 3);
+        assert_eq!(report.num_dangling_edges, 2);
+        assert_eq!(report.num_placeholders, 2);
+        let person = |id: usize| (1 << LABEL_SHIFT_BITS) | id;
+        let graphs = open_partitions(&temp.path().join("graph"));
+        let placeholder =
+            graphs[0].get_schema().get_vertex_label_id(PLACEHOLDER_LABEL_NAME).unwrap();
+        let mut placeholders = graphs
+            .iter()
+            .flat_map(|g| {
+                g.get_all_vertices(Some(&vec![placeholder])).map(|v| v.get_id()).collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        placeholders.sort();
+        assert_eq!(placeholders, vec![person(888), person(999)]);
+        for graph in &graphs {
+            if graph.is_vertex_local(person(999)) {
+                let vertex = graph.get_vertex(person(999)).unwrap();
+                assert_eq!(vertex.get_label(), placeholder_label());
+                assert!(vertex.clone_all_properties().is_none());
+                assert!(vertex.get_property("firstName").is_none());
+                let out_vertices = graph
+                    .get_out_vertices(person(999), None)
+                    .map(|v| v.get_id())
+                    .collect::<Vec<_>>();
+                assert_eq!(out_vertices, vec![person(888)]);
+            }
+        }
+        assert_eq!(graphs.iter().map(|g| g.count_all_vertices(None)).sum::<usize>(), 4);
+    }
+
+    /// Load the persons 111, 222, 333 and 444, with the knows edges of 111 to the others
+    fn sorted_spec(temp: &Path) -> BulkLoadSpec {
+        let person = temp.join("person.csv");
+        let knows = temp.join("person_knows_person.csv");
+        write_file(
+            &person,
+            &[
+                "111|Mahinda|Perera|male|19891203|20100214153210447|119.235.7.103|Firefox",
+                "222|Carmen|Lepland|female|19840218|20100128063958781|195.20.151.175|Chrome",
+                "333|Hans|Johansson|male|19840315|20100223210458137|77.245.239.11|Firefox",
+                "444|Ali|Abouba|male|19820810|20100227013512491|41.203.141.129|Chrome",
+            ],
+        );
+        write_file(
+            &knows,
+            &[
+                "111|333|20100920094243187",
+                "111|222|20100313073721718",
+                "111|444|20120812024437291",
+            ],
+        );
+        BulkLoadSpec {
+            vertices: vec![VertexInput { label: "PERSON".to_string(), files: vec![person] }],
+            edges: vec![EdgeInput { label: "PERSON_KNOWS_PERSON".to_string(), files: vec![knows] }],
+        }
+    }
+
+    #[test]
+    fn test_bulk_load_sorted_adjacency() {
+        let temp = tempdir::TempDir::new("test_sorted_adjacency").expect("Open temp folder error");
+        let schema = LDBCGraphSchema::from_json_file("data/schema.json").expect("Get schema error");
+        let root_dir = temp.path().join("graph");
+        BulkLoader::<DefaultId, InternalId>::new(sorted_spec(temp.path()), schema, &root_dir)
+            .partitions(2)
+            .sort_adjacency("KNOWS", "creationDate", true)
+            .load()
+            .expect("Bulk load error");
+
+        let person = |id: usize| (1 << LABEL_SHIFT_BITS) | id;
+        let graphs = open_partitions(&root_dir);
+        let knows = graphs[0].get_schema().get_edge_label_id("KNOWS").unwrap();
+        let graph = graphs.iter().find(|g| g.is_vertex_local(person(111))).unwrap();
+        let order = graph.get_adj_order(knows).expect("no order of knows");
+        assert_eq!((order.property.as_str(), order.descending), ("creationDate", true));
+        let segment = graph.get_adj_segment(knows, Direction::Outgoing).unwrap();
+        let vertices = graph
+            .get_segment_vertices(segment.clone(), person(111))
+            .map(|v| v.get_id())
+            .collect::<Vec<_>>();
+        assert_eq!(vertices, vec![person(444), person(333), person(222)]);
+        let dates = graph
+            .get_segment_edges(segment, person(111))
+            .map(|e| e.get_property("creationDate").unwrap().try_to_owned().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(dates.len(), 3);
+        assert!(dates.windows(2).all(|pair| pair[0] > pair[1]));
+    }
+
+    #[test]
+    fn test_bulk_load_invalid_adjacency_order() {
+        let temp = tempdir::TempDir::new("test_invalid_order").expect("Open temp folder error");
+        let schema = LDBCGraphSchema::from_json_file("data/schema.json").expect("Get schema error");
+        let root_dir = temp.path().join("graph");
+        let rst =
+            BulkLoader::<DefaultId, InternalId>::new(sorted_spec(temp.path()), schema, &root_dir)
+                .sort_adjacency("KNOWS", "start_id", false)
+                .load();
+        assert!(matches!(rst, Err(GDBError::FieldNotExistError)));
+
+        let schema = LDBCGraphSchema::from_json_file("data/schema.json").expect("Get schema error");
+        let rst =
+            BulkLoader::<DefaultId, InternalId>::new(sorted_spec(temp.path()), schema, &root_dir)
+                .sort_adjacency("FOLLOWS", "creationDate", false)
+                .load();
+        assert!(matches!(rst, Err(GDBError::InvalidTypeError)));
+        assert!(!root_dir.exists());
+    }
+
+    /// Load the persons 111, 222 and 333, of which the birthdays flip from dates to strings in the
+    /// second file, by the schema mode and the coercion of the birthdays if any
+    fn load_mixed_types(
+        temp: &Path, mode: SchemaMode, coercion: Option<TypeCoercion>,
+    ) -> GDBResult<LoadReport> {
+        let person_1 = temp.join("person_1.csv");
+        let person_2 = temp.join("person_2.csv");
+        write_file(
+            &person_1,
+            &["111|Mahinda|Perera|male|19891203|20100214153210447|119.235.7.103|Firefox"],
+        );
+        write_file(
+            &person_2,
+            &[
+                "222|Carmen|Lepland|female|Feb 18|20100128063958781|195.20.151.175|Chrome",
+                "333|Hans|Johansson|male|Mar 15|20100223210458137|77.245.239.11|Firefox",
+            ],
+        );
+        let spec = BulkLoadSpec {
+            vertices: vec![VertexInput {
+                label: "PERSON".to_string(),
+                files: vec![person_1, person_2],
+            }],
+            edges: vec![],
+        };
+        let schema = LDBCGraphSchema::from_json_file("data/schema.json").expect("Get schema error");
+        let mut loader = BulkLoader::<DefaultId, InternalId>::new(spec, schema, temp.join("graph"))
+            .partitions(2)
+            .max_errors(2)
+            .schema_mode(mode);
+        if let Some(coercion) = coercion {
+            loader = loader.coerce("PERSON", "birthday", coercion);
+        }
+        loader.load()
+    }
+
+    #[test]
+    fn test_bulk_load_lenient_types() {
+        let temp = tempdir::TempDir::new("test_lenient_types").expect("Open temp folder error");
+        let report = load_mixed_types(temp.path(), SchemaMode::Lenient, None).expect("Load error");
+
+        assert_eq!(report.num_vertices, 1);
+        let lines = report.errors.iter().map(|error| error.line).collect::<Vec<_>>();
+        assert_eq!(lines, vec![1, 2]);
+        assert_eq!(report.num_coerced_values, 0);
+        let graphs = open_partitions(&temp.path().join("graph"));
+        assert!(!graphs[0].graph_schema.is_strict());
+        assert!(!graphs[0].schema().strict);
+    }
+
+    #[test]
+    fn test_bulk_load_strict_reject_types() {
+        let temp = tempdir::TempDir::new("test_reject_types").expect("Open temp folder error");
+        let rst = load_mixed_types(temp.path(), SchemaMode::Strict, None);
+
+        match rst {
+            Err(GDBError::TypeViolationError(num, violations)) => {
+                assert_eq!(num, 2);
+                for (violation, line) in violations.iter().zip(vec![1, 2]) {
+                    assert_eq!(violation.file, temp.path().join("person_2.csv"));
+                    assert_eq!(violation.line, line);
+                    assert_eq!(violation.property, "birthday");
+                    assert_eq!(violation.expected, DataType::Date);
+                }
+                assert_eq!(violations.len(), 2);
+            }
+            _ => panic!("expect mistyped birthdays"),
+        }
+        assert!(!temp.path().join("graph").exists());
+    }
+
+    #[test]
+    fn test_bulk_load_strict_coerce_types() {
+        let temp = tempdir::TempDir::new("test_coerce_types").expect("Open temp folder error");
+        let coercion = TypeCoercion::Replace("19700101".to_string());
+        let report =
+            load_mixed_types(temp.path(), SchemaMode::Strict, Some(coercion)).expect("Load error");
+
+        assert_eq!(report.num_vertices, 3);
+        assert!(report.errors.is_empty());
+        assert_eq!(report.num_coerced_values, 2);
+        let person = |id: usize| (1 << LABEL_SHIFT_BITS) | id;
+        let graphs = open_partitions(&temp.path().join("graph"));
+        assert!(graphs[0].graph_schema.is_strict());
+        assert!(graphs.iter().all(|g| g.schema().strict));
+        for (id, birthday) in [(111, 19891203), (222, 19700101), (333, 19700101)] {
+            let graph = graphs.iter().find(|g| g.is_vertex_local(person(id))).unwrap();
+            let vertex = graph.get_vertex(person(id)).unwrap();
+            assert_eq!(vertex.get_property("birthday").unwrap().as_u64().unwrap(), birthday);
+        }
+    }
+}
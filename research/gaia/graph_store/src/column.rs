@@ -0,0 +1,680 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The property tables stored by columns, which are memory-mapped from their files once opened
+//! instead of being read into the heap, so that opening a graph of many properties costs little,
+//! and only the columns the queries touch are paged in.
+//!
+//! A `ColumnTable` is built in rows like a dense `PropertyTable`, and exported into a directory
+//! of one file per column, along with a meta file of the kinds of the columns. Each column file
+//! begins with a bitmap telling which rows have a value in the column, padded to 8 bytes, followed
+//! by the values:
+//! * the fixed-width values, i.e. the primitives, are laid out one after another, each of which
+//!   is read directly from the map;
+//! * the variable-width values, e.g. the strings, are laid out as the offsets of the values in
+//!   `u64`, one more than the rows, followed by the data, where a value is decoded per access,
+//!   borrowing its bytes from the map;
+//!
+//! The values of a column of mixed or dynamic types are encoded as the variable-width ones, but
+//! are decoded into the heap once the table is opened, as they can not be borrowed from the map.
+
+use crate::error::{GDBError, GDBResult};
+use crate::table::{ItemType, ItemTypeRef, PropertyTable, PropertyTableTrait, Row, RowRef};
+use dyn_type::{BorrowObject, Object, Primitives};
+use memmap::Mmap;
+use pegasus_common::codec::{Decode, Encode};
+use std::convert::TryInto;
+use std::fs::{create_dir_all, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// The file of the meta of the columns in the directory of a `ColumnTable`
+pub const FILE_COLUMN_META: &str = "columns_meta";
+/// The prefix of the files of the columns, followed by the indices of the columns
+pub const COLUMN_FILE_PREFIX: &str = "column_";
+/// The size of the pages the residency of the columns is counted by
+pub const PAGE_SIZE: usize = 4096;
+
+/// The kind of the values of a column, which tells how they are laid out in the column file
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ColumnKind {
+    /// `i8` values of 1 byte each
+    Byte,
+    /// `i32` values of 4 bytes each
+    Integer,
+    /// `i64` values of 8 bytes each
+    Long,
+    /// `f64` values of 8 bytes each
+    Float,
+    /// The utf-8 strings of variable widths
+    String,
+    /// The bytes of variable widths
+    Blob,
+    /// The values of mixed or dynamic types, which are decoded into the heap once opened
+    Object,
+}
+
+impl ColumnKind {
+    fn of(value: &ItemType) -> Self {
+        match value {
+            Object::Primitive(Primitives::Byte(_)) => ColumnKind::Byte,
+            Object::Primitive(Primitives::Integer(_)) => ColumnKind::Integer,
+            Object::Primitive(Primitives::Long(_)) => ColumnKind::Long,
+            Object::Primitive(Primitives::Float(_)) => ColumnKind::Float,
+            Object::String(_) => ColumnKind::String,
+            Object::Blob(_) => ColumnKind::Blob,
+            _ => ColumnKind::Object,
+        }
+    }
+
+    /// The width of each value of the column, or `None` if the values are of variable widths
+    fn width(&self) -> Option<usize> {
+        match self {
+            ColumnKind::Byte => Some(1),
+            ColumnKind::Integer => Some(4),
+            ColumnKind::Long | ColumnKind::Float => Some(8),
+            _ => None,
+        }
+    }
+}
+
+/// How much of a column is in memory, see `ColumnTable::residency`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ColumnResidency {
+    /// The index of the column, i.e. of the property in the rows
+    pub index: usize,
+    pub kind: ColumnKind,
+    /// The bytes of the column file mapped into memory
+    pub mapped_bytes: usize,
+    /// The bytes of the pages of the map read since the table is opened, which are the ones paged
+    /// into memory unless evicted by the os
+    pub touched_bytes: usize,
+    /// The encoded bytes of the values decoded into the heap, for `ColumnKind::Object` only
+    pub heap_bytes: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ColumnsMeta {
+    rows: usize,
+    kinds: Vec<ColumnKind>,
+}
+
+/// The bytes of the bitmap of the rows having a value in a column, padded to 8 bytes
+#[inline]
+fn bitmap_len(rows: usize) -> usize {
+    rows.div_ceil(64) * 8
+}
+
+fn invalid_data(msg: String) -> GDBError {
+    GDBError::IOError(std::io::Error::new(std::io::ErrorKind::InvalidData, msg))
+}
+
+/// Export the values given by `get` of each row and column into the column files under `dir`
+fn export_columns<F>(dir: &Path, rows: usize, columns: usize, get: F) -> GDBResult<()>
+where
+    F: Fn(usize, usize) -> Option<ItemType>,
+{
+    create_dir_all(dir)?;
+    let mut kinds = Vec::with_capacity(columns);
+    for column in 0..columns {
+        let values: Vec<Option<ItemType>> = (0..rows).map(|row| get(row, column)).collect();
+        let mut kind = None;
+        for value in values.iter().flatten() {
+            let of = ColumnKind::of(value);
+            if *kind.get_or_insert(of) != of {
+                kind = Some(ColumnKind::Object);
+                break;
+            }
+        }
+        // a column of no value at all takes no byte for each row
+        let kind = kind.unwrap_or(ColumnKind::Byte);
+        let path = dir.join(format!("{}{}", COLUMN_FILE_PREFIX, column));
+        write_column(&path, kind, &values)?;
+        kinds.push(kind);
+    }
+    crate::io::export(&ColumnsMeta { rows, kinds }, dir.join(FILE_COLUMN_META))?;
+    Ok(())
+}
+
+fn write_column(path: &Path, kind: ColumnKind, values: &[Option<ItemType>]) -> GDBResult<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    let mut bitmap = vec![0u8; bitmap_len(values.len())];
+    for (row, value) in values.iter().enumerate() {
+        if value.is_some() {
+            bitmap[row / 8] |= 1 << (row % 8);
+        }
+    }
+    writer.write_all(&bitmap)?;
+    if let Some(width) = kind.width() {
+        for value in values.iter() {
+            match value {
+                Some(Object::Primitive(Primitives::Byte(v))) => {
+                    writer.write_all(&v.to_le_bytes())?
+                }
+                Some(Object::Primitive(Primitives::Integer(v))) => {
+                    writer.write_all(&v.to_le_bytes())?
+                }
+                Some(Object::Primitive(Primitives::Long(v))) => {
+                    writer.write_all(&v.to_le_bytes())?
+                }
+                Some(Object::Primitive(Primitives::Float(v))) => {
+                    writer.write_all(&v.to_le_bytes())?
+                }
+                _ => writer.write_all(&[0u8; 8][..width])?,
+            }
+        }
+    } else {
+        let mut data = Vec::new();
+        writer.write_all(&0u64.to_le_bytes())?;
+        for value in values.iter() {
+            match value {
+                Some(Object::String(s)) if kind == ColumnKind::String => {
+                    data.extend_from_slice(s.as_bytes())
+                }
+                Some(Object::Blob(b)) if kind == ColumnKind::Blob => data.extend_from_slice(b),
+                Some(value) => value.write_to(&mut data)?,
+                None => (),
+            }
+            writer.write_all(&(data.len() as u64).to_le_bytes())?;
+        }
+        writer.write_all(&data)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// A column memory-mapped from its file, or decoded into the heap for `ColumnKind::Object`
+struct Column {
+    index: usize,
+    kind: ColumnKind,
+    rows: usize,
+    map: Option<Mmap>,
+    heap: Vec<Option<ItemType>>,
+    heap_bytes: usize,
+    // one bit for each page of the map, telling whether the page has been read
+    touched: Vec<AtomicU64>,
+}
+
+impl Column {
+    fn open(path: &Path, index: usize, kind: ColumnKind, rows: usize) -> GDBResult<Self> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len() as usize;
+        let payload = bitmap_len(rows);
+        let least = match kind.width() {
+            Some(width) => payload + rows * width,
+            None => payload + (rows + 1) * 8,
+        };
+        if len < least {
+            return Err(invalid_data(format!(
+                "column file {:?} of {} bytes is shorter than the {} bytes of {} rows",
+                path, len, least, rows
+            )));
+        }
+        // an empty file can not be mapped, which has no row anyway
+        // Safety: the column files are read-only once exported
+        let map = if len > 0 { Some(unsafe { Mmap::map(&file)? }) } else { None };
+        let pages = len.div_ceil(PAGE_SIZE);
+        let mut column = Column {
+            index,
+            kind,
+            rows,
+            map,
+            heap: vec![],
+            heap_bytes: 0,
+            touched: (0..pages.div_ceil(64)).map(|_| AtomicU64::new(0)).collect(),
+        };
+        if kind == ColumnKind::Object {
+            let mut heap = Vec::with_capacity(rows);
+            for row in 0..rows {
+                let value = match column.var_bytes(row) {
+                    Some(mut bytes) => Some(ItemType::read_from(&mut bytes)?),
+                    None => None,
+                };
+                heap.push(value);
+            }
+            column.heap_bytes = len - payload - (rows + 1) * 8;
+            column.heap = heap;
+            column.map = None;
+            column.touched.clear();
+        }
+        Ok(column)
+    }
+
+    /// Read the bytes at `offset` of the map, marking the pages of them as touched
+    #[inline]
+    fn read(&self, offset: usize, len: usize) -> Option<&[u8]> {
+        let bytes = self.map.as_ref()?.get(offset..offset + len)?;
+        if len > 0 {
+            for page in offset / PAGE_SIZE..=(offset + len - 1) / PAGE_SIZE {
+                let (word, bit) = (page / 64, 1u64 << (page % 64));
+                if let Some(word) = self.touched.get(word) {
+                    if word.load(Ordering::Relaxed) & bit == 0 {
+                        word.fetch_or(bit, Ordering::Relaxed);
+                    }
+                }
+            }
+        }
+        Some(bytes)
+    }
+
+    #[inline]
+    fn has_value(&self, row: usize) -> bool {
+        row < self.rows
+            && self.read(row / 8, 1).map(|byte| byte[0] & (1 << (row % 8)) != 0).unwrap_or(false)
+    }
+
+    /// The bytes of the variable-width value of the row, located by its offsets
+    fn var_bytes(&self, row: usize) -> Option<&[u8]> {
+        if !self.has_value(row) {
+            return None;
+        }
+        let offsets = bitmap_len(self.rows);
+        let bounds = self.read(offsets + row * 8, 16)?;
+        let start = u64::from_le_bytes(bounds[..8].try_into().ok()?) as usize;
+        let end = u64::from_le_bytes(bounds[8..].try_into().ok()?) as usize;
+        let data = offsets + (self.rows + 1) * 8;
+        self.read(data + start, end.checked_sub(start)?)
+    }
+
+    fn get(&self, row: usize) -> Option<ItemTypeRef<'_>> {
+        match self.kind {
+            ColumnKind::Object => self.heap.get(row)?.as_ref().map(|value| value.as_borrow()),
+            ColumnKind::String => {
+                std::str::from_utf8(self.var_bytes(row)?).ok().map(BorrowObject::String)
+            }
+            ColumnKind::Blob => self.var_bytes(row).map(BorrowObject::Blob),
+            kind => {
+                if !self.has_value(row) {
+                    return None;
+                }
+                let width = kind.width()?;
+                let bytes = self.read(bitmap_len(self.rows) + row * width, width)?;
+                let value = match kind {
+                    ColumnKind::Byte => Primitives::Byte(bytes[0] as i8),
+                    ColumnKind::Integer => {
+                        Primitives::Integer(i32::from_le_bytes(bytes.try_into().ok()?))
+                    }
+                    ColumnKind::Long => {
+                        Primitives::Long(i64::from_le_bytes(bytes.try_into().ok()?))
+                    }
+                    _ => Primitives::Float(f64::from_le_bytes(bytes.try_into().ok()?)),
+                };
+                Some(BorrowObject::Primitive(value))
+            }
+        }
+    }
+
+    fn residency(&self) -> ColumnResidency {
+        let pages: u32 = self.touched.iter().map(|w| w.load(Ordering::Relaxed).count_ones()).sum();
+        let mapped_bytes = self.map.as_ref().map(|map| map.len()).unwrap_or(0);
+        ColumnResidency {
+            index: self.index,
+            kind: self.kind,
+            mapped_bytes,
+            touched_bytes: (pages as usize * PAGE_SIZE).min(mapped_bytes),
+            heap_bytes: self.heap_bytes,
+        }
+    }
+}
+
+/// The columns of a `ColumnTable` opened from its directory
+struct MappedColumns {
+    rows: usize,
+    columns: Vec<Column>,
+}
+
+/// A row of a `ColumnTable` opened from its directory, whose values are decoded from the columns
+/// only once they are accessed
+#[derive(Clone)]
+pub struct ColumnRow<'a> {
+    columns: &'a [Column],
+    row: usize,
+}
+
+impl<'a> ColumnRow<'a> {
+    pub fn get(&self, index: usize) -> Option<ItemTypeRef<'_>> {
+        self.columns.get(index).and_then(|column| column.get(self.row))
+    }
+}
+
+impl<'a> std::fmt::Debug for ColumnRow<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let values: Vec<Option<ItemTypeRef>> =
+            (0..self.columns.len()).map(|i| self.get(i)).collect();
+        f.debug_struct("ColumnRow").field("row", &self.row).field("values", &values).finish()
+    }
+}
+
+impl<'a> PartialEq for ColumnRow<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self.columns, other.columns) && self.row == other.row
+    }
+}
+
+enum ColumnStore {
+    /// The rows being built, or read from a file exported by `PropertyTable`
+    Rows(PropertyTable),
+    Mapped(MappedColumns),
+}
+
+/// A table storing the properties by columns, which is built in rows like a dense
+/// `PropertyTable`, but exported by columns, and memory-mapped read-only once imported, see
+/// the module doc
+pub struct ColumnTable {
+    store: ColumnStore,
+}
+
+impl ColumnTable {
+    /// The residency of each column, which is empty unless the table is imported from a directory
+    /// of column files
+    pub fn residency(&self) -> Vec<ColumnResidency> {
+        match &self.store {
+            ColumnStore::Rows(_) => vec![],
+            ColumnStore::Mapped(mapped) => mapped.columns.iter().map(Column::residency).collect(),
+        }
+    }
+
+    fn open(dir: &Path) -> GDBResult<Self> {
+        let meta = crate::io::import::<ColumnsMeta, _>(dir.join(FILE_COLUMN_META))?;
+        let mut columns = Vec::with_capacity(meta.kinds.len());
+        for (index, kind) in meta.kinds.iter().enumerate() {
+            let path = dir.join(format!("{}{}", COLUMN_FILE_PREFIX, index));
+            columns.push(Column::open(&path, index, *kind, meta.rows)?);
+        }
+        Ok(ColumnTable { store: ColumnStore::Mapped(MappedColumns { rows: meta.rows, columns }) })
+    }
+}
+
+impl PropertyTableTrait for ColumnTable {
+    fn len(&self) -> usize {
+        match &self.store {
+            ColumnStore::Rows(table) => table.len(),
+            ColumnStore::Mapped(mapped) => mapped.rows,
+        }
+    }
+
+    fn get_row(&self, index: usize) -> GDBResult<RowRef<'_>> {
+        match &self.store {
+            ColumnStore::Rows(table) => table.get_row(index),
+            ColumnStore::Mapped(mapped) => {
+                if index < mapped.rows {
+                    Ok(RowRef::Column(ColumnRow { columns: &mapped.columns, row: index }))
+                } else {
+                    Ok(RowRef::None)
+                }
+            }
+        }
+    }
+
+    fn insert(&mut self, index: usize, row: Row) -> GDBResult<Option<Row>> {
+        match &mut self.store {
+            ColumnStore::Rows(table) => table.insert(index, row),
+            ColumnStore::Mapped(_) => Err(GDBError::ModifyReadOnlyError),
+        }
+    }
+
+    fn new<P: AsRef<Path>>(_path: P) -> Self {
+        ColumnTable { store: ColumnStore::Rows(PropertyTable::new_dense()) }
+    }
+
+    fn export<P: AsRef<Path>>(&self, path: P) -> GDBResult<()> {
+        let rows = self.len();
+        let mut columns = 0;
+        for index in 0..rows {
+            match self.get_row(index)? {
+                RowRef::Ref(row) => columns = columns.max(row.len()),
+                RowRef::Column(row) => columns = columns.max(row.columns.len()),
+                _ => (),
+            }
+        }
+        export_columns(path.as_ref(), rows, columns, |row, column| {
+            self.get_row(row).ok()?.get(column).and_then(|value| value.try_to_owned())
+        })
+    }
+
+    /// Import the table from a directory of column files, or from a file exported by
+    /// `PropertyTable`, which is read into the heap as it is
+    fn import<P: AsRef<Path>>(path: P) -> GDBResult<Self> {
+        let path = path.as_ref();
+        if path.is_dir() {
+            ColumnTable::open(path)
+        } else {
+            Ok(ColumnTable { store: ColumnStore::Rows(PropertyTable::import(path)?) })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::common::{DefaultId, InternalId, INVALID_LABEL_ID};
+    use crate::config::{GraphDBConfig, JsonConf};
+    use crate::graph_db::{GlobalStoreTrait, GlobalStoreUpdate};
+    use crate::graph_db_impl::{LargeGraphDB, MutableGraphDB};
+    use crate::schema::LDBCGraphSchema;
+    use crate::table::SingleValueTable;
+    use std::collections::HashMap;
+
+    const NUM_PROPERTIES: usize = 20;
+
+    /// The value of the property of the vertex, of the kind following the index of the property,
+    /// where the last property is of mixed kinds, and some vertices miss the last properties
+    fn property(vertex: usize, index: usize) -> Option<ItemType> {
+        if vertex.is_multiple_of(5) && index >= NUM_PROPERTIES - 5 {
+            return None;
+        }
+        let value = match index % 5 {
+            _ if index == NUM_PROPERTIES - 1 => {
+                if vertex.is_multiple_of(2) {
+                    object!(vertex as i64)
+                } else {
+                    object!(format!("v{}", vertex))
+                }
+            }
+            0 => object!((vertex * index) as i64),
+            1 => object!((vertex + index) as i32),
+            2 => object!(vertex as f64 / (index as f64 + 1.0)),
+            3 => object!(format!("{}-{}", index, vertex % 13)),
+            _ => object!(vertex as i8),
+        };
+        Some(value)
+    }
+
+    fn row_of(vertex: usize) -> Row {
+        Row::from(
+            (0..NUM_PROPERTIES).filter_map(|index| property(vertex, index)).collect::<Vec<_>>(),
+        )
+    }
+
+    #[test]
+    fn test_export_import_columns() {
+        let temp = tempdir::TempDir::new("test_columns").expect("Open temp folder error");
+        let path = temp.path().join("node_property");
+        let mut table = ColumnTable::new(&path);
+        let mut reference = PropertyTable::new_dense();
+        // the rows 3 and 4 are absent
+        for vertex in (0..100).filter(|v| *v != 3 && *v != 4) {
+            table.insert(vertex, row_of(vertex)).unwrap();
+            reference.insert(vertex, row_of(vertex)).unwrap();
+        }
+        table.export(&path).unwrap();
+        let table = ColumnTable::import(&path).unwrap();
+
+        assert_eq!(table.len(), reference.len());
+        let residency = table.residency();
+        assert_eq!(residency.len(), NUM_PROPERTIES);
+        assert_eq!(residency[0].kind, ColumnKind::Long);
+        assert_eq!(residency[1].kind, ColumnKind::Integer);
+        assert_eq!(residency[3].kind, ColumnKind::String);
+        assert_eq!(residency[NUM_PROPERTIES - 1].kind, ColumnKind::Object);
+        assert!(residency[NUM_PROPERTIES - 1].heap_bytes > 0);
+        assert!(residency.iter().all(|column| column.touched_bytes == 0));
+
+        for vertex in 0..101 {
+            let row = table.get_row(vertex).unwrap();
+            let expected = reference.get_row(vertex).unwrap();
+            for index in 0..NUM_PROPERTIES + 1 {
+                assert_eq!(row.get(index), expected.get(index), "{}, {}", vertex, index);
+            }
+        }
+        let residency = table.residency();
+        assert!(residency[3].touched_bytes > 0);
+        assert!(residency[3].touched_bytes <= residency[3].mapped_bytes);
+
+        // a table opened read-only can not be modified
+        let mut table = table;
+        assert!(table.insert(0, Row::default()).is_err());
+    }
+
+    #[test]
+    fn test_residency_of_untouched_columns() {
+        let temp = tempdir::TempDir::new("test_residency").expect("Open temp folder error");
+        let path = temp.path().join("node_property");
+        let mut table = ColumnTable::new(&path);
+        for vertex in 0..10000 {
+            table.insert(vertex, row_of(vertex)).unwrap();
+        }
+        table.export(&path).unwrap();
+        let table = ColumnTable::import(&path).unwrap();
+        for vertex in 0..100 {
+            table.get_row(vertex).unwrap().get(1);
+        }
+        let residency = table.residency();
+        // only the first pages of the column accessed are paged in
+        assert!(residency[1].touched_bytes > 0);
+        assert!(residency[1].touched_bytes < residency[1].mapped_bytes);
+        for column in residency.iter().filter(|column| column.index != 1) {
+            assert_eq!(column.touched_bytes, 0, "{:?}", column);
+        }
+    }
+
+    #[test]
+    fn test_import_property_table_file() {
+        let temp = tempdir::TempDir::new("test_import_rows").expect("Open temp folder error");
+        let path = temp.path().join("node_property");
+        let mut reference = PropertyTable::new_dense();
+        for vertex in 0..10 {
+            reference.insert(vertex, row_of(vertex)).unwrap();
+        }
+        reference.export(&path).unwrap();
+        let table = ColumnTable::import(&path).unwrap();
+        assert_eq!(table.len(), 10);
+        assert!(table.residency().is_empty());
+        for vertex in 0..10 {
+            assert_eq!(table.get_row(vertex).unwrap(), reference.get_row(vertex).unwrap());
+        }
+    }
+
+    fn schema_of_properties() -> LDBCGraphSchema {
+        let mut properties = vec!["[\"id\", \"ID\"]".to_string()];
+        for index in 1..NUM_PROPERTIES {
+            properties.push(format!("[\"p{}\", \"String\"]", index));
+        }
+        let json = format!(
+            "{{\"vertex_type_map\": {{\"ITEM\": 0}}, \"edge_type_map\": {{\"LINK\": 1}}, \
+             \"vertex_prop\": {{\"ITEM\": [{}]}}, \"edge_prop\": {{\"LINK\": []}}}}",
+            properties.join(", ")
+        );
+        LDBCGraphSchema::from_json(json).expect("Parse schema error")
+    }
+
+    fn build_and_open<N>(
+        root_dir: &Path, schema_file: &Path,
+    ) -> LargeGraphDB<DefaultId, InternalId, N>
+    where
+        N: PropertyTableTrait + Send + Sync + 'static,
+    {
+        let mut graph: MutableGraphDB<DefaultId, InternalId, N, SingleValueTable> =
+            GraphDBConfig::default().root_dir(root_dir).new();
+        for vertex in 0..500 {
+            // some vertices have no property
+            if vertex % 11 == 0 {
+                graph.add_vertex(vertex, [0.into(), INVALID_LABEL_ID]);
+            } else {
+                graph
+                    .add_vertex_with_properties(
+                        vertex,
+                        [0.into(), INVALID_LABEL_ID],
+                        row_of(vertex),
+                    )
+                    .unwrap();
+            }
+        }
+        graph.export().expect("Export error");
+        GraphDBConfig::default()
+            .root_dir(root_dir)
+            .schema_file(schema_file)
+            .open::<DefaultId, InternalId, N, SingleValueTable>()
+            .expect("Open graph error")
+    }
+
+    #[test]
+    fn test_columns_equivalence() {
+        let temp = tempdir::TempDir::new("test_columns_eq").expect("Open temp folder error");
+        let schema_file = temp.path().join("schema.json");
+        schema_of_properties().to_json_file(&schema_file).unwrap();
+        let rows = build_and_open::<PropertyTable>(&temp.path().join("rows"), &schema_file);
+        let columns = build_and_open::<ColumnTable>(&temp.path().join("columns"), &schema_file);
+        assert_eq!(columns.get_vertex_prop_table().residency().len(), NUM_PROPERTIES);
+
+        // all the properties of each vertex
+        for vertex in 0..501 {
+            let expected = rows.get_vertex(vertex).and_then(|v| v.clone_all_properties());
+            let actual = columns.get_vertex(vertex).and_then(|v| v.clone_all_properties());
+            assert_eq!(actual, expected, "{}", vertex);
+        }
+
+        let mut expected = filtered(&rows);
+        let mut actual = filtered(&columns);
+        expected.sort();
+        actual.sort();
+        assert!(!expected.is_empty());
+        assert_eq!(actual, expected);
+        assert_eq!(projected(&columns), projected(&rows));
+    }
+
+    /// Filter the vertices by a numeric property and a string property
+    fn filtered<N: PropertyTableTrait + Sync>(
+        graph: &LargeGraphDB<DefaultId, InternalId, N>,
+    ) -> Vec<DefaultId> {
+        graph
+            .get_all_vertices(None)
+            .filter(|v| {
+                let p6 = v.get_property("p6").and_then(|p| p.as_i32().ok()).unwrap_or(0);
+                let p8 = v.get_property("p8").and_then(|p| p.as_str().ok().map(|s| s.into_owned()));
+                p6 > 100 && p8 != Some("8-5".to_owned())
+            })
+            .map(|v| v.get_id())
+            .collect()
+    }
+
+    /// Project some properties of the vertices, by their names and by their indices
+    fn projected<N: PropertyTableTrait + Sync>(
+        graph: &LargeGraphDB<DefaultId, InternalId, N>,
+    ) -> HashMap<DefaultId, Vec<Option<ItemType>>> {
+        let mut projected = HashMap::new();
+        for v in graph.get_all_vertices(None) {
+            let index = v.get_property_index("p13");
+            let values = vec![
+                v.get_property("p2").and_then(|p| p.try_to_owned()),
+                v.get_property("p19").and_then(|p| p.try_to_owned()),
+                index.and_then(|i| v.get_property_at(i)).and_then(|p| p.try_to_owned()),
+            ];
+            projected.insert(v.get_id(), values);
+        }
+        projected
+    }
+}
@@ -13,13 +13,124 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
+use crate::error::{GDBError, GDBResult};
+use std::convert::TryFrom;
+use std::fmt;
+
 pub type DefaultId = usize;
-pub type LabelId = u8;
 /// A vertex's label can be hierarchical, which so far can have at most two layers.
 pub type Label = [LabelId; 2];
 pub type PartitionId = u16;
 pub type InternalId = u32;
 
+/// The id of a vertex or an edge label, stored in a byte, where `INVALID_LABEL_ID` is reserved
+/// for the absence of a label, e.g. the second layer of a label of one layer. The ids given from
+/// the outside, e.g. by the queries, are checked by `LabelId::new` instead of being truncated
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    Abomonation,
+)]
+#[serde(transparent)]
+pub struct LabelId(u8);
+
+impl LabelId {
+    /// The label of the id, or `GDBError::InvalidLabelIdError` if the id is out of the range of
+    /// the labels, i.e. negative, or not below `INVALID_LABEL_ID`
+    pub fn new(id: i64) -> GDBResult<Self> {
+        if id >= 0 && id < INVALID_LABEL_ID.0 as i64 {
+            Ok(LabelId(id as u8))
+        } else {
+            Err(GDBError::InvalidLabelIdError(id))
+        }
+    }
+
+    /// The label, or `None` if it is `INVALID_LABEL_ID`, e.g. the absent second layer of a label
+    #[inline]
+    pub fn valid(self) -> Option<Self> {
+        if self == INVALID_LABEL_ID {
+            None
+        } else {
+            Some(self)
+        }
+    }
+
+    /// The index of the label, e.g. of the vertices of the label grouped by their labels
+    #[inline]
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// The label stored in a byte, e.g. by the raw data or the snapshots, which is `INVALID_LABEL_ID`
+/// if the byte is 0xff
+impl From<u8> for LabelId {
+    fn from(id: u8) -> Self {
+        LabelId(id)
+    }
+}
+
+impl From<LabelId> for u8 {
+    fn from(label: LabelId) -> Self {
+        label.0
+    }
+}
+
+/// The label id given by `int32` in the protobuf messages, e.g. of the queries
+impl From<LabelId> for i32 {
+    fn from(label: LabelId) -> Self {
+        label.0 as i32
+    }
+}
+
+/// Check the label id given by `int32` in the protobuf messages, see `LabelId::new`
+impl TryFrom<i32> for LabelId {
+    type Error = GDBError;
+
+    fn try_from(id: i32) -> GDBResult<Self> {
+        LabelId::new(id as i64)
+    }
+}
+
+impl fmt::Display for LabelId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 pub static VERSION: &str = env!("CARGO_PKG_VERSION");
 pub static NAME: &str = env!("CARGO_PKG_NAME");
-pub static INVALID_LABEL_ID: LabelId = 0xff;
+pub const INVALID_LABEL_ID: LabelId = LabelId(0xff);
+/// The reserved label of the placeholders created for the absent end vertices of the dangling
+/// edges, see `DanglingEdgePolicy::CreatePlaceholder`
+pub const PLACEHOLDER_LABEL_ID: LabelId = LabelId(0xfe);
+/// The name of `PLACEHOLDER_LABEL_ID`, which is reserved in all the schemas
+pub static PLACEHOLDER_LABEL_NAME: &str = "PLACEHOLDER";
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_label_id() {
+        assert_eq!(LabelId::new(0).unwrap(), LabelId::from(0));
+        assert_eq!(LabelId::new(254).unwrap(), PLACEHOLDER_LABEL_ID);
+        assert!(matches!(LabelId::new(255), Err(GDBError::InvalidLabelIdError(255))));
+        assert!(matches!(LabelId::new(300), Err(GDBError::InvalidLabelIdError(300))));
+        assert!(matches!(LabelId::new(-1), Err(GDBError::InvalidLabelIdError(-1))));
+        assert_eq!(INVALID_LABEL_ID.valid(), None);
+        assert_eq!(LabelId::from(3).valid(), Some(LabelId::from(3)));
+        assert_eq!(LabelId::from(3).to_string(), "3");
+        assert_eq!(LabelId::try_from(12).map(i32::from).unwrap(), 12);
+        assert!(LabelId::try_from(256).is_err());
+    }
+}
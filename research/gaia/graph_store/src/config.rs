@@ -17,8 +17,10 @@ use crate::common::{Label, LabelId};
 use crate::error::{GDBError, GDBResult};
 use crate::graph_db_impl::{IndexData, LargeGraphDB, MutableGraphDB};
 use crate::io::import;
-use crate::schema::LDBCGraphSchema;
+use crate::schema::{GraphSchemaInfo, LDBCGraphSchema};
+use crate::statistics::{GraphStatistics, SKETCH_HASH};
 use crate::table::PropertyTableTrait;
+use crate::upgrade::{layout_version, upgrade_partition, UpgradePolicy, LAYOUT_VERSION};
 use petgraph::graph::{DiGraph, IndexType};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
@@ -57,6 +59,8 @@ pub const FILE_GRAPH_STRUCT: &'static str = "graph_struct";
 pub const FILE_NODE_PPT_DATA: &'static str = "node_property";
 pub const FILE_EDGE_PPT_DATA: &'static str = "edge_property";
 pub const FILE_INDEX_DATA: &'static str = "index_data";
+pub const FILE_STATISTICS: &str = "statistics";
+pub const FILE_LAYOUT_VERSION: &str = "layout_version";
 pub const PARTITION_PREFIX: &'static str = "partition_";
 
 /// The configuration to open an graph database for loading and querying data.
@@ -67,9 +71,13 @@ pub const PARTITION_PREFIX: &'static str = "partition_";
 /// <`root_dir`>
 /// ---- DIR_BINARY_DATA (graph_data_bin) # a directory of graph data
 /// ---- ---- FILE_GRAPH_STRUCT (graph_struct) # a binary file that encodes graph structure
-/// ---- ---- FILE_NODE_PPT_DATA (node_property) # a binary file that encodes vertices' properties
+/// ---- ---- FILE_NODE_PPT_DATA (node_property) # a binary file that encodes vertices' properties,
+///           or a directory of column files for `ColumnTable`
 /// ---- ---- FILE_EDGE_PPT_DATA (edge_property) # a binary file that encodes edges' properties
 /// ---- ---- FILE_INDEX_DATA (index_data) # a binary file that encodes any index data
+/// ---- ---- FILE_STATISTICS (statistics) # an optional binary file that encodes the statistics
+/// ---- ---- FILE_LAYOUT_VERSION (layout_version) # a text file of the layout version, see
+///           `crate::upgrade`
 /// ---- DIR_GRAPH_SCHEMA (graph_schema) # a directory of schema
 /// ---- ---- FILE_SCHEMA (schema.json)  # a json file that contains the graph schema (user given)
 ///
@@ -86,9 +94,9 @@ pub const PARTITION_PREFIX: &'static str = "partition_";
 ///     .partition(0)
 ///     .new();
 ///
-/// mut_graph.add_vertex(0, [0, 0]);
-/// mut_graph.add_vertex(1, [0, 0]);
-/// mut_graph.add_edge(0, 1, 0);
+/// mut_graph.add_vertex(0, [0.into(), INVALID_LABEL_ID]);
+/// mut_graph.add_vertex(1, [0.into(), INVALID_LABEL_ID]);
+/// mut_graph.add_edge(0, 1, 0.into());
 ///
 /// // Shall see the above graph directories created under data/test_data
 /// // Note that user must place the schema file as "data/test_data/graph_schema/schema.json"
@@ -103,7 +111,7 @@ pub const PARTITION_PREFIX: &'static str = "partition_";
 ///
 /// assert_eq!(
 ///     vec![1],
-///     graph.get_out_vertices(0, Some(&vec![0])).map(|v| v.get_id()).collect::<Vec<DefaultId>>()
+///     graph.get_out_vertices(0, Some(&vec![0.into()])).map(|v| v.get_id()).collect::<Vec<DefaultId>>()
 /// );
 ///
 /// ```
@@ -112,7 +120,7 @@ pub struct GraphDBConfig {
     /// The root directory in which the encoded data (and their directories are maintained)
     pub root_dir: PathBuf,
     /// The path to the schema file of the graph DB
-    schema_file: PathBuf,
+    pub(crate) schema_file: PathBuf,
     /// The initial number of vertices, used only for building graphs via `MutableGraphDB`
     init_vertices: usize,
     /// The initial number of edges, used only for building graphs via `MutableGraphDB`
@@ -121,6 +129,9 @@ pub struct GraphDBConfig {
     number_vertex_labels: usize,
     /// The partition id of this graph data
     partition: usize,
+    /// What to do with the partition of an older layout version while opening it, or `None` to
+    /// open it as it is, as long as this version reads it
+    upgrade_policy: Option<UpgradePolicy>,
 }
 
 impl Default for GraphDBConfig {
@@ -132,6 +143,7 @@ impl Default for GraphDBConfig {
             init_edges: 1000,
             number_vertex_labels: 20,
             partition: 0,
+            upgrade_policy: None,
         }
     }
 }
@@ -167,6 +179,11 @@ impl GraphDBConfig {
         self
     }
 
+    pub fn upgrade_policy(mut self, policy: UpgradePolicy) -> Self {
+        self.upgrade_policy = Some(policy);
+        self
+    }
+
     /// Open an existing **read-only** graph database from `Self::root_dir`.
     pub fn open<G, I, N, E>(&self) -> GDBResult<LargeGraphDB<G, I, N, E>>
     where
@@ -220,10 +237,35 @@ impl GraphDBConfig {
             }
         }
 
+        if partition_dir.exists() {
+            match &self.upgrade_policy {
+                Some(policy) => {
+                    let (dir, from) = upgrade_partition::<G, I, N, _>(
+                        self,
+                        &partition_dir,
+                        &graph_schema,
+                        policy,
+                        &mut |step| info!("Partition {:?}: {:?}", which_part, step),
+                    )?;
+                    if from < LAYOUT_VERSION {
+                        info!("Partition {:?} is upgraded from version {}", which_part, from);
+                    }
+                    partition_dir = dir;
+                }
+                None => {
+                    let version = layout_version(&partition_dir)?;
+                    if version > LAYOUT_VERSION {
+                        return Err(GDBError::LayoutVersionError(version, LAYOUT_VERSION));
+                    }
+                }
+            }
+        }
+
         let file_graph_struct = partition_dir.join(FILE_GRAPH_STRUCT);
         let file_node_ppt_data = partition_dir.join(FILE_NODE_PPT_DATA);
         let file_edge_ppt_data = partition_dir.join(FILE_EDGE_PPT_DATA);
         let file_index_data = partition_dir.join(FILE_INDEX_DATA);
+        let file_statistics = partition_dir.join(FILE_STATISTICS);
 
         let graph_handle =
             std::thread::spawn(move || import::<DiGraph<Label, LabelId, I>, _>(&file_graph_struct));
@@ -237,14 +279,31 @@ impl GraphDBConfig {
         let edge_prop_table = e_prop_handle.join()??;
         let index_data = index_handle.join()??;
 
-        let graph_db = LargeGraphDB {
+        let schema_info = Arc::new(GraphSchemaInfo::compute(&graph, &graph_schema));
+        let mut graph_db = LargeGraphDB {
             partition: which_part,
             graph,
             graph_schema: Arc::new(graph_schema),
             vertex_prop_table,
             edge_prop_table,
             index_data,
+            statistics: Arc::new(GraphStatistics::default()),
+            schema_info,
+            segments: Default::default(),
         };
+        // The statistics are not exported by earlier versions, nor of a stable hash before the
+        // version 3, compute them if so
+        let statistics = if file_statistics.exists() && layout_version(&partition_dir)? >= 3 {
+            Some(import::<GraphStatistics, _>(&file_statistics)?)
+        } else {
+            None
+        };
+        match statistics {
+            Some(statistics) if statistics.sketch_hash() == SKETCH_HASH => {
+                graph_db.statistics = Arc::new(statistics)
+            }
+            _ => graph_db.recompute_statistics(),
+        }
 
         info!("Time elapsed: {:?}", timer.elapsed().as_secs_f64());
 
@@ -0,0 +1,210 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The dangling edges, of which an end vertex is found in none of the vertex files, e.g. of a
+//! person deleted after the edges are dumped. An end vertex of an edge is told dangling by the ids
+//! of all the vertices loaded before the edges, which are kept during the loading. The loaders
+//! handle the dangling edges by `DanglingEdgePolicy`.
+//!
+//! The placeholders are created in the partitions their ids belong to, labelled by the reserved
+//! `PLACEHOLDER_LABEL_ID`, and carry no property. The end of every edge to a placeholder is
+//! labelled the same, so that the corner vertices of a placeholder in the other partitions are
+//! of the same label as the placeholder itself.
+
+use crate::common::{Label, LabelId, INVALID_LABEL_ID, PLACEHOLDER_LABEL_ID};
+use crate::error::{GDBError, GDBResult};
+use crate::parser::EdgeMeta;
+use petgraph::graph::IndexType;
+use std::collections::HashSet;
+use std::hash::Hash;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+
+/// The most dangling edges listed by `GDBError::DanglingEdgesError`
+pub const MAX_LISTED_DANGLING_EDGES: usize = 100;
+
+/// The label of the placeholders created for the absent end vertices of the dangling edges
+pub fn placeholder_label() -> Label {
+    [PLACEHOLDER_LABEL_ID, INVALID_LABEL_ID]
+}
+
+/// How the loaders handle the dangling edges
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum DanglingEdgePolicy {
+    /// Fail the loading, listing the dangling edges by `GDBError::DanglingEdgesError`
+    #[default]
+    Reject,
+    /// Drop the dangling edges, which are counted in the report of the loading
+    Skip,
+    /// Load the dangling edges, with a placeholder created for each absent end vertex
+    CreatePlaceholder,
+}
+
+/// A dangling edge
+#[derive(Clone, Debug, PartialEq)]
+pub struct DanglingEdge {
+    /// The file that contains the edge
+    pub file: PathBuf,
+    /// The line number of the edge, starting from 1
+    pub line: u64,
+    pub src_global_id: usize,
+    pub dst_global_id: usize,
+    pub label_id: LabelId,
+}
+
+struct Collected<G> {
+    num_dangling_edges: usize,
+    // the first dangling edges, which are listed once the loading is rejected
+    listed: Vec<DanglingEdge>,
+    placeholders: HashSet<G>,
+}
+
+/// Tell the dangling edges by the vertices loaded before, and handle them by the policy, which is
+/// shared by the threads reading the raw files
+#[derive(Clone)]
+pub(crate) struct DanglingEdgeChecker<G> {
+    policy: DanglingEdgePolicy,
+    vertices: Arc<RwLock<HashSet<G>>>,
+    collected: Arc<Mutex<Collected<G>>>,
+}
+
+impl<G: IndexType + Eq + Hash> DanglingEdgeChecker<G> {
+    pub(crate) fn new(policy: DanglingEdgePolicy) -> Self {
+        DanglingEdgeChecker {
+            policy,
+            vertices: Arc::new(RwLock::new(HashSet::new())),
+            collected: Arc::new(Mutex::new(Collected {
+                num_dangling_edges: 0,
+                listed: vec![],
+                placeholders: HashSet::new(),
+            })),
+        }
+    }
+
+    /// Record the vertices loaded, which must be done before any edge is checked
+    pub(crate) fn add_vertices<I: IntoIterator<Item = G>>(&self, ids: I) -> GDBResult<()> {
+        let mut vertices = self.vertices.write().map_err(|_| GDBError::UnknownError)?;
+        vertices.extend(ids);
+        Ok(())
+    }
+
+    /// Check the ends of the edge, and tell whether to load it. The absent ends of the edge are
+    /// labelled by `PLACEHOLDER_LABEL_ID` if their placeholders are to be created.
+    pub(crate) fn check(&self, edge: &mut EdgeMeta<G>, file: &Path, line: u64) -> GDBResult<bool> {
+        let (src_absent, dst_absent) = {
+            let vertices = self.vertices.read().map_err(|_| GDBError::UnknownError)?;
+            (!vertices.contains(&edge.src_global_id), !vertices.contains(&edge.dst_global_id))
+        };
+        if !src_absent && !dst_absent {
+            return Ok(true);
+        }
+        debug!("Dangling edge at {:?}:{}: {:?}", file, line, edge);
+        let mut collected = self.collected.lock().map_err(|_| GDBError::UnknownError)?;
+        collected.num_dangling_edges += 1;
+        match self.policy {
+            DanglingEdgePolicy::Reject => {
+                if collected.listed.len() < MAX_LISTED_DANGLING_EDGES {
+                    collected.listed.push(DanglingEdge {
+                        file: file.to_path_buf(),
+                        line,
+                        src_global_id: edge.src_global_id.index(),
+                        dst_global_id: edge.dst_global_id.index(),
+                        label_id: edge.label_id,
+                    });
+                }
+                Ok(false)
+            }
+            DanglingEdgePolicy::Skip => Ok(false),
+            DanglingEdgePolicy::CreatePlaceholder => {
+                if src_absent {
+                    edge.src_label_id = PLACEHOLDER_LABEL_ID;
+                    collected.placeholders.insert(edge.src_global_id);
+                }
+                if dst_absent {
+                    edge.dst_label_id = PLACEHOLDER_LABEL_ID;
+                    collected.placeholders.insert(edge.dst_global_id);
+                }
+                Ok(true)
+            }
+        }
+    }
+
+    /// Finish checking the edges, which fails by `GDBError::DanglingEdgesError` if any edge is
+    /// rejected, or gives the number of the dangling edges and that of the placeholders otherwise
+    pub(crate) fn finish(&self) -> GDBResult<(usize, usize)> {
+        let collected = self.collected.lock().map_err(|_| GDBError::UnknownError)?;
+        if self.policy == DanglingEdgePolicy::Reject && collected.num_dangling_edges > 0 {
+            let mut listed = collected.listed.clone();
+            listed.sort_by(|e1, e2| (&e1.file, e1.line).cmp(&(&e2.file, e2.line)));
+            Err(GDBError::DanglingEdgesError(collected.num_dangling_edges, listed))
+        } else {
+            Ok((collected.num_dangling_edges, collected.placeholders.len()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::common::DefaultId;
+
+    fn edge(src: DefaultId, dst: DefaultId) -> EdgeMeta<DefaultId> {
+        EdgeMeta {
+            src_global_id: src,
+            src_label_id: 1.into(),
+            dst_global_id: dst,
+            dst_label_id: 1.into(),
+            label_id: 0.into(),
+        }
+    }
+
+    fn checker(policy: DanglingEdgePolicy) -> DanglingEdgeChecker<DefaultId> {
+        let checker = DanglingEdgeChecker::new(policy);
+        checker.add_vertices(vec![1, 2]).unwrap();
+        checker
+    }
+
+    #[test]
+    fn test_reject_dangling_edges() {
+        let checker = checker(DanglingEdgePolicy::Reject);
+        let file = Path::new("knows.csv");
+        assert!(checker.check(&mut edge(1, 2), file, 1).unwrap());
+        assert!(!checker.check(&mut edge(1, 3), file, 2).unwrap());
+        match checker.finish() {
+            Err(GDBError::DanglingEdgesError(num, listed)) => {
+                assert_eq!(num, 1);
+                assert_eq!(listed.len(), 1);
+                assert_eq!((listed[0].line, listed[0].dst_global_id), (2, 3));
+            }
+            _ => panic!("expect the dangling edges rejected"),
+        }
+    }
+
+    #[test]
+    fn test_placeholder_dangling_edges() {
+        let checker = checker(DanglingEdgePolicy::CreatePlaceholder);
+        let file = Path::new("knows.csv");
+        let mut dangling = edge(3, 2);
+        assert!(checker.check(&mut dangling, file, 1).unwrap());
+        assert_eq!(
+            (dangling.src_label_id, dangling.dst_label_id),
+            (PLACEHOLDER_LABEL_ID, 1.into())
+        );
+        assert!(checker.check(&mut edge(1, 3), file, 2).unwrap());
+        assert!(checker.check(&mut edge(3, 4), file, 3).unwrap());
+        // 3 edges dangle on 2 placeholders
+        assert_eq!(checker.finish().unwrap(), (3, 2));
+    }
+}
@@ -0,0 +1,678 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The mutable delta of a graph, i.e. the vertices and edges added after the graph is loaded,
+//! which the immutable `LargeGraphDB` is read together with. Each mutation is tagged by the
+//! version it creates, which increases by one with each mutation, where the loaded graph is of
+//! version 0. Adding a vertex of an id added before, or loaded, adds a newer version of the
+//! vertex, which replaces the older ones for the readers of the newer versions.
+//!
+//! A reader pins the version of the latest mutation by `GraphDelta::pin`, e.g. once a query is
+//! submitted, and reads at the pinned version by all its reads, so that it never sees any
+//! mutation after, however long it runs. The versions of a vertex replaced before every pinned
+//! version are never read again, which are dropped by `GraphDelta::compact`, while those the
+//! pinned readers may read are kept until they are unpinned.
+//!
+//! A vertex or an edge, loaded or added, is deleted by a mutation of its own version as well, so
+//! that a reader sees it as long as it reads at a version before the deletion. An edge is deleted
+//! with either of its end vertices, and a vertex deleted is seen again once it is added after the
+//! deletion, while its edges deleted with it are not. The deletions are screened by bitmaps of
+//! the hashes of the ids deleted, whose single word is checked by a read of an id never deleted.
+//!
+//! A property of the vertices of a label may be indexed by `GraphDelta::create_index`, from the
+//! vertices loaded, which is kept up to date by each vertex added later, tagged by its version as
+//! well. A lookup of the index at a version finds exactly the vertices seen at the version of the
+//! value, where those replaced or deleted by then are masked. The entries of the vertices added
+//! are folded into those loaded by `GraphDelta::compact`, once all readers see the same versions.
+
+use crate::common::LabelId;
+use crate::graph_db::Direction;
+use dyn_type::Object;
+use petgraph::graph::IndexType;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+
+/// The version of a graph, i.e. the number of mutations applied to the loaded graph
+pub type Version = u64;
+
+/// A version of a vertex added by the delta
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeltaVertex<G> {
+    pub id: G,
+    pub label: LabelId,
+    pub properties: HashMap<String, Object>,
+    /// the version of the mutation adding the vertex
+    pub version: Version,
+}
+
+/// An edge added by the delta
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeltaEdge<G> {
+    pub src: G,
+    pub dst: G,
+    pub label: LabelId,
+    pub properties: HashMap<String, Object>,
+    /// the version of the mutation adding the edge
+    pub version: Version,
+}
+
+// the number of the bits screening the deletions of each kind
+const DELETION_BITS: usize = 1 << 16;
+
+/// A bitmap of the hashes of the keys deleted, where a key of a clear bit is never deleted, so that
+/// most keys are read without the lock of the deletions, together with the first version deleting
+struct DeletionBits {
+    words: Box<[AtomicU64]>,
+    first: AtomicU64,
+}
+
+impl DeletionBits {
+    fn new() -> Self {
+        let words = (0..DELETION_BITS / 64).map(|_| AtomicU64::new(0)).collect();
+        DeletionBits { words, first: AtomicU64::new(Version::MAX) }
+    }
+
+    fn insert(&self, hash: u64, version: Version) {
+        let bit = hash as usize % DELETION_BITS;
+        self.words[bit / 64].fetch_or(1 << (bit % 64), Ordering::SeqCst);
+        self.first.fetch_min(version, Ordering::SeqCst);
+    }
+
+    /// Whether any key is deleted by the version, where `first` is `Version::MAX` until any is
+    fn any(&self, at: Version) -> bool {
+        let first = self.first.load(Ordering::SeqCst);
+        first != Version::MAX && first <= at
+    }
+
+    /// Whether the key of the hash may be deleted by the version, which is false for most keys
+    /// never deleted
+    fn may_contain(&self, hash: u64, at: Version) -> bool {
+        let bit = hash as usize % DELETION_BITS;
+        self.any(at) && self.words[bit / 64].load(Ordering::SeqCst) & (1 << (bit % 64)) != 0
+    }
+}
+
+// the finalizer of splitmix64, spreading the ids of a range over the bitmaps
+fn mix(mut h: u64) -> u64 {
+    h = (h ^ (h >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94d049bb133111eb);
+    h ^ (h >> 31)
+}
+
+fn vertex_hash<G: IndexType>(id: G) -> u64 {
+    mix(id.index() as u64)
+}
+
+fn edge_hash<G: IndexType>(src: G, dst: G, label: LabelId) -> u64 {
+    mix(mix(src.index() as u64) ^ (dst.index() as u64).rotate_left(17) ^ label.index() as u64)
+}
+
+/// The label of the vertices and the property of them indexed
+pub type IndexKey = (LabelId, String);
+
+struct PropertyIndex<G> {
+    // value -> the vertices loaded, tagged by version 0, or folded by the compaction, tagged by
+    // their versions, where some may be replaced since
+    base: HashMap<Object, Vec<(G, Version)>>,
+    // value -> the versions of the vertices added, which are not folded yet
+    delta: HashMap<Object, Vec<(G, Version)>>,
+}
+
+struct DeltaData<G> {
+    // id -> the versions of the vertex, in the order of their versions
+    vertices: HashMap<G, Vec<Arc<DeltaVertex<G>>>>,
+    edges: Vec<Arc<DeltaEdge<G>>>,
+    // id -> the indexes of the edges starting from the vertex, in the order of their versions
+    out_edges: HashMap<G, Vec<usize>>,
+    // id -> the indexes of the edges ending at the vertex, in the order of their versions
+    in_edges: HashMap<G, Vec<usize>>,
+    // id -> the versions deleting the vertex
+    deleted_vertices: HashMap<G, Vec<Version>>,
+    // (src, dst, label) -> the versions deleting the edges of the key
+    deleted_edges: HashMap<(G, G, LabelId), Vec<Version>>,
+    // the properties indexed of the vertices
+    indexes: HashMap<IndexKey, PropertyIndex<G>>,
+}
+
+impl<G> Default for DeltaData<G> {
+    fn default() -> Self {
+        DeltaData {
+            vertices: HashMap::new(),
+            edges: vec![],
+            out_edges: HashMap::new(),
+            in_edges: HashMap::new(),
+            deleted_vertices: HashMap::new(),
+            deleted_edges: HashMap::new(),
+            indexes: HashMap::new(),
+        }
+    }
+}
+
+impl<G: IndexType> DeltaData<G> {
+    // the latest version of the vertex added by the version
+    fn get_vertex(&self, id: G, at: Version) -> Option<&Arc<DeltaVertex<G>>> {
+        self.vertices.get(&id)?.iter().rev().find(|v| v.version <= at)
+    }
+
+    fn is_vertex_deleted(&self, id: G, at: Version) -> bool {
+        match last_before(self.deleted_vertices.get(&id), at) {
+            Some(deleted) => deleted > self.get_vertex(id, at).map(|v| v.version).unwrap_or(0),
+            None => false,
+        }
+    }
+
+    // add the entry of the version of the vertex to the indexes of its label
+    fn index_vertex(&mut self, vertex: &DeltaVertex<G>) {
+        for ((label, property), index) in self.indexes.iter_mut() {
+            if *label == vertex.label {
+                if let Some(value) = vertex.properties.get(property) {
+                    let entries = index.delta.entry(value.clone()).or_insert_with(Vec::new);
+                    entries.push((vertex.id, vertex.version));
+                }
+            }
+        }
+    }
+}
+
+// the pinned versions -> the number of readers pinning each
+type Pins = Arc<Mutex<BTreeMap<Version, usize>>>;
+
+/// The vertices and edges added to a partition of a graph, each tagged by its version, where an
+/// edge is added to the partitions of both its end vertices
+pub struct GraphDelta<G> {
+    version: AtomicU64,
+    data: RwLock<DeltaData<G>>,
+    pins: Pins,
+    vertex_deletions: DeletionBits,
+    edge_deletions: DeletionBits,
+}
+
+impl<G> Default for GraphDelta<G> {
+    fn default() -> Self {
+        GraphDelta {
+            version: AtomicU64::new(0),
+            data: RwLock::new(DeltaData::default()),
+            pins: Arc::new(Mutex::new(BTreeMap::new())),
+            vertex_deletions: DeletionBits::new(),
+            edge_deletions: DeletionBits::new(),
+        }
+    }
+}
+
+/// A version of the delta pinned by a reader, which is unpinned once dropped
+#[derive(Debug)]
+pub struct SnapshotPin {
+    version: Version,
+    pins: Pins,
+}
+
+impl SnapshotPin {
+    pub fn version(&self) -> Version {
+        self.version
+    }
+}
+
+impl Drop for SnapshotPin {
+    fn drop(&mut self) {
+        if let Ok(mut pins) = self.pins.lock() {
+            if let Some(count) = pins.get_mut(&self.version) {
+                *count -= 1;
+                if *count == 0 {
+                    pins.remove(&self.version);
+                }
+            }
+        }
+    }
+}
+
+impl<G: IndexType> GraphDelta<G> {
+    /// The version of the latest mutation, or 0 if nothing is added
+    pub fn version(&self) -> Version {
+        self.version.load(Ordering::SeqCst)
+    }
+
+    /// Whether nothing is added, where the graph is read as it is loaded
+    pub fn is_empty(&self) -> bool {
+        self.version() == 0
+    }
+
+    /// Pin the version of the latest mutation, which is read as long as the pin is kept
+    pub fn pin(&self) -> SnapshotPin {
+        let mut pins = self.pins.lock().expect("lock poisoned");
+        // read under the lock of the pins, so that no compaction misses the version
+        let version = self.version();
+        *pins.entry(version).or_insert(0) += 1;
+        SnapshotPin { version, pins: self.pins.clone() }
+    }
+
+    /// The oldest version pinned by the readers, or `None` if no version is pinned
+    pub fn oldest_pinned(&self) -> Option<Version> {
+        self.pins.lock().ok()?.keys().next().copied()
+    }
+
+    /// Add a version of the vertex, which returns the version of the mutation
+    pub fn add_vertex(
+        &self, id: G, label: LabelId, properties: HashMap<String, Object>,
+    ) -> Version {
+        let mut data = self.data.write().expect("lock poisoned");
+        // the version is created under the lock, so that the readers of the version find the vertex
+        let version = self.version.fetch_add(1, Ordering::SeqCst) + 1;
+        let vertex = Arc::new(DeltaVertex { id, label, properties, version });
+        data.index_vertex(&vertex);
+        data.vertices.entry(id).or_default().push(vertex);
+        version
+    }
+
+    /// Add the edge, which returns the version of the mutation
+    pub fn add_edge(
+        &self, src: G, dst: G, label: LabelId, properties: HashMap<String, Object>,
+    ) -> Version {
+        let mut data = self.data.write().expect("lock poisoned");
+        let version = self.version.fetch_add(1, Ordering::SeqCst) + 1;
+        let index = data.edges.len();
+        data.edges.push(Arc::new(DeltaEdge { src, dst, label, properties, version }));
+        data.out_edges.entry(src).or_default().push(index);
+        data.in_edges.entry(dst).or_default().push(index);
+        version
+    }
+
+    /// Delete the vertex, loaded or added, together with its edges, which returns the version of
+    /// the mutation
+    pub fn delete_vertex(&self, id: G) -> Version {
+        let mut data = self.data.write().expect("lock poisoned");
+        let version = self.version.fetch_add(1, Ordering::SeqCst) + 1;
+        // the bit is set under the lock as well, before any reader of the version reads the id
+        self.vertex_deletions.insert(vertex_hash(id), version);
+        data.deleted_vertices.entry(id).or_default().push(version);
+        version
+    }
+
+    /// Delete the edges of the label from `src` to `dst`, loaded or added, which returns the
+    /// version of the mutation, where the edges are told by their end vertices and label, as the
+    /// edges loaded are
+    pub fn delete_edge(&self, src: G, dst: G, label: LabelId) -> Version {
+        let mut data = self.data.write().expect("lock poisoned");
+        let version = self.version.fetch_add(1, Ordering::SeqCst) + 1;
+        self.edge_deletions.insert(edge_hash(src, dst, label), version);
+        data.deleted_edges.entry((src, dst, label)).or_default().push(version);
+        version
+    }
+
+    /// Whether any vertex or edge is deleted by the version
+    pub fn has_deletions(&self, at: Version) -> bool {
+        self.vertex_deletions.any(at) || self.edge_deletions.any(at)
+    }
+
+    /// Whether any vertex is deleted by the version
+    pub fn has_vertex_deletions(&self, at: Version) -> bool {
+        self.vertex_deletions.any(at)
+    }
+
+    /// Whether any edge is deleted by the version, besides those deleted with their vertices
+    pub fn has_edge_deletions(&self, at: Version) -> bool {
+        self.edge_deletions.any(at)
+    }
+
+    /// Whether the vertex is deleted when read at the version, i.e. it is deleted after it is
+    /// added, or loaded, for the last time by then
+    pub fn is_vertex_deleted(&self, id: G, at: Version) -> bool {
+        if !self.vertex_deletions.may_contain(vertex_hash(id), at) {
+            return false;
+        }
+        self.data.read().map(|data| data.is_vertex_deleted(id, at)).unwrap_or(false)
+    }
+
+    /// Whether the edge of the label from `src` to `dst`, added by `version`, or 0 if it is
+    /// loaded, is deleted when read at the version, by itself or with either of its end vertices
+    pub fn is_edge_deleted(
+        &self, src: G, dst: G, label: LabelId, version: Version, at: Version,
+    ) -> bool {
+        let by_edge = self.edge_deletions.may_contain(edge_hash(src, dst, label), at);
+        let by_src = self.vertex_deletions.may_contain(vertex_hash(src), at);
+        let by_dst = self.vertex_deletions.may_contain(vertex_hash(dst), at);
+        if !(by_edge || by_src || by_dst) {
+            return false;
+        }
+        let data = match self.data.read() {
+            Ok(data) => data,
+            Err(_) => return false,
+        };
+        // the edge is deleted by any deletion after it is added, which it is never added back by
+        let deleted = |versions: Option<&Vec<Version>>| {
+            last_before(versions, at).map(|deleted| deleted > version).unwrap_or(false)
+        };
+        (by_edge && deleted(data.deleted_edges.get(&(src, dst, label))))
+            || (by_src && deleted(data.deleted_vertices.get(&src)))
+            || (by_dst && deleted(data.deleted_vertices.get(&dst)))
+    }
+
+    /// The version of the vertex read at the version, or `None` if the vertex is not added by
+    /// then, where it is read from the loaded graph if any
+    pub fn get_vertex(&self, id: G, at: Version) -> Option<Arc<DeltaVertex<G>>> {
+        self.data.read().ok()?.get_vertex(id, at).cloned()
+    }
+
+    /// The vertices read at the version, each of its latest version by then
+    pub fn get_vertices(&self, at: Version) -> Vec<Arc<DeltaVertex<G>>> {
+        match self.data.read() {
+            Ok(data) => data
+                .vertices
+                .values()
+                .filter_map(|versions| versions.iter().rev().find(|v| v.version <= at).cloned())
+                .collect(),
+            Err(_) => vec![],
+        }
+    }
+
+    /// The edges of the labels, or of any label if `None`, adjacent to the vertex in the
+    /// direction, which are read at the version
+    pub fn get_adj_edges(
+        &self, id: G, direction: Direction, labels: Option<&Vec<LabelId>>, at: Version,
+    ) -> Vec<Arc<DeltaEdge<G>>> {
+        let data = match self.data.read() {
+            Ok(data) => data,
+            Err(_) => return vec![],
+        };
+        let adjacency = match direction {
+            Direction::Outgoing => data.out_edges.get(&id),
+            Direction::Incoming => data.in_edges.get(&id),
+        };
+        adjacency
+            .into_iter()
+            .flatten()
+            .map(|i| &data.edges[*i])
+            // the edges of a vertex are added in the order of their versions
+            .take_while(|e| e.version <= at)
+            .filter(|e| labels.map(|labels| labels.contains(&e.label)).unwrap_or(true))
+            .cloned()
+            .collect()
+    }
+
+    /// Index the property of the vertices of the label, of the values of the vertices loaded, and
+    /// of the vertices added by then and later, which replaces the index of the same key if any
+    pub fn create_index<I>(&self, label: LabelId, property: &str, loaded: I)
+    where
+        I: IntoIterator<Item = (G, Object)>,
+    {
+        let mut data = self.data.write().expect("lock poisoned");
+        let mut base = HashMap::new();
+        for (id, value) in loaded {
+            base.entry(value).or_insert_with(Vec::new).push((id, 0));
+        }
+        let mut delta = HashMap::new();
+        for vertex in data.vertices.values().flatten().filter(|v| v.label == label) {
+            if let Some(value) = vertex.properties.get(property) {
+                delta
+                    .entry(value.clone())
+                    .or_insert_with(Vec::new)
+                    .push((vertex.id, vertex.version));
+            }
+        }
+        data.indexes.insert((label, property.to_owned()), PropertyIndex { base, delta });
+    }
+
+    /// The keys of the indexes created by `create_index`
+    pub fn get_index_keys(&self) -> Vec<IndexKey> {
+        match self.data.read() {
+            Ok(data) => data.indexes.keys().cloned().collect(),
+            Err(_) => vec![],
+        }
+    }
+
+    /// The vertices of the label whose property is of the value when read at the version, in the
+    /// order of their ids, which are looked up by the index of the property, or `None` if it is
+    /// not indexed
+    pub fn get_indexed_vertices(
+        &self, label: LabelId, property: &str, value: &Object, at: Version,
+    ) -> Option<Vec<G>> {
+        let data = self.data.read().ok()?;
+        let index = data.indexes.get(&(label, property.to_owned()))?;
+        let entries = index.base.get(value).into_iter().chain(index.delta.get(value)).flatten();
+        let mut ids: Vec<G> = entries
+            .filter(|(id, version)| {
+                // the entry is of the version of the vertex seen at the version, i.e. of the
+                // vertex loaded if no version is added by then
+                let seen = data.get_vertex(*id, at).map(|v| v.version).unwrap_or(0);
+                *version == seen && !data.is_vertex_deleted(*id, at)
+            })
+            .map(|(id, _)| *id)
+            .collect();
+        ids.sort();
+        ids.dedup();
+        Some(ids)
+    }
+
+    /// Drop the versions of the vertices replaced before the oldest pinned version, or before the
+    /// latest version if none is pinned, as no reader reads them any more, which returns the
+    /// number of versions dropped; The entries of the indexes of the versions seen by all readers
+    /// are folded into those of the vertices loaded, and those of the versions dropped are dropped
+    pub fn compact(&self) -> usize {
+        let mut data = self.data.write().expect("lock poisoned");
+        let horizon = {
+            let pins = self.pins.lock().expect("lock poisoned");
+            // later readers pin the version of the latest mutation at least
+            pins.keys().next().copied().unwrap_or_else(|| self.version())
+        };
+        let DeltaData { vertices, indexes, .. } = &mut *data;
+        for index in indexes.values_mut() {
+            for (value, entries) in index.delta.iter_mut() {
+                let mut folded = vec![];
+                entries.retain(|(id, version)| {
+                    if *version > horizon {
+                        return true;
+                    }
+                    let seen = vertices
+                        .get(id)
+                        .and_then(|versions| versions.iter().rev().find(|v| v.version <= horizon));
+                    if seen.map(|v| v.version) == Some(*version) {
+                        folded.push((*id, *version));
+                    }
+                    false
+                });
+                if !folded.is_empty() {
+                    index.base.entry(value.clone()).or_insert_with(Vec::new).extend(folded);
+                }
+            }
+            index.delta.retain(|_, entries| !entries.is_empty());
+        }
+        let mut dropped = 0;
+        for versions in data.vertices.values_mut() {
+            // the latest version by the horizon is read by the oldest readers
+            let visible = versions.iter().rposition(|v| v.version <= horizon);
+            if let Some(visible) = visible {
+                versions.drain(..visible);
+                dropped += visible;
+            }
+        }
+        dropped
+    }
+}
+
+// the latest of the versions by `at`, where the versions are in their order
+fn last_before(versions: Option<&Vec<Version>>, at: Version) -> Option<Version> {
+    versions?.iter().rev().find(|v| **v <= at).copied()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::common::DefaultId;
+
+    fn named(name: &str) -> HashMap<String, Object> {
+        let mut properties = HashMap::new();
+        properties.insert("name".to_owned(), object!(name));
+        properties
+    }
+
+    #[test]
+    fn test_read_at_versions() {
+        let delta = GraphDelta::<DefaultId>::default();
+        assert!(delta.is_empty());
+        assert_eq!(delta.add_vertex(1, LabelId::from(0), named("marko")), 1);
+        assert_eq!(delta.add_vertex(2, LabelId::from(0), named("vadas")), 2);
+        assert_eq!(delta.add_edge(1, 2, LabelId::from(0), HashMap::new()), 3);
+        assert_eq!(delta.add_vertex(1, LabelId::from(0), named("marko2")), 4);
+
+        assert!(delta.get_vertex(1, 0).is_none());
+        assert_eq!(delta.get_vertex(1, 3).unwrap().properties, named("marko"));
+        assert_eq!(delta.get_vertex(1, 4).unwrap().properties, named("marko2"));
+        assert_eq!(delta.get_vertices(1).len(), 1);
+        assert_eq!(delta.get_vertices(4).len(), 2);
+        assert!(delta.get_adj_edges(1, Direction::Outgoing, None, 2).is_empty());
+        let edges = delta.get_adj_edges(1, Direction::Outgoing, None, 3);
+        assert_eq!((edges[0].src, edges[0].dst), (1, 2));
+        assert_eq!(
+            delta.get_adj_edges(2, Direction::Incoming, Some(&vec![LabelId::from(0)]), 3).len(),
+            1
+        );
+        assert!(delta
+            .get_adj_edges(2, Direction::Incoming, Some(&vec![LabelId::from(1)]), 3)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_compact_retains_pinned() {
+        let delta = GraphDelta::<DefaultId>::default();
+        delta.add_vertex(1, LabelId::from(0), named("v1"));
+        let pin = delta.pin();
+        assert_eq!(pin.version(), 1);
+        delta.add_vertex(1, LabelId::from(0), named("v2"));
+        delta.add_vertex(1, LabelId::from(0), named("v3"));
+        assert_eq!(delta.oldest_pinned(), Some(1));
+        // the version read by the pin is kept
+        assert_eq!(delta.compact(), 0);
+        assert_eq!(delta.get_vertex(1, pin.version()).unwrap().properties, named("v1"));
+        let later = delta.pin();
+        drop(pin);
+        assert_eq!(delta.oldest_pinned(), Some(3));
+        assert_eq!(delta.compact(), 2);
+        assert_eq!(delta.get_vertex(1, later.version()).unwrap().properties, named("v3"));
+        drop(later);
+        assert_eq!(delta.oldest_pinned(), None);
+        assert_eq!(delta.compact(), 0);
+    }
+
+    #[test]
+    fn test_delete_at_versions() {
+        let delta = GraphDelta::<DefaultId>::default();
+        delta.add_vertex(1, LabelId::from(0), named("marko"));
+        delta.add_edge(1, 2, LabelId::from(0), HashMap::new());
+        assert!(!delta.has_deletions(2));
+        // the loaded vertex 3 and the edges loaded from it
+        assert_eq!(delta.delete_vertex(3), 3);
+        assert_eq!(delta.delete_edge(1, 2, LabelId::from(0)), 4);
+        assert!(!delta.has_deletions(2));
+        assert!(delta.has_vertex_deletions(3) && !delta.has_edge_deletions(3));
+        assert!(!delta.is_vertex_deleted(3, 2));
+        assert!(delta.is_vertex_deleted(3, 3));
+        assert!(!delta.is_vertex_deleted(4, 3));
+        assert!(delta.is_edge_deleted(3, 4, LabelId::from(0), 0, 3));
+        assert!(delta.is_edge_deleted(4, 3, LabelId::from(1), 0, 3));
+        assert!(!delta.is_edge_deleted(3, 4, LabelId::from(0), 0, 2));
+        // the added edge 1 -> 2 and those loaded of the same key
+        assert!(!delta.is_edge_deleted(1, 2, LabelId::from(0), 2, 3));
+        assert!(delta.is_edge_deleted(1, 2, LabelId::from(0), 2, 4));
+        assert!(delta.is_edge_deleted(1, 2, LabelId::from(0), 0, 4));
+        assert!(!delta.is_edge_deleted(1, 2, LabelId::from(1), 0, 4));
+
+        // the vertex added back is seen again, while its edges deleted are not
+        assert_eq!(delta.add_vertex(3, LabelId::from(0), named("lop")), 5);
+        assert!(delta.is_vertex_deleted(3, 4));
+        assert!(!delta.is_vertex_deleted(3, 5));
+        assert!(delta.is_edge_deleted(3, 4, LabelId::from(0), 0, 5));
+        assert_eq!(delta.add_edge(1, 2, LabelId::from(0), HashMap::new()), 6);
+        assert!(!delta.is_edge_deleted(1, 2, LabelId::from(0), 6, 6));
+        // the added vertex deleted
+        delta.delete_vertex(1);
+        assert!(!delta.is_vertex_deleted(1, 6));
+        assert!(delta.is_vertex_deleted(1, 7));
+        assert!(delta.is_edge_deleted(1, 2, LabelId::from(0), 6, 7));
+    }
+
+    #[test]
+    fn test_index_at_versions() {
+        let delta = GraphDelta::<DefaultId>::default();
+        assert_eq!(delta.add_vertex(10, LabelId::from(0), named("marko")), 1);
+        let loaded = vec![(1, object!("marko")), (2, object!("vadas")), (3, object!("josh"))];
+        delta.create_index(LabelId::from(0), "name", loaded);
+        assert_eq!(delta.get_index_keys(), vec![(LabelId::from(0), "name".to_owned())]);
+        let lookup = |name: &str, at: Version| {
+            delta
+                .get_indexed_vertices(LabelId::from(0), "name", &object!(name), at)
+                .expect("not indexed")
+        };
+        assert!(delta.get_indexed_vertices(LabelId::from(0), "age", &object!(29), 1).is_none());
+        assert_eq!(lookup("marko", 0), vec![1]);
+        assert_eq!(lookup("marko", 1), vec![1, 10]);
+
+        assert_eq!(delta.add_vertex(11, LabelId::from(0), named("marko")), 2);
+        // the loaded vertex replaced by another value
+        assert_eq!(delta.add_vertex(1, LabelId::from(0), named("lop")), 3);
+        let pin = delta.pin();
+        assert_eq!(delta.delete_vertex(10), 4);
+        // of another label
+        assert_eq!(delta.add_vertex(12, LabelId::from(1), named("marko")), 5);
+        // added back after deleted
+        assert_eq!(delta.add_vertex(10, LabelId::from(0), named("marko")), 6);
+        // deleted and never added back
+        assert_eq!(delta.delete_vertex(2), 7);
+        assert_eq!(lookup("marko", 2), vec![1, 10, 11]);
+        assert_eq!(lookup("marko", 3), vec![10, 11]);
+        assert_eq!(lookup("lop", 2), Vec::<DefaultId>::new());
+        assert_eq!(lookup("lop", 3), vec![1]);
+        assert_eq!(lookup("marko", 4), vec![11]);
+        assert_eq!(lookup("marko", 5), vec![11]);
+        assert_eq!(lookup("marko", 6), vec![10, 11]);
+        assert_eq!(lookup("vadas", 6), vec![2]);
+        assert_eq!(lookup("vadas", 7), Vec::<DefaultId>::new());
+
+        // the lookups at the versions pinned are the same once compacted
+        let names = ["marko", "vadas", "josh", "lop"];
+        let lookups = |versions: &[Version]| {
+            let mut results = vec![];
+            for at in versions {
+                results.extend(names.iter().map(|name| lookup(name, *at)));
+            }
+            results
+        };
+        let before = lookups(&[3, 5, 7]);
+        // the entries by the version 3 pinned are folded
+        assert_eq!(delta.compact(), 0);
+        assert_eq!(lookups(&[3, 5, 7]), before);
+        drop(pin);
+        // all entries are folded, where only the latest version is read
+        assert_eq!(delta.compact(), 1);
+        assert_eq!(lookups(&[7]), before[8..].to_vec());
+        assert!(delta.data.read().unwrap().indexes[&(LabelId::from(0), "name".to_owned())]
+            .delta
+            .is_empty());
+    }
+
+    #[test]
+    fn test_deletion_bits_screen() {
+        let bits = DeletionBits::new();
+        assert!(!bits.any(Version::MAX));
+        let hashes: Vec<u64> = (0..100usize).map(vertex_hash).collect();
+        for (i, hash) in hashes.iter().enumerate().step_by(2) {
+            bits.insert(*hash, i as Version + 1);
+        }
+        assert!(!bits.any(0) && bits.any(1));
+        // no key deleted is screened out, and most of the others are
+        assert!(hashes.iter().step_by(2).all(|h| bits.may_contain(*h, 1)));
+        let screened = hashes.iter().skip(1).step_by(2).filter(|h| !bits.may_contain(**h, 1));
+        assert!(screened.count() > 45);
+    }
+}
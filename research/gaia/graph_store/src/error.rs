@@ -13,6 +13,8 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
+use crate::dangling::DanglingEdge;
+use crate::strict::TypeViolation;
 use dyn_type::CastError;
 use std::any::Any;
 use std::io::Error;
@@ -43,6 +45,22 @@ pub enum GDBError {
     InvalidFunctionCallError,
     InvalidTypeError,
     FieldNotExistError,
+    /// Too many malformed rows while loading the raw data, with the number of malformed rows
+    TooManyMalformedRowsError(usize),
+    /// The edges dangle on absent vertices, with the number of them and the first of them
+    DanglingEdgesError(usize, Vec<DanglingEdge>),
+    /// The values are not of the declared types of their properties in the strict schema mode,
+    /// with the number of them and the first of them
+    TypeViolationError(usize, Vec<TypeViolation>),
+    /// The snapshot is written by an unsupported (major, minor) version
+    SnapshotVersionError(u16, u16),
+    /// The snapshot is corrupted, with the reason
+    CorruptedSnapshotError(String),
+    /// The label id is out of the range of the labels, see `LabelId::new`
+    InvalidLabelIdError(i64),
+    /// The partition is of the layout version (found, supported), which is newer than this crate
+    /// reads, or older and not migrated by the `UpgradePolicy`, see `crate::upgrade`
+    LayoutVersionError(u32, u32),
 }
 
 impl From<std::io::Error> for GDBError {
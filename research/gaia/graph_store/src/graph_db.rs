@@ -16,7 +16,7 @@
 use crate::common::*;
 use crate::error::GDBResult;
 use crate::parser::DataType;
-use crate::schema::Schema;
+use crate::schema::{GraphSchemaInfo, Schema};
 use crate::table::*;
 use crate::utils::Iter;
 use petgraph::graph::{EdgeIndex, IndexType};
@@ -75,6 +75,22 @@ impl<'a> RowWithSchema<'a> {
         }
     }
 
+    /// Get the index of the field of the property specified by `key`, which is the same for all
+    /// the rows of the same schema, to get the property by `Self::get_at` without looking up
+    /// `Self::header` again
+    pub fn get_index(&self, key: &str) -> Option<usize> {
+        if let Some(header) = &self.header {
+            header.get(key).map(|(_, index)| *index)
+        } else {
+            key.parse::<usize>().ok()
+        }
+    }
+
+    /// Get the property of the field at `index`, e.g. given by `Self::get_index`
+    pub fn get_at(&self, index: usize) -> Option<ItemTypeRef<'_>> {
+        self.row.get(index)
+    }
+
     /// Turn into a map of all properties
     pub fn into_properties(self) -> Option<HashMap<String, ItemType>> {
         self.header.and_then(|header| {
@@ -124,6 +140,17 @@ impl<'a, G: IndexType> LocalVertex<'a, G> {
         self.prop_row.as_ref().and_then(|prop| prop.get(key))
     }
 
+    /// Get the index of the property specified by `key`, which is the same for all the vertices
+    /// of the same label, see `RowWithSchema::get_index`
+    pub fn get_property_index(&self, key: &str) -> Option<usize> {
+        self.prop_row.as_ref().and_then(|prop| prop.get_index(key))
+    }
+
+    /// Get the property at `index`, e.g. given by `Self::get_property_index`
+    pub fn get_property_at(&self, index: usize) -> Option<ItemTypeRef<'_>> {
+        self.prop_row.as_ref().and_then(|prop| prop.get_at(index))
+    }
+
     pub fn clone_all_properties(&self) -> Option<HashMap<String, ItemType>> {
         self.prop_row.as_ref().and_then(|prop| prop.clone().into_properties())
     }
@@ -180,6 +207,11 @@ impl<'a, G: IndexType, I: IndexType> LocalEdge<'a, G, I> {
         self.label
     }
 
+    /// The global id of the edge, see `EdgeId`
+    pub fn get_edge_id(&self) -> EdgeId<G> {
+        EdgeId::new(self.start, self._edge_id.index())
+    }
+
     pub fn get_property(&self, key: &str) -> Option<ItemTypeRef> {
         if let Some(prop_row) = &self.prop_row {
             prop_row.get(key)
@@ -193,6 +225,33 @@ impl<'a, G: IndexType, I: IndexType> LocalEdge<'a, G, I> {
     }
 }
 
+/// The global id of an edge, i.e. the global id of its start vertex, whose partition keeps the
+/// edge as an outgoing edge, and the internal index of the edge in that partition. The edge is
+/// thus looked up in the partition of its start vertex, where the index never changes as the
+/// edges are only added.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EdgeId<G> {
+    pub src: G,
+    pub index: usize,
+}
+
+impl<G: IndexType> EdgeId<G> {
+    pub fn new(src: G, index: usize) -> Self {
+        EdgeId { src, index }
+    }
+
+    /// Encode the id into 128 bits, with the start vertex in the lower 64 bits, so that the edge
+    /// is routed as its start vertex by the lower 64 bits
+    pub fn encode(&self) -> u128 {
+        ((self.index as u128) << 64) | (self.src.index() as u64 as u128)
+    }
+
+    /// Decode the id encoded by `Self::encode()`
+    pub fn decode(id: u128) -> Self {
+        EdgeId { src: G::new(id as u64 as usize), index: (id >> 64) as usize }
+    }
+}
+
 pub trait GlobalStoreTrait<G: IndexType, I: IndexType> {
     /// Get all the vertices linked from the given vertex `src_id`. The linked edge must also
     /// satisfy the edge labels `edge_labels` and the direction `dir`.
@@ -214,6 +273,15 @@ pub trait GlobalStoreTrait<G: IndexType, I: IndexType> {
         &self, src_id: G, edge_labels: Option<&Vec<LabelId>>, dir: Direction,
     ) -> Iter<LocalEdge<G, I>>;
 
+    /// Count the edges regarding with the labels `edge_labels` and direction `dir` from the given
+    /// vertex `src_id`, i.e. the degree of the vertex, without materializing the edges as
+    /// `Self::get_adj_edges()`, which is 0 if the vertex does not present.
+    fn count_adj_edges(
+        &self, src_id: G, edge_labels: Option<&Vec<LabelId>>, dir: Direction,
+    ) -> usize {
+        self.get_adj_edges(src_id, edge_labels, dir).count()
+    }
+
     /// A wrapper of `Self::get_adj_vertices()` for outgoing direction.
     fn get_out_vertices(
         &self, src_id: G, edge_labels: Option<&Vec<LabelId>>,
@@ -262,6 +330,17 @@ pub trait GlobalStoreTrait<G: IndexType, I: IndexType> {
     /// Get the vertex of given global identity
     fn get_vertex(&self, id: G) -> Option<LocalVertex<G>>;
 
+    /// Get the edge of given global id, if its start vertex is in current partition.
+    ///
+    /// # Return
+    /// * The edge, if it presents
+    /// * `None`, if the edge does not present, or is kept by another partition.
+    fn get_edge(&self, id: EdgeId<G>) -> Option<LocalEdge<'_, G, I>>;
+
+    /// Get the edges of given global ids in the order of the ids, skipping those not present in
+    /// current partition, see `Self::get_edge()`.
+    fn get_edges(&self, ids: &[EdgeId<G>]) -> Iter<'_, LocalEdge<'_, G, I>>;
+
     /// Get all vertices of a given labels. If `None` label is given, return all vertices.
     fn get_all_vertices(&self, labels: Option<&Vec<LabelId>>) -> Iter<LocalVertex<G>>;
 
@@ -279,6 +358,10 @@ pub trait GlobalStoreTrait<G: IndexType, I: IndexType> {
     /// Get the schema for either vertex/edge properties
     fn get_schema(&self) -> Arc<dyn Schema>;
 
+    /// Get the labels of the graph and the names and types of their properties, together with
+    /// the (src, dst) vertex labels linked by the edges of each edge label, for introspection
+    fn schema(&self) -> Arc<GraphSchemaInfo>;
+
     /// Get the partition of current storage.
     fn get_current_partition(&self) -> usize;
 }
@@ -17,11 +17,15 @@ use super::graph_db::*;
 use crate::common::*;
 use crate::config::{
     DIR_BINARY_DATA, FILE_EDGE_PPT_DATA, FILE_GRAPH_STRUCT, FILE_INDEX_DATA, FILE_NODE_PPT_DATA,
+    FILE_STATISTICS,
 };
 use crate::error::{GDBError, GDBResult};
 use crate::io::export;
-use crate::schema::{LDBCGraphSchema, Schema};
+use crate::schema::{AdjOrder, GraphSchemaInfo, LDBCGraphSchema, Schema};
+use crate::segment::{AdjSegment, AdjSegments};
+use crate::statistics::GraphStatistics;
 use crate::table::*;
+use crate::upgrade::{write_layout_version, LAYOUT_VERSION};
 use crate::utils::{Iter, IterList};
 use petgraph::graph::{EdgeReference, IndexType};
 use petgraph::prelude::*;
@@ -40,9 +44,9 @@ use std::sync::Arc;
 #[derive(Serialize, Deserialize)]
 pub struct IndexData<G: Send + Sync + IndexType, I: Send + Sync + IndexType> {
     /// A mapping from global vertex id to internal vertex index.
-    global_id_to_index: HashMap<G, NodeIndex<I>>,
+    pub(crate) global_id_to_index: HashMap<G, NodeIndex<I>>,
     /// Group the internal indices of the vertices by their labels
-    label_indices: Vec<Vec<NodeIndex<I>>>,
+    pub(crate) label_indices: Vec<Vec<NodeIndex<I>>>,
     /// A mapping from global vertex id to corner internal vertex index. The corner vertexs
     /// are the vertexs that do not belong to current partition, but included by edges.
     corner_global_id_to_index: HashMap<G, NodeIndex<I>>,
@@ -76,19 +80,19 @@ where
         &mut self, global_id: G, label: Label, internal_id: NodeIndex<I>, is_corner: bool,
     ) -> bool {
         let existed = if !is_corner {
-            let max_label_id = if label[1] != INVALID_LABEL_ID {
-                std::cmp::max(label[0], label[1])
-            } else {
-                label[0]
-            } as usize;
+            let max_label_id = match label[1].valid() {
+                Some(second) => std::cmp::max(label[0], second),
+                None => label[0],
+            }
+            .index();
             // only a non-corner vertex will be inserted in the labelled vectors.
             while max_label_id >= self.label_indices.len() {
                 self.label_indices.push(vec![]);
             }
 
-            self.label_indices[label[0] as usize].push(internal_id);
-            if label[1] != INVALID_LABEL_ID {
-                self.label_indices[label[1] as usize].push(internal_id);
+            self.label_indices[label[0].index()].push(internal_id);
+            if let Some(second) = label[1].valid() {
+                self.label_indices[second.index()].push(internal_id);
             }
 
             self.global_id_to_index.insert(global_id, internal_id).is_some()
@@ -117,7 +121,7 @@ where
 
     /// Get all internal ids from a given label
     fn get_indices_of_label(&self, label_id: LabelId) -> Iter<NodeIndex<I>> {
-        if let Some(label_indices) = self.label_indices.get(label_id as usize) {
+        if let Some(label_indices) = self.label_indices.get(label_id.index()) {
             Iter::from_iter(label_indices.iter().cloned())
         } else {
             Iter::from_iter(vec![].into_iter())
@@ -125,7 +129,7 @@ where
     }
 
     /// Get global id from a given internal id
-    fn get_global_id(&self, internal_id: NodeIndex<I>) -> Option<G> {
+    pub(crate) fn get_global_id(&self, internal_id: NodeIndex<I>) -> Option<G> {
         self.index_to_global_id.get(internal_id.index()).cloned()
     }
 
@@ -157,7 +161,8 @@ where
 /// the properties are maintain through the `PropertyTableTrait`, which is an abstraction
 /// of many forms of storage, including an in-memory hashmap-based storage `PropertyTable`,
 /// a `SingleValueTable` designed specifically for LDBC's edge data (which has at most one
-/// property of `u64` type), and a `RocksDB`-based storage `RocksTable`. See `graph_partition.rs`
+/// property of `u64` type), a `ColumnTable` whose columns are memory-mapped once opened, and a
+/// `RocksDB`-based storage `RocksTable`. See `graph_partition.rs`
 /// for how to partition the raw graph data (preprocessed as csv format) over a cluster of
 /// workers and maintain a partition in each worker.
 ///
@@ -179,6 +184,12 @@ pub struct LargeGraphDB<
     pub(crate) edge_prop_table: E,
     /// The index data that maintains the mapping between vertices' global ids and their internal ids
     pub(crate) index_data: IndexData<G, I>,
+    /// The statistics of this partition of graph, for estimating the cardinalities of queries
+    pub(crate) statistics: Arc<GraphStatistics>,
+    /// The labels and properties of the graph, recorded while the graph is loaded
+    pub(crate) schema_info: Arc<GraphSchemaInfo>,
+    /// The adjacency segments of the edge labels, built on their first use
+    pub(crate) segments: AdjSegments<I>,
 }
 
 impl<G, I, N, E> LargeGraphDB<G, I, N, E>
@@ -212,30 +223,25 @@ where
     }
 
     fn edge_ref_to_local_edge(&self, edge: EdgeReference<LabelId, I>) -> Option<LocalEdge<G, I>> {
-        let src_global_id = self.index_data.get_global_id(edge.source());
-        let dst_global_id = self.index_data.get_global_id(edge.target());
-
-        if src_global_id.is_some() && dst_global_id.is_some() {
-            let edge_id = edge.id();
-            let label = *edge.weight();
-            if let Some(property) = self.get_all_edge_property(&edge_id) {
-                Some(LocalEdge::with_property(
-                    src_global_id.unwrap(),
-                    dst_global_id.unwrap(),
-                    label,
-                    edge_id,
-                    RowWithSchema::new(Some(property), self.graph_schema.get_edge_schema(label)),
-                ))
-            } else {
-                Some(LocalEdge::new(
-                    src_global_id.unwrap(),
-                    dst_global_id.unwrap(),
-                    label,
-                    edge.id(),
-                ))
-            }
+        self.to_local_edge(edge.source(), edge.target(), edge.id(), *edge.weight())
+    }
+
+    fn to_local_edge(
+        &self, src: NodeIndex<I>, dst: NodeIndex<I>, edge_id: EdgeIndex<I>, label: LabelId,
+    ) -> Option<LocalEdge<'_, G, I>> {
+        let src_global_id = self.index_data.get_global_id(src)?;
+        let dst_global_id = self.index_data.get_global_id(dst)?;
+
+        if let Some(property) = self.get_all_edge_property(&edge_id) {
+            Some(LocalEdge::with_property(
+                src_global_id,
+                dst_global_id,
+                label,
+                edge_id,
+                RowWithSchema::new(Some(property), self.graph_schema.get_edge_schema(label)),
+            ))
         } else {
-            None
+            Some(LocalEdge::new(src_global_id, dst_global_id, label, edge_id))
         }
     }
 
@@ -350,7 +356,7 @@ where
         if let Some(label) = _label {
             let iter = self
                 .index_data
-                .get_indices_of_label(label as LabelId)
+                .get_indices_of_label(label)
                 .map(move |internal_id| self.index_to_local_vertex(internal_id, true).unwrap());
             Iter::from_iter(iter)
         } else {
@@ -370,7 +376,7 @@ where
         for label in labels.into_iter() {
             let result_iter_of_label = self
                 .index_data
-                .get_indices_of_label(label as LabelId)
+                .get_indices_of_label(label)
                 .map(move |internal_id| self.index_to_local_vertex(internal_id, true).unwrap());
             result_iter.push(result_iter_of_label);
         }
@@ -447,6 +453,101 @@ where
         self.index_data.global_id_to_index.contains_key(&global_id)
     }
 
+    /// Get the adjacency segment of the edges of `label` in the direction `dir`, which is built on
+    /// its first use, or `None` if `label` is not an edge label of the schema
+    pub fn get_adj_segment(&self, label: LabelId, dir: Direction) -> Option<Arc<AdjSegment<I>>> {
+        if self.graph_schema.has_edge_label(label) {
+            Some(self.segments.get_or_build(label, dir, || self.build_adj_segment(label, dir)))
+        } else {
+            None
+        }
+    }
+
+    /// Build the adjacency segment of the edges of `label` in the direction `dir`, sorted by the
+    /// property of the edges if the schema records the order of the label
+    fn build_adj_segment(&self, label: LabelId, dir: Direction) -> AdjSegment<I> {
+        let sort_by = self.graph_schema.get_adj_order(label).and_then(|order| {
+            let (_, index) = self.graph_schema.get_edge_schema(label)?.get(&order.property)?;
+            Some((*index, order.descending))
+        });
+        match sort_by {
+            Some((index, descending)) => {
+                let key = |edge: EdgeIndex<I>| {
+                    self.get_all_edge_property(&edge)
+                        .and_then(|row| row.get(index).and_then(|value| value.try_to_owned()))
+                };
+                AdjSegment::build_sorted(&self.graph, label, dir, key, descending)
+            }
+            None => AdjSegment::build(&self.graph, label, dir),
+        }
+    }
+
+    /// The order the edges of `label` are stored in each adjacency segment, see
+    /// `LDBCGraphSchema::get_adj_order`
+    pub fn get_adj_order(&self, label: LabelId) -> Option<&AdjOrder> {
+        self.graph_schema.get_adj_order(label)
+    }
+
+    /// Get the vertices adjacent to `src_id` in the segment, the same as `get_adj_vertices()` of
+    /// the label and the direction of the segment, without comparing the labels of the edges
+    pub fn get_segment_vertices(
+        &self, segment: Arc<AdjSegment<I>>, src_id: G,
+    ) -> Iter<'_, LocalVertex<'_, G>> {
+        if let Some(index) = self.index_data.get_internal_id(src_id) {
+            let len = segment.neighbors(index).len();
+            Iter::from_iter((0..len).map(move |i| {
+                self.index_to_local_vertex(segment.neighbors(index)[i], false).unwrap()
+            }))
+        } else {
+            Iter::from_iter(vec![].into_iter())
+        }
+    }
+
+    /// Get the edges adjacent to `src_id` in the segment, in the order of the segment, i.e. sorted
+    /// by the property of the edges if the label of the segment is stored sorted
+    pub fn get_segment_edges(
+        &self, segment: Arc<AdjSegment<I>>, src_id: G,
+    ) -> Iter<'_, LocalEdge<'_, G, I>> {
+        if let Some(index) = self.index_data.get_internal_id(src_id) {
+            let len = segment.edges(index).len();
+            let label = segment.label();
+            Iter::from_iter((0..len).filter_map(move |i| {
+                let edge_id = segment.edges(index)[i];
+                let (src, dst) = self.graph.edge_endpoints(edge_id)?;
+                self.to_local_edge(src, dst, edge_id, label)
+            }))
+        } else {
+            Iter::from_iter(vec![].into_iter())
+        }
+    }
+
+    /// Get the statistics of this partition of graph, which are either computed while the graph
+    /// is built, or loaded along with the graph data
+    pub fn get_statistics(&self) -> Arc<GraphStatistics> {
+        self.statistics.clone()
+    }
+
+    /// Get the table of the properties of the vertices, e.g. to tell the residency of the columns
+    /// of a `ColumnTable`
+    pub fn get_vertex_prop_table(&self) -> &N {
+        &self.vertex_prop_table
+    }
+
+    /// Get the table of the properties of the edges
+    pub fn get_edge_prop_table(&self) -> &E {
+        &self.edge_prop_table
+    }
+
+    /// Recompute the statistics of this partition of graph
+    pub fn recompute_statistics(&mut self) {
+        self.statistics = Arc::new(GraphStatistics::compute(
+            &self.graph,
+            &self.index_data,
+            &self.vertex_prop_table,
+            &self.graph_schema,
+        ));
+    }
+
     /// Print the statistics for debugging
     pub fn print_statistics(&self) {
         println!("Statics of the graph in partition: {}", self.partition);
@@ -532,6 +633,21 @@ where
         }
     }
 
+    fn count_adj_edges(
+        &self, src_id: G, edge_labels: Option<&Vec<LabelId>>, dir: Direction,
+    ) -> usize {
+        if let Some(index) = self.index_data.get_internal_id(src_id) {
+            self.graph
+                .edges_directed(index, dir)
+                .filter(|edge| {
+                    edge_labels.map(|labels| labels.contains(edge.weight())).unwrap_or(true)
+                })
+                .count()
+        } else {
+            0
+        }
+    }
+
     fn get_vertex(&self, id: G) -> Option<LocalVertex<G>> {
         if let Some(index) = self.index_data.get_internal_id(id) {
             self.index_to_local_vertex(index, true)
@@ -540,6 +656,24 @@ where
         }
     }
 
+    fn get_edge(&self, id: EdgeId<G>) -> Option<LocalEdge<'_, G, I>> {
+        let src = self.index_data.get_internal_id(id.src)?;
+        // only the partition of the start vertex keeps the edge by the id
+        if !self._is_vertex_local(src) {
+            return None;
+        }
+        let edge_id = EdgeIndex::new(id.index);
+        self.graph
+            .edges_directed(src, Direction::Outgoing)
+            .find(|edge| edge.id() == edge_id)
+            .and_then(|edge| self.edge_ref_to_local_edge(edge))
+    }
+
+    fn get_edges(&self, ids: &[EdgeId<G>]) -> Iter<'_, LocalEdge<'_, G, I>> {
+        let edges: Vec<_> = ids.iter().filter_map(|id| self.get_edge(*id)).collect();
+        Iter::from_iter(edges.into_iter())
+    }
+
     fn get_all_vertices(&self, _labels: Option<&Vec<LabelId>>) -> Iter<LocalVertex<G>> {
         if let Some(labels) = _labels {
             if labels.len() == 1 {
@@ -568,7 +702,7 @@ where
         let mut count = 0;
         if let Some(labels) = _labels {
             for &label in labels {
-                if let Some(ids) = self.index_data.label_indices.get(label as usize) {
+                if let Some(ids) = self.index_data.label_indices.get(label.index()) {
                     count += ids.len();
                 }
             }
@@ -602,6 +736,10 @@ where
         self.graph_schema.clone()
     }
 
+    fn schema(&self) -> Arc<GraphSchemaInfo> {
+        self.schema_info.clone()
+    }
+
     fn get_current_partition(&self) -> usize {
         self.partition
     }
@@ -644,7 +782,7 @@ where
     fn add_vertex_internal(&mut self, global_id: G, label: Label) -> (bool, NodeIndex<I>) {
         if let Some(existed_vertex) = self.index_data.global_id_to_index.get(&global_id) {
             // update a more fine-grained label
-            if label[1] != INVALID_LABEL_ID {
+            if label[1].valid().is_some() {
                 if let Some(w) = self.graph.node_weight_mut(*existed_vertex) {
                     *w = label;
                 }
@@ -713,14 +851,20 @@ where
 
     pub fn into_graph(self, mut schema: LDBCGraphSchema) -> LargeGraphDB<G, I, N, E> {
         schema.trim();
-        LargeGraphDB {
+        let schema_info = Arc::new(GraphSchemaInfo::compute(&self.graph, &schema));
+        let mut graph = LargeGraphDB {
             partition: self.partition,
             graph: self.graph,
             vertex_prop_table: self.vertex_prop_table,
             edge_prop_table: self.edge_prop_table,
             index_data: self.index_data,
             graph_schema: Arc::new(schema),
-        }
+            statistics: Arc::new(GraphStatistics::default()),
+            schema_info,
+            segments: AdjSegments::default(),
+        };
+        graph.recompute_statistics();
+        graph
     }
 }
 
@@ -743,6 +887,27 @@ where
         self.vertex_prop_table.export(&partition_dir.join(FILE_NODE_PPT_DATA))?;
         self.edge_prop_table.export(&partition_dir.join(FILE_EDGE_PPT_DATA))?;
         export(&self.index_data, &partition_dir.join(FILE_INDEX_DATA))?;
+        write_layout_version(&partition_dir, LAYOUT_VERSION)?;
+
+        Ok(())
+    }
+
+    /// Compute the statistics of the graph and export them to a bin file next to the graph data,
+    /// so that they can be loaded instead of being recomputed while opening the graph.
+    pub fn export_statistics(&self, schema: &LDBCGraphSchema) -> GDBResult<()> {
+        let mut schema = schema.clone();
+        schema.trim();
+        let statistics = GraphStatistics::compute(
+            &self.graph,
+            &self.index_data,
+            &self.vertex_prop_table,
+            &schema,
+        );
+        let partition_dir =
+            self.root_dir.join(DIR_BINARY_DATA).join(format!("partition_{}", self.partition));
+
+        create_dir_all(&partition_dir)?;
+        export(&statistics, partition_dir.join(FILE_STATISTICS))?;
 
         Ok(())
     }
@@ -870,11 +1035,11 @@ mod test {
         let root_dir = "data/simple_data";
         let mut graphdb: MutableGraphDB<DefaultId, InternalId> =
             GraphDBConfig::default().root_dir(root_dir).number_vertex_labels(20).new();
-        assert!(graphdb.add_vertex(PIDS[0], [1, INVALID_LABEL_ID]));
+        assert!(graphdb.add_vertex(PIDS[0], [1.into(), INVALID_LABEL_ID]));
         // Cannot re-add a vertex
-        assert!(!graphdb.add_vertex(PIDS[0], [1, INVALID_LABEL_ID]));
-        assert!(graphdb.add_vertex(PIDS[2], [1, INVALID_LABEL_ID]));
-        assert!(graphdb.add_corner_vertex(PIDS[5], 1));
+        assert!(!graphdb.add_vertex(PIDS[0], [1.into(), INVALID_LABEL_ID]));
+        assert!(graphdb.add_vertex(PIDS[2], [1.into(), INVALID_LABEL_ID]));
+        assert!(graphdb.add_corner_vertex(PIDS[5], 1.into()));
 
         let prop = Row::from(vec![object!(15), object!("John")]);
         let new_prop = Row::from(vec![object!(16), object!("Steve")]);
@@ -887,7 +1052,7 @@ mod test {
 
         // Add vertex PIDS[1]
         assert!(graphdb
-            .add_vertex_with_properties(PIDS[1], [1, INVALID_LABEL_ID], prop.clone())
+            .add_vertex_with_properties(PIDS[1], [1.into(), INVALID_LABEL_ID], prop.clone())
             .unwrap()
             .is_none());
 
@@ -895,27 +1060,29 @@ mod test {
         assert_eq!(
             Some(prop.clone()),
             graphdb
-                .add_vertex_with_properties(PIDS[1], [1, INVALID_LABEL_ID], new_prop.clone())
+                .add_vertex_with_properties(PIDS[1], [1.into(), INVALID_LABEL_ID], new_prop.clone())
                 .unwrap()
         );
 
-        assert!(graphdb.add_edge(PIDS[0], PIDS[1], 13));
+        assert!(graphdb.add_edge(PIDS[0], PIDS[1], 13.into()));
         // add an edge from a corner vertex
-        assert!(graphdb.add_edge(PIDS[5], PIDS[0], 12));
+        assert!(graphdb.add_edge(PIDS[5], PIDS[0], 12.into()));
 
         // PIDS[3] does not exist, therefore `false` is returned
-        assert!(!graphdb.add_edge(PIDS[0], PIDS[3], 12));
-        assert!(!graphdb.add_edge(PIDS[3], PIDS[0], 12));
+        assert!(!graphdb.add_edge(PIDS[0], PIDS[3], 12.into()));
+        assert!(!graphdb.add_edge(PIDS[3], PIDS[0], 12.into()));
 
         let edge_prop = Row::from(20200202_i64);
         // add duplicate edge to the db
         assert!(graphdb
-            .add_edge_with_properties(PIDS[0], PIDS[1], 12, edge_prop.clone())
+            .add_edge_with_properties(PIDS[0], PIDS[1], 12.into(), edge_prop.clone())
             .unwrap()
             .is_none());
 
         // PIDS[3] does not exist, thus return error.
-        assert!(graphdb.add_edge_with_properties(PIDS[0], PIDS[3], 12, edge_prop.clone()).is_err());
+        assert!(graphdb
+            .add_edge_with_properties(PIDS[0], PIDS[3], 12.into(), edge_prop.clone())
+            .is_err());
 
         let schema =
             LDBCGraphSchema::from_json_file("data/schema.json").expect("Get Schema error!");
@@ -942,9 +1109,25 @@ mod test {
         assert_eq!(3, graph.count_all_vertices(None));
         assert_eq!(2, graph.count_all_edges(None));
         // one edge of label 12
-        assert_eq!(1, graph.count_all_edges(Some(&vec![12])));
+        assert_eq!(1, graph.count_all_edges(Some(&vec![12.into()])));
         // one edge of label 13
-        assert_eq!(1, graph.count_all_edges(Some(&vec![13])));
+        assert_eq!(1, graph.count_all_edges(Some(&vec![13.into()])));
+
+        // the degrees agree with the adjacent edges, including those from the corner vertex
+        for dir in [Direction::Outgoing, Direction::Incoming] {
+            for labels in [None, Some(vec![12.into()]), Some(vec![12.into(), 13.into()])] {
+                for pid in &PIDS[0..3] {
+                    assert_eq!(
+                        graph.get_adj_edges(*pid, labels.as_ref(), dir).count(),
+                        graph.count_adj_edges(*pid, labels.as_ref(), dir)
+                    );
+                }
+            }
+        }
+        assert_eq!(2, graph.count_adj_edges(PIDS[0], None, Direction::Outgoing));
+        assert_eq!(1, graph.count_adj_edges(PIDS[0], Some(&vec![12.into()]), Direction::Incoming));
+        assert_eq!(0, graph.count_adj_edges(PIDS[2], None, Direction::Outgoing));
+        assert_eq!(0, graph.count_adj_edges(PIDS[3], None, Direction::Incoming));
     }
 
     #[test]
@@ -964,36 +1147,49 @@ mod test {
             .map(|item| (item.get_id(), item.get_label()[0]))
             .collect();
         in_vertices.sort();
-        assert_eq!(vec![(PIDS[0], 1), (CIDS[0], 2)], in_vertices);
+        assert_eq!(vec![(PIDS[0], 1.into()), (CIDS[0], 2.into())], in_vertices);
 
         // get in_vertices of given edge labels
         let in_vertices_has_creator: Vec<(DefaultId, LabelId)> = graphdb
-            .get_adj_vertices(PIDS[1], Some(&vec![0]), Direction::Incoming)
+            .get_adj_vertices(PIDS[1], Some(&vec![0.into()]), Direction::Incoming)
             .map(|item| (item.get_id(), item.get_label()[0]))
             .collect();
-        assert_eq!(vec![(CIDS[0], 2)], in_vertices_has_creator);
+        assert_eq!(vec![(CIDS[0], 2.into())], in_vertices_has_creator);
 
         // test get_out_vertices..
         let mut out_vertices: Vec<(DefaultId, LabelId)> = graphdb
-            .get_adj_vertices(PIDS[1], Some(&vec![0, 12]), Direction::Outgoing)
+            .get_adj_vertices(PIDS[1], Some(&vec![0.into(), 12.into()]), Direction::Outgoing)
             .map(|item| (item.get_id(), item.get_label()[0]))
             .collect();
         out_vertices.sort();
-        assert_eq!(vec![(PIDS[3], 1), (PIDS[6], 1), (PIDS[7], 1), (PIDS[8], 1)], out_vertices);
+        assert_eq!(
+            vec![
+                (PIDS[3], 1.into()),
+                (PIDS[6], 1.into()),
+                (PIDS[7], 1.into()),
+                (PIDS[8], 1.into())
+            ],
+            out_vertices
+        );
 
         let mut out_vertices_knows: Vec<(DefaultId, LabelId)> = graphdb
-            .get_out_vertices(PIDS[1], Some(&vec![12]))
+            .get_out_vertices(PIDS[1], Some(&vec![12.into()]))
             .map(|item| (item.get_id(), item.get_label()[0]))
             .collect();
         out_vertices_knows.sort();
         assert_eq!(
-            vec![(PIDS[3], 1), (PIDS[6], 1), (PIDS[7], 1), (PIDS[8], 1)],
+            vec![
+                (PIDS[3], 1.into()),
+                (PIDS[6], 1.into()),
+                (PIDS[7], 1.into()),
+                (PIDS[8], 1.into())
+            ],
             out_vertices_knows
         );
 
         // test get_in_edges..
         let in_edge_neighbor: Vec<(DefaultId, Option<ItemType>)> = graphdb
-            .get_adj_edges(PIDS[1], Some(&vec![0, 12]), Direction::Incoming)
+            .get_adj_edges(PIDS[1], Some(&vec![0.into(), 12.into()]), Direction::Incoming)
             .map(move |item| {
                 (
                     item.get_src_id(),
@@ -1007,7 +1203,7 @@ mod test {
         );
 
         let in_edge_neighbor_has_creator: Vec<(DefaultId, Option<ItemType>)> = graphdb
-            .get_adj_edges(PIDS[1], Some(&vec![0]), Direction::Incoming)
+            .get_adj_edges(PIDS[1], Some(&vec![0.into()]), Direction::Incoming)
             .map(move |item| {
                 (
                     item.get_src_id(),
@@ -1019,7 +1215,7 @@ mod test {
 
         // test get_out_edges..
         let mut out_edge_neighbor: Vec<(DefaultId, Option<ItemType>)> = graphdb
-            .get_adj_edges(PIDS[1], Some(&vec![0, 12]), Direction::Outgoing)
+            .get_adj_edges(PIDS[1], Some(&vec![0.into(), 12.into()]), Direction::Outgoing)
             .map(move |item| {
                 (
                     item.get_dst_id(),
@@ -1047,11 +1243,11 @@ mod test {
         assert_eq!(true, vertex_none.is_none());
 
         // test get_all_vertices..
-        let all_vertices = graphdb.get_all_vertices(Some(&vec![1, 2]));
+        let all_vertices = graphdb.get_all_vertices(Some(&vec![1.into(), 2.into()]));
         assert_eq!(18, all_vertices.count());
 
         let mut all_person_vertices = graphdb
-            .get_all_vertices(Some(&vec![1]))
+            .get_all_vertices(Some(&vec![1.into()]))
             .map(|local_vertex| local_vertex.get_id())
             .collect::<Vec<DefaultId>>();
         all_person_vertices.sort();
@@ -1061,11 +1257,11 @@ mod test {
         );
 
         // test get_all_edges..
-        let all_edges = graphdb.get_all_edges(Some(&vec![0, 12]));
+        let all_edges = graphdb.get_all_edges(Some(&vec![0.into(), 12.into()]));
         assert_eq!(18, all_edges.count());
 
         let all_knows_edges = graphdb
-            .get_all_edges(Some(&vec![12]))
+            .get_all_edges(Some(&vec![12.into()]))
             .map(|local_edge| (local_edge.get_src_id(), local_edge.get_dst_id()))
             .collect::<Vec<(DefaultId, DefaultId)>>();
         assert_eq!(
@@ -1118,6 +1314,96 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_adj_segment() {
+        let data_dir = "data/large_data";
+        let root_dir = "data/large_data";
+        let schema_file = "data/schema.json";
+        let mut loader =
+            GraphLoader::<DefaultId, u32>::new(data_dir, root_dir, schema_file, 20, 0, 1);
+        loader.load().expect("Load graph error!");
+        let graphdb = loader.into_graph();
+
+        let mut labels: Vec<LabelId> = graphdb.graph.raw_edges().iter().map(|e| e.weight).collect();
+        labels.sort();
+        labels.dedup();
+        assert!(labels.len() > 1);
+        let ids: Vec<DefaultId> = graphdb.get_all_vertices(None).map(|v| v.get_id()).collect();
+        for &label in labels.iter() {
+            for &dir in [Direction::Outgoing, Direction::Incoming].iter() {
+                let segment = graphdb.get_adj_segment(label, dir).expect("no segment of label");
+                assert_eq!((segment.label(), segment.direction()), (label, dir));
+                let mut edges = 0;
+                for &id in ids.iter() {
+                    // the same vertices in the same order as filtering the edges by the label
+                    let expected: Vec<(DefaultId, Label)> = graphdb
+                        .get_adj_vertices(id, Some(&vec![label]), dir)
+                        .map(|v| (v.get_id(), v.get_label()))
+                        .collect();
+                    let vertices: Vec<(DefaultId, Label)> = graphdb
+                        .get_segment_vertices(segment.clone(), id)
+                        .map(|v| (v.get_id(), v.get_label()))
+                        .collect();
+                    assert_eq!(expected, vertices);
+                    edges += vertices.len();
+                }
+                assert!(edges <= segment.edge_count());
+            }
+        }
+        // the same segment once built
+        let segment = graphdb.get_adj_segment(labels[0], Direction::Outgoing).unwrap();
+        let again = graphdb.get_adj_segment(labels[0], Direction::Outgoing).unwrap();
+        assert!(Arc::ptr_eq(&segment, &again));
+        // no segment of a label not in the schema
+        assert!(graphdb.get_adj_segment(INVALID_LABEL_ID, Direction::Outgoing).is_none());
+        assert_eq!(graphdb.get_segment_vertices(segment, CIDS[0] + 1000).count(), 0);
+    }
+
+    #[test]
+    fn test_get_edge() {
+        let mut graphdb: MutableGraphDB<DefaultId, InternalId> =
+            GraphDBConfig::default().number_vertex_labels(20).new();
+        assert!(graphdb.add_vertex(PIDS[0], [1.into(), INVALID_LABEL_ID]));
+        assert!(graphdb.add_vertex(PIDS[1], [1.into(), INVALID_LABEL_ID]));
+        assert!(graphdb.add_corner_vertex(PIDS[5], 1.into()));
+        // the parallel edges are told apart by their ids
+        assert!(graphdb.add_edge(PIDS[0], PIDS[1], 12.into()));
+        assert!(graphdb.add_edge(PIDS[0], PIDS[1], 13.into()));
+        assert!(graphdb.add_edge(PIDS[1], PIDS[0], 12.into()));
+        // an edge from a corner vertex is kept by the partition of the corner vertex
+        assert!(graphdb.add_edge(PIDS[5], PIDS[0], 12.into()));
+        let schema =
+            LDBCGraphSchema::from_json_file("data/schema.json").expect("Get Schema error!");
+        let graph = graphdb.into_graph(schema);
+
+        let edges: Vec<_> = graph.get_all_edges(None).collect();
+        assert_eq!(edges.len(), 3);
+        let mut ids: Vec<_> = edges.iter().map(|e| e.get_edge_id()).collect();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), 3);
+        for edge in edges.iter() {
+            let id = edge.get_edge_id();
+            assert_eq!(id.src, edge.get_src_id());
+            assert_eq!(EdgeId::decode(id.encode()), id);
+            assert_eq!(id.encode() as u64 as DefaultId, edge.get_src_id());
+            let found = graph.get_edge(id).expect("edge not found by id");
+            assert_eq!(
+                (found.get_src_id(), found.get_dst_id(), found.get_label()),
+                (edge.get_src_id(), edge.get_dst_id(), edge.get_label())
+            );
+        }
+        let corner = graph.get_in_edges(PIDS[0], None).find(|e| e.get_src_id() == PIDS[5]);
+        assert!(graph.get_edge(corner.unwrap().get_edge_id()).is_none());
+        // the ids not present are skipped
+        let absent = vec![EdgeId::new(PIDS[0], 100), EdgeId::new(PIDS[3], 0), ids[1]];
+        let found: Vec<_> = graph.get_edges(&absent).map(|e| e.get_edge_id()).collect();
+        assert_eq!(found, vec![ids[1]]);
+        // the index of an edge does not address the edge by another start vertex
+        let out = graph.get_out_edges(PIDS[0], None).next().unwrap().get_edge_id();
+        assert!(graph.get_edge(EdgeId::new(PIDS[1], out.index)).is_none());
+    }
+
     #[test]
     fn test_serde() {
         let temp = tempdir::TempDir::new("test_serde").expect("Open temp folder error");
@@ -1137,8 +1423,8 @@ mod test {
             .open::<DefaultId, InternalId, _, _>()
             .expect("Import graph error");
 
-        assert_eq!(9, imported_graph.count_all_vertices(Some(&vec![1])));
-        assert_eq!(9, imported_graph.count_all_edges(Some(&vec![12])));
+        assert_eq!(9, imported_graph.count_all_vertices(Some(&vec![1.into()])));
+        assert_eq!(9, imported_graph.count_all_edges(Some(&vec![12.into()])));
 
         // test properties when import graph from binary
         let vertex = imported_graph.get_vertex(PIDS[0]).unwrap();
@@ -17,11 +17,13 @@
 
 use super::graph_db::*;
 use super::graph_db_impl::LargeGraphDB;
-use crate::common::{DefaultId, InternalId, LabelId, INVALID_LABEL_ID};
+use crate::common::{DefaultId, InternalId, LabelId, INVALID_LABEL_ID, PLACEHOLDER_LABEL_ID};
 use crate::config::{GraphDBConfig, JsonConf};
+use crate::dangling::{placeholder_label, DanglingEdgeChecker, DanglingEdgePolicy};
 use crate::error::{GDBError, GDBResult};
 use crate::graph_db_impl::MutableGraphDB;
 use crate::parser::{parse_properties, EdgeMeta, ParserTrait, VertexMeta};
+use crate::partition::{GraphPartition, HashPartition};
 use crate::schema::{LDBCGraphSchema, Schema, ID_FIELD, LABEL_FIELD};
 use csv::{Reader, ReaderBuilder};
 use petgraph::graph::IndexType;
@@ -30,6 +32,7 @@ use std::fmt::Formatter;
 use std::fmt::Result as FmtResult;
 use std::fs::read_dir;
 use std::fs::File;
+use std::hash::Hash;
 use std::io::{BufReader, Read};
 use std::marker::PhantomData;
 use std::path::{Path, PathBuf};
@@ -144,7 +147,7 @@ pub const LABEL_SHIFT_BITS: usize =
 
 impl<G: IndexType> LDBCVertexParser<G> {
     pub fn to_global_id(ldbc_id: usize, label_id: LabelId) -> G {
-        let global_id: usize = ((label_id as usize) << LABEL_SHIFT_BITS) | ldbc_id;
+        let global_id: usize = (label_id.index() << LABEL_SHIFT_BITS) | ldbc_id;
         G::new(global_id)
     }
 }
@@ -154,16 +157,17 @@ impl<G: FromStr + PartialEq + Default + IndexType> ParserTrait<G> for LDBCVertex
         &self, record_iter: Iter,
     ) -> GDBResult<VertexMeta<G>> {
         let mut id = 0_usize;
-        let mut extra_label_id = INVALID_LABEL_ID;
+        let mut extra_label_id = None;
         for (index, record) in record_iter.enumerate() {
             if let Some(label_index) = self.label_index {
                 if index == self.id_index {
                     id = record.parse::<usize>()?;
                 } else if index == label_index {
-                    extra_label_id = self
-                        .schema
-                        .get_vertex_label_id(&record.to_uppercase())
-                        .ok_or(GDBError::FieldNotExistError)?;
+                    extra_label_id = Some(
+                        self.schema
+                            .get_vertex_label_id(&record.to_uppercase())
+                            .ok_or(GDBError::FieldNotExistError)?,
+                    );
                     // can break here because id always presents before label
                     break;
                 }
@@ -174,7 +178,7 @@ impl<G: FromStr + PartialEq + Default + IndexType> ParserTrait<G> for LDBCVertex
         }
 
         let global_id = Self::to_global_id(id, self.vertex_type);
-        let label = [self.vertex_type, extra_label_id];
+        let label = [self.vertex_type, extra_label_id.unwrap_or(INVALID_LABEL_ID)];
 
         let vertex_meta = VertexMeta { global_id, label };
         debug!("Parse vertex_meta successfully: {:?}", vertex_meta);
@@ -260,6 +264,14 @@ pub struct GraphLoader<
     work_id: usize,
     /// How many processors all together
     peers: usize,
+    /// The strategy of partitioning the vertices among the processors
+    partition: Arc<dyn GraphPartition>,
+    /// How the dangling edges are handled, which fail the loading by default
+    dangling_edge_policy: DanglingEdgePolicy,
+    /// The number of dangling edges, which are skipped, or loaded with the placeholders
+    num_dangling_edges: usize,
+    /// The number of placeholders created for the absent end vertices of the dangling edges
+    num_placeholders: usize,
     /// Detailed performance metrics
     perf_metrics: PerfMetrices,
     /// Phantomize the generic types
@@ -267,15 +279,27 @@ pub struct GraphLoader<
     ph2: PhantomData<I>,
 }
 
-fn keep_vertex<G: IndexType>(vid: G, peers: usize, work_id: usize) -> bool {
-    vid.index() % peers == work_id
+fn keep_vertex<G: IndexType>(
+    vid: G, partition: &dyn GraphPartition, work_id: usize, peers: usize,
+) -> bool {
+    partition.get_server(vid.index(), peers) == work_id
 }
 
-impl<G: IndexType + Eq + FromStr + Send + Sync, I: IndexType + Send + Sync> GraphLoader<G, I> {
+impl<G, I> GraphLoader<G, I>
+where
+    G: IndexType + Eq + Hash + FromStr + Send + Sync,
+    I: IndexType + Send + Sync,
+{
     /// Load vertices recorded in the file of `vertex_type` into the database.
     /// Return the number of vertices that are successfully loaded.
-    fn load_vertices_to_db<R: Read>(&mut self, vertex_type: LabelId, mut rdr: Reader<R>) -> usize {
+    ///
+    /// All the vertices parsed are added to `dangling`, including those of the other partitions,
+    /// to tell the dangling edges afterwards.
+    fn load_vertices_to_db<R: Read>(
+        &mut self, vertex_type: LabelId, mut rdr: Reader<R>, dangling: &DanglingEdgeChecker<G>,
+    ) -> GDBResult<usize> {
         let mut num_vertices = 0_usize;
+        let mut ids = vec![];
         let graph_db = &mut self.graph_builder;
         let schema = self.graph_schema.clone();
         let parser =
@@ -290,7 +314,13 @@ impl<G: IndexType + Eq + FromStr + Send + Sync, I: IndexType + Send + Sync> Grap
                 let record_iter_cloned = record_iter.clone();
                 let mut parse_error = true;
                 if let Ok(vertex_meta) = parser.parse_vertex_meta(record_iter) {
-                    if keep_vertex(vertex_meta.global_id, self.peers, self.work_id) {
+                    ids.push(vertex_meta.global_id);
+                    if keep_vertex(
+                        vertex_meta.global_id,
+                        self.partition.as_ref(),
+                        self.work_id,
+                        self.peers,
+                    ) {
                         if let Ok(properties) = parse_properties(
                             record_iter_cloned,
                             self.graph_schema.get_vertex_header(vertex_type),
@@ -340,16 +370,20 @@ impl<G: IndexType + Eq + FromStr + Send + Sync, I: IndexType + Send + Sync> Grap
             if num_vertices == 0 { 0 } else { (num_vertices - 1) / 50000 },
             num_vertices
         );
+        dangling.add_vertices(ids)?;
 
-        num_vertices
+        Ok(num_vertices)
     }
 
     /// Load edges recorded in the file of `edge_type` into the database.
     /// Return the number of edges that are successfully loaded.
+    ///
+    /// The dangling edges are handled by `dangling`, where the placeholders of this partition are
+    /// added as the vertices, and the others as the corner vertices.
     fn load_edges_to_db<R: Read>(
         &mut self, src_vertex_type: LabelId, dst_vertex_type: LabelId, edge_type: LabelId,
-        mut rdr: Reader<R>,
-    ) -> usize {
+        mut rdr: Reader<R>, edge_file: &Path, dangling: &DanglingEdgeChecker<G>,
+    ) -> GDBResult<usize> {
         let mut num_edges = 0_usize;
         let graph_db = &mut self.graph_builder;
         let schema = self.graph_schema.clone();
@@ -366,7 +400,11 @@ impl<G: IndexType + Eq + FromStr + Send + Sync, I: IndexType + Send + Sync> Grap
                 let record_iter = record.iter();
                 let record_iter_cloned = record_iter.clone();
 
-                if let Ok(edge_meta) = parser.parse_edge_meta(record_iter) {
+                if let Ok(mut edge_meta) = parser.parse_edge_meta(record_iter) {
+                    let line = record.position().map(|pos| pos.line()).unwrap_or(0);
+                    if !dangling.check(&mut edge_meta, edge_file, line)? {
+                        continue;
+                    }
                     if let Ok(properties) = parse_properties(
                         record_iter_cloned,
                         self.graph_schema.get_edge_header(edge_type),
@@ -376,20 +414,37 @@ impl<G: IndexType + Eq + FromStr + Send + Sync, I: IndexType + Send + Sync> Grap
                         start = end;
                         // add edge
                         //TODO: in this part, we read all edges and add corner if not in current work_id
-                        if keep_vertex(edge_meta.src_global_id, self.peers, self.work_id)
-                            || keep_vertex(edge_meta.dst_global_id, self.peers, self.work_id)
-                        {
-                            if !graph_db.is_vertex_local(edge_meta.src_global_id) {
-                                graph_db.add_corner_vertex(
-                                    edge_meta.src_global_id,
-                                    edge_meta.src_label_id,
-                                );
-                            }
-                            if !graph_db.is_vertex_local(edge_meta.dst_global_id) {
-                                graph_db.add_corner_vertex(
-                                    edge_meta.dst_global_id,
-                                    edge_meta.dst_label_id,
-                                );
+                        if keep_vertex(
+                            edge_meta.src_global_id,
+                            self.partition.as_ref(),
+                            self.work_id,
+                            self.peers,
+                        ) || keep_vertex(
+                            edge_meta.dst_global_id,
+                            self.partition.as_ref(),
+                            self.work_id,
+                            self.peers,
+                        ) {
+                            let ends = [
+                                (edge_meta.src_global_id, edge_meta.src_label_id),
+                                (edge_meta.dst_global_id, edge_meta.dst_label_id),
+                            ];
+                            for (id, label_id) in ends.iter().cloned() {
+                                if graph_db.is_vertex_local(id) {
+                                    continue;
+                                }
+                                if label_id == PLACEHOLDER_LABEL_ID
+                                    && keep_vertex(
+                                        id,
+                                        self.partition.as_ref(),
+                                        self.work_id,
+                                        self.peers,
+                                    )
+                                {
+                                    graph_db.add_vertex(id, placeholder_label());
+                                } else {
+                                    graph_db.add_corner_vertex(id, label_id);
+                                }
                             }
                             if properties.len() > 0 {
                                 if graph_db
@@ -435,13 +490,15 @@ impl<G: IndexType + Eq + FromStr + Send + Sync, I: IndexType + Send + Sync> Grap
             num_edges
         );
 
-        num_edges
+        Ok(num_edges)
     }
 
-    /// Load from raw data to a graph database.
+    /// Load from raw data to a graph database. Return `GDBError::DanglingEdgesError` if any edge
+    /// dangles while the policy is `DanglingEdgePolicy::Reject`.
     pub fn load(&mut self) -> GDBResult<()> {
         let (vertex_files, edge_files) =
             split_vertex_edge_files(self.raw_data_dir.clone(), self.work_id, self.peers)?;
+        let dangling = DanglingEdgeChecker::new(self.dangling_edge_policy);
 
         for (vertex_type, vertex_file) in vertex_files {
             let rdr = ReaderBuilder::new()
@@ -454,7 +511,7 @@ impl<G: IndexType + Eq + FromStr + Send + Sync, I: IndexType + Send + Sync> Grap
 
             if let Some(vertex_type_id) = self.graph_schema.get_vertex_label_id(&vertex_type) {
                 info!("Process vertex type & file {:?} {:?}", vertex_type, vertex_file);
-                self.load_vertices_to_db(vertex_type_id, rdr);
+                self.load_vertices_to_db(vertex_type_id, rdr, &dangling)?;
             } else {
                 debug!("Invalid vertex type: {}", vertex_type);
             }
@@ -476,11 +533,22 @@ impl<G: IndexType + Eq + FromStr + Send + Sync, I: IndexType + Send + Sync> Grap
                     label_tuple.dst_vertex_label,
                     label_tuple.edge_label,
                     rdr,
-                );
+                    &edge_file,
+                    &dangling,
+                )?;
             } else {
                 debug!("Invalid edge type: {}", edge_type);
             }
         }
+        let (num_dangling_edges, num_placeholders) = dangling.finish()?;
+        self.num_dangling_edges = num_dangling_edges;
+        self.num_placeholders = num_placeholders;
+        if num_dangling_edges > 0 {
+            info!(
+                "{} dangling edges handled by {:?}, with {} placeholders",
+                num_dangling_edges, self.dangling_edge_policy, num_placeholders
+            );
+        }
         info!("Total time: {:?}", self.timer.elapsed().as_secs_f64());
         info!("Time in details: {:?}", self.perf_metrics);
 
@@ -509,6 +577,10 @@ impl<G: FromStr + Send + Sync + IndexType, I: Send + Sync + IndexType> GraphLoad
             timer: Instant::now(),
             work_id,
             peers,
+            partition: Arc::new(HashPartition::new(peers)),
+            dangling_edge_policy: DanglingEdgePolicy::default(),
+            num_dangling_edges: 0,
+            num_placeholders: 0,
             perf_metrics: PerfMetrices::default(),
             ph1: PhantomData,
             ph2: PhantomData,
@@ -521,6 +593,28 @@ impl<G: FromStr + Send + Sync + IndexType, I: Send + Sync + IndexType> GraphLoad
         self
     }
 
+    /// For specifying a strategy of partitioning the vertices other than `HashPartition`
+    pub fn with_partition(mut self, partition: Arc<dyn GraphPartition>) -> Self {
+        self.partition = partition;
+        self
+    }
+
+    /// For specifying how the dangling edges are handled other than `DanglingEdgePolicy::Reject`
+    pub fn with_dangling_edge_policy(mut self, policy: DanglingEdgePolicy) -> Self {
+        self.dangling_edge_policy = policy;
+        self
+    }
+
+    /// The number of dangling edges handled in the last loading
+    pub fn num_dangling_edges(&self) -> usize {
+        self.num_dangling_edges
+    }
+
+    /// The number of placeholders created in the last loading
+    pub fn num_placeholders(&self) -> usize {
+        self.num_placeholders
+    }
+
     pub fn into_mutable_graph(self) -> MutableGraphDB<G, I> {
         self.graph_builder
     }
@@ -651,11 +745,11 @@ mod test {
 
     #[test]
     fn test_ldbc_parse() {
-        let org_id: LabelId = 5;
-        let company_id: LabelId = 11;
-        let place_id: LabelId = 0;
-        let country_id: LabelId = 8;
-        let org_in_place_id: LabelId = 2;
+        let org_id: LabelId = 5.into();
+        let company_id: LabelId = 11.into();
+        let place_id: LabelId = 0.into();
+        let country_id: LabelId = 8.into();
+        let org_in_place_id: LabelId = 2.into();
 
         let ldbc_schema_file = "data/schema.json";
         let schema = Arc::new(LDBCGraphSchema::from_json_file(ldbc_schema_file).unwrap());
@@ -670,7 +764,7 @@ mod test {
         assert_eq!(
             vertex_meta.unwrap(),
             VertexMeta {
-                global_id: (org_id as usize) << LABEL_SHIFT_BITS | 0,
+                global_id: org_id.index() << LABEL_SHIFT_BITS,
                 label: [org_id, company_id],
             }
         );
@@ -698,7 +792,7 @@ mod test {
         assert_eq!(
             vertex_meta.unwrap(),
             VertexMeta {
-                global_id: (place_id as usize) << LABEL_SHIFT_BITS | 0,
+                global_id: place_id.index() << LABEL_SHIFT_BITS,
                 label: [place_id, country_id],
             }
         );
@@ -728,9 +822,9 @@ mod test {
         assert_eq!(
             edge_meta.unwrap(),
             EdgeMeta {
-                src_global_id: (org_id as usize) << LABEL_SHIFT_BITS | 0,
+                src_global_id: org_id.index() << LABEL_SHIFT_BITS,
                 src_label_id: org_id,
-                dst_global_id: (place_id as usize) << LABEL_SHIFT_BITS | 59,
+                dst_global_id: place_id.index() << LABEL_SHIFT_BITS | 59,
                 dst_label_id: place_id,
                 label_id: org_in_place_id,
             }
@@ -798,29 +892,35 @@ mod test {
         assert_eq!(
             in_vertices,
             vec![
-                (BEIJING_ID, [0, 9]),
-                (SHANGHAI_ID, [0, 9]),
-                (PDD_ID, [5, 11]),
-                (TSINGHUA_ID, [5, 12])
+                (BEIJING_ID, [0.into(), 9.into()]),
+                (SHANGHAI_ID, [0.into(), 9.into()]),
+                (PDD_ID, [5.into(), 11.into()]),
+                (TSINGHUA_ID, [5.into(), 12.into()])
             ],
         );
 
         let in_vertices: Vec<(DefaultId, Label)> = graphdb
-            .get_adj_vertices(CHINA_ID, Some(&vec![17]), Direction::Incoming)
+            .get_adj_vertices(CHINA_ID, Some(&vec![17.into()]), Direction::Incoming)
             .map(|item| (item.get_id(), item.get_label()))
             .sorted()
             .collect();
-        assert_eq!(in_vertices, vec![(BEIJING_ID, [0, 9]), (SHANGHAI_ID, [0, 9]),],);
+        assert_eq!(
+            in_vertices,
+            vec![(BEIJING_ID, [0.into(), 9.into()]), (SHANGHAI_ID, [0.into(), 9.into()]),],
+        );
 
         let in_vertices: Vec<(DefaultId, Label)> = graphdb
-            .get_adj_vertices(CHINA_ID, Some(&vec![11]), Direction::Incoming)
+            .get_adj_vertices(CHINA_ID, Some(&vec![11.into()]), Direction::Incoming)
             .map(|item| (item.get_id(), item.get_label()))
             .sorted()
             .collect();
-        assert_eq!(in_vertices, vec![(PDD_ID, [5, 11]), (TSINGHUA_ID, [5, 12])],);
+        assert_eq!(
+            in_vertices,
+            vec![(PDD_ID, [5.into(), 11.into()]), (TSINGHUA_ID, [5.into(), 12.into()])],
+        );
 
         let in_edges: Vec<DefaultId> = graphdb
-            .get_adj_edges(CHINA_ID, Some(&vec![11]), Direction::Incoming)
+            .get_adj_edges(CHINA_ID, Some(&vec![11.into()]), Direction::Incoming)
             .map(|item| item.get_src_id())
             .sorted()
             .collect();
@@ -832,18 +932,24 @@ mod test {
             .sorted()
             .collect();
 
-        assert_eq!(out_vertices, vec![(CHINA_ID, [0, 8]), (SHANGHAI_ID, [0, 9]),],);
+        assert_eq!(
+            out_vertices,
+            vec![(CHINA_ID, [0.into(), 8.into()]), (SHANGHAI_ID, [0.into(), 9.into()]),],
+        );
 
         let out_vertices: Vec<(DefaultId, Label)> = graphdb
-            .get_out_vertices(PDD_ID, Some(&vec![11]))
+            .get_out_vertices(PDD_ID, Some(&vec![11.into()]))
             .map(|item| (item.get_id(), item.get_label()))
             .sorted()
             .collect();
 
-        assert_eq!(out_vertices, vec![(CHINA_ID, [0, 8]), (SHANGHAI_ID, [0, 9]),],);
+        assert_eq!(
+            out_vertices,
+            vec![(CHINA_ID, [0.into(), 8.into()]), (SHANGHAI_ID, [0.into(), 9.into()]),],
+        );
 
         let out_vertices: Vec<(DefaultId, Label)> = graphdb
-            .get_out_vertices(PDD_ID, Some(&vec![17]))
+            .get_out_vertices(PDD_ID, Some(&vec![17.into()]))
             .map(|item| (item.get_id(), item.get_label()))
             .sorted()
             .collect();
@@ -869,78 +975,92 @@ mod test {
         assert_eq!(
             vertices,
             vec![
-                (CHINA_ID, [0, 8]),
-                (BEIJING_ID, [0, 9]),
-                (SHANGHAI_ID, [0, 9]),
-                (PDD_ID, [5, 11]),
-                (TSINGHUA_ID, [5, 12])
+                (CHINA_ID, [0.into(), 8.into()]),
+                (BEIJING_ID, [0.into(), 9.into()]),
+                (SHANGHAI_ID, [0.into(), 9.into()]),
+                (PDD_ID, [5.into(), 11.into()]),
+                (TSINGHUA_ID, [5.into(), 12.into()])
             ],
         );
 
         let vertices_place: Vec<(DefaultId, Label)> = graphdb
-            .get_all_vertices(Some(&vec![0]))
+            .get_all_vertices(Some(&vec![0.into()]))
             .map(|item| (item.get_id(), item.get_label()))
             .sorted()
             .collect();
 
         assert_eq!(
             vertices_place,
-            vec![(CHINA_ID, [0, 8]), (BEIJING_ID, [0, 9]), (SHANGHAI_ID, [0, 9]),],
+            vec![
+                (CHINA_ID, [0.into(), 8.into()]),
+                (BEIJING_ID, [0.into(), 9.into()]),
+                (SHANGHAI_ID, [0.into(), 9.into()]),
+            ],
         );
 
         let vertices_city: Vec<(DefaultId, Label)> = graphdb
-            .get_all_vertices(Some(&vec![9]))
+            .get_all_vertices(Some(&vec![9.into()]))
             .map(|item| (item.get_id(), item.get_label()))
             .sorted()
             .collect();
 
-        assert_eq!(vertices_city, vec![(BEIJING_ID, [0, 9]), (SHANGHAI_ID, [0, 9]),],);
+        assert_eq!(
+            vertices_city,
+            vec![(BEIJING_ID, [0.into(), 9.into()]), (SHANGHAI_ID, [0.into(), 9.into()]),],
+        );
 
         let vertices_country: Vec<(DefaultId, Label)> = graphdb
-            .get_all_vertices(Some(&vec![8]))
+            .get_all_vertices(Some(&vec![8.into()]))
             .map(|item| (item.get_id(), item.get_label()))
             .sorted()
             .collect();
 
-        assert_eq!(vertices_country, vec![(CHINA_ID, [0, 8]),],);
+        assert_eq!(vertices_country, vec![(CHINA_ID, [0.into(), 8.into()]),],);
 
         let vertices_org: Vec<(DefaultId, Label)> = graphdb
-            .get_all_vertices(Some(&vec![5]))
+            .get_all_vertices(Some(&vec![5.into()]))
             .map(|item| (item.get_id(), item.get_label()))
             .sorted()
             .collect();
 
-        assert_eq!(vertices_org, vec![(PDD_ID, [5, 11]), (TSINGHUA_ID, [5, 12])],);
+        assert_eq!(
+            vertices_org,
+            vec![(PDD_ID, [5.into(), 11.into()]), (TSINGHUA_ID, [5.into(), 12.into()])],
+        );
 
         let vertices_company: Vec<(DefaultId, Label)> = graphdb
-            .get_all_vertices(Some(&vec![11]))
+            .get_all_vertices(Some(&vec![11.into()]))
             .map(|item| (item.get_id(), item.get_label()))
             .sorted()
             .collect();
 
-        assert_eq!(vertices_company, vec![(PDD_ID, [5, 11]),],);
+        assert_eq!(vertices_company, vec![(PDD_ID, [5.into(), 11.into()]),],);
 
         let vertices_university: Vec<(DefaultId, Label)> = graphdb
-            .get_all_vertices(Some(&vec![12]))
+            .get_all_vertices(Some(&vec![12.into()]))
             .map(|item| (item.get_id(), item.get_label()))
             .sorted()
             .collect();
 
-        assert_eq!(vertices_university, vec![(TSINGHUA_ID, [5, 12])],);
+        assert_eq!(vertices_university, vec![(TSINGHUA_ID, [5.into(), 12.into()])],);
 
         let vertices_company_city: Vec<(DefaultId, Label)> = graphdb
-            .get_all_vertices(Some(&vec![11, 9]))
+            .get_all_vertices(Some(&vec![11.into(), 9.into()]))
             .map(|item| (item.get_id(), item.get_label()))
             .sorted()
             .collect();
 
         assert_eq!(
             vertices_company_city,
-            vec![(BEIJING_ID, [0, 9]), (SHANGHAI_ID, [0, 9]), (PDD_ID, [5, 11]),],
+            vec![
+                (BEIJING_ID, [0.into(), 9.into()]),
+                (SHANGHAI_ID, [0.into(), 9.into()]),
+                (PDD_ID, [5.into(), 11.into()]),
+            ],
         );
 
         let vertices_place_city: Vec<(DefaultId, Label)> = graphdb
-            .get_all_vertices(Some(&vec![0, 9]))
+            .get_all_vertices(Some(&vec![0.into(), 9.into()]))
             .map(|item| (item.get_id(), item.get_label()))
             .sorted()
             .collect();
@@ -948,11 +1068,11 @@ mod test {
         assert_eq!(
             vertices_place_city,
             vec![
-                (CHINA_ID, [0, 8]),
-                (BEIJING_ID, [0, 9]),
-                (BEIJING_ID, [0, 9]), // beijing and shanghai will be included twice
-                (SHANGHAI_ID, [0, 9]),
-                (SHANGHAI_ID, [0, 9]),
+                (CHINA_ID, [0.into(), 8.into()]),
+                (BEIJING_ID, [0.into(), 9.into()]),
+                (BEIJING_ID, [0.into(), 9.into()]), // beijing and shanghai will be included twice
+                (SHANGHAI_ID, [0.into(), 9.into()]),
+                (SHANGHAI_ID, [0.into(), 9.into()]),
             ],
         );
     }
@@ -995,10 +1115,10 @@ mod test {
         assert_eq!(
             in_vertices,
             vec![
-                (BEIJING_ID, [0, INVALID_LABEL_ID]),  // corner vertices
-                (SHANGHAI_ID, [0, INVALID_LABEL_ID]), // corner vertices
-                (PDD_ID, [5, INVALID_LABEL_ID]),      // corner vertices
-                (TSINGHUA_ID, [5, 12])
+                (BEIJING_ID, [0.into(), INVALID_LABEL_ID]),  // corner vertices
+                (SHANGHAI_ID, [0.into(), INVALID_LABEL_ID]), // corner vertices
+                (PDD_ID, [5.into(), INVALID_LABEL_ID]),      // corner vertices
+                (TSINGHUA_ID, [5.into(), 12.into()])
             ],
         );
 
@@ -1032,10 +1152,57 @@ mod test {
         assert_eq!(
             out_vertices,
             vec![
-                (CHINA_ID, [0, INVALID_LABEL_ID]), // corner vertex
-                (SHANGHAI_ID, [0, 9]),
+                (CHINA_ID, [0.into(), INVALID_LABEL_ID]), // corner vertex
+                (SHANGHAI_ID, [0.into(), 9.into()]),
             ],
         );
     }
      */
+
+    #[test]
+    fn test_load_dangling_edges() {
+        let temp = tempdir::TempDir::new("test_load_dangling").expect("Open temp folder error");
+        let raw_dir = temp.path().join("raw");
+        std::fs::create_dir_all(&raw_dir).unwrap();
+        std::fs::write(
+            raw_dir.join("person_0_0.csv"),
+            "111|Mahinda|Perera|male|19891203|20100214153210447|119.235.7.103|Firefox\n",
+        )
+        .unwrap();
+        std::fs::write(raw_dir.join("person_knows_person_0_0.csv"), "111|999|20100313073721718\n")
+            .unwrap();
+        let root_dir = temp.path().join("graph");
+        let schema_file = PathBuf::from("data/schema.json");
+        let new_loader = || {
+            GraphLoader::<DefaultId, InternalId>::new(&raw_dir, &root_dir, &schema_file, 20, 0, 1)
+        };
+
+        match new_loader().load() {
+            Err(GDBError::DanglingEdgesError(1, edges)) => {
+                assert_eq!(edges.len(), 1);
+                assert_eq!(edges[0].line, 1);
+            }
+            _ => panic!("expect dangling edges"),
+        }
+
+        let mut loader = new_loader().with_dangling_edge_policy(DanglingEdgePolicy::Skip);
+        loader.load().expect("Load graph error");
+        assert_eq!(loader.num_dangling_edges(), 1);
+        assert_eq!(loader.into_graph().count_all_edges(None), 0);
+
+        let mut loader =
+            new_loader().with_dangling_edge_policy(DanglingEdgePolicy::CreatePlaceholder);
+        loader.load().expect("Load graph error");
+        assert_eq!(loader.num_placeholders(), 1);
+        let graphdb = loader.into_graph();
+        let person = |id: usize| (1 << LABEL_SHIFT_BITS) | id;
+        let placeholder = graphdb.get_vertex(person(999)).unwrap();
+        assert_eq!(placeholder.get_label(), placeholder_label());
+        assert!(placeholder.clone_all_properties().is_none());
+        let out_vertices = graphdb
+            .get_out_vertices(person(111), None)
+            .map(|v| (v.get_id(), v.get_label()))
+            .collect::<Vec<_>>();
+        assert_eq!(out_vertices, vec![(person(999), placeholder_label())]);
+    }
 }
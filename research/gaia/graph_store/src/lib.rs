@@ -13,17 +13,27 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
+pub mod bulk_loader;
+pub mod column;
 pub mod common;
 pub mod config;
+pub mod dangling;
+pub mod delta;
 pub mod error;
 pub mod graph_db;
 pub mod graph_db_impl;
 pub mod io;
 pub mod ldbc;
 pub mod parser;
+pub mod partition;
 pub mod prelude;
 pub mod schema;
+pub mod segment;
+pub mod snapshot;
+pub mod statistics;
+pub mod strict;
 pub mod table;
+pub mod upgrade;
 pub mod utils;
 
 #[macro_use]
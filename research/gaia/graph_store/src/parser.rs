@@ -19,7 +19,7 @@ use self::chrono::Datelike;
 use crate::common::{DefaultId, Label, LabelId};
 use crate::error::{GDBError, GDBResult};
 use crate::schema::*;
-use crate::table::Row;
+use crate::table::{ItemType, Row};
 use chrono::offset::{TimeZone, Utc};
 use std::fmt::Debug;
 use std::str::FromStr;
@@ -138,6 +138,29 @@ fn parse_datetime(val: &str) -> GDBResult<u64> {
     Ok(_time.parse::<u64>()?)
 }
 
+/// Parse the value of a field by its type, or `None` if the field is not recorded as a property,
+/// e.g. the label of a vertex
+pub fn parse_value(field: &str, ty: &DataType, val: &str) -> GDBResult<Option<ItemType>> {
+    let value = match ty {
+        DataType::String => object!(val.to_string()),
+        DataType::Integer => object!(val.parse::<i32>()?),
+        DataType::Long => object!(val.parse::<i64>()?),
+        DataType::Double => object!(val.parse::<f64>()?),
+        DataType::Date => object!(parse_datetime(val)?),
+        DataType::ID => {
+            // do not record the starting (ldbc) id and end id of an edge
+            if field == START_ID_FIELD || field == END_ID_FIELD {
+                return Ok(None);
+            }
+            object!(val.parse::<DefaultId>()?)
+        }
+        // do not further record the label of a vertex
+        DataType::LABEL => return Ok(None),
+        DataType::NULL => return Err(GDBError::ParseError),
+    };
+    Ok(Some(value))
+}
+
 pub fn parse_properties<'a, Iter: Iterator<Item = &'a str>>(
     mut record_iter: Iter, _header: Option<&[(String, DataType)]>,
 ) -> GDBResult<Row> {
@@ -152,26 +175,8 @@ pub fn parse_properties<'a, Iter: Iterator<Item = &'a str>>(
     while let Some(val) = record_iter.next() {
         // unwrap the property and type
         if let Some((field, ty)) = header_iter.next() {
-            if ty == &DataType::String {
-                properties.push(object!(val.to_string()));
-            } else if ty == &DataType::Integer {
-                properties.push(object!(val.parse::<i32>()?));
-            } else if ty == &DataType::Long {
-                properties.push(object!(val.parse::<i64>()?));
-            } else if ty == &DataType::Double {
-                properties.push(object!(val.parse::<f64>()?));
-            } else if ty == &DataType::Date {
-                properties.push(object!(parse_datetime(val)?));
-            } else if ty == &DataType::ID {
-                // do not record the starting (ldbc) id and end id of an edge
-                if field != START_ID_FIELD && field != END_ID_FIELD {
-                    properties.push(object!(val.parse::<DefaultId>()?));
-                }
-            } else if ty == &DataType::LABEL {
-                // do not further record the label of a vertex
-                continue;
-            } else {
-                return GDBResult::Err(GDBError::ParseError);
+            if let Some(value) = parse_value(field, ty, val)? {
+                properties.push(value);
             }
         }
     }
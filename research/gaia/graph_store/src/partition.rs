@@ -0,0 +1,344 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The strategies of partitioning the vertices of a graph. The same strategy must be used for
+//! loading the graph and for querying it, so that a vertex is always processed by the
+//! server (and worker) that maintains its data.
+
+use crate::common::DefaultId;
+use crate::error::{GDBError, GDBResult};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Decide the partition of each vertex, and the server and worker to process each vertex.
+///
+/// The partitions are assigned to the servers in a round-robin manner. If there are more
+/// partitions than servers, e.g. one partition per worker, the partitions of a server are
+/// further assigned to its workers in a round-robin manner. Otherwise, the vertices of a server
+/// are spread among its workers by their global ids.
+pub trait GraphPartition: Send + Sync + 'static {
+    /// Get the partition of the vertex of given global id
+    fn get_partition(&self, vid: DefaultId) -> usize;
+
+    /// Get the number of partitions
+    fn num_partitions(&self) -> usize;
+
+    /// Get the server that maintains the vertex of given global id, among `num_servers` servers
+    fn get_server(&self, vid: DefaultId, num_servers: usize) -> usize {
+        self.get_partition(vid) % num_servers
+    }
+
+    /// Get the servers holding the replicas of the partition of the vertex of given global id,
+    /// with the primary, i.e. `get_server`, first, which alone applies the updates to the vertex,
+    /// while any replica can serve the reads of the immutable data loaded
+    fn get_replica_servers(&self, vid: DefaultId, num_servers: usize) -> Vec<usize> {
+        vec![self.get_server(vid, num_servers)]
+    }
+
+    /// Get the worker to process the vertex of given global id, among the `workers` workers
+    /// of the server that maintains the vertex
+    fn get_worker(&self, vid: DefaultId, num_servers: usize, workers: usize) -> usize {
+        if self.num_partitions() > num_servers {
+            (self.get_partition(vid) / num_servers) % workers
+        } else {
+            (vid / num_servers) % workers
+        }
+    }
+}
+
+/// Partition the vertices by the hash (modulo) of their global ids, which is the default.
+#[derive(Copy, Clone, Debug)]
+pub struct HashPartition {
+    num_partitions: usize,
+}
+
+impl HashPartition {
+    pub fn new(num_partitions: usize) -> Self {
+        assert!(num_partitions > 0, "the number of partitions must be positive");
+        HashPartition { num_partitions }
+    }
+}
+
+impl GraphPartition for HashPartition {
+    fn get_partition(&self, vid: DefaultId) -> usize {
+        vid % self.num_partitions
+    }
+
+    fn num_partitions(&self) -> usize {
+        self.num_partitions
+    }
+}
+
+/// Partition the vertices by the ranges of their global ids. The `i`-th partition holds the
+/// vertices whose ids are in `[upper_bounds[i - 1], upper_bounds[i])`, while the last partition
+/// also holds all vertices whose ids are beyond the last bound.
+#[derive(Clone, Debug)]
+pub struct RangePartition {
+    upper_bounds: Vec<DefaultId>,
+}
+
+impl RangePartition {
+    pub fn new(mut upper_bounds: Vec<DefaultId>) -> Self {
+        assert!(!upper_bounds.is_empty(), "the number of partitions must be positive");
+        upper_bounds.sort();
+        RangePartition { upper_bounds }
+    }
+}
+
+impl GraphPartition for RangePartition {
+    fn get_partition(&self, vid: DefaultId) -> usize {
+        let partition = match self.upper_bounds.binary_search(&vid) {
+            Ok(idx) => idx + 1,
+            Err(idx) => idx,
+        };
+        std::cmp::min(partition, self.upper_bounds.len() - 1)
+    }
+
+    fn num_partitions(&self) -> usize {
+        self.upper_bounds.len()
+    }
+}
+
+/// Partition the vertices following an explicit mapping, e.g. an edge-cut computed by METIS.
+/// The vertices absent from the mapping fall back to `HashPartition`.
+#[derive(Clone, Debug)]
+pub struct ExplicitPartition {
+    mapping: HashMap<DefaultId, usize>,
+    fallback: HashPartition,
+}
+
+impl ExplicitPartition {
+    pub fn new(mapping: HashMap<DefaultId, usize>, num_partitions: usize) -> Self {
+        ExplicitPartition { mapping, fallback: HashPartition::new(num_partitions) }
+    }
+
+    /// Load the mapping from a partition map file, of which each non-empty line that does not
+    /// start with '#' is either:
+    /// * "<vertex id><delim><partition>", or
+    /// * "<partition>", as the output of METIS, where the vertex id is the index of the line
+    ///   among all such lines.
+    ///
+    /// Return `GDBError::ParseError` if a line is malformed, or a partition is out of range.
+    pub fn from_file<P: AsRef<Path>>(
+        path: P, delim: char, num_partitions: usize,
+    ) -> GDBResult<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        let mut mapping = HashMap::new();
+        let mut index = 0;
+        for line in reader.lines() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (vid, partition) = if let Some(pos) = line.find(delim) {
+                (line[..pos].trim().parse::<DefaultId>()?, line[pos + 1..].trim().parse::<usize>()?)
+            } else {
+                (index, line.parse::<usize>()?)
+            };
+            if partition >= num_partitions {
+                error!("Partition {} of vertex {} is out of range", partition, vid);
+                return Err(GDBError::ParseError);
+            }
+            mapping.insert(vid, partition);
+            index += 1;
+        }
+
+        Ok(Self::new(mapping, num_partitions))
+    }
+}
+
+impl GraphPartition for ExplicitPartition {
+    fn get_partition(&self, vid: DefaultId) -> usize {
+        if let Some(partition) = self.mapping.get(&vid) {
+            *partition
+        } else {
+            self.fallback.get_partition(vid)
+        }
+    }
+
+    fn num_partitions(&self) -> usize {
+        self.fallback.num_partitions()
+    }
+}
+
+/// Place the replicas of each partition of another strategy on a number of servers, which
+/// are the consecutive servers starting from the one of the partition by default, or as listed
+/// explicitly, where the first owner is the primary. The servers lost are left out of the
+/// owners, so that the reads are served by the rest replicas, and the first of them is promoted
+/// as the primary.
+#[derive(Clone)]
+pub struct ReplicatedPartition {
+    inner: Arc<dyn GraphPartition>,
+    replicas: usize,
+    // the owners of the partitions listed explicitly, indexed by the partitions
+    owners: Vec<Vec<usize>>,
+    lost: Vec<usize>,
+}
+
+impl ReplicatedPartition {
+    pub fn new(inner: Arc<dyn GraphPartition>, replicas: usize) -> Self {
+        assert!(replicas > 0, "the number of replicas must be positive");
+        ReplicatedPartition { inner, replicas, owners: vec![], lost: vec![] }
+    }
+
+    /// Place the replicas of the `i`-th partition on the servers of `owners[i]`, with the primary
+    /// first, while the partitions not listed follow the default placement of one replica;
+    pub fn with_owners(inner: Arc<dyn GraphPartition>, owners: Vec<Vec<usize>>) -> Self {
+        assert!(owners.iter().all(|o| !o.is_empty()), "a partition must have an owner");
+        ReplicatedPartition { inner, replicas: 1, owners, lost: vec![] }
+    }
+
+    /// Leave the server out of the owners of all partitions, e.g. once it is lost
+    pub fn without_server(mut self, server: usize) -> Self {
+        self.lost.push(server);
+        self
+    }
+
+    fn get_owners(&self, partition: usize, num_servers: usize) -> Vec<usize> {
+        let mut owners = if let Some(owners) = self.owners.get(partition) {
+            owners.clone()
+        } else {
+            let replicas = std::cmp::min(self.replicas, num_servers);
+            (0..replicas).map(|r| (partition + r) % num_servers).collect()
+        };
+        owners.retain(|s| !self.lost.contains(s));
+        owners
+    }
+}
+
+impl GraphPartition for ReplicatedPartition {
+    fn get_partition(&self, vid: DefaultId) -> usize {
+        self.inner.get_partition(vid)
+    }
+
+    fn num_partitions(&self) -> usize {
+        self.inner.num_partitions()
+    }
+
+    /// Get the primary of the partition of the vertex, or the server of the partition as if it
+    /// is not replicated if all its owners are lost
+    fn get_server(&self, vid: DefaultId, num_servers: usize) -> usize {
+        let partition = self.get_partition(vid);
+        match self.get_owners(partition, num_servers).first() {
+            Some(primary) => *primary,
+            None => partition % num_servers,
+        }
+    }
+
+    fn get_replica_servers(&self, vid: DefaultId, num_servers: usize) -> Vec<usize> {
+        let owners = self.get_owners(self.get_partition(vid), num_servers);
+        if owners.is_empty() {
+            vec![self.get_server(vid, num_servers)]
+        } else {
+            owners
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_hash_partition() {
+        let partition = HashPartition::new(3);
+        assert_eq!(partition.get_partition(7), 1);
+        assert_eq!(partition.get_server(7, 3), 1);
+        // 7 / 3 = 2, 10 / 3 = 3
+        assert_eq!(partition.get_worker(7, 3, 2), 0);
+        assert_eq!(partition.get_worker(10, 3, 2), 1);
+        // one partition per worker of 3 servers, each of 2 workers
+        let partition = HashPartition::new(6);
+        assert_eq!(partition.get_server(10, 3), 1);
+        assert_eq!(partition.get_worker(10, 3, 2), 1);
+        assert_eq!(partition.get_server(7, 3), 1);
+        assert_eq!(partition.get_worker(7, 3, 2), 0);
+    }
+
+    #[test]
+    fn test_range_partition() {
+        let partition = RangePartition::new(vec![100, 10, 1000]);
+        assert_eq!(partition.num_partitions(), 3);
+        assert_eq!(partition.get_partition(0), 0);
+        assert_eq!(partition.get_partition(10), 1);
+        assert_eq!(partition.get_partition(99), 1);
+        assert_eq!(partition.get_partition(100), 2);
+        assert_eq!(partition.get_partition(100000), 2);
+    }
+
+    #[test]
+    fn test_replicated_partition() {
+        let partition = ReplicatedPartition::new(Arc::new(HashPartition::new(3)), 2);
+        assert_eq!(partition.get_partition(7), 1);
+        assert_eq!(partition.get_server(7, 3), 1);
+        assert_eq!(partition.get_replica_servers(7, 3), vec![1, 2]);
+        assert_eq!(partition.get_replica_servers(8, 3), vec![2, 0]);
+        // never two replicas on a server
+        assert_eq!(partition.get_replica_servers(8, 1), vec![0]);
+        // the replica is promoted once the primary is lost
+        let partition = partition.without_server(2);
+        assert_eq!(partition.get_server(8, 3), 0);
+        assert_eq!(partition.get_replica_servers(8, 3), vec![0]);
+        assert_eq!(partition.get_replica_servers(7, 3), vec![1]);
+
+        let owners = vec![vec![1, 0], vec![1]];
+        let partition = ReplicatedPartition::with_owners(Arc::new(HashPartition::new(3)), owners);
+        assert_eq!(partition.get_replica_servers(3, 2), vec![1, 0]);
+        assert_eq!(partition.get_server(4, 2), 1);
+        // not listed
+        assert_eq!(partition.get_replica_servers(5, 2), vec![0]);
+        // the partitions of the lost owners fall back to no replicas
+        let partition = partition.without_server(1);
+        assert_eq!(partition.get_replica_servers(4, 2), vec![1]);
+    }
+
+    #[test]
+    fn test_explicit_partition_from_file() {
+        let temp = tempdir::TempDir::new("test_partition").expect("Open temp folder error");
+        let path = temp.path().join("partition_map");
+        let mut file = File::create(&path).unwrap();
+        writeln!(file, "# vertex|partition").unwrap();
+        writeln!(file, "1|1").unwrap();
+        writeln!(file, "2 | 0").unwrap();
+        writeln!(file).unwrap();
+        writeln!(file, "4|1").unwrap();
+        drop(file);
+
+        let partition = ExplicitPartition::from_file(&path, '|', 2).unwrap();
+        assert_eq!(partition.get_partition(1), 1);
+        assert_eq!(partition.get_partition(2), 0);
+        assert_eq!(partition.get_partition(4), 1);
+        // fall back to hash partition
+        assert_eq!(partition.get_partition(3), 1);
+        assert_eq!(partition.get_partition(6), 0);
+
+        // in the output format of METIS
+        std::fs::write(&path, "1\n1\n0\n").unwrap();
+        let partition = ExplicitPartition::from_file(&path, '|', 2).unwrap();
+        assert_eq!(partition.get_partition(0), 1);
+        assert_eq!(partition.get_partition(1), 1);
+        assert_eq!(partition.get_partition(2), 0);
+
+        std::fs::write(&path, "1|3\n").unwrap();
+        assert!(ExplicitPartition::from_file(&path, '|', 2).is_err());
+        std::fs::write(&path, "1|x\n").unwrap();
+        assert!(ExplicitPartition::from_file(&path, '|', 2).is_err());
+    }
+}
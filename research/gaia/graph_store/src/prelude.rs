@@ -13,14 +13,21 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
-pub use crate::common::{DefaultId, InternalId, Label, LabelId, INVALID_LABEL_ID, NAME, VERSION};
+pub use crate::column::{ColumnResidency, ColumnTable};
+pub use crate::common::{
+    DefaultId, InternalId, Label, LabelId, INVALID_LABEL_ID, NAME, PLACEHOLDER_LABEL_ID,
+    PLACEHOLDER_LABEL_NAME, VERSION,
+};
 pub use crate::config::GraphDBConfig;
+pub use crate::dangling::DanglingEdgePolicy;
 pub use crate::error::{GDBError, GDBResult};
 pub use crate::graph_db::{
-    Direction, GlobalStoreTrait, GlobalStoreUpdate, LocalAdjEdge, LocalEdge, LocalVertex,
+    Direction, EdgeId, GlobalStoreTrait, GlobalStoreUpdate, LocalAdjEdge, LocalEdge, LocalVertex,
 };
 pub use crate::graph_db_impl::{LargeGraphDB, MutableGraphDB};
-pub use crate::schema::{LDBCGraphSchema, Schema};
+pub use crate::schema::{GraphSchemaInfo, LDBCGraphSchema, LabelSchema, Schema};
+pub use crate::statistics::GraphStatistics;
 pub use crate::table::{
     ItemType, ItemTypeRef, PropertyTable, PropertyTableTrait, Row, RowRef, SingleValueTable,
 };
+pub use crate::upgrade::UpgradePolicy;
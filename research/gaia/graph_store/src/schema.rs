@@ -13,10 +13,13 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
-use crate::common::LabelId;
+use crate::common::{Label, LabelId, PLACEHOLDER_LABEL_ID, PLACEHOLDER_LABEL_NAME};
 use crate::config::JsonConf;
+use crate::error::{GDBError, GDBResult};
 use crate::parser::DataType;
 use itertools::Itertools;
+use petgraph::graph::{DiGraph, IndexType};
+use petgraph::visit::EdgeRef;
 use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
 use std::fs::File;
@@ -34,7 +37,7 @@ pub const END_ID_FIELD: &'static str = "end_id";
 
 /// An edge's label is consisted of three elements:
 /// edge_label, src_vertex_label and dst_vertex_label.
-#[derive(Copy, Clone, Debug, Ord, PartialOrd, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
 pub struct EdgeLabelTuple {
     pub edge_label: LabelId,
     pub src_vertex_label: LabelId,
@@ -65,6 +68,17 @@ pub trait Schema {
     fn get_edge_label_id(&self, edge_type: &str) -> Option<LabelId>;
 }
 
+/// The order the edges of a label are stored in each adjacency of the graph, i.e. sorted by a
+/// property of the edges, as chosen while the graph is loaded, see `BulkLoader::sort_adjacency`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct AdjOrder {
+    /// The property of the edges they are sorted by
+    pub property: String,
+    /// Whether they are sorted in the descending order of the property, or the ascending one
+    #[serde(default)]
+    pub descending: bool,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct LDBCGraphSchema {
     /// Map from vertex types to labelid
@@ -76,9 +90,62 @@ pub struct LDBCGraphSchema {
     vertex_prop_vec: HashMap<LabelId, Vec<(String, DataType)>>,
     edge_prop_meta: HashMap<LabelId, HashMap<String, (DataType, usize)>>,
     edge_prop_vec: HashMap<LabelId, Vec<(String, DataType)>>,
+    /// Map from edge (labelid) to the order its edges are stored in each adjacency, if sorted,
+    /// which is kept in a section of its own by the snapshots, see `snapshot`
+    #[serde(skip)]
+    adj_orders: HashMap<LabelId, AdjOrder>,
+    /// Whether the graph is loaded in the strict schema mode, which is kept in a section of its
+    /// own by the snapshots as well, see `crate::strict`
+    #[serde(skip)]
+    strict: bool,
 }
 
 impl LDBCGraphSchema {
+    /// The order the edges of `label` are stored in each adjacency, or `None` if they are stored
+    /// in the order they are added
+    pub fn get_adj_order(&self, label: LabelId) -> Option<&AdjOrder> {
+        self.adj_orders.get(&label)
+    }
+
+    /// Record that the edges of `edge_type` are stored sorted by the order in each adjacency,
+    /// whose property must be one of the edge type other than the ids of its ends
+    pub fn set_adj_order(&mut self, edge_type: &str, order: AdjOrder) -> GDBResult<()> {
+        let label = self.get_edge_label_id(edge_type).ok_or(GDBError::InvalidTypeError)?;
+        let is_property = self
+            .edge_prop_meta
+            .get(&label)
+            .map(|meta| meta.contains_key(&order.property))
+            .unwrap_or(false);
+        if !is_property || order.property == START_ID_FIELD || order.property == END_ID_FIELD {
+            return Err(GDBError::FieldNotExistError);
+        }
+        self.adj_orders.insert(label, order);
+        Ok(())
+    }
+
+    pub(crate) fn adj_orders(&self) -> &HashMap<LabelId, AdjOrder> {
+        &self.adj_orders
+    }
+
+    pub(crate) fn set_adj_orders(&mut self, adj_orders: HashMap<LabelId, AdjOrder>) {
+        self.adj_orders = adj_orders;
+    }
+
+    /// Whether every value of a property is of the type declared for it, as the graph is loaded
+    /// in the strict schema mode, see `crate::strict::SchemaMode`
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    pub(crate) fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// Whether `label` is the id of an edge type
+    pub fn has_edge_label(&self, label: LabelId) -> bool {
+        self.edge_type_to_id.values().any(|id| *id == label)
+    }
+
     /// While loading graphs, we will have properties such as LABELs and edge's
     /// ids data recorded in the schema. While these data for now does not actually
     /// maintain in the database, we will trim them in the schema.
@@ -161,7 +228,9 @@ impl PartialEq for LDBCGraphSchema {
             && is_map_eq(&self.vertex_prop_vec, &other.vertex_prop_vec)
             && is_map_eq(&self.edge_prop_vec, &other.edge_prop_vec)
             && self.vertex_prop_meta.len() == other.vertex_prop_meta.len()
-            && self.edge_prop_meta.len() == other.edge_prop_meta.len();
+            && self.edge_prop_meta.len() == other.edge_prop_meta.len()
+            && self.adj_orders == other.adj_orders
+            && self.strict == other.strict;
 
         if is_eq {
             for ((k1, v1), (k2, v2)) in self
@@ -199,6 +268,12 @@ struct LDBCGraphSchemaJson {
     edge_type_map: HashMap<String, LabelId>,
     vertex_prop: HashMap<String, Vec<(String, DataType)>>,
     edge_prop: HashMap<String, Vec<(String, DataType)>>,
+    /// The edge types whose edges are stored sorted in each adjacency, by the names of the types
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    adj_order: HashMap<String, AdjOrder>,
+    /// Whether the graph is loaded in the strict schema mode
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    strict: bool,
 }
 
 impl<'a> From<&'a LDBCGraphSchema> for LDBCGraphSchemaJson {
@@ -225,7 +300,20 @@ impl<'a> From<&'a LDBCGraphSchema> for LDBCGraphSchemaJson {
             edge_prop.insert(edge_type_map_rev[key].clone(), value.clone());
         }
 
-        Self { vertex_type_map, edge_type_map, vertex_prop, edge_prop }
+        let adj_order = schema
+            .adj_orders
+            .iter()
+            .map(|(key, order)| (edge_type_map_rev[key].clone(), order.clone()))
+            .collect();
+
+        Self {
+            vertex_type_map,
+            edge_type_map,
+            vertex_prop,
+            edge_prop,
+            adj_order,
+            strict: schema.strict,
+        }
     }
 }
 
@@ -265,6 +353,12 @@ impl<'a> From<&'a LDBCGraphSchemaJson> for LDBCGraphSchema {
             }
         }
 
+        let adj_orders = schema_json
+            .adj_order
+            .iter()
+            .map(|(key, order)| (edge_type_to_id[key], order.clone()))
+            .collect();
+
         Self {
             vertex_type_to_id,
             edge_type_to_id,
@@ -272,6 +366,8 @@ impl<'a> From<&'a LDBCGraphSchemaJson> for LDBCGraphSchema {
             vertex_prop_vec,
             edge_prop_meta,
             edge_prop_vec,
+            adj_orders,
+            strict: schema_json.strict,
         }
     }
 }
@@ -297,6 +393,9 @@ impl Schema for LDBCGraphSchema {
     }
 
     fn get_vertex_label_id(&self, vertex_type: &str) -> Option<LabelId> {
+        if vertex_type == PLACEHOLDER_LABEL_NAME {
+            return Some(PLACEHOLDER_LABEL_ID);
+        }
         self.vertex_type_to_id.get(vertex_type).cloned()
     }
 
@@ -333,9 +432,113 @@ impl JsonConf<LDBCGraphSchema> for LDBCGraphSchema {
     }
 }
 
+/// A vertex or edge label of a graph, with the names and types of its properties
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LabelSchema {
+    pub id: LabelId,
+    pub name: String,
+    pub properties: Vec<(String, DataType)>,
+}
+
+impl LabelSchema {
+    pub fn get_property_type(&self, name: &str) -> Option<&DataType> {
+        self.properties.iter().find(|(prop, _)| prop == name).map(|(_, dt)| dt)
+    }
+}
+
+/// The labels of a graph and their properties, together with the (src, dst) vertex labels
+/// actually linked by the edges of each edge label, which are recorded while the graph is loaded,
+/// for the clients to introspect the graph and for the queries to be checked before running.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GraphSchemaInfo {
+    /// The vertex labels sorted by their ids
+    pub vertex_labels: Vec<LabelSchema>,
+    /// The edge labels sorted by their ids
+    pub edge_labels: Vec<LabelSchema>,
+    /// The distinct label tuples of the edges, sorted
+    pub relations: Vec<EdgeLabelTuple>,
+    /// Whether the graph is loaded in the strict schema mode, in which the types of the
+    /// properties of each label are authoritative, see `LDBCGraphSchema::is_strict`
+    pub strict: bool,
+}
+
+impl GraphSchemaInfo {
+    /// Collect the schema info from the structure of a graph, where `schema` must be trimmed
+    /// in order to match the stored properties.
+    pub(crate) fn compute<I: IndexType>(
+        graph: &DiGraph<Label, LabelId, I>, schema: &LDBCGraphSchema,
+    ) -> Self {
+        let labels = |type_to_id: &HashMap<String, LabelId>,
+                      prop_vec: &HashMap<LabelId, Vec<(String, DataType)>>| {
+            type_to_id
+                .iter()
+                .map(|(name, id)| LabelSchema {
+                    id: *id,
+                    name: name.clone(),
+                    properties: prop_vec.get(id).cloned().unwrap_or_default(),
+                })
+                .sorted_by_key(|label| label.id)
+                .collect::<Vec<_>>()
+        };
+        let mut relations = HashSet::new();
+        // the labels of the corner vertices may be absent in current partition, and the
+        // placeholders are of no relation in the schema
+        let known = |label: Option<&Label>| {
+            label.and_then(|l| l[0].valid()).filter(|l| *l != PLACEHOLDER_LABEL_ID)
+        };
+        for edge in graph.edge_references() {
+            let src = known(graph.node_weight(edge.source()));
+            let dst = known(graph.node_weight(edge.target()));
+            if let (Some(src), Some(dst)) = (src, dst) {
+                relations.insert(EdgeLabelTuple {
+                    edge_label: *edge.weight(),
+                    src_vertex_label: src,
+                    dst_vertex_label: dst,
+                });
+            }
+        }
+        GraphSchemaInfo {
+            vertex_labels: labels(&schema.vertex_type_to_id, &schema.vertex_prop_vec),
+            edge_labels: labels(&schema.edge_type_to_id, &schema.edge_prop_vec),
+            relations: relations.into_iter().sorted().collect(),
+            strict: schema.strict,
+        }
+    }
+
+    pub fn get_vertex_label(&self, name: &str) -> Option<&LabelSchema> {
+        self.vertex_labels.iter().find(|label| label.name == name)
+    }
+
+    pub fn get_edge_label(&self, name: &str) -> Option<&LabelSchema> {
+        self.edge_labels.iter().find(|label| label.name == name)
+    }
+
+    /// Get the (src, dst) vertex labels linked by the edges of the edge label
+    pub fn get_relations(&self, edge_label: LabelId) -> Vec<(LabelId, LabelId)> {
+        self.relations
+            .iter()
+            .filter(|r| r.edge_label == edge_label)
+            .map(|r| (r.src_vertex_label, r.dst_vertex_label))
+            .collect()
+    }
+
+    /// Get the distinct types of the property among all vertex and edge labels having it,
+    /// which is empty if no label has the property
+    pub fn get_property_types(&self, name: &str) -> Vec<DataType> {
+        self.vertex_labels
+            .iter()
+            .chain(self.edge_labels.iter())
+            .filter_map(|label| label.get_property_type(name).cloned())
+            .sorted()
+            .dedup()
+            .collect()
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::common::INVALID_LABEL_ID;
 
     #[test]
     fn test_trim_schema() {
@@ -418,4 +621,33 @@ mod test {
 
         assert!(is_map_eq(knows_schema, &expected_knows_schema));
     }
+
+    #[test]
+    fn test_schema_info() {
+        let mut schema =
+            LDBCGraphSchema::from_json_file("data/schema.json").expect("Get schema error");
+        schema.trim();
+        let person = schema.get_vertex_label_id("PERSON").unwrap();
+        let org = schema.get_vertex_label_id("ORGANISATION").unwrap();
+        let knows = schema.get_edge_label_id("KNOWS").unwrap();
+        let mut graph = DiGraph::<Label, LabelId>::default();
+        let v1 = graph.add_node([person, INVALID_LABEL_ID]);
+        let v2 = graph.add_node([person, INVALID_LABEL_ID]);
+        graph.add_node([org, INVALID_LABEL_ID]);
+        graph.add_edge(v1, v2, knows);
+        graph.add_edge(v2, v1, knows);
+
+        let info = GraphSchemaInfo::compute(&graph, &schema);
+        assert_eq!(info.vertex_labels.len(), schema.vertex_type_to_id.len());
+        assert_eq!(info.edge_labels.len(), schema.edge_type_to_id.len());
+        let org_label = info.get_vertex_label("ORGANISATION").unwrap();
+        assert_eq!(org_label.id, org);
+        assert_eq!(org_label.get_property_type("url"), Some(&DataType::String));
+        assert_eq!(org_label.get_property_type("~LABEL"), None);
+        assert_eq!(info.get_edge_label("KNOWS").unwrap().id, knows);
+        assert_eq!(info.relations.len(), 1);
+        assert_eq!(info.get_relations(knows), vec![(person, person)]);
+        assert_eq!(info.get_property_types("creationDate"), vec![DataType::Date]);
+        assert!(info.get_property_types("unknown").is_empty());
+    }
 }
@@ -0,0 +1,176 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The adjacency of the edges of each label in each direction, kept in a segment of its own as a
+//! compressed sparse row, so that the vertices adjacent by the edges of a label are read without
+//! going through the edges of the other labels and comparing their labels. The segments are built
+//! out of the graph on their first use, as the graph is never changed once built. The adjacent
+//! vertices of each vertex in a segment are in the order the graph gives its edges, unless the
+//! edges of the label are stored sorted by a property, see `LDBCGraphSchema::get_adj_order`.
+
+use crate::common::{Label, LabelId};
+use petgraph::graph::{DiGraph, EdgeIndex, IndexType, NodeIndex};
+use petgraph::visit::EdgeRef;
+use petgraph::Direction;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+/// The adjacency of the edges of a label in a direction
+pub struct AdjSegment<I: IndexType> {
+    label: LabelId,
+    dir: Direction,
+    /// The vertices adjacent to the vertex of internal index `i` are
+    /// `neighbors[offsets[i]..offsets[i + 1]]`
+    offsets: Vec<usize>,
+    neighbors: Vec<NodeIndex<I>>,
+    /// The edges the vertices are adjacent by, along with `neighbors`
+    edges: Vec<EdgeIndex<I>>,
+}
+
+impl<I: IndexType> AdjSegment<I> {
+    /// Build the segment out of the graph, where the adjacent vertices of each vertex are in the
+    /// order the graph gives its edges, the same as filtering them by the label
+    pub fn build(graph: &DiGraph<Label, LabelId, I>, label: LabelId, dir: Direction) -> Self {
+        let mut offsets = Vec::with_capacity(graph.node_count() + 1);
+        let mut neighbors = vec![];
+        let mut edges = vec![];
+        offsets.push(0);
+        for index in graph.node_indices() {
+            for edge in graph.edges_directed(index, dir) {
+                if *edge.weight() == label {
+                    let neighbor =
+                        if dir == Direction::Outgoing { edge.target() } else { edge.source() };
+                    neighbors.push(neighbor);
+                    edges.push(edge.id());
+                }
+            }
+            offsets.push(neighbors.len());
+        }
+        neighbors.shrink_to_fit();
+        edges.shrink_to_fit();
+        AdjSegment { label, dir, offsets, neighbors, edges }
+    }
+
+    /// Build the segment as `build` does, where the adjacent vertices of each vertex are sorted by
+    /// the keys of the edges they are adjacent by, in the descending order if `descending`, while
+    /// those of equal or incomparable keys are kept in the order the graph gives their edges
+    pub fn build_sorted<K, F>(
+        graph: &DiGraph<Label, LabelId, I>, label: LabelId, dir: Direction, key: F,
+        descending: bool,
+    ) -> Self
+    where
+        K: PartialOrd,
+        F: Fn(EdgeIndex<I>) -> K,
+    {
+        let mut segment = Self::build(graph, label, dir);
+        let mut adjacency = vec![];
+        for i in 0..segment.offsets.len() - 1 {
+            let (start, end) = (segment.offsets[i], segment.offsets[i + 1]);
+            if end - start < 2 {
+                continue;
+            }
+            adjacency.extend(
+                (start..end)
+                    .map(|j| (key(segment.edges[j]), segment.neighbors[j], segment.edges[j])),
+            );
+            adjacency.sort_by(|a, b| {
+                let ordering = a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal);
+                if descending {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            });
+            for (j, (_, neighbor, edge)) in adjacency.drain(..).enumerate() {
+                segment.neighbors[start + j] = neighbor;
+                segment.edges[start + j] = edge;
+            }
+        }
+        segment
+    }
+
+    pub fn label(&self) -> LabelId {
+        self.label
+    }
+
+    pub fn direction(&self) -> Direction {
+        self.dir
+    }
+
+    /// The internal indices of the vertices adjacent to the vertex of internal index `index`,
+    /// which are empty if the vertex is not in the graph
+    #[inline]
+    pub fn neighbors(&self, index: NodeIndex<I>) -> &[NodeIndex<I>] {
+        let i = index.index();
+        if i + 1 < self.offsets.len() {
+            &self.neighbors[self.offsets[i]..self.offsets[i + 1]]
+        } else {
+            &[]
+        }
+    }
+
+    /// The edges the vertices of `neighbors(index)` are adjacent by, in the same order
+    #[inline]
+    pub fn edges(&self, index: NodeIndex<I>) -> &[EdgeIndex<I>] {
+        let i = index.index();
+        if i + 1 < self.offsets.len() {
+            &self.edges[self.offsets[i]..self.offsets[i + 1]]
+        } else {
+            &[]
+        }
+    }
+
+    /// The number of edges in the segment
+    pub fn edge_count(&self) -> usize {
+        self.neighbors.len()
+    }
+}
+
+/// The segments of the labels in each direction
+type SegmentMap<I> = HashMap<(LabelId, Direction), Arc<AdjSegment<I>>>;
+
+/// The segments of a graph built so far
+pub(crate) struct AdjSegments<I: IndexType> {
+    segments: RwLock<SegmentMap<I>>,
+}
+
+impl<I: IndexType> Default for AdjSegments<I> {
+    fn default() -> Self {
+        AdjSegments { segments: RwLock::new(HashMap::new()) }
+    }
+}
+
+impl<I: IndexType> AdjSegments<I> {
+    /// Get the segment of the label in the direction, which is built by `build` if not yet
+    pub(crate) fn get_or_build<F>(
+        &self, label: LabelId, dir: Direction, build: F,
+    ) -> Arc<AdjSegment<I>>
+    where
+        F: FnOnce() -> AdjSegment<I>,
+    {
+        if let Some(segment) = self.segments.read().expect("lock poisoned").get(&(label, dir)) {
+            return segment.clone();
+        }
+        let mut segments = self.segments.write().expect("lock poisoned");
+        segments
+            .entry((label, dir))
+            .or_insert_with(|| {
+                debug!("Build the adjacency segment of edge label {} of {:?}", label, dir);
+                Arc::new(build())
+            })
+            .clone()
+    }
+}
@@ -0,0 +1,564 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! A portable snapshot of a `LargeGraphDB`, which serializes one partition of the graph
+//! into a single file. The file is formatted as:
+//!
+//! ```text
+//! | magic (8 bytes) | major version (u16) | minor version (u16) | number of sections (u32) |
+//! | section kind (u32) | section length (u64) | section crc32 (u32) | section data |
+//! | ...                                                                              |
+//! ```
+//!
+//! where all numbers are little-endian, and each section's data is encoded using `bincode`.
+//!
+//! A snapshot with a higher major version, or the same major version but a higher minor
+//! version, than `SNAPSHOT_VERSION` is refused with `GDBError::SnapshotVersionError`. Within a
+//! major version, a newer minor version may only add sections, so that the snapshots of all the
+//! older minor versions are imported, with what the missing sections record falling back to the
+//! defaults or being recomputed. The revisions are:
+//! * 1.0: the schema, graph structure, vertex/edge properties and index data.
+//! * 1.1: add the meta section, which records the partition of the graph.
+//! * 1.2: add the statistics section, which are otherwise recomputed while importing.
+//! * 1.3: add the adjacency order section, which records the edge labels stored sorted by their
+//!   properties, see `LDBCGraphSchema::get_adj_order`.
+//! * 1.4: add the strict section, which records whether the graph is loaded in the strict schema
+//!   mode, see `LDBCGraphSchema::is_strict`.
+//! * 1.5: the statistics record the hash of their sketches, see `SketchHash`, whose sketches of
+//!   earlier versions are hashed by an unstable hash, and are recomputed while importing.
+//!
+//! The snapshot is imported from a memory-mapped file, from which the sections are verified and
+//! decoded without being read into a buffer first. The sections are not served from the map in
+//! place, however: the adjacency of the graph is a `petgraph` graph and the property tables are
+//! any `PropertyTableTrait`, all of which own their data, so each section is decoded into the
+//! graph, which is independent of the file once imported. The property tables served from maps
+//! are exported and opened by columns instead, see `ColumnTable`.
+
+use crate::common::Label;
+use crate::common::LabelId;
+use crate::error::{GDBError, GDBResult};
+use crate::graph_db_impl::{IndexData, LargeGraphDB};
+use crate::schema::{AdjOrder, GraphSchemaInfo, LDBCGraphSchema};
+use crate::statistics::{GraphStatistics, SKETCH_HASH};
+use crate::table::PropertyTableTrait;
+use memmap::Mmap;
+use petgraph::graph::{DiGraph, IndexType};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+/// The magic number that a snapshot file starts with
+pub const SNAPSHOT_MAGIC: &[u8; 8] = b"GAIASNAP";
+/// The (major, minor) version of the snapshot format written by this crate
+pub const SNAPSHOT_VERSION: (u16, u16) = (1, 5);
+
+const FILE_HEADER_SIZE: usize = 16;
+const SECTION_HEADER_SIZE: usize = 16;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(u32)]
+enum SectionKind {
+    Schema = 1,
+    Graph = 2,
+    VertexProperty = 3,
+    EdgeProperty = 4,
+    Index = 5,
+    // since 1.1
+    Meta = 6,
+    // since 1.2
+    Statistics = 7,
+    // since 1.3
+    AdjOrder = 8,
+    // since 1.4
+    Strict = 9,
+}
+
+impl SectionKind {
+    fn name(kind: u32) -> &'static str {
+        match kind {
+            1 => "schema",
+            2 => "graph",
+            3 => "vertex_property",
+            4 => "edge_property",
+            5 => "index",
+            6 => "meta",
+            7 => "statistics",
+            8 => "adj_order",
+            9 => "strict",
+            _ => "unknown",
+        }
+    }
+}
+
+/// The meta data of the snapshot, since 1.1
+#[derive(Serialize, Deserialize)]
+struct SnapshotMeta {
+    partition: usize,
+}
+
+/// A writer that computes the length and crc32 of all the written data
+struct CrcWriter<W> {
+    inner: W,
+    hasher: crc32fast::Hasher,
+    len: u64,
+}
+
+impl<W: Write> Write for CrcWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let size = self.inner.write(buf)?;
+        self.hasher.update(&buf[..size]);
+        self.len += size as u64;
+        Ok(size)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Write a snapshot section, by reserving the section header, streaming the data and then
+/// filling the header, so that the data needs not to be maintained in memory as a whole.
+fn write_section<W: Write + Seek, T: Serialize>(
+    writer: &mut W, kind: SectionKind, obj: &T,
+) -> GDBResult<()> {
+    let start = writer.stream_position()?;
+    writer.write_all(&[0_u8; SECTION_HEADER_SIZE])?;
+    let mut crc_writer =
+        CrcWriter { inner: &mut *writer, hasher: crc32fast::Hasher::new(), len: 0 };
+    bincode::serialize_into(&mut crc_writer, obj)?;
+    let len = crc_writer.len;
+    let crc = crc_writer.hasher.finalize();
+
+    let end = writer.stream_position()?;
+    writer.seek(SeekFrom::Start(start))?;
+    writer.write_all(&(kind as u32).to_le_bytes())?;
+    writer.write_all(&len.to_le_bytes())?;
+    writer.write_all(&crc.to_le_bytes())?;
+    writer.seek(SeekFrom::Start(end))?;
+
+    Ok(())
+}
+
+fn write_file_header<W: Write>(
+    writer: &mut W, version: (u16, u16), sections: u32,
+) -> GDBResult<()> {
+    writer.write_all(SNAPSHOT_MAGIC)?;
+    writer.write_all(&version.0.to_le_bytes())?;
+    writer.write_all(&version.1.to_le_bytes())?;
+    writer.write_all(&sections.to_le_bytes())?;
+    Ok(())
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    let mut buf = [0_u8; 2];
+    buf.copy_from_slice(&bytes[offset..offset + 2]);
+    u16::from_le_bytes(buf)
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    let mut buf = [0_u8; 4];
+    buf.copy_from_slice(&bytes[offset..offset + 4]);
+    u32::from_le_bytes(buf)
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    let mut buf = [0_u8; 8];
+    buf.copy_from_slice(&bytes[offset..offset + 8]);
+    u64::from_le_bytes(buf)
+}
+
+/// The sections of a snapshot whose checksums have been verified
+struct SnapshotSections<'a> {
+    version: (u16, u16),
+    sections: Vec<(u32, &'a [u8])>,
+}
+
+impl<'a> SnapshotSections<'a> {
+    /// Parse the file header and section headers, and verify the checksums of all sections.
+    fn parse(bytes: &'a [u8]) -> GDBResult<Self> {
+        if bytes.len() < FILE_HEADER_SIZE || &bytes[0..8] != SNAPSHOT_MAGIC {
+            return Err(GDBError::CorruptedSnapshotError("invalid magic header".to_string()));
+        }
+        let version = (read_u16(bytes, 8), read_u16(bytes, 10));
+        if version.0 != SNAPSHOT_VERSION.0 || version.1 > SNAPSHOT_VERSION.1 {
+            return Err(GDBError::SnapshotVersionError(version.0, version.1));
+        }
+        let num_sections = read_u32(bytes, 12);
+
+        // the number of sections is untrusted, which is bounded by the section headers the file
+        // may hold at most
+        let max_sections = (bytes.len() - FILE_HEADER_SIZE) / SECTION_HEADER_SIZE;
+        let mut sections = Vec::with_capacity(std::cmp::min(num_sections as usize, max_sections));
+        let mut offset = FILE_HEADER_SIZE;
+        for _ in 0..num_sections {
+            if offset + SECTION_HEADER_SIZE > bytes.len() {
+                return Err(GDBError::CorruptedSnapshotError(
+                    "truncated section header".to_string(),
+                ));
+            }
+            let kind = read_u32(bytes, offset);
+            let len = read_u64(bytes, offset + 4) as usize;
+            let crc = read_u32(bytes, offset + 12);
+            offset += SECTION_HEADER_SIZE;
+            if len > bytes.len() - offset {
+                return Err(GDBError::CorruptedSnapshotError(format!(
+                    "truncated section {}",
+                    SectionKind::name(kind)
+                )));
+            }
+            let data = &bytes[offset..offset + len];
+            if crc32fast::hash(data) != crc {
+                return Err(GDBError::CorruptedSnapshotError(format!(
+                    "checksum mismatch in section {}",
+                    SectionKind::name(kind)
+                )));
+            }
+            sections.push((kind, data));
+            offset += len;
+        }
+
+        Ok(Self { version, sections })
+    }
+
+    fn get(&self, kind: SectionKind) -> Option<&'a [u8]> {
+        self.sections.iter().find(|(k, _)| *k == kind as u32).map(|(_, data)| *data)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, kind: SectionKind) -> GDBResult<T> {
+        let data = self.get(kind).ok_or_else(|| {
+            GDBError::CorruptedSnapshotError(format!(
+                "missing section {}",
+                SectionKind::name(kind as u32)
+            ))
+        })?;
+        Ok(bincode::deserialize(data)?)
+    }
+}
+
+impl<G, I, N, E> LargeGraphDB<G, I, N, E>
+where
+    G: Eq + IndexType + Serialize + DeserializeOwned + Send + Sync,
+    I: IndexType + Serialize + DeserializeOwned + Send + Sync,
+    N: PropertyTableTrait + Serialize + DeserializeOwned + Sync,
+    E: PropertyTableTrait + Serialize + DeserializeOwned + Sync,
+{
+    /// Export this partition of graph into a single snapshot file at `path`,
+    /// see the module-level document for the format.
+    pub fn export<P: AsRef<Path>>(&self, path: P) -> GDBResult<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        write_file_header(&mut writer, SNAPSHOT_VERSION, 9)?;
+        write_section(&mut writer, SectionKind::Meta, &SnapshotMeta { partition: self.partition })?;
+        write_section(&mut writer, SectionKind::Schema, self.graph_schema.as_ref())?;
+        write_section(&mut writer, SectionKind::Graph, &self.graph)?;
+        write_section(&mut writer, SectionKind::VertexProperty, &self.vertex_prop_table)?;
+        write_section(&mut writer, SectionKind::EdgeProperty, &self.edge_prop_table)?;
+        write_section(&mut writer, SectionKind::Index, &self.index_data)?;
+        write_section(&mut writer, SectionKind::Statistics, self.statistics.as_ref())?;
+        write_section(&mut writer, SectionKind::AdjOrder, self.graph_schema.adj_orders())?;
+        write_section(&mut writer, SectionKind::Strict, &self.graph_schema.is_strict())?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// Import a snapshot file exported by `Self::export()`, whose sections are decoded from the
+    /// mapped file into the graph, see the module-level document.
+    ///
+    /// Return `GDBError::SnapshotVersionError` if the snapshot is written by a newer version,
+    /// and `GDBError::CorruptedSnapshotError` if the snapshot is corrupted.
+    pub fn import<P: AsRef<Path>>(path: P) -> GDBResult<Self> {
+        let file = File::open(path)?;
+        // Safety: the snapshot file is read-only once exported
+        let mmap = unsafe { Mmap::map(&file)? };
+        let sections = SnapshotSections::parse(&mmap[..])?;
+
+        let partition = if sections.get(SectionKind::Meta).is_some() {
+            sections.decode::<SnapshotMeta>(SectionKind::Meta)?.partition
+        } else {
+            // the partition is not recorded before 1.1
+            0
+        };
+        let mut graph_schema = sections.decode::<LDBCGraphSchema>(SectionKind::Schema)?;
+        // the edges are stored in the order they are added before 1.3
+        if sections.get(SectionKind::AdjOrder).is_some() {
+            graph_schema.set_adj_orders(
+                sections.decode::<HashMap<LabelId, AdjOrder>>(SectionKind::AdjOrder)?,
+            );
+        }
+        // the graphs are all lenient before 1.4
+        if sections.get(SectionKind::Strict).is_some() {
+            graph_schema.set_strict(sections.decode::<bool>(SectionKind::Strict)?);
+        }
+        let graph = sections.decode::<DiGraph<Label, LabelId, I>>(SectionKind::Graph)?;
+        let vertex_prop_table = sections.decode::<N>(SectionKind::VertexProperty)?;
+        let edge_prop_table = sections.decode::<E>(SectionKind::EdgeProperty)?;
+        let index_data = sections.decode::<IndexData<G, I>>(SectionKind::Index)?;
+        debug!("Import snapshot of version {:?}", sections.version);
+
+        let schema_info = Arc::new(GraphSchemaInfo::compute(&graph, &graph_schema));
+        let mut graph = LargeGraphDB {
+            partition,
+            graph,
+            graph_schema: Arc::new(graph_schema),
+            vertex_prop_table,
+            edge_prop_table,
+            index_data,
+            statistics: Arc::new(GraphStatistics::default()),
+            schema_info,
+            segments: Default::default(),
+        };
+        // the statistics are not recorded before 1.2, nor of a stable hash before 1.5
+        let statistics =
+            if sections.get(SectionKind::Statistics).is_some() && sections.version >= (1, 5) {
+                Some(sections.decode::<GraphStatistics>(SectionKind::Statistics)?)
+            } else {
+                None
+            };
+        match statistics {
+            Some(statistics) if statistics.sketch_hash() == SKETCH_HASH => {
+                graph.statistics = Arc::new(statistics)
+            }
+            _ => graph.recompute_statistics(),
+        }
+
+        Ok(graph)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::common::{DefaultId, InternalId};
+    use crate::graph_db::{GlobalStoreTrait, LocalEdge, LocalVertex};
+    use crate::ldbc::GraphLoader;
+    use crate::schema::Schema;
+    use crate::table::ItemType;
+    use petgraph::Direction;
+    use std::collections::HashMap;
+
+    fn load_graph() -> LargeGraphDB<DefaultId, InternalId> {
+        let mut loader = GraphLoader::<DefaultId, InternalId>::new(
+            "data/large_data",
+            "data/large_data",
+            "data/schema.json",
+            20,
+            0,
+            1,
+        );
+        loader.load().expect("Load graph error");
+        loader.into_graph()
+    }
+
+    fn vertex_to_tuple(v: LocalVertex<DefaultId>) -> (DefaultId, Label, Vec<(String, ItemType)>) {
+        let mut properties = v
+            .clone_all_properties()
+            .unwrap_or_default()
+            .into_iter()
+            .collect::<Vec<(String, ItemType)>>();
+        properties.sort_by(|p1, p2| p1.0.cmp(&p2.0));
+        (v.get_id(), v.get_label(), properties)
+    }
+
+    fn edge_to_tuple(
+        e: LocalEdge<DefaultId, InternalId>,
+    ) -> (DefaultId, DefaultId, LabelId, Option<ItemType>) {
+        (
+            e.get_src_id(),
+            e.get_dst_id(),
+            e.get_label(),
+            e.get_property("creationDate").map(|p| p.try_to_owned().unwrap()),
+        )
+    }
+
+    fn assert_graph_eq(
+        g1: &LargeGraphDB<DefaultId, InternalId>, g2: &LargeGraphDB<DefaultId, InternalId>,
+    ) {
+        assert_eq!(g1.get_current_partition(), g2.get_current_partition());
+        let vertices1 = g1.get_all_vertices(None).map(vertex_to_tuple).collect::<Vec<_>>();
+        let vertices2 = g2.get_all_vertices(None).map(vertex_to_tuple).collect::<Vec<_>>();
+        assert_eq!(vertices1, vertices2);
+        let edges1 = g1.get_all_edges(None).map(edge_to_tuple).collect::<Vec<_>>();
+        let edges2 = g2.get_all_edges(None).map(edge_to_tuple).collect::<Vec<_>>();
+        assert_eq!(edges1, edges2);
+        for (id, _, _) in vertices1 {
+            let out1 = g1.get_out_edges(id, None).map(edge_to_tuple).collect::<Vec<_>>();
+            let out2 = g2.get_out_edges(id, None).map(edge_to_tuple).collect::<Vec<_>>();
+            assert_eq!(out1, out2);
+            let in1 = g1.get_in_vertices(id, None).map(|v| v.get_id()).collect::<Vec<_>>();
+            let in2 = g2.get_in_vertices(id, None).map(|v| v.get_id()).collect::<Vec<_>>();
+            assert_eq!(in1, in2);
+        }
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let temp = tempdir::TempDir::new("test_snapshot").expect("Open temp folder error");
+        let path = temp.path().join("graph.snapshot");
+        let graph = load_graph();
+        graph.export(&path).expect("Export snapshot error");
+        let imported: LargeGraphDB<DefaultId, InternalId> =
+            LargeGraphDB::import(&path).expect("Import snapshot error");
+
+        assert_eq!(18, imported.count_all_vertices(None));
+        assert_eq!(18, imported.count_all_edges(None));
+        assert_graph_eq(&graph, &imported);
+        assert_eq!(
+            graph.get_statistics().avg_out_degree(LabelId::from(1), LabelId::from(12)),
+            imported.get_statistics().avg_out_degree(LabelId::from(1), LabelId::from(12))
+        );
+    }
+
+    #[test]
+    fn test_snapshot_adj_order() {
+        let temp = tempdir::TempDir::new("test_snapshot_order").expect("Open temp folder error");
+        let path = temp.path().join("graph.snapshot");
+        let mut graph = load_graph();
+        let mut schema = graph.graph_schema.as_ref().clone();
+        let order = AdjOrder { property: "creationDate".to_string(), descending: true };
+        schema.set_adj_order("KNOWS", order.clone()).expect("Set adjacency order error");
+        graph.graph_schema = Arc::new(schema);
+        graph.export(&path).expect("Export snapshot error");
+        let imported: LargeGraphDB<DefaultId, InternalId> =
+            LargeGraphDB::import(&path).expect("Import snapshot error");
+
+        let knows = imported.graph_schema.get_edge_label_id("KNOWS").unwrap();
+        assert_eq!(imported.get_adj_order(knows), Some(&order));
+        assert_eq!(imported.graph_schema, graph.graph_schema);
+        let segment = graph.get_adj_segment(knows, Direction::Outgoing).unwrap();
+        let imported_segment = imported.get_adj_segment(knows, Direction::Outgoing).unwrap();
+        for v in graph.get_all_vertices(None) {
+            let edges = graph
+                .get_segment_edges(segment.clone(), v.get_id())
+                .map(edge_to_tuple)
+                .collect::<Vec<_>>();
+            let imported_edges = imported
+                .get_segment_edges(imported_segment.clone(), v.get_id())
+                .map(edge_to_tuple)
+                .collect::<Vec<_>>();
+            assert_eq!(edges, imported_edges);
+        }
+    }
+
+    #[test]
+    fn test_snapshot_strict() {
+        let temp = tempdir::TempDir::new("test_snapshot_strict").expect("Open temp folder error");
+        let path = temp.path().join("graph.snapshot");
+        let mut graph = load_graph();
+        let mut schema = graph.graph_schema.as_ref().clone();
+        schema.set_strict(true);
+        graph.graph_schema = Arc::new(schema);
+        graph.export(&path).expect("Export snapshot error");
+        let imported: LargeGraphDB<DefaultId, InternalId> =
+            LargeGraphDB::import(&path).expect("Import snapshot error");
+
+        assert!(imported.graph_schema.is_strict());
+        assert!(imported.schema().strict);
+    }
+
+    #[test]
+    fn test_snapshot_minor_version_compatible() {
+        let temp = tempdir::TempDir::new("test_snapshot_1_0").expect("Open temp folder error");
+        let path = temp.path().join("graph.snapshot");
+        let graph = load_graph();
+        // A snapshot of version 1.0 has no meta section
+        {
+            let mut writer = BufWriter::new(File::create(&path).unwrap());
+            write_file_header(&mut writer, (1, 0), 5).unwrap();
+            write_section(&mut writer, SectionKind::Schema, graph.graph_schema.as_ref()).unwrap();
+            write_section(&mut writer, SectionKind::Graph, &graph.graph).unwrap();
+            write_section(&mut writer, SectionKind::VertexProperty, &graph.vertex_prop_table)
+                .unwrap();
+            write_section(&mut writer, SectionKind::EdgeProperty, &graph.edge_prop_table).unwrap();
+            write_section(&mut writer, SectionKind::Index, &graph.index_data).unwrap();
+            writer.flush().unwrap();
+        }
+        let imported: LargeGraphDB<DefaultId, InternalId> =
+            LargeGraphDB::import(&path).expect("Import snapshot error");
+
+        assert_graph_eq(&graph, &imported);
+        // the statistics are recomputed
+        assert_eq!(
+            imported.get_statistics().edge_count(LabelId::from(12)),
+            graph.get_statistics().edge_count(LabelId::from(12))
+        );
+    }
+
+    #[test]
+    fn test_snapshot_newer_version() {
+        let temp = tempdir::TempDir::new("test_snapshot_newer").expect("Open temp folder error");
+        let path = temp.path().join("graph.snapshot");
+        load_graph().export(&path).expect("Export snapshot error");
+
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[10..12].copy_from_slice(&(SNAPSHOT_VERSION.1 + 1).to_le_bytes());
+        std::fs::write(&path, &bytes).unwrap();
+        match LargeGraphDB::<DefaultId, InternalId>::import(&path) {
+            Err(GDBError::SnapshotVersionError(1, minor)) => {
+                assert_eq!(minor, SNAPSHOT_VERSION.1 + 1)
+            }
+            _ => panic!("expect a version error"),
+        }
+
+        bytes[8..10].copy_from_slice(&(SNAPSHOT_VERSION.0 + 1).to_le_bytes());
+        std::fs::write(&path, &bytes).unwrap();
+        assert!(LargeGraphDB::<DefaultId, InternalId>::import(&path).is_err());
+    }
+
+    #[test]
+    fn test_snapshot_corrupted() {
+        let temp =
+            tempdir::TempDir::new("test_snapshot_corrupted").expect("Open temp folder error");
+        let path = temp.path().join("graph.snapshot");
+        load_graph().export(&path).expect("Export snapshot error");
+
+        let bytes = std::fs::read(&path).unwrap();
+        // flip one byte in the last section
+        let mut corrupted = bytes.clone();
+        let last = corrupted.len() - 1;
+        corrupted[last] ^= 0xff;
+        std::fs::write(&path, &corrupted).unwrap();
+        match LargeGraphDB::<DefaultId, InternalId>::import(&path) {
+            Err(GDBError::CorruptedSnapshotError(_)) => {}
+            _ => panic!("expect a corrupted snapshot error"),
+        }
+
+        // truncate the file
+        std::fs::write(&path, &bytes[..bytes.len() / 2]).unwrap();
+        match LargeGraphDB::<DefaultId, InternalId>::import(&path) {
+            Err(GDBError::CorruptedSnapshotError(_)) => {}
+            _ => panic!("expect a corrupted snapshot error"),
+        }
+
+        // a number of sections far more than the file holds
+        let mut corrupted = bytes.clone();
+        corrupted[12..16].copy_from_slice(&u32::MAX.to_le_bytes());
+        std::fs::write(&path, &corrupted).unwrap();
+        match LargeGraphDB::<DefaultId, InternalId>::import(&path) {
+            Err(GDBError::CorruptedSnapshotError(_)) => {}
+            _ => panic!("expect a corrupted snapshot error"),
+        }
+
+        // not a snapshot file at all
+        std::fs::write(&path, b"not a snapshot").unwrap();
+        match LargeGraphDB::<DefaultId, InternalId>::import(&path) {
+            Err(GDBError::CorruptedSnapshotError(_)) => {}
+            _ => panic!("expect a corrupted snapshot error"),
+        }
+    }
+}
@@ -0,0 +1,416 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The statistics of a graph, which are used for estimating the cardinalities while
+//! building a query plan.
+
+use crate::common::{Label, LabelId};
+use crate::graph_db_impl::IndexData;
+use crate::parser::DataType;
+use crate::schema::{LDBCGraphSchema, Schema};
+use crate::table::{ItemTypeRef, PropertyTableTrait};
+use petgraph::graph::{DiGraph, IndexType};
+use petgraph::visit::EdgeRef;
+use std::collections::HashMap;
+
+/// The number of bits of the hash value used to select a register of `HyperLogLog`
+const HLL_PRECISION: u32 = 12;
+const HLL_REGISTERS: usize = 1 << HLL_PRECISION;
+
+/// The hash of the values inserted into the sketches of `HyperLogLog`, which is recorded along with
+/// the persisted statistics, as the sketches of different hashes can be neither merged nor updated
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SketchHash {
+    /// `Object::stable_hash`, namely the XXH64 with the seed `STABLE_HASH_SEED` of the canonical
+    /// bytes of the values
+    StableXxh64,
+}
+
+/// The hash of the sketches computed by this crate
+pub const SKETCH_HASH: SketchHash = SketchHash::StableXxh64;
+
+impl Default for SketchHash {
+    fn default() -> Self {
+        SKETCH_HASH
+    }
+}
+
+/// A HyperLogLog sketch for approximately counting the distinct values, of which
+/// the standard error is around `1.04 / sqrt(HLL_REGISTERS)`, namely 1.6%.
+///
+/// The values are hashed by `SKETCH_HASH`, which is identical across platforms and versions, so
+/// that a persisted sketch can keep being updated, and sketches of different partitions can be
+/// merged.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HyperLogLog {
+    registers: Vec<u8>,
+}
+
+impl Default for HyperLogLog {
+    fn default() -> Self {
+        HyperLogLog { registers: vec![0; HLL_REGISTERS] }
+    }
+}
+
+impl HyperLogLog {
+    pub fn insert(&mut self, value: ItemTypeRef) {
+        let hash = value.stable_hash();
+        let index = (hash >> (64 - HLL_PRECISION)) as usize;
+        // the position of the leftmost 1-bit of the remaining bits, with a sentinel bit
+        // to bound the rank when all remaining bits are 0
+        let rank = ((hash << HLL_PRECISION) | (1 << (HLL_PRECISION - 1))).leading_zeros() + 1;
+        if self.registers[index] < rank as u8 {
+            self.registers[index] = rank as u8;
+        }
+    }
+
+    /// Merge another sketch into this one, after which this sketch estimates the
+    /// distinct count of the union of both
+    pub fn merge(&mut self, other: &HyperLogLog) {
+        for (r1, r2) in self.registers.iter_mut().zip(other.registers.iter()) {
+            if *r1 < *r2 {
+                *r1 = *r2;
+            }
+        }
+    }
+
+    pub fn estimate(&self) -> usize {
+        let m = HLL_REGISTERS as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum: f64 = self.registers.iter().map(|&r| 2.0_f64.powi(-(r as i32))).sum();
+        let raw = alpha * m * m / sum;
+        let zeros = self.registers.iter().filter(|&&r| r == 0).count();
+        if raw <= 2.5 * m && zeros > 0 {
+            // linear counting for small cardinalities
+            (m * (m / zeros as f64).ln()).round() as usize
+        } else {
+            raw.round() as usize
+        }
+    }
+}
+
+/// The statistics of (a partition of) a graph. Only the local vertices and the edges starting
+/// from (for out-degree) or ending at (for in-degree) the local vertices are counted, so that
+/// the statistics of all partitions can be merged via `Self::merge()` without double counting.
+///
+/// A vertex with a hierarchical label is counted for both of its labels.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct GraphStatistics {
+    /// The hash of the sketches of `distinct_values`
+    sketch_hash: SketchHash,
+    vertex_count: HashMap<LabelId, usize>,
+    edge_count: HashMap<LabelId, usize>,
+    /// The number of edges keyed by (label of the source vertex, label of the edge)
+    out_edge_count: HashMap<(LabelId, LabelId), usize>,
+    /// The number of edges keyed by (label of the target vertex, label of the edge)
+    in_edge_count: HashMap<(LabelId, LabelId), usize>,
+    /// The sketch of the distinct values keyed by (vertex label, property name), of the indexed
+    /// properties only, namely the ids looked up by the index data
+    distinct_values: HashMap<(LabelId, String), HyperLogLog>,
+}
+
+fn label_ids(label: &Label) -> impl Iterator<Item = LabelId> {
+    let label = *label;
+    std::iter::once(label[0]).chain(label[1].valid())
+}
+
+impl GraphStatistics {
+    /// Compute the statistics from the components of a graph, where `schema` must be trimmed
+    /// in order to match the stored properties.
+    pub(crate) fn compute<G, I, N>(
+        graph: &DiGraph<Label, LabelId, I>, index_data: &IndexData<G, I>, vertex_prop_table: &N,
+        schema: &LDBCGraphSchema,
+    ) -> Self
+    where
+        G: IndexType + Send + Sync,
+        I: IndexType + Send + Sync,
+        N: PropertyTableTrait,
+    {
+        let mut stats = GraphStatistics::default();
+        let is_local = |index| {
+            index_data
+                .get_global_id(index)
+                .map(|gid| index_data.global_id_to_index.contains_key(&gid))
+                .unwrap_or(false)
+        };
+
+        for (label_id, indices) in index_data.label_indices.iter().enumerate() {
+            if !indices.is_empty() {
+                stats.vertex_count.insert(LabelId::from(label_id as u8), indices.len());
+            }
+        }
+
+        for edge in graph.edge_references() {
+            let edge_label = *edge.weight();
+            if is_local(edge.source()) {
+                *stats.edge_count.entry(edge_label).or_insert(0) += 1;
+                if let Some(label) = graph.node_weight(edge.source()) {
+                    for src_label in label_ids(label) {
+                        *stats.out_edge_count.entry((src_label, edge_label)).or_insert(0) += 1;
+                    }
+                }
+            }
+            if is_local(edge.target()) {
+                if let Some(label) = graph.node_weight(edge.target()) {
+                    for dst_label in label_ids(label) {
+                        *stats.in_edge_count.entry((dst_label, edge_label)).or_insert(0) += 1;
+                    }
+                }
+            }
+        }
+
+        for index in index_data.global_id_to_index.values() {
+            let label = match graph.node_weight(*index) {
+                Some(label) => label,
+                None => continue,
+            };
+            let row = match vertex_prop_table.get_row(index.index()) {
+                Ok(row) => row,
+                Err(_) => continue,
+            };
+            for label_id in label_ids(label) {
+                if let Some(header) = schema.get_vertex_header(label_id) {
+                    for (field_index, (name, data_type)) in header.iter().enumerate() {
+                        if *data_type != DataType::ID {
+                            continue;
+                        }
+                        if let Some(value) = row.get(field_index) {
+                            stats
+                                .distinct_values
+                                .entry((label_id, name.clone()))
+                                .or_insert_with(HyperLogLog::default)
+                                .insert(value);
+                        }
+                    }
+                }
+            }
+        }
+
+        stats
+    }
+
+    /// Merge the statistics of another partition into this one
+    pub fn merge(&mut self, other: &GraphStatistics) {
+        for (label, count) in &other.vertex_count {
+            *self.vertex_count.entry(*label).or_insert(0) += *count;
+        }
+        for (label, count) in &other.edge_count {
+            *self.edge_count.entry(*label).or_insert(0) += *count;
+        }
+        for (key, count) in &other.out_edge_count {
+            *self.out_edge_count.entry(*key).or_insert(0) += *count;
+        }
+        for (key, count) in &other.in_edge_count {
+            *self.in_edge_count.entry(*key).or_insert(0) += *count;
+        }
+        if self.sketch_hash != other.sketch_hash {
+            // the union of the sketches of different hashes is unknown
+            self.distinct_values.clear();
+            return;
+        }
+        for (key, hll) in &other.distinct_values {
+            self.distinct_values.entry(key.clone()).or_default().merge(hll);
+        }
+    }
+
+    /// The hash of the sketches of the distinct values, which must be `SKETCH_HASH` for the
+    /// statistics to be updated or merged with those computed by this crate
+    pub fn sketch_hash(&self) -> SketchHash {
+        self.sketch_hash
+    }
+
+    /// The number of vertices of the given label
+    pub fn vertex_count(&self, label: LabelId) -> usize {
+        self.vertex_count.get(&label).cloned().unwrap_or(0)
+    }
+
+    /// The number of all vertices
+    pub fn total_vertex_count(&self) -> usize {
+        self.vertex_count.values().sum()
+    }
+
+    /// The number of edges of the given label
+    pub fn edge_count(&self, label: LabelId) -> usize {
+        self.edge_count.get(&label).cloned().unwrap_or(0)
+    }
+
+    /// The number of all edges
+    pub fn total_edge_count(&self) -> usize {
+        self.edge_count.values().sum()
+    }
+
+    /// The average number of outgoing edges of label `edge_label` from a vertex of label
+    /// `src_label`, or 0.0 if there is no such vertex.
+    pub fn avg_out_degree(&self, src_label: LabelId, edge_label: LabelId) -> f64 {
+        let count = self.out_edge_count.get(&(src_label, edge_label)).cloned().unwrap_or(0);
+        Self::avg(count, self.vertex_count(src_label))
+    }
+
+    /// The average number of incoming edges of label `edge_label` to a vertex of label
+    /// `dst_label`, or 0.0 if there is no such vertex.
+    pub fn avg_in_degree(&self, dst_label: LabelId, edge_label: LabelId) -> f64 {
+        let count = self.in_edge_count.get(&(dst_label, edge_label)).cloned().unwrap_or(0);
+        Self::avg(count, self.vertex_count(dst_label))
+    }
+
+    /// The approximate number of distinct values of the property `prop` among the vertices of
+    /// label `label`, or `None` if no vertex of the label has the property, or it is not indexed.
+    pub fn distinct_count(&self, label: LabelId, prop: &str) -> Option<usize> {
+        self.distinct_values.get(&(label, prop.to_string())).map(|hll| hll.estimate())
+    }
+
+    fn avg(count: usize, num_vertices: usize) -> f64 {
+        if num_vertices == 0 {
+            0.0
+        } else {
+            count as f64 / num_vertices as f64
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::common::{DefaultId, InternalId, INVALID_LABEL_ID};
+    use crate::config::GraphDBConfig;
+    use crate::graph_db::{GlobalStoreTrait, GlobalStoreUpdate};
+    use crate::graph_db_impl::{LargeGraphDB, MutableGraphDB};
+    use crate::ldbc::GraphLoader;
+    use crate::table::{ItemType, Row};
+
+    fn assert_near(estimate: usize, expected: usize) {
+        let error = (estimate as f64 - expected as f64).abs();
+        assert!(error <= expected as f64 * 0.05, "estimate {} vs {}", estimate, expected);
+    }
+
+    #[test]
+    fn test_hll_estimate() {
+        for &n in &[0_usize, 10, 1000, 100_000] {
+            let mut hll = HyperLogLog::default();
+            for i in 0..n {
+                hll.insert(object!(i as i64).as_borrow());
+                // duplicates must not be counted
+                hll.insert(object!(i as i64).as_borrow());
+            }
+            assert_near(hll.estimate(), n);
+        }
+
+        let mut hll1 = HyperLogLog::default();
+        let mut hll2 = HyperLogLog::default();
+        for i in 0..20000_i64 {
+            hll1.insert(object!(i).as_borrow());
+            hll2.insert(object!(i + 10000).as_borrow());
+        }
+        hll1.merge(&hll2);
+        assert_near(hll1.estimate(), 30000);
+    }
+
+    #[test]
+    fn test_statistics_small_graph() {
+        let temp = tempdir::TempDir::new("test_statistics").expect("Open temp folder error");
+        let mut mut_graph: MutableGraphDB<DefaultId, InternalId> =
+            GraphDBConfig::default().root_dir(temp.path()).partition(0).new();
+        let schema = GraphDBConfig::default().schema_file("data/schema.json").schema().unwrap();
+
+        // 200 persons (label 1) with 50 distinct first names, person i knows person i+1 and i+2
+        let num_persons = 200;
+        for id in 0..num_persons {
+            let props: Vec<ItemType> = vec![
+                (id as u64).into(),
+                format!("name{}", id % 50).into(),
+                "last".into(),
+                "male".into(),
+            ];
+            mut_graph.add_vertex(id, [1.into(), INVALID_LABEL_ID]);
+            mut_graph.add_or_update_vertex_properties(id, Row::from(props)).unwrap();
+        }
+        for id in 0..num_persons - 2 {
+            mut_graph.add_edge(id, id + 1, 12.into());
+            mut_graph.add_edge(id, id + 2, 12.into());
+        }
+        // an extra vertex of another label
+        mut_graph.add_vertex(1000, [2.into(), INVALID_LABEL_ID]);
+        let graph = mut_graph.into_graph(schema);
+        let stats = graph.get_statistics();
+
+        assert_eq!(stats.vertex_count(1.into()), 200);
+        assert_eq!(stats.vertex_count(2.into()), 1);
+        assert_eq!(stats.vertex_count(3.into()), 0);
+        assert_eq!(stats.edge_count(12.into()), 396);
+        assert_eq!(stats.total_edge_count(), 396);
+        assert_eq!(stats.avg_out_degree(1.into(), 12.into()), 396.0 / 200.0);
+        assert_eq!(stats.avg_in_degree(1.into(), 12.into()), 396.0 / 200.0);
+        assert_eq!(stats.avg_out_degree(2.into(), 12.into()), 0.0);
+        assert_near(stats.distinct_count(1.into(), "id").unwrap(), 200);
+        // the properties not indexed are not sketched
+        assert_eq!(stats.distinct_count(1.into(), "firstName"), None);
+        assert_eq!(stats.distinct_count(1.into(), "birthday"), None);
+        assert_eq!(stats.distinct_count(2.into(), "id"), None);
+    }
+
+    #[test]
+    fn test_statistics_persist() {
+        let temp =
+            tempdir::TempDir::new("test_statistics_persist").expect("Open temp folder error");
+        let mut loader = GraphLoader::<DefaultId, InternalId>::new(
+            "data/large_data",
+            temp.path().to_str().unwrap(),
+            "data/schema.json",
+            20,
+            0,
+            1,
+        );
+        loader.load().expect("Load graph error");
+        let schema = GraphDBConfig::default().schema_file("data/schema.json").schema().unwrap();
+        let mut_graph = loader.into_mutable_graph();
+        mut_graph.export().expect("Export error");
+        mut_graph.export_statistics(&schema).expect("Export statistics error");
+        let expected = mut_graph.into_graph(schema).get_statistics();
+
+        let graph: LargeGraphDB<DefaultId, InternalId> = GraphDBConfig::default()
+            .root_dir(temp.path())
+            .schema_file("data/schema.json")
+            .partition(0)
+            .open()
+            .expect("Open graph error");
+        let stats = graph.get_statistics();
+        assert_eq!(stats.total_edge_count(), graph.count_all_edges(None));
+        for label in (0..20).map(LabelId::from) {
+            assert_eq!(stats.vertex_count(label), expected.vertex_count(label));
+            assert_eq!(stats.edge_count(label), expected.edge_count(label));
+        }
+        assert_eq!(stats.distinct_count(1.into(), "id"), expected.distinct_count(1.into(), "id"));
+        assert_eq!(stats.sketch_hash(), SKETCH_HASH);
+    }
+
+    #[test]
+    fn test_hll_stable_registers() {
+        // the registers of a persisted sketch must be the same as those computed by any later
+        // version, which are protected by the golden values of `Object::stable_hash`
+        let mut hll = HyperLogLog::default();
+        hll.insert(object!(1_i64).as_borrow());
+        hll.insert(object!("a").as_borrow());
+        let hash = object!(1_i64).stable_hash();
+        let index = (hash >> (64 - HLL_PRECISION)) as usize;
+        assert!(hll.registers[index] > 0);
+        assert_eq!(hll.registers.iter().filter(|&&r| r > 0).count(), 2);
+
+        let mut other = GraphStatistics::default();
+        other.distinct_values.insert((1.into(), "id".to_string()), hll);
+        let mut stats = GraphStatistics::default();
+        stats.merge(&other);
+        assert_eq!(stats.distinct_count(1.into(), "id"), Some(2));
+    }
+}
@@ -0,0 +1,299 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The strict schema mode, in which the declared type of every property is authoritative. The
+//! loaders check each value against the type of its property, and handle a mistyped value, e.g.
+//! of a column flipped from int to string between files, by the `TypeCoercion` of its column,
+//! instead of skipping its row as malformed. The loading is rejected by default, listing the
+//! mistyped values by `GDBError::TypeViolationError`.
+//!
+//! A graph loaded in the strict mode is marked so in its schema, see
+//! `LDBCGraphSchema::is_strict`, so that every value of a property is known to be of the type
+//! recorded for it, against which the constants of the queries are checked before running.
+
+use crate::common::LabelId;
+use crate::error::{GDBError, GDBResult};
+use crate::parser::{parse_properties, parse_value, DataType};
+use crate::schema::{LDBCGraphSchema, Schema};
+use crate::table::Row;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// The most mistyped values listed by `GDBError::TypeViolationError`
+pub const MAX_LISTED_TYPE_VIOLATIONS: usize = 100;
+
+/// How the loaders check the values against the declared types of their properties
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum SchemaMode {
+    /// Fail the row of a mistyped value as malformed, which is skipped and reported
+    #[default]
+    Lenient,
+    /// Handle a mistyped value by the `TypeCoercion` of its column, and mark the schema strict
+    Strict,
+}
+
+/// How the mistyped values of a column are handled in the strict schema mode
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub enum TypeCoercion {
+    /// Fail the loading, listing the mistyped values by `GDBError::TypeViolationError`
+    #[default]
+    Reject,
+    /// Replace the value by the zero of the type, i.e. 0, 0.0 or the empty string
+    Zero,
+    /// Replace the value by the given one, which must be of the type
+    Replace(String),
+}
+
+/// A value not of the declared type of its property
+#[derive(Clone, Debug, PartialEq)]
+pub struct TypeViolation {
+    /// The file that contains the value
+    pub file: PathBuf,
+    /// The line number of the row of the value, starting from 1
+    pub line: u64,
+    pub property: String,
+    pub expected: DataType,
+    pub value: String,
+}
+
+struct Collected {
+    num_violations: usize,
+    // the first mistyped values rejected, which are listed once the loading is rejected
+    listed: Vec<TypeViolation>,
+    num_coerced: usize,
+}
+
+/// The coercions of the columns, by the labels and the names of their properties
+type Coercions = HashMap<(LabelId, String), TypeCoercion>;
+
+/// Check the values against the types of their properties, and handle the mistyped ones by the
+/// schema mode, which is shared by the threads reading the raw files
+#[derive(Clone)]
+pub(crate) struct TypeChecker {
+    mode: SchemaMode,
+    vertex_coercions: Arc<Coercions>,
+    edge_coercions: Arc<Coercions>,
+    collected: Arc<Mutex<Collected>>,
+}
+
+impl TypeChecker {
+    /// Resolve the coercions given by (label, property), where the label is either a vertex type
+    /// or an edge type, e.g. "PERSON" or "KNOWS". The property must be of the label, and the value
+    /// replacing the mistyped ones must be of the type of the property.
+    pub(crate) fn new(
+        mode: SchemaMode, coercions: &[(String, String, TypeCoercion)], schema: &LDBCGraphSchema,
+    ) -> GDBResult<Self> {
+        let mut vertex_coercions = HashMap::new();
+        let mut edge_coercions = HashMap::new();
+        for (label, property, coercion) in coercions {
+            let (label_id, header, resolved) = if let Some(id) = schema.get_vertex_label_id(label) {
+                (id, schema.get_vertex_header(id), &mut vertex_coercions)
+            } else if let Some(id) = schema.get_edge_label_id(label) {
+                (id, schema.get_edge_header(id), &mut edge_coercions)
+            } else {
+                return Err(GDBError::InvalidTypeError);
+            };
+            let (field, ty) = header
+                .and_then(|header| header.iter().find(|(name, _)| name == property))
+                .ok_or(GDBError::FieldNotExistError)?;
+            if let TypeCoercion::Replace(value) = coercion {
+                parse_value(field, ty, value).map_err(|_| GDBError::InvalidTypeError)?;
+            }
+            resolved.insert((label_id, property.clone()), coercion.clone());
+        }
+        Ok(TypeChecker {
+            mode,
+            vertex_coercions: Arc::new(vertex_coercions),
+            edge_coercions: Arc::new(edge_coercions),
+            collected: Arc::new(Mutex::new(Collected {
+                num_violations: 0,
+                listed: vec![],
+                num_coerced: 0,
+            })),
+        })
+    }
+
+    /// Parse the properties of a vertex of the label, see `parse`
+    pub(crate) fn parse_vertex_properties<'a, Iter: Iterator<Item = &'a str>>(
+        &self, label: LabelId, record_iter: Iter, header: Option<&[(String, DataType)]>,
+        file: &Path, line: u64,
+    ) -> GDBResult<Option<Row>> {
+        self.parse(&self.vertex_coercions, label, record_iter, header, file, line)
+    }
+
+    /// Parse the properties of an edge of the label, see `parse`
+    pub(crate) fn parse_edge_properties<'a, Iter: Iterator<Item = &'a str>>(
+        &self, label: LabelId, record_iter: Iter, header: Option<&[(String, DataType)]>,
+        file: &Path, line: u64,
+    ) -> GDBResult<Option<Row>> {
+        self.parse(&self.edge_coercions, label, record_iter, header, file, line)
+    }
+
+    /// Parse the properties of a row, which fails as malformed by a mistyped value in the lenient
+    /// mode as `parse_properties` does. In the strict mode, the mistyped values are coerced, or
+    /// the row is rejected, which gives `None`.
+    fn parse<'a, Iter: Iterator<Item = &'a str>>(
+        &self, coercions: &Coercions, label: LabelId, record_iter: Iter,
+        header: Option<&[(String, DataType)]>, file: &Path, line: u64,
+    ) -> GDBResult<Option<Row>> {
+        if self.mode == SchemaMode::Lenient {
+            return parse_properties(record_iter, header).map(Some);
+        }
+        let mut properties = Row::default();
+        let header = match header {
+            Some(header) => header,
+            None => return Ok(Some(properties)),
+        };
+        let mut rejected = false;
+        for ((field, ty), val) in header.iter().zip(record_iter) {
+            let value = match parse_value(field, ty, val) {
+                Ok(value) => value,
+                Err(_) => {
+                    let coercion = coercions.get(&(label, field.clone()));
+                    let mut collected =
+                        self.collected.lock().map_err(|_| GDBError::UnknownError)?;
+                    match coercion.unwrap_or(&TypeCoercion::Reject) {
+                        TypeCoercion::Reject => {
+                            debug!("Mistyped value {:?} of {} at {:?}:{}", val, field, file, line);
+                            collected.num_violations += 1;
+                            if collected.listed.len() < MAX_LISTED_TYPE_VIOLATIONS {
+                                collected.listed.push(TypeViolation {
+                                    file: file.to_path_buf(),
+                                    line,
+                                    property: field.clone(),
+                                    expected: ty.clone(),
+                                    value: val.to_string(),
+                                });
+                            }
+                            rejected = true;
+                            continue;
+                        }
+                        TypeCoercion::Zero => {
+                            collected.num_coerced += 1;
+                            parse_value(field, ty, zero_of(ty))?
+                        }
+                        TypeCoercion::Replace(value) => {
+                            collected.num_coerced += 1;
+                            parse_value(field, ty, value)?
+                        }
+                    }
+                }
+            };
+            if let Some(value) = value {
+                properties.push(value);
+            }
+        }
+        Ok(if rejected { None } else { Some(properties) })
+    }
+
+    /// Finish checking the values, which fails by `GDBError::TypeViolationError` if any value is
+    /// rejected, or gives the number of the values coerced otherwise
+    pub(crate) fn finish(&self) -> GDBResult<usize> {
+        let collected = self.collected.lock().map_err(|_| GDBError::UnknownError)?;
+        if collected.num_violations > 0 {
+            let mut listed = collected.listed.clone();
+            listed.sort_by(|v1, v2| (&v1.file, v1.line).cmp(&(&v2.file, v2.line)));
+            Err(GDBError::TypeViolationError(collected.num_violations, listed))
+        } else {
+            Ok(collected.num_coerced)
+        }
+    }
+}
+
+/// The raw zero of the type, which is parsed as the type
+fn zero_of(ty: &DataType) -> &'static str {
+    match ty {
+        DataType::String => "",
+        DataType::Double => "0.0",
+        _ => "0",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::JsonConf;
+
+    fn schema() -> LDBCGraphSchema {
+        LDBCGraphSchema::from_json_file("data/schema.json").expect("Get schema error")
+    }
+
+    fn person_row(birthday: &str) -> String {
+        format!("111|Mahinda|Perera|male|{}|20100214153210447|119.235.7.103|Firefox", birthday)
+    }
+
+    #[test]
+    fn test_check_types() {
+        let schema = schema();
+        let person = schema.get_vertex_label_id("PERSON").unwrap();
+        let header = schema.get_vertex_header(person);
+        let file = Path::new("person.csv");
+        let coercions = vec![(
+            "PERSON".to_string(),
+            "birthday".to_string(),
+            TypeCoercion::Replace("19700101".to_string()),
+        )];
+
+        let lenient = TypeChecker::new(SchemaMode::Lenient, &coercions, &schema).unwrap();
+        let row = person_row("unknown");
+        assert!(lenient.parse_vertex_properties(person, row.split('|'), header, file, 1).is_err());
+        assert_eq!(lenient.finish().unwrap(), 0);
+
+        let coerce = TypeChecker::new(SchemaMode::Strict, &coercions, &schema).unwrap();
+        let coerced = coerce
+            .parse_vertex_properties(person, row.split('|'), header, file, 1)
+            .unwrap()
+            .unwrap();
+        let row = person_row("19700101");
+        let expected = parse_properties(row.split('|'), header).unwrap();
+        assert_eq!(coerced, expected);
+        assert_eq!(coerce.finish().unwrap(), 1);
+
+        let reject = TypeChecker::new(SchemaMode::Strict, &[], &schema).unwrap();
+        let row = person_row("unknown");
+        assert!(reject
+            .parse_vertex_properties(person, row.split('|'), header, file, 3)
+            .unwrap()
+            .is_none());
+        match reject.finish() {
+            Err(GDBError::TypeViolationError(1, listed)) => {
+                assert_eq!(listed[0].line, 3);
+                assert_eq!(listed[0].property, "birthday");
+                assert_eq!(listed[0].expected, DataType::Date);
+                assert_eq!(listed[0].value, "unknown");
+            }
+            _ => panic!("expect the mistyped birthday"),
+        }
+    }
+
+    #[test]
+    fn test_invalid_coercions() {
+        let schema = schema();
+        let coercion = |label: &str, property: &str, value: &str| {
+            let coercion = TypeCoercion::Replace(value.to_string());
+            TypeChecker::new(
+                SchemaMode::Strict,
+                &[(label.to_string(), property.to_string(), coercion)],
+                &schema,
+            )
+            .err()
+        };
+        assert!(matches!(coercion("UNKNOWN", "birthday", "0"), Some(GDBError::InvalidTypeError)));
+        assert!(matches!(coercion("PERSON", "unknown", "0"), Some(GDBError::FieldNotExistError)));
+        assert!(matches!(coercion("PERSON", "birthday", "x"), Some(GDBError::InvalidTypeError)));
+        assert!(coercion("KNOWS", "creationDate", "0").is_none());
+    }
+}
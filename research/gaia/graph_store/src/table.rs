@@ -13,6 +13,7 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
+use crate::column::ColumnRow;
 use crate::error::{GDBError, GDBResult};
 use dyn_type::{BorrowObject, Object};
 use pegasus_common::codec::{Decode, Encode};
@@ -154,6 +155,8 @@ pub enum RowRef<'a> {
     Ref(&'a Row),
     Owned(Row),
     Single(ItemType),
+    /// A row of a `ColumnTable`, whose values are decoded from the columns once accessed
+    Column(ColumnRow<'a>),
     None,
 }
 
@@ -163,6 +166,7 @@ impl<'a> RowRef<'a> {
             RowRef::Ref(row) => row.get(field_index),
             RowRef::Owned(row) => row.get(field_index),
             RowRef::Single(val) => Some(val.as_borrow()),
+            RowRef::Column(row) => row.get(field_index),
             RowRef::None => None,
         }
     }
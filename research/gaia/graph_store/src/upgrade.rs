@@ -0,0 +1,477 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The versioned layout of the directories of the graph data, see `GraphDBConfig`. Each partition
+//! records the version of its layout in the file `FILE_LAYOUT_VERSION`, written by
+//! `MutableGraphDB::export` as `LAYOUT_VERSION`, while a partition without the file is exported
+//! before the layout is versioned, namely of version 1. A partition of an older version is
+//! upgraded by applying the migrations from its version one after another, instead of reloading
+//! it from the raw data, either once it is opened by `GraphDBConfig::open` with an
+//! `UpgradePolicy`, or by `upgrade` of all the partitions of a store, e.g. by an ops tool. The
+//! versions are:
+//! * 1: the graph structure, the vertex/edge properties, the index data and the optional
+//!   statistics, which are recomputed while opening the graph if missing.
+//! * 2: record the layout version, where the statistics missing are computed and exported.
+//! * 3: the statistics record the hash of their sketches, see `SketchHash`, where the statistics
+//!   of the unstable hash are recomputed and exported.
+//!
+//! A partition of a newer version than `LAYOUT_VERSION` is refused by any policy.
+
+use crate::common::{Label, LabelId};
+use crate::config::{
+    GraphDBConfig, DIR_BINARY_DATA, DIR_GRAPH_SCHEMA, FILE_GRAPH_STRUCT, FILE_INDEX_DATA,
+    FILE_LAYOUT_VERSION, FILE_NODE_PPT_DATA, FILE_SCHEMA, FILE_STATISTICS, PARTITION_PREFIX,
+};
+use crate::error::{GDBError, GDBResult};
+use crate::graph_db_impl::IndexData;
+use crate::io::{export, import};
+use crate::schema::LDBCGraphSchema;
+use crate::statistics::GraphStatistics;
+use crate::table::PropertyTableTrait;
+use petgraph::graph::{DiGraph, IndexType};
+use serde::de::DeserializeOwned;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The version of the layout written by this crate
+pub const LAYOUT_VERSION: u32 = 3;
+/// The prefix of the backup of a partition migrated in place, followed by the old version and the
+/// name of the partition, e.g. "backup_v1_partition_0"
+pub const BACKUP_PREFIX: &str = "backup_v";
+
+/// What to do with a partition of an older layout than `LAYOUT_VERSION`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum UpgradePolicy {
+    /// Refuse it by `GDBError::LayoutVersionError`
+    Refuse,
+    /// Migrate it in place, after copying it into a backup next to it, named by `BACKUP_PREFIX`,
+    /// which is restored if the migration fails, and kept otherwise until removed by hand
+    Migrate,
+    /// Migrate a copy of it into the store of the root directory given, along with the schema,
+    /// leaving it as it is; A copy already of `LAYOUT_VERSION` there is used as it is
+    MigrateCopy(PathBuf),
+}
+
+/// A migration about to be applied to a partition, as reported to the progress of `upgrade`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UpgradeStep {
+    /// The directory of the partition migrated, which is the copy of `UpgradePolicy::MigrateCopy`
+    pub partition_dir: PathBuf,
+    pub from: u32,
+    pub to: u32,
+    pub name: &'static str,
+}
+
+/// A partition upgraded from an older version to `LAYOUT_VERSION`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PartitionUpgrade {
+    /// The directory of the partition upgraded, which is the copy of `UpgradePolicy::MigrateCopy`
+    pub partition_dir: PathBuf,
+    pub from: u32,
+}
+
+/// A migration of a partition from the version `from` to the next
+struct Migration {
+    from: u32,
+    name: &'static str,
+    apply: fn(&Path, &LDBCGraphSchema) -> GDBResult<()>,
+}
+
+/// The migrations in the order of their versions, which are given the directory of the partition
+/// and the trimmed schema
+fn migrations<G, I, N>() -> Vec<Migration>
+where
+    G: IndexType + DeserializeOwned + Send + Sync,
+    I: IndexType + DeserializeOwned + Send + Sync,
+    N: PropertyTableTrait,
+{
+    vec![
+        Migration { from: 1, name: "persist the statistics", apply: persist_statistics::<G, I, N> },
+        Migration { from: 2, name: "rehash the statistics", apply: rehash_statistics::<G, I, N> },
+    ]
+}
+
+fn rehash_statistics<G, I, N>(partition_dir: &Path, schema: &LDBCGraphSchema) -> GDBResult<()>
+where
+    G: IndexType + DeserializeOwned + Send + Sync,
+    I: IndexType + DeserializeOwned + Send + Sync,
+    N: PropertyTableTrait,
+{
+    let file_statistics = partition_dir.join(FILE_STATISTICS);
+    if file_statistics.exists() {
+        fs::remove_file(&file_statistics)?;
+    }
+    persist_statistics::<G, I, N>(partition_dir, schema)
+}
+
+fn persist_statistics<G, I, N>(partition_dir: &Path, schema: &LDBCGraphSchema) -> GDBResult<()>
+where
+    G: IndexType + DeserializeOwned + Send + Sync,
+    I: IndexType + DeserializeOwned + Send + Sync,
+    N: PropertyTableTrait,
+{
+    let file_statistics = partition_dir.join(FILE_STATISTICS);
+    if !file_statistics.exists() {
+        let graph = import::<DiGraph<Label, LabelId, I>, _>(partition_dir.join(FILE_GRAPH_STRUCT))?;
+        let index_data = import::<IndexData<G, I>, _>(partition_dir.join(FILE_INDEX_DATA))?;
+        let vertex_prop_table = N::import(partition_dir.join(FILE_NODE_PPT_DATA))?;
+        let statistics = GraphStatistics::compute(&graph, &index_data, &vertex_prop_table, schema);
+        export(&statistics, &file_statistics)?;
+    }
+    Ok(())
+}
+
+/// The layout version of the partition in the directory, which is 1 if not recorded
+pub fn layout_version<P: AsRef<Path>>(partition_dir: P) -> GDBResult<u32> {
+    let file = partition_dir.as_ref().join(FILE_LAYOUT_VERSION);
+    if file.exists() {
+        Ok(fs::read_to_string(file)?.trim().parse::<u32>()?)
+    } else {
+        Ok(1)
+    }
+}
+
+pub(crate) fn write_layout_version(partition_dir: &Path, version: u32) -> GDBResult<()> {
+    fs::write(partition_dir.join(FILE_LAYOUT_VERSION), format!("{}\n", version))?;
+    Ok(())
+}
+
+/// Bring the partition in the directory to `LAYOUT_VERSION` by the policy, and return the
+/// directory of the partition to open, along with the version it is of before
+pub(crate) fn upgrade_partition<G, I, N, F>(
+    config: &GraphDBConfig, partition_dir: &Path, schema: &LDBCGraphSchema, policy: &UpgradePolicy,
+    progress: &mut F,
+) -> GDBResult<(PathBuf, u32)>
+where
+    G: IndexType + DeserializeOwned + Send + Sync,
+    I: IndexType + DeserializeOwned + Send + Sync,
+    N: PropertyTableTrait,
+    F: FnMut(&UpgradeStep),
+{
+    let version = layout_version(partition_dir)?;
+    if version > LAYOUT_VERSION {
+        return Err(GDBError::LayoutVersionError(version, LAYOUT_VERSION));
+    } else if version == LAYOUT_VERSION {
+        return Ok((partition_dir.to_path_buf(), version));
+    }
+    let name = partition_dir.file_name().ok_or(GDBError::UnknownError)?;
+    match policy {
+        UpgradePolicy::Refuse => Err(GDBError::LayoutVersionError(version, LAYOUT_VERSION)),
+        UpgradePolicy::Migrate => {
+            let backup = partition_dir.with_file_name(format!(
+                "{}{}_{}",
+                BACKUP_PREFIX,
+                version,
+                name.to_string_lossy()
+            ));
+            // a backup left by an earlier migration of the same version, which has been restored
+            if backup.exists() {
+                fs::remove_dir_all(&backup)?;
+            }
+            copy_dir(partition_dir, &backup)?;
+            if let Err(e) = migrate::<G, I, N, F>(partition_dir, version, schema, progress) {
+                fs::remove_dir_all(partition_dir)?;
+                fs::rename(&backup, partition_dir)?;
+                return Err(e);
+            }
+            info!("Partition {:?} is migrated and backed up into {:?}", partition_dir, backup);
+            Ok((partition_dir.to_path_buf(), version))
+        }
+        UpgradePolicy::MigrateCopy(root_dir) => {
+            let target = root_dir.join(DIR_BINARY_DATA).join(name);
+            if target.exists() {
+                if layout_version(&target)? == LAYOUT_VERSION {
+                    return Ok((target, version));
+                }
+                // a copy left half migrated
+                fs::remove_dir_all(&target)?;
+            }
+            let schema_dir = root_dir.join(DIR_GRAPH_SCHEMA);
+            if !schema_dir.join(FILE_SCHEMA).exists() {
+                fs::create_dir_all(&schema_dir)?;
+                fs::copy(&config.schema_file, schema_dir.join(FILE_SCHEMA))?;
+            }
+            copy_dir(partition_dir, &target)?;
+            if let Err(e) = migrate::<G, I, N, F>(&target, version, schema, progress) {
+                fs::remove_dir_all(&target)?;
+                return Err(e);
+            }
+            Ok((target, version))
+        }
+    }
+}
+
+fn migrate<G, I, N, F>(
+    partition_dir: &Path, version: u32, schema: &LDBCGraphSchema, progress: &mut F,
+) -> GDBResult<()>
+where
+    G: IndexType + DeserializeOwned + Send + Sync,
+    I: IndexType + DeserializeOwned + Send + Sync,
+    N: PropertyTableTrait,
+    F: FnMut(&UpgradeStep),
+{
+    for migration in migrations::<G, I, N>().into_iter().filter(|m| m.from >= version) {
+        progress(&UpgradeStep {
+            partition_dir: partition_dir.to_path_buf(),
+            from: migration.from,
+            to: migration.from + 1,
+            name: migration.name,
+        });
+        (migration.apply)(partition_dir, schema)?;
+        // recorded after each migration, so that a migration failed later resumes from here
+        write_layout_version(partition_dir, migration.from + 1)?;
+    }
+    Ok(())
+}
+
+fn copy_dir(from: &Path, to: &Path) -> GDBResult<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let path = entry?.path();
+        let target = to.join(path.file_name().ok_or(GDBError::UnknownError)?);
+        if path.is_dir() {
+            copy_dir(&path, &target)?;
+        } else {
+            fs::copy(&path, &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Upgrade all the partitions of the store of the config to `LAYOUT_VERSION` by the policy, as
+/// `GraphDBConfig::open` does for the partition it opens, e.g. by an ops tool upgrading the store
+/// offline, where each migration is reported to `progress` before it is applied; Return the
+/// partitions upgraded, or `GDBError::LayoutVersionError` of the first partition of an older
+/// version for `UpgradePolicy::Refuse`, i.e. to check the store only
+pub fn upgrade<G, I, N, F>(
+    config: &GraphDBConfig, policy: &UpgradePolicy, mut progress: F,
+) -> GDBResult<Vec<PartitionUpgrade>>
+where
+    G: IndexType + DeserializeOwned + Send + Sync,
+    I: IndexType + DeserializeOwned + Send + Sync,
+    N: PropertyTableTrait,
+    F: FnMut(&UpgradeStep),
+{
+    let mut schema = config.schema()?;
+    schema.trim();
+    let mut partitions = vec![];
+    for entry in fs::read_dir(config.root_dir.join(DIR_BINARY_DATA))? {
+        let path = entry?.path();
+        let is_partition = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map(|name| name.starts_with(PARTITION_PREFIX))
+            .unwrap_or(false);
+        if is_partition && path.is_dir() {
+            partitions.push(path);
+        }
+    }
+    partitions.sort();
+    let mut upgraded = vec![];
+    for partition_dir in partitions {
+        let (partition_dir, from) = upgrade_partition::<G, I, N, F>(
+            config,
+            &partition_dir,
+            &schema,
+            policy,
+            &mut progress,
+        )?;
+        if from < LAYOUT_VERSION {
+            upgraded.push(PartitionUpgrade { partition_dir, from });
+        }
+    }
+    Ok(upgraded)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::common::{DefaultId, InternalId};
+    use crate::graph_db::GlobalStoreTrait;
+    use crate::graph_db_impl::LargeGraphDB;
+    use crate::ldbc::GraphLoader;
+    use crate::table::PropertyTable;
+
+    fn partition_dir(root_dir: &Path, partition: usize) -> PathBuf {
+        root_dir.join(DIR_BINARY_DATA).join(format!("{}{}", PARTITION_PREFIX, partition))
+    }
+
+    /// Build a store of the partitions exported before the layout is versioned
+    fn build_old_store(root_dir: &Path, partitions: usize) {
+        for partition in 0..partitions {
+            let mut loader = GraphLoader::<DefaultId, InternalId>::new(
+                Path::new("data/large_data"),
+                root_dir,
+                Path::new("data/schema.json"),
+                20,
+                partition,
+                partitions,
+            );
+            loader.load().expect("Load graph error");
+            loader.into_mutable_graph().export().expect("Export error");
+            let dir = partition_dir(root_dir, partition);
+            assert_eq!(layout_version(&dir).unwrap(), LAYOUT_VERSION);
+            fs::remove_file(dir.join(FILE_LAYOUT_VERSION)).unwrap();
+            assert_eq!(layout_version(&dir).unwrap(), 1);
+        }
+    }
+
+    fn config(root_dir: &Path) -> GraphDBConfig {
+        GraphDBConfig::default().root_dir(root_dir).schema_file("data/schema.json").partition(0)
+    }
+
+    fn open(config: GraphDBConfig) -> GDBResult<LargeGraphDB<DefaultId, InternalId>> {
+        config.open()
+    }
+
+    #[test]
+    fn test_upgrade_refuse() {
+        let temp = tempdir::TempDir::new("test_upgrade_refuse").expect("Open temp folder error");
+        build_old_store(temp.path(), 1);
+        let refused = open(config(temp.path()).upgrade_policy(UpgradePolicy::Refuse));
+        assert!(matches!(refused.err(), Some(GDBError::LayoutVersionError(1, LAYOUT_VERSION))));
+        // opened as it is without a policy
+        let graph = open(config(temp.path())).expect("Open graph error");
+        assert_eq!(graph.count_all_vertices(Some(&vec![1.into()])), 9);
+        let dir = partition_dir(temp.path(), 0);
+        assert_eq!(layout_version(&dir).unwrap(), 1);
+        assert!(!dir.join(FILE_STATISTICS).exists());
+    }
+
+    #[test]
+    fn test_upgrade_migrate() {
+        let temp = tempdir::TempDir::new("test_upgrade_migrate").expect("Open temp folder error");
+        build_old_store(temp.path(), 1);
+        let graph = open(config(temp.path()).upgrade_policy(UpgradePolicy::Migrate))
+            .expect("Open graph error");
+        assert_eq!(graph.count_all_vertices(Some(&vec![1.into()])), 9);
+        assert_eq!(graph.get_statistics().vertex_count(1.into()), 9);
+        let dir = partition_dir(temp.path(), 0);
+        assert_eq!(layout_version(&dir).unwrap(), LAYOUT_VERSION);
+        assert!(dir.join(FILE_STATISTICS).exists());
+        // the backup is of the old layout, which is not taken as a partition
+        let backup = temp.path().join(DIR_BINARY_DATA).join("backup_v1_partition_0");
+        assert_eq!(layout_version(&backup).unwrap(), 1);
+        assert!(!backup.join(FILE_STATISTICS).exists());
+        assert!(backup.join(FILE_GRAPH_STRUCT).exists());
+        let graph = open(config(temp.path()).upgrade_policy(UpgradePolicy::Refuse))
+            .expect("Open graph error");
+        assert_eq!(graph.count_all_edges(Some(&vec![12.into()])), 9);
+    }
+
+    #[test]
+    fn test_upgrade_migrate_copy() {
+        let temp = tempdir::TempDir::new("test_upgrade_copy").expect("Open temp folder error");
+        let copy = tempdir::TempDir::new("test_upgrade_copy_to").expect("Open temp folder error");
+        build_old_store(temp.path(), 1);
+        let policy = UpgradePolicy::MigrateCopy(copy.path().to_path_buf());
+        let graph =
+            open(config(temp.path()).upgrade_policy(policy.clone())).expect("Open graph error");
+        assert_eq!(graph.count_all_vertices(Some(&vec![1.into()])), 9);
+        // the store is left as it is
+        let dir = partition_dir(temp.path(), 0);
+        assert_eq!(layout_version(&dir).unwrap(), 1);
+        assert!(!dir.join(FILE_STATISTICS).exists());
+        let copied = partition_dir(copy.path(), 0);
+        assert_eq!(layout_version(&copied).unwrap(), LAYOUT_VERSION);
+        assert!(copied.join(FILE_STATISTICS).exists());
+        // the copy is a store of its own, and is used as it is by the later opening
+        let schema_file = copy.path().join(DIR_GRAPH_SCHEMA).join(FILE_SCHEMA);
+        let config_of_copy =
+            GraphDBConfig::default().root_dir(copy.path()).schema_file(schema_file);
+        let graph =
+            open(config_of_copy.upgrade_policy(UpgradePolicy::Refuse)).expect("Open graph error");
+        assert_eq!(graph.count_all_vertices(Some(&vec![1.into()])), 9);
+        let upgraded = upgrade::<DefaultId, InternalId, PropertyTable, _>(
+            &config(temp.path()),
+            &policy,
+            |_| panic!("the copy is already migrated"),
+        )
+        .expect("Upgrade error");
+        assert_eq!(upgraded, vec![PartitionUpgrade { partition_dir: copied, from: 1 }]);
+    }
+
+    #[test]
+    fn test_upgrade_newer_version() {
+        let temp = tempdir::TempDir::new("test_upgrade_newer").expect("Open temp folder error");
+        build_old_store(temp.path(), 1);
+        let dir = partition_dir(temp.path(), 0);
+        write_layout_version(&dir, LAYOUT_VERSION + 1).unwrap();
+        let newer = LAYOUT_VERSION + 1;
+        for policy in &[None, Some(UpgradePolicy::Refuse), Some(UpgradePolicy::Migrate)] {
+            let mut config = config(temp.path());
+            if let Some(policy) = policy {
+                config = config.upgrade_policy(policy.clone());
+            }
+            let refused = open(config).err();
+            assert!(matches!(
+                refused,
+                Some(GDBError::LayoutVersionError(v, LAYOUT_VERSION)) if v == newer
+            ));
+        }
+        assert_eq!(layout_version(&dir).unwrap(), LAYOUT_VERSION + 1);
+    }
+
+    #[test]
+    fn test_upgrade_store() {
+        let temp = tempdir::TempDir::new("test_upgrade_store").expect("Open temp folder error");
+        build_old_store(temp.path(), 2);
+        let config = config(temp.path());
+        let check = upgrade::<DefaultId, InternalId, PropertyTable, _>(
+            &config,
+            &UpgradePolicy::Refuse,
+            |_| panic!("nothing is migrated to check"),
+        );
+        assert!(matches!(check.err(), Some(GDBError::LayoutVersionError(1, LAYOUT_VERSION))));
+
+        let mut steps = vec![];
+        let upgraded = upgrade::<DefaultId, InternalId, PropertyTable, _>(
+            &config,
+            &UpgradePolicy::Migrate,
+            |step| steps.push(step.clone()),
+        )
+        .expect("Upgrade error");
+        let dirs = [partition_dir(temp.path(), 0), partition_dir(temp.path(), 1)];
+        assert_eq!(
+            upgraded,
+            dirs.iter()
+                .map(|dir| PartitionUpgrade { partition_dir: dir.clone(), from: 1 })
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(steps.len(), 4);
+        for (steps, dir) in steps.chunks(2).zip(dirs.iter()) {
+            assert_eq!((&steps[0].partition_dir, steps[0].from, steps[0].to), (dir, 1, 2));
+            assert_eq!(steps[0].name, "persist the statistics");
+            assert_eq!((&steps[1].partition_dir, steps[1].from, steps[1].to), (dir, 2, 3));
+            assert_eq!(steps[1].name, "rehash the statistics");
+        }
+        let again = upgrade::<DefaultId, InternalId, PropertyTable, _>(
+            &config,
+            &UpgradePolicy::Refuse,
+            |_| panic!("nothing is migrated again"),
+        )
+        .expect("Upgrade error");
+        assert!(again.is_empty());
+        // the statistics of both partitions are those computed while loading
+        let mut vertices = 0;
+        for partition in 0..2 {
+            let graph = open(config.clone().partition(partition)).expect("Open graph error");
+            let statistics = graph.get_statistics();
+            assert_eq!(statistics.total_edge_count(), graph.count_all_edges(None));
+            vertices += statistics.vertex_count(1.into());
+        }
+        assert_eq!(vertices, 9);
+    }
+}
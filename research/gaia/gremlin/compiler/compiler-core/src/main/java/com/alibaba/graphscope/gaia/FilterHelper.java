@@ -165,5 +165,40 @@ public class FilterHelper {
             throw new UnsupportedOperationException("number type not supported " + value.getClass());
         }
     }
-}
 
+    // the predicate on the values themselves, e.g. is(within(1, 2)), whose key is left unset
+    public Gremlin.FilterExp valuePredicate(final Object value, final BiPredicate predicate) {
+        Gremlin.Compare compare = convertFromBiPredicate(predicate);
+        return Gremlin.FilterExp.newBuilder().setCmp(compare).setRight(encodeValue(value)).build();
+    }
+
+    private Common.Value encodeValue(final Object value) {
+        if (value == null) {
+            return EncodeValue.fromNull();
+        } else if (value instanceof Long) {
+            return EncodeValue.fromLong((Long) value);
+        } else if (value instanceof Integer) {
+            return EncodeValue.fromInt((Integer) value);
+        } else if (value instanceof Double || value instanceof Float || value instanceof BigDecimal) {
+            return EncodeValue.fromDouble(((Number) value).doubleValue());
+        } else if (value instanceof String) {
+            return EncodeValue.fromString((String) value);
+        } else if (value instanceof List) {
+            List<Object> values = (List<Object>) value;
+            if (values.isEmpty()) {
+                return EncodeValue.fromNull();
+            } else if (values.stream().allMatch(k -> k instanceof String)) {
+                return EncodeValue.fromStrArray(values.stream().map(k -> (String) k).collect(Collectors.toList()));
+            } else if (values.stream().allMatch(k -> k instanceof Integer)) {
+                return EncodeValue.fromIntArray(values.stream().map(k -> (Integer) k).collect(Collectors.toList()));
+            } else if (values.stream().allMatch(k -> k instanceof Integer || k instanceof Long)) {
+                return EncodeValue.fromLongArray(values.stream().map(k -> ((Number) k).longValue()).collect(Collectors.toList()));
+            } else if (values.stream().allMatch(k -> k instanceof Number)) {
+                return EncodeValue.fromDoubleArray(values.stream().map(k -> ((Number) k).doubleValue()).collect(Collectors.toList()));
+            }
+            throw new UnsupportedOperationException("cannot support list of mixed types " + value);
+        } else {
+            throw new UnsupportedOperationException("value type not supported " + value.getClass());
+        }
+    }
+}
@@ -24,11 +24,13 @@ import com.alibaba.graphscope.gaia.plan.strategy.global.TransformTraverserStep;
 import com.alibaba.graphscope.gaia.FilterHelper;
 import com.alibaba.pegasus.builder.JobBuilder;
 import com.alibaba.pegasus.builder.ReduceBuilder;
+import com.alibaba.pegasus.service.protocol.PegasusClient;
 import com.alibaba.graphscope.gaia.plan.extractor.TagKeyExtractorFactory;
 import com.alibaba.graphscope.gaia.plan.resource.GremlinStepResource;
 import com.alibaba.graphscope.gaia.plan.resource.JobBuilderResource;
 import com.alibaba.graphscope.gaia.plan.resource.StepResource;
 import com.alibaba.graphscope.gaia.plan.predicate.HasContainerP;
+import com.alibaba.graphscope.gaia.plan.predicate.IsValueP;
 import com.alibaba.graphscope.gaia.plan.predicate.WherePredicateP;
 import com.alibaba.graphscope.gaia.plan.translator.PredicateTranslator;
 import com.alibaba.graphscope.gaia.plan.translator.TraversalTranslator;
@@ -36,15 +38,22 @@ import com.alibaba.graphscope.gaia.plan.translator.builder.StepBuilder;
 import com.alibaba.graphscope.gaia.plan.translator.builder.TraversalBuilder;
 import com.google.protobuf.ByteString;
 import org.apache.commons.configuration.Configuration;
+import org.apache.tinkerpop.gremlin.process.traversal.Compare;
+import org.apache.tinkerpop.gremlin.process.traversal.Contains;
 import org.apache.tinkerpop.gremlin.process.traversal.P;
 import org.apache.tinkerpop.gremlin.process.traversal.Step;
 import org.apache.tinkerpop.gremlin.process.traversal.Traversal;
 import org.apache.tinkerpop.gremlin.process.traversal.lambda.LoopTraversal;
+import org.apache.tinkerpop.gremlin.process.traversal.lambda.TrueTraversal;
 import org.apache.tinkerpop.gremlin.process.traversal.step.ComparatorHolder;
 import org.apache.tinkerpop.gremlin.process.traversal.step.branch.RepeatStep;
 import org.apache.tinkerpop.gremlin.process.traversal.step.branch.UnionStep;
 import org.apache.tinkerpop.gremlin.process.traversal.step.filter.*;
 import org.apache.tinkerpop.gremlin.process.traversal.step.map.*;
+import org.apache.tinkerpop.gremlin.process.traversal.step.sideEffect.AggregateGlobalStep;
+import org.apache.tinkerpop.gremlin.process.traversal.step.sideEffect.AggregateLocalStep;
+import org.apache.tinkerpop.gremlin.process.traversal.step.sideEffect.SideEffectCapStep;
+import org.apache.tinkerpop.gremlin.process.traversal.step.sideEffect.StoreStep;
 import org.apache.tinkerpop.gremlin.process.traversal.util.DefaultTraversal;
 import org.apache.tinkerpop.gremlin.process.traversal.util.TraversalRing;
 import org.apache.tinkerpop.gremlin.structure.Direction;
@@ -58,6 +67,9 @@ public class LogicPlanGlobalMap {
         GraphStep,
         MaxGraphStep,
         TraversalFilterStep,
+        NotStep,
+        AndStep,
+        OrStep,
         HasStep,
         WherePredicateStep,
         VertexStep,
@@ -73,6 +85,10 @@ public class LogicPlanGlobalMap {
         GroupStep,
         GroupCountStep,
         CountGlobalStep,
+        SumGlobalStep,
+        MinGlobalStep,
+        MaxGlobalStep,
+        MeanGlobalStep,
         BySubTaskStep,
         UnionStep,
         PropertiesStep,
@@ -88,7 +104,30 @@ public class LogicPlanGlobalMap {
         TransformTraverserStep,
         HasAnyStep,
         IsStep,
-        FoldStep
+        FoldStep,
+        AggregateGlobalStep,
+        AggregateLocalStep,
+        StoreStep,
+        SideEffectCapStep
+    }
+
+    // sum(), min(), max() and mean() over the numeric heads of traversers
+    private static JobBuilderResource numericAccum(PegasusClient.AccumKind accumKind, Gremlin.NumericAccumStep.Kind kind) {
+        return new JobBuilderResource() {
+            @Override
+            public void buildJob(StepBuilder stepBuilder) {
+                JobBuilder target = (JobBuilder) stepBuilder.getJobBuilder();
+                stepBuilder.setJobBuilder(target.foldCustom(true, accumKind, Gremlin.GremlinStep.newBuilder()
+                        .setNumericAccumStep(Gremlin.NumericAccumStep.newBuilder().setKind(kind))
+                        .build().toByteString()));
+            }
+        };
+    }
+
+    private static ByteString sideEffect(Gremlin.SideEffectStep.Kind kind, String key) {
+        return Gremlin.GremlinStep.newBuilder()
+                .setSideEffectStep(Gremlin.SideEffectStep.newBuilder().setKind(kind).setKey(key))
+                .build().toByteString();
     }
 
     public static STEP stepType(Step t) {
@@ -140,9 +179,45 @@ public class LogicPlanGlobalMap {
                 Configuration conf = stepBuilder.getConf();
                 JobBuilder builder = (JobBuilder) stepBuilder.getJobBuilder();
                 Traversal.Admin traversal = (Traversal.Admin) ((TraversalFilterStep) t).getLocalChildren().get(0);
-                builder.forkJoin(Gremlin.SubTaskJoiner.newBuilder()
-                        .setWhereJoiner(Gremlin.WhereJoiner.newBuilder()).build()
-                        .toByteString(), (JobBuilder) new TraversalTranslator((new TraversalBuilder(traversal)).setConf(conf)).translate());
+                builder.semiJoin(PegasusClient.Subtask.SemiJoin.ANY_EXISTS,
+                        (JobBuilder) new TraversalTranslator((new TraversalBuilder(traversal)).setConf(conf)).translate());
+            }
+        });
+        stepPlanMap.put(STEP.NotStep, new JobBuilderResource() {
+            @Override
+            public void buildJob(StepBuilder stepBuilder) {
+                Step t = stepBuilder.getStep();
+                Configuration conf = stepBuilder.getConf();
+                JobBuilder builder = (JobBuilder) stepBuilder.getJobBuilder();
+                Traversal.Admin traversal = (Traversal.Admin) ((NotStep) t).getLocalChildren().get(0);
+                builder.semiJoin(PegasusClient.Subtask.SemiJoin.NONE_EXISTS,
+                        (JobBuilder) new TraversalTranslator((new TraversalBuilder(traversal)).setConf(conf)).translate());
+            }
+        });
+        stepPlanMap.put(STEP.AndStep, new JobBuilderResource() {
+            @Override
+            public void buildJob(StepBuilder stepBuilder) {
+                Step t = stepBuilder.getStep();
+                Configuration conf = stepBuilder.getConf();
+                JobBuilder builder = (JobBuilder) stepBuilder.getJobBuilder();
+                // each branch filters the parents left by the previous one
+                for (Object k : ((AndStep) t).getLocalChildren()) {
+                    builder.semiJoin(PegasusClient.Subtask.SemiJoin.ANY_EXISTS,
+                            (JobBuilder) new TraversalTranslator((new TraversalBuilder((Traversal.Admin) k)).setConf(conf)).translate());
+                }
+            }
+        });
+        stepPlanMap.put(STEP.OrStep, new JobBuilderResource() {
+            @Override
+            public void buildJob(StepBuilder stepBuilder) {
+                Step t = stepBuilder.getStep();
+                Configuration conf = stepBuilder.getConf();
+                JobBuilder builder = (JobBuilder) stepBuilder.getJobBuilder();
+                // a parent qualifies if the union of all branches produces anything
+                List<JobBuilder> branches = (List<JobBuilder>) ((OrStep) t).getLocalChildren().stream()
+                        .map(k -> new TraversalTranslator(new TraversalBuilder((Traversal.Admin) k).setConf(conf)).translate())
+                        .collect(Collectors.toList());
+                builder.semiJoin(PegasusClient.Subtask.SemiJoin.ANY_EXISTS, new JobBuilder().union(branches));
             }
         });
         stepPlanMap.put(STEP.WherePredicateStep, new JobBuilderResource() {
@@ -152,6 +227,14 @@ public class LogicPlanGlobalMap {
                 Configuration conf = stepBuilder.getConf();
                 JobBuilder target = (JobBuilder) stepBuilder.getJobBuilder();
                 WherePredicateStep t1 = (WherePredicateStep) t;
+                Optional<String> sideKey = PlanUtils.getWithinSideKey(t1);
+                if (sideKey.isPresent()) {
+                    // filter by the side collection collected by aggregate("x") or store("x")
+                    boolean negate = t1.getPredicate().get().getBiPredicate() == Contains.without;
+                    target.filter(Gremlin.GremlinStep.newBuilder().setWithinSideStep(Gremlin.WithinSideStep.newBuilder()
+                            .setKey(sideKey.get()).setNegate(negate)).build().toByteString());
+                    return;
+                }
                 TraversalRing modulateBy = PlanUtils.getTraversalRing(t1.getLocalChildren(), true);
                 Gremlin.WhereStep.Builder builder = Gremlin.WhereStep.newBuilder()
                         .setStartToken(TagKeyExtractorFactory.WherePredicate.extractFrom(modulateBy.next()).getByKey().getKey());
@@ -200,12 +283,26 @@ public class LogicPlanGlobalMap {
                     repeat.addStep(s);
                 }
                 // add loops
-                Traversal.Admin traversal = ((RepeatStep) step).getUntilTraversal();
-                long times = 0;
-                if (traversal instanceof LoopTraversal) {
-                    times = ((LoopTraversal) traversal).getMaxLoops();
+                RepeatStep repeatStep = (RepeatStep) step;
+                Traversal.Admin traversal = repeatStep.getUntilTraversal();
+                Traversal.Admin emitTraversal = repeatStep.getEmitTraversal();
+                if (traversal instanceof LoopTraversal && emitTraversal == null) {
+                    long times = ((LoopTraversal) traversal).getMaxLoops();
+                    target.repeat((int) times, (JobBuilder) new TraversalTranslator((new TraversalBuilder(repeat)).setConf(conf)).translate());
+                    return;
                 }
-                target.repeat((int) times, (JobBuilder) new TraversalTranslator((new TraversalBuilder(repeat)).setConf(conf)).translate());
+                // repeat().until() or repeat().emit(), count the loops of traversers for loops()
+                PegasusClient.Iteration.EmitKind emit = PegasusClient.Iteration.EmitKind.EMIT_NONE;
+                if (emitTraversal != null) {
+                    if (!(emitTraversal instanceof TrueTraversal)) {
+                        throw new UnsupportedOperationException("emit with condition is unsupported yet");
+                    }
+                    emit = repeatStep.emitFirst ? PegasusClient.Iteration.EmitKind.EMIT_BEFORE : PegasusClient.Iteration.EmitKind.EMIT_AFTER;
+                }
+                JobBuilder body = (JobBuilder) new TraversalTranslator((new TraversalBuilder(repeat)).setConf(conf)).translate();
+                body.map(repeatLoops(Gremlin.RepeatLoopsStep.Kind.INCR));
+                target.map(repeatLoops(Gremlin.RepeatLoopsStep.Kind.RESET));
+                target.repeatUntil(0, untilPredicate(traversal), !repeatStep.untilFirst, emit, body);
             }
         });
         stepPlanMap.put(STEP.PathStep, new GremlinStepResource() {
@@ -326,6 +423,49 @@ public class LogicPlanGlobalMap {
                 stepBuilder.setJobBuilder(target.count(true));
             }
         });
+        stepPlanMap.put(STEP.SumGlobalStep, numericAccum(PegasusClient.AccumKind.SUM, Gremlin.NumericAccumStep.Kind.SUM));
+        stepPlanMap.put(STEP.MinGlobalStep, numericAccum(PegasusClient.AccumKind.MIN, Gremlin.NumericAccumStep.Kind.MIN));
+        stepPlanMap.put(STEP.MaxGlobalStep, numericAccum(PegasusClient.AccumKind.MAX, Gremlin.NumericAccumStep.Kind.MAX));
+        stepPlanMap.put(STEP.MeanGlobalStep, numericAccum(PegasusClient.AccumKind.MEAN, Gremlin.NumericAccumStep.Kind.MEAN));
+        stepPlanMap.put(STEP.AggregateGlobalStep, new JobBuilderResource() {
+            @Override
+            public void buildJob(StepBuilder stepBuilder) {
+                JobBuilder target = (JobBuilder) stepBuilder.getJobBuilder();
+                String key = ((AggregateGlobalStep) stepBuilder.getStep()).getSideEffectKey();
+                // gather all traversers globally into the side collection, then unfold them
+                stepBuilder.setJobBuilder(target.fold(true, PegasusClient.AccumKind.TO_LIST)
+                        .unfold(sideEffect(Gremlin.SideEffectStep.Kind.AGGREGATE, key)));
+            }
+        });
+        stepPlanMap.put(STEP.AggregateLocalStep, new JobBuilderResource() {
+            @Override
+            public void buildJob(StepBuilder stepBuilder) {
+                JobBuilder target = (JobBuilder) stepBuilder.getJobBuilder();
+                String key = ((AggregateLocalStep) stepBuilder.getStep()).getSideEffectKey();
+                target.map(sideEffect(Gremlin.SideEffectStep.Kind.STORE, key));
+            }
+        });
+        stepPlanMap.put(STEP.StoreStep, new JobBuilderResource() {
+            @Override
+            public void buildJob(StepBuilder stepBuilder) {
+                JobBuilder target = (JobBuilder) stepBuilder.getJobBuilder();
+                String key = ((StoreStep) stepBuilder.getStep()).getSideEffectKey();
+                target.map(sideEffect(Gremlin.SideEffectStep.Kind.STORE, key));
+            }
+        });
+        stepPlanMap.put(STEP.SideEffectCapStep, new JobBuilderResource() {
+            @Override
+            public void buildJob(StepBuilder stepBuilder) {
+                JobBuilder target = (JobBuilder) stepBuilder.getJobBuilder();
+                List<String> keys = ((SideEffectCapStep) stepBuilder.getStep()).getSideEffectKeys();
+                if (keys.size() != 1) {
+                    throw new UnsupportedOperationException("cannot support cap of multiple side effect keys " + keys);
+                }
+                // emit the side collection once all traversers arrive
+                stepBuilder.setJobBuilder(target.count(true).unfold(Gremlin.GremlinStep.newBuilder()
+                        .setCapStep(Gremlin.CapStep.newBuilder().setKey(keys.get(0))).build().toByteString()));
+            }
+        });
         stepPlanMap.put(STEP.BySubTaskStep, new JobBuilderResource() {
             @Override
             protected void buildJob(StepBuilder stepBuilder) {
@@ -441,13 +581,58 @@ public class LogicPlanGlobalMap {
                 Step t = stepBuilder.getStep();
                 JobBuilder target = (JobBuilder) stepBuilder.getJobBuilder();
                 P p = ((IsStep) t).getPredicate();
-                target.filter(Gremlin.GremlinStep.newBuilder().setIsStep(
-                        Gremlin.IsStep.newBuilder().setSingle(FilterHelper.INSTANCE.valueComparePredicate((Number) p.getValue(), p.getBiPredicate()))
-                ).build().toByteString());
+                // compare the ids of the elements, or else the values themselves
+                if (isElementStep(t.getPreviousStep())) {
+                    target.filter(Gremlin.GremlinStep.newBuilder().setHasStep(Gremlin.HasStep.newBuilder()
+                            .setPredicates(new PredicateTranslator(new IsValueP(p, true)).translate())
+                    ).build().toByteString());
+                } else {
+                    target.filter(Gremlin.GremlinStep.newBuilder().setIsStep(Gremlin.IsStep.newBuilder()
+                            .setPredicates(new PredicateTranslator(new IsValueP(p, false)).translate())
+                    ).build().toByteString());
+                }
             }
         });
     }
 
+    // whether the traversers output by the step are elements, skipping the filters before
+    private static boolean isElementStep(Step step) {
+        while (step instanceof FilterStep) {
+            step = step.getPreviousStep();
+        }
+        return step instanceof GraphStep || step instanceof VertexStep || step instanceof EdgeVertexStep
+                || step instanceof EdgeOtherVertexStep;
+    }
+
+    private static ByteString repeatLoops(Gremlin.RepeatLoopsStep.Kind kind) {
+        return Gremlin.GremlinStep.newBuilder()
+                .setRepeatLoopsStep(Gremlin.RepeatLoopsStep.newBuilder().setKind(kind))
+                .build().toByteString();
+    }
+
+    // support until(loops().is(...)), until(has(...)) and times(n) in repeat
+    private static ByteString untilPredicate(Traversal.Admin until) {
+        if (until instanceof LoopTraversal) {
+            long times = ((LoopTraversal) until).getMaxLoops();
+            return Gremlin.GremlinStep.newBuilder().setLoopsStep(Gremlin.LoopsStep.newBuilder()
+                    .setSingle(FilterHelper.INSTANCE.valueComparePredicate(times, Compare.eq))
+            ).build().toByteString();
+        }
+        List<Step> steps = until.getSteps();
+        if (steps.size() == 2 && steps.get(0) instanceof LoopsStep && steps.get(1) instanceof IsStep) {
+            P p = ((IsStep) steps.get(1)).getPredicate();
+            return Gremlin.GremlinStep.newBuilder().setLoopsStep(Gremlin.LoopsStep.newBuilder()
+                    .setSingle(FilterHelper.INSTANCE.valueComparePredicate((Number) p.getValue(), p.getBiPredicate()))
+            ).build().toByteString();
+        }
+        if (steps.size() == 1 && steps.get(0) instanceof HasStep) {
+            return Gremlin.GremlinStep.newBuilder().setHasStep(Gremlin.HasStep.newBuilder()
+                    .setPredicates(new PredicateTranslator(new HasContainerP((HasStep) steps.get(0))).translate())
+            ).build().toByteString();
+        }
+        throw new UnsupportedOperationException("until traversal " + until + " is unsupported yet");
+    }
+
     public static Optional<StepResource> getResourceConstructor(STEP step) {
         return Optional.ofNullable(stepPlanMap.get(step));
     }
@@ -35,7 +35,10 @@ import org.apache.tinkerpop.gremlin.process.traversal.*;
 import org.apache.tinkerpop.gremlin.process.traversal.lambda.IdentityTraversal;
 import org.apache.tinkerpop.gremlin.process.traversal.lambda.TokenTraversal;
 import org.apache.tinkerpop.gremlin.process.traversal.step.ComparatorHolder;
+import org.apache.tinkerpop.gremlin.process.traversal.step.HasContainerHolder;
+import org.apache.tinkerpop.gremlin.process.traversal.step.filter.WherePredicateStep;
 import org.apache.tinkerpop.gremlin.process.traversal.step.map.*;
+import org.apache.tinkerpop.gremlin.process.traversal.step.util.HasContainer;
 import org.apache.tinkerpop.gremlin.process.traversal.util.TraversalRing;
 import org.apache.tinkerpop.gremlin.structure.Graph;
 import org.apache.tinkerpop.gremlin.structure.T;
@@ -49,6 +52,7 @@ import java.lang.reflect.Field;
 import java.lang.reflect.Modifier;
 import java.nio.charset.StandardCharsets;
 import java.util.*;
+import java.util.function.BiPredicate;
 
 public class PlanUtils {
     private static final Logger logger = LoggerFactory.getLogger(PlanUtils.class);
@@ -242,6 +246,38 @@ public class PlanUtils {
         }
     }
 
+    /**
+     * The name of the operators compiled from the step, e.g. "has[name eq marko]", "out[knows]" or
+     * "count[global]", as shown in the profile of the job.
+     */
+    public static String stepName(Step step) {
+        if (step instanceof HasContainerHolder) {
+            List<String> predicates = new ArrayList<>();
+            for (HasContainer container : ((HasContainerHolder) step).getHasContainers()) {
+                predicates.add(container.getKey() + " " + container.getBiPredicate() + " " + container.getValue());
+            }
+            return "has[" + String.join(", ", predicates) + "]";
+        } else if (step instanceof VertexStep) {
+            VertexStep vertexStep = (VertexStep) step;
+            String direction = vertexStep.getDirection().name().toLowerCase();
+            String name = vertexStep.returnsEdge() ? direction + "E" : direction;
+            return name + "[" + String.join(", ", vertexStep.getEdgeLabels()) + "]";
+        }
+        String name = step.getClass().getSimpleName().replaceAll("Step$", "");
+        String range = "";
+        if (name.endsWith("Global")) {
+            name = name.substring(0, name.length() - "Global".length());
+            range = "[global]";
+        } else if (name.endsWith("Local")) {
+            name = name.substring(0, name.length() - "Local".length());
+            range = "[local]";
+        }
+        if (name.isEmpty()) {
+            return step.toString();
+        }
+        return Character.toLowerCase(name.charAt(0)) + name.substring(1) + range;
+    }
+
     public static Traversal.Admin[] toArray(List<Traversal.Admin> traversals) {
         if (traversals == null || traversals.isEmpty()) return null;
         Traversal.Admin[] newTraversals = new Traversal.Admin[traversals.size()];
@@ -348,6 +384,24 @@ public class PlanUtils {
         }
     }
 
+    /**
+     * get the key of side collection if the step is where(within("x")) or where(without("x")),
+     * where "x" is collected by aggregate("x") or store("x") instead of a tag
+     */
+    public static Optional<String> getWithinSideKey(WherePredicateStep step) {
+        Optional<P<?>> predicateOpt = step.getPredicate();
+        List<String> keys = getSelectKeysList(step);
+        if (step.getStartKey().isPresent() || !predicateOpt.isPresent() || keys == null || keys.size() != 1
+                || !step.getTraversal().getSideEffects().exists(keys.get(0))) {
+            return Optional.empty();
+        }
+        BiPredicate biPredicate = predicateOpt.get().getBiPredicate();
+        if (biPredicate != Contains.within && biPredicate != Contains.without) {
+            return Optional.empty();
+        }
+        return Optional.of(keys.get(0));
+    }
+
     public static IdMaker getTagIdMaker(Configuration conf) {
         return (IdMaker) conf.getProperty(PlanConfig.TAG_ID_MAKER);
     }
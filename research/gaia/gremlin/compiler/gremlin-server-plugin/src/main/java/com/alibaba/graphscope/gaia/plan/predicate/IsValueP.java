@@ -0,0 +1,56 @@
+/*
+ * Copyright 2020 Alibaba Group Holding Limited.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ * http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+package com.alibaba.graphscope.gaia.plan.predicate;
+
+import com.alibaba.graphscope.common.proto.Common;
+import com.alibaba.graphscope.common.proto.Gremlin;
+import com.alibaba.graphscope.gaia.FilterHelper;
+import org.apache.tinkerpop.gremlin.process.traversal.P;
+
+// the predicate of is(), on the values themselves, e.g. values("age").is(within(29, 32)), or on the ids
+// if the traversers are elements, e.g. V().is(1)
+public class IsValueP implements PredicateContainer {
+    private P predicate;
+    private boolean onElements;
+    private boolean hasNext = true;
+
+    public IsValueP(P predicate, boolean onElements) {
+        this.predicate = predicate;
+        this.onElements = onElements;
+    }
+
+    @Override
+    public Gremlin.FilterExp generateSimpleP(P predicate) {
+        if (onElements) {
+            Common.Key idKey = Common.Key.newBuilder().setId(Common.IdKey.newBuilder().build()).build();
+            return HasContainerP.generateFilter(idKey, predicate, false);
+        } else {
+            return FilterHelper.INSTANCE.valuePredicate(predicate.getValue(), predicate.getBiPredicate());
+        }
+    }
+
+    @Override
+    public boolean hasNext() {
+        boolean oldState = this.hasNext;
+        this.hasNext = false;
+        return oldState;
+    }
+
+    @Override
+    public P next() {
+        return this.predicate;
+    }
+}
@@ -18,6 +18,9 @@ package com.alibaba.graphscope.gaia.plan.strategy;
 import org.apache.tinkerpop.gremlin.process.traversal.Step;
 import org.apache.tinkerpop.gremlin.process.traversal.Traversal;
 import org.apache.tinkerpop.gremlin.process.traversal.TraversalStrategy;
+import org.apache.tinkerpop.gremlin.process.traversal.step.TraversalParent;
+import org.apache.tinkerpop.gremlin.process.traversal.step.filter.ConnectiveStep;
+import org.apache.tinkerpop.gremlin.process.traversal.step.filter.NotStep;
 import org.apache.tinkerpop.gremlin.process.traversal.step.filter.TraversalFilterStep;
 import org.apache.tinkerpop.gremlin.process.traversal.strategy.AbstractTraversalStrategy;
 
@@ -38,11 +41,16 @@ public class WhereEndLimitStrategy extends AbstractTraversalStrategy<TraversalSt
     public void apply(Traversal.Admin<?, ?> traversal) {
         List<Step> stepList = traversal.getSteps();
         for (Step step : stepList) {
-            if (step instanceof TraversalFilterStep) {
-                List<Traversal.Admin> subList = ((TraversalFilterStep) step).getLocalChildren();
+            if (step instanceof TraversalFilterStep || step instanceof NotStep) {
+                List<Traversal.Admin> subList = ((TraversalParent) step).getLocalChildren();
                 if (subList.size() > 0) {
                     subList.get(0).addStep(new HasAnyStep(subList.get(0)));
                 }
+            } else if (step instanceof ConnectiveStep) {
+                List<Traversal.Admin> subList = ((ConnectiveStep) step).getLocalChildren();
+                for (Traversal.Admin sub : subList) {
+                    sub.addStep(new HasAnyStep(sub));
+                }
             }
         }
     }
@@ -37,7 +37,11 @@ import org.apache.tinkerpop.gremlin.process.traversal.step.branch.RepeatStep;
 import org.apache.tinkerpop.gremlin.process.traversal.step.branch.UnionStep;
 import org.apache.tinkerpop.gremlin.process.traversal.step.filter.*;
 import org.apache.tinkerpop.gremlin.process.traversal.step.map.*;
+import org.apache.tinkerpop.gremlin.process.traversal.step.sideEffect.AggregateGlobalStep;
+import org.apache.tinkerpop.gremlin.process.traversal.step.sideEffect.AggregateLocalStep;
 import org.apache.tinkerpop.gremlin.process.traversal.step.sideEffect.IdentityStep;
+import org.apache.tinkerpop.gremlin.process.traversal.step.sideEffect.SideEffectCapStep;
+import org.apache.tinkerpop.gremlin.process.traversal.step.sideEffect.StoreStep;
 import org.apache.tinkerpop.gremlin.process.traversal.step.util.EmptyStep;
 import org.apache.tinkerpop.gremlin.process.traversal.util.TraversalHelper;
 import org.apache.tinkerpop.gremlin.process.traversal.util.TraversalRing;
@@ -210,7 +214,16 @@ public class StepMetaCollector extends AttributeTranslator<StepMetaBuilder, Trav
             }
             return element;
         }
-        if (step instanceof TraversalFilterStep || step instanceof BySubTaskStep) {
+        if (step instanceof ConnectiveStep) {
+            // create a sub traversal for each branch
+            int i = 0;
+            for (Object sub : ((ConnectiveStep) step).getLocalChildren()) {
+                newTraversal((Traversal.Admin) sub, head.fork(), metaId.fork(stepId.getStepId(), i), metaBuilder.getConf()).translate();
+                ++i;
+            }
+            return head;
+        }
+        if (step instanceof TraversalFilterStep || step instanceof NotStep || step instanceof BySubTaskStep) {
             // create sub traversal
             Traversal.Admin sub = ((TraversalParent) step).getLocalChildren().get(0);
             newTraversal(sub, head.fork(), metaId.fork(stepId.getStepId(), 0), metaBuilder.getConf()).translate();
@@ -219,6 +232,15 @@ public class StepMetaCollector extends AttributeTranslator<StepMetaBuilder, Trav
         if (step instanceof HasStep || step instanceof PropertyIdentityStep || step instanceof IdentityStep || step instanceof PathFilterStep) {
             return head;
         }
+        if (step instanceof AggregateGlobalStep || step instanceof AggregateLocalStep || step instanceof StoreStep) {
+            return head;
+        }
+        if (step instanceof SideEffectCapStep) {
+            return new TraverserElement(new CompositeObject(List.class, Collections.singletonList(new CompositeObject(Object.class))));
+        }
+        if (step instanceof WherePredicateStep && PlanUtils.getWithinSideKey((WherePredicateStep) step).isPresent()) {
+            return head;
+        }
         if (step instanceof WherePredicateStep) {
             TraversalRing modulateBy = PlanUtils.getTraversalRing(((WherePredicateStep) step).getLocalChildren(), true);
             Optional<String> startKey = ((WherePredicateStep) step).getStartKey();
@@ -295,6 +317,10 @@ public class StepMetaCollector extends AttributeTranslator<StepMetaBuilder, Trav
             // for next unfold step
             return new TraverserElement(new CompositeObject(List.class, Arrays.asList(new CompositeObject(Integer.class))));
         }
+        if (step instanceof SumGlobalStep || step instanceof MinGlobalStep || step instanceof MaxGlobalStep
+                || step instanceof MeanGlobalStep) {
+            return new TraverserElement(new CompositeObject(List.class, Arrays.asList(new CompositeObject(Number.class))));
+        }
         if (step instanceof UnionStep) {
             TraverserElement element = null;
             boolean findIdentity = false;
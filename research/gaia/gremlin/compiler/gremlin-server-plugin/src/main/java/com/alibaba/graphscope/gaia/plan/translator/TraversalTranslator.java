@@ -19,6 +19,7 @@ import com.alibaba.pegasus.builder.AbstractBuilder;
 import com.alibaba.pegasus.builder.JobBuilder;
 import com.alibaba.pegasus.builder.ReduceBuilder;
 import com.alibaba.pegasus.service.protocol.PegasusClient;
+import com.alibaba.graphscope.gaia.plan.PlanUtils;
 import com.alibaba.graphscope.gaia.plan.strategy.DummyStep;
 import com.alibaba.graphscope.gaia.plan.translator.builder.PlanConfig;
 import com.alibaba.graphscope.gaia.plan.translator.builder.StepBuilder;
@@ -42,9 +43,12 @@ public class TraversalTranslator extends AttributeTranslator<TraversalBuilder, A
             List<Step> steps = t.getAdmin().getSteps();
             for (Step s : steps) {
                 if (s instanceof DummyStep) continue;
+                // the shuffle right before may be chained into the operators of the step
+                int start = builder.getPlan().getPlan().size() - 1;
                 StepBuilder stepBuilder = (new StepBuilder(s, builder)).setConf(t.getConf());
                 (new StepTranslator(stepBuilder)).translate();
                 builder = stepBuilder.getJobBuilder();
+                builder.getPlan().nameFrom(start, PlanUtils.stepName(s));
             }
             if (builder instanceof ReduceBuilder) {
                 builder = ((ReduceBuilder) builder).unfold(ByteString.EMPTY);
@@ -59,6 +59,14 @@ public class DefaultResultParser implements ResultParser {
             resultData.getValueList().getItemList().forEach(k -> {
                 result.add(parseValue(k));
             });
+        } else if (resultData.getInnerCase() == GremlinResult.Result.InnerCase.ITEMS) {
+            resultData.getItems().getItemList().forEach(k -> {
+                Object item = parseResultItem(k);
+                // bulk of 0 is the same as 1
+                for (long i = 0; i < Math.max(k.getBulk(), 1); ++i) {
+                    result.add(item);
+                }
+            });
         } else {
             throw new UnsupportedOperationException("");
         }
@@ -129,11 +137,40 @@ public class DefaultResultParser implements ResultParser {
                 result.add(parseValue(e));
             });
             return result;
+        } else if (pairElement.getInnerCase() == GremlinResult.PairElement.InnerCase.ITEM) {
+            return parseResultItem(pairElement.getItem());
         } else {
             throw new UnsupportedOperationException("parse pair element not support " + pairElement.getInnerCase());
         }
     }
 
+    protected Object parseResultItem(GremlinResult.ResultItem item) {
+        if (item.getInnerCase() == GremlinResult.ResultItem.InnerCase.ELEMENT) {
+            return parseElement(item.getElement());
+        } else if (item.getInnerCase() == GremlinResult.ResultItem.InnerCase.VALUE) {
+            return parseValue(item.getValue());
+        } else if (item.getInnerCase() == GremlinResult.ResultItem.InnerCase.PATH) {
+            return parsePath(item.getPath());
+        } else if (item.getInnerCase() == GremlinResult.ResultItem.InnerCase.LIST) {
+            List<Object> result = new ArrayList<>();
+            item.getList().getItemList().forEach(e -> {
+                Object value = parseResultItem(e);
+                for (long i = 0; i < Math.max(e.getBulk(), 1); ++i) {
+                    result.add(value);
+                }
+            });
+            return result;
+        } else if (item.getInnerCase() == GremlinResult.ResultItem.InnerCase.MAP) {
+            Map<Object, Object> result = new LinkedHashMap<>();
+            item.getMap().getEntryList().forEach(e -> {
+                result.put(parseResultItem(e.getKey()), parseResultItem(e.getValue()));
+            });
+            return result;
+        } else {
+            throw new UnsupportedOperationException("parse result item not support " + item.getInnerCase());
+        }
+    }
+
     protected Object parseValue(Common.Value value) {
         if (value.getItemCase() == Common.Value.ItemCase.BOOLEAN) {
             return value.getBoolean();
@@ -57,6 +57,8 @@ public class GremlinResultProcessor implements ResultProcessor {
                         resultCollectors.addAll(resultParser.parseFrom(resultData));
                     } else if (response.getResultCase() == PegasusClient.JobResponse.ResultCase.ERR) {
                         logger.debug("error is {}", response.getErr());
+                    } else if (response.getResultCase() == PegasusClient.JobResponse.ResultCase.COMPLETE) {
+                        logger.debug("job complete with {}", response.getComplete().getStats());
                     }
                 }
             } catch (Exception e) {
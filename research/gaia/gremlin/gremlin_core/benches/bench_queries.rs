@@ -25,7 +25,9 @@ use crate::common::benchmark::{
     initialize, read_pb_request, start_bench_service, submit_query, BenchJobFactory,
     BENCHMARK_PARAM_PATH, BENCHMARK_PLAN_PATH, ID,
 };
+use gremlin_core::process::metrics::take_job_metrics;
 use gremlin_core::process::traversal::traverser::Requirement;
+use gremlin_core::structure::cache::{CACHE_HIT_COUNTER, CACHE_MISS_COUNTER};
 use pegasus_server::JobRequest;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
@@ -153,6 +155,49 @@ fn bench_three_hop_without_path(b: &mut Bencher) {
     bench_queries(b, WhichQuery::ThreeHop, Requirement::OBJECT);
 }
 
+/// Bench the query with the adjacency cache of `cache_limit` bytes per worker, and report the
+/// hit rate of the cache, to compare with the same query without the cache
+fn bench_queries_with_cache(
+    b: &mut Bencher, which: WhichQuery, requirement: Requirement, cache_limit: u64,
+) {
+    initialize();
+    let bench_job_factory = BenchJobFactory::new(
+        prepare_src_ids(which).iter().map(|id| *id as ID).collect(),
+        requirement,
+    );
+    let service = start_bench_service(bench_job_factory);
+    let pb_request = prepare_pb_request(which).expect("read pb failed");
+    let (mut hits, mut misses) = (0, 0);
+    b.iter(|| {
+        let mut job_req = pb_request.clone();
+        incr_request_job_id(&mut job_req);
+        job_req.conf.as_mut().expect("no job_conf").cache_limit = cache_limit;
+        let job_id = job_req.conf.as_ref().expect("no job_conf").job_id;
+        submit_query(&service, job_req);
+        for ((_, name), value) in take_job_metrics(job_id) {
+            match name {
+                CACHE_HIT_COUNTER => hits += value,
+                CACHE_MISS_COUNTER => misses += value,
+                _ => (),
+            }
+        }
+    });
+    if hits + misses > 0 {
+        println!(
+            "adjacency cache of {} bytes: {} hits, {} misses, hit rate {:.2}%",
+            cache_limit,
+            hits,
+            misses,
+            hits as f64 * 100.0 / (hits + misses) as f64
+        );
+    }
+}
+
+#[bench]
+fn bench_three_hop_with_cache(b: &mut Bencher) {
+    bench_queries_with_cache(b, WhichQuery::ThreeHop, Requirement::OBJECT, 64 << 20);
+}
+
 #[bench]
 //g.V().out("PERSON_KNOWS_PERSON").out("PERSON_KNOWS_PERSON").out("PERSON_KNOWS_PERSON").out("PERSON_KNOWS_PERSON")
 fn bench_four_hop(b: &mut Bencher) {
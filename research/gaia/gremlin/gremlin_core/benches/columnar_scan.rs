@@ -0,0 +1,90 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! A scan of 10M vertices filtered by `has("age", gt(50))` and counted, i.e. the fused source of
+//! `g.V().has("age", gt(50)).count()`, where each vertex is converted to a traverser before the
+//! has step tests it, or the has step is evaluated over the columns of the scanned batches and
+//! only the selected vertices are converted. The scan goes through a batch of 1024 vertices for
+//! 9766 times.
+
+#![feature(test)]
+
+extern crate test;
+
+use dyn_type::Object;
+use gremlin_core::process::columnar::{eval_batch, ColumnarStep};
+use gremlin_core::process::traversal::traverser::Traverser;
+use gremlin_core::structure::{
+    has_property_gt, DefaultDetails, ElementFilter, Filter, Label, Vertex, DEFAULT_BATCH_SIZE,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use test::Bencher;
+
+const SCANNED: usize = 10_000_000;
+
+fn vertices() -> Vec<Vertex> {
+    (0..DEFAULT_BATCH_SIZE as u64)
+        .map(|id| {
+            let mut properties = HashMap::new();
+            properties.insert("age".to_owned(), Object::from((id * 37 % 100) as i32));
+            let label = Label::Id(0.into());
+            Vertex::new(
+                id as _,
+                Some(label.clone()),
+                DefaultDetails::new_with_prop(id as _, label, properties),
+            )
+        })
+        .collect()
+}
+
+fn filter() -> Filter<Vertex, ElementFilter> {
+    Filter::with(has_property_gt("age".to_owned(), 50))
+}
+
+#[bench]
+fn scan_filter_count_rows_10m(b: &mut Bencher) {
+    let vertices = vertices();
+    let filter = filter();
+    b.iter(|| {
+        let mut count = 0;
+        for _ in 0..SCANNED / DEFAULT_BATCH_SIZE {
+            for v in vertices.iter() {
+                let traverser = Traverser::new(v.clone());
+                let element = traverser.get_element().expect("vertex not found");
+                if let Some(v) = element.as_vertex() {
+                    if filter.test(v).unwrap_or(false) {
+                        count += 1;
+                    }
+                }
+            }
+        }
+        count
+    })
+}
+
+#[bench]
+fn scan_filter_count_columns_10m(b: &mut Bencher) {
+    let vertices = vertices();
+    let steps = vec![ColumnarStep::Has(Arc::new(filter()))];
+    let keys = vec!["age".to_owned()];
+    b.iter(|| {
+        let mut count = 0;
+        for _ in 0..SCANNED / DEFAULT_BATCH_SIZE {
+            count += eval_batch(&vertices, &keys, &steps).len();
+        }
+        count
+    })
+}
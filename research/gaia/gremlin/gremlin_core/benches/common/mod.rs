@@ -39,8 +39,9 @@ pub mod benchmark {
         CompareFunction, EncodeFunction, FilterFunction, FlatMapFunction, LeftJoinFunction,
         MapFunction, MultiRouteFunction, RouteFunction,
     };
-    use pegasus::{Configuration, StartupError};
+    use pegasus::{Configuration, JobConf, StartupError};
     use pegasus_common::collections::{Collection, CollectionFactory, Set};
+    use pegasus_server::error::QueryError;
     use pegasus_server::factory::{CompileResult, FoldFunction, GroupFunction, JobCompiler};
     use pegasus_server::service::{Output, Service};
     use pegasus_server::{JobRequest, JobResponse, JobResult};
@@ -173,6 +174,10 @@ pub mod benchmark {
         fn sink(&self, res: &[u8]) -> CompileResult<Box<dyn EncodeFunction<Traverser>>> {
             self.inner.sink(res)
         }
+
+        fn prepare(&self, graph: &str, conf: &mut JobConf) -> Result<(), QueryError> {
+            self.inner.prepare(graph, conf)
+        }
     }
 
     pub fn start_pegasus() {
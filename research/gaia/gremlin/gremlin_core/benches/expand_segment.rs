@@ -0,0 +1,85 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The expansion of the vertices adjacent by an edge label, out of a graph of 100K vertices, each
+//! of 8 out edges of each of 4 labels, i.e. `out('l0')` of every vertex, by the edges of all labels
+//! whose labels are compared one by one, or through the adjacency segment of the label.
+
+#![feature(test)]
+
+extern crate test;
+
+use graph_store::config::JsonConf;
+use graph_store::prelude::{
+    DefaultId, Direction, GlobalStoreTrait, GlobalStoreUpdate, GraphDBConfig, InternalId,
+    LDBCGraphSchema, LargeGraphDB, MutableGraphDB, INVALID_LABEL_ID,
+};
+use test::Bencher;
+
+const VERTICES: usize = 100_000;
+const LABELS: u8 = 4;
+const DEGREE: usize = 8;
+
+const SCHEMA: &str = r#"
+{
+  "vertex_type_map": { "v": 0 },
+  "edge_type_map": { "l0": 0, "l1": 1, "l2": 2, "l3": 3 },
+  "vertex_prop": { "v": [] },
+  "edge_prop": { "l0": [], "l1": [], "l2": [], "l3": [] }
+}
+"#;
+
+fn graph() -> LargeGraphDB<DefaultId, InternalId> {
+    let mut graph: MutableGraphDB<DefaultId, InternalId> = GraphDBConfig::default().new();
+    for id in 0..VERTICES {
+        graph.add_vertex(id, [0.into(), INVALID_LABEL_ID]);
+    }
+    for id in 0..VERTICES {
+        for d in 0..DEGREE {
+            for label in 0..LABELS {
+                let dst = (id * 31 + d * 7919 + label as usize * 104729) % VERTICES;
+                graph.add_edge(id, dst, label.into());
+            }
+        }
+    }
+    let schema = LDBCGraphSchema::from_json(SCHEMA.to_owned()).expect("parse schema error");
+    graph.into_graph(schema)
+}
+
+#[bench]
+fn expand_by_labels(b: &mut Bencher) {
+    let graph = graph();
+    let labels = vec![0.into()];
+    b.iter(|| {
+        let mut count = 0;
+        for id in 0..VERTICES {
+            count += graph.get_out_vertices(id, Some(&labels)).count();
+        }
+        test::black_box(count);
+    })
+}
+
+#[bench]
+fn expand_by_segment(b: &mut Bencher) {
+    let graph = graph();
+    b.iter(|| {
+        let segment = graph.get_adj_segment(0.into(), Direction::Outgoing).unwrap();
+        let mut count = 0;
+        for id in 0..VERTICES {
+            count += graph.get_segment_vertices(segment.clone(), id).count();
+        }
+        test::black_box(count);
+    })
+}
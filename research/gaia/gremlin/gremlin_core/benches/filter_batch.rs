@@ -0,0 +1,84 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The filter of a scan of 10M vertices by one int predicate, i.e. `has("age", gt(50))`, tested
+//! vertex by vertex, or evaluated by batches of the column of ages. The scan goes through a batch
+//! of 1024 vertices for 9766 times, whose column of ages is read out of the vertices each time as
+//! a scan does.
+
+#![feature(test)]
+
+extern crate test;
+
+use dyn_type::Object;
+use gremlin_core::structure::{
+    has_property_gt, ColumnBatch, DefaultDetails, ElementFilter, Filter, Label, Vertex,
+    DEFAULT_BATCH_SIZE,
+};
+use std::collections::HashMap;
+use test::Bencher;
+
+const SCANNED: usize = 10_000_000;
+
+fn vertices() -> Vec<Vertex> {
+    (0..DEFAULT_BATCH_SIZE as u64)
+        .map(|id| {
+            let mut properties = HashMap::new();
+            properties.insert("age".to_owned(), Object::from((id * 37 % 100) as i32));
+            let label = Label::Id(0.into());
+            Vertex::new(
+                id as _,
+                Some(label.clone()),
+                DefaultDetails::new_with_prop(id as _, label, properties),
+            )
+        })
+        .collect()
+}
+
+fn filter() -> Filter<Vertex, ElementFilter> {
+    Filter::with(has_property_gt("age".to_owned(), 50))
+}
+
+#[bench]
+fn filter_scalar_10m(b: &mut Bencher) {
+    let vertices = vertices();
+    let filter = filter();
+    b.iter(|| {
+        let mut selected = 0;
+        for _ in 0..SCANNED / DEFAULT_BATCH_SIZE {
+            for v in vertices.iter() {
+                if filter.test(v).unwrap_or(false) {
+                    selected += 1;
+                }
+            }
+        }
+        selected
+    })
+}
+
+#[bench]
+fn filter_batch_10m(b: &mut Bencher) {
+    let vertices = vertices();
+    let filter = filter();
+    let keys = vec!["age".to_owned()];
+    b.iter(|| {
+        let mut selected = 0;
+        for _ in 0..SCANNED / DEFAULT_BATCH_SIZE {
+            let batch = ColumnBatch::with_properties(&vertices, &keys);
+            selected += filter.select_batch(&batch).count_ones();
+        }
+        selected
+    })
+}
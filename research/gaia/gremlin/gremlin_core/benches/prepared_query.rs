@@ -0,0 +1,80 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The point queries `g.V().has("name", x).out().has("age", gt(y))` on the demo graph, each of
+//! which is built, validated and decoded by the workers as a new traversal, or run by binding the
+//! parameters to a traversal prepared once, whose steps are neither validated nor decoded again.
+
+#![feature(test)]
+
+extern crate test;
+
+use dyn_type::Object;
+use gremlin_core::create_demo_graph;
+use gremlin_core::traversal::*;
+use pegasus::{Configuration, StartupError};
+use pegasus_server::generated::protocol as server_pb;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Once;
+use test::Bencher;
+
+static INIT: Once = Once::new();
+static JOB_ID: AtomicU64 = AtomicU64::new(0);
+
+const NAMES: [&str; 4] = ["marko", "josh", "peter", "vadas"];
+
+fn initialize() {
+    INIT.call_once(|| match pegasus::startup(Configuration::singleton()) {
+        Ok(_) => create_demo_graph(),
+        Err(StartupError::AlreadyStarted(_)) => {}
+        Err(_) => panic!("start pegasus failed"),
+    });
+}
+
+fn job_conf() -> server_pb::JobConfig {
+    server_pb::JobConfig {
+        job_id: JOB_ID.fetch_add(1, Ordering::SeqCst),
+        job_name: "prepared_query".to_owned(),
+        workers: 1,
+        ..Default::default()
+    }
+}
+
+fn point_query(name: &str, age: i32) -> GraphTraversal {
+    Graph::traversal().v().has("name", eq(name)).out(&[]).has("age", gt(age))
+}
+
+#[bench]
+fn point_query_unprepared(b: &mut Bencher) {
+    initialize();
+    let mut i = 0;
+    b.iter(|| {
+        i += 1;
+        let query = point_query(NAMES[i % NAMES.len()], i as i32 % 40);
+        query.run(job_conf()).count()
+    })
+}
+
+#[bench]
+fn point_query_prepared(b: &mut Bencher) {
+    initialize();
+    let prepared = point_query("marko", 0).prepare().expect("prepare query failure");
+    let mut i = 0;
+    b.iter(|| {
+        i += 1;
+        let params = [Object::from(NAMES[i % NAMES.len()]), Object::from(i as i32 % 40)];
+        prepared.run(&params, job_conf()).expect("bind parameters failure").count()
+    })
+}
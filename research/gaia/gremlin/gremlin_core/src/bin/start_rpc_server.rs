@@ -15,14 +15,21 @@
 
 extern crate clap;
 
+use graph_store::partition::{ExplicitPartition, HashPartition, ReplicatedPartition};
 use gremlin_core::compiler::GremlinJobCompiler;
-use gremlin_core::{create_demo_graph, register_gremlin_types, Partition};
+use gremlin_core::process::limits::{set_default_limits, AccessLimits};
+use gremlin_core::structure::set_default_float_epsilon;
+use gremlin_core::{
+    create_demo_graph, register_gremlin_types, GraphPartition, GraphPartitioner, Partition,
+};
 use log::info;
+use pegasus::api::function::ReplicaPolicy;
 use pegasus::Configuration;
 use pegasus_server::config::combine_config;
 use pegasus_server::rpc::start_debug_rpc_server;
 use pegasus_server::service::Service;
 use pegasus_server::{CommonConfig, HostsConfig};
+use std::sync::Arc;
 use structopt::StructOpt;
 
 #[derive(Debug, Clone, StructOpt)]
@@ -58,6 +65,59 @@ pub struct ServerConfig {
     pub config: String,
     #[structopt(long = "report", help = "the option to report the job latency and memory usage")]
     pub report: bool,
+    #[structopt(
+        long = "partition_map",
+        default_value = "",
+        help = "the path of the file that maps vertices to partitions, hash partition if not given"
+    )]
+    pub partition_map: String,
+    #[structopt(
+        long = "partitions",
+        default_value = "0",
+        help = "the number of partitions in the partition map, the number of servers if not given"
+    )]
+    pub partitions: usize,
+    #[structopt(
+        long = "replicas",
+        default_value = "1",
+        help = "the number of servers holding each partition, placed on the consecutive servers"
+    )]
+    pub replicas: usize,
+    #[structopt(
+        long = "round_robin",
+        help = "route the lookups to the replicas in turn, instead of preferring the local ones"
+    )]
+    pub round_robin: bool,
+    #[structopt(
+        long = "max_vertices",
+        default_value = "0",
+        help = "the most vertices a query can access in each server by default, 0 for unlimited"
+    )]
+    pub max_vertices: u64,
+    #[structopt(
+        long = "max_edges",
+        default_value = "0",
+        help = "the most edges a query can access in each server by default, 0 for unlimited"
+    )]
+    pub max_edges: u64,
+    #[structopt(
+        long = "max_results",
+        default_value = "0",
+        help = "the most results a query can return in each server by default, 0 for unlimited"
+    )]
+    pub max_results: u64,
+    #[structopt(
+        long = "float_epsilon",
+        default_value = "1e-9",
+        help = "the relative epsilon of eq and neq on floats in the filters by default, 0 for exact"
+    )]
+    pub float_epsilon: f64,
+    #[structopt(
+        long = "warm_file",
+        default_value = "",
+        help = "the file to save the plan cache and prepared queries to as drained, and load from"
+    )]
+    pub warm_file: String,
 }
 
 #[tokio::main]
@@ -80,6 +140,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     create_demo_graph();
     register_gremlin_types().expect("register gremlin types failed");
+    set_default_limits(AccessLimits::new(
+        server_config.max_vertices,
+        server_config.max_edges,
+        server_config.max_results,
+    ));
+    set_default_float_epsilon(server_config.float_epsilon);
 
     if let Some(engine_config) = config {
         pegasus::startup(engine_config).unwrap();
@@ -87,10 +153,44 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         pegasus::startup(Configuration::singleton()).unwrap();
     }
 
+    let warm_file = server_config.warm_file.clone();
+    let warm_up = move |compiler: GremlinJobCompiler| {
+        if warm_file.is_empty() {
+            compiler
+        } else {
+            compiler.with_warm_file(warm_file)
+        }
+    };
     info!("try to start rpc server;");
-    let partition = Partition { num_servers: num_servers.clone() };
-    let factory = GremlinJobCompiler::new(partition, num_servers, server_config.server_id);
-    let service = Service::new(factory);
+    let service = if server_config.partition_map.is_empty() && server_config.replicas <= 1 {
+        let partition = Partition { num_servers };
+        let compiler = GremlinJobCompiler::new(partition, num_servers, server_config.server_id);
+        Service::new(warm_up(compiler))
+    } else {
+        // there can be more partitions than servers, e.g. one partition per worker
+        let partitions =
+            if server_config.partitions > 0 { server_config.partitions } else { num_servers };
+        let mut partition: Arc<dyn GraphPartition> = if server_config.partition_map.is_empty() {
+            Arc::new(HashPartition::new(partitions))
+        } else {
+            let partition =
+                ExplicitPartition::from_file(&server_config.partition_map, '|', partitions)
+                    .map_err(|e| format!("load partition map error: {:?}", e))?;
+            Arc::new(partition)
+        };
+        if server_config.replicas > 1 {
+            partition = Arc::new(ReplicatedPartition::new(partition, server_config.replicas));
+        }
+        let policy = if server_config.round_robin {
+            ReplicaPolicy::RoundRobin
+        } else {
+            ReplicaPolicy::PreferLocal
+        };
+        let partitioner = GraphPartitioner::new(partition, num_servers);
+        let compiler = GremlinJobCompiler::new(partitioner, num_servers, server_config.server_id)
+            .with_replica_policy(policy);
+        Service::new(warm_up(compiler))
+    };
     start_debug_rpc_server(addr.parse().unwrap(), service, server_config.report).await?;
 
     Ok(())
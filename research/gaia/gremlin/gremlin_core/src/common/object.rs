@@ -16,6 +16,7 @@
 use crate::common::serde_dyn::de_dyn_obj;
 use crate::common::DynType;
 use crate::generated::common as pb;
+use crate::structure::codec::{ParseError, ARRAY_VALUE};
 use crate::structure::Label;
 use core::any::TypeId;
 use pegasus::codec::{Decode, Encode, ReadExt, WriteExt};
@@ -1091,7 +1092,7 @@ impl From<Label> for Object {
     fn from(label: Label) -> Self {
         match label {
             Label::Str(s) => Object::String(s),
-            Label::Id(id) => Object::Primitive(Primitives::Integer(id as i32)),
+            Label::Id(id) => Object::Primitive(Primitives::Integer(id.into())),
         }
     }
 }
@@ -1100,14 +1101,21 @@ impl From<&Label> for Object {
     fn from(label: &Label) -> Self {
         match label {
             Label::Str(s) => Object::String(s.to_string()),
-            Label::Id(id) => Object::Primitive(Primitives::Integer(*id as i32)),
+            Label::Id(id) => Object::Primitive(Primitives::Integer((*id).into())),
         }
     }
 }
 
-impl From<&pb::Value> for Option<Object> {
-    fn from(raw: &pb::Value) -> Self {
-        match &raw.item {
+/// The json values without a counterpart of objects, i.e. the arrays, the maps and the nulls
+pub const JSON_COMPOSITE: &str = "conversion of json arrays, objects and nulls";
+
+/// Convert a value, or `None` for the none value, where the lists fail by
+/// `ParseError::Unsupported` instead of panicking
+impl TryFrom<&pb::Value> for Option<Object> {
+    type Error = ParseError;
+
+    fn try_from(raw: &pb::Value) -> Result<Self, Self::Error> {
+        let object = match &raw.item {
             Some(pb::value::Item::Blob(blob)) => {
                 let mut bytes = vec![0; blob.len()];
                 bytes.copy_from_slice(blob);
@@ -1118,19 +1126,24 @@ impl From<&pb::Value> for Option<Object> {
             Some(pb::value::Item::I64(item)) => Some((*item).into()),
             Some(pb::value::Item::F64(item)) => Some((*item).into()),
             Some(pb::value::Item::Str(item)) => Some(item.as_str().into()),
-            Some(pb::value::Item::I32Array(_)) => unimplemented!(),
-            Some(pb::value::Item::I64Array(_)) => unimplemented!(),
-            Some(pb::value::Item::F64Array(_)) => unimplemented!(),
-            Some(pb::value::Item::StrArray(_)) => unimplemented!(),
+            Some(pb::value::Item::I32Array(_))
+            | Some(pb::value::Item::I64Array(_))
+            | Some(pb::value::Item::F64Array(_))
+            | Some(pb::value::Item::StrArray(_)) => {
+                return Err(ParseError::Unsupported(ARRAY_VALUE))
+            }
             Some(pb::value::Item::None(_)) => None,
             _ => None,
-        }
+        };
+        Ok(object)
     }
 }
 
-impl From<&serde_json::Value> for Object {
-    fn from(val: &serde_json::Value) -> Self {
-        match val {
+impl TryFrom<&serde_json::Value> for Object {
+    type Error = ParseError;
+
+    fn try_from(val: &serde_json::Value) -> Result<Self, Self::Error> {
+        let object = match val {
             serde_json::Value::Bool(item) => Object::from(*item as i8),
             Value::Number(num) => {
                 if num.is_i64() {
@@ -1142,10 +1155,11 @@ impl From<&serde_json::Value> for Object {
                 }
             }
             Value::String(item) => Object::from(item.as_str()),
-            Value::Array(_) => unimplemented!(),
-            Value::Object(_) => unimplemented!(),
-            _ => unimplemented!(),
-        }
+            Value::Array(_) | Value::Object(_) | Value::Null => {
+                return Err(ParseError::Unsupported(JSON_COMPOSITE))
+            }
+        };
+        Ok(object)
     }
 }
 
@@ -13,28 +13,194 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
+use crate::plan_cache::{PlanCache, StepBindings, STEP_BINDINGS};
+use crate::prepared::{self, PreparedQueries, PreparedQuery};
+use crate::process::adaptive::{adaptive_has, Adaptive};
+use crate::process::columnar::fuse_steps;
+use crate::process::expand::fuse_expand;
+use crate::process::metrics;
+use crate::process::side_store::{bind_job_side_store, get_job_side_store};
+use crate::process::simplify::{never_matches, simplify_request};
+use crate::process::sorted_expand::push_down_order;
 use crate::process::traversal::step::*;
 use crate::process::traversal::step::{BySubJoin, HasAnyJoin};
 use crate::process::traversal::traverser::Traverser;
-use crate::structure::Element;
+use crate::session::{decode_result, get_session_job, SessionJob};
+use crate::structure::codec::ParseError;
+use crate::structure::filter::codec::pb_value_to_object;
+use crate::structure::{
+    get_graph_limits, get_graph_name, BoundGraph, Direction, Element, GRAPH_NAMED_FEATURE,
+    JOB_GRAPH,
+};
+use crate::validate::TypeCheck;
+use crate::warm_state;
 use crate::Partitioner;
-use crate::{generated as pb, TraverserSinkEncoder};
+use crate::{generated as pb, Detach, DynError, FromPb, TraverserSinkEncoder};
+use graph_store::common::LabelId;
+use graph_store::parser::DataType;
+use graph_store::schema::{GraphSchemaInfo, LabelSchema};
 use pegasus::api::function::*;
-use pegasus::BuildJobError;
+use pegasus::{BuildJobError, JobConf};
 use pegasus_common::collections::{Collection, CollectionFactory, Set};
-use pegasus_server::factory::{CompileResult, FoldFunction, GroupFunction, JobCompiler};
-use prost::Message;
+use pegasus_server::error::{ErrorCause, QueryError};
+use pegasus_server::factory::{
+    AdaptivePoint, CompileResult, FoldFunction, GroupFunction, JobCompiler, PlanError, SourceAccess,
+};
+use pegasus_server::generated::protocol as server_pb;
+use prost::{DecodeError, Message};
+use std::convert::TryFrom;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
 pub struct GremlinJobCompiler {
     partitioner: Arc<dyn Partitioner>,
     num_servers: usize,
     server_index: u64,
+    plan_cache: Arc<PlanCache>,
+    records_per_worker: usize,
+    shortcut_enabled: bool,
+    columnar_enabled: bool,
+    fused_expand_enabled: bool,
+    sorted_expand_enabled: bool,
+    adaptive: Adaptive,
+    simplify_enabled: bool,
+    type_check: TypeCheck,
+    replica_policy: ReplicaPolicy,
+    prepared: Arc<PreparedQueries>,
+    // the file the plan cache and the prepared queries are saved to as the server is drained
+    warm_file: Option<PathBuf>,
 }
 
+/// The default number of records scanned from the graph by each worker, which decides the number
+/// of workers of the jobs with the `Auto` worker hint
+pub const DEFAULT_RECORDS_PER_WORKER: usize = 100_000;
+
 impl GremlinJobCompiler {
     pub fn new<D: Partitioner>(partitioner: D, num_servers: usize, server_index: u64) -> Self {
-        GremlinJobCompiler { partitioner: Arc::new(partitioner), num_servers, server_index }
+        GremlinJobCompiler {
+            partitioner: Arc::new(partitioner),
+            num_servers,
+            server_index,
+            plan_cache: Arc::new(PlanCache::default()),
+            records_per_worker: DEFAULT_RECORDS_PER_WORKER,
+            shortcut_enabled: true,
+            columnar_enabled: true,
+            fused_expand_enabled: true,
+            sorted_expand_enabled: true,
+            adaptive: Adaptive::Off,
+            simplify_enabled: true,
+            type_check: TypeCheck::default(),
+            replica_policy: ReplicaPolicy::PreferLocal,
+            prepared: Arc::new(PreparedQueries::default()),
+            warm_file: None,
+        }
+    }
+
+    /// Set the number of records each worker deserves to scan, see `estimate_workers`
+    pub fn with_records_per_worker(mut self, records_per_worker: usize) -> Self {
+        self.records_per_worker = std::cmp::max(1, records_per_worker);
+        self
+    }
+
+    /// Whether to answer the plans by the statistics of the graph without running the jobs, e.g.
+    /// `g.V().count()`, which is enabled by default, see `count_by_statistics`
+    pub fn with_shortcut(mut self, enabled: bool) -> Self {
+        self.shortcut_enabled = enabled;
+        self
+    }
+
+    /// Whether to fuse the leading has and values steps of the plans into the scan of the source,
+    /// which evaluates them over the columns of the scanned batches, enabled by default, see
+    /// `fuse_steps`
+    pub fn with_columnar_scan(mut self, enabled: bool) -> Self {
+        self.columnar_enabled = enabled;
+        self
+    }
+
+    /// Whether to fuse the consecutive vertex steps of the plans into one expansion, e.g. of
+    /// `out("a").out("b")`, if the partitioner allows, enabled by default, see `fuse_expand`
+    pub fn with_fused_expand(mut self, enabled: bool) -> Self {
+        self.fused_expand_enabled = enabled;
+        self
+    }
+
+    /// Whether to push the order with a limit into the edge step right before it, e.g. of
+    /// `outE("knows").order().by("weight", desc).limit(10)`, if the edges are stored sorted by the
+    /// property, enabled by default, see `push_down_order`
+    pub fn with_sorted_expand(mut self, enabled: bool) -> Self {
+        self.sorted_expand_enabled = enabled;
+        self
+    }
+
+    /// How to order the consecutive has steps of the plans, which are evaluated in the order of
+    /// the plans by default, see `adaptive_has`
+    pub fn with_adaptive(mut self, adaptive: Adaptive) -> Self {
+        self.adaptive = adaptive;
+        self
+    }
+
+    /// Whether to simplify the predicates of the plans before the jobs are built, e.g. merging
+    /// `has("age", gt(10)).has("age", gt(20))` into `has("age", gt(20))`, enabled by default, see
+    /// `simplify_request`
+    pub fn with_simplify(mut self, enabled: bool) -> Self {
+        self.simplify_enabled = enabled;
+        self
+    }
+
+    /// Whether to reject the plans comparing constants to the properties of incomparable types by
+    /// the schema of the graph, or only to warn of them, which are rejected by default
+    pub fn with_type_check(mut self, type_check: TypeCheck) -> Self {
+        self.type_check = type_check;
+        self
+    }
+
+    /// How the traversers are routed to the replicas of the vertices they look up, if the graph
+    /// is replicated, which prefers the replicas in current server by default
+    pub fn with_replica_policy(mut self, policy: ReplicaPolicy) -> Self {
+        self.replica_policy = policy;
+        self
+    }
+
+    /// Share the plan cache among compilers, instead of a cache of its own
+    pub fn with_plan_cache(mut self, plan_cache: Arc<PlanCache>) -> Self {
+        self.plan_cache = plan_cache;
+        self
+    }
+
+    /// Prepare the query of the request to be executed again and again with only its parameters
+    /// bound, whose literals are checked by the schema as the submitted queries are
+    pub fn prepare_query(&self, req: &server_pb::JobRequest) -> Result<PreparedQuery, PlanError> {
+        PreparedQuery::prepare(req, self.type_check)
+    }
+
+    /// Warm up the plan cache and the prepared queries with the states saved to the file, which
+    /// are saved again as the server is drained, see `warm_state`; It is set after the plan
+    /// cache and the type check, as the states are loaded here against them.
+    pub fn with_warm_file<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        let path = path.into();
+        let features = self.features();
+        match warm_state::load(&path, &self.plan_cache, &self.prepared, &features, self.type_check)
+        {
+            Ok(report) => info!("warmed up by {:?}: {:?}", path, report),
+            Err(e) => warn!("fail to warm up by {:?}: {}", path, e),
+        }
+        self.warm_file = Some(path);
+        self
+    }
+
+    /// Prepare the query of the request and register it by its handle, which is kept across the
+    /// restarts of the server with a warm file, see `PreparedQuery::handle`
+    pub fn register_prepared(&self, req: &server_pb::JobRequest) -> Result<u64, PlanError> {
+        self.prepared.register(req, self.type_check)
+    }
+
+    pub fn get_prepared(&self, handle: u64) -> Option<Arc<PreparedQuery>> {
+        self.prepared.get(handle)
+    }
+
+    pub fn get_plan_cache(&self) -> &Arc<PlanCache> {
+        &self.plan_cache
     }
 
     pub fn get_num_servers(&self) -> usize {
@@ -44,20 +210,189 @@ impl GremlinJobCompiler {
     pub fn get_server_index(&self) -> u64 {
         self.server_index
     }
+
+    pub fn get_partitioner(&self) -> Arc<dyn Partitioner> {
+        self.partitioner.clone()
+    }
+
+    /// Decode the step by the bindings of current job if it is running, so that each step of the
+    /// job is bound once, instead of once per worker
+    fn decode_step(&self, res: &[u8]) -> Result<pb::gremlin::GremlinStep, BuildJobError> {
+        let step = match pegasus::get_job_resource::<StepBindings>(STEP_BINDINGS) {
+            Ok(bindings) => bindings.get_step(&self.plan_cache, res),
+            Err(_) => self.plan_cache.get_step(res),
+        };
+        step.map_err(decode_error)
+    }
+
+    /// Start from the result saved in the session of the job, e.g. `SessionRef("x")`
+    fn session_source(
+        &self, name: &str, session_job: Option<&Arc<SessionJob>>,
+    ) -> CompileResult<Box<dyn Iterator<Item = Traverser> + Send>> {
+        let session_job = session_job
+            .ok_or_else(|| format!("session result {} is referenced out of sessions", name))?;
+        let session = session_job.get_session();
+        let result = session.get_result(name).ok_or_else(|| {
+            format!(
+                "session result {} is referenced before stored in session {}",
+                name,
+                session.id()
+            )
+        })?;
+        // each worker of current server takes its own part of the result saved in the server
+        let (index, peers) = match pegasus::get_current_worker() {
+            Some(worker_id) => {
+                let peers = std::cmp::max(1, worker_id.peers as usize / self.num_servers);
+                (worker_id.index as usize % peers, peers)
+            }
+            None => (0, 1),
+        };
+        let mut traversers = vec![];
+        for bytes in result.iter().skip(index).step_by(peers) {
+            let traverser = decode_result(bytes)
+                .map_err(|e| format!("fail to decode session result {}: {}", name, e))?;
+            traversers.push(traverser);
+        }
+        Ok(Box::new(traversers.into_iter()))
+    }
+
+    /// Estimate the number of records the source scans in current server, by the given ids, or
+    /// by the statistics of the named graph for the given labels, or `None` if unknown
+    fn estimate_scan_size(&self, graph: &str, step: &pb::gremlin::GremlinStep) -> Option<usize> {
+        let graph_step = match step.step.as_ref()? {
+            pb::gremlin::gremlin_step::Step::GraphStep(graph_step) => graph_step,
+            _ => return None,
+        };
+        if !graph_step.ids.is_empty() {
+            let num_servers = std::cmp::max(1, self.num_servers);
+            return Some(graph_step.ids.len().div_ceil(num_servers));
+        }
+        let statistics = crate::get_named_graph(graph)?.get_statistics()?;
+        let is_edge = graph_step.return_type == pb::gremlin::EntityType::Edge as i32;
+        // the labels out of range are of no vertex or edge
+        let labels = graph_step.labels.iter().filter_map(|l| LabelId::try_from(*l).ok());
+        let size = match (is_edge, graph_step.labels.is_empty()) {
+            (false, true) => statistics.total_vertex_count(),
+            (false, false) => labels.map(|l| statistics.vertex_count(l)).sum(),
+            (true, true) => statistics.total_edge_count(),
+            (true, false) => labels.map(|l| statistics.edge_count(l)).sum(),
+        };
+        Some(size)
+    }
+
+    /// Count the scan of the source by the statistics of the graph, if the plan is nothing but a
+    /// global count, e.g. `g.V().count()`, `g.V().hasLabel("person").count()` or `g.E().count()`
+    fn count_by_statistics(&self, req: &server_pb::JobRequest) -> Option<u64> {
+        // the statistics of a server only count its own partition
+        if !self.shortcut_enabled || self.num_servers > 1 {
+            return None;
+        }
+        let plan = req.plan.as_ref()?;
+        let fold = match plan.plan.as_slice() {
+            [server_pb::OperatorDef {
+                op_kind: Some(server_pb::operator_def::OpKind::Fold(fold)),
+                ..
+            }] => fold,
+            _ => return None,
+        };
+        // the unfold of the count may carry side effects, e.g. `cap("x")`
+        let has_side_effect = fold.unfold.as_ref().map(|f| !f.resource.is_empty()).unwrap_or(false);
+        if fold.accum != server_pb::AccumKind::Cnt as i32
+            || fold.range != server_pb::Range::Global as i32
+            || has_side_effect
+        {
+            return None;
+        }
+        let step = self.decode_step(&req.source.as_ref()?.resource).ok()?;
+        let graph_step = match step.step? {
+            pb::gremlin::gremlin_step::Step::GraphStep(graph_step) => graph_step,
+            _ => return None,
+        };
+        if !graph_step.ids.is_empty() || graph_step.predicates.is_some() {
+            return None;
+        }
+        let graph = crate::get_named_graph(&req.graph)?;
+        if !graph.has_exact_statistics() {
+            return None;
+        }
+        let statistics = graph.get_statistics()?;
+        let mut labels = graph_step
+            .labels
+            .iter()
+            .map(|l| LabelId::try_from(*l))
+            .collect::<Result<Vec<_>, _>>()
+            .ok()?;
+        labels.sort();
+        labels.dedup();
+        let count = if graph_step.return_type == pb::gremlin::EntityType::Edge as i32 {
+            if labels.is_empty() {
+                statistics.total_edge_count()
+            } else {
+                labels.iter().map(|l| statistics.edge_count(*l)).sum()
+            }
+        } else {
+            // a vertex of two labels is counted by either label
+            match labels.as_slice() {
+                [] => statistics.total_vertex_count(),
+                [label] => statistics.vertex_count(*label),
+                _ => return None,
+            }
+        };
+        Some(count as u64)
+    }
+
+    /// The graph step of the source set up for current worker, along with the index of the
+    /// worker, or `None` out of the workers
+    fn graph_source(
+        &self, step: &mut pb::gremlin::GremlinStep,
+    ) -> Result<(GraphVertexStep, Option<usize>), BuildJobError> {
+        let mut graph_step = graph_step_from(step, self.num_servers)?;
+        graph_step.set_server_index(self.server_index);
+        graph_step.set_partitioner(self.partitioner.clone());
+        match pegasus::get_current_worker() {
+            Some(worker_id) => {
+                graph_step.set_num_workers(worker_id.peers as usize / self.num_servers);
+                Ok((graph_step, Some(worker_id.index as usize)))
+            }
+            None => Ok((graph_step, None)),
+        }
+    }
+}
+
+/// Hold the session of the job, if any, as running the job until the source is dropped
+fn join_session(
+    source: Box<dyn Iterator<Item = Traverser> + Send>, session_job: Option<Arc<SessionJob>>,
+) -> Box<dyn Iterator<Item = Traverser> + Send> {
+    match session_job {
+        Some(job) => Box::new(SessionSource { inner: source, _job: job }),
+        None => source,
+    }
+}
+
+/// The workers to look up the element of a traverser, which go to the first worker if it has no
+/// element
+struct ElementReplicas {
+    partitioner: Arc<dyn Partitioner>,
+    num_workers: usize,
+}
+
+impl ReplicaFunction<Traverser> for ElementReplicas {
+    fn replicas(&self, t: &Traverser) -> FnResult<Vec<u64>> {
+        if let Some(e) = t.get_element() {
+            Ok(self.partitioner.get_replicas(&e.id(), self.num_workers))
+        } else {
+            Ok(vec![0])
+        }
+    }
 }
 
 impl JobCompiler<Traverser> for GremlinJobCompiler {
     fn shuffle(&self, _: &[u8]) -> CompileResult<Box<dyn RouteFunction<Traverser>>> {
-        let p = self.partitioner.clone();
         if let Some(worker_id) = pegasus::get_current_worker() {
             let num_workers = worker_id.peers as usize / self.num_servers;
-            Ok(box_route!(move |t: &Traverser| -> u64 {
-                if let Some(e) = t.get_element() {
-                    p.get_partition(&e.id(), num_workers)
-                } else {
-                    0
-                }
-            }))
+            let replicas = ElementReplicas { partitioner: self.partitioner.clone(), num_workers };
+            let route = ReplicaRoute::new(replicas, self.replica_policy, num_workers as u64);
+            Ok(Box::new(route))
         } else {
             Err("worker id not found")?
         }
@@ -68,36 +403,128 @@ impl JobCompiler<Traverser> for GremlinJobCompiler {
     }
 
     fn source(&self, src: &[u8]) -> CompileResult<Box<dyn Iterator<Item = Traverser> + Send>> {
-        let mut step = decode::<pb::gremlin::GremlinStep>(src)?;
-        if let Some(worker_id) = pegasus::get_current_worker() {
-            let num_workers = worker_id.peers as usize / self.num_servers;
-            let mut step = graph_step_from(&mut step, self.num_servers)?;
-            step.set_num_workers(num_workers);
-            step.set_server_index(self.server_index);
-            Ok(step.gen_source(Some(worker_id.index as usize)))
+        let mut step = self.decode_step(src)?;
+        // the session is joined by the source, which every job has, to run one job at a time
+        let session_job = get_session_job()?;
+        let source = if let Some(pb::gremlin::gremlin_step::Step::SessionRefStep(session_ref)) =
+            step.step.as_ref()
+        {
+            self.session_source(&session_ref.name, session_job.as_ref())?
+        } else if never_matches(&step) {
+            Box::new(std::iter::empty::<Traverser>())
         } else {
-            let mut step = graph_step_from(&mut step, self.num_servers)?;
-            step.set_server_index(self.server_index);
-            Ok(step.gen_source(None))
+            let (step, worker_index) = self.graph_source(&mut step)?;
+            step.gen_source(worker_index)
+        };
+        Ok(join_session(source, session_job))
+    }
+
+    fn fused_source(
+        &self, src: &[u8], plan: &[server_pb::OperatorDef],
+    ) -> CompileResult<Option<(Box<dyn Iterator<Item = Traverser> + Send>, usize)>> {
+        if !self.columnar_enabled {
+            return Ok(None);
+        }
+        let mut step = self.decode_step(src)?;
+        match step.step.as_ref() {
+            Some(pb::gremlin::gremlin_step::Step::GraphStep(graph_step))
+                if graph_step.return_type == pb::gremlin::EntityType::Vertex as i32 => {}
+            _ => return Ok(None),
+        }
+        if never_matches(&step) {
+            return Ok(None);
+        }
+        let steps = fuse_steps(plan, |res| self.decode_step(res).ok());
+        if steps.is_empty() {
+            return Ok(None);
+        }
+        let (graph_step, worker_index) = self.graph_source(&mut step)?;
+        // the traversers tracking the paths are needed from the source on
+        if graph_step.tracks_path() {
+            return Ok(None);
+        }
+        let fused = steps.len();
+        let session_job = get_session_job()?;
+        let source = graph_step.gen_columnar_source(worker_index, steps);
+        Ok(Some((join_session(source, session_job), fused)))
+    }
+
+    fn fused_flat_map(
+        &self, plan: &[server_pb::OperatorDef],
+    ) -> CompileResult<
+        Option<(
+            Box<dyn FlatMapFunction<Traverser, Traverser, Target = DynIter<Traverser>>>,
+            usize,
+        )>,
+    > {
+        if self.sorted_expand_enabled {
+            if let Some(graph) = crate::get_graph() {
+                let decode = |res: &[u8]| self.decode_step(res).ok();
+                if let Some(expand) =
+                    push_down_order(plan, decode, |label| graph.get_adj_order(label))
+                {
+                    // only the edge step is built, after which the order merges the top edges
+                    return Ok(Some((expand.gen_flat_map().map_err(build_error)?, 1)));
+                }
+            }
+        }
+        // the vertices reached by the first step are explored where they are reached
+        if !self.fused_expand_enabled || !self.partitioner.all_local() {
+            return Ok(None);
+        }
+        match fuse_expand(plan, |res| self.decode_step(res).ok()) {
+            Some((expand, fused)) => Ok(Some((expand.gen_flat_map().map_err(build_error)?, fused))),
+            None => Ok(None),
+        }
+    }
+
+    fn adaptive(
+        &self, plan: &[server_pb::OperatorDef],
+    ) -> CompileResult<Option<AdaptivePoint<Traverser>>> {
+        adaptive_has(self.adaptive, plan, |res| self.decode_step(res).ok()).map_err(build_error)
+    }
+
+    fn may_split(&self, res: &[u8]) -> bool {
+        // only the vertex steps expand the vertices of too many edges
+        match self.decode_step(res) {
+            Ok(step) => matches!(step.step, Some(pb::gremlin::gremlin_step::Step::VertexStep(_))),
+            Err(_) => false,
         }
     }
 
     fn map(&self, res: &[u8]) -> CompileResult<Box<dyn MapFunction<Traverser, Traverser>>> {
-        let step = decode::<pb::gremlin::GremlinStep>(res)?;
-        step.gen_map().map_err(|err| BuildJobError::from(err.to_string()))
+        let step = self.decode_step(res)?;
+        step.gen_map().map_err(build_error)
     }
 
     fn flat_map(
         &self, res: &[u8],
     ) -> CompileResult<Box<dyn FlatMapFunction<Traverser, Traverser, Target = DynIter<Traverser>>>>
     {
-        let step = decode::<pb::gremlin::GremlinStep>(res)?;
-        step.gen_flat_map().map_err(|err| BuildJobError::from(err.to_string()))
+        let step = self.decode_step(res)?;
+        step.gen_flat_map().map_err(build_error)
     }
 
     fn filter(&self, res: &[u8]) -> CompileResult<Box<dyn FilterFunction<Traverser>>> {
-        let step = decode::<pb::gremlin::GremlinStep>(res)?;
-        step.gen_filter().map_err(|err| BuildJobError::from(err.to_string()))
+        // the has steps are decoded once for all workers of the job, e.g. with thousands of ids
+        // in `hasId(within(..))`, to share the filter instead of a copy per worker
+        let store = get_job_side_store();
+        if let Some(store) = store.as_ref() {
+            if let Some(filter) = store.find_filter(res) {
+                return Ok(gen_shared_filter(filter));
+            }
+        }
+        let step = self.decode_step(res)?;
+        match (step.step, store) {
+            (Some(pb::gremlin::gremlin_step::Step::HasStep(has_step)), Some(store)) => {
+                let filter = has_filter_chain(has_step).map_err(build_error)?;
+                Ok(gen_shared_filter(store.share_filter(res, filter)))
+            }
+            (inner, _) => {
+                let step = pb::gremlin::GremlinStep { step: inner, ..step };
+                step.gen_filter().map_err(build_error)
+            }
+        }
     }
 
     fn left_join(&self, res: &[u8]) -> CompileResult<Box<dyn LeftJoinFunction<Traverser>>> {
@@ -111,34 +538,39 @@ impl JobCompiler<Traverser> for GremlinJobCompiler {
             Some(pb::gremlin::sub_task_joiner::Inner::SelectByJoiner(_)) => {
                 Ok(Box::new(SelectBySubJoin))
             }
+            Some(pb::gremlin::sub_task_joiner::Inner::ProjectByJoiner(joiner)) => {
+                let empty = joiner.empty.as_ref().map(pb_value_to_object).transpose()?.flatten();
+                Ok(Box::new(ProjectBySubJoin { column: joiner.column, empty }))
+            }
             None => Err("join information not found;")?,
         }
     }
 
     fn compare(&self, res: &[u8]) -> CompileResult<Box<dyn CompareFunction<Traverser>>> {
-        let step = decode::<pb::gremlin::GremlinStep>(res)?;
-        step.gen_cmp().map_err(|err| BuildJobError::from(err.to_string()))
+        let step = self.decode_step(res)?;
+        step.gen_cmp().map_err(build_error)
     }
 
     fn group(
         &self, map_factory: &[u8], _unfold: &[u8], _: &[u8],
     ) -> CompileResult<Box<dyn GroupFunction<Traverser>>> {
-        let step = decode::<pb::gremlin::GremlinStep>(map_factory)?;
-        step.gen_group().map_err(|err| BuildJobError::from(err.to_string()))
+        let step = self.decode_step(map_factory)?;
+        step.gen_group().map_err(build_error)
     }
 
     fn fold(
-        &self, _: &[u8], unfold: &[u8], _sink: &[u8],
+        &self, accum: &[u8], unfold: &[u8], _sink: &[u8],
     ) -> CompileResult<Box<dyn FoldFunction<Traverser>>> {
-        let step = decode::<pb::gremlin::GremlinStep>(unfold)?;
-        step.gen_fold().map_err(|err| BuildJobError::from(err.to_string()))
+        let step =
+            if accum.is_empty() { self.decode_step(unfold)? } else { self.decode_step(accum)? };
+        step.gen_fold().map_err(build_error)
     }
 
     fn collection_factory(
         &self, _res: &[u8],
     ) -> CompileResult<Box<dyn CollectionFactory<Traverser, Target = Box<dyn Collection<Traverser>>>>>
     {
-        unimplemented!()
+        Err(BuildJobError::Unsupported("collection factory is not supported yet".to_owned()))
     }
 
     // dedup
@@ -146,16 +578,221 @@ impl JobCompiler<Traverser> for GremlinJobCompiler {
         &self, res: &[u8],
     ) -> CompileResult<Box<dyn CollectionFactory<Traverser, Target = Box<dyn Set<Traverser>>>>>
     {
-        let step = decode::<pb::gremlin::GremlinStep>(res)?;
-        step.gen_collection().map_err(|err| BuildJobError::from(err.to_string()))
+        let step = self.decode_step(res)?;
+        step.gen_collection().map_err(build_error)
+    }
+
+    fn sink(&self, res: &[u8]) -> CompileResult<Box<dyn EncodeFunction<Traverser>>> {
+        let detach = Detach::from_resource(res).map_err(BuildJobError::from)?;
+        Ok(Box::new(TraverserSinkEncoder::with_detach(detach)))
+    }
+
+    fn validate(&self, req: &server_pb::JobRequest) -> Result<(), PlanError> {
+        crate::validate::validate_request_with(req, self.type_check)
+    }
+
+    fn simplify(&self, req: &mut server_pb::JobRequest) -> Result<bool, PlanError> {
+        if !self.simplify_enabled {
+            return Ok(false);
+        }
+        let schema = crate::get_named_graph(&req.graph).and_then(|graph| graph.get_schema());
+        simplify_request(req, schema.as_deref(), self.type_check)
+    }
+
+    fn estimate_workers(&self, graph: &str, src: &[u8]) -> Option<u32> {
+        let step = self.decode_step(src).ok()?;
+        let size = self.estimate_scan_size(graph, &step)?;
+        let workers = size.div_ceil(self.records_per_worker);
+        Some(std::cmp::max(1, workers) as u32)
+    }
+
+    fn source_size(&self, src: &[u8]) -> Option<u64> {
+        let step = self.decode_step(src).ok()?;
+        let size = self.estimate_scan_size(&get_graph_name(), &step)?;
+        // the scan of current server is shared by its workers, whose sizes sum up to the scan
+        let worker_id = pegasus::get_current_worker()?;
+        let num_workers = std::cmp::max(1, worker_id.peers as usize / self.num_servers);
+        let index = worker_id.index as usize % num_workers;
+        let share = size / num_workers + if index < size % num_workers { 1 } else { 0 };
+        Some(share as u64)
+    }
+
+    fn explain_source(&self, graph: &str, src: &[u8]) -> Option<SourceAccess> {
+        let step = self.decode_step(src).ok()?;
+        let access = match step.step.as_ref()? {
+            pb::gremlin::gremlin_step::Step::SessionRefStep(session_ref) => {
+                format!("session result {}", session_ref.name)
+            }
+            pb::gremlin::gremlin_step::Step::GraphStep(graph_step) => {
+                let is_edge = graph_step.return_type == pb::gremlin::EntityType::Edge as i32;
+                let entity = if is_edge { "edge" } else { "vertex" };
+                // the ids are looked up by the index of the graph, while any other is scanned
+                let mut access = if !graph_step.ids.is_empty() {
+                    format!("{} lookup by {} ids", entity, graph_step.ids.len())
+                } else if !graph_step.labels.is_empty() {
+                    format!("{} scan of labels {:?}", entity, graph_step.labels)
+                } else {
+                    format!("{} scan", entity)
+                };
+                if never_matches(&step) {
+                    // the predicates are never true, see `simplify_request`
+                    return Some(SourceAccess {
+                        access: format!("{} scan with predicates never true", entity),
+                        estimated_records: Some(0),
+                    });
+                } else if graph_step.predicates.is_some() {
+                    access.push_str(" with predicates");
+                }
+                access
+            }
+            _ => return None,
+        };
+        let estimated_records = self.estimate_scan_size(graph, &step).map(|size| size as u64);
+        Some(SourceAccess { access, estimated_records })
+    }
+
+    fn estimate_output(&self, graph: &str, res: &[u8], input: u64) -> Option<u64> {
+        let step = self.decode_step(res).ok()?;
+        match step.step.as_ref()? {
+            pb::gremlin::gremlin_step::Step::VertexStep(vertex_step) => {
+                let direction = pb::gremlin::Direction::from_i32(vertex_step.direction)?;
+                let direction = Direction::from_pb(direction).ok()?;
+                let labels: Vec<LabelId> = vertex_step
+                    .edge_labels
+                    .iter()
+                    .filter_map(|l| LabelId::try_from(*l).ok())
+                    .collect();
+                let graph = crate::get_named_graph(graph)?;
+                let degree = average_degree(graph.as_ref(), direction, &labels)?;
+                Some((input as f64 * degree).round() as u64)
+            }
+            _ => None,
+        }
+    }
+
+    fn features(&self) -> Vec<String> {
+        // the epsilon of the float equality in the filters, see `FilterExp::epsilon`, and the
+        // graphs named by the requests, see `register_named_graph`
+        vec!["filter.epsilon".to_owned(), GRAPH_NAMED_FEATURE.to_owned()]
+    }
+
+    fn shortcut(&self, req: &server_pb::JobRequest) -> Option<Vec<Traverser>> {
+        let count = self.count_by_statistics(req)?;
+        if let Some(conf) = req.conf.as_ref() {
+            metrics::report_counter(conf.job_id, 0, metrics::SHORTCUT_COUNTER, 1);
+        }
+        // the same as the count unfolded by the job
+        Some(vec![Traverser::object(count.into())])
+    }
+
+    fn template_hash(&self, req: &server_pb::JobRequest) -> Option<u64> {
+        Some(prepared::template_hash(req))
+    }
+
+    fn schema(&self) -> Option<Vec<u8>> {
+        let schema = crate::get_graph()?.get_schema()?;
+        let mut bytes = vec![];
+        schema_to_pb(&schema).encode(&mut bytes).ok()?;
+        Some(bytes)
+    }
+
+    fn prepare(&self, graph: &str, conf: &mut JobConf) -> Result<(), QueryError> {
+        // admitted ahead of waiting for the graph, so that the jobs over the limits fail fast
+        let limits = get_graph_limits(graph);
+        if !limits.is_unlimited() {
+            limits.admit(graph, conf)?;
+        }
+        // the job reads the mutation of `min_version` at least, e.g. one its client has just made
+        let wait = Duration::from_millis(conf.min_version_wait_ms);
+        let bound = BoundGraph::since(graph, conf.min_version, wait).map_err(|e| {
+            warn!("job {} fails to pin its snapshot: {}", conf.job_id, e);
+            QueryError::Timeout { limit_ms: conf.min_version_wait_ms, worker: None }
+        })?;
+        conf.set_user_data(STEP_BINDINGS, StepBindings::default());
+        conf.set_user_data(JOB_GRAPH, bound);
+        bind_job_side_store(conf);
+        Ok(())
+    }
+
+    fn on_drain(&self) {
+        if let Some(path) = self.warm_file.as_ref() {
+            match warm_state::save(path, &self.plan_cache, &self.prepared) {
+                Ok(()) => info!("saved the warm states to {:?}", path),
+                Err(e) => error!("fail to save the warm states to {:?}: {}", path, e),
+            }
+        }
+    }
+}
+
+/// Encode the schema of the graph replied to the schema requests of the clients
+pub fn schema_to_pb(schema: &GraphSchemaInfo) -> pb::gremlin::GraphSchema {
+    use pb::gremlin::graph_schema::{Label, Property, PropertyType, Relation};
+    let to_pb = |label: &LabelSchema| Label {
+        id: label.id.into(),
+        name: label.name.clone(),
+        properties: label
+            .properties
+            .iter()
+            .map(|(name, data_type)| {
+                let data_type = match data_type {
+                    DataType::String => PropertyType::String,
+                    DataType::Integer => PropertyType::Integer,
+                    DataType::Long => PropertyType::Long,
+                    DataType::Double => PropertyType::Double,
+                    DataType::Date => PropertyType::Date,
+                    DataType::ID => PropertyType::Id,
+                    DataType::NULL | DataType::LABEL => PropertyType::Unknown,
+                };
+                Property { name: name.clone(), data_type: data_type as i32 }
+            })
+            .collect(),
+    };
+    pb::gremlin::GraphSchema {
+        vertex_labels: schema.vertex_labels.iter().map(to_pb).collect(),
+        edge_labels: schema.edge_labels.iter().map(to_pb).collect(),
+        relations: schema
+            .relations
+            .iter()
+            .map(|r| Relation {
+                edge_label: r.edge_label.into(),
+                src_label: r.src_vertex_label.into(),
+                dst_label: r.dst_vertex_label.into(),
+            })
+            .collect(),
     }
+}
+
+/// The source of a job running in a session, which holds the session as running the job until
+/// the source is dropped
+struct SessionSource {
+    inner: Box<dyn Iterator<Item = Traverser> + Send>,
+    _job: Arc<SessionJob>,
+}
 
-    fn sink(&self, _: &[u8]) -> CompileResult<Box<dyn EncodeFunction<Traverser>>> {
-        Ok(Box::new(TraverserSinkEncoder))
+impl Iterator for SessionSource {
+    type Item = Traverser;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
     }
 }
 
 #[inline]
 fn decode<T: Message + Default>(binary: &[u8]) -> Result<T, BuildJobError> {
-    Ok(T::decode(binary).map_err(|e| format!("protobuf decode failure: {}", e))?)
+    T::decode(binary).map_err(decode_error)
+}
+
+fn decode_error(e: DecodeError) -> BuildJobError {
+    let msg = format!("protobuf decode failure: {}", e);
+    let err = QueryError::Parse { op_index: vec![], msg, cause: Some(ErrorCause::of(&e)) };
+    BuildJobError::UserError(Box::new(err))
+}
+
+/// Keep the error a step fails to be compiled with classified, e.g. a `ParseError` as
+/// `QueryError::Parse`, which is told to the client
+fn build_error(err: DynError) -> BuildJobError {
+    match err.downcast::<ParseError>() {
+        Ok(e) => BuildJobError::from(*e),
+        Err(err) => BuildJobError::UserError(err),
+    }
 }
@@ -0,0 +1,75 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use crate::graph_algo::VertexProgram;
+use crate::structure::{Direction, Edge, Label, QueryParams, Vertex};
+use crate::{Element, ID};
+
+/// Weakly connected components, where each vertex is labeled by the smallest id of the vertices
+/// in its component
+pub struct ConnectedComponents {
+    edge_labels: Vec<Label>,
+}
+
+impl Default for ConnectedComponents {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConnectedComponents {
+    pub fn new() -> Self {
+        ConnectedComponents { edge_labels: vec![] }
+    }
+
+    /// Connect the vertices only by the edges of the labels
+    pub fn with_edge_labels(edge_labels: Vec<Label>) -> Self {
+        ConnectedComponents { edge_labels }
+    }
+}
+
+impl VertexProgram for ConnectedComponents {
+    type State = ID;
+    type Message = ID;
+
+    fn direction(&self) -> Direction {
+        Direction::Both
+    }
+
+    fn edge_params(&self) -> QueryParams<Edge> {
+        let mut params = QueryParams::new();
+        params.labels = self.edge_labels.clone();
+        params
+    }
+
+    fn init(&self, vertex: &Vertex) -> Self::State {
+        vertex.id()
+    }
+
+    fn message(&self, _: &Edge, state: &Self::State) -> Option<Self::Message> {
+        Some(*state)
+    }
+
+    fn combine(&self, left: Self::Message, right: Self::Message) -> Self::Message {
+        left.min(right)
+    }
+
+    fn apply(&self, state: Self::State, message: Option<Self::Message>) -> (Self::State, bool) {
+        match message {
+            Some(component) if component < state => (component, true),
+            _ => (state, false),
+        }
+    }
+}
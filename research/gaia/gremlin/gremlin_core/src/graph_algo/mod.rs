@@ -0,0 +1,212 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! A vertex-centric (Pregel-style) API on top of pegasus iteration, to run graph algorithms,
+//! e.g. PageRank or connected components, over the graph registered in current server.
+//!
+//! A job of a `VertexProgram` runs in supersteps. Each vertex starts with the state given by
+//! `init` and is active at first. In each superstep, every active vertex sends messages along its
+//! edges by `message`, and the messages to the same vertex are combined by `combine`. Then every
+//! vertex that is active or receives messages updates its state by `apply`, which also decides
+//! if the vertex stays active. The job ends once no vertex is active, or after the max supersteps.
+
+mod components;
+mod pagerank;
+mod shortest_path;
+
+pub use components::ConnectedComponents;
+pub use pagerank::{PageRank, PageRankState};
+pub use shortest_path::{shortest_paths, DistanceState, PathRecord, ShortestPath};
+
+use crate::structure::{read_id, write_id, Direction, Edge, QueryParams, Vertex};
+use crate::{str_to_dyn_error, DynResult, Element, Partition, Partitioner, ID};
+use pegasus::api::state::{OperatorState, ScopedState};
+use pegasus::api::{Exchange, Iteration, Map, ResultSet, Sink, Unary, UnaryState};
+use pegasus::codec::{Decode, Encode, ReadExt, WriteExt};
+use pegasus::communication::{Input, Output, Pipeline};
+use pegasus::errors::JobExecError;
+use pegasus::{Data, JobConf, Tag};
+use std::collections::HashMap;
+use std::io;
+use std::sync::{Arc, Mutex};
+
+pub trait VertexProgram: Send + Sync + 'static {
+    type State: Send + 'static;
+    type Message: Data;
+
+    /// The direction of the edges along which the messages are sent
+    fn direction(&self) -> Direction {
+        Direction::Out
+    }
+
+    /// The edges along which the messages are sent, e.g. of some labels
+    fn edge_params(&self) -> QueryParams<Edge> {
+        QueryParams::new()
+    }
+
+    fn init(&self, vertex: &Vertex) -> Self::State;
+
+    /// The message sent along the edge by the vertex of the state, or `None` if nothing to send
+    fn message(&self, edge: &Edge, state: &Self::State) -> Option<Self::Message>;
+
+    fn combine(&self, left: Self::Message, right: Self::Message) -> Self::Message;
+
+    /// Update the state by the combined message, which is `None` if the vertex is active but
+    /// receives nothing, and return if the vertex stays active in the next superstep
+    fn apply(&self, state: Self::State, message: Option<Self::Message>) -> (Self::State, bool);
+}
+
+/// A message to the vertex, or a token of the vertex staying active if without message
+#[derive(Clone, Debug)]
+struct Superstep<M> {
+    vertex: ID,
+    message: Option<M>,
+}
+
+impl<M: Encode> Encode for Superstep<M> {
+    fn write_to<W: WriteExt>(&self, writer: &mut W) -> io::Result<()> {
+        write_id(self.vertex, writer)?;
+        self.message.write_to(writer)
+    }
+}
+
+impl<M: Decode> Decode for Superstep<M> {
+    fn read_from<R: ReadExt>(reader: &mut R) -> io::Result<Self> {
+        let vertex = read_id(reader)?;
+        let message = Option::<M>::read_from(reader)?;
+        Ok(Superstep { vertex, message })
+    }
+}
+
+type States<S> = Arc<Mutex<HashMap<ID, S>>>;
+
+/// Run the program over the graph by the workers of current server, and return the final states
+/// of all vertices.
+pub fn run_vertex_program<P: VertexProgram>(
+    conf: JobConf, program: P, max_supersteps: u32,
+) -> DynResult<HashMap<ID, P::State>> {
+    let workers = conf.workers as usize;
+    let program = Arc::new(program);
+    let states: Vec<States<P::State>> = (0..workers).map(|_| States::default()).collect();
+    let worker_states = states.clone();
+    let guard = pegasus::run(conf, move |worker| {
+        let index = worker.id.index as usize;
+        let program = program.clone();
+        let states = worker_states[index].clone();
+        worker.dataflow(move |dfb| {
+            let graph = crate::get_graph().ok_or("graph not found")?;
+            let partitioner = Arc::new(Partition { num_servers: 1 });
+            let explore = graph
+                .prepare_explore_edge(program.direction(), &program.edge_params())
+                .map_err(|e| e.to_string())?;
+            let vertices = graph.scan_vertex(&QueryParams::new()).map_err(|e| e.to_string())?;
+            let mut tokens = vec![];
+            {
+                let mut states = states.lock().expect("lock poisoned");
+                for v in vertices {
+                    if partitioner.get_partition(&v.id(), workers) == index as u64 {
+                        states.insert(v.id(), program.init(&v));
+                        tokens.push(Superstep::<P::Message> { vertex: v.id(), message: None });
+                    }
+                }
+            }
+            let scatter_program = program.clone();
+            let scatter_states = states.clone();
+            dfb.input_from_iter(tokens.into_iter())?
+                .iterate(max_supersteps, move |start| {
+                    start
+                        .flat_map_with_fn(Pipeline, move |token: Superstep<P::Message>| {
+                            let v = token.vertex;
+                            let mut messages = vec![Superstep { vertex: v, message: None }];
+                            let states = scatter_states.lock().expect("lock poisoned");
+                            if let Some(state) = states.get(&v) {
+                                for edge in explore.exec(v)? {
+                                    let edge = edge?;
+                                    let other =
+                                        if edge.src_id == v { edge.dst_id } else { edge.src_id };
+                                    if let Some(m) = scatter_program.message(&edge, state) {
+                                        messages
+                                            .push(Superstep { vertex: other, message: Some(m) });
+                                    }
+                                }
+                            }
+                            Ok(messages.into_iter().map(Ok))
+                        })?
+                        .exchange_with_fn(move |m: &Superstep<P::Message>| {
+                            partitioner.get_partition(&m.vertex, workers)
+                        })?
+                        .unary_with_state("apply", Pipeline, move |_| Apply { program, states })
+                })?
+                // the states are left in the workers, instead of going through the sink
+                .sink_by(|_| |_: &Tag, _: ResultSet<Superstep<P::Message>>| ())?;
+            Ok(())
+        })
+    })
+    .map_err(|e| str_to_dyn_error(&e.to_string()))?;
+    if let Some(mut guard) = guard {
+        guard.join().map_err(|e| str_to_dyn_error(&e.to_string()))?;
+    }
+    let mut result = HashMap::new();
+    for states in states {
+        result.extend(states.lock().expect("lock poisoned").drain());
+    }
+    Ok(result)
+}
+
+/// Combine the messages to each vertex in a superstep, and apply them to the vertex once the
+/// superstep ends
+struct Apply<P: VertexProgram> {
+    program: Arc<P>,
+    states: States<P::State>,
+}
+
+type Messages<M> = ScopedState<ID, Option<M>>;
+
+impl<P: VertexProgram>
+    UnaryState<Superstep<P::Message>, Superstep<P::Message>, Messages<P::Message>> for Apply<P>
+{
+    type NotifyResult = Vec<Superstep<P::Message>>;
+
+    fn on_receive(
+        &self, input: &mut Input<Superstep<P::Message>>, _: &mut Output<Superstep<P::Message>>,
+        state: &mut OperatorState<Messages<P::Message>>,
+    ) -> Result<(), JobExecError> {
+        input.for_each_batch(|dataset| {
+            for m in dataset.drain(..) {
+                let combined = state.get_or_insert_with(m.vertex, || None);
+                *combined = match (combined.take(), m.message) {
+                    (Some(pre), Some(next)) => Some(self.program.combine(pre, next)),
+                    (pre, next) => pre.or(next),
+                };
+            }
+            Ok(())
+        })
+    }
+
+    fn on_notify(&self, messages: Messages<P::Message>) -> Self::NotifyResult {
+        let mut states = self.states.lock().expect("lock poisoned");
+        let mut actives = vec![];
+        for (vertex, message) in messages {
+            if let Some(state) = states.remove(&vertex) {
+                let (state, active) = self.program.apply(state, message);
+                states.insert(vertex, state);
+                if active {
+                    actives.push(Superstep { vertex, message: None });
+                }
+            }
+        }
+        actives
+    }
+}
@@ -0,0 +1,69 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use crate::graph_algo::VertexProgram;
+use crate::structure::{Direction, Edge, QueryParams, Vertex};
+use crate::{str_to_dyn_error, DynResult, Element};
+
+/// The rank of a vertex, along with its out degree to split the rank among its out edges
+#[derive(Copy, Clone, Debug)]
+pub struct PageRankState {
+    pub rank: f64,
+    pub out_degree: usize,
+}
+
+/// PageRank running a fixed number of supersteps, starting from the uniform ranks, where the
+/// ranks of the vertices without out edges are not redistributed.
+pub struct PageRank {
+    damping: f64,
+    num_vertices: usize,
+}
+
+impl PageRank {
+    /// Rank the vertices of the graph registered in current server
+    pub fn new(damping: f64) -> DynResult<Self> {
+        let graph = crate::get_graph().ok_or_else(|| str_to_dyn_error("graph not found"))?;
+        let num_vertices = graph.scan_vertex(&QueryParams::new())?.count();
+        Ok(PageRank { damping, num_vertices })
+    }
+}
+
+impl VertexProgram for PageRank {
+    type State = PageRankState;
+    type Message = f64;
+
+    fn init(&self, vertex: &Vertex) -> Self::State {
+        let out_degree = crate::get_graph()
+            .and_then(|graph| graph.prepare_explore_edge(Direction::Out, &QueryParams::new()).ok())
+            .and_then(|explore| explore.exec(vertex.id()).ok())
+            .map(|edges| edges.count())
+            .unwrap_or(0);
+        PageRankState { rank: 1.0 / self.num_vertices as f64, out_degree }
+    }
+
+    fn message(&self, _: &Edge, state: &Self::State) -> Option<Self::Message> {
+        Some(state.rank / state.out_degree as f64)
+    }
+
+    fn combine(&self, left: Self::Message, right: Self::Message) -> Self::Message {
+        left + right
+    }
+
+    fn apply(&self, state: Self::State, message: Option<Self::Message>) -> (Self::State, bool) {
+        let rank =
+            (1.0 - self.damping) / self.num_vertices as f64 + self.damping * message.unwrap_or(0.0);
+        (PageRankState { rank, out_degree: state.out_degree }, true)
+    }
+}
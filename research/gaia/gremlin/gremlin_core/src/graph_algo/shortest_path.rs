@@ -0,0 +1,162 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use crate::graph_algo::{run_vertex_program, VertexProgram};
+use crate::structure::{Details, Direction, Edge, Vertex};
+use crate::{DynResult, Element, ID};
+use pegasus::JobConf;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+/// The distance of a vertex from the nearest source, and its parent on the shortest path
+#[derive(Copy, Clone, Debug)]
+pub struct DistanceState {
+    pub vertex: ID,
+    /// `None` if the vertex is not reached
+    pub distance: Option<f64>,
+    /// `None` if the vertex is a source or not reached
+    pub parent: Option<ID>,
+}
+
+/// The shortest paths from a set of source vertices, along the edges weighted by a property, or
+/// weighted by 1 for a BFS if no weight is given. The edges without a numeric weight are skipped.
+/// The shortest path of ties is the one whose parents have smaller ids, for paths to be
+/// deterministic.
+pub struct ShortestPath {
+    sources: HashSet<ID>,
+    weight: Option<String>,
+    direction: Direction,
+}
+
+impl ShortestPath {
+    pub fn new(sources: Vec<ID>) -> Self {
+        ShortestPath {
+            sources: sources.into_iter().collect(),
+            weight: None,
+            direction: Direction::Out,
+        }
+    }
+
+    /// Weight the edges by the property of the key
+    pub fn with_weight(mut self, key: &str) -> Self {
+        self.weight = Some(key.to_owned());
+        self
+    }
+
+    pub fn with_direction(mut self, direction: Direction) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    fn get_weight(&self, edge: &Edge) -> Option<f64> {
+        match self.weight {
+            Some(ref key) => edge.details().get_property(key)?.as_f64().ok(),
+            None => Some(1.0),
+        }
+    }
+}
+
+/// Compare the distances along with the parents, where the smaller parent breaks a tie
+fn compare(left: (f64, ID), right: (f64, ID)) -> Ordering {
+    left.0.partial_cmp(&right.0).unwrap_or(Ordering::Equal).then(left.1.cmp(&right.1))
+}
+
+impl VertexProgram for ShortestPath {
+    type State = DistanceState;
+    /// The distance through the parent
+    type Message = (f64, ID);
+
+    fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    fn init(&self, vertex: &Vertex) -> Self::State {
+        let distance = if self.sources.contains(&vertex.id()) { Some(0.0) } else { None };
+        DistanceState { vertex: vertex.id(), distance, parent: None }
+    }
+
+    fn message(&self, edge: &Edge, state: &Self::State) -> Option<Self::Message> {
+        let distance = state.distance? + self.get_weight(edge)?;
+        Some((distance, state.vertex))
+    }
+
+    fn combine(&self, left: Self::Message, right: Self::Message) -> Self::Message {
+        if compare(left, right) == Ordering::Greater {
+            right
+        } else {
+            left
+        }
+    }
+
+    fn apply(&self, mut state: Self::State, message: Option<Self::Message>) -> (Self::State, bool) {
+        if let Some((distance, parent)) = message {
+            match state.distance {
+                Some(current) if distance > current => (state, false),
+                // only a shorter distance goes on to the following vertices, while a tie just
+                // picks the smaller parent
+                Some(current) if distance == current => {
+                    if state.parent.map(|p| parent < p).unwrap_or(false) {
+                        state.parent = Some(parent);
+                    }
+                    (state, false)
+                }
+                _ => {
+                    state.distance = Some(distance);
+                    state.parent = Some(parent);
+                    (state, true)
+                }
+            }
+        } else {
+            (state, false)
+        }
+    }
+}
+
+/// A vertex reached from the sources, with the distance and the path from the nearest source
+#[derive(Clone, Debug, PartialEq)]
+pub struct PathRecord {
+    pub vertex: ID,
+    pub distance: f64,
+    pub path: Option<Vec<ID>>,
+}
+
+/// Find the shortest paths of no more than `max_depth` edges if given, and return the vertices
+/// reached ordered by their ids, along with the paths if `with_path`.
+pub fn shortest_paths(
+    conf: JobConf, sp: ShortestPath, max_depth: Option<u32>, with_path: bool,
+) -> DynResult<Vec<PathRecord>> {
+    let states = run_vertex_program(conf, sp, max_depth.unwrap_or(!0u32))?;
+    let mut records = vec![];
+    for state in states.values() {
+        if let Some(distance) = state.distance {
+            let path = if with_path {
+                let mut path = vec![state.vertex];
+                let mut parent = state.parent;
+                // a path is no longer than all vertices, unless it runs into a cycle of 0 weight
+                while let Some(p) = parent.filter(|_| path.len() <= states.len()) {
+                    path.push(p);
+                    parent = states.get(&p).and_then(|s| s.parent);
+                }
+                path.reverse();
+                Some(path)
+            } else {
+                None
+            };
+            records.push(PathRecord { vertex: state.vertex, distance, path });
+        }
+    }
+    records.sort_by_key(|r| r.vertex);
+    Ok(records)
+}
@@ -19,6 +19,7 @@ extern crate bitflags;
 extern crate strum_macros;
 #[macro_use]
 extern crate enum_dispatch;
+#[macro_use]
 extern crate lazy_static;
 #[macro_use]
 extern crate pegasus_common;
@@ -32,8 +33,14 @@ extern crate graph_store;
 #[macro_use]
 extern crate dyn_type;
 
+use crate::process::limits::{get_job_access, JobAccess};
+use crate::process::traversal::step::projection::CompositeKey;
+use crate::process::traversal::step::ProjectRecord;
 use crate::process::traversal::traverser::{ShadeSync, Traverser};
-pub use crate::structure::{get_graph, register_graph};
+pub use crate::structure::{
+    get_graph, get_named_graph, load_named_graph, register_graph, register_named_graph,
+    set_graph_limits, unregister_named_graph,
+};
 pub use crate::structure::{Element, GraphProxy, ID};
 use pegasus::api::accum::{Count, ToList};
 use pegasus::api::function::*;
@@ -43,17 +50,28 @@ pub mod process;
 pub mod structure;
 
 pub mod compiler;
+pub mod graph_algo;
+pub mod plan_cache;
+pub mod prepared;
 mod result_process;
+pub mod session;
 mod storage;
+pub mod traversal;
+pub mod validate;
+pub mod warm_state;
 
-use crate::result_process::result_to_pb;
+use crate::result_process::result_to_pb_on;
+pub use crate::result_process::{get_detach_fetch_count, Detach};
 use crate::structure::filter::codec::ParseError;
 pub use generated::gremlin::GremlinStep as GremlinStepPb;
+pub use graph_store::partition::GraphPartition;
+use graph_store::prelude::DefaultId;
 use std::io;
-pub use storage::create_demo_graph;
+use std::sync::Arc;
+pub use storage::{create_demo_graph, create_graph, create_mutable_graph, get_vertex_scan_count};
 
 #[cfg(feature = "proto_inplace")]
-mod generated {
+pub mod generated {
     #[path = "common.rs"]
     pub mod common;
     #[path = "gremlin.rs"]
@@ -63,7 +81,7 @@ mod generated {
 }
 
 #[cfg(not(feature = "proto_inplace"))]
-mod generated {
+pub mod generated {
     pub mod common {
         tonic::include_proto!("common");
     }
@@ -106,10 +124,75 @@ pub trait FromPb<T> {
 }
 
 pub trait Partitioner: Send + Sync + 'static {
+    /// Get the index (among all workers of all servers) of the worker to process the vertex
+    /// of given id, where each server runs `job_workers` workers
     fn get_partition(&self, id: &ID, job_workers: usize) -> u64;
+
+    /// Get the indexes of the workers holding the replicas of the vertex of given id, with the
+    /// primary, i.e. `get_partition`, first, any of which can look up the vertex
+    fn get_replicas(&self, id: &ID, job_workers: usize) -> Vec<u64> {
+        vec![self.get_partition(id, job_workers)]
+    }
+
+    /// Get the index of the worker to scan the vertex of given id, which is one of its replicas,
+    /// the same for all jobs, so that each vertex is scanned exactly once over the replicas
+    fn get_scan_partition(&self, id: &ID, job_workers: usize) -> u64 {
+        self.get_partition(id, job_workers)
+    }
+
+    /// Whether all the vertices are stored in current server, e.g. of a single server, so that a
+    /// vertex reached by an edge can be explored by the worker reaching it, instead of the worker
+    /// given by `get_partition`, see `process::expand`
+    fn all_local(&self) -> bool {
+        false
+    }
+}
+
+/// A partition utility following the given `GraphPartition`, which must be consistent with
+/// how the graph is loaded.
+pub struct GraphPartitioner {
+    partition: Arc<dyn GraphPartition>,
+    num_servers: usize,
 }
 
-/// A simple partition utility
+impl GraphPartitioner {
+    pub fn new(partition: Arc<dyn GraphPartition>, num_servers: usize) -> Self {
+        GraphPartitioner { partition, num_servers }
+    }
+}
+
+impl Partitioner for GraphPartitioner {
+    fn get_partition(&self, id: &ID, workers: usize) -> u64 {
+        let vid = *id as DefaultId;
+        let server = self.partition.get_server(vid, self.num_servers);
+        let worker = self.partition.get_worker(vid, self.num_servers, workers);
+        (server * workers + worker) as u64
+    }
+
+    fn get_replicas(&self, id: &ID, workers: usize) -> Vec<u64> {
+        let vid = *id as DefaultId;
+        let worker = self.partition.get_worker(vid, self.num_servers, workers);
+        self.partition
+            .get_replica_servers(vid, self.num_servers)
+            .into_iter()
+            .map(|server| (server * workers + worker) as u64)
+            .collect()
+    }
+
+    /// Spread the vertices of a partition over its replicas by their ids
+    fn get_scan_partition(&self, id: &ID, workers: usize) -> u64 {
+        let replicas = self.get_replicas(id, workers);
+        let vid = *id as DefaultId;
+        replicas[(vid / self.partition.num_partitions()) % replicas.len()]
+    }
+
+    fn all_local(&self) -> bool {
+        self.num_servers == 1
+    }
+}
+
+/// A simple partition utility, which is equivalent to a `GraphPartitioner` following
+/// `HashPartition` of `num_servers` partitions
 pub struct Partition {
     pub num_servers: usize,
 }
@@ -126,13 +209,56 @@ impl Partitioner for Partition {
         // to do the computation.
         ((id_usize - magic_num * self.num_servers) * workers + magic_num % workers) as u64
     }
+
+    fn all_local(&self) -> bool {
+        self.num_servers == 1
+    }
+}
+
+/// Encode the results of a job into the wire format of the clients, which runs either on the
+/// workers or in the service, see `JobConfig.encode_in_service`, as all it needs of the job is
+/// taken once it is built on a worker
+pub struct TraverserSinkEncoder {
+    access: Option<Arc<JobAccess>>,
+    detach: Detach,
+    // the graph of the job the properties of the detached vertices are fetched from
+    graph: Option<Arc<dyn GraphProxy>>,
+    // whether the results are consolidated by the sink, see `JobConf::sink_consolidation`, so
+    // that their bulks are kept in the encoded items
+    bulked: bool,
+}
+
+impl Default for TraverserSinkEncoder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-pub struct TraverserSinkEncoder;
+impl TraverserSinkEncoder {
+    pub fn new() -> Self {
+        TraverserSinkEncoder::with_detach(Detach::Reference)
+    }
+
+    /// Encode the graph elements of the results by the policy, see `Detach`
+    pub fn with_detach(detach: Detach) -> Self {
+        let bulked = pegasus::get_current_job_conf()
+            .map(|conf| conf.sink_consolidation > 0)
+            .unwrap_or(false);
+        TraverserSinkEncoder { access: get_job_access(), detach, graph: get_graph(), bulked }
+    }
+}
 
 impl EncodeFunction<Traverser> for TraverserSinkEncoder {
-    fn encode(&self, data: Vec<Traverser>) -> Vec<u8> {
-        let result_pb = result_to_pb(data);
+    fn encode(&self, mut data: Vec<Traverser>) -> Vec<u8> {
+        if let Some(access) = self.access.as_ref() {
+            // the batch exceeding the limits, which aborts the job, and all following ones are
+            // dropped rather than returned as partial results
+            if let Err(err) = access.add_sink_results(&data) {
+                error!("{}, drop the results", err);
+                data.clear();
+            }
+        }
+        let result_pb = result_to_pb_on(data, &self.detach, self.bulked, self.graph.clone());
         let mut bytes = vec![];
         result_pb.encode_raw(&mut bytes);
         bytes
@@ -143,5 +269,7 @@ pub fn register_gremlin_types() -> io::Result<()> {
     dyn_type::register_type::<ShadeSync<(Traverser, Traverser)>>()?;
     dyn_type::register_type::<ShadeSync<Count<Traverser>>>()?;
     dyn_type::register_type::<ShadeSync<ToList<Traverser>>>()?;
+    dyn_type::register_type::<ProjectRecord>()?;
+    dyn_type::register_custom_type::<CompositeKey>()?;
     Ok(())
 }
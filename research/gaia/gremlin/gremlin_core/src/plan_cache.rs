@@ -0,0 +1,647 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The cache of the decoded steps of the query plans. The parameterized queries are submitted
+//! again and again with only the literals changed, e.g. `g.V().has("name", "marko")` and
+//! `g.V().has("name", "vadas")`, which are decoded into the same steps except the literals.
+//!
+//! A step is normalized by scanning its bytes, where the literal values of the predicates are
+//! stripped into a vector of parameters, and replaced by empty placeholders. The normalized
+//! bytes are the key of the cache, and are decoded only once into a template of the step, along
+//! with the chains nested in its predicates, e.g. of `or()`, which the step keeps as bytes. The
+//! templates are cached in an LRU order. Once hit, the step is given by binding the parameters to
+//! the slots of a copy of the template, without decoding the step or any nested chain again,
+//! where only the nested chains bound are encoded back into the bytes of the step.
+
+use crate::generated::common as common_pb;
+use crate::generated::gremlin as pb;
+use prost::{DecodeError, Message};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// The default number of steps kept in the plan cache
+pub const DEFAULT_PLAN_CACHE_CAPACITY: usize = 1024;
+
+/// The key of the `StepBindings` of a job among its resources, see `pegasus::get_job_resource`
+pub const STEP_BINDINGS: &str = "gremlin.step_bindings";
+
+/// The literal values stripped from a step, as the encoded `common.Value` in the order of scanning
+type Params = Vec<Vec<u8>>;
+
+/// The chains nested in the nodes of a chain, decoded from the bytes of the nodes, in the order of
+/// the nodes
+#[derive(Clone, Default)]
+struct NestedChains {
+    // the index of the node -> the chain nested in the node, with those nested in it
+    chains: Vec<(usize, pb::FilterChain, NestedChains)>,
+}
+
+impl NestedChains {
+    /// Decode the chains nested in the chain, and count the chains decoded into `decodes`
+    fn decode(chain: Option<&pb::FilterChain>, decodes: &mut u64) -> Result<Self, DecodeError> {
+        let mut nested = NestedChains::default();
+        for (i, node) in chain.map(|c| c.node.as_slice()).unwrap_or(&[]).iter().enumerate() {
+            if let Some(pb::filter_node::Inner::Chain(bytes)) = node.inner.as_ref() {
+                let chain = pb::FilterChain::decode(bytes.as_slice())?;
+                *decodes += 1;
+                let inner = NestedChains::decode(Some(&chain), decodes)?;
+                nested.chains.push((i, chain, inner));
+            }
+        }
+        Ok(nested)
+    }
+
+    fn get(&self, index: usize) -> Option<&(usize, pb::FilterChain, NestedChains)> {
+        self.chains.iter().find(|(i, _, _)| *i == index)
+    }
+}
+
+/// A step decoded from its normalized bytes, whose literals are empty placeholders, i.e. the slots
+/// of the parameters, with the chains nested in its predicates decoded as well
+#[derive(Clone)]
+pub(crate) struct StepTemplate {
+    step: pb::GremlinStep,
+    nested: NestedChains,
+    // the number of messages decoded into the template, i.e. the step and the nested chains
+    decodes: u64,
+}
+
+impl StepTemplate {
+    pub(crate) fn decode(normalized: &[u8]) -> Result<Self, DecodeError> {
+        use pb::gremlin_step::Step;
+        let step = pb::GremlinStep::decode(normalized)?;
+        let mut decodes = 1;
+        let chain = match step.step.as_ref() {
+            Some(Step::GraphStep(s)) => s.predicates.as_ref(),
+            Some(Step::VertexStep(s)) => s.predicates.as_ref(),
+            Some(Step::HasStep(s)) => s.predicates.as_ref(),
+            Some(Step::WhereStep(s)) => s.predicates.as_ref(),
+            Some(Step::IsStep(s)) => s.predicates.as_ref(),
+            _ => None,
+        };
+        let nested = NestedChains::decode(chain, &mut decodes)?;
+        Ok(StepTemplate { step, nested, decodes })
+    }
+}
+
+struct LruCache {
+    // normalized bytes -> (template, tick of last access)
+    entries: HashMap<Vec<u8>, (Arc<StepTemplate>, u64)>,
+    // tick of last access -> normalized bytes, the least recently used entry comes first
+    order: BTreeMap<u64, Vec<u8>>,
+    tick: u64,
+    capacity: usize,
+}
+
+impl LruCache {
+    fn new(capacity: usize) -> Self {
+        LruCache { entries: HashMap::new(), order: BTreeMap::new(), tick: 0, capacity }
+    }
+
+    fn get(&mut self, key: &[u8]) -> Option<Arc<StepTemplate>> {
+        if let Some((template, tick)) = self.entries.get_mut(key) {
+            self.tick += 1;
+            let key = self.order.remove(tick).expect("lru order lost");
+            *tick = self.tick;
+            self.order.insert(self.tick, key);
+            Some(template.clone())
+        } else {
+            None
+        }
+    }
+
+    fn insert(&mut self, key: Vec<u8>, template: Arc<StepTemplate>) {
+        if self.capacity == 0 {
+            return;
+        }
+        if let Some((_, tick)) = self.entries.remove(&key) {
+            self.order.remove(&tick);
+        }
+        while self.entries.len() >= self.capacity {
+            let lru = self.order.keys().next().cloned().expect("lru order lost");
+            let lru_key = self.order.remove(&lru).expect("lru order lost");
+            self.entries.remove(&lru_key);
+        }
+        self.tick += 1;
+        self.order.insert(self.tick, key.clone());
+        self.entries.insert(key, (template, self.tick));
+    }
+}
+
+pub struct PlanCache {
+    lru: Mutex<LruCache>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    decodes: AtomicU64,
+}
+
+impl PlanCache {
+    /// Create a cache that keeps at most `capacity` steps, or nothing if `capacity` is 0
+    pub fn new(capacity: usize) -> Self {
+        PlanCache {
+            lru: Mutex::new(LruCache::new(capacity)),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            decodes: AtomicU64::new(0),
+        }
+    }
+
+    /// Get the step encoded in `res`, by binding its literals to the cached template of the step
+    /// if any, or decoding and caching the template otherwise.
+    pub fn get_step(&self, res: &[u8]) -> Result<pb::GremlinStep, DecodeError> {
+        let mut key = Vec::with_capacity(res.len());
+        let mut params = vec![];
+        if normalize(Schema::Step, res, &mut key, &mut params).is_none() {
+            // e.g. the fields are not in order, which can't be bound to the template correctly
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            return self.decode(res);
+        }
+        let cached = self.lru.lock().ok().and_then(|mut lru| lru.get(&key));
+        let template = if let Some(template) = cached {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            template
+        } else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            let template = Arc::new(self.decode_template(&key)?);
+            if let Ok(mut lru) = self.lru.lock() {
+                lru.insert(key, template.clone());
+            }
+            template
+        };
+        let mut params = params.iter();
+        let bound = bind(&template, &mut || {
+            params.next().and_then(|p| common_pb::Value::decode(&p[..]).ok())
+        });
+        match bound {
+            // all parameters must be bound
+            Some(step) if params.len() == 0 => Ok(step),
+            _ => self.decode(res),
+        }
+    }
+
+    /// The number of steps given by the cached templates
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// The number of steps not found in the cache
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    /// The number of messages decoded from their bytes, i.e. the steps, and the chains nested in
+    /// the predicates of the templates, none of which is decoded by the hits
+    pub fn decodes(&self) -> u64 {
+        self.decodes.load(Ordering::Relaxed)
+    }
+
+    /// The number of cached steps
+    pub fn len(&self) -> usize {
+        self.lru.lock().map(|lru| lru.entries.len()).unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The normalized steps of the cached templates, the least recently used first, which are
+    /// saved to warm up the cache once the server restarts, see `warm_state`
+    pub fn normalized_steps(&self) -> Vec<Vec<u8>> {
+        self.lru.lock().map(|lru| lru.order.values().cloned().collect()).unwrap_or_default()
+    }
+
+    /// Cache the template decoded from the normalized step, e.g. saved before restarting, which
+    /// is rejected if the step is not normalized as the cache does, so could never be hit
+    pub fn warm(&self, normalized: &[u8]) -> Result<(), String> {
+        match normalize_step(normalized) {
+            Some((key, _)) if key == normalized => {}
+            _ => return Err("the step is not normalized".to_owned()),
+        }
+        let template = StepTemplate::decode(normalized).map_err(|e| e.to_string())?;
+        if let Ok(mut lru) = self.lru.lock() {
+            lru.insert(normalized.to_vec(), Arc::new(template));
+        }
+        Ok(())
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<pb::GremlinStep, DecodeError> {
+        self.decodes.fetch_add(1, Ordering::Relaxed);
+        pb::GremlinStep::decode(bytes)
+    }
+
+    fn decode_template(&self, normalized: &[u8]) -> Result<StepTemplate, DecodeError> {
+        let template = StepTemplate::decode(normalized)?;
+        self.decodes.fetch_add(template.decodes, Ordering::Relaxed);
+        Ok(template)
+    }
+}
+
+impl Default for PlanCache {
+    fn default() -> Self {
+        PlanCache::new(DEFAULT_PLAN_CACHE_CAPACITY)
+    }
+}
+
+/// The steps of a job bound to their parameters, shared by all the workers of the job, so that a
+/// step is bound by the first worker compiling it, and copied by the others
+#[derive(Default)]
+pub struct StepBindings {
+    steps: Mutex<HashMap<Vec<u8>, pb::GremlinStep>>,
+}
+
+impl StepBindings {
+    /// The bindings of the steps bound ahead, e.g. those of a prepared query, by their resources
+    pub fn with_steps(steps: HashMap<Vec<u8>, pb::GremlinStep>) -> Self {
+        StepBindings { steps: Mutex::new(steps) }
+    }
+
+    /// Get the step encoded in `res` bound for the job if any, or by the plan cache otherwise
+    pub fn get_step(
+        &self, plan_cache: &PlanCache, res: &[u8],
+    ) -> Result<pb::GremlinStep, DecodeError> {
+        if let Some(step) = self.steps.lock().ok().and_then(|steps| steps.get(res).cloned()) {
+            return Ok(step);
+        }
+        let step = plan_cache.get_step(res)?;
+        if let Ok(mut steps) = self.steps.lock() {
+            steps.insert(res.to_vec(), step.clone());
+        }
+        Ok(step)
+    }
+
+    /// The number of steps bound for the job
+    pub fn len(&self) -> usize {
+        self.steps.lock().map(|steps| steps.len()).unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// The messages on the paths from a step to the literals of its predicates
+#[derive(Copy, Clone)]
+enum Schema {
+    Step,
+    GraphStep,
+    VertexStep,
+    HasStep,
+    WhereStep,
+    // `IsStep` and `LoopsStep`
+    SingleStep,
+    FilterChain,
+    FilterNode,
+    FilterExp,
+    FilterValueExp,
+}
+
+enum Field {
+    Copy,
+    Nested(Schema),
+    Literal,
+}
+
+fn field_of(schema: Schema, number: u64) -> Field {
+    match (schema, number) {
+        (Schema::Step, 3) => Field::Nested(Schema::GraphStep),
+        (Schema::Step, 4) => Field::Nested(Schema::VertexStep),
+        (Schema::Step, 5) => Field::Nested(Schema::HasStep),
+        (Schema::Step, 6) => Field::Nested(Schema::WhereStep),
+        (Schema::Step, 22) | (Schema::Step, 24) => Field::Nested(Schema::SingleStep),
+        (Schema::GraphStep, 4)
+        | (Schema::VertexStep, 4)
+        | (Schema::HasStep, 1)
+        | (Schema::WhereStep, 4) => Field::Nested(Schema::FilterChain),
+        (Schema::SingleStep, 1) => Field::Nested(Schema::FilterValueExp),
+        // the predicates of `IsStep` on the values, e.g. of `is(within(1, 2))`
+        (Schema::SingleStep, 2) => Field::Nested(Schema::FilterChain),
+        (Schema::FilterChain, 1) => Field::Nested(Schema::FilterNode),
+        (Schema::FilterNode, 1) => Field::Nested(Schema::FilterExp),
+        // the nested chain is encoded as bytes, which are the same as a message on the wire
+        (Schema::FilterNode, 2) => Field::Nested(Schema::FilterChain),
+        (Schema::FilterExp, 3) | (Schema::FilterValueExp, 2) => Field::Literal,
+        _ => Field::Copy,
+    }
+}
+
+const WIRE_VARINT: u64 = 0;
+const WIRE_64BIT: u64 = 1;
+const WIRE_LEN_DELIMITED: u64 = 2;
+const WIRE_32BIT: u64 = 5;
+
+fn read_varint(buf: &mut &[u8]) -> Option<u64> {
+    let bytes: &[u8] = buf;
+    let mut value = 0u64;
+    for (i, byte) in bytes.iter().enumerate().take(10) {
+        value |= ((byte & 0x7f) as u64) << (7 * i);
+        if byte & 0x80 == 0 {
+            *buf = &bytes[i + 1..];
+            return Some(value);
+        }
+    }
+    None
+}
+
+pub(crate) fn write_varint(mut value: u64, buf: &mut Vec<u8>) {
+    while value >= 0x80 {
+        buf.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+/// Normalize the step encoded in `res` into the key of its template and its parameters, see
+/// `normalize`
+pub(crate) fn normalize_step(res: &[u8]) -> Option<(Vec<u8>, Params)> {
+    let mut key = Vec::with_capacity(res.len());
+    let mut params = vec![];
+    normalize(Schema::Step, res, &mut key, &mut params)?;
+    Some((key, params))
+}
+
+/// Copy the message of `schema` in `bytes` into `normalized`, with the literals replaced by
+/// empty values and pushed into `params`. It gives `None` if the bytes are malformed, or the
+/// fields are not in the order of their numbers, as the literals are bound in that order.
+fn normalize(
+    schema: Schema, bytes: &[u8], normalized: &mut Vec<u8>, params: &mut Params,
+) -> Option<()> {
+    let mut rest = bytes;
+    let mut last = 0;
+    while !rest.is_empty() {
+        let start = rest;
+        let key = read_varint(&mut rest)?;
+        let number = key >> 3;
+        if number < last {
+            return None;
+        }
+        last = number;
+        match key & 0x07 {
+            WIRE_VARINT => {
+                read_varint(&mut rest)?;
+            }
+            WIRE_64BIT => rest = rest.get(8..)?,
+            WIRE_32BIT => rest = rest.get(4..)?,
+            WIRE_LEN_DELIMITED => {
+                let len = read_varint(&mut rest)? as usize;
+                let value = rest.get(..len)?;
+                rest = &rest[len..];
+                match field_of(schema, number) {
+                    Field::Nested(nested) => {
+                        let mut inner = Vec::with_capacity(value.len());
+                        normalize(nested, value, &mut inner, params)?;
+                        write_varint(key, normalized);
+                        write_varint(inner.len() as u64, normalized);
+                        normalized.extend_from_slice(&inner);
+                        continue;
+                    }
+                    Field::Literal => {
+                        params.push(value.to_vec());
+                        write_varint(key, normalized);
+                        write_varint(0, normalized);
+                        continue;
+                    }
+                    Field::Copy => {}
+                }
+            }
+            // the deprecated groups are never used by the plans
+            _ => return None,
+        }
+        normalized.extend_from_slice(&start[..start.len() - rest.len()]);
+    }
+    Some(())
+}
+
+/// Bind the parameters given one by one by `params` to a copy of the template in the order they
+/// are stripped, where `params` gives `None` if they run out
+pub(crate) fn bind<F: FnMut() -> Option<common_pb::Value>>(
+    template: &StepTemplate, params: &mut F,
+) -> Option<pb::GremlinStep> {
+    use pb::gremlin_step::Step;
+    let mut step = template.step.clone();
+    let nested = &template.nested;
+    match step.step.as_mut() {
+        Some(Step::GraphStep(s)) => bind_chain(s.predicates.as_mut(), nested, params)?,
+        Some(Step::VertexStep(s)) => bind_chain(s.predicates.as_mut(), nested, params)?,
+        Some(Step::HasStep(s)) => bind_chain(s.predicates.as_mut(), nested, params)?,
+        Some(Step::WhereStep(s)) => bind_chain(s.predicates.as_mut(), nested, params)?,
+        Some(Step::IsStep(s)) => {
+            bind_value_exp(s.single.as_mut(), params)?;
+            bind_chain(s.predicates.as_mut(), nested, params)?
+        }
+        Some(Step::LoopsStep(s)) => bind_value_exp(s.single.as_mut(), params)?,
+        _ => {}
+    }
+    Some(step)
+}
+
+fn bind_chain<F: FnMut() -> Option<common_pb::Value>>(
+    chain: Option<&mut pb::FilterChain>, nested: &NestedChains, params: &mut F,
+) -> Option<()> {
+    if let Some(chain) = chain {
+        for (i, node) in chain.node.iter_mut().enumerate() {
+            match node.inner.as_mut() {
+                Some(pb::filter_node::Inner::Single(exp)) => {
+                    bind_value(exp.right.as_mut(), params)?
+                }
+                Some(pb::filter_node::Inner::Chain(bytes)) => {
+                    // the nested chain is decoded along with the template, and kept as bytes by
+                    // the step, which are encoded once it is bound
+                    let (_, template, inner) = nested.get(i)?;
+                    let mut chain = template.clone();
+                    bind_chain(Some(&mut chain), inner, params)?;
+                    bytes.clear();
+                    chain.encode(bytes).ok()?;
+                }
+                None => {}
+            }
+        }
+    }
+    Some(())
+}
+
+fn bind_value_exp<F: FnMut() -> Option<common_pb::Value>>(
+    exp: Option<&mut pb::FilterValueExp>, params: &mut F,
+) -> Option<()> {
+    match exp {
+        Some(exp) => bind_value(exp.right.as_mut(), params),
+        None => Some(()),
+    }
+}
+
+fn bind_value<F: FnMut() -> Option<common_pb::Value>>(
+    placeholder: Option<&mut common_pb::Value>, params: &mut F,
+) -> Option<()> {
+    if let Some(placeholder) = placeholder {
+        *placeholder = params()?;
+    }
+    Some(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn str_value(s: &str) -> common_pb::Value {
+        common_pb::Value { item: Some(common_pb::value::Item::Str(s.to_owned())) }
+    }
+
+    fn i32_value(v: i32) -> common_pb::Value {
+        common_pb::Value { item: Some(common_pb::value::Item::I32(v)) }
+    }
+
+    fn exp_node(key: &str, cmp: pb::Compare, value: common_pb::Value) -> pb::FilterNode {
+        let exp = pb::FilterExp {
+            left: Some(common_pb::Key { item: Some(common_pb::key::Item::Name(key.to_owned())) }),
+            cmp: cmp as i32,
+            right: Some(value),
+            epsilon: 0.0,
+            predicate: String::new(),
+        };
+        pb::FilterNode {
+            inner: Some(pb::filter_node::Inner::Single(exp)),
+            next: pb::Connect::And as i32,
+        }
+    }
+
+    fn encode<M: Message>(msg: &M) -> Vec<u8> {
+        let mut bytes = vec![];
+        msg.encode(&mut bytes).unwrap();
+        bytes
+    }
+
+    // has(key, eq(value))
+    fn has_step(key: &str, value: common_pb::Value) -> pb::GremlinStep {
+        let chain = pb::FilterChain { node: vec![exp_node(key, pb::Compare::Eq, value)] };
+        let has_step = pb::HasStep { predicates: Some(chain) };
+        pb::GremlinStep {
+            tags: vec![],
+            remove_tags: vec![],
+            step: Some(pb::gremlin_step::Step::HasStep(has_step)),
+        }
+    }
+
+    // has("age", lte(low).or(gte(high))), where the or-chain is nested
+    fn has_range_step(low: i32, high: i32) -> pb::GremlinStep {
+        let mut lte = exp_node("age", pb::Compare::Le, i32_value(low));
+        lte.next = pb::Connect::Or as i32;
+        let or =
+            pb::FilterChain { node: vec![lte, exp_node("age", pb::Compare::Ge, i32_value(high))] };
+        let node = pb::FilterNode {
+            inner: Some(pb::filter_node::Inner::Chain(encode(&or))),
+            next: pb::Connect::And as i32,
+        };
+        let has_step = pb::HasStep { predicates: Some(pb::FilterChain { node: vec![node] }) };
+        pb::GremlinStep {
+            tags: vec![],
+            remove_tags: vec![],
+            step: Some(pb::gremlin_step::Step::HasStep(has_step)),
+        }
+    }
+
+    #[test]
+    fn normalize_test() {
+        let normalize_step = |step: &pb::GremlinStep| {
+            let mut key = vec![];
+            let mut params = vec![];
+            normalize(Schema::Step, &encode(step), &mut key, &mut params).unwrap();
+            (key, params)
+        };
+        let (marko, marko_params) = normalize_step(&has_step("name", str_value("marko")));
+        let (vadas, vadas_params) = normalize_step(&has_step("name", str_value("vadas")));
+        assert_eq!(marko, vadas);
+        assert_eq!(marko_params, vec![encode(&str_value("marko"))]);
+        assert_eq!(vadas_params, vec![encode(&str_value("vadas"))]);
+        // the keys of properties are not literals
+        let (age, _) = normalize_step(&has_step("age", str_value("marko")));
+        assert_ne!(marko, age);
+        let (range, params) = normalize_step(&has_range_step(28, 32));
+        assert_eq!(range, normalize_step(&has_range_step(1, 2)).0);
+        assert_eq!(params, vec![encode(&i32_value(28)), encode(&i32_value(32))]);
+    }
+
+    #[test]
+    fn rebind_test() {
+        let cache = PlanCache::new(4);
+        let marko = has_step("name", str_value("marko"));
+        assert_eq!(cache.get_step(&encode(&marko)).unwrap(), marko);
+        assert_eq!((cache.hits(), cache.misses(), cache.decodes()), (0, 1, 1));
+        let vadas = has_step("name", str_value("vadas"));
+        assert_eq!(cache.get_step(&encode(&vadas)).unwrap(), vadas);
+        assert_eq!((cache.hits(), cache.misses(), cache.decodes()), (1, 1, 1));
+        // the chain nested in the template is decoded along with it, and never again once hit
+        let range = has_range_step(28, 32);
+        assert_eq!(cache.get_step(&encode(&range)).unwrap(), range);
+        assert_eq!((cache.hits(), cache.misses(), cache.decodes()), (1, 2, 3));
+        let range = has_range_step(27, 29);
+        assert_eq!(cache.get_step(&encode(&range)).unwrap(), range);
+        assert_eq!((cache.hits(), cache.misses(), cache.decodes()), (2, 2, 3));
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn lru_eviction_test() {
+        let cache = PlanCache::new(2);
+        let steps: Vec<Vec<u8>> = vec!["name", "age", "id"]
+            .into_iter()
+            .map(|key| encode(&has_step(key, i32_value(1))))
+            .collect();
+        cache.get_step(&steps[0]).unwrap();
+        cache.get_step(&steps[1]).unwrap();
+        // touch "name", so that "age" is the least recently used one
+        cache.get_step(&steps[0]).unwrap();
+        cache.get_step(&steps[2]).unwrap();
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.decodes(), 3);
+        cache.get_step(&steps[0]).unwrap();
+        assert_eq!(cache.decodes(), 3);
+        cache.get_step(&steps[1]).unwrap();
+        assert_eq!(cache.decodes(), 4);
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn uncached_test() {
+        let cache = PlanCache::new(0);
+        let marko = has_step("name", str_value("marko"));
+        assert_eq!(cache.get_step(&encode(&marko)).unwrap(), marko);
+        assert_eq!(cache.get_step(&encode(&marko)).unwrap(), marko);
+        assert_eq!((cache.hits(), cache.misses(), cache.decodes()), (0, 2, 2));
+        assert!(cache.is_empty());
+        // the malformed bytes are reported as by decoding
+        assert!(cache.get_step(&[0x0a, 0x05, 0x01]).is_err());
+    }
+
+    #[test]
+    fn warm_test() {
+        let cache = PlanCache::new(4);
+        cache.get_step(&encode(&has_step("name", str_value("marko")))).unwrap();
+        cache.get_step(&encode(&has_range_step(28, 32))).unwrap();
+        let steps = cache.normalized_steps();
+        assert_eq!(steps.len(), 2);
+        let warmed = PlanCache::new(4);
+        for step in steps.iter() {
+            warmed.warm(step).unwrap();
+        }
+        assert_eq!(warmed.normalized_steps(), steps);
+        // the warmed templates are hit without decoding
+        let vadas = has_step("name", str_value("vadas"));
+        assert_eq!(warmed.get_step(&encode(&vadas)).unwrap(), vadas);
+        assert_eq!((warmed.hits(), warmed.decodes()), (1, 0));
+        // the steps with literals are not normalized
+        assert!(warmed.warm(&encode(&vadas)).is_err());
+        assert!(warmed.warm(&[0x0a, 0x05, 0x01]).is_err());
+    }
+}
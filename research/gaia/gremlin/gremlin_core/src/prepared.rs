@@ -0,0 +1,382 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The queries prepared once and executed again and again with only their literals changed, e.g.
+//! the point queries `g.V().has("name", x)` looked up by a secondary index for each `x`.
+//!
+//! A query is prepared by normalizing each of its steps as the plan cache does, see `plan_cache`,
+//! where the literals of the predicates are stripped into the slots of the parameters, and the
+//! normalized step is decoded once into a template. Executing the prepared query binds the
+//! parameters to copies of the templates after checking them against the types of the slots, and
+//! hands the bound steps to the job by its `StepBindings`, so no step is decoded again.
+//!
+//! The steps of the same shape, e.g. both has steps of `has("name", x).out().has("name", y)`, are
+//! normalized into the same bytes, which are told apart by the index of the step appended as an
+//! extra field unknown to the decoders.
+//!
+//! The prepared queries registered by `PreparedQueries` are given by their handles, which are the
+//! stable hashes of the normalized queries, so a query is given the same handle once prepared
+//! again, e.g. after the server restarts, see `warm_state`.
+
+use crate::generated::common as common_pb;
+use crate::plan_cache::{bind, normalize_step, write_varint, StepBindings, StepTemplate};
+use crate::validate::{validate_request_with, TypeCheck};
+use pegasus::api::key::StableHasher;
+use pegasus_server::factory::PlanError;
+use pegasus_server::generated::protocol as server_pb;
+use prost::Message;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+use std::sync::{Arc, RwLock};
+
+/// The number of the field tagging a normalized step with its index in the query, which is beyond
+/// those of `GremlinStep`, so is skipped by the decoders
+const STEP_INDEX_FIELD: u64 = 1000;
+
+struct PreparedStep {
+    // the normalized bytes of the step tagged by its index, which replace the step in the query
+    resource: Vec<u8>,
+    template: StepTemplate,
+    // the slots of the parameters bound to the step
+    slots: Range<usize>,
+    op_index: Vec<usize>,
+}
+
+/// A query prepared by `PreparedQuery::prepare`, which is executed by the request given by
+/// `request` with the steps given by `bind` as its `StepBindings`
+pub struct PreparedQuery {
+    // the request without its configuration, whose steps of parameters are normalized
+    request: server_pb::JobRequest,
+    steps: Vec<PreparedStep>,
+    // the type of each slot, which is that of the literal the query is prepared with
+    slots: Vec<&'static str>,
+}
+
+impl PreparedQuery {
+    /// Prepare the query of the request, whose literals of the predicates are the slots of the
+    /// parameters in the order of the steps. The query is validated here only once, with the
+    /// literals it is prepared with.
+    ///
+    /// The steps which the plan cache can't normalize, e.g. those of the fields out of order, are
+    /// left as they are, whose literals are not parameters.
+    pub fn prepare(req: &server_pb::JobRequest, type_check: TypeCheck) -> Result<Self, PlanError> {
+        validate_request_with(req, type_check)?;
+        PreparedQuery::normalize(req)
+    }
+
+    // prepare the query validated ahead
+    fn normalize(req: &server_pb::JobRequest) -> Result<Self, PlanError> {
+        let mut request = req.clone();
+        request.conf = None;
+        let mut preparer = Preparer::default();
+        if let Some(source) = request.source.as_mut() {
+            preparer.prepare_step(&mut source.resource)?;
+        }
+        if let Some(plan) = request.plan.as_mut() {
+            preparer.prepare_plan(plan)?;
+        }
+        Ok(PreparedQuery { request, steps: preparer.steps, slots: preparer.slots })
+    }
+
+    /// The types of the parameters in the order they are bound, e.g. `["str", "i32"]`
+    pub fn slots(&self) -> &[&'static str] {
+        &self.slots
+    }
+
+    /// Bind the parameters to the steps of the query, which are rejected if they are not as many
+    /// as the slots, or any of them doesn't fit the type of its slot. The integers are widened to
+    /// the slots of the longer integers or the floats.
+    pub fn bind(&self, params: &[common_pb::Value]) -> Result<StepBindings, PlanError> {
+        if params.len() != self.slots.len() {
+            let msg =
+                format!("{} parameters are given to {} slots", params.len(), self.slots.len());
+            return Err(PlanError::new(vec![], msg));
+        }
+        let mut steps = HashMap::with_capacity(self.steps.len());
+        for step in self.steps.iter() {
+            let mut values = Vec::with_capacity(step.slots.len());
+            for i in step.slots.clone() {
+                let value = coerce(&params[i], self.slots[i]).ok_or_else(|| {
+                    let msg = format!(
+                        "parameter {} of {} doesn't fit the slot of {}",
+                        i,
+                        value_type(&params[i]),
+                        self.slots[i]
+                    );
+                    PlanError::new(step.op_index.clone(), msg)
+                })?;
+                values.push(value);
+            }
+            let mut values = values.into_iter();
+            let bound = bind(&step.template, &mut || values.next()).ok_or_else(|| {
+                PlanError::new(step.op_index.clone(), "parameters of the step fail to be bound")
+            })?;
+            steps.insert(step.resource.clone(), bound);
+        }
+        Ok(StepBindings::with_steps(steps))
+    }
+
+    /// The request of the prepared query with the configuration of an execution
+    pub fn request(&self, conf: server_pb::JobConfig) -> server_pb::JobRequest {
+        let mut req = self.request.clone();
+        req.conf = Some(conf);
+        req
+    }
+
+    /// The stable hash of the normalized query and the types of its slots, the same for the
+    /// queries of the same shape whatever their literals, across the servers and their restarts
+    pub fn handle(&self) -> u64 {
+        let mut bytes = vec![];
+        self.request.encode(&mut bytes).expect("encode request failure");
+        let mut hasher = StableHasher::default();
+        hasher.write(&bytes);
+        for slot in self.slots.iter() {
+            hasher.write(slot.as_bytes());
+            // the slots are separated from each other
+            hasher.write(&[0]);
+        }
+        hasher.finish()
+    }
+}
+
+/// The hash of the query template of the request, i.e. the handle of the query once prepared, see
+/// `PreparedQuery::handle`, which the runs of the query share whatever their literals, or the
+/// hash of the request as it is if its steps fail to be decoded
+pub fn template_hash(req: &server_pb::JobRequest) -> u64 {
+    match PreparedQuery::normalize(req) {
+        Ok(query) => query.handle(),
+        Err(_) => {
+            let mut request = req.clone();
+            request.conf = None;
+            let mut bytes = vec![];
+            request.encode(&mut bytes).expect("encode request failure");
+            let mut hasher = StableHasher::default();
+            hasher.write(&bytes);
+            hasher.finish()
+        }
+    }
+}
+
+/// The prepared queries by their handles, see `PreparedQuery::handle`, along with the requests
+/// they are prepared from, to be prepared again after the server restarts
+#[derive(Default)]
+pub struct PreparedQueries {
+    queries: RwLock<BTreeMap<u64, (server_pb::JobRequest, Arc<PreparedQuery>)>>,
+}
+
+impl PreparedQueries {
+    /// Prepare the query of the request and register it by its handle, which is returned, where
+    /// the query of the same handle registered before is kept
+    pub fn register(
+        &self, req: &server_pb::JobRequest, type_check: TypeCheck,
+    ) -> Result<u64, PlanError> {
+        let query = PreparedQuery::prepare(req, type_check)?;
+        let handle = query.handle();
+        let mut request = req.clone();
+        request.conf = None;
+        if let Ok(mut queries) = self.queries.write() {
+            queries.entry(handle).or_insert_with(|| (request, Arc::new(query)));
+        }
+        Ok(handle)
+    }
+
+    /// Get the prepared query of the handle, or `None` if it is not registered
+    pub fn get(&self, handle: u64) -> Option<Arc<PreparedQuery>> {
+        self.queries.read().ok()?.get(&handle).map(|(_, query)| query.clone())
+    }
+
+    /// Unregister the prepared query of the handle, returning if it is registered
+    pub fn remove(&self, handle: u64) -> bool {
+        self.queries.write().map(|mut queries| queries.remove(&handle).is_some()).unwrap_or(false)
+    }
+
+    /// The requests the registered queries are prepared from, in the order of their handles
+    pub fn requests(&self) -> Vec<server_pb::JobRequest> {
+        match self.queries.read() {
+            Ok(queries) => queries.values().map(|(req, _)| req.clone()).collect(),
+            Err(_) => vec![],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.queries.read().map(|queries| queries.len()).unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[derive(Default)]
+struct Preparer {
+    op_index: Vec<usize>,
+    steps: Vec<PreparedStep>,
+    slots: Vec<&'static str>,
+}
+
+impl Preparer {
+    fn prepare_plan(&mut self, plan: &mut server_pb::TaskPlan) -> Result<(), PlanError> {
+        for (i, op) in plan.plan.iter_mut().enumerate() {
+            self.op_index.push(i);
+            self.prepare_op(op)?;
+            self.op_index.pop();
+        }
+        Ok(())
+    }
+
+    fn prepare_op(&mut self, op: &mut server_pb::OperatorDef) -> Result<(), PlanError> {
+        use server_pb::operator_def::OpKind;
+        match op.op_kind.as_mut() {
+            Some(OpKind::Map(map)) => self.prepare_named_step(&mut map.resource, &mut op.name),
+            Some(OpKind::FlatMap(flat_map)) => {
+                self.prepare_named_step(&mut flat_map.resource, &mut op.name)
+            }
+            Some(OpKind::Filter(filter)) => {
+                self.prepare_named_step(&mut filter.resource, &mut op.name)
+            }
+            Some(OpKind::Union(union)) => self.prepare_branches(&mut union.branches),
+            Some(OpKind::Coalesce(coalesce)) => self.prepare_branches(&mut coalesce.branches),
+            Some(OpKind::Iterate(iteration)) => {
+                if let Some(body) = iteration.body.as_mut() {
+                    self.prepare_plan(body)?;
+                }
+                match iteration.until.as_mut() {
+                    Some(until) => self.prepare_step(&mut until.resource),
+                    None => Ok(()),
+                }
+            }
+            Some(OpKind::Subtask(subtask)) => match subtask.task.as_mut() {
+                Some(task) => self.prepare_plan(task),
+                None => Ok(()),
+            },
+            Some(OpKind::TimeLimit(time_limit)) => match time_limit.task.as_mut() {
+                Some(task) => self.prepare_plan(task),
+                None => Ok(()),
+            },
+            _ => Ok(()),
+        }
+    }
+
+    fn prepare_branches(&mut self, branches: &mut [server_pb::TaskPlan]) -> Result<(), PlanError> {
+        for (i, branch) in branches.iter_mut().enumerate() {
+            self.op_index.push(i);
+            self.prepare_plan(branch)?;
+            self.op_index.pop();
+        }
+        Ok(())
+    }
+
+    // the literals of the step are left out of the name of its operator as well, e.g. from
+    // `has[name eq marko]` to `has`, as the queries of the template share the operator
+    fn prepare_named_step(
+        &mut self, res: &mut Vec<u8>, name: &mut String,
+    ) -> Result<(), PlanError> {
+        let slots = self.slots.len();
+        self.prepare_step(res)?;
+        if self.slots.len() > slots {
+            if let Some(at) = name.find('[') {
+                name.truncate(at);
+            }
+        }
+        Ok(())
+    }
+
+    fn prepare_step(&mut self, res: &mut Vec<u8>) -> Result<(), PlanError> {
+        let (mut normalized, params) = match normalize_step(res) {
+            Some((normalized, params)) if !params.is_empty() => (normalized, params),
+            _ => return Ok(()),
+        };
+        let template = StepTemplate::decode(normalized.as_slice()).map_err(|e| {
+            PlanError::new(self.op_index.clone(), format!("protobuf decode failure: {}", e))
+        })?;
+        let start = self.slots.len();
+        for param in params.iter() {
+            let value = common_pb::Value::decode(param.as_slice()).map_err(|e| {
+                PlanError::new(self.op_index.clone(), format!("protobuf decode failure: {}", e))
+            })?;
+            self.slots.push(value_type(&value));
+        }
+        write_varint(STEP_INDEX_FIELD << 3, &mut normalized);
+        write_varint(self.steps.len() as u64, &mut normalized);
+        *res = normalized.clone();
+        self.steps.push(PreparedStep {
+            resource: normalized,
+            template,
+            slots: start..self.slots.len(),
+            op_index: self.op_index.clone(),
+        });
+        Ok(())
+    }
+}
+
+/// The name of the type of the value, as told by the slots of the parameters
+pub fn value_type(value: &common_pb::Value) -> &'static str {
+    use common_pb::value::Item;
+    match value.item.as_ref() {
+        Some(Item::Boolean(_)) => "bool",
+        Some(Item::I32(_)) => "i32",
+        Some(Item::I64(_)) => "i64",
+        Some(Item::F64(_)) => "f64",
+        Some(Item::Str(_)) => "str",
+        Some(Item::Blob(_)) => "blob",
+        Some(Item::I32Array(_)) => "i32 array",
+        Some(Item::I64Array(_)) => "i64 array",
+        Some(Item::F64Array(_)) => "f64 array",
+        Some(Item::StrArray(_)) => "str array",
+        Some(Item::None(_)) | None => "none",
+        Some(Item::Custom(_)) => "custom",
+    }
+}
+
+// the value fitting the slot of the type, widened if it is a shorter integer
+fn coerce(value: &common_pb::Value, slot: &str) -> Option<common_pb::Value> {
+    use common_pb::value::Item;
+    if value_type(value) == slot {
+        return Some(value.clone());
+    }
+    let item = match (value.item.as_ref()?, slot) {
+        (Item::I32(v), "i64") => Item::I64(*v as i64),
+        (Item::I32(v), "f64") => Item::F64(*v as f64),
+        (Item::I64(v), "f64") => Item::F64(*v as f64),
+        (Item::I32Array(array), "i64 array") => Item::I64Array(common_pb::I64Array {
+            item: array.item.iter().map(|v| *v as i64).collect(),
+        }),
+        _ => return None,
+    };
+    Some(common_pb::Value { item: Some(item) })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn str_value(s: &str) -> common_pb::Value {
+        common_pb::Value { item: Some(common_pb::value::Item::Str(s.to_owned())) }
+    }
+
+    fn i32_value(v: i32) -> common_pb::Value {
+        common_pb::Value { item: Some(common_pb::value::Item::I32(v)) }
+    }
+
+    #[test]
+    fn coerce_test() {
+        assert_eq!(coerce(&str_value("marko"), "str"), Some(str_value("marko")));
+        let widened = common_pb::Value { item: Some(common_pb::value::Item::I64(29)) };
+        assert_eq!(coerce(&i32_value(29), "i64"), Some(widened));
+        assert_eq!(coerce(&i32_value(29), "str"), None);
+        assert_eq!(coerce(&str_value("29"), "i32"), None);
+        assert_eq!(value_type(&common_pb::Value { item: None }), "none");
+    }
+}
@@ -0,0 +1,142 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The adaptive order of two consecutive has steps, e.g. of `has("age", gt(0)).has("name",
+//! eq("josh"))`, where the more selective step is better evaluated first, so the traversers it
+//! filters out are never tested by the other one. The selectivity is hardly known as the plan is
+//! compiled, so both orders are built as the alternatives of an adaptive point of the job, see
+//! `JobCompiler::adaptive`, and each scope decides the order by the pass rates of both steps
+//! over the first traversers it samples, the rest of which go through the order decided.
+//!
+//! Both orders give exactly the same traversers, as the steps are only swapped if neither is
+//! tagged, i.e. each only tests the element of the traverser itself. The decisions are told in
+//! the profile of the job, e.g. "has[age gt 0] passed 4/6, has[name eq josh] passed 1/6".
+
+use crate::generated::gremlin as pb;
+use crate::process::traversal::step::has_filter_chain;
+use crate::process::traversal::traverser::Traverser;
+use crate::structure::TraverserFilterChain;
+use crate::DynResult;
+use pegasus::api::{Branch, Decision};
+use pegasus_server::factory::{AdaptivePoint, DecideFn};
+use pegasus_server::generated::protocol as server_pb;
+use std::sync::Arc;
+
+/// The name of the adaptive point of two has steps in the profile of the job
+pub const ADAPTIVE_HAS: &str = "adaptive_has";
+
+/// How the jobs order the consecutive has steps of the plans
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Adaptive {
+    /// The steps are evaluated in the order of the plan, by default
+    #[default]
+    Off,
+    /// Each scope decides the order by the first traversers of the number it samples
+    Sample(usize),
+    /// The steps are evaluated in the order of the plan by `Branch::Left`, or in the reversed
+    /// order by `Branch::Right`, to verify both orders give the same results
+    Force(Branch),
+}
+
+/// The adaptive point of the two has steps at the head of the plan, each decoded from the
+/// resource of its operator by `decode`, whose first alternative keeps the order of the plan and
+/// the second one reverses it; `None` if the plan doesn't start with two has steps without tags
+/// taking the traversers by the pipeline
+pub fn adaptive_has<F>(
+    mode: Adaptive, plan: &[server_pb::OperatorDef], decode: F,
+) -> DynResult<Option<AdaptivePoint<Traverser>>>
+where
+    F: Fn(&[u8]) -> Option<pb::GremlinStep>,
+{
+    if mode == Adaptive::Off || plan.len() < 2 {
+        return Ok(None);
+    }
+    let has_step = |op: &server_pb::OperatorDef| {
+        let pipelined = matches!(
+            op.ch.as_ref().and_then(|ch| ch.ch_kind.as_ref()),
+            None | Some(server_pb::channel_def::ChKind::ToLocal(_))
+        );
+        let step = match op.op_kind.as_ref() {
+            Some(server_pb::operator_def::OpKind::Filter(filter)) if pipelined => {
+                decode(&filter.resource)?
+            }
+            _ => return None,
+        };
+        if !step.tags.is_empty() || !step.remove_tags.is_empty() {
+            return None;
+        }
+        match step.step {
+            Some(pb::gremlin_step::Step::HasStep(has_step)) => Some(has_step),
+            _ => None,
+        }
+    };
+    let (first, second) = match (has_step(&plan[0]), has_step(&plan[1])) {
+        (Some(first), Some(second)) => (first, second),
+        _ => return Ok(None),
+    };
+    let decide: DecideFn<Traverser> = match mode {
+        Adaptive::Sample(_) => {
+            let first = Arc::new(has_filter_chain(first)?);
+            let second = Arc::new(has_filter_chain(second)?);
+            let names = (step_name(&plan[0], 0), step_name(&plan[1], 1));
+            Box::new(move |sample| decide_order(&first, &second, &names, sample))
+        }
+        Adaptive::Force(choice) => Box::new(move |_| Decision::new(choice, "forced")),
+        Adaptive::Off => unreachable!(),
+    };
+    let sample = match mode {
+        Adaptive::Sample(sample) => sample,
+        _ => 1,
+    };
+    Ok(Some(AdaptivePoint {
+        covers: 2,
+        name: ADAPTIVE_HAS.to_owned(),
+        sample,
+        first: vec![plan[0].clone(), plan[1].clone()],
+        second: vec![plan[1].clone(), plan[0].clone()],
+        decide,
+    }))
+}
+
+/// Evaluate the step passing fewer traversers of the sample first, or keep the order of the plan
+/// if they pass as many
+fn decide_order(
+    first: &TraverserFilterChain, second: &TraverserFilterChain, names: &(String, String),
+    sample: &[Traverser],
+) -> Decision {
+    let passed = |filter: &TraverserFilterChain| {
+        sample.iter().filter(|t| filter.test(t) == Some(true)).count()
+    };
+    let (first_passed, second_passed) = (passed(first), passed(second));
+    let choice = if second_passed < first_passed { Branch::Right } else { Branch::Left };
+    let reason = format!(
+        "{} passed {}/{}, {} passed {}/{}",
+        names.0,
+        first_passed,
+        sample.len(),
+        names.1,
+        second_passed,
+        sample.len()
+    );
+    Decision::new(choice, reason)
+}
+
+fn step_name(op: &server_pb::OperatorDef, index: usize) -> String {
+    if op.name.is_empty() {
+        format!("step {}", index)
+    } else {
+        op.name.clone()
+    }
+}
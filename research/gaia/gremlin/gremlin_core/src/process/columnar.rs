@@ -0,0 +1,225 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The scan producing the vertices by batches of columns, over which the leading stateless steps
+//! of a plan are evaluated column-wise before the vertices are converted to traversers, e.g. the
+//! has and values steps of `g.V().has("age", gt(30)).values("name")`. A batch keeps the ids of
+//! its vertices, the columns of the properties the fused steps read, and the bitmap of the
+//! vertices selected so far, which each has step narrows by its filter evaluated over the
+//! columns, see `Filter::select_batch`. The values step, the last one to be fused, takes the
+//! values of the selected vertices out of the columns.
+//!
+//! The steps are fused until the first one that needs the traversers, e.g. one after an exchange
+//! by the key it computes, or any step of a source tracking the paths, as decided by the compiler
+//! by `fuse_steps`. The traversers produced are exactly those of the steps evaluated one by one.
+
+use crate::generated::gremlin as pb;
+use crate::process::traversal::traverser::Traverser;
+use crate::structure::codec::{pb_chain_to_filter, referenced_properties};
+use crate::structure::{
+    Bitmap, ColumnBatch, Details, ElementFilter, Filter, Vertex, DEFAULT_BATCH_SIZE, ID,
+};
+use crate::Element;
+use pegasus_server::generated::protocol as server_pb;
+use std::sync::Arc;
+
+/// A step fused into the columnar scan
+#[derive(Clone)]
+pub enum ColumnarStep {
+    /// `has(..)` without tags, whose filter narrows the selection of each batch
+    Has(Arc<Filter<Vertex, ElementFilter>>),
+    /// `values(..)` without tags, whose values are taken out of the columns of the properties
+    Values(Vec<String>),
+}
+
+impl ColumnarStep {
+    /// The step if it can be fused, i.e. a has step or a values step of some properties, neither
+    /// of which is tagged
+    pub fn from_step(step: &pb::GremlinStep) -> Option<Self> {
+        if !step.tags.is_empty() || !step.remove_tags.is_empty() {
+            return None;
+        }
+        match step.step.as_ref()? {
+            pb::gremlin_step::Step::HasStep(has_step) => {
+                let filter = match has_step.predicates.as_ref() {
+                    Some(predicates) => pb_chain_to_filter(predicates).ok()?,
+                    None => None,
+                };
+                Some(ColumnarStep::Has(Arc::new(filter.unwrap_or_default())))
+            }
+            pb::gremlin_step::Step::PropertiesStep(properties_step)
+                if !properties_step.properties.is_empty() =>
+            {
+                Some(ColumnarStep::Values(properties_step.properties.clone()))
+            }
+            _ => None,
+        }
+    }
+
+    /// The properties the step reads out of the columns
+    fn columns(&self) -> Vec<String> {
+        match self {
+            ColumnarStep::Has(filter) => referenced_properties(filter),
+            ColumnarStep::Values(keys) => keys.clone(),
+        }
+    }
+}
+
+/// The leading steps of the plan to be fused into the columnar scan, i.e. the has steps followed
+/// by at most one values step, each decoded from the resource of its operator by `decode`. The
+/// fusion stops at the first operator of another step, or of a channel other than the pipeline.
+/// No step is fused unless a has step is, as nothing is selected over the columns otherwise
+pub fn fuse_steps<F>(plan: &[server_pb::OperatorDef], decode: F) -> Vec<ColumnarStep>
+where
+    F: Fn(&[u8]) -> Option<pb::GremlinStep>,
+{
+    use server_pb::channel_def::ChKind;
+    use server_pb::operator_def::OpKind;
+
+    let mut steps = vec![];
+    for op in plan {
+        // the operator after an exchange takes the traversers routed by their keys
+        let kind = op.ch.as_ref().and_then(|ch| ch.ch_kind.as_ref());
+        if !matches!(kind, None | Some(ChKind::ToLocal(_))) {
+            break;
+        }
+        let step = match op.op_kind.as_ref() {
+            Some(OpKind::Filter(filter)) => decode(&filter.resource)
+                .and_then(|step| ColumnarStep::from_step(&step))
+                .filter(|step| matches!(step, ColumnarStep::Has(_))),
+            Some(OpKind::FlatMap(flat_map)) => decode(&flat_map.resource)
+                .and_then(|step| ColumnarStep::from_step(&step))
+                .filter(|step| matches!(step, ColumnarStep::Values(_))),
+            _ => None,
+        };
+        match step {
+            Some(step) => {
+                let is_values = matches!(step, ColumnarStep::Values(_));
+                steps.push(step);
+                if is_values {
+                    break;
+                }
+            }
+            None => break,
+        }
+    }
+    if !steps.iter().any(|step| matches!(step, ColumnarStep::Has(_))) {
+        steps.clear();
+    }
+    steps
+}
+
+/// A batch of the scanned vertices, with the columns of the properties the fused steps read, and
+/// the bitmap of the vertices selected so far
+pub struct VertexBatch<'a> {
+    ids: Vec<ID>,
+    columns: ColumnBatch<'a, Vertex>,
+    selected: Bitmap,
+}
+
+impl<'a> VertexBatch<'a> {
+    /// The batch of the vertices all selected, with the columns of the properties of `keys`
+    pub fn new(vertices: &'a [Vertex], keys: &[String]) -> Self {
+        VertexBatch {
+            ids: vertices.iter().map(|v| v.id()).collect(),
+            columns: ColumnBatch::with_properties(vertices, keys),
+            selected: Bitmap::ones(vertices.len()),
+        }
+    }
+
+    pub fn ids(&self) -> &[ID] {
+        &self.ids
+    }
+
+    pub fn selected(&self) -> &Bitmap {
+        &self.selected
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Narrow the selection by the filter evaluated over the columns of the batch
+    pub fn filter(&mut self, filter: &Filter<Vertex, ElementFilter>) {
+        if self.selected.count_ones() > 0 {
+            let selected = filter.select_batch(&self.columns);
+            self.selected.and_with(&selected);
+        }
+    }
+
+    /// The traversers of the selected vertices, in the order of the batch
+    pub fn into_traversers(self) -> Vec<Traverser> {
+        let elements = self.columns.elements();
+        self.selected.iter_ones().map(|i| Traverser::new(elements[i].clone())).collect()
+    }
+
+    /// The traversers of the values of the properties of `keys` of the selected vertices, in the
+    /// order of the vertices and then of the keys, as `values(..)` of each vertex
+    pub fn values(&self, keys: &[String]) -> Vec<Traverser> {
+        let elements = self.columns.elements();
+        let mut values = vec![];
+        for i in self.selected.iter_ones() {
+            for key in keys {
+                let value = match self.columns.column(key) {
+                    Some(column) => column.to_object(i),
+                    None => elements[i].details().get_property(key).and_then(|v| v.try_to_owned()),
+                };
+                if let Some(value) = value {
+                    values.push(Traverser::object(value));
+                }
+            }
+        }
+        values
+    }
+}
+
+/// The traversers of the vertices scanned by batches of `DEFAULT_BATCH_SIZE`, after the fused
+/// steps are evaluated over each batch one by one
+pub fn columnar_scan(
+    mut vertices: Box<dyn Iterator<Item = Vertex> + Send>, steps: Vec<ColumnarStep>,
+) -> Box<dyn Iterator<Item = Traverser> + Send> {
+    let mut keys: Vec<String> = vec![];
+    for key in steps.iter().flat_map(|step| step.columns()) {
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+    let batches = std::iter::from_fn(move || {
+        let vertices: Vec<Vertex> = vertices.by_ref().take(DEFAULT_BATCH_SIZE).collect();
+        if vertices.is_empty() {
+            None
+        } else {
+            Some(eval_batch(&vertices, &keys, &steps))
+        }
+    });
+    Box::new(batches.flatten())
+}
+
+/// Evaluate the fused steps over the batch of the vertices, which are converted to traversers
+/// at last unless a values step takes the values out of the columns
+pub fn eval_batch(vertices: &[Vertex], keys: &[String], steps: &[ColumnarStep]) -> Vec<Traverser> {
+    let mut batch = VertexBatch::new(vertices, keys);
+    for step in steps {
+        match step {
+            ColumnarStep::Has(filter) => batch.filter(filter),
+            ColumnarStep::Values(keys) => return batch.values(keys),
+        }
+    }
+    batch.into_traversers()
+}
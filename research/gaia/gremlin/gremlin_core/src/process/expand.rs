@@ -0,0 +1,142 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The expansion fusing two consecutive vertex steps into one, e.g. `out("a").out("b")`, whose
+//! second step explores each vertex the first one reaches as soon as it is reached, so the
+//! vertices in between, i.e. the frontier of the first step, are never collected into batches.
+//! The has step right after the second step is fused as well, whose predicates are evaluated by
+//! the second step on the elements it reaches, e.g. of `out("a").out("b").has("name", eq("x"))`.
+//!
+//! A vertex reached by the first step is explored by the worker reaching it, instead of the one
+//! storing it, so the steps are only fused if the partitioner tells all the vertices are stored
+//! in current server, see `Partitioner::all_local`, where the exchange before the first step is
+//! kept while the one before the second step is dropped. Otherwise each step is built on its own,
+//! after the vertices are exchanged to where they are stored, as decided by the compiler by
+//! `fuse_expand`. The first step must not be tagged, i.e. the vertices in between are never
+//! selected by the labels of the paths, while a path tracked anyway is still extended by each
+//! vertex in between. The traversers produced are exactly those of the steps built one by one.
+
+use crate::generated::gremlin as pb;
+use crate::process::traversal::step::FlatMapFuncGen;
+use crate::process::traversal::traverser::Traverser;
+use crate::{str_to_dyn_error, DynIter, DynResult};
+use pegasus::api::function::FlatMapFunction;
+use pegasus_server::generated::protocol as server_pb;
+use std::sync::{Arc, Mutex};
+
+type DynFlatMap = Box<dyn FlatMapFunction<Traverser, Traverser, Target = DynIter<Traverser>>>;
+
+/// The vertex steps fused into one expansion by `fuse_expand`
+pub struct FusedExpand {
+    /// the first vertex step, of vertices
+    pub first: pb::GremlinStep,
+    /// the second vertex step, along with the predicates of the has step after it if fused
+    pub second: pb::GremlinStep,
+}
+
+impl FlatMapFuncGen for FusedExpand {
+    fn gen_flat_map(self) -> DynResult<DynFlatMap> {
+        let first = self.first.gen_flat_map()?;
+        let second = self.second.gen_flat_map()?;
+        Ok(Box::new(FusedExplore { first, second: Arc::new(Mutex::new(second)) }))
+    }
+}
+
+struct FusedExplore {
+    first: DynFlatMap,
+    // the function is only `Send`, while the lazy iterators it feeds outlive each call, so it is
+    // shared by a mutex locked once per vertex the first step reaches, which is never contended
+    // as the iterators are all drained by the worker owning the function
+    second: Arc<Mutex<DynFlatMap>>,
+}
+
+impl FlatMapFunction<Traverser, Traverser> for FusedExplore {
+    type Target = DynIter<Traverser>;
+
+    fn exec(&self, input: Traverser) -> DynResult<DynIter<Traverser>> {
+        let second = self.second.clone();
+        let iter = self.first.exec(input)?.flat_map(move |reached| {
+            let explored = reached.and_then(|t| match second.lock() {
+                Ok(second) => second.exec(t),
+                Err(_) => Err(str_to_dyn_error("the fused vertex step is poisoned")),
+            });
+            let iter: DynIter<Traverser> = match explored {
+                Ok(iter) => iter,
+                Err(e) => Box::new(std::iter::once(Err(e))),
+            };
+            iter
+        });
+        Ok(Box::new(iter))
+    }
+}
+
+/// The vertex steps at the head of the plan to be fused, each decoded from the resource of its
+/// operator by `decode`, along with the number of the operators they are of, i.e. a vertex step
+/// of vertices without tags right followed by another vertex step, and the has step without tags
+/// after it, if any, which is only fused if the second step has no predicates of its own. It
+/// doesn't tell if the partitioner allows the fusion, see `Partitioner::all_local`
+pub fn fuse_expand<F>(plan: &[server_pb::OperatorDef], decode: F) -> Option<(FusedExpand, usize)>
+where
+    F: Fn(&[u8]) -> Option<pb::GremlinStep>,
+{
+    use server_pb::channel_def::ChKind;
+    use server_pb::operator_def::OpKind;
+
+    let decode_op = |op: &server_pb::OperatorDef| match op.op_kind.as_ref() {
+        Some(OpKind::FlatMap(flat_map)) => decode(&flat_map.resource),
+        Some(OpKind::Filter(filter)) => decode(&filter.resource),
+        _ => None,
+    };
+    let untagged = |step: &pb::GremlinStep| step.tags.is_empty() && step.remove_tags.is_empty();
+    let first = decode_op(plan.first()?).filter(|step| untagged(step))?;
+    match first.step.as_ref() {
+        Some(pb::gremlin_step::Step::VertexStep(vertex_step))
+            if vertex_step.return_type == pb::EntityType::Vertex as i32 => {}
+        _ => return None,
+    }
+    // the second step takes the vertices by the pipeline, or by the exchange the fusion drops
+    let second_op = plan.get(1).filter(|op| {
+        matches!(channel_kind(op), None | Some(ChKind::ToLocal(_)) | Some(ChKind::ToAnother(_)))
+            && matches!(op.op_kind, Some(OpKind::FlatMap(_)))
+    })?;
+    let mut second = decode_op(second_op)?;
+    let vertex_step = match second.step.as_mut() {
+        Some(pb::gremlin_step::Step::VertexStep(vertex_step)) => vertex_step,
+        _ => return None,
+    };
+    let has = plan
+        .get(2)
+        .filter(|op| {
+            matches!(channel_kind(op), None | Some(ChKind::ToLocal(_)))
+                && matches!(op.op_kind, Some(OpKind::Filter(_)))
+        })
+        .and_then(decode_op)
+        .filter(|step| untagged(step))
+        .and_then(|step| match step.step {
+            Some(pb::gremlin_step::Step::HasStep(has_step)) => has_step.predicates,
+            _ => None,
+        });
+    match has {
+        Some(predicates) if vertex_step.predicates.is_none() => {
+            vertex_step.predicates = Some(predicates);
+            Some((FusedExpand { first, second }, 3))
+        }
+        _ => Some((FusedExpand { first, second }, 2)),
+    }
+}
+
+fn channel_kind(op: &server_pb::OperatorDef) -> Option<&server_pb::channel_def::ChKind> {
+    op.ch.as_ref().and_then(|ch| ch.ch_kind.as_ref())
+}
@@ -0,0 +1,409 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The limits of how many vertices and edges a query can access, and how many results it can
+//! return, as a guardrail of the queries from the end users. The limits are shared by all
+//! workers of a job in current server, and each server checks its own part of the job.
+//! A job exceeding any of its limits is aborted with `LimitExceeded`, naming the limit and the
+//! step exceeding it, see `pegasus::abort_current_job`.
+//!
+//! Each graph may also be registered with its own `GraphLimits`, e.g. of a tenant, which cap the
+//! jobs on it once they are admitted, overriding the defaults of the server.
+
+use crate::process::metrics;
+use crate::process::traversal::traverser::Traverser;
+use pegasus::JobConf;
+use pegasus_server::error::QueryError;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock, Weak};
+
+/// The limits of a query, where 0 means unlimited
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct AccessLimits {
+    pub max_vertices: u64,
+    pub max_edges: u64,
+    pub max_results: u64,
+}
+
+impl AccessLimits {
+    pub fn new(max_vertices: u64, max_edges: u64, max_results: u64) -> Self {
+        AccessLimits { max_vertices, max_edges, max_results }
+    }
+
+    pub fn is_unlimited(&self) -> bool {
+        self.max_vertices == 0 && self.max_edges == 0 && self.max_results == 0
+    }
+
+    /// Override the limits by the non-zero limits of the job
+    fn override_by(mut self, conf: &pegasus::JobConf) -> Self {
+        if conf.vertex_limit > 0 {
+            self.max_vertices = conf.vertex_limit;
+        }
+        if conf.edge_limit > 0 {
+            self.max_edges = conf.edge_limit;
+        }
+        if conf.result_limit > 0 {
+            self.max_results = conf.result_limit;
+        }
+        self
+    }
+}
+
+pub const VERTEX_LIMIT: &str = "vertex";
+pub const EDGE_LIMIT: &str = "edge";
+pub const RESULT_LIMIT: &str = "result";
+
+/// The error to abort the job whose access exceeds one of its limits
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LimitExceeded {
+    /// The name of the limit, e.g. `EDGE_LIMIT`
+    pub limit: &'static str,
+    pub max: u64,
+    /// The step that trips the limit, e.g. "out()"
+    pub step: String,
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "LimitExceeded: the {} limit {} is exceeded by {}",
+            self.limit, self.max, self.step
+        )
+    }
+}
+
+impl std::error::Error for LimitExceeded {}
+
+impl From<LimitExceeded> for QueryError {
+    fn from(e: LimitExceeded) -> Self {
+        let worker = pegasus::get_current_worker().map(|w| w.index);
+        // the limits may be of the graph the job runs on, rather than of the job itself
+        let graph = crate::structure::get_graph_name();
+        let msg = if graph.is_empty() {
+            e.to_string()
+        } else {
+            format!("{} on the graph '{}'", e, graph)
+        };
+        QueryError::ResourceLimit { limit: e.limit.to_owned(), worker, msg, cause: None }
+    }
+}
+
+impl From<LimitExceeded> for crate::DynError {
+    fn from(e: LimitExceeded) -> Self {
+        // raised as a `QueryError`, so that the client is told which limit is exceeded
+        Box::new(QueryError::from(e))
+    }
+}
+
+lazy_static! {
+    /// The default limits of the queries, following the configuration of the server
+    static ref DEFAULT_LIMITS: RwLock<AccessLimits> = RwLock::new(AccessLimits::default());
+    /// job id -> the access of the job, which is released once all operators of the job are
+    /// dropped
+    static ref JOB_ACCESSES: Mutex<HashMap<u64, Weak<JobAccess>>> = Mutex::new(HashMap::new());
+    /// graph name -> the number of jobs admitted to run on it, see `GraphJobSlot`
+    static ref GRAPH_JOBS: Mutex<HashMap<String, u32>> = Mutex::new(HashMap::new());
+}
+
+/// The limits of the jobs on a graph, registered by `set_graph_limits`, where 0 means unlimited
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct GraphLimits {
+    /// the most jobs running on the graph at the same time in current server
+    pub max_jobs: u32,
+    /// the most workers of each job, beyond which the jobs are clamped instead of rejected
+    pub max_workers: u32,
+    /// the most memory(MB) each job can use in each server, see `JobConf::memory_limit`
+    pub memory_limit_mb: u32,
+    /// the most results each job can return, see `JobConf::result_limit`
+    pub max_results: u64,
+}
+
+pub const MAX_JOBS_LIMIT: &str = "max_jobs";
+
+impl GraphLimits {
+    pub fn is_unlimited(&self) -> bool {
+        *self == GraphLimits::default()
+    }
+
+    /// Admit the job to run on the graph of the name under the limits, which is rejected if the
+    /// graph runs `max_jobs` already, or otherwise holds a `GraphJobSlot` among its resources
+    /// until it ends; The workers of the job are clamped to `max_workers` with a warning, and
+    /// its memory and results are limited by the limits of the graph if they are lower
+    pub fn admit(&self, graph: &str, conf: &mut JobConf) -> Result<(), QueryError> {
+        let slot = GraphJobSlot::acquire(graph, self.max_jobs)?;
+        conf.set_user_data(JOB_GRAPH_SLOT, slot);
+        self.apply(graph, conf);
+        Ok(())
+    }
+
+    fn apply(&self, graph: &str, conf: &mut JobConf) {
+        if self.max_workers > 0 && conf.workers > self.max_workers {
+            warn!(
+                "job {} of {} workers is clamped to max_workers {} of the graph '{}'",
+                conf.job_id, conf.workers, self.max_workers, graph
+            );
+            let clamped = (conf.workers - self.max_workers) as u64;
+            metrics::report_counter(conf.job_id, 0, metrics::CLAMPED_WORKERS_COUNTER, clamped);
+            conf.workers = self.max_workers;
+        }
+        // `!0` by default, i.e. unlimited
+        if self.memory_limit_mb > 0 && conf.memory_limit > self.memory_limit_mb {
+            conf.memory_limit = self.memory_limit_mb;
+        }
+        // 0 by default, i.e. limited by the default limits, which the graph's overrides
+        if self.max_results > 0 && (conf.result_limit == 0 || conf.result_limit > self.max_results)
+        {
+            conf.result_limit = self.max_results;
+        }
+    }
+}
+
+/// The key of the `GraphJobSlot` of a job among its resources
+pub const JOB_GRAPH_SLOT: &str = "gremlin.graph_slot";
+
+/// A job counted among the jobs running on a graph, which is released once dropped, i.e. once
+/// the resources of the job are dropped as it ends
+pub struct GraphJobSlot {
+    graph: String,
+}
+
+impl GraphJobSlot {
+    /// Count a job running on the graph, or reject it if the graph runs `max_jobs` already,
+    /// where 0 means unlimited
+    pub fn acquire(graph: &str, max_jobs: u32) -> Result<Self, QueryError> {
+        let mut jobs = GRAPH_JOBS.lock().map_err(|_| QueryError::internal("poisoned lock"))?;
+        let running = jobs.entry(graph.to_owned()).or_insert(0);
+        if max_jobs > 0 && *running >= max_jobs {
+            let msg = format!(
+                "the graph '{}' runs {} jobs already, at its limit {} {}",
+                graph, running, MAX_JOBS_LIMIT, max_jobs
+            );
+            return Err(QueryError::ResourceLimit {
+                limit: MAX_JOBS_LIMIT.to_owned(),
+                worker: None,
+                msg,
+                cause: None,
+            });
+        }
+        *running += 1;
+        Ok(GraphJobSlot { graph: graph.to_owned() })
+    }
+}
+
+impl Drop for GraphJobSlot {
+    fn drop(&mut self) {
+        if let Ok(mut jobs) = GRAPH_JOBS.lock() {
+            if let Some(running) = jobs.get_mut(&self.graph) {
+                *running = running.saturating_sub(1);
+            }
+        }
+    }
+}
+
+/// The number of jobs admitted to run on the graph of the name, which are not ended yet
+pub fn get_graph_jobs(graph: &str) -> u32 {
+    GRAPH_JOBS.lock().ok().and_then(|jobs| jobs.get(graph).cloned()).unwrap_or(0)
+}
+
+/// Set the default limits of the queries that do not specify their own
+pub fn set_default_limits(limits: AccessLimits) {
+    if let Ok(mut default) = DEFAULT_LIMITS.write() {
+        *default = limits;
+    }
+}
+
+pub fn get_default_limits() -> AccessLimits {
+    DEFAULT_LIMITS.read().map(|limits| *limits).unwrap_or_default()
+}
+
+/// The number of vertices, edges and results a job has accessed in current server
+pub struct JobAccess {
+    limits: AccessLimits,
+    vertices: AtomicU64,
+    edges: AtomicU64,
+    results: AtomicU64,
+    // the first limit that is exceeded, which is raised by all following checks
+    exceeded: Mutex<Option<LimitExceeded>>,
+}
+
+impl JobAccess {
+    fn new(limits: AccessLimits) -> Self {
+        JobAccess {
+            limits,
+            vertices: AtomicU64::new(0),
+            edges: AtomicU64::new(0),
+            results: AtomicU64::new(0),
+            exceeded: Mutex::new(None),
+        }
+    }
+
+    pub fn get_limits(&self) -> &AccessLimits {
+        &self.limits
+    }
+
+    /// Count the vertices accessed by the step, and return `LimitExceeded` if too many
+    #[inline]
+    pub fn add_vertices(&self, n: u64, step: &str) -> Result<(), LimitExceeded> {
+        Self::add(&self.vertices, n, self.limits.max_vertices, VERTEX_LIMIT, step)
+            .map_err(|e| self.trip(e))
+    }
+
+    /// Count the edges accessed by the step, and return `LimitExceeded` if too many
+    #[inline]
+    pub fn add_edges(&self, n: u64, step: &str) -> Result<(), LimitExceeded> {
+        Self::add(&self.edges, n, self.limits.max_edges, EDGE_LIMIT, step).map_err(|e| self.trip(e))
+    }
+
+    /// Count the results returned by the sink, and return `LimitExceeded` if too many
+    #[inline]
+    pub fn add_results(&self, n: u64, step: &str) -> Result<(), LimitExceeded> {
+        Self::add(&self.results, n, self.limits.max_results, RESULT_LIMIT, step)
+            .map_err(|e| self.trip(e))
+    }
+
+    /// Count the results of the batch returned by the sink, each by its bulk, and return
+    /// `LimitExceeded` if too many, or if any limit has been exceeded before
+    pub fn add_sink_results(&self, data: &[Traverser]) -> Result<(), LimitExceeded> {
+        self.check()?;
+        self.add_results(data.iter().map(|t| t.get_bulk()).sum(), "sink")
+    }
+
+    /// Return the limit exceeded before, e.g. by the source which can't raise errors itself
+    pub fn check(&self) -> Result<(), LimitExceeded> {
+        match self.exceeded.lock().ok().and_then(|exceeded| exceeded.clone()) {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    fn add(
+        counter: &AtomicU64, n: u64, max: u64, limit: &'static str, step: &str,
+    ) -> Result<(), LimitExceeded> {
+        let total = counter.fetch_add(n, Ordering::Relaxed) + n;
+        if max > 0 && total > max {
+            Err(LimitExceeded { limit, max, step: step.to_owned() })
+        } else {
+            Ok(())
+        }
+    }
+
+    // the first limit exceeded aborts the job, so that the steps which can't raise errors
+    // themselves, e.g. the source or the sink, fail the job as well
+    fn trip(&self, err: LimitExceeded) -> LimitExceeded {
+        if let Ok(mut exceeded) = self.exceeded.lock() {
+            if exceeded.is_none() {
+                *exceeded = Some(err.clone());
+                pegasus::abort_current_job(err.clone());
+            }
+        }
+        err
+    }
+}
+
+/// Get the access of the job whose dataflow is being built by current thread, following the
+/// default limits overridden by the limits of the job. Return `None` if the job is unlimited,
+/// or it is not called while building the dataflow of a worker.
+pub fn get_job_access() -> Option<Arc<JobAccess>> {
+    let conf = pegasus::get_current_job_conf()?;
+    let limits = get_default_limits().override_by(&conf);
+    if limits.is_unlimited() {
+        return None;
+    }
+    let mut accesses = JOB_ACCESSES.lock().ok()?;
+    accesses.retain(|_, access| access.strong_count() > 0);
+    if let Some(access) = accesses.get(&conf.job_id).and_then(|access| access.upgrade()) {
+        Some(access)
+    } else {
+        let access = Arc::new(JobAccess::new(limits));
+        accesses.insert(conf.job_id, Arc::downgrade(&access));
+        Some(access)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pegasus_server::generated::protocol::QueryErrorKind;
+
+    #[test]
+    fn test_job_access() {
+        let access = JobAccess::new(AccessLimits::new(0, 3, 0));
+        assert!(access.add_vertices(100, "V()").is_ok());
+        assert!(access.add_edges(2, "out()").is_ok());
+        assert!(access.check().is_ok());
+        let err = access.add_edges(2, "outE()").unwrap_err();
+        assert_eq!(err, LimitExceeded { limit: EDGE_LIMIT, max: 3, step: "outE()".to_owned() });
+        assert_eq!(
+            err.to_string(),
+            "LimitExceeded: the edge limit 3 is exceeded by outE()".to_owned()
+        );
+        // the first exceeded limit is kept
+        assert!(access.add_edges(1, "in()").is_err());
+        assert_eq!(access.check(), Err(err));
+    }
+
+    #[test]
+    fn test_override_limits() {
+        let mut conf = pegasus::JobConf::default();
+        conf.edge_limit = 10;
+        let limits = AccessLimits::new(1, 2, 3).override_by(&conf);
+        assert_eq!(limits, AccessLimits::new(1, 10, 3));
+    }
+
+    #[test]
+    fn test_apply_graph_limits() {
+        let limits =
+            GraphLimits { max_jobs: 0, max_workers: 2, memory_limit_mb: 64, max_results: 10 };
+        let mut conf = pegasus::JobConf::default();
+        conf.workers = 4;
+        conf.result_limit = 100;
+        limits.apply("test_apply", &mut conf);
+        assert_eq!((conf.workers, conf.memory_limit, conf.result_limit), (2, 64, 10));
+        // the lower limits of the job are kept
+        let mut conf = pegasus::JobConf::default();
+        conf.workers = 1;
+        conf.memory_limit = 32;
+        conf.result_limit = 5;
+        limits.apply("test_apply", &mut conf);
+        assert_eq!((conf.workers, conf.memory_limit, conf.result_limit), (1, 32, 5));
+    }
+
+    #[test]
+    fn test_graph_job_slot() {
+        let first = GraphJobSlot::acquire("test_slot", 2).unwrap();
+        let second = GraphJobSlot::acquire("test_slot", 2).unwrap();
+        let err = GraphJobSlot::acquire("test_slot", 2).err().expect("over max_jobs");
+        assert_eq!(err.to_pb().limit, MAX_JOBS_LIMIT);
+        assert!(err.to_string().contains("the graph 'test_slot' runs 2 jobs"), "{}", err);
+        assert_eq!(get_graph_jobs("test_slot"), 2);
+        drop(first);
+        let _third = GraphJobSlot::acquire("test_slot", 2).unwrap();
+        drop(second);
+        assert_eq!(get_graph_jobs("test_slot"), 1);
+    }
+
+    #[test]
+    fn test_limit_exceeded_classified() {
+        let err = LimitExceeded { limit: EDGE_LIMIT, max: 3, step: "out()".to_owned() };
+        let err = pegasus::errors::JobExecError::from(crate::DynError::from(err));
+        let err = QueryError::from(&err);
+        assert_eq!(err.kind(), QueryErrorKind::ResourceLimit);
+        assert_eq!(err.to_pb().limit, EDGE_LIMIT);
+        assert!(err.to_string().contains("the edge limit 3 is exceeded by out()"), "{}", err);
+    }
+}
@@ -0,0 +1,101 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The metrics of the running jobs, tagged by the workers that report them.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// The number of traversers expanded by the vertex/edge steps
+pub const EXPAND_COUNTER: &str = "expand";
+/// The number of edges emitted by the edge steps the order after them is pushed into, which are
+/// at most the limit of the order for each traverser expanded, see `push_down_order`
+pub const SORTED_EXPAND_COUNTER: &str = "sorted_expand";
+/// The number of jobs answered without running, e.g. by the statistics of the graph, which are
+/// reported as by the worker of index 0
+pub const SHORTCUT_COUNTER: &str = "shortcut";
+/// The number of workers a job is cut by, as it asks for more than the `max_workers` of its
+/// graph, which are reported as by the worker of index 0, see `GraphLimits`
+pub const CLAMPED_WORKERS_COUNTER: &str = "clamped_workers";
+
+/// The counters of a job, as (worker index, counter name) -> value
+pub type JobMetrics = HashMap<(u32, &'static str), u64>;
+
+lazy_static! {
+    /// job id -> the counters of the job
+    static ref JOB_METRICS: Mutex<HashMap<u64, JobMetrics>> = Mutex::new(HashMap::new());
+}
+
+/// A counter owned by an operator of a worker, which is accumulated locally without any
+/// synchronization across workers, and reported to the job metrics once the operator is dropped.
+pub struct WorkerCounter {
+    name: &'static str,
+    // (job id, worker index), or `None` if created out of any worker
+    worker: Option<(u64, u32)>,
+    value: AtomicU64,
+}
+
+impl WorkerCounter {
+    pub fn new(name: &'static str) -> Self {
+        let worker = pegasus::get_current_worker().map(|w| (w.job_id, w.index));
+        WorkerCounter { name, worker, value: AtomicU64::new(0) }
+    }
+
+    #[inline]
+    pub fn add(&self, delta: u64) {
+        self.value.fetch_add(delta, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn get(&self) -> u64 {
+        self.value.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for WorkerCounter {
+    fn drop(&mut self) {
+        if let Some((job_id, index)) = self.worker {
+            report_counter(job_id, index, self.name, self.value.load(Ordering::Relaxed));
+        }
+    }
+}
+
+/// Add the value to the counter of the worker of given index in the job
+pub fn report_counter(job_id: u64, worker_index: u32, name: &'static str, value: u64) {
+    if let Ok(mut metrics) = JOB_METRICS.lock() {
+        *metrics
+            .entry(job_id)
+            .or_insert_with(HashMap::new)
+            .entry((worker_index, name))
+            .or_insert(0) += value;
+    }
+}
+
+/// Get the value of the counter reported by the worker of given index in the job
+pub fn get_worker_counter(job_id: u64, worker_index: u32, name: &'static str) -> u64 {
+    JOB_METRICS
+        .lock()
+        .ok()
+        .and_then(|metrics| {
+            metrics.get(&job_id).and_then(|m| m.get(&(worker_index, name)).cloned())
+        })
+        .unwrap_or(0)
+}
+
+/// Remove and return all the metrics of the job
+pub fn take_job_metrics(job_id: u64) -> JobMetrics {
+    JOB_METRICS.lock().ok().and_then(|mut metrics| metrics.remove(&job_id)).unwrap_or_default()
+}
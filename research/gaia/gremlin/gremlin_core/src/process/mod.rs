@@ -13,4 +13,14 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
+pub mod adaptive;
+pub mod columnar;
+pub mod expand;
+pub mod limits;
+pub mod metrics;
+pub mod shared_scan;
+pub mod side_store;
+pub mod simplify;
+pub mod sorted_expand;
+pub mod subgraph;
 pub mod traversal;
@@ -0,0 +1,278 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Share one scan of the local graph partition among the jobs scanning vertices at about the same
+//! time, e.g. many small queries all starting with `g.V().hasLabel('person')`. The source of each
+//! job subscribes to the broker of its worker index, and the first subscriber opens a scan group
+//! which waits a short window for others to join before scanning. The group scans once by the
+//! union of the labels of all subscribers, and feeds each of them its own filtered copy through a
+//! bounded buffer.
+//!
+//! The scans are shared among the jobs on the same graph only, as the brokers are of each graph
+//! registered by name, see `register_named_graph`.
+//!
+//! The jobs of no operator but the source, whose sources are read by the service instead of the
+//! workers, share the scans of the whole server, as each source is read by the thread accepting
+//! its job.
+//!
+//! A job stays independently cancellable: once its source is dropped, it is removed from the scan
+//! at the next vertex it accepts, and the scan stops if no subscriber is left. A slow job whose
+//! buffer keeps full for a while is left behind instead of stalling the others, and resumes by
+//! scanning on its own from where it is left, as a scan visits the vertices in the same order.
+
+use crate::structure::{get_graph_name, Label, QueryParams, Vertex};
+use crate::{Element, GraphProxy};
+use crossbeam_channel::{Receiver, SendTimeoutError, Sender};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+lazy_static! {
+    /// How the scans are shared, or `None` if every job scans on its own
+    static ref SHARED_SCAN_CONF: RwLock<Option<SharedScanConf>> = RwLock::new(None);
+    /// the broker of the scans of each worker on each graph
+    static ref SCAN_BROKERS: Mutex<HashMap<BrokerKey, Arc<ScanBroker>>> =
+        Mutex::new(HashMap::new());
+}
+
+/// (graph name, worker index), where `None` stands for the scans of the whole server
+type BrokerKey = (String, Option<usize>);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SharedScanConf {
+    /// How long a scan waits for other jobs to join it before it starts
+    pub window: Duration,
+    /// The number of vertices buffered for each job
+    pub buffer_size: usize,
+    /// How long a scan waits for a job with full buffer before leaving it behind
+    pub stall_timeout: Duration,
+}
+
+impl Default for SharedScanConf {
+    fn default() -> Self {
+        SharedScanConf {
+            window: Duration::from_millis(10),
+            buffer_size: 1024,
+            stall_timeout: Duration::from_millis(10),
+        }
+    }
+}
+
+/// Share the scans of the jobs following the configuration, or disable the sharing by `None`
+pub fn set_shared_scan(conf: Option<SharedScanConf>) {
+    if let Ok(mut shared) = SHARED_SCAN_CONF.write() {
+        *shared = conf;
+    }
+}
+
+pub fn get_shared_scan() -> Option<SharedScanConf> {
+    SHARED_SCAN_CONF.read().ok().and_then(|conf| *conf)
+}
+
+type VertexFilter = Arc<dyn Fn(&Vertex) -> bool + Send + Sync>;
+
+/// Scan the vertices of `params` along with other jobs scanning at the same time, keeping the
+/// vertices accepted by `is_owner`. Return `None` if the scans are not shared, or the scan can't
+/// be shared, e.g. it is limited.
+pub fn shared_scan<F>(
+    worker_index: Option<usize>, params: &QueryParams<Vertex>, is_owner: F,
+) -> Option<Box<dyn Iterator<Item = Vertex> + Send>>
+where
+    F: Fn(&Vertex) -> bool + Send + Sync + 'static,
+{
+    let conf = get_shared_scan()?;
+    if params.limit.is_some() {
+        return None;
+    }
+    let graph = crate::get_graph()?;
+    let labels = params.labels.clone();
+    let filter = params.filter.clone();
+    let accept: VertexFilter = Arc::new(move |v: &Vertex| {
+        (labels.is_empty() || labels.contains(v.label()))
+            && filter.as_ref().map(|f| f.test(v).unwrap_or(false)).unwrap_or(true)
+            && is_owner(v)
+    });
+    let broker = {
+        let mut brokers = SCAN_BROKERS.lock().ok()?;
+        let key = (get_graph_name(), worker_index);
+        brokers.entry(key).or_insert_with(|| Arc::new(ScanBroker::default())).clone()
+    };
+    Some(Box::new(broker.subscribe(graph, &params.labels, accept, conf)))
+}
+
+/// Where a subscriber left behind resumes its scan, i.e. the position of the first vertex it
+/// misses in the scan of the labels
+type Resume = Arc<Mutex<Option<(usize, Vec<Label>)>>>;
+
+struct Subscriber {
+    accept: VertexFilter,
+    tx: Sender<Vertex>,
+    resume: Resume,
+}
+
+impl Subscriber {
+    /// Offer the vertex at `pos` of the scan to the subscriber, and return false if the
+    /// subscriber leaves the scan, i.e. it is cancelled or left behind
+    fn offer(&self, pos: usize, v: &Vertex, labels: &[Label], stall_timeout: Duration) -> bool {
+        if !(self.accept)(v) {
+            return true;
+        }
+        match self.tx.send_timeout(v.clone(), stall_timeout) {
+            Ok(()) => true,
+            Err(SendTimeoutError::Timeout(_)) => {
+                if let Ok(mut resume) = self.resume.lock() {
+                    *resume = Some((pos, labels.to_vec()));
+                }
+                false
+            }
+            Err(SendTimeoutError::Disconnected(_)) => false,
+        }
+    }
+}
+
+/// The jobs sharing one scan, where empty labels mean scanning all vertices
+struct ScanGroup {
+    labels: Vec<Label>,
+    subscribers: Vec<Subscriber>,
+}
+
+impl ScanGroup {
+    fn join(&mut self, labels: &[Label], subscriber: Subscriber) {
+        if labels.is_empty() {
+            self.labels.clear();
+        } else if !self.labels.is_empty() {
+            for label in labels {
+                if !self.labels.contains(label) {
+                    self.labels.push(label.clone());
+                }
+            }
+        }
+        self.subscribers.push(subscriber);
+    }
+
+    fn run(self, graph: &dyn GraphProxy, stall_timeout: Duration) {
+        let ScanGroup { labels, mut subscribers } = self;
+        let mut params = QueryParams::new();
+        params.labels = labels.clone();
+        let vertices = match graph.scan_vertex(&params) {
+            Ok(vertices) => vertices,
+            Err(err) => {
+                error!("shared scan of {:?} failed: {}", labels, err);
+                return;
+            }
+        };
+        for (pos, v) in vertices.enumerate() {
+            subscribers.retain(|subscriber| subscriber.offer(pos, &v, &labels, stall_timeout));
+            if subscribers.is_empty() {
+                break;
+            }
+        }
+    }
+}
+
+/// The scans of a worker, which holds the group opened by now and waiting for others to join
+#[derive(Default)]
+struct ScanBroker {
+    pending: Mutex<Option<ScanGroup>>,
+}
+
+impl ScanBroker {
+    fn subscribe(
+        self: Arc<Self>, graph: Arc<dyn GraphProxy>, labels: &[Label], accept: VertexFilter,
+        conf: SharedScanConf,
+    ) -> SharedScanIter {
+        let (tx, rx) = crossbeam_channel::bounded(conf.buffer_size.max(1));
+        let resume = Resume::default();
+        let subscriber = Subscriber { accept: accept.clone(), tx, resume: resume.clone() };
+        let mut pending = self.pending.lock().expect("lock poisoned");
+        if let Some(group) = pending.as_mut() {
+            group.join(labels, subscriber);
+        } else {
+            *pending = Some(ScanGroup { labels: labels.to_vec(), subscribers: vec![subscriber] });
+            let broker = self.clone();
+            let scan_graph = graph.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(conf.window);
+                let group = broker.pending.lock().ok().and_then(|mut pending| pending.take());
+                if let Some(group) = group {
+                    group.run(scan_graph.as_ref(), conf.stall_timeout);
+                }
+            });
+        }
+        SharedScanIter { rx, graph, accept, resume, behind: None }
+    }
+}
+
+/// The vertices fed to a job by a shared scan, which goes on scanning on its own once it is left
+/// behind by the shared scan
+struct SharedScanIter {
+    rx: Receiver<Vertex>,
+    graph: Arc<dyn GraphProxy>,
+    accept: VertexFilter,
+    resume: Resume,
+    behind: Option<Box<dyn Iterator<Item = Vertex> + Send>>,
+}
+
+impl SharedScanIter {
+    fn resume_alone(&mut self) -> Option<Box<dyn Iterator<Item = Vertex> + Send>> {
+        let (pos, labels) = self.resume.lock().ok()?.take()?;
+        let mut params = QueryParams::new();
+        params.labels = labels;
+        let vertices = self.graph.scan_vertex(&params).ok()?;
+        let accept = self.accept.clone();
+        Some(Box::new(vertices.skip(pos).filter(move |v| accept(v))))
+    }
+}
+
+impl Iterator for SharedScanIter {
+    type Item = Vertex;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(behind) = self.behind.as_mut() {
+            return behind.next();
+        }
+        match self.rx.recv() {
+            Ok(v) => Some(v),
+            Err(_) => {
+                // the shared scan ends, or leaves the job behind
+                self.behind =
+                    Some(self.resume_alone().unwrap_or_else(|| Box::new(std::iter::empty())));
+                self.behind.as_mut().and_then(|behind| behind.next())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn scan_group_join_test() {
+        let subscriber = || {
+            let (tx, _) = crossbeam_channel::bounded(1);
+            Subscriber { accept: Arc::new(|_: &Vertex| true), tx, resume: Resume::default() }
+        };
+        let mut group = ScanGroup { labels: vec![Label::Id(0.into())], subscribers: vec![] };
+        group.join(&[Label::Id(1.into()), Label::Id(0.into())], subscriber());
+        assert_eq!(group.labels, vec![Label::Id(0.into()), Label::Id(1.into())]);
+        // scan all vertices if any subscriber scans all
+        group.join(&[], subscriber());
+        assert!(group.labels.is_empty());
+        group.join(&[Label::Id(2.into())], subscriber());
+        assert!(group.labels.is_empty());
+        assert_eq!(group.subscribers.len(), 3);
+    }
+}
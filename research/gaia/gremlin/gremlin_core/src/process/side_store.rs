@@ -0,0 +1,265 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The named side collections of a job, e.g. collected by `aggregate("x")` or `store("x")`, and
+//! read by `cap("x")` or `where(within("x"))`, and the subgraphs collected by `subgraph("x")`.
+//! The collections are shared by all workers of a job in current server, and are dropped along
+//! with the resources of the job once all its workers in current server end, when they are saved
+//! into the session of the job if any, see `bind_job_side_store`. So are the filters of the has steps, which are decoded once by the first worker
+//! building the operator and shared by the others, as they are immutable once decoded.
+
+use crate::process::subgraph::SubgraphBuilder;
+use crate::process::traversal::traverser::Traverser;
+use crate::session::{get_session_job, SessionJob};
+use crate::structure::TraverserFilterChain;
+use crate::{Element, ID};
+use dyn_type::Object;
+use pegasus::JobConf;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, RwLock};
+
+/// The key of the side store among the resources of a job, see `pegasus::get_job_resource`
+pub const JOB_SIDE_STORE: &str = "gremlin.side_store";
+
+/// The head of a traverser in a side collection, to check if other traversers are within the
+/// collection by their heads
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SideKey {
+    Element(ID),
+    Value(Object),
+}
+
+impl SideKey {
+    pub fn of(traverser: &Traverser) -> Option<SideKey> {
+        if let Some(element) = traverser.get_element() {
+            Some(SideKey::Element(element.id()))
+        } else {
+            traverser.get_object().map(|o| SideKey::Value(o.clone()))
+        }
+    }
+}
+
+/// A named collection of traversers
+#[derive(Default)]
+pub struct SideCollection {
+    items: Mutex<Vec<Traverser>>,
+    // the heads of the collection, cached once the collection is sealed
+    sealed: RwLock<Option<Arc<HashSet<SideKey>>>>,
+}
+
+impl SideCollection {
+    /// Add traversers to the collection, e.g. by `store("x")`
+    pub fn extend<I: IntoIterator<Item = Traverser>>(&self, traversers: I) {
+        if let Ok(mut items) = self.items.lock() {
+            items.extend(traversers);
+        }
+    }
+
+    /// Seal the collection once all traversers are added, e.g. by the barrier of `aggregate("x")`
+    pub fn seal(&self) {
+        let set = self.build_set();
+        if let Ok(mut sealed) = self.sealed.write() {
+            *sealed = Some(Arc::new(set));
+        }
+    }
+
+    pub fn is_sealed(&self) -> bool {
+        self.sealed.read().map(|sealed| sealed.is_some()).unwrap_or(false)
+    }
+
+    /// Get all traversers collected by now
+    pub fn get_all(&self) -> Vec<Traverser> {
+        self.items.lock().map(|items| items.clone()).unwrap_or_default()
+    }
+
+    /// Get the heads of the collection as a set, which is built once if the collection is sealed,
+    /// or on each call otherwise.
+    pub fn as_set(&self) -> Arc<HashSet<SideKey>> {
+        if let Some(set) = self.sealed.read().ok().and_then(|sealed| sealed.clone()) {
+            set
+        } else {
+            Arc::new(self.build_set())
+        }
+    }
+
+    fn build_set(&self) -> HashSet<SideKey> {
+        self.items
+            .lock()
+            .map(|items| items.iter().filter_map(SideKey::of).collect())
+            .unwrap_or_default()
+    }
+}
+
+/// The side collections of a job in current server
+pub struct JobSideStore {
+    job_id: u64,
+    collections: Mutex<HashMap<String, Arc<SideCollection>>>,
+    subgraphs: Mutex<HashMap<String, Arc<SubgraphBuilder>>>,
+    // the encoded has steps -> the filters decoded from them
+    filters: Mutex<HashMap<Vec<u8>, Arc<TraverserFilterChain>>>,
+    // the session the job runs in, to save the collections into
+    session_job: Option<Arc<SessionJob>>,
+}
+
+impl JobSideStore {
+    fn new(job_id: u64) -> Self {
+        // the failure of joining the session is raised by the source of the job
+        let session_job = get_session_job().ok().flatten();
+        JobSideStore {
+            job_id,
+            collections: Mutex::new(HashMap::new()),
+            subgraphs: Mutex::new(HashMap::new()),
+            filters: Mutex::new(HashMap::new()),
+            session_job,
+        }
+    }
+
+    /// Get the collection of the name, or create an empty one if not exist
+    pub fn get_collection(&self, name: &str) -> Arc<SideCollection> {
+        let mut collections = self.collections.lock().expect("lock poisoned");
+        collections
+            .entry(name.to_owned())
+            .or_insert_with(|| Arc::new(SideCollection::default()))
+            .clone()
+    }
+
+    /// Get the subgraph of the name, or create an empty one of at most `max_edges` edges if not
+    /// exist, which is published by the handle of the name and the job, e.g. "sg@1"
+    pub fn get_subgraph(&self, name: &str, max_edges: u64) -> Arc<SubgraphBuilder> {
+        let mut subgraphs = self.subgraphs.lock().expect("lock poisoned");
+        let handle = format!("{}@{}", name, self.job_id);
+        subgraphs
+            .entry(name.to_owned())
+            .or_insert_with(|| Arc::new(SubgraphBuilder::new(handle, max_edges)))
+            .clone()
+    }
+
+    /// Get the subgraph of the name if collected by the job, e.g. to tell what `cap("x")` emits
+    pub fn find_subgraph(&self, name: &str) -> Option<Arc<SubgraphBuilder>> {
+        self.subgraphs.lock().ok().and_then(|subgraphs| subgraphs.get(name).cloned())
+    }
+
+    /// Get the filter decoded from the encoded step by any worker of the job
+    pub fn find_filter(&self, step: &[u8]) -> Option<Arc<TraverserFilterChain>> {
+        self.filters.lock().ok().and_then(|filters| filters.get(step).cloned())
+    }
+
+    /// Share the filter decoded from the encoded step with the other workers, or get the one
+    /// shared first if the workers decode the step at the same time
+    pub fn share_filter(
+        &self, step: &[u8], filter: TraverserFilterChain,
+    ) -> Arc<TraverserFilterChain> {
+        let mut filters = self.filters.lock().expect("lock poisoned");
+        filters.entry(step.to_vec()).or_insert_with(|| Arc::new(filter)).clone()
+    }
+}
+
+impl Drop for JobSideStore {
+    fn drop(&mut self) {
+        let session = match self.session_job.as_ref() {
+            Some(job) => job.get_session(),
+            None => return,
+        };
+        // the collections only read by the job, e.g. by `where(within("x"))`, are not saved
+        if let Ok(collections) = self.collections.lock() {
+            for (name, collection) in collections.iter() {
+                let items = collection.get_all();
+                if !items.is_empty() || collection.is_sealed() {
+                    if let Err(err) = session.save_result(name, items) {
+                        error!("fail to save the result {} into the session: {}", name, err);
+                    }
+                }
+            }
+        }
+        if let Ok(subgraphs) = self.subgraphs.lock() {
+            for (name, subgraph) in subgraphs.iter() {
+                let edges = subgraph.get_edges().into_iter().map(Traverser::new).collect();
+                if let Err(err) = session.save_result(name, edges) {
+                    error!("fail to save the subgraph {} into the session: {}", name, err);
+                }
+            }
+        }
+    }
+}
+
+/// The side store of a job, which is created by the first worker of the job in current server
+/// building its dataflow, and dropped along with the resources of the job once all its workers in
+/// current server end, so that a worker building late still shares the store with the others
+#[derive(Default)]
+pub struct JobSideStoreSlot {
+    store: Mutex<Option<Arc<JobSideStore>>>,
+}
+
+impl JobSideStoreSlot {
+    fn get_or_create(&self, job_id: u64) -> Option<Arc<JobSideStore>> {
+        let mut store = self.store.lock().ok()?;
+        Some(store.get_or_insert_with(|| Arc::new(JobSideStore::new(job_id))).clone())
+    }
+}
+
+/// Bind a side store to the job, which must be done before the job is submitted for its steps to
+/// collect or read any side collection, e.g. by `GremlinJobCompiler::prepare`
+pub fn bind_job_side_store(conf: &mut JobConf) {
+    conf.set_user_data(JOB_SIDE_STORE, JobSideStoreSlot::default());
+}
+
+/// Get the side collections of the job whose dataflow is being built by current thread, or `None`
+/// if it is not called while building the dataflow of a worker, or no side store is bound to the
+/// job by `bind_job_side_store`.
+pub fn get_job_side_store() -> Option<Arc<JobSideStore>> {
+    let conf = pegasus::get_current_job_conf()?;
+    let slot = pegasus::get_job_resource::<JobSideStoreSlot>(JOB_SIDE_STORE).ok()?;
+    slot.get_or_create(conf.job_id)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_side_collection() {
+        let store = JobSideStoreSlot::default().get_or_create(6020).unwrap();
+        let collection = store.get_collection("x");
+        collection.extend(vec![Traverser::object(1.into()), Traverser::object(2.into())]);
+        assert!(!collection.is_sealed());
+        // the same collection is shared by the name
+        store.get_collection("x").extend(vec![Traverser::object(1.into())]);
+        collection.seal();
+        assert!(collection.is_sealed());
+        assert_eq!(collection.get_all().len(), 3);
+        let set = collection.as_set();
+        assert_eq!(set.len(), 2);
+        assert!(set.contains(&SideKey::Value(2.into())));
+        assert!(!set.contains(&SideKey::Value(3.into())));
+        assert!(store.get_collection("y").get_all().is_empty());
+        assert!(store.find_subgraph("x").is_none());
+        assert_eq!(store.get_subgraph("sg", 0).handle(), "sg@6020");
+        assert!(store.find_subgraph("sg").is_some());
+    }
+
+    #[test]
+    fn test_side_store_slot() {
+        let slot = JobSideStoreSlot::default();
+        let store = slot.get_or_create(6021).unwrap();
+        let weak = Arc::downgrade(&store);
+        drop(store);
+        // kept by the slot for the workers building late, until the slot is dropped with the job
+        let another = slot.get_or_create(6021).unwrap();
+        assert!(Arc::ptr_eq(&weak.upgrade().unwrap(), &another));
+        drop(another);
+        drop(slot);
+        assert!(weak.upgrade().is_none());
+    }
+}
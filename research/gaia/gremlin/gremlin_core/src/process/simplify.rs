@@ -0,0 +1,1175 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The simplification of the predicates of the plans before the dataflows are built, e.g. of
+//! `has("age", gt(10)).has("age", gt(20))` into `has("age", gt(20))`, see
+//! `JobCompiler::simplify`. The predicates of a chain are evaluated from right to left with short
+//! circuits as `pb_chain_to_filter` decodes them, where `and` is the first of its predicates not
+//! true, `or` is the first one not false, and a predicate failing to compare, e.g. on a missing
+//! property, fails the whole chain. So the predicates are only merged by their values if all of
+//! them must be true for a step to pass, i.e. those of `and` at the top of the step, or of the
+//! consecutive has steps:
+//!
+//! * the bounds of a property, e.g. `gt(10)` and `gt(20)`, are merged into the tightest ones,
+//!   while the predicates never all true, e.g. `gt(30)` and `lt(20)`, or `eq(1)` and `neq(1)`,
+//!   make the step never pass;
+//! * the predicates true or false for every element, e.g. `hasId(without([]))`, are folded away,
+//!   in `or` as well as in `and`;
+//! * the properties none of the labels scanned by the source has by the schema are rejected, or
+//!   taken as missing if the type check is lenient, see `TypeCheck`.
+//!
+//! A step never passing drops the steps before it which only feed it, as well as the scan of the
+//! source if it reaches the source. The consecutive has steps are only merged if the predicates
+//! are simplified by merging, and are kept as they are otherwise, e.g. to be ordered adaptively,
+//! see `process::adaptive`.
+
+use crate::generated::common as common_pb;
+use crate::generated::gremlin as pb;
+use crate::structure::resolve_float_epsilon;
+use crate::validate::TypeCheck;
+use graph_store::common::LabelId;
+use graph_store::schema::GraphSchemaInfo;
+use pegasus_server::factory::PlanError;
+use pegasus_server::generated::protocol as server_pb;
+use prost::Message;
+use server_pb::operator_def::OpKind;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::convert::TryFrom;
+
+/// Simplify the predicates of the source and of the has steps of the plans of the request, where
+/// the properties are checked by the schema of the graph if any; returns whether the request is
+/// rewritten
+pub fn simplify_request(
+    req: &mut server_pb::JobRequest, schema: Option<&GraphSchemaInfo>, type_check: TypeCheck,
+) -> Result<bool, PlanError> {
+    let mut source = req
+        .source
+        .as_ref()
+        .and_then(|source| pb::GremlinStep::decode(source.resource.as_slice()).ok())
+        .filter(|step| matches!(step.step, Some(pb::gremlin_step::Step::GraphStep(_))));
+    let mut simplifier =
+        PlanSimplifier { known: None, type_check, op_index: vec![], rewritten: false };
+    if let Some(graph_step) = source.as_mut().and_then(graph_step_of) {
+        simplifier.known = schema.and_then(|schema| scanned_properties(schema, graph_step));
+        if let Some(pred) = graph_step.predicates.as_ref().and_then(parse_chain) {
+            simplifier.check_properties(&pred)?;
+            let mut chain = Simplifier::new(simplifier.known.as_ref());
+            match chain.simplify(pred, true) {
+                _ if !chain.fired => {}
+                Simplified::Always(true) => graph_step.predicates = None,
+                Simplified::Pred(pred) => graph_step.predicates = Some(encode(&pred)),
+                Simplified::Always(false) | Simplified::Never => {
+                    graph_step.predicates = Some(constant_chain(false))
+                }
+            }
+        }
+    }
+    if let Some(plan) = req.plan.as_mut() {
+        let scanned = source.is_some();
+        if simplifier.simplify_plan(&mut plan.plan, scanned)? {
+            // nothing the source scans passes the plan
+            if let Some(graph_step) = source.as_mut().and_then(graph_step_of) {
+                graph_step.predicates = Some(constant_chain(false));
+            }
+        }
+    }
+    if let (Some(step), Some(raw)) = (source, req.source.as_mut()) {
+        let resource = to_bytes(&step);
+        if resource != raw.resource {
+            raw.resource = resource;
+            simplifier.rewritten = true;
+        }
+    }
+    Ok(simplifier.rewritten)
+}
+
+/// Whether the step is a scan whose predicates are never true as simplified by
+/// `simplify_request`, which scans nothing
+pub fn never_matches(step: &pb::GremlinStep) -> bool {
+    match step.step.as_ref() {
+        Some(pb::gremlin_step::Step::GraphStep(graph_step)) => {
+            graph_step.predicates.as_ref().is_some_and(|chain| *chain == constant_chain(false))
+        }
+        _ => false,
+    }
+}
+
+/// The chain true for every element, i.e. `hasId(without([]))`
+pub fn true_chain() -> pb::FilterChain {
+    constant_chain(true)
+}
+
+/// The chain false for every element, i.e. `hasId(within([]))`
+pub fn false_chain() -> pb::FilterChain {
+    constant_chain(false)
+}
+
+fn graph_step_of(step: &mut pb::GremlinStep) -> Option<&mut pb::GraphStep> {
+    match step.step.as_mut() {
+        Some(pb::gremlin_step::Step::GraphStep(graph_step)) => Some(graph_step),
+        _ => None,
+    }
+}
+
+/// The properties the elements scanned by the source may have by the schema of their labels, or
+/// `None` if the source scans all the labels, or any label unknown to the schema
+fn scanned_properties(
+    schema: &GraphSchemaInfo, graph_step: &pb::GraphStep,
+) -> Option<HashSet<String>> {
+    if graph_step.labels.is_empty() {
+        return None;
+    }
+    let labels = if graph_step.return_type == pb::EntityType::Edge as i32 {
+        &schema.edge_labels
+    } else {
+        &schema.vertex_labels
+    };
+    let mut properties = HashSet::new();
+    for id in graph_step.labels.iter() {
+        let id = LabelId::try_from(*id).ok()?;
+        let label = labels.iter().find(|label| label.id == id)?;
+        properties.extend(label.properties.iter().map(|(name, _)| name.clone()));
+    }
+    Some(properties)
+}
+
+/// A has step of a plan with its predicates
+struct HasOp {
+    step: pb::GremlinStep,
+    pred: Pred,
+    // whether the step tests the elements the source scans, whose properties are known
+    scanned: bool,
+}
+
+struct PlanSimplifier {
+    // the properties of the elements the source scans, if known by the schema
+    known: Option<HashSet<String>>,
+    type_check: TypeCheck,
+    // the index of the operator being simplified, as that of `PlanError`
+    op_index: Vec<usize>,
+    rewritten: bool,
+}
+
+impl PlanSimplifier {
+    /// Simplify the plan, where `scanned` tells the plan starts with the elements the source
+    /// scans, whose properties are checked until an operator changes the traversers; returns
+    /// whether nothing passes the plan from its head on
+    fn simplify_plan(
+        &mut self, plan: &mut Vec<server_pb::OperatorDef>, scanned: bool,
+    ) -> Result<bool, PlanError> {
+        // the has steps are checked by their indices in the plan as it is given
+        let mut has = Vec::with_capacity(plan.len());
+        let mut scanned = scanned;
+        for (i, op) in plan.iter_mut().enumerate() {
+            self.op_index.push(i);
+            self.simplify_nested(op)?;
+            scanned &= matches!(op.op_kind, Some(OpKind::Filter(_)) | Some(OpKind::Shuffle(_)));
+            let has_op = parse_has(op).map(|(step, pred)| HasOp { step, pred, scanned });
+            if let Some(has_op) = has_op.as_ref().filter(|has_op| has_op.scanned) {
+                self.check_properties(&has_op.pred)?;
+            }
+            has.push(has_op);
+            self.op_index.pop();
+        }
+
+        let given = std::mem::take(plan);
+        let mut never_passes = false;
+        let mut i = 0;
+        while i < given.len() {
+            let has_op = match has[i].as_ref() {
+                Some(has_op) => has_op,
+                None => {
+                    plan.push(given[i].clone());
+                    i += 1;
+                    continue;
+                }
+            };
+            let known = if has_op.scanned { self.known.as_ref() } else { None };
+            let mut simplifier = Simplifier::new(known);
+            let mut simplified = simplifier.simplify(has_op.pred.clone(), true);
+            let mut end = i + 1;
+            // merge the next has steps as long as the predicates are simplified by merging
+            while let Some(Some(next)) = has.get(end) {
+                if !is_mergeable(&given[end], &next.step) {
+                    break;
+                }
+                let separate = Simplifier::new(known).simplify(next.pred.clone(), true);
+                let both = Pred::And(vec![simplified.clone().into_pred(), next.pred.clone()]);
+                let merged = Simplifier::new(known).simplify(both, true);
+                if merged.leaves() >= simplified.leaves() + separate.leaves() {
+                    break;
+                }
+                simplified = merged;
+                simplifier.fired = true;
+                end += 1;
+            }
+            if !simplifier.fired {
+                plan.push(given[i].clone());
+                i += 1;
+                continue;
+            }
+            let predicates = encode_simplified(&simplified);
+            let mut step = has_op.step.clone();
+            step.step =
+                Some(pb::gremlin_step::Step::HasStep(pb::HasStep { predicates: Some(predicates) }));
+            let filter = server_pb::Filter { resource: to_bytes(&step) };
+            let names: Vec<&str> = given[i..end].iter().map(|op| op.name.as_str()).collect();
+            let op = server_pb::OperatorDef {
+                ch: given[i].ch.clone(),
+                op_kind: Some(OpKind::Filter(filter)),
+                name: names.join("."),
+            };
+            self.rewritten |= end > i + 1 || op != given[i];
+            if simplified.never_passes() {
+                // the operators only feeding the step are of no use, as nothing passes it
+                while plan.last().is_some_and(only_feeds) {
+                    plan.pop();
+                    self.rewritten = true;
+                }
+                never_passes |= plan.is_empty();
+            }
+            plan.push(op);
+            i = end;
+        }
+        Ok(never_passes)
+    }
+
+    // the plans nested in the operator, indexed as those of `explain`
+    fn simplify_nested(&mut self, op: &mut server_pb::OperatorDef) -> Result<(), PlanError> {
+        match op.op_kind.as_mut() {
+            Some(OpKind::Union(union)) => self.simplify_branches(&mut union.branches),
+            Some(OpKind::Coalesce(coalesce)) => self.simplify_branches(&mut coalesce.branches),
+            Some(OpKind::Iterate(iteration)) => self.simplify_task(&mut iteration.body),
+            Some(OpKind::Subtask(subtask)) => self.simplify_task(&mut subtask.task),
+            Some(OpKind::TimeLimit(time_limit)) => self.simplify_task(&mut time_limit.task),
+            _ => Ok(()),
+        }
+    }
+
+    fn simplify_branches(&mut self, branches: &mut [server_pb::TaskPlan]) -> Result<(), PlanError> {
+        for (i, branch) in branches.iter_mut().enumerate() {
+            self.op_index.push(i);
+            self.simplify_plan(&mut branch.plan, false)?;
+            self.op_index.pop();
+        }
+        Ok(())
+    }
+
+    fn simplify_task(&mut self, task: &mut Option<server_pb::TaskPlan>) -> Result<(), PlanError> {
+        if let Some(task) = task.as_mut() {
+            self.simplify_plan(&mut task.plan, false)?;
+        }
+        Ok(())
+    }
+
+    // the properties compared by the predicates on the elements the source scans must be of
+    // their labels, or they are never there
+    fn check_properties(&self, pred: &Pred) -> Result<(), PlanError> {
+        let known = match self.known.as_ref() {
+            Some(known) => known,
+            None => return Ok(()),
+        };
+        let unknown =
+            pred.exps().into_iter().filter_map(property_of).find(|key| !known.contains(*key));
+        if let Some(key) = unknown {
+            let msg = format!("property {} is of none of the labels the source scans", key);
+            match self.type_check {
+                TypeCheck::Strict => return Err(PlanError::new(self.op_index.clone(), msg)),
+                TypeCheck::Lenient => warn!("{}, which never matches", msg),
+            }
+        }
+        Ok(())
+    }
+}
+
+// the has step of the operator with its predicates
+fn parse_has(op: &server_pb::OperatorDef) -> Option<(pb::GremlinStep, Pred)> {
+    let filter = match op.op_kind.as_ref() {
+        Some(OpKind::Filter(filter)) => filter,
+        _ => return None,
+    };
+    let step = pb::GremlinStep::decode(filter.resource.as_slice()).ok()?;
+    let pred = match step.step.as_ref() {
+        Some(pb::gremlin_step::Step::HasStep(has_step)) => {
+            parse_chain(has_step.predicates.as_ref()?)?
+        }
+        _ => return None,
+    };
+    Some((step, pred))
+}
+
+// the has step of the operator can be merged into the step before it, as it tests the same
+// traversers and tags none of them
+fn is_mergeable(op: &server_pb::OperatorDef, step: &pb::GremlinStep) -> bool {
+    let pipelined = matches!(
+        op.ch.as_ref().and_then(|ch| ch.ch_kind.as_ref()),
+        None | Some(server_pb::channel_def::ChKind::ToLocal(_))
+    );
+    pipelined && step.tags.is_empty() && step.remove_tags.is_empty()
+}
+
+// the operators only feeding the next ones, which are of no use before a step nothing passes
+fn only_feeds(op: &server_pb::OperatorDef) -> bool {
+    match op.op_kind.as_ref() {
+        Some(OpKind::Shuffle(_)) | Some(OpKind::FlatMap(_)) | Some(OpKind::Filter(_)) => true,
+        Some(OpKind::Map(map)) => match pb::GremlinStep::decode(map.resource.as_slice()) {
+            Ok(step) => !matches!(step.step, Some(pb::gremlin_step::Step::SideEffectStep(_))),
+            Err(_) => false,
+        },
+        _ => false,
+    }
+}
+
+/// The predicates of a chain as it is evaluated, with those of the same connector flattened, as
+/// both `and` and `or` are associative
+#[derive(Clone, Debug, PartialEq)]
+enum Pred {
+    Leaf(pb::FilterExp),
+    And(Vec<Pred>),
+    Or(Vec<Pred>),
+}
+
+impl Pred {
+    fn exps(&self) -> Vec<&pb::FilterExp> {
+        match self {
+            Pred::Leaf(exp) => vec![exp],
+            Pred::And(preds) | Pred::Or(preds) => {
+                preds.iter().flat_map(|pred| pred.exps()).collect()
+            }
+        }
+    }
+}
+
+/// What the predicates of a step are simplified into
+#[derive(Clone, Debug, PartialEq)]
+enum Simplified {
+    Pred(Pred),
+    /// True or false for every element, e.g. `hasId(without([]))` or `hasId(within([]))`
+    Always(bool),
+    /// Never true, while it may fail to compare, e.g. `has("age", gt(30).and(lt(20)))`, which is
+    /// only told of the predicates all of which must be true
+    Never,
+}
+
+impl Simplified {
+    fn into_pred(self) -> Pred {
+        match self {
+            Simplified::Pred(pred) => pred,
+            Simplified::Always(value) => Pred::Leaf(constant_exp(value)),
+            Simplified::Never => Pred::Leaf(constant_exp(false)),
+        }
+    }
+
+    fn leaves(&self) -> usize {
+        match self {
+            Simplified::Pred(pred) => pred.exps().len(),
+            _ => 1,
+        }
+    }
+
+    fn never_passes(&self) -> bool {
+        matches!(self, Simplified::Always(false) | Simplified::Never)
+    }
+}
+
+struct Simplifier<'a> {
+    // the properties the elements may have, if known by the schema
+    known: Option<&'a HashSet<String>>,
+    // whether any predicate is simplified
+    fired: bool,
+}
+
+impl<'a> Simplifier<'a> {
+    fn new(known: Option<&'a HashSet<String>>) -> Self {
+        Simplifier { known, fired: false }
+    }
+
+    /// Simplify the predicates, where `all` tells they must be true for the step to pass, as any
+    /// of those of `and` at the top of the step, so that they are `Never` if never all true
+    fn simplify(&mut self, pred: Pred, all: bool) -> Simplified {
+        match pred {
+            Pred::Leaf(exp) => self.simplify_leaf(exp, all),
+            Pred::And(preds) => self.simplify_and(preds, all),
+            Pred::Or(preds) => self.simplify_or(preds),
+        }
+    }
+
+    fn simplify_leaf(&mut self, exp: pb::FilterExp, all: bool) -> Simplified {
+        if let Some(value) = constant_of(&exp) {
+            self.fired = true;
+            Simplified::Always(value)
+        } else if all && self.is_unknown(&exp) {
+            // a missing property never compares
+            self.fired = true;
+            Simplified::Never
+        } else {
+            Simplified::Pred(Pred::Leaf(exp))
+        }
+    }
+
+    fn is_unknown(&self, exp: &pb::FilterExp) -> bool {
+        match (self.known, property_of(exp)) {
+            (Some(known), Some(key)) => !known.contains(key),
+            _ => false,
+        }
+    }
+
+    fn simplify_and(&mut self, preds: Vec<Pred>, all: bool) -> Simplified {
+        let mut kept = vec![];
+        for pred in preds {
+            match self.simplify(pred, all) {
+                Simplified::Always(true) => {}
+                // the predicates after it are never evaluated
+                Simplified::Always(false) if !all => {
+                    if kept.is_empty() {
+                        return Simplified::Always(false);
+                    }
+                    kept.push(Pred::Leaf(constant_exp(false)));
+                    return Simplified::Pred(Pred::And(kept));
+                }
+                Simplified::Always(false) | Simplified::Never => return Simplified::Never,
+                Simplified::Pred(Pred::And(preds)) => kept.extend(preds),
+                Simplified::Pred(pred) => kept.push(pred),
+            }
+        }
+        if all {
+            match self.merge_all(kept) {
+                Some(merged) => kept = merged,
+                None => return Simplified::Never,
+            }
+        }
+        match kept.len() {
+            0 => Simplified::Always(true),
+            1 => Simplified::Pred(kept.remove(0)),
+            _ => Simplified::Pred(Pred::And(kept)),
+        }
+    }
+
+    // any predicate of `or` may decide the chain, which is never `Never` as not all must be true
+    fn simplify_or(&mut self, preds: Vec<Pred>) -> Simplified {
+        let mut kept = vec![];
+        for pred in preds {
+            match self.simplify(pred, false) {
+                Simplified::Always(false) => {}
+                // the predicates after it are never evaluated
+                Simplified::Always(true) => {
+                    if kept.is_empty() {
+                        return Simplified::Always(true);
+                    }
+                    kept.push(Pred::Leaf(constant_exp(true)));
+                    return Simplified::Pred(Pred::Or(kept));
+                }
+                Simplified::Never => unreachable!("the predicates of `or` are never `Never`"),
+                Simplified::Pred(Pred::Or(preds)) => kept.extend(preds),
+                Simplified::Pred(pred) => kept.push(pred),
+            }
+        }
+        match kept.len() {
+            0 => Simplified::Always(false),
+            1 => Simplified::Pred(kept.remove(0)),
+            _ => Simplified::Pred(Pred::Or(kept)),
+        }
+    }
+
+    /// Merge the predicates all of which must be true, with the single ones first, where the
+    /// bounds of a property are merged into the tightest ones; `None` if never all true
+    fn merge_all(&mut self, preds: Vec<Pred>) -> Option<Vec<Pred>> {
+        let mut exps: Vec<pb::FilterExp> = vec![];
+        let mut nested = vec![];
+        for pred in preds {
+            match pred {
+                Pred::Leaf(exp) if exps.contains(&exp) => self.fired = true,
+                Pred::Leaf(exp) => exps.push(exp),
+                pred => nested.push(pred),
+            }
+        }
+        if is_contradictory(&exps) {
+            self.fired = true;
+            return None;
+        }
+        let bounds: Vec<Option<Bound>> = exps.iter().map(Bound::of).collect();
+        let mut merged = Vec::with_capacity(exps.len() + nested.len());
+        for (i, exp) in exps.into_iter().enumerate() {
+            let looser = bounds[i].as_ref().is_some_and(|bound| {
+                bounds.iter().enumerate().any(|(j, other)| {
+                    other.as_ref().is_some_and(|other| j != i && other.covers(bound, j < i))
+                })
+            });
+            if looser {
+                self.fired = true;
+            } else {
+                merged.push(Pred::Leaf(exp));
+            }
+        }
+        merged.extend(nested);
+        Some(merged)
+    }
+}
+
+/// A constant a property is compared to, which is a number or a string
+#[derive(Clone, Debug, PartialEq)]
+enum Constant {
+    Number(f64),
+    Str(String),
+}
+
+impl Constant {
+    fn of(value: &common_pb::Value) -> Option<(Constant, u8)> {
+        // the class of the constant, whose properties are the same if compared by an order, as an
+        // integer property fails to compare to a long beyond its range, e.g. `Integer` to 1 << 40
+        let class = |v: i64| {
+            if i8::try_from(v).is_ok() {
+                0
+            } else if i32::try_from(v).is_ok() {
+                1
+            } else {
+                2
+            }
+        };
+        match value.item.as_ref()? {
+            common_pb::value::Item::I32(v) => Some((Constant::Number(*v as f64), class(*v as i64))),
+            // exact as a float
+            common_pb::value::Item::I64(v) if (-(1 << 53)..=1 << 53).contains(v) => {
+                Some((Constant::Number(*v as f64), class(*v)))
+            }
+            common_pb::value::Item::F64(v) if v.is_finite() => Some((Constant::Number(*v), 0)),
+            common_pb::value::Item::Str(v) => Some((Constant::Str(v.clone()), 3)),
+            _ => None,
+        }
+    }
+
+    // the numbers and the strings are never equal or in order
+    fn cmp(&self, other: &Constant) -> Option<Ordering> {
+        match (self, other) {
+            (Constant::Number(a), Constant::Number(b)) => a.partial_cmp(b),
+            (Constant::Str(a), Constant::Str(b)) => Some(a.cmp(b)),
+            _ => None,
+        }
+    }
+}
+
+/// The comparison of a property to a constant
+#[derive(Debug)]
+struct Bound {
+    key: String,
+    cmp: pb::Compare,
+    value: Constant,
+    class: u8,
+    // the window of the property around the value if equal to it
+    window: f64,
+}
+
+impl Bound {
+    fn of(exp: &pb::FilterExp) -> Option<Bound> {
+        let key = property_of(exp)?.to_owned();
+        let cmp = pb::Compare::from_i32(exp.cmp)?;
+        if !matches!(
+            cmp,
+            pb::Compare::Eq | pb::Compare::Lt | pb::Compare::Le | pb::Compare::Gt | pb::Compare::Ge
+        ) {
+            return None;
+        }
+        let (value, class) = Constant::of(exp.right.as_ref()?)?;
+        let window = match &value {
+            Constant::Number(v) if cmp == pb::Compare::Eq => {
+                // a property equal to the value within the relative epsilon `e` is within
+                // `e / (1 - e) * |v|` of the value, i.e. 2 * e * |v| if e is at most 0.5
+                let epsilon = resolve_float_epsilon(exp.epsilon);
+                if epsilon > 0.5 {
+                    return None;
+                }
+                2.0 * epsilon * v.abs()
+            }
+            _ => 0.0,
+        };
+        Some(Bound { key, cmp, value, class, window })
+    }
+
+    // 1 for the lower bounds, -1 for the upper ones, or 0 for the equality
+    fn direction(&self) -> i32 {
+        match self.cmp {
+            pb::Compare::Gt | pb::Compare::Ge => 1,
+            pb::Compare::Lt | pb::Compare::Le => -1,
+            _ => 0,
+        }
+    }
+
+    fn is_strict(&self) -> bool {
+        matches!(self.cmp, pb::Compare::Gt | pb::Compare::Lt)
+    }
+
+    /// Whether the bound is true whenever this one is, so that it can be dropped, where the
+    /// equally tight bounds cover each other, and only the one `before` it is kept
+    fn covers(&self, other: &Bound, before: bool) -> bool {
+        if self.key != other.key || self.class != other.class {
+            return false;
+        }
+        let direction = self.direction();
+        if direction == 0 || direction != other.direction() {
+            return false;
+        }
+        let tighter = match self.value.cmp(&other.value) {
+            Some(Ordering::Equal) => match (self.is_strict(), other.is_strict()) {
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                _ => Ordering::Equal,
+            },
+            Some(ordering) if direction > 0 => ordering,
+            Some(ordering) => ordering.reverse(),
+            None => return false,
+        };
+        tighter == Ordering::Greater || (tighter == Ordering::Equal && before)
+    }
+
+    /// Whether the comparisons of the same property are never both true
+    fn excludes(&self, other: &Bound) -> bool {
+        if self.key != other.key {
+            return false;
+        }
+        // an equality is taken as a lower bound or an upper one by the other comparison
+        let (lower, upper) = match (self.direction(), other.direction()) {
+            (0, 0) => {
+                return match (&self.value, &other.value) {
+                    (Constant::Number(a), Constant::Number(b)) => {
+                        (a - b).abs() > self.window + other.window
+                    }
+                    (a, b) => a != b,
+                }
+            }
+            (a, b) if a == b => return false,
+            (a, b) if a > b => (self, other),
+            _ => (other, self),
+        };
+        match lower.shifted(-lower.window).cmp(&upper.shifted(upper.window)) {
+            Some(Ordering::Less) => false,
+            Some(Ordering::Equal) => lower.is_strict() || upper.is_strict(),
+            // a number is never in order with a string, or equal to it
+            Some(Ordering::Greater) | None => true,
+        }
+    }
+
+    fn shifted(&self, delta: f64) -> Constant {
+        match &self.value {
+            Constant::Number(v) => Constant::Number(v + delta),
+            value => value.clone(),
+        }
+    }
+}
+
+/// Whether the predicates all of which must be true are never all true
+fn is_contradictory(exps: &[pb::FilterExp]) -> bool {
+    let is_cmp = |exp: &pb::FilterExp, cmp: pb::Compare| exp.cmp == cmp as i32;
+    let negated = exps.iter().filter(|exp| is_cmp(exp, pb::Compare::Eq)).any(|exp| {
+        exps.iter().filter(|other| is_cmp(other, pb::Compare::Ne)).any(|other| {
+            other.left == exp.left && other.right == exp.right && other.epsilon == exp.epsilon
+        })
+    });
+    let ids: HashSet<i64> = exps.iter().filter_map(id_of).collect();
+    let bounds: Vec<Bound> = exps.iter().filter_map(Bound::of).collect();
+    let excluded = bounds
+        .iter()
+        .enumerate()
+        .any(|(i, bound)| bounds[i + 1..].iter().any(|other| bound.excludes(other)));
+    negated || ids.len() > 1 || excluded
+}
+
+// the id an element must be of, e.g. of `hasId(1)`
+fn id_of(exp: &pb::FilterExp) -> Option<i64> {
+    match (exp.left.as_ref()?.item.as_ref()?, exp.right.as_ref()?.item.as_ref()?) {
+        (common_pb::key::Item::Id(_), common_pb::value::Item::I32(id)) if *id >= 0 => {
+            Some(*id as i64)
+        }
+        (common_pb::key::Item::Id(_), common_pb::value::Item::I64(id)) if *id >= 0 => Some(*id),
+        _ => None,
+    }
+    .filter(|_| exp.cmp == pb::Compare::Eq as i32)
+}
+
+fn property_of(exp: &pb::FilterExp) -> Option<&str> {
+    match exp.left.as_ref()?.item.as_ref()? {
+        common_pb::key::Item::Name(name) => Some(name.as_str()),
+        _ => None,
+    }
+}
+
+/// Whether the predicate is true or false for every element, i.e. whether the id or the label is
+/// within or without an empty list
+fn constant_of(exp: &pb::FilterExp) -> Option<bool> {
+    use common_pb::key::Item as Key;
+    use common_pb::value::Item as Value;
+    let empty = match (exp.left.as_ref()?.item.as_ref()?, exp.right.as_ref()?.item.as_ref()?) {
+        (Key::Id(_), Value::I64Array(ids)) => ids.item.is_empty(),
+        (Key::Id(_), Value::I32Array(ids)) => ids.item.is_empty(),
+        (Key::Label(_), Value::I32Array(labels)) => labels.item.is_empty(),
+        (Key::Label(_), Value::StrArray(labels)) => labels.item.is_empty(),
+        _ => false,
+    };
+    match pb::Compare::from_i32(exp.cmp)? {
+        pb::Compare::Within if empty => Some(false),
+        pb::Compare::Without if empty => Some(true),
+        _ => None,
+    }
+}
+
+fn constant_exp(value: bool) -> pb::FilterExp {
+    let cmp = if value { pb::Compare::Without } else { pb::Compare::Within };
+    pb::FilterExp {
+        left: Some(common_pb::Key { item: Some(common_pb::key::Item::Id(common_pb::IdKey {})) }),
+        cmp: cmp as i32,
+        right: Some(common_pb::Value {
+            item: Some(common_pb::value::Item::I64Array(common_pb::I64Array { item: vec![] })),
+        }),
+        epsilon: 0.0,
+        predicate: String::new(),
+    }
+}
+
+fn constant_chain(value: bool) -> pb::FilterChain {
+    pb::FilterChain { node: vec![single_node(constant_exp(value), pb::Connect::And)] }
+}
+
+fn single_node(exp: pb::FilterExp, next: pb::Connect) -> pb::FilterNode {
+    pb::FilterNode { inner: Some(pb::filter_node::Inner::Single(exp)), next: next as i32 }
+}
+
+fn to_bytes<M: Message>(message: &M) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(message.encoded_len());
+    message.encode(&mut bytes).expect("encode message failure");
+    bytes
+}
+
+/// Parse the chain as `pb_chain_to_filter` decodes it; `None` if it is empty or malformed
+fn parse_chain(chain: &pb::FilterChain) -> Option<Pred> {
+    fold(parse_nodes(chain)?)
+}
+
+// the predicates of the nodes with the connectors after them, where a nested chain of a single
+// predicate is spliced into them, while an empty one is skipped, with its connector taken by the
+// predicate before it
+fn parse_nodes(chain: &pb::FilterChain) -> Option<Vec<(Pred, pb::Connect)>> {
+    let mut preds: Vec<(Pred, pb::Connect)> = vec![];
+    for node in chain.node.iter() {
+        let next = pb::Connect::from_i32(node.next)?;
+        match node.inner.as_ref()? {
+            pb::filter_node::Inner::Single(exp) => preds.push((Pred::Leaf(exp.clone()), next)),
+            pb::filter_node::Inner::Chain(bytes) => {
+                let mut nested = parse_nodes(&pb::FilterChain::decode(bytes.as_slice()).ok()?)?;
+                if nested.is_empty() {
+                    if let Some(last) = preds.last_mut() {
+                        last.1 = next;
+                    }
+                } else if nested.len() == 1 {
+                    if let Some(last) = nested.last_mut() {
+                        last.1 = next;
+                    }
+                    preds.append(&mut nested);
+                } else {
+                    preds.push((fold(nested)?, next));
+                }
+            }
+        }
+    }
+    Some(preds)
+}
+
+// the predicates connected from right to left, e.g. `a and b or c` is `a and (b or c)`
+fn fold(preds: Vec<(Pred, pb::Connect)>) -> Option<Pred> {
+    let mut preds = preds.into_iter().rev();
+    let (mut folded, _) = preds.next()?;
+    for (pred, connect) in preds {
+        let mut connected = vec![];
+        let mut push = |pred: Pred| match (connect, pred) {
+            (pb::Connect::And, Pred::And(inner)) | (pb::Connect::Or, Pred::Or(inner)) => {
+                connected.extend(inner)
+            }
+            (_, pred) => connected.push(pred),
+        };
+        push(pred);
+        push(folded);
+        folded = match connect {
+            pb::Connect::And => Pred::And(connected),
+            pb::Connect::Or => Pred::Or(connected),
+        };
+    }
+    Some(folded)
+}
+
+/// The chain of the predicates simplified
+fn encode_simplified(simplified: &Simplified) -> pb::FilterChain {
+    match simplified {
+        Simplified::Pred(pred) => encode(pred),
+        Simplified::Always(value) => constant_chain(*value),
+        Simplified::Never => constant_chain(false),
+    }
+}
+
+/// Encode the predicates as a chain
+fn encode(pred: &Pred) -> pb::FilterChain {
+    pb::FilterChain { node: encode_nodes(pred) }
+}
+
+// the nodes of the predicates, the last of which is connected to those before it as it is, e.g.
+// `a and (b or c)` as `a and b or c`, while the others are nested chains
+fn encode_nodes(pred: &Pred) -> Vec<pb::FilterNode> {
+    let (preds, connect) = match pred {
+        Pred::Leaf(exp) => return vec![single_node(exp.clone(), pb::Connect::And)],
+        Pred::And(preds) => (preds, pb::Connect::And),
+        Pred::Or(preds) => (preds, pb::Connect::Or),
+    };
+    let mut nodes = vec![];
+    for (i, pred) in preds.iter().enumerate() {
+        match pred {
+            Pred::Leaf(exp) => nodes.push(single_node(exp.clone(), connect)),
+            _ if i + 1 == preds.len() => nodes.extend(encode_nodes(pred)),
+            _ => {
+                let chain = to_bytes(&encode(pred));
+                let inner = Some(pb::filter_node::Inner::Chain(chain));
+                nodes.push(pb::FilterNode { inner, next: connect as i32 });
+            }
+        }
+    }
+    nodes
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::structure::codec::pb_chain_to_filter;
+    use crate::structure::{DefaultDetails, Label, Vertex};
+    use crate::ID;
+    use dyn_type::Object;
+    use graph_store::parser::DataType;
+    use graph_store::schema::LabelSchema;
+    use std::collections::HashMap;
+
+    use pb::Compare::*;
+    use pb::Connect::*;
+
+    fn exp(key: &str, cmp: pb::Compare, value: common_pb::value::Item) -> pb::FilterExp {
+        pb::FilterExp {
+            left: Some(common_pb::Key { item: Some(common_pb::key::Item::Name(key.to_owned())) }),
+            cmp: cmp as i32,
+            right: Some(common_pb::Value { item: Some(value) }),
+            epsilon: 0.0,
+            predicate: String::new(),
+        }
+    }
+
+    fn int(value: i32) -> common_pb::value::Item {
+        common_pb::value::Item::I32(value)
+    }
+
+    fn chain(exps: Vec<pb::FilterExp>, connect: pb::Connect) -> pb::FilterChain {
+        pb::FilterChain { node: exps.into_iter().map(|exp| single_node(exp, connect)).collect() }
+    }
+
+    fn has_op(name: &str, predicates: pb::FilterChain) -> server_pb::OperatorDef {
+        let has_step = pb::HasStep { predicates: Some(predicates) };
+        let step = pb::GremlinStep {
+            tags: vec![],
+            remove_tags: vec![],
+            step: Some(pb::gremlin_step::Step::HasStep(has_step)),
+        };
+        let filter = server_pb::Filter { resource: to_bytes(&step) };
+        server_pb::OperatorDef {
+            ch: None,
+            op_kind: Some(OpKind::Filter(filter)),
+            name: name.to_owned(),
+        }
+    }
+
+    fn out_op() -> server_pb::OperatorDef {
+        let flat_map = server_pb::FlatMap { resource: vec![] };
+        server_pb::OperatorDef {
+            ch: None,
+            op_kind: Some(OpKind::FlatMap(flat_map)),
+            name: "out".to_owned(),
+        }
+    }
+
+    fn request(
+        graph_step: pb::GraphStep, plan: Vec<server_pb::OperatorDef>,
+    ) -> server_pb::JobRequest {
+        let step = pb::GremlinStep {
+            tags: vec![],
+            remove_tags: vec![],
+            step: Some(pb::gremlin_step::Step::GraphStep(graph_step)),
+        };
+        server_pb::JobRequest {
+            source: Some(server_pb::Source { resource: to_bytes(&step) }),
+            plan: Some(server_pb::TaskPlan { plan }),
+            ..Default::default()
+        }
+    }
+
+    fn source_of(req: &server_pb::JobRequest) -> pb::GremlinStep {
+        let source = req.source.as_ref().expect("source not found");
+        pb::GremlinStep::decode(source.resource.as_slice()).expect("decode source failure")
+    }
+
+    fn plan_of(req: &server_pb::JobRequest) -> &[server_pb::OperatorDef] {
+        req.plan.as_ref().map(|plan| plan.plan.as_slice()).unwrap_or(&[])
+    }
+
+    fn predicates_of(op: &server_pb::OperatorDef) -> Option<Pred> {
+        parse_has(op).map(|(_, pred)| pred)
+    }
+
+    // g.V().has("age", gt(10)).has("age", gt(20)), merged into has("age", gt(20))
+    #[test]
+    fn merge_bounds_test() {
+        let mut req = request(
+            pb::GraphStep::default(),
+            vec![
+                has_op("has[age gt 10]", chain(vec![exp("age", Gt, int(10))], And)),
+                has_op("has[age gt 20]", chain(vec![exp("age", Gt, int(20))], And)),
+            ],
+        );
+        assert!(simplify_request(&mut req, None, TypeCheck::Strict).expect("simplify failure"));
+        let plan = plan_of(&req);
+        assert_eq!(plan.len(), 1);
+        assert_eq!(plan[0].name, "has[age gt 10].has[age gt 20]");
+        assert_eq!(predicates_of(&plan[0]), Some(Pred::Leaf(exp("age", Gt, int(20)))));
+    }
+
+    // g.V().has("age", gt(0)).has("name", eq("josh")), which is kept as it is, e.g. to be ordered
+    // adaptively
+    #[test]
+    fn keep_unmerged_test() {
+        let name = common_pb::value::Item::Str("josh".to_owned());
+        let mut req = request(
+            pb::GraphStep::default(),
+            vec![
+                has_op("has[age gt 0]", chain(vec![exp("age", Gt, int(0))], And)),
+                has_op("has[name eq josh]", chain(vec![exp("name", Eq, name)], And)),
+            ],
+        );
+        let given = req.clone();
+        assert!(!simplify_request(&mut req, None, TypeCheck::Strict).expect("simplify failure"));
+        assert_eq!(req, given);
+    }
+
+    // g.V().out().has("age", gt(30).and(lt(20))), which never passes, so the source scans nothing
+    #[test]
+    fn contradiction_test() {
+        let mut req = request(
+            pb::GraphStep::default(),
+            vec![
+                out_op(),
+                has_op(
+                    "has[age]",
+                    chain(vec![exp("age", Gt, int(30)), exp("age", Lt, int(20))], And),
+                ),
+            ],
+        );
+        assert!(simplify_request(&mut req, None, TypeCheck::Strict).expect("simplify failure"));
+        let plan = plan_of(&req);
+        assert_eq!(plan.len(), 1);
+        assert_eq!(predicates_of(&plan[0]), parse_chain(&false_chain()));
+        assert!(never_matches(&source_of(&req)));
+
+        // eq and neq of the same value, or the values out of the bounds
+        let contradictions = vec![
+            vec![exp("age", Eq, int(1)), exp("age", Ne, int(1))],
+            vec![exp("age", Eq, int(1)), exp("age", Eq, int(2))],
+            vec![exp("age", Ge, int(5)), exp("age", Eq, int(3))],
+            vec![exp("age", Gt, int(5)), exp("age", Le, int(5))],
+            vec![exp("age", Lt, int(5)), exp("age", Eq, common_pb::value::Item::F64(6.0))],
+            vec![
+                exp("name", Eq, common_pb::value::Item::Str("a".to_owned())),
+                exp("name", Eq, int(1)),
+            ],
+        ];
+        for exps in contradictions {
+            let mut simplifier = Simplifier::new(None);
+            let pred = parse_chain(&chain(exps, And)).expect("parse chain failure");
+            assert_eq!(simplifier.simplify(pred.clone(), true), Simplified::Never, "{:?}", pred);
+        }
+    }
+
+    // the predicates always true are folded away, from the source as well as from the has steps
+    #[test]
+    fn fold_constants_test() {
+        let graph_step = pb::GraphStep {
+            predicates: Some(chain(vec![constant_exp(true), exp("age", Gt, int(1))], Or)),
+            ..Default::default()
+        };
+        let predicates = chain(vec![exp("age", Gt, int(1)), constant_exp(true)], And);
+        let mut req = request(graph_step, vec![has_op("has[age gt 1]", predicates)]);
+        assert!(simplify_request(&mut req, None, TypeCheck::Strict).expect("simplify failure"));
+        match source_of(&req).step {
+            Some(pb::gremlin_step::Step::GraphStep(graph_step)) => {
+                assert_eq!(graph_step.predicates, None)
+            }
+            step => panic!("unexpected source {:?}", step),
+        }
+        let plan = plan_of(&req);
+        assert_eq!(predicates_of(&plan[0]), Some(Pred::Leaf(exp("age", Gt, int(1)))));
+
+        // a missing age fails the chain before the predicate always true is evaluated
+        let mut simplifier = Simplifier::new(None);
+        let pred = parse_chain(&chain(vec![exp("age", Gt, int(1)), constant_exp(true)], Or));
+        let simplified = simplifier.simplify(pred.expect("parse chain failure"), true);
+        assert!(matches!(simplified, Simplified::Pred(Pred::Or(_))));
+    }
+
+    // g.V().hasLabel("person").has("weight", gt(0.5)), where persons have no weight
+    #[test]
+    fn unknown_property_test() {
+        let properties =
+            vec![("name".to_owned(), DataType::String), ("age".to_owned(), DataType::Integer)];
+        let person = LabelSchema { id: 0.into(), name: "person".to_owned(), properties };
+        let schema = GraphSchemaInfo { vertex_labels: vec![person], ..Default::default() };
+        let graph_step = pb::GraphStep { labels: vec![0], ..Default::default() };
+        let weight = exp("weight", Gt, common_pb::value::Item::F64(0.5));
+        let req = request(graph_step, vec![has_op("has[weight gt 0.5]", chain(vec![weight], And))]);
+
+        let err = simplify_request(&mut req.clone(), Some(&schema), TypeCheck::Strict)
+            .expect_err("unknown property is accepted");
+        assert_eq!(err.op_index, vec![0]);
+        let mut lenient = req.clone();
+        assert!(simplify_request(&mut lenient, Some(&schema), TypeCheck::Lenient)
+            .expect("simplify failure"));
+        assert!(never_matches(&source_of(&lenient)));
+        // all the labels may have the property
+        let mut unlabeled = request(pb::GraphStep::default(), plan_of(&req).to_vec());
+        assert!(!simplify_request(&mut unlabeled, Some(&schema), TypeCheck::Strict)
+            .expect("simplify failure"));
+    }
+
+    // xorshift, to generate the same chains in every run without a crate of random numbers
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self, n: u64) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0 % n
+        }
+    }
+
+    const STRS: [&str; 3] = ["x", "y", "z"];
+
+    fn random_exp(rng: &mut Rng) -> pb::FilterExp {
+        let value = match rng.next(4) {
+            0 => int(rng.next(7) as i32 - 3),
+            1 => common_pb::value::Item::I64(rng.next(7) as i64 - 3),
+            2 => common_pb::value::Item::F64(rng.next(13) as f64 / 2.0 - 3.0),
+            _ => common_pb::value::Item::Str(STRS[rng.next(3) as usize].to_owned()),
+        };
+        match rng.next(8) {
+            0 => constant_exp(rng.next(2) == 0),
+            1 => pb::FilterExp {
+                left: Some(common_pb::Key {
+                    item: Some(common_pb::key::Item::Id(common_pb::IdKey {})),
+                }),
+                cmp: Eq as i32,
+                right: Some(common_pb::Value {
+                    item: Some(common_pb::value::Item::I64(rng.next(3) as i64)),
+                }),
+                ..Default::default()
+            },
+            _ => {
+                let cmp = [Eq, Ne, Lt, Le, Gt, Ge][rng.next(6) as usize];
+                let mut exp = exp(["a", "b"][rng.next(2) as usize], cmp, value);
+                if rng.next(4) == 0 {
+                    exp.epsilon = 0.1;
+                }
+                exp
+            }
+        }
+    }
+
+    fn random_chain(rng: &mut Rng, depth: u32) -> pb::FilterChain {
+        let len = 1 + rng.next(4);
+        let node = (0..len)
+            .map(|_| {
+                let next = if rng.next(2) == 0 { And } else { Or };
+                if depth > 0 && rng.next(4) == 0 {
+                    let inner =
+                        pb::filter_node::Inner::Chain(to_bytes(&random_chain(rng, depth - 1)));
+                    pb::FilterNode { inner: Some(inner), next: next as i32 }
+                } else {
+                    single_node(random_exp(rng), next)
+                }
+            })
+            .collect();
+        pb::FilterChain { node }
+    }
+
+    fn random_vertex(rng: &mut Rng) -> Vertex {
+        let mut properties = HashMap::new();
+        for key in ["a", "b"].iter() {
+            let value = match rng.next(5) {
+                0 => continue,
+                1 => Object::from(rng.next(7) as i32 - 3),
+                2 => Object::from(rng.next(7) as i64 - 3),
+                3 => Object::from(rng.next(13) as f64 / 2.0 - 3.0),
+                _ => Object::from(STRS[rng.next(3) as usize]),
+            };
+            properties.insert(key.to_string(), value);
+        }
+        let id = rng.next(3) as ID;
+        let label = Label::Str("person".to_owned());
+        Vertex::new(id, Some(label.clone()), DefaultDetails::new_with_prop(id, label, properties))
+    }
+
+    fn passes(chain: &pb::FilterChain, vertex: &Vertex) -> bool {
+        match pb_chain_to_filter::<Vertex>(chain).expect("decode chain failure") {
+            Some(filter) => filter.test(vertex) == Some(true),
+            None => true,
+        }
+    }
+
+    // the chains simplified, or just parsed and encoded again, pass the same elements as given
+    #[test]
+    fn simplify_random_chains_test() {
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        let mut fired = 0;
+        for _ in 0..2000 {
+            let given = random_chain(&mut rng, 2);
+            let pred = parse_chain(&given).expect("parse chain failure");
+            let encoded = encode(&pred);
+            let mut simplifier = Simplifier::new(None);
+            let simplified = simplifier.simplify(pred, true);
+            if simplifier.fired {
+                fired += 1;
+            }
+            let chain = encode_simplified(&simplified);
+            for _ in 0..20 {
+                let vertex = random_vertex(&mut rng);
+                let expected = passes(&given, &vertex);
+                assert_eq!(
+                    passes(&encoded, &vertex),
+                    expected,
+                    "{:?} encoded as {:?}",
+                    given,
+                    encoded
+                );
+                assert_eq!(
+                    passes(&chain, &vertex),
+                    expected,
+                    "{:?} simplified into {:?}",
+                    given,
+                    simplified
+                );
+                assert!(!expected || !simplified.never_passes(), "{:?} never passes", given);
+            }
+        }
+        assert!(fired > 100, "only {} chains are simplified", fired);
+    }
+}
@@ -0,0 +1,134 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The order with a limit pushed into the edge step right before it, e.g. of
+//! `outE("knows").order().by("weight", desc).limit(10)`, where the edges of the label are stored
+//! sorted by the property in each adjacency, see `AdjOrder`, so that the step reads only the first
+//! `limit` edges of each vertex it expands, instead of all of them. The order is kept after the
+//! step, which merges the edges of all the vertices into the global top `limit` ones, so the
+//! results are exactly those of the steps built one by one.
+//!
+//! The order is only pushed down if it is by the very property of the stored order, in the same
+//! direction, with no tags on either side, while a predicate of the edge step is evaluated on
+//! the edges in the stored order before the limit, as the order does on the edges it is given.
+
+use crate::generated::common as common_pb;
+use crate::generated::gremlin as pb;
+use crate::process::traversal::step::{FlatMapFuncGen, SortedEdgeStep, Step};
+use crate::process::traversal::traverser::Traverser;
+use crate::structure::label_id_from_pb;
+use crate::{str_to_dyn_error, DynIter, DynResult};
+use graph_store::common::LabelId;
+use graph_store::schema::AdjOrder;
+use pegasus::api::function::FlatMapFunction;
+use pegasus_server::generated::protocol as server_pb;
+
+type DynFlatMap = Box<dyn FlatMapFunction<Traverser, Traverser, Target = DynIter<Traverser>>>;
+
+/// The edge step the order after it is pushed into by `push_down_order`
+pub struct SortedExpand {
+    /// the edge step, of a single label stored sorted
+    pub step: pb::GremlinStep,
+    /// the limit of the order, i.e. the edges read of each vertex at most
+    pub limit: usize,
+}
+
+impl FlatMapFuncGen for SortedExpand {
+    fn gen_flat_map(self) -> DynResult<DynFlatMap> {
+        let tags = self.step.get_tags();
+        match self.step.step {
+            Some(pb::gremlin_step::Step::VertexStep(step)) => {
+                SortedEdgeStep { step, tags, limit: self.limit }.gen_flat_map()
+            }
+            _ => Err(str_to_dyn_error("the order is only pushed into an edge step")),
+        }
+    }
+}
+
+/// The edge step at the head of the plan to push the order right after it into, decoded from the
+/// resource of its operator by `decode`, i.e. `outE()` or `inE()` of a single label, followed by
+/// the global order with a limit by a property of the edges, which is the property the edges of
+/// the label are stored sorted by in the same direction, as told by `adj_order`. The order itself
+/// stays in the plan, and it doesn't tell if the graph is able to explore the sorted edges
+pub fn push_down_order<F, O>(
+    plan: &[server_pb::OperatorDef], decode: F, adj_order: O,
+) -> Option<SortedExpand>
+where
+    F: Fn(&[u8]) -> Option<pb::GremlinStep>,
+    O: Fn(LabelId) -> Option<AdjOrder>,
+{
+    use server_pb::operator_def::OpKind;
+
+    let step = match plan.first()?.op_kind.as_ref() {
+        Some(OpKind::FlatMap(flat_map)) => decode(&flat_map.resource)?,
+        _ => return None,
+    };
+    let label = match step.step.as_ref() {
+        Some(pb::gremlin_step::Step::VertexStep(vertex_step))
+            if vertex_step.return_type == pb::EntityType::Edge as i32
+                && vertex_step.direction != pb::Direction::Both as i32 =>
+        {
+            match vertex_step.edge_labels.as_slice() {
+                [label] => label_id_from_pb(*label).ok()?,
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+    let order = match plan.get(1)?.op_kind.as_ref() {
+        Some(OpKind::Order(order))
+            if order.range == server_pb::Range::Global as i32 && order.limit > 0 =>
+        {
+            order
+        }
+        _ => return None,
+    };
+    let (property, descending) = order_by_property(decode(&order.compare)?)?;
+    let stored = adj_order(label)?;
+    if stored.property != property || stored.descending != descending {
+        return None;
+    }
+    Some(SortedExpand { step, limit: order.limit as usize })
+}
+
+/// The property and whether descending of the order by a single property of the heads, e.g.
+/// `order().by("weight", desc)`, or `None` if it orders by anything else
+fn order_by_property(step: pb::GremlinStep) -> Option<(String, bool)> {
+    use pb::order_by_compare_pair::Order;
+
+    let mut order_by = match step.step {
+        Some(pb::gremlin_step::Step::OrderByStep(order_by)) => order_by,
+        _ => return None,
+    };
+    if order_by.pairs.len() != 1 {
+        return None;
+    }
+    let pair = order_by.pairs.pop()?;
+    let key = pair.key?;
+    if key.tag.is_some() {
+        return None;
+    }
+    let name = match key.by_key?.item? {
+        pb::by_key::Item::Key(common_pb::Key { item: Some(common_pb::key::Item::Name(name)) }) => {
+            name
+        }
+        _ => return None,
+    };
+    match pair.order {
+        o if o == Order::Asc as i32 => Some((name, false)),
+        o if o == Order::Desc as i32 => Some((name, true)),
+        _ => None,
+    }
+}
@@ -0,0 +1,351 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The in-memory subgraphs extracted by `subgraph("x")`, which collects the edges of the
+//! traversers of a job along with their end vertices, where the edges traversed repeatedly are
+//! kept once, and the number of edges is capped. Once all edges are collected, `cap("x")`
+//! publishes the subgraph under a handle of the job, by which the client takes it via
+//! `get_subgraph()`.
+//!
+//! A subgraph is itself a `GraphProxy`, which can be registered under a name by
+//! `register_subgraph()`, and traversed by following queries once registered as the graph.
+//! The vertices and edges are kept as they are traversed, with their properties, instead of being
+//! loaded into the graph store, which requires a schema of the properties in advance.
+
+use crate::structure::{
+    DefaultDetails, Direction, Edge, Element, Label, QueryParams, Statement, Vertex,
+};
+use crate::{str_to_dyn_error, DynIter, DynResult, GraphProxy, ID};
+use graph_store::common::PLACEHOLDER_LABEL_ID;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, RwLock};
+
+/// The max number of edges of a subgraph if not given by `subgraph("x")`
+pub const DEFAULT_MAX_SUBGRAPH_EDGES: u64 = 1 << 20;
+
+lazy_static! {
+    /// name -> the subgraphs published by `cap("x")` or registered by the users, which are kept
+    /// until removed
+    static ref SUBGRAPHS: RwLock<HashMap<String, Subgraph>> = RwLock::new(HashMap::new());
+}
+
+/// An immutable graph of the extracted vertices and edges, which is cheap to clone
+#[derive(Clone, Default)]
+pub struct Subgraph {
+    inner: Arc<SubgraphData>,
+}
+
+#[derive(Default)]
+struct SubgraphData {
+    // sorted by the ids, to scan the vertices in a fixed order
+    vertices: Vec<Vertex>,
+    // the labels of the vertices, which are `None` for the placeholders of the ends of the edges
+    // missing from the vertices given, so that the placeholders never pass a filter of labels
+    labels: Vec<Option<Label>>,
+    index: HashMap<ID, usize>,
+    edges: Vec<Edge>,
+    out_edges: HashMap<ID, Vec<usize>>,
+    in_edges: HashMap<ID, Vec<usize>>,
+}
+
+impl Subgraph {
+    /// Build the subgraph of the vertices and edges, where the edges must be distinct, and the
+    /// end vertices of the edges missing from `vertices` are added as placeholders without labels
+    /// nor properties, which are handed out by the reserved label of the placeholders of the store.
+    pub fn new(vertices: Vec<Vertex>, edges: Vec<Edge>) -> Self {
+        let mut vertices: HashMap<ID, (Vertex, Option<Label>)> = vertices
+            .into_iter()
+            .map(|v| {
+                let label = Some(v.label().clone());
+                (v.id, (v, label))
+            })
+            .collect();
+        let mut out_edges: HashMap<ID, Vec<usize>> = HashMap::new();
+        let mut in_edges: HashMap<ID, Vec<usize>> = HashMap::new();
+        for (i, edge) in edges.iter().enumerate() {
+            out_edges.entry(edge.src_id).or_default().push(i);
+            in_edges.entry(edge.dst_id).or_default().push(i);
+            for id in &[edge.src_id, edge.dst_id] {
+                vertices.entry(*id).or_insert_with(|| {
+                    let label = Label::Id(PLACEHOLDER_LABEL_ID);
+                    (Vertex::new(*id, None, DefaultDetails::new(*id, label)), None)
+                });
+            }
+        }
+        let mut vertices = vertices.into_values().collect::<Vec<_>>();
+        vertices.sort_by_key(|(v, _)| v.id);
+        let (vertices, labels): (Vec<_>, Vec<_>) = vertices.into_iter().unzip();
+        let index = vertices.iter().enumerate().map(|(i, v)| (v.id, i)).collect();
+        let data = SubgraphData { vertices, labels, index, edges, out_edges, in_edges };
+        Subgraph { inner: Arc::new(data) }
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.inner.vertices.len()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.inner.edges.len()
+    }
+
+    pub fn vertices(&self) -> &[Vertex] {
+        &self.inner.vertices
+    }
+
+    pub fn edges(&self) -> &[Edge] {
+        &self.inner.edges
+    }
+
+    fn get(&self, id: &ID) -> Option<&Vertex> {
+        self.inner.index.get(id).map(|i| &self.inner.vertices[*i])
+    }
+
+    /// The label of the vertex, or `None` if it is a placeholder
+    fn label_of(&self, vertex: &Vertex) -> Option<Label> {
+        self.inner.index.get(&vertex.id).and_then(|i| self.inner.labels[*i].clone())
+    }
+
+    fn adjacent_edges(&self, id: ID, direction: Direction) -> Vec<&Edge> {
+        let out_edges =
+            if direction != Direction::In { self.inner.out_edges.get(&id) } else { None };
+        let in_edges =
+            if direction != Direction::Out { self.inner.in_edges.get(&id) } else { None };
+        out_edges.into_iter().chain(in_edges).flatten().map(|i| &self.inner.edges[*i]).collect()
+    }
+}
+
+/// Keep the elements of the labels, by `label_of`, and passing the filter of the params, up to
+/// its limit, where the elements without labels only pass if no label is given
+fn select<'a, E, I, L>(elements: I, params: &QueryParams<E>, label_of: L) -> Vec<E>
+where
+    E: Element + Clone + Send + Sync + 'a,
+    I: Iterator<Item = &'a E>,
+    L: Fn(&E) -> Option<Label>,
+{
+    let selected = elements
+        .filter(|e| {
+            params.labels.is_empty()
+                || label_of(*e).map(|l| params.labels.contains(&l)).unwrap_or(false)
+        })
+        .filter(|e| params.filter.as_ref().map(|f| f.test(*e).unwrap_or(false)).unwrap_or(true))
+        .cloned();
+    match params.limit {
+        Some(limit) => selected.take(limit).collect(),
+        None => selected.collect(),
+    }
+}
+
+impl GraphProxy for Subgraph {
+    fn scan_vertex(
+        &self, params: &QueryParams<Vertex>,
+    ) -> DynResult<Box<dyn Iterator<Item = Vertex> + Send>> {
+        Ok(Box::new(select(self.inner.vertices.iter(), params, |v| self.label_of(v)).into_iter()))
+    }
+
+    fn get_vertex(
+        &self, ids: &[ID], params: &QueryParams<Vertex>,
+    ) -> DynResult<Box<dyn Iterator<Item = Vertex> + Send>> {
+        let vertices = ids.iter().filter_map(|id| self.get(id));
+        let vertices = select(vertices, params, |v| self.label_of(v));
+        Ok(Box::new(vertices.into_iter()))
+    }
+
+    fn prepare_explore_vertex(
+        &self, direction: Direction, params: &QueryParams<Vertex>,
+    ) -> DynResult<Box<dyn Statement<ID, Vertex>>> {
+        let graph = self.clone();
+        let params = params.clone();
+        let stmt = move |v: ID| -> DynResult<DynIter<Vertex>> {
+            let adjacent = graph
+                .adjacent_edges(v, direction)
+                .into_iter()
+                .filter_map(|e| graph.get(if e.src_id == v { &e.dst_id } else { &e.src_id }));
+            let selected = select(adjacent, &params, |v| graph.label_of(v));
+            Ok(Box::new(selected.into_iter().map(Ok)))
+        };
+        Ok(Box::new(stmt))
+    }
+
+    fn prepare_explore_edge(
+        &self, direction: Direction, params: &QueryParams<Edge>,
+    ) -> DynResult<Box<dyn Statement<ID, Edge>>> {
+        let graph = self.clone();
+        let params = params.clone();
+        let stmt = move |v: ID| -> DynResult<DynIter<Edge>> {
+            let adjacent = graph.adjacent_edges(v, direction);
+            let selected = select(adjacent.into_iter(), &params, |e| Some(e.label().clone()));
+            Ok(Box::new(selected.into_iter().map(Ok)))
+        };
+        Ok(Box::new(stmt))
+    }
+}
+
+/// Collect the edges of `subgraph("x")` of a job, shared by all workers of the job in current
+/// server
+pub struct SubgraphBuilder {
+    handle: String,
+    max_edges: u64,
+    edges: Mutex<CollectedEdges>,
+}
+
+#[derive(Default)]
+struct CollectedEdges {
+    // the edges are told apart by the ends and labels besides the ids, as the storage may not
+    // keep the ids of edges unique, e.g. by the ids of their sources
+    keys: HashSet<(ID, ID, ID, Label)>,
+    edges: Vec<Edge>,
+}
+
+impl SubgraphBuilder {
+    /// Create the builder publishing the subgraph by `handle`, with at most `max_edges` edges,
+    /// or `DEFAULT_MAX_SUBGRAPH_EDGES` if 0
+    pub fn new(handle: String, max_edges: u64) -> Self {
+        let max_edges = if max_edges == 0 { DEFAULT_MAX_SUBGRAPH_EDGES } else { max_edges };
+        SubgraphBuilder { handle, max_edges, edges: Mutex::new(CollectedEdges::default()) }
+    }
+
+    pub fn handle(&self) -> &str {
+        &self.handle
+    }
+
+    /// Add the edges, where the edges traversed repeatedly are only kept once, and fail once the
+    /// distinct edges exceed the cap.
+    pub fn add_edges<I: IntoIterator<Item = Edge>>(&self, edges: I) -> DynResult<()> {
+        let mut collected = self.edges.lock().map_err(|_| str_to_dyn_error("lock poisoned"))?;
+        for edge in edges {
+            let key = (edge.id, edge.src_id, edge.dst_id, edge.label().clone());
+            if collected.keys.insert(key) {
+                if collected.edges.len() as u64 >= self.max_edges {
+                    let msg = format!(
+                        "subgraph {} exceeds the cap of {} edges",
+                        self.handle, self.max_edges
+                    );
+                    return Err(str_to_dyn_error(&msg));
+                }
+                collected.edges.push(edge);
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the distinct edges collected by now
+    pub fn get_edges(&self) -> Vec<Edge> {
+        self.edges.lock().map(|c| c.edges.clone()).unwrap_or_default()
+    }
+
+    /// Build the subgraph of the edges collected by now, with the end vertices read from the
+    /// registered graph, or without properties if not found in current server.
+    pub fn build(&self) -> Subgraph {
+        let edges = self.get_edges();
+        let mut ids = edges.iter().flat_map(|e| vec![e.src_id, e.dst_id]).collect::<Vec<_>>();
+        ids.sort();
+        ids.dedup();
+        let vertices = crate::get_graph()
+            .and_then(|graph| graph.get_vertex(&ids, &QueryParams::new()).ok())
+            .map(|vertices| vertices.collect())
+            .unwrap_or_default();
+        Subgraph::new(vertices, edges)
+    }
+
+    /// Build the subgraph and publish it by the handle, replacing the one published before
+    pub fn publish(&self) -> Subgraph {
+        let subgraph = self.build();
+        register_subgraph(&self.handle, subgraph.clone());
+        subgraph
+    }
+}
+
+/// Register the subgraph under the name, replacing the one of the same name
+pub fn register_subgraph(name: &str, subgraph: Subgraph) {
+    if let Ok(mut subgraphs) = SUBGRAPHS.write() {
+        subgraphs.insert(name.to_owned(), subgraph);
+    }
+}
+
+/// Get the subgraph registered or published under the name
+pub fn get_subgraph(name: &str) -> Option<Subgraph> {
+    SUBGRAPHS.read().ok().and_then(|subgraphs| subgraphs.get(name).cloned())
+}
+
+pub fn remove_subgraph(name: &str) -> Option<Subgraph> {
+    SUBGRAPHS.write().ok().and_then(|mut subgraphs| subgraphs.remove(name))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::structure::DynDetails;
+
+    fn edge(id: ID, src: ID, dst: ID) -> Edge {
+        Edge::new(
+            id,
+            Some(Label::Id(0.into())),
+            src,
+            dst,
+            DynDetails::new(DefaultDetails::new(id, Label::Id(0.into()))),
+        )
+    }
+
+    #[test]
+    fn subgraph_builder_test() {
+        let builder = SubgraphBuilder::new("sg@6190".to_owned(), 3);
+        builder.add_edges(vec![edge(1, 1, 2), edge(2, 1, 4)]).unwrap();
+        // the edges traversed again are not counted by the cap
+        builder.add_edges(vec![edge(1, 1, 2), edge(2, 1, 4), edge(3, 4, 3)]).unwrap();
+        assert!(builder.add_edges(vec![edge(4, 3, 2)]).is_err());
+        let subgraph = builder.build();
+        assert_eq!(subgraph.edge_count(), 3);
+        assert_eq!(subgraph.vertices().iter().map(|v| v.id).collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn subgraph_explore_test() {
+        let subgraph = Subgraph::new(vec![], vec![edge(1, 1, 2), edge(2, 1, 4), edge(3, 4, 1)]);
+        let params = QueryParams::new();
+        let out = subgraph.prepare_explore_vertex(Direction::Out, &params).unwrap();
+        let mut ids = out.exec(1).unwrap().map(|v| v.unwrap().id).collect::<Vec<_>>();
+        ids.sort();
+        assert_eq!(ids, vec![2, 4]);
+        let both = subgraph.prepare_explore_edge(Direction::Both, &QueryParams::new()).unwrap();
+        assert_eq!(both.exec(4).unwrap().count(), 2);
+        let mut params = QueryParams::new();
+        params.labels = vec![Label::Id(1.into())];
+        assert_eq!(subgraph.scan_vertex(&params).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn subgraph_placeholder_test() {
+        let given = Vertex::new(1, None, DefaultDetails::new(1, Label::Id(0.into())));
+        let subgraph = Subgraph::new(vec![given], vec![edge(1, 1, 2)]);
+        assert_eq!(subgraph.vertex_count(), 2);
+        // the placeholder of vertex 2 has no label, which never passes a filter of labels
+        let mut params = QueryParams::new();
+        params.labels = vec![Label::Id(0.into())];
+        let ids = subgraph.scan_vertex(&params).unwrap().map(|v| v.id).collect::<Vec<_>>();
+        assert_eq!(ids, vec![1]);
+        params.labels = vec![Label::Id(PLACEHOLDER_LABEL_ID)];
+        assert_eq!(subgraph.get_vertex(&[1, 2], &params).unwrap().count(), 0);
+        assert_eq!(subgraph.scan_vertex(&QueryParams::new()).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn register_subgraph_test() {
+        let subgraph = Subgraph::new(vec![], vec![edge(1, 1, 2)]);
+        register_subgraph("register_subgraph_test", subgraph);
+        assert_eq!(get_subgraph("register_subgraph_test").unwrap().vertex_count(), 2);
+        assert!(remove_subgraph("register_subgraph_test").is_some());
+        assert!(get_subgraph("register_subgraph_test").is_none());
+    }
+}
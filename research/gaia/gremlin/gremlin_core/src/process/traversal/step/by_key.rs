@@ -15,6 +15,7 @@
 
 use crate::generated::common as common_pb;
 use crate::generated::gremlin as pb;
+use crate::process::traversal::step::util::projection::Projection;
 use crate::structure::codec::ParseError;
 use crate::structure::{Tag, Token};
 use crate::FromPb;
@@ -32,6 +33,9 @@ pub enum ByStepOption {
     OptGroupValues(Option<Token>),
     /// by a value computed in previous sub_traversal
     OptSubtraversal,
+    /// by(values('name1','name2',...)) or by an expression of the properties, which projects the
+    /// element in one access of its properties
+    OptProjection(Projection),
 }
 
 #[derive(Clone, Debug, Default)]
@@ -86,6 +90,9 @@ impl FromPb<pb::ByKey> for ByStepOption {
                 }
             }
             Some(pb::by_key::Item::Computed(_)) => Ok(ByStepOption::OptSubtraversal),
+            Some(pb::by_key::Item::Projection(projection)) => {
+                Ok(ByStepOption::OptProjection(Projection::from_pb(projection)?))
+            }
             _ => Err(ParseError::InvalidData),
         }
     }
@@ -14,13 +14,95 @@
 //! limitations under the License.
 
 use crate::generated::gremlin as pb;
+use crate::process::traversal::step::by_key::{ByStepOption, TagKey};
+use crate::process::traversal::step::util::projection::Projection;
 use crate::process::traversal::traverser::Traverser;
-use pegasus_common::collections::{CollectionFactory, Set};
+use crate::structure::codec::ParseError;
+use crate::structure::Tag;
+use crate::FromPb;
+use dyn_type::Object;
+use pegasus_common::collections::{Collection, CollectionFactory, Set};
 use std::collections::HashSet;
+use std::io;
 
-impl CollectionFactory<Traverser> for pb::DedupStep {
+/// The set of dedup().by(..), which holds the projected keys of the traversers, where the head,
+/// or the element tagged by `tag`, is projected
+struct KeyedSet {
+    tag: Option<Tag>,
+    projection: Projection,
+    keys: HashSet<Object>,
+}
+
+impl KeyedSet {
+    fn key_of(&self, item: &Traverser) -> io::Result<Object> {
+        let element = item.select_as_element(self.tag.as_ref()).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "dedup by projection requires elements")
+        })?;
+        self.projection
+            .key(element)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+    }
+}
+
+impl Collection<Traverser> for KeyedSet {
+    fn add(&mut self, item: Traverser) -> Result<(), io::Error> {
+        let key = self.key_of(&item)?;
+        self.keys.insert(key);
+        Ok(())
+    }
+
+    fn clear(&mut self) {
+        self.keys.clear()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    fn len(&self) -> usize {
+        self.keys.len()
+    }
+}
+
+impl Set<Traverser> for KeyedSet {
+    fn contains(&self, item: &Traverser) -> bool {
+        self.key_of(item).map(|key| self.keys.contains(&key)).unwrap_or(false)
+    }
+}
+
+/// The factory of the sets of dedup(), which dedups the traversers by themselves, or by the
+/// projected keys of dedup().by(..)
+pub struct DedupSetFactory {
+    by: Option<(Option<Tag>, Projection)>,
+}
+
+impl CollectionFactory<Traverser> for DedupSetFactory {
     type Target = Box<dyn Set<Traverser>>;
     fn create(&self) -> Self::Target {
-        Box::new(HashSet::new()) as Box<dyn Set<Traverser>>
+        match self.by.as_ref() {
+            Some((tag, projection)) => Box::new(KeyedSet {
+                tag: *tag,
+                projection: projection.clone(),
+                keys: HashSet::new(),
+            }) as Box<dyn Set<Traverser>>,
+            None => Box::new(HashSet::new()) as Box<dyn Set<Traverser>>,
+        }
+    }
+}
+
+impl FromPb<pb::DedupStep> for DedupSetFactory {
+    fn from_pb(dedup: pb::DedupStep) -> Result<Self, ParseError>
+    where
+        Self: Sized,
+    {
+        match dedup.key {
+            Some(key) => match TagKey::from_pb(key)? {
+                TagKey { tag, by_key: Some(ByStepOption::OptProjection(projection)) } => {
+                    Ok(DedupSetFactory { by: Some((tag, projection)) })
+                }
+                _ => Err("dedup() only supports by() of a projection".into()),
+            },
+            None => Ok(DedupSetFactory { by: None }),
+        }
     }
 }
@@ -15,7 +15,8 @@
 
 use crate::generated::gremlin as pb;
 use crate::process::traversal::traverser::Traverser;
-use crate::{str_to_dyn_error, DynResult};
+use crate::{str_to_dyn_error, DynResult, FromPb};
+use dedup::DedupSetFactory;
 use pegasus_common::collections::{CollectionFactory, Set};
 
 mod dedup;
@@ -32,7 +33,7 @@ impl CollectionFactoryGen for pb::GremlinStep {
         self,
     ) -> DynResult<Box<dyn CollectionFactory<Traverser, Target = Box<dyn Set<Traverser>>>>> {
         if let Some(pb::gremlin_step::Step::DedupStep(dedup)) = self.step {
-            Ok(Box::new(dedup))
+            Ok(Box::new(DedupSetFactory::from_pb(dedup)?))
         } else {
             Err(str_to_dyn_error("pb GremlinStep is not a Dedup Step"))
         }
@@ -16,9 +16,10 @@
 use crate::generated::gremlin as pb;
 use crate::process::traversal::step::filter::FilterFuncGen;
 use crate::process::traversal::traverser::Traverser;
-use crate::structure::codec::pb_chain_to_filter;
+use crate::structure::codec::{pb_chain_to_filter, pb_chain_to_value_filter};
 use crate::structure::{
-    without_tag, Filter, IsSimple, TraverserFilter, TraverserFilterChain, ValueFilter,
+    on_value, without_tag, Filter, IsSimple, LoopsFilter, TraverserFilter, TraverserFilterChain,
+    ValueFilter,
 };
 use crate::{str_to_dyn_error, DynResult, FromPb};
 use pegasus::api::function::{FilterFunction, FnResult};
@@ -47,14 +48,25 @@ impl FilterFunction<Traverser> for HasTraverser {
 
 impl FilterFuncGen for pb::HasStep {
     fn gen_filter(self) -> DynResult<Box<dyn FilterFunction<Traverser>>> {
-        let mut filter = Filter::default();
-        if let Some(predicates) = self.predicates {
-            if let Some(test) = pb_chain_to_filter(&predicates)? {
-                filter = without_tag(test)
-            }
+        Ok(Box::new(HasTraverser::new(Arc::new(has_filter_chain(self)?))))
+    }
+}
+
+/// Decode the predicates of the has step into a filter
+pub fn has_filter_chain(has_step: pb::HasStep) -> DynResult<TraverserFilterChain> {
+    let mut filter = Filter::default();
+    if let Some(predicates) = has_step.predicates {
+        if let Some(test) = pb_chain_to_filter(&predicates)? {
+            filter = without_tag(test)
         }
-        Ok(Box::new(HasTraverser::new(Arc::new(filter))))
     }
+    Ok(filter)
+}
+
+/// Generate the has step by the filter shared by all workers of the job in the side store, which
+/// is decoded once, along with the large constants it holds, e.g. the ids of `hasId(within(..))`
+pub fn gen_shared_filter(filter: Arc<TraverserFilterChain>) -> Box<dyn FilterFunction<Traverser>> {
+    Box::new(HasTraverser { filter })
 }
 
 impl FilterFuncGen for pb::PathFilterStep {
@@ -70,6 +82,12 @@ impl FilterFuncGen for pb::PathFilterStep {
 
 impl FilterFuncGen for pb::IsStep {
     fn gen_filter(self) -> DynResult<Box<dyn FilterFunction<Traverser>>> {
+        // the chain of the predicates, e.g. of `is(within(1, 2))`, overrides the single one
+        if let Some(predicates) = self.predicates.as_ref().filter(|p| !p.node.is_empty()) {
+            let value_filter = pb_chain_to_value_filter(predicates)?
+                .ok_or(str_to_dyn_error("no predicates in is step"))?;
+            return Ok(Box::new(HasTraverser::new(Arc::new(on_value(value_filter)))));
+        }
         let value_filter_pb =
             self.single.ok_or(str_to_dyn_error("filter is not set in is step"))?;
         let value_filter = ValueFilter::from_pb(value_filter_pb)?;
@@ -77,3 +95,13 @@ impl FilterFuncGen for pb::IsStep {
         Ok(Box::new(HasTraverser::new(Arc::new(Filter::with(traverser_filter)))))
     }
 }
+
+impl FilterFuncGen for pb::LoopsStep {
+    fn gen_filter(self) -> DynResult<Box<dyn FilterFunction<Traverser>>> {
+        let value_filter_pb =
+            self.single.ok_or(str_to_dyn_error("filter is not set in loops step"))?;
+        let value_filter = ValueFilter::from_pb(value_filter_pb)?;
+        let traverser_filter = TraverserFilter::IsLoops(LoopsFilter(value_filter));
+        Ok(Box::new(HasTraverser::new(Arc::new(Filter::with(traverser_filter)))))
+    }
+}
@@ -19,7 +19,10 @@ use crate::{str_to_dyn_error, DynResult};
 use pegasus::api::function::FilterFunction;
 
 mod has;
+
+pub use has::{gen_shared_filter, has_filter_chain};
 mod where_predicate;
+mod within_side;
 
 #[enum_dispatch]
 pub trait FilterFuncGen {
@@ -36,6 +39,8 @@ impl FilterFuncGen for pb::GremlinStep {
                     path_filter_step.gen_filter()
                 }
                 pb::gremlin_step::Step::IsStep(is_step) => is_step.gen_filter(),
+                pb::gremlin_step::Step::LoopsStep(loops_step) => loops_step.gen_filter(),
+                pb::gremlin_step::Step::WithinSideStep(within_step) => within_step.gen_filter(),
                 _ => Err(str_to_dyn_error("pb GremlinStep is not a Filter Step")),
             }
         } else {
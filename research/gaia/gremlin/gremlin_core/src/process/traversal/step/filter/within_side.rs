@@ -0,0 +1,49 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use crate::generated::gremlin as pb;
+use crate::process::side_store::{get_job_side_store, SideCollection, SideKey};
+use crate::process::traversal::step::filter::FilterFuncGen;
+use crate::process::traversal::traverser::Traverser;
+use crate::{str_to_dyn_error, DynResult};
+use pegasus::api::function::{FilterFunction, FnResult};
+use std::sync::Arc;
+
+/// Filter the traversers by whether their heads are within a side collection of the job,
+/// see `pb::WithinSideStep`. The collection is read as a set shared by all workers, which is
+/// complete once the collection is sealed by the barrier of `aggregate()`.
+struct WithinSide {
+    collection: Arc<SideCollection>,
+    negate: bool,
+}
+
+impl FilterFunction<Traverser> for WithinSide {
+    fn exec(&self, input: &Traverser) -> FnResult<bool> {
+        let within = match SideKey::of(input) {
+            Some(key) => self.collection.as_set().contains(&key),
+            None => false,
+        };
+        Ok(within != self.negate)
+    }
+}
+
+impl FilterFuncGen for pb::WithinSideStep {
+    fn gen_filter(self) -> DynResult<Box<dyn FilterFunction<Traverser>>> {
+        let store = get_job_side_store()
+            .ok_or(str_to_dyn_error("side collections are only available in a job"))?;
+        let collection = store.get_collection(&self.key);
+        Ok(Box::new(WithinSide { collection, negate: self.negate }))
+    }
+}
@@ -15,18 +15,146 @@
 
 use super::FlatMapFuncGen;
 use crate::generated::gremlin as pb;
-use crate::process::traversal::traverser::{Traverser, TraverserSplitIter};
+use crate::process::limits::{get_job_access, JobAccess};
+use crate::process::metrics::{WorkerCounter, EXPAND_COUNTER, SORTED_EXPAND_COUNTER};
+use crate::process::traversal::traverser::{Continuation, Traverser, TraverserSplitIter};
 use crate::structure::codec::pb_chain_to_filter;
-use crate::structure::{Direction, Element, GraphElement, Label, QueryParams, Statement, ID};
+use crate::structure::{
+    label_id_from_pb, Direction, GraphElement, GraphProxy, Label, QueryParams, Statement, ID,
+};
 use crate::{str_to_dyn_error, DynIter, DynResult, FromPb};
 use bit_set::BitSet;
 use graph_store::prelude::LabelId;
 use pegasus::api::function::FlatMapFunction;
+use pegasus::WorkerId;
 use std::sync::Arc;
 
 pub struct FlatMapStatement<E: Into<GraphElement>> {
     tags: Arc<BitSet>,
     stmt: Box<dyn Statement<ID, E>>,
+    counter: WorkerCounter,
+    // the name of the step, e.g. "out()", and whether it returns vertices, to check the limits
+    step_name: &'static str,
+    is_vertex: bool,
+    access: Option<Arc<JobAccess>>,
+    // splits the edges of the vertices of too many edges among the workers, if the job does
+    splitter: Option<Splitter>,
+    // the average edges expanded of a vertex, by the statistics of the graph if any
+    fanout: Option<f64>,
+}
+
+impl<E: Into<GraphElement>> FlatMapStatement<E> {
+    fn new(
+        tags: BitSet, stmt: Box<dyn Statement<ID, E>>, step_name: &'static str, is_vertex: bool,
+        splitter: Option<Splitter>,
+    ) -> Self {
+        FlatMapStatement {
+            tags: Arc::new(tags),
+            stmt,
+            counter: WorkerCounter::new(EXPAND_COUNTER),
+            step_name,
+            is_vertex,
+            access: get_job_access(),
+            splitter,
+            fanout: None,
+        }
+    }
+
+    fn with_fanout(mut self, fanout: Option<f64>) -> Self {
+        self.fanout = fanout;
+        self
+    }
+
+    /// The ranges of the edges of the vertex expanded by the workers, the first of which by
+    /// current worker, or none if the vertex is not split
+    fn split(&self, id: ID) -> DynResult<Vec<Continuation>> {
+        match (self.splitter.as_ref(), pegasus::get_current_worker()) {
+            (Some(splitter), Some(worker)) => {
+                let degree =
+                    splitter.graph.count_adj_edges(id, splitter.direction, &splitter.labels)?;
+                Ok(splitter.split(degree as u64, &worker))
+            }
+            _ => Ok(vec![]),
+        }
+    }
+}
+
+/// Split the edges of a vertex of more than `degree` edges, see `JobConf::split_degree`, into
+/// the ranges of about `degree` edges each, at most one for each worker, where the first range is
+/// expanded by current worker, and the others are left as the continuations to the next workers
+struct Splitter {
+    degree: u64,
+    // whether the ranges are expanded by the workers of current server only
+    local: bool,
+    graph: Arc<dyn GraphProxy>,
+    direction: Direction,
+    labels: Vec<Label>,
+}
+
+impl Splitter {
+    /// The splitter of the step expanding the edges of the labels in the direction, or `None` if
+    /// current job never splits
+    fn of_job(graph: Arc<dyn GraphProxy>, direction: Direction, labels: &[Label]) -> Option<Self> {
+        let conf = pegasus::get_current_job_conf()?;
+        if conf.split_degree == 0 {
+            return None;
+        }
+        let (degree, local) = (conf.split_degree, conf.split_local);
+        Some(Splitter { degree, local, graph, direction, labels: labels.to_vec() })
+    }
+
+    fn split(&self, degree: u64, worker: &WorkerId) -> Vec<Continuation> {
+        split_ranges(degree, self.degree, self.local, worker)
+    }
+}
+
+/// Split the `degree` edges into the ranges of about `split_degree` edges each, at most one for
+/// each of the workers, or of those on the server of `worker` if `local`, starting from `worker`
+fn split_ranges(
+    degree: u64, split_degree: u64, local: bool, worker: &WorkerId,
+) -> Vec<Continuation> {
+    let (first, workers) =
+        if local { (worker.server_leader(), worker.local_peers) } else { (0, worker.peers) };
+    let parts = degree.div_ceil(split_degree).min(workers as u64);
+    if parts <= 1 {
+        return vec![];
+    }
+    let len = degree.div_ceil(parts);
+    (0..parts)
+        .map(|i| Continuation {
+            start: i * len,
+            // the last range takes all the edges left, in case some are added after counted
+            end: if i + 1 == parts { u64::MAX } else { (i + 1) * len },
+            worker: first + (worker.index - first + i as u32) % workers,
+        })
+        .collect()
+}
+
+/// The average edges of the labels, or of all labels if none, a vertex has in the direction by
+/// the statistics of the graph, i.e. the outputs expected of expanding a vertex, before any
+/// predicate filters them; `None` if the graph has no statistics
+pub(crate) fn average_degree(
+    graph: &dyn GraphProxy, direction: Direction, labels: &[LabelId],
+) -> Option<f64> {
+    let statistics = graph.get_statistics()?;
+    let vertices = statistics.total_vertex_count();
+    if vertices == 0 {
+        return None;
+    }
+    let edges: usize = if labels.is_empty() {
+        statistics.total_edge_count()
+    } else {
+        labels.iter().map(|label| statistics.edge_count(*label)).sum()
+    };
+    let degree = edges as f64 / vertices as f64;
+    // each edge is expanded from both its ends
+    Some(if direction == Direction::Both { degree * 2.0 } else { degree })
+}
+
+/// Take the range of the edges out of all the edges expanded
+fn expand_range<E: 'static>(edges: DynIter<E>, range: &Continuation) -> DynIter<E> {
+    let len = range.end.saturating_sub(range.start).min(usize::MAX as u64) as usize;
+    Box::new(edges.skip(range.start as usize).take(len))
 }
 
 impl<E: Into<GraphElement> + 'static> FlatMapFunction<Traverser, Traverser>
@@ -34,11 +162,57 @@ impl<E: Into<GraphElement> + 'static> FlatMapFunction<Traverser, Traverser>
 {
     type Target = DynIter<Traverser>;
 
-    fn exec(&self, input: Traverser) -> DynResult<DynIter<Traverser>> {
+    fn fanout(&self) -> Option<f64> {
+        self.fanout
+    }
+
+    fn exec(&self, mut input: Traverser) -> DynResult<DynIter<Traverser>> {
         if let Some(e) = input.get_element() {
-            let id = e.id();
+            // an edge never reaches here by a validated plan, whose id is not of a vertex
+            let id = e.expect_vertex(self.step_name)?.id;
+            self.counter.add(1);
+            let (range, continuations) = match input.take_continuation() {
+                Some(range) => (Some(range), vec![]),
+                None => {
+                    let mut ranges = self.split(id)?.into_iter();
+                    (ranges.next(), ranges.collect())
+                }
+            };
             let iter = self.stmt.exec(id)?;
-            Ok(Box::new(TraverserSplitIter::new(input, &self.tags, iter)))
+            let iter = match range.as_ref() {
+                Some(range) => expand_range(iter, range),
+                None => iter,
+            };
+            // the continuations carry the path of the input to the workers expanding them
+            let continued: Vec<Traverser> = continuations
+                .into_iter()
+                .map(|continuation| {
+                    let mut t = input.clone();
+                    t.set_continuation(continuation);
+                    t
+                })
+                .collect();
+            let iter = TraverserSplitIter::new(input, &self.tags, iter);
+            let iter: DynIter<Traverser> = if let Some(access) = self.access.clone() {
+                // e.g. the source has stopped scanning as it exceeds the vertex limit
+                access.check()?;
+                let (step_name, is_vertex) = (self.step_name, self.is_vertex);
+                Box::new(iter.map(move |t| {
+                    // each vertex is reached by an edge
+                    access.add_edges(1, step_name)?;
+                    if is_vertex {
+                        access.add_vertices(1, step_name)?;
+                    }
+                    t
+                }))
+            } else {
+                Box::new(iter)
+            };
+            if continued.is_empty() {
+                Ok(iter)
+            } else {
+                Ok(Box::new(continued.into_iter().map(Ok).chain(iter)))
+            }
         } else {
             Err(str_to_dyn_error("invalid input for vertex/edge step"))
         }
@@ -59,8 +233,23 @@ impl FlatMapFuncGen for VertexStep {
         let mut step = self.step;
         let direction_pb = unsafe { std::mem::transmute(step.direction) };
         let direction = Direction::from_pb(direction_pb)?;
-        let labels = step.edge_labels.iter().map(|id| Label::Id(*id as LabelId)).collect();
+        let label_ids = step
+            .edge_labels
+            .iter()
+            .map(|id| label_id_from_pb(*id))
+            .collect::<DynResult<Vec<_>>>()?;
+        let labels: Vec<Label> = label_ids.iter().map(|id| Label::Id(*id)).collect();
         let graph = crate::get_graph().ok_or(str_to_dyn_error("Graph is None"))?;
+        let splitter = Splitter::of_job(graph.clone(), direction, &labels);
+        let fanout = average_degree(graph.as_ref(), direction, &label_ids);
+        let step_name = match (direction, step.return_type) {
+            (Direction::Out, 0) => "out()",
+            (Direction::In, 0) => "in()",
+            (Direction::Both, 0) => "both()",
+            (Direction::Out, _) => "outE()",
+            (Direction::In, _) => "inE()",
+            (Direction::Both, _) => "bothE()",
+        };
         if step.return_type == 0 {
             let mut params = QueryParams::new();
             params.labels = labels;
@@ -70,7 +259,8 @@ impl FlatMapFuncGen for VertexStep {
                 }
             }
             let stmt = graph.prepare_explore_vertex(direction, &params)?;
-            Ok(Box::new(FlatMapStatement { tags: Arc::new(self.tags), stmt }))
+            let func = FlatMapStatement::new(self.tags, stmt, step_name, true, splitter);
+            Ok(Box::new(func.with_fanout(fanout)))
         } else if step.return_type == 1 {
             let mut params = QueryParams::new();
             params.labels = labels;
@@ -80,9 +270,138 @@ impl FlatMapFuncGen for VertexStep {
                 }
             }
             let stmt = graph.prepare_explore_edge(direction, &params)?;
-            Ok(Box::new(FlatMapStatement { tags: Arc::new(self.tags), stmt }))
+            let func = FlatMapStatement::new(self.tags, stmt, step_name, false, splitter);
+            Ok(Box::new(func.with_fanout(fanout)))
         } else {
             Err(str_to_dyn_error("Wrong return type in VertexStep"))
         }
     }
 }
+
+/// outE(), inE() of a single label whose adjacency is sorted by a property, see `AdjOrder`, which
+/// reads at most `limit` edges of each vertex in the stored order, as the order by the property
+/// right after the step takes only the top `limit` traversers anyway, see `push_down_order`
+pub struct SortedEdgeStep {
+    pub step: pb::VertexStep,
+    pub tags: BitSet,
+    pub limit: usize,
+}
+
+impl FlatMapFuncGen for SortedEdgeStep {
+    fn gen_flat_map(
+        self,
+    ) -> DynResult<Box<dyn FlatMapFunction<Traverser, Traverser, Target = DynIter<Traverser>>>>
+    {
+        let mut step = self.step;
+        let direction_pb = pb::Direction::from_i32(step.direction)
+            .ok_or(str_to_dyn_error("invalid direction of sorted edge step"))?;
+        let direction = Direction::from_pb(direction_pb)?;
+        let label_ids = step
+            .edge_labels
+            .iter()
+            .map(|id| label_id_from_pb(*id))
+            .collect::<DynResult<Vec<_>>>()?;
+        let graph = crate::get_graph().ok_or(str_to_dyn_error("Graph is None"))?;
+        let mut params = QueryParams::new();
+        params.labels = label_ids.iter().map(|id| Label::Id(*id)).collect();
+        params.limit = Some(self.limit);
+        if let Some(test) = step.predicates.take() {
+            if let Some(filter) = pb_chain_to_filter(&test)? {
+                params.set_filter(filter);
+            }
+        }
+        let stmt = graph.prepare_explore_sorted_edge(direction, &params)?;
+        let counter = Arc::new(WorkerCounter::new(SORTED_EXPAND_COUNTER));
+        let stmt = CountedStatement { inner: stmt, counter };
+        let step_name = if direction == Direction::In { "inE()" } else { "outE()" };
+        let limit = self.limit;
+        // the edges of a vertex are never split among the workers, as only the first are read
+        let func = FlatMapStatement::new(self.tags, Box::new(stmt), step_name, false, None);
+        let fanout = average_degree(graph.as_ref(), direction, &label_ids);
+        Ok(Box::new(func.with_fanout(fanout.map(|degree| degree.min(limit as f64)))))
+    }
+}
+
+/// Count the edges read by the statement, see `SORTED_EXPAND_COUNTER`
+struct CountedStatement<E> {
+    inner: Box<dyn Statement<ID, E>>,
+    // shared by the lazy iterators of the edges, reported once the last of them is dropped
+    counter: Arc<WorkerCounter>,
+}
+
+impl<E: 'static> Statement<ID, E> for CountedStatement<E> {
+    fn exec(&self, id: ID) -> DynResult<DynIter<E>> {
+        let counter = self.counter.clone();
+        let iter = self.inner.exec(id)?.inspect(move |item| {
+            if item.is_ok() {
+                counter.add(1);
+            }
+        });
+        Ok(Box::new(iter))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::process::traversal::traverser::Requirement;
+    use crate::structure::{DefaultDetails, Vertex};
+    use crate::Element;
+
+    fn vertex(id: ID) -> Vertex {
+        Vertex::new(id, Some(Label::Id(0.into())), DefaultDetails::new(id, Label::Id(0.into())))
+    }
+
+    fn workers(ranges: &[Continuation]) -> Vec<u32> {
+        ranges.iter().map(|range| range.worker).collect()
+    }
+
+    #[test]
+    fn split_ranges_test() {
+        // worker 2 of 4 workers, on 2 servers of 2 workers each
+        let worker = WorkerId::new(1, 4, 2, false).on_servers(2);
+        assert!(split_ranges(100, 100, false, &worker).is_empty());
+        let ranges = split_ranges(1_000_000, 1000, false, &worker);
+        assert_eq!(workers(&ranges), vec![2, 3, 0, 1]);
+        let starts: Vec<u64> = ranges.iter().map(|range| range.start).collect();
+        assert_eq!(starts, vec![0, 250_000, 500_000, 750_000]);
+        assert_eq!(ranges[2].end, 750_000);
+        assert_eq!(ranges[3].end, u64::MAX);
+        // as many ranges as needed, within the workers of the server
+        assert_eq!(workers(&split_ranges(250, 100, false, &worker)), vec![2, 3, 0]);
+        assert_eq!(workers(&split_ranges(250, 100, true, &worker)), vec![2, 3]);
+        let worker = WorkerId::new(1, 4, 3, false).on_servers(2);
+        assert_eq!(workers(&split_ranges(250, 100, true, &worker)), vec![3, 2]);
+    }
+
+    #[test]
+    fn expand_range_test() {
+        let edges = || -> DynIter<u64> { Box::new((0..10).map(Ok)) };
+        let range = Continuation { start: 3, end: 6, worker: 0 };
+        let expanded: Vec<u64> = expand_range(edges(), &range).map(|e| e.unwrap()).collect();
+        assert_eq!(expanded, vec![3, 4, 5]);
+        let range = Continuation { start: 8, end: u64::MAX, worker: 0 };
+        let expanded: Vec<u64> = expand_range(edges(), &range).map(|e| e.unwrap()).collect();
+        assert_eq!(expanded, vec![8, 9]);
+    }
+
+    // the continuation expands its range only, extending the path of the traverser it is of
+    #[test]
+    fn expand_continuation_test() {
+        let stmt = |v: ID| -> DynResult<DynIter<Vertex>> {
+            Ok(Box::new((0..10).map(move |i| Ok(vertex(v * 100 + i)))))
+        };
+        let func = FlatMapStatement::new(BitSet::new(), Box::new(stmt), "out()", true, None);
+        let mut input = Traverser::with_path(vertex(1), &BitSet::new(), Requirement::PATH);
+        input.set_continuation(Continuation { start: 3, end: 5, worker: 1 });
+        let outputs: Vec<Traverser> = func.exec(input).unwrap().map(|t| t.unwrap()).collect();
+        let paths: Vec<Vec<ID>> = outputs
+            .into_iter()
+            .map(|t| {
+                assert!(t.get_continuation().is_none());
+                t.take_path().iter().map(|item| item.as_element().unwrap().id()).collect()
+            })
+            .collect();
+        assert_eq!(paths, vec![vec![1, 103], vec![1, 104]]);
+    }
+}
@@ -0,0 +1,138 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use crate::generated::gremlin as pb;
+use crate::process::traversal::step::flat_map::FlatMapFuncGen;
+use crate::process::traversal::step::fold::{NumericAccum, NumericAccumKind};
+use crate::process::traversal::step::util::result_downcast::{
+    try_downcast_list, try_downcast_project,
+};
+use crate::process::traversal::traverser::Traverser;
+use crate::{str_to_dyn_error, DynError, DynIter, DynResult};
+use bit_set::BitSet;
+use dyn_type::Object;
+use pegasus::api::accum::Accumulator;
+use pegasus::api::function::FlatMapFunction;
+use pegasus::OverflowPolicy;
+
+struct LocalAccumFunc {
+    kind: NumericAccumKind,
+    overflow: OverflowPolicy,
+    tags: BitSet,
+    remove_tags: BitSet,
+}
+
+impl LocalAccumFunc {
+    /// Accumulate the items of the collection head, or `None` if the head is not a collection, or
+    /// is empty to sum up
+    fn accum(&self, head: &Object) -> DynResult<Option<Object>> {
+        let items = if let Some(items) = try_downcast_list(head) {
+            items
+        } else if let Some(record) = try_downcast_project(head) {
+            // the null columns are skipped
+            let values = record.columns().iter().filter_map(|(_, value)| value.clone());
+            values.map(Traverser::object).collect()
+        } else {
+            return Ok(None);
+        };
+        let mut accum = NumericAccum::with_overflow(self.kind, self.overflow);
+        for item in items {
+            accum.accum(item).map_err(|e| Box::new(e) as DynError)?;
+        }
+        match accum.get_value() {
+            // the count of an empty collection is zero, while the others give no value
+            None if self.kind == NumericAccumKind::Count => Ok(Some(0i64.into())),
+            value => Ok(value),
+        }
+    }
+}
+
+impl FlatMapFunction<Traverser, Traverser> for LocalAccumFunc {
+    type Target = DynIter<Traverser>;
+
+    fn exec(&self, mut input: Traverser) -> DynResult<DynIter<Traverser>> {
+        let value = match input.get_object() {
+            Some(head) => self.accum(head)?,
+            None => None,
+        };
+        match value {
+            Some(value) => {
+                input.split_with_value(value, &self.tags);
+                input.remove_tags(&self.remove_tags);
+                Ok(Box::new(std::iter::once(Ok(input))))
+            }
+            None => Ok(Box::new(std::iter::empty())),
+        }
+    }
+}
+
+/// The accumulation of each traverser over the items of its collection head, e.g. count(local)
+/// after fold(), see `pb::NumericAccumStep`
+pub struct LocalAccumStep {
+    pub step: pb::NumericAccumStep,
+    pub tags: BitSet,
+    pub remove_tags: BitSet,
+}
+
+impl FlatMapFuncGen for LocalAccumStep {
+    fn gen_flat_map(
+        self,
+    ) -> DynResult<Box<dyn FlatMapFunction<Traverser, Traverser, Target = DynIter<Traverser>>>>
+    {
+        let kind = NumericAccumKind::from_i32(self.step.kind)
+            .ok_or(str_to_dyn_error("invalid numeric accum kind"))?;
+        let overflow = pegasus::get_current_job_conf().map(|c| c.overflow).unwrap_or_default();
+        Ok(Box::new(LocalAccumFunc {
+            kind,
+            overflow,
+            tags: self.tags,
+            remove_tags: self.remove_tags,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pegasus::api::accum::ToList;
+
+    fn local(kind: NumericAccumKind) -> LocalAccumFunc {
+        LocalAccumFunc {
+            kind,
+            overflow: OverflowPolicy::default(),
+            tags: BitSet::new(),
+            remove_tags: BitSet::new(),
+        }
+    }
+
+    fn exec(func: &LocalAccumFunc, input: Traverser) -> Vec<Object> {
+        let results = func.exec(input).unwrap();
+        results.map(|t| t.unwrap().get_object().unwrap().clone()).collect()
+    }
+
+    #[test]
+    fn local_accum_test() {
+        let items = vec![Traverser::object(29i32.into()), Traverser::object(32i32.into())];
+        let list = Traverser::with(ToList { inner: items });
+        assert_eq!(exec(&local(NumericAccumKind::Count), list.clone()), vec![Object::from(2i64)]);
+        assert_eq!(exec(&local(NumericAccumKind::Sum), list.clone()), vec![Object::from(61)]);
+        assert_eq!(exec(&local(NumericAccumKind::Max), list), vec![Object::from(32)]);
+        let empty = Traverser::with(ToList { inner: Vec::<Traverser>::new() });
+        assert_eq!(exec(&local(NumericAccumKind::Count), empty.clone()), vec![Object::from(0i64)]);
+        assert!(exec(&local(NumericAccumKind::Sum), empty).is_empty());
+        // the heads other than collections are filtered out
+        assert!(exec(&local(NumericAccumKind::Count), Traverser::object(29i32.into())).is_empty());
+    }
+}
@@ -0,0 +1,98 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use crate::generated::gremlin as pb;
+use crate::process::traversal::step::flat_map::FlatMapFuncGen;
+use crate::process::traversal::step::util::expr::{parse, Expr, Variable};
+use crate::process::traversal::traverser::Traverser;
+use crate::structure::Tag;
+use crate::{str_to_dyn_error, DynIter, DynResult, FromPb};
+use bit_set::BitSet;
+use dyn_type::Object;
+use pegasus::api::function::FlatMapFunction;
+use std::collections::HashMap;
+
+/// A variable of the expression bound to where its value is read from the traverser
+struct Operand {
+    name: String,
+    /// the tag of the label, or `None` for the head
+    tag: Option<Tag>,
+}
+
+struct MathFunc {
+    expr: Expr<Operand>,
+    tags: BitSet,
+    remove_tags: BitSet,
+}
+
+impl FlatMapFunction<Traverser, Traverser> for MathFunc {
+    type Target = DynIter<Traverser>;
+
+    fn exec(&self, mut input: Traverser) -> DynResult<DynIter<Traverser>> {
+        let value = self.expr.eval(&|operand: &Operand| {
+            let value = match operand.tag {
+                Some(tag) => input.select_as_value(&tag),
+                None => input.get_object(),
+            };
+            value.and_then(|v| v.as_primitive().ok()).ok_or_else(|| {
+                str_to_dyn_error(&format!(
+                    "math() requires numeric values, but `{}` of {:?} is not",
+                    operand.name, input
+                ))
+            })
+        })?;
+        match value {
+            Some(value) => {
+                input.split_with_value(Object::Primitive(value), &self.tags);
+                input.remove_tags(&self.remove_tags);
+                Ok(Box::new(std::iter::once(Ok(input))))
+            }
+            // divided by zero
+            None => Ok(Box::new(std::iter::empty())),
+        }
+    }
+}
+
+/// math() of each traverser, see `pb::MathStep`
+pub struct MathStep {
+    pub step: pb::MathStep,
+    pub tags: BitSet,
+    pub remove_tags: BitSet,
+}
+
+impl FlatMapFuncGen for MathStep {
+    fn gen_flat_map(
+        self,
+    ) -> DynResult<Box<dyn FlatMapFunction<Traverser, Traverser, Target = DynIter<Traverser>>>>
+    {
+        let expr = parse(&self.step.expression)?;
+        let mut labels = HashMap::new();
+        for variable in self.step.variables {
+            let tag = variable.tag.ok_or(str_to_dyn_error("tag is none in MathVariable"))?;
+            labels.insert(variable.name, Tag::from_pb(tag)?);
+        }
+        let expr = expr.bind(&mut |variable| match variable {
+            Variable::Current => Ok(Operand { name: "_".to_owned(), tag: None }),
+            Variable::Label(name) => match labels.get(&name) {
+                Some(tag) => Ok(Operand { tag: Some(*tag), name }),
+                None => Err(str_to_dyn_error(&format!(
+                    "variable `{}` of math() is not bound to a label",
+                    name
+                ))),
+            },
+        })?;
+        Ok(Box::new(MathFunc { expr, tags: self.tags, remove_tags: self.remove_tags }))
+    }
+}
@@ -15,6 +15,8 @@
 
 use crate::generated::gremlin as pb;
 use crate::process::traversal::step::flat_map::explore::VertexStep;
+use crate::process::traversal::step::flat_map::local_accum::LocalAccumStep;
+use crate::process::traversal::step::flat_map::math::MathStep;
 use crate::process::traversal::step::flat_map::values::PropertiesStep;
 use crate::process::traversal::step::Step;
 use crate::process::traversal::traverser::Traverser;
@@ -22,9 +24,13 @@ use crate::{str_to_dyn_error, DynResult};
 use pegasus::api::function::{DynIter, FlatMapFunction};
 
 mod explore;
+mod local_accum;
+mod math;
 mod unfold;
 mod values;
 
+pub(crate) use explore::{average_degree, SortedEdgeStep};
+
 #[enum_dispatch]
 pub trait FlatMapFuncGen {
     fn gen_flat_map(
@@ -38,6 +44,7 @@ impl FlatMapFuncGen for pb::GremlinStep {
     ) -> DynResult<Box<dyn FlatMapFunction<Traverser, Traverser, Target = DynIter<Traverser>>>>
     {
         let tags = self.get_tags();
+        let remove_tags = self.get_remove_tags();
 
         if let Some(step) = self.step {
             match step {
@@ -49,6 +56,12 @@ impl FlatMapFuncGen for pb::GremlinStep {
                     Ok(Box::new(PropertiesStep { props: properties_step.properties.clone(), tags }))
                 }
                 pb::gremlin_step::Step::UnfoldStep(unfold_step) => Ok(Box::new(unfold_step)),
+                pb::gremlin_step::Step::MathStep(math_step) => {
+                    MathStep { step: math_step, tags, remove_tags }.gen_flat_map()
+                }
+                pb::gremlin_step::Step::NumericAccumStep(accum) if accum.local => {
+                    LocalAccumStep { step: accum, tags, remove_tags }.gen_flat_map()
+                }
                 _ => Err(str_to_dyn_error("pb GremlinStep is not a FlatMap Step")),
             }
         } else {
@@ -16,25 +16,27 @@
 use crate::generated::gremlin as pb;
 use crate::process::traversal::step::util::result_downcast::try_downcast_list;
 use crate::process::traversal::traverser::Traverser;
-use crate::{str_to_dyn_error, DynResult};
+use crate::DynResult;
 use pegasus::api::function::{DynIter, FlatMapFunction};
 
-/// This unfold step is used in group().by().by(sub_traversal)
-/// When we process by(sub_traversal) on the result of group().by(), which is a pair of (traverser, Vec<Traverser>),
-/// We need to get_values which is a list, and unfold it first
-
+/// unfold(), which emits the items of a list, e.g. the list folded by fold(), or the values of a
+/// group in group().by().by(sub_traversal), taking the bulk of the list into account. As the
+/// results of group() are already emitted entry by entry, the heads of other kinds, including the
+/// entries, pass through unchanged.
 impl FlatMapFunction<Traverser, Traverser> for pb::UnfoldStep {
     type Target = DynIter<Traverser>;
 
     fn exec(&self, input: Traverser) -> DynResult<DynIter<Traverser>> {
-        let list_traverser = try_downcast_list(
-            input
-                .get_object()
-                .ok_or(str_to_dyn_error("The input traverser for fold should be object"))?,
-        )
-        .ok_or(str_to_dyn_error("Try downcast object for unfold failed"))?
-        .into_iter()
-        .map(|trav| Ok(trav));
-        Ok(Box::new(list_traverser))
+        match input.get_object().and_then(try_downcast_list) {
+            Some(items) => {
+                let bulk = input.get_bulk();
+                let items = items.into_iter().map(move |mut item| {
+                    item.set_bulk(item.get_bulk() * bulk);
+                    Ok(item)
+                });
+                Ok(Box::new(items))
+            }
+            None => Ok(Box::new(std::iter::once(Ok(input)))),
+        }
     }
 }
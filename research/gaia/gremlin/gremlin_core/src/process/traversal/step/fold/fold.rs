@@ -15,38 +15,116 @@
 
 use crate::generated::common as common_pb;
 use crate::generated::protobuf as pb_result;
+use crate::process::side_store::SideCollection;
+use crate::process::subgraph::SubgraphBuilder;
+use crate::process::traversal::step::fold::{
+    ListAccum, ListAccumFactory, NumericAccum, NumericAccumFactory, NumericAccumKind,
+};
 use crate::process::traversal::traverser::Traverser;
+use crate::result_process::{object_to_pb_value, result_to_pb};
 use crate::str_to_dyn_error;
-use pegasus::api::accum::{AccumFactory, Accumulator};
+use pegasus::api::accum::{AccumFactory, Accumulator, ToList};
 use pegasus::api::function::{DynIter, EncodeFunction, FlatMapFunction, FnResult};
+use pegasus::OverflowPolicy;
 use pegasus_common::downcast::AsAny;
-use pegasus_server::factory::{CompileResult, FoldFunction};
+use pegasus_server::factory::{CompileResult, FoldFunction, QuantileValues};
 use prost::Message;
+use std::sync::Arc;
 
-pub struct FoldFunc {}
-struct FoldUnfold {}
-struct FoldSink {}
+/// The side effect of a fold on the side collections of the job
+#[derive(Clone)]
+pub enum FoldSideEffect {
+    /// Collect the folded traversers into the collection before unfolding them, e.g. `aggregate("x")`
+    Aggregate(Arc<SideCollection>),
+    /// Emit the collection as a list once the fold completes, e.g. `cap("x")`
+    Cap(Arc<SideCollection>),
+    /// Collect the edges of the folded traversers into the subgraph before unfolding them,
+    /// e.g. `subgraph("x")`
+    Subgraph(Arc<SubgraphBuilder>),
+    /// Publish the subgraph and emit its handle once the fold completes, e.g. `cap("x")` of
+    /// `subgraph("x")`
+    CapSubgraph(Arc<SubgraphBuilder>),
+}
+
+impl FoldSideEffect {
+    fn exec(&self, input: &mut Box<dyn Accumulator<Traverser>>) -> FnResult<Vec<Traverser>> {
+        match self {
+            FoldSideEffect::Aggregate(collection) => {
+                let list = input
+                    .as_any_mut()
+                    .downcast_mut::<ToList<Traverser>>()
+                    .ok_or(str_to_dyn_error("aggregate() requires to fold the traversers"))?;
+                let traversers = std::mem::take(&mut list.inner);
+                collection.extend(traversers.iter().cloned());
+                collection.seal();
+                Ok(traversers)
+            }
+            FoldSideEffect::Cap(collection) => {
+                Ok(vec![Traverser::with(ToList { inner: collection.get_all() })])
+            }
+            FoldSideEffect::Subgraph(builder) => {
+                let list = input
+                    .as_any_mut()
+                    .downcast_mut::<ToList<Traverser>>()
+                    .ok_or(str_to_dyn_error("subgraph() requires to fold the traversers"))?;
+                let traversers = std::mem::take(&mut list.inner);
+                let mut edges = Vec::with_capacity(traversers.len());
+                for traverser in traversers.iter() {
+                    match traverser.get_element().and_then(|e| e.as_edge()) {
+                        Some(edge) => edges.push(edge.clone()),
+                        None => Err(str_to_dyn_error("subgraph() requires edges, e.g. outE()"))?,
+                    }
+                }
+                builder.add_edges(edges)?;
+                Ok(traversers)
+            }
+            FoldSideEffect::CapSubgraph(builder) => {
+                builder.publish();
+                Ok(vec![Traverser::object(builder.handle().into())])
+            }
+        }
+    }
+}
+
+pub struct FoldFunc {
+    pub accum_kind: Option<NumericAccumKind>,
+    /// Fold the traversers into a list, e.g. `fold()`
+    pub to_list: bool,
+    pub side_effect: Option<FoldSideEffect>,
+}
+struct FoldUnfold {
+    side_effect: Option<FoldSideEffect>,
+}
+struct FoldSink {
+    side_effect: Option<FoldSideEffect>,
+    overflow: OverflowPolicy,
+}
 
 type DynFoldUnfold = Box<
     dyn FlatMapFunction<Box<dyn Accumulator<Traverser>>, Traverser, Target = DynIter<Traverser>>,
 >;
 
 impl FoldFunction<Traverser> for FoldFunc {
-    // TODO(yyy)
     fn accumulate(
         &self,
     ) -> CompileResult<Box<dyn AccumFactory<Traverser, Target = Box<dyn Accumulator<Traverser>>>>>
     {
-        unimplemented!()
+        // TODO(yyy): other accumulators
+        if self.to_list {
+            return Ok(Box::new(ListAccumFactory::new()));
+        }
+        let kind = self.accum_kind.ok_or("accumulator of fold not found")?;
+        Ok(Box::new(NumericAccumFactory::new(kind)))
     }
 
     fn fold_unfold(&self) -> CompileResult<DynFoldUnfold> {
-        let fold_unfold = FoldUnfold {};
+        let fold_unfold = FoldUnfold { side_effect: self.side_effect.clone() };
         Ok(Box::new(fold_unfold) as DynFoldUnfold)
     }
 
     fn fold_sink(&self) -> CompileResult<Box<dyn EncodeFunction<Box<dyn Accumulator<Traverser>>>>> {
-        let count_sink = FoldSink {};
+        let overflow = pegasus::get_current_job_conf().map(|c| c.overflow).unwrap_or_default();
+        let count_sink = FoldSink { side_effect: self.side_effect.clone(), overflow };
         Ok(Box::new(count_sink) as Box<dyn EncodeFunction<Box<dyn Accumulator<Traverser>>>>)
     }
 }
@@ -54,9 +132,21 @@ impl FoldFunction<Traverser> for FoldFunc {
 impl FlatMapFunction<Box<dyn Accumulator<Traverser>>, Traverser> for FoldUnfold {
     type Target = DynIter<Traverser>;
 
-    fn exec(&self, input: Box<dyn Accumulator<Traverser>>) -> FnResult<Self::Target> {
-        if let Some(count) = input.as_any_ref().downcast_ref::<u64>() {
-            let result = vec![Ok(Traverser::Object((*count).into()))];
+    fn exec(&self, mut input: Box<dyn Accumulator<Traverser>>) -> FnResult<Self::Target> {
+        if let Some(side_effect) = self.side_effect.as_ref() {
+            let result = side_effect.exec(&mut input)?;
+            Ok(Box::new(result.into_iter().map(Ok)) as DynIter<Traverser>)
+        } else if let Some(count) = input.as_any_ref().downcast_ref::<u64>() {
+            let result = vec![Ok(Traverser::object((*count).into()))];
+            Ok(Box::new(result.into_iter()) as DynIter<Traverser>)
+        } else if let Some(accum) = input.as_any_ref().downcast_ref::<NumericAccum>() {
+            let result = accum.get_value().map(|v| Ok(Traverser::object(v)));
+            Ok(Box::new(result.into_iter()) as DynIter<Traverser>)
+        } else if let Some(quantiles) = input.as_any_ref().downcast_ref::<QuantileValues>() {
+            let result = quantile_traversers(quantiles).into_iter().map(Ok);
+            Ok(Box::new(result) as DynIter<Traverser>)
+        } else if let Some(list) = input.as_any_mut().downcast_mut::<ListAccum>() {
+            let result = vec![Ok(Traverser::with(ToList { inner: list.take() }))];
             Ok(Box::new(result.into_iter()) as DynIter<Traverser>)
         } else {
             // TODO: for other fold-unfold cases
@@ -67,10 +157,25 @@ impl FlatMapFunction<Box<dyn Accumulator<Traverser>>, Traverser> for FoldUnfold
 
 impl EncodeFunction<Box<dyn Accumulator<Traverser>>> for FoldSink {
     fn encode(&self, data: Vec<Box<dyn Accumulator<Traverser>>>) -> Vec<u8> {
-        for datum in data {
-            if let Some(count) = datum.as_any_ref().downcast_ref::<u64>() {
+        match self.try_encode(data) {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                error!("fail to encode the result of fold: {}", err);
+                vec![]
+            }
+        }
+    }
+
+    fn try_encode(&self, data: Vec<Box<dyn Accumulator<Traverser>>>) -> FnResult<Vec<u8>> {
+        for mut datum in data {
+            if let Some(side_effect) = self.side_effect.as_ref() {
+                let result = side_effect.exec(&mut datum)?;
+                let mut bytes = vec![];
+                result_to_pb(result).encode_raw(&mut bytes);
+                return Ok(bytes);
+            } else if let Some(count) = datum.as_any_ref().downcast_ref::<u64>() {
                 println!("count result {:?}", count);
-                let val_item = common_pb::value::Item::I64(*count as i64);
+                let val_item = common_pb::value::Item::I64(narrow_count(*count, self.overflow)?);
                 let result_pb = pb_result::Result {
                     inner: Some(pb_result::result::Inner::Value {
                         0: common_pb::Value { item: Some(val_item) },
@@ -78,12 +183,63 @@ impl EncodeFunction<Box<dyn Accumulator<Traverser>>> for FoldSink {
                 };
                 let mut bytes = vec![];
                 result_pb.encode_raw(&mut bytes);
-                return bytes;
+                return Ok(bytes);
+            } else if let Some(accum) = datum.as_any_ref().downcast_ref::<NumericAccum>() {
+                if let Some(value) = accum.get_value() {
+                    let result_pb = pb_result::Result {
+                        inner: Some(pb_result::result::Inner::Value(object_to_pb_value(&value))),
+                    };
+                    let mut bytes = vec![];
+                    result_pb.encode_raw(&mut bytes);
+                    return Ok(bytes);
+                }
+            } else if let Some(quantiles) = datum.as_any_ref().downcast_ref::<QuantileValues>() {
+                let mut bytes = vec![];
+                result_to_pb(quantile_traversers(quantiles)).encode_raw(&mut bytes);
+                return Ok(bytes);
+            } else if let Some(list) = datum.as_any_mut().downcast_mut::<ListAccum>() {
+                let mut bytes = vec![];
+                let result = vec![Traverser::with(ToList { inner: list.take() })];
+                result_to_pb(result).encode_raw(&mut bytes);
+                return Ok(bytes);
             } else {
                 // TODO: for other fold-sink cases
                 unimplemented!()
             }
         }
-        vec![]
+        Ok(vec![])
+    }
+}
+
+/// The quantiles estimated by `quantiles()`, each as a float in the order of the quantiles asked
+fn quantile_traversers(quantiles: &QuantileValues) -> Vec<Traverser> {
+    quantiles.values.iter().map(|v| Traverser::object((*v).into())).collect()
+}
+
+/// Narrow the count to the signed 64-bit integer of the results, where a count above `i64::MAX`
+/// either saturates or fails by the overflow policy
+fn narrow_count(count: u64, overflow: OverflowPolicy) -> FnResult<i64> {
+    if count <= i64::MAX as u64 {
+        Ok(count as i64)
+    } else if overflow == OverflowPolicy::Saturate {
+        Ok(i64::MAX)
+    } else {
+        let msg = format!("count() of {} exceeds i64::MAX of the results", count);
+        Err(str_to_dyn_error(&msg))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn narrow_count_test() {
+        let max = i64::MAX as u64;
+        assert_eq!(narrow_count(max, OverflowPolicy::Error).unwrap(), i64::MAX);
+        assert!(narrow_count(max + 1, OverflowPolicy::Error).is_err());
+        assert!(narrow_count(u64::MAX, OverflowPolicy::Error).is_err());
+        assert_eq!(narrow_count(max + 1, OverflowPolicy::Saturate).unwrap(), i64::MAX);
+        assert_eq!(narrow_count(u64::MAX, OverflowPolicy::Saturate).unwrap(), i64::MAX);
     }
 }
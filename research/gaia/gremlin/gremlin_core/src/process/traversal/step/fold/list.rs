@@ -0,0 +1,122 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use crate::process::limits::{get_job_access, LimitExceeded, RESULT_LIMIT};
+use crate::process::traversal::traverser::Traverser;
+use pegasus::api::accum::{AccumFactory, Accumulator};
+use pegasus_common::downcast::*;
+use pegasus_server::error::QueryError;
+use std::io;
+
+pub struct ListAccumFactory {
+    max_items: u64,
+}
+
+impl ListAccumFactory {
+    /// Create the accumulators bounded by the result limit of the job being built, if any
+    pub fn new() -> Self {
+        let max_items = get_job_access().map(|access| access.get_limits().max_results);
+        ListAccumFactory { max_items: max_items.unwrap_or(0) }
+    }
+}
+
+impl AccumFactory<Traverser> for ListAccumFactory {
+    type Target = Box<dyn Accumulator<Traverser>>;
+
+    fn create(&self) -> Self::Target {
+        Box::new(ListAccum::with_max_items(self.max_items))
+    }
+}
+
+/// Fold the traversers into a list for fold(), where a traverser of bulk n is folded as n items,
+/// and the fold fails once it has more than `max_items` items, or never if 0, as the list is a
+/// single result which can't be cut by the result limit of the sink.
+#[derive(Debug, Clone, Default)]
+pub struct ListAccum {
+    items: Vec<Traverser>,
+    max_items: u64,
+}
+
+impl ListAccum {
+    pub fn with_max_items(max_items: u64) -> Self {
+        ListAccum { items: vec![], max_items }
+    }
+
+    pub fn take(&mut self) -> Vec<Traverser> {
+        std::mem::take(&mut self.items)
+    }
+}
+
+impl Accumulator<Traverser> for ListAccum {
+    fn accum(&mut self, mut next: Traverser) -> Result<(), io::Error> {
+        let bulk = next.get_bulk();
+        if self.max_items > 0 && self.items.len() as u64 + bulk > self.max_items {
+            let err = LimitExceeded {
+                limit: RESULT_LIMIT,
+                max: self.max_items,
+                step: "fold()".to_owned(),
+            };
+            return Err(io::Error::other(QueryError::from(err)));
+        }
+        next.set_bulk(1);
+        for _ in 1..bulk {
+            self.items.push(next.clone());
+        }
+        self.items.push(next);
+        Ok(())
+    }
+}
+
+impl AsAny for ListAccum {
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn as_any_ref(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn traverser(value: i32, bulk: u64) -> Traverser {
+        let mut traverser = Traverser::object(value.into());
+        traverser.set_bulk(bulk);
+        traverser
+    }
+
+    #[test]
+    fn fold_list_test() {
+        let mut accum = ListAccum::with_max_items(0);
+        accum.accum(traverser(1, 1)).unwrap();
+        accum.accum(traverser(2, 2)).unwrap();
+        let items = accum.take();
+        let values: Vec<_> = items.iter().map(|t| t.get_object().unwrap().clone()).collect();
+        assert_eq!(values, vec![1.into(), 2.into(), 2.into()]);
+        assert!(items.iter().all(|t| t.get_bulk() == 1));
+        assert!(accum.take().is_empty());
+    }
+
+    #[test]
+    fn fold_list_limit_test() {
+        let mut accum = ListAccum::with_max_items(3);
+        accum.accum(traverser(1, 2)).unwrap();
+        accum.accum(traverser(2, 1)).unwrap();
+        let err = accum.accum(traverser(3, 1)).unwrap_err();
+        assert!(err.to_string().contains("the result limit 3 is exceeded by fold()"), "{}", err);
+    }
+}
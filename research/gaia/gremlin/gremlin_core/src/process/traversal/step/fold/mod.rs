@@ -14,12 +14,18 @@
 //! limitations under the License.
 
 use crate::generated::gremlin as pb;
-use crate::process::traversal::step::fold::fold::FoldFunc;
+use crate::process::side_store::get_job_side_store;
+use crate::process::traversal::step::fold::fold::{FoldFunc, FoldSideEffect};
 use crate::process::traversal::traverser::Traverser;
-use crate::DynResult;
+use crate::{str_to_dyn_error, DynResult};
 use pegasus_server::factory::FoldFunction;
 
 mod fold;
+mod list;
+mod numeric;
+
+pub use list::{ListAccum, ListAccumFactory};
+pub use numeric::{NumericAccum, NumericAccumFactory, NumericAccumKind};
 
 #[enum_dispatch]
 pub trait FoldFunctionGen {
@@ -29,6 +35,43 @@ pub trait FoldFunctionGen {
 impl FoldFunctionGen for pb::GremlinStep {
     fn gen_fold(self) -> DynResult<Box<dyn FoldFunction<Traverser>>> {
         // TODO: should define a unfold step pb with compiler, which provides the choices of different unfold types
-        Ok(Box::new(FoldFunc {}))
+        let mut accum_kind = None;
+        let mut side_effect = None;
+        let mut to_list = false;
+        match self.step {
+            Some(pb::gremlin_step::Step::NumericAccumStep(accum)) => {
+                accum_kind = Some(
+                    NumericAccumKind::from_i32(accum.kind)
+                        .ok_or(str_to_dyn_error("invalid numeric accum kind"))?,
+                );
+            }
+            Some(pb::gremlin_step::Step::FoldStep(_)) => to_list = true,
+            Some(pb::gremlin_step::Step::SideEffectStep(s))
+                if s.kind == pb::side_effect_step::Kind::Aggregate as i32 =>
+            {
+                let store = get_job_side_store()
+                    .ok_or(str_to_dyn_error("side collections are only available in a job"))?;
+                side_effect = Some(FoldSideEffect::Aggregate(store.get_collection(&s.key)));
+            }
+            Some(pb::gremlin_step::Step::SideEffectStep(s))
+                if s.kind == pb::side_effect_step::Kind::Subgraph as i32 =>
+            {
+                let store = get_job_side_store()
+                    .ok_or(str_to_dyn_error("side collections are only available in a job"))?;
+                let builder = store.get_subgraph(&s.key, s.max_edges);
+                side_effect = Some(FoldSideEffect::Subgraph(builder));
+            }
+            Some(pb::gremlin_step::Step::CapStep(s)) => {
+                let store = get_job_side_store()
+                    .ok_or(str_to_dyn_error("side collections are only available in a job"))?;
+                // the subgraph is collected by the steps before, which are built ahead of cap()
+                side_effect = match store.find_subgraph(&s.key) {
+                    Some(builder) => Some(FoldSideEffect::CapSubgraph(builder)),
+                    None => Some(FoldSideEffect::Cap(store.get_collection(&s.key))),
+                };
+            }
+            _ => {}
+        }
+        Ok(Box::new(FoldFunc { accum_kind, to_list, side_effect }))
     }
 }
@@ -0,0 +1,336 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use crate::generated::gremlin as pb;
+use crate::process::traversal::traverser::Traverser;
+use dyn_type::object::Primitives;
+use dyn_type::Object;
+use pegasus::api::accum::{AccumFactory, Accumulator};
+use pegasus::OverflowPolicy;
+use pegasus_common::downcast::*;
+use std::cmp::Ordering;
+use std::io;
+
+pub type NumericAccumKind = pb::numeric_accum_step::Kind;
+
+pub struct NumericAccumFactory {
+    kind: NumericAccumKind,
+    overflow: OverflowPolicy,
+}
+
+impl NumericAccumFactory {
+    /// Create the accumulators following the overflow policy of the job being built
+    pub fn new(kind: NumericAccumKind) -> Self {
+        let overflow = pegasus::get_current_job_conf().map(|c| c.overflow).unwrap_or_default();
+        NumericAccumFactory { kind, overflow }
+    }
+}
+
+impl AccumFactory<Traverser> for NumericAccumFactory {
+    type Target = Box<dyn Accumulator<Traverser>>;
+
+    fn create(&self) -> Self::Target {
+        Box::new(NumericAccum::with_overflow(self.kind, self.overflow))
+    }
+
+    fn is_associative(&self) -> bool {
+        true
+    }
+}
+
+/// Accumulate the numeric heads of traversers for sum(), min(), max() and mean();
+/// Integers are summed as the widest type among them, and promoted to i64 on overflow of i32,
+/// while any float turns the sum into f64. The min and max keep the type of the chosen value,
+/// and the mean is always f64. Nothing is accumulated for an empty stream, which gives no value
+/// instead of zero. The sum overflowing i64 either saturates or fails by the overflow policy.
+/// The count, e.g. of count(local), counts the heads of any kind into an i64.
+#[derive(Debug, Clone)]
+pub struct NumericAccum {
+    kind: NumericAccumKind,
+    value: Option<Primitives>,
+    count: u64,
+    overflow: OverflowPolicy,
+}
+
+impl NumericAccum {
+    pub fn with_overflow(kind: NumericAccumKind, overflow: OverflowPolicy) -> Self {
+        NumericAccum { kind, value: None, count: 0, overflow }
+    }
+
+    pub fn get_value(&self) -> Option<Object> {
+        let value = self.value?;
+        match self.kind {
+            NumericAccumKind::Mean => {
+                Some(Object::Primitive(Primitives::Float(to_f64(value) / self.count as f64)))
+            }
+            _ => Some(Object::Primitive(value)),
+        }
+    }
+
+    fn accum_value(&mut self, next: Primitives, bulk: u64) -> Result<(), io::Error> {
+        self.count = self.count.saturating_add(bulk);
+        let next = match self.kind {
+            NumericAccumKind::Sum | NumericAccumKind::Mean | NumericAccumKind::Count => {
+                multiply(next, bulk, self.overflow).ok_or_else(|| self.overflow_error())?
+            }
+            _ => next,
+        };
+        self.value = Some(match self.value.take() {
+            None => next,
+            Some(pre) => match self.kind {
+                NumericAccumKind::Sum | NumericAccumKind::Mean | NumericAccumKind::Count => {
+                    add(pre, next, self.overflow).ok_or_else(|| self.overflow_error())?
+                }
+                NumericAccumKind::Min => {
+                    if compare(&next, &pre) == Ordering::Less {
+                        next
+                    } else {
+                        pre
+                    }
+                }
+                NumericAccumKind::Max => {
+                    if compare(&next, &pre) == Ordering::Greater {
+                        next
+                    } else {
+                        pre
+                    }
+                }
+            },
+        });
+        Ok(())
+    }
+
+    fn overflow_error(&self) -> io::Error {
+        let step = match self.kind {
+            NumericAccumKind::Mean => "mean()",
+            NumericAccumKind::Count => "count()",
+            _ => "sum()",
+        };
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} overflows i64, or set the overflow policy to saturate", step),
+        )
+    }
+}
+
+impl Accumulator<Traverser> for NumericAccum {
+    fn accum(&mut self, next: Traverser) -> Result<(), io::Error> {
+        if self.kind == NumericAccumKind::Count {
+            return self.accum_value(Primitives::Long(1), next.get_bulk());
+        }
+        let value = next.get_object().and_then(|o| o.as_primitive().ok()).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{:?} requires numeric values, but got {:?}", self.kind, next),
+            )
+        })?;
+        self.accum_value(value, next.get_bulk())
+    }
+}
+
+impl AsAny for NumericAccum {
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn as_any_ref(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[inline]
+fn to_f64(value: Primitives) -> f64 {
+    match value {
+        Primitives::Byte(v) => v as f64,
+        Primitives::Integer(v) => v as f64,
+        Primitives::Long(v) => v as f64,
+        Primitives::Float(v) => v,
+    }
+}
+
+#[inline]
+fn to_i64(value: Primitives) -> i64 {
+    match value {
+        Primitives::Byte(v) => v as i64,
+        Primitives::Integer(v) => v as i64,
+        Primitives::Long(v) => v,
+        Primitives::Float(v) => v as i64,
+    }
+}
+
+/// Add up two numbers, or `None` if the sum overflows i64 while the policy is `Error`
+fn add(left: Primitives, right: Primitives, overflow: OverflowPolicy) -> Option<Primitives> {
+    match (left, right) {
+        (Primitives::Float(_), _) | (_, Primitives::Float(_)) => {
+            Some(Primitives::Float(to_f64(left) + to_f64(right)))
+        }
+        (Primitives::Long(_), _) | (_, Primitives::Long(_)) => {
+            let (left, right) = (to_i64(left), to_i64(right));
+            let sum = match overflow {
+                OverflowPolicy::Saturate => left.saturating_add(right),
+                OverflowPolicy::Error => left.checked_add(right)?,
+            };
+            Some(Primitives::Long(sum))
+        }
+        _ => {
+            let sum = to_i64(left) + to_i64(right);
+            if sum >= i32::MIN as i64 && sum <= i32::MAX as i64 {
+                Some(Primitives::Integer(sum as i32))
+            } else {
+                Some(Primitives::Long(sum))
+            }
+        }
+    }
+}
+
+/// Multiply a number by the bulk, or `None` if the product overflows i64 while the policy is
+/// `Error`
+fn multiply(value: Primitives, times: u64, overflow: OverflowPolicy) -> Option<Primitives> {
+    if times == 1 {
+        Some(value)
+    } else if let Primitives::Float(v) = value {
+        Some(Primitives::Float(v * times as f64))
+    } else {
+        let product = to_i64(value) as i128 * times as i128;
+        let product = if product > i64::MAX as i128 || product < i64::MIN as i128 {
+            match overflow {
+                OverflowPolicy::Saturate if product > 0 => i64::MAX,
+                OverflowPolicy::Saturate => i64::MIN,
+                OverflowPolicy::Error => return None,
+            }
+        } else {
+            product as i64
+        };
+        Some(match value {
+            Primitives::Long(_) => Primitives::Long(product),
+            _ if product >= i32::MIN as i64 && product <= i32::MAX as i64 => {
+                Primitives::Integer(product as i32)
+            }
+            _ => Primitives::Long(product),
+        })
+    }
+}
+
+fn compare(left: &Primitives, right: &Primitives) -> Ordering {
+    match (left, right) {
+        (Primitives::Float(_), _) | (_, Primitives::Float(_)) => {
+            to_f64(*left).partial_cmp(&to_f64(*right)).unwrap_or(Ordering::Equal)
+        }
+        _ => to_i64(*left).cmp(&to_i64(*right)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn accum(kind: NumericAccumKind, values: Vec<Object>) -> Option<Object> {
+        let mut accum = NumericAccum::with_overflow(kind, OverflowPolicy::default());
+        for value in values {
+            accum.accum(Traverser::object(value)).unwrap();
+        }
+        accum.get_value()
+    }
+
+    fn mixed() -> Vec<Object> {
+        vec![1i32.into(), 2i64.into(), 3i32.into()]
+    }
+
+    #[test]
+    fn sum_with_promotion_test() {
+        let sum = accum(NumericAccumKind::Sum, vec![1i32.into(), 2i32.into()]).unwrap();
+        assert!(matches!(sum, Object::Primitive(Primitives::Integer(3))));
+        let sum = accum(NumericAccumKind::Sum, mixed()).unwrap();
+        assert!(matches!(sum, Object::Primitive(Primitives::Long(6))));
+        let sum = accum(NumericAccumKind::Sum, vec![1i32.into(), 0.5f64.into()]).unwrap();
+        assert_eq!(sum.as_f64().unwrap(), 1.5);
+        let sum = accum(NumericAccumKind::Sum, vec![i32::MAX.into(), 1i32.into()]).unwrap();
+        assert!(matches!(sum, Object::Primitive(Primitives::Long(v)) if v == i32::MAX as i64 + 1));
+    }
+
+    #[test]
+    fn min_max_mean_test() {
+        let min = accum(NumericAccumKind::Min, mixed()).unwrap();
+        assert!(matches!(min, Object::Primitive(Primitives::Integer(1))));
+        let max = accum(NumericAccumKind::Max, mixed()).unwrap();
+        assert!(matches!(max, Object::Primitive(Primitives::Integer(3))));
+        let mean = accum(NumericAccumKind::Mean, mixed()).unwrap();
+        assert_eq!(mean.as_f64().unwrap(), 2.0);
+    }
+
+    #[test]
+    fn count_test() {
+        let count = accum(NumericAccumKind::Count, vec!["marko".into(), 1.5f64.into()]).unwrap();
+        assert!(matches!(count, Object::Primitive(Primitives::Long(2))));
+        let mut accum =
+            NumericAccum::with_overflow(NumericAccumKind::Count, OverflowPolicy::default());
+        let mut bulked = Traverser::object(1i32.into());
+        bulked.set_bulk(3);
+        accum.accum(bulked).unwrap();
+        assert_eq!(accum.get_value().unwrap().as_i64().unwrap(), 3);
+        assert!(NumericAccum::with_overflow(NumericAccumKind::Count, OverflowPolicy::default())
+            .get_value()
+            .is_none());
+    }
+
+    #[test]
+    fn empty_stream_test() {
+        assert!(accum(NumericAccumKind::Min, vec![]).is_none());
+        assert!(accum(NumericAccumKind::Max, vec![]).is_none());
+        assert!(accum(NumericAccumKind::Mean, vec![]).is_none());
+    }
+
+    fn sum_with_overflow(overflow: OverflowPolicy, values: Vec<i64>) -> Result<Object, io::Error> {
+        let mut accum = NumericAccum::with_overflow(NumericAccumKind::Sum, overflow);
+        for value in values {
+            accum.accum(Traverser::object(value.into()))?;
+        }
+        Ok(accum.get_value().unwrap())
+    }
+
+    #[test]
+    fn sum_overflow_test() {
+        let values = vec![i64::MAX - 1, 1];
+        let sum = sum_with_overflow(OverflowPolicy::Error, values).unwrap();
+        assert!(matches!(sum, Object::Primitive(Primitives::Long(i64::MAX))));
+        let values = vec![i64::MAX - 1, 2];
+        let err = sum_with_overflow(OverflowPolicy::Error, values.clone()).unwrap_err();
+        assert!(err.to_string().contains("sum() overflows i64"));
+        let sum = sum_with_overflow(OverflowPolicy::Saturate, values).unwrap();
+        assert!(matches!(sum, Object::Primitive(Primitives::Long(i64::MAX))));
+        let sum = sum_with_overflow(OverflowPolicy::Saturate, vec![i64::MIN, -1]).unwrap();
+        assert!(matches!(sum, Object::Primitive(Primitives::Long(i64::MIN))));
+    }
+
+    #[test]
+    fn bulk_overflow_test() {
+        let mut traverser = Traverser::object((i64::MAX / 2 + 1).into());
+        traverser.set_bulk(2);
+        let mut accum = NumericAccum::with_overflow(NumericAccumKind::Sum, OverflowPolicy::Error);
+        assert!(accum.accum(traverser.clone()).is_err());
+        let mut accum =
+            NumericAccum::with_overflow(NumericAccumKind::Sum, OverflowPolicy::Saturate);
+        accum.accum(traverser).unwrap();
+        let sum = accum.get_value().unwrap();
+        assert!(matches!(sum, Object::Primitive(Primitives::Long(i64::MAX))));
+    }
+
+    #[test]
+    fn non_numeric_test() {
+        let mut accum =
+            NumericAccum::with_overflow(NumericAccumKind::Sum, OverflowPolicy::default());
+        assert!(accum.accum(Traverser::object("marko".into())).is_err());
+    }
+}
@@ -111,7 +111,7 @@ impl KeyFunction<Traverser> for KeyBy {
                             .try_to_owned()
                             .ok_or(str_to_dyn_error("Can't get owned property value"))?,
                     };
-                    Ok(Traverser::Object(obj))
+                    Ok(Traverser::object(obj))
                 }
                 // TODO: by select("a").by(valueMap("name")) or by(valueMap("name"))
                 ByStepOption::OptProperties(_) => {
@@ -130,7 +130,15 @@ impl KeyFunction<Traverser> for KeyBy {
                         .get_attached()
                         .ok_or(str_to_dyn_error("should with attached object"))?
                         .clone();
-                    Ok(Traverser::Object(obj))
+                    Ok(Traverser::object(obj))
+                }
+                // "a" or head should be a graph_element, which is keyed by the composite of its
+                // projected values
+                ByStepOption::OptProjection(projection) => {
+                    let graph_element = item
+                        .select_as_element(tag)
+                        .ok_or(str_to_dyn_error("should be graph_element"))?;
+                    Ok(Traverser::object(projection.key(graph_element)?))
                 }
             }
         } else {
@@ -140,13 +148,13 @@ impl KeyFunction<Traverser> for KeyBy {
                     .select_as_value(tag)
                     .ok_or(str_to_dyn_error("Select tag as value error!"))?
                     .clone();
-                Ok(Traverser::Object(obj))
+                Ok(Traverser::object(obj))
             } else {
                 // group by self, no need to keep path
                 if let Some(element) = item.get_element() {
                     Ok(Traverser::new(element.clone()))
                 } else if let Some(object) = item.get_object() {
-                    Ok(Traverser::Object(object.clone()))
+                    Ok(Traverser::object(object.clone()))
                 } else {
                     unreachable!()
                 }
@@ -0,0 +1,108 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use crate::generated::gremlin as pb;
+use crate::process::traversal::step::map::MapFuncGen;
+use crate::process::traversal::traverser::Traverser;
+use crate::structure::codec::pb_chain_to_filter;
+use crate::structure::{label_id_from_pb, Direction, Edge, Label, QueryParams, Statement};
+use crate::{str_to_dyn_error, DynResult, FromPb, GraphProxy, ID};
+use bit_set::BitSet;
+use pegasus::api::function::{FnResult, MapFunction};
+use std::sync::Arc;
+
+/// How the degree of a vertex is counted
+enum DegreeCounter {
+    /// asked from the graph, which may know the degree without expanding the edges
+    Graph(Arc<dyn GraphProxy>),
+    /// by expanding the edges that pass the edge-property filters
+    Expand(Box<dyn Statement<ID, Edge>>),
+}
+
+struct DegreeFunc {
+    direction: Direction,
+    labels: Vec<Label>,
+    counter: DegreeCounter,
+    tags: BitSet,
+    remove_tags: BitSet,
+}
+
+impl MapFunction<Traverser, Traverser> for DegreeFunc {
+    fn exec(&self, mut input: Traverser) -> FnResult<Traverser> {
+        let id = input
+            .get_element()
+            .ok_or(str_to_dyn_error("invalid input for degree step"))?
+            .expect_vertex("degree step")?
+            .id;
+        let degree = match &self.counter {
+            DegreeCounter::Graph(graph) => {
+                graph.count_adj_edges(id, self.direction, &self.labels)?
+            }
+            DegreeCounter::Expand(stmt) => {
+                let mut count = 0;
+                for edge in stmt.exec(id)? {
+                    edge?;
+                    count += 1;
+                }
+                count
+            }
+        };
+        // the same type as `outE().count()`
+        input.split_with_value(degree as u64, &self.tags);
+        input.remove_tags(&self.remove_tags);
+        Ok(input)
+    }
+}
+
+/// outE().count(), inE().count() and bothE().count() of each vertex, see `pb::DegreeStep`
+pub struct DegreeStep {
+    pub step: pb::DegreeStep,
+    pub tags: BitSet,
+    pub remove_tags: BitSet,
+}
+
+impl MapFuncGen for DegreeStep {
+    fn gen_map(self) -> DynResult<Box<dyn MapFunction<Traverser, Traverser>>> {
+        let mut step = self.step;
+        let direction_pb = pb::Direction::from_i32(step.direction)
+            .ok_or(str_to_dyn_error("invalid direction of degree step"))?;
+        let direction = Direction::from_pb(direction_pb)?;
+        let labels = step
+            .edge_labels
+            .iter()
+            .map(|id| label_id_from_pb(*id).map(Label::Id))
+            .collect::<DynResult<Vec<_>>>()?;
+        let graph = crate::get_graph().ok_or(str_to_dyn_error("Graph is None"))?;
+        let filter = match step.predicates.take() {
+            Some(test) => pb_chain_to_filter(&test)?,
+            None => None,
+        };
+        let counter = if let Some(filter) = filter {
+            let mut params = QueryParams::new();
+            params.labels = labels.clone();
+            params.set_filter(filter);
+            DegreeCounter::Expand(graph.prepare_explore_edge(direction, &params)?)
+        } else {
+            DegreeCounter::Graph(graph)
+        };
+        Ok(Box::new(DegreeFunc {
+            direction,
+            labels,
+            counter,
+            tags: self.tags,
+            remove_tags: self.remove_tags,
+        }))
+    }
+}
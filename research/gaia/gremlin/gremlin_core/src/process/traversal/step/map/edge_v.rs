@@ -16,7 +16,7 @@
 use crate::generated::gremlin as pb;
 use crate::process::traversal::step::map::MapFuncGen;
 use crate::process::traversal::traverser::Traverser;
-use crate::structure::{EndPointOpt, QueryParams, Vertex, VertexOrEdge};
+use crate::structure::{EndPointOpt, QueryParams, Vertex};
 use crate::{str_to_dyn_error, DynResult, FromPb};
 use bit_set::BitSet;
 use pegasus::api::function::{FnResult, MapFunction};
@@ -31,21 +31,18 @@ struct EdgeVertexFunc {
 impl MapFunction<Traverser, Traverser> for EdgeVertexFunc {
     fn exec(&self, mut input: Traverser) -> FnResult<Traverser> {
         if let Some(elem) = input.get_element() {
-            match elem.get() {
-                VertexOrEdge::E(e) => {
-                    let id = if self.get_src { e.src_id } else { e.dst_id };
-                    let graph = crate::get_graph().ok_or(str_to_dyn_error("Graph is None"))?;
-                    let mut r = graph.get_vertex(&[id], &self.params)?;
-                    if let Some(v) = r.next() {
-                        input.split(v, &self.tags);
-                        input.remove_tags(&self.remove_tags);
+            let step = if self.get_src { "outV()" } else { "inV()" };
+            let e = elem.expect_edge(step)?;
+            let id = if self.get_src { e.src_id } else { e.dst_id };
+            let graph = crate::get_graph().ok_or(str_to_dyn_error("Graph is None"))?;
+            let mut r = graph.get_vertex(&[id], &self.params)?;
+            if let Some(v) = r.next() {
+                input.split(v, &self.tags);
+                input.remove_tags(&self.remove_tags);
 
-                        Ok(input)
-                    } else {
-                        Err(str_to_dyn_error(&format!("Vertex with id {} not found", id)))
-                    }
-                }
-                _ => Err(str_to_dyn_error("Should not call `EdgeVertexStep` on a vertex")),
+                Ok(input)
+            } else {
+                Err(str_to_dyn_error(&format!("Vertex with id {} not found", id)))
             }
         } else {
             Err(str_to_dyn_error("invalid input for `EdgeVertexStep`"))
@@ -166,7 +166,7 @@ impl MapFunction<Traverser, Traverser> for SelectStep {
                             input.get_object().ok_or(str_to_dyn_error("should with an object"))?
                         };
                         if let Some(count_value) = try_downcast_group_count_value(map_object) {
-                            return Ok(Traverser::Object(count_value.into()));
+                            return Ok(Traverser::object(count_value.into()));
                         } else if let Some(traverser_value) = try_downcast_group_value(map_object) {
                             return Ok(traverser_value.clone());
                         } else {
@@ -179,6 +179,9 @@ impl MapFunction<Traverser, Traverser> for SelectStep {
                     ByStepOption::OptSubtraversal => {
                         Err(str_to_dyn_error("Do not support OptSubtraversal in select step"))?;
                     }
+                    ByStepOption::OptProjection(_) => {
+                        Err(str_to_dyn_error("Do not support OptProjection in select step"))?;
+                    }
                 }
             } else {
                 // select("a") where "a" is a preserved value, or select("a","b","c") where any tag may refer to a graph element
@@ -201,7 +204,7 @@ impl MapFunction<Traverser, Traverser> for SelectStep {
                 Err(str_to_dyn_error("no tag is provided in select, should be unreachable"))?;
             }
         }
-        Ok(Traverser::Object(Object::DynOwned(Box::new(result))))
+        Ok(Traverser::object(Object::DynOwned(Box::new(result))))
     }
 }
 
@@ -246,6 +249,9 @@ impl MapFuncGen for pb::SelectStep {
                     ByStepOption::OptSubtraversal => {
                         Err(str_to_dyn_error("Do not support OptSubtraversal in select step"))?;
                     }
+                    ByStepOption::OptProjection(_) => {
+                        Err(str_to_dyn_error("Do not support OptProjection in select step"))?;
+                    }
                 }
             } else {
                 if tag.is_none() {
@@ -14,10 +14,15 @@
 //! limitations under the License.
 
 use crate::generated::gremlin as pb;
+use crate::process::side_store::get_job_side_store;
+use crate::process::traversal::step::map::degree::DegreeStep;
 use crate::process::traversal::step::map::edge_v::EdgeVertexStep;
 use crate::process::traversal::step::map::get_path::PathLocalCountStep;
 use crate::process::traversal::step::map::identity::IdentityStep;
+use crate::process::traversal::step::map::project::ProjectStep;
+use crate::process::traversal::step::map::repeat_loops::RepeatLoopsStep;
 use crate::process::traversal::step::map::select_one::SelectOneStep;
+use crate::process::traversal::step::map::store::StoreStep;
 use crate::process::traversal::step::map::transform_traverser::TransformTraverserStep;
 use crate::process::traversal::step::Step;
 use crate::process::traversal::traverser::{Requirement, Traverser};
@@ -26,17 +31,22 @@ use crate::FromPb;
 use crate::{str_to_dyn_error, DynResult};
 pub use get_property::ResultProperty;
 use pegasus::api::function::MapFunction;
+pub use project::ProjectRecord;
 
 #[enum_dispatch]
 pub trait MapFuncGen {
     fn gen_map(self) -> DynResult<Box<dyn MapFunction<Traverser, Traverser>>>;
 }
 
+mod degree;
 mod edge_v;
 mod get_path;
 mod get_property;
 mod identity;
+mod project;
+mod repeat_loops;
 mod select_one;
+mod store;
 mod transform_traverser;
 
 impl MapFuncGen for pb::GremlinStep {
@@ -67,11 +77,30 @@ impl MapFuncGen for pb::GremlinStep {
                         EdgeVertexStep { step: edge_vertex_step, tags, remove_tags };
                     edge_vertex_step.gen_map()
                 }
+                pb::gremlin_step::Step::DegreeStep(degree_step) => {
+                    DegreeStep { step: degree_step, tags, remove_tags }.gen_map()
+                }
+                pb::gremlin_step::Step::ProjectStep(project_step) => {
+                    ProjectStep { step: project_step, tags, remove_tags }.gen_map()
+                }
                 pb::gremlin_step::Step::TransformTraverserStep(s) => {
                     let requirements_pb = unsafe { std::mem::transmute(s.traverser_requirements) };
                     let requirements = Requirement::from_pb(requirements_pb)?;
                     Ok(Box::new(TransformTraverserStep { requirement: requirements, remove_tags }))
                 }
+                pb::gremlin_step::Step::RepeatLoopsStep(s) => {
+                    let kind = pb::repeat_loops_step::Kind::from_i32(s.kind)
+                        .ok_or(str_to_dyn_error("invalid kind of RepeatLoopsStep"))?;
+                    Ok(Box::new(RepeatLoopsStep { kind }))
+                }
+                pb::gremlin_step::Step::SideEffectStep(s)
+                    if s.kind == pb::side_effect_step::Kind::Store as i32 =>
+                {
+                    let store = get_job_side_store()
+                        .ok_or(str_to_dyn_error("side collections are only available in a job"))?;
+                    let collection = store.get_collection(&s.key);
+                    Ok(Box::new(StoreStep { collection }))
+                }
                 _ => Err(str_to_dyn_error("pb GremlinStep is not a Map Step")),
             }
         } else {
@@ -0,0 +1,180 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use crate::generated::gremlin as pb;
+use crate::process::traversal::step::map::MapFuncGen;
+use crate::process::traversal::step::util::projection::Projection;
+use crate::process::traversal::step::util::result_downcast::try_downcast_project;
+use crate::process::traversal::traverser::Traverser;
+use crate::structure::{Details, Token};
+use crate::{str_to_dyn_error, DynResult, Element, FromPb};
+use bit_set::BitSet;
+use dyn_type::Object;
+use pegasus::api::function::{FnResult, MapFunction};
+use pegasus::codec::{Decode, Encode, ReadExt, WriteExt};
+use std::io;
+
+/// The record of `project()`, which lists the values of the columns in the declared order, where
+/// a column without value is either missing, or valued `None` as null
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProjectRecord {
+    columns: Vec<(String, Option<Object>)>,
+}
+
+impl ProjectRecord {
+    pub fn new() -> Self {
+        ProjectRecord { columns: vec![] }
+    }
+
+    /// The columns in the declared order
+    pub fn columns(&self) -> &[(String, Option<Object>)] {
+        &self.columns
+    }
+
+    /// The value of the column, or `None` if the column is missing or null
+    pub fn get(&self, column: &str) -> Option<&Object> {
+        self.columns.iter().find(|(name, _)| name == column).and_then(|(_, value)| value.as_ref())
+    }
+
+    pub fn contains(&self, column: &str) -> bool {
+        self.columns.iter().any(|(name, _)| name == column)
+    }
+
+    /// Set the value of the column, which replaces the former value if the column exists
+    pub fn set(&mut self, column: &str, value: Option<Object>) {
+        match self.columns.iter_mut().find(|(name, _)| name == column) {
+            Some(entry) => entry.1 = value,
+            None => self.columns.push((column.to_owned(), value)),
+        }
+    }
+
+    fn take(&mut self, column: &str) -> Option<Object> {
+        self.columns.iter_mut().find(|(name, _)| name == column).and_then(|(_, value)| value.take())
+    }
+}
+
+impl Encode for ProjectRecord {
+    fn write_to<W: WriteExt>(&self, writer: &mut W) -> io::Result<()> {
+        self.columns.write_to(writer)
+    }
+}
+
+impl Decode for ProjectRecord {
+    fn read_from<R: ReadExt>(reader: &mut R) -> io::Result<Self> {
+        let columns = <Vec<(String, Option<Object>)>>::read_from(reader)?;
+        Ok(ProjectRecord { columns })
+    }
+}
+
+// what a column of project() is valued by
+enum ColumnBy {
+    Key(Token),
+    Projection(Projection),
+    // the sub traversal joined ahead
+    Joined,
+}
+
+struct ProjectFunc {
+    columns: Vec<(String, ColumnBy)>,
+    null_if_empty: bool,
+    tags: BitSet,
+    remove_tags: BitSet,
+}
+
+impl MapFunction<Traverser, Traverser> for ProjectFunc {
+    fn exec(&self, mut input: Traverser) -> FnResult<Traverser> {
+        let element =
+            input.get_element().ok_or(str_to_dyn_error("invalid input for project step"))?;
+        // the values of the sub traversals, attached to the element by `ProjectBySubJoin`
+        let mut joined =
+            element.get_attached().and_then(try_downcast_project).cloned().unwrap_or_default();
+        let mut record = ProjectRecord::new();
+        for (column, by) in self.columns.iter() {
+            let value = match by {
+                ColumnBy::Key(Token::Id) => Some(element.id().into()),
+                ColumnBy::Key(Token::Label) => Some(element.label().as_object()),
+                ColumnBy::Key(Token::Property(prop)) => {
+                    element.details().get_property(prop).and_then(|v| v.try_to_owned())
+                }
+                ColumnBy::Projection(projection) => projection.value(element)?,
+                ColumnBy::Joined => joined.take(column),
+            };
+            if value.is_some() || self.null_if_empty {
+                record.set(column, value);
+            }
+        }
+        input.split_with_value(Object::DynOwned(Box::new(record)), &self.tags);
+        input.remove_tags(&self.remove_tags);
+        Ok(input)
+    }
+}
+
+/// project() of each element, see `pb::ProjectStep`
+pub struct ProjectStep {
+    pub step: pb::ProjectStep,
+    pub tags: BitSet,
+    pub remove_tags: BitSet,
+}
+
+impl MapFuncGen for ProjectStep {
+    fn gen_map(self) -> DynResult<Box<dyn MapFunction<Traverser, Traverser>>> {
+        let mut columns = Vec::with_capacity(self.step.columns.len());
+        for column in self.step.columns {
+            if column.name.is_empty() {
+                Err(str_to_dyn_error("column name of project step must not be empty"))?;
+            }
+            let by = match (column.key, column.projection) {
+                (Some(key), None) => ColumnBy::Key(Token::from_pb(key)?),
+                (None, Some(projection)) => ColumnBy::Projection(Projection::from_pb(projection)?),
+                (None, None) => ColumnBy::Joined,
+                (Some(_), Some(_)) => Err(str_to_dyn_error(
+                    "column of project step must not have both key and projection",
+                ))?,
+            };
+            columns.push((column.name, by));
+        }
+        Ok(Box::new(ProjectFunc {
+            columns,
+            null_if_empty: self.step.null_if_empty,
+            tags: self.tags,
+            remove_tags: self.remove_tags,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn record_columns_test() {
+        let mut record = ProjectRecord::new();
+        record.set("name", Some("marko".into()));
+        record.set("age", None);
+        record.set("degree", Some(3u64.into()));
+        record.set("name", Some("josh".into()));
+        let names: Vec<_> = record.columns().iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["name", "age", "degree"]);
+        assert_eq!(record.get("name"), Some(&"josh".into()));
+        assert!(record.contains("age"));
+        assert_eq!(record.get("age"), None);
+        assert!(!record.contains("lang"));
+
+        let mut bytes = vec![];
+        record.write_to(&mut bytes).unwrap();
+        let decoded = ProjectRecord::read_from(&mut &bytes[..]).unwrap();
+        assert_eq!(decoded, record);
+    }
+}
@@ -0,0 +1,33 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use crate::generated::gremlin as pb;
+use crate::process::traversal::traverser::Traverser;
+use pegasus::api::function::{FnResult, MapFunction};
+
+/// Maintain the loops of traversers in `repeat()`, see `pb::RepeatLoopsStep`
+pub struct RepeatLoopsStep {
+    pub kind: pb::repeat_loops_step::Kind,
+}
+
+impl MapFunction<Traverser, Traverser> for RepeatLoopsStep {
+    fn exec(&self, mut input: Traverser) -> FnResult<Traverser> {
+        match self.kind {
+            pb::repeat_loops_step::Kind::Reset => input.reset_loops(),
+            pb::repeat_loops_step::Kind::Incr => input.incr_loops(),
+        }
+        Ok(input)
+    }
+}
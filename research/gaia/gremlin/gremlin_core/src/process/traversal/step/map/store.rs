@@ -0,0 +1,32 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use crate::process::side_store::SideCollection;
+use crate::process::traversal::traverser::Traverser;
+use pegasus::api::function::{FnResult, MapFunction};
+use std::sync::Arc;
+
+/// Collect the traversers into a side collection of the job lazily as they pass by, e.g.
+/// `store("x")`, see `pb::SideEffectStep`
+pub struct StoreStep {
+    pub collection: Arc<SideCollection>,
+}
+
+impl MapFunction<Traverser, Traverser> for StoreStep {
+    fn exec(&self, input: Traverser) -> FnResult<Traverser> {
+        self.collection.extend(Some(input.clone()));
+        Ok(input)
+    }
+}
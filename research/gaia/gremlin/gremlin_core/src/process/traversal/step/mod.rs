@@ -71,15 +71,18 @@ use crate::structure::{Tag, INIT_TAG_NUM};
 use crate::FromPb;
 use bit_set::BitSet;
 pub use dedup::CollectionFactoryGen;
-pub use filter::FilterFuncGen;
+pub use filter::{gen_shared_filter, has_filter_chain, FilterFuncGen};
 pub use flat_map::FlatMapFuncGen;
+pub(crate) use flat_map::{average_degree, SortedEdgeStep};
 pub use fold::FoldFunctionGen;
 pub use group_by::GroupFunctionGen;
 pub use map::MapFuncGen;
-pub use map::ResultProperty;
+pub use map::{ProjectRecord, ResultProperty};
 pub use order_by::CompareFunctionGen;
 pub use sink::SinkFuncGen;
 pub use source::graph_step_from;
 pub use source::GraphVertexStep;
-pub use sub_traversal::{BySubJoin, GroupBySubJoin, HasAnyJoin, JoinFuncGen, SelectBySubJoin};
-pub use util::result_downcast;
+pub use sub_traversal::{
+    BySubJoin, GroupBySubJoin, HasAnyJoin, JoinFuncGen, ProjectBySubJoin, SelectBySubJoin,
+};
+pub use util::{expr, projection, result_downcast};
@@ -148,6 +148,16 @@ impl CompareFunction<Traverser> for OrderStep {
                         // TODO: only support count() computed by engine for now
                         ordering = left_value.partial_cmp(&right_value);
                     }
+                    // "a" or head should be a graph_element, whose projections are compared, where
+                    // the ones missing values sort last in either order
+                    ByStepOption::OptProjection(projection) => {
+                        if let (Some(left_element), Some(right_element)) =
+                            (left.select_as_element(tag), right.select_as_element(tag))
+                        {
+                            let desc = matches!(order, Order::Desc);
+                            ordering = projection.compare(left_element, right_element, desc);
+                        }
+                    }
                     _ => {}
                 }
             } else {
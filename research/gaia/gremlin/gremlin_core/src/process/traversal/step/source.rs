@@ -14,27 +14,38 @@
 //! limitations under the License.
 
 use crate::generated::gremlin as pb;
+use crate::process::columnar::{columnar_scan, ColumnarStep};
+use crate::process::limits::get_job_access;
+use crate::process::shared_scan::shared_scan;
 use crate::process::traversal::step::util::StepSymbol;
 use crate::process::traversal::step::Step;
 use crate::process::traversal::traverser::{Requirement, Traverser};
 use crate::structure::codec::pb_chain_to_filter;
-use crate::structure::{Label, QueryParams, Vertex, ID};
-use crate::FromPb;
+use crate::structure::{
+    join_id, label_id_from_pb, Direction, Edge, Element, GraphElement, Label, QueryParams, Vertex,
+    ID,
+};
+use crate::{DynResult, FromPb, Partition, Partitioner};
 use bit_set::BitSet;
-use graph_store::common::LabelId;
 use pegasus::BuildJobError;
 use pegasus_common::downcast::*;
+use std::sync::Arc;
 
-/// V(),
+/// V(), or E() if `edge_params` is given
 pub struct GraphVertexStep {
     pub symbol: StepSymbol,
     pub params: QueryParams<Vertex>,
-    src: Option<Vec<Vec<ID>>>,
+    /// The labels and predicates of the edges of E(), or `None` for V()
+    pub edge_params: Option<QueryParams<Edge>>,
+    src: Option<Vec<ID>>,
     as_tags: BitSet,
     requirement: Requirement,
     // workers per server, for gen_source
     workers: usize,
     server_index: u64,
+    num_servers: usize,
+    // decide which worker generates which source vertex, `Partition` if not specified
+    partitioner: Option<Arc<dyn Partitioner>>,
 }
 
 impl_as_any!(GraphVertexStep);
@@ -47,8 +58,11 @@ impl GraphVertexStep {
             as_tags: BitSet::new(),
             requirement: req,
             params: QueryParams::new(),
+            edge_params: None,
             workers: 1,
             server_index: 0,
+            num_servers: 1,
+            partitioner: None,
         }
     }
 
@@ -61,15 +75,18 @@ impl GraphVertexStep {
     }
 
     pub fn set_src(&mut self, ids: Vec<ID>, server_num: usize) {
-        let mut partition = Vec::with_capacity(server_num);
-        for _ in 0..server_num {
-            partition.push(vec![]);
-        }
-        for id in ids {
-            let idx = (id % server_num as ID) as usize;
-            partition[idx].push(id);
-        }
-        self.src = Some(partition);
+        self.num_servers = server_num;
+        self.src = Some(ids);
+    }
+
+    pub fn set_partitioner(&mut self, partitioner: Arc<dyn Partitioner>) {
+        self.partitioner = Some(partitioner);
+    }
+
+    /// Generate the edges of the ids, or all edges if no id is given, i.e. E(), instead of vertices
+    pub fn set_edge_params(&mut self, params: QueryParams<Edge>) {
+        self.symbol = StepSymbol::E;
+        self.edge_params = Some(params);
     }
 
     pub fn set_requirement(&mut self, requirement: Requirement) {
@@ -90,43 +107,130 @@ impl GraphVertexStep {
     pub fn gen_source(
         self, worker_index: Option<usize>,
     ) -> Box<dyn Iterator<Item = Traverser> + Send> {
-        let gen_flag =
-            if let Some(w_index) = worker_index { w_index % self.workers == 0 } else { true };
-        let source = if let Some(ref seeds) = self.src {
-            // work 0 in current server are going to get_vertex
-            if gen_flag {
-                if let Some(src) = seeds.get(self.server_index as usize) {
-                    if !src.is_empty() {
-                        let graph = crate::get_graph().unwrap();
-                        graph.get_vertex(src, &self.params).unwrap_or(Box::new(std::iter::empty()))
-                    } else {
-                        Box::new(std::iter::empty())
-                    }
-                } else {
-                    Box::new(std::iter::empty())
-                }
+        if self.edge_params.is_some() {
+            self.to_traversers(self.gen_edges(worker_index))
+        } else {
+            self.to_traversers(self.gen_vertices(worker_index))
+        }
+    }
+
+    fn to_traversers<E: Into<GraphElement> + 'static>(
+        &self, source: Box<dyn Iterator<Item = E> + Send>,
+    ) -> Box<dyn Iterator<Item = Traverser> + Send> {
+        if self.tracks_path() {
+            let tags = self.as_tags.clone();
+            let requirement = self.requirement;
+            Box::new(source.map(move |e| Traverser::with_path(e, &tags, requirement)))
+        } else {
+            Box::new(source.map(|e| Traverser::new(e)))
+        }
+    }
+
+    /// Generate the source scanning the vertices by batches, over which the fused steps are
+    /// evaluated column-wise before the vertices are converted to traversers, see `columnar_scan`,
+    /// which the source tracking the paths can't fuse any step into
+    pub fn gen_columnar_source(
+        self, worker_index: Option<usize>, steps: Vec<ColumnarStep>,
+    ) -> Box<dyn Iterator<Item = Traverser> + Send> {
+        assert!(!self.tracks_path(), "columnar scan of the source tracking the paths");
+        columnar_scan(self.gen_vertices(worker_index), steps)
+    }
+
+    /// Whether the traversers of the source track their paths
+    pub fn tracks_path(&self) -> bool {
+        self.requirement.contains(Requirement::PATH)
+            || self.requirement.contains(Requirement::LABELED_PATH)
+    }
+
+    // whether the element of the id is generated by the worker of the index, or by current server
+    // if `None`, where an edge is generated as its start vertex, see `encode_runtime_e_id`
+    fn owner(
+        &self, worker_index: Option<usize>,
+    ) -> impl Fn(&ID) -> bool + Clone + Send + Sync + 'static {
+        let partitioner = self
+            .partitioner
+            .clone()
+            .unwrap_or_else(|| Arc::new(Partition { num_servers: self.num_servers }));
+        let workers = self.workers;
+        let server_index = self.server_index;
+        // Each vertex is generated by a worker that maintains it, i.e. one of its replicas, to
+        // preserve the data locality.
+        move |id: &ID| match worker_index {
+            Some(w_index) => partitioner.get_scan_partition(id, workers) == w_index as u64,
+            None => partitioner.get_scan_partition(id, 1) == server_index,
+        }
+    }
+
+    // the vertices of the source on the worker of the index, or on current server if `None`
+    fn gen_vertices(&self, worker_index: Option<usize>) -> Box<dyn Iterator<Item = Vertex> + Send> {
+        let is_owner = self.owner(worker_index);
+        // the scan shared with other jobs keeps the vertices of current worker the same way
+        let shared_owner = is_owner.clone();
+        let source: Box<dyn Iterator<Item = Vertex> + Send> = if let Some(ref seeds) = self.src {
+            let src = seeds.iter().filter(|id| is_owner(id)).cloned().collect::<Vec<ID>>();
+            if !src.is_empty() {
+                let graph = crate::get_graph().unwrap();
+                graph.get_vertex(&src, &self.params).unwrap_or(Box::new(std::iter::empty()))
             } else {
                 Box::new(std::iter::empty())
             }
+        } else if let Some(vertices) = shared_scan(worker_index, &self.params, move |v: &Vertex| {
+            worker_index.is_none() || shared_owner(&v.id())
+        }) {
+            vertices
         } else {
-            // work 0 in current server are going to scan_vertex
-            if gen_flag {
-                let graph = crate::get_graph().unwrap();
-                graph.scan_vertex(&self.params).unwrap_or(Box::new(std::iter::empty()))
+            let graph = crate::get_graph().unwrap();
+            let vertices = if worker_index.is_some() {
+                // each worker in current server scans its own part of the local vertices only
+                graph.scan_vertex_partition(&self.params, Arc::new(is_owner))
+            } else {
+                graph.scan_vertex(&self.params)
+            };
+            vertices.unwrap_or(Box::new(std::iter::empty()))
+        };
+
+        // the source stops scanning once exceeding the vertex limit, which aborts the job
+        match get_job_access() {
+            Some(access) => {
+                Box::new(source.take_while(move |_| access.add_vertices(1, "V()").is_ok()))
+            }
+            None => source,
+        }
+    }
+
+    // the edges of the source on the worker of the index, or on current server if `None`, each
+    // kept by the partition of its start vertex, so that all edges are scanned with the vertices
+    fn gen_edges(&self, worker_index: Option<usize>) -> Box<dyn Iterator<Item = Edge> + Send> {
+        let params = self.edge_params.clone().unwrap_or_default();
+        let is_owner = self.owner(worker_index);
+        let graph = crate::get_graph().unwrap();
+        let source: Box<dyn Iterator<Item = Edge> + Send> = if let Some(ref seeds) = self.src {
+            let src = seeds.iter().filter(|id| is_owner(id)).cloned().collect::<Vec<ID>>();
+            if !src.is_empty() {
+                graph.get_edge(&src, &params).unwrap_or(Box::new(std::iter::empty()))
             } else {
-                // return an emtpy iterator;
                 Box::new(std::iter::empty())
             }
+        } else {
+            let vertices = if worker_index.is_some() {
+                graph.scan_vertex_partition(&QueryParams::new(), Arc::new(is_owner))
+            } else {
+                graph.scan_vertex(&QueryParams::new())
+            };
+            let vertices = vertices.unwrap_or(Box::new(std::iter::empty()));
+            match graph.prepare_explore_edge(Direction::Out, &params) {
+                Ok(stmt) => Box::new(vertices.flat_map(move |v| {
+                    stmt.exec(v.id()).into_iter().flatten().filter_map(|e| e.ok())
+                })),
+                Err(_) => Box::new(std::iter::empty()),
+            }
         };
 
-        if self.requirement.contains(Requirement::PATH)
-            || self.requirement.contains(Requirement::LABELED_PATH)
-        {
-            let tags = self.as_tags;
-            let requirement = self.requirement.clone();
-            Box::new(source.map(move |v| Traverser::with_path(v, &tags, requirement)))
-        } else {
-            Box::new(source.map(|v| Traverser::new(v)))
+        match get_job_access() {
+            Some(access) => {
+                Box::new(source.take_while(move |_| access.add_edges(1, "E()").is_ok()))
+            }
+            None => source,
         }
     }
 }
@@ -134,32 +238,44 @@ impl GraphVertexStep {
 pub fn graph_step_from(
     gremlin_step: &mut pb::GremlinStep, num_servers: usize,
 ) -> Result<GraphVertexStep, BuildJobError> {
-    if let Some(option) = gremlin_step.step.take() {
-        match option {
-            pb::gremlin_step::Step::GraphStep(mut opt) => {
-                let requirements_pb = unsafe { std::mem::transmute(opt.traverser_requirements) };
-                let requirements = Requirement::from_pb(requirements_pb)?;
-                let mut step = GraphVertexStep::new(requirements);
-                step.set_tags(gremlin_step.get_tags());
-                let mut ids = vec![];
-                for id in opt.ids {
-                    ids.push(id as ID);
-                }
-                if !ids.is_empty() {
-                    step.set_src(ids, num_servers);
+    if let Some(pb::gremlin_step::Step::GraphStep(mut opt)) = gremlin_step.step.take() {
+        let requirements_pb: Vec<pb::TraverserRequirement> =
+            unsafe { std::mem::transmute(opt.traverser_requirements) };
+        let requirements = Requirement::from_pb(requirements_pb)?;
+        let mut step = GraphVertexStep::new(requirements);
+        step.set_tags(gremlin_step.get_tags());
+        let mut ids = vec![];
+        for (i, id) in opt.ids.iter().enumerate() {
+            ids.push(join_id(*id, opt.high_ids.get(i).copied().unwrap_or(0)));
+        }
+        step.num_servers = num_servers;
+        if !ids.is_empty() {
+            step.set_src(ids, num_servers);
+        }
+        let labels = std::mem::take(&mut opt.labels);
+        let labels = labels
+            .into_iter()
+            .map(|id| label_id_from_pb(id).map(Label::Id))
+            .collect::<DynResult<Vec<_>>>()
+            .map_err(|e| e.to_string())?;
+        if opt.return_type == pb::EntityType::Edge as i32 {
+            let mut params = QueryParams::new();
+            params.labels = labels;
+            if let Some(ref test) = opt.predicates {
+                if let Some(filter) = pb_chain_to_filter(test)? {
+                    params.set_filter(filter);
                 }
-                let labels = std::mem::replace(&mut opt.labels, vec![]);
-                step.params.labels =
-                    labels.into_iter().map(|id| Label::Id(id as LabelId)).collect();
-                if let Some(ref test) = opt.predicates {
-                    if let Some(filter) = pb_chain_to_filter(test)? {
-                        step.params.set_filter(filter);
-                    }
+            }
+            step.set_edge_params(params);
+        } else {
+            step.params.labels = labels;
+            if let Some(ref test) = opt.predicates {
+                if let Some(filter) = pb_chain_to_filter(test)? {
+                    step.params.set_filter(filter);
                 }
-                return Ok(step);
             }
-            _ => (),
         }
+        return Ok(step);
     }
     Err("Unsupported source step in pb_request")?
 }
@@ -13,9 +13,13 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
-use crate::process::traversal::step::util::result_downcast::try_downcast_group_key;
+use crate::process::traversal::step::util::result_downcast::{
+    try_downcast_group_key, try_downcast_project,
+};
 use crate::process::traversal::traverser::Traverser;
+use crate::Element;
 use bit_set::BitSet;
+use dyn_type::Object;
 use pegasus::api::function::LeftJoinFunction;
 use std::sync::Arc;
 
@@ -92,3 +96,40 @@ impl LeftJoinFunction<Traverser> for SelectBySubJoin {
         }
     }
 }
+
+// for e.g., project("degree").by(out().count()), where the first result of the sub traversal, or
+// `empty` if there is none, is kept in the record attached to the element as the value of `column`,
+// until the record is emitted by the project step
+pub struct ProjectBySubJoin {
+    pub column: String,
+    pub empty: Option<Object>,
+}
+
+impl ProjectBySubJoin {
+    fn join(&self, parent: &Traverser, value: Option<Object>) -> Option<Traverser> {
+        let mut parent = parent.clone();
+        let element = parent.get_element_mut()?;
+        let mut record =
+            element.get_attached().and_then(try_downcast_project).cloned().unwrap_or_default();
+        if value.is_some() {
+            record.set(&self.column, value);
+        }
+        element.attach(Object::DynOwned(Box::new(record)));
+        Some(parent)
+    }
+}
+
+impl LeftJoinFunction<Traverser> for ProjectBySubJoin {
+    fn exec(&self, parent: &Traverser, sub: Traverser) -> Option<Traverser> {
+        // the elements are valued by their ids, as they are in the results of the traversal
+        let value = match sub.get_element() {
+            Some(element) => Some(element.id().into()),
+            None => sub.get_object().cloned(),
+        };
+        self.join(parent, value)
+    }
+
+    fn exec_empty(&self, parent: &Traverser) -> Option<Traverser> {
+        self.join(parent, self.empty.clone())
+    }
+}
@@ -0,0 +1,501 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The arithmetic expressions of math(), e.g. `_ * 1.2 + 3` or `(a - b) % 7`, made of `+`, `-`,
+//! `*`, `/`, `%`, parentheses, unary minus, numeric literals and variables, where `_` is the head
+//! of the traverser and any other name is a label selected from its path. An expression is parsed
+//! once for a plan by `parse`, whose variables are bound by `Expr::bind`, and is evaluated for
+//! each traverser by `Expr::eval`.
+//!
+//! The numbers are promoted as by sum(): bytes and integers give an integer, or a long if the
+//! result overflows i32, any long gives a long, and any float gives a float. The division of
+//! integers truncates toward zero, e.g. `7 / 2` is 3 while `7 / 2.0` is 3.5. A division or modulo
+//! by zero evaluates to nothing instead of failing, which filters out the traverser as TinkerPop
+//! does.
+
+use crate::structure::codec::ParseError;
+use crate::{str_to_dyn_error, DynResult};
+use dyn_type::object::Primitives;
+
+/// The most nested parentheses and unary minus of an expression, to reject the ones which would
+/// overflow the stack of the parser
+pub const MAX_EXPR_DEPTH: usize = 64;
+
+/// A variable of an expression before it is bound
+#[derive(Debug, Clone, PartialEq)]
+pub enum Variable {
+    /// `_`, the head of the traverser
+    Current,
+    /// the value of a label, e.g. `a` of `as("a")`
+    Label(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+}
+
+/// An arithmetic expression over the variables of type `V`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr<V = Variable> {
+    Number(Primitives),
+    Var(V),
+    Neg(Box<Expr<V>>),
+    Binary(BinaryOp, Box<Expr<V>>, Box<Expr<V>>),
+}
+
+impl<V> Expr<V> {
+    /// The variables in the expression from left to right, with duplicates
+    pub fn variables(&self) -> Vec<&V> {
+        let mut variables = vec![];
+        self.collect_variables(&mut variables);
+        variables
+    }
+
+    fn collect_variables<'a>(&'a self, variables: &mut Vec<&'a V>) {
+        match self {
+            Expr::Number(_) => {}
+            Expr::Var(v) => variables.push(v),
+            Expr::Neg(e) => e.collect_variables(variables),
+            Expr::Binary(_, left, right) => {
+                left.collect_variables(variables);
+                right.collect_variables(variables);
+            }
+        }
+    }
+
+    /// Bind each variable to what it is evaluated by, e.g. the tag of its label
+    pub fn bind<W, E, F>(self, func: &mut F) -> Result<Expr<W>, E>
+    where
+        F: FnMut(V) -> Result<W, E>,
+    {
+        Ok(match self {
+            Expr::Number(n) => Expr::Number(n),
+            Expr::Var(v) => Expr::Var(func(v)?),
+            Expr::Neg(e) => Expr::Neg(Box::new(e.bind(func)?)),
+            Expr::Binary(op, left, right) => {
+                Expr::Binary(op, Box::new(left.bind(func)?), Box::new(right.bind(func)?))
+            }
+        })
+    }
+
+    /// Evaluate the expression by the values of the variables given by `lookup`, which is `None`
+    /// on a division or modulo by zero, or an error if an integer overflows i64
+    pub fn eval<F>(&self, lookup: &F) -> DynResult<Option<Primitives>>
+    where
+        F: Fn(&V) -> DynResult<Primitives>,
+    {
+        match self {
+            Expr::Number(n) => Ok(Some(*n)),
+            Expr::Var(v) => lookup(v).map(Some),
+            Expr::Neg(e) => match e.eval(lookup)? {
+                Some(value) => negate(value).map(Some),
+                None => Ok(None),
+            },
+            Expr::Binary(op, left, right) => {
+                let left = match left.eval(lookup)? {
+                    Some(value) => value,
+                    None => return Ok(None),
+                };
+                match right.eval(lookup)? {
+                    Some(right) => apply(*op, left, right),
+                    None => Ok(None),
+                }
+            }
+        }
+    }
+}
+
+/// Parse the expression, e.g. `_ * 1.2 + 3`, where `*`, `/` and `%` bind tighter than `+` and
+/// `-`, and the operators of the same precedence are left associative
+pub fn parse(expression: &str) -> Result<Expr, ParseError> {
+    let mut parser = Parser { expression, bytes: expression.as_bytes(), pos: 0, depth: 0 };
+    let expr = parser.parse_sum()?;
+    match parser.peek() {
+        None => Ok(expr),
+        Some(_) => Err(parser.unexpected()),
+    }
+}
+
+struct Parser<'a> {
+    expression: &'a str,
+    bytes: &'a [u8],
+    pos: usize,
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, msg: &str) -> ParseError {
+        ParseError::OtherErr(format!(
+            "invalid expression `{}` at {}: {}",
+            self.expression, self.pos, msg
+        ))
+    }
+
+    fn unexpected(&self) -> ParseError {
+        match self.expression[self.pos..].chars().next() {
+            Some(c) => self.error(&format!("unexpected `{}`", c)),
+            None => self.error("unexpected end"),
+        }
+    }
+
+    // the next non-space byte, which is not consumed
+    fn peek(&mut self) -> Option<u8> {
+        while self.pos < self.bytes.len() && self.bytes[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn enter(&mut self) -> Result<(), ParseError> {
+        self.depth += 1;
+        if self.depth > MAX_EXPR_DEPTH {
+            Err(self.error(&format!("nested deeper than {}", MAX_EXPR_DEPTH)))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn parse_sum(&mut self) -> Result<Expr, ParseError> {
+        let mut left = self.parse_product()?;
+        loop {
+            let op = match self.peek() {
+                Some(b'+') => BinaryOp::Add,
+                Some(b'-') => BinaryOp::Sub,
+                _ => return Ok(left),
+            };
+            self.pos += 1;
+            let right = self.parse_product()?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+    }
+
+    fn parse_product(&mut self) -> Result<Expr, ParseError> {
+        let mut left = self.parse_unary()?;
+        loop {
+            let op = match self.peek() {
+                Some(b'*') => BinaryOp::Mul,
+                Some(b'/') => BinaryOp::Div,
+                Some(b'%') => BinaryOp::Mod,
+                _ => return Ok(left),
+            };
+            self.pos += 1;
+            let right = self.parse_unary()?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, ParseError> {
+        match self.peek() {
+            Some(b'-') => {
+                self.pos += 1;
+                self.enter()?;
+                let expr = self.parse_unary()?;
+                self.depth -= 1;
+                Ok(Expr::Neg(Box::new(expr)))
+            }
+            Some(b'+') => {
+                self.pos += 1;
+                self.parse_unary()
+            }
+            _ => self.parse_primary(),
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, ParseError> {
+        match self.peek() {
+            Some(b'(') => {
+                self.pos += 1;
+                self.enter()?;
+                let expr = self.parse_sum()?;
+                if self.peek() != Some(b')') {
+                    return Err(self.error("expect `)`"));
+                }
+                self.pos += 1;
+                self.depth -= 1;
+                Ok(expr)
+            }
+            Some(c) if c.is_ascii_digit() || c == b'.' => self.parse_number(),
+            Some(c) if c.is_ascii_alphabetic() || c == b'_' => {
+                let start = self.pos;
+                while self.pos < self.bytes.len()
+                    && (self.bytes[self.pos].is_ascii_alphanumeric()
+                        || self.bytes[self.pos] == b'_')
+                {
+                    self.pos += 1;
+                }
+                match &self.expression[start..self.pos] {
+                    "_" => Ok(Expr::Var(Variable::Current)),
+                    name => Ok(Expr::Var(Variable::Label(name.to_owned()))),
+                }
+            }
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    // e.g. `3`, `1.2`, `.5` or `1e-3`, where a number with `.` or an exponent is a float
+    fn parse_number(&mut self) -> Result<Expr, ParseError> {
+        let start = self.pos;
+        let mut is_float = false;
+        self.skip_digits();
+        if self.bytes.get(self.pos) == Some(&b'.') {
+            is_float = true;
+            self.pos += 1;
+            self.skip_digits();
+        }
+        if matches!(self.bytes.get(self.pos), Some(b'e') | Some(b'E')) {
+            let exp = match self.bytes.get(self.pos + 1) {
+                Some(b'+') | Some(b'-') => self.pos + 2,
+                _ => self.pos + 1,
+            };
+            if matches!(self.bytes.get(exp), Some(c) if c.is_ascii_digit()) {
+                is_float = true;
+                self.pos = exp;
+                self.skip_digits();
+            }
+        }
+        let expression = self.expression;
+        let literal = &expression[start..self.pos];
+        let number = if is_float {
+            literal.parse::<f64>().ok().map(Primitives::Float)
+        } else {
+            literal.parse::<i64>().ok().map(|v| narrow(v, false))
+        };
+        number.map(Expr::Number).ok_or_else(|| {
+            self.pos = start;
+            self.error(&format!("invalid number `{}`", literal))
+        })
+    }
+
+    fn skip_digits(&mut self) {
+        while self.pos < self.bytes.len() && self.bytes[self.pos].is_ascii_digit() {
+            self.pos += 1;
+        }
+    }
+}
+
+#[inline]
+fn to_f64(value: Primitives) -> f64 {
+    match value {
+        Primitives::Byte(v) => v as f64,
+        Primitives::Integer(v) => v as f64,
+        Primitives::Long(v) => v as f64,
+        Primitives::Float(v) => v,
+    }
+}
+
+#[inline]
+fn to_i64(value: Primitives) -> i64 {
+    match value {
+        Primitives::Byte(v) => v as i64,
+        Primitives::Integer(v) => v as i64,
+        Primitives::Long(v) => v,
+        Primitives::Float(v) => v as i64,
+    }
+}
+
+// an integer unless it is from a long or overflows i32
+#[inline]
+fn narrow(value: i64, long: bool) -> Primitives {
+    if !long && value >= i32::MIN as i64 && value <= i32::MAX as i64 {
+        Primitives::Integer(value as i32)
+    } else {
+        Primitives::Long(value)
+    }
+}
+
+fn negate(value: Primitives) -> DynResult<Primitives> {
+    match value {
+        Primitives::Float(v) => Ok(Primitives::Float(-v)),
+        Primitives::Long(v) => v
+            .checked_neg()
+            .map(Primitives::Long)
+            .ok_or_else(|| str_to_dyn_error("negation overflows i64 in math()")),
+        _ => Ok(narrow(-to_i64(value), false)),
+    }
+}
+
+fn apply(op: BinaryOp, left: Primitives, right: Primitives) -> DynResult<Option<Primitives>> {
+    match (left, right) {
+        (Primitives::Float(_), _) | (_, Primitives::Float(_)) => {
+            let (left, right) = (to_f64(left), to_f64(right));
+            Ok(match op {
+                BinaryOp::Add => Some(left + right),
+                BinaryOp::Sub => Some(left - right),
+                BinaryOp::Mul => Some(left * right),
+                BinaryOp::Div | BinaryOp::Mod if right == 0.0 => None,
+                BinaryOp::Div => Some(left / right),
+                BinaryOp::Mod => Some(left % right),
+            }
+            .map(Primitives::Float))
+        }
+        _ => {
+            let long = matches!(left, Primitives::Long(_)) || matches!(right, Primitives::Long(_));
+            let (left, right) = (to_i64(left), to_i64(right));
+            let value = match op {
+                BinaryOp::Div | BinaryOp::Mod if right == 0 => return Ok(None),
+                BinaryOp::Add => left.checked_add(right),
+                BinaryOp::Sub => left.checked_sub(right),
+                BinaryOp::Mul => left.checked_mul(right),
+                BinaryOp::Div => left.checked_div(right),
+                BinaryOp::Mod => left.checked_rem(right),
+            };
+            let value = value.ok_or_else(|| {
+                str_to_dyn_error(&format!(
+                    "{:?} of {} and {} overflows i64 in math()",
+                    op, left, right
+                ))
+            })?;
+            Ok(Some(narrow(value, long)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn eval_with(expression: &str, labels: &HashMap<&str, Primitives>) -> Option<Primitives> {
+        let expr = parse(expression).unwrap();
+        let lookup = |v: &Variable| match v {
+            Variable::Current => Ok(labels["_"]),
+            Variable::Label(name) => {
+                labels.get(name.as_str()).copied().ok_or_else(|| str_to_dyn_error("unbound"))
+            }
+        };
+        expr.eval(&lookup).unwrap()
+    }
+
+    fn eval(expression: &str) -> Option<Primitives> {
+        let mut labels = HashMap::new();
+        labels.insert("_", Primitives::Integer(10));
+        eval_with(expression, &labels)
+    }
+
+    fn binary(op: BinaryOp, left: Expr, right: Expr) -> Expr {
+        Expr::Binary(op, Box::new(left), Box::new(right))
+    }
+
+    fn int(v: i32) -> Expr {
+        Expr::Number(Primitives::Integer(v))
+    }
+
+    #[test]
+    fn parse_precedence_test() {
+        // 1 + (2 * 3) - (4 % 5)
+        let expected = binary(
+            BinaryOp::Sub,
+            binary(BinaryOp::Add, int(1), binary(BinaryOp::Mul, int(2), int(3))),
+            binary(BinaryOp::Mod, int(4), int(5)),
+        );
+        assert_eq!(parse("1 + 2 * 3 - 4 % 5").unwrap(), expected);
+        // (1 + 2) * -_
+        let expected = binary(
+            BinaryOp::Mul,
+            binary(BinaryOp::Add, int(1), int(2)),
+            Expr::Neg(Box::new(Expr::Var(Variable::Current))),
+        );
+        assert_eq!(parse(" ( 1+2 )*-_ ").unwrap(), expected);
+        // left associative
+        let expected = binary(BinaryOp::Div, binary(BinaryOp::Div, int(8), int(4)), int(2));
+        assert_eq!(parse("8 / 4 / 2").unwrap(), expected);
+    }
+
+    #[test]
+    fn parse_numbers_and_variables_test() {
+        assert_eq!(parse("1.2").unwrap(), Expr::Number(Primitives::Float(1.2)));
+        assert_eq!(parse(".5").unwrap(), Expr::Number(Primitives::Float(0.5)));
+        assert_eq!(parse("1e-3").unwrap(), Expr::Number(Primitives::Float(0.001)));
+        assert_eq!(parse("3000000000").unwrap(), Expr::Number(Primitives::Long(3_000_000_000)));
+        let expr = parse("a * _ + price_2 - a").unwrap();
+        let label = |name: &str| Variable::Label(name.to_owned());
+        let expected = [label("a"), Variable::Current, label("price_2"), label("a")];
+        assert_eq!(expr.variables(), expected.iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn parse_error_test() {
+        for (expression, msg) in [
+            ("", "unexpected end"),
+            ("1 +", "unexpected end"),
+            ("(1 + 2", "expect `)`"),
+            ("1 2", "unexpected `2`"),
+            ("1.2.3", "unexpected `.`"),
+            ("_ ^ 2", "unexpected `^`"),
+            ("99999999999999999999", "invalid number"),
+            ("1 + é", "unexpected `é`"),
+        ] {
+            let err = parse(expression).expect_err(expression).to_string();
+            assert!(err.contains(msg), "unexpected error of `{}`: {}", expression, err);
+        }
+        let nested =
+            format!("{}1{}", "(".repeat(MAX_EXPR_DEPTH + 1), ")".repeat(MAX_EXPR_DEPTH + 1));
+        assert!(parse(&nested).is_err());
+        assert!(parse(&"-".repeat(MAX_EXPR_DEPTH + 1)).is_err());
+    }
+
+    #[test]
+    fn eval_promotion_test() {
+        // numbers of different types may be equal, so their types are compared by debug output
+        let typed = |value: Option<Primitives>| format!("{:?}", value.unwrap());
+        assert_eq!(typed(eval("_ * 2 + 3")), "Integer(23)");
+        assert_eq!(typed(eval("_ * 1.5 + 3")), "Float(18.0)");
+        assert_eq!(typed(eval("7 / 2")), "Integer(3)");
+        assert_eq!(typed(eval("7 / 2.0")), "Float(3.5)");
+        assert_eq!(typed(eval("-7 % 3")), "Integer(-1)");
+        assert_eq!(typed(eval("2147483647 + 1")), "Long(2147483648)");
+        assert_eq!(typed(eval("-(_ - 12) * (1 + 2)")), "Integer(6)");
+        let mut labels = HashMap::new();
+        labels.insert("_", Primitives::Byte(3));
+        labels.insert("a", Primitives::Long(1));
+        assert_eq!(typed(eval_with("_ + 1", &labels)), "Integer(4)");
+        assert_eq!(typed(eval_with("_ + a", &labels)), "Long(4)");
+    }
+
+    #[test]
+    fn eval_labels_test() {
+        let mut labels = HashMap::new();
+        labels.insert("_", Primitives::Integer(1));
+        labels.insert("a", Primitives::Integer(10));
+        labels.insert("b", Primitives::Float(4.0));
+        assert_eq!(eval_with("(a - b) / 2 + _", &labels), Some(Primitives::Float(4.0)));
+        // the variables are bound to what they are evaluated by
+        let expr = parse("a * b").unwrap();
+        let bound = expr
+            .bind(&mut |v| match v {
+                Variable::Label(name) if name == "a" => Ok(0),
+                Variable::Label(name) if name == "b" => Ok(1),
+                _ => Err(()),
+            })
+            .unwrap();
+        let values = [Primitives::Integer(6), Primitives::Integer(7)];
+        assert_eq!(bound.eval(&|i: &usize| Ok(values[*i])).unwrap(), Some(Primitives::Integer(42)));
+        assert!(parse("a * c").unwrap().bind(&mut |_| Err::<usize, _>(())).is_err());
+    }
+
+    #[test]
+    fn eval_division_by_zero_test() {
+        for expression in ["_ / 0", "_ % 0", "_ / 0.0", "_ % (5 - 5)", "1 + -(_ / 0)"] {
+            assert_eq!(eval(expression), None, "{}", expression);
+        }
+        let expr = parse("9223372036854775807 + _").unwrap();
+        assert!(expr.eval(&|_: &Variable| Ok(Primitives::Integer(1))).is_err());
+    }
+}
@@ -13,7 +13,9 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
+pub mod expr;
 mod predicate;
+pub mod projection;
 pub mod result_downcast;
 
 pub use predicate::TraverserPredicate;
@@ -0,0 +1,251 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The projections of by() into a single value in one access of the properties of an element,
+//! either by a composite of several keys, e.g. by(values("age", "name")), or by the math
+//! expression of its properties, e.g. `age * 2 + weight`, see `pb::Projection`. A composite is
+//! carried as a custom value of `CompositeKey`, so that order(), group() and dedup() compare,
+//! hash and route it as any other value, where a missing value sorts after all the values and
+//! groups with the other missing ones.
+
+use crate::generated::gremlin as pb;
+use crate::process::traversal::step::util::expr::{parse, Expr, Variable};
+use crate::structure::codec::ParseError;
+use crate::structure::{Details, GraphElement, Token};
+use crate::{str_to_dyn_error, DynResult, Element, FromPb};
+use dyn_type::stable_hash::xxh64;
+use dyn_type::{register_custom_type, CustomObject, CustomType, Object, STABLE_HASH_SEED};
+use pegasus::codec::{Decode, Encode};
+use std::cmp::Ordering;
+use std::io;
+use std::sync::Once;
+
+/// The values of the keys of a projection in order, where `None` is a missing value, which is
+/// greater than any value and equal to another missing value
+#[derive(Clone, Debug, Default)]
+pub struct CompositeKey {
+    values: Vec<Option<Object>>,
+}
+
+impl CompositeKey {
+    pub fn new(values: Vec<Option<Object>>) -> Self {
+        CompositeKey { values }
+    }
+
+    pub fn values(&self) -> &[Option<Object>] {
+        self.values.as_slice()
+    }
+}
+
+// compare the values in order, where a missing value is `missing` to any value
+fn compare_values(
+    left: &[Option<Object>], right: &[Option<Object>], missing: Ordering,
+) -> Option<Ordering> {
+    for (left, right) in left.iter().zip(right.iter()) {
+        let ordering = match (left, right) {
+            (Some(left), Some(right)) => left.partial_cmp(right)?,
+            (Some(_), None) => missing.reverse(),
+            (None, Some(_)) => missing,
+            (None, None) => Ordering::Equal,
+        };
+        if ordering != Ordering::Equal {
+            return Some(ordering);
+        }
+    }
+    Some(left.len().cmp(&right.len()))
+}
+
+impl CustomType for CompositeKey {
+    const TAG: &'static str = "gremlin.composite_key";
+
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        self.values.write_to(&mut bytes).expect("write to vec failure");
+        bytes
+    }
+
+    fn decode(bytes: &[u8]) -> io::Result<Self> {
+        let values = <Vec<Option<Object>>>::read_from(&mut &bytes[..])?;
+        Ok(CompositeKey { values })
+    }
+
+    fn compare(&self, other: &Self) -> Option<Ordering> {
+        compare_values(&self.values, &other.values, Ordering::Greater)
+    }
+
+    fn hash(&self) -> u64 {
+        // by the stable hashes of the values, as the equal numbers of different types are hashed
+        // the same
+        let mut bytes = Vec::with_capacity(self.values.len() * 9);
+        for value in self.values.iter() {
+            match value {
+                Some(value) => {
+                    bytes.push(1);
+                    bytes.extend_from_slice(&value.stable_hash().to_le_bytes());
+                }
+                None => bytes.push(0),
+            }
+        }
+        xxh64(&bytes, STABLE_HASH_SEED)
+    }
+}
+
+static REGISTER_COMPOSITE_KEY: Once = Once::new();
+
+/// Register `CompositeKey` as a custom type once, before any composite is compared or decoded
+pub fn register_composite_key() {
+    REGISTER_COMPOSITE_KEY.call_once(|| {
+        register_custom_type::<CompositeKey>().expect("register composite key failure")
+    });
+}
+
+/// A projection of the element compiled from `pb::Projection`
+#[derive(Clone, Debug)]
+pub enum Projection {
+    /// the values of the keys in order
+    Composite(Vec<Token>),
+    /// the value of the expression whose variables are bound to the property names
+    Expression(Expr<String>),
+}
+
+impl Projection {
+    /// The values of the keys of the element read in one access of its properties, or the value
+    /// of the expression alone, which is missing if any of its properties is missing or not a
+    /// number, or if it is divided by zero
+    pub fn values(&self, element: &GraphElement) -> DynResult<Vec<Option<Object>>> {
+        let details = element.details();
+        match self {
+            Projection::Composite(keys) => Ok(keys
+                .iter()
+                .map(|key| match key {
+                    Token::Id => Some(element.id().into()),
+                    Token::Label => Some(element.label().as_object()),
+                    Token::Property(prop) => {
+                        details.get_property(prop).and_then(|v| v.try_to_owned())
+                    }
+                })
+                .collect()),
+            Projection::Expression(expr) => {
+                let mut operands = Vec::new();
+                for prop in expr.variables() {
+                    match details.get_property(prop).and_then(|v| v.as_primitive().ok()) {
+                        Some(value) => operands.push((prop, value)),
+                        None => return Ok(vec![None]),
+                    }
+                }
+                let value = expr.eval(&|prop: &String| {
+                    operands.iter().find(|(p, _)| *p == prop).map(|(_, value)| *value).ok_or_else(
+                        || str_to_dyn_error(&format!("property `{}` is not found", prop)),
+                    )
+                })?;
+                Ok(vec![value.map(Object::Primitive)])
+            }
+        }
+    }
+
+    /// The projected value of the element, which is the single value of an expression or of a
+    /// single key, or the composite of several keys
+    pub fn value(&self, element: &GraphElement) -> DynResult<Option<Object>> {
+        let mut values = self.values(element)?;
+        if values.len() == 1 {
+            Ok(values.pop().unwrap_or(None))
+        } else {
+            Ok(Some(composite_object(values)))
+        }
+    }
+
+    /// The key of the element to group or dedup by, as the composite of the projected values,
+    /// where the elements missing the same values are keyed the same
+    pub fn key(&self, element: &GraphElement) -> DynResult<Object> {
+        Ok(composite_object(self.values(element)?))
+    }
+
+    /// Compare the elements by their projected values, where a missing value is the greatest, or
+    /// the least if `desc`, so that it sorts last once the ordering is reversed for `desc`
+    pub fn compare(
+        &self, left: &GraphElement, right: &GraphElement, desc: bool,
+    ) -> Option<Ordering> {
+        let missing = if desc { Ordering::Less } else { Ordering::Greater };
+        match (self.values(left), self.values(right)) {
+            (Ok(left), Ok(right)) => compare_values(&left, &right, missing),
+            _ => None,
+        }
+    }
+}
+
+fn composite_object(values: Vec<Option<Object>>) -> Object {
+    Object::Custom(CustomObject::from_value(&CompositeKey::new(values)))
+}
+
+impl FromPb<pb::Projection> for Projection {
+    fn from_pb(projection_pb: pb::Projection) -> Result<Self, ParseError>
+    where
+        Self: Sized,
+    {
+        register_composite_key();
+        if !projection_pb.expression.is_empty() {
+            if !projection_pb.keys.is_empty() {
+                return Err("projection has both keys and an expression".into());
+            }
+            let expr = parse(&projection_pb.expression)?;
+            let expr = expr.bind(&mut |variable| match variable {
+                Variable::Label(prop) => Ok(prop),
+                Variable::Current => {
+                    Err(ParseError::from("`_` is not a property of the projection"))
+                }
+            })?;
+            Ok(Projection::Expression(expr))
+        } else if projection_pb.keys.is_empty() {
+            Err("keys of projection not found".into())
+        } else {
+            let mut keys = Vec::with_capacity(projection_pb.keys.len());
+            for key in projection_pb.keys {
+                keys.push(Token::from_pb(key)?);
+            }
+            Ok(Projection::Composite(keys))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn key(values: Vec<Option<Object>>) -> CompositeKey {
+        CompositeKey::new(values)
+    }
+
+    #[test]
+    fn composite_key_test() {
+        let marko = key(vec![Some(29.into()), Some("marko".into())]);
+        let josh = key(vec![Some(32.into()), Some("josh".into())]);
+        let lop = key(vec![None, Some("lop".into())]);
+        let ripple = key(vec![None, Some("ripple".into())]);
+        assert_eq!(marko.compare(&josh), Some(Ordering::Less));
+        assert_eq!(josh.compare(&lop), Some(Ordering::Less));
+        assert_eq!(ripple.compare(&lop), Some(Ordering::Greater));
+        assert_eq!(key(vec![None, None]).compare(&key(vec![None, None])), Some(Ordering::Equal));
+
+        // the equal numbers of different types are the same key
+        let int = key(vec![Some(1.into()), None]);
+        let long = key(vec![Some(1i64.into()), None]);
+        assert_eq!(int.compare(&long), Some(Ordering::Equal));
+        assert_eq!(int.hash(), long.hash());
+        assert_ne!(int.hash(), key(vec![None, Some(1.into())]).hash());
+
+        let decoded = CompositeKey::decode(&lop.encode()).unwrap();
+        assert_eq!(decoded.values(), lop.values());
+    }
+}
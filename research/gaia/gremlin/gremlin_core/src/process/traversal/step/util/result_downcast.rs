@@ -1,3 +1,4 @@
+use crate::process::traversal::step::map::ProjectRecord;
 use crate::process::traversal::traverser::{ShadeSync, Traverser};
 use dyn_type::Object;
 use pegasus::api::accum::{Count, ToList};
@@ -79,3 +80,13 @@ pub fn try_downcast_group_count_value(obj: &Object) -> Option<u64> {
         None
     }
 }
+
+/// downcast the record of project(), i.e., Object::DynOwned(ProjectRecord), which is also the
+/// partial record attached to the element by project().by(sub_traversal)
+pub fn try_downcast_project(obj: &Object) -> Option<&ProjectRecord> {
+    if let Object::DynOwned(object) = obj {
+        object.try_downcast_ref::<ProjectRecord>()
+    } else {
+        None
+    }
+}
@@ -21,7 +21,8 @@ use crate::structure::codec::ParseError;
 use crate::structure::{GraphElement, Tag};
 use crate::{DynIter, Element, FromPb};
 use bit_set::BitSet;
-use dyn_type::Object;
+use dyn_type::object::Primitives;
+use dyn_type::{BorrowObject, Object};
 use pegasus::api::function::{FnResult, Partition};
 use pegasus::codec::*;
 use pegasus::Data;
@@ -71,16 +72,49 @@ impl FromPb<Vec<pb::TraverserRequirement>> for Requirement {
 }
 
 #[derive(Clone, Debug)]
-pub enum Traverser {
+enum TraverserKind {
     Path(Path),
     LabeledPath(Path),
     NoPath(GraphElement),
     Object(Object),
 }
 
+#[derive(Clone, Debug)]
+pub struct Traverser {
+    kind: TraverserKind,
+    // the number of loops that the traverser has gone through in repeat(), i.e., loops()
+    loops: u32,
+    // the number of equal traversers this one stands for, which are merged when bulking
+    bulk: u64,
+    // the range of the adjacency of the head left to be expanded by another worker, if any
+    continuation: Option<Continuation>,
+}
+
+/// The range of the edges of the head of a traverser expanded by another worker, as the head is
+/// a vertex of too many edges to be expanded by one worker, see `JobConf::split_degree`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Continuation {
+    /// the index of the first edge of the range
+    pub start: u64,
+    /// the index after the last edge of the range, or `u64::MAX` for all the edges after `start`
+    pub end: u64,
+    /// the index of the worker to expand the range
+    pub worker: u32,
+}
+
+impl From<TraverserKind> for Traverser {
+    fn from(kind: TraverserKind) -> Self {
+        Traverser { kind, loops: 0, bulk: 1, continuation: None }
+    }
+}
+
 impl Traverser {
     pub fn new<E: Into<GraphElement>>(e: E) -> Self {
-        Traverser::NoPath(e.into())
+        TraverserKind::NoPath(e.into()).into()
+    }
+
+    pub fn object(o: Object) -> Self {
+        TraverserKind::Object(o).into()
     }
 
     pub fn with_path<E: Into<GraphElement>>(e: E, tags: &BitSet, requirement: Requirement) -> Self {
@@ -88,46 +122,50 @@ impl Traverser {
             debug!("start a path traverser");
             let mut path = Path::new(e.into(), false);
             path.extend(tags);
-            Traverser::Path(path)
+            TraverserKind::Path(path).into()
         } else {
             debug!("start a label path traverser");
             let mut path = Path::new(e.into(), true);
             path.extend(tags);
-            Traverser::LabeledPath(path)
+            TraverserKind::LabeledPath(path).into()
         }
     }
 
     pub fn get_element(&self) -> Option<&GraphElement> {
-        match self {
-            Traverser::Path(p) | Traverser::LabeledPath(p) => p.head().and_then(|x| x.as_element()),
-            Traverser::NoPath(e) => Some(e),
-            Traverser::Object(_) => None,
+        match &self.kind {
+            TraverserKind::Path(p) | TraverserKind::LabeledPath(p) => {
+                p.head().and_then(|x| x.as_element())
+            }
+            TraverserKind::NoPath(e) => Some(e),
+            TraverserKind::Object(_) => None,
         }
     }
 
     pub fn get_element_mut(&mut self) -> Option<&mut GraphElement> {
-        match self {
-            Traverser::Path(p) | Traverser::LabeledPath(p) => p.head_mut().as_mut_element(),
-            Traverser::NoPath(e) => Some(e),
-            Traverser::Object(_) => None,
+        match &mut self.kind {
+            TraverserKind::Path(p) | TraverserKind::LabeledPath(p) => p.head_mut().as_mut_element(),
+            TraverserKind::NoPath(e) => Some(e),
+            TraverserKind::Object(_) => None,
         }
     }
 
     pub fn get_object(&self) -> Option<&Object> {
-        match self {
-            Traverser::Path(p) | Traverser::LabeledPath(p) => {
+        match &self.kind {
+            TraverserKind::Path(p) | TraverserKind::LabeledPath(p) => {
                 p.head().and_then(|x| x.as_detached())
             }
-            Traverser::NoPath(_) => None,
-            Traverser::Object(o) => Some(o),
+            TraverserKind::NoPath(_) => None,
+            TraverserKind::Object(o) => Some(o),
         }
     }
 
     pub fn get_object_mut(&mut self) -> Option<&mut Object> {
-        match self {
-            Traverser::Path(p) | Traverser::LabeledPath(p) => p.head_mut().as_mut_detached(),
-            Traverser::NoPath(_) => None,
-            Traverser::Object(o) => Some(o),
+        match &mut self.kind {
+            TraverserKind::Path(p) | TraverserKind::LabeledPath(p) => {
+                p.head_mut().as_mut_detached()
+            }
+            TraverserKind::NoPath(_) => None,
+            TraverserKind::Object(o) => Some(o),
         }
     }
 
@@ -140,66 +178,66 @@ impl Traverser {
     }
 
     pub fn split<E: Into<GraphElement>>(&mut self, e: E, tags: &BitSet) {
-        match self {
-            Traverser::Path(p) => {
+        match &mut self.kind {
+            TraverserKind::Path(p) => {
                 p.extend_with(e.into(), tags, false);
             }
-            Traverser::LabeledPath(p) => {
+            TraverserKind::LabeledPath(p) => {
                 p.extend_with(e.into(), tags, true);
             }
-            Traverser::NoPath(ori) => *ori = e.into(),
-            Traverser::Object(_) => unimplemented!(),
+            TraverserKind::NoPath(ori) => *ori = e.into(),
+            TraverserKind::Object(_) => unimplemented!(),
         }
     }
 
     pub fn split_with_value<T: Into<Object>>(&mut self, o: T, tags: &BitSet) {
-        match self {
-            Traverser::Path(p) => {
+        match &mut self.kind {
+            TraverserKind::Path(p) => {
                 p.extend_with(o.into(), tags, false);
             }
-            Traverser::LabeledPath(p) => {
+            TraverserKind::LabeledPath(p) => {
                 p.extend_with(o.into(), tags, true);
             }
-            Traverser::NoPath(_) => *self = Traverser::Object(o.into()),
-            Traverser::Object(ori) => {
+            TraverserKind::NoPath(_) => self.kind = TraverserKind::Object(o.into()),
+            TraverserKind::Object(ori) => {
                 *ori = o.into();
             }
         }
     }
 
     pub fn remove_tags(&mut self, tags: &BitSet) {
-        match self {
-            Traverser::Path(p) => {
+        match &mut self.kind {
+            TraverserKind::Path(p) => {
                 debug!("Remove tags {:?} in Path {:?}, but why?", tags, p);
                 p.remove_tag(tags)
             }
-            Traverser::LabeledPath(p) => p.remove_tag(tags),
-            Traverser::NoPath(e) => {
+            TraverserKind::LabeledPath(p) => p.remove_tag(tags),
+            TraverserKind::NoPath(e) => {
                 debug!("Try remove tags {:?} in NoPath {:?}, but will not", tags, e)
             }
-            Traverser::Object(o) => {
+            TraverserKind::Object(o) => {
                 debug!("Try remove tags {:?} in Unknown {:?}, but will not", tags, o)
             }
         }
     }
 
     pub fn add_tags(&mut self, tags: &BitSet) {
-        match self {
-            Traverser::Path(p) | Traverser::LabeledPath(p) => p.extend(tags),
+        match &mut self.kind {
+            TraverserKind::Path(p) | TraverserKind::LabeledPath(p) => p.extend(tags),
             _ => (),
         }
     }
 
     pub fn is_simple(&self) -> bool {
-        match self {
-            Traverser::Path(p) => p.is_simple(),
+        match &self.kind {
+            TraverserKind::Path(p) => p.is_simple(),
             _ => true,
         }
     }
 
     pub fn select(&self, tag: &Tag) -> Option<&PathItem> {
-        match self {
-            Traverser::Path(p) | Traverser::LabeledPath(p) => p.select(tag),
+        match &self.kind {
+            TraverserKind::Path(p) | TraverserKind::LabeledPath(p) => p.select(tag),
             _ => None,
         }
     }
@@ -217,10 +255,8 @@ impl Traverser {
     }
 
     pub fn select_pop(&self, pop: Pop, tag: &Tag) -> Option<&PathItem> {
-        match self {
-            Traverser::Path(p) | Traverser::LabeledPath(p) => match pop {
-                _ => p.select(tag),
-            },
+        match &self.kind {
+            TraverserKind::Path(p) | TraverserKind::LabeledPath(p) => p.select(tag),
             _ => None,
         }
     }
@@ -244,24 +280,24 @@ impl Traverser {
     }
 
     pub fn has_cyclic_path(&self) -> bool {
-        match self {
-            Traverser::Path(p) => !p.is_simple(),
+        match &self.kind {
+            TraverserKind::Path(p) => !p.is_simple(),
             _ => false,
         }
     }
 
     pub fn take_path(self) -> ResultPath {
-        match self {
-            Traverser::Path(p) | Traverser::LabeledPath(p) => p.finalize(),
-            Traverser::NoPath(e) => ResultPath::new(vec![PathItem::OnGraph(e)]),
-            Traverser::Object(e) => ResultPath::new(vec![PathItem::Detached(e)]),
+        match self.kind {
+            TraverserKind::Path(p) | TraverserKind::LabeledPath(p) => p.finalize(),
+            TraverserKind::NoPath(e) => ResultPath::new(vec![PathItem::OnGraph(e)]),
+            TraverserKind::Object(e) => ResultPath::new(vec![PathItem::Detached(e)]),
         }
     }
 
     pub fn get_path_len(&self) -> usize {
-        match self {
-            Traverser::Path(p) => p.length(),
-            Traverser::LabeledPath(p) => {
+        match &self.kind {
+            TraverserKind::Path(p) => p.length(),
+            TraverserKind::LabeledPath(p) => {
                 debug!("May not be right, since this is label path length rather than path");
                 p.length()
             }
@@ -270,50 +306,111 @@ impl Traverser {
     }
 
     pub fn transform(self, requirement: Requirement) -> Traverser {
-        match self {
-            Traverser::Path(p) => {
+        let kind = match self.kind {
+            TraverserKind::Path(p) => {
                 if requirement.contains(Requirement::PATH) {
-                    Traverser::Path(p)
+                    TraverserKind::Path(p)
                 } else if requirement.contains(Requirement::LABELED_PATH) {
-                    Traverser::LabeledPath(p)
+                    TraverserKind::LabeledPath(p)
                 } else {
                     // Assume it's object for now
                     match p.head() {
-                        Some(PathItem::OnGraph(e)) => Traverser::NoPath(e.clone()),
-                        Some(PathItem::Detached(o)) => Traverser::Object(o.clone()),
+                        Some(PathItem::OnGraph(e)) => TraverserKind::NoPath(e.clone()),
+                        Some(PathItem::Detached(o)) => TraverserKind::Object(o.clone()),
                         // TODO(bingqing) unimplemented!()
                         Some(PathItem::Empty) => unimplemented!(),
                         None => unimplemented!(),
                     }
                 }
             }
-            Traverser::LabeledPath(_) => unimplemented!(),
-            Traverser::NoPath(_) => unimplemented!(),
-            Traverser::Object(_) => unimplemented!(),
+            TraverserKind::LabeledPath(_) => unimplemented!(),
+            TraverserKind::NoPath(_) => unimplemented!(),
+            TraverserKind::Object(_) => unimplemented!(),
+        };
+        Traverser { kind, loops: self.loops, bulk: self.bulk, continuation: self.continuation }
+    }
+
+    /// The number of loops that the traverser has gone through in current repeat()
+    pub fn get_loops(&self) -> u32 {
+        self.loops
+    }
+
+    /// Count one more loop at the end of each iteration of repeat()
+    pub fn incr_loops(&mut self) {
+        self.loops += 1;
+    }
+
+    /// Reset the loops before entering repeat()
+    pub fn reset_loops(&mut self) {
+        self.loops = 0;
+    }
+
+    /// The number of equal traversers this one stands for
+    pub fn get_bulk(&self) -> u64 {
+        self.bulk
+    }
+
+    pub fn set_bulk(&mut self, bulk: u64) {
+        self.bulk = bulk;
+    }
+
+    /// The range of the edges of the head this traverser is left to expand, if it is a
+    /// continuation of the expansion of a vertex of too many edges
+    pub fn get_continuation(&self) -> Option<&Continuation> {
+        self.continuation.as_ref()
+    }
+
+    /// Turn the traverser into a continuation expanding the range of the edges of its head
+    pub fn set_continuation(&mut self, continuation: Continuation) {
+        self.continuation = Some(continuation);
+    }
+
+    /// Take the range of the continuation away, which turns it back into a usual traverser
+    pub fn take_continuation(&mut self) -> Option<Continuation> {
+        self.continuation.take()
+    }
+
+    /// Whether the traverser tracks its path, which must not be merged with any other one, as
+    /// they may have reached the same head by different paths
+    fn is_path_tracking(&self) -> bool {
+        match &self.kind {
+            TraverserKind::Path(_) | TraverserKind::LabeledPath(_) => true,
+            TraverserKind::NoPath(_) | TraverserKind::Object(_) => false,
         }
     }
 }
 
 impl Encode for Traverser {
     fn write_to<W: WriteExt>(&self, writer: &mut W) -> std::io::Result<()> {
-        match self {
-            Traverser::Path(p) => {
+        match &self.kind {
+            TraverserKind::Path(p) => {
                 writer.write_u8(0)?;
                 p.write_to(writer)?;
             }
-            Traverser::NoPath(element) => {
+            TraverserKind::NoPath(element) => {
                 writer.write_u8(1)?;
                 element.write_to(writer)?;
             }
-            Traverser::Object(object) => {
+            TraverserKind::Object(object) => {
                 writer.write_u8(2)?;
                 object.write_to(writer)?;
             }
-            Traverser::LabeledPath(p) => {
+            TraverserKind::LabeledPath(p) => {
                 writer.write_u8(3)?;
                 p.write_to(writer)?;
             }
         }
+        writer.write_u32(self.loops)?;
+        writer.write_u64(self.bulk)?;
+        match &self.continuation {
+            Some(continuation) => {
+                writer.write_u8(1)?;
+                writer.write_u64(continuation.start)?;
+                writer.write_u64(continuation.end)?;
+                writer.write_u32(continuation.worker)?;
+            }
+            None => writer.write_u8(0)?,
+        }
         Ok(())
     }
 }
@@ -321,25 +418,36 @@ impl Encode for Traverser {
 impl Decode for Traverser {
     fn read_from<R: ReadExt>(reader: &mut R) -> std::io::Result<Self> {
         let e = reader.read_u8()?;
-        match e {
+        let kind = match e {
             0 => {
                 let p = <Path>::read_from(reader)?;
-                Ok(Traverser::Path(p))
+                TraverserKind::Path(p)
             }
             1 => {
                 let element = <GraphElement>::read_from(reader)?;
-                Ok(Traverser::NoPath(element))
+                TraverserKind::NoPath(element)
             }
             2 => {
                 let object = <Object>::read_from(reader)?;
-                Ok(Traverser::Object(object))
+                TraverserKind::Object(object)
             }
             3 => {
                 let p = <Path>::read_from(reader)?;
-                Ok(Traverser::LabeledPath(p))
+                TraverserKind::LabeledPath(p)
             }
-            _ => Err(io::Error::new(io::ErrorKind::Other, "unreachable")),
-        }
+            _ => return Err(io::Error::other("unreachable")),
+        };
+        let loops = reader.read_u32()?;
+        let bulk = reader.read_u64()?;
+        let continuation = if reader.read_u8()? == 0 {
+            None
+        } else {
+            let start = reader.read_u64()?;
+            let end = reader.read_u64()?;
+            let worker = reader.read_u32()?;
+            Some(Continuation { start, end, worker })
+        };
+        Ok(Traverser { kind, loops, bulk, continuation })
     }
 }
 
@@ -375,26 +483,26 @@ impl PartialEq for Traverser {
             }
         };
 
-        match (self, other) {
+        match (&self.kind, &other.kind) {
             // Path compare with Path
-            (Traverser::Path(p1), Traverser::Path(p2))
-            | (Traverser::Path(p1), Traverser::LabeledPath(p2))
-            | (Traverser::LabeledPath(p1), Traverser::Path(p2))
-            | (Traverser::LabeledPath(p1), Traverser::LabeledPath(p2)) => p1.is_head_eq(p2),
+            (TraverserKind::Path(p1), TraverserKind::Path(p2))
+            | (TraverserKind::Path(p1), TraverserKind::LabeledPath(p2))
+            | (TraverserKind::LabeledPath(p1), TraverserKind::Path(p2))
+            | (TraverserKind::LabeledPath(p1), TraverserKind::LabeledPath(p2)) => p1.is_head_eq(p2),
             // Path compare with NoPath, namely GraphElement
-            (Traverser::Path(p), Traverser::NoPath(e))
-            | (Traverser::LabeledPath(p), Traverser::NoPath(e))
-            | (Traverser::NoPath(e), Traverser::Path(p))
-            | (Traverser::NoPath(e), Traverser::LabeledPath(p)) => _is_path_eq_elem(p, e),
+            (TraverserKind::Path(p), TraverserKind::NoPath(e))
+            | (TraverserKind::LabeledPath(p), TraverserKind::NoPath(e))
+            | (TraverserKind::NoPath(e), TraverserKind::Path(p))
+            | (TraverserKind::NoPath(e), TraverserKind::LabeledPath(p)) => _is_path_eq_elem(p, e),
             // Path compare with Object
-            (Traverser::Path(p), Traverser::Object(o))
-            | (Traverser::LabeledPath(p), Traverser::Object(o))
-            | (Traverser::Object(o), Traverser::Path(p))
-            | (Traverser::Object(o), Traverser::LabeledPath(p)) => _is_path_eq_obj(p, o),
+            (TraverserKind::Path(p), TraverserKind::Object(o))
+            | (TraverserKind::LabeledPath(p), TraverserKind::Object(o))
+            | (TraverserKind::Object(o), TraverserKind::Path(p))
+            | (TraverserKind::Object(o), TraverserKind::LabeledPath(p)) => _is_path_eq_obj(p, o),
             // GraphElement compare with GraphElement
-            (Traverser::NoPath(e1), Traverser::NoPath(e2)) => e1 == e2,
+            (TraverserKind::NoPath(e1), TraverserKind::NoPath(e2)) => e1 == e2,
             // Object compare with Object
-            (Traverser::Object(o1), Traverser::Object(o2)) => o1 == o2,
+            (TraverserKind::Object(o1), TraverserKind::Object(o2)) => o1 == o2,
             // `false` for all other cases
             (_, _) => false,
         }
@@ -477,8 +585,8 @@ impl<E: Into<GraphElement>> Iterator for TraverserSplitIter<E> {
 
 impl Hash for Traverser {
     fn hash<H: Hasher>(&self, mut state: &mut H) {
-        match self {
-            Traverser::Path(p) | Traverser::LabeledPath(p) => {
+        match &self.kind {
+            TraverserKind::Path(p) | TraverserKind::LabeledPath(p) => {
                 let head = p.head();
                 match head {
                     Some(PathItem::OnGraph(e)) => e.id().hash(&mut state),
@@ -489,24 +597,121 @@ impl Hash for Traverser {
                     None => "~NONE".hash(&mut state),
                 }
             }
-            Traverser::NoPath(e) => e.id().hash(&mut state),
-            Traverser::Object(o) => o.hash(&mut state),
+            TraverserKind::NoPath(e) => e.id().hash(&mut state),
+            TraverserKind::Object(o) => o.hash(&mut state),
+        }
+    }
+}
+
+impl Traverser {
+    /// The stable hash of the head of the traverser, following the same tokens as its `Hash`,
+    /// which is identical across processes and platforms, see `Object::stable_hash()`;
+    pub fn stable_hash(&self) -> u64 {
+        let head = match &self.kind {
+            TraverserKind::Path(p) | TraverserKind::LabeledPath(p) => p.head(),
+            TraverserKind::NoPath(e) => return Object::from(e.id()).stable_hash(),
+            TraverserKind::Object(o) => return o.stable_hash(),
+        };
+        match head {
+            Some(PathItem::OnGraph(e)) => Object::from(e.id()).stable_hash(),
+            Some(PathItem::Detached(o)) => o.stable_hash(),
+            Some(PathItem::Empty) => BorrowObject::String("").stable_hash(),
+            None => BorrowObject::String("~NONE").stable_hash(),
         }
     }
 }
 
 impl Partition for Traverser {
+    /// Route by the stable hash of the head, so that the traversers of the same key meet in the
+    /// same worker no matter which process emits them;
     fn get_partition(&self) -> FnResult<u64> {
-        let mut state = DefaultHasher::new();
-        self.hash(&mut state);
-        Ok(state.finish())
+        Ok(self.stable_hash())
     }
 }
 
-impl AnyData for Traverser {}
+impl AnyData for Traverser {
+    fn get_bulk(&self) -> u64 {
+        self.bulk
+    }
+
+    fn set_bulk(&mut self, bulk: u64) {
+        self.bulk = bulk;
+    }
+
+    fn bulk_key(&self) -> Option<u64> {
+        if self.is_path_tracking() || self.continuation.is_some() {
+            None
+        } else {
+            let mut state = DefaultHasher::new();
+            self.hash(&mut state);
+            Some(state.finish())
+        }
+    }
+
+    fn try_merge(&mut self, other: Self) -> Option<Self> {
+        if !self.is_path_tracking()
+            && !other.is_path_tracking()
+            && self.loops == other.loops
+            && self.continuation.is_none()
+            && other.continuation.is_none()
+            && *self == other
+        {
+            self.bulk += other.bulk;
+            None
+        } else {
+            Some(other)
+        }
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        match self.get_object()?.as_primitive().ok()? {
+            Primitives::Byte(v) => Some(v as f64),
+            Primitives::Integer(v) => Some(v as f64),
+            Primitives::Long(v) => Some(v as f64),
+            Primitives::Float(v) => Some(v),
+        }
+    }
+
+    fn continuation_route(&self) -> Option<u64> {
+        self.continuation.map(|continuation| continuation.worker as u64)
+    }
+}
 impl Traverser {
     pub fn with<T: Data + Eq>(raw: T) -> Self {
         let v = ShadeSync { inner: raw };
-        Traverser::Object(Object::DynOwned(Box::new(v)))
+        Traverser::object(Object::DynOwned(Box::new(v)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn stable_partition_test() {
+        // the golden values of `Object::stable_hash()`, which must never change
+        assert_eq!(Traverser::object(1.into()).get_partition().unwrap(), 0x7b2075f90a671183);
+        assert_eq!(Traverser::object(1.0.into()).get_partition().unwrap(), 0x7b2075f90a671183);
+        let marko = Traverser::object("marko".into());
+        assert_eq!(marko.get_partition().unwrap(), 0x717c689023339e7f);
+    }
+
+    #[test]
+    fn continuation_codec_test() {
+        let mut traverser = Traverser::object(1.into());
+        traverser.set_bulk(2);
+        assert_eq!(traverser.continuation_route(), None);
+        let continuation = Continuation { start: 10, end: u64::MAX, worker: 3 };
+        traverser.set_continuation(continuation);
+        assert_eq!(traverser.continuation_route(), Some(3));
+        // a continuation stands for its range only, which is never merged with the others
+        assert_eq!(traverser.bulk_key(), None);
+        assert!(traverser.clone().try_merge(traverser.clone()).is_some());
+        let mut bytes = vec![];
+        traverser.write_to(&mut bytes).unwrap();
+        let mut decoded = Traverser::read_from(&mut bytes.as_slice()).unwrap();
+        assert_eq!(decoded.get_bulk(), 2);
+        assert_eq!(decoded.take_continuation(), Some(continuation));
+        assert_eq!(decoded.continuation_route(), None);
     }
 }
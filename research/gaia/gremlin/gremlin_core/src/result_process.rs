@@ -13,80 +13,293 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
+//! Encode the results of the jobs into protobuf, where the vertices and edges are given by the
+//! `Detach` policy of the sink of the job. The properties of the vertices are fetched from the
+//! graph for a whole batch of results at once, as the vertices may have lost them on being
+//! exchanged across the workers.
+
 use crate::generated::common as common_pb;
+use crate::generated::gremlin as pb;
 use crate::generated::protobuf as result_pb;
 use crate::generated::protobuf::OneTagValue;
 use crate::process::traversal::path::{PathItem, ResultPath};
+use crate::process::traversal::step::projection::CompositeKey;
 use crate::process::traversal::step::result_downcast::{
     try_downcast_count, try_downcast_list, try_downcast_pair,
 };
-use crate::process::traversal::step::ResultProperty;
+use crate::process::traversal::step::{ProjectRecord, ResultProperty};
 use crate::process::traversal::traverser::Traverser;
-use crate::structure::{Edge, GraphElement, Label, Vertex, VertexOrEdge};
+use crate::structure::codec::ParseError;
+use crate::structure::{
+    get_graph, Details, DynDetails, Edge, Element, GraphElement, Label, QueryParams, Vertex,
+    VertexOrEdge,
+};
+use crate::{FromPb, GraphProxy, ID};
 use dyn_type::object::{Object, Primitives};
+use dyn_type::CustomType;
+use prost::Message;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// What the results give of the vertices and edges, as decoded from the `DetachPolicy` of the sink
+#[derive(Clone, Debug, PartialEq, Default)]
+pub enum Detach {
+    /// The ids only
+    Id,
+    /// The ids and labels, as the results are encoded by default
+    #[default]
+    Reference,
+    /// The ids, labels and all properties
+    Full,
+    /// The ids, labels and the properties of the given keys only, in the order of the keys
+    Custom(Vec<String>),
+}
+
+impl Detach {
+    /// Decode the policy from the resource of the sink, where an empty resource is `Reference`
+    pub fn from_resource(resource: &[u8]) -> Result<Self, ParseError> {
+        if resource.is_empty() {
+            Ok(Detach::Reference)
+        } else {
+            Detach::from_pb(pb::DetachPolicy::decode(resource)?)
+        }
+    }
+
+    pub fn to_pb(&self) -> pb::DetachPolicy {
+        let (mode, keys) = match self {
+            Detach::Id => (pb::detach_policy::Mode::Id, vec![]),
+            Detach::Reference => (pb::detach_policy::Mode::Reference, vec![]),
+            Detach::Full => (pb::detach_policy::Mode::Full, vec![]),
+            Detach::Custom(keys) => (pb::detach_policy::Mode::Custom, keys.clone()),
+        };
+        pb::DetachPolicy { mode: mode as i32, keys }
+    }
+
+    // the keys of the properties to fetch, where an empty list is for all properties
+    fn fetched_keys(&self) -> Option<Vec<String>> {
+        match self {
+            Detach::Full => Some(vec![]),
+            Detach::Custom(keys) if !keys.is_empty() => Some(keys.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl FromPb<pb::DetachPolicy> for Detach {
+    fn from_pb(policy: pb::DetachPolicy) -> Result<Self, ParseError> {
+        match pb::detach_policy::Mode::from_i32(policy.mode) {
+            Some(pb::detach_policy::Mode::Id) => Ok(Detach::Id),
+            Some(pb::detach_policy::Mode::Reference) => Ok(Detach::Reference),
+            Some(pb::detach_policy::Mode::Full) => Ok(Detach::Full),
+            Some(pb::detach_policy::Mode::Custom) => Ok(Detach::Custom(policy.keys)),
+            None => Err(ParseError::OtherErr(format!("invalid detach mode {}", policy.mode))),
+        }
+    }
+}
+
+static DETACH_FETCHES: AtomicU64 = AtomicU64::new(0);
+
+/// The times the properties of the vertices in the results are fetched from the graph, once for
+/// each batch of results of the `Full` or `Custom` policy
+pub fn get_detach_fetch_count() -> u64 {
+    DETACH_FETCHES.load(Ordering::SeqCst)
+}
+
+/// The policy to encode a batch of results by, with the properties of the vertices in the batch
+struct Detached {
+    policy: Detach,
+    vertices: HashMap<ID, Vec<result_pb::Property>>,
+}
 
-fn vertex_to_pb(v: &Vertex) -> result_pb::Vertex {
+impl Detached {
+    fn reference() -> Self {
+        Detached { policy: Detach::Reference, vertices: HashMap::new() }
+    }
+
+    // fetch the properties of all the vertices in the batch by a single read of the graph
+    fn fetch(policy: &Detach, data: &[Traverser], graph: Option<Arc<dyn GraphProxy>>) -> Self {
+        let mut detached = Detached { policy: policy.clone(), vertices: HashMap::new() };
+        let keys = match policy.fetched_keys() {
+            Some(keys) => keys,
+            None => return detached,
+        };
+        let mut ids = vec![];
+        for t in data {
+            collect_traverser_ids(t, &mut ids);
+        }
+        ids.sort();
+        ids.dedup();
+        if ids.is_empty() {
+            return detached;
+        }
+        let graph = match graph {
+            Some(graph) => graph,
+            None => {
+                warn!("no graph to fetch the properties of {} vertices", ids.len());
+                return detached;
+            }
+        };
+        let mut params = QueryParams::new();
+        params.props = Some(keys);
+        DETACH_FETCHES.fetch_add(1, Ordering::SeqCst);
+        match graph.get_vertex(&ids, &params) {
+            Ok(vertices) => {
+                for v in vertices {
+                    let properties = properties_to_pb(v.details(), policy);
+                    detached.vertices.insert(v.id, properties);
+                }
+            }
+            Err(err) => warn!("fail to fetch the properties of {} vertices: {}", ids.len(), err),
+        }
+        detached
+    }
+
+    fn label(&self, label: Option<&Label>) -> String {
+        match (label, &self.policy) {
+            (_, Detach::Id) | (None, _) => String::new(),
+            (Some(Label::Str(s)), _) => s.clone(),
+            // TODO(longbin) should turn back to its actual string
+            (Some(Label::Id(id)), _) => id.to_string(),
+        }
+    }
+
+    // the fetched properties, or those the vertex holds if not fetched, e.g. of a vertex not found
+    fn vertex_properties(&self, v: &Vertex) -> Vec<result_pb::Property> {
+        match self.vertices.get(&v.id) {
+            Some(properties) => properties.clone(),
+            None => properties_to_pb(v.details(), &self.policy),
+        }
+    }
+}
+
+// the properties by the policy, where all properties are sorted by their keys
+fn properties_to_pb(details: &DynDetails, policy: &Detach) -> Vec<result_pb::Property> {
+    let to_pb = |key: String, value: &Object| result_pb::Property {
+        key,
+        value: Some(object_to_pb_value(value)),
+    };
+    match policy {
+        Detach::Full => {
+            let mut properties: Vec<_> =
+                details.get_all_properties().unwrap_or_default().into_iter().collect();
+            properties.sort_by(|a, b| a.0.cmp(&b.0));
+            properties.into_iter().map(|(key, value)| to_pb(key, &value)).collect()
+        }
+        Detach::Custom(keys) => keys
+            .iter()
+            .filter_map(|key| {
+                let value = details.get_property(key)?.try_to_owned()?;
+                Some(to_pb(key.clone(), &value))
+            })
+            .collect(),
+        Detach::Id | Detach::Reference => vec![],
+    }
+}
+
+fn collect_element_id(g: &GraphElement, ids: &mut Vec<ID>) {
+    if let Some(v) = g.as_vertex() {
+        ids.push(v.id);
+    }
+}
+
+fn collect_traverser_ids(t: &Traverser, ids: &mut Vec<ID>) {
+    if let Some(e) = t.get_element() {
+        collect_element_id(e, ids);
+    } else if let Some(o) = t.get_object() {
+        collect_object_ids(o, ids);
+    }
+}
+
+// the ids of the vertices anywhere in the (nested) structure, as encoded by `object_to_pb_item`
+fn collect_object_ids(o: &Object, ids: &mut Vec<ID>) {
+    let x = match o {
+        Object::DynOwned(x) => x,
+        _ => return,
+    };
+    if let Some(path) = x.try_downcast_ref::<ResultPath>() {
+        for item in path.iter() {
+            match item {
+                PathItem::OnGraph(e) => collect_element_id(e, ids),
+                PathItem::Detached(o) => collect_object_ids(o, ids),
+                PathItem::Empty => {}
+            }
+        }
+    } else if let Some(result_prop) = x.try_downcast_ref::<ResultProperty>() {
+        for (_, value) in result_prop.tag_entries.iter() {
+            if let Some(e) = value.graph_element.as_ref() {
+                collect_element_id(e, ids);
+            }
+        }
+    } else if let Some(record) = x.try_downcast_ref::<ProjectRecord>() {
+        for (_, value) in record.columns() {
+            if let Some(value) = value.as_ref() {
+                collect_object_ids(value, ids);
+            }
+        }
+    } else if let Some((k, v)) = try_downcast_pair(o) {
+        collect_traverser_ids(k, ids);
+        collect_traverser_ids(v, ids);
+    } else if let Some(list) = try_downcast_list(o) {
+        for t in list.iter() {
+            collect_traverser_ids(t, ids);
+        }
+    }
+}
+
+fn vertex_to_pb(v: &Vertex, d: &Detached) -> result_pb::Vertex {
     result_pb::Vertex {
         id: v.id as i64,
-        label: if let Some(label) = v.label.clone() {
-            match label {
-                Label::Str(s) => s,
-                // TODO(longbin) should turn back to its actual string
-                Label::Id(id) => id.to_string(),
-            }
-        } else {
-            String::new()
-        },
-        properties: vec![],
+        label: d.label(v.label.as_ref()),
+        properties: d.vertex_properties(v),
     }
 }
-fn edge_to_pb(e: &Edge) -> result_pb::Edge {
+
+fn edge_to_pb(e: &Edge, d: &Detached) -> result_pb::Edge {
     result_pb::Edge {
         id: e.id as i64,
-        label: if let Some(label) = e.label.clone() {
-            match label {
-                Label::Str(s) => s,
-                // TODO(longbin) should turn back to its actual string
-                Label::Id(id) => id.to_string(),
-            }
-        } else {
-            String::new()
-        },
+        label: d.label(e.label.as_ref()),
         src_id: e.src_id as i64,
         src_label: "".to_string(),
         dst_id: e.dst_id as i64,
         dst_label: "".to_string(),
-        properties: vec![],
+        properties: properties_to_pb(e.details(), &d.policy),
     }
 }
 
-fn element_to_pb(g: &GraphElement) -> result_pb::GraphElement {
+fn element_to_pb(g: &GraphElement, d: &Detached) -> result_pb::GraphElement {
     let inner = match g.get() {
-        VertexOrEdge::V(v) => result_pb::graph_element::Inner::Vertex(vertex_to_pb(v)),
-        VertexOrEdge::E(e) => result_pb::graph_element::Inner::Edge(edge_to_pb(e)),
+        VertexOrEdge::V(v) => result_pb::graph_element::Inner::Vertex(vertex_to_pb(v, d)),
+        VertexOrEdge::E(e) => result_pb::graph_element::Inner::Edge(edge_to_pb(e, d)),
     };
     result_pb::GraphElement { inner: Some(inner) }
 }
 
-fn path_to_pb(path: &ResultPath) -> result_pb::Path {
+fn path_to_pb(path: &ResultPath, d: &Detached) -> result_pb::Path {
     let mut path_pb = vec![];
     for item in path.iter() {
         match item {
             PathItem::OnGraph(graph_element) => {
-                path_pb.push(element_to_pb(graph_element));
+                path_pb.push(element_to_pb(graph_element, d));
             }
-            PathItem::Detached(_) => unimplemented!(),
+            PathItem::Detached(_) => unreachable!("detached path is encoded as a list"),
             PathItem::Empty => {}
         }
     }
     result_pb::Path { path: path_pb }
 }
 
-fn property_to_pb(result_property: &ResultProperty) -> result_pb::TagEntries {
+/// A path is encoded as `Path` only if all its items are graph elements, e.g., path().by("name")
+/// is encoded as a list of values instead
+fn is_graph_path(path: &ResultPath) -> bool {
+    path.iter().all(|item| !matches!(item, PathItem::Detached(_)))
+}
+
+fn property_to_pb(result_property: &ResultProperty, d: &Detached) -> result_pb::TagEntries {
     let mut tag_entries = vec![];
     for (tag, one_tag_value) in result_property.tag_entries.iter() {
         let one_tag_value_pb = if let Some(element) = one_tag_value.graph_element.as_ref() {
-            let pb_element = element_to_pb(element);
+            let pb_element = element_to_pb(element, d);
             OneTagValue { item: Some(result_pb::one_tag_value::Item::Element(pb_element)) }
         } else if let Some(value) = one_tag_value.value.as_ref() {
             let pb_value = object_to_pb_value(value);
@@ -113,21 +326,20 @@ fn property_to_pb(result_property: &ResultProperty) -> result_pb::TagEntries {
     result_pb::TagEntries { entries: tag_entries }
 }
 
-fn object_to_pb_value(value: &Object) -> common_pb::Value {
+pub(crate) fn object_to_pb_value(value: &Object) -> common_pb::Value {
     let item = match value {
-        Object::Primitive(v) => {
-            match v {
-                Primitives::Byte(_) => {
-                    // TODO: check
-                    unimplemented!()
-                }
-                Primitives::Integer(v) => common_pb::value::Item::I32(*v),
-                Primitives::Long(v) => common_pb::value::Item::I64(*v),
-                Primitives::Float(v) => common_pb::value::Item::F64(*v),
-            }
-        }
+        Object::Primitive(v) => match v {
+            Primitives::Byte(v) => common_pb::value::Item::I32(*v as i32),
+            Primitives::Integer(v) => common_pb::value::Item::I32(*v),
+            Primitives::Long(v) => common_pb::value::Item::I64(*v),
+            Primitives::Float(v) => common_pb::value::Item::F64(*v),
+        },
         Object::String(s) => common_pb::value::Item::Str(s.clone()),
         Object::Blob(b) => common_pb::value::Item::Blob(b.to_vec()),
+        Object::Custom(v) => common_pb::value::Item::Custom(common_pb::CustomValue {
+            tag: v.tag().to_owned(),
+            bytes: v.bytes().to_vec(),
+        }),
         Object::DynOwned(_u) => {
             if let Some(count_val) = try_downcast_count(value) {
                 common_pb::value::Item::I64(count_val as i64)
@@ -140,9 +352,148 @@ fn object_to_pb_value(value: &Object) -> common_pb::Value {
     common_pb::Value { item: Some(item) }
 }
 
+fn new_item(inner: result_pb::result_item::Inner) -> result_pb::ResultItem {
+    result_pb::ResultItem { inner: Some(inner), bulk: 1 }
+}
+
+fn tag_value_to_pb_item(tag_value: &OneTagValue) -> result_pb::ResultItem {
+    match tag_value.item.as_ref() {
+        Some(result_pb::one_tag_value::Item::Element(e)) => {
+            new_item(result_pb::result_item::Inner::Element(e.clone()))
+        }
+        Some(result_pb::one_tag_value::Item::Value(v)) => {
+            new_item(result_pb::result_item::Inner::Value(v.clone()))
+        }
+        Some(result_pb::one_tag_value::Item::Properties(props)) => {
+            let entry = props
+                .property
+                .iter()
+                .map(|p| result_pb::ResultMapEntry {
+                    key: Some(new_item(result_pb::result_item::Inner::Value(common_pb::Value {
+                        item: Some(common_pb::value::Item::Str(p.key.clone())),
+                    }))),
+                    value: p
+                        .value
+                        .clone()
+                        .map(|v| new_item(result_pb::result_item::Inner::Value(v))),
+                })
+                .collect();
+            new_item(result_pb::result_item::Inner::Map(result_pb::ResultMap { entry }))
+        }
+        None => result_pb::ResultItem { inner: None, bulk: 1 },
+    }
+}
+
+/// Encode an object of any (nested) structure, e.g., a path, a list of fold(), or a pair of
+/// group(), where the values of lists and maps are encoded recursively.
+fn object_to_pb_item(o: &Object, d: &Detached) -> result_pb::ResultItem {
+    match o {
+        // the values of a composite key of by() in order, where a missing value has no value
+        Object::Custom(v) if v.tag() == CompositeKey::TAG => match v.to_value::<CompositeKey>() {
+            Ok(key) => {
+                let item = key
+                    .values()
+                    .iter()
+                    .map(|value| match value {
+                        Some(value) => object_to_pb_item(value, d),
+                        None => result_pb::ResultItem { inner: None, bulk: 1 },
+                    })
+                    .collect();
+                new_item(result_pb::result_item::Inner::List(result_pb::ResultList { item }))
+            }
+            Err(_) => new_item(result_pb::result_item::Inner::Value(object_to_pb_value(o))),
+        },
+        Object::Primitive(_) | Object::String(_) | Object::Blob(_) | Object::Custom(_) => {
+            new_item(result_pb::result_item::Inner::Value(object_to_pb_value(o)))
+        }
+        Object::DynOwned(x) => {
+            if let Some(p) = x.try_downcast_ref::<ResultPath>() {
+                if is_graph_path(p) {
+                    new_item(result_pb::result_item::Inner::Path(path_to_pb(p, d)))
+                } else {
+                    let mut items = vec![];
+                    for path_item in p.iter() {
+                        match path_item {
+                            PathItem::OnGraph(e) => items.push(new_item(
+                                result_pb::result_item::Inner::Element(element_to_pb(e, d)),
+                            )),
+                            PathItem::Detached(o) => items.push(object_to_pb_item(o, d)),
+                            PathItem::Empty => {}
+                        }
+                    }
+                    new_item(result_pb::result_item::Inner::List(result_pb::ResultList {
+                        item: items,
+                    }))
+                }
+            } else if let Some(result_prop) = x.try_downcast_ref::<ResultProperty>() {
+                // a map from the tags to their values, e.g., select("a", "b")
+                let entry = property_to_pb(result_prop, d)
+                    .entries
+                    .iter()
+                    .map(|e| result_pb::ResultMapEntry {
+                        key: Some(new_item(result_pb::result_item::Inner::Value(
+                            common_pb::Value { item: Some(common_pb::value::Item::I32(e.tag)) },
+                        ))),
+                        value: e.value.as_ref().map(tag_value_to_pb_item),
+                    })
+                    .collect();
+                new_item(result_pb::result_item::Inner::Map(result_pb::ResultMap { entry }))
+            } else if let Some(record) = x.try_downcast_ref::<ProjectRecord>() {
+                // the columns of project() in the declared order, where a null column has no value
+                let entry = record
+                    .columns()
+                    .iter()
+                    .map(|(column, value)| result_pb::ResultMapEntry {
+                        key: Some(new_item(result_pb::result_item::Inner::Value(
+                            common_pb::Value {
+                                item: Some(common_pb::value::Item::Str(column.clone())),
+                            },
+                        ))),
+                        value: value.as_ref().map(|value| object_to_pb_item(value, d)),
+                    })
+                    .collect();
+                new_item(result_pb::result_item::Inner::Map(result_pb::ResultMap { entry }))
+            } else if let Some((k, v)) = try_downcast_pair(o) {
+                let entry = result_pb::ResultMapEntry {
+                    key: Some(traverser_to_pb_item(k, d)),
+                    value: Some(traverser_to_pb_item(v, d)),
+                };
+                new_item(result_pb::result_item::Inner::Map(result_pb::ResultMap {
+                    entry: vec![entry],
+                }))
+            } else if let Some(list) = try_downcast_list(o) {
+                let items = list.iter().map(|t| traverser_to_pb_item(t, d)).collect();
+                new_item(result_pb::result_item::Inner::List(result_pb::ResultList { item: items }))
+            } else if let Some(count) = try_downcast_count(o) {
+                new_item(result_pb::result_item::Inner::Value(common_pb::Value {
+                    item: Some(common_pb::value::Item::I64(count as i64)),
+                }))
+            } else {
+                warn!("unsupported object result {:?}", x);
+                result_pb::ResultItem { inner: None, bulk: 1 }
+            }
+        }
+    }
+}
+
+fn traverser_to_pb_item(t: &Traverser, d: &Detached) -> result_pb::ResultItem {
+    if let Some(e) = t.get_element() {
+        new_item(result_pb::result_item::Inner::Element(element_to_pb(e, d)))
+    } else if let Some(o) = t.get_object() {
+        object_to_pb_item(o, d)
+    } else {
+        result_pb::ResultItem { inner: None, bulk: 1 }
+    }
+}
+
+/// Encode a key or value of group() with the ids and labels of the graph elements
 pub fn pair_element_to_pb(t: &Traverser) -> result_pb::PairElement {
+    pair_element_to_pb_with(t, &Detached::reference())
+}
+
+fn pair_element_to_pb_with(t: &Traverser, d: &Detached) -> result_pb::PairElement {
     if let Some(g) = t.get_element() {
-        let graph_element_pb = element_to_pb(g);
+        let graph_element_pb = element_to_pb(g, d);
         result_pb::PairElement {
             inner: Some(result_pb::pair_element::Inner::GraphElement(graph_element_pb)),
         }
@@ -154,7 +505,7 @@ pub fn pair_element_to_pb(t: &Traverser) -> result_pb::PairElement {
             let mut graph_element_array = vec![];
             for traverser in traverser_list {
                 if let Some(graph_element) = traverser.get_element() {
-                    graph_element_array.push(element_to_pb(graph_element));
+                    graph_element_array.push(element_to_pb(graph_element, d));
                 } else {
                     is_element_list = false;
                     break;
@@ -167,8 +518,15 @@ pub fn pair_element_to_pb(t: &Traverser) -> result_pb::PairElement {
                     )),
                 }
             } else {
-                unimplemented!()
+                let item_pb = object_to_pb_item(o, d);
+                result_pb::PairElement {
+                    inner: Some(result_pb::pair_element::Inner::Item(item_pb)),
+                }
             }
+        } else if let (Object::DynOwned(_), None) = (o, try_downcast_count(o)) {
+            // e.g., the nested pairs or paths
+            let item_pb = object_to_pb_item(o, d);
+            result_pb::PairElement { inner: Some(result_pb::pair_element::Inner::Item(item_pb)) }
         } else {
             let object_pb = object_to_pb_value(o);
             result_pb::PairElement { inner: Some(result_pb::pair_element::Inner::Value(object_pb)) }
@@ -178,39 +536,99 @@ pub fn pair_element_to_pb(t: &Traverser) -> result_pb::PairElement {
     }
 }
 
+#[inline]
+fn push_bulk<T: Clone>(encoded: &mut Vec<T>, value: T, bulk: u64) {
+    encoded.extend(std::iter::repeat_n(value, bulk as usize));
+}
+
+/// Encode the results with the ids and labels of the graph elements, i.e. by `Detach::Reference`
 pub fn result_to_pb(data: Vec<Traverser>) -> result_pb::Result {
+    result_to_pb_with(data, &Detach::Reference)
+}
+
+/// Encode the results with the graph elements given by the policy
+pub fn result_to_pb_with(data: Vec<Traverser>, detach: &Detach) -> result_pb::Result {
+    result_to_pb_on(data, detach, false, get_graph())
+}
+
+/// Encode the results as the items with their bulks if `bulked`, e.g. the results consolidated by
+/// the sink, or else as `result_to_pb_with`, which repeats the merged traversers in the legacy
+/// arrays, with the properties fetched from the given graph instead of the graph of current job,
+/// so that the results can be encoded out of the job, e.g. in the service instead of on the workers
+pub fn result_to_pb_on(
+    data: Vec<Traverser>, detach: &Detach, bulked: bool, graph: Option<Arc<dyn GraphProxy>>,
+) -> result_pb::Result {
+    if bulked {
+        bulked_to_pb(data, detach, graph)
+    } else {
+        arrays_to_pb(data, detach, graph)
+    }
+}
+
+fn bulked_to_pb(
+    data: Vec<Traverser>, detach: &Detach, graph: Option<Arc<dyn GraphProxy>>,
+) -> result_pb::Result {
+    if data.is_empty() {
+        return result_pb::Result { inner: None };
+    }
+    let d = Detached::fetch(detach, &data, graph);
+    let item = data
+        .iter()
+        .map(|t| result_pb::ResultItem { bulk: t.get_bulk(), ..traverser_to_pb_item(t, &d) })
+        .collect();
+    let items = result_pb::ResultList { item };
+    result_pb::Result { inner: Some(result_pb::result::Inner::Items(items)) }
+}
+
+fn arrays_to_pb(
+    data: Vec<Traverser>, detach: &Detach, graph: Option<Arc<dyn GraphProxy>>,
+) -> result_pb::Result {
+    let d = Detached::fetch(detach, &data, graph);
     let mut paths_encode = vec![];
     let mut elements_encode = vec![];
     let mut properties_encode = vec![];
     let mut pairs_encode = vec![];
     let mut values_encode = vec![];
+    let mut items_encode = vec![];
     for t in data {
+        // the legacy arrays have no bulk, so the merged traversers are repeated
+        let bulk = t.get_bulk();
         if let Some(e) = t.get_element() {
             info!("element: {:?}", e);
-            elements_encode.push(element_to_pb(e));
+            push_bulk(&mut elements_encode, element_to_pb(e, &d), bulk);
         } else if let Some(o) = t.get_object() {
             match o {
-                Object::Primitive(_) | Object::String(_) | Object::Blob(_) => {
+                Object::Primitive(_) | Object::String(_) | Object::Blob(_) | Object::Custom(_) => {
                     info!("object result {:?}", o);
-                    values_encode.push(object_to_pb_value(o));
+                    push_bulk(&mut values_encode, object_to_pb_value(o), bulk);
                 }
                 Object::DynOwned(x) => {
                     if let Some(p) = x.try_downcast_ref::<ResultPath>() {
                         info!("path: {:?}", p);
-                        paths_encode.push(path_to_pb(p));
+                        if is_graph_path(p) {
+                            push_bulk(&mut paths_encode, path_to_pb(p, &d), bulk);
+                        } else {
+                            items_encode
+                                .push(result_pb::ResultItem { bulk, ..object_to_pb_item(o, &d) });
+                        }
                     } else if let Some(result_prop) = x.try_downcast_ref::<ResultProperty>() {
                         info!("property: {:?}", result_prop);
-                        properties_encode.push(property_to_pb(result_prop));
+                        push_bulk(&mut properties_encode, property_to_pb(result_prop, &d), bulk);
                     } else if let Some(result_pair) = try_downcast_pair(o) {
                         info!("group result {:?}", result_pair);
                         let (k, v) = result_pair;
-                        let key_pb = pair_element_to_pb(&k);
-                        let value_pb = pair_element_to_pb(&v);
+                        let key_pb = pair_element_to_pb_with(k, &d);
+                        let value_pb = pair_element_to_pb_with(v, &d);
                         let map_pair_pb =
                             result_pb::MapPair { first: Some(key_pb), second: Some(value_pb) };
-                        pairs_encode.push(map_pair_pb);
+                        push_bulk(&mut pairs_encode, map_pair_pb, bulk);
+                    } else if let Some(count) = try_downcast_count(o) {
+                        info!("count result {:?}", count);
+                        push_bulk(&mut values_encode, object_to_pb_value(o), bulk);
                     } else {
                         info!("other object result {:?}", x);
+                        items_encode
+                            .push(result_pb::ResultItem { bulk, ..object_to_pb_item(o, &d) });
                     }
                 }
             }
@@ -233,7 +651,262 @@ pub fn result_to_pb(data: Vec<Traverser>) -> result_pb::Result {
     } else if !values_encode.is_empty() {
         let values = result_pb::ValueArray { item: values_encode };
         result_pb::Result { inner: Some(result_pb::result::Inner::ValueList(values)) }
+    } else if !items_encode.is_empty() {
+        let items = result_pb::ResultList { item: items_encode };
+        result_pb::Result { inner: Some(result_pb::result::Inner::Items(items)) }
     } else {
         result_pb::Result { inner: None }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::process::traversal::traverser::Requirement;
+    use crate::structure::{DefaultDetails, ID};
+    use bit_set::BitSet;
+    use pegasus::api::accum::ToList;
+    use prost::Message;
+
+    fn new_vertex(id: ID, label: &str) -> Vertex {
+        let label = Label::Str(label.to_string());
+        Vertex::new(id, Some(label.clone()), DefaultDetails::new(id, label))
+    }
+
+    fn round_trip(data: Vec<Traverser>) -> result_pb::Result {
+        let result_pb = result_to_pb(data);
+        let mut bytes = vec![];
+        result_pb.encode(&mut bytes).expect("encode result failed");
+        let decoded = result_pb::Result::decode(bytes.as_slice()).expect("decode result failed");
+        assert_eq!(decoded, result_pb);
+        decoded
+    }
+
+    fn vertex_id(item: &result_pb::ResultItem) -> i64 {
+        match item.inner.as_ref() {
+            Some(result_pb::result_item::Inner::Element(result_pb::GraphElement {
+                inner: Some(result_pb::graph_element::Inner::Vertex(v)),
+            })) => v.id,
+            _ => panic!("not a vertex {:?}", item),
+        }
+    }
+
+    // group().by().by(fold()), where the keys are vertices, and the values are lists of vertices
+    #[test]
+    fn group_result_round_trip() {
+        let values = vec![
+            Traverser::new(new_vertex(2, "person")),
+            Traverser::new(new_vertex(3, "software")),
+        ];
+        let pair =
+            (Traverser::new(new_vertex(1, "person")), Traverser::with(ToList { inner: values }));
+        let result = round_trip(vec![Traverser::with(pair)]);
+        match result.inner {
+            Some(result_pb::result::Inner::MapResult(map)) => {
+                assert_eq!(map.item.len(), 1);
+                let pair = &map.item[0];
+                match pair.first.as_ref().and_then(|k| k.inner.as_ref()) {
+                    Some(result_pb::pair_element::Inner::GraphElement(e)) => match &e.inner {
+                        Some(result_pb::graph_element::Inner::Vertex(v)) => {
+                            assert_eq!(v.id, 1);
+                            assert_eq!(v.label, "person");
+                        }
+                        _ => panic!("key is not a vertex"),
+                    },
+                    _ => panic!("key is not a graph element"),
+                }
+                match pair.second.as_ref().and_then(|v| v.inner.as_ref()) {
+                    Some(result_pb::pair_element::Inner::GraphElementList(list)) => {
+                        assert_eq!(list.item.len(), 2);
+                    }
+                    _ => panic!("value is not a list of graph elements"),
+                }
+            }
+            _ => panic!("not a map result"),
+        }
+    }
+
+    // project("name", "age").by("name").by("age"), where the age is null
+    #[test]
+    fn project_result_round_trip() {
+        let mut record = ProjectRecord::new();
+        record.set("name", Some("lop".into()));
+        record.set("age", None);
+        let result = round_trip(vec![Traverser::object(Object::DynOwned(Box::new(record)))]);
+        let items = match result.inner {
+            Some(result_pb::result::Inner::Items(items)) => items.item,
+            _ => panic!("not a list of items"),
+        };
+        let entry = match items[0].inner.as_ref() {
+            Some(result_pb::result_item::Inner::Map(map)) => map.entry.clone(),
+            _ => panic!("not a map"),
+        };
+        let str_item = |s: &str| {
+            Some(new_item(result_pb::result_item::Inner::Value(common_pb::Value {
+                item: Some(common_pb::value::Item::Str(s.to_owned())),
+            })))
+        };
+        assert_eq!(entry.len(), 2);
+        assert_eq!(entry[0].key, str_item("name"));
+        assert_eq!(entry[0].value, str_item("lop"));
+        assert_eq!(entry[1].key, str_item("age"));
+        assert_eq!(entry[1].value, None);
+    }
+
+    // group().by("name").by(fold()) of mixed values, e.g., ["marko" -> [v2, 29]]
+    #[test]
+    fn nested_group_result_round_trip() {
+        let values = vec![Traverser::new(new_vertex(2, "person")), Traverser::object(29.into())];
+        let pair = (Traverser::object("marko".into()), Traverser::with(ToList { inner: values }));
+        let result = round_trip(vec![Traverser::with(pair)]);
+        match result.inner {
+            Some(result_pb::result::Inner::MapResult(map)) => {
+                let pair = &map.item[0];
+                match pair.second.as_ref().and_then(|v| v.inner.as_ref()) {
+                    Some(result_pb::pair_element::Inner::Item(item)) => match &item.inner {
+                        Some(result_pb::result_item::Inner::List(list)) => {
+                            assert_eq!(list.item.len(), 2);
+                            assert_eq!(vertex_id(&list.item[0]), 2);
+                            assert_eq!(
+                                list.item[1].inner,
+                                Some(result_pb::result_item::Inner::Value(common_pb::Value {
+                                    item: Some(common_pb::value::Item::I32(29))
+                                }))
+                            );
+                            assert_eq!(list.item[1].bulk, 1);
+                        }
+                        _ => panic!("value is not a list"),
+                    },
+                    _ => panic!("value is not a nested item"),
+                }
+            }
+            _ => panic!("not a map result"),
+        }
+    }
+
+    // the merged traversers are encoded once with their bulks, rather than repeated
+    #[test]
+    fn bulked_result_round_trip() {
+        let mut merged = Traverser::new(new_vertex(1, "person"));
+        merged.set_bulk(3);
+        let data = vec![merged, Traverser::object(29.into())];
+        let result = result_to_pb_on(data, &Detach::Reference, true, None);
+        let items = match result.inner {
+            Some(result_pb::result::Inner::Items(items)) => items.item,
+            _ => panic!("not a list of items"),
+        };
+        assert_eq!(items.len(), 2);
+        assert_eq!(vertex_id(&items[0]), 1);
+        assert_eq!(items[0].bulk, 3);
+        assert_eq!(items[1].bulk, 1);
+        assert_eq!(result_to_pb_on(vec![], &Detach::Reference, true, None).inner, None);
+    }
+
+    #[test]
+    fn path_result_round_trip() {
+        let tags = BitSet::new();
+        let mut traverser = Traverser::with_path(new_vertex(1, "person"), &tags, Requirement::PATH);
+        traverser.split(new_vertex(4, "person"), &tags);
+        traverser.split(new_vertex(5, "software"), &tags);
+        let path = Traverser::object(Object::DynOwned(Box::new(traverser.take_path())));
+        let result = round_trip(vec![path]);
+        match result.inner {
+            Some(result_pb::result::Inner::Paths(paths)) => {
+                assert_eq!(paths.item.len(), 1);
+                let ids: Vec<i64> = paths.item[0]
+                    .path
+                    .iter()
+                    .map(|e| match &e.inner {
+                        Some(result_pb::graph_element::Inner::Vertex(v)) => v.id,
+                        _ => panic!("not a vertex"),
+                    })
+                    .collect();
+                assert_eq!(ids, vec![1, 4, 5]);
+            }
+            _ => panic!("not a path result"),
+        }
+    }
+
+    // path().by("name") is encoded as a list of values
+    #[test]
+    fn value_path_result_round_trip() {
+        let tags = BitSet::new();
+        let mut traverser = Traverser::with_path(new_vertex(1, "person"), &tags, Requirement::PATH);
+        traverser.split_with_value("josh", &tags);
+        traverser.split_with_value("ripple", &tags);
+        let path = Traverser::object(Object::DynOwned(Box::new(traverser.take_path())));
+        let result = round_trip(vec![path]);
+        match result.inner {
+            Some(result_pb::result::Inner::Items(items)) => match &items.item[0].inner {
+                Some(result_pb::result_item::Inner::List(list)) => {
+                    assert_eq!(list.item.len(), 3);
+                    assert_eq!(vertex_id(&list.item[0]), 1);
+                    assert_eq!(
+                        list.item[2].inner,
+                        Some(result_pb::result_item::Inner::Value(common_pb::Value {
+                            item: Some(common_pb::value::Item::Str("ripple".to_string()))
+                        }))
+                    );
+                }
+                _ => panic!("path is not a list"),
+            },
+            _ => panic!("not a list result"),
+        }
+    }
+
+    #[test]
+    fn detach_policy_test() {
+        assert_eq!(Detach::from_resource(&[]).unwrap(), Detach::Reference);
+        let policies = vec![
+            Detach::Id,
+            Detach::Reference,
+            Detach::Full,
+            Detach::Custom(vec!["name".to_owned(), "age".to_owned()]),
+        ];
+        for policy in policies {
+            let mut bytes = vec![];
+            policy.to_pb().encode(&mut bytes).expect("encode policy failed");
+            assert_eq!(Detach::from_resource(&bytes).unwrap(), policy);
+        }
+        let mut bytes = vec![];
+        pb::DetachPolicy { mode: 9, keys: vec![] }.encode(&mut bytes).unwrap();
+        assert!(Detach::from_resource(&bytes).is_err());
+    }
+
+    // the edges hold their properties, which are detached without reading the graph
+    #[test]
+    fn detach_edge_test() {
+        let mut properties = HashMap::new();
+        properties.insert("weight".to_owned(), Object::from(0.4));
+        properties.insert("date".to_owned(), Object::from("2010"));
+        let label = Label::Str("created".to_owned());
+        let details = DefaultDetails::new_with_prop(9, label.clone(), properties);
+        let edge = Edge::new(9, Some(label), 1, 3, DynDetails::new(details));
+        let detach_edge =
+            |detach: Detach| match result_to_pb_with(vec![Traverser::new(edge.clone())], &detach)
+                .inner
+            {
+                Some(result_pb::result::Inner::Elements(elements)) => {
+                    match elements.item[0].inner.clone() {
+                        Some(result_pb::graph_element::Inner::Edge(e)) => e,
+                        _ => panic!("not an edge"),
+                    }
+                }
+                _ => panic!("not an element result"),
+            };
+        let keys =
+            |e: &result_pb::Edge| e.properties.iter().map(|p| p.key.clone()).collect::<Vec<_>>();
+        let e = detach_edge(Detach::Id);
+        assert_eq!((e.id, e.label.as_str(), e.properties.len()), (9, "", 0));
+        let e = detach_edge(Detach::Reference);
+        assert_eq!((e.label.as_str(), e.properties.len()), ("created", 0));
+        let e = detach_edge(Detach::Full);
+        assert_eq!(keys(&e), vec!["date".to_owned(), "weight".to_owned()]);
+        let e = detach_edge(Detach::Custom(vec!["weight".to_owned(), "absent".to_owned()]));
+        assert_eq!(keys(&e), vec!["weight".to_owned()]);
+        assert_eq!(
+            e.properties[0].value,
+            Some(common_pb::Value { item: Some(common_pb::value::Item::F64(0.4)) })
+        );
+    }
+}
@@ -0,0 +1,317 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The sessions opened by the clients, to share the named results among the jobs of a session
+//! without computing them again. A job running in a session saves its side collections, e.g. of
+//! `aggregate("x")`, `store("x")` or the edges of `subgraph("x")`, into the session by their
+//! names once it ends, and the following jobs of the session start from a saved result by
+//! `SessionRefStep` instead of `g.V()`.
+//!
+//! The jobs of a session run one by one, where a job is rejected while being built if another job
+//! of the session is still running, so a job never sees the results being saved by another one.
+//! A session expires once it has been idle for longer than its TTL, and all of its results are
+//! released once it is closed or expired.
+
+use crate::process::traversal::traverser::Traverser;
+use crate::structure::{get_graph, Details, Element, Label, QueryParams, Vertex, VertexOrEdge};
+use crate::{GraphProxy, ID};
+use dyn_type::BorrowObject;
+use pegasus::codec::{Decode, Encode};
+use pegasus_common::downcast::*;
+use std::collections::HashMap;
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, RwLock, Weak};
+use std::time::{Duration, Instant};
+
+lazy_static! {
+    /// session id -> the sessions opened and not expired yet
+    static ref SESSIONS: Mutex<HashMap<u64, Arc<Session>>> = Mutex::new(HashMap::new());
+    /// job id -> the job running in a session, which is done once all operators of the job are
+    /// dropped
+    static ref SESSION_JOBS: Mutex<HashMap<u64, Weak<SessionJob>>> = Mutex::new(HashMap::new());
+}
+
+static NEXT_SESSION_ID: AtomicU64 = AtomicU64::new(1);
+
+/// A session of a client, with the named results saved by its jobs
+pub struct Session {
+    id: u64,
+    ttl: Duration,
+    last_active: Mutex<Instant>,
+    // the traversers of the results are kept encoded, as the sessions are shared by all threads
+    results: RwLock<HashMap<String, Arc<Vec<Vec<u8>>>>>,
+    // the job running in the session
+    running: Mutex<Option<(u64, Weak<SessionJob>)>>,
+}
+
+impl Session {
+    fn new(id: u64, ttl: Duration) -> Self {
+        Session {
+            id,
+            ttl,
+            last_active: Mutex::new(Instant::now()),
+            results: RwLock::new(HashMap::new()),
+            running: Mutex::new(None),
+        }
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Get the result saved under the name, e.g. by `aggregate("x")` of a former job, with each
+    /// of its traversers encoded
+    pub fn get_result(&self, name: &str) -> Option<Arc<Vec<Vec<u8>>>> {
+        self.results.read().ok().and_then(|results| results.get(name).cloned())
+    }
+
+    /// Save the result under the name, replacing the one saved before
+    pub fn save_result(&self, name: &str, result: Vec<Traverser>) -> io::Result<()> {
+        let mut encoded = Vec::with_capacity(result.len());
+        for traverser in result {
+            let mut bytes = vec![];
+            traverser.write_to(&mut bytes)?;
+            encoded.push(bytes);
+        }
+        if let Ok(mut results) = self.results.write() {
+            results.insert(name.to_owned(), Arc::new(encoded));
+        }
+        Ok(())
+    }
+
+    pub fn result_names(&self) -> Vec<String> {
+        self.results.read().map(|results| results.keys().cloned().collect()).unwrap_or_default()
+    }
+
+    /// Get the id of the job running in the session
+    pub fn get_running_job(&self) -> Option<u64> {
+        let running = self.running.lock().ok()?;
+        running.as_ref().filter(|(_, job)| job.strong_count() > 0).map(|(job_id, _)| *job_id)
+    }
+
+    fn touch(&self) {
+        if let Ok(mut last_active) = self.last_active.lock() {
+            *last_active = Instant::now();
+        }
+    }
+
+    fn is_expired(&self, now: Instant) -> bool {
+        let idle = self
+            .last_active
+            .lock()
+            .map(|last_active| now.saturating_duration_since(*last_active))
+            .unwrap_or_default();
+        idle > self.ttl && self.get_running_job().is_none()
+    }
+}
+
+/// Decode a traverser of a saved result, whose vertices read their properties from the graph
+/// only once any of them is accessed, as the properties are not kept by the session
+pub fn decode_result(mut bytes: &[u8]) -> io::Result<Traverser> {
+    let mut traverser = Traverser::read_from(&mut bytes)?;
+    if let (Some(graph), Some(element)) = (get_graph(), traverser.get_element_mut()) {
+        if let VertexOrEdge::V(vertex) = element.get_mut() {
+            let details = SessionVertexDetails::new(vertex.id, vertex.label().clone(), graph);
+            *vertex = Vertex::new(vertex.id, vertex.label.clone(), details);
+        }
+    }
+    Ok(traverser)
+}
+
+/// The details of a vertex of a saved result, which reads the vertex with its properties from the
+/// graph on the first property accessed
+struct SessionVertexDetails {
+    id: ID,
+    label: Label,
+    graph: Arc<dyn GraphProxy>,
+    inner: OnceLock<Vertex>,
+}
+
+impl_as_any!(SessionVertexDetails);
+
+impl SessionVertexDetails {
+    fn new(id: ID, label: Label, graph: Arc<dyn GraphProxy>) -> Self {
+        SessionVertexDetails { id, label, graph, inner: OnceLock::new() }
+    }
+}
+
+impl Details for SessionVertexDetails {
+    fn get_property(&self, key: &str) -> Option<BorrowObject<'_>> {
+        let vertex = match self.inner.get() {
+            Some(vertex) => vertex,
+            None => {
+                // all the properties are read at once, as the vertex is read only once, where the
+                // vertex read by others at the same time is kept instead if set first
+                let mut params = QueryParams::new();
+                params.props = Some(vec![]);
+                let vertex = self.graph.get_vertex(&[self.id], &params).ok()?.next()?;
+                self.inner.get_or_init(|| vertex)
+            }
+        };
+        vertex.details().get_property(key)
+    }
+
+    fn get_id(&self) -> ID {
+        self.id
+    }
+
+    fn get_label(&self) -> &Label {
+        &self.label
+    }
+}
+
+/// The job running in a session, held by the operators of the job, which marks the session as
+/// running the job until all of them are dropped
+pub struct SessionJob {
+    job_id: u64,
+    session: Arc<Session>,
+}
+
+impl SessionJob {
+    pub fn get_session(&self) -> &Arc<Session> {
+        &self.session
+    }
+}
+
+impl Drop for SessionJob {
+    fn drop(&mut self) {
+        // the session is idle from the end of its last job
+        self.session.touch();
+        if let Ok(mut running) = self.session.running.lock() {
+            if running.as_ref().map(|(job_id, _)| *job_id == self.job_id).unwrap_or(false) {
+                running.take();
+            }
+        }
+    }
+}
+
+/// Open a session which expires once idle for longer than the TTL, and get its id
+pub fn open_session(ttl: Duration) -> u64 {
+    let id = NEXT_SESSION_ID.fetch_add(1, Ordering::SeqCst);
+    let mut sessions = SESSIONS.lock().expect("lock poisoned");
+    expire(&mut sessions);
+    sessions.insert(id, Arc::new(Session::new(id, ttl)));
+    id
+}
+
+/// Close the session and release all of its results, or `false` if it is not found, e.g. expired
+pub fn close_session(id: u64) -> bool {
+    let mut sessions = SESSIONS.lock().expect("lock poisoned");
+    expire(&mut sessions);
+    sessions.remove(&id).is_some()
+}
+
+/// Get the session if it is neither closed nor expired
+pub fn get_session(id: u64) -> Option<Arc<Session>> {
+    let mut sessions = SESSIONS.lock().ok()?;
+    expire(&mut sessions);
+    sessions.get(&id).cloned()
+}
+
+/// Release the expired sessions, and get how many are released, which is also done whenever
+/// sessions are opened, closed or got.
+pub fn expire_sessions() -> usize {
+    SESSIONS.lock().map(|mut sessions| expire(&mut sessions)).unwrap_or(0)
+}
+
+fn expire(sessions: &mut HashMap<u64, Arc<Session>>) -> usize {
+    let now = Instant::now();
+    let before = sessions.len();
+    sessions.retain(|id, session| {
+        let expired = session.is_expired(now);
+        if expired {
+            info!("session {} expires after idle for {:?}", id, session.ttl);
+        }
+        !expired
+    });
+    before - sessions.len()
+}
+
+/// Get the session of the job whose dataflow is being built by current thread, or `None` if the
+/// job doesn't run in a session. It fails if the session is closed or expired, or if another job
+/// of the session is still running.
+pub fn get_session_job() -> Result<Option<Arc<SessionJob>>, String> {
+    match pegasus::get_current_job_conf() {
+        Some(conf) if conf.session_id != 0 => join(conf.job_id, conf.session_id).map(Some),
+        _ => Ok(None),
+    }
+}
+
+fn join(job_id: u64, session_id: u64) -> Result<Arc<SessionJob>, String> {
+    let mut jobs = SESSION_JOBS.lock().map_err(|_| "lock poisoned".to_owned())?;
+    jobs.retain(|_, job| job.strong_count() > 0);
+    if let Some(job) = jobs.get(&job_id).and_then(|job| job.upgrade()) {
+        return Ok(job);
+    }
+    let session = get_session(session_id)
+        .ok_or_else(|| format!("session {} is not found, or closed or expired", session_id))?;
+    let mut running = session.running.lock().map_err(|_| "lock poisoned".to_owned())?;
+    if let Some((other, _)) = running.as_ref().filter(|(_, job)| job.strong_count() > 0) {
+        return Err(format!(
+            "session {} is running job {}, and runs its jobs one by one",
+            session_id, other
+        ));
+    }
+    let job = Arc::new(SessionJob { job_id, session: session.clone() });
+    *running = Some((job_id, Arc::downgrade(&job)));
+    drop(running);
+    session.touch();
+    jobs.insert(job_id, Arc::downgrade(&job));
+    Ok(job)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn serial_jobs_test() {
+        let id = open_session(Duration::from_secs(60));
+        let job = join(6201, id).unwrap();
+        // the same job is joined by all of its workers
+        assert!(Arc::ptr_eq(&job, &join(6201, id).unwrap()));
+        assert_eq!(get_session(id).unwrap().get_running_job(), Some(6201));
+        assert!(join(6202, id).is_err());
+        drop(job);
+        assert!(get_session(id).unwrap().get_running_job().is_none());
+        assert!(join(6202, id).is_ok());
+        assert!(close_session(id));
+    }
+
+    #[test]
+    fn session_results_test() {
+        let id = open_session(Duration::from_secs(60));
+        let session = get_session(id).unwrap();
+        assert!(session.get_result("x").is_none());
+        session.save_result("x", vec![Traverser::object(1.into())]).unwrap();
+        assert_eq!(session.get_result("x").unwrap().len(), 1);
+        assert!(close_session(id));
+        assert!(get_session(id).is_none());
+        assert!(join(6203, id).is_err());
+    }
+
+    #[test]
+    fn session_expire_test() {
+        let id = open_session(Duration::from_millis(10));
+        let job = join(6204, id).unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        // never expires while running a job
+        assert!(get_session(id).is_some());
+        drop(job);
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(get_session(id).is_none());
+    }
+}
@@ -13,22 +13,32 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
+use crate::structure::cache::{get_worker_cache, AdjacencyKey};
 use crate::structure::{
-    DefaultDetails, Details, Direction, DynDetails, Edge, Label, QueryParams, Statement, Vertex,
+    get_snapshot_version, Column, ColumnBatch, DefaultDetails, Details, Direction, DynDetails,
+    Edge, Element, ElementFilter, Filter, Label, QueryParams, ScanPartition, Statement, Vertex,
+    DEFAULT_BATCH_SIZE,
 };
-use crate::{register_graph, DynResult, GraphProxy, ID};
+use crate::{register_graph, str_to_dyn_error, DynResult, GraphProxy, ID};
 use dyn_type::BorrowObject;
 use graph_store::config::{JsonConf, DIR_GRAPH_SCHEMA, FILE_SCHEMA};
+use graph_store::delta::{DeltaEdge, DeltaVertex, GraphDelta, SnapshotPin, Version};
 use graph_store::ldbc::LDBCVertexParser;
+use graph_store::prelude::Direction as StoreDirection;
 use graph_store::prelude::{
-    DefaultId, GlobalStoreTrait, GlobalStoreUpdate, GraphDBConfig, InternalId, LDBCGraphSchema,
-    LabelId, LargeGraphDB, LocalEdge, LocalVertex, MutableGraphDB, Row, INVALID_LABEL_ID,
+    DefaultId, GlobalStoreTrait, GlobalStoreUpdate, GraphDBConfig, GraphSchemaInfo,
+    GraphStatistics, InternalId, LDBCGraphSchema, LabelId, LargeGraphDB, LocalEdge, LocalVertex,
+    MutableGraphDB, Row, INVALID_LABEL_ID,
 };
+use graph_store::schema::AdjOrder;
+use graph_store::segment::AdjSegment;
+use graph_store::utils::Iter;
 use pegasus::api::function::DynIter;
 use pegasus_common::downcast::*;
-use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
-use std::sync::atomic::{AtomicPtr, Ordering};
+use std::sync::atomic::{AtomicPtr, AtomicU64, Ordering};
 use std::sync::Arc;
 
 lazy_static! {
@@ -38,13 +48,51 @@ lazy_static! {
     static ref GRAPH_PROXY: Arc<DemoGraph> = initialize();
 }
 
+/// The number of scans over the vertices of the demo graph, to tell how many times the storage
+/// is read through
+static VERTEX_SCANS: AtomicU64 = AtomicU64::new(0);
+
+pub fn get_vertex_scan_count() -> u64 {
+    VERTEX_SCANS.load(Ordering::SeqCst)
+}
+
+/// The graph of the store loaded, read together with the delta mutating it, see `GraphDelta`
 pub struct DemoGraph {
     store: &'static LargeGraphDB<DefaultId, InternalId>,
+    delta: Arc<GraphDelta<DefaultId>>,
 }
 
 fn initialize() -> Arc<DemoGraph> {
     lazy_static::initialize(&GRAPH);
-    Arc::new(DemoGraph { store: &GRAPH })
+    Arc::new(DemoGraph { store: &GRAPH, delta: Arc::new(GraphDelta::default()) })
+}
+
+impl DemoGraph {
+    /// The version read by current job, i.e. the version pinned once it is submitted, or the
+    /// latest version out of any job
+    fn read_version(&self) -> Version {
+        get_snapshot_version().unwrap_or_else(|| self.delta.version())
+    }
+
+    /// The vertices of the labels loaded, besides those replaced by the versions added later, and
+    /// those deleted by the version
+    fn get_loaded_vertices(
+        &self, label_ids: Option<&Vec<LabelId>>, added: &[Arc<DeltaVertex<DefaultId>>], at: Version,
+    ) -> Iter<'static, LocalVertex<'static, DefaultId>> {
+        let vertices = self.store.get_all_vertices(label_ids);
+        let vertices = if added.is_empty() {
+            vertices
+        } else {
+            let replaced: HashSet<DefaultId> = added.iter().map(|v| v.id).collect();
+            Iter::from_iter(vertices.filter(move |v| !replaced.contains(&v.get_id())))
+        };
+        if self.delta.has_vertex_deletions(at) {
+            let delta = self.delta.clone();
+            Iter::from_iter(vertices.filter(move |v| !delta.is_vertex_deleted(v.get_id(), at)))
+        } else {
+            vertices
+        }
+    }
 }
 
 fn _init_graph() -> LargeGraphDB<DefaultId, InternalId> {
@@ -65,19 +113,19 @@ fn _init_graph() -> LargeGraphDB<DefaultId, InternalId> {
 fn _init_modern_graph() -> LargeGraphDB<DefaultId, InternalId> {
     let mut mut_graph: MutableGraphDB<DefaultId, InternalId> = GraphDBConfig::default().new();
 
-    let v1: DefaultId = LDBCVertexParser::to_global_id(1, 0);
-    let v2: DefaultId = LDBCVertexParser::to_global_id(2, 0);
-    let v3: DefaultId = LDBCVertexParser::to_global_id(3, 1);
-    let v4: DefaultId = LDBCVertexParser::to_global_id(4, 0);
-    let v5: DefaultId = LDBCVertexParser::to_global_id(5, 1);
-    let v6: DefaultId = LDBCVertexParser::to_global_id(6, 0);
+    let v1: DefaultId = LDBCVertexParser::to_global_id(1, 0.into());
+    let v2: DefaultId = LDBCVertexParser::to_global_id(2, 0.into());
+    let v3: DefaultId = LDBCVertexParser::to_global_id(3, 1.into());
+    let v4: DefaultId = LDBCVertexParser::to_global_id(4, 0.into());
+    let v5: DefaultId = LDBCVertexParser::to_global_id(5, 1.into());
+    let v6: DefaultId = LDBCVertexParser::to_global_id(6, 0.into());
 
-    mut_graph.add_vertex(v1, [0, INVALID_LABEL_ID]);
-    mut_graph.add_vertex(v2, [0, INVALID_LABEL_ID]);
-    mut_graph.add_vertex(v3, [1, INVALID_LABEL_ID]);
-    mut_graph.add_vertex(v4, [0, INVALID_LABEL_ID]);
-    mut_graph.add_vertex(v5, [1, INVALID_LABEL_ID]);
-    mut_graph.add_vertex(v6, [0, INVALID_LABEL_ID]);
+    mut_graph.add_vertex(v1, [0.into(), INVALID_LABEL_ID]);
+    mut_graph.add_vertex(v2, [0.into(), INVALID_LABEL_ID]);
+    mut_graph.add_vertex(v3, [1.into(), INVALID_LABEL_ID]);
+    mut_graph.add_vertex(v4, [0.into(), INVALID_LABEL_ID]);
+    mut_graph.add_vertex(v5, [1.into(), INVALID_LABEL_ID]);
+    mut_graph.add_vertex(v6, [0.into(), INVALID_LABEL_ID]);
 
     let prop7 = Row::from(vec![object!(0.5)]);
     let prop8 = Row::from(vec![object!(0.4)]);
@@ -86,12 +134,12 @@ fn _init_modern_graph() -> LargeGraphDB<DefaultId, InternalId> {
     let prop11 = Row::from(vec![object!(1.0)]);
     let prop12 = Row::from(vec![object!(0.2)]);
 
-    mut_graph.add_edge_with_properties(v1, v2, 0, prop7).unwrap();
-    mut_graph.add_edge_with_properties(v1, v3, 1, prop8).unwrap();
-    mut_graph.add_edge_with_properties(v1, v4, 0, prop9).unwrap();
-    mut_graph.add_edge_with_properties(v4, v3, 1, prop10).unwrap();
-    mut_graph.add_edge_with_properties(v4, v5, 1, prop11).unwrap();
-    mut_graph.add_edge_with_properties(v6, v3, 1, prop12).unwrap();
+    mut_graph.add_edge_with_properties(v1, v2, 0.into(), prop7).unwrap();
+    mut_graph.add_edge_with_properties(v1, v3, 1.into(), prop8).unwrap();
+    mut_graph.add_edge_with_properties(v1, v4, 0.into(), prop9).unwrap();
+    mut_graph.add_edge_with_properties(v4, v3, 1.into(), prop10).unwrap();
+    mut_graph.add_edge_with_properties(v4, v5, 1.into(), prop11).unwrap();
+    mut_graph.add_edge_with_properties(v6, v3, 1.into(), prop12).unwrap();
 
     let prop1 = Row::from(vec![object!(1), object!("marko"), object!(29)]);
     let prop2 = Row::from(vec![object!(2), object!("vadas"), object!(27)]);
@@ -176,6 +224,12 @@ fn _init_modern_graph() -> LargeGraphDB<DefaultId, InternalId> {
             "Double"
           ]
         ]
+      },
+      "adj_order": {
+        "knows": {
+          "property": "weight",
+          "descending": true
+        }
       }
     }
     "#;
@@ -209,18 +263,54 @@ macro_rules! filter_limit_ok {
     };
 }
 
-impl GraphProxy for DemoGraph {
-    fn scan_vertex(
-        &self, params: &QueryParams<Vertex>,
+impl DemoGraph {
+    /// Scan the vertices of `params`, of the partitions of `is_owner` only if given, whose ids
+    /// are checked before the vertices are read, e.g. by the filter of `params`
+    fn scan_vertex_of(
+        &self, params: &QueryParams<Vertex>, is_owner: Option<ScanPartition>,
     ) -> DynResult<Box<dyn Iterator<Item = Vertex> + Send>> {
+        VERTEX_SCANS.fetch_add(1, Ordering::SeqCst);
         let label_ids = encode_storage_vertex_label(&params.labels);
         let store = self.store;
-        let result = self.store.get_all_vertices(label_ids.as_ref()).map(move |v| {
-            // TODO: Only process label[0] for now
-            // TODO: change to  to_runtime_vertex_with_property
-            to_runtime_vertex(v, store)
-            //  to_runtime_vertex_with_property(v, params.props.as_ref())
-        });
+        let at = self.read_version();
+        let added = self.delta.get_vertices(at);
+        let mut vertices = self.get_loaded_vertices(label_ids.as_ref(), &added, at);
+        if let Some(is_owner) = is_owner.clone() {
+            vertices = Iter::from_iter(vertices.filter(move |v| is_owner(&(v.get_id() as ID))));
+        }
+        let added: Vec<Vertex> = added
+            .iter()
+            .filter(|v| label_ids.as_ref().map(|ids| ids.contains(&v.label)).unwrap_or(true))
+            .filter(|v| !self.delta.is_vertex_deleted(v.id, at))
+            .filter(|v| is_owner.as_ref().map(|f| f(&(v.id as ID))).unwrap_or(true))
+            .map(|v| to_runtime_added_vertex(v))
+            .collect();
+        if let (Some(filter), false) = (params.filter.as_ref(), params.columns.is_empty()) {
+            let filter = filter.clone();
+            let keys = params.columns.clone();
+            let mut indexes = HashMap::new();
+            let added_filter = filter.clone();
+            let added = added.into_iter().filter(move |v| added_filter.test(v).unwrap_or(false));
+            let result = std::iter::from_fn(move || {
+                let batch: Vec<_> = vertices.by_ref().take(DEFAULT_BATCH_SIZE).collect();
+                if batch.is_empty() {
+                    None
+                } else {
+                    Some(filter_vertex_batch(batch, &keys, &mut indexes, &filter, store))
+                }
+            })
+            .flatten()
+            .chain(added);
+            return Ok(limit_n!(result, params.limit));
+        }
+        let result = vertices
+            .map(move |v| {
+                // TODO: Only process label[0] for now
+                // TODO: change to  to_runtime_vertex_with_property
+                to_runtime_vertex(v, store)
+                //  to_runtime_vertex_with_property(v, params.props.as_ref())
+            })
+            .chain(added);
 
         if let Some(ref filter) = params.filter {
             let f = filter.clone();
@@ -230,14 +320,38 @@ impl GraphProxy for DemoGraph {
             Ok(limit_n!(result, params.limit))
         }
     }
+}
+
+impl GraphProxy for DemoGraph {
+    fn scan_vertex(
+        &self, params: &QueryParams<Vertex>,
+    ) -> DynResult<Box<dyn Iterator<Item = Vertex> + Send>> {
+        self.scan_vertex_of(params, None)
+    }
+
+    fn scan_vertex_partition(
+        &self, params: &QueryParams<Vertex>, is_owner: ScanPartition,
+    ) -> DynResult<Box<dyn Iterator<Item = Vertex> + Send>> {
+        self.scan_vertex_of(params, Some(is_owner))
+    }
 
     fn get_vertex(
         &self, ids: &[ID], params: &QueryParams<Vertex>,
     ) -> DynResult<Box<dyn Iterator<Item = Vertex> + Send>> {
         let mut result = Vec::with_capacity(ids.len());
+        let at = self.read_version();
         for id in ids {
-            if let Some(local_vertex) = self.store.get_vertex(*id as DefaultId) {
-                let v = to_runtime_vertex_with_property(local_vertex, params.props.as_ref());
+            if self.delta.is_vertex_deleted(*id as DefaultId, at) {
+                continue;
+            }
+            let v = if let Some(added) = self.delta.get_vertex(*id as DefaultId, at) {
+                Some(to_runtime_added_vertex(&added))
+            } else {
+                self.store
+                    .get_vertex(*id as DefaultId)
+                    .map(|v| to_runtime_vertex_with_property(v, params.props.as_ref()))
+            };
+            if let Some(v) = v {
                 if let Some(ref filter) = params.filter {
                     if filter.test(&v).unwrap_or(false) {
                         result.push(v);
@@ -251,6 +365,32 @@ impl GraphProxy for DemoGraph {
         DynResult::Ok(Box::new(result.into_iter()))
     }
 
+    fn get_edge(
+        &self, ids: &[ID], params: &QueryParams<Edge>,
+    ) -> DynResult<Box<dyn Iterator<Item = Edge> + Send>> {
+        let label_ids = encode_storage_edge_label(&params.labels);
+        let mut result = Vec::with_capacity(ids.len());
+        let at = self.read_version();
+        for id in ids {
+            let loaded = get_edges_of_id(self.store, *id)
+                .filter(|e| {
+                    label_ids.as_ref().map(|ids| ids.contains(&e.get_label())).unwrap_or(true)
+                })
+                .filter(|e| !is_loaded_edge_deleted(&self.delta, e, at))
+                .map(|e| to_runtime_edge(e, self.store));
+            let added = get_added_edges_of_id(&self.delta, *id, label_ids.as_ref(), at);
+            for e in loaded.chain(added.iter().map(|e| to_runtime_added_edge(e))) {
+                if let Some(ref filter) = params.filter {
+                    if !filter.test(&e).unwrap_or(false) {
+                        continue;
+                    }
+                }
+                result.push(e);
+            }
+        }
+        Ok(limit_n!(result.into_iter(), params.limit))
+    }
+
     fn prepare_explore_vertex(
         &self, direction: Direction, params: &QueryParams<Vertex>,
     ) -> DynResult<Box<dyn Statement<ID, Vertex>>> {
@@ -258,15 +398,60 @@ impl GraphProxy for DemoGraph {
         let filter = params.filter.clone();
         let limit = params.limit.clone();
         let graph = self.store;
+        let cache = get_worker_cache();
+        // the labels known ahead bind the expansion to their adjacency segments
+        let segments = get_adj_segments(graph, direction, edge_label_ids.as_ref());
+        let delta = self.delta.clone();
+        let at = self.read_version();
+        // the adjacency is read by the edges once any is deleted, each of which is checked
+        let by_edges = delta.has_deletions(at);
 
         let stmt = from_fn(move |v: ID| {
-            let iter = match direction {
-                Direction::Out => graph.get_out_vertices(v as DefaultId, edge_label_ids.as_ref()),
-                Direction::In => graph.get_in_vertices(v as DefaultId, edge_label_ids.as_ref()),
-                Direction::Both => graph.get_both_vertices(v as DefaultId, edge_label_ids.as_ref()),
-            }
-            // TODO: change to to_runtime_vertex_with_property
-            .map(move |v| to_runtime_vertex(v, graph));
+            let iter: Box<dyn Iterator<Item = Vertex> + Send> = if by_edges {
+                let delta = delta.clone();
+                Box::new(
+                    get_adj_edges(graph, v, direction, edge_label_ids.as_ref())
+                        .filter(move |e| !is_loaded_edge_deleted(&delta, e, at))
+                        .map(move |e| {
+                            let (src, dst) = (e.get_src_id(), e.get_dst_id());
+                            get_other_vertex(graph, if src == v as DefaultId { dst } else { src })
+                        }),
+                )
+            } else if let Some(ref cache) = cache {
+                let key = AdjacencyKey::new(v, direction, edge_label_ids.as_ref());
+                let adjacency = cache.get_or_load(key, || {
+                    get_adj_vertices_of(graph, v, direction, edge_label_ids.as_ref(), &segments)
+                        .map(|v| (encode_runtime_v_id(&v), v.get_label()[0]))
+                        .collect()
+                });
+                Box::new((0..adjacency.len()).map(move |i| {
+                    let (id, label) = adjacency[i];
+                    Vertex::new(
+                        id,
+                        Some(Label::Id(label)),
+                        LazyVertexDetails::new(id as DefaultId, graph),
+                    )
+                }))
+            } else {
+                // TODO: change to to_runtime_vertex_with_property
+                Box::new(
+                    get_adj_vertices_of(graph, v, direction, edge_label_ids.as_ref(), &segments)
+                        .map(move |v| to_runtime_vertex(v, graph)),
+                )
+            };
+            let iter: Box<dyn Iterator<Item = Vertex> + Send> = if at == 0 {
+                iter
+            } else {
+                let added =
+                    get_added_adj_vertices(graph, &delta, v, direction, &edge_label_ids, at);
+                // the vertices loaded are replaced by their versions added later if any
+                let delta = delta.clone();
+                let iter = iter.map(move |v| match delta.get_vertex(v.id as DefaultId, at) {
+                    Some(added) => to_runtime_added_vertex(&added),
+                    None => v,
+                });
+                Box::new(iter.chain(added))
+            };
             Ok(filter_limit_ok!(iter, filter, limit))
         });
         Ok(stmt)
@@ -279,17 +464,120 @@ impl GraphProxy for DemoGraph {
         let filter = params.filter.clone();
         let limit = params.limit.clone();
         let graph = self.store;
+        let delta = self.delta.clone();
+        let at = self.read_version();
+        let deletions = delta.has_deletions(at);
         let stmt = from_fn(move |v: ID| {
-            let iter = match direction {
-                Direction::Out => graph.get_out_edges(v as DefaultId, edge_label_ids.as_ref()),
-                Direction::In => graph.get_in_edges(v as DefaultId, edge_label_ids.as_ref()),
-                Direction::Both => graph.get_both_edges(v as DefaultId, edge_label_ids.as_ref()),
-            }
-            .map(move |e| to_runtime_edge(e, graph));
+            let added = get_added_adj_edges(&delta, v, direction, edge_label_ids.as_ref(), at);
+            let delta = delta.clone();
+            let iter = get_adj_edges(graph, v, direction, edge_label_ids.as_ref())
+                .filter(move |e| !deletions || !is_loaded_edge_deleted(&delta, e, at))
+                .map(move |e| to_runtime_edge(e, graph))
+                .chain(added.into_iter().map(|e| to_runtime_added_edge(&e)));
             Ok(filter_limit_ok!(iter, filter, limit))
         });
         Ok(stmt)
     }
+
+    fn get_adj_order(&self, label: LabelId) -> Option<AdjOrder> {
+        self.store.get_adj_order(label).cloned()
+    }
+
+    fn prepare_explore_sorted_edge(
+        &self, direction: Direction, params: &QueryParams<Edge>,
+    ) -> DynResult<Box<dyn Statement<ID, Edge>>> {
+        let label = match encode_storage_edge_label(&params.labels).as_deref() {
+            Some([label]) => *label,
+            _ => Err(str_to_dyn_error("the sorted edges are explored of exactly one label"))?,
+        };
+        let order = self
+            .store
+            .get_adj_order(label)
+            .cloned()
+            .ok_or_else(|| str_to_dyn_error("the edges of the label are not stored sorted"))?;
+        let dir = match direction {
+            Direction::Out => StoreDirection::Outgoing,
+            Direction::In => StoreDirection::Incoming,
+            Direction::Both => Err(str_to_dyn_error("the sorted edges are explored one way"))?,
+        };
+        let segment = self.store.get_adj_segment(label, dir);
+        let filter = params.filter.clone();
+        let limit = params.limit;
+        let graph = self.store;
+        let delta = self.delta.clone();
+        let at = self.read_version();
+        let deletions = delta.has_deletions(at);
+        let labels = vec![label];
+        let stmt = from_fn(move |v: ID| {
+            let iter: Box<dyn Iterator<Item = Edge> + Send> = match segment.as_ref() {
+                Some(segment) => {
+                    let delta = delta.clone();
+                    Box::new(
+                        graph
+                            .get_segment_edges(segment.clone(), v as DefaultId)
+                            .filter(move |e| !deletions || !is_loaded_edge_deleted(&delta, e, at))
+                            .map(move |e| to_runtime_edge(e, graph)),
+                    )
+                }
+                None => Box::new(std::iter::empty()),
+            };
+            let added = get_added_adj_edges(&delta, v, direction, Some(&labels), at);
+            if added.is_empty() {
+                Ok(filter_limit_ok!(iter, filter, limit))
+            } else {
+                // the edges added are merged into those loaded by the order
+                let mut edges: Vec<Edge> =
+                    iter.chain(added.iter().map(|e| to_runtime_added_edge(e))).collect();
+                edges.sort_by(|e1, e2| compare_by_order(e1, e2, &order));
+                Ok(filter_limit_ok!(edges.into_iter(), filter, limit))
+            }
+        });
+        Ok(stmt)
+    }
+
+    fn count_adj_edges(
+        &self, vid: ID, direction: Direction, edge_labels: &[Label],
+    ) -> DynResult<usize> {
+        let edge_label_ids = encode_storage_edge_label(&edge_labels.to_vec());
+        let (id, labels) = (vid as DefaultId, edge_label_ids.as_ref());
+        let at = self.read_version();
+        let count_out = || self.store.count_adj_edges(id, labels, StoreDirection::Outgoing);
+        let count_in = || self.store.count_adj_edges(id, labels, StoreDirection::Incoming);
+        let count = if self.delta.has_deletions(at) {
+            // the edges deleted are left out one by one
+            get_adj_edges(self.store, vid, direction, labels)
+                .filter(|e| !is_loaded_edge_deleted(&self.delta, e, at))
+                .count()
+        } else {
+            match direction {
+                Direction::Out => count_out(),
+                Direction::In => count_in(),
+                Direction::Both => count_out() + count_in(),
+            }
+        };
+        let added = get_added_adj_edges(&self.delta, vid, direction, labels, at);
+        Ok(count + added.len())
+    }
+
+    fn get_statistics(&self) -> Option<Arc<GraphStatistics>> {
+        Some(self.store.get_statistics())
+    }
+
+    fn has_exact_statistics(&self) -> bool {
+        // the vertices of two labels are counted twice by the statistics, and those added or
+        // deleted later are not counted at all
+        self.delta.is_empty()
+            && self.store.count_all_vertices(None)
+                == self.store.get_statistics().total_vertex_count()
+    }
+
+    fn get_schema(&self) -> Option<Arc<GraphSchemaInfo>> {
+        Some(self.store.schema())
+    }
+
+    fn pin_snapshot(&self) -> Option<SnapshotPin> {
+        Some(self.delta.pin())
+    }
 }
 
 #[allow(dead_code)]
@@ -298,6 +586,153 @@ pub fn create_demo_graph() {
     register_graph(GRAPH_PROXY.clone());
 }
 
+/// Create the graph of the store, e.g. of another dataset than the demo graph, to be registered
+/// by `register_named_graph`, where the store lives as long as the process, as the demo graph does
+pub fn create_graph(store: LargeGraphDB<DefaultId, InternalId>) -> Arc<dyn GraphProxy> {
+    create_mutable_graph(store).0
+}
+
+/// Create the graph of the store as `create_graph` does, together with the delta the graph is
+/// mutated by, whose mutations are read by the jobs submitted after them
+pub fn create_mutable_graph(
+    store: LargeGraphDB<DefaultId, InternalId>,
+) -> (Arc<dyn GraphProxy>, Arc<GraphDelta<DefaultId>>) {
+    let delta = Arc::new(GraphDelta::default());
+    (Arc::new(DemoGraph { store: Box::leak(Box::new(store)), delta: delta.clone() }), delta)
+}
+
+#[inline]
+fn get_adj_vertices(
+    graph: &'static LargeGraphDB<DefaultId, InternalId>, v: ID, direction: Direction,
+    edge_label_ids: Option<&Vec<LabelId>>,
+) -> Iter<'static, LocalVertex<'static, DefaultId>> {
+    match direction {
+        Direction::Out => graph.get_out_vertices(v as DefaultId, edge_label_ids),
+        Direction::In => graph.get_in_vertices(v as DefaultId, edge_label_ids),
+        Direction::Both => graph.get_both_vertices(v as DefaultId, edge_label_ids),
+    }
+}
+
+#[inline]
+fn get_adj_edges(
+    graph: &'static LargeGraphDB<DefaultId, InternalId>, v: ID, direction: Direction,
+    edge_label_ids: Option<&Vec<LabelId>>,
+) -> Iter<'static, LocalEdge<'static, DefaultId, InternalId>> {
+    match direction {
+        Direction::Out => graph.get_out_edges(v as DefaultId, edge_label_ids),
+        Direction::In => graph.get_in_edges(v as DefaultId, edge_label_ids),
+        Direction::Both => graph.get_both_edges(v as DefaultId, edge_label_ids),
+    }
+}
+
+/// Whether the edge loaded is deleted when read at the version, see `GraphDelta::is_edge_deleted`
+#[inline]
+fn is_loaded_edge_deleted(
+    delta: &GraphDelta<DefaultId>, e: &LocalEdge<DefaultId, InternalId>, at: Version,
+) -> bool {
+    delta.is_edge_deleted(e.get_src_id(), e.get_dst_id(), e.get_label(), 0, at)
+}
+
+/// The adjacency segments of the edge labels in the direction, or `None` if the labels are not
+/// given, where a label not in the schema has no segment, as no edge is of the label
+fn get_adj_segments(
+    graph: &'static LargeGraphDB<DefaultId, InternalId>, direction: Direction,
+    edge_label_ids: Option<&Vec<LabelId>>,
+) -> Option<Arc<[Arc<AdjSegment<InternalId>>]>> {
+    let mut labels = edge_label_ids?.clone();
+    labels.sort_unstable();
+    labels.dedup();
+    let dirs: &[StoreDirection] = match direction {
+        Direction::Out => &[StoreDirection::Outgoing],
+        Direction::In => &[StoreDirection::Incoming],
+        Direction::Both => &[StoreDirection::Outgoing, StoreDirection::Incoming],
+    };
+    let mut segments = Vec::with_capacity(labels.len() * dirs.len());
+    for dir in dirs {
+        for label in labels.iter() {
+            segments.extend(graph.get_adj_segment(*label, *dir));
+        }
+    }
+    Some(segments.into())
+}
+
+/// The vertices adjacent to `v` through the adjacency segments if any, without comparing the
+/// labels of the edges, or by the edges of the labels otherwise
+#[inline]
+fn get_adj_vertices_of(
+    graph: &'static LargeGraphDB<DefaultId, InternalId>, v: ID, direction: Direction,
+    edge_label_ids: Option<&Vec<LabelId>>, segments: &Option<Arc<[Arc<AdjSegment<InternalId>>]>>,
+) -> Iter<'static, LocalVertex<'static, DefaultId>> {
+    match segments {
+        Some(segments) if segments.len() == 1 => {
+            graph.get_segment_vertices(segments[0].clone(), v as DefaultId)
+        }
+        Some(segments) => {
+            let segments = segments.clone();
+            Iter::from_iter(
+                (0..segments.len()).flat_map(move |i| {
+                    graph.get_segment_vertices(segments[i].clone(), v as DefaultId)
+                }),
+            )
+        }
+        None => get_adj_vertices(graph, v, direction, edge_label_ids),
+    }
+}
+
+/// The edges added adjacent to `v` in the direction, which are read at the version, besides
+/// those deleted by then
+fn get_added_adj_edges(
+    delta: &GraphDelta<DefaultId>, v: ID, direction: Direction,
+    edge_label_ids: Option<&Vec<LabelId>>, at: Version,
+) -> Vec<Arc<DeltaEdge<DefaultId>>> {
+    if at == 0 {
+        return vec![];
+    }
+    let id = v as DefaultId;
+    let mut edges = match direction {
+        Direction::Out => delta.get_adj_edges(id, StoreDirection::Outgoing, edge_label_ids, at),
+        Direction::In => delta.get_adj_edges(id, StoreDirection::Incoming, edge_label_ids, at),
+        Direction::Both => {
+            let mut edges = delta.get_adj_edges(id, StoreDirection::Outgoing, edge_label_ids, at);
+            edges.extend(delta.get_adj_edges(id, StoreDirection::Incoming, edge_label_ids, at));
+            edges
+        }
+    };
+    if delta.has_deletions(at) {
+        edges.retain(|e| !delta.is_edge_deleted(e.src, e.dst, e.label, e.version, at));
+    }
+    edges
+}
+
+/// The vertices adjacent to `v` by the edges added, each of its version read at the version, or
+/// as it is loaded, where a vertex of another partition is given by its id only
+fn get_added_adj_vertices(
+    graph: &'static LargeGraphDB<DefaultId, InternalId>, delta: &GraphDelta<DefaultId>, v: ID,
+    direction: Direction, edge_label_ids: &Option<Vec<LabelId>>, at: Version,
+) -> Vec<Vertex> {
+    get_added_adj_edges(delta, v, direction, edge_label_ids.as_ref(), at)
+        .into_iter()
+        .map(|e| {
+            let other = if e.src == v as DefaultId { e.dst } else { e.src };
+            if let Some(added) = delta.get_vertex(other, at) {
+                to_runtime_added_vertex(&added)
+            } else {
+                get_other_vertex(graph, other)
+            }
+        })
+        .collect()
+}
+
+/// The vertex loaded at the other end of an edge, which is given by its id only if it is of
+/// another partition
+fn get_other_vertex(graph: &'static LargeGraphDB<DefaultId, InternalId>, id: DefaultId) -> Vertex {
+    if let Some(loaded) = graph.get_vertex(id) {
+        to_runtime_vertex(loaded, graph)
+    } else {
+        Vertex::new(id as ID, None, LazyVertexDetails::new(id, graph))
+    }
+}
+
 #[inline]
 fn to_runtime_vertex(
     v: LocalVertex<DefaultId>, store: &'static LargeGraphDB<DefaultId, InternalId>,
@@ -309,6 +744,41 @@ fn to_runtime_vertex(
     Vertex::new(id, label, details)
 }
 
+/// Filter a batch of the vertices of a scan by the properties of `keys` read into columns, whose
+/// indexes in the rows are resolved once for each label
+fn filter_vertex_batch(
+    batch: Vec<LocalVertex<'static, DefaultId>>, keys: &[String],
+    indexes: &mut HashMap<[LabelId; 2], Vec<Option<usize>>>,
+    filter: &Filter<Vertex, ElementFilter>, store: &'static LargeGraphDB<DefaultId, InternalId>,
+) -> Vec<Vertex> {
+    let mut columns: Vec<Column> =
+        keys.iter().map(|_| Column::with_capacity(batch.len())).collect();
+    for v in batch.iter() {
+        let label = v.get_label();
+        if let Entry::Vacant(entry) = indexes.entry(label) {
+            let index: Vec<_> = keys.iter().map(|key| v.get_property_index(key)).collect();
+            // a vertex without properties tells nothing of the others of its label
+            if index.iter().all(Option::is_none) {
+                columns.iter_mut().for_each(|column| column.push(None));
+                continue;
+            }
+            entry.insert(index);
+        }
+        for (column, index) in columns.iter_mut().zip(indexes[&label].iter()) {
+            column.push(index.and_then(|i| v.get_property_at(i)));
+        }
+    }
+    let vertices: Vec<Vertex> = batch.iter().map(|v| to_runtime_vertex(v.clone(), store)).collect();
+    let selected = {
+        let mut column_batch = ColumnBatch::new(&vertices);
+        for (key, column) in keys.iter().zip(columns) {
+            column_batch.add_column(key.clone(), column);
+        }
+        filter.select_batch(&column_batch)
+    };
+    vertices.into_iter().enumerate().filter(|(i, _)| selected.get(*i)).map(|(_, v)| v).collect()
+}
+
 fn to_runtime_vertex_with_property(
     v: LocalVertex<DefaultId>, props: Option<&Vec<String>>,
 ) -> Vertex {
@@ -334,6 +804,34 @@ fn to_runtime_vertex_with_property(
     Vertex::new(id, label, details)
 }
 
+fn to_runtime_added_vertex(v: &DeltaVertex<DefaultId>) -> Vertex {
+    let id = v.id as ID;
+    let label = Label::Id(v.label);
+    let details = DefaultDetails::new_with_prop(id, label.clone(), v.properties.clone());
+    Vertex::new(id, Some(label), details)
+}
+
+/// The edges added are identified by their start vertices, as those loaded are without
+/// `llong_id`, see `encode_runtime_e_id`
+fn to_runtime_added_edge(e: &DeltaEdge<DefaultId>) -> Edge {
+    let id = e.src as ID;
+    let label = Label::Id(e.label);
+    let details = DefaultDetails::new_with_prop(id, label.clone(), e.properties.clone());
+    Edge::new(id, Some(label), e.src as ID, e.dst as ID, DynDetails::new(details))
+}
+
+/// Compare the edges by the property of the order, as the edges of a label stored sorted are
+fn compare_by_order(e1: &Edge, e2: &Edge, order: &AdjOrder) -> std::cmp::Ordering {
+    let p1 = e1.details().get_property(&order.property);
+    let p2 = e2.details().get_property(&order.property);
+    let ordering = p1.partial_cmp(&p2).unwrap_or(std::cmp::Ordering::Equal);
+    if order.descending {
+        ordering.reverse()
+    } else {
+        ordering
+    }
+}
+
 #[inline]
 fn to_runtime_edge(
     e: LocalEdge<DefaultId, InternalId>, _store: &'static LargeGraphDB<DefaultId, InternalId>,
@@ -446,15 +944,50 @@ fn encode_runtime_v_id(v: &LocalVertex<DefaultId>) -> ID {
     v.get_id() as ID
 }
 
+/// The id of the edge is the id of its start vertex, as the global id of the edge, i.e. `EdgeId`,
+/// needs 128 bits, see `llong_id`, so that the edges of a start vertex share the same id
 #[cfg(not(feature = "llong_id"))]
 fn encode_runtime_e_id(e: &LocalEdge<DefaultId, InternalId>) -> ID {
     // TODO(longbin) Use source id for edge id for now
     e.get_src_id() as ID
 }
 
+/// The id of the edge is its global id, i.e. `EdgeId`, whose lower 64 bits are the id of its start
+/// vertex, so that the edge is routed as its start vertex
 #[cfg(feature = "llong_id")]
 fn encode_runtime_e_id(e: &LocalEdge<DefaultId, InternalId>) -> ID {
-    ((e.get_dst_id() as ID) << 64) | (e.get_src_id() as ID)
+    e.get_edge_id().encode()
+}
+
+/// The edges of the id in current partition, see `encode_runtime_e_id`
+#[cfg(not(feature = "llong_id"))]
+fn get_edges_of_id(
+    graph: &'static LargeGraphDB<DefaultId, InternalId>, id: ID,
+) -> Iter<'static, LocalEdge<'static, DefaultId, InternalId>> {
+    graph.get_out_edges(id as DefaultId, None)
+}
+
+#[cfg(feature = "llong_id")]
+fn get_edges_of_id(
+    graph: &'static LargeGraphDB<DefaultId, InternalId>, id: ID,
+) -> Iter<'static, LocalEdge<'static, DefaultId, InternalId>> {
+    graph.get_edges(&[graph_store::prelude::EdgeId::decode(id)])
+}
+
+/// The edges added of the id, i.e. those starting from the vertex of the id
+#[cfg(not(feature = "llong_id"))]
+fn get_added_edges_of_id(
+    delta: &GraphDelta<DefaultId>, id: ID, edge_label_ids: Option<&Vec<LabelId>>, at: Version,
+) -> Vec<Arc<DeltaEdge<DefaultId>>> {
+    get_added_adj_edges(delta, id, Direction::Out, edge_label_ids, at)
+}
+
+/// The edges added are never got by the global ids of the edges, of which they have none
+#[cfg(feature = "llong_id")]
+fn get_added_edges_of_id(
+    _delta: &GraphDelta<DefaultId>, _id: ID, _edge_label_ids: Option<&Vec<LabelId>>, _at: Version,
+) -> Vec<Arc<DeltaEdge<DefaultId>>> {
+    vec![]
 }
 
 fn encode_runtime_v_label(v: &LocalVertex<DefaultId>) -> Option<Label> {
@@ -465,9 +998,9 @@ fn encode_runtime_e_label(e: &LocalEdge<DefaultId, InternalId>) -> Option<Label>
     Some(Label::Id(e.get_label()))
 }
 
-/// Transform string-typed labels into a id-typed labels.
-/// `is_true_label` records whether the label is an actual label, or already transformed into
-/// an id-type.
+/// Transform string-typed labels into a id-typed labels, where `None` is of all labels.
+/// The names not in the schema are of no vertex or edge, which are left out, so that the labels
+/// of only such names are an empty list that matches nothing.
 fn labels_to_ids(labels: &Vec<Label>, is_vertex: bool) -> Option<Vec<LabelId>> {
     if labels.is_empty() {
         None
@@ -475,16 +1008,19 @@ fn labels_to_ids(labels: &Vec<Label>, is_vertex: bool) -> Option<Vec<LabelId>> {
         Some(
             labels
                 .iter()
-                .map(|label| match label {
+                .filter_map(|label| match label {
                     Label::Str(s) => {
                         let label_id = if is_vertex {
                             (*GRAPH).get_schema().get_vertex_label_id(s)
                         } else {
-                            (*GRAPH).get_schema().get_edge_label_id(s).map(|id| id)
+                            (*GRAPH).get_schema().get_edge_label_id(s)
                         };
-                        label_id.unwrap_or(INVALID_LABEL_ID)
+                        if label_id.is_none() {
+                            debug!("label {} is not in the schema, which never matches", s);
+                        }
+                        label_id
                     }
-                    Label::Id(id) => *id,
+                    Label::Id(id) => Some(*id),
                 })
                 .collect::<Vec<LabelId>>(),
         )
@@ -501,18 +1037,38 @@ fn encode_storage_edge_label(labels: &Vec<Label>) -> Option<Vec<LabelId>> {
 
 #[cfg(test)]
 mod tests {
-    use super::GRAPH;
+    use super::{GRAPH, GRAPH_PROXY};
+    use crate::structure::{QueryParams, Vertex};
+    use crate::{Element, GraphProxy, ID};
     use graph_store::ldbc::LDBCVertexParser;
     use graph_store::prelude::{DefaultId, GlobalStoreTrait};
+    use std::sync::Arc;
 
     #[test]
     fn it_works() {
-        let v1: DefaultId = LDBCVertexParser::to_global_id(1, 0);
-        let v2: DefaultId = LDBCVertexParser::to_global_id(2, 0);
-        let v4: DefaultId = LDBCVertexParser::to_global_id(4, 0);
+        let v1: DefaultId = LDBCVertexParser::to_global_id(1, 0.into());
+        let v2: DefaultId = LDBCVertexParser::to_global_id(2, 0.into());
+        let v4: DefaultId = LDBCVertexParser::to_global_id(4, 0.into());
 
-        let out_iter = GRAPH.get_out_vertices(v1, Some(&vec![0]));
+        let out_iter = GRAPH.get_out_vertices(v1, Some(&vec![0.into()]));
         let out: Vec<DefaultId> = out_iter.map(|v| v.get_id()).collect();
         assert_eq!(out, vec![v4, v2]);
     }
+
+    #[test]
+    fn scan_vertex_partition_test() {
+        let params = QueryParams::<Vertex>::new();
+        let mut all: Vec<ID> = GRAPH_PROXY.scan_vertex(&params).unwrap().map(|v| v.id()).collect();
+        all.sort();
+        let mut parts = vec![];
+        for part in 0..2_u64 {
+            let is_owner = Arc::new(move |id: &ID| *id % 2 == part);
+            let vertices = GRAPH_PROXY.scan_vertex_partition(&params, is_owner).unwrap();
+            let ids: Vec<ID> = vertices.map(|v| v.id()).collect();
+            assert!(ids.iter().all(|id| *id % 2 == part));
+            parts.extend(ids);
+        }
+        parts.sort();
+        assert_eq!(parts, all);
+    }
 }
@@ -0,0 +1,324 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The adjacency cache of the workers. Multi-hop traversals tend to expand the same hot
+//! vertices again and again, so each worker of a job may keep the decoded adjacency lists of the
+//! recently expanded vertices in an LRU cache, bounded by the `cache_limit` bytes of the job.
+//! The cache is shared by all operators of the worker, which may be scheduled on any thread of
+//! the pool, and it is guarded by a lock accordingly.
+//!
+//! Only the adjacency of the graph as it is loaded is cached, which never changes. The mutations
+//! of the delta, see `GraphDelta`, are applied over the cached adjacency by each expansion at the
+//! version the job reads, so that they need not invalidate the cache.
+
+use crate::process::metrics::WorkerCounter;
+use crate::structure::{Direction, ID};
+use graph_store::common::LabelId;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, Weak};
+
+/// The number of expansions that hit the adjacency cache
+pub const CACHE_HIT_COUNTER: &str = "adj_cache_hit";
+/// The number of expansions that miss the adjacency cache
+pub const CACHE_MISS_COUNTER: &str = "adj_cache_miss";
+
+/// The adjacent vertices of a vertex, as pairs of (vertex id, vertex label)
+pub type Adjacency = Arc<Vec<(ID, LabelId)>>;
+
+#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+pub struct AdjacencyKey {
+    vid: ID,
+    direction: Direction,
+    // sorted edge labels, empty for all labels
+    labels: Vec<LabelId>,
+}
+
+impl AdjacencyKey {
+    pub fn new(vid: ID, direction: Direction, labels: Option<&Vec<LabelId>>) -> Self {
+        let mut labels = labels.cloned().unwrap_or_default();
+        labels.sort();
+        labels.dedup();
+        AdjacencyKey { vid, direction, labels }
+    }
+
+    /// The estimated bytes of an entry of this key and the given adjacency in the cache,
+    /// including the key maintained for the LRU order
+    fn entry_bytes(&self, adjacency: &Adjacency) -> usize {
+        2 * (std::mem::size_of::<AdjacencyKey>()
+            + self.labels.len() * std::mem::size_of::<LabelId>())
+            + std::mem::size_of::<LruEntry>()
+            + std::mem::size_of::<u64>()
+            + adjacency.len() * std::mem::size_of::<(ID, LabelId)>()
+    }
+}
+
+struct LruEntry {
+    adjacency: Adjacency,
+    bytes: usize,
+    tick: u64,
+}
+
+struct LruCache {
+    entries: HashMap<AdjacencyKey, LruEntry>,
+    // tick of last access -> key, the least recently used entry comes first
+    order: BTreeMap<u64, AdjacencyKey>,
+    tick: u64,
+    bytes: usize,
+    limit: usize,
+}
+
+impl LruCache {
+    fn new(limit: usize) -> Self {
+        LruCache { entries: HashMap::new(), order: BTreeMap::new(), tick: 0, bytes: 0, limit }
+    }
+
+    fn get(&mut self, key: &AdjacencyKey) -> Option<Adjacency> {
+        if let Some(entry) = self.entries.get_mut(key) {
+            self.tick += 1;
+            let key = self.order.remove(&entry.tick).expect("lru order lost");
+            entry.tick = self.tick;
+            self.order.insert(self.tick, key);
+            Some(entry.adjacency.clone())
+        } else {
+            None
+        }
+    }
+
+    fn insert(&mut self, key: AdjacencyKey, adjacency: Adjacency) {
+        let bytes = key.entry_bytes(&adjacency);
+        if bytes > self.limit {
+            // never cache an adjacency that is larger than the whole cache
+            return;
+        }
+        self.remove(&key);
+        while self.bytes + bytes > self.limit {
+            let lru = self.order.keys().next().cloned().expect("lru order lost");
+            let lru_key = self.order.remove(&lru).expect("lru order lost");
+            if let Some(entry) = self.entries.remove(&lru_key) {
+                self.bytes -= entry.bytes;
+            }
+        }
+        self.tick += 1;
+        self.order.insert(self.tick, key.clone());
+        self.entries.insert(key, LruEntry { adjacency, bytes, tick: self.tick });
+        self.bytes += bytes;
+    }
+
+    fn remove(&mut self, key: &AdjacencyKey) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.order.remove(&entry.tick);
+            self.bytes -= entry.bytes;
+        }
+    }
+
+    fn remove_vertex(&mut self, vid: ID) {
+        let keys: Vec<AdjacencyKey> =
+            self.entries.keys().filter(|k| k.vid == vid).cloned().collect();
+        for key in keys {
+            self.remove(&key);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+        self.bytes = 0;
+    }
+}
+
+pub struct AdjacencyCache {
+    lru: Mutex<LruCache>,
+    hits: WorkerCounter,
+    misses: WorkerCounter,
+}
+
+impl AdjacencyCache {
+    /// Create a cache that keeps at most `limit` bytes of adjacency lists
+    pub fn new(limit: usize) -> Self {
+        AdjacencyCache {
+            lru: Mutex::new(LruCache::new(limit)),
+            hits: WorkerCounter::new(CACHE_HIT_COUNTER),
+            misses: WorkerCounter::new(CACHE_MISS_COUNTER),
+        }
+    }
+
+    /// Get the adjacency of the given key from the cache, or load it by `load` and cache it if
+    /// missed. The loading is done out of the lock, so that other operators are not blocked.
+    pub fn get_or_load<F>(&self, key: AdjacencyKey, load: F) -> Adjacency
+    where
+        F: FnOnce() -> Vec<(ID, LabelId)>,
+    {
+        if let Some(adjacency) = self.lru.lock().ok().and_then(|mut lru| lru.get(&key)) {
+            self.hits.add(1);
+            return adjacency;
+        }
+        self.misses.add(1);
+        let adjacency = Arc::new(load());
+        if let Ok(mut lru) = self.lru.lock() {
+            lru.insert(key, adjacency.clone());
+        }
+        adjacency
+    }
+
+    /// Remove all cached adjacency lists of the given vertex
+    pub fn invalidate_vertex(&self, vid: ID) {
+        if let Ok(mut lru) = self.lru.lock() {
+            lru.remove_vertex(vid);
+        }
+    }
+
+    /// Remove all cached adjacency lists
+    pub fn clear(&self) {
+        if let Ok(mut lru) = self.lru.lock() {
+            lru.clear();
+        }
+    }
+
+    pub fn hits(&self) -> u64 {
+        self.hits.get()
+    }
+
+    pub fn misses(&self) -> u64 {
+        self.misses.get()
+    }
+
+    /// The estimated bytes of all cached adjacency lists
+    pub fn used_bytes(&self) -> usize {
+        self.lru.lock().map(|lru| lru.bytes).unwrap_or(0)
+    }
+}
+
+impl Drop for AdjacencyCache {
+    fn drop(&mut self) {
+        pegasus::metrics::add_cache_accesses("adjacency", self.hits.get(), self.misses.get());
+    }
+}
+
+lazy_static! {
+    /// (job id, worker index) -> the adjacency cache of the worker, which is released once all
+    /// operators of the worker are dropped
+    static ref WORKER_CACHES: Mutex<HashMap<(u64, u32), Weak<AdjacencyCache>>> =
+        Mutex::new(HashMap::new());
+}
+
+/// Get the adjacency cache of the current worker, or create one following the `cache_limit` of
+/// the job. Return `None` if the cache is disabled, or it is not called while building the
+/// dataflow of a worker.
+pub fn get_worker_cache() -> Option<Arc<AdjacencyCache>> {
+    let worker = pegasus::get_current_worker()?;
+    let limit = pegasus::get_current_job_conf()?.cache_limit;
+    if limit == 0 {
+        return None;
+    }
+    let mut caches = WORKER_CACHES.lock().ok()?;
+    caches.retain(|_, cache| cache.strong_count() > 0);
+    let key = (worker.job_id, worker.index);
+    if let Some(cache) = caches.get(&key).and_then(|cache| cache.upgrade()) {
+        Some(cache)
+    } else {
+        let cache = Arc::new(AdjacencyCache::new(limit as usize));
+        caches.insert(key, Arc::downgrade(&cache));
+        Some(cache)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn adjacency(len: usize) -> Vec<(ID, LabelId)> {
+        (0..len).map(|i| (i as ID, 0.into())).collect()
+    }
+
+    #[test]
+    fn test_adjacency_cache_lru() {
+        let key = |vid: ID| AdjacencyKey::new(vid, Direction::Out, None);
+        let entry_bytes = key(0).entry_bytes(&Arc::new(adjacency(4)));
+        // room for two entries
+        let cache = AdjacencyCache::new(entry_bytes * 2 + 1);
+        assert_eq!(cache.get_or_load(key(1), || adjacency(4)).len(), 4);
+        cache.get_or_load(key(2), || adjacency(4));
+        // touch 1, so 2 is the least recently used
+        cache.get_or_load(key(1), || unreachable!());
+        cache.get_or_load(key(3), || adjacency(4));
+        assert_eq!((cache.hits(), cache.misses()), (1, 3));
+        assert_eq!(cache.used_bytes(), entry_bytes * 2);
+        cache.get_or_load(key(1), || unreachable!());
+        cache.get_or_load(key(3), || unreachable!());
+        cache.get_or_load(key(2), || adjacency(4));
+        assert_eq!((cache.hits(), cache.misses()), (3, 4));
+
+        // too large to be cached
+        cache.get_or_load(key(4), || adjacency(1024));
+        cache.get_or_load(key(4), || adjacency(1024));
+        assert_eq!(cache.misses(), 6);
+    }
+
+    #[test]
+    fn test_adjacency_cache_key() {
+        let cache = AdjacencyCache::new(1 << 20);
+        let labels: Vec<LabelId> = vec![2.into(), 1.into()];
+        cache.get_or_load(AdjacencyKey::new(1, Direction::Out, Some(&labels)), || adjacency(1));
+        let labels: Vec<LabelId> = vec![1.into(), 2.into(), 2.into()];
+        cache.get_or_load(AdjacencyKey::new(1, Direction::Out, Some(&labels)), || unreachable!());
+        cache.get_or_load(AdjacencyKey::new(1, Direction::In, Some(&labels)), || adjacency(1));
+        cache.get_or_load(AdjacencyKey::new(1, Direction::Out, None), || adjacency(1));
+        assert_eq!((cache.hits(), cache.misses()), (1, 3));
+    }
+
+    #[test]
+    fn test_adjacency_cache_invalidate() {
+        let cache = AdjacencyCache::new(1 << 20);
+        cache.get_or_load(AdjacencyKey::new(1, Direction::Out, None), || adjacency(1));
+        cache.get_or_load(AdjacencyKey::new(1, Direction::In, None), || adjacency(1));
+        cache.get_or_load(AdjacencyKey::new(2, Direction::Out, None), || adjacency(1));
+        cache.invalidate_vertex(1);
+        cache.get_or_load(AdjacencyKey::new(2, Direction::Out, None), || unreachable!());
+        cache.get_or_load(AdjacencyKey::new(1, Direction::Out, None), || adjacency(2));
+        assert_eq!(
+            cache.get_or_load(AdjacencyKey::new(1, Direction::Out, None), || unreachable!()).len(),
+            2
+        );
+        cache.clear();
+        assert_eq!(cache.used_bytes(), 0);
+    }
+
+    #[test]
+    fn test_adjacency_cache_concurrent() {
+        let cache = Arc::new(AdjacencyCache::new(1 << 12));
+        let mut handles = vec![];
+        for t in 0..4 {
+            let cache = cache.clone();
+            handles.push(std::thread::spawn(move || {
+                for i in 0..1000 {
+                    let vid = ((i * 7 + t) % 64) as ID;
+                    let adj = cache
+                        .get_or_load(AdjacencyKey::new(vid, Direction::Out, None), || {
+                            adjacency(vid as usize % 8)
+                        });
+                    assert_eq!(adj.len(), vid as usize % 8);
+                    if i % 100 == 0 {
+                        cache.invalidate_vertex(vid);
+                    }
+                }
+            }));
+        }
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(cache.hits() + cache.misses(), 4000);
+        assert!(cache.used_bytes() <= 1 << 12);
+    }
+}
@@ -14,11 +14,13 @@
 //! limitations under the License.
 
 use crate::structure::property::DynDetails;
+use crate::{str_to_dyn_error, DynError, DynResult};
 use dyn_type::object::Primitives;
 use dyn_type::Object;
 pub use edge::Edge;
 use graph_store::common::LabelId;
 use pegasus::codec::{Decode, Encode, ReadExt, WriteExt};
+use std::convert::TryFrom;
 use std::fmt::Debug;
 use std::io;
 use std::ops::{Deref, DerefMut};
@@ -50,6 +52,29 @@ pub fn read_id<R: ReadExt>(reader: &mut R) -> io::Result<ID> {
     reader.read_u128()
 }
 
+/// Split the id into its lower and higher 64 bits, as the ids are given by `int64` in the steps,
+/// with the higher bits aside, which are 0 unless the id is of 128 bits, e.g. of an edge
+#[cfg(not(feature = "llong_id"))]
+pub fn split_id(id: ID) -> (i64, u64) {
+    (id as i64, 0)
+}
+
+/// Join the lower and higher 64 bits split by `split_id` into the id
+#[cfg(not(feature = "llong_id"))]
+pub fn join_id(low: i64, _high: u64) -> ID {
+    low as ID
+}
+
+#[cfg(feature = "llong_id")]
+pub fn split_id(id: ID) -> (i64, u64) {
+    (id as u64 as i64, (id >> 64) as u64)
+}
+
+#[cfg(feature = "llong_id")]
+pub fn join_id(low: i64, high: u64) -> ID {
+    ((high as ID) << 64) | (low as u64 as ID)
+}
+
 #[derive(Clone, Debug, PartialOrd, Ord, PartialEq, Eq, Hash)]
 pub enum Label {
     Str(String),
@@ -60,17 +85,23 @@ impl Label {
     pub fn as_object(&self) -> Object {
         match self {
             Label::Str(s) => Object::String(s.to_string()),
-            Label::Id(id) => Object::Primitive(Primitives::Integer(*id as i32)),
+            Label::Id(id) => Object::Primitive(Primitives::Integer((*id).into())),
         }
     }
 }
 
+/// The label id given by the steps, failing if the id is out of the range of the labels instead
+/// of being truncated to the id of another label
+pub fn label_id_from_pb(id: i32) -> DynResult<LabelId> {
+    LabelId::try_from(id).map_err(|_| str_to_dyn_error(&format!("label id {} is out of range", id)))
+}
+
 impl Encode for Label {
     fn write_to<W: WriteExt>(&self, writer: &mut W) -> io::Result<()> {
         match self {
             Label::Id(id) => {
                 writer.write_u8(0)?;
-                writer.write_u8(*id)?;
+                writer.write_u8((*id).into())?;
             }
             Label::Str(str) => {
                 writer.write_u8(1)?;
@@ -87,7 +118,7 @@ impl Decode for Label {
         match e {
             0 => {
                 let label_id = reader.read_u8()?;
-                Ok(Label::Id(label_id))
+                Ok(Label::Id(label_id.into()))
             }
             1 => {
                 let str = <String>::read_from(reader)?;
@@ -117,6 +148,69 @@ pub enum VertexOrEdge {
     E(Edge),
 }
 
+/// Whether a graph element is a vertex or an edge, e.g. to tell what a step requires
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ElementKind {
+    Vertex,
+    Edge,
+}
+
+impl ElementKind {
+    /// The plural name of the kind as used in error messages, e.g. "outV() requires edges"
+    pub fn plural(&self) -> &'static str {
+        match self {
+            ElementKind::Vertex => "vertices",
+            ElementKind::Edge => "edges",
+        }
+    }
+}
+
+impl VertexOrEdge {
+    #[inline]
+    pub fn kind(&self) -> ElementKind {
+        match self {
+            VertexOrEdge::V(_) => ElementKind::Vertex,
+            VertexOrEdge::E(_) => ElementKind::Edge,
+        }
+    }
+
+    #[inline]
+    pub fn as_vertex(&self) -> Option<&Vertex> {
+        match self {
+            VertexOrEdge::V(v) => Some(v),
+            VertexOrEdge::E(_) => None,
+        }
+    }
+
+    #[inline]
+    pub fn as_edge(&self) -> Option<&Edge> {
+        match self {
+            VertexOrEdge::V(_) => None,
+            VertexOrEdge::E(e) => Some(e),
+        }
+    }
+
+    /// Get the vertex, or an error telling `step` requires vertices if it is an edge
+    pub fn expect_vertex(&self, step: &str) -> DynResult<&Vertex> {
+        self.as_vertex().ok_or_else(|| self.kind_error(step, ElementKind::Vertex))
+    }
+
+    /// Get the edge, or an error telling `step` requires edges if it is a vertex
+    pub fn expect_edge(&self, step: &str) -> DynResult<&Edge> {
+        self.as_edge().ok_or_else(|| self.kind_error(step, ElementKind::Edge))
+    }
+
+    fn kind_error(&self, step: &str, expected: ElementKind) -> DynError {
+        str_to_dyn_error(&format!(
+            "{} requires {}, but got {:?} of {}",
+            step,
+            expected.plural(),
+            self,
+            self.kind().plural()
+        ))
+    }
+}
+
 impl Debug for VertexOrEdge {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
@@ -287,3 +381,26 @@ impl Decode for GraphElement {
         Ok(GraphElement { element, attached })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::structure::property::DefaultDetails;
+
+    #[test]
+    fn downcast_element_test() {
+        let v =
+            GraphElement::from(Vertex::new(1, None, DefaultDetails::new(1, Label::Id(0.into()))));
+        assert_eq!(v.kind(), ElementKind::Vertex);
+        assert_eq!(v.as_vertex().map(|v| v.id), Some(1));
+        assert!(v.as_edge().is_none());
+        let err = v.expect_edge("outV()").err().unwrap();
+        assert_eq!(err.to_string(), "outV() requires edges, but got v[1] of vertices");
+
+        let details = DynDetails::new(DefaultDetails::new(2, Label::Id(0.into())));
+        let e = GraphElement::from(Edge::new(2, None, 1, 3, details));
+        assert_eq!(e.kind(), ElementKind::Edge);
+        assert_eq!(e.as_edge().map(|e| (e.src_id, e.dst_id)), Some((1, 3)));
+        assert!(e.expect_vertex("out()").is_err());
+    }
+}
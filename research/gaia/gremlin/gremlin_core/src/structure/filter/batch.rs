@@ -0,0 +1,705 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The filters of the elements evaluated by batches, e.g. of a scan, where the properties the
+//! filters compare are read out of the elements of a batch into columns in advance. A property
+//! compared to a number or a string is evaluated by a tight loop over its column, while the other
+//! predicates are tested element by element, either of which tells exactly what `Filter::test`
+//! tells of each element.
+
+use crate::structure::filter::compare::{approx_eq_f64, to_f64, Compare, EqCmp, OrdCmp};
+use crate::structure::filter::element::{ElementFilter, ExpectValue};
+use crate::structure::filter::{Chain, ChainKind, Filter, Predicate};
+use crate::structure::{BiPredicate, Details};
+use crate::Element;
+use dyn_type::{BorrowObject, Object, Primitives};
+
+/// The number of elements of a batch a scan evaluates its filter over
+pub const DEFAULT_BATCH_SIZE: usize = 1024;
+
+/// A fixed number of bits, whose bits beyond the length are always unset
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Bitmap {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl Bitmap {
+    /// The bitmap of `len` unset bits
+    pub fn new(len: usize) -> Self {
+        Bitmap { words: vec![0; len.div_ceil(64)], len }
+    }
+
+    /// The bitmap of `len` set bits
+    pub fn ones(len: usize) -> Self {
+        let mut bitmap = Bitmap::new(len);
+        bitmap.negate();
+        bitmap
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Bitmap { words: Vec::with_capacity(capacity.div_ceil(64)), len: 0 }
+    }
+
+    /// The bitmap of whether each of the values satisfies the function
+    #[inline]
+    fn from_fn<T: Copy, F: Fn(T) -> bool>(values: &[T], func: F) -> Self {
+        let mut words = Vec::with_capacity(values.len().div_ceil(64));
+        for chunk in values.chunks(64) {
+            let mut word = 0u64;
+            for (i, v) in chunk.iter().enumerate() {
+                word |= (func(*v) as u64) << i;
+            }
+            words.push(word);
+        }
+        Bitmap { words, len: values.len() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[inline]
+    pub fn get(&self, index: usize) -> bool {
+        index < self.len && self.words[index / 64] & (1 << (index % 64)) != 0
+    }
+
+    #[inline]
+    pub fn set(&mut self, index: usize, value: bool) {
+        assert!(index < self.len, "bit {} is out of {} bits", index, self.len);
+        if value {
+            self.words[index / 64] |= 1 << (index % 64);
+        } else {
+            self.words[index / 64] &= !(1 << (index % 64));
+        }
+    }
+
+    #[inline]
+    pub fn push(&mut self, value: bool) {
+        if self.len.is_multiple_of(64) {
+            self.words.push(0);
+        }
+        self.len += 1;
+        self.set(self.len - 1, value);
+    }
+
+    pub fn count_ones(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    /// The indexes of the set bits in ascending order
+    pub fn iter_ones(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.len).filter(move |i| self.get(*i))
+    }
+
+    pub fn negate(&mut self) {
+        for word in self.words.iter_mut() {
+            *word = !*word;
+        }
+        if !self.len.is_multiple_of(64) {
+            if let Some(last) = self.words.last_mut() {
+                *last &= (1 << (self.len % 64)) - 1;
+            }
+        }
+    }
+
+    pub fn and_with(&mut self, other: &Bitmap) {
+        assert_eq!(self.len, other.len);
+        for (word, other) in self.words.iter_mut().zip(other.words.iter()) {
+            *word &= *other;
+        }
+    }
+
+    pub fn or_with(&mut self, other: &Bitmap) {
+        assert_eq!(self.len, other.len);
+        for (word, other) in self.words.iter_mut().zip(other.words.iter()) {
+            *word |= *other;
+        }
+    }
+
+    /// Unset the bits set in `other`
+    pub fn and_not_with(&mut self, other: &Bitmap) {
+        assert_eq!(self.len, other.len);
+        for (word, other) in self.words.iter_mut().zip(other.words.iter()) {
+            *word &= !*other;
+        }
+    }
+}
+
+/// What a predicate tells of each element of a batch, i.e. `Some(true)`, `Some(false)`, or `None`
+/// if it can't tell, e.g. the element has no such property
+#[derive(Clone, Debug, Default)]
+pub struct Selection {
+    known: Bitmap,
+    // always unset where unknown
+    value: Bitmap,
+}
+
+impl Selection {
+    /// The selection of `len` elements none of which is told yet
+    pub fn new(len: usize) -> Self {
+        Selection { known: Bitmap::new(len), value: Bitmap::new(len) }
+    }
+
+    fn constant(len: usize, value: Option<bool>) -> Self {
+        match value {
+            Some(true) => Selection { known: Bitmap::ones(len), value: Bitmap::ones(len) },
+            Some(false) => Selection { known: Bitmap::ones(len), value: Bitmap::new(len) },
+            None => Selection::new(len),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.known.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.known.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<bool> {
+        if self.known.get(index) {
+            Some(self.value.get(index))
+        } else {
+            None
+        }
+    }
+
+    pub fn set(&mut self, index: usize, value: Option<bool>) {
+        self.known.set(index, value.is_some());
+        self.value.set(index, value.unwrap_or(false));
+    }
+
+    /// The elements told `Some(true)`
+    pub fn selected(&self) -> &Bitmap {
+        &self.value
+    }
+
+    pub fn into_selected(self) -> Bitmap {
+        self.value
+    }
+}
+
+enum Values<'a> {
+    /// none of the values is present yet
+    Empty,
+    Int(Vec<i32>),
+    Long(Vec<i64>),
+    Float(Vec<f64>),
+    Str(Vec<&'a str>),
+    /// the values of the other types, or of more than one type
+    Other(Vec<Option<BorrowObject<'a>>>),
+}
+
+/// The values of a property of the elements of a batch, in the order of the elements, which are
+/// kept typed if all of them are of the same type of int, long, float or string
+pub struct Column<'a> {
+    values: Values<'a>,
+    present: Bitmap,
+}
+
+impl<'a> Column<'a> {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Column { values: Values::Empty, present: Bitmap::with_capacity(capacity) }
+    }
+
+    pub fn len(&self) -> usize {
+        self.present.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.present.is_empty()
+    }
+
+    /// Append the value of the property of the next element, or `None` if it has no such property
+    pub fn push(&mut self, value: Option<BorrowObject<'a>>) {
+        let len = self.len();
+        self.present.push(value.is_some());
+        let value = match (&mut self.values, value) {
+            (Values::Int(values), None) => push(values, 0),
+            (Values::Long(values), None) => push(values, 0),
+            (Values::Float(values), None) => push(values, 0.0),
+            (Values::Str(values), None) => push(values, ""),
+            (Values::Other(values), None) => push(values, None),
+            (Values::Int(values), Some(BorrowObject::Primitive(Primitives::Integer(v)))) => {
+                push(values, v)
+            }
+            (Values::Long(values), Some(BorrowObject::Primitive(Primitives::Long(v)))) => {
+                push(values, v)
+            }
+            (Values::Float(values), Some(BorrowObject::Primitive(Primitives::Float(v)))) => {
+                push(values, v)
+            }
+            (Values::Str(values), Some(BorrowObject::String(v))) => push(values, v),
+            (Values::Other(values), Some(v)) => push(values, Some(v)),
+            (_, value) => value,
+        };
+        // the first value present, or one of another type than the column
+        if let Some(v) = value {
+            if let Values::Empty = self.values {
+                self.values = Values::start(v, len);
+            } else {
+                let mut values = self.take_objects();
+                values.push(Some(v));
+                self.values = Values::Other(values);
+            }
+        }
+    }
+
+    /// The value of the element at the index as an owned object, or `None` if it has no such
+    /// property
+    pub fn to_object(&self, index: usize) -> Option<Object> {
+        if !self.present.get(index) {
+            return None;
+        }
+        match &self.values {
+            Values::Empty => None,
+            Values::Int(values) => Some(Object::from(values[index])),
+            Values::Long(values) => Some(Object::from(values[index])),
+            Values::Float(values) => Some(Object::from(values[index])),
+            Values::Str(values) => Some(Object::from(values[index])),
+            Values::Other(values) => values[index].as_ref().and_then(|v| v.try_to_owned()),
+        }
+    }
+
+    // the values so far as objects, once the column is of more than one type
+    fn take_objects(&mut self) -> Vec<Option<BorrowObject<'a>>> {
+        let present = &self.present;
+        let wrap = |i: usize, v: BorrowObject<'a>| if present.get(i) { Some(v) } else { None };
+        match std::mem::replace(&mut self.values, Values::Empty) {
+            Values::Empty => vec![],
+            Values::Int(values) => values
+                .into_iter()
+                .enumerate()
+                .map(|(i, v)| wrap(i, BorrowObject::Primitive(Primitives::Integer(v))))
+                .collect(),
+            Values::Long(values) => values
+                .into_iter()
+                .enumerate()
+                .map(|(i, v)| wrap(i, BorrowObject::Primitive(Primitives::Long(v))))
+                .collect(),
+            Values::Float(values) => values
+                .into_iter()
+                .enumerate()
+                .map(|(i, v)| wrap(i, BorrowObject::Primitive(Primitives::Float(v))))
+                .collect(),
+            Values::Str(values) => values
+                .into_iter()
+                .enumerate()
+                .map(|(i, v)| wrap(i, BorrowObject::String(v)))
+                .collect(),
+            Values::Other(values) => values,
+        }
+    }
+
+    /// Test the value of each element having the property by the function
+    fn test_each<F>(&self, func: F, out: &mut Selection)
+    where
+        F: Fn(&BorrowObject) -> Option<bool>,
+    {
+        *out = Selection::new(self.len());
+        for i in self.present.iter_ones() {
+            let value = match &self.values {
+                Values::Empty => continue,
+                Values::Int(values) => {
+                    func(&BorrowObject::Primitive(Primitives::Integer(values[i])))
+                }
+                Values::Long(values) => func(&BorrowObject::Primitive(Primitives::Long(values[i]))),
+                Values::Float(values) => {
+                    func(&BorrowObject::Primitive(Primitives::Float(values[i])))
+                }
+                Values::Str(values) => func(&BorrowObject::String(values[i])),
+                Values::Other(values) => values[i].as_ref().and_then(&func),
+            };
+            out.set(i, value);
+        }
+    }
+}
+
+#[inline]
+fn push<'a, T>(values: &mut Vec<T>, value: T) -> Option<BorrowObject<'a>> {
+    values.push(value);
+    None
+}
+
+impl<'a> Values<'a> {
+    // the values of `len` absent ones followed by the first present one
+    fn start(value: BorrowObject<'a>, len: usize) -> Self {
+        match value {
+            BorrowObject::Primitive(Primitives::Integer(v)) => {
+                let mut values = vec![0; len];
+                values.push(v);
+                Values::Int(values)
+            }
+            BorrowObject::Primitive(Primitives::Long(v)) => {
+                let mut values = vec![0; len];
+                values.push(v);
+                Values::Long(values)
+            }
+            BorrowObject::Primitive(Primitives::Float(v)) => {
+                let mut values = vec![0.0; len];
+                values.push(v);
+                Values::Float(values)
+            }
+            BorrowObject::String(v) => {
+                let mut values = vec![""; len];
+                values.push(v);
+                Values::Str(values)
+            }
+            v => {
+                let mut values: Vec<_> = (0..len).map(|_| None).collect();
+                values.push(Some(v));
+                Values::Other(values)
+            }
+        }
+    }
+}
+
+/// A batch of elements with the columns of their properties, borrowed from where the elements are
+/// kept, e.g. the rows of the storage
+pub struct ColumnBatch<'a, E> {
+    elements: &'a [E],
+    columns: Vec<(String, Column<'a>)>,
+}
+
+impl<'a, E: Element> ColumnBatch<'a, E> {
+    pub fn new(elements: &'a [E]) -> Self {
+        ColumnBatch { elements, columns: vec![] }
+    }
+
+    /// The batch with the columns of the properties of `keys` read out of the elements
+    pub fn with_properties(elements: &'a [E], keys: &[String]) -> Self {
+        let mut batch = ColumnBatch::new(elements);
+        for key in keys {
+            let mut column = Column::with_capacity(elements.len());
+            for element in elements {
+                column.push(element.details().get_property(key));
+            }
+            batch.add_column(key.clone(), column);
+        }
+        batch
+    }
+
+    /// Add the column of the property of `key`, whose values must be in the order of the elements
+    pub fn add_column(&mut self, key: String, column: Column<'a>) {
+        assert_eq!(column.len(), self.elements.len(), "column {} of another length", key);
+        self.columns.push((key, column));
+    }
+
+    pub fn column(&self, key: &str) -> Option<&Column<'a>> {
+        self.columns.iter().find(|(k, _)| k == key).map(|(_, column)| column)
+    }
+
+    pub fn elements(&self) -> &'a [E] {
+        self.elements
+    }
+
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+}
+
+impl ElementFilter {
+    /// Evaluate the filter over the elements of the batch, which is vectorized for the comparison
+    /// of a property in the batch to a number or a string, and tests each element otherwise
+    pub fn eval_batch<E: Element>(&self, batch: &ColumnBatch<E>, out: &mut Selection) {
+        match self {
+            ElementFilter::PassBy(v) => *out = Selection::constant(batch.len(), Some(*v)),
+            ElementFilter::HasProperty(f) => match (batch.column(&f.key), &f.expect) {
+                (Some(column), ExpectValue::Local(right)) => {
+                    eval_column(&f.cmp, column, right, out)
+                }
+                _ => self.eval_each(batch, out),
+            },
+            _ => self.eval_each(batch, out),
+        }
+    }
+
+    fn eval_each<E: Element>(&self, batch: &ColumnBatch<E>, out: &mut Selection) {
+        *out = Selection::new(batch.len());
+        for (i, element) in batch.elements().iter().enumerate() {
+            out.set(i, Predicate::<E>::test(self, element));
+        }
+    }
+}
+
+impl<E: Element> Filter<E, ElementFilter> {
+    /// Select the elements of the batch the filter tells `Some(true)` by `Filter::test`
+    pub fn select_batch(&self, batch: &ColumnBatch<E>) -> Bitmap {
+        let mut out = Selection::default();
+        self.eval_batch(batch, &mut out);
+        out.into_selected()
+    }
+
+    /// Evaluate the filter over the elements of the batch, which tells of each element exactly
+    /// what `Filter::test` tells
+    pub fn eval_batch(&self, batch: &ColumnBatch<E>, out: &mut Selection) {
+        match self {
+            Filter::Ph(_) => *out = Selection::constant(batch.len(), Some(true)),
+            Filter::Simple(p) => p.eval_batch(batch, out),
+            Filter::Chain(chain) => chain.eval_batch(batch, out),
+        }
+    }
+}
+
+impl<E: Element> Chain<E, ElementFilter> {
+    // the same as `Chain::test` on each element, whose nodes are folded from left to right until
+    // one tells `None`, or the result can't be changed by the next connection
+    fn eval_batch(&self, batch: &ColumnBatch<E>, out: &mut Selection) {
+        let len = batch.len();
+        let mut result = Bitmap::new(len);
+        let mut unknown = Bitmap::new(len);
+        // the elements whose results are decided
+        let mut done = Bitmap::new(len);
+        let mut next = ChainKind::Or;
+        let mut node_out = Selection::default();
+        for node in self.list.iter() {
+            if done.count_ones() == len {
+                break;
+            }
+            node.filter.eval_batch(batch, &mut node_out);
+            let mut active = done.clone();
+            active.negate();
+            let mut missing = active.clone();
+            missing.and_not_with(&node_out.known);
+            unknown.or_with(&missing);
+            done.or_with(&missing);
+            active.and_with(&node_out.known);
+            match next {
+                ChainKind::And => {
+                    let mut falsified = active.clone();
+                    falsified.and_not_with(&node_out.value);
+                    result.and_not_with(&falsified);
+                }
+                ChainKind::Or => {
+                    let mut verified = active.clone();
+                    verified.and_with(&node_out.value);
+                    result.or_with(&verified);
+                }
+            }
+            next = node.next;
+            match next {
+                ChainKind::And => active.and_not_with(&result),
+                ChainKind::Or => active.and_with(&result),
+            }
+            done.or_with(&active);
+        }
+        result.and_not_with(&unknown);
+        unknown.negate();
+        *out = Selection { known: unknown, value: result };
+    }
+}
+
+/// The value compared to those of a column of numbers, as taken by the scalar comparison
+enum Operand<T> {
+    /// of the same type as the column
+    Exact(T),
+    /// a float the numbers are taken as floats to be compared to
+    Float(f64),
+    /// a number the numbers can't be compared to, e.g. a long beyond the range of an int
+    Mismatch,
+}
+
+trait Number: Copy + PartialOrd {
+    fn to_f64(self) -> f64;
+}
+
+impl Number for i32 {
+    #[inline]
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+}
+
+impl Number for i64 {
+    #[inline]
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+}
+
+impl Number for f64 {
+    #[inline]
+    fn to_f64(self) -> f64 {
+        self
+    }
+}
+
+fn eval_column(cmp: &Compare, column: &Column, right: &Object, out: &mut Selection) {
+    let vectorized = match (&column.values, right) {
+        (Values::Int(values), Object::Primitive(r)) => {
+            let operand = match r {
+                Primitives::Float(r) => Operand::Float(*r),
+                r => r.as_i32().map(Operand::Exact).unwrap_or(Operand::Mismatch),
+            };
+            eval_numbers(cmp, values, operand, out)
+        }
+        (Values::Long(values), Object::Primitive(r)) => {
+            let operand = match r {
+                Primitives::Float(r) => Operand::Float(*r),
+                r => r.as_i64().map(Operand::Exact).unwrap_or(Operand::Mismatch),
+            };
+            eval_numbers(cmp, values, operand, out)
+        }
+        (Values::Float(values), Object::Primitive(r)) => {
+            // a long is taken as a float by the approximate equality, while it must be a short
+            // to be compared to a float otherwise, as `Primitives::as_f64` requires
+            let operand = match cmp {
+                Compare::ApproxEq(_, _) => Operand::Float(to_f64(r)),
+                _ => r.as_f64().map(Operand::Float).unwrap_or(Operand::Mismatch),
+            };
+            eval_numbers(cmp, values, operand, out)
+        }
+        (Values::Str(values), Object::String(r)) => eval_strings(cmp, values, r.as_str(), out),
+        _ => false,
+    };
+    if vectorized {
+        out.known.and_with(&column.present);
+        out.value.and_with(&column.present);
+    } else {
+        let right = right.as_borrow();
+        column.test_each(|left| cmp.test(left, &right), out);
+    }
+}
+
+// whether the comparison is vectorized, which is not for the custom predicates
+fn eval_numbers<T: Number>(
+    cmp: &Compare, values: &[T], right: Operand<T>, out: &mut Selection,
+) -> bool {
+    let len = values.len();
+    match (cmp, right) {
+        (Compare::Eq(p), Operand::Exact(r)) | (Compare::ApproxEq(p, _), Operand::Exact(r)) => {
+            eval_eq(values, |v| v == r, *p, out)
+        }
+        (Compare::Eq(p), Operand::Float(r)) => eval_eq(values, |v| v.to_f64() == r, *p, out),
+        (Compare::ApproxEq(p, epsilon), Operand::Float(r)) => {
+            let epsilon = *epsilon;
+            eval_eq(values, |v| approx_eq_f64(v.to_f64(), r, epsilon), *p, out)
+        }
+        (Compare::Eq(p), Operand::Mismatch) | (Compare::ApproxEq(p, _), Operand::Mismatch) => {
+            *out = Selection::constant(len, Some(*p == EqCmp::NotEq))
+        }
+        (Compare::Ord(p), Operand::Exact(r)) => eval_ord(values, |v| v, r, *p, out),
+        (Compare::Ord(p), Operand::Float(r)) => eval_ord(values, |v| v.to_f64(), r, *p, out),
+        (Compare::Ord(_), Operand::Mismatch) => *out = Selection::constant(len, None),
+        (Compare::Custom(_), _) => return false,
+    }
+    true
+}
+
+fn eval_strings(cmp: &Compare, values: &[&str], right: &str, out: &mut Selection) -> bool {
+    match cmp {
+        Compare::Eq(p) | Compare::ApproxEq(p, _) => eval_eq(values, |v| v == right, *p, out),
+        Compare::Ord(p) => eval_ord(values, |v| v, right, *p, out),
+        Compare::Custom(_) => return false,
+    }
+    true
+}
+
+#[inline]
+fn eval_eq<T: Copy, F: Fn(T) -> bool>(values: &[T], eq: F, cmp: EqCmp, out: &mut Selection) {
+    let value = match cmp {
+        EqCmp::Eq => Bitmap::from_fn(values, eq),
+        EqCmp::NotEq => Bitmap::from_fn(values, |v| !eq(v)),
+    };
+    *out = Selection { known: Bitmap::ones(values.len()), value };
+}
+
+#[inline]
+fn eval_ord<T, U, F>(values: &[T], map: F, right: U, cmp: OrdCmp, out: &mut Selection)
+where
+    T: Copy,
+    U: Copy + PartialOrd,
+    F: Fn(T) -> U,
+{
+    // unknown if either is NaN
+    let known = Bitmap::from_fn(values, |v| map(v).partial_cmp(&right).is_some());
+    let value = match cmp {
+        OrdCmp::Less => Bitmap::from_fn(values, |v| map(v) < right),
+        OrdCmp::LessEq => Bitmap::from_fn(values, |v| map(v) <= right),
+        OrdCmp::Greater => Bitmap::from_fn(values, |v| map(v) > right),
+        OrdCmp::GreaterEq => Bitmap::from_fn(values, |v| map(v) >= right),
+    };
+    *out = Selection { known, value };
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn bitmap_test() {
+        let mut bitmap = Bitmap::new(70);
+        assert_eq!(bitmap.count_ones(), 0);
+        bitmap.set(3, true);
+        bitmap.set(69, true);
+        assert!(bitmap.get(3) && bitmap.get(69) && !bitmap.get(4));
+        assert_eq!(bitmap.iter_ones().collect::<Vec<_>>(), vec![3, 69]);
+        bitmap.negate();
+        // the bits beyond the length are never set
+        assert_eq!(bitmap.count_ones(), 68);
+        assert!(!bitmap.get(70));
+        let mut other = Bitmap::ones(70);
+        other.set(5, false);
+        bitmap.and_with(&other);
+        assert_eq!(bitmap.count_ones(), 67);
+        bitmap.and_not_with(&other);
+        assert_eq!(bitmap.count_ones(), 0);
+        bitmap.or_with(&other);
+        assert_eq!(bitmap, other);
+    }
+
+    #[test]
+    fn bitmap_push_test() {
+        let mut bitmap = Bitmap::with_capacity(2);
+        for i in 0..130 {
+            bitmap.push(i % 3 == 0);
+        }
+        assert_eq!(bitmap.len(), 130);
+        assert_eq!(bitmap.count_ones(), 44);
+        assert_eq!(Bitmap::from_fn(&(0..130).collect::<Vec<_>>(), |i| i % 3 == 0), bitmap);
+    }
+
+    #[test]
+    fn column_test() {
+        let mut column = Column::with_capacity(4);
+        column.push(None);
+        column.push(Some(BorrowObject::Primitive(Primitives::Integer(1))));
+        assert!(matches!(column.values, Values::Int(ref v) if v == &vec![0, 1]));
+        assert_eq!(column.to_object(0), None);
+        assert_eq!(column.to_object(1), Some(Object::from(1)));
+        // mixed with another type
+        column.push(Some(BorrowObject::String("a")));
+        column.push(None);
+        assert!(matches!(column.values, Values::Other(_)));
+        let mut out = Selection::default();
+        let one = BorrowObject::Primitive(Primitives::Integer(1));
+        column.test_each(|v| Some(v == &one), &mut out);
+        assert_eq!(
+            (0..4).map(|i| out.get(i)).collect::<Vec<_>>(),
+            vec![None, Some(true), Some(false), None]
+        );
+        assert_eq!(column.to_object(2), Some(Object::from("a")));
+        assert_eq!(column.to_object(3), None);
+    }
+}
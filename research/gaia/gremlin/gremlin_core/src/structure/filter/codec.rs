@@ -15,15 +15,34 @@
 
 use crate::generated::common as pb_type;
 use crate::generated::gremlin as pb;
+use crate::structure::filter::compare::resolve_float_epsilon;
 use crate::structure::filter::*;
 use crate::structure::Label;
-use crate::Element;
-use dyn_type::{CastError, Object, Primitives};
-use graph_store::prelude::INVALID_LABEL_ID;
+use crate::{Element, ID};
+use dyn_type::custom::{get_custom_predicate, is_custom_type_registered};
+use dyn_type::{CastError, CustomObject, Object, Primitives};
+use graph_store::parser::DataType;
+use graph_store::prelude::LabelId;
+use graph_store::schema::GraphSchemaInfo;
 use pegasus::BuildJobError;
+use pegasus_server::error::{ErrorCause, QueryError};
 use prost::{DecodeError, Message};
-use std::convert::TryInto;
+use std::collections::HashSet;
+use std::convert::TryFrom;
 use std::fmt::Display;
+use std::sync::Arc;
+
+/// The constructs of the plans decoded but not supported yet, each failing the plan by
+/// `ParseError::Unsupported` instead of panicking the workers
+pub const ARRAY_VALUE: &str = "comparison to a list other than within/without";
+pub const PROPERTY_KEY_ID: &str = "property key by id";
+pub const ID_ORDER: &str = "order comparison of element ids";
+pub const LABEL_ORDER: &str = "order comparison of element labels";
+pub const WITHIN_PROPERTY: &str = "within/without on properties";
+
+/// All the unsupported constructs, as a checklist of the gaps of the decoder
+pub const UNSUPPORTED_FEATURES: [&str; 5] =
+    [ARRAY_VALUE, PROPERTY_KEY_ID, ID_ORDER, LABEL_ORDER, WITHIN_PROPERTY];
 
 pub fn pb_chain_to_filter<E: Element>(
     pb_chain: &pb::FilterChain,
@@ -50,7 +69,7 @@ pub fn pb_chain_to_filter<E: Element>(
                     }
                 }
             }
-            let logic_opr: pb::Connect = unsafe { std::mem::transmute(node.next) };
+            let logic_opr = pb::Connect::from_i32(node.next).ok_or(ParseError::InvalidData)?;
             match logic_opr {
                 pb::Connect::Or => connect = ChainKind::Or,
                 pb::Connect::And => connect = ChainKind::And,
@@ -64,8 +83,60 @@ pub fn pb_chain_to_filter<E: Element>(
     }
 }
 
-pub fn pb_value_to_object(raw: &pb_type::Value) -> Option<Object> {
-    match &raw.item {
+/// Decode the predicates on the values themselves, e.g. of `is(gt(1).and(lt(5)))`, whose keys are
+/// left unset
+pub fn pb_chain_to_value_filter(
+    pb_chain: &pb::FilterChain,
+) -> Result<Option<Filter<Object, ValueFilter>>, ParseError> {
+    let mut chain = Filter::default();
+    let mut connect = ChainKind::Or;
+    for node in pb_chain.node.iter() {
+        if let Some(f) = parse_value_node(node)? {
+            match connect {
+                ChainKind::And => {
+                    chain.and(f);
+                }
+                ChainKind::Or => {
+                    chain.or(f);
+                }
+            }
+        }
+        let logic_opr = pb::Connect::from_i32(node.next).ok_or(ParseError::InvalidData)?;
+        match logic_opr {
+            pb::Connect::Or => connect = ChainKind::Or,
+            pb::Connect::And => connect = ChainKind::And,
+        }
+    }
+    if chain.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(chain))
+    }
+}
+
+fn parse_value_node(
+    node: &pb::FilterNode,
+) -> Result<Option<Filter<Object, ValueFilter>>, ParseError> {
+    if let Some(single) = get_single(node) {
+        if single.left.as_ref().map(|key| key.item.is_some()).unwrap_or(false) {
+            return Err(ParseError::OtherErr("the key of a value predicate must be unset".into()));
+        }
+        let right = single.right.as_ref().ok_or(ParseError::InvalidData)?;
+        check_custom_value(right)?;
+        let f = ValueFilter::from_exp(single.cmp, right, single.epsilon)?;
+        Ok(Some(Filter::with(f)))
+    } else if let Some(chain_bytes) = get_chain(node) {
+        let chain = Message::decode(chain_bytes.as_slice())?;
+        pb_chain_to_value_filter(&chain)
+    } else {
+        Err(ParseError::InvalidData)
+    }
+}
+
+/// Decode a single value, or `None` for the none value. The lists are only decoded as the sets of
+/// within and without, and are unsupported elsewhere
+pub fn pb_value_to_object(raw: &pb_type::Value) -> Result<Option<Object>, ParseError> {
+    let object = match &raw.item {
         Some(pb_type::value::Item::Blob(blob)) => {
             let mut bytes = vec![0; blob.len()];
             bytes.copy_from_slice(blob);
@@ -76,12 +147,40 @@ pub fn pb_value_to_object(raw: &pb_type::Value) -> Option<Object> {
         Some(pb_type::value::Item::I64(item)) => Some((*item).into()),
         Some(pb_type::value::Item::F64(item)) => Some((*item).into()),
         Some(pb_type::value::Item::Str(item)) => Some(item.as_str().into()),
-        Some(pb_type::value::Item::I32Array(_)) => unimplemented!(),
-        Some(pb_type::value::Item::I64Array(_)) => unimplemented!(),
-        Some(pb_type::value::Item::F64Array(_)) => unimplemented!(),
-        Some(pb_type::value::Item::StrArray(_)) => unimplemented!(),
+        Some(pb_type::value::Item::I32Array(_))
+        | Some(pb_type::value::Item::I64Array(_))
+        | Some(pb_type::value::Item::F64Array(_))
+        | Some(pb_type::value::Item::StrArray(_)) => {
+            return Err(ParseError::Unsupported(ARRAY_VALUE))
+        }
         Some(pb_type::value::Item::None(_)) => None,
+        Some(pb_type::value::Item::Custom(custom)) => {
+            Some(Object::Custom(CustomObject::new(custom.tag.as_str(), custom.bytes.clone())))
+        }
         _ => None,
+    };
+    Ok(object)
+}
+
+/// Whether the constant can be compared to the values of a property of the data type, where the
+/// numbers are coerced to each other, e.g. an i32 to a long or a date, but never to a string.
+pub fn is_comparable(data_type: &DataType, value: &pb_type::Value) -> bool {
+    use pb_type::value::Item;
+    let is_numeric = match data_type {
+        DataType::String => false,
+        DataType::Integer | DataType::Long | DataType::Double | DataType::Date | DataType::ID => {
+            true
+        }
+        // which are not stored as properties
+        DataType::NULL | DataType::LABEL => return true,
+    };
+    match value.item.as_ref() {
+        Some(Item::I32(_)) | Some(Item::I64(_)) | Some(Item::F64(_)) => is_numeric,
+        Some(Item::I32Array(_)) | Some(Item::I64Array(_)) | Some(Item::F64Array(_)) => is_numeric,
+        Some(Item::Str(_)) | Some(Item::StrArray(_)) => !is_numeric,
+        Some(Item::Boolean(_)) => false,
+        // e.g. none for the existence of the property, or the custom types unknown to the schema
+        Some(Item::Blob(_)) | Some(Item::Custom(_)) | Some(Item::None(_)) | None => true,
     }
 }
 
@@ -103,14 +202,19 @@ pub fn parse_node<E: Element>(
     node: &pb::FilterNode,
 ) -> Result<Option<Filter<E, ElementFilter>>, ParseError> {
     if let Some(single) = get_single(node) {
-        assert!(single.left.is_some() && single.right.is_some());
-        let right = single.right.as_ref().unwrap();
-        let left = single.left.as_ref().unwrap();
-        let cmp: pb::Compare = { unsafe { std::mem::transmute(single.cmp) } };
+        let left = single.left.as_ref().ok_or(ParseError::InvalidData)?;
+        let right = single.right.as_ref().ok_or(ParseError::InvalidData)?;
+        check_custom_value(right)?;
+        if let Some(schema) = strict_schema() {
+            check_strict_type(&schema, left, right)?;
+        }
+        let cmp = pb::Compare::from_i32(single.cmp).ok_or(ParseError::InvalidData)?;
+        // the epsilon applies to eq and ne only, as the orders and the lists are exact
+        let epsilon = resolve_float_epsilon(single.epsilon);
         let f = match cmp {
-            pb::Compare::Eq => eq(left, right)?,
+            pb::Compare::Eq => eq(left, right, epsilon)?,
             pb::Compare::Ne => {
-                let mut f = eq(left, right)?;
+                let mut f = eq(left, right, epsilon)?;
                 f.reverse();
                 f
             }
@@ -132,6 +236,7 @@ pub fn parse_node<E: Element>(
                 f.reverse();
                 f
             }
+            pb::Compare::Custom => custom(left, right, &single.predicate)?,
         };
         Ok(Some(Filter::with(f)))
     } else {
@@ -144,19 +249,74 @@ pub fn parse_node<E: Element>(
     }
 }
 
+/// The schema of the graph of current job if the graph is loaded in the strict schema mode, see
+/// `graph_store::strict`, by which the constants compared to the properties are checked
+fn strict_schema() -> Option<Arc<GraphSchemaInfo>> {
+    crate::get_graph()?.get_schema().filter(|schema| schema.strict)
+}
+
+/// Check the constant compared to a property against all the types the strict schema records for
+/// the property among the labels, failing by `ParseError::TypeMismatch` if it is incomparable to
+/// any of them, which would otherwise compare to the elements of some labels only. The properties
+/// unknown to the schema are left unchecked.
+pub fn check_strict_type(
+    schema: &GraphSchemaInfo, left: &pb_type::Key, right: &pb_type::Value,
+) -> Result<(), ParseError> {
+    if let Some(pb_type::key::Item::Name(name)) = left.item.as_ref() {
+        let types = schema.get_property_types(name);
+        if !types.iter().all(|data_type| is_comparable(data_type, right)) {
+            return Err(ParseError::TypeMismatch {
+                property: name.clone(),
+                expected: types,
+                found: format!("{:?}", right.item),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// The label of the id compared by a predicate, or `None` if the id is out of the range of the
+/// labels, which is of no element, so that the predicate never matches it instead of matching the
+/// elements of an arbitrary label, see `validate::TypeCheck` to reject such queries beforehand
+fn label_of_id(id: i32) -> Option<Label> {
+    match LabelId::try_from(id) {
+        Ok(label_id) => Some(Label::Id(label_id)),
+        Err(_) => {
+            warn!("label id {} is out of range, which never matches", id);
+            None
+        }
+    }
+}
+
+/// The properties the filter compares, in the order they first appear, e.g. to be read into the
+/// columns of the batches the filter is evaluated over
+pub fn referenced_properties<E: Element>(filter: &Filter<E, ElementFilter>) -> Vec<String> {
+    let mut keys: Vec<String> = vec![];
+    filter.for_each(&mut |p| {
+        if let ElementFilter::HasProperty(f) = p {
+            if !keys.contains(&f.key) {
+                keys.push(f.key.clone());
+            }
+        }
+    });
+    keys
+}
+
 #[inline]
-fn eq(left: &pb_type::Key, right: &pb_type::Value) -> Result<ElementFilter, ParseError> {
-    let right: Option<Object> = pb_value_to_object(right);
+fn eq(
+    left: &pb_type::Key, right: &pb_type::Value, epsilon: f64,
+) -> Result<ElementFilter, ParseError> {
+    let right: Option<Object> = pb_value_to_object(right)?;
     match &left.item {
         Some(pb_type::key::Item::Name(name)) => {
             if let Some(value) = right {
                 // TODO(longbin) String clone, potentially downgrade performance
-                Ok(has_property(name.clone(), value))
+                Ok(has_property_approx(name.clone(), value, epsilon))
             } else {
-                Ok(by_property(name.clone()))
+                Ok(by_property_approx(name.clone(), epsilon))
             }
         }
-        Some(pb_type::key::Item::NameId(_)) => unimplemented!(),
+        Some(pb_type::key::Item::NameId(_)) => Err(ParseError::Unsupported(PROPERTY_KEY_ID)),
         Some(pb_type::key::Item::Id(_)) => {
             #[cfg(not(feature = "llong_id"))]
             let r = right.map(|r| r.as_u64()).transpose()?;
@@ -167,58 +327,103 @@ fn eq(left: &pb_type::Key, right: &pb_type::Value) -> Result<ElementFilter, Pars
         Some(pb_type::key::Item::Label(_)) => {
             let label = right
                 .map(|r| match r {
-                    Object::Primitive(Primitives::Integer(id)) => {
-                        Ok(Label::Id(id.try_into().unwrap_or(INVALID_LABEL_ID)))
-                    }
-                    Object::String(str) => Ok(Label::Str(str)),
+                    Object::Primitive(Primitives::Integer(id)) => Ok(label_of_id(id)),
+                    Object::String(str) => Ok(Some(Label::Str(str))),
                     _ => Err(ParseError::InvalidData),
                 })
                 .transpose()?;
-            Ok(has_label(label))
+            match label {
+                Some(Some(label)) => Ok(has_label(Some(label))),
+                // no label is contained in the empty set
+                Some(None) => Ok(contains_label(HashSet::new())),
+                None => Ok(has_label(None)),
+            }
         }
         _ => Err(ParseError::InvalidData),
     }
 }
 
-#[inline]
-fn lt(left: &pb_type::Key, right: &pb_type::Value) -> Result<ElementFilter, ParseError> {
+/// The property compared by the order predicates, which are only supported on the properties
+fn order_key(left: &pb_type::Key) -> Result<&String, ParseError> {
     match &left.item {
-        Some(pb_type::key::Item::Name(name)) => {
-            let right: Option<Object> = pb_value_to_object(right);
-            if let Some(value) = right {
-                // TODO(longbin) String clone, potentially downgrade performance
-                Ok(has_property_lt(name.clone(), value))
-            } else {
-                Ok(by_property_lt(name.clone()))
-            }
-        }
-        Some(pb_type::key::Item::NameId(_)) => unimplemented!(),
-        Some(pb_type::key::Item::Id(_)) => unimplemented!("can't compare between element id;"),
-        Some(pb_type::key::Item::Label(_)) => {
-            unimplemented!("can't compare between element label;")
-        }
+        Some(pb_type::key::Item::Name(name)) => Ok(name),
+        Some(pb_type::key::Item::NameId(_)) => Err(ParseError::Unsupported(PROPERTY_KEY_ID)),
+        Some(pb_type::key::Item::Id(_)) => Err(ParseError::Unsupported(ID_ORDER)),
+        Some(pb_type::key::Item::Label(_)) => Err(ParseError::Unsupported(LABEL_ORDER)),
         _ => Err(ParseError::InvalidData),
     }
 }
 
+#[inline]
+fn lt(left: &pb_type::Key, right: &pb_type::Value) -> Result<ElementFilter, ParseError> {
+    let name = order_key(left)?;
+    if let Some(value) = pb_value_to_object(right)? {
+        // TODO(longbin) String clone, potentially downgrade performance
+        Ok(has_property_lt(name.clone(), value))
+    } else {
+        Ok(by_property_lt(name.clone()))
+    }
+}
+
 #[inline]
 fn lte(left: &pb_type::Key, right: &pb_type::Value) -> Result<ElementFilter, ParseError> {
-    match &left.item {
-        Some(pb_type::key::Item::Name(name)) => {
-            let right: Option<Object> = pb_value_to_object(right);
-            if let Some(value) = right {
-                Ok(has_property_le(name.clone(), value))
-            } else {
-                Ok(by_property_le(name.clone()))
-            }
+    let name = order_key(left)?;
+    if let Some(value) = pb_value_to_object(right)? {
+        Ok(has_property_le(name.clone(), value))
+    } else {
+        Ok(by_property_le(name.clone()))
+    }
+}
+
+#[inline]
+fn with_in(left: &pb_type::Key, right: &pb_type::Value) -> Result<ElementFilter, ParseError> {
+    use pb_type::key::Item as Key;
+    use pb_type::value::Item as Value;
+    match (&left.item, &right.item) {
+        (Some(Key::Id(_)), Some(Value::I64Array(ids))) => {
+            Ok(contains_id(ids.item.iter().map(|id| *id as ID).collect()))
         }
-        _ => unimplemented!(),
+        (Some(Key::Id(_)), Some(Value::I32Array(ids))) => {
+            Ok(contains_id(ids.item.iter().map(|id| *id as ID).collect()))
+        }
+        (Some(Key::Label(_)), Some(Value::I32Array(labels))) => {
+            Ok(contains_label(labels.item.iter().filter_map(|id| label_of_id(*id)).collect()))
+        }
+        (Some(Key::Label(_)), Some(Value::StrArray(labels))) => {
+            Ok(contains_label(labels.item.iter().map(|name| Label::Str(name.clone())).collect()))
+        }
+        (Some(Key::Name(_)), _) | (Some(Key::NameId(_)), _) => {
+            Err(ParseError::Unsupported(WITHIN_PROPERTY))
+        }
+        _ => Err("within is only supported on the ids by integers and the labels".into()),
     }
 }
 
 #[inline]
-fn with_in(_left: &pb_type::Key, _right: &pb_type::Value) -> Result<ElementFilter, ParseError> {
-    unimplemented!()
+fn custom(
+    left: &pb_type::Key, right: &pb_type::Value, predicate: &str,
+) -> Result<ElementFilter, ParseError> {
+    let right = match pb_value_to_object(right)? {
+        Some(Object::Custom(right)) => right,
+        _ => return Err("the right of a custom predicate must be a custom value".into()),
+    };
+    let func = get_custom_predicate(right.tag(), predicate)
+        .map_err(|e| ParseError::OtherErr(e.to_string()))?;
+    match &left.item {
+        Some(pb_type::key::Item::Name(name)) => Ok(has_property_custom(name.clone(), right, func)),
+        _ => Err("custom predicates are only supported on the properties".into()),
+    }
+}
+
+/// Fail a custom value whose type is not registered on current server, instead of never matching
+fn check_custom_value(value: &pb_type::Value) -> Result<(), ParseError> {
+    if let Some(pb_type::value::Item::Custom(custom)) = value.item.as_ref() {
+        if !is_custom_type_registered(&custom.tag) {
+            let msg = format!("custom type `{}` is not registered", custom.tag);
+            return Err(ParseError::OtherErr(msg));
+        }
+    }
+    Ok(())
 }
 
 #[derive(Debug)]
@@ -227,6 +432,14 @@ pub enum ParseError {
     TypeCast(CastError),
     InvalidData,
     OtherErr(String),
+    /// A construct of the plan not supported yet, one of `UNSUPPORTED_FEATURES`
+    Unsupported(&'static str),
+    /// A constant incomparable to the types recorded for the property by a strict schema
+    TypeMismatch {
+        property: String,
+        expected: Vec<DataType>,
+        found: String,
+    },
 }
 
 impl Display for ParseError {
@@ -236,6 +449,12 @@ impl Display for ParseError {
             ParseError::TypeCast(e) => write!(f, "type cast error {}", e),
             ParseError::InvalidData => write!(f, "invalid data error"),
             ParseError::OtherErr(e) => write!(f, "parse error {}", e),
+            ParseError::Unsupported(feature) => write!(f, "{} is not supported yet", feature),
+            ParseError::TypeMismatch { property, expected, found } => write!(
+                f,
+                "property {} of type {:?} can't be compared to {} by the strict schema",
+                property, expected, found
+            ),
         }
     }
 }
@@ -260,8 +479,74 @@ impl From<CastError> for ParseError {
     }
 }
 
+impl From<ParseError> for QueryError {
+    fn from(e: ParseError) -> Self {
+        let msg = e.to_string();
+        match e {
+            ParseError::Unsupported(_) => {
+                QueryError::Unsupported { op_index: vec![], msg, cause: None }
+            }
+            ParseError::ReadPB(ref pb_err) => {
+                QueryError::Parse { op_index: vec![], msg, cause: Some(ErrorCause::of(pb_err)) }
+            }
+            ParseError::TypeCast(ref cast_err) => {
+                QueryError::Parse { op_index: vec![], msg, cause: Some(ErrorCause::of(cast_err)) }
+            }
+            _ => QueryError::Parse { op_index: vec![], msg, cause: None },
+        }
+    }
+}
+
 impl From<ParseError> for BuildJobError {
     fn from(e: ParseError) -> Self {
-        format!("decode filter error: {}", e).into()
+        // kept as classified, which is told to the client once the job fails to be built
+        BuildJobError::UserError(Box::new(QueryError::from(e)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use graph_store::schema::LabelSchema;
+
+    fn schema() -> GraphSchemaInfo {
+        let label = |id: u8, name: &str, properties: Vec<(&str, DataType)>| LabelSchema {
+            id: id.into(),
+            name: name.to_owned(),
+            properties: properties.into_iter().map(|(p, dt)| (p.to_owned(), dt)).collect(),
+        };
+        GraphSchemaInfo {
+            vertex_labels: vec![
+                label(0, "person", vec![("age", DataType::Integer), ("code", DataType::Long)]),
+                label(1, "software", vec![("lang", DataType::String), ("code", DataType::String)]),
+            ],
+            edge_labels: vec![],
+            relations: vec![],
+            strict: true,
+        }
+    }
+
+    fn check(key: &str, value: pb_type::value::Item) -> Result<(), ParseError> {
+        let left = pb_type::Key { item: Some(pb_type::key::Item::Name(key.to_owned())) };
+        check_strict_type(&schema(), &left, &pb_type::Value { item: Some(value) })
+    }
+
+    #[test]
+    fn strict_type_test() {
+        use pb_type::value::Item;
+        match check("age", Item::Str("29".to_owned())) {
+            Err(ParseError::TypeMismatch { property, expected, .. }) => {
+                assert_eq!(property, "age");
+                assert_eq!(expected, vec![DataType::Integer]);
+            }
+            _ => panic!("expect the age mistyped"),
+        }
+        assert!(check("age", Item::I64(29)).is_ok());
+        assert!(check("lang", Item::Str("java".to_owned())).is_ok());
+        // the codes of the persons are numbers while those of the software are strings
+        assert!(check("code", Item::I32(1)).is_err());
+        assert!(check("code", Item::Str("1".to_owned())).is_err());
+        assert!(check("unknown", Item::Str("x".to_owned())).is_ok());
+        assert!(check("age", Item::None(pb_type::None {})).is_ok());
     }
 }
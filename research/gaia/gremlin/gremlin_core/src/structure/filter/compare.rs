@@ -15,7 +15,110 @@
 
 use crate::structure::filter::element::Reverse;
 use crate::structure::filter::BiPredicate;
+use dyn_type::custom::CustomPredicate;
+use dyn_type::{BorrowObject, CustomObject, Object, Primitives};
 use std::cmp::Ordering;
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+
+/// The relative epsilon of the float equality by default, where two numbers are equal if they
+/// differ by at most the epsilon of the larger magnitude of them
+pub const DEFAULT_FLOAT_EPSILON: f64 = 1e-9;
+
+lazy_static! {
+    /// The relative epsilon of the float equality of the filters not giving their own, following
+    /// the configuration of the server, kept as the bits of the f64
+    static ref FLOAT_EPSILON: AtomicU64 = AtomicU64::new(DEFAULT_FLOAT_EPSILON.to_bits());
+}
+
+/// Set the relative epsilon of the float equality of the filters not giving their own, where 0
+/// compares the floats exactly
+pub fn set_default_float_epsilon(epsilon: f64) {
+    let epsilon = if epsilon.is_finite() && epsilon > 0.0 { epsilon } else { 0.0 };
+    FLOAT_EPSILON.store(epsilon.to_bits(), AtomicOrdering::SeqCst);
+}
+
+pub fn get_default_float_epsilon() -> f64 {
+    f64::from_bits(FLOAT_EPSILON.load(AtomicOrdering::SeqCst))
+}
+
+/// The relative epsilon of the float equality of a filter given by `FilterExp::epsilon`, where 0
+/// follows the default of the server, and a negative one compares the floats exactly
+pub fn resolve_float_epsilon(epsilon: f64) -> f64 {
+    if epsilon == 0.0 {
+        get_default_float_epsilon()
+    } else if epsilon > 0.0 && epsilon.is_finite() {
+        epsilon
+    } else {
+        0.0
+    }
+}
+
+/// Whether the numbers are equal within the relative epsilon if either of them is a float, where
+/// an integer compared to a float is taken as a float, while two integers are compared exactly
+pub fn approx_eq(left: &Primitives, right: &Primitives, epsilon: f64) -> bool {
+    match (left, right) {
+        (Primitives::Float(_), _) | (_, Primitives::Float(_)) => {
+            approx_eq_f64(to_f64(left), to_f64(right), epsilon)
+        }
+        _ => left == right,
+    }
+}
+
+#[inline]
+pub(crate) fn approx_eq_f64(left: f64, right: f64, epsilon: f64) -> bool {
+    left == right || (left - right).abs() <= epsilon * left.abs().max(right.abs())
+}
+
+// as the integers are taken by the equality to a float, e.g. a long beyond the exact range of f64
+#[inline]
+pub(crate) fn to_f64(number: &Primitives) -> f64 {
+    match number {
+        Primitives::Byte(v) => *v as f64,
+        Primitives::Integer(v) => *v as f64,
+        Primitives::Long(v) => *v as f64,
+        Primitives::Float(v) => *v,
+    }
+}
+
+/// The values which may be numbers, whose equality may be within an epsilon
+pub trait AsNumber {
+    fn as_number(&self) -> Option<Primitives>;
+}
+
+impl AsNumber for Object {
+    fn as_number(&self) -> Option<Primitives> {
+        match self {
+            Object::Primitive(p) => Some(*p),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> AsNumber for BorrowObject<'a> {
+    fn as_number(&self) -> Option<Primitives> {
+        match self {
+            BorrowObject::Primitive(p) => Some(*p),
+            _ => None,
+        }
+    }
+}
+
+/// The values which may be of custom types, compared by the predicates registered for the types
+pub trait AsCustom {
+    fn as_custom_object(&self) -> Option<&CustomObject>;
+}
+
+impl AsCustom for Object {
+    fn as_custom_object(&self) -> Option<&CustomObject> {
+        self.as_custom().ok()
+    }
+}
+
+impl<'a> AsCustom for BorrowObject<'a> {
+    fn as_custom_object(&self) -> Option<&CustomObject> {
+        self.as_custom().ok()
+    }
+}
 
 #[derive(Copy, Clone, Eq, PartialEq)]
 pub enum EqCmp {
@@ -71,17 +174,51 @@ impl Reverse for OrdCmp {
     }
 }
 
-#[derive(Copy, Clone, PartialEq)]
+/// A predicate registered for a custom type, e.g. whether a point is within a radius of another,
+/// which is never satisfied by the values of other types
+#[derive(Clone)]
+pub struct CustomCmp {
+    pub func: CustomPredicate,
+    pub negated: bool,
+}
+
+#[derive(Clone)]
 pub enum Compare {
     Eq(EqCmp),
     Ord(OrdCmp),
+    /// The equality of the numbers within the relative epsilon if either is a float, and exact
+    /// for the others
+    ApproxEq(EqCmp, f64),
+    Custom(CustomCmp),
 }
 
-impl<T: PartialOrd> BiPredicate<T, T> for Compare {
+impl Compare {
+    /// The equality of the floats within the relative epsilon, or exact if the epsilon is 0
+    pub fn approx_eq(cmp: EqCmp, epsilon: f64) -> Self {
+        if epsilon > 0.0 {
+            Compare::ApproxEq(cmp, epsilon)
+        } else {
+            Compare::Eq(cmp)
+        }
+    }
+}
+
+impl<T: PartialOrd + AsNumber + AsCustom> BiPredicate<T, T> for Compare {
     fn test(&self, left: &T, right: &T) -> Option<bool> {
         match self {
             Compare::Eq(p) => p.test(left, right),
             Compare::Ord(p) => p.test(left, right),
+            Compare::ApproxEq(p, epsilon) => match (left.as_number(), right.as_number()) {
+                (Some(l), Some(r)) => {
+                    let eq = approx_eq(&l, &r, *epsilon);
+                    Some(if *p == EqCmp::Eq { eq } else { !eq })
+                }
+                _ => p.test(left, right),
+            },
+            Compare::Custom(p) => match (left.as_custom_object(), right.as_custom_object()) {
+                (Some(l), Some(r)) if l.tag() == r.tag() => Some((p.func)(l, r) != p.negated),
+                _ => None,
+            },
         }
     }
 }
@@ -91,6 +228,54 @@ impl Reverse for Compare {
         match self {
             Compare::Eq(x) => x.reverse(),
             Compare::Ord(x) => x.reverse(),
+            Compare::ApproxEq(x, _) => x.reverse(),
+            Compare::Custom(x) => x.negated = !x.negated,
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn approx_eq_test() {
+        let sum = Primitives::Float(0.1 + 0.2);
+        assert!(sum != Primitives::Float(0.3));
+        assert!(approx_eq(&sum, &Primitives::Float(0.3), DEFAULT_FLOAT_EPSILON));
+        assert!(!approx_eq(&sum, &Primitives::Float(0.3), 0.0));
+        assert!(!approx_eq(&Primitives::Float(0.3), &Primitives::Float(0.31), 1e-9));
+        // relative to the magnitude, instead of an absolute difference
+        assert!(approx_eq(&Primitives::Float(1e12), &Primitives::Float(1e12 + 1e-3), 1e-9));
+        assert!(!approx_eq(&Primitives::Float(1e-12), &Primitives::Float(2e-12), 1e-9));
+        assert!(!approx_eq(&Primitives::Float(f64::NAN), &Primitives::Float(f64::NAN), 1e-9));
+        // an integer is taken as a float against a float, while integers are compared exactly
+        assert!(approx_eq(&Primitives::Integer(3), &Primitives::Float(3.0000000001), 1e-9));
+        assert!(approx_eq(&Primitives::Float(3.0000000001), &Primitives::Long(3), 1e-9));
+        assert!(!approx_eq(&Primitives::Long(1 << 60), &Primitives::Long((1 << 60) + 1), 1e-9));
+    }
+
+    #[test]
+    fn approx_compare_test() {
+        let mut cmp = Compare::approx_eq(EqCmp::Eq, 1e-9);
+        let sum = Object::from(0.1 + 0.2);
+        assert_eq!(cmp.test(&sum, &Object::from(0.3)), Some(true));
+        // the strings are compared exactly
+        assert_eq!(cmp.test(&Object::from("a"), &Object::from("a")), Some(true));
+        assert_eq!(cmp.test(&Object::from("a"), &Object::from(0.3)), Some(false));
+        cmp.reverse();
+        assert_eq!(cmp.test(&sum, &Object::from(0.3)), Some(false));
+        assert_eq!(cmp.test(&sum, &Object::from(0.4)), Some(true));
+        // exact if without an epsilon
+        let exact = Compare::approx_eq(EqCmp::Eq, 0.0);
+        assert_eq!(exact.test(&sum, &Object::from(0.3)), Some(false));
+    }
+
+    #[test]
+    fn resolve_float_epsilon_test() {
+        assert_eq!(resolve_float_epsilon(1e-6), 1e-6);
+        assert_eq!(resolve_float_epsilon(-1.0), 0.0);
+        assert_eq!(resolve_float_epsilon(f64::NAN), 0.0);
+        assert_eq!(resolve_float_epsilon(0.0), get_default_float_epsilon());
+    }
+}
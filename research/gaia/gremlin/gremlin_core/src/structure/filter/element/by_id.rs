@@ -19,6 +19,7 @@ use crate::structure::filter::element::{ExpectValue, Reverse};
 use crate::structure::filter::{BiPredicate, Predicate};
 use crate::{Element, ID};
 use std::collections::HashSet;
+use std::sync::Arc;
 
 pub struct HasId {
     pub cmp: EqCmp,
@@ -50,23 +51,24 @@ impl<E: Element> Predicate<E> for HasId {
 
 pub struct ContainsId {
     pub cmp: Contains,
-    pub expect: HashSet<ID>,
+    /// the ids to be contained, which may be thousands, shared by the copies of the filter
+    pub expect: Arc<HashSet<ID>>,
 }
 
 impl ContainsId {
     pub fn with_in(expect: HashSet<ID>) -> Self {
-        ContainsId { cmp: Contains::Within, expect }
+        ContainsId { cmp: Contains::Within, expect: Arc::new(expect) }
     }
 
     pub fn with_out(expect: HashSet<ID>) -> Self {
-        ContainsId { cmp: Contains::Without, expect }
+        ContainsId { cmp: Contains::Without, expect: Arc::new(expect) }
     }
 }
 
 impl<E: Element> Predicate<E> for ContainsId {
     fn test(&self, entry: &E) -> Option<bool> {
         let left = entry.id();
-        self.cmp.test(&left, &self.expect)
+        self.cmp.test(&left, &*self.expect)
     }
 }
 
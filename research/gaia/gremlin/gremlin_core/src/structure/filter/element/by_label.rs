@@ -20,6 +20,7 @@ use crate::structure::filter::element::{ExpectValue, Reverse};
 use crate::structure::filter::{BiPredicate, Predicate};
 use crate::structure::Element;
 use std::collections::HashSet;
+use std::sync::Arc;
 
 pub struct HasLabel {
     pub cmp: EqCmp,
@@ -50,18 +51,19 @@ impl Reverse for HasLabel {
 
 pub struct ContainsLabel {
     pub cmp: Contains,
-    pub expect: HashSet<Label>,
+    /// the labels to be contained, shared by the copies of the filter
+    pub expect: Arc<HashSet<Label>>,
 }
 
 impl<E: Element> Predicate<E> for ContainsLabel {
     fn test(&self, entry: &E) -> Option<bool> {
-        self.cmp.test(entry.label(), &self.expect)
+        self.cmp.test(entry.label(), &*self.expect)
     }
 }
 
 impl ContainsLabel {
     pub fn with_in(expect: HashSet<Label>) -> Self {
-        ContainsLabel { cmp: Contains::Within, expect }
+        ContainsLabel { cmp: Contains::Within, expect: Arc::new(expect) }
     }
 }
 
@@ -13,11 +13,12 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
-use crate::structure::filter::compare::{Compare, EqCmp, OrdCmp};
+use crate::structure::filter::compare::{Compare, CustomCmp, EqCmp, OrdCmp};
 use crate::structure::filter::element::{ExpectValue, Reverse};
 use crate::structure::filter::Predicate;
 use crate::structure::{with_tlv, BiPredicate, Details, DynDetails, Element};
-use dyn_type::Object;
+use dyn_type::custom::CustomPredicate;
+use dyn_type::{CustomObject, Object};
 
 pub struct HasProperty {
     pub key: String,
@@ -42,8 +43,9 @@ impl<E: Element> Predicate<E> for HasProperty {
 }
 
 impl HasProperty {
-    pub fn eq(key: String, expect: Option<Object>) -> Self {
-        HasProperty { key, cmp: Compare::Eq(EqCmp::Eq), expect: expect.into() }
+    /// The equality to the value, where the floats are equal within the relative epsilon
+    pub fn eq(key: String, expect: Option<Object>, epsilon: f64) -> Self {
+        HasProperty { key, cmp: Compare::approx_eq(EqCmp::Eq, epsilon), expect: expect.into() }
     }
 
     pub fn lt(key: String, expect: Option<Object>) -> Self {
@@ -61,6 +63,11 @@ impl HasProperty {
     pub fn ge(key: String, expect: Option<Object>) -> Self {
         HasProperty { key, cmp: Compare::Ord(OrdCmp::GreaterEq), expect: expect.into() }
     }
+
+    pub fn custom(key: String, expect: CustomObject, func: CustomPredicate) -> Self {
+        let cmp = Compare::Custom(CustomCmp { func, negated: false });
+        HasProperty { key, cmp, expect: ExpectValue::Local(Object::Custom(expect)) }
+    }
 }
 
 impl Reverse for HasProperty {
@@ -14,6 +14,7 @@
 //! limitations under the License.
 
 use crate::structure::element::Label;
+use crate::structure::filter::compare::get_default_float_epsilon;
 use crate::structure::filter::{BiPredicate, Predicate};
 use crate::{Element, ID};
 use std::cell::RefCell;
@@ -26,7 +27,8 @@ mod by_property;
 use by_id::*;
 use by_label::*;
 use by_property::*;
-use dyn_type::{DynType, Object};
+use dyn_type::custom::CustomPredicate;
+use dyn_type::{CustomObject, DynType, Object};
 
 pub enum ExpectValue<T: DynType> {
     Local(T),
@@ -148,8 +150,16 @@ pub fn contains_label(labels: HashSet<Label>) -> ElementFilter {
     ElementFilter::ContainsLabel(ContainsLabel::with_in(labels))
 }
 
+/// The equality to the value of the property, where the floats are equal within the default
+/// epsilon of the server
 pub fn has_property<O: Into<Object>>(key: String, value: O) -> ElementFilter {
-    ElementFilter::HasProperty(HasProperty::eq(key, Some(value.into())))
+    has_property_approx(key, value, get_default_float_epsilon())
+}
+
+/// The equality to the value of the property, where the floats are equal within the relative
+/// epsilon, or exactly if it is 0
+pub fn has_property_approx<O: Into<Object>>(key: String, value: O, epsilon: f64) -> ElementFilter {
+    ElementFilter::HasProperty(HasProperty::eq(key, Some(value.into()), epsilon))
 }
 
 pub fn has_property_lt<O: Into<Object>>(key: String, value: O) -> ElementFilter {
@@ -168,6 +178,14 @@ pub fn has_property_ge<O: Into<Object>>(key: String, value: O) -> ElementFilter
     ElementFilter::HasProperty(HasProperty::ge(key, Some(value.into())))
 }
 
+/// The predicate registered for the custom type of the value, e.g. whether the point of the
+/// property is within a radius of the value
+pub fn has_property_custom(
+    key: String, value: CustomObject, func: CustomPredicate,
+) -> ElementFilter {
+    ElementFilter::HasProperty(HasProperty::custom(key, value, func))
+}
+
 pub fn by() -> ElementFilter {
     has_id(None)
 }
@@ -177,7 +195,11 @@ pub fn by_label() -> ElementFilter {
 }
 
 pub fn by_property(key: String) -> ElementFilter {
-    ElementFilter::HasProperty(HasProperty::eq(key, None))
+    by_property_approx(key, get_default_float_epsilon())
+}
+
+pub fn by_property_approx(key: String, epsilon: f64) -> ElementFilter {
+    ElementFilter::HasProperty(HasProperty::eq(key, None, epsilon))
 }
 
 pub fn by_property_lt(key: String) -> ElementFilter {
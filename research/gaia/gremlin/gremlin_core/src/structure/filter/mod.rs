@@ -38,6 +38,7 @@ pub trait BiPredicate<T, K> {
     fn test(&self, left: &T, right: &K) -> Option<bool>;
 }
 
+mod batch;
 pub mod codec;
 mod compare;
 mod contains;
@@ -45,6 +46,12 @@ mod element;
 mod traverser;
 
 use crate::structure::{GraphElement, Tag};
+pub use batch::{Bitmap, Column, ColumnBatch, Selection, DEFAULT_BATCH_SIZE};
+pub use compare::{
+    get_default_float_epsilon, resolve_float_epsilon, set_default_float_epsilon,
+    DEFAULT_FLOAT_EPSILON,
+};
+use dyn_type::Object;
 pub use element::*;
 pub use traverser::*;
 
@@ -74,10 +81,19 @@ impl<T, P: Predicate<T>> Filter<T, P> {
         Filter::Chain(chain)
     }
 
+    // the first filter of a chain, where a chain is kept nested, or the filters chained after it
+    // would be spliced into it, e.g. `(a and b) or c` into `a and b or c`
+    fn head(f: Filter<T, P>) -> Self {
+        match f {
+            Filter::Chain(_) => Filter::with_chain(f),
+            f => f,
+        }
+    }
+
     pub fn and<F: Into<Filter<T, P>>>(&mut self, f: F) -> &mut Self {
         match self {
             Filter::Ph(_) => {
-                let _ = std::mem::replace(self, f.into());
+                let _ = std::mem::replace(self, Filter::head(f.into()));
             }
             Filter::Simple(_) => {
                 let old = std::mem::replace(self, Filter::Ph(PhantomData));
@@ -93,7 +109,7 @@ impl<T, P: Predicate<T>> Filter<T, P> {
     pub fn or<F: Into<Filter<T, P>>>(&mut self, f: F) -> &mut Self {
         match self {
             Filter::Ph(_) => {
-                let _ = std::mem::replace(self, f.into());
+                let _ = std::mem::replace(self, Filter::head(f.into()));
             }
             Filter::Simple(_) => {
                 let old = std::mem::replace(self, Filter::Ph(PhantomData));
@@ -184,6 +200,33 @@ pub fn without_tag(filter: Filter<GraphElement, ElementFilter>) -> TraverserFilt
     tf
 }
 
+/// Test the heads of the traversers by the predicates on the values, e.g. of `values("age").is(..)`
+pub fn on_value(filter: Filter<Object, ValueFilter>) -> TraverserFilterChain {
+    let mut tf = Filter::<Traverser, traverser::TraverserFilter>::default();
+    let mut connect = ChainKind::Or;
+    match filter {
+        Filter::Ph(_) => {}
+        Filter::Simple(f) => {
+            tf.or(Filter::with(TraverserFilter::from(f)));
+        }
+        Filter::Chain(chain) => {
+            for node in chain.list {
+                let next = on_value(node.filter);
+                match connect {
+                    ChainKind::And => {
+                        tf.and(next);
+                    }
+                    ChainKind::Or => {
+                        tf.or(next);
+                    }
+                }
+                connect = node.next;
+            }
+        }
+    }
+    tf
+}
+
 pub fn with_tag(
     tags: &mut dyn Iterator<Item = Tag>, filter: Filter<GraphElement, ElementFilter>,
 ) -> TraverserFilterChain {
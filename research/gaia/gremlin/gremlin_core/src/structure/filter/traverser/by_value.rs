@@ -13,85 +13,260 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
+use crate::generated::common as pb_type;
 use crate::generated::gremlin as pb;
 use crate::process::traversal::traverser::Traverser;
 use crate::structure::codec::{pb_value_to_object, ParseError};
-use crate::structure::filter::compare::{Compare, EqCmp, OrdCmp};
+use crate::structure::filter::compare::{resolve_float_epsilon, Compare, EqCmp, OrdCmp};
+use crate::structure::filter::contains::Contains;
 use crate::structure::filter::BiPredicate;
 use crate::structure::filter::Predicate;
 use crate::structure::{with_tlv, ExpectValue, Reverse};
 use crate::FromPb;
 use dyn_type::Object;
+use std::collections::HashMap;
+use std::sync::Arc;
 
-pub struct ValueFilter {
+/// The predicates on the values themselves rather than the properties of the elements, e.g. the
+/// heads of `values("age").is(gt(30))`
+pub enum ValueFilter {
+    Compare(CompareValue),
+    Contains(ContainsValue),
+}
+
+/// The comparison of the value to the expected one, e.g. `is(gt(30))`
+pub struct CompareValue {
     pub cmp: Compare,
     pub expect: ExpectValue<Object>,
 }
 
+/// Whether the value is one of the expected ones, e.g. `is(within(1, 2, 3))`
+pub struct ContainsValue {
+    pub cmp: Contains,
+    /// the values to be contained, shared by the copies of the filter
+    pub expect: Arc<ValueSet>,
+}
+
+/// A set of the values, where the equal numbers of different types are the same, e.g. the
+/// integer 1 and the float 1.0, as they have the same stable hash
+#[derive(Default)]
+pub struct ValueSet {
+    buckets: HashMap<u64, Vec<Object>>,
+}
+
+impl ValueSet {
+    pub fn insert(&mut self, value: Object) {
+        let bucket = self.buckets.entry(value.stable_hash()).or_default();
+        if !bucket.contains(&value) {
+            bucket.push(value);
+        }
+    }
+
+    pub fn contains(&self, value: &Object) -> bool {
+        self.buckets.get(&value.stable_hash()).map(|b| b.contains(value)).unwrap_or(false)
+    }
+
+    pub fn len(&self) -> usize {
+        self.buckets.values().map(|b| b.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buckets.is_empty()
+    }
+}
+
+impl std::iter::FromIterator<Object> for ValueSet {
+    fn from_iter<I: IntoIterator<Item = Object>>(iter: I) -> Self {
+        let mut set = ValueSet::default();
+        for value in iter {
+            set.insert(value);
+        }
+        set
+    }
+}
+
 impl Reverse for ValueFilter {
     fn reverse(&mut self) {
-        self.cmp.reverse()
+        match self {
+            ValueFilter::Compare(f) => f.cmp.reverse(),
+            ValueFilter::Contains(f) => f.cmp.reverse(),
+        }
+    }
+}
+
+impl Predicate<Object> for ValueFilter {
+    fn test(&self, entry: &Object) -> Option<bool> {
+        self.test_value(entry)
     }
 }
 
 impl Predicate<Traverser> for ValueFilter {
     fn test(&self, entry: &Traverser) -> Option<bool> {
         if let Some(left) = entry.get_object() {
-            match self.expect {
-                ExpectValue::Local(ref v) => self.cmp.test(&left, &v),
-                ExpectValue::TLV => with_tlv(|obj| self.cmp.test(&left, &obj).unwrap_or(false)),
-            }
+            self.test_value(left)
         } else {
             None
         }
     }
 }
 
+/// Filter the traversers by the number of loops they have gone through in `repeat()`,
+/// e.g., `until(loops().is(3))`
+pub struct LoopsFilter(pub ValueFilter);
+
+impl Predicate<Traverser> for LoopsFilter {
+    fn test(&self, entry: &Traverser) -> Option<bool> {
+        let loops = Object::from(entry.get_loops() as i64);
+        self.0.test_value(&loops)
+    }
+}
+
 impl ValueFilter {
-    pub fn eq(expect: Option<Object>) -> Self {
-        ValueFilter { cmp: Compare::Eq(EqCmp::Eq), expect: expect.into() }
+    pub fn test_value(&self, left: &Object) -> Option<bool> {
+        match self {
+            ValueFilter::Compare(f) => match f.expect {
+                ExpectValue::Local(ref v) => f.cmp.test(left, v),
+                ExpectValue::TLV => with_tlv(|obj| f.cmp.test(left, obj).unwrap_or(false)),
+            },
+            ValueFilter::Contains(f) => {
+                let contains = f.expect.contains(left);
+                Some(match f.cmp {
+                    Contains::Within => contains,
+                    Contains::Without => !contains,
+                })
+            }
+        }
+    }
+
+    fn compare(cmp: Compare, expect: Option<Object>) -> Self {
+        ValueFilter::Compare(CompareValue { cmp, expect: expect.into() })
     }
 
-    pub fn neq(expect: Option<Object>) -> Self {
-        ValueFilter { cmp: Compare::Eq(EqCmp::NotEq), expect: expect.into() }
+    /// The equality to the value, where the floats are equal within the relative epsilon
+    pub fn eq(expect: Option<Object>, epsilon: f64) -> Self {
+        ValueFilter::compare(Compare::approx_eq(EqCmp::Eq, epsilon), expect)
+    }
+
+    pub fn neq(expect: Option<Object>, epsilon: f64) -> Self {
+        ValueFilter::compare(Compare::approx_eq(EqCmp::NotEq, epsilon), expect)
     }
 
     pub fn lt(expect: Option<Object>) -> Self {
-        ValueFilter { cmp: Compare::Ord(OrdCmp::Less), expect: expect.into() }
+        ValueFilter::compare(Compare::Ord(OrdCmp::Less), expect)
     }
 
     pub fn le(expect: Option<Object>) -> Self {
-        ValueFilter { cmp: Compare::Ord(OrdCmp::LessEq), expect: expect.into() }
+        ValueFilter::compare(Compare::Ord(OrdCmp::LessEq), expect)
     }
 
     pub fn gt(expect: Option<Object>) -> Self {
-        ValueFilter { cmp: Compare::Ord(OrdCmp::Greater), expect: expect.into() }
+        ValueFilter::compare(Compare::Ord(OrdCmp::Greater), expect)
     }
 
     pub fn ge(expect: Option<Object>) -> Self {
-        ValueFilter { cmp: Compare::Ord(OrdCmp::GreaterEq), expect: expect.into() }
+        ValueFilter::compare(Compare::Ord(OrdCmp::GreaterEq), expect)
+    }
+
+    pub fn with_in(expect: ValueSet) -> Self {
+        ValueFilter::Contains(ContainsValue { cmp: Contains::Within, expect: Arc::new(expect) })
+    }
+
+    pub fn with_out(expect: ValueSet) -> Self {
+        ValueFilter::Contains(ContainsValue { cmp: Contains::Without, expect: Arc::new(expect) })
+    }
+
+    /// Decode the predicate of the comparison to the value, where the value is a list for
+    /// `within` and `without`
+    pub fn from_exp(cmp: i32, right: &pb_type::Value, epsilon: f64) -> Result<Self, ParseError> {
+        let cmp: pb::Compare = pb::Compare::from_i32(cmp).ok_or(ParseError::InvalidData)?;
+        let epsilon = resolve_float_epsilon(epsilon);
+        let value_filter = match cmp {
+            pb::Compare::Eq => ValueFilter::eq(pb_value_to_object(right)?, epsilon),
+            pb::Compare::Ne => ValueFilter::neq(pb_value_to_object(right)?, epsilon),
+            pb::Compare::Lt => ValueFilter::lt(pb_value_to_object(right)?),
+            pb::Compare::Le => ValueFilter::le(pb_value_to_object(right)?),
+            pb::Compare::Gt => ValueFilter::gt(pb_value_to_object(right)?),
+            pb::Compare::Ge => ValueFilter::ge(pb_value_to_object(right)?),
+            pb::Compare::Within => ValueFilter::with_in(pb_value_to_set(right)?),
+            pb::Compare::Without => ValueFilter::with_out(pb_value_to_set(right)?),
+            pb::Compare::Custom => {
+                return Err("custom predicates are only supported on the properties".into())
+            }
+        };
+        Ok(value_filter)
     }
 }
 
+/// The values of a list, e.g. of `within(1, 2, 3)`
+fn pb_value_to_set(raw: &pb_type::Value) -> Result<ValueSet, ParseError> {
+    use pb_type::value::Item;
+    let set = match raw.item.as_ref() {
+        Some(Item::I32Array(array)) => array.item.iter().map(|v| Object::from(*v)).collect(),
+        Some(Item::I64Array(array)) => array.item.iter().map(|v| Object::from(*v)).collect(),
+        Some(Item::F64Array(array)) => array.item.iter().map(|v| Object::from(*v)).collect(),
+        Some(Item::StrArray(array)) => {
+            array.item.iter().map(|v| Object::from(v.as_str())).collect()
+        }
+        // a single value is taken as a list of itself
+        _ => pb_value_to_object(raw)?.into_iter().collect(),
+    };
+    Ok(set)
+}
+
 impl FromPb<pb::FilterValueExp> for ValueFilter {
     fn from_pb(filter: pb::FilterValueExp) -> Result<Self, ParseError>
     where
         Self: Sized,
     {
-        let cmp_pb: pb::Compare = { unsafe { std::mem::transmute(filter.cmp) } };
-        let right_value = pb_value_to_object(&filter.right.ok_or("right value is not set")?);
-        let value_filter = match cmp_pb {
-            pb::Compare::Eq => ValueFilter::eq(right_value),
-            pb::Compare::Ne => ValueFilter::neq(right_value),
-            pb::Compare::Lt => ValueFilter::lt(right_value),
-            pb::Compare::Le => ValueFilter::le(right_value),
-            pb::Compare::Gt => ValueFilter::gt(right_value),
-            pb::Compare::Ge => ValueFilter::ge(right_value),
-            pb::Compare::Within => return Err("Have not support Within in ValueFilter yet".into()),
-            pb::Compare::Without => {
-                return Err("Have not support Without in ValueFilter yet".into())
-            }
-        };
-        Ok(value_filter)
+        let right = filter.right.ok_or("right value is not set")?;
+        ValueFilter::from_exp(filter.cmp, &right, filter.epsilon)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::structure::filter::Filter;
+
+    #[test]
+    fn value_set_test() {
+        let set: ValueSet =
+            vec![Object::from(1), Object::from(2i64), Object::from(2), Object::from("a")]
+                .into_iter()
+                .collect();
+        assert_eq!(set.len(), 3);
+        // the equal numbers of different types are contained
+        assert!(set.contains(&Object::from(1i64)));
+        assert!(set.contains(&Object::from(2.0)));
+        assert!(set.contains(&Object::from("a")));
+        assert!(!set.contains(&Object::from(3)));
+        assert!(!set.contains(&Object::from("b")));
+    }
+
+    #[test]
+    fn contains_value_test() {
+        let expect = || vec![Object::from(27), Object::from(32)].into_iter().collect();
+        let mut within = ValueFilter::with_in(expect());
+        assert_eq!(within.test_value(&Object::from(27i64)), Some(true));
+        assert_eq!(within.test_value(&Object::from(29)), Some(false));
+        within.reverse();
+        assert_eq!(within.test_value(&Object::from(27i64)), Some(false));
+        let without = ValueFilter::with_out(expect());
+        assert_eq!(without.test_value(&Object::from(29)), Some(true));
+    }
+
+    #[test]
+    fn value_chain_test() {
+        // gt(27).and(lt(35)).or(eq(40))
+        let mut chain: Filter<Object, ValueFilter> =
+            Filter::with_chain(ValueFilter::gt(Some(27.into())));
+        chain.and(ValueFilter::lt(Some(35.into())));
+        let mut filter = Filter::with_chain(chain);
+        filter.or(ValueFilter::eq(Some(40.into()), 0.0));
+        let ages: Vec<i32> = vec![27, 29, 35, 40]
+            .into_iter()
+            .filter(|v| filter.test(&Object::from(*v)) == Some(true))
+            .collect();
+        assert_eq!(ages, vec![29, 40]);
     }
 }
@@ -33,6 +33,10 @@ impl HasHead {
     pub fn new(filter: ElementFilter) -> Self {
         HasHead { filter }
     }
+
+    pub fn get_filter(&self) -> &ElementFilter {
+        &self.filter
+    }
 }
 
 impl From<HasHead> for TraverserFilter {
@@ -56,6 +60,7 @@ pub enum TraverserFilter {
     HasTag(HasTag),
     HasCycle(IsSimple),
     IsValue(ValueFilter),
+    IsLoops(LoopsFilter),
 }
 
 impl Predicate<Traverser> for TraverserFilter {
@@ -65,6 +70,7 @@ impl Predicate<Traverser> for TraverserFilter {
             TraverserFilter::HasTag(f) => f.test(entry),
             TraverserFilter::HasCycle(f) => f.test(entry),
             TraverserFilter::IsValue(f) => f.test(entry),
+            TraverserFilter::IsLoops(f) => f.test(entry),
         }
     }
 }
@@ -80,3 +86,9 @@ impl From<ValueFilter> for TraverserFilter {
         TraverserFilter::IsValue(raw)
     }
 }
+
+impl From<LoopsFilter> for TraverserFilter {
+    fn from(raw: LoopsFilter) -> Self {
+        TraverserFilter::IsLoops(raw)
+    }
+}
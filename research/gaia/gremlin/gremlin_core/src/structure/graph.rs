@@ -13,8 +13,16 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
+use crate::process::limits::GraphLimits;
+use crate::structure::codec::referenced_properties;
+use crate::structure::property_cache::drop_graph_property_caches;
 use crate::structure::{Direction, Edge, ElementFilter, Filter, Label, Vertex, ID};
-use crate::{DynIter, DynResult, Element};
+use crate::{str_to_dyn_error, DynIter, DynResult, Element};
+use graph_store::common::LabelId;
+use graph_store::delta::{SnapshotPin, Version};
+use graph_store::schema::{AdjOrder, GraphSchemaInfo};
+use graph_store::statistics::GraphStatistics;
+use pegasus::health::ComponentState;
 
 #[derive(Clone)]
 pub struct QueryParams<E: Element + Send + Sync> {
@@ -22,14 +30,24 @@ pub struct QueryParams<E: Element + Send + Sync> {
     pub limit: Option<usize>,
     pub props: Option<Vec<String>>,
     pub filter: Option<Arc<Filter<E, ElementFilter>>>,
+    /// The properties compared by the filter, which a scan may read into columns to evaluate the
+    /// filter by batches, see `ColumnBatch`
+    pub columns: Vec<String>,
+}
+
+impl<E: Element + Send + Sync> Default for QueryParams<E> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<E: Element + Send + Sync> QueryParams<E> {
     pub fn new() -> Self {
-        QueryParams { labels: vec![], limit: None, props: None, filter: None }
+        QueryParams { labels: vec![], limit: None, props: None, filter: None, columns: vec![] }
     }
 
     pub fn set_filter(&mut self, filter: Filter<E, ElementFilter>) {
+        self.columns = referenced_properties(&filter);
         self.filter = Some(Arc::new(filter))
     }
 }
@@ -47,15 +65,36 @@ where
     }
 }
 
+/// Whether the vertex of the id is of the partitions owned by a worker scanning the graph, see
+/// `GraphPartition::get_scan_partition`
+pub type ScanPartition = Arc<dyn Fn(&ID) -> bool + Send + Sync>;
+
 pub trait GraphProxy: Send + Sync {
     fn scan_vertex(
         &self, params: &QueryParams<Vertex>,
     ) -> DynResult<Box<dyn Iterator<Item = Vertex> + Send>>;
 
+    /// Scan the vertices as `scan_vertex` does, of the partitions owned by a worker only, so that
+    /// the workers of a server each read their own part of the local vertices instead of all of
+    /// them; By default, all the vertices are scanned, and those of other partitions are dropped
+    fn scan_vertex_partition(
+        &self, params: &QueryParams<Vertex>, is_owner: ScanPartition,
+    ) -> DynResult<Box<dyn Iterator<Item = Vertex> + Send>> {
+        Ok(Box::new(self.scan_vertex(params)?.filter(move |v| is_owner(&v.id()))))
+    }
+
     fn get_vertex(
         &self, ids: &[ID], params: &QueryParams<Vertex>,
     ) -> DynResult<Box<dyn Iterator<Item = Vertex> + Send>>;
 
+    /// Get the edges of the given ids kept by current partition, i.e. by the partition of their
+    /// start vertices, skipping the ids of no edge, e.g. of the edges deleted
+    fn get_edge(
+        &self, _ids: &[ID], _params: &QueryParams<Edge>,
+    ) -> DynResult<Box<dyn Iterator<Item = Edge> + Send>> {
+        Err(str_to_dyn_error("getting the edges by ids is not supported by the graph"))
+    }
+
     fn prepare_explore_vertex(
         &self, direction: Direction, params: &QueryParams<Vertex>,
     ) -> DynResult<Box<dyn Statement<ID, Vertex>>>;
@@ -63,21 +102,233 @@ pub trait GraphProxy: Send + Sync {
     fn prepare_explore_edge(
         &self, direction: Direction, params: &QueryParams<Edge>,
     ) -> DynResult<Box<dyn Statement<ID, Edge>>>;
+
+    /// The order the edges of the label are stored in each adjacency, i.e. sorted by a property
+    /// of the edges, or `None` if they are not, see `prepare_explore_sorted_edge`
+    fn get_adj_order(&self, _label: LabelId) -> Option<AdjOrder> {
+        None
+    }
+
+    /// Explore the edges as `prepare_explore_edge` does, of the only label of `params` whose
+    /// edges are stored sorted, in the direction `Out` or `In`, where the edges of each vertex are
+    /// given in the order of the label, so that the first `params.limit` edges of each vertex are
+    /// the top ones by the order, see `get_adj_order`
+    fn prepare_explore_sorted_edge(
+        &self, _direction: Direction, _params: &QueryParams<Edge>,
+    ) -> DynResult<Box<dyn Statement<ID, Edge>>> {
+        Err(str_to_dyn_error("exploring the edges in their stored order is not supported"))
+    }
+
+    /// Count the edges of the labels adjacent to the vertex in the direction, i.e. the degree of
+    /// the vertex, which are expanded and counted unless the graph knows the degree itself
+    fn count_adj_edges(
+        &self, vid: ID, direction: Direction, edge_labels: &[Label],
+    ) -> DynResult<usize> {
+        let mut params = QueryParams::new();
+        params.labels = edge_labels.to_vec();
+        let mut count = 0;
+        for edge in self.prepare_explore_edge(direction, &params)?.exec(vid)? {
+            edge?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// The statistics of the graph for estimating the cardinalities while building the plan,
+    /// or `None` if the graph does not maintain any statistics
+    fn get_statistics(&self) -> Option<Arc<GraphStatistics>> {
+        None
+    }
+
+    /// Whether the statistics count exactly the elements of the graph by labels, which is not the
+    /// case once the graph is updated after the statistics are built, e.g. by applying a delta,
+    /// so that a count can be answered by the statistics instead of a scan
+    fn has_exact_statistics(&self) -> bool {
+        false
+    }
+
+    /// The labels and properties of the graph, which the queries are checked against before
+    /// running, or `None` if the graph is schema-free
+    fn get_schema(&self) -> Option<Arc<GraphSchemaInfo>> {
+        None
+    }
+
+    /// Pin the version of the latest mutation of the graph, which the jobs bound to the graph
+    /// read at by all their reads, see `BoundGraph`, or `None` if the graph is never mutated
+    fn pin_snapshot(&self) -> Option<SnapshotPin> {
+        None
+    }
+
+    /// Pin the version as `pin_snapshot` does, which must be `min_version` at least, e.g. of a
+    /// mutation the job must read, waiting for the graph to catch up with it, e.g. a replica the
+    /// mutation is still replicated to, which fails if it never catches up in the timeout
+    fn pin_snapshot_since(
+        &self, min_version: Version, timeout: Duration,
+    ) -> DynResult<Option<SnapshotPin>> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let pin = self.pin_snapshot();
+            let version = pin.as_ref().map(|pin| pin.version()).unwrap_or(0);
+            if version >= min_version {
+                return Ok(pin);
+            }
+            // unpinned while waiting, so that the older versions are compacted meanwhile
+            drop(pin);
+            let now = Instant::now();
+            if now >= deadline {
+                let msg = format!(
+                    "the graph is of version {}, not caught up with version {} in {:?}",
+                    version, min_version, timeout
+                );
+                return Err(str_to_dyn_error(&msg));
+            }
+            std::thread::sleep(std::cmp::min(deadline - now, SNAPSHOT_POLL_INTERVAL));
+        }
+    }
 }
 
+use std::collections::HashMap;
 use std::sync::atomic::{AtomicPtr, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+/// How often the version of a graph is polled while a job waits for it to catch up, see
+/// `GraphProxy::pin_snapshot_since`
+const SNAPSHOT_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// The key of the `BoundGraph` of a job among its resources, see `pegasus::get_job_resource`
+pub const JOB_GRAPH: &str = "gremlin.graph";
+
+/// The feature required by the requests naming their graphs, which the servers unaware of the
+/// named graphs reject, instead of running the requests on their default graphs
+pub const GRAPH_NAMED_FEATURE: &str = "graph.named";
 
 lazy_static! {
     pub static ref GRAPH_PROXY: AtomicPtr<Arc<dyn GraphProxy>> = AtomicPtr::default();
+    /// name -> the graph registered by `register_named_graph`
+    static ref NAMED_GRAPHS: RwLock<HashMap<String, Arc<dyn GraphProxy>>> =
+        RwLock::new(HashMap::new());
+    /// name -> the limits of the jobs on the graph set by `set_graph_limits`
+    static ref GRAPH_LIMITS: RwLock<HashMap<String, GraphLimits>> = RwLock::new(HashMap::new());
 }
 
+/// Register the default graph, which the jobs naming no graph run on
 pub fn register_graph(graph: Arc<dyn GraphProxy>) {
     let ptr = Box::into_raw(Box::new(graph));
     GRAPH_PROXY.store(ptr, Ordering::SeqCst);
+    drop_graph_property_caches("");
+    pegasus::health::set_component_state(&graph_component(""), ComponentState::Ready);
+}
+
+/// Register the graph by the name, which the jobs naming it by the `graph` of their requests run
+/// on, replacing the graph registered by the same name if any
+pub fn register_named_graph(name: &str, graph: Arc<dyn GraphProxy>) {
+    if let Ok(mut graphs) = NAMED_GRAPHS.write() {
+        graphs.insert(name.to_owned(), graph);
+    }
+    drop_graph_property_caches(name);
+    pegasus::health::set_component_state(&graph_component(name), ComponentState::Ready);
+}
+
+/// Remove the graph of the name, which tells whether it is found; The jobs running on it go on
+/// with the graph they are bound to, while the jobs submitted later naming it are rejected
+pub fn unregister_named_graph(name: &str) -> bool {
+    drop_graph_property_caches(name);
+    if let Ok(mut limits) = GRAPH_LIMITS.write() {
+        limits.remove(name);
+    }
+    pegasus::health::remove_component(&graph_component(name));
+    NAMED_GRAPHS.write().map(|mut graphs| graphs.remove(name).is_some()).unwrap_or(false)
+}
+
+/// Set the limits of the jobs on the graph of the name, or the default graph if the name is
+/// empty, which are kept once the graph is replaced, until it is removed by
+/// `unregister_named_graph`; The jobs admitted before go on under the limits they are admitted by
+pub fn set_graph_limits(name: &str, limits: GraphLimits) {
+    if let Ok(mut all) = GRAPH_LIMITS.write() {
+        if limits.is_unlimited() {
+            all.remove(name);
+        } else {
+            all.insert(name.to_owned(), limits);
+        }
+    }
+}
+
+/// Get the limits of the jobs on the graph of the name, which are unlimited if never set
+pub fn get_graph_limits(name: &str) -> GraphLimits {
+    GRAPH_LIMITS.read().ok().and_then(|all| all.get(name).cloned()).unwrap_or_default()
+}
+
+/// The component the load state of the graph of the name is reported as to the health of the
+/// server, e.g. "graph:ldbc", or "graph" of the default graph, see `pegasus::health`
+pub fn graph_component(name: &str) -> String {
+    if name.is_empty() {
+        "graph".to_owned()
+    } else {
+        format!("graph:{}", name)
+    }
 }
 
+/// Load the graph by `load` and register it by the name, or as the default graph if the name is
+/// empty, where the graph is reported loading until it is registered, or failed by the error of
+/// `load`, which keeps the server requiring the graph unready meanwhile
+pub fn load_named_graph<F>(name: &str, load: F) -> DynResult<()>
+where
+    F: FnOnce() -> DynResult<Arc<dyn GraphProxy>>,
+{
+    let component = graph_component(name);
+    pegasus::health::set_component_state(&component, ComponentState::Loading);
+    match load() {
+        Ok(graph) => {
+            if name.is_empty() {
+                register_graph(graph);
+            } else {
+                register_named_graph(name, graph);
+            }
+            Ok(())
+        }
+        Err(e) => {
+            let state = ComponentState::Failed(e.to_string());
+            pegasus::health::set_component_state(&component, state);
+            Err(e)
+        }
+    }
+}
+
+/// Get the graph registered by the name, or the default graph if the name is empty
+pub fn get_named_graph(name: &str) -> Option<Arc<dyn GraphProxy>> {
+    if name.is_empty() {
+        get_default_graph()
+    } else {
+        NAMED_GRAPHS.read().ok()?.get(name).cloned()
+    }
+}
+
+/// Get the graph of current job, i.e. the graph it is bound to by `BoundGraph`, or the default
+/// graph if current thread runs no job bound to any graph
 pub fn get_graph() -> Option<Arc<dyn GraphProxy>> {
+    match pegasus::get_job_resource::<BoundGraph>(JOB_GRAPH) {
+        Ok(bound) => bound.graph.clone(),
+        Err(_) => get_default_graph(),
+    }
+}
+
+/// The version of the graph current job reads at, i.e. the version pinned once the job is
+/// submitted, or `None` if current thread runs no job, or the graph is never mutated
+pub fn get_snapshot_version() -> Option<Version> {
+    let bound = pegasus::get_job_resource::<BoundGraph>(JOB_GRAPH).ok()?;
+    bound.snapshot.as_ref().map(|pin| pin.version())
+}
+
+/// The name of the graph of current job, which is empty for the default graph
+pub fn get_graph_name() -> String {
+    match pegasus::get_job_resource::<BoundGraph>(JOB_GRAPH) {
+        Ok(bound) => bound.name.clone(),
+        Err(_) => String::new(),
+    }
+}
+
+fn get_default_graph() -> Option<Arc<dyn GraphProxy>> {
     let ptr = GRAPH_PROXY.load(Ordering::SeqCst);
     if ptr.is_null() {
         None
@@ -85,3 +336,33 @@ pub fn get_graph() -> Option<Arc<dyn GraphProxy>> {
         Some(unsafe { (*ptr).clone() })
     }
 }
+
+/// The graph a job runs on, resolved by its name once the job is submitted, so that all its
+/// operators run on the same graph even if it is replaced or removed in the middle of the job,
+/// and read at the same version, never seeing the mutations after the job is submitted
+pub struct BoundGraph {
+    pub name: String,
+    /// `None` if no graph is registered by the name, where the operators fail to read the graph
+    pub graph: Option<Arc<dyn GraphProxy>>,
+    /// the version pinned until the job ends, as its resources are dropped then
+    pub snapshot: Option<SnapshotPin>,
+}
+
+impl BoundGraph {
+    pub fn new(name: &str) -> Self {
+        let graph = get_named_graph(name);
+        let snapshot = graph.as_ref().and_then(|graph| graph.pin_snapshot());
+        BoundGraph { name: name.to_owned(), graph, snapshot }
+    }
+
+    /// Bind the job to the graph as `new` does, pinning the version of `min_version` at least,
+    /// e.g. of a mutation the client has just made, see `GraphProxy::pin_snapshot_since`
+    pub fn since(name: &str, min_version: Version, timeout: Duration) -> DynResult<Self> {
+        let graph = get_named_graph(name);
+        let snapshot = match graph.as_ref() {
+            Some(graph) => graph.pin_snapshot_since(min_version, timeout)?,
+            None => None,
+        };
+        Ok(BoundGraph { name: name.to_owned(), graph, snapshot })
+    }
+}
@@ -13,20 +13,25 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
+pub mod cache;
 mod element;
 pub mod filter;
 mod graph;
 mod property;
+pub mod property_cache;
 
 use crate::generated::gremlin as pb;
 use crate::structure::codec::ParseError;
 use crate::FromPb;
-pub use element::{Edge, Element, GraphElement, Label, Vertex, VertexOrEdge, ID};
+pub use element::{
+    join_id, label_id_from_pb, read_id, split_id, write_id, Edge, Element, ElementKind,
+    GraphElement, Label, Vertex, VertexOrEdge, ID,
+};
 pub use filter::*;
 pub use graph::*;
 pub use property::{DefaultDetails, Details, DynDetails, Token};
 
-#[derive(Copy, Clone, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
 pub enum Direction {
     Out = 0,
     In = 1,
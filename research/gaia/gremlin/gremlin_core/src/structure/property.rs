@@ -36,6 +36,12 @@ pub trait Details: Send + Sync + AsAny {
     fn get_id(&self) -> ID;
 
     fn get_label(&self) -> &Label;
+
+    /// All the properties, or `None` if they are not held by the details, e.g. of the elements
+    /// read from the graph on demand
+    fn get_all_properties(&self) -> Option<HashMap<String, Object>> {
+        None
+    }
 }
 
 #[derive(Clone)]
@@ -63,6 +69,10 @@ impl Details for DynDetails {
     fn get_label(&self) -> &Label {
         self.inner.get_label()
     }
+
+    fn get_all_properties(&self) -> Option<HashMap<String, Object>> {
+        self.inner.get_all_properties()
+    }
 }
 
 impl Encode for DynDetails {
@@ -89,7 +99,7 @@ impl Decode for DynDetails {
         } else {
             // TODO(yyy): handle other kinds of details
             // safety: fake never be used
-            let fake = DefaultDetails::new(0, Label::Id(0));
+            let fake = DefaultDetails::new(0, Label::Id(0.into()));
             Ok(DynDetails::new(fake))
         }
     }
@@ -141,6 +151,10 @@ impl Details for DefaultDetails {
     fn get_label(&self) -> &Label {
         &self.label
     }
+
+    fn get_all_properties(&self) -> Option<HashMap<String, Object>> {
+        Some(self.inner.clone())
+    }
 }
 
 impl Encode for DefaultDetails {
@@ -0,0 +1,467 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The property cache of the workers. A graph kept by a remote storage reads the properties of
+//! an element on demand, where the same properties of the hot vertices are read again and again,
+//! e.g. of the hubs reached by many traversers. So each worker keeps the property values it reads
+//! from a graph in a bounded cache keyed by (element id, property id), which is shared by the jobs
+//! running on the same graph. A cached value is served to a job only within the staleness bound
+//! of the job, i.e. its `property_cache_ttl_ms` since the value is read, while a job of
+//! `fresh_reads` always reads the storage, refreshing the cache for the others. The caches of a
+//! graph are dropped once the graph is unregistered or replaced.
+
+use crate::process::metrics::WorkerCounter;
+use crate::structure::{Details, Label, ID};
+use dyn_type::{BorrowObject, Object};
+use pegasus_common::downcast::*;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::{Duration, Instant};
+
+/// The number of property reads served by the property cache
+pub const PROPERTY_CACHE_HIT_COUNTER: &str = "prop_cache_hit";
+/// The number of property reads that go to the graph storage
+pub const PROPERTY_CACHE_MISS_COUNTER: &str = "prop_cache_miss";
+/// The most property values each worker caches of a graph
+pub const PROPERTY_CACHE_CAPACITY: usize = 1 << 16;
+
+/// The id of a property name, assigned per graph by `GraphPropertyCaches::property_id`
+pub type PropertyId = u32;
+
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
+pub struct PropertyKey {
+    pub id: ID,
+    pub property: PropertyId,
+}
+
+struct CachedValue {
+    // `None` if the element has no such property, which is cached as well
+    value: Option<Object>,
+    read_at: Instant,
+    tick: u64,
+}
+
+struct CachedValues {
+    values: HashMap<PropertyKey, CachedValue>,
+    // tick of last read -> key, the value read earliest comes first
+    order: BTreeMap<u64, PropertyKey>,
+    tick: u64,
+    capacity: usize,
+}
+
+impl CachedValues {
+    fn get(&self, key: &PropertyKey, ttl: Duration) -> Option<Option<Object>> {
+        self.values
+            .get(key)
+            .filter(|cached| cached.read_at.elapsed() < ttl)
+            .map(|cached| cached.value.clone())
+    }
+
+    fn insert(&mut self, key: PropertyKey, value: Option<Object>, read_at: Instant) {
+        if let Some(cached) = self.values.remove(&key) {
+            self.order.remove(&cached.tick);
+        }
+        while self.values.len() >= self.capacity {
+            let earliest = self.order.keys().next().cloned().expect("cache order lost");
+            let earliest = self.order.remove(&earliest).expect("cache order lost");
+            self.values.remove(&earliest);
+        }
+        self.tick += 1;
+        self.order.insert(self.tick, key);
+        self.values.insert(key, CachedValue { value, read_at, tick: self.tick });
+    }
+}
+
+/// The property values a worker reads from a graph, of which the ones read earliest are evicted
+/// first once the cache is full
+pub struct PropertyCache {
+    cached: Mutex<CachedValues>,
+}
+
+impl PropertyCache {
+    pub fn new(capacity: usize) -> Self {
+        let cached =
+            CachedValues { values: HashMap::new(), order: BTreeMap::new(), tick: 0, capacity };
+        PropertyCache { cached: Mutex::new(cached) }
+    }
+
+    /// Get the value read within `ttl`, or `None` if it is not cached, or it is staler than that
+    pub fn get(&self, key: &PropertyKey, ttl: Duration) -> Option<Option<Object>> {
+        self.cached.lock().ok().and_then(|cached| cached.get(key, ttl))
+    }
+
+    /// Cache the value just read from the graph, replacing the one cached before if any
+    pub fn insert(&self, key: PropertyKey, value: Option<Object>) {
+        if let Ok(mut cached) = self.cached.lock() {
+            if cached.capacity > 0 {
+                cached.insert(key, value, Instant::now());
+            }
+        }
+    }
+
+    /// The number of cached values
+    pub fn len(&self) -> usize {
+        self.cached.lock().map(|cached| cached.values.len()).unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// The property caches of a graph, one for each worker index, and the ids of its property names
+pub struct GraphPropertyCaches {
+    workers: Mutex<HashMap<u32, Arc<PropertyCache>>>,
+    properties: RwLock<HashMap<String, PropertyId>>,
+    capacity: usize,
+}
+
+impl GraphPropertyCaches {
+    pub fn new(capacity: usize) -> Self {
+        GraphPropertyCaches {
+            workers: Mutex::new(HashMap::new()),
+            properties: RwLock::new(HashMap::new()),
+            capacity,
+        }
+    }
+
+    /// Get the cache of the worker of the index, which is shared by the workers of the same index
+    /// in all jobs on the graph
+    pub fn get_worker_cache(&self, index: u32) -> Arc<PropertyCache> {
+        let mut workers = self.workers.lock().expect("lock poisoned");
+        workers.entry(index).or_insert_with(|| Arc::new(PropertyCache::new(self.capacity))).clone()
+    }
+
+    /// Get the id of the property name, which is assigned once the name is first seen
+    pub fn property_id(&self, key: &str) -> PropertyId {
+        if let Some(id) = self.properties.read().ok().and_then(|p| p.get(key).cloned()) {
+            return id;
+        }
+        let mut properties = self.properties.write().expect("lock poisoned");
+        let next = properties.len() as PropertyId;
+        *properties.entry(key.to_owned()).or_insert(next)
+    }
+}
+
+lazy_static! {
+    /// graph name -> the property caches of the graph, where the default graph is named empty
+    static ref GRAPH_CACHES: Mutex<HashMap<String, Arc<GraphPropertyCaches>>> =
+        Mutex::new(HashMap::new());
+}
+
+/// Get the property caches of the graph of the name, or create them if not yet
+pub fn get_graph_property_caches(graph: &str) -> Arc<GraphPropertyCaches> {
+    let mut caches = GRAPH_CACHES.lock().expect("lock poisoned");
+    caches
+        .entry(graph.to_owned())
+        .or_insert_with(|| Arc::new(GraphPropertyCaches::new(PROPERTY_CACHE_CAPACITY)))
+        .clone()
+}
+
+/// Drop the property caches of the graph of the name, once the graph is unregistered or replaced;
+/// The jobs running on it go on with the caches they hold, while the later jobs start with new
+/// ones
+pub fn drop_graph_property_caches(graph: &str) {
+    if let Ok(mut caches) = GRAPH_CACHES.lock() {
+        caches.remove(graph);
+    }
+}
+
+struct AccessCounters {
+    hits: WorkerCounter,
+    misses: WorkerCounter,
+}
+
+impl Drop for AccessCounters {
+    fn drop(&mut self) {
+        pegasus::metrics::add_cache_accesses("property", self.hits.get(), self.misses.get());
+    }
+}
+
+/// How a job reads the property values, through the property cache of its worker or not
+#[derive(Clone)]
+pub struct PropertyAccess {
+    // the caches of the graph and the one of the worker, or `None` if the cache is disabled
+    cache: Option<(Arc<GraphPropertyCaches>, Arc<PropertyCache>)>,
+    ttl: Duration,
+    fresh_reads: bool,
+    counters: Arc<AccessCounters>,
+}
+
+impl PropertyAccess {
+    pub fn new(caches: &Arc<GraphPropertyCaches>, index: u32, ttl: Duration, fresh: bool) -> Self {
+        let cache = Some((caches.clone(), caches.get_worker_cache(index)));
+        PropertyAccess { cache, ttl, fresh_reads: fresh, counters: Self::new_counters() }
+    }
+
+    /// Read the property values from the graph always
+    pub fn uncached() -> Self {
+        PropertyAccess {
+            cache: None,
+            ttl: Duration::default(),
+            fresh_reads: true,
+            counters: Self::new_counters(),
+        }
+    }
+
+    /// Get the property access of the current worker, following the `property_cache_ttl_ms` and
+    /// `fresh_reads` of the job, on the graph the job is bound to; It reads the graph always if the
+    /// cache is disabled, or it is not called while building the dataflow of a worker.
+    pub fn of_current_worker() -> Self {
+        let worker = pegasus::get_current_worker();
+        let conf = pegasus::get_current_job_conf();
+        match (worker, conf) {
+            (Some(worker), Some(conf)) if conf.property_cache_ttl_ms > 0 => {
+                let caches = get_graph_property_caches(&super::get_graph_name());
+                let ttl = Duration::from_millis(conf.property_cache_ttl_ms);
+                PropertyAccess::new(&caches, worker.index, ttl, conf.fresh_reads)
+            }
+            _ => PropertyAccess::uncached(),
+        }
+    }
+
+    fn new_counters() -> Arc<AccessCounters> {
+        Arc::new(AccessCounters {
+            hits: WorkerCounter::new(PROPERTY_CACHE_HIT_COUNTER),
+            misses: WorkerCounter::new(PROPERTY_CACHE_MISS_COUNTER),
+        })
+    }
+
+    /// Get the property value of the element, from the cache if it is read within the staleness
+    /// bound, or else by `read` from the graph, which is cached then
+    pub fn get_property<F>(&self, id: ID, key: &str, read: F) -> Option<Object>
+    where
+        F: FnOnce() -> Option<Object>,
+    {
+        if let Some((caches, cache)) = self.cache.as_ref() {
+            let key = PropertyKey { id, property: caches.property_id(key) };
+            if !self.fresh_reads {
+                if let Some(value) = cache.get(&key, self.ttl) {
+                    self.counters.hits.add(1);
+                    return value;
+                }
+            }
+            self.counters.misses.add(1);
+            let value = read();
+            cache.insert(key, value.clone());
+            value
+        } else {
+            self.counters.misses.add(1);
+            read()
+        }
+    }
+
+    pub fn hits(&self) -> u64 {
+        self.counters.hits.get()
+    }
+
+    pub fn misses(&self) -> u64 {
+        self.counters.misses.get()
+    }
+}
+
+/// Read a property of an element from the storage of a graph, e.g. by a remote call
+pub trait PropertyReader: Send + Sync {
+    fn read_property(&self, id: ID, key: &str) -> Option<Object>;
+}
+
+/// The details of an element read from a remote storage, whose properties are read on demand
+/// through the property access of the job
+pub struct CachedDetails {
+    id: ID,
+    label: Label,
+    reader: Arc<dyn PropertyReader>,
+    access: PropertyAccess,
+    // the values read by now, which are only appended, so that they are borrowed by
+    // `get_property` as long as the details
+    values: OnceLock<Box<ReadValue>>,
+    // held by appending a value, so that each value is read once
+    appending: Mutex<()>,
+}
+
+/// The value of a property read by the details, followed by those read after it
+struct ReadValue {
+    key: String,
+    value: Option<Object>,
+    next: OnceLock<Box<ReadValue>>,
+}
+
+impl CachedDetails {
+    pub fn new(
+        id: ID, label: Label, reader: Arc<dyn PropertyReader>, access: PropertyAccess,
+    ) -> Self {
+        CachedDetails {
+            id,
+            label,
+            reader,
+            access,
+            values: OnceLock::new(),
+            appending: Mutex::new(()),
+        }
+    }
+
+    // the value of the key read by now, or else the end of the values to append it to
+    fn find(&self, key: &str) -> Result<&ReadValue, &OnceLock<Box<ReadValue>>> {
+        let mut end = &self.values;
+        while let Some(cached) = end.get() {
+            if cached.key == key {
+                return Ok(cached);
+            }
+            end = &cached.next;
+        }
+        Err(end)
+    }
+}
+
+impl_as_any!(CachedDetails);
+
+impl Details for CachedDetails {
+    fn get_property(&self, key: &str) -> Option<BorrowObject<'_>> {
+        let cached = match self.find(key) {
+            Ok(cached) => cached,
+            Err(_) => {
+                let _appending = self.appending.lock().ok()?;
+                // found again, as it may be appended by others before the lock is held
+                match self.find(key) {
+                    Ok(cached) => cached,
+                    Err(end) => {
+                        let reader = &self.reader;
+                        let value = self
+                            .access
+                            .get_property(self.id, key, || reader.read_property(self.id, key));
+                        let next = OnceLock::new();
+                        end.get_or_init(|| Box::new(ReadValue { key: key.to_owned(), value, next }))
+                    }
+                }
+            }
+        };
+        cached.value.as_ref().map(|v| v.as_borrow())
+    }
+
+    fn get_id(&self) -> ID {
+        self.id
+    }
+
+    fn get_label(&self) -> &Label {
+        &self.label
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// A storage of the property "age" of each vertex, counting the reads
+    struct CountingStorage {
+        reads: AtomicUsize,
+    }
+
+    impl PropertyReader for CountingStorage {
+        fn read_property(&self, id: ID, key: &str) -> Option<Object> {
+            self.reads.fetch_add(1, Ordering::SeqCst);
+            if key == "age" {
+                Some(Object::from(id + 20))
+            } else {
+                None
+            }
+        }
+    }
+
+    fn read_ages(storage: &Arc<CountingStorage>, access: &PropertyAccess, ids: &[ID]) -> u64 {
+        let mut sum = 0;
+        for id in ids {
+            let details =
+                CachedDetails::new(*id, Label::Id(0.into()), storage.clone(), access.clone());
+            sum += details.get_property("age").unwrap().as_u64().unwrap();
+            // read by the details again
+            assert!(details.get_property("age").is_some());
+            assert!(details.get_property("name").is_none());
+        }
+        sum
+    }
+
+    #[test]
+    fn test_property_cache_ttl() {
+        let storage = Arc::new(CountingStorage { reads: AtomicUsize::new(0) });
+        let caches = Arc::new(GraphPropertyCaches::new(1024));
+        let access = PropertyAccess::new(&caches, 0, Duration::from_millis(200), false);
+        assert_eq!(read_ages(&storage, &access, &[1, 2, 1, 2, 1]), 21 + 22 + 21 + 22 + 21);
+        // read "age" and "name" of vertex 1 and 2 once
+        assert_eq!(storage.reads.load(Ordering::SeqCst), 4);
+        assert_eq!((access.hits(), access.misses()), (6, 4));
+
+        std::thread::sleep(Duration::from_millis(300));
+        read_ages(&storage, &access, &[1, 2]);
+        assert_eq!(storage.reads.load(Ordering::SeqCst), 8);
+        read_ages(&storage, &access, &[1, 2]);
+        assert_eq!(storage.reads.load(Ordering::SeqCst), 8);
+    }
+
+    #[test]
+    fn test_property_cache_fresh_reads() {
+        let storage = Arc::new(CountingStorage { reads: AtomicUsize::new(0) });
+        let caches = Arc::new(GraphPropertyCaches::new(1024));
+        let ttl = Duration::from_secs(60);
+        let fresh = PropertyAccess::new(&caches, 0, ttl, true);
+        read_ages(&storage, &fresh, &[1, 1, 1]);
+        assert_eq!(storage.reads.load(Ordering::SeqCst), 6);
+        // served by the values refreshed by the fresh reads
+        let cached = PropertyAccess::new(&caches, 0, ttl, false);
+        read_ages(&storage, &cached, &[1, 1]);
+        assert_eq!(storage.reads.load(Ordering::SeqCst), 6);
+        // the workers of other indexes have their own caches
+        let other = PropertyAccess::new(&caches, 1, ttl, false);
+        read_ages(&storage, &other, &[1]);
+        assert_eq!(storage.reads.load(Ordering::SeqCst), 8);
+
+        let uncached = PropertyAccess::uncached();
+        read_ages(&storage, &uncached, &[1, 1]);
+        assert_eq!(storage.reads.load(Ordering::SeqCst), 12);
+    }
+
+    #[test]
+    fn test_property_cache_capacity() {
+        let cache = PropertyCache::new(2);
+        let ttl = Duration::from_secs(60);
+        let key = |id: ID| PropertyKey { id, property: 0 };
+        for id in 1..=3 {
+            cache.insert(key(id), Some(Object::from(id)));
+        }
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(&key(1), ttl).is_none());
+        assert_eq!(cache.get(&key(3), ttl), Some(Some(Object::from(3u64))));
+        // refreshing 2 makes 3 the earliest read
+        cache.insert(key(2), None);
+        cache.insert(key(4), None);
+        assert!(cache.get(&key(3), ttl).is_none());
+        assert_eq!(cache.get(&key(2), ttl), Some(None));
+    }
+
+    #[test]
+    fn test_property_cache_dropped_with_graph() {
+        let storage = Arc::new(CountingStorage { reads: AtomicUsize::new(0) });
+        let ttl = Duration::from_secs(60);
+        let caches = get_graph_property_caches("prop_cache_graph");
+        assert!(Arc::ptr_eq(&caches, &get_graph_property_caches("prop_cache_graph")));
+        read_ages(&storage, &PropertyAccess::new(&caches, 0, ttl, false), &[1]);
+        assert_eq!(storage.reads.load(Ordering::SeqCst), 2);
+
+        crate::structure::unregister_named_graph("prop_cache_graph");
+        let caches = get_graph_property_caches("prop_cache_graph");
+        read_ages(&storage, &PropertyAccess::new(&caches, 0, ttl, false), &[1]);
+        assert_eq!(storage.reads.load(Ordering::SeqCst), 4);
+    }
+}
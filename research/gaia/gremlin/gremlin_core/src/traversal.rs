@@ -0,0 +1,1532 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! An embedded API to build and run traversals in current process without the rpc service, e.g.
+//! `Graph::traversal().v().has("name", eq("marko")).out(&[0]).values(&["age"]).run(conf)`.
+//! The traversal is built into the same plan as compiled from the gremlin query by the compiler,
+//! and runs by the local pegasus runtime, which must have been started up, on the graph
+//! registered by `register_graph()`. Only a few steps are supported for now.
+
+use crate::compiler::GremlinJobCompiler;
+use crate::generated::common as common_pb;
+use crate::generated::gremlin as pb;
+use crate::plan_cache::{StepBindings, STEP_BINDINGS};
+use crate::prepared::PreparedQuery;
+pub use crate::process::adaptive::Adaptive;
+use crate::process::traversal::traverser::Traverser;
+use crate::result_process::object_to_pb_value;
+use crate::structure::{split_id, Element, GRAPH_NAMED_FEATURE};
+use crate::{str_to_dyn_error, Detach, DynResult, Partition, ID};
+use crossbeam_channel::{Receiver, Sender};
+use dyn_type::{CustomObject, Object, Primitives};
+use pegasus::api::function::*;
+use pegasus::{JobConf, JobGuard};
+use pegasus_common::collections::{Collection, CollectionFactory, Set};
+use pegasus_server::capability::PLAN_VERSION;
+use pegasus_server::error::QueryError;
+use pegasus_server::factory::{
+    AdaptivePoint, CompileResult, FoldFunction, GroupFunction, JobCompiler, PlanError, SourceAccess,
+};
+use pegasus_server::generated::protocol as server_pb;
+use pegasus_server::service::{Output, Service};
+use pegasus_server::{JobRequest, JobResponse, JobResult};
+use prost::Message;
+use std::sync::{Arc, Mutex};
+
+/// The feature of the engine required by the plans of `dedup_approx`
+pub const APPROX_DEDUP_FEATURE: &str = "dedup.approx";
+
+/// The feature of the engine required by the plans of `time_limit`
+pub const TIME_LIMIT_FEATURE: &str = "time_limit";
+
+/// The entry of the embedded traversals
+pub struct Graph;
+
+impl Graph {
+    pub fn traversal() -> GraphTraversalSource {
+        GraphTraversalSource {}
+    }
+
+    /// An anonymous traversal without source, as the sub-traversal of a step, e.g. `outE().count()`
+    /// of `order().by(outE().count())`
+    pub fn anonymous() -> GraphTraversal {
+        GraphTraversal {
+            source: vec![],
+            plan: vec![],
+            profile: false,
+            adaptive: Adaptive::Off,
+            elements: false,
+            detach: Detach::Reference,
+            graph: String::new(),
+        }
+    }
+}
+
+pub struct GraphTraversalSource {}
+
+impl GraphTraversalSource {
+    /// Scan all vertices, i.e. `g.V()`
+    pub fn v(self) -> GraphTraversal {
+        self.v_ids(&[])
+    }
+
+    /// Get the vertices of the given ids, i.e. `g.V(1, 2)`, or all vertices if no id is given
+    pub fn v_ids(self, ids: &[ID]) -> GraphTraversal {
+        Self::graph_step(ids, pb::EntityType::Vertex)
+    }
+
+    /// Scan all edges, i.e. `g.E()`
+    pub fn e(self) -> GraphTraversal {
+        self.e_ids(&[])
+    }
+
+    /// Get the edges of the given ids, i.e. `g.E(id1, id2)`, where the ids not of any edge are
+    /// skipped, or all edges if no id is given
+    pub fn e_ids(self, ids: &[ID]) -> GraphTraversal {
+        Self::graph_step(ids, pb::EntityType::Edge)
+    }
+
+    fn graph_step(ids: &[ID], return_type: pb::EntityType) -> GraphTraversal {
+        let (ids, high_ids): (Vec<i64>, Vec<u64>) = ids.iter().map(|id| split_id(*id)).unzip();
+        let graph_step = pb::GraphStep {
+            ids,
+            labels: vec![],
+            return_type: return_type as i32,
+            predicates: None,
+            traverser_requirements: vec![],
+            // the higher bits are left out unless any id is of 128 bits
+            high_ids: if high_ids.iter().any(|h| *h != 0) { high_ids } else { vec![] },
+        };
+        GraphTraversal {
+            source: encode_step(pb::gremlin_step::Step::GraphStep(graph_step)),
+            plan: vec![],
+            profile: false,
+            adaptive: Adaptive::Off,
+            elements: true,
+            detach: Detach::Reference,
+            graph: String::new(),
+        }
+    }
+
+    /// Start from the result saved under the name by a former traversal of the same session,
+    /// e.g. by `aggregate("x")`, where the session is given by the job config
+    pub fn session_ref(self, name: &str) -> GraphTraversal {
+        let session_ref = pb::SessionRefStep { name: name.to_owned() };
+        GraphTraversal {
+            source: encode_step(pb::gremlin_step::Step::SessionRefStep(session_ref)),
+            plan: vec![],
+            profile: false,
+            adaptive: Adaptive::Off,
+            elements: false,
+            detach: Detach::Reference,
+            graph: String::new(),
+        }
+    }
+}
+
+/// The predicate of `has()` or `is()`, e.g. `has("age", gt(30))`, which may be chained with others
+/// by `and()` and `or()` from left to right, e.g. `lte(28).or(gte(32))`
+pub struct P {
+    cmp: pb::Compare,
+    value: common_pb::Value,
+    // the value as shown in the name of the step
+    value_name: String,
+    epsilon: f64,
+    // the name of the predicate registered for the custom type of the value, of `custom()`
+    predicate: String,
+    // the predicates chained to this one in order, by the connectors before them
+    connected: Vec<(pb::Connect, P)>,
+}
+
+impl P {
+    fn new(cmp: pb::Compare, value: Object) -> Self {
+        let value_name = value_name(&value);
+        let value = object_to_pb_value(&value);
+        P { cmp, value, value_name, epsilon: 0.0, predicate: String::new(), connected: vec![] }
+    }
+
+    /// Compare the floats by `eq()` or `neq()` within the relative epsilon instead of the default
+    /// of the server, or exactly if it is negative
+    pub fn with_epsilon(mut self, epsilon: f64) -> Self {
+        self.epsilon = epsilon;
+        self
+    }
+
+    /// Both this and the other predicate hold, e.g. `gt(1).and(lt(5))`
+    pub fn and(mut self, other: P) -> Self {
+        self.connected.push((pb::Connect::And, other));
+        self
+    }
+
+    /// Either this or the other predicate holds, e.g. `lte(28).or(gte(32))`
+    pub fn or(mut self, other: P) -> Self {
+        self.connected.push((pb::Connect::Or, other));
+        self
+    }
+
+    /// Encode the predicates on the key, or on the values themselves if `None`, where the nodes
+    /// before a change of the connector are nested into a chain, as a chain short-circuits by the
+    /// connector after each node
+    fn to_chain(&self, key: Option<&common_pb::Key>) -> pb::FilterChain {
+        let exp = pb::FilterExp {
+            left: key.cloned(),
+            cmp: self.cmp as i32,
+            right: Some(self.value.clone()),
+            epsilon: self.epsilon,
+            predicate: self.predicate.clone(),
+        };
+        let mut nodes = vec![pb::FilterNode {
+            inner: Some(pb::filter_node::Inner::Single(exp)),
+            next: pb::Connect::And as i32,
+        }];
+        let mut last_connect: Option<pb::Connect> = None;
+        for (connect, p) in self.connected.iter() {
+            if last_connect.map(|c| c != *connect).unwrap_or(false) {
+                nodes = vec![chain_node(pb::FilterChain { node: nodes })];
+            }
+            if let Some(last) = nodes.last_mut() {
+                last.next = *connect as i32;
+            }
+            let mut chain = p.to_chain(key);
+            if chain.node.len() == 1 {
+                nodes.append(&mut chain.node);
+            } else {
+                nodes.push(chain_node(chain));
+            }
+            last_connect = Some(*connect);
+        }
+        pb::FilterChain { node: nodes }
+    }
+
+    /// e.g. "gt 1 and lt 5"
+    fn describe(&self) -> String {
+        let cmp = if self.cmp == pb::Compare::Custom {
+            self.predicate.as_str()
+        } else {
+            compare_name(self.cmp)
+        };
+        let mut name = format!("{} {}", cmp, self.value_name);
+        for (connect, p) in self.connected.iter() {
+            let connect = if *connect == pb::Connect::And { "and" } else { "or" };
+            let p =
+                if p.connected.is_empty() { p.describe() } else { format!("({})", p.describe()) };
+            name = format!("{} {} {}", name, connect, p);
+        }
+        name
+    }
+}
+
+pub fn eq<V: Into<Object>>(value: V) -> P {
+    P::new(pb::Compare::Eq, value.into())
+}
+
+pub fn neq<V: Into<Object>>(value: V) -> P {
+    P::new(pb::Compare::Ne, value.into())
+}
+
+pub fn lt<V: Into<Object>>(value: V) -> P {
+    P::new(pb::Compare::Lt, value.into())
+}
+
+pub fn lte<V: Into<Object>>(value: V) -> P {
+    P::new(pb::Compare::Le, value.into())
+}
+
+pub fn gt<V: Into<Object>>(value: V) -> P {
+    P::new(pb::Compare::Gt, value.into())
+}
+
+pub fn gte<V: Into<Object>>(value: V) -> P {
+    P::new(pb::Compare::Ge, value.into())
+}
+
+/// The value is one of the given ones, e.g. `within(vec![29, 32])`, which are either all numbers
+/// or all strings
+pub fn within<V: Into<Object>>(values: Vec<V>) -> P {
+    list_p(pb::Compare::Within, values)
+}
+
+/// The value is none of the given ones, e.g. `without(vec!["lop", "ripple"])`
+pub fn without<V: Into<Object>>(values: Vec<V>) -> P {
+    list_p(pb::Compare::Without, values)
+}
+
+/// The value satisfies the predicate registered by the name for its custom type on the servers,
+/// e.g. `has("location", custom("within_radius", center))`, see `register_custom_predicate`
+pub fn custom(name: &str, value: CustomObject) -> P {
+    let mut p = P::new(pb::Compare::Custom, Object::Custom(value));
+    p.predicate = name.to_owned();
+    p
+}
+
+fn list_p<V: Into<Object>>(cmp: pb::Compare, values: Vec<V>) -> P {
+    let values: Vec<Object> = values.into_iter().map(|v| v.into()).collect();
+    let value_name = format!("[{}]", values.iter().map(value_name).collect::<Vec<_>>().join(", "));
+    let value = objects_to_pb_array(&values);
+    P { cmp, value, value_name, epsilon: 0.0, predicate: String::new(), connected: vec![] }
+}
+
+/// A projection of the elements for by() in one access of their properties, e.g. the composite of
+/// `by_keys(&["age", "name"])` or the value of `by_expr("age * 2 + weight")`, where a missing
+/// value sorts after all the values and groups with the other missing ones, see `pb::Projection`
+pub struct By {
+    projection: pb::Projection,
+    // the projection as shown in the name of the step
+    name: String,
+}
+
+impl By {
+    fn to_tag_key(&self) -> pb::TagKey {
+        let item = pb::by_key::Item::Projection(self.projection.clone());
+        pb::TagKey { tag: None, by_key: Some(pb::ByKey { item: Some(item) }) }
+    }
+}
+
+/// By the composite of the properties `keys` in order
+pub fn by_keys(keys: &[&str]) -> By {
+    let name = format!("[{}]", keys.join(", "));
+    let keys = keys
+        .iter()
+        .map(|key| common_pb::Key { item: Some(common_pb::key::Item::Name(key.to_string())) })
+        .collect();
+    By { projection: pb::Projection { keys, expression: String::new() }, name }
+}
+
+/// By the value of the arithmetic `expression` of the properties, see `step::expr`
+pub fn by_expr(expression: &str) -> By {
+    let projection = pb::Projection { keys: vec![], expression: expression.to_owned() };
+    By { projection, name: expression.to_owned() }
+}
+
+/// A traversal being built, as the source step and the plan of the following steps
+pub struct GraphTraversal {
+    source: Vec<u8>,
+    plan: Vec<server_pb::OperatorDef>,
+    profile: bool,
+    adaptive: Adaptive,
+    // whether the heads of the traversers are known to be elements, by which `is()` is compiled
+    elements: bool,
+    detach: Detach,
+    // the name of the graph to run on, empty for the default graph
+    graph: String,
+}
+
+impl GraphTraversal {
+    /// Filter by a property of the elements, e.g. `has("name", eq("marko"))`
+    pub fn has(self, key: &str, p: P) -> Self {
+        let name = format!("has[{} {}]", key, p.describe());
+        let key = common_pb::Key { item: Some(common_pb::key::Item::Name(key.to_owned())) };
+        self.has_step(name, p.to_chain(Some(&key)))
+    }
+
+    /// Filter by the values themselves, e.g. `values("age").is(within(vec![29, 32]))`, or by the
+    /// ids of the elements, as `hasId()`, if the traversers are known to be of elements
+    pub fn is(mut self, p: P) -> Self {
+        let name = format!("is[{}]", p.describe());
+        if self.elements {
+            let key = common_pb::Key { item: Some(common_pb::key::Item::Id(common_pb::IdKey {})) };
+            return self.has_step(name, p.to_chain(Some(&key)));
+        }
+        let is_step = pb::IsStep { single: None, predicates: Some(p.to_chain(None)) };
+        let filter =
+            server_pb::Filter { resource: encode_step(pb::gremlin_step::Step::IsStep(is_step)) };
+        self.plan.push(pipeline_op(name, server_pb::operator_def::OpKind::Filter(filter)));
+        self
+    }
+
+    /// The adjacent vertices by the outgoing edges of given labels, or all edges if empty
+    pub fn out(self, edge_labels: &[i32]) -> Self {
+        self.vertex_step(pb::Direction::Out, edge_labels, pb::EntityType::Vertex)
+    }
+
+    /// The adjacent vertices by the incoming edges of given labels, or all edges if empty
+    pub fn in_(self, edge_labels: &[i32]) -> Self {
+        self.vertex_step(pb::Direction::In, edge_labels, pb::EntityType::Vertex)
+    }
+
+    /// The adjacent vertices by the edges of given labels in both directions
+    pub fn both(self, edge_labels: &[i32]) -> Self {
+        self.vertex_step(pb::Direction::Both, edge_labels, pb::EntityType::Vertex)
+    }
+
+    /// The outgoing edges of given labels, or all edges if empty
+    pub fn out_e(self, edge_labels: &[i32]) -> Self {
+        self.vertex_step(pb::Direction::Out, edge_labels, pb::EntityType::Edge)
+    }
+
+    /// The incoming edges of given labels, or all edges if empty
+    pub fn in_e(self, edge_labels: &[i32]) -> Self {
+        self.vertex_step(pb::Direction::In, edge_labels, pb::EntityType::Edge)
+    }
+
+    /// The edges of given labels in both directions
+    pub fn both_e(self, edge_labels: &[i32]) -> Self {
+        self.vertex_step(pb::Direction::Both, edge_labels, pb::EntityType::Edge)
+    }
+
+    /// The number of outgoing edges of given labels of each vertex, or of all edges if empty, i.e.
+    /// `outE().count()` as a sub-traversal, which is counted without expanding the edges
+    pub fn out_degree(self, edge_labels: &[i32]) -> Self {
+        self.degree_step(pb::Direction::Out, edge_labels)
+    }
+
+    /// The number of incoming edges of given labels of each vertex, i.e. `inE().count()`
+    pub fn in_degree(self, edge_labels: &[i32]) -> Self {
+        self.degree_step(pb::Direction::In, edge_labels)
+    }
+
+    /// The number of edges of given labels in both directions of each vertex, i.e.
+    /// `bothE().count()`
+    pub fn both_degree(self, edge_labels: &[i32]) -> Self {
+        self.degree_step(pb::Direction::Both, edge_labels)
+    }
+
+    /// The values of given properties of the elements, or of all properties if empty
+    pub fn values(mut self, properties: &[&str]) -> Self {
+        let name = format!("values[{}]", properties.join(", "));
+        let properties_step =
+            pb::PropertiesStep { properties: properties.iter().map(|p| p.to_string()).collect() };
+        let flat_map = server_pb::FlatMap {
+            resource: encode_step(pb::gremlin_step::Step::PropertiesStep(properties_step)),
+        };
+        self.plan.push(pipeline_op(name, server_pb::operator_def::OpKind::FlatMap(flat_map)));
+        self.elements = false;
+        self
+    }
+
+    /// Evaluate the arithmetic `expression` of `_` for the numeric heads, e.g.
+    /// `math("_ * 1.2 + 3")`, where a traverser dividing by zero is filtered out, see `step::expr`
+    pub fn math(mut self, expression: &str) -> Self {
+        let math_step = pb::MathStep { expression: expression.to_owned(), variables: vec![] };
+        let flat_map = server_pb::FlatMap {
+            resource: encode_step(pb::gremlin_step::Step::MathStep(math_step)),
+        };
+        let name = format!("math[{}]", expression);
+        self.plan.push(pipeline_op(name, server_pb::operator_def::OpKind::FlatMap(flat_map)));
+        self.elements = false;
+        self
+    }
+
+    pub fn count(mut self) -> Self {
+        let fold = server_pb::Fold {
+            range: server_pb::Range::Global as i32,
+            accum: server_pb::AccumKind::Cnt as i32,
+            resource: vec![],
+            unfold: Some(server_pb::FlatMap { resource: vec![] }),
+            ..Default::default()
+        };
+        let name = "count[global]".to_owned();
+        self.plan.push(pipeline_op(name, server_pb::operator_def::OpKind::Fold(fold)));
+        self.elements = false;
+        self
+    }
+
+    /// Count the distinct heads approximately by HyperLogLog of the `precision` in [4, 18], whose
+    /// standard error is about `1.04 / sqrt(2^precision)`, e.g. 0.81% of precision 14
+    pub fn count_approx_distinct(mut self, precision: u32) -> Self {
+        let fold = server_pb::Fold {
+            range: server_pb::Range::Global as i32,
+            accum: server_pb::AccumKind::ApproxDistinct as i32,
+            unfold: Some(server_pb::FlatMap { resource: vec![] }),
+            precision,
+            ..Default::default()
+        };
+        let name = format!("approxDistinct[{}]", precision);
+        self.plan.push(pipeline_op(name, server_pb::operator_def::OpKind::Fold(fold)));
+        self.elements = false;
+        self
+    }
+
+    /// Estimate the quantiles `qs` in [0, 1] of the numeric heads by t-digest, which emits a float
+    /// per quantile in the order of `qs`
+    pub fn quantiles(mut self, qs: &[f64]) -> Self {
+        let fold = server_pb::Fold {
+            range: server_pb::Range::Global as i32,
+            accum: server_pb::AccumKind::Quantiles as i32,
+            unfold: Some(server_pb::FlatMap { resource: vec![] }),
+            quantiles: qs.to_vec(),
+            ..Default::default()
+        };
+        let name = format!("quantiles[{}]", join_values(qs));
+        self.plan.push(pipeline_op(name, server_pb::operator_def::OpKind::Fold(fold)));
+        self.elements = false;
+        self
+    }
+
+    /// Fold the traversers into a single list, or the traversers of each scope apart in a
+    /// sub-traversal, e.g. `where(out().fold())`, which fails once the list exceeds the result
+    /// limit of the traversal
+    pub fn fold(mut self) -> Self {
+        let fold = server_pb::Fold {
+            range: server_pb::Range::Global as i32,
+            accum: server_pb::AccumKind::Custom as i32,
+            resource: encode_step(pb::gremlin_step::Step::FoldStep(pb::FoldStep {})),
+            unfold: Some(server_pb::FlatMap { resource: vec![] }),
+            ..Default::default()
+        };
+        self.plan.push(pipeline_op("fold".to_owned(), server_pb::operator_def::OpKind::Fold(fold)));
+        self.elements = false;
+        self
+    }
+
+    /// Emit the items of the lists one by one, e.g. `fold().unfold()`, where the heads other than
+    /// lists pass through unchanged
+    pub fn unfold(mut self) -> Self {
+        let flat_map = server_pb::FlatMap {
+            resource: encode_step(pb::gremlin_step::Step::UnfoldStep(pb::UnfoldStep {})),
+        };
+        let name = "unfold".to_owned();
+        self.plan.push(pipeline_op(name, server_pb::operator_def::OpKind::FlatMap(flat_map)));
+        self.elements = false;
+        self
+    }
+
+    /// Count the items of the collection head of each traverser apart, e.g.
+    /// `out().fold().count(local)`, or the columns of `project()`, without a barrier across the
+    /// traversers, where the heads other than collections are filtered out
+    pub fn count_local(self) -> Self {
+        self.accum_local(pb::numeric_accum_step::Kind::Count, "count")
+    }
+
+    /// Sum up the numeric items of the collection head of each traverser apart, e.g.
+    /// `values("age").fold().sum(local)`, where an empty collection gives nothing
+    pub fn sum_local(self) -> Self {
+        self.accum_local(pb::numeric_accum_step::Kind::Sum, "sum")
+    }
+
+    pub fn min_local(self) -> Self {
+        self.accum_local(pb::numeric_accum_step::Kind::Min, "min")
+    }
+
+    pub fn max_local(self) -> Self {
+        self.accum_local(pb::numeric_accum_step::Kind::Max, "max")
+    }
+
+    pub fn mean_local(self) -> Self {
+        self.accum_local(pb::numeric_accum_step::Kind::Mean, "mean")
+    }
+
+    fn accum_local(mut self, kind: pb::numeric_accum_step::Kind, name: &str) -> Self {
+        let accum = pb::NumericAccumStep { kind: kind as i32, local: true };
+        let flat_map = server_pb::FlatMap {
+            resource: encode_step(pb::gremlin_step::Step::NumericAccumStep(accum)),
+        };
+        let name = format!("{}[local]", name);
+        self.plan.push(pipeline_op(name, server_pb::operator_def::OpKind::FlatMap(flat_map)));
+        self.elements = false;
+        self
+    }
+
+    /// Order the traversers by the value of the sub-traversal `by` of each, in descending order if
+    /// `desc`, e.g. `order().by(outE().count(), desc)` by
+    /// `order_by(Graph::anonymous().out_degree(&[]), true)`
+    pub fn order_by(mut self, by: GraphTraversal, desc: bool) -> Self {
+        let joiner = pb::SubTaskJoiner {
+            inner: Some(pb::sub_task_joiner::Inner::ByJoiner(pb::ByJoiner {})),
+        };
+        let mut resource = vec![];
+        joiner.encode(&mut resource).expect("encode joiner failure");
+        let subtask = server_pb::Subtask {
+            join: Some(server_pb::LeftJoin { resource }),
+            task: Some(server_pb::TaskPlan { plan: by.plan }),
+            ..Default::default()
+        };
+        self.plan
+            .push(pipeline_op("by".to_owned(), server_pb::operator_def::OpKind::Subtask(subtask)));
+        let order = if desc {
+            pb::order_by_compare_pair::Order::Desc
+        } else {
+            pb::order_by_compare_pair::Order::Asc
+        };
+        let computed = pb::ByKey { item: Some(pb::by_key::Item::Computed(pb::SubValue {})) };
+        let pair = pb::OrderByComparePair {
+            key: Some(pb::TagKey { tag: None, by_key: Some(computed) }),
+            order: order as i32,
+        };
+        let order_by_step = pb::OrderByStep { pairs: vec![pair] };
+        let order_by = server_pb::OrderBy {
+            range: server_pb::Range::Global as i32,
+            limit: 0,
+            compare: encode_step(pb::gremlin_step::Step::OrderByStep(order_by_step)),
+        };
+        let name = if desc { "order[desc]" } else { "order[asc]" };
+        self.plan
+            .push(pipeline_op(name.to_owned(), server_pb::operator_def::OpKind::Order(order_by)));
+        self
+    }
+
+    /// Order the traversers by the property `key` of the heads, in descending order if `desc`,
+    /// e.g. `order().by("weight", desc)` by `order_by_key("weight", true)`
+    pub fn order_by_key(mut self, key: &str, desc: bool) -> Self {
+        let order = if desc {
+            pb::order_by_compare_pair::Order::Desc
+        } else {
+            pb::order_by_compare_pair::Order::Asc
+        };
+        let key = common_pb::Key { item: Some(common_pb::key::Item::Name(key.to_owned())) };
+        let pair = pb::OrderByComparePair {
+            key: Some(pb::TagKey {
+                tag: None,
+                by_key: Some(pb::ByKey { item: Some(pb::by_key::Item::Key(key)) }),
+            }),
+            order: order as i32,
+        };
+        let order_by_step = pb::OrderByStep { pairs: vec![pair] };
+        let order_by = server_pb::OrderBy {
+            range: server_pb::Range::Global as i32,
+            limit: 0,
+            compare: encode_step(pb::gremlin_step::Step::OrderByStep(order_by_step)),
+        };
+        let name = if desc { "order[desc]" } else { "order[asc]" };
+        self.plan
+            .push(pipeline_op(name.to_owned(), server_pb::operator_def::OpKind::Order(order_by)));
+        self
+    }
+
+    /// Order the elements by their projections, in descending order if `desc`, e.g.
+    /// `order().by(values("age", "name"), desc)` by `order_by_projection(by_keys(..), true)`
+    pub fn order_by_projection(mut self, by: By, desc: bool) -> Self {
+        let order = if desc {
+            pb::order_by_compare_pair::Order::Desc
+        } else {
+            pb::order_by_compare_pair::Order::Asc
+        };
+        let pair = pb::OrderByComparePair { key: Some(by.to_tag_key()), order: order as i32 };
+        let order_by_step = pb::OrderByStep { pairs: vec![pair] };
+        let order_by = server_pb::OrderBy {
+            range: server_pb::Range::Global as i32,
+            limit: 0,
+            compare: encode_step(pb::gremlin_step::Step::OrderByStep(order_by_step)),
+        };
+        let name = format!("order[{} {}]", by.name, if desc { "desc" } else { "asc" });
+        self.plan.push(pipeline_op(name, server_pb::operator_def::OpKind::Order(order_by)));
+        self
+    }
+
+    /// Count the elements of each projection, e.g. `groupCount().by(values("age", "name"))` by
+    /// `group_count_by(by_keys(&["age", "name"]))`, which emits a pair of the projection and the
+    /// count for each
+    pub fn group_count_by(mut self, by: By) -> Self {
+        let group_by_step = pb::GroupByStep {
+            key: Some(by.to_tag_key()),
+            accum: pb::group_by_step::AccumKind::Cnt as i32,
+            opt_order: vec![],
+        };
+        let group = server_pb::GroupBy {
+            range: server_pb::Range::Global as i32,
+            map: encode_step(pb::gremlin_step::Step::GroupByStep(group_by_step)),
+            unfold: Some(server_pb::FlatMap { resource: vec![] }),
+        };
+        let name = format!("groupCount[{}]", by.name);
+        self.plan.push(pipeline_op(name, server_pb::operator_def::OpKind::Group(group)));
+        self.elements = false;
+        self
+    }
+
+    /// Keep the traversers whose sub-traversal `sub` emits any result, e.g. `where(out())` by
+    /// `where_(Graph::anonymous().out(&[]))`
+    pub fn where_(mut self, sub: GraphTraversal) -> Self {
+        let subtask = server_pb::Subtask {
+            join: None,
+            task: Some(server_pb::TaskPlan { plan: sub.plan }),
+            semi_join: server_pb::subtask::SemiJoin::AnyExists as i32,
+            optional: false,
+            first: false,
+        };
+        self.plan.push(pipeline_op(
+            "where".to_owned(),
+            server_pb::operator_def::OpKind::Subtask(subtask),
+        ));
+        self
+    }
+
+    /// Emit the results of the first sub-traversal of `branches` with any result for each
+    /// traverser, e.g. `coalesce(out("knows"), out())`, where a traverser is passed to a branch
+    /// only if all the branches before emit nothing for it
+    pub fn coalesce(mut self, branches: Vec<GraphTraversal>) -> Self {
+        let branches = branches.into_iter().map(|b| server_pb::TaskPlan { plan: b.plan }).collect();
+        let coalesce = server_pb::Coalesce { branches };
+        self.plan.push(pipeline_op(
+            "coalesce".to_owned(),
+            server_pb::operator_def::OpKind::Coalesce(coalesce),
+        ));
+        self.elements = false;
+        self
+    }
+
+    /// Bound the traversal so far by time, e.g. `timeLimit(100)` by `time_limit(100)`, which stops
+    /// traversing once `ms` milliseconds pass since it starts, and emits the traversers reaching
+    /// here so far as if there were no more; The deadline is checked between the batches of the
+    /// steps, so a step busy on a batch overruns it until the batch is done
+    pub fn time_limit(mut self, ms: u64) -> Self {
+        let task = server_pb::TaskPlan { plan: std::mem::take(&mut self.plan) };
+        let time_limit = server_pb::TimeLimit { ms, task: Some(task) };
+        self.plan.push(pipeline_op(
+            format!("timeLimit[{}]", ms),
+            server_pb::operator_def::OpKind::TimeLimit(time_limit),
+        ));
+        self
+    }
+
+    /// Emit the results of the sub-traversal `sub` of each traverser, or the traverser itself if
+    /// there is none, e.g. `optional(out("knows"))` by `optional(Graph::anonymous().out(&[0]))`
+    pub fn optional(mut self, sub: GraphTraversal) -> Self {
+        let subtask = server_pb::Subtask {
+            task: Some(server_pb::TaskPlan { plan: sub.plan }),
+            optional: true,
+            ..Default::default()
+        };
+        self.plan.push(pipeline_op(
+            "optional".to_owned(),
+            server_pb::operator_def::OpKind::Subtask(subtask),
+        ));
+        self.elements = false;
+        self
+    }
+
+    /// Emit a record of the `columns` for each element, each valued by the first result of its
+    /// sub-traversal in `bys`, e.g. `project("name", "degree").by("name").by(out().count())` by
+    /// `project(&["name", "degree"], vec![name_by, degree_by], false)`; A column whose
+    /// sub-traversal emits nothing is missing in the record, or null if `null_if_empty`, while the
+    /// count of nothing is 0
+    pub fn project(
+        mut self, columns: &[&str], bys: Vec<GraphTraversal>, null_if_empty: bool,
+    ) -> Self {
+        assert_eq!(columns.len(), bys.len(), "each column of project() requires a by()");
+        let mut project_columns = Vec::with_capacity(columns.len());
+        for (column, by) in columns.iter().zip(bys) {
+            // a single property is read from the elements directly, instead of by a subtask
+            let key = by
+                .single_property()
+                .map(|name| common_pb::Key { item: Some(common_pb::key::Item::Name(name)) });
+            if key.is_none() {
+                let empty = if by.ends_with_count() {
+                    Some(common_pb::Value { item: Some(common_pb::value::Item::I64(0)) })
+                } else {
+                    None
+                };
+                let joiner = pb::SubTaskJoiner {
+                    inner: Some(pb::sub_task_joiner::Inner::ProjectByJoiner(pb::ProjectByJoiner {
+                        column: column.to_string(),
+                        empty,
+                    })),
+                };
+                let mut resource = vec![];
+                joiner.encode(&mut resource).expect("encode joiner failure");
+                let subtask = server_pb::Subtask {
+                    join: Some(server_pb::LeftJoin { resource }),
+                    task: Some(server_pb::TaskPlan { plan: by.plan }),
+                    first: true,
+                    ..Default::default()
+                };
+                let name = format!("by[{}]", column);
+                self.plan
+                    .push(pipeline_op(name, server_pb::operator_def::OpKind::Subtask(subtask)));
+            }
+            project_columns.push(pb::ProjectColumn {
+                name: column.to_string(),
+                key,
+                projection: None,
+            });
+        }
+        let project_step = pb::ProjectStep { columns: project_columns, null_if_empty };
+        let map = server_pb::Map {
+            resource: encode_step(pb::gremlin_step::Step::ProjectStep(project_step)),
+        };
+        let name = format!("project[{}]", columns.join(", "));
+        self.plan.push(pipeline_op(name, server_pb::operator_def::OpKind::Map(map)));
+        self.elements = false;
+        self
+    }
+
+    /// Keep the first `limit` traversers, which turns the order right before into the top-k, e.g.
+    /// `order().by(outE().count(), desc).limit(10)`
+    pub fn limit(mut self, limit: u32) -> Self {
+        if let Some(op) = self.plan.last_mut() {
+            if let Some(server_pb::operator_def::OpKind::Order(order)) = op.op_kind.as_mut() {
+                if order.range == server_pb::Range::Global as i32 && order.limit <= 0 {
+                    order.limit = limit as i64;
+                    op.name = format!("{}.limit[{}]", op.name, limit);
+                    return self;
+                }
+            }
+        }
+        let name = format!("limit[{}]", limit);
+        let limit = server_pb::Limit { range: server_pb::Range::Global as i32, limit };
+        self.plan.push(pipeline_op(name, server_pb::operator_def::OpKind::Limit(limit)));
+        self
+    }
+
+    pub fn dedup(self) -> Self {
+        self.dedup_with_filter("dedup[global]".to_owned(), 0, 0.0, None)
+    }
+
+    /// Dedup the elements by their projections, e.g. `dedup().by(values("age", "name"))`, which
+    /// keeps the first element of each projection
+    pub fn dedup_by(self, by: By) -> Self {
+        let name = format!("dedup[global, by {}]", by.name);
+        self.dedup_with_filter(name, 0, 0.0, Some(by.to_tag_key()))
+    }
+
+    /// Dedup in bounded memory by the query hint of the expected distinct traversers, i.e. by a
+    /// Bloom filter of `expected_items` at the false positive rate `fp_rate`, which never emits a
+    /// duplicate but may drop a few distinct traversers
+    pub fn dedup_approx(self, expected_items: u64, fp_rate: f64) -> Self {
+        let name = format!("dedup[global, approx {} at {}]", expected_items, fp_rate);
+        self.dedup_with_filter(name, expected_items, fp_rate, None)
+    }
+
+    fn dedup_with_filter(
+        mut self, name: String, expected_items: u64, fp_rate: f64, key: Option<pb::TagKey>,
+    ) -> Self {
+        let dedup_type = pb::dedup_step::DedupSetType::HashSet as i32;
+        let dedup_step = pb::DedupStep { dedup_type, key };
+        let dedup = server_pb::Dedup {
+            range: server_pb::Range::Global as i32,
+            set: encode_step(pb::gremlin_step::Step::DedupStep(dedup_step)),
+            expected_items,
+            fp_rate,
+        };
+        self.plan.push(pipeline_op(name, server_pb::operator_def::OpKind::Dedup(dedup)));
+        self
+    }
+
+    /// Collect the traversers into the side collection named by `key` as a barrier, e.g.
+    /// `aggregate("x")`
+    pub fn aggregate(mut self, key: &str) -> Self {
+        let step = pb::SideEffectStep {
+            kind: pb::side_effect_step::Kind::Aggregate as i32,
+            key: key.to_owned(),
+            max_edges: 0,
+        };
+        let fold = server_pb::Fold {
+            range: server_pb::Range::Global as i32,
+            accum: server_pb::AccumKind::ToList as i32,
+            resource: vec![],
+            unfold: Some(server_pb::FlatMap {
+                resource: encode_step(pb::gremlin_step::Step::SideEffectStep(step)),
+            }),
+            ..Default::default()
+        };
+        let name = format!("aggregate[{}]", key);
+        self.plan.push(pipeline_op(name, server_pb::operator_def::OpKind::Fold(fold)));
+        self
+    }
+
+    /// Collect the edges into the subgraph named by `key`, e.g. `subgraph("sg")`, with at most
+    /// the default number of edges
+    pub fn subgraph(self, key: &str) -> Self {
+        self.subgraph_with_max_edges(key, 0)
+    }
+
+    /// Collect the edges into the subgraph named by `key`, which fails the traversal once it has
+    /// more than `max_edges` distinct edges
+    pub fn subgraph_with_max_edges(mut self, key: &str, max_edges: u64) -> Self {
+        let step = pb::SideEffectStep {
+            kind: pb::side_effect_step::Kind::Subgraph as i32,
+            key: key.to_owned(),
+            max_edges,
+        };
+        let fold = server_pb::Fold {
+            range: server_pb::Range::Global as i32,
+            accum: server_pb::AccumKind::ToList as i32,
+            resource: vec![],
+            unfold: Some(server_pb::FlatMap {
+                resource: encode_step(pb::gremlin_step::Step::SideEffectStep(step)),
+            }),
+            ..Default::default()
+        };
+        let name = format!("subgraph[{}]", key);
+        self.plan.push(pipeline_op(name, server_pb::operator_def::OpKind::Fold(fold)));
+        self
+    }
+
+    /// Emit the side effect named by `key` once the traversal completes, e.g. `cap("sg")`, which
+    /// emits the handle of a subgraph to get it by `get_subgraph()`
+    pub fn cap(mut self, key: &str) -> Self {
+        let cap = pb::CapStep { key: key.to_owned() };
+        let fold = server_pb::Fold {
+            range: server_pb::Range::Global as i32,
+            accum: server_pb::AccumKind::Cnt as i32,
+            resource: vec![],
+            unfold: Some(server_pb::FlatMap {
+                resource: encode_step(pb::gremlin_step::Step::CapStep(cap)),
+            }),
+            ..Default::default()
+        };
+        let name = format!("cap[{}]", key);
+        self.plan.push(pipeline_op(name, server_pb::operator_def::OpKind::Fold(fold)));
+        self.elements = false;
+        self
+    }
+
+    /// Profile the time and records of each step on running, to be got by
+    /// `ResultStream::profile()` once all results are consumed
+    pub fn profile(mut self) -> Self {
+        self.profile = true;
+        self
+    }
+
+    /// Order the consecutive has steps at runtime by the mode, e.g. by the pass rates of the
+    /// steps over the traversers sampled by `Adaptive::Sample`, where the orders decided are told
+    /// in the profile of the traversal, see `adaptive_has`
+    pub fn adaptive(mut self, adaptive: Adaptive) -> Self {
+        self.adaptive = adaptive;
+        self
+    }
+
+    /// Give the vertices and edges in the encoded results by the policy, e.g. with all their
+    /// properties by `Detach::Full`, instead of their ids and labels only
+    pub fn detach(mut self, detach: Detach) -> Self {
+        self.detach = detach;
+        self
+    }
+
+    /// Run on the graph registered by the name, see `register_named_graph`, instead of the default
+    /// graph
+    pub fn on_graph(mut self, name: &str) -> Self {
+        self.graph = name.to_owned();
+        self
+    }
+
+    /// Get the job request of the traversal, as submitted to the rpc service
+    pub fn to_request(&self, mut conf: server_pb::JobConfig) -> JobRequest {
+        conf.profile |= self.profile;
+        // the default policy is sent as no resource, as by the clients not aware of the policies
+        let mut detach = vec![];
+        if self.detach != Detach::Reference {
+            self.detach.to_pb().encode(&mut detach).expect("encode detach policy failure");
+        }
+        let mut features = vec![];
+        if !self.graph.is_empty() {
+            features.push(GRAPH_NAMED_FEATURE.to_owned());
+        }
+        if has_approx_dedup(&self.plan) {
+            features.push(APPROX_DEDUP_FEATURE.to_owned());
+        }
+        if has_op(&self.plan, &|kind| matches!(kind, server_pb::operator_def::OpKind::TimeLimit(_)))
+        {
+            features.push(TIME_LIMIT_FEATURE.to_owned());
+        }
+        JobRequest {
+            conf: Some(conf),
+            source: Some(server_pb::Source { resource: self.source.clone() }),
+            plan: Some(server_pb::TaskPlan { plan: self.plan.clone() }),
+            sink: Some(server_pb::Sink { sinker: Some(server_pb::sink::Sinker::Resource(detach)) }),
+            plan_version: PLAN_VERSION,
+            features,
+            graph: self.graph.clone(),
+        }
+    }
+
+    /// Run the traversal on current server, where the graph elements in the results are given
+    /// by their ids.
+    pub fn run(self, conf: server_pb::JobConfig) -> ResultStream<Object> {
+        run_request(self.to_request(conf), self.profile, self.adaptive, None)
+    }
+
+    /// Explain the traversal instead of running it, i.e. its steps with how the source reads the
+    /// graph and the records each step is estimated to output, along with the dataflow it would
+    /// run on current server, or the error failing the traversal to be built
+    pub fn explain(&self, mut conf: server_pb::JobConfig) -> DynResult<server_pb::JobPlan> {
+        conf.explain = true;
+        let service = Service::new(GremlinJobCompiler::new(Partition { num_servers: 1 }, 1, 0));
+        let output = PlanOutput::default();
+        // the plan is sent before the request is accepted, as the job never runs
+        service.accept(self.to_request(conf), output.clone());
+        let plan = output.plan.lock().ok().and_then(|mut plan| plan.take());
+        plan.unwrap_or_else(|| Err(str_to_dyn_error("no plan is explained for the traversal")))
+    }
+
+    /// Prepare the traversal once to be run again and again with only the literals of its
+    /// predicates changed, which are validated here instead of each run
+    pub fn prepare(&self) -> Result<PreparedTraversal, PlanError> {
+        let compiler = GremlinJobCompiler::new(Partition { num_servers: 1 }, 1, 0);
+        let query = compiler.prepare_query(&self.to_request(server_pb::JobConfig::default()))?;
+        Ok(PreparedTraversal { query, profile: self.profile, adaptive: self.adaptive })
+    }
+
+    fn has_step(mut self, name: String, predicates: pb::FilterChain) -> Self {
+        let has_step = pb::HasStep { predicates: Some(predicates) };
+        let filter =
+            server_pb::Filter { resource: encode_step(pb::gremlin_step::Step::HasStep(has_step)) };
+        self.plan.push(pipeline_op(name, server_pb::operator_def::OpKind::Filter(filter)));
+        self
+    }
+
+    // the property if the traversal is only the values of a single property, e.g. values("name")
+    fn single_property(&self) -> Option<String> {
+        let flat_map = match self.plan.as_slice() {
+            [op] => match op.op_kind.as_ref() {
+                Some(server_pb::operator_def::OpKind::FlatMap(flat_map)) => flat_map,
+                _ => return None,
+            },
+            _ => return None,
+        };
+        match pb::GremlinStep::decode(flat_map.resource.as_slice()).ok()?.step {
+            Some(pb::gremlin_step::Step::PropertiesStep(mut properties))
+                if properties.properties.len() == 1 =>
+            {
+                properties.properties.pop()
+            }
+            _ => None,
+        }
+    }
+
+    fn ends_with_count(&self) -> bool {
+        match self.plan.last().and_then(|op| op.op_kind.as_ref()) {
+            Some(server_pb::operator_def::OpKind::Fold(fold)) => {
+                fold.accum == server_pb::AccumKind::Cnt as i32
+            }
+            _ => false,
+        }
+    }
+
+    fn degree_step(mut self, direction: pb::Direction, edge_labels: &[i32]) -> Self {
+        let degree_step = pb::DegreeStep {
+            edge_labels: edge_labels.to_vec(),
+            direction: direction as i32,
+            predicates: None,
+        };
+        let map = server_pb::Map {
+            resource: encode_step(pb::gremlin_step::Step::DegreeStep(degree_step)),
+        };
+        // the edges are counted where the vertices are stored
+        let ch = server_pb::ChannelDef {
+            ch_kind: Some(server_pb::channel_def::ChKind::ToAnother(server_pb::Exchange {
+                resource: vec![],
+            })),
+        };
+        let name = format!("{}Degree[{}]", direction_name(direction), join_values(edge_labels));
+        self.plan.push(server_pb::OperatorDef {
+            name,
+            ch: Some(ch),
+            op_kind: Some(server_pb::operator_def::OpKind::Map(map)),
+        });
+        self.elements = false;
+        self
+    }
+
+    fn vertex_step(
+        mut self, direction: pb::Direction, edge_labels: &[i32], return_type: pb::EntityType,
+    ) -> Self {
+        let vertex_step = pb::VertexStep {
+            edge_labels: edge_labels.to_vec(),
+            direction: direction as i32,
+            return_type: return_type as i32,
+            predicates: None,
+        };
+        let flat_map = server_pb::FlatMap {
+            resource: encode_step(pb::gremlin_step::Step::VertexStep(vertex_step)),
+        };
+        // exchange the vertices to where they are stored, before exploring their edges
+        let ch = server_pb::ChannelDef {
+            ch_kind: Some(server_pb::channel_def::ChKind::ToAnother(server_pb::Exchange {
+                resource: vec![],
+            })),
+        };
+        let mut name = direction_name(direction).to_owned();
+        if return_type == pb::EntityType::Edge {
+            name.push('E');
+        }
+        if !edge_labels.is_empty() {
+            name = format!("{}[{}]", name, join_values(edge_labels));
+        }
+        self.plan.push(server_pb::OperatorDef {
+            name,
+            ch: Some(ch),
+            op_kind: Some(server_pb::operator_def::OpKind::FlatMap(flat_map)),
+        });
+        self.elements = true;
+        self
+    }
+}
+
+/// A traversal prepared by `GraphTraversal::prepare`
+pub struct PreparedTraversal {
+    query: PreparedQuery,
+    profile: bool,
+    adaptive: Adaptive,
+}
+
+impl PreparedTraversal {
+    /// The types of the parameters in the order of the predicates, e.g. `["str", "i32"]`
+    pub fn slots(&self) -> &[&'static str] {
+        self.query.slots()
+    }
+
+    /// Run the traversal with the parameters bound to the literals of its predicates in order,
+    /// which fails before the traversal is submitted if they don't fit the slots
+    pub fn run(
+        &self, params: &[Object], mut conf: server_pb::JobConfig,
+    ) -> Result<ResultStream<Object>, PlanError> {
+        conf.profile |= self.profile;
+        let mut values = Vec::with_capacity(params.len());
+        for (i, param) in params.iter().enumerate() {
+            if let Object::DynOwned(_) = param {
+                let msg = format!("parameter {} of a dynamic type can't be bound", i);
+                return Err(PlanError::new(vec![], msg));
+            }
+            values.push(object_to_pb_value(param));
+        }
+        let bindings = self.query.bind(&values)?;
+        Ok(run_request(self.query.request(conf), self.profile, self.adaptive, Some(bindings)))
+    }
+}
+
+/// Run the request on current server, with the steps bound ahead if it is of a prepared traversal
+fn run_request(
+    req: JobRequest, profiled: bool, adaptive: Adaptive, bindings: Option<StepBindings>,
+) -> ResultStream<Object> {
+    let job_id = req.conf.as_ref().map(|conf| conf.job_id).unwrap_or_default();
+    let (tx, rx) = crossbeam_channel::unbounded();
+    let compiler = EmbeddedCompiler {
+        inner: GremlinJobCompiler::new(Partition { num_servers: 1 }, 1, 0).with_adaptive(adaptive),
+        tx: tx.clone(),
+    };
+    let service = Service::new(compiler);
+    let profile = Arc::new(Mutex::new(None));
+    // the stream of a profiled traversal waits for the profile sent once the job is torn down,
+    // instead of ending once the job is complete
+    let (complete_tx, complete_rx) = crossbeam_channel::bounded(1);
+    let (complete_tx, complete) =
+        if profiled { (None, None) } else { (Some(complete_tx), Some(complete_rx)) };
+    let output = EmbeddedOutput { tx, profile: profile.clone(), complete: complete_tx };
+    match bindings {
+        Some(bindings) => {
+            let bind = |conf: &mut JobConf| {
+                conf.set_user_data(STEP_BINDINGS, bindings);
+            };
+            service.accept_prepared(req, bind, output)
+        }
+        None => service.accept(req, output),
+    }
+    // the results are sent into the stream by the job, so only the guard of the job is kept,
+    // to report the failure of the job at the end of the stream
+    let guard = service.job_guards.write().ok().and_then(|mut guards| guards.remove(&job_id));
+    ResultStream { rx, guard, profile, complete, completed: false }
+}
+
+/// The results of a traversal, which ends once the traversal is completed, with an error at last
+/// if the traversal fails. The stream ends as soon as the job of the traversal is told complete,
+/// while the job is torn down in the background.
+pub struct ResultStream<T> {
+    rx: Receiver<DynResult<T>>,
+    guard: Option<JobGuard>,
+    profile: Arc<Mutex<Option<server_pb::JobProfile>>>,
+    // tells the job is complete, after all its results are sent
+    complete: Option<Receiver<()>>,
+    completed: bool,
+}
+
+impl<T> ResultStream<T> {
+    /// The profile of the steps of a traversal run with `profile()`, which is sent before the
+    /// stream ends, so it is `None` until all results are consumed.
+    pub fn profile(&self) -> Option<server_pb::JobProfile> {
+        self.profile.lock().ok().and_then(|profile| profile.clone())
+    }
+}
+
+impl<T> Iterator for ResultStream<T> {
+    type Item = DynResult<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.completed {
+                // the results are all sent before the job is told complete
+                return match self.rx.try_recv() {
+                    Ok(next) => Some(next),
+                    Err(_) => {
+                        self.detach();
+                        None
+                    }
+                };
+            }
+            let complete = match self.complete.as_ref() {
+                Some(complete) => complete,
+                None => break,
+            };
+            let ready = crossbeam_channel::select! {
+                recv(self.rx) -> next => Ok(next),
+                recv(complete) -> told => Err(told.is_ok()),
+            };
+            match ready {
+                Ok(Ok(next)) => return Some(next),
+                Ok(Err(_)) => break,
+                Err(true) => self.completed = true,
+                // never told complete, e.g. as the job fails
+                Err(false) => self.complete = None,
+            }
+        }
+        if let Ok(next) = self.rx.recv() {
+            Some(next)
+        } else if let Some(mut guard) = self.guard.take() {
+            guard.join().err().map(|err| Err(str_to_dyn_error(&err.to_string())))
+        } else {
+            None
+        }
+    }
+}
+
+impl<T> ResultStream<T> {
+    // tear down the complete job in the background instead of waiting for it
+    fn detach(&mut self) {
+        if let Some(mut guard) = self.guard.take() {
+            std::thread::spawn(move || {
+                if let Err(err) = guard.join() {
+                    error!("job[{}] fails on tearing down: {}", guard.job_id, err);
+                }
+            });
+        }
+    }
+}
+
+impl<T> Drop for ResultStream<T> {
+    fn drop(&mut self) {
+        // the traversal is no more needed if dropped before it ends
+        if let Some(mut guard) = self.guard.take() {
+            guard.cancel_execute();
+        }
+    }
+}
+
+#[inline]
+fn encode_step(step: pb::gremlin_step::Step) -> Vec<u8> {
+    let step = pb::GremlinStep { tags: vec![], remove_tags: vec![], step: Some(step) };
+    let mut bytes = vec![];
+    step.encode(&mut bytes).expect("encode gremlin step failure");
+    bytes
+}
+
+fn chain_node(chain: pb::FilterChain) -> pb::FilterNode {
+    let mut bytes = vec![];
+    chain.encode(&mut bytes).expect("encode filter chain failure");
+    pb::FilterNode {
+        inner: Some(pb::filter_node::Inner::Chain(bytes)),
+        next: pb::Connect::And as i32,
+    }
+}
+
+/// Encode the values of `within()` into a list of strings if all are strings, or of floats if any
+/// is a float, or else of longs
+fn objects_to_pb_array(values: &[Object]) -> common_pb::Value {
+    use common_pb::value::Item;
+    let item = if !values.is_empty() && values.iter().all(|v| matches!(v, Object::String(_))) {
+        let item = values.iter().filter_map(|v| v.as_str().ok().map(|s| s.into_owned())).collect();
+        Item::StrArray(common_pb::StringArray { item })
+    } else if values.iter().any(|v| matches!(v, Object::Primitive(Primitives::Float(_)))) {
+        let item = values.iter().filter_map(|v| v.as_f64().ok()).collect();
+        Item::F64Array(common_pb::DoubleArray { item })
+    } else {
+        let item = values.iter().filter_map(|v| v.as_i64().ok()).collect();
+        Item::I64Array(common_pb::I64Array { item })
+    };
+    common_pb::Value { item: Some(item) }
+}
+
+#[inline]
+fn pipeline_op(name: String, op_kind: server_pb::operator_def::OpKind) -> server_pb::OperatorDef {
+    let ch = server_pb::ChannelDef {
+        ch_kind: Some(server_pb::channel_def::ChKind::ToLocal(server_pb::Pipeline {})),
+    };
+    server_pb::OperatorDef { name, ch: Some(ch), op_kind: Some(op_kind) }
+}
+
+/// Whether any operator of the plan or its sub-plans is of `pred`
+fn has_op(
+    plan: &[server_pb::OperatorDef], pred: &dyn Fn(&server_pb::operator_def::OpKind) -> bool,
+) -> bool {
+    use server_pb::operator_def::OpKind;
+    plan.iter().filter_map(|op| op.op_kind.as_ref()).any(|kind| {
+        pred(kind)
+            || match kind {
+                OpKind::Iterate(iter) => iter.body.as_ref().is_some_and(|b| has_op(&b.plan, pred)),
+                OpKind::Subtask(subtask) => {
+                    subtask.task.as_ref().is_some_and(|t| has_op(&t.plan, pred))
+                }
+                OpKind::TimeLimit(time_limit) => {
+                    time_limit.task.as_ref().is_some_and(|t| has_op(&t.plan, pred))
+                }
+                OpKind::Union(union) => union.branches.iter().any(|b| has_op(&b.plan, pred)),
+                OpKind::Coalesce(coalesce) => {
+                    coalesce.branches.iter().any(|b| has_op(&b.plan, pred))
+                }
+                _ => false,
+            }
+    })
+}
+
+/// Whether any dedup of the plan or its sub-plans is by a Bloom filter, see `dedup_approx`
+fn has_approx_dedup(plan: &[server_pb::OperatorDef]) -> bool {
+    use server_pb::operator_def::OpKind;
+    has_op(plan, &|kind| matches!(kind, OpKind::Dedup(dedup) if dedup.expected_items > 0))
+}
+
+fn compare_name(cmp: pb::Compare) -> &'static str {
+    match cmp {
+        pb::Compare::Eq => "eq",
+        pb::Compare::Ne => "neq",
+        pb::Compare::Lt => "lt",
+        pb::Compare::Le => "lte",
+        pb::Compare::Gt => "gt",
+        pb::Compare::Ge => "gte",
+        pb::Compare::Within => "within",
+        pb::Compare::Without => "without",
+        pb::Compare::Custom => "custom",
+    }
+}
+
+fn direction_name(direction: pb::Direction) -> &'static str {
+    match direction {
+        pb::Direction::Out => "out",
+        pb::Direction::In => "in",
+        pb::Direction::Both => "both",
+    }
+}
+
+fn value_name(value: &Object) -> String {
+    match value {
+        Object::Primitive(Primitives::Float(f)) => f.to_string(),
+        Object::Primitive(p) => p.as_i64().map(|i| i.to_string()).unwrap_or_default(),
+        Object::Custom(v) => v.tag().to_owned(),
+        _ => value.as_str().map(|s| s.into_owned()).unwrap_or_else(|_| format!("{:?}", value)),
+    }
+}
+
+fn join_values<T: ToString>(values: &[T]) -> String {
+    values.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", ")
+}
+
+#[derive(Clone)]
+struct EmbeddedOutput {
+    tx: Sender<DynResult<Object>>,
+    profile: Arc<Mutex<Option<server_pb::JobProfile>>>,
+    complete: Option<Sender<()>>,
+}
+
+impl Output for EmbeddedOutput {
+    fn send(&self, res: JobResponse) {
+        // the results are sent by `ResultSender` instead of the encoded ones
+        match res.result {
+            Some(JobResult::Err(err)) => {
+                let _ = self.tx.send(Err(str_to_dyn_error(&err.err_msg)));
+            }
+            Some(JobResult::Profile(profile)) => {
+                if let Ok(mut slot) = self.profile.lock() {
+                    *slot = Some(profile);
+                }
+            }
+            Some(JobResult::Complete(_)) => {
+                if let Some(complete) = self.complete.as_ref() {
+                    let _ = complete.try_send(());
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn close(&self) {}
+}
+
+/// Keep the plan of an explained traversal, or the error failing it
+#[derive(Clone, Default)]
+struct PlanOutput {
+    plan: Arc<Mutex<Option<DynResult<server_pb::JobPlan>>>>,
+}
+
+impl Output for PlanOutput {
+    fn send(&self, res: JobResponse) {
+        let result = match res.result {
+            Some(JobResult::Plan(plan)) => Ok(plan),
+            Some(JobResult::Err(err)) => Err(str_to_dyn_error(&err.err_msg)),
+            _ => return,
+        };
+        if let Ok(mut plan) = self.plan.lock() {
+            plan.get_or_insert(result);
+        }
+    }
+
+    fn close(&self) {}
+}
+
+/// Send the results into the `ResultStream` instead of encoding them
+struct ResultSender {
+    tx: Sender<DynResult<Object>>,
+}
+
+impl EncodeFunction<Traverser> for ResultSender {
+    fn encode(&self, data: Vec<Traverser>) -> Vec<u8> {
+        for traverser in data {
+            let result = if let Some(element) = traverser.get_element() {
+                Ok(element.id().into())
+            } else if let Some(object) = traverser.get_object() {
+                Ok(object.clone())
+            } else {
+                Err(str_to_dyn_error("only elements and values are supported in results"))
+            };
+            match result {
+                Ok(object) => {
+                    for _ in 0..traverser.get_bulk() {
+                        let _ = self.tx.send(Ok(object.clone()));
+                    }
+                }
+                Err(err) => {
+                    let _ = self.tx.send(Err(err));
+                }
+            }
+        }
+        vec![]
+    }
+}
+
+/// The `GremlinJobCompiler` with the results sent into a `ResultStream`
+struct EmbeddedCompiler {
+    inner: GremlinJobCompiler,
+    tx: Sender<DynResult<Object>>,
+}
+
+impl JobCompiler<Traverser> for EmbeddedCompiler {
+    fn shuffle(&self, res: &[u8]) -> CompileResult<Box<dyn RouteFunction<Traverser>>> {
+        self.inner.shuffle(res)
+    }
+
+    fn broadcast(&self, res: &[u8]) -> CompileResult<Box<dyn MultiRouteFunction<Traverser>>> {
+        self.inner.broadcast(res)
+    }
+
+    fn source(&self, src: &[u8]) -> CompileResult<Box<dyn Iterator<Item = Traverser> + Send>> {
+        self.inner.source(src)
+    }
+
+    fn map(&self, res: &[u8]) -> CompileResult<Box<dyn MapFunction<Traverser, Traverser>>> {
+        self.inner.map(res)
+    }
+
+    fn flat_map(
+        &self, res: &[u8],
+    ) -> CompileResult<Box<dyn FlatMapFunction<Traverser, Traverser, Target = DynIter<Traverser>>>>
+    {
+        self.inner.flat_map(res)
+    }
+
+    fn filter(&self, res: &[u8]) -> CompileResult<Box<dyn FilterFunction<Traverser>>> {
+        self.inner.filter(res)
+    }
+
+    fn left_join(&self, res: &[u8]) -> CompileResult<Box<dyn LeftJoinFunction<Traverser>>> {
+        self.inner.left_join(res)
+    }
+
+    fn compare(&self, res: &[u8]) -> CompileResult<Box<dyn CompareFunction<Traverser>>> {
+        self.inner.compare(res)
+    }
+
+    fn group(
+        &self, map_factory: &[u8], unfold: &[u8], sink: &[u8],
+    ) -> CompileResult<Box<dyn GroupFunction<Traverser>>> {
+        self.inner.group(map_factory, unfold, sink)
+    }
+
+    fn fold(
+        &self, accum: &[u8], unfold: &[u8], sink: &[u8],
+    ) -> CompileResult<Box<dyn FoldFunction<Traverser>>> {
+        self.inner.fold(accum, unfold, sink)
+    }
+
+    fn collection_factory(
+        &self, res: &[u8],
+    ) -> CompileResult<Box<dyn CollectionFactory<Traverser, Target = Box<dyn Collection<Traverser>>>>>
+    {
+        self.inner.collection_factory(res)
+    }
+
+    fn set_factory(
+        &self, res: &[u8],
+    ) -> CompileResult<Box<dyn CollectionFactory<Traverser, Target = Box<dyn Set<Traverser>>>>>
+    {
+        self.inner.set_factory(res)
+    }
+
+    fn sink(&self, _res: &[u8]) -> CompileResult<Box<dyn EncodeFunction<Traverser>>> {
+        Ok(Box::new(ResultSender { tx: self.tx.clone() }))
+    }
+
+    fn validate(&self, req: &JobRequest) -> Result<(), PlanError> {
+        self.inner.validate(req)
+    }
+
+    fn simplify(&self, req: &mut JobRequest) -> Result<bool, PlanError> {
+        self.inner.simplify(req)
+    }
+
+    fn estimate_workers(&self, graph: &str, src: &[u8]) -> Option<u32> {
+        self.inner.estimate_workers(graph, src)
+    }
+
+    fn source_size(&self, src: &[u8]) -> Option<u64> {
+        self.inner.source_size(src)
+    }
+
+    fn explain_source(&self, graph: &str, src: &[u8]) -> Option<SourceAccess> {
+        self.inner.explain_source(graph, src)
+    }
+
+    fn estimate_output(&self, graph: &str, res: &[u8], input: u64) -> Option<u64> {
+        self.inner.estimate_output(graph, res, input)
+    }
+
+    fn features(&self) -> Vec<String> {
+        self.inner.features()
+    }
+
+    fn shortcut(&self, req: &JobRequest) -> Option<Vec<Traverser>> {
+        self.inner.shortcut(req)
+    }
+
+    fn template_hash(&self, req: &JobRequest) -> Option<u64> {
+        self.inner.template_hash(req)
+    }
+
+    fn prepare(&self, graph: &str, conf: &mut JobConf) -> Result<(), QueryError> {
+        self.inner.prepare(graph, conf)
+    }
+
+    fn fused_source(
+        &self, src: &[u8], plan: &[server_pb::OperatorDef],
+    ) -> CompileResult<Option<(Box<dyn Iterator<Item = Traverser> + Send>, usize)>> {
+        self.inner.fused_source(src, plan)
+    }
+
+    fn fused_flat_map(
+        &self, plan: &[server_pb::OperatorDef],
+    ) -> CompileResult<
+        Option<(
+            Box<dyn FlatMapFunction<Traverser, Traverser, Target = DynIter<Traverser>>>,
+            usize,
+        )>,
+    > {
+        self.inner.fused_flat_map(plan)
+    }
+
+    fn adaptive(
+        &self, plan: &[server_pb::OperatorDef],
+    ) -> CompileResult<Option<AdaptivePoint<Traverser>>> {
+        self.inner.adaptive(plan)
+    }
+
+    fn may_split(&self, res: &[u8]) -> bool {
+        self.inner.may_split(res)
+    }
+}
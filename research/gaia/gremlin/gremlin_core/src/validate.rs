@@ -0,0 +1,1845 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Validate the job requests of gremlin queries before the jobs are submitted, so that a malformed
+//! plan is rejected with the operator and the reason, instead of failing deep inside building the
+//! operators. It checks that:
+//! * the arguments required by the operators and the steps are present and decodable, as is the
+//!   detach policy of the sink;
+//! * the enums are within their ranges;
+//! * the tags are defined by the preceding steps before referenced;
+//! * the property keys are not empty;
+//! * the repeat() are not nested too deep;
+//! * the steps are applicable to the traversers if statically known, e.g. sum() over vertices,
+//!   or outV() over the vertices of out(), which names both steps;
+//! * the constants are comparable to the properties by the schema of the graph if any, e.g. a
+//!   string is never compared to an int property, see `TypeCheck`;
+//! * the constructs not supported yet are not in the plan, see `codec::UNSUPPORTED_FEATURES`;
+//! * the graph named by the request is registered, see `register_named_graph`.
+
+use crate::generated::common as common_pb;
+use crate::generated::gremlin as pb;
+use crate::process::traversal::step::expr;
+use crate::structure::codec::{is_comparable, parse_node, ParseError};
+use crate::structure::{label_id_from_pb, ElementKind, GraphElement, ValueFilter};
+use crate::Detach;
+use dyn_type::custom::{get_custom_predicate, is_custom_type_registered};
+use graph_store::schema::GraphSchemaInfo;
+use pegasus_server::factory::PlanError;
+use pegasus_server::generated::protocol as server_pb;
+use prost::Message;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// The max depth of nested repeat() in a query
+pub const MAX_REPEAT_DEPTH: usize = 8;
+
+/// What the heads of the traversers are known to be after a step
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum HeadKind {
+    Element(ElementKind),
+    Value,
+    Unknown,
+}
+
+impl HeadKind {
+    fn is_element(&self) -> bool {
+        matches!(self, HeadKind::Element(_))
+    }
+
+    fn of_entity(entity: i32) -> Self {
+        if entity == pb::EntityType::Edge as i32 {
+            HeadKind::Element(ElementKind::Edge)
+        } else {
+            HeadKind::Element(ElementKind::Vertex)
+        }
+    }
+}
+
+/// How the constants compared to the properties are checked by the schema of the graph
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum TypeCheck {
+    /// Reject the queries comparing a constant to a property of an incomparable type, e.g.
+    /// `has("age", "29")` where age is an int, which would never match
+    #[default]
+    Strict,
+    /// Only warn of such comparisons and leave them to never match while running
+    Lenient,
+}
+
+pub fn validate_request(req: &server_pb::JobRequest) -> Result<(), PlanError> {
+    validate_request_with(req, TypeCheck::default())
+}
+
+/// Validate the request, checking the types of the constants by the schema of the graph it names
+/// if any, which must have been registered
+pub fn validate_request_with(
+    req: &server_pb::JobRequest, type_check: TypeCheck,
+) -> Result<(), PlanError> {
+    let graph = crate::get_named_graph(&req.graph);
+    if graph.is_none() && !req.graph.is_empty() {
+        let msg = format!("graph {:?} is not registered", req.graph);
+        return Err(PlanError::new(vec![], msg));
+    }
+    let schema = graph.and_then(|graph| graph.get_schema());
+    validate_with_schema(req, schema, type_check)
+}
+
+fn validate_with_schema(
+    req: &server_pb::JobRequest, schema: Option<Arc<GraphSchemaInfo>>, type_check: TypeCheck,
+) -> Result<(), PlanError> {
+    let mut validator = Validator::new();
+    validator.schema = schema;
+    validator.type_check = type_check;
+    let source = req.source.as_ref().ok_or_else(|| validator.error("source of job not found"))?;
+    validator.check_source(&source.resource)?;
+    if let Some(plan) = req.plan.as_ref() {
+        validator.check_plan(&plan.plan)?;
+    }
+    if let Some(sink) = req.sink.as_ref() {
+        validator.check_sink(sink)?;
+    }
+    Ok(())
+}
+
+#[derive(Clone)]
+struct Validator {
+    op_index: Vec<usize>,
+    // the tags defined by the steps checked so far
+    tags: HashSet<i32>,
+    head: HeadKind,
+    // the step which the heads are from, e.g. "out()", to be named if they don't fit a later step
+    head_step: String,
+    repeat_depth: usize,
+    // the schema to check the types of the constants compared to the properties
+    schema: Option<Arc<GraphSchemaInfo>>,
+    type_check: TypeCheck,
+}
+
+impl Validator {
+    fn new() -> Self {
+        Validator {
+            op_index: vec![],
+            tags: HashSet::new(),
+            head: HeadKind::Unknown,
+            head_step: String::new(),
+            repeat_depth: 0,
+            schema: None,
+            type_check: TypeCheck::default(),
+        }
+    }
+
+    fn error<S: Into<String>>(&self, msg: S) -> PlanError {
+        PlanError::new(self.op_index.clone(), msg)
+    }
+
+    fn check_enum<T>(
+        &self, value: i32, from_i32: fn(i32) -> Option<T>, name: &str,
+    ) -> Result<T, PlanError> {
+        from_i32(value).ok_or_else(|| self.error(format!("invalid {} {}", name, value)))
+    }
+
+    // the constructs decoded but not supported yet are rejected by the decoder itself, which
+    // tells the construct, see `codec::UNSUPPORTED_FEATURES`
+    fn check_supported<T>(&self, decoded: Result<T, ParseError>) -> Result<(), PlanError> {
+        match decoded {
+            Err(err @ ParseError::Unsupported(_)) => Err(self.error(err.to_string())),
+            _ => Ok(()),
+        }
+    }
+
+    fn decode_step(&self, resource: &[u8], name: &str) -> Result<pb::GremlinStep, PlanError> {
+        pb::GremlinStep::decode(resource)
+            .map_err(|e| self.error(format!("fail to decode the step of {}: {}", name, e)))
+    }
+
+    fn check_source(&mut self, resource: &[u8]) -> Result<(), PlanError> {
+        let step = self.decode_step(resource, "source")?;
+        match step.step.as_ref() {
+            Some(pb::gremlin_step::Step::GraphStep(graph_step)) => {
+                self.check_enum(graph_step.return_type, pb::EntityType::from_i32, "entity type")?;
+                for requirement in graph_step.traverser_requirements.iter() {
+                    self.check_enum(
+                        *requirement,
+                        pb::TraverserRequirement::from_i32,
+                        "traverser requirement",
+                    )?;
+                }
+                self.check_step_labels(&graph_step.labels)?;
+                if let Some(predicates) = graph_step.predicates.as_ref() {
+                    self.check_filter_chain(predicates)?;
+                }
+                if !graph_step.high_ids.is_empty()
+                    && graph_step.high_ids.len() != graph_step.ids.len()
+                {
+                    Err(self.error(format!(
+                        "{} higher bits given for {} ids of the source",
+                        graph_step.high_ids.len(),
+                        graph_step.ids.len()
+                    )))?;
+                }
+            }
+            Some(pb::gremlin_step::Step::SessionRefStep(session_ref)) => {
+                if session_ref.name.is_empty() {
+                    Err(self.error("name of session result must not be empty"))?;
+                }
+                self.define_tags(&step);
+                // the saved result may be of either elements or values
+                self.head = HeadKind::Unknown;
+                return Ok(());
+            }
+            _ => Err(self.error("the source must be a graph step, e.g. g.V()"))?,
+        }
+        self.define_tags(&step);
+        if let Some(inner) = step.step.as_ref() {
+            self.set_head(inner);
+        }
+        Ok(())
+    }
+
+    fn check_plan(&mut self, plan: &[server_pb::OperatorDef]) -> Result<(), PlanError> {
+        for (i, op) in plan.iter().enumerate() {
+            self.op_index.push(i);
+            self.check_op(op)?;
+            self.op_index.pop();
+        }
+        Ok(())
+    }
+
+    // check a nested plan, e.g. the body of an iteration, with the index `i` appended if given,
+    // and get the validator after the nested plan
+    fn check_nested(
+        &self, plan: &server_pb::TaskPlan, i: Option<usize>,
+    ) -> Result<Validator, PlanError> {
+        let mut nested = self.clone();
+        if let Some(i) = i {
+            nested.op_index.push(i);
+        }
+        if plan.plan.is_empty() {
+            Err(nested.error("the nested plan is empty"))?;
+        }
+        nested.check_plan(&plan.plan)?;
+        if i.is_some() {
+            nested.op_index.pop();
+        }
+        Ok(nested)
+    }
+
+    fn check_op(&mut self, op: &server_pb::OperatorDef) -> Result<(), PlanError> {
+        use server_pb::operator_def::OpKind;
+        let op_kind = op.op_kind.as_ref().ok_or_else(|| self.error("operator kind not found"))?;
+        match op_kind {
+            OpKind::Shuffle(_) => match op.ch.as_ref().and_then(|ch| ch.ch_kind.as_ref()) {
+                Some(server_pb::channel_def::ChKind::ToAnother(_)) => {}
+                _ => Err(self.error("shuffle requires an exchange channel"))?,
+            },
+            OpKind::Map(map) => {
+                let step = self.decode_step(&map.resource, "map")?;
+                self.check_step(&step)?;
+                match step.step.as_ref() {
+                    Some(pb::gremlin_step::Step::IdentityStep(_))
+                    | Some(pb::gremlin_step::Step::TransformTraverserStep(_))
+                    | Some(pb::gremlin_step::Step::RepeatLoopsStep(_))
+                    | Some(pb::gremlin_step::Step::SideEffectStep(_)) => {}
+                    Some(inner) => self.set_head(inner),
+                    None => self.head = HeadKind::Unknown,
+                }
+            }
+            OpKind::FlatMap(flat_map) => {
+                let step = self.decode_step(&flat_map.resource, "flat_map")?;
+                self.check_step(&step)?;
+                match step.step.as_ref() {
+                    Some(inner) => self.set_head(inner),
+                    None => self.head = HeadKind::Unknown,
+                }
+            }
+            OpKind::Filter(filter) => {
+                let step = self.decode_step(&filter.resource, "filter")?;
+                self.check_step(&step)?;
+            }
+            OpKind::Limit(limit) => {
+                self.check_enum(limit.range, server_pb::Range::from_i32, "range")?;
+            }
+            OpKind::Order(order) => {
+                self.check_enum(order.range, server_pb::Range::from_i32, "range")?;
+                let step = self.decode_step(&order.compare, "order")?;
+                match step.step.as_ref() {
+                    Some(pb::gremlin_step::Step::OrderByStep(_)) => self.check_step(&step)?,
+                    _ => Err(self.error("order requires an order by step"))?,
+                }
+            }
+            OpKind::Fold(fold) => {
+                let unfold =
+                    fold.unfold.as_ref().ok_or_else(|| self.error("unfold of fold not found"))?;
+                self.check_fold(fold)?;
+                let unfold = self.decode_step(&unfold.resource, "unfold")?;
+                self.check_step(&unfold)?;
+                self.head = match (fold.accum, unfold.step.as_ref()) {
+                    (_, Some(pb::gremlin_step::Step::SideEffectStep(_))) => self.head,
+                    (accum, None)
+                        if accum != server_pb::AccumKind::ToList as i32
+                            && accum != server_pb::AccumKind::Custom as i32 =>
+                    {
+                        HeadKind::Value
+                    }
+                    _ => HeadKind::Unknown,
+                };
+            }
+            OpKind::Group(group) => {
+                self.check_enum(group.range, server_pb::Range::from_i32, "range")?;
+                if group.unfold.is_none() {
+                    Err(self.error("unfold of group not found"))?;
+                }
+                self.check_group_map(&group.map)?;
+                self.head = HeadKind::Unknown;
+            }
+            OpKind::Union(union) => {
+                if union.branches.len() < 2 {
+                    Err(self.error("union requires at least two branches"))?;
+                }
+                self.check_branches(&union.branches)?;
+            }
+            OpKind::Coalesce(coalesce) => {
+                if coalesce.branches.is_empty() {
+                    Err(self.error("coalesce requires at least one branch"))?;
+                }
+                self.check_branches(&coalesce.branches)?;
+            }
+            OpKind::Iterate(iteration) => {
+                self.check_enum(
+                    iteration.emit,
+                    server_pb::iteration::EmitKind::from_i32,
+                    "emit kind",
+                )?;
+                if self.repeat_depth >= MAX_REPEAT_DEPTH {
+                    Err(self.error(format!(
+                        "repeat() is nested deeper than the limit {}",
+                        MAX_REPEAT_DEPTH
+                    )))?;
+                }
+                let body = iteration
+                    .body
+                    .as_ref()
+                    .ok_or_else(|| self.error("body of iteration not found"))?;
+                self.repeat_depth += 1;
+                let mut nested = self.check_nested(body, None)?;
+                if iteration.max_iters != 1 && nested.head.is_element() && nested.head != self.head
+                {
+                    // the later rounds start with what the former round ends with, e.g. the edges
+                    // of repeat(outE()), which must fit the body as well
+                    let mut next_round = self.clone();
+                    next_round.head = nested.head;
+                    next_round.head_step = nested.head_step.clone();
+                    next_round.check_nested(body, None)?;
+                }
+                self.repeat_depth -= 1;
+                if let Some(until) = iteration.until.as_ref() {
+                    let step = nested.decode_step(&until.resource, "until")?;
+                    nested.check_step(&step)?;
+                }
+                // the traversers may leave the loop before or after any round
+                if nested.head != self.head {
+                    self.head = HeadKind::Unknown;
+                }
+                self.tags = nested.tags;
+            }
+            OpKind::Subtask(subtask) => {
+                let semi_join = self.check_enum(
+                    subtask.semi_join,
+                    server_pb::subtask::SemiJoin::from_i32,
+                    "semi join",
+                )?;
+                let body =
+                    subtask.task.as_ref().ok_or_else(|| self.error("body of subtask not found"))?;
+                let nested = self.check_nested(body, None)?;
+                if let Some(join) = subtask.join.as_ref() {
+                    pb::SubTaskJoiner::decode(join.resource.as_slice())
+                        .ok()
+                        .and_then(|joiner| joiner.inner)
+                        .ok_or_else(|| self.error("invalid joiner of subtask"))?;
+                }
+                self.tags.extend(nested.tags);
+                if subtask.first && subtask.join.is_none() {
+                    Err(self.error("the first result of subtask is joined without a joiner"))?;
+                }
+                if subtask.optional {
+                    if subtask.join.is_some() || semi_join != server_pb::subtask::SemiJoin::NoSemi {
+                        Err(self.error("optional subtask can't be joined or semi joined"))?;
+                    }
+                    // the traversers are either the parents or the results of the subtask
+                    if nested.head != self.head {
+                        self.head = HeadKind::Unknown;
+                    }
+                } else if semi_join == server_pb::subtask::SemiJoin::NoSemi && !subtask.first {
+                    // the parents are kept as they are only if joined with the first results
+                    self.head = HeadKind::Unknown;
+                }
+            }
+            OpKind::TimeLimit(time_limit) => {
+                if time_limit.ms == 0 {
+                    Err(self.error("the time limit should be positive"))?;
+                }
+                let body = time_limit
+                    .task
+                    .as_ref()
+                    .ok_or_else(|| self.error("body of time limit not found"))?;
+                // the traversers pass the time limit as they are, which bounds its body only
+                if !body.plan.is_empty() {
+                    *self = self.check_nested(body, None)?;
+                }
+            }
+            OpKind::Dedup(dedup) => {
+                self.check_enum(dedup.range, server_pb::Range::from_i32, "range")?;
+                let step = self.decode_step(&dedup.set, "dedup")?;
+                match step.step.as_ref() {
+                    Some(pb::gremlin_step::Step::DedupStep(_)) => self.check_step(&step)?,
+                    _ => Err(self.error("dedup requires a dedup step"))?,
+                }
+                if dedup.expected_items > 0 && !(dedup.fp_rate > 0.0 && dedup.fp_rate < 1.0) {
+                    let msg = format!(
+                        "the false positive rate {} of dedup should be in (0, 1)",
+                        dedup.fp_rate
+                    );
+                    Err(self.error(msg))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the branches each of which starts with the traversers, where the traversers after
+    /// the branches are of the head of all branches if the same
+    fn check_branches(&mut self, branches: &[server_pb::TaskPlan]) -> Result<(), PlanError> {
+        let mut head = None;
+        let mut tags = HashSet::new();
+        for (i, branch) in branches.iter().enumerate() {
+            let nested = self.check_nested(branch, Some(i))?;
+            head = match head {
+                Some((h, _)) if h != nested.head => Some((HeadKind::Unknown, String::new())),
+                Some(head) => Some(head),
+                None => Some((nested.head, nested.head_step)),
+            };
+            tags.extend(nested.tags);
+        }
+        let (head, head_step) = head.unwrap_or((HeadKind::Unknown, String::new()));
+        self.head = head;
+        self.head_step = head_step;
+        self.tags = tags;
+        Ok(())
+    }
+
+    fn check_sink(&mut self, sink: &server_pb::Sink) -> Result<(), PlanError> {
+        match sink.sinker.as_ref() {
+            Some(server_pb::sink::Sinker::Fold(fold)) => {
+                self.check_fold(fold)?;
+                if let Some(unfold) = fold.unfold.as_ref() {
+                    let step = self.decode_step(&unfold.resource, "unfold")?;
+                    self.check_step(&step)?;
+                }
+            }
+            Some(server_pb::sink::Sinker::Group(group)) => {
+                self.check_enum(group.range, server_pb::Range::from_i32, "range")?;
+                self.check_group_map(&group.map)?;
+            }
+            Some(server_pb::sink::Sinker::Resource(resource)) => {
+                let detach = Detach::from_resource(resource)
+                    .map_err(|e| self.error(format!("invalid detach policy of sink: {}", e)))?;
+                if let Detach::Custom(keys) = detach {
+                    if keys.iter().any(|key| key.is_empty()) {
+                        Err(self.error("property keys of detach policy must not be empty"))?;
+                    }
+                }
+            }
+            None => {}
+        }
+        Ok(())
+    }
+
+    fn check_fold(&mut self, fold: &server_pb::Fold) -> Result<(), PlanError> {
+        self.check_enum(fold.range, server_pb::Range::from_i32, "range")?;
+        let accum = self.check_enum(fold.accum, server_pb::AccumKind::from_i32, "accum kind")?;
+        match accum {
+            server_pb::AccumKind::Cnt | server_pb::AccumKind::ToList => Ok(()),
+            server_pb::AccumKind::Sum
+            | server_pb::AccumKind::Max
+            | server_pb::AccumKind::Min
+            | server_pb::AccumKind::Mean => {
+                let step = self.decode_step(&fold.resource, "fold")?;
+                match step.step.as_ref() {
+                    Some(pb::gremlin_step::Step::NumericAccumStep(s)) if s.local => {
+                        let msg = format!("{:?} requires a global numeric accum step", accum);
+                        Err(self.error(msg))?
+                    }
+                    Some(pb::gremlin_step::Step::NumericAccumStep(_)) => self.check_step(&step)?,
+                    _ => Err(self.error(format!("{:?} requires a numeric accum step", accum)))?,
+                }
+                if self.head.is_element() {
+                    Err(self.error(format!(
+                        "{:?} requires numeric values, but the traversers are graph elements",
+                        accum
+                    )))?;
+                }
+                Ok(())
+            }
+            server_pb::AccumKind::Custom => {
+                let step = self.decode_step(&fold.resource, "fold")?;
+                match step.step.as_ref() {
+                    Some(pb::gremlin_step::Step::FoldStep(_)) => self.check_step(&step),
+                    _ => Err(self.error("Custom requires a fold step")),
+                }
+            }
+            server_pb::AccumKind::ApproxDistinct => {
+                if !(4..=18).contains(&fold.precision) {
+                    Err(self.error(format!(
+                        "precision {} of ApproxDistinct is out of [4, 18]",
+                        fold.precision
+                    )))?;
+                }
+                Ok(())
+            }
+            server_pb::AccumKind::Quantiles => {
+                if fold.quantiles.is_empty() {
+                    Err(self.error("Quantiles requires at least one quantile"))?;
+                }
+                if let Some(q) = fold.quantiles.iter().find(|q| !(0.0..=1.0).contains(*q)) {
+                    Err(self.error(format!("quantile {} is out of [0, 1]", q)))?;
+                }
+                if self.head.is_element() {
+                    Err(self.error(
+                        "Quantiles requires numeric values, but the traversers are graph elements",
+                    ))?;
+                }
+                Ok(())
+            }
+            _ => Err(self.error(format!("accum kind {:?} is not supported", accum))),
+        }
+    }
+
+    fn check_group_map(&mut self, map: &[u8]) -> Result<(), PlanError> {
+        let step = self.decode_step(map, "group")?;
+        match step.step.as_ref() {
+            Some(pb::gremlin_step::Step::GroupByStep(_)) => self.check_step(&step),
+            _ => Err(self.error("group requires a group by step")),
+        }
+    }
+
+    // check the step, and define its tags after that
+    fn check_step(&mut self, step: &pb::GremlinStep) -> Result<(), PlanError> {
+        use pb::gremlin_step::Step;
+        let inner = match step.step.as_ref() {
+            Some(inner) => inner,
+            // e.g. the unfold of count()
+            None => {
+                self.define_tags(step);
+                return Ok(());
+            }
+        };
+        match inner {
+            Step::GraphStep(_) => Err(self.error("graph step is only supported as the source"))?,
+            Step::SessionRefStep(_) => {
+                Err(self.error("session reference is only supported as the source"))?
+            }
+            Step::VertexStep(vertex_step) => {
+                self.require_element(inner, ElementKind::Vertex)?;
+                self.check_enum(vertex_step.direction, pb::Direction::from_i32, "direction")?;
+                self.check_enum(vertex_step.return_type, pb::EntityType::from_i32, "entity type")?;
+                self.check_step_labels(&vertex_step.edge_labels)?;
+                if let Some(predicates) = vertex_step.predicates.as_ref() {
+                    self.check_filter_chain(predicates)?;
+                }
+            }
+            Step::DegreeStep(degree_step) => {
+                self.require_element(inner, ElementKind::Vertex)?;
+                self.check_enum(degree_step.direction, pb::Direction::from_i32, "direction")?;
+                self.check_step_labels(&degree_step.edge_labels)?;
+                if let Some(predicates) = degree_step.predicates.as_ref() {
+                    self.check_filter_chain(predicates)?;
+                }
+            }
+            Step::HasStep(has_step) => {
+                let predicates = has_step
+                    .predicates
+                    .as_ref()
+                    .ok_or_else(|| self.error("predicates of has() not found"))?;
+                self.check_filter_chain(predicates)?;
+            }
+            Step::WhereStep(where_step) => {
+                self.check_tag(where_step.start_tag.as_ref())?;
+                for tag in where_step.tags.iter() {
+                    self.check_tag(Some(tag))?;
+                }
+                if let Some(key) = where_step.start_token.as_ref() {
+                    self.check_key(key)?;
+                }
+                if let Some(predicates) = where_step.predicates.as_ref() {
+                    self.check_filter_chain(predicates)?;
+                }
+            }
+            Step::PathFilterStep(path_filter) => {
+                self.check_enum(
+                    path_filter.hint,
+                    pb::path_filter_step::PathHint::from_i32,
+                    "path hint",
+                )?;
+            }
+            Step::RangeGlobalStep(range) => {
+                if range.low_range < 0 || range.low_range > range.high_range {
+                    Err(self.error(format!(
+                        "invalid range [{}, {})",
+                        range.low_range, range.high_range
+                    )))?;
+                }
+            }
+            Step::SelectStep(select) => {
+                self.check_enum(select.pop, pb::select_step::Pop::from_i32, "pop")?;
+                if select.select_keys.is_empty() {
+                    Err(self.error("keys of select() not found"))?;
+                }
+                for key in select.select_keys.iter() {
+                    self.check_tag_key(key)?;
+                }
+            }
+            Step::IdentityStep(identity) => {
+                self.check_properties(&identity.properties)?;
+            }
+            Step::OrderByStep(order_by) => {
+                for pair in order_by.pairs.iter() {
+                    self.check_order_pair(pair)?;
+                }
+            }
+            Step::GroupByStep(group_by) => {
+                self.check_enum(
+                    group_by.accum,
+                    pb::group_by_step::AccumKind::from_i32,
+                    "accum kind",
+                )?;
+                if let Some(key) = group_by.key.as_ref() {
+                    self.check_tag_key(key)?;
+                }
+                for pair in group_by.opt_order.iter() {
+                    self.check_order_pair(pair)?;
+                }
+            }
+            Step::SelectOneWithoutBy(select_one) => {
+                if select_one.tag.as_ref().and_then(|t| t.item.as_ref()).is_none() {
+                    Err(self.error("tag of select() not found"))?;
+                }
+                self.check_tag(select_one.tag.as_ref())?;
+            }
+            Step::PropertiesStep(properties) => {
+                self.check_properties(&properties.properties)?;
+            }
+            Step::EdgeVertexStep(edge_vertex) => {
+                self.require_element(inner, ElementKind::Edge)?;
+                self.check_enum(
+                    edge_vertex.endpoint_opt,
+                    pb::edge_vertex_step::EndpointOpt::from_i32,
+                    "endpoint",
+                )?;
+            }
+            Step::DedupStep(dedup) => {
+                self.check_enum(
+                    dedup.dedup_type,
+                    pb::dedup_step::DedupSetType::from_i32,
+                    "dedup type",
+                )?;
+                if let Some(key) = dedup.key.as_ref() {
+                    match key.by_key.as_ref().and_then(|by_key| by_key.item.as_ref()) {
+                        Some(pb::by_key::Item::Projection(_)) => self.check_tag_key(key)?,
+                        _ => Err(self.error("dedup() only supports by() of a projection"))?,
+                    }
+                }
+            }
+            Step::TransformTraverserStep(transform) => {
+                for requirement in transform.traverser_requirements.iter() {
+                    self.check_enum(
+                        *requirement,
+                        pb::TraverserRequirement::from_i32,
+                        "traverser requirement",
+                    )?;
+                }
+            }
+            Step::IsStep(is_step) => {
+                if let HeadKind::Element(head) = self.head {
+                    Err(self.error(format!(
+                        "is() requires values, but the traversers are {} from {}",
+                        head.plural(),
+                        self.head_step
+                    )))?;
+                }
+                match is_step.predicates.as_ref().filter(|p| !p.node.is_empty()) {
+                    Some(predicates) => self.check_value_chain(predicates)?,
+                    None => self.check_filter_value(is_step.single.as_ref())?,
+                }
+            }
+            Step::LoopsStep(loops) => self.check_filter_value(loops.single.as_ref())?,
+            Step::RepeatLoopsStep(repeat_loops) => {
+                self.check_enum(repeat_loops.kind, pb::repeat_loops_step::Kind::from_i32, "kind")?;
+            }
+            Step::NumericAccumStep(accum) => {
+                self.check_enum(accum.kind, pb::numeric_accum_step::Kind::from_i32, "kind")?;
+                if accum.local {
+                    self.check_local_accum(inner)?;
+                }
+            }
+            Step::SideEffectStep(side_effect) => {
+                self.check_enum(side_effect.kind, pb::side_effect_step::Kind::from_i32, "kind")?;
+                self.check_side_key(&side_effect.key)?;
+            }
+            Step::CapStep(cap) => self.check_side_key(&cap.key)?,
+            Step::WithinSideStep(within) => self.check_side_key(&within.key)?,
+            Step::EdgeBothVStep(_) => self.require_element(inner, ElementKind::Edge)?,
+            Step::ProjectStep(project) => self.check_project(project)?,
+            Step::MathStep(math) => self.check_math(math)?,
+            Step::PathStep(_)
+            | Step::PathLocalCountStep(_)
+            | Step::UnfoldStep(_)
+            | Step::FoldStep(_) => {}
+        }
+        self.define_tags(step);
+        Ok(())
+    }
+
+    // the traversers after the step, which must have passed `check_step`
+    fn set_head(&mut self, step: &pb::gremlin_step::Step) {
+        use pb::gremlin_step::Step;
+        self.head = match step {
+            Step::GraphStep(graph_step) => HeadKind::of_entity(graph_step.return_type),
+            Step::VertexStep(vertex_step) => HeadKind::of_entity(vertex_step.return_type),
+            Step::EdgeVertexStep(_) | Step::EdgeBothVStep(_) => {
+                HeadKind::Element(ElementKind::Vertex)
+            }
+            Step::DegreeStep(_)
+            | Step::PropertiesStep(_)
+            | Step::MathStep(_)
+            | Step::NumericAccumStep(_) => HeadKind::Value,
+            _ => HeadKind::Unknown,
+        };
+        self.head_step = step_name(step);
+    }
+
+    // reject the step if the traversers are known to be of the other kind of elements
+    fn require_element(
+        &self, step: &pb::gremlin_step::Step, kind: ElementKind,
+    ) -> Result<(), PlanError> {
+        match self.head {
+            HeadKind::Element(head) if head != kind => Err(self.error(format!(
+                "{} requires {}, but the traversers are {} from {}",
+                step_name(step),
+                kind.plural(),
+                head.plural(),
+                self.head_step
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    // the local accumulation over the heads known not to be collections filters out all of them,
+    // e.g. out().count(local), which is rejected or only warned of by the type check
+    fn check_local_accum(&self, step: &pb::gremlin_step::Step) -> Result<(), PlanError> {
+        let head = match self.head {
+            HeadKind::Element(head) => head.plural(),
+            HeadKind::Value => "values",
+            HeadKind::Unknown => return Ok(()),
+        };
+        let msg = format!(
+            "{} requires collections, e.g. of fold(), but the traversers are {} from {}",
+            step_name(step),
+            head,
+            self.head_step
+        );
+        match self.type_check {
+            TypeCheck::Strict => Err(self.error(msg)),
+            TypeCheck::Lenient => {
+                warn!("{}, which are filtered out", msg);
+                Ok(())
+            }
+        }
+    }
+
+    fn define_tags(&mut self, step: &pb::GremlinStep) {
+        for tag in step.tags.iter() {
+            if let Some(pb::step_tag::Item::Tag(tag)) = tag.item {
+                self.tags.insert(tag);
+            }
+        }
+    }
+
+    fn check_tag(&self, tag: Option<&pb::StepTag>) -> Result<(), PlanError> {
+        match tag.and_then(|t| t.item.as_ref()) {
+            Some(pb::step_tag::Item::Tag(tag)) if !self.tags.contains(tag) => {
+                Err(self.error(format!("tag {} is referenced before defined", tag)))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn check_tag_key(&self, tag_key: &pb::TagKey) -> Result<(), PlanError> {
+        self.check_tag(tag_key.tag.as_ref())?;
+        match tag_key.by_key.as_ref().and_then(|by_key| by_key.item.as_ref()) {
+            Some(pb::by_key::Item::Key(key)) => self.check_key(key),
+            Some(pb::by_key::Item::Name(names)) => self.check_properties(&names.item),
+            Some(pb::by_key::Item::MapKeys(map_keys)) => {
+                map_keys.key.as_ref().map(|k| self.check_key(k)).unwrap_or(Ok(()))
+            }
+            Some(pb::by_key::Item::MapValues(map_values)) => {
+                map_values.key.as_ref().map(|k| self.check_key(k)).unwrap_or(Ok(()))
+            }
+            Some(pb::by_key::Item::Projection(projection)) => {
+                if tag_key.tag.as_ref().and_then(|t| t.item.as_ref()).is_none()
+                    && self.head == HeadKind::Value
+                {
+                    Err(self.error(format!(
+                        "by() of a projection requires vertices or edges, but the traversers are \
+                         values from {}",
+                        self.head_step
+                    )))?;
+                }
+                self.check_projection(projection)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn check_projection(&self, projection: &pb::Projection) -> Result<(), PlanError> {
+        if projection.expression.is_empty() {
+            if projection.keys.is_empty() {
+                Err(self.error("keys of projection not found"))?;
+            }
+            for key in projection.keys.iter() {
+                self.check_key(key)?;
+            }
+            return Ok(());
+        }
+        if !projection.keys.is_empty() {
+            Err(self.error("projection must not have both keys and an expression"))?;
+        }
+        let expr = expr::parse(&projection.expression).map_err(|e| self.error(e.to_string()))?;
+        if expr.variables().iter().any(|v| **v == expr::Variable::Current) {
+            Err(self.error("variables of projection must be property names, but `_` is given"))?;
+        }
+        Ok(())
+    }
+
+    fn check_order_pair(&self, pair: &pb::OrderByComparePair) -> Result<(), PlanError> {
+        self.check_enum(pair.order, pb::order_by_compare_pair::Order::from_i32, "order")?;
+        if let Some(key) = pair.key.as_ref() {
+            self.check_tag_key(key)?;
+        }
+        Ok(())
+    }
+
+    fn check_key(&self, key: &common_pb::Key) -> Result<(), PlanError> {
+        match key.item.as_ref() {
+            Some(common_pb::key::Item::Name(name)) if name.is_empty() => {
+                Err(self.error("property key must not be empty"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn check_properties(&self, properties: &[String]) -> Result<(), PlanError> {
+        if properties.iter().any(|p| p.is_empty()) {
+            Err(self.error("property key must not be empty"))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn check_project(&self, project: &pb::ProjectStep) -> Result<(), PlanError> {
+        if self.head == HeadKind::Value {
+            Err(self.error(format!(
+                "project() requires vertices or edges, but the traversers are values from {}",
+                self.head_step
+            )))?;
+        }
+        if project.columns.is_empty() {
+            Err(self.error("columns of project() not found"))?;
+        }
+        let mut names = HashSet::new();
+        for column in project.columns.iter() {
+            if column.name.is_empty() {
+                Err(self.error("column name of project() must not be empty"))?;
+            }
+            if !names.insert(column.name.as_str()) {
+                Err(self.error(format!("column {} of project() is duplicated", column.name)))?;
+            }
+            if let Some(key) = column.key.as_ref() {
+                self.check_key(key)?;
+            }
+            if let Some(projection) = column.projection.as_ref() {
+                if column.key.is_some() {
+                    let msg =
+                        format!("column {} of project() has both key and projection", column.name);
+                    Err(self.error(msg))?;
+                }
+                self.check_projection(projection)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn check_math(&self, math: &pb::MathStep) -> Result<(), PlanError> {
+        let expr = expr::parse(&math.expression).map_err(|e| self.error(e.to_string()))?;
+        let mut labels = HashSet::new();
+        for variable in math.variables.iter() {
+            if variable.tag.as_ref().and_then(|t| t.item.as_ref()).is_none() {
+                let msg = format!("tag of variable `{}` of math() not found", variable.name);
+                Err(self.error(msg))?;
+            }
+            self.check_tag(variable.tag.as_ref())?;
+            labels.insert(variable.name.as_str());
+        }
+        for variable in expr.variables() {
+            match variable {
+                expr::Variable::Current => {
+                    if let HeadKind::Element(head) = self.head {
+                        Err(self.error(format!(
+                            "math() requires values of `_`, but the traversers are {} from {}",
+                            head.plural(),
+                            self.head_step
+                        )))?;
+                    }
+                }
+                expr::Variable::Label(name) if !labels.contains(name.as_str()) => {
+                    let msg = format!("variable `{}` of math() is not bound to a label", name);
+                    Err(self.error(msg))?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn check_side_key(&self, key: &str) -> Result<(), PlanError> {
+        if key.is_empty() {
+            Err(self.error("key of side collection must not be empty"))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn check_filter_chain(&self, chain: &pb::FilterChain) -> Result<(), PlanError> {
+        for node in chain.node.iter() {
+            self.check_enum(node.next, pb::Connect::from_i32, "connect")?;
+            match node.inner.as_ref() {
+                Some(pb::filter_node::Inner::Single(exp)) => {
+                    let left = exp
+                        .left
+                        .as_ref()
+                        .ok_or_else(|| self.error("left of predicate not found"))?;
+                    self.check_key(left)?;
+                    self.check_enum(exp.cmp, pb::Compare::from_i32, "compare")?;
+                    let right = exp
+                        .right
+                        .as_ref()
+                        .ok_or_else(|| self.error("right of predicate not found"))?;
+                    match left.item.as_ref() {
+                        Some(common_pb::key::Item::Name(name)) => {
+                            self.check_value_type(name, right)?
+                        }
+                        Some(common_pb::key::Item::Label(_)) => self.check_label_value(right)?,
+                        _ => (),
+                    }
+                    self.check_custom(exp.cmp, right, &exp.predicate)?;
+                    self.check_supported(parse_node::<GraphElement>(node))?;
+                }
+                Some(pb::filter_node::Inner::Chain(bytes)) => {
+                    let chain = pb::FilterChain::decode(bytes.as_slice()).map_err(|e| {
+                        self.error(format!("fail to decode the nested predicates: {}", e))
+                    })?;
+                    self.check_filter_chain(&chain)?;
+                }
+                None => Err(self.error("predicate not found"))?,
+            }
+        }
+        Ok(())
+    }
+
+    // the predicates on the values themselves, whose keys must be unset
+    fn check_value_chain(&self, chain: &pb::FilterChain) -> Result<(), PlanError> {
+        for node in chain.node.iter() {
+            self.check_enum(node.next, pb::Connect::from_i32, "connect")?;
+            match node.inner.as_ref() {
+                Some(pb::filter_node::Inner::Single(exp)) => {
+                    if exp.left.as_ref().map(|key| key.item.is_some()).unwrap_or(false) {
+                        Err(self.error("key of predicate on values must be unset"))?;
+                    }
+                    self.check_enum(exp.cmp, pb::Compare::from_i32, "compare")?;
+                    let right = exp
+                        .right
+                        .as_ref()
+                        .ok_or_else(|| self.error("right of predicate not found"))?;
+                    if exp.cmp == pb::Compare::Custom as i32 {
+                        Err(self.error("custom predicates are only supported on the properties"))?;
+                    }
+                    self.check_custom(exp.cmp, right, "")?;
+                    self.check_supported(ValueFilter::from_exp(exp.cmp, right, exp.epsilon))?;
+                }
+                Some(pb::filter_node::Inner::Chain(bytes)) => {
+                    let chain = pb::FilterChain::decode(bytes.as_slice()).map_err(|e| {
+                        self.error(format!("fail to decode the nested predicates: {}", e))
+                    })?;
+                    self.check_value_chain(&chain)?;
+                }
+                None => Err(self.error("predicate not found"))?,
+            }
+        }
+        Ok(())
+    }
+
+    fn check_value_type(&self, name: &str, value: &common_pb::Value) -> Result<(), PlanError> {
+        let types = match self.schema.as_ref() {
+            Some(schema) => schema.get_property_types(name),
+            None => return Ok(()),
+        };
+        // the properties unknown to the schema are left unchecked
+        if types.is_empty() || types.iter().any(|data_type| is_comparable(data_type, value)) {
+            return Ok(());
+        }
+        let msg = format!(
+            "property {} of type {:?} can't be compared to {:?}",
+            name,
+            types,
+            value.item.as_ref()
+        );
+        match self.type_check {
+            TypeCheck::Strict => Err(self.error(msg)),
+            TypeCheck::Lenient => {
+                warn!("{}, which never matches", msg);
+                Ok(())
+            }
+        }
+    }
+
+    // the label ids a predicate compares to, which are out of range are of no element
+    fn check_label_value(&self, value: &common_pb::Value) -> Result<(), PlanError> {
+        let ids = match value.item.as_ref() {
+            Some(common_pb::value::Item::I32(id)) => std::slice::from_ref(id),
+            Some(common_pb::value::Item::I32Array(ids)) => ids.item.as_slice(),
+            _ => return Ok(()),
+        };
+        let msg = match ids.iter().find_map(|id| label_id_from_pb(*id).err()) {
+            Some(e) => e.to_string(),
+            None => return Ok(()),
+        };
+        match self.type_check {
+            TypeCheck::Strict => Err(self.error(msg)),
+            TypeCheck::Lenient => {
+                warn!("{}, which never matches", msg);
+                Ok(())
+            }
+        }
+    }
+
+    // the labels a step reads by, which are out of range fail the step anyway
+    fn check_step_labels(&self, labels: &[i32]) -> Result<(), PlanError> {
+        for id in labels.iter() {
+            label_id_from_pb(*id).map_err(|e| self.error(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    fn check_filter_value(&self, exp: Option<&pb::FilterValueExp>) -> Result<(), PlanError> {
+        let exp = exp.ok_or_else(|| self.error("predicate not found"))?;
+        self.check_enum(exp.cmp, pb::Compare::from_i32, "compare")?;
+        let right = exp.right.as_ref().ok_or_else(|| self.error("right of predicate not found"))?;
+        if exp.cmp == pb::Compare::Custom as i32 {
+            Err(self.error("custom predicates are only supported on the properties"))?;
+        }
+        self.check_custom(exp.cmp, right, "")?;
+        self.check_supported(ValueFilter::from_exp(exp.cmp, right, exp.epsilon))
+    }
+
+    // the custom values must be of the types registered on current server, as must the custom
+    // predicates on them
+    fn check_custom(
+        &self, cmp: i32, value: &common_pb::Value, predicate: &str,
+    ) -> Result<(), PlanError> {
+        let custom = match value.item.as_ref() {
+            Some(common_pb::value::Item::Custom(custom)) => custom,
+            _ if cmp == pb::Compare::Custom as i32 => {
+                return Err(self.error("the right of a custom predicate must be a custom value"))
+            }
+            _ => return Ok(()),
+        };
+        if cmp == pb::Compare::Custom as i32 {
+            // which also fails if the type is not registered
+            get_custom_predicate(&custom.tag, predicate).map_err(|e| self.error(e.to_string()))?;
+        } else if !is_custom_type_registered(&custom.tag) {
+            Err(self.error(format!("custom type `{}` is not registered", custom.tag)))?;
+        }
+        Ok(())
+    }
+}
+
+/// The name of the step as in the gremlin query, e.g. "outE()", to tell the step in errors
+fn step_name(step: &pb::gremlin_step::Step) -> String {
+    use pb::gremlin_step::Step;
+    let direction = |d: i32| match pb::Direction::from_i32(d) {
+        Some(pb::Direction::In) => "in",
+        Some(pb::Direction::Both) => "both",
+        _ => "out",
+    };
+    match step {
+        Step::GraphStep(graph_step) if graph_step.return_type == pb::EntityType::Edge as i32 => {
+            "E()".to_owned()
+        }
+        Step::GraphStep(_) => "V()".to_owned(),
+        Step::VertexStep(vertex_step) => {
+            let suffix =
+                if vertex_step.return_type == pb::EntityType::Edge as i32 { "E" } else { "" };
+            format!("{}{}()", direction(vertex_step.direction), suffix)
+        }
+        Step::DegreeStep(degree_step) => format!("{}E().count()", direction(degree_step.direction)),
+        Step::EdgeVertexStep(edge_vertex) => {
+            match pb::edge_vertex_step::EndpointOpt::from_i32(edge_vertex.endpoint_opt) {
+                Some(pb::edge_vertex_step::EndpointOpt::In) => "inV()".to_owned(),
+                Some(pb::edge_vertex_step::EndpointOpt::Other) => "otherV()".to_owned(),
+                _ => "outV()".to_owned(),
+            }
+        }
+        Step::EdgeBothVStep(_) => "bothV()".to_owned(),
+        Step::PropertiesStep(_) => "values()".to_owned(),
+        Step::MathStep(_) => "math()".to_owned(),
+        Step::NumericAccumStep(accum) => {
+            let kind = match pb::numeric_accum_step::Kind::from_i32(accum.kind) {
+                Some(pb::numeric_accum_step::Kind::Min) => "min",
+                Some(pb::numeric_accum_step::Kind::Max) => "max",
+                Some(pb::numeric_accum_step::Kind::Mean) => "mean",
+                Some(pb::numeric_accum_step::Kind::Count) => "count",
+                _ => "sum",
+            };
+            format!("{}({})", kind, if accum.local { "local" } else { "" })
+        }
+        // the other steps are not named in errors for now
+        _ => "the former step".to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use server_pb::operator_def::OpKind;
+
+    fn step(step: pb::gremlin_step::Step, tags: Vec<i32>) -> Vec<u8> {
+        let tags = tags.into_iter().map(|t| pb::StepTag { item: Some(pb::step_tag::Item::Tag(t)) });
+        let step = pb::GremlinStep { tags: tags.collect(), remove_tags: vec![], step: Some(step) };
+        let mut bytes = vec![];
+        step.encode(&mut bytes).unwrap();
+        bytes
+    }
+
+    fn op(op_kind: OpKind) -> server_pb::OperatorDef {
+        server_pb::OperatorDef { op_kind: Some(op_kind), ..Default::default() }
+    }
+
+    fn out() -> server_pb::OperatorDef {
+        vertex_step(pb::EntityType::Vertex)
+    }
+
+    fn out_e() -> server_pb::OperatorDef {
+        vertex_step(pb::EntityType::Edge)
+    }
+
+    fn vertex_step(return_type: pb::EntityType) -> server_pb::OperatorDef {
+        let vertex_step = pb::VertexStep {
+            edge_labels: vec![],
+            direction: pb::Direction::Out as i32,
+            return_type: return_type as i32,
+            predicates: None,
+        };
+        let resource = step(pb::gremlin_step::Step::VertexStep(vertex_step), vec![]);
+        op(OpKind::FlatMap(server_pb::FlatMap { resource }))
+    }
+
+    fn in_v() -> server_pb::OperatorDef {
+        let endpoint_opt = pb::edge_vertex_step::EndpointOpt::In as i32;
+        let edge_vertex = pb::EdgeVertexStep { endpoint_opt };
+        let resource = step(pb::gremlin_step::Step::EdgeVertexStep(edge_vertex), vec![]);
+        op(OpKind::Map(server_pb::Map { resource }))
+    }
+
+    fn out_degree() -> server_pb::OperatorDef {
+        let degree_step = pb::DegreeStep {
+            edge_labels: vec![],
+            direction: pb::Direction::Out as i32,
+            predicates: None,
+        };
+        let resource = step(pb::gremlin_step::Step::DegreeStep(degree_step), vec![]);
+        op(OpKind::Map(server_pb::Map { resource }))
+    }
+
+    fn values(key: &str) -> server_pb::OperatorDef {
+        let properties = pb::PropertiesStep { properties: vec![key.to_owned()] };
+        let resource = step(pb::gremlin_step::Step::PropertiesStep(properties), vec![]);
+        op(OpKind::FlatMap(server_pb::FlatMap { resource }))
+    }
+
+    fn sum() -> server_pb::OperatorDef {
+        let accum =
+            pb::NumericAccumStep { kind: pb::numeric_accum_step::Kind::Sum as i32, local: false };
+        op(OpKind::Fold(server_pb::Fold {
+            range: server_pb::Range::Global as i32,
+            accum: server_pb::AccumKind::Sum as i32,
+            resource: step(pb::gremlin_step::Step::NumericAccumStep(accum), vec![]),
+            unfold: Some(server_pb::FlatMap { resource: vec![] }),
+            ..Default::default()
+        }))
+    }
+
+    fn local_accum(kind: pb::numeric_accum_step::Kind) -> server_pb::OperatorDef {
+        let accum = pb::NumericAccumStep { kind: kind as i32, local: true };
+        let resource = step(pb::gremlin_step::Step::NumericAccumStep(accum), vec![]);
+        op(OpKind::FlatMap(server_pb::FlatMap { resource }))
+    }
+
+    fn fold(resource: Vec<u8>) -> server_pb::OperatorDef {
+        op(OpKind::Fold(server_pb::Fold {
+            range: server_pb::Range::Global as i32,
+            accum: server_pb::AccumKind::Custom as i32,
+            resource,
+            unfold: Some(server_pb::FlatMap { resource: vec![] }),
+            ..Default::default()
+        }))
+    }
+
+    fn unfold() -> server_pb::OperatorDef {
+        let resource = step(pb::gremlin_step::Step::UnfoldStep(pb::UnfoldStep {}), vec![]);
+        op(OpKind::FlatMap(server_pb::FlatMap { resource }))
+    }
+
+    fn approx(accum: server_pb::AccumKind, precision: u32, qs: Vec<f64>) -> server_pb::OperatorDef {
+        op(OpKind::Fold(server_pb::Fold {
+            range: server_pb::Range::Global as i32,
+            accum: accum as i32,
+            unfold: Some(server_pb::FlatMap { resource: vec![] }),
+            precision,
+            quantiles: qs,
+            ..Default::default()
+        }))
+    }
+
+    fn select(tag: i32) -> server_pb::OperatorDef {
+        let select_one = pb::SelectOneStepWithoutBy {
+            tag: Some(pb::StepTag { item: Some(pb::step_tag::Item::Tag(tag)) }),
+        };
+        let resource = step(pb::gremlin_step::Step::SelectOneWithoutBy(select_one), vec![]);
+        op(OpKind::Map(server_pb::Map { resource }))
+    }
+
+    fn math(expression: &str, variables: Vec<(&str, i32)>) -> server_pb::OperatorDef {
+        let variables = variables.into_iter().map(|(name, tag)| pb::MathVariable {
+            name: name.to_owned(),
+            tag: Some(pb::StepTag { item: Some(pb::step_tag::Item::Tag(tag)) }),
+        });
+        let math =
+            pb::MathStep { expression: expression.to_owned(), variables: variables.collect() };
+        let resource = step(pb::gremlin_step::Step::MathStep(math), vec![]);
+        op(OpKind::FlatMap(server_pb::FlatMap { resource }))
+    }
+
+    fn repeat(body: Vec<server_pb::OperatorDef>) -> server_pb::OperatorDef {
+        op(OpKind::Iterate(server_pb::Iteration {
+            max_iters: 2,
+            until: None,
+            body: Some(server_pb::TaskPlan { plan: body }),
+            post_check: false,
+            emit: server_pb::iteration::EmitKind::EmitNone as i32,
+        }))
+    }
+
+    fn request(plan: Vec<server_pb::OperatorDef>) -> server_pb::JobRequest {
+        request_with_source(vec![], plan)
+    }
+
+    fn request_with_source(
+        tags: Vec<i32>, plan: Vec<server_pb::OperatorDef>,
+    ) -> server_pb::JobRequest {
+        let graph_step = pb::GraphStep {
+            ids: vec![],
+            labels: vec![],
+            return_type: pb::EntityType::Vertex as i32,
+            predicates: None,
+            traverser_requirements: vec![],
+            high_ids: vec![],
+        };
+        let resource = step(pb::gremlin_step::Step::GraphStep(graph_step), tags);
+        server_pb::JobRequest {
+            conf: None,
+            source: Some(server_pb::Source { resource }),
+            plan: Some(server_pb::TaskPlan { plan }),
+            sink: None,
+            plan_version: 0,
+            features: vec![],
+            graph: String::new(),
+        }
+    }
+
+    fn assert_error(req: server_pb::JobRequest, op_index: Vec<usize>, msg: &str) {
+        let err = validate_request(&req).expect_err("the plan is expected to be invalid");
+        assert_eq!(err.op_index, op_index);
+        assert!(err.msg.contains(msg), "unexpected error: {}", err);
+    }
+
+    #[test]
+    fn valid_plan_test() {
+        let req = request_with_source(vec![0], vec![out(), select(0), values("age"), sum()]);
+        assert!(validate_request(&req).is_ok());
+        let req = request(vec![repeat(vec![out()]), values("age"), sum()]);
+        assert!(validate_request(&req).is_ok());
+    }
+
+    #[test]
+    fn missing_source_test() {
+        let mut req = request(vec![out()]);
+        req.source = None;
+        assert_error(req, vec![], "source of job not found");
+    }
+
+    #[test]
+    fn missing_op_kind_test() {
+        let req = request(vec![out(), server_pb::OperatorDef::default()]);
+        assert_error(req, vec![1], "operator kind not found");
+    }
+
+    #[test]
+    fn missing_unfold_test() {
+        let mut fold = sum();
+        if let Some(OpKind::Fold(fold)) = fold.op_kind.as_mut() {
+            fold.unfold = None;
+        }
+        assert_error(request(vec![values("age"), fold]), vec![1], "unfold of fold not found");
+    }
+
+    #[test]
+    fn invalid_enum_test() {
+        let limit = op(OpKind::Limit(server_pb::Limit { range: 7, limit: 10 }));
+        assert_error(request(vec![limit]), vec![0], "invalid range 7");
+        let mut fold = sum();
+        if let Some(OpKind::Fold(fold)) = fold.op_kind.as_mut() {
+            fold.accum = 100;
+        }
+        assert_error(request(vec![values("age"), fold]), vec![1], "invalid accum kind 100");
+    }
+
+    #[test]
+    fn undefined_tag_test() {
+        assert_error(
+            request(vec![out(), select(1)]),
+            vec![1],
+            "tag 1 is referenced before defined",
+        );
+        // the tag defined in the later step can't be referenced either
+        let req = request(vec![
+            select(0),
+            op(OpKind::Map(server_pb::Map {
+                resource: step(
+                    pb::gremlin_step::Step::IdentityStep(pb::IdentityStep {
+                        properties: vec![],
+                        is_all: false,
+                    }),
+                    vec![0],
+                ),
+            })),
+        ]);
+        assert_error(req, vec![0], "tag 0 is referenced before defined");
+    }
+
+    #[test]
+    fn empty_property_key_test() {
+        assert_error(request(vec![values("")]), vec![0], "property key must not be empty");
+        let has = pb::HasStep {
+            predicates: Some(pb::FilterChain {
+                node: vec![pb::FilterNode {
+                    inner: Some(pb::filter_node::Inner::Single(pb::FilterExp {
+                        left: Some(common_pb::Key {
+                            item: Some(common_pb::key::Item::Name("".to_owned())),
+                        }),
+                        cmp: pb::Compare::Eq as i32,
+                        right: Some(common_pb::Value {
+                            item: Some(common_pb::value::Item::I32(1)),
+                        }),
+                        epsilon: 0.0,
+                        predicate: String::new(),
+                    })),
+                    next: pb::Connect::And as i32,
+                }],
+            }),
+        };
+        let resource = step(pb::gremlin_step::Step::HasStep(has), vec![]);
+        let req = request(vec![op(OpKind::Filter(server_pb::Filter { resource }))]);
+        assert_error(req, vec![0], "property key must not be empty");
+    }
+
+    #[test]
+    fn invalid_detach_policy_test() {
+        let sink = |policy: pb::DetachPolicy| {
+            let mut resource = vec![];
+            policy.encode(&mut resource).expect("encode policy failed");
+            let mut req = request(vec![out()]);
+            req.sink =
+                Some(server_pb::Sink { sinker: Some(server_pb::sink::Sinker::Resource(resource)) });
+            req
+        };
+        let custom = pb::detach_policy::Mode::Custom as i32;
+        let req = sink(pb::DetachPolicy { mode: custom, keys: vec!["name".to_owned()] });
+        assert!(validate_request(&req).is_ok());
+        let req = sink(pb::DetachPolicy { mode: custom, keys: vec!["".to_owned()] });
+        assert_error(req, vec![], "property keys of detach policy must not be empty");
+        assert_error(
+            sink(pb::DetachPolicy { mode: 9, keys: vec![] }),
+            vec![],
+            "invalid detach mode 9",
+        );
+    }
+
+    #[test]
+    fn missing_predicate_test() {
+        let has = pb::HasStep {
+            predicates: Some(pb::FilterChain {
+                node: vec![pb::FilterNode {
+                    inner: Some(pb::filter_node::Inner::Single(pb::FilterExp {
+                        left: Some(common_pb::Key {
+                            item: Some(common_pb::key::Item::Name("age".to_owned())),
+                        }),
+                        cmp: pb::Compare::Gt as i32,
+                        right: None,
+                        epsilon: 0.0,
+                        predicate: String::new(),
+                    })),
+                    next: pb::Connect::And as i32,
+                }],
+            }),
+        };
+        let resource = step(pb::gremlin_step::Step::HasStep(has), vec![]);
+        let req = request(vec![out(), op(OpKind::Filter(server_pb::Filter { resource }))]);
+        assert_error(req, vec![1], "right of predicate not found");
+    }
+
+    #[test]
+    fn repeat_too_deep_test() {
+        let mut plan = vec![out()];
+        for _ in 0..MAX_REPEAT_DEPTH {
+            plan = vec![repeat(plan)];
+        }
+        assert!(validate_request(&request(plan.clone())).is_ok());
+        let req = request(vec![repeat(plan)]);
+        assert_error(req, vec![0, 0, 0, 0, 0, 0, 0, 0, 0], "nested deeper than the limit");
+    }
+
+    #[test]
+    fn empty_repeat_body_test() {
+        assert_error(request(vec![out(), repeat(vec![])]), vec![1], "the nested plan is empty");
+    }
+
+    #[test]
+    fn sum_over_vertices_test() {
+        assert_error(request(vec![out(), sum()]), vec![1], "requires numeric values");
+        assert_error(request(vec![repeat(vec![out()]), sum()]), vec![1], "requires numeric values");
+    }
+
+    #[test]
+    fn element_kind_test() {
+        for plan in [
+            vec![out_e(), in_v(), out()],
+            vec![out(), out_degree()],
+            vec![repeat(vec![out_e(), in_v()]), out_e()],
+        ] {
+            validate_request(&request(plan)).unwrap();
+        }
+        assert_error(
+            request(vec![out(), in_v()]),
+            vec![1],
+            "inV() requires edges, but the traversers are vertices from out()",
+        );
+        assert_error(
+            request(vec![out_e(), out()]),
+            vec![1],
+            "out() requires vertices, but the traversers are edges from outE()",
+        );
+        assert_error(
+            request(vec![out_e(), out_degree()]),
+            vec![1],
+            "outE().count() requires vertices, but the traversers are edges from outE()",
+        );
+        assert_error(
+            request(vec![repeat(vec![out_e()]), out_e()]),
+            vec![0, 0],
+            "outE() requires vertices, but the traversers are edges from outE()",
+        );
+        // g.V() of vertices can't be followed by inV()
+        assert_error(request(vec![in_v()]), vec![0], "from V()");
+    }
+
+    #[test]
+    fn approx_accum_test() {
+        let distinct = |p| approx(server_pb::AccumKind::ApproxDistinct, p, vec![]);
+        assert!(validate_request(&request(vec![out(), distinct(14)])).is_ok());
+        assert_error(request(vec![out(), distinct(3)]), vec![1], "precision 3");
+        assert_error(request(vec![out(), distinct(19)]), vec![1], "out of [4, 18]");
+        let quantiles = |qs| approx(server_pb::AccumKind::Quantiles, 0, qs);
+        let req = request(vec![values("age"), quantiles(vec![0.5, 0.99])]);
+        assert!(validate_request(&req).is_ok());
+        let req = request(vec![values("age"), quantiles(vec![])]);
+        assert_error(req, vec![1], "at least one quantile");
+        let req = request(vec![values("age"), quantiles(vec![0.5, 1.5])]);
+        assert_error(req, vec![1], "quantile 1.5 is out of [0, 1]");
+        assert_error(request(vec![out(), quantiles(vec![0.5])]), vec![1], "requires numeric");
+    }
+
+    #[test]
+    fn fold_unfold_test() {
+        let fold_step = || step(pb::gremlin_step::Step::FoldStep(pb::FoldStep {}), vec![]);
+        let req = request(vec![out(), fold(fold_step()), unfold(), out()]);
+        assert!(validate_request(&req).is_ok());
+        let accum =
+            pb::NumericAccumStep { kind: pb::numeric_accum_step::Kind::Sum as i32, local: false };
+        let accum = step(pb::gremlin_step::Step::NumericAccumStep(accum), vec![]);
+        assert_error(request(vec![out(), fold(accum)]), vec![1], "Custom requires a fold step");
+    }
+
+    #[test]
+    fn local_accum_test() {
+        use pb::numeric_accum_step::Kind;
+        let fold_step = || step(pb::gremlin_step::Step::FoldStep(pb::FoldStep {}), vec![]);
+        let req = request(vec![out(), fold(fold_step()), local_accum(Kind::Count)]);
+        assert!(validate_request(&req).is_ok());
+        let req = request(vec![values("age"), fold(fold_step()), local_accum(Kind::Sum)]);
+        assert!(validate_request(&req).is_ok());
+        let req = request(vec![out(), local_accum(Kind::Count)]);
+        assert_error(
+            req.clone(),
+            vec![1],
+            "count(local) requires collections, e.g. of fold(), but the traversers are vertices \
+             from out()",
+        );
+        // which are only filtered out if lenient
+        assert!(validate_with_schema(&req, None, TypeCheck::Lenient).is_ok());
+        let req = request(vec![values("age"), local_accum(Kind::Sum)]);
+        assert_error(req, vec![1], "the traversers are values from values()");
+        // the local accumulation is not a fold
+        let accum = pb::NumericAccumStep { kind: Kind::Sum as i32, local: true };
+        let sum = op(OpKind::Fold(server_pb::Fold {
+            range: server_pb::Range::Global as i32,
+            accum: server_pb::AccumKind::Sum as i32,
+            resource: step(pb::gremlin_step::Step::NumericAccumStep(accum), vec![]),
+            unfold: Some(server_pb::FlatMap { resource: vec![] }),
+            ..Default::default()
+        }));
+        assert_error(request(vec![values("age"), sum]), vec![1], "requires a global numeric");
+    }
+
+    #[test]
+    fn union_branches_test() {
+        let union = |branches: Vec<Vec<server_pb::OperatorDef>>| {
+            let branches = branches.into_iter().map(|plan| server_pb::TaskPlan { plan });
+            op(OpKind::Union(server_pb::Union { branches: branches.collect() }))
+        };
+        let req = request(vec![union(vec![vec![out()]])]);
+        assert_error(req, vec![0], "at least two branches");
+        // the index of the branch is in the path of the malformed operator
+        let req = request(vec![union(vec![vec![out()], vec![out(), select(3)]])]);
+        assert_error(req, vec![0, 1, 1], "tag 3 is referenced before defined");
+    }
+
+    #[test]
+    fn coalesce_branches_test() {
+        let coalesce = |branches: Vec<Vec<server_pb::OperatorDef>>| {
+            let branches = branches.into_iter().map(|plan| server_pb::TaskPlan { plan });
+            op(OpKind::Coalesce(server_pb::Coalesce { branches: branches.collect() }))
+        };
+        assert_error(request(vec![coalesce(vec![])]), vec![0], "at least one branch");
+        assert!(validate_request(&request(vec![coalesce(vec![vec![out()]])])).is_ok());
+        let req = request(vec![coalesce(vec![vec![out()], vec![out(), select(3)]])]);
+        assert_error(req, vec![0, 1, 1], "tag 3 is referenced before defined");
+    }
+
+    #[test]
+    fn approx_dedup_test() {
+        let dedup = |expected_items: u64, fp_rate: f64| {
+            let dedup_type = pb::dedup_step::DedupSetType::HashSet as i32;
+            let dedup_step = pb::DedupStep { dedup_type, key: None };
+            let set = step(pb::gremlin_step::Step::DedupStep(dedup_step), vec![]);
+            let dedup = server_pb::Dedup { range: 1, set, expected_items, fp_rate };
+            op(OpKind::Dedup(dedup))
+        };
+        assert!(validate_request(&request(vec![out(), dedup(0, 0.0)])).is_ok());
+        assert!(validate_request(&request(vec![out(), dedup(1000, 0.01)])).is_ok());
+        for fp_rate in [0.0, 1.0, -0.1, f64::NAN] {
+            let req = request(vec![out(), dedup(1000, fp_rate)]);
+            assert_error(req, vec![1], "false positive rate");
+        }
+    }
+
+    #[test]
+    fn project_columns_test() {
+        let project = |names: Vec<&str>| {
+            let columns = names.into_iter().map(|name| pb::ProjectColumn {
+                name: name.to_owned(),
+                key: None,
+                projection: None,
+            });
+            let project = pb::ProjectStep { columns: columns.collect(), null_if_empty: false };
+            let resource = step(pb::gremlin_step::Step::ProjectStep(project), vec![]);
+            op(OpKind::Map(server_pb::Map { resource }))
+        };
+        let by = || {
+            let joiner = pb::SubTaskJoiner {
+                inner: Some(pb::sub_task_joiner::Inner::ProjectByJoiner(pb::ProjectByJoiner {
+                    column: "degree".to_owned(),
+                    empty: None,
+                })),
+            };
+            let mut resource = vec![];
+            joiner.encode(&mut resource).unwrap();
+            op(OpKind::Subtask(server_pb::Subtask {
+                join: Some(server_pb::LeftJoin { resource }),
+                task: Some(server_pb::TaskPlan { plan: vec![out()] }),
+                first: true,
+                ..Default::default()
+            }))
+        };
+        // the parents stay vertices once joined with the first results of the subtasks
+        assert!(validate_request(&request(vec![by(), project(vec!["name", "degree"])])).is_ok());
+        assert_error(request(vec![project(vec![])]), vec![0], "columns of project() not found");
+        let req = request(vec![project(vec!["name", "name"])]);
+        assert_error(req, vec![0], "column name of project() is duplicated");
+        let req = request(vec![values("name"), project(vec!["name"])]);
+        assert_error(req, vec![1], "project() requires vertices or edges");
+        let mut subtask = by();
+        if let Some(OpKind::Subtask(subtask)) = subtask.op_kind.as_mut() {
+            subtask.join = None;
+        }
+        assert_error(request(vec![subtask]), vec![0], "joined without a joiner");
+    }
+
+    #[test]
+    fn projection_test() {
+        let order_by = |keys: Vec<&str>, expression: &str| {
+            let keys = keys
+                .into_iter()
+                .map(|k| common_pb::Key { item: Some(common_pb::key::Item::Name(k.to_owned())) });
+            let projection = pb::Projection { keys: keys.collect(), expression: expression.into() };
+            let by_key = pb::ByKey { item: Some(pb::by_key::Item::Projection(projection)) };
+            let pair = pb::OrderByComparePair {
+                key: Some(pb::TagKey { tag: None, by_key: Some(by_key) }),
+                order: pb::order_by_compare_pair::Order::Asc as i32,
+            };
+            let order_by = pb::OrderByStep { pairs: vec![pair] };
+            let compare = step(pb::gremlin_step::Step::OrderByStep(order_by), vec![]);
+            op(OpKind::Order(server_pb::OrderBy { range: 1, limit: 0, compare }))
+        };
+        assert!(validate_request(&request(vec![out(), order_by(vec!["age", "name"], "")])).is_ok());
+        assert!(validate_request(&request(vec![out(), order_by(vec![], "age * 2")])).is_ok());
+        let req = request(vec![out(), order_by(vec![], "")]);
+        assert_error(req, vec![1], "keys of projection not found");
+        let req = request(vec![out(), order_by(vec!["age", ""], "")]);
+        assert_error(req, vec![1], "property key must not be empty");
+        let req = request(vec![out(), order_by(vec!["age"], "age + 1")]);
+        assert_error(req, vec![1], "both keys and an expression");
+        let req = request(vec![out(), order_by(vec![], "_ + age")]);
+        assert_error(req, vec![1], "`_` is given");
+        let req = request(vec![out(), order_by(vec![], "age +")]);
+        assert!(validate_request(&req).is_err());
+        let req = request(vec![values("age"), order_by(vec!["age"], "")]);
+        assert_error(req, vec![1], "by() of a projection requires vertices or edges");
+    }
+
+    fn has(key: &str, value: common_pb::value::Item) -> server_pb::OperatorDef {
+        let has = pb::HasStep {
+            predicates: Some(pb::FilterChain {
+                node: vec![pb::FilterNode {
+                    inner: Some(pb::filter_node::Inner::Single(pb::FilterExp {
+                        left: Some(common_pb::Key {
+                            item: Some(common_pb::key::Item::Name(key.to_owned())),
+                        }),
+                        cmp: pb::Compare::Eq as i32,
+                        right: Some(common_pb::Value { item: Some(value) }),
+                        epsilon: 0.0,
+                        predicate: String::new(),
+                    })),
+                    next: pb::Connect::And as i32,
+                }],
+            }),
+        };
+        let resource = step(pb::gremlin_step::Step::HasStep(has), vec![]);
+        op(OpKind::Filter(server_pb::Filter { resource }))
+    }
+
+    fn schema() -> Arc<GraphSchemaInfo> {
+        use graph_store::parser::DataType;
+        use graph_store::schema::LabelSchema;
+        let label = |id, name: &str, properties: Vec<(&str, DataType)>| LabelSchema {
+            id,
+            name: name.to_owned(),
+            properties: properties.into_iter().map(|(p, dt)| (p.to_owned(), dt)).collect(),
+        };
+        Arc::new(GraphSchemaInfo {
+            vertex_labels: vec![
+                label(
+                    0.into(),
+                    "person",
+                    vec![("name", DataType::String), ("age", DataType::Integer)],
+                ),
+                label(
+                    1.into(),
+                    "software",
+                    vec![("name", DataType::String), ("lang", DataType::String)],
+                ),
+            ],
+            edge_labels: vec![label(0.into(), "created", vec![("weight", DataType::Double)])],
+            relations: vec![],
+            strict: false,
+        })
+    }
+
+    #[test]
+    fn math_expression_test() {
+        let req = request(vec![values("age"), math("_ * 1.5 + 3", vec![]), sum()]);
+        assert!(validate_request(&req).is_ok());
+        let req = request_with_source(vec![0], vec![values("age"), math("_ - a", vec![("a", 0)])]);
+        assert!(validate_request(&req).is_ok());
+        assert_error(request(vec![values("age"), math("_ +", vec![])]), vec![1], "unexpected end");
+        let req = request_with_source(vec![0], vec![values("age"), math("_ - b", vec![("a", 0)])]);
+        assert_error(req, vec![1], "variable `b` of math() is not bound to a label");
+        let req = request(vec![values("age"), math("a", vec![("a", 3)])]);
+        assert_error(req, vec![1], "tag 3 is referenced before defined");
+        let req = request(vec![out(), math("_ * 2", vec![])]);
+        assert_error(req, vec![1], "math() requires values of `_`");
+    }
+
+    #[test]
+    fn ill_typed_comparison_test() {
+        use common_pb::value::Item;
+        let validate =
+            |op, type_check| validate_with_schema(&request(vec![op]), Some(schema()), type_check);
+        let err = validate(has("age", Item::Str("29".to_owned())), TypeCheck::Strict).unwrap_err();
+        assert_eq!(err.op_index, vec![0]);
+        assert!(err.msg.contains("property age of type [Integer] can't be compared"), "{}", err);
+        assert!(validate(has("weight", Item::Str("0.4".to_owned())), TypeCheck::Strict).is_err());
+        assert!(validate(has("lang", Item::Boolean(true)), TypeCheck::Strict).is_err());
+        // the numbers are coerced to each other
+        assert!(validate(has("age", Item::I64(29)), TypeCheck::Strict).is_ok());
+        assert!(validate(has("weight", Item::I32(1)), TypeCheck::Strict).is_ok());
+        assert!(validate(has("name", Item::Str("marko".to_owned())), TypeCheck::Strict).is_ok());
+        // the properties unknown to the schema are left unchecked
+        assert!(validate(has("unknown", Item::Str("x".to_owned())), TypeCheck::Strict).is_ok());
+        assert!(validate(has("age", Item::Str("29".to_owned())), TypeCheck::Lenient).is_ok());
+        // nothing is checked without a schema
+        let req = request(vec![has("age", Item::Str("29".to_owned()))]);
+        assert!(validate_with_schema(&req, None, TypeCheck::Strict).is_ok());
+    }
+
+    #[test]
+    fn label_out_of_range_test() {
+        use common_pb::key::Item as Key;
+        use common_pb::value::Item as Value;
+        let label = || Key::Label(common_pb::LabelKey {});
+        let validate = |op, type_check| validate_with_schema(&request(vec![op]), None, type_check);
+        let eq = |id| filter_by(label(), pb::Compare::Eq, Value::I32(id));
+        let err = validate(eq(300), TypeCheck::Strict).unwrap_err();
+        assert!(err.msg.contains("label id 300 is out of range"), "{}", err);
+        assert!(validate(eq(-1), TypeCheck::Strict).is_err());
+        let within = Value::I32Array(common_pb::I32Array { item: vec![1, 256] });
+        assert!(
+            validate(filter_by(label(), pb::Compare::Within, within), TypeCheck::Strict).is_err()
+        );
+        assert!(validate(eq(1), TypeCheck::Strict).is_ok());
+        // the filter never matches instead
+        assert!(validate(eq(300), TypeCheck::Lenient).is_ok());
+        // while the labels of a step are never truncated
+        let vertex_step = pb::VertexStep {
+            edge_labels: vec![0, 256],
+            direction: pb::Direction::Out as i32,
+            return_type: pb::EntityType::Vertex as i32,
+            predicates: None,
+        };
+        let resource = step(pb::gremlin_step::Step::VertexStep(vertex_step), vec![]);
+        let out = op(OpKind::FlatMap(server_pb::FlatMap { resource }));
+        let err = validate(out, TypeCheck::Lenient).unwrap_err();
+        assert!(err.msg.contains("label id 256 is out of range"), "{}", err);
+    }
+
+    fn filter_by(
+        key: common_pb::key::Item, cmp: pb::Compare, value: common_pb::value::Item,
+    ) -> server_pb::OperatorDef {
+        let has = pb::HasStep {
+            predicates: Some(pb::FilterChain {
+                node: vec![pb::FilterNode {
+                    inner: Some(pb::filter_node::Inner::Single(pb::FilterExp {
+                        left: Some(common_pb::Key { item: Some(key) }),
+                        cmp: cmp as i32,
+                        right: Some(common_pb::Value { item: Some(value) }),
+                        epsilon: 0.0,
+                        predicate: String::new(),
+                    })),
+                    next: pb::Connect::And as i32,
+                }],
+            }),
+        };
+        let resource = step(pb::gremlin_step::Step::HasStep(has), vec![]);
+        op(OpKind::Filter(server_pb::Filter { resource }))
+    }
+
+    fn is_value(cmp: pb::Compare, value: common_pb::value::Item) -> server_pb::OperatorDef {
+        let right = common_pb::Value { item: Some(value) };
+        let single = pb::FilterValueExp { cmp: cmp as i32, right: Some(right), epsilon: 0.0 };
+        let is = pb::IsStep { single: Some(single), predicates: None };
+        let resource = step(pb::gremlin_step::Step::IsStep(is), vec![]);
+        op(OpKind::Filter(server_pb::Filter { resource }))
+    }
+
+    // each construct not supported yet is rejected with what it is, instead of panicking the
+    // workers decoding it
+    #[test]
+    fn unsupported_features_test() {
+        use crate::structure::codec::*;
+        use common_pb::key::Item as Key;
+        use common_pb::value::Item as Value;
+        use pb::Compare;
+        let name = || Key::Name("age".to_owned());
+        let id = || Key::Id(common_pb::IdKey {});
+        let label = || Key::Label(common_pb::LabelKey {});
+        let ints = || Value::I32Array(common_pb::I32Array { item: vec![1, 2] });
+        let longs = || Value::I64Array(common_pb::I64Array { item: vec![1, 2] });
+        let floats = || Value::F64Array(common_pb::DoubleArray { item: vec![0.5] });
+        let strs = || Value::StrArray(common_pb::StringArray { item: vec!["x".to_owned()] });
+        let cases = vec![
+            (out(), filter_by(name(), Compare::Eq, ints()), ARRAY_VALUE),
+            (out(), filter_by(name(), Compare::Ne, longs()), ARRAY_VALUE),
+            (out(), filter_by(name(), Compare::Lt, floats()), ARRAY_VALUE),
+            (out(), filter_by(name(), Compare::Ge, strs()), ARRAY_VALUE),
+            (out(), filter_by(id(), Compare::Eq, longs()), ARRAY_VALUE),
+            (values("age"), is_value(Compare::Eq, ints()), ARRAY_VALUE),
+            (values("age"), is_value(Compare::Gt, floats()), ARRAY_VALUE),
+            (out(), filter_by(Key::NameId(1), Compare::Eq, Value::I32(1)), PROPERTY_KEY_ID),
+            (out(), filter_by(Key::NameId(1), Compare::Le, Value::I32(1)), PROPERTY_KEY_ID),
+            (out(), filter_by(id(), Compare::Lt, Value::I64(1)), ID_ORDER),
+            (out(), filter_by(id(), Compare::Ge, Value::I64(1)), ID_ORDER),
+            (out(), filter_by(label(), Compare::Le, Value::I32(1)), LABEL_ORDER),
+            (out(), filter_by(label(), Compare::Gt, Value::I32(1)), LABEL_ORDER),
+            (out(), filter_by(name(), Compare::Within, ints()), WITHIN_PROPERTY),
+            (out(), filter_by(Key::NameId(1), Compare::Without, strs()), WITHIN_PROPERTY),
+        ];
+        let mut rejected = HashSet::new();
+        for (head, op, feature) in cases {
+            assert_error(request(vec![head, op]), vec![1], feature);
+            rejected.insert(feature);
+        }
+        // the checklist of the gaps is walked through
+        assert_eq!(rejected, UNSUPPORTED_FEATURES.iter().cloned().collect());
+    }
+}
@@ -0,0 +1,207 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The states the compiler warms up with as the server restarts, i.e. the templates of the plan
+//! cache and the prepared queries, which are saved as the server is drained before it shuts down,
+//! and loaded as it starts, so the queries submitted right after a restart are not slowed by a
+//! cold cache, and the clients keep the handles of their prepared queries.
+//!
+//! Nothing saved is trusted as it is loaded, as the engine or the graph may have changed across
+//! the restart. The templates are kept only if they are normalized and decoded as the plan cache
+//! does, and the prepared queries only if their features are supported by the engine and they
+//! are validated against the schema of the graph again; the others are discarded with a warning.
+
+use crate::generated::gremlin as pb;
+use crate::plan_cache::PlanCache;
+use crate::prepared::PreparedQueries;
+use crate::validate::TypeCheck;
+use pegasus_server::capability::{check_request, PLAN_VERSION};
+use pegasus_server::generated::protocol as server_pb;
+use prost::Message;
+use std::io;
+use std::path::Path;
+
+/// How much of the saved states are loaded
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct WarmReport {
+    /// the templates cached in the plan cache
+    pub steps: usize,
+    /// the queries prepared again
+    pub queries: usize,
+    /// the templates and the queries discarded as incompatible
+    pub discarded: usize,
+}
+
+/// Save the templates of the plan cache and the prepared queries to the file, which is written
+/// aside first and then renamed, so the file saved before is never left half written
+pub fn save(path: &Path, plan_cache: &PlanCache, prepared: &PreparedQueries) -> io::Result<()> {
+    let mut state = pb::WarmState {
+        plan_version: PLAN_VERSION,
+        plan_steps: plan_cache.normalized_steps(),
+        prepared: vec![],
+    };
+    for req in prepared.requests() {
+        let mut bytes = vec![];
+        req.encode(&mut bytes).map_err(io::Error::other)?;
+        state.prepared.push(bytes);
+    }
+    let mut bytes = vec![];
+    state.encode(&mut bytes).map_err(io::Error::other)?;
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    std::fs::write(&temp, bytes)?;
+    std::fs::rename(&temp, path)
+}
+
+/// Load the states saved to the file into the plan cache and the prepared queries, where the
+/// prepared queries are checked against the `features` of the compiler and the schema of the
+/// graph, see `JobCompiler::features`; Nothing is loaded if the file doesn't exist.
+pub fn load(
+    path: &Path, plan_cache: &PlanCache, prepared: &PreparedQueries, features: &[String],
+    type_check: TypeCheck,
+) -> io::Result<WarmReport> {
+    let bytes = match std::fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(WarmReport::default()),
+        Err(e) => return Err(e),
+    };
+    let state = pb::WarmState::decode(bytes.as_slice())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let mut report = WarmReport::default();
+    if state.plan_version == PLAN_VERSION {
+        for step in state.plan_steps.iter() {
+            match plan_cache.warm(step) {
+                Ok(()) => report.steps += 1,
+                Err(e) => {
+                    warn!("discard the template of the plan cache: {}", e);
+                    report.discarded += 1;
+                }
+            }
+        }
+    } else {
+        // the templates may be normalized differently by the engine of another version
+        warn!(
+            "discard {} templates of the plan cache saved by plan version {}, other than {}",
+            state.plan_steps.len(),
+            state.plan_version,
+            PLAN_VERSION
+        );
+        report.discarded += state.plan_steps.len();
+    }
+    for bytes in state.prepared.iter() {
+        let prepared = server_pb::JobRequest::decode(bytes.as_slice())
+            .map_err(|e| e.to_string())
+            .and_then(|req| check_request(&req, features).map(|_| req).map_err(|e| e.to_string()))
+            .and_then(|req| prepared.register(&req, type_check).map_err(|e| e.to_string()));
+        match prepared {
+            Ok(_) => report.queries += 1,
+            Err(e) => {
+                warn!("discard the prepared query: {}", e);
+                report.discarded += 1;
+            }
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::traversal::*;
+
+    fn request(traversal: GraphTraversal) -> server_pb::JobRequest {
+        let mut req = traversal.to_request(server_pb::JobConfig::default());
+        req.conf = None;
+        req
+    }
+
+    fn temp_file(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("{}_{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn save_load_test() {
+        let path = temp_file("warm_state_save_load");
+        let plan_cache = PlanCache::new(16);
+        let prepared = PreparedQueries::default();
+        let marko = request(Graph::traversal().v().has("name", eq("marko")));
+        let handle = prepared.register(&marko, TypeCheck::default()).unwrap();
+        let age = request(Graph::traversal().v().has("age", gt(29)).out(&[]));
+        prepared.register(&age, TypeCheck::default()).unwrap();
+        for op in marko.plan.as_ref().unwrap().plan.iter() {
+            if let Some(server_pb::operator_def::OpKind::Filter(filter)) = op.op_kind.as_ref() {
+                plan_cache.get_step(&filter.resource).unwrap();
+            }
+        }
+        assert_eq!(plan_cache.len(), 1);
+        save(&path, &plan_cache, &prepared).unwrap();
+
+        let warmed_cache = PlanCache::new(16);
+        let warmed = PreparedQueries::default();
+        let report = load(&path, &warmed_cache, &warmed, &[], TypeCheck::default()).unwrap();
+        assert_eq!(report, WarmReport { steps: 1, queries: 2, discarded: 0 });
+        assert_eq!(warmed_cache.normalized_steps(), plan_cache.normalized_steps());
+        // the prepared queries keep their handles across the restart
+        assert_eq!(warmed.get(handle).unwrap().slots(), &["str"]);
+        assert_eq!(warmed.requests(), prepared.requests());
+        std::fs::remove_file(&path).unwrap();
+        // nothing to warm up with
+        let report = load(&path, &warmed_cache, &warmed, &[], TypeCheck::default()).unwrap();
+        assert_eq!(report, WarmReport::default());
+    }
+
+    #[test]
+    fn discard_incompatible_test() {
+        let path = temp_file("warm_state_discard");
+        let prepared = PreparedQueries::default();
+        let mut window = request(Graph::traversal().v().has("name", eq("marko")));
+        window.features.push("op.window".to_owned());
+        prepared.register(&window, TypeCheck::default()).unwrap();
+        let josh = request(Graph::traversal().v().has("name", eq("josh")).out(&[]));
+        prepared.register(&josh, TypeCheck::default()).unwrap();
+        save(&path, &PlanCache::new(16), &prepared).unwrap();
+
+        let warmed = PreparedQueries::default();
+        let report = load(&path, &PlanCache::new(16), &warmed, &[], TypeCheck::default()).unwrap();
+        assert_eq!(report, WarmReport { steps: 0, queries: 1, discarded: 1 });
+        // the query requiring the feature unknown to the engine is discarded
+        assert_eq!(warmed.requests(), vec![josh]);
+        // unless the compiler supports the feature
+        let warmed = PreparedQueries::default();
+        let features = vec!["op.window".to_owned()];
+        let report = load(&path, &PlanCache::new(16), &warmed, &features, TypeCheck::default());
+        assert_eq!(report.unwrap().queries, 2);
+
+        std::fs::write(&path, b"not a warm state").unwrap();
+        assert!(load(&path, &PlanCache::new(16), &warmed, &[], TypeCheck::default()).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn stable_handle_test() {
+        let prepared = PreparedQueries::default();
+        let marko = request(Graph::traversal().v().has("name", eq("marko")));
+        let josh = request(Graph::traversal().v().has("name", eq("josh")));
+        let handle = prepared.register(&marko, TypeCheck::default()).unwrap();
+        // the queries of the same shape are given the same handle, whatever their literals
+        assert_eq!(prepared.register(&josh, TypeCheck::default()).unwrap(), handle);
+        assert_eq!(prepared.len(), 1);
+        let age = request(Graph::traversal().v().has("age", eq(29)));
+        assert_ne!(prepared.register(&age, TypeCheck::default()).unwrap(), handle);
+        assert!(prepared.remove(handle));
+        assert!(prepared.get(handle).is_none());
+        assert_eq!(prepared.len(), 1);
+    }
+}
@@ -0,0 +1,167 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use gremlin_core::process::adaptive::{adaptive_has, ADAPTIVE_HAS};
+    use gremlin_core::traversal::*;
+    use gremlin_core::GremlinStepPb;
+    use pegasus::api::Branch;
+    use pegasus_server::generated::protocol as server_pb;
+    use prost::Message;
+
+    fn job_conf(job_id: u64, workers: u32) -> server_pb::JobConfig {
+        server_pb::JobConfig {
+            job_id,
+            job_name: "adaptive_test".to_owned(),
+            workers,
+            ..Default::default()
+        }
+    }
+
+    fn plan(traversal: &GraphTraversal) -> Vec<server_pb::OperatorDef> {
+        let req = traversal.to_request(server_pb::JobConfig::default());
+        req.plan.expect("plan not found").plan
+    }
+
+    fn adapted(traversal: &GraphTraversal) -> Option<(usize, Vec<String>)> {
+        let plan = plan(traversal);
+        let point = adaptive_has(Adaptive::Sample(4), &plan, |res| GremlinStepPb::decode(res).ok())
+            .expect("adaptive point failed");
+        point.map(|p| (p.covers, p.second.iter().map(|op| op.name.clone()).collect()))
+    }
+
+    fn names(traversal: GraphTraversal, job_id: u64) -> Vec<String> {
+        let results = traversal.run(job_conf(job_id, 2));
+        let mut names: Vec<String> =
+            results.map(|r| r.expect("traversal failed").as_str().unwrap().into_owned()).collect();
+        names.sort();
+        names
+    }
+
+    // run the traversal by each order of its has steps, forced or decided by the samples, which
+    // must give the same results as the traversal evaluating the steps in the order of the plan
+    fn assert_equivalent<F: Fn() -> GraphTraversal>(traversal: F, job_id: u64) {
+        initialize();
+        let expected = names(traversal(), job_id);
+        assert!(!expected.is_empty());
+        let modes = [
+            Adaptive::Force(Branch::Left),
+            Adaptive::Force(Branch::Right),
+            Adaptive::Sample(1),
+            Adaptive::Sample(100),
+        ];
+        for (i, mode) in modes.iter().enumerate() {
+            let results = names(traversal().adaptive(*mode), job_id + 1 + i as u64);
+            assert_eq!(results, expected, "{:?} differs", mode);
+        }
+    }
+
+    fn decisions(traversal: GraphTraversal, job_id: u64) -> Vec<server_pb::AdaptiveDecision> {
+        initialize();
+        let mut results = traversal.profile().run(job_conf(job_id, 1));
+        let rows = results.by_ref().collect::<Result<Vec<_>, _>>().expect("traversal failed");
+        assert_eq!(rows.len(), 1);
+        let profile = results.profile().expect("profile not sent");
+        profile.decisions
+    }
+
+    // only the consecutive has steps piped one to another are adapted
+    #[test]
+    fn adaptive_has_rule_test() {
+        let g = || Graph::traversal().v();
+        let (covers, second) =
+            adapted(&g().has("age", gt(0)).has("name", eq("josh"))).expect("not adapted");
+        assert_eq!(covers, 2);
+        assert_eq!(second, vec!["has[name eq josh]", "has[age gt 0]"]);
+        assert!(adapted(&g().has("age", gt(0)).out(&[]).has("name", eq("josh"))).is_none());
+        assert!(adapted(&g().has("age", gt(0))).is_none());
+        assert!(adapted(&g().out(&[]).has("name", eq("josh"))).is_none());
+    }
+
+    // g.V().has("age", gt(0)).has("name", eq("josh")).values("name")
+    #[test]
+    fn adaptive_has_equivalence_test() {
+        let traversal =
+            || Graph::traversal().v().has("age", gt(0)).has("name", eq("josh")).values(&["name"]);
+        assert_equivalent(traversal, 6427);
+    }
+
+    // g.V().out().has("lang", eq("java")).has("name", neq("lop")).values("name"), where the has
+    // steps take the vertices reached by the expansion
+    #[test]
+    fn adaptive_has_after_expand_equivalence_test() {
+        let traversal = || {
+            Graph::traversal()
+                .v()
+                .out(&[])
+                .has("lang", eq("java"))
+                .has("name", neq("lop"))
+                .values(&["name"])
+        };
+        assert_equivalent(traversal, 6432);
+    }
+
+    // the first step passes 4 of the 6 vertices while the second passes only 1, so the second is
+    // evaluated first, instead of the order of the plan
+    #[test]
+    fn adaptive_has_switch_test() {
+        let traversal = Graph::traversal()
+            .v()
+            .has("age", gt(0))
+            .has("name", eq("josh"))
+            .values(&["name"])
+            .adaptive(Adaptive::Sample(6));
+        let decisions = decisions(traversal, 6437);
+        assert_eq!(decisions.len(), 1);
+        assert_eq!(decisions[0].name, ADAPTIVE_HAS);
+        assert_eq!(decisions[0].choice, 1);
+        assert_eq!(decisions[0].sampled, 6);
+        assert_eq!(decisions[0].reason, "has[age gt 0] passed 4/6, has[name eq josh] passed 1/6");
+    }
+
+    // the order of the plan is kept if its first step is already the more selective one
+    #[test]
+    fn adaptive_has_keep_test() {
+        let traversal = Graph::traversal()
+            .v()
+            .has("name", eq("josh"))
+            .has("age", gt(0))
+            .values(&["name"])
+            .adaptive(Adaptive::Sample(6));
+        let decisions = decisions(traversal, 6438);
+        assert_eq!(decisions.len(), 1);
+        assert_eq!(decisions[0].choice, 0);
+        assert_eq!(decisions[0].reason, "has[name eq josh] passed 1/6, has[age gt 0] passed 4/6");
+    }
+
+    // the forced order is told in the profile as well
+    #[test]
+    fn adaptive_has_forced_test() {
+        let traversal = Graph::traversal()
+            .v()
+            .has("name", eq("josh"))
+            .has("age", gt(0))
+            .values(&["name"])
+            .adaptive(Adaptive::Force(Branch::Right));
+        let decisions = decisions(traversal, 6439);
+        assert_eq!(decisions.len(), 1);
+        assert_eq!(decisions[0].choice, 1);
+        assert_eq!(decisions[0].reason, "forced");
+    }
+}
@@ -0,0 +1,69 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use dyn_type::Object;
+    use gremlin_core::traversal::*;
+    use pegasus_server::generated::protocol as server_pb;
+
+    fn job_conf(job_id: u64, workers: u32) -> server_pb::JobConfig {
+        server_pb::JobConfig {
+            job_id,
+            job_name: "approx_accum_test".to_owned(),
+            workers,
+            ..Default::default()
+        }
+    }
+
+    // run the traversal embedded with 2 workers, whose sketches are merged globally, and check
+    // the same results by its job request submitted to the service
+    fn run_approx(traversal: GraphTraversal, expected: Vec<Object>, job_id: u64) {
+        initialize();
+        let pb_request = traversal.to_request(job_conf(job_id + 100, 2));
+        let values: Vec<Object> =
+            traversal.run(job_conf(job_id, 2)).map(|r| r.expect("traversal failed")).collect();
+        assert_eq!(values, expected);
+        let test_job_factory = TestJobFactory::with_expect_values(expected);
+        run_test_with_job_id(test_job_factory, pb_request, job_id + 100, 2);
+    }
+
+    // g.V().out().countApproxDistinct(14), which is exact on the tiny graph
+    #[test]
+    fn count_approx_distinct_test() {
+        let traversal = Graph::traversal().v().out(&[]).count_approx_distinct(14);
+        run_approx(traversal, vec![4u64.into()], 6070);
+    }
+
+    // g.V().values("age").quantiles(0, 0.5, 1), of the ages 27, 29, 32 and 35
+    #[test]
+    fn quantiles_test() {
+        let traversal = Graph::traversal().v().values(&["age"]).quantiles(&[0.0, 0.5, 1.0]);
+        let expected = vec![27.0f64.into(), 30.5f64.into(), 35.0f64.into()];
+        run_approx(traversal, expected, 6071);
+    }
+
+    // g.V().values("name").quantiles(0.5), which fails on the names that are not numbers
+    #[test]
+    fn quantiles_of_non_numeric_test() {
+        initialize();
+        let traversal = Graph::traversal().v().values(&["name"]).quantiles(&[0.5]);
+        let results: Vec<_> = traversal.run(job_conf(6072, 2)).collect();
+        assert!(results.iter().any(|r| r.is_err()));
+    }
+}
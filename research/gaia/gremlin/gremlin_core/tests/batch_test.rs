@@ -0,0 +1,213 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use gremlin_core::compiler::GremlinJobCompiler;
+    use gremlin_core::process::traversal::traverser::Traverser;
+    use gremlin_core::traversal::*;
+    use gremlin_core::Partition;
+    use pegasus_server::batch::{BatchJobs, BatchOutput};
+    use pegasus_server::generated::protocol as server_pb;
+    use pegasus_server::generated::protocol::batch_response::Frame;
+    use pegasus_server::service::Service;
+    use pegasus_server::{JobRequest, JobResult};
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    #[derive(Clone, Default)]
+    struct CollectBatch {
+        frames: Arc<Mutex<Vec<server_pb::BatchResponse>>>,
+        // how long each result takes the client to receive, as a slow client
+        delay: Option<Duration>,
+    }
+
+    impl BatchOutput for CollectBatch {
+        fn send(&self, res: server_pb::BatchResponse) {
+            if let (Some(delay), Some(Frame::Response(_))) = (self.delay, res.frame.as_ref()) {
+                std::thread::sleep(delay);
+            }
+            self.frames.lock().unwrap().push(res);
+        }
+    }
+
+    impl CollectBatch {
+        fn frames(&self) -> Vec<server_pb::BatchResponse> {
+            self.frames.lock().unwrap().clone()
+        }
+
+        // the responses of the query of the index
+        fn results(&self, index: u32) -> Vec<JobResult> {
+            let frames = self.frames().into_iter().filter(|f| f.index == index);
+            frames
+                .filter_map(|f| match f.frame {
+                    Some(Frame::Response(res)) => res.result,
+                    _ => None,
+                })
+                .collect()
+        }
+
+        // wait for the ends of the queries, as statuses by their indexes
+        fn wait_ends(&self, queries: usize) -> Vec<server_pb::JobStatus> {
+            let deadline = Instant::now() + Duration::from_secs(60);
+            loop {
+                let mut ends = vec![None; queries];
+                for frame in self.frames() {
+                    if let Some(Frame::End(end)) = frame.frame {
+                        assert!(ends[frame.index as usize].is_none(), "query ended twice");
+                        ends[frame.index as usize] = server_pb::JobStatus::from_i32(end.status);
+                    }
+                }
+                if ends.iter().all(|end| end.is_some()) {
+                    return ends.into_iter().map(|end| end.unwrap()).collect();
+                }
+                assert!(Instant::now() < deadline, "queries not ended in time");
+                std::thread::sleep(Duration::from_millis(10));
+            }
+        }
+    }
+
+    fn request(traversal: GraphTraversal, job_id: u64) -> JobRequest {
+        let conf = server_pb::JobConfig {
+            job_id,
+            job_name: "batch_test".to_owned(),
+            workers: 2,
+            ..Default::default()
+        };
+        traversal.to_request(conf)
+    }
+
+    fn service() -> Service<Traverser> {
+        Service::new(GremlinJobCompiler::new(Partition { num_servers: 1 }, 1, 0))
+    }
+
+    fn admission(output: &CollectBatch) -> server_pb::BatchAdmission {
+        match output.frames().into_iter().next().and_then(|f| f.frame) {
+            Some(Frame::Admission(admission)) => admission,
+            other => panic!("expect the admission first, but got {:?}", other),
+        }
+    }
+
+    // g.V().out().count(), a query requiring an unknown feature, and g.V().values("name"), where
+    // the invalid one is rejected on its own while the others still run
+    #[test]
+    fn batch_with_invalid_query_test() {
+        initialize();
+        let mut invalid = request(Graph::traversal().v().out(&[]), 6441);
+        invalid.features.push("no.such.feature".to_owned());
+        let batch = server_pb::BatchRequest {
+            requests: vec![
+                request(Graph::traversal().v().out(&[]).count(), 6440),
+                invalid,
+                request(Graph::traversal().v().values(&["name"]), 6442),
+            ],
+        };
+        let jobs = BatchJobs::new(&batch);
+        assert_eq!(jobs.job_ids(), &[6440, 6441, 6442]);
+        let output = CollectBatch::default();
+        service().accept_batch(batch, &jobs, output.clone());
+        let ends = output.wait_ends(3);
+        assert_eq!(
+            ends,
+            vec![
+                server_pb::JobStatus::Completed,
+                server_pb::JobStatus::Failed,
+                server_pb::JobStatus::Completed
+            ]
+        );
+        let admission = admission(&output);
+        assert!(admission.admitted);
+        assert_eq!(admission.invalid, vec![1]);
+
+        match output.results(1).as_slice() {
+            [JobResult::Err(err)] => {
+                assert_eq!(err.kind, server_pb::QueryErrorKind::Unsupported as i32);
+                assert!(err.err_msg.contains("no.such.feature"), "{}", err.err_msg);
+            }
+            other => panic!("expect the error of the invalid query, but got {:?}", other),
+        }
+        for index in [0, 2].iter() {
+            let results = output.results(*index);
+            assert!(results.iter().any(|res| matches!(res, JobResult::Data(_))));
+            assert!(matches!(results.last(), Some(JobResult::Complete(_))));
+        }
+        // each query ends after all its responses
+        let frames = output.frames();
+        for index in 0..3 {
+            let last = frames.iter().rfind(|f| f.index == index);
+            assert!(matches!(last.and_then(|f| f.frame.as_ref()), Some(Frame::End(_))));
+        }
+    }
+
+    // g.V().both()...both() twice to a slow client, who cancels the batch as the results start to
+    // arrive, which cancels both queries
+    #[test]
+    fn batch_cancel_test() {
+        initialize();
+        let traversal = || {
+            let mut traversal = Graph::traversal().v();
+            for _ in 0..9 {
+                traversal = traversal.both(&[]);
+            }
+            traversal
+        };
+        let batch = server_pb::BatchRequest {
+            requests: vec![request(traversal(), 6443), request(traversal(), 6444)],
+        };
+        let jobs = BatchJobs::new(&batch);
+        let service = service();
+        let output =
+            CollectBatch { delay: Some(Duration::from_millis(5)), ..CollectBatch::default() };
+        service.accept_batch(batch, &jobs, output.clone());
+        assert!(admission(&output).admitted);
+        let deadline = Instant::now() + Duration::from_secs(60);
+        while output.results(0).is_empty() && output.results(1).is_empty() {
+            assert!(Instant::now() < deadline, "no result arrives in time");
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        service.cancel_batch(&jobs);
+        assert!(jobs.is_cancelled());
+        let ends = output.wait_ends(2);
+        assert_eq!(ends, vec![server_pb::JobStatus::Cancelled, server_pb::JobStatus::Cancelled]);
+        for index in 0..2 {
+            let results = output.results(index);
+            assert!(!results.iter().any(|res| matches!(res, JobResult::Complete(_))));
+        }
+    }
+
+    // the batch of a job id taken twice is rejected as a whole, before any query is submitted
+    #[test]
+    fn batch_rejected_test() {
+        initialize();
+        let batch = server_pb::BatchRequest {
+            requests: vec![
+                request(Graph::traversal().v().count(), 6445),
+                request(Graph::traversal().v().count(), 6445),
+            ],
+        };
+        let jobs = BatchJobs::new(&batch);
+        let output = CollectBatch::default();
+        service().accept_batch(batch, &jobs, output.clone());
+        let admission = admission(&output);
+        assert!(!admission.admitted);
+        let err = admission.err.expect("no error of the rejection");
+        assert_eq!(err.kind, server_pb::QueryErrorKind::Validation as i32);
+        assert!(err.err_msg.contains("6445"), "{}", err.err_msg);
+        assert_eq!(output.frames().len(), 1);
+    }
+}
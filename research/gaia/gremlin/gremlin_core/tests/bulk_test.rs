@@ -0,0 +1,116 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use gremlin_core::generated::gremlin as pb;
+    use gremlin_core::process::metrics::{get_worker_counter, EXPAND_COUNTER};
+    use pegasus_server::generated::protocol as server_pb;
+    use pegasus_server::JobRequest;
+    use prost::Message;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    // g.V().out().in().count(), built from the plan of g.V().out().out().count(), where the
+    // three traversers reaching vertex 3 by out() come from 1, 4 and 6, as a diamond.
+    fn out_in_count_request(bulking: bool) -> JobRequest {
+        let mut pb_request =
+            read_pb_request(gen_path("count_step_test_w2")).expect("read pb failed");
+        pb_request.conf.as_mut().expect("no job_conf").bulking = bulking;
+        let plan = pb_request.plan.as_mut().expect("plan not found");
+        let op = plan.plan.last_mut().expect("plan is empty");
+        if let Some(server_pb::operator_def::OpKind::FlatMap(flat_map)) = op.op_kind.as_mut() {
+            let mut step =
+                pb::GremlinStep::decode(flat_map.resource.as_slice()).expect("decode step failed");
+            if let Some(pb::gremlin_step::Step::VertexStep(vertex_step)) = step.step.as_mut() {
+                vertex_step.direction = pb::Direction::In as i32;
+            } else {
+                panic!("vertex step not found");
+            }
+            flat_map.resource.clear();
+            step.encode(&mut flat_map.resource).expect("encode step failed");
+        } else {
+            panic!("flat map not found");
+        }
+        pb_request
+    }
+
+    // g.V().out().in()
+    fn out_in_request(bulking: bool) -> JobRequest {
+        let mut pb_request = out_in_count_request(bulking);
+        let out_request = read_pb_request(gen_path("out_step_test_01")).expect("read pb failed");
+        pb_request.sink = out_request.sink;
+        pb_request
+    }
+
+    fn run_out_in_count(bulking: bool, job_id: u64) -> u64 {
+        initialize();
+        let test_job_factory = TestJobFactory::with_expect_values(vec![12.into()]);
+        run_test_with_job_id(test_job_factory, out_in_count_request(bulking), job_id, 1);
+        get_worker_counter(job_id, 0, EXPAND_COUNTER)
+    }
+
+    #[test]
+    fn bulking_count_test() {
+        let expanded = run_out_in_count(false, 5981);
+        let bulk_expanded = run_out_in_count(true, 5982);
+        // 6 expanded by out(), and 6 by in() without bulking
+        assert_eq!(expanded, 12);
+        // the traversers reaching vertex 3 are merged before in()
+        assert!(bulk_expanded < expanded);
+    }
+
+    // g.V().out().in() with the results consolidated by the sink if `consolidation` is not 0,
+    // returning the number of the records reaching the sink, whose results are the same multiset
+    fn run_out_in_consolidated(consolidation: u64, job_id: u64) -> usize {
+        initialize();
+        let mut expected = to_global_ids(vec![1, 1, 1, 1, 1, 4, 4, 4, 4, 6, 6, 6]);
+        expected.sort();
+        let records = Arc::new(AtomicUsize::new(0));
+        let test_job_factory =
+            TestJobFactory::with_expect_ids(expected).with_record_counter(records.clone());
+        let mut request = out_in_request(false);
+        request.conf.as_mut().expect("no job_conf").sink_consolidation = consolidation;
+        run_test_with_job_id(test_job_factory, request, job_id, 1);
+        records.load(Ordering::SeqCst)
+    }
+
+    #[test]
+    fn sink_consolidation_test() {
+        let records = run_out_in_consolidated(0, 6407);
+        let consolidated = run_out_in_consolidated(16, 6408);
+        assert_eq!(records, 12);
+        // one record for each of the vertices 1, 4 and 6
+        assert_eq!(consolidated, 3);
+    }
+
+    #[test]
+    fn sink_consolidation_overflow_test() {
+        // the distinct results exceed the bound at once, so the most of them pass through
+        let records = run_out_in_consolidated(1, 6409);
+        assert!(records > 3);
+    }
+
+    #[test]
+    fn bulking_out_in_test() {
+        initialize();
+        let mut expected = to_global_ids(vec![1, 1, 1, 1, 1, 4, 4, 4, 4, 6, 6, 6]);
+        expected.sort();
+        let test_job_factory = TestJobFactory::with_expect_ids(expected);
+        run_test_with_job_id(test_job_factory, out_in_request(true), 5983, 1);
+    }
+}
@@ -0,0 +1,49 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use gremlin_core::process::metrics::get_worker_counter;
+    use gremlin_core::process::traversal::traverser::Requirement;
+    use gremlin_core::structure::cache::{CACHE_HIT_COUNTER, CACHE_MISS_COUNTER};
+
+    fn run_two_hop(job_id: u64, cache_limit: u64) {
+        let mut test_job_factory = TestJobFactory::with_expect_ids(to_global_ids(vec![3, 5]));
+        test_job_factory.set_requirement(Requirement::OBJECT);
+        let mut pb_request =
+            read_pb_request(gen_path("object_requirement_test_02")).expect("read pb failed");
+        pb_request.conf.as_mut().expect("no job_conf").cache_limit = cache_limit;
+        run_test_with_job_id(test_job_factory, pb_request, job_id, 1);
+    }
+
+    // g.V().out().out(), where the second hop reads the cached adjacency of 2, 3, 3, 3, 4, 5
+    #[test]
+    fn adjacency_cache_test() {
+        initialize();
+        run_two_hop(5951, 1 << 20);
+        assert_eq!(get_worker_counter(5951, 0, CACHE_MISS_COUNTER), 6);
+        assert_eq!(get_worker_counter(5951, 0, CACHE_HIT_COUNTER), 6);
+    }
+
+    #[test]
+    fn adjacency_cache_disabled_test() {
+        initialize();
+        run_two_hop(5952, 0);
+        assert_eq!(get_worker_counter(5952, 0, CACHE_MISS_COUNTER), 0);
+        assert_eq!(get_worker_counter(5952, 0, CACHE_HIT_COUNTER), 0);
+    }
+}
@@ -0,0 +1,78 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use dyn_type::Object;
+    use gremlin_core::traversal::*;
+    use gremlin_core::ID;
+    use pegasus_server::generated::protocol as server_pb;
+
+    fn job_conf(job_id: u64, workers: u32) -> server_pb::JobConfig {
+        server_pb::JobConfig {
+            job_id,
+            job_name: "coalesce_test".to_owned(),
+            workers,
+            ..Default::default()
+        }
+    }
+
+    fn sorted_ids(traversal: GraphTraversal, conf: server_pb::JobConfig) -> Vec<ID> {
+        let values: Vec<Object> =
+            traversal.run(conf).map(|r| r.expect("traversal failed")).collect();
+        let mut ids: Vec<ID> =
+            values.iter().map(|v| v.as_u128().expect("not an id") as ID).collect();
+        ids.sort();
+        ids
+    }
+
+    // g.V().coalesce(out("knows"), out()), where marko knows vadas and josh, josh and peter
+    // only create software, and the others have no out edges
+    #[test]
+    fn coalesce_test_01() {
+        initialize();
+        let branches = vec![Graph::anonymous().out(&[0]), Graph::anonymous().out(&[])];
+        let traversal = Graph::traversal().v().coalesce(branches);
+        let ids = sorted_ids(traversal, job_conf(6264, 2));
+        let mut expected = to_global_ids(vec![2, 3, 3, 4, 5]);
+        expected.sort();
+        assert_eq!(ids, expected);
+    }
+
+    // g.V().coalesce(out("knows"), in("knows")), where only the vertices known by marko match
+    // the second branch
+    #[test]
+    fn coalesce_test_02() {
+        initialize();
+        let branches = vec![Graph::anonymous().out(&[0]), Graph::anonymous().in_(&[0])];
+        let traversal = Graph::traversal().v().coalesce(branches);
+        let ids = sorted_ids(traversal, job_conf(6265, 2));
+        assert_eq!(ids, to_global_ids(vec![1, 1, 2, 4]));
+    }
+
+    // g.V().coalesce(out()), the same as g.V().out()
+    #[test]
+    fn coalesce_single_branch_test() {
+        initialize();
+        let traversal = Graph::traversal().v().coalesce(vec![Graph::anonymous().out(&[])]);
+        let ids = sorted_ids(traversal, job_conf(6266, 1));
+        let mut expected = to_global_ids(vec![2, 3, 3, 3, 4, 5]);
+        expected.sort();
+        assert_eq!(ids, expected);
+    }
+}
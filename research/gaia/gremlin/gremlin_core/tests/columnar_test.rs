@@ -0,0 +1,143 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use dyn_type::Object;
+    use gremlin_core::compiler::GremlinJobCompiler;
+    use gremlin_core::process::columnar::{fuse_steps, ColumnarStep};
+    use gremlin_core::process::traversal::traverser::Traverser;
+    use gremlin_core::traversal::*;
+    use gremlin_core::{GremlinStepPb, Partition};
+    use pegasus_server::factory::JobCompiler;
+    use pegasus_server::generated::protocol as server_pb;
+    use pegasus_server::JobRequest;
+    use prost::Message;
+
+    fn job_conf(job_id: u64) -> server_pb::JobConfig {
+        server_pb::JobConfig {
+            job_id,
+            job_name: "columnar_test".to_owned(),
+            workers: 2,
+            ..Default::default()
+        }
+    }
+
+    fn request(traversal: &GraphTraversal) -> JobRequest {
+        traversal.to_request(server_pb::JobConfig::default())
+    }
+
+    fn fused(traversal: &GraphTraversal) -> Vec<ColumnarStep> {
+        let req = request(traversal);
+        let plan = req.plan.expect("plan not found").plan;
+        fuse_steps(&plan, |res| GremlinStepPb::decode(res).ok())
+    }
+
+    // the has steps are fused until a values step, or the first step after an exchange
+    #[test]
+    fn fuse_steps_test() {
+        let g = || Graph::traversal().v();
+        assert_eq!(fused(&g().has("age", gt(28))).len(), 1);
+        assert_eq!(fused(&g().has("age", gt(28)).has("name", neq("josh"))).len(), 2);
+        let steps = fused(&g().has("age", gt(28)).values(&["name"]).is(eq("marko")));
+        assert_eq!(steps.len(), 2);
+        assert!(matches!(steps[1], ColumnarStep::Values(ref keys) if keys == &["name"]));
+        assert_eq!(fused(&g().has("age", gt(28)).out(&[]).has("name", eq("lop"))).len(), 1);
+        assert!(fused(&g().out(&[]).has("name", eq("lop"))).is_empty());
+        assert!(fused(&g().count()).is_empty());
+        // nothing is selected over the columns by the values step alone
+        assert!(fused(&g().values(&["name"])).is_empty());
+    }
+
+    // the results of the traversal by the operators evaluated one by one over the traversers
+    fn by_rows(compiler: &GremlinJobCompiler, req: &JobRequest) -> Vec<String> {
+        let source = compiler.source(&req.source.as_ref().unwrap().resource).unwrap();
+        let mut traversers: Vec<Traverser> = source.collect();
+        for op in req.plan.as_ref().unwrap().plan.iter() {
+            traversers = match op.op_kind.as_ref() {
+                Some(server_pb::operator_def::OpKind::Filter(filter)) => {
+                    let func = compiler.filter(&filter.resource).unwrap();
+                    traversers.into_iter().filter(|t| func.exec(t).unwrap()).collect()
+                }
+                Some(server_pb::operator_def::OpKind::FlatMap(flat_map)) => {
+                    let func = compiler.flat_map(&flat_map.resource).unwrap();
+                    traversers
+                        .into_iter()
+                        .flat_map(|t| func.exec(t).unwrap().map(|t| t.unwrap()))
+                        .collect()
+                }
+                _ => unreachable!(),
+            };
+        }
+        let mut results: Vec<String> = traversers.iter().map(|t| format!("{:?}", t)).collect();
+        results.sort();
+        results
+    }
+
+    // the results of the traversal by the operators fused into the columnar scan
+    fn by_columns(compiler: &GremlinJobCompiler, req: &JobRequest) -> Vec<String> {
+        let plan = &req.plan.as_ref().unwrap().plan;
+        let (source, fused) = compiler
+            .fused_source(&req.source.as_ref().unwrap().resource, plan)
+            .unwrap()
+            .expect("no step fused");
+        assert_eq!(fused, plan.len());
+        let mut results: Vec<String> = source.map(|t| format!("{:?}", t)).collect();
+        results.sort();
+        results
+    }
+
+    // the columnar scan produces exactly the traversers the steps evaluated one by one produce
+    #[test]
+    fn columnar_equivalence_test() {
+        initialize();
+        let compiler = GremlinJobCompiler::new(Partition { num_servers: 1 }, 1, 0);
+        let g = || Graph::traversal().v();
+        let traversals = vec![
+            g().has("age", gt(28)),
+            g().has("age", lte(28).or(gte(32))),
+            g().has("name", eq("marko").or(eq("lop"))),
+            g().has("lang", eq("java")).has("name", neq("lop")),
+            g().has("age", eq(27).or(eq(35))).values(&["name", "age"]),
+            g().has("name", neq("vadas")).values(&["age"]),
+            g().has("age", gt(100)).values(&["name"]),
+        ];
+        for traversal in traversals {
+            let req = request(&traversal);
+            assert_eq!(by_columns(&compiler, &req), by_rows(&compiler, &req));
+        }
+    }
+
+    fn run(traversal: GraphTraversal, job_id: u64) -> Vec<Object> {
+        initialize();
+        traversal.run(job_conf(job_id)).map(|r| r.expect("traversal failed")).collect()
+    }
+
+    // g.V().has("age", gt(28)).values("name") and g.V().has("age", gt(28)).count() by the scan
+    // the steps are fused into
+    #[test]
+    fn columnar_traversal_test() {
+        let traversal = Graph::traversal().v().has("age", gt(28)).values(&["name"]);
+        let mut names: Vec<String> =
+            run(traversal, 6357).iter().map(|o| o.as_str().unwrap().into_owned()).collect();
+        names.sort();
+        assert_eq!(names, vec!["josh", "marko", "peter"]);
+        let traversal = Graph::traversal().v().has("age", gt(28)).count();
+        assert_eq!(run(traversal, 6358), vec![Object::from(3u64)]);
+    }
+}
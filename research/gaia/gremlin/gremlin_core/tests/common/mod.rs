@@ -24,6 +24,8 @@ pub mod test {
     use graph_store::ldbc::LDBCVertexParser;
     use graph_store::prelude::DefaultId;
     use gremlin_core::compiler::GremlinJobCompiler;
+    use gremlin_core::plan_cache::PlanCache;
+    use gremlin_core::process::limits::{get_job_access, JobAccess};
     use gremlin_core::process::traversal::path::ResultPath;
     use gremlin_core::process::traversal::step::result_downcast::{
         try_downcast_count, try_downcast_list, try_downcast_pair,
@@ -35,16 +37,22 @@ pub mod test {
     use gremlin_core::{GremlinStepPb, Partition};
     use pegasus::api::function::{
         CompareFunction, EncodeFunction, FilterFunction, FlatMapFunction, LeftJoinFunction,
-        MapFunction, MultiRouteFunction, RouteFunction,
+        MapFunction, MultiRouteFunction, ReplicaPolicy, RouteFunction,
     };
-    use pegasus::{Configuration, StartupError};
+    use pegasus::{Configuration, JobConf, StartupError};
     use pegasus_common::collections::{Collection, CollectionFactory, Set};
-    use pegasus_server::factory::{CompileResult, FoldFunction, GroupFunction, JobCompiler};
+    use pegasus_server::error::QueryError;
+    use pegasus_server::factory::{
+        CompileResult, FoldFunction, GroupFunction, JobCompiler, PlanError,
+    };
     use pegasus_server::service::{Output, Service};
     use pegasus_server::{JobRequest, JobResponse, JobResult};
     use prost::Message;
+    use std::collections::HashMap;
     use std::path::{Path, PathBuf};
-    use std::sync::Once;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex, Once};
+    use std::time::{Duration, Instant};
 
     const TEST_PLAN_PATH: &'static str = "resource/test/query_plans";
 
@@ -73,6 +81,12 @@ pub mod test {
         expected_tag_props: Option<Vec<Vec<(Tag, Vec<(String, Object)>)>>>,
         // to test early stop, with the expected value of number of results
         expected_result_num: Option<usize>,
+        // to test the number of workers decided for the job, by the peers of the worker running sink
+        expected_peers: Option<u32>,
+        // to count the records reaching the sink, before the merged ones are split by their bulks
+        record_counter: Option<Arc<AtomicUsize>>,
+        // the results of the sinks of all workers by their jobs, checked once the jobs end
+        results: Arc<Mutex<HashMap<u64, Vec<Traverser>>>>,
     }
 
     impl TestJobFactory {
@@ -89,9 +103,36 @@ pub mod test {
                 expected_path_len: None,
                 expected_tag_props: None,
                 expected_result_num: None,
+                expected_peers: None,
+                record_counter: None,
+                results: Arc::new(Mutex::new(HashMap::new())),
             }
         }
 
+        /// Decode the steps by the given plan cache, e.g. shared by the factories of many tests
+        pub fn with_plan_cache(mut self, plan_cache: Arc<PlanCache>) -> Self {
+            self.inner = self.inner.with_plan_cache(plan_cache);
+            self
+        }
+
+        /// Estimate the workers of jobs by the given records each worker deserves to scan
+        pub fn with_records_per_worker(mut self, records_per_worker: usize) -> Self {
+            self.inner = self.inner.with_records_per_worker(records_per_worker);
+            self
+        }
+
+        /// Check the number of workers the job runs with
+        pub fn with_expect_peers(mut self, expected_peers: u32) -> Self {
+            self.expected_peers = Some(expected_peers);
+            self
+        }
+
+        /// Count the records reaching the sink by the counter, where a merged one counts once
+        pub fn with_record_counter(mut self, counter: Arc<AtomicUsize>) -> Self {
+            self.record_counter = Some(counter);
+            self
+        }
+
         pub fn with_expect_ids(expected_ids: Vec<ID>) -> Self {
             let mut factory = TestJobFactory::new();
             factory.expected_ids = Some(expected_ids);
@@ -149,9 +190,43 @@ pub mod test {
         pub fn set_requirement(&mut self, requirement: Requirement) {
             self.requirement = requirement;
         }
+
+        pub fn set_partitioner<D: Partitioner>(&mut self, partitioner: D) {
+            self.inner = GremlinJobCompiler::new(partitioner, 1, 0);
+        }
+
+        /// Emulate the servers by the workers of the job, each running an equal share of them,
+        /// where the traversers are routed to the replicas of their vertices by the policy
+        pub fn set_servers<D: Partitioner>(
+            &mut self, partitioner: D, num_servers: usize, policy: ReplicaPolicy,
+        ) {
+            self.inner =
+                GremlinJobCompiler::new(partitioner, num_servers, 0).with_replica_policy(policy);
+        }
+
+        // the sink of the job, which keeps the results of the job to be checked by `check_job`
+        fn sink_of(&self, job_id: u64) -> TestSinkEncoder {
+            TestSinkEncoder {
+                job_id,
+                expected_ids: self.expected_ids.clone(),
+                expected_values: self.expected_values.clone(),
+                expected_paths: self.expected_path_result.clone(),
+                expected_group_result: self.expected_group_result.clone(),
+                is_ordered: self.is_ordered,
+                property_opt: self.expected_properties.clone(),
+                expected_path_len: self.expected_path_len,
+                expected_tag_props: self.expected_tag_props.clone(),
+                expected_result_num: self.expected_result_num,
+                expected_peers: self.expected_peers,
+                record_counter: self.record_counter.clone(),
+                results: self.results.clone(),
+                access: get_job_access(),
+            }
+        }
     }
 
     pub struct TestSinkEncoder {
+        job_id: u64,
         expected_ids: Option<Vec<ID>>,
         expected_values: Option<Vec<Object>>,
         expected_paths: Option<Vec<Vec<ID>>>,
@@ -161,11 +236,59 @@ pub mod test {
         expected_path_len: Option<usize>,
         expected_tag_props: Option<Vec<Vec<(Tag, Vec<(String, Object)>)>>>,
         expected_result_num: Option<usize>,
+        expected_peers: Option<u32>,
+        record_counter: Option<Arc<AtomicUsize>>,
+        results: Arc<Mutex<HashMap<u64, Vec<Traverser>>>>,
+        // the limits of the job, counting the results as the sink of the server does
+        access: Option<Arc<JobAccess>>,
     }
 
     impl EncodeFunction<Traverser> for TestSinkEncoder {
-        fn encode(&self, data: Vec<Traverser>) -> Vec<u8> {
+        fn encode(&self, mut data: Vec<Traverser>) -> Vec<u8> {
             println!("result to encode {:?}", data);
+            if let Some(access) = self.access.as_ref() {
+                if access.add_sink_results(&data).is_err() {
+                    data.clear();
+                }
+            }
+            if let Some(counter) = self.record_counter.as_ref() {
+                counter.fetch_add(data.len(), Ordering::SeqCst);
+            }
+            if let Some(expected_peers) = self.expected_peers {
+                let worker_id = pegasus::get_current_worker().expect("worker id not found");
+                assert_eq!(expected_peers, worker_id.peers);
+            }
+            let mut results = self.results.lock().expect("lock results failure");
+            results.entry(self.job_id).or_default().extend(data);
+            vec![]
+        }
+    }
+
+    impl TestSinkEncoder {
+        /// Check the results of the job sent by the sinks of all its workers, or of all the jobs
+        /// if `None`, e.g. the only job of the test; nothing is checked if no results are sent
+        /// to the sinks, e.g. by the jobs sinking their folds by the compiler
+        fn check_job(&self, job_id: Option<u64>) {
+            let data: Vec<Traverser> = {
+                let mut results = self.results.lock().expect("lock results failure");
+                let data = match job_id {
+                    Some(job_id) => results.remove(&job_id),
+                    None if results.is_empty() => None,
+                    None => Some(results.drain().flat_map(|(_, data)| data).collect()),
+                };
+                match data {
+                    Some(data) => data,
+                    None => return,
+                }
+            };
+            // split the merged traversers, if bulking, to check the results one by one
+            let data: Vec<Traverser> = data
+                .into_iter()
+                .flat_map(|t| {
+                    let bulk = t.get_bulk() as usize;
+                    std::iter::repeat_n(t, bulk)
+                })
+                .collect();
             if self.expected_result_num.is_some() {
                 assert_eq!(self.expected_result_num.unwrap(), data.len());
             }
@@ -192,7 +315,10 @@ pub mod test {
                     }
                 } else if let Some(o) = traverser.get_object() {
                     match o {
-                        Object::Primitive(_) | Object::String(_) | Object::Blob(_) => {
+                        Object::Primitive(_)
+                        | Object::String(_)
+                        | Object::Blob(_)
+                        | Object::Custom(_) => {
                             obj_result.push(o.clone());
                         }
                         Object::DynOwned(x) => {
@@ -267,7 +393,6 @@ pub mod test {
             } else {
                 println!("no expected values specified in test");
             }
-            vec![]
         }
     }
 
@@ -288,11 +413,13 @@ pub mod test {
                 let mut step = graph_step_from(&mut step, self.inner.get_num_servers())?;
                 step.set_num_workers(num_workers);
                 step.set_server_index(self.inner.get_server_index());
+                step.set_partitioner(self.inner.get_partitioner());
                 step.set_requirement(self.requirement);
                 Ok(step.gen_source(Some(worker_id.index as usize)))
             } else {
                 let mut step = graph_step_from(&mut step, self.inner.get_num_servers())?;
                 step.set_server_index(self.inner.get_server_index());
+                step.set_partitioner(self.inner.get_partitioner());
                 step.set_requirement(self.requirement);
                 Ok(step.gen_source(None))
             }
@@ -350,17 +477,33 @@ pub mod test {
         }
 
         fn sink(&self, _res: &[u8]) -> CompileResult<Box<dyn EncodeFunction<Traverser>>> {
-            Ok(Box::new(TestSinkEncoder {
-                expected_ids: self.expected_ids.clone(),
-                expected_values: self.expected_values.clone(),
-                expected_paths: self.expected_path_result.clone(),
-                expected_group_result: self.expected_group_result.clone(),
-                is_ordered: self.is_ordered,
-                property_opt: self.expected_properties.clone(),
-                expected_path_len: self.expected_path_len,
-                expected_tag_props: self.expected_tag_props.clone(),
-                expected_result_num: self.expected_result_num,
-            }))
+            // built by each worker of the job, or by the service if the job runs no workers
+            let job_id = pegasus::get_current_worker().map(|w| w.job_id).unwrap_or(0);
+            Ok(Box::new(self.sink_of(job_id)))
+        }
+
+        fn validate(&self, req: &JobRequest) -> Result<(), PlanError> {
+            self.inner.validate(req)
+        }
+
+        fn features(&self) -> Vec<String> {
+            self.inner.features()
+        }
+
+        fn estimate_workers(&self, graph: &str, src: &[u8]) -> Option<u32> {
+            self.inner.estimate_workers(graph, src)
+        }
+
+        fn source_size(&self, src: &[u8]) -> Option<u64> {
+            self.inner.source_size(src)
+        }
+
+        fn may_split(&self, res: &[u8]) -> bool {
+            self.inner.may_split(res)
+        }
+
+        fn prepare(&self, graph: &str, conf: &mut JobConf) -> Result<(), QueryError> {
+            self.inner.prepare(graph, conf)
         }
     }
 
@@ -415,8 +558,8 @@ pub mod test {
 
     pub fn to_global_id(id: usize) -> DefaultId {
         match id {
-            1 | 2 | 4 | 6 => LDBCVertexParser::to_global_id(id, 0),
-            3 | 5 => LDBCVertexParser::to_global_id(id, 1),
+            1 | 2 | 4 | 6 => LDBCVertexParser::to_global_id(id, 0.into()),
+            3 | 5 => LDBCVertexParser::to_global_id(id, 1.into()),
             _ => unreachable!(),
         }
     }
@@ -442,11 +585,12 @@ pub mod test {
 
     #[cfg(feature = "llong_id")]
     pub fn eids_to_global_ids(edges: Vec<(usize, usize)>) -> Vec<ID> {
+        // the edges of the modern graph in the order they are added, i.e. by their indexes
+        let modern_edges = vec![(1, 2), (1, 3), (1, 4), (4, 3), (4, 5), (6, 3)];
         let mut global_ids = vec![];
-        for (src, dst) in edges {
-            let g_src = to_global_id(src);
-            let g_dst = to_global_id(dst);
-            let eid = ((g_dst as ID) << 64) | (g_src as ID);
+        for edge in edges {
+            let index = modern_edges.iter().position(|e| *e == edge).expect("not an edge");
+            let eid = graph_store::prelude::EdgeId::new(to_global_id(edge.0), index).encode();
             global_ids.push(eid);
         }
         global_ids
@@ -456,27 +600,93 @@ pub mod test {
         Path::new(TEST_PLAN_PATH).join(file.to_string())
     }
 
+    /// The time limit of the queries of the tests, over which a query fails instead of ending with
+    /// the results by then
+    const TEST_TIME_LIMIT_MS: u64 = 60_000;
+
     fn submit_query(service: &Service<Traverser>, mut job_req: JobRequest, num_workers: u32) {
-        let job_id = job_req.conf.clone().expect("no job_conf").job_id;
-        job_req.conf.as_mut().expect("no job_conf").workers = num_workers;
+        let conf = job_req.conf.as_mut().expect("no job_conf");
+        conf.workers = num_workers;
+        if conf.time_limit == 0 || conf.time_limit > TEST_TIME_LIMIT_MS {
+            conf.time_limit = TEST_TIME_LIMIT_MS;
+        }
+        let (job_id, time_limit) = (conf.job_id, conf.time_limit);
         println!("job_id: {}", job_id);
+        let start = Instant::now();
         service.accept(job_req, TestOutputStruct);
         if let Ok(mut job_guards) = service.job_guards.write() {
             if let Some(job_guard) = job_guards.get_mut(&job_id) {
                 job_guard.join().expect("run query failed");
             }
         }
+        // the job timed out is cancelled as finished, with the results by then
+        let elapsed = start.elapsed();
+        assert!(elapsed < Duration::from_millis(time_limit), "job {} timed out", job_id);
     }
 
     pub fn run_test_with_worker_num(
         factory: TestJobFactory, job_request: JobRequest, num_workers: u32,
     ) {
+        let checker = factory.sink_of(0);
         let service = start_test_service(factory);
         submit_query(&service, job_request, num_workers);
+        checker.check_job(None);
+    }
+
+    pub fn run_test_with_job_id(
+        factory: TestJobFactory, mut job_request: JobRequest, job_id: u64, num_workers: u32,
+    ) {
+        job_request.conf.as_mut().expect("no job_conf").job_id = job_id;
+        run_test_with_worker_num(factory, job_request, num_workers);
+    }
+
+    /// Submit all queries before running any of them, so that they run concurrently in the same
+    /// service, and return how each job ends, e.g. the error aborting the job
+    pub fn run_tests_concurrently(
+        factory: TestJobFactory, job_requests: Vec<JobRequest>, num_workers: u32,
+    ) -> Vec<Result<(), String>> {
+        let checker = factory.sink_of(0);
+        let service = start_test_service(factory);
+        let mut job_ids = vec![];
+        for mut job_req in job_requests {
+            let conf = job_req.conf.as_mut().expect("no job_conf");
+            conf.workers = num_workers;
+            job_ids.push(conf.job_id);
+            service.accept(job_req, TestOutputStruct);
+        }
+        let mut job_guards = service.job_guards.write().expect("fetch job guards failed");
+        job_ids
+            .into_iter()
+            .map(|job_id| match job_guards.get_mut(&job_id) {
+                Some(job_guard) => {
+                    job_guard.join().map_err(|e| e.to_string())?;
+                    checker.check_job(Some(job_id));
+                    Ok(())
+                }
+                None => Err(format!("job {} not found", job_id)),
+            })
+            .collect()
+    }
+
+    /// Accept each query from a thread of its own, as the connections of the clients do, so that
+    /// the sources of the queries read by the service are read concurrently, and check the results
+    /// of all of them together
+    pub fn run_sources_concurrently(factory: TestJobFactory, job_requests: Vec<JobRequest>) {
+        let checker = factory.sink_of(0);
+        let service = start_test_service(factory);
+        std::thread::scope(|scope| {
+            for job_req in job_requests {
+                let service = &service;
+                scope.spawn(move || service.accept(job_req, TestOutputStruct));
+            }
+        });
+        checker.check_job(None);
     }
 
     pub fn run_test(factory: TestJobFactory, job_request: JobRequest) {
+        let checker = factory.sink_of(0);
         let service = start_test_service(factory);
         submit_query(&service, job_request, 1);
+        checker.check_job(None);
     }
 }
@@ -0,0 +1,139 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use gremlin_core::compiler::GremlinJobCompiler;
+    use gremlin_core::generated::gremlin as pb;
+    use gremlin_core::process::metrics::{get_worker_counter, SHORTCUT_COUNTER};
+    use gremlin_core::traversal::*;
+    use gremlin_core::{GremlinStepPb, Partition};
+    use pegasus_server::generated::protocol as server_pb;
+    use pegasus_server::service::{Output, Service};
+    use pegasus_server::{JobRequest, JobResponse, JobResult};
+    use prost::Message;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct CollectOutput {
+        results: Arc<Mutex<Vec<JobResult>>>,
+    }
+
+    impl Output for CollectOutput {
+        fn send(&self, res: JobResponse) {
+            if let Some(result) = res.result {
+                self.results.lock().unwrap().push(result);
+            }
+        }
+
+        fn close(&self) {}
+    }
+
+    fn job_conf(job_id: u64) -> server_pb::JobConfig {
+        server_pb::JobConfig {
+            job_id,
+            job_name: "count_shortcut_test".to_owned(),
+            workers: 2,
+            ..Default::default()
+        }
+    }
+
+    // g.V().hasLabel(..).count() or g.E().hasLabel(..).count()
+    fn count_request(job_id: u64, return_type: pb::EntityType, labels: &[i32]) -> JobRequest {
+        let mut request = Graph::traversal().v().count().to_request(job_conf(job_id));
+        let graph_step = pb::GraphStep {
+            labels: labels.to_vec(),
+            return_type: return_type as i32,
+            ..Default::default()
+        };
+        let step = GremlinStepPb {
+            step: Some(pb::gremlin_step::Step::GraphStep(graph_step)),
+            ..Default::default()
+        };
+        let mut bytes = vec![];
+        step.encode(&mut bytes).expect("encode gremlin step failure");
+        request.source = Some(server_pb::Source { resource: bytes });
+        request
+    }
+
+    // the results sent by the service, and if the job is answered without running
+    fn run(request: JobRequest, shortcut: bool) -> (Vec<JobResult>, bool) {
+        initialize();
+        let job_id = request.conf.as_ref().unwrap().job_id;
+        let compiler =
+            GremlinJobCompiler::new(Partition { num_servers: 1 }, 1, 0).with_shortcut(shortcut);
+        let service = Service::new(compiler);
+        let output = CollectOutput::default();
+        service.accept(request, output.clone());
+        if let Some(guard) = service.job_guards.write().unwrap().get_mut(&job_id) {
+            guard.join().expect("job failed");
+        }
+        let results = output.results.lock().unwrap().clone();
+        // the stats of the completion differ between the shortcut and the job
+        let results =
+            results.into_iter().filter(|res| !matches!(res, JobResult::Complete(_))).collect();
+        (results, get_worker_counter(job_id, 0, SHORTCUT_COUNTER) > 0)
+    }
+
+    fn assert_same(fast: JobRequest, slow: JobRequest) {
+        let (fast_results, is_shortcut) = run(fast, true);
+        assert!(is_shortcut);
+        let (slow_results, is_shortcut) = run(slow, false);
+        assert!(!is_shortcut);
+        assert_eq!(fast_results.len(), 1);
+        assert_eq!(fast_results, slow_results);
+    }
+
+    // g.V().count() and g.V().hasLabel("person").count()
+    #[test]
+    fn vertex_count_shortcut_test() {
+        let request = |job_id| Graph::traversal().v().count().to_request(job_conf(job_id));
+        assert_same(request(6080), request(6081));
+        assert_same(
+            count_request(6082, pb::EntityType::Vertex, &[0]),
+            count_request(6083, pb::EntityType::Vertex, &[0]),
+        );
+        assert_same(
+            count_request(6084, pb::EntityType::Vertex, &[1, 1]),
+            count_request(6085, pb::EntityType::Vertex, &[1]),
+        );
+    }
+
+    // g.E().hasLabel("knows").count() and g.E().count(), compared with counting the out edges
+    #[test]
+    fn edge_count_shortcut_test() {
+        let slow = Graph::traversal().v().out_e(&[0]).count().to_request(job_conf(6087));
+        assert_same(count_request(6086, pb::EntityType::Edge, &[0]), slow);
+        let slow = Graph::traversal().v().out_e(&[]).count().to_request(job_conf(6089));
+        assert_same(count_request(6088, pb::EntityType::Edge, &[]), slow);
+    }
+
+    // the plans not only counting the scan are run as jobs
+    #[test]
+    fn no_shortcut_test() {
+        let request = Graph::traversal().v().out(&[]).count().to_request(job_conf(6090));
+        assert!(!run(request, true).1);
+        let request = Graph::traversal().v_ids(&to_global_ids(vec![1])).count();
+        assert!(!run(request.to_request(job_conf(6091)), true).1);
+        let request = Graph::traversal().v().dedup().count().to_request(job_conf(6092));
+        assert!(!run(request, true).1);
+        // a vertex of two labels would be counted twice by the statistics
+        let request = count_request(6093, pb::EntityType::Vertex, &[0, 1]);
+        assert!(!run(request, true).1);
+    }
+}
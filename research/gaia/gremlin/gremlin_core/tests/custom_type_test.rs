@@ -0,0 +1,167 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use dyn_type::stable_hash::xxh64;
+    use dyn_type::{register_custom_predicate, register_custom_type};
+    use dyn_type::{CustomObject, CustomType, Object, STABLE_HASH_SEED};
+    use graph_store::table::Row;
+    use gremlin_core::generated::common as common_pb;
+    use gremlin_core::generated::gremlin as pb;
+    use gremlin_core::structure::codec::pb_chain_to_filter;
+    use gremlin_core::structure::{DefaultDetails, Details, Element, Label, Vertex};
+    use pegasus_common::codec::{Decode, Encode};
+    use std::cmp::Ordering;
+    use std::collections::HashMap;
+    use std::io;
+
+    // a 2D point, as a custom property type of the application
+    struct Point {
+        x: f64,
+        y: f64,
+    }
+
+    impl CustomType for Point {
+        const TAG: &'static str = "point";
+
+        fn encode(&self) -> Vec<u8> {
+            let mut bytes = self.x.to_le_bytes().to_vec();
+            bytes.extend_from_slice(&self.y.to_le_bytes());
+            bytes
+        }
+
+        fn decode(bytes: &[u8]) -> io::Result<Self> {
+            if bytes.len() != 16 {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "a point is of 16 bytes"));
+            }
+            let mut x = [0u8; 8];
+            let mut y = [0u8; 8];
+            x.copy_from_slice(&bytes[0..8]);
+            y.copy_from_slice(&bytes[8..16]);
+            Ok(Point { x: f64::from_le_bytes(x), y: f64::from_le_bytes(y) })
+        }
+
+        fn compare(&self, other: &Self) -> Option<Ordering> {
+            match self.x.partial_cmp(&other.x)? {
+                Ordering::Equal => self.y.partial_cmp(&other.y),
+                ord => Some(ord),
+            }
+        }
+
+        fn hash(&self) -> u64 {
+            xxh64(&self.encode(), STABLE_HASH_SEED)
+        }
+    }
+
+    fn point(x: f64, y: f64) -> CustomObject {
+        CustomObject::from_value(&Point { x, y })
+    }
+
+    fn register() {
+        register_custom_type::<Point>().expect("register point failure");
+        register_custom_predicate("within_radius", |p: &Point, center: &Point| {
+            (p.x - center.x).hypot(p.y - center.y) <= 1.5
+        })
+        .expect("register within_radius failure");
+    }
+
+    // the vertices with the locations loaded from the stored rows of their properties
+    fn load_vertices() -> Vec<Vertex> {
+        let locations = vec![("a", 0.0, 0.0), ("b", 1.0, 1.0), ("c", 3.0, 0.0), ("d", 0.0, -1.0)];
+        let mut vertices = vec![];
+        for (id, (name, x, y)) in locations.into_iter().enumerate() {
+            let row = Row::from(vec![Object::from(name), Object::Custom(point(x, y))]);
+            let mut bytes = vec![];
+            row.write_to(&mut bytes).expect("encode row failure");
+            let row = Row::read_from(&mut &bytes[0..]).expect("decode row failure");
+            let mut properties = HashMap::new();
+            properties.insert("name".to_owned(), row.get(0).unwrap().try_to_owned().unwrap());
+            properties.insert("location".to_owned(), row.get(1).unwrap().try_to_owned().unwrap());
+            let label = Label::Str("place".to_owned());
+            let details = DefaultDetails::new_with_prop(id as _, label.clone(), properties);
+            vertices.push(Vertex::new(id as _, Some(label), details));
+        }
+        vertices
+    }
+
+    fn chain(cmp: pb::Compare, value: CustomObject, predicate: &str) -> pb::FilterChain {
+        let exp = pb::FilterExp {
+            left: Some(common_pb::Key {
+                item: Some(common_pb::key::Item::Name("location".to_owned())),
+            }),
+            cmp: cmp as i32,
+            right: Some(common_pb::Value {
+                item: Some(common_pb::value::Item::Custom(common_pb::CustomValue {
+                    tag: value.tag().to_owned(),
+                    bytes: value.bytes().to_vec(),
+                })),
+            }),
+            epsilon: 0.0,
+            predicate: predicate.to_owned(),
+        };
+        let node = pb::FilterNode {
+            inner: Some(pb::filter_node::Inner::Single(exp)),
+            next: pb::Connect::And as i32,
+        };
+        pb::FilterChain { node: vec![node] }
+    }
+
+    fn filter_names(chain: pb::FilterChain) -> Vec<String> {
+        let filter = pb_chain_to_filter::<Vertex>(&chain).expect("parse filter failure").unwrap();
+        load_vertices()
+            .iter()
+            .filter(|v| filter.test(v) == Some(true))
+            .map(|v| {
+                let name = v.details().get_property("name").unwrap();
+                name.as_str().unwrap().into_owned()
+            })
+            .collect()
+    }
+
+    // has("location", custom("within_radius", point(0, 0)))
+    #[test]
+    fn custom_predicate_test() {
+        initialize();
+        register();
+        let names = filter_names(chain(pb::Compare::Custom, point(0.0, 0.0), "within_radius"));
+        assert_eq!(names, vec!["a", "b", "d"]);
+    }
+
+    // has("location", point(1, 1)) and has("location", lt(point(1, 0))) by the registered order
+    #[test]
+    fn custom_compare_test() {
+        initialize();
+        register();
+        assert_eq!(filter_names(chain(pb::Compare::Eq, point(1.0, 1.0), "")), vec!["b"]);
+        assert_eq!(filter_names(chain(pb::Compare::Ne, point(1.0, 1.0), "")), vec!["a", "c", "d"]);
+        assert_eq!(filter_names(chain(pb::Compare::Lt, point(1.0, 0.0), "")), vec!["a", "d"]);
+    }
+
+    #[test]
+    fn unregistered_custom_test() {
+        initialize();
+        register();
+        let ip = CustomObject::new("ip_address", vec![127, 0, 0, 1]);
+        let err = pb_chain_to_filter::<Vertex>(&chain(pb::Compare::Eq, ip, "")).err().unwrap();
+        assert!(err.to_string().contains("`ip_address`"), "{}", err);
+        let nearest = chain(pb::Compare::Custom, point(0.0, 0.0), "nearest");
+        let err = pb_chain_to_filter::<Vertex>(&nearest).err().unwrap();
+        assert!(err.to_string().contains("`nearest`"), "{}", err);
+    }
+}
@@ -0,0 +1,116 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use dyn_type::Object;
+    use graph_store::ldbc::{GraphLoader, LDBCVertexParser};
+    use graph_store::prelude::{DanglingEdgePolicy, DefaultId, InternalId};
+    use gremlin_core::traversal::*;
+    use gremlin_core::{create_graph, register_named_graph, ID};
+    use pegasus_server::generated::protocol as server_pb;
+    use std::sync::Once;
+
+    static REGISTER: Once = Once::new();
+
+    const SCHEMA: &str = r#"
+    {
+      "vertex_type_map": { "PERSON": 0 },
+      "edge_type_map": { "KNOWS": 0 },
+      "vertex_prop": {
+        "PERSON": [["id", "ID"], ["name", "String"]]
+      },
+      "edge_prop": {
+        "KNOWS": [["start_id", "ID"], ["end_id", "ID"]]
+      }
+    }
+    "#;
+
+    // the persons alice and bob, where alice knows bob and the absent person 3, which is loaded as
+    // a placeholder
+    fn register_dangling_graph() {
+        initialize();
+        REGISTER.call_once(|| {
+            let temp = tempdir::TempDir::new("dangling_edge_test").expect("no temp dir");
+            let raw_dir = temp.path().join("raw");
+            std::fs::create_dir_all(&raw_dir).unwrap();
+            std::fs::write(raw_dir.join("person_0_0.csv"), "1|alice\n2|bob\n").unwrap();
+            std::fs::write(raw_dir.join("person_knows_person_0_0.csv"), "1|2\n1|3\n").unwrap();
+            let schema_file = temp.path().join("schema.json");
+            std::fs::write(&schema_file, SCHEMA).unwrap();
+            let root_dir = temp.path().join("graph");
+            let mut loader = GraphLoader::<DefaultId, InternalId>::new(
+                &raw_dir,
+                &root_dir,
+                &schema_file,
+                20,
+                0,
+                1,
+            )
+            .with_dangling_edge_policy(DanglingEdgePolicy::CreatePlaceholder);
+            loader.load().expect("load failed");
+            assert_eq!(loader.num_placeholders(), 1);
+            register_named_graph("dangling", create_graph(loader.into_graph()));
+        });
+    }
+
+    fn job_conf(job_id: u64) -> server_pb::JobConfig {
+        server_pb::JobConfig {
+            job_id,
+            job_name: "dangling_edge_test".to_owned(),
+            workers: 2,
+            ..Default::default()
+        }
+    }
+
+    fn alice() -> GraphTraversal {
+        let alice: DefaultId = LDBCVertexParser::to_global_id(1, 0.into());
+        Graph::traversal().v_ids(&[alice as ID]).on_graph("dangling")
+    }
+
+    // g.V(alice).out().count(), where the placeholder is traversed as the other vertices
+    #[test]
+    fn placeholder_out_test() {
+        register_dangling_graph();
+        let results: Vec<_> = alice().out(&[]).count().run(job_conf(6350)).collect();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].as_ref().expect("count failed").as_u64().unwrap(), 2);
+    }
+
+    // g.V(alice).out().values("name"), where the placeholder has no properties
+    #[test]
+    fn placeholder_values_test() {
+        register_dangling_graph();
+        let results: Vec<Object> = alice()
+            .out(&[])
+            .values(&["name"])
+            .run(job_conf(6351))
+            .map(|r| r.expect("traversal failed"))
+            .collect();
+        assert_eq!(results, vec![Object::from("bob")]);
+    }
+
+    // g.V(alice).out().has("name", eq("bob")), which never matches the placeholder
+    #[test]
+    fn placeholder_has_test() {
+        register_dangling_graph();
+        let results: Vec<_> =
+            alice().out(&[]).has("name", eq("bob")).count().run(job_conf(6352)).collect();
+        assert_eq!(results[0].as_ref().expect("count failed").as_u64().unwrap(), 1);
+    }
+}
@@ -18,6 +18,28 @@ mod common;
 #[cfg(test)]
 mod test {
     use crate::common::test::*;
+    use gremlin_core::traversal::*;
+    use gremlin_core::ID;
+    use pegasus_server::generated::protocol as server_pb;
+
+    fn job_conf(job_id: u64) -> server_pb::JobConfig {
+        server_pb::JobConfig {
+            job_id,
+            job_name: "dedup_test".to_owned(),
+            workers: 2,
+            ..Default::default()
+        }
+    }
+
+    fn run_ids(traversal: GraphTraversal, job_id: u64) -> Vec<ID> {
+        initialize();
+        let mut ids: Vec<ID> = traversal
+            .run(job_conf(job_id))
+            .map(|r| r.expect("traversal failed").as_u128().expect("not an id") as ID)
+            .collect();
+        ids.sort();
+        ids
+    }
 
     #[test]
     // g.V().union(identity(),identity()).dedup()
@@ -29,4 +51,20 @@ mod test {
         let pb_request = read_pb_request(gen_path("dedup_step_test_01")).expect("read pb failed");
         run_test(test_job_factory, pb_request);
     }
+
+    // g.V().both().dedup() by the hint of 100 distinct vertices, where the few vertices of the
+    // modern graph are never taken as duplicates by the Bloom filter of 1% false positives
+    #[test]
+    fn dedup_approx_test() {
+        let traversal = Graph::traversal().v().both(&[]).dedup_approx(100, 0.01);
+        let request = traversal.to_request(job_conf(6389));
+        assert!(request.features.contains(&APPROX_DEDUP_FEATURE.to_owned()));
+        let exact = Graph::traversal().v().both(&[]).dedup();
+        assert!(exact.to_request(job_conf(6389)).features.is_empty());
+        let ids = run_ids(traversal, 6389);
+        assert_eq!(ids, run_ids(exact, 6390));
+        let mut expected = to_global_ids(vec![1, 2, 3, 4, 5, 6]);
+        expected.sort();
+        assert_eq!(ids, expected);
+    }
 }
@@ -0,0 +1,97 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use dyn_type::Object;
+    use gremlin_core::traversal::*;
+    use pegasus_server::generated::protocol as server_pb;
+
+    fn job_conf(job_id: u64) -> server_pb::JobConfig {
+        server_pb::JobConfig {
+            job_id,
+            job_name: "degree_test".to_owned(),
+            workers: 2,
+            ..Default::default()
+        }
+    }
+
+    fn run_embedded(traversal: GraphTraversal, job_id: u64) -> Vec<Object> {
+        traversal.run(job_conf(job_id)).map(|r| r.expect("traversal failed")).collect()
+    }
+
+    // the degree of each vertex, against the count of its expanded edges
+    fn degree_test(
+        degree: fn(GraphTraversal, &[i32]) -> GraphTraversal,
+        expand: fn(GraphTraversal, &[i32]) -> GraphTraversal, labels: &[i32], job_id: u64,
+    ) -> Vec<u64> {
+        initialize();
+        let mut degrees = vec![];
+        for (i, id) in to_global_ids(vec![1, 2, 3, 4, 5, 6]).into_iter().enumerate() {
+            let job_id = job_id + 2 * i as u64;
+            let traversal = degree(Graph::traversal().v_ids(&[id]), labels);
+            let result = run_embedded(traversal, job_id);
+            let traversal = expand(Graph::traversal().v_ids(&[id]), labels).count();
+            let mut expected = run_embedded(traversal, job_id + 1);
+            // the count of no edges gives nothing, where the degree is 0
+            if expected.is_empty() {
+                expected.push(Object::from(0i64));
+            }
+            assert_eq!(result, expected, "degree of vertex {}", id);
+            degrees.push(result[0].as_u64().unwrap());
+        }
+        degrees
+    }
+
+    #[test]
+    fn out_degree_test() {
+        let degrees = degree_test(GraphTraversal::out_degree, GraphTraversal::out_e, &[], 6100);
+        assert_eq!(degrees, vec![3, 0, 0, 2, 0, 1]);
+    }
+
+    #[test]
+    fn in_degree_test() {
+        let degrees = degree_test(GraphTraversal::in_degree, GraphTraversal::in_e, &[], 6120);
+        assert_eq!(degrees, vec![0, 1, 3, 1, 1, 0]);
+    }
+
+    #[test]
+    fn both_degree_test() {
+        let degrees = degree_test(GraphTraversal::both_degree, GraphTraversal::both_e, &[], 6140);
+        assert_eq!(degrees, vec![3, 1, 3, 3, 1, 1]);
+    }
+
+    #[test]
+    fn degree_with_label_test() {
+        // the knows edges
+        let degrees = degree_test(GraphTraversal::out_degree, GraphTraversal::out_e, &[0], 6160);
+        assert_eq!(degrees, vec![2, 0, 0, 0, 0, 0]);
+    }
+
+    // g.V().order().by(outE().count(), desc).limit(2)
+    #[test]
+    fn order_by_degree_test() {
+        initialize();
+        let traversal =
+            Graph::traversal().v().order_by(Graph::anonymous().out_degree(&[]), true).limit(2);
+        let result = run_embedded(traversal, 6180);
+        let expected: Vec<Object> =
+            to_global_ids(vec![1, 4]).into_iter().map(Object::from).collect();
+        assert_eq!(result, expected);
+    }
+}
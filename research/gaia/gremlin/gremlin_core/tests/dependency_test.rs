@@ -0,0 +1,108 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use dyn_type::Object;
+    use gremlin_core::traversal::*;
+    use gremlin_core::{DynResult, ID};
+    use pegasus_server::generated::protocol as server_pb;
+
+    fn job_conf(job_id: u64) -> server_pb::JobConfig {
+        initialize();
+        server_pb::JobConfig {
+            job_id,
+            job_name: "dependency_test".to_owned(),
+            workers: 2,
+            ..Default::default()
+        }
+    }
+
+    fn kept(job_id: u64) -> server_pb::JobConfig {
+        server_pb::JobConfig { keep_output: true, ..job_conf(job_id) }
+    }
+
+    fn depending(job_id: u64, depends_on: u64) -> server_pb::JobConfig {
+        server_pb::JobConfig { depends_on, ..job_conf(job_id) }
+    }
+
+    fn expect_error(results: Vec<DynResult<Object>>, msg: &str) {
+        let err = results.into_iter().find_map(|r| r.err()).expect("traversal should fail");
+        assert!(err.to_string().contains(msg), "unexpected error: {}", err);
+    }
+
+    fn expect_values(results: Vec<DynResult<Object>>) -> Vec<Object> {
+        let mut values =
+            results.into_iter().map(|r| r.expect("traversal failed")).collect::<Vec<_>>();
+        values.sort_by(|a, b| a.partial_cmp(b).expect("incomparable values"));
+        values
+    }
+
+    // g.V(1).out(), whose output is kept
+    fn marko_out() -> GraphTraversal {
+        Graph::traversal().v_ids(&[to_global_id(1) as ID]).out(&[])
+    }
+
+    // g.V().out().values("name"), run on the output of g.V(1).out() instead of all the vertices
+    fn out_names() -> GraphTraversal {
+        Graph::traversal().v().out(&[]).values(&["name"])
+    }
+
+    #[test]
+    fn run_on_output_test() {
+        assert_eq!(expect_values(marko_out().run(kept(6509)).collect()).len(), 3);
+        let names = expect_values(out_names().run(depending(6510, 6509)).collect());
+        assert_eq!(names, vec![Object::from("lop"), Object::from("ripple")]);
+        // the output is kept for any job depending on it, with each worker reading all of it
+        let conf = server_pb::JobConfig {
+            dependency_input: server_pb::DependencyInput::Broadcast as i32,
+            ..depending(6511, 6509)
+        };
+        let names = expect_values(out_names().run(conf).collect());
+        let expected = vec!["lop", "lop", "ripple", "ripple"];
+        assert_eq!(names, expected.into_iter().map(Object::from).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn queued_for_dependency_test() {
+        // the job is queued until the job it depends on, submitted after it, completes
+        let dependent = out_names().run(depending(6512, 6513));
+        assert_eq!(expect_values(marko_out().run(kept(6513)).collect()).len(), 3);
+        let names = expect_values(dependent.collect());
+        assert_eq!(names, vec![Object::from("lop"), Object::from("ripple")]);
+    }
+
+    #[test]
+    fn failed_dependency_test() {
+        // the traversal referring to the results of a session out of any session fails
+        let failed = Graph::traversal().session_ref("x");
+        expect_error(failed.run(kept(6514)).collect(), "out of sessions");
+        expect_error(
+            out_names().run(depending(6515, 6514)).collect(),
+            "dependency failed: job 6514",
+        );
+
+        let conf = server_pb::JobConfig { dependency_wait_ms: 200, ..depending(6516, 6599) };
+        expect_error(out_names().run(conf).collect(), "not completed within 200 ms");
+    }
+
+    #[test]
+    fn cyclic_dependency_test() {
+        expect_error(out_names().run(depending(6517, 6517)).collect(), "cyclic dependency");
+    }
+}
@@ -0,0 +1,71 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use gremlin_core::compiler::GremlinJobCompiler;
+    use gremlin_core::traversal::*;
+    use gremlin_core::{get_detach_fetch_count, Detach, Partition};
+    use pegasus_server::generated::protocol as server_pb;
+    use pegasus_server::service::{Output, Service};
+    use pegasus_server::{JobResponse, JobResult};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct CountBatches {
+        batches: Arc<Mutex<usize>>,
+    }
+
+    impl Output for CountBatches {
+        fn send(&self, res: JobResponse) {
+            if let Some(JobResult::Data(_)) = res.result {
+                *self.batches.lock().unwrap() += 1;
+            }
+        }
+
+        fn close(&self) {}
+    }
+
+    // g.V().both().both(), whose vertices are fetched with their properties once for each batch of
+    // results, instead of once for each vertex
+    #[test]
+    fn detach_fetch_by_batch_test() {
+        initialize();
+        let job_id = 6325;
+        let conf = server_pb::JobConfig {
+            job_id,
+            job_name: "detach_fetch_test".to_owned(),
+            workers: 2,
+            batch_size: 16,
+            ..Default::default()
+        };
+        let traversal = Graph::traversal().v().both(&[]).both(&[]).detach(Detach::Full);
+        let service = Service::new(GremlinJobCompiler::new(Partition { num_servers: 1 }, 1, 0));
+        let output = CountBatches::default();
+        let fetches = get_detach_fetch_count();
+        service.accept(traversal.to_request(conf), output.clone());
+        if let Some(guard) = service.job_guards.write().unwrap().get_mut(&job_id) {
+            guard.join().expect("job failed");
+        }
+        let fetches = get_detach_fetch_count() - fetches;
+        let batches = *output.batches.lock().unwrap();
+        // the 30 results of g.V().both().both() are encoded by batches of both workers
+        assert!(batches > 0 && batches < 30, "the results are sent by {} batches", batches);
+        assert!(fetches > 0 && fetches as usize <= batches, "fetched {} times", fetches);
+    }
+}
@@ -0,0 +1,181 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use gremlin_core::compiler::GremlinJobCompiler;
+    use gremlin_core::generated::common as common_pb;
+    use gremlin_core::generated::protobuf as result_pb;
+    use gremlin_core::traversal::*;
+    use gremlin_core::{Detach, Partition, ID};
+    use pegasus_server::generated::protocol as server_pb;
+    use pegasus_server::service::{Output, Service};
+    use pegasus_server::{JobResponse, JobResult};
+    use prost::Message;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct CollectOutput {
+        results: Arc<Mutex<Vec<JobResult>>>,
+    }
+
+    impl Output for CollectOutput {
+        fn send(&self, res: JobResponse) {
+            if let Some(result) = res.result {
+                self.results.lock().unwrap().push(result);
+            }
+        }
+
+        fn close(&self) {}
+    }
+
+    fn run(traversal: GraphTraversal, job_id: u64, workers: u32) -> Vec<result_pb::GraphElement> {
+        initialize();
+        let conf = server_pb::JobConfig {
+            job_id,
+            job_name: "detach_test".to_owned(),
+            workers,
+            batch_size: 2,
+            ..Default::default()
+        };
+        let service = Service::new(GremlinJobCompiler::new(Partition { num_servers: 1 }, 1, 0));
+        let output = CollectOutput::default();
+        service.accept(traversal.to_request(conf), output.clone());
+        if let Some(guard) = service.job_guards.write().unwrap().get_mut(&job_id) {
+            guard.join().expect("job failed");
+        }
+        let mut elements = vec![];
+        for result in output.results.lock().unwrap().iter() {
+            if let JobResult::Data(bytes) = result {
+                let result = result_pb::Result::decode(&bytes[0..]).expect("decode result failure");
+                match result.inner {
+                    Some(result_pb::result::Inner::Elements(e)) => elements.extend(e.item),
+                    other => panic!("unexpected result {:?}", other),
+                }
+            }
+        }
+        elements
+    }
+
+    fn run_vertices(
+        traversal: GraphTraversal, job_id: u64, workers: u32,
+    ) -> Vec<result_pb::Vertex> {
+        let mut vertices: Vec<_> = run(traversal, job_id, workers)
+            .into_iter()
+            .map(|e| match e.inner {
+                Some(result_pb::graph_element::Inner::Vertex(v)) => v,
+                other => panic!("not a vertex {:?}", other),
+            })
+            .collect();
+        vertices.sort_by_key(|v| v.id);
+        vertices
+    }
+
+    fn keys(properties: &[result_pb::Property]) -> Vec<&str> {
+        properties.iter().map(|p| p.key.as_str()).collect()
+    }
+
+    fn value(properties: &[result_pb::Property], key: &str) -> Option<common_pb::value::Item> {
+        properties.iter().find(|p| p.key == key).and_then(|p| p.value.clone()?.item)
+    }
+
+    fn marko() -> GraphTraversal {
+        Graph::traversal().v_ids(&[to_global_id(1) as ID])
+    }
+
+    // g.V(1), as by default
+    #[test]
+    fn detach_reference_test() {
+        let vertices = run_vertices(marko(), 6318, 1);
+        assert_eq!(vertices.len(), 1);
+        assert_eq!(vertices[0].id, to_global_id(1) as i64);
+        assert_eq!(vertices[0].label, "0");
+        assert!(vertices[0].properties.is_empty());
+    }
+
+    #[test]
+    fn detach_id_test() {
+        let vertices = run_vertices(marko().detach(Detach::Id), 6319, 1);
+        assert_eq!(vertices[0].id, to_global_id(1) as i64);
+        assert!(vertices[0].label.is_empty());
+        assert!(vertices[0].properties.is_empty());
+    }
+
+    #[test]
+    fn detach_full_test() {
+        let vertices = run_vertices(marko().detach(Detach::Full), 6320, 1);
+        assert_eq!(vertices[0].label, "0");
+        // all properties in the order of their keys
+        assert_eq!(keys(&vertices[0].properties), vec!["age", "id", "name"]);
+        let name = value(&vertices[0].properties, "name");
+        assert_eq!(name, Some(common_pb::value::Item::Str("marko".to_owned())));
+        assert_eq!(value(&vertices[0].properties, "age"), Some(common_pb::value::Item::I32(29)));
+    }
+
+    #[test]
+    fn detach_custom_test() {
+        let detach = Detach::Custom(vec!["name".to_owned(), "lang".to_owned()]);
+        let vertices = run_vertices(marko().detach(detach), 6321, 1);
+        // the keys the vertex has not are skipped
+        assert_eq!(keys(&vertices[0].properties), vec!["name"]);
+    }
+
+    // g.V().out(), where the vertices lose their properties on being exchanged across the workers,
+    // so they are fetched from the graph once they reach the sink
+    #[test]
+    fn detach_custom_w2_test() {
+        let detach = Detach::Custom(vec!["lang".to_owned(), "name".to_owned()]);
+        let traversal = Graph::traversal().v().out(&[]).detach(detach);
+        let vertices = run_vertices(traversal, 6322, 2);
+        let mut names: Vec<_> = vertices
+            .iter()
+            .map(|v| match value(&v.properties, "name") {
+                Some(common_pb::value::Item::Str(name)) => name,
+                other => panic!("name of {} is {:?}", v.id, other),
+            })
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["josh", "lop", "lop", "lop", "ripple", "vadas"]);
+        for v in vertices.iter() {
+            match value(&v.properties, "name") {
+                Some(common_pb::value::Item::Str(name)) if name == "lop" || name == "ripple" => {
+                    assert_eq!(keys(&v.properties), vec!["lang", "name"]);
+                }
+                _ => assert_eq!(keys(&v.properties), vec!["name"]),
+            }
+        }
+    }
+
+    // g.V().outE(), where the edges hold all their properties
+    #[test]
+    fn detach_edge_w2_test() {
+        for (i, detach) in vec![Detach::Reference, Detach::Full].into_iter().enumerate() {
+            let full = detach == Detach::Full;
+            let traversal = Graph::traversal().v().out_e(&[]).detach(detach);
+            let elements = run(traversal, 6323 + i as u64, 2);
+            assert_eq!(elements.len(), 6);
+            for e in elements {
+                let edge = match e.inner {
+                    Some(result_pb::graph_element::Inner::Edge(edge)) => edge,
+                    other => panic!("not an edge {:?}", other),
+                };
+                assert_eq!(value(&edge.properties, "weight").is_some(), full);
+            }
+        }
+    }
+}
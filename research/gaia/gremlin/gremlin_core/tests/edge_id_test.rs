@@ -0,0 +1,116 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use dyn_type::Object;
+    use gremlin_core::traversal::*;
+    use gremlin_core::ID;
+    use pegasus_server::generated::protocol as server_pb;
+
+    fn job_conf(job_id: u64) -> server_pb::JobConfig {
+        server_pb::JobConfig {
+            job_id,
+            job_name: "edge_id_test".to_owned(),
+            workers: 2,
+            ..Default::default()
+        }
+    }
+
+    fn run_embedded(traversal: GraphTraversal, job_id: u64) -> Vec<Object> {
+        traversal.run(job_conf(job_id)).map(|r| r.expect("traversal failed")).collect()
+    }
+
+    fn to_ids(results: Vec<Object>) -> Vec<ID> {
+        let mut ids: Vec<ID> =
+            results.iter().map(|o| o.as_u128().expect("not an id") as ID).collect();
+        ids.sort();
+        ids
+    }
+
+    fn to_weights(results: Vec<Object>) -> Vec<f64> {
+        let mut weights: Vec<f64> =
+            results.iter().map(|o| o.as_f64().expect("not a weight")).collect();
+        weights.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        weights
+    }
+
+    // the ids of the edges by g.V().outE()
+    fn all_edge_ids(job_id: u64) -> Vec<ID> {
+        let mut ids = to_ids(run_embedded(Graph::traversal().v().out_e(&[]), job_id));
+        ids.dedup();
+        ids
+    }
+
+    // g.E(ids), with the edges fetched by g.V().outE() and fetched again by their ids
+    #[test]
+    fn edge_id_round_trip_test() {
+        initialize();
+        let ids = all_edge_ids(6359);
+        let expected = eids_to_global_ids(vec![(1, 2), (1, 3), (1, 4), (4, 3), (4, 5), (6, 3)]);
+        let mut expected_ids = expected.clone();
+        expected_ids.dedup();
+        assert_eq!(ids, expected_ids);
+
+        let result = to_ids(run_embedded(Graph::traversal().e_ids(&ids), 6360));
+        assert_eq!(result, expected);
+        let weights = run_embedded(Graph::traversal().e_ids(&ids).values(&["weight"]), 6361);
+        let traversal = Graph::traversal().v().out_e(&[]).values(&["weight"]);
+        let expected_weights = run_embedded(traversal, 6362);
+        assert_eq!(to_weights(weights), to_weights(expected_weights));
+        // each id fetches the edges of the id only
+        for (i, id) in ids.iter().enumerate() {
+            let result = to_ids(run_embedded(Graph::traversal().e_ids(&[*id]), 6363 + i as u64));
+            let count = expected.iter().filter(|e| *e == id).count();
+            assert_eq!(result, vec![*id; count]);
+        }
+    }
+
+    // the ids of no edge are skipped
+    #[test]
+    fn edge_id_absent_test() {
+        initialize();
+        let ids = all_edge_ids(6370);
+        // the vertex 2 has no outgoing edges
+        let mut with_absent = vec![to_global_id(2) as ID, to_global_id(1) as ID + 1000];
+        with_absent.extend(ids.iter().cloned());
+        let result = to_ids(run_embedded(Graph::traversal().e_ids(&with_absent), 6371));
+        let expected = to_ids(run_embedded(Graph::traversal().e_ids(&ids), 6372));
+        assert_eq!(result, expected);
+        assert!(run_embedded(Graph::traversal().e_ids(&with_absent[0..2]), 6373).is_empty());
+    }
+
+    // g.E() scans the edges with their start vertices
+    #[test]
+    fn edge_scan_test() {
+        initialize();
+        let result = to_ids(run_embedded(Graph::traversal().e(), 6374));
+        let expected = to_ids(run_embedded(Graph::traversal().v().out_e(&[]), 6375));
+        assert_eq!(result, expected);
+        let result = run_embedded(Graph::traversal().e().count(), 6376);
+        assert_eq!(result, vec![Object::from(6u64)]);
+    }
+
+    // the edges are told apart by their ids, including those of the same start vertex
+    #[cfg(feature = "llong_id")]
+    #[test]
+    fn edge_id_unique_test() {
+        initialize();
+        assert_eq!(all_edge_ids(6377).len(), 6);
+    }
+}
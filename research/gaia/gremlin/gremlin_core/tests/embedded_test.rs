@@ -0,0 +1,142 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use dyn_type::Object;
+    use gremlin_core::traversal::*;
+    use gremlin_core::ID;
+    use pegasus_server::generated::protocol as server_pb;
+
+    fn job_conf(job_id: u64, workers: u32) -> server_pb::JobConfig {
+        server_pb::JobConfig {
+            job_id,
+            job_name: "embedded_test".to_owned(),
+            workers,
+            ..Default::default()
+        }
+    }
+
+    fn run_embedded(traversal: GraphTraversal, job_id: u64, workers: u32) -> Vec<Object> {
+        initialize();
+        traversal.run(job_conf(job_id, workers)).map(|r| r.expect("traversal failed")).collect()
+    }
+
+    fn to_sorted_ids(results: Vec<Object>) -> Vec<ID> {
+        let mut ids: Vec<ID> =
+            results.iter().map(|o| o.as_u128().expect("cannot cast to u128") as ID).collect();
+        ids.sort();
+        ids
+    }
+
+    // compare the vertices of an embedded traversal with the query plan compiled from gremlin
+    fn compare_ids_with_plan(traversal: GraphTraversal, plan: &str, job_id: u64) {
+        let ids = to_sorted_ids(run_embedded(traversal, job_id, 2));
+        assert!(!ids.is_empty());
+        let pb_request = read_pb_request(gen_path(plan)).expect("read pb failed");
+        let test_job_factory = TestJobFactory::with_expect_ids(ids);
+        run_test_with_job_id(test_job_factory, pb_request, job_id + 100, 2);
+    }
+
+    // compare the values of an embedded traversal with its job request submitted to the service
+    fn compare_values_with_request(traversal: GraphTraversal, expected: Vec<Object>, job_id: u64) {
+        let pb_request = traversal.to_request(job_conf(job_id + 100, 1));
+        let values = run_embedded(traversal, job_id, 1);
+        assert_eq!(values, expected);
+        let test_job_factory = TestJobFactory::with_expect_values(values);
+        run_test_with_job_id(test_job_factory, pb_request, job_id + 100, 1);
+    }
+
+    // g.V().out()
+    #[test]
+    fn embedded_out_test() {
+        compare_ids_with_plan(Graph::traversal().v().out(&[]), "out_step_test_01", 6030);
+    }
+
+    // g.V().in()
+    #[test]
+    fn embedded_in_test() {
+        compare_ids_with_plan(Graph::traversal().v().in_(&[]), "in_step_test_01", 6031);
+    }
+
+    // g.V().both()
+    #[test]
+    fn embedded_both_test() {
+        compare_ids_with_plan(Graph::traversal().v().both(&[]), "both_step_test_01", 6032);
+    }
+
+    // g.V().has("name", "marko")
+    #[test]
+    fn embedded_has_test_01() {
+        let traversal = Graph::traversal().v().has("name", eq("marko"));
+        compare_ids_with_plan(traversal, "has_step_test_03", 6033);
+    }
+
+    // g.V().has("id", neq(1))
+    #[test]
+    fn embedded_has_test_02() {
+        let traversal = Graph::traversal().v().has("id", neq(1));
+        compare_ids_with_plan(traversal, "has_step_test_04", 6034);
+    }
+
+    // g.V().values("id")
+    #[test]
+    fn embedded_values_test() {
+        let values = run_embedded(Graph::traversal().v().values(&["id"]), 6035, 1);
+        let pb_request = read_pb_request(gen_path("values_step_test_01")).expect("read pb failed");
+        let test_job_factory = TestJobFactory::with_expect_values(values);
+        run_test_with_job_id(test_job_factory, pb_request, 6135, 1);
+    }
+
+    // g.V().has("name", "marko").out().values("age")
+    #[test]
+    fn embedded_has_out_values_test() {
+        let traversal = Graph::traversal().v().has("name", eq("marko")).out(&[0]).values(&["age"]);
+        let mut ages = run_embedded(traversal, 6036, 2);
+        ages.sort_by(|a, b| a.partial_cmp(b).expect("incomparable ages"));
+        assert_eq!(ages, vec![Object::from(27), Object::from(32)]);
+    }
+
+    // g.V().out().dedup().count()
+    #[test]
+    fn embedded_dedup_count_test() {
+        let traversal = Graph::traversal().v().out(&[]).dedup().count();
+        compare_values_with_request(traversal, vec![4u64.into()], 6037);
+    }
+
+    // g.V().out().limit(2).count()
+    #[test]
+    fn embedded_limit_count_test() {
+        let traversal = Graph::traversal().v().out(&[]).limit(2).count();
+        compare_values_with_request(traversal, vec![2u64.into()], 6038);
+    }
+
+    // g.V().outE().out() is rejected before running, as out() never applies to the edges
+    #[test]
+    fn embedded_mistyped_plan_test() {
+        initialize();
+        let traversal = Graph::traversal().v().out_e(&[]).out(&[]);
+        let results: Vec<_> = traversal.run(job_conf(6039, 2)).collect();
+        assert_eq!(results.len(), 1);
+        let err = results[0].as_ref().expect_err("mistyped plan is accepted").to_string();
+        assert!(err.contains("out() requires vertices, but the traversers are edges from outE()"));
+        // g.V().outE().count() as a correct plan
+        let traversal = Graph::traversal().v().out_e(&[]).count();
+        assert_eq!(run_embedded(traversal, 6040, 2), vec![6u64.into()]);
+    }
+}
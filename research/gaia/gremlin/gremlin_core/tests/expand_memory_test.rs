@@ -0,0 +1,159 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use dyn_type::Object;
+    use graph_store::ldbc::{GraphLoader, LDBCVertexParser};
+    use graph_store::prelude::{DefaultId, InternalId};
+    use gremlin_core::traversal::*;
+    use gremlin_core::{create_graph, register_named_graph, ID};
+    use pegasus_server::generated::protocol as server_pb;
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Track the bytes allocated and not yet freed by all threads, and the most of them
+    struct PeakAlloc;
+
+    static LIVE: AtomicUsize = AtomicUsize::new(0);
+    static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+    fn grow(bytes: usize) {
+        let live = LIVE.fetch_add(bytes, Ordering::Relaxed) + bytes;
+        PEAK.fetch_max(live, Ordering::Relaxed);
+    }
+
+    unsafe impl GlobalAlloc for PeakAlloc {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            grow(layout.size());
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            LIVE.fetch_sub(layout.size(), Ordering::Relaxed);
+            System.dealloc(ptr, layout)
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            if new_size > layout.size() {
+                grow(new_size - layout.size());
+            } else {
+                LIVE.fetch_sub(layout.size() - new_size, Ordering::Relaxed);
+            }
+            System.realloc(ptr, layout, new_size)
+        }
+    }
+
+    #[global_allocator]
+    static GLOBAL: PeakAlloc = PeakAlloc;
+
+    const SCHEMA: &str = r#"
+    {
+      "vertex_type_map": { "PERSON": 0 },
+      "edge_type_map": { "KNOWS": 0 },
+      "vertex_prop": {
+        "PERSON": [["id", "ID"], ["name", "String"]]
+      },
+      "edge_prop": {
+        "KNOWS": [["start_id", "ID"], ["end_id", "ID"]]
+      }
+    }
+    "#;
+
+    // the persons the hub knows, each of whom knows either amy or zed
+    const FOLLOWERS: usize = 50_000;
+    const HUB: usize = 1;
+    const AMY: usize = FOLLOWERS + 2;
+    const ZED: usize = FOLLOWERS + 3;
+
+    // the hub knowing all the followers, where one follower out of every 100 knows zed, and the
+    // others know amy
+    fn register_hub_graph() {
+        let temp = tempdir::TempDir::new("expand_memory_test").expect("no temp dir");
+        let raw_dir = temp.path().join("raw");
+        std::fs::create_dir_all(&raw_dir).unwrap();
+        let mut persons = format!("{}|hub\n{}|amy\n{}|zed\n", HUB, AMY, ZED);
+        let mut knows = String::new();
+        for follower in HUB + 1..AMY {
+            persons.push_str(&format!("{}|follower\n", follower));
+            knows.push_str(&format!("{}|{}\n", HUB, follower));
+            let known = if follower % 100 == 0 { ZED } else { AMY };
+            knows.push_str(&format!("{}|{}\n", follower, known));
+        }
+        std::fs::write(raw_dir.join("person_0_0.csv"), persons).unwrap();
+        std::fs::write(raw_dir.join("person_knows_person_0_0.csv"), knows).unwrap();
+        let schema_file = temp.path().join("schema.json");
+        std::fs::write(&schema_file, SCHEMA).unwrap();
+        let root_dir = temp.path().join("graph");
+        let mut loader =
+            GraphLoader::<DefaultId, InternalId>::new(&raw_dir, &root_dir, &schema_file, 20, 0, 1);
+        loader.load().expect("load failed");
+        register_named_graph("hub", create_graph(loader.into_graph()));
+    }
+
+    fn job_conf(job_id: u64) -> server_pb::JobConfig {
+        server_pb::JobConfig {
+            job_id,
+            job_name: "expand_memory_test".to_owned(),
+            workers: 1,
+            ..Default::default()
+        }
+    }
+
+    // g.V(hub).out().out().has("name", "zed").count(), with the bytes allocated at most while
+    // it runs, besides those allocated before
+    fn count_zed(job_id: u64, profile: bool) -> (Vec<Object>, usize) {
+        let hub: DefaultId = LDBCVertexParser::to_global_id(HUB, 0.into());
+        let traversal = Graph::traversal()
+            .v_ids(&[hub as ID])
+            .on_graph("hub")
+            .out(&[])
+            .out(&[])
+            .has("name", eq("zed"))
+            .count();
+        // the steps of the profiled traversal are never fused
+        let traversal = if profile { traversal.profile() } else { traversal };
+        let before = LIVE.load(Ordering::SeqCst);
+        PEAK.store(before, Ordering::SeqCst);
+        let results = traversal.run(job_conf(job_id)).map(|r| r.expect("count failed")).collect();
+        (results, PEAK.load(Ordering::SeqCst).saturating_sub(before))
+    }
+
+    // the followers reached by the first step of the fused expansion are never collected into
+    // batches, nor are those of the second step filtered out by the has step, so it allocates
+    // far less than the steps one by one, whose batches hold all the followers at once
+    #[test]
+    fn fused_expand_memory_test() {
+        initialize();
+        register_hub_graph();
+        let expected = vec![Object::from((FOLLOWERS / 100) as u64)];
+        // warm up the caches of the runtime and the graph
+        assert_eq!(count_zed(6397, false).0, expected);
+        assert_eq!(count_zed(6398, true).0, expected);
+        let (fused, fused_peak) = count_zed(6399, false);
+        let (unfused, unfused_peak) = count_zed(6400, true);
+        assert_eq!(fused, expected);
+        assert_eq!(unfused, expected);
+        assert!(
+            fused_peak * 2 < unfused_peak,
+            "peak of the fused {} vs the unfused {}",
+            fused_peak,
+            unfused_peak
+        );
+    }
+}
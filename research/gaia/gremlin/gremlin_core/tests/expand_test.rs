@@ -0,0 +1,169 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use dyn_type::Object;
+    use gremlin_core::compiler::GremlinJobCompiler;
+    use gremlin_core::process::expand::fuse_expand;
+    use gremlin_core::process::traversal::traverser::Traverser;
+    use gremlin_core::traversal::*;
+    use gremlin_core::{GremlinStepPb, Partition};
+    use pegasus_server::factory::JobCompiler;
+    use pegasus_server::generated::protocol as server_pb;
+    use pegasus_server::JobRequest;
+    use prost::Message;
+
+    fn job_conf(job_id: u64) -> server_pb::JobConfig {
+        server_pb::JobConfig {
+            job_id,
+            job_name: "expand_test".to_owned(),
+            workers: 2,
+            ..Default::default()
+        }
+    }
+
+    fn request(traversal: &GraphTraversal) -> JobRequest {
+        traversal.to_request(server_pb::JobConfig::default())
+    }
+
+    fn fused(traversal: &GraphTraversal) -> Option<usize> {
+        let plan = request(traversal).plan.expect("plan not found").plan;
+        fuse_expand(&plan, |res| GremlinStepPb::decode(res).ok()).map(|(_, fused)| fused)
+    }
+
+    // two consecutive vertex steps are fused, with the has step right after them
+    #[test]
+    fn fuse_expand_test() {
+        let g = || Graph::traversal().v();
+        assert_eq!(fused(&g().out(&[0]).out(&[1])), Some(2));
+        assert_eq!(fused(&g().out(&[]).in_e(&[])), Some(2));
+        assert_eq!(fused(&g().out(&[]).both(&[]).has("name", eq("lop"))), Some(3));
+        assert_eq!(fused(&g().out(&[]).out(&[]).has("age", gt(28)).out(&[])), Some(3));
+        // the first step must be of vertices, right followed by the second one
+        assert_eq!(fused(&g().out_e(&[]).out(&[])), None);
+        assert_eq!(fused(&g().out(&[]).has("age", gt(28)).out(&[])), None);
+        assert_eq!(fused(&g().out(&[]).values(&["name"])), None);
+        assert_eq!(fused(&g().out(&[]).count()), None);
+    }
+
+    // the results of the traversal by the operators evaluated one by one, from the `start`-th
+    fn by_steps(
+        compiler: &GremlinJobCompiler, plan: &[server_pb::OperatorDef], start: usize,
+        mut traversers: Vec<Traverser>,
+    ) -> Vec<String> {
+        for op in plan[start..].iter() {
+            traversers = match op.op_kind.as_ref() {
+                Some(server_pb::operator_def::OpKind::Filter(filter)) => {
+                    let func = compiler.filter(&filter.resource).unwrap();
+                    traversers.into_iter().filter(|t| func.exec(t).unwrap()).collect()
+                }
+                Some(server_pb::operator_def::OpKind::FlatMap(flat_map)) => {
+                    let func = compiler.flat_map(&flat_map.resource).unwrap();
+                    traversers
+                        .into_iter()
+                        .flat_map(|t| func.exec(t).unwrap().map(|t| t.unwrap()))
+                        .collect()
+                }
+                _ => unreachable!(),
+            };
+        }
+        let mut results: Vec<String> = traversers.iter().map(|t| format!("{:?}", t)).collect();
+        results.sort();
+        results
+    }
+
+    // the results of the traversal whose leading vertex steps are fused into one expansion
+    fn by_fused(compiler: &GremlinJobCompiler, req: &JobRequest) -> Vec<String> {
+        let source = compiler.source(&req.source.as_ref().unwrap().resource).unwrap();
+        let plan = &req.plan.as_ref().unwrap().plan;
+        let (func, fused) = compiler.fused_flat_map(plan).unwrap().expect("no step fused");
+        let traversers =
+            source.flat_map(|t| func.exec(t).unwrap().map(|t| t.unwrap())).collect::<Vec<_>>();
+        by_steps(compiler, plan, fused, traversers)
+    }
+
+    // the fused expansion produces exactly the traversers the steps produce one by one
+    #[test]
+    fn fused_expand_equivalence_test() {
+        initialize();
+        let compiler = GremlinJobCompiler::new(Partition { num_servers: 1 }, 1, 0);
+        let g = || Graph::traversal().v();
+        let traversals = vec![
+            g().out(&[]).out(&[]),
+            g().out(&[0]).out(&[1]),
+            g().in_(&[]).out(&[]),
+            g().both(&[]).both(&[]),
+            g().out(&[]).both_e(&[]),
+            g().both(&[]).out(&[]).has("name", eq("lop")),
+            g().both(&[]).both(&[]).has("age", gte(29)).values(&["name"]),
+        ];
+        for traversal in traversals {
+            let req = request(&traversal);
+            let source = compiler.source(&req.source.as_ref().unwrap().resource).unwrap();
+            let plan = &req.plan.as_ref().unwrap().plan;
+            assert_eq!(by_fused(&compiler, &req), by_steps(&compiler, plan, 0, source.collect()));
+        }
+    }
+
+    // the vertices reached by the first step are exchanged to where they are stored unless the
+    // partitioner tells all the vertices are in current server
+    #[test]
+    fn fused_expand_partitioner_test() {
+        initialize();
+        let plan = request(&Graph::traversal().v().out(&[]).out(&[])).plan.unwrap().plan;
+        let compiler = GremlinJobCompiler::new(Partition { num_servers: 1 }, 1, 0);
+        assert!(compiler.fused_flat_map(&plan).unwrap().is_some());
+        let compiler = GremlinJobCompiler::new(Partition { num_servers: 2 }, 2, 0);
+        assert!(compiler.fused_flat_map(&plan).unwrap().is_none());
+        let compiler =
+            GremlinJobCompiler::new(Partition { num_servers: 1 }, 1, 0).with_fused_expand(false);
+        assert!(compiler.fused_flat_map(&plan).unwrap().is_none());
+    }
+
+    fn run(traversal: GraphTraversal, job_id: u64) -> Vec<Object> {
+        traversal.run(job_conf(job_id)).map(|r| r.expect("traversal failed")).collect()
+    }
+
+    fn names(traversal: GraphTraversal, job_id: u64) -> Vec<String> {
+        let mut names: Vec<String> =
+            run(traversal, job_id).iter().map(|o| o.as_str().unwrap().into_owned()).collect();
+        names.sort();
+        names
+    }
+
+    // the traversals run by the fused expansion give the results of the profiled ones, whose
+    // steps are never fused
+    #[test]
+    fn fused_expand_traversal_test() {
+        initialize();
+        let g = || Graph::traversal().v();
+        let fused = names(g().out(&[]).out(&[]).values(&["name"]), 6391);
+        let unfused = names(g().out(&[]).out(&[]).values(&["name"]).profile(), 6392);
+        assert_eq!(fused, vec!["lop", "ripple"]);
+        assert_eq!(fused, unfused);
+        let fused = names(g().both(&[]).in_(&[]).has("age", gt(29)).values(&["name"]), 6393);
+        let unfused =
+            names(g().both(&[]).in_(&[]).has("age", gt(29)).values(&["name"]).profile(), 6394);
+        assert!(!fused.is_empty());
+        assert_eq!(fused, unfused);
+        let fused = run(g().both(&[]).both(&[]).count(), 6395);
+        let unfused = run(g().both(&[]).both(&[]).count().profile(), 6396);
+        assert_eq!(fused, unfused);
+    }
+}
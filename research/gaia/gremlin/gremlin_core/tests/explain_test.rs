@@ -0,0 +1,91 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use gremlin_core::traversal::*;
+    use pegasus_server::generated::protocol as server_pb;
+
+    fn job_conf(job_id: u64) -> server_pb::JobConfig {
+        server_pb::JobConfig {
+            job_id,
+            job_name: "explain_test".to_owned(),
+            workers: 2,
+            ..Default::default()
+        }
+    }
+
+    fn explain(traversal: GraphTraversal, job_id: u64) -> server_pb::JobPlan {
+        traversal.explain(job_conf(job_id)).expect("explain failed")
+    }
+
+    fn assert_dataflow(plan: &server_pb::JobPlan) {
+        assert_eq!(plan.workers, 2);
+        assert_eq!(plan.servers, 1);
+        assert!(!plan.shortcut);
+        let names: Vec<&str> = plan.dataflow.iter().map(|op| op.name.as_str()).collect();
+        assert!(names.first().unwrap().contains("source"), "{:?}", names);
+        assert!(names.last().unwrap().contains("sink"), "{:?}", names);
+        let last = plan.dataflow.len() as u32 - 1;
+        assert!(plan.channels.iter().any(|ch| ch.target == last), "{:?}", plan.channels);
+    }
+
+    // g.V(1).out()
+    #[test]
+    fn explain_id_lookup_test() {
+        initialize();
+        let traversal = Graph::traversal().v_ids(&to_global_ids(vec![1])).out(&[]);
+        let plan = explain(traversal, 6420);
+        let source = &plan.operators[0];
+        assert_eq!(source.kind, "source");
+        assert_eq!(source.access, "vertex lookup by 1 ids");
+        assert_eq!(source.estimated_records, 1);
+        let out = &plan.operators[1];
+        assert_eq!(out.op_index, vec![0]);
+        assert_eq!(out.kind, "flat_map");
+        assert_dataflow(&plan);
+    }
+
+    // g.V().is(1).out(), i.e. g.V().hasId(1).out(), which scans all vertices for the id
+    #[test]
+    fn explain_scan_test() {
+        initialize();
+        let id = to_global_id(1) as i64;
+        let traversal = Graph::traversal().v().is(eq(id)).out(&[]);
+        let plan = explain(traversal, 6421);
+        let source = &plan.operators[0];
+        assert_eq!(source.kind, "source");
+        assert_eq!(source.access, "vertex scan");
+        let is = &plan.operators[1];
+        assert_eq!(is.op_index, vec![0]);
+        assert_eq!(is.kind, "filter");
+        assert_eq!(plan.operators[2].kind, "flat_map");
+        assert_dataflow(&plan);
+    }
+
+    // g.V(1, 2, 3).limit(2), whose estimate is bound by the limit
+    #[test]
+    fn explain_limit_test() {
+        initialize();
+        let traversal = Graph::traversal().v_ids(&to_global_ids(vec![1, 2, 3])).limit(2);
+        let plan = explain(traversal, 6422);
+        assert_eq!(plan.operators[0].estimated_records, 3);
+        let limit = plan.operators.iter().find(|op| op.kind == "limit").expect("limit not found");
+        assert_eq!(limit.estimated_records, 2);
+    }
+}
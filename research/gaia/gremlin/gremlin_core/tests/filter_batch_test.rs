@@ -0,0 +1,247 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use dyn_type::Object;
+    use gremlin_core::structure::{
+        by_property, has_id, has_label, has_property_approx, has_property_ge, has_property_gt,
+        has_property_le, has_property_lt, reset_tlv_right_value, ColumnBatch, DefaultDetails,
+        ElementFilter, Filter, Label, QueryParams, Reverse, Selection, Vertex,
+    };
+    use gremlin_core::{Element, ID};
+    use std::collections::HashMap;
+
+    const KEYS: [&str; 6] = ["int", "long", "float", "str", "byte", "mixed"];
+
+    // the values of each property, where a vertex has no such property once in a while
+    fn values(key: &str) -> Vec<Object> {
+        match key {
+            "int" => {
+                vec![i32::MIN, -1, 0, 1, 2, 3, i32::MAX].into_iter().map(Object::from).collect()
+            }
+            "long" => vec![i64::MIN, -40000, -1, 0, 1, 3, 40000, 1 << 40, i64::MAX]
+                .into_iter()
+                .map(Object::from)
+                .collect(),
+            "float" => vec![f64::NAN, -1.5, 0.0, 1.0, 1.0000000001, 3.0, f64::INFINITY]
+                .into_iter()
+                .map(Object::from)
+                .collect(),
+            "str" => vec!["", "a", "ab", "b"].into_iter().map(Object::from).collect(),
+            "byte" => vec![-1i8, 0, 1, 3].into_iter().map(Object::from).collect(),
+            _ => vec![
+                Object::from(1),
+                Object::from(1i64),
+                Object::from(1.0),
+                Object::from("1"),
+                Object::from(1i8),
+            ],
+        }
+    }
+
+    // the values the properties are compared to
+    fn expects() -> Vec<Object> {
+        vec![
+            Object::from(-1),
+            Object::from(0),
+            Object::from(1),
+            Object::from(3),
+            Object::from(i32::MAX),
+            Object::from(1i64),
+            Object::from(40000i64),
+            Object::from(1i64 << 40),
+            Object::from(1i8),
+            Object::from(1.0),
+            Object::from(1.5),
+            Object::from(3.0000000001),
+            Object::from(f64::NAN),
+            Object::from(""),
+            Object::from("a"),
+            Object::from("b"),
+        ]
+    }
+
+    // more than one word of the bitmaps, with the vertices of different properties missing
+    fn vertices() -> Vec<Vertex> {
+        (0..200u64)
+            .map(|id| {
+                let mut properties = HashMap::new();
+                for (k, key) in KEYS.iter().enumerate() {
+                    if id % (k as u64 + 5) != 0 {
+                        let values = values(key);
+                        let value = values[(id as usize * 7 + k) % values.len()].clone();
+                        properties.insert(key.to_string(), value);
+                    }
+                }
+                let label = Label::Id(((id % 2) as u8).into());
+                Vertex::new(
+                    id as _,
+                    Some(label.clone()),
+                    DefaultDetails::new_with_prop(id as _, label, properties),
+                )
+            })
+            .collect()
+    }
+
+    const COMPARES: usize = 8;
+
+    // the comparison of each property to each value, by each comparator
+    fn simple_filter(index: usize) -> ElementFilter {
+        let expects = expects();
+        let cmp = index % COMPARES;
+        let expect = expects[(index / COMPARES) % expects.len()].clone();
+        let key = KEYS[(index / COMPARES / expects.len()) % KEYS.len()].to_owned();
+        match cmp {
+            0 => has_property_approx(key, expect, 0.0),
+            1 => has_property_approx(key, expect, 1e-9),
+            2 | 3 => {
+                let mut f = has_property_approx(key, expect, if cmp == 2 { 0.0 } else { 1e-9 });
+                f.reverse();
+                f
+            }
+            4 => has_property_lt(key, expect),
+            5 => has_property_le(key, expect),
+            6 => has_property_gt(key, expect),
+            _ => has_property_ge(key, expect),
+        }
+    }
+
+    fn simple_filters() -> usize {
+        COMPARES * expects().len() * KEYS.len()
+    }
+
+    // the predicates tested element by element, mixed in the chains
+    fn other_filter(index: usize) -> ElementFilter {
+        match index % 4 {
+            0 => has_id(Some(3)),
+            1 => has_label(Some(Label::Id(1.into()))),
+            2 => ElementFilter::PassBy(false),
+            _ => by_property("int".to_owned()),
+        }
+    }
+
+    fn check(
+        filter: &Filter<Vertex, ElementFilter>, vertices: &[Vertex], batch: &ColumnBatch<Vertex>,
+    ) {
+        let mut out = Selection::default();
+        filter.eval_batch(batch, &mut out);
+        let selected = filter.select_batch(batch);
+        assert_eq!(out.len(), vertices.len());
+        for (i, v) in vertices.iter().enumerate() {
+            let expected = filter.test(v);
+            assert_eq!(out.get(i), expected, "vertex {}", i);
+            assert_eq!(selected.get(i), expected == Some(true));
+        }
+    }
+
+    fn keys() -> Vec<String> {
+        KEYS.iter().map(|key| key.to_string()).collect()
+    }
+
+    // each comparison evaluated over the columns tells what the scalar comparison tells
+    #[test]
+    fn simple_filter_test() {
+        let vertices = vertices();
+        let batch = ColumnBatch::with_properties(&vertices, &keys());
+        for index in 0..simple_filters() {
+            check(&Filter::with(simple_filter(index)), &vertices, &batch);
+        }
+    }
+
+    // the same if the properties are not in the columns, which are tested element by element
+    #[test]
+    fn without_columns_test() {
+        let vertices = vertices();
+        let batch = ColumnBatch::new(&vertices);
+        for index in (0..simple_filters()).step_by(7) {
+            check(&Filter::with(simple_filter(index)), &vertices, &batch);
+        }
+        reset_tlv_right_value(1);
+        for index in 0..4 {
+            check(&Filter::with(other_filter(index)), &vertices, &batch);
+        }
+    }
+
+    // the chains of and, or, and the nested chains, which are folded from left to right and stop
+    // once the result can't be changed, or a node tells `None`
+    #[test]
+    fn chain_filter_test() {
+        let vertices = vertices();
+        let batch = ColumnBatch::with_properties(&vertices, &keys());
+        let n = simple_filters();
+        reset_tlv_right_value(1);
+        for i in 0..300 {
+            let mut filter = Filter::with_chain(simple_filter(i * 7 % n));
+            if i % 2 == 0 {
+                filter.and(simple_filter(i * 13 % n));
+            } else {
+                filter.or(simple_filter(i * 13 % n));
+            }
+            match i % 3 {
+                0 => filter.or(other_filter(i)),
+                1 => filter.and(simple_filter(i * 29 % n)),
+                _ => {
+                    let mut nested = Filter::with_chain(simple_filter(i * 31 % n));
+                    nested.or(other_filter(i));
+                    filter.and(nested)
+                }
+            };
+            check(&filter, &vertices, &batch);
+        }
+        check(&Filter::default(), &vertices, &batch);
+    }
+
+    #[test]
+    fn referenced_properties_test() {
+        let mut filter = Filter::with_chain(has_property_lt("int".to_owned(), 3));
+        let mut nested = Filter::with_chain(has_id(Some(1)));
+        nested.or(has_property_ge("str".to_owned(), "a")).or(has_property_gt("int".to_owned(), 0));
+        filter.and(nested);
+        let mut params = QueryParams::<Vertex>::new();
+        params.set_filter(filter);
+        assert_eq!(params.columns, vec!["int".to_owned(), "str".to_owned()]);
+    }
+
+    fn scan_ids(params: &QueryParams<Vertex>) -> Vec<ID> {
+        let graph = gremlin_core::get_graph().expect("graph not registered");
+        let mut ids: Vec<ID> =
+            graph.scan_vertex(params).expect("scan failure").map(|v| v.id()).collect();
+        ids.sort();
+        ids
+    }
+
+    // g.V().has("age", gt(27)).has("name", neq("josh")), whose filter is evaluated by batches of
+    // the columns of age and name, or vertex by vertex without the columns, where the software
+    // have no age, which the filter can't tell
+    #[test]
+    fn scan_filter_test() {
+        initialize();
+        let mut not_josh = has_property_approx("name".to_owned(), "josh", 0.0);
+        not_josh.reverse();
+        let mut filter = Filter::with_chain(has_property_gt("age".to_owned(), 27));
+        filter.and(not_josh);
+        let mut params = QueryParams::<Vertex>::new();
+        params.set_filter(filter);
+        assert_eq!(params.columns, vec!["age".to_owned(), "name".to_owned()]);
+        let ids = scan_ids(&params);
+        assert_eq!(ids, to_global_ids(vec![1, 6]));
+        params.columns.clear();
+        assert_eq!(scan_ids(&params), ids);
+    }
+}
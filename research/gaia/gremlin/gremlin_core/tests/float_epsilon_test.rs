@@ -0,0 +1,153 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use dyn_type::Object;
+    use gremlin_core::generated::common as common_pb;
+    use gremlin_core::generated::gremlin as pb;
+    use gremlin_core::structure::codec::{pb_chain_to_filter, ParseError, WITHIN_PROPERTY};
+    use gremlin_core::structure::{
+        get_default_float_epsilon, set_default_float_epsilon, Vertex, DEFAULT_FLOAT_EPSILON,
+    };
+    use gremlin_core::traversal::*;
+    use pegasus_server::generated::protocol as server_pb;
+
+    fn job_conf(job_id: u64) -> server_pb::JobConfig {
+        server_pb::JobConfig {
+            job_id,
+            job_name: "float_epsilon_test".to_owned(),
+            workers: 2,
+            ..Default::default()
+        }
+    }
+
+    fn run(traversal: GraphTraversal, job_id: u64) -> Vec<Object> {
+        initialize();
+        traversal.run(job_conf(job_id)).map(|r| r.expect("traversal failed")).collect()
+    }
+
+    // the weight 0.4 computed as 0.6 - 0.2, which is 0.39999999999999997 in f64
+    fn computed_weight() -> f64 {
+        0.6 - 0.2
+    }
+
+    // g.V().outE().has("weight", eq(0.6 - 0.2)), where two edges of weight 0.4 are equal within
+    // the epsilon, but not exactly
+    #[test]
+    fn float_eq_test() {
+        assert_ne!(computed_weight(), 0.4);
+        let approx = Graph::traversal()
+            .v()
+            .out_e(&[])
+            .has("weight", eq(computed_weight()).with_epsilon(1e-9));
+        assert_eq!(run(approx, 6270).len(), 2);
+        let exact = Graph::traversal()
+            .v()
+            .out_e(&[])
+            .has("weight", eq(computed_weight()).with_epsilon(-1.0));
+        assert_eq!(run(exact, 6271).len(), 0);
+    }
+
+    // g.V().outE().has("weight", neq(0.6 - 0.2)), of the 6 edges
+    #[test]
+    fn float_neq_test() {
+        let approx = Graph::traversal()
+            .v()
+            .out_e(&[])
+            .has("weight", neq(computed_weight()).with_epsilon(1e-9));
+        assert_eq!(run(approx, 6272).len(), 4);
+        let exact = Graph::traversal()
+            .v()
+            .out_e(&[])
+            .has("weight", neq(computed_weight()).with_epsilon(-1.0));
+        assert_eq!(run(exact, 6273).len(), 6);
+    }
+
+    // g.V().outE().has("weight", gt(0.6 - 0.2)) and lte, where the orders are never within the
+    // epsilon, so the edges of weight 0.4 are greater than 0.39999999999999997 either way
+    #[test]
+    fn float_order_test() {
+        let gt_approx = Graph::traversal()
+            .v()
+            .out_e(&[])
+            .has("weight", gt(computed_weight()).with_epsilon(1e-9));
+        assert_eq!(run(gt_approx, 6274).len(), 5);
+        let lte_approx = Graph::traversal()
+            .v()
+            .out_e(&[])
+            .has("weight", lte(computed_weight()).with_epsilon(1e-9));
+        assert_eq!(run(lte_approx, 6275).len(), 1);
+    }
+
+    // g.V().has("age", eq(29.0000000001)), where the integer age of marko is taken as a float
+    // against the float, while two integers are always compared exactly
+    #[test]
+    fn mixed_int_float_test() {
+        let approx = Graph::traversal()
+            .v()
+            .has("age", eq(29.000_000_000_1).with_epsilon(1e-9))
+            .values(&["name"]);
+        assert_eq!(run(approx, 6276), vec![Object::from("marko")]);
+        let exact = Graph::traversal().v().has("age", eq(29.000_000_000_1).with_epsilon(-1.0));
+        assert!(run(exact, 6277).is_empty());
+        let integral = Graph::traversal().v().has("age", eq(29.0).with_epsilon(-1.0));
+        assert_eq!(run(integral, 6278).len(), 1);
+        // two integers are compared exactly, even if they are within the epsilon
+        let int = Graph::traversal().v().has("age", eq(30).with_epsilon(0.1)).values(&["name"]);
+        assert!(run(int, 6279).is_empty());
+    }
+
+    // the filters not giving their own epsilon follow the default of the server
+    #[test]
+    fn default_epsilon_test() {
+        assert_eq!(get_default_float_epsilon(), DEFAULT_FLOAT_EPSILON);
+        let traversal = || Graph::traversal().v().out_e(&[]).has("weight", eq(0.4 + 1e-8));
+        assert_eq!(run(traversal(), 6280).len(), 0);
+        set_default_float_epsilon(1e-6);
+        let count = run(traversal(), 6281).len();
+        set_default_float_epsilon(DEFAULT_FLOAT_EPSILON);
+        assert_eq!(count, 2);
+    }
+
+    // within is not supported on the properties, so neither are the lists of floats, even if the
+    // filter gives an epsilon
+    #[test]
+    fn within_floats_test() {
+        let exp = pb::FilterExp {
+            left: Some(common_pb::Key {
+                item: Some(common_pb::key::Item::Name("weight".to_owned())),
+            }),
+            cmp: pb::Compare::Within as i32,
+            right: Some(common_pb::Value {
+                item: Some(common_pb::value::Item::F64Array(common_pb::DoubleArray {
+                    item: vec![computed_weight(), 1.0],
+                })),
+            }),
+            epsilon: 1e-9,
+            predicate: String::new(),
+        };
+        let node = pb::FilterNode {
+            inner: Some(pb::filter_node::Inner::Single(exp)),
+            next: pb::Connect::And as i32,
+        };
+        let chain = pb::FilterChain { node: vec![node] };
+        let err = pb_chain_to_filter::<Vertex>(&chain).err().expect("within floats accepted");
+        assert!(matches!(err, ParseError::Unsupported(WITHIN_PROPERTY)));
+    }
+}
@@ -0,0 +1,109 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use dyn_type::Object;
+    use gremlin_core::process::traversal::step::result_downcast::try_downcast_list;
+    use gremlin_core::structure::Element;
+    use gremlin_core::traversal::*;
+    use gremlin_core::ID;
+    use pegasus_server::generated::protocol as server_pb;
+
+    fn job_conf(job_id: u64, workers: u32) -> server_pb::JobConfig {
+        server_pb::JobConfig {
+            job_id,
+            job_name: "fold_test".to_owned(),
+            workers,
+            ..Default::default()
+        }
+    }
+
+    fn sorted_ids(values: Vec<Object>) -> Vec<ID> {
+        let mut ids: Vec<ID> =
+            values.iter().map(|v| v.as_u128().expect("not an id") as ID).collect();
+        ids.sort();
+        ids
+    }
+
+    fn run(traversal: GraphTraversal, conf: server_pb::JobConfig) -> Vec<Object> {
+        traversal.run(conf).map(|r| r.expect("traversal failed")).collect()
+    }
+
+    // g.V().out().fold()
+    #[test]
+    fn fold_test() {
+        initialize();
+        let results = run(Graph::traversal().v().out(&[]).fold(), job_conf(6251, 2));
+        assert_eq!(results.len(), 1);
+        let items = try_downcast_list(&results[0]).expect("the result is not a list");
+        let mut ids: Vec<ID> =
+            items.iter().map(|t| t.get_element().expect("not an element").id()).collect();
+        ids.sort();
+        let mut expected = to_global_ids(vec![2, 3, 3, 3, 4, 5]);
+        expected.sort();
+        assert_eq!(ids, expected);
+    }
+
+    // g.V().out().fold().unfold(), the same as g.V().out()
+    #[test]
+    fn fold_unfold_test() {
+        initialize();
+        let traversal = Graph::traversal().v().out(&[]).fold().unfold();
+        let results = run(traversal, job_conf(6252, 2));
+        let mut expected = to_global_ids(vec![2, 3, 3, 3, 4, 5]);
+        expected.sort();
+        assert_eq!(sorted_ids(results), expected);
+        // the heads other than lists pass through
+        let results = run(Graph::traversal().v().out(&[]).unfold(), job_conf(6253, 2));
+        let mut expected = to_global_ids(vec![2, 3, 3, 3, 4, 5]);
+        expected.sort();
+        assert_eq!(sorted_ids(results), expected);
+    }
+
+    // g.V().where(out().fold().unfold()), the same as g.V().where(out())
+    #[test]
+    fn fold_in_where_test() {
+        initialize();
+        let sub = Graph::anonymous().out(&[]).fold().unfold();
+        let results = run(Graph::traversal().v().where_(sub), job_conf(6254, 2));
+        assert_eq!(sorted_ids(results), to_global_ids(vec![1, 4, 6]));
+    }
+
+    // g.V().order().by(out().fold().unfold().count(), desc).limit(1), which folds the out
+    // vertices of each vertex apart
+    #[test]
+    fn fold_in_scope_test() {
+        initialize();
+        let by = Graph::anonymous().out(&[]).fold().unfold().count();
+        let traversal = Graph::traversal().v().order_by(by, true).limit(1);
+        let results = run(traversal, job_conf(6255, 2));
+        assert_eq!(sorted_ids(results), to_global_ids(vec![1]));
+    }
+
+    // g.V().fold() of 6 vertices with the result limit 3
+    #[test]
+    fn fold_exceeds_result_limit_test() {
+        initialize();
+        let mut conf = job_conf(6256, 1);
+        conf.result_limit = 3;
+        let results: Vec<_> = Graph::traversal().v().fold().run(conf).collect();
+        let err = results.last().expect("no result").as_ref().expect_err("fold should fail");
+        assert!(err.to_string().contains("the result limit 3 is exceeded by fold()"), "{}", err);
+    }
+}
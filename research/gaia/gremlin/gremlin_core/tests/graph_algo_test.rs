@@ -0,0 +1,71 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use gremlin_core::graph_algo::{run_vertex_program, ConnectedComponents, PageRank};
+    use gremlin_core::structure::Label;
+    use gremlin_core::ID;
+    use pegasus::JobConf;
+
+    // the modern graph: 1->2, 1->3, 1->4, 4->3, 4->5, 6->3, where 1->2 and 1->4 are `knows`
+    #[test]
+    fn pagerank_test() {
+        initialize();
+        let conf = JobConf::new(6171, "pagerank_test", 2);
+        let ranks = run_vertex_program(conf, PageRank::new(0.85).unwrap(), 10).unwrap();
+        assert_eq!(ranks.len(), 6);
+        let base = 0.15 / 6.0;
+        let r1 = base;
+        let r6 = base;
+        let r2 = base + 0.85 * r1 / 3.0;
+        let r4 = r2;
+        let r5 = base + 0.85 * r4 / 2.0;
+        let r3 = base + 0.85 * (r1 / 3.0 + r4 / 2.0 + r6);
+        let expected = vec![(1, r1), (2, r2), (3, r3), (4, r4), (5, r5), (6, r6)];
+        for (v, rank) in expected {
+            let state = ranks[&(to_global_id(v) as ID)];
+            assert!((state.rank - rank).abs() < 1e-9, "rank of v{} is {}", v, state.rank);
+        }
+    }
+
+    #[test]
+    fn connected_components_test() {
+        initialize();
+        let conf = JobConf::new(6172, "connected_components_test", 2);
+        let components = run_vertex_program(conf, ConnectedComponents::new(), 100).unwrap();
+        let min = *to_global_ids(vec![1, 2, 3, 4, 5, 6]).iter().min().unwrap();
+        assert_eq!(components.len(), 6);
+        assert!(components.values().all(|c| *c == min));
+    }
+
+    #[test]
+    fn connected_components_by_knows_test() {
+        initialize();
+        let conf = JobConf::new(6173, "connected_components_by_knows_test", 2);
+        let knows = ConnectedComponents::with_edge_labels(vec![Label::Id(0.into())]);
+        let components = run_vertex_program(conf, knows, 100).unwrap();
+        let min = *to_global_ids(vec![1, 2, 4]).iter().min().unwrap();
+        for v in to_global_ids(vec![1, 2, 4]) {
+            assert_eq!(components[&v], min);
+        }
+        // the software and peter are alone without `knows`
+        for v in to_global_ids(vec![3, 5, 6]) {
+            assert_eq!(components[&v], v);
+        }
+    }
+}
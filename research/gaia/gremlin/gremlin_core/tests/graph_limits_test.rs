@@ -0,0 +1,109 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use dyn_type::Object;
+    use gremlin_core::process::limits::{GraphJobSlot, GraphLimits};
+    use gremlin_core::process::metrics::{get_worker_counter, CLAMPED_WORKERS_COUNTER};
+    use gremlin_core::traversal::*;
+    use gremlin_core::{register_named_graph, set_graph_limits};
+    use pegasus_server::generated::protocol as server_pb;
+    use std::sync::Once;
+
+    static REGISTER: Once = Once::new();
+
+    // the modern graph registered as "limits_small" and "limits_large" of different limits, and
+    // as "limits_single" running one job at most
+    fn register_limited_graphs() {
+        initialize();
+        REGISTER.call_once(|| {
+            let graph = gremlin_core::get_graph().expect("no default graph");
+            let small =
+                GraphLimits { max_jobs: 0, max_workers: 1, memory_limit_mb: 64, max_results: 3 };
+            let large =
+                GraphLimits { max_jobs: 16, max_workers: 4, memory_limit_mb: 0, max_results: 0 };
+            let single = GraphLimits { max_jobs: 1, ..Default::default() };
+            for (name, limits) in
+                [("limits_small", small), ("limits_large", large), ("limits_single", single)]
+            {
+                register_named_graph(name, graph.clone());
+                set_graph_limits(name, limits);
+            }
+        });
+    }
+
+    fn job_conf(job_id: u64, workers: u32) -> server_pb::JobConfig {
+        server_pb::JobConfig {
+            job_id,
+            job_name: "graph_limits_test".to_owned(),
+            workers,
+            ..Default::default()
+        }
+    }
+
+    fn run_on(graph: &str, traversal: GraphTraversal, conf: server_pb::JobConfig) -> Vec<Object> {
+        traversal.on_graph(graph).run(conf).map(|r| r.expect("traversal failed")).collect()
+    }
+
+    fn error_on(graph: &str, traversal: GraphTraversal, conf: server_pb::JobConfig) -> String {
+        let results: Vec<_> = traversal.on_graph(graph).run(conf).collect();
+        let err = results.last().expect("no result").as_ref().expect_err("job should fail");
+        err.to_string()
+    }
+
+    // g.V().fold() of 6 vertices, beyond the result limit 3 of the small graph only
+    #[test]
+    fn graph_result_limit_test() {
+        register_limited_graphs();
+        let err = error_on("limits_small", Graph::traversal().v().fold(), job_conf(6502, 1));
+        assert!(err.contains("the result limit 3 is exceeded by fold()"), "{}", err);
+        assert!(err.contains("on the graph 'limits_small'"), "{}", err);
+        let results = run_on("limits_large", Graph::traversal().v().fold(), job_conf(6503, 1));
+        assert_eq!(results.len(), 1);
+    }
+
+    // g.V().count() of 2 workers, which are clamped to the only worker of the small graph
+    #[test]
+    fn graph_max_workers_test() {
+        register_limited_graphs();
+        let count = |graph: &str, job_id: u64| {
+            let results = run_on(graph, Graph::traversal().v().count(), job_conf(job_id, 2));
+            let clamped = get_worker_counter(job_id, 0, CLAMPED_WORKERS_COUNTER);
+            (results[0].as_u64().expect("not a count"), clamped)
+        };
+        assert_eq!(count("limits_small", 6504), (6, 1));
+        assert_eq!(count("limits_large", 6505), (6, 0));
+    }
+
+    // g.V().count() on the graph running one job at most, while a job is running on it
+    #[test]
+    fn graph_max_jobs_test() {
+        register_limited_graphs();
+        let running = GraphJobSlot::acquire("limits_single", 1).expect("graph is busy");
+        let err = error_on("limits_single", Graph::traversal().v().count(), job_conf(6506, 1));
+        assert!(err.contains("the graph 'limits_single' runs 1 jobs"), "{}", err);
+        assert!(err.contains("max_jobs 1"), "{}", err);
+        // the graph of more jobs is unaffected
+        let results = run_on("limits_large", Graph::traversal().v().count(), job_conf(6507, 1));
+        assert_eq!(results[0].as_u64().ok(), Some(6));
+        drop(running);
+        let results = run_on("limits_single", Graph::traversal().v().count(), job_conf(6508, 1));
+        assert_eq!(results[0].as_u64().ok(), Some(6));
+    }
+}
@@ -0,0 +1,82 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use gremlin_core::structure::graph_component;
+    use gremlin_core::{
+        get_graph, load_named_graph, unregister_named_graph, DynResult, GraphProxy,
+    };
+    use pegasus::health::{ComponentState, Requirement as ReadyRequirement};
+    use std::sync::{mpsc, Arc};
+    use std::time::{Duration, Instant};
+
+    fn graph_state(name: &str) -> Option<ComponentState> {
+        let component = graph_component(name);
+        let report = pegasus::health();
+        report.components.into_iter().find(|c| c.name == component).map(|c| c.state)
+    }
+
+    #[test]
+    fn graph_load_readiness_test() {
+        initialize();
+        assert_eq!(graph_state(""), Some(ComponentState::Ready));
+        let requirement = ReadyRequirement::ComponentReady(graph_component("health_graph"));
+        pegasus::health::set_readiness(vec![requirement]);
+        assert!(!pegasus::health::is_ready());
+
+        let (tx, rx) = mpsc::channel::<()>();
+        let loading = std::thread::spawn(move || {
+            load_named_graph("health_graph", || {
+                rx.recv().ok();
+                Ok(get_graph().expect("demo graph not registered"))
+            })
+        });
+        let start = Instant::now();
+        while graph_state("health_graph") != Some(ComponentState::Loading) {
+            assert!(start.elapsed() < Duration::from_secs(10), "graph is never loading");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert!(!pegasus::health::is_ready());
+        tx.send(()).unwrap();
+        assert!(loading.join().unwrap().is_ok());
+        assert_eq!(graph_state("health_graph"), Some(ComponentState::Ready));
+        assert!(pegasus::health::is_ready());
+
+        assert!(unregister_named_graph("health_graph"));
+        assert_eq!(graph_state("health_graph"), None);
+        assert!(!pegasus::health::is_ready());
+    }
+
+    #[test]
+    fn graph_load_failure_test() {
+        initialize();
+        let result = load_named_graph("broken_graph", || -> DynResult<Arc<dyn GraphProxy>> {
+            let e = std::io::Error::new(std::io::ErrorKind::NotFound, "no such file");
+            Err(Box::new(e))
+        });
+        assert!(result.is_err());
+        match graph_state("broken_graph") {
+            Some(ComponentState::Failed(reason)) => assert!(reason.contains("no such file")),
+            state => panic!("unexpected state {:?}", state),
+        }
+        let report = pegasus::health();
+        assert!(report.live);
+        assert!(report.peers.is_empty());
+    }
+}
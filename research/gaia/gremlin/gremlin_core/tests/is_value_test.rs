@@ -0,0 +1,100 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use dyn_type::Object;
+    use gremlin_core::traversal::*;
+    use gremlin_core::ID;
+    use pegasus_server::generated::protocol as server_pb;
+
+    fn job_conf(job_id: u64) -> server_pb::JobConfig {
+        server_pb::JobConfig {
+            job_id,
+            job_name: "is_value_test".to_owned(),
+            workers: 2,
+            ..Default::default()
+        }
+    }
+
+    fn run(traversal: GraphTraversal, job_id: u64) -> Vec<Object> {
+        initialize();
+        traversal.run(job_conf(job_id)).map(|r| r.expect("traversal failed")).collect()
+    }
+
+    // the ages of the persons, which are 29, 27, 32 and 35
+    fn ages_by(p: P, job_id: u64) -> Vec<i64> {
+        let traversal = Graph::traversal().v().values(&["age"]).is(p);
+        let mut ages: Vec<i64> = run(traversal, job_id)
+            .iter()
+            .map(|o| o.as_i64().expect("age is not a number"))
+            .collect();
+        ages.sort();
+        ages
+    }
+
+    // g.V().values("age").is(eq(29))
+    #[test]
+    fn is_eq_test() {
+        assert_eq!(ages_by(eq(29), 6282), vec![29]);
+        assert_eq!(ages_by(neq(29), 6283), vec![27, 32, 35]);
+    }
+
+    // g.V().values("age").is(within(27, 32, 40))
+    #[test]
+    fn is_within_test() {
+        assert_eq!(ages_by(within(vec![27, 32, 40]), 6284), vec![27, 32]);
+        assert_eq!(ages_by(without(vec![27, 32, 40]), 6285), vec![29, 35]);
+        // the floats equal to the ages of integers
+        assert_eq!(ages_by(within(vec![27.0, 35.0]), 6286), vec![27, 35]);
+        assert_eq!(ages_by(within(Vec::<i64>::new()), 6287), Vec::<i64>::new());
+    }
+
+    // g.V().values("age").is(gt(27).and(lt(35))) and is(lt(28).or(gt(33)))
+    #[test]
+    fn is_chained_test() {
+        assert_eq!(ages_by(gt(27).and(lt(35)), 6288), vec![29, 32]);
+        assert_eq!(ages_by(lt(28).or(gt(33)), 6289), vec![27, 35]);
+        assert_eq!(ages_by(within(vec![27, 29, 32]).and(gte(29)), 6290), vec![29, 32]);
+    }
+
+    // g.V().values("age").is(gt(28).and(lt(30)).or(eq(35)).and(neq(29))), which is evaluated
+    // from left to right, i.e. (((gt(28) and lt(30)) or eq(35)) and neq(29))
+    #[test]
+    fn is_mixed_connect_test() {
+        assert_eq!(ages_by(gt(28).and(lt(30)).or(eq(35)), 6291), vec![29, 35]);
+        assert_eq!(ages_by(gt(28).and(lt(30)).or(eq(35)).and(neq(29)), 6292), vec![35]);
+        assert_eq!(ages_by(eq(27).or(gt(28).and(lt(33))), 6293), vec![27, 29, 32]);
+    }
+
+    // g.V().is(within(1, 2)), which compares the ids of the vertices
+    #[test]
+    fn is_on_elements_test() {
+        let expected = to_global_ids(vec![1, 2]);
+        let ids: Vec<i64> = expected.iter().map(|id| *id as i64).collect();
+        let traversal = Graph::traversal().v().is(within(ids));
+        let mut results: Vec<ID> = run(traversal, 6294)
+            .iter()
+            .map(|o| o.as_u128().expect("cannot cast to u128") as ID)
+            .collect();
+        results.sort();
+        let mut expected = expected;
+        expected.sort();
+        assert_eq!(results, expected);
+    }
+}
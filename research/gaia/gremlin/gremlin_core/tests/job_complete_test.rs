@@ -0,0 +1,148 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use gremlin_core::compiler::GremlinJobCompiler;
+    use gremlin_core::traversal::*;
+    use gremlin_core::Partition;
+    use pegasus_server::generated::protocol as server_pb;
+    use pegasus_server::service::{Output, Service};
+    use pegasus_server::{JobRequest, JobResponse, JobResult};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Debug)]
+    enum Event {
+        Result(JobResult),
+        Close,
+    }
+
+    #[derive(Clone, Default)]
+    struct CollectOutput {
+        events: Arc<Mutex<Vec<Event>>>,
+    }
+
+    impl Output for CollectOutput {
+        fn send(&self, res: JobResponse) {
+            if let Some(result) = res.result {
+                self.events.lock().unwrap().push(Event::Result(result));
+            }
+        }
+
+        fn close(&self) {
+            self.events.lock().unwrap().push(Event::Close);
+        }
+    }
+
+    fn job_conf(job_id: u64, workers: u32) -> server_pb::JobConfig {
+        server_pb::JobConfig {
+            job_id,
+            job_name: "job_complete_test".to_owned(),
+            workers,
+            batch_size: 2,
+            ..Default::default()
+        }
+    }
+
+    fn run(request: JobRequest) -> Vec<Event> {
+        initialize();
+        let job_id = request.conf.as_ref().unwrap().job_id;
+        let service = Service::new(GremlinJobCompiler::new(Partition { num_servers: 1 }, 1, 0));
+        let output = CollectOutput::default();
+        service.accept(request, output.clone());
+        if let Some(guard) = service.job_guards.write().unwrap().get_mut(&job_id) {
+            guard.join().expect("job failed");
+        }
+        let mut events = output.events.lock().unwrap();
+        std::mem::take(&mut *events)
+    }
+
+    fn is_complete(event: &Event) -> bool {
+        matches!(event, Event::Result(JobResult::Complete(_)))
+    }
+
+    fn is_data(event: &Event) -> bool {
+        matches!(event, Event::Result(JobResult::Data(_)))
+    }
+
+    fn assert_complete(events: &[Event], workers: u32) {
+        let complete = events.iter().position(is_complete).expect("job complete not sent");
+        assert_eq!(events.iter().filter(|e| is_complete(e)).count(), 1);
+        // all results are sent before the job is told complete, ahead of the output being closed
+        let batches = events.iter().filter(|e| is_data(e)).count();
+        assert!(events[complete..].iter().all(|e| !is_data(e)));
+        let last_close = events.iter().rposition(|e| matches!(e, Event::Close));
+        assert!(complete < last_close.expect("output not closed"));
+        match &events[complete] {
+            Event::Result(JobResult::Complete(complete)) => {
+                let stats = complete.stats.as_ref().expect("stats not sent");
+                assert_eq!(stats.workers, workers);
+                assert_eq!(stats.batches, batches as u64);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    // g.V().both().both()
+    #[test]
+    fn complete_after_results_test() {
+        for workers in [1, 4] {
+            let traversal = Graph::traversal().v().both(&[]).both(&[]);
+            let events = run(traversal.to_request(job_conf(6295 + workers as u64, workers)));
+            assert_complete(&events, workers);
+        }
+    }
+
+    // g.V(), whose results are sent from the source directly
+    #[test]
+    fn complete_source_test() {
+        let events = run(Graph::traversal().v().to_request(job_conf(6300, 2)));
+        assert_complete(&events, 2);
+    }
+
+    // g.V().out().profile()
+    #[test]
+    fn complete_before_profile_test() {
+        let traversal = Graph::traversal().v().out(&[]).profile();
+        let events = run(traversal.to_request(job_conf(6301, 2)));
+        assert_complete(&events, 2);
+        let complete = events.iter().position(is_complete).unwrap();
+        let profile = events.iter().position(|e| matches!(e, Event::Result(JobResult::Profile(_))));
+        assert!(complete < profile.expect("profile not sent"));
+    }
+
+    #[test]
+    fn paged_job_not_complete_test() {
+        let mut conf = job_conf(6302, 2);
+        conf.page_size = 2;
+        let events = run(Graph::traversal().v().out(&[]).to_request(conf));
+        assert!(events.iter().position(is_complete).is_none());
+    }
+
+    // g.V().both(), whose stream ends once the job is complete
+    #[test]
+    fn stream_ends_on_complete_test() {
+        initialize();
+        for i in 0..10 {
+            let traversal = Graph::traversal().v().both(&[]);
+            let results = traversal.run(job_conf(6303 + i, 4));
+            let values: Vec<_> = results.map(|r| r.expect("traversal failed")).collect();
+            assert_eq!(values.len(), 12);
+        }
+    }
+}
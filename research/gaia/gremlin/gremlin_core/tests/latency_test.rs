@@ -0,0 +1,148 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use dyn_type::Object;
+    use graph_store::config::JsonConf;
+    use graph_store::ldbc::LDBCVertexParser;
+    use graph_store::prelude::{
+        DefaultId, GlobalStoreUpdate, GraphDBConfig, InternalId, LDBCGraphSchema, LargeGraphDB,
+        MutableGraphDB, Row, INVALID_LABEL_ID,
+    };
+    use gremlin_core::prepared::template_hash;
+    use gremlin_core::structure::{Direction, Edge, QueryParams, Statement, Vertex};
+    use gremlin_core::traversal::*;
+    use gremlin_core::{create_graph, register_named_graph, DynResult, GraphProxy, ID};
+    use pegasus_server::generated::protocol as server_pb;
+    use pegasus_server::latency::latency_recorder;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    const SCHEMA: &str = r#"
+    {
+      "vertex_type_map": { "person": 0 },
+      "edge_type_map": { "knows": 0 },
+      "vertex_prop": {
+        "person": [["id", "ID"], ["name", "String"]]
+      },
+      "edge_prop": {
+        "knows": [["start_id", "ID"], ["end_id", "ID"]]
+      }
+    }
+    "#;
+
+    fn create_store() -> LargeGraphDB<DefaultId, InternalId> {
+        let mut graph: MutableGraphDB<DefaultId, InternalId> = GraphDBConfig::default().new();
+        for (id, name) in [(1, "marko"), (2, "vadas"), (3, "lop"), (4, "josh")] {
+            let id = LDBCVertexParser::to_global_id(id, 0.into());
+            graph.add_vertex(id, [0.into(), INVALID_LABEL_ID]);
+            let row = Row::from(vec![Object::from(id as u64), Object::from(name)]);
+            graph.add_or_update_vertex_properties(id, row).unwrap();
+        }
+        graph.into_graph(LDBCGraphSchema::from_json(SCHEMA.to_owned()).unwrap())
+    }
+
+    // the milliseconds each scan is delayed by before reading any vertex
+    static SCAN_DELAY_MS: AtomicU64 = AtomicU64::new(0);
+
+    /// The graph whose scans are delayed, as the slow queries
+    struct DelayedGraph {
+        inner: Arc<dyn GraphProxy>,
+    }
+
+    impl GraphProxy for DelayedGraph {
+        fn scan_vertex(
+            &self, params: &QueryParams<Vertex>,
+        ) -> DynResult<Box<dyn Iterator<Item = Vertex> + Send>> {
+            std::thread::sleep(Duration::from_millis(SCAN_DELAY_MS.load(Ordering::SeqCst)));
+            self.inner.scan_vertex(params)
+        }
+
+        fn get_vertex(
+            &self, ids: &[ID], params: &QueryParams<Vertex>,
+        ) -> DynResult<Box<dyn Iterator<Item = Vertex> + Send>> {
+            self.inner.get_vertex(ids, params)
+        }
+
+        fn prepare_explore_vertex(
+            &self, direction: Direction, params: &QueryParams<Vertex>,
+        ) -> DynResult<Box<dyn Statement<ID, Vertex>>> {
+            self.inner.prepare_explore_vertex(direction, params)
+        }
+
+        fn prepare_explore_edge(
+            &self, direction: Direction, params: &QueryParams<Edge>,
+        ) -> DynResult<Box<dyn Statement<ID, Edge>>> {
+            self.inner.prepare_explore_edge(direction, params)
+        }
+    }
+
+    fn job_conf(job_id: u64) -> server_pb::JobConfig {
+        server_pb::JobConfig {
+            job_id,
+            job_name: "latency_test".to_owned(),
+            workers: 1,
+            ..Default::default()
+        }
+    }
+
+    // the names of the persons but one, of the same template whatever the name left out
+    fn names_but(name: &str) -> GraphTraversal {
+        Graph::traversal().v().on_graph("latency").has("name", neq(name)).values(&["name"])
+    }
+
+    // the jobs of a template delayed by 10ms mostly, and 80ms at times, are told by their
+    // percentiles bracketing the delays, where the first results are sent after the delays
+    #[test]
+    fn template_latency_test() {
+        initialize();
+        register_named_graph(
+            "latency",
+            Arc::new(DelayedGraph { inner: create_graph(create_store()) }),
+        );
+        let template = template_hash(&names_but("marko").to_request(job_conf(0)));
+        for i in 0..20 {
+            let delay_ms = if i % 10 == 9 { 80 } else { 10 };
+            SCAN_DELAY_MS.store(delay_ms, Ordering::SeqCst);
+            let name = format!("nobody{}", i);
+            let results: Vec<Object> = names_but(&name)
+                .run(job_conf(6482 + i))
+                .map(|r| r.expect("traversal failed"))
+                .collect();
+            assert_eq!(results.len(), 4);
+        }
+        // any literal is of the same template
+        assert_eq!(template_hash(&names_but("vadas").to_request(job_conf(0))), template);
+
+        let latency = latency_recorder().get(template).expect("the template is not recorded");
+        assert_eq!(latency.count, 20);
+        assert_eq!(latency.job_name, "latency_test");
+        assert!(latency.p50_us >= 10_000 && latency.p50_us < 80_000, "p50 {}", latency.p50_us);
+        assert!(latency.p95_us >= 80_000, "p95 {}", latency.p95_us);
+        assert!(latency.p99_us >= 80_000, "p99 {}", latency.p99_us);
+        let first_p50 = latency.first_result_p50_us;
+        assert!(first_p50 >= 10_000 && first_p50 <= latency.p50_us, "first p50 {}", first_p50);
+        assert!(latency.first_result_p99_us >= 80_000);
+        assert!(latency.first_result_p99_us <= latency.p99_us);
+        let top = latency_recorder().top_by_p99(0);
+        assert!(top.windows(2).all(|w| w[0].p99_us >= w[1].p99_us));
+        assert!(top.iter().any(|t| t.template_hash == template));
+    }
+}
@@ -0,0 +1,79 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use pegasus_server::JobRequest;
+
+    // g.V().out().out().count(), which visits 6 edges by the first out(), and 2 by the second
+    fn out_out_count_request(job_id: u64, edge_limit: u64) -> JobRequest {
+        let mut pb_request =
+            read_pb_request(gen_path("count_step_test_w2")).expect("read pb failed");
+        let conf = pb_request.conf.as_mut().expect("no job_conf");
+        conf.job_id = job_id;
+        conf.edge_limit = edge_limit;
+        pb_request
+    }
+
+    // g.V().outE().inV(), which accesses 6 vertices by V() and 6 by inV(), and returns the latter
+    fn scan_request(job_id: u64, vertex_limit: u64, result_limit: u64) -> JobRequest {
+        let mut pb_request = read_pb_request(gen_path("inv_step_test_01")).expect("read pb failed");
+        let conf = pb_request.conf.as_mut().expect("no job_conf");
+        conf.job_id = job_id;
+        conf.vertex_limit = vertex_limit;
+        conf.result_limit = result_limit;
+        pb_request
+    }
+
+    #[test]
+    fn edge_limit_exceeded_test_w2() {
+        initialize();
+        let requests = vec![out_out_count_request(5991, 3), out_out_count_request(5992, 0)];
+        let results = run_tests_concurrently(TestJobFactory::new(), requests, 2);
+        let err = results[0].as_ref().expect_err("the job should be aborted");
+        assert!(err.contains("LimitExceeded: the edge limit 3 is exceeded by out()"), "{}", err);
+        // the sibling query without limit is unaffected
+        assert!(results[1].is_ok());
+    }
+
+    #[test]
+    fn edge_limit_not_exceeded_test() {
+        initialize();
+        let test_job_factory = TestJobFactory::with_expect_values(vec![2.into()]);
+        run_test(test_job_factory, out_out_count_request(5993, 8));
+    }
+
+    #[test]
+    fn source_vertex_limit_exceeded_test_w2() {
+        initialize();
+        let requests = vec![scan_request(5994, 3, 0), scan_request(5995, 12, 0)];
+        let results = run_tests_concurrently(TestJobFactory::new(), requests, 2);
+        let err = results[0].as_ref().expect_err("the job should be aborted");
+        assert!(err.contains("LimitExceeded: the vertex limit 3 is exceeded by V()"), "{}", err);
+        assert!(results[1].is_ok());
+    }
+
+    #[test]
+    fn result_limit_exceeded_test_w2() {
+        initialize();
+        let requests = vec![scan_request(5996, 0, 3), scan_request(5997, 0, 6)];
+        let results = run_tests_concurrently(TestJobFactory::new(), requests, 2);
+        let err = results[0].as_ref().expect_err("the job should be aborted");
+        assert!(err.contains("LimitExceeded: the result limit 3 is exceeded by sink"), "{}", err);
+        assert!(results[1].is_ok());
+    }
+}
@@ -0,0 +1,90 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use dyn_type::Object;
+    use gremlin_core::traversal::*;
+    use pegasus_server::generated::protocol as server_pb;
+
+    fn job_conf(job_id: u64) -> server_pb::JobConfig {
+        server_pb::JobConfig {
+            job_id,
+            job_name: "local_accum_test".to_owned(),
+            workers: 2,
+            ..Default::default()
+        }
+    }
+
+    fn run_embedded(traversal: GraphTraversal, job_id: u64) -> Vec<Object> {
+        initialize();
+        traversal.run(job_conf(job_id)).map(|r| r.expect("traversal failed")).collect()
+    }
+
+    fn to_longs(results: Vec<Object>) -> Vec<i64> {
+        let mut values: Vec<i64> =
+            results.iter().map(|o| o.as_i64().expect("not an integer")).collect();
+        values.sort();
+        values
+    }
+
+    // g.V().out().count() by a barrier across the traversers, and g.V().out().fold().count(local)
+    // by counting the folded list
+    #[test]
+    fn count_global_vs_local_test() {
+        let global = to_longs(run_embedded(Graph::traversal().v().out(&[]).count(), 6378));
+        let local = Graph::traversal().v().out(&[]).fold().count_local();
+        assert_eq!(to_longs(run_embedded(local, 6379)), global);
+        assert_eq!(global, vec![6]);
+    }
+
+    // g.V().values("age").fold().sum(local)/min(local)/max(local)/mean(local)
+    #[test]
+    fn numeric_local_test() {
+        let ages = || Graph::traversal().v().values(&["age"]).fold();
+        assert_eq!(to_longs(run_embedded(ages().sum_local(), 6380)), vec![123]);
+        assert_eq!(to_longs(run_embedded(ages().min_local(), 6381)), vec![27]);
+        assert_eq!(to_longs(run_embedded(ages().max_local(), 6382)), vec![35]);
+        let mean = run_embedded(ages().mean_local(), 6383);
+        assert_eq!(mean.len(), 1);
+        assert_eq!(mean[0].as_f64().unwrap(), 30.75);
+    }
+
+    // g.V().project("out", "in").by(out().count()).by(in().count()).sum(local), which sums the
+    // columns of each record apart, the same as g.V().bothE().count() of each vertex
+    #[test]
+    fn project_local_test() {
+        let bys = || vec![Graph::anonymous().out(&[]).count(), Graph::anonymous().in_(&[]).count()];
+        let project = || Graph::traversal().v().project(&["out", "in"], bys(), false);
+        let degrees = to_longs(run_embedded(Graph::traversal().v().both_degree(&[]), 6384));
+        assert_eq!(to_longs(run_embedded(project().sum_local(), 6385)), degrees);
+        assert_eq!(to_longs(run_embedded(project().count_local(), 6386)), vec![2; 6]);
+    }
+
+    // g.V().has("name", "vadas").project("out").by(out().values("name")), whose record of only a
+    // null column is counted as 0, but summed to nothing, as vadas has no out edges; note that
+    // g.V().has("name", "nobody").fold() gives no list at all, as no traverser reaches the fold
+    #[test]
+    fn empty_local_test() {
+        let by = || vec![Graph::anonymous().out(&[]).values(&["name"])];
+        let vadas =
+            || Graph::traversal().v().has("name", eq("vadas")).project(&["out"], by(), false);
+        assert_eq!(to_longs(run_embedded(vadas().count_local(), 6387)), vec![0]);
+        assert!(run_embedded(vadas().sum_local(), 6388).is_empty());
+    }
+}
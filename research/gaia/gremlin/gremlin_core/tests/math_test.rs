@@ -0,0 +1,72 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use dyn_type::Object;
+    use gremlin_core::traversal::*;
+    use pegasus_server::generated::protocol as server_pb;
+
+    fn job_conf(job_id: u64) -> server_pb::JobConfig {
+        server_pb::JobConfig {
+            job_id,
+            job_name: "math_test".to_owned(),
+            workers: 2,
+            ..Default::default()
+        }
+    }
+
+    // the results of math() over the ages of the persons, which are 29, 27, 32 and 35
+    fn math_of_ages(expression: &str, job_id: u64) -> Vec<Object> {
+        initialize();
+        let traversal = Graph::traversal().v().values(&["age"]).math(expression);
+        traversal.run(job_conf(job_id)).map(|r| r.expect("traversal failed")).collect()
+    }
+
+    fn sorted_i64(results: Vec<Object>) -> Vec<i64> {
+        let mut values: Vec<i64> =
+            results.iter().map(|o| o.as_i64().expect("not an integer")).collect();
+        values.sort();
+        values
+    }
+
+    // g.V().values("age").math("_ * 2 + 1")
+    #[test]
+    fn math_integer_test() {
+        assert_eq!(sorted_i64(math_of_ages("_ * 2 + 1", 6353)), vec![55, 59, 65, 71]);
+    }
+
+    // g.V().values("age").math("_ * 1.5 + 3")
+    #[test]
+    fn math_float_test() {
+        let mut values: Vec<f64> = math_of_ages("_ * 1.5 + 3", 6354)
+            .iter()
+            .map(|o| o.as_f64().expect("not a float"))
+            .collect();
+        values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(values, vec![43.5, 46.5, 51.0, 55.5]);
+    }
+
+    // g.V().values("age").math("_ / (_ - 29)"), where marko of 29 divides by zero and is filtered
+    // out instead of failing the job
+    #[test]
+    fn math_division_by_zero_test() {
+        assert_eq!(sorted_i64(math_of_ages("_ / (_ - 29)", 6355)), vec![-13, 5, 10]);
+        assert!(math_of_ages("_ % 0.0", 6356).is_empty());
+    }
+}
@@ -0,0 +1,158 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use dyn_type::Object;
+    use graph_store::config::JsonConf;
+    use graph_store::ldbc::LDBCVertexParser;
+    use graph_store::prelude::{
+        DefaultId, GlobalStoreUpdate, GraphDBConfig, InternalId, LDBCGraphSchema, MutableGraphDB,
+        Row, INVALID_LABEL_ID,
+    };
+    use gremlin_core::process::shared_scan::{set_shared_scan, SharedScanConf};
+    use gremlin_core::traversal::*;
+    use gremlin_core::{create_graph, register_named_graph, unregister_named_graph, ID};
+    use pegasus_server::generated::protocol as server_pb;
+    use std::sync::Once;
+    use std::time::Duration;
+
+    static REGISTER: Once = Once::new();
+
+    const TINY_SCHEMA: &str = r#"
+    {
+      "vertex_type_map": { "person": 0 },
+      "edge_type_map": { "knows": 0 },
+      "vertex_prop": {
+        "person": [["id", "ID"], ["name", "String"], ["age", "Integer"]]
+      },
+      "edge_prop": {
+        "knows": [["start_id", "ID"], ["end_id", "ID"], ["weight", "Double"]]
+      }
+    }
+    "#;
+
+    // a graph of the persons alice and bob, where alice knows bob, of the same ids as marko and
+    // vadas of the modern graph
+    fn register_tiny_graph() {
+        initialize();
+        REGISTER.call_once(|| {
+            let mut graph: MutableGraphDB<DefaultId, InternalId> = GraphDBConfig::default().new();
+            let v1: DefaultId = LDBCVertexParser::to_global_id(1, 0.into());
+            let v2: DefaultId = LDBCVertexParser::to_global_id(2, 0.into());
+            graph.add_vertex(v1, [0.into(), INVALID_LABEL_ID]);
+            graph.add_vertex(v2, [0.into(), INVALID_LABEL_ID]);
+            graph
+                .add_edge_with_properties(v1, v2, 0.into(), Row::from(vec![Object::from(0.5)]))
+                .unwrap();
+            let alice = vec![Object::from(1), Object::from("alice"), Object::from(21)];
+            let bob = vec![Object::from(2), Object::from("bob"), Object::from(23)];
+            graph.add_or_update_vertex_properties(v1, Row::from(alice)).unwrap();
+            graph.add_or_update_vertex_properties(v2, Row::from(bob)).unwrap();
+            let schema = LDBCGraphSchema::from_json(TINY_SCHEMA.to_owned()).expect("bad schema");
+            register_named_graph("tiny", create_graph(graph.into_graph(schema)));
+        });
+    }
+
+    fn job_conf(job_id: u64) -> server_pb::JobConfig {
+        server_pb::JobConfig {
+            job_id,
+            job_name: "named_graph_test".to_owned(),
+            workers: 2,
+            ..Default::default()
+        }
+    }
+
+    fn names(results: ResultStream<Object>) -> Vec<String> {
+        let mut names: Vec<String> = results
+            .map(|r| r.expect("traversal failed").as_str().expect("not a name").into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    // g.V().values("name") on the default graph and the tiny one
+    #[test]
+    fn named_graph_values_test() {
+        register_tiny_graph();
+        let modern = Graph::traversal().v().values(&["name"]).run(job_conf(6326));
+        let tiny = Graph::traversal().v().values(&["name"]).on_graph("tiny").run(job_conf(6327));
+        assert_eq!(names(modern), vec!["josh", "lop", "marko", "peter", "ripple", "vadas"]);
+        assert_eq!(names(tiny), vec!["alice", "bob"]);
+    }
+
+    // g.V(1).out().values("name"), where vertex 1 is marko in the modern graph and alice in the
+    // tiny one
+    #[test]
+    fn named_graph_explore_test() {
+        register_tiny_graph();
+        let out_names = |graph: &str, job_id: u64| {
+            let traversal = Graph::traversal().v_ids(&[to_global_id(1) as ID]);
+            let traversal = traversal.out(&[]).values(&["name"]).on_graph(graph);
+            names(traversal.run(job_conf(job_id)))
+        };
+        assert_eq!(out_names("", 6328), vec!["josh", "lop", "vadas"]);
+        assert_eq!(out_names("tiny", 6329), vec!["bob"]);
+    }
+
+    // g.V().count(), which may be answered by the statistics of each graph
+    #[test]
+    fn named_graph_count_test() {
+        register_tiny_graph();
+        let count = |graph: &str, job_id: u64| {
+            let traversal = Graph::traversal().v().count().on_graph(graph);
+            let results: Vec<_> = traversal.run(job_conf(job_id)).collect();
+            results[0].as_ref().expect("count failed").as_u64().expect("not a count")
+        };
+        assert_eq!(count("", 6330), 6);
+        assert_eq!(count("tiny", 6331), 2);
+    }
+
+    // the scans of the jobs on different graphs at the same time are never shared
+    #[test]
+    fn named_graph_shared_scan_test() {
+        register_tiny_graph();
+        set_shared_scan(Some(SharedScanConf {
+            window: Duration::from_millis(200),
+            ..Default::default()
+        }));
+        let modern = Graph::traversal().v().values(&["name"]).run(job_conf(6332));
+        let tiny = Graph::traversal().v().values(&["name"]).on_graph("tiny").run(job_conf(6333));
+        let (modern, tiny) = (names(modern), names(tiny));
+        set_shared_scan(None);
+        assert_eq!(modern, vec!["josh", "lop", "marko", "peter", "ripple", "vadas"]);
+        assert_eq!(tiny, vec!["alice", "bob"]);
+    }
+
+    #[test]
+    fn unknown_graph_test() {
+        register_tiny_graph();
+        let traversal = Graph::traversal().v().on_graph("absent");
+        let results: Vec<_> = traversal.run(job_conf(6334)).collect();
+        assert_eq!(results.len(), 1);
+        let err = results[0].as_ref().expect_err("unknown graph is accepted").to_string();
+        assert!(err.contains("graph \"absent\" is not registered"), "{}", err);
+        // the graph removed is unknown to the jobs submitted later
+        register_named_graph("removed", gremlin_core::get_graph().expect("no default graph"));
+        assert!(unregister_named_graph("removed"));
+        assert!(!unregister_named_graph("removed"));
+        let results: Vec<_> =
+            Graph::traversal().v().on_graph("removed").run(job_conf(6335)).collect();
+        assert!(results[0].is_err());
+    }
+}
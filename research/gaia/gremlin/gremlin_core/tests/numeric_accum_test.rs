@@ -0,0 +1,128 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use gremlin_core::generated::gremlin as pb;
+    use pegasus_server::generated::protocol as server_pb;
+    use pegasus_server::JobRequest;
+    use prost::Message;
+
+    // g.V().values(props).sum()/min()/max()/mean(), built from the plan of g.V().values("id")
+    fn numeric_accum_request(props: Vec<&str>, kind: pb::numeric_accum_step::Kind) -> JobRequest {
+        let mut pb_request =
+            read_pb_request(gen_path("values_step_test_01")).expect("read pb failed");
+        let plan = pb_request.plan.as_mut().expect("plan not found");
+        for op in plan.plan.iter_mut() {
+            if let Some(server_pb::operator_def::OpKind::FlatMap(flat_map)) = op.op_kind.as_mut() {
+                let mut step = pb::GremlinStep::decode(flat_map.resource.as_slice())
+                    .expect("decode step failed");
+                if let Some(pb::gremlin_step::Step::PropertiesStep(values)) = step.step.as_mut() {
+                    values.properties = props.iter().map(|p| p.to_string()).collect();
+                }
+                flat_map.resource.clear();
+                step.encode(&mut flat_map.resource).expect("encode step failed");
+            }
+        }
+        let step = pb::GremlinStep {
+            tags: vec![],
+            remove_tags: vec![],
+            step: Some(pb::gremlin_step::Step::NumericAccumStep(pb::NumericAccumStep {
+                kind: kind as i32,
+                local: false,
+            })),
+        };
+        let mut resource = vec![];
+        step.encode(&mut resource).expect("encode step failed");
+        let accum = match kind {
+            pb::numeric_accum_step::Kind::Sum => server_pb::AccumKind::Sum,
+            pb::numeric_accum_step::Kind::Min => server_pb::AccumKind::Min,
+            pb::numeric_accum_step::Kind::Max => server_pb::AccumKind::Max,
+            pb::numeric_accum_step::Kind::Mean => server_pb::AccumKind::Mean,
+            pb::numeric_accum_step::Kind::Count => server_pb::AccumKind::Cnt,
+        };
+        let fold = server_pb::Fold {
+            range: server_pb::Range::Global as i32,
+            accum: accum as i32,
+            resource,
+            unfold: Some(server_pb::FlatMap { resource: vec![] }),
+            ..Default::default()
+        };
+        plan.plan.push(server_pb::OperatorDef {
+            op_kind: Some(server_pb::operator_def::OpKind::Fold(fold)),
+            ..Default::default()
+        });
+        pb_request
+    }
+
+    fn run_numeric_accum(
+        props: Vec<&str>, kind: pb::numeric_accum_step::Kind, expected: TestJobFactory, job_id: u64,
+    ) {
+        initialize();
+        run_test_with_job_id(expected, numeric_accum_request(props, kind), job_id, 2);
+    }
+
+    // g.V().values("age").sum()
+    #[test]
+    fn sum_test_01() {
+        let expected = TestJobFactory::with_expect_values(vec![123.into()]);
+        run_numeric_accum(vec!["age"], pb::numeric_accum_step::Kind::Sum, expected, 6011);
+    }
+
+    // g.V().values("id", "age").sum(), with ids of i64 and ages of i32 summed as i64
+    #[test]
+    fn sum_mixed_test_01() {
+        let expected = TestJobFactory::with_expect_values(vec![144i64.into()]);
+        run_numeric_accum(vec!["id", "age"], pb::numeric_accum_step::Kind::Sum, expected, 6012);
+    }
+
+    // g.V().values("id", "age").min()
+    #[test]
+    fn min_mixed_test_01() {
+        let expected = TestJobFactory::with_expect_values(vec![1.into()]);
+        run_numeric_accum(vec!["id", "age"], pb::numeric_accum_step::Kind::Min, expected, 6013);
+    }
+
+    // g.V().values("age").max()
+    #[test]
+    fn max_test_01() {
+        let expected = TestJobFactory::with_expect_values(vec![35.into()]);
+        run_numeric_accum(vec!["age"], pb::numeric_accum_step::Kind::Max, expected, 6014);
+    }
+
+    // g.V().values("age").mean()
+    #[test]
+    fn mean_test_01() {
+        let expected = TestJobFactory::with_expect_values(vec![30.75f64.into()]);
+        run_numeric_accum(vec!["age"], pb::numeric_accum_step::Kind::Mean, expected, 6015);
+    }
+
+    // g.V().values("missing").min()/max()/mean(), which output nothing instead of zero
+    #[test]
+    fn accum_all_missing_test_01() {
+        let kinds = vec![
+            pb::numeric_accum_step::Kind::Min,
+            pb::numeric_accum_step::Kind::Max,
+            pb::numeric_accum_step::Kind::Mean,
+        ];
+        for (i, kind) in kinds.into_iter().enumerate() {
+            let expected = TestJobFactory::with_expect_result_num(0);
+            run_numeric_accum(vec!["missing"], kind, expected, 6016 + i as u64);
+        }
+    }
+}
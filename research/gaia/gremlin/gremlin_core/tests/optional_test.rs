@@ -0,0 +1,68 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use dyn_type::Object;
+    use gremlin_core::traversal::*;
+    use gremlin_core::ID;
+    use pegasus_server::generated::protocol as server_pb;
+
+    fn job_conf(job_id: u64, workers: u32) -> server_pb::JobConfig {
+        server_pb::JobConfig {
+            job_id,
+            job_name: "optional_test".to_owned(),
+            workers,
+            ..Default::default()
+        }
+    }
+
+    fn run(traversal: GraphTraversal, conf: server_pb::JobConfig) -> Vec<Object> {
+        traversal.run(conf).map(|r| r.expect("traversal failed")).collect()
+    }
+
+    // g.V().optional(out("knows")), where marko knows vadas and josh, and the others pass through
+    #[test]
+    fn optional_test_01() {
+        initialize();
+        let traversal = Graph::traversal().v().optional(Graph::anonymous().out(&[0]));
+        let results = run(traversal, job_conf(6267, 2));
+        let mut ids: Vec<ID> =
+            results.iter().map(|v| v.as_u128().expect("not an id") as ID).collect();
+        ids.sort();
+        let mut expected = to_global_ids(vec![2, 2, 3, 4, 4, 5, 6]);
+        expected.sort();
+        assert_eq!(ids, expected);
+    }
+
+    // g.V().optional(out("knows")).values("name")
+    #[test]
+    fn optional_test_02() {
+        initialize();
+        let traversal =
+            Graph::traversal().v().optional(Graph::anonymous().out(&[0])).values(&["name"]);
+        let mut names = run(traversal, job_conf(6268, 2));
+        names.sort_by(|a, b| a.partial_cmp(b).expect("incomparable names"));
+        let expected: Vec<Object> =
+            vec!["josh", "josh", "lop", "peter", "ripple", "vadas", "vadas"]
+                .into_iter()
+                .map(|name| name.into())
+                .collect();
+        assert_eq!(names, expected);
+    }
+}
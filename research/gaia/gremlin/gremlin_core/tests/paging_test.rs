@@ -0,0 +1,117 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use gremlin_core::compiler::GremlinJobCompiler;
+    use gremlin_core::generated::protobuf as result_pb;
+    use gremlin_core::traversal::*;
+    use gremlin_core::Partition;
+    use pegasus_server::generated::protocol as server_pb;
+    use pegasus_server::service::{Output, Service};
+    use pegasus_server::{JobResponse, JobResult};
+    use prost::Message;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct CollectOutput {
+        results: Arc<Mutex<Vec<JobResult>>>,
+    }
+
+    impl Output for CollectOutput {
+        fn send(&self, res: JobResponse) {
+            if let Some(result) = res.result {
+                self.results.lock().unwrap().push(result);
+            }
+        }
+
+        fn close(&self) {}
+    }
+
+    fn count_rows(data: &[Vec<u8>]) -> usize {
+        let mut rows = 0;
+        for bytes in data {
+            let result = result_pb::Result::decode(&bytes[0..]).expect("decode result failure");
+            match result.inner {
+                Some(result_pb::result::Inner::Elements(elements)) => rows += elements.item.len(),
+                other => panic!("unexpected result {:?}", other),
+            }
+        }
+        rows
+    }
+
+    // g.V().both()...both().limit(10000) fetched by pages of 1000 results
+    #[test]
+    fn fetch_result_pages_test() {
+        initialize();
+        let job_id = 6250;
+        let mut traversal = Graph::traversal().v();
+        for _ in 0..9 {
+            traversal = traversal.both(&[]);
+        }
+        let conf = server_pb::JobConfig {
+            job_id,
+            job_name: "paging_test".to_owned(),
+            workers: 2,
+            page_size: 1000,
+            ..Default::default()
+        };
+        let request = traversal.limit(10000).to_request(conf);
+        let service = Service::new(GremlinJobCompiler::new(Partition { num_servers: 1 }, 1, 0));
+        let output = CollectOutput::default();
+        service.accept(request, output.clone());
+        // the job completes without the pages being fetched
+        if let Some(guard) = service.job_guards.write().unwrap().get_mut(&job_id) {
+            guard.join().expect("job failed");
+        }
+        let results = output.results.lock().unwrap().clone();
+        match results.first() {
+            Some(JobResult::Cursor(cursor)) => assert_eq!(cursor.page_size, 1000),
+            other => panic!("expect the cursor of the job, but got {:?}", other),
+        }
+        // the results are not sent through the output of the job
+        assert!(!results.iter().any(|res| matches!(res, JobResult::Data(_))));
+
+        let fetch = |token| {
+            let req = server_pb::FetchPageRequest { job_id, token, wait_ms: 1000 };
+            service.fetch_page(req)
+        };
+        let mut token = 0;
+        let mut total = 0;
+        loop {
+            let page = fetch(token);
+            assert!(page.err.is_none(), "fetch page {} failure: {:?}", token, page.err);
+            let rows = count_rows(&page.data);
+            assert!(rows <= 1000);
+            total += rows;
+            if page.last {
+                break;
+            }
+            assert_eq!(page.next_token, token + 1);
+            token = page.next_token;
+        }
+        assert_eq!(total, 10000);
+        // the pages before the fetched one are released
+        assert!(fetch(0).err.is_some());
+
+        let cancelled = service.cancel_cursor(server_pb::CancelCursorRequest { job_id });
+        assert!(cancelled.cancelled);
+        assert!(fetch(token).err.is_some());
+        assert!(!service.cancel_cursor(server_pb::CancelCursorRequest { job_id }).cancelled);
+    }
+}
@@ -0,0 +1,64 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use graph_store::partition::ExplicitPartition;
+    use gremlin_core::process::metrics::{get_worker_counter, EXPAND_COUNTER};
+    use gremlin_core::GraphPartitioner;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    // g.V(1).out(), built from the plan of g.V().out() and the source of g.V(1)
+    fn out_from_one_request() -> pegasus_server::JobRequest {
+        let mut pb_request = read_pb_request(gen_path("out_step_test_01")).expect("read pb failed");
+        let src_request = read_pb_request(gen_path("source_test_02")).expect("read pb failed");
+        pb_request.source = src_request.source;
+        pb_request
+    }
+
+    // g.V(1).out() with the vertex 1 explicitly mapped to the partition of worker 0, while it
+    // is processed by worker 1 following the default hash partition (1 / 1 % 2 = 1)
+    #[test]
+    fn explicit_partition_test_w2() {
+        initialize();
+        let vid = to_global_id(1);
+        let mut mapping = HashMap::new();
+        mapping.insert(vid, 0);
+        let partition = ExplicitPartition::new(mapping, 2);
+        let mut expected = to_global_ids(vec![2, 3, 4]);
+        expected.sort();
+        let mut test_job_factory = TestJobFactory::with_expect_ids(expected);
+        test_job_factory.set_partitioner(GraphPartitioner::new(Arc::new(partition), 1));
+        run_test_with_job_id(test_job_factory, out_from_one_request(), 5941, 2);
+        assert_eq!(get_worker_counter(5941, 0, EXPAND_COUNTER), 1);
+        assert_eq!(get_worker_counter(5941, 1, EXPAND_COUNTER), 0);
+    }
+
+    // the same query with the default hash partition
+    #[test]
+    fn hash_partition_test_w2() {
+        initialize();
+        let mut expected = to_global_ids(vec![2, 3, 4]);
+        expected.sort();
+        let test_job_factory = TestJobFactory::with_expect_ids(expected);
+        run_test_with_job_id(test_job_factory, out_from_one_request(), 5942, 2);
+        assert_eq!(get_worker_counter(5942, 0, EXPAND_COUNTER), 0);
+        assert_eq!(get_worker_counter(5942, 1, EXPAND_COUNTER), 1);
+    }
+}
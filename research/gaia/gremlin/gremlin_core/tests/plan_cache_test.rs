@@ -0,0 +1,73 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use gremlin_core::plan_cache::PlanCache;
+    use gremlin_core::traversal::*;
+    use pegasus_server::generated::protocol as server_pb;
+    use std::sync::Arc;
+
+    fn run_has_name(plan_cache: &Arc<PlanCache>, name: &str, expected: usize, job_id: u64) {
+        run_has_name_with_workers(plan_cache, name, expected, job_id, 1);
+    }
+
+    fn run_has_name_with_workers(
+        plan_cache: &Arc<PlanCache>, name: &str, expected: usize, job_id: u64, workers: u32,
+    ) {
+        initialize();
+        let conf = server_pb::JobConfig {
+            job_id,
+            job_name: "plan_cache_test".to_owned(),
+            workers,
+            ..Default::default()
+        };
+        let request = Graph::traversal().v().has("name", eq(name)).to_request(conf);
+        let test_job_factory = TestJobFactory::with_expect_ids(to_global_ids(vec![expected]))
+            .with_plan_cache(plan_cache.clone());
+        run_test_with_job_id(test_job_factory, request, job_id, workers);
+    }
+
+    // g.V().has("name", "marko") and g.V().has("name", "vadas") only differ in the literal, where
+    // the source is cached besides the has step, as it is decoded to estimate its size
+    #[test]
+    fn plan_cache_rebind_test() {
+        let plan_cache = Arc::new(PlanCache::default());
+        run_has_name(&plan_cache, "marko", 1, 6050);
+        assert_eq!(plan_cache.misses(), 2);
+        assert_eq!(plan_cache.decodes(), 2);
+        // hit the same entries, while still giving vadas instead of marko
+        run_has_name(&plan_cache, "vadas", 2, 6051);
+        assert_eq!(plan_cache.hits(), 2);
+        assert_eq!(plan_cache.decodes(), 2);
+        assert_eq!(plan_cache.len(), 2);
+        run_has_name(&plan_cache, "marko", 1, 6052);
+        assert_eq!(plan_cache.hits(), 4);
+        assert_eq!(plan_cache.decodes(), 2);
+    }
+
+    // the steps are bound once for the job, and shared by its workers through the job resources
+    #[test]
+    fn plan_cache_bind_once_per_job_test() {
+        let plan_cache = Arc::new(PlanCache::default());
+        run_has_name_with_workers(&plan_cache, "marko", 1, 6317, 4);
+        assert_eq!(plan_cache.misses(), 2);
+        assert_eq!(plan_cache.hits(), 0);
+        assert_eq!(plan_cache.decodes(), 2);
+    }
+}
@@ -0,0 +1,108 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use dyn_type::Object;
+    use gremlin_core::traversal::*;
+    use gremlin_core::ID;
+    use pegasus_server::generated::protocol as server_pb;
+
+    fn job_conf(job_id: u64) -> server_pb::JobConfig {
+        server_pb::JobConfig {
+            job_id,
+            job_name: "prepared_test".to_owned(),
+            workers: 2,
+            ..Default::default()
+        }
+    }
+
+    fn run_prepared(prepared: &PreparedTraversal, params: &[Object], job_id: u64) -> Vec<ID> {
+        initialize();
+        let results = prepared.run(params, job_conf(job_id)).expect("bind parameters failed");
+        let mut ids: Vec<ID> = results
+            .map(|r| r.expect("traversal failed").as_u128().expect("cannot cast to u128") as ID)
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    // g.V().has("name", x) with x bound to marko, josh and nobody in turn
+    #[test]
+    fn prepared_rebind_test() {
+        let prepared = Graph::traversal().v().has("name", eq("marko")).prepare().unwrap();
+        assert_eq!(prepared.slots(), &["str"]);
+        assert_eq!(run_prepared(&prepared, &["marko".into()], 6340), to_global_ids(vec![1]));
+        assert_eq!(run_prepared(&prepared, &["josh".into()], 6341), to_global_ids(vec![4]));
+        assert!(run_prepared(&prepared, &["nobody".into()], 6342).is_empty());
+    }
+
+    // g.V().has("name", x).out().has("name", y), whose has steps are of the same shape, but are
+    // bound to their own parameters
+    #[test]
+    fn prepared_same_shape_test() {
+        let prepared = Graph::traversal()
+            .v()
+            .has("name", eq("marko"))
+            .out(&[])
+            .has("name", eq("vadas"))
+            .prepare()
+            .unwrap();
+        assert_eq!(prepared.slots(), &["str", "str"]);
+        let params = vec!["marko".into(), "josh".into()];
+        assert_eq!(run_prepared(&prepared, &params, 6343), to_global_ids(vec![4]));
+        let params = vec!["josh".into(), "ripple".into()];
+        assert_eq!(run_prepared(&prepared, &params, 6344), to_global_ids(vec![5]));
+    }
+
+    // g.V().has("age", gt(x)), whose slot takes the ints only
+    #[test]
+    fn prepared_type_mismatch_test() {
+        let prepared = Graph::traversal().v().has("age", gt(30)).prepare().unwrap();
+        assert_eq!(prepared.slots(), &["i32"]);
+        assert_eq!(run_prepared(&prepared, &[Object::from(30)], 6345), to_global_ids(vec![4, 6]));
+        let err = prepared.run(&["30".into()], job_conf(6346)).err().expect("str bound to int");
+        assert!(err.msg.contains("parameter 0 of str doesn't fit the slot of i32"), "{}", err);
+        let err = prepared.run(&[Object::from(30i64)], job_conf(6346)).err();
+        assert!(err.is_some());
+    }
+
+    #[test]
+    fn prepared_param_count_test() {
+        let prepared = Graph::traversal().v().has("name", eq("marko")).prepare().unwrap();
+        let err = prepared.run(&[], job_conf(6347)).err().expect("no parameter bound");
+        assert_eq!(err.msg, "0 parameters are given to 1 slots");
+        let params = vec!["marko".into(), "josh".into()];
+        assert!(prepared.run(&params, job_conf(6347)).is_err());
+    }
+
+    // the prepared traversal gives the same results as the traversal with the same literals
+    #[test]
+    fn prepared_same_results_test() {
+        initialize();
+        let traversal = || Graph::traversal().v().has("age", lte(29)).out(&[]);
+        let prepared = traversal().prepare().unwrap();
+        let mut expected: Vec<ID> = traversal()
+            .run(job_conf(6348))
+            .map(|r| r.expect("traversal failed").as_u128().expect("cannot cast to u128") as ID)
+            .collect();
+        expected.sort();
+        assert!(!expected.is_empty());
+        assert_eq!(run_prepared(&prepared, &[Object::from(29)], 6349), expected);
+    }
+}
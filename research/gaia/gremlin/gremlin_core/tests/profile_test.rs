@@ -0,0 +1,60 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use gremlin_core::traversal::*;
+    use pegasus_server::generated::protocol as server_pb;
+
+    fn job_conf(job_id: u64, workers: u32) -> server_pb::JobConfig {
+        server_pb::JobConfig {
+            job_id,
+            job_name: "profile_test".to_owned(),
+            workers,
+            ..Default::default()
+        }
+    }
+
+    fn total_records(profile: &server_pb::JobProfile, name: &str) -> u64 {
+        let op = profile.operators.iter().find(|op| op.name == name);
+        op.unwrap_or_else(|| panic!("operator {} not profiled", name)).total_records
+    }
+
+    // g.V().has("name", "marko").out().count().profile()
+    #[test]
+    fn profile_steps_test() {
+        initialize();
+        let traversal = Graph::traversal().v().has("name", eq("marko")).out(&[]).count().profile();
+        let mut results = traversal.run(job_conf(6240, 2));
+        let values: Vec<_> = results.by_ref().map(|r| r.expect("traversal failed")).collect();
+        assert_eq!(values, vec![3u64.into()]);
+        let profile = results.profile().expect("profile not sent");
+        assert_eq!(total_records(&profile, "has[name eq marko]"), 6);
+        assert_eq!(total_records(&profile, "out"), 1);
+        assert_eq!(total_records(&profile, "count[global]"), 3);
+    }
+
+    #[test]
+    fn not_profiled_test() {
+        initialize();
+        let traversal = Graph::traversal().v().has("name", eq("marko")).out(&[]);
+        let mut results = traversal.run(job_conf(6241, 2));
+        assert_eq!(results.by_ref().count(), 3);
+        assert!(results.profile().is_none());
+    }
+}
@@ -0,0 +1,60 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use gremlin_core::traversal::*;
+    use pegasus_server::generated::protocol as server_pb;
+
+    fn job_conf(job_id: u64, workers: u32) -> server_pb::JobConfig {
+        server_pb::JobConfig {
+            job_id,
+            job_name: "progress_test".to_owned(),
+            workers,
+            ..Default::default()
+        }
+    }
+
+    // g.V().values("name"), whose scan expects the vertices of the modern graph by its statistics
+    #[test]
+    fn scan_progress_test() {
+        initialize();
+        for (job_id, workers) in [(6336, 1), (6337, 2), (6338, 4)] {
+            let traversal = Graph::traversal().v().values(&["name"]);
+            let results: Vec<_> = traversal.run(job_conf(job_id, workers)).collect();
+            assert_eq!(results.len(), 6);
+            let progress = pegasus::peek_progress(job_id).expect("job not tracked");
+            assert_eq!(progress.sources.len(), 1);
+            assert_eq!(progress.sources[0].expected, Some(6));
+            assert_eq!(progress.sources[0].consumed, 6);
+            assert_eq!(progress.fraction(), Some(1.0));
+        }
+    }
+
+    // g.V(1).out(), whose scan expects the ids given
+    #[test]
+    fn ids_progress_test() {
+        initialize();
+        let traversal = Graph::traversal().v_ids(&to_global_ids(vec![1, 2])).out(&[]);
+        let results: Vec<_> = traversal.run(job_conf(6339, 2)).collect();
+        assert_eq!(results.len(), 3);
+        let progress = pegasus::peek_progress(6339).expect("job not tracked");
+        assert_eq!(progress.sources[0].expected, Some(2));
+        assert_eq!(progress.sources[0].consumed, 2);
+    }
+}
@@ -0,0 +1,138 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use dyn_type::Object;
+    use gremlin_core::process::traversal::step::ProjectRecord;
+    use gremlin_core::traversal::*;
+    use pegasus_server::generated::protocol as server_pb;
+
+    fn job_conf(job_id: u64, workers: u32) -> server_pb::JobConfig {
+        server_pb::JobConfig {
+            job_id,
+            job_name: "project_test".to_owned(),
+            workers,
+            ..Default::default()
+        }
+    }
+
+    // the records sorted by their names
+    fn records(traversal: GraphTraversal, conf: server_pb::JobConfig) -> Vec<ProjectRecord> {
+        let mut records: Vec<ProjectRecord> = traversal
+            .run(conf)
+            .map(|r| match r.expect("traversal failed") {
+                Object::DynOwned(x) => {
+                    x.try_downcast_ref::<ProjectRecord>().expect("not a record").clone()
+                }
+                other => panic!("unexpected result {:?}", other),
+            })
+            .collect();
+        records.sort_by_key(name_of);
+        records
+    }
+
+    fn name_of(record: &ProjectRecord) -> String {
+        record.get("name").and_then(|name| name.as_str().ok()).unwrap_or_default().into_owned()
+    }
+
+    // g.V().project("name", "degree").by("name").by(out().count())
+    #[test]
+    fn project_count_test() {
+        initialize();
+        let bys = vec![Graph::anonymous().values(&["name"]), Graph::anonymous().out(&[]).count()];
+        let traversal = Graph::traversal().v().project(&["name", "degree"], bys, false);
+        let records = records(traversal, job_conf(6313, 2));
+        let degrees: Vec<(String, i64)> = records
+            .iter()
+            .map(|r| (name_of(r), r.get("degree").unwrap().as_i64().unwrap()))
+            .collect();
+        let expected =
+            vec![("josh", 2), ("lop", 0), ("marko", 3), ("peter", 1), ("ripple", 0), ("vadas", 0)];
+        let expected: Vec<(String, i64)> =
+            expected.into_iter().map(|(name, degree)| (name.to_owned(), degree)).collect();
+        assert_eq!(degrees, expected);
+        for record in records.iter() {
+            let columns: Vec<&str> = record.columns().iter().map(|(c, _)| c.as_str()).collect();
+            assert_eq!(columns, vec!["name", "degree"]);
+        }
+    }
+
+    // g.V().project("name", "friend").by("name").by(out("knows").values("name")), where only
+    // marko knows vadas and josh, so the others miss the friend column
+    #[test]
+    fn project_missing_column_test() {
+        initialize();
+        let bys = vec![
+            Graph::anonymous().values(&["name"]),
+            Graph::anonymous().out(&[0]).values(&["name"]),
+        ];
+        let traversal = Graph::traversal().v().project(&["name", "friend"], bys, false);
+        let records = records(traversal, job_conf(6314, 1));
+        assert_eq!(records.len(), 6);
+        for record in records.iter() {
+            if name_of(record) == "marko" {
+                let friend = record.get("friend").unwrap().as_str().unwrap().into_owned();
+                assert!(friend == "vadas" || friend == "josh");
+            } else {
+                assert!(!record.contains("friend"));
+            }
+        }
+    }
+
+    // the same as above, where the missing columns are null
+    #[test]
+    fn project_null_if_empty_test() {
+        initialize();
+        let bys = vec![
+            Graph::anonymous().values(&["name"]),
+            Graph::anonymous().out(&[0]).values(&["name"]),
+        ];
+        let traversal = Graph::traversal().v().project(&["name", "friend"], bys, true);
+        let records = records(traversal, job_conf(6315, 2));
+        assert_eq!(records.len(), 6);
+        for record in records.iter().filter(|r| name_of(r) != "marko") {
+            assert!(record.contains("friend"));
+            assert!(record.get("friend").is_none());
+        }
+    }
+
+    // g.V().project("name", "age").by("name").by("age"), where the software has no age
+    #[test]
+    fn project_properties_test() {
+        initialize();
+        let bys = vec![Graph::anonymous().values(&["name"]), Graph::anonymous().values(&["age"])];
+        let traversal = Graph::traversal().v().project(&["name", "age"], bys, false);
+        let records = records(traversal, job_conf(6316, 2));
+        let ages: Vec<(String, Option<i64>)> = records
+            .iter()
+            .map(|r| (name_of(r), r.get("age").map(|age| age.as_i64().unwrap())))
+            .collect();
+        let expected = vec![
+            ("josh", Some(32)),
+            ("lop", None),
+            ("marko", Some(29)),
+            ("peter", Some(35)),
+            ("ripple", None),
+            ("vadas", Some(27)),
+        ];
+        let expected: Vec<(String, Option<i64>)> =
+            expected.into_iter().map(|(name, age)| (name.to_owned(), age)).collect();
+        assert_eq!(ages, expected);
+    }
+}
@@ -0,0 +1,123 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use dyn_type::Object;
+    use gremlin_core::process::traversal::step::projection::CompositeKey;
+    use gremlin_core::process::traversal::step::result_downcast::{
+        try_downcast_group_count_value, try_downcast_group_key,
+    };
+    use gremlin_core::traversal::*;
+    use gremlin_core::ID;
+    use pegasus_server::generated::protocol as server_pb;
+
+    fn job_conf(job_id: u64) -> server_pb::JobConfig {
+        server_pb::JobConfig {
+            job_id,
+            job_name: "projection_test".to_owned(),
+            workers: 2,
+            ..Default::default()
+        }
+    }
+
+    fn run_ids(traversal: GraphTraversal, job_id: u64) -> Vec<ID> {
+        initialize();
+        traversal
+            .run(job_conf(job_id))
+            .map(|r| r.expect("traversal failed").as_u128().expect("not an id") as ID)
+            .collect()
+    }
+
+    // the values of a composite key, where a missing value is `None`
+    fn key_values(key: &Object) -> Vec<Option<Object>> {
+        let key = key.as_custom().expect("not a custom value");
+        key.to_value::<CompositeKey>().expect("not a composite key").values().to_vec()
+    }
+
+    // g.V().order().by(values("age", "name")), where the software without ages sort last by names
+    #[test]
+    fn order_by_composite_test() {
+        let traversal =
+            Graph::traversal().v().order_by_projection(by_keys(&["age", "name"]), false);
+        let expected = to_global_ids(vec![2, 1, 4, 6, 3, 5]);
+        assert_eq!(run_ids(traversal, 6459), expected);
+        // the persons without langs sort last by names
+        let traversal =
+            Graph::traversal().v().order_by_projection(by_keys(&["lang", "name"]), false);
+        let expected = to_global_ids(vec![3, 5, 4, 1, 6, 2]);
+        assert_eq!(run_ids(traversal, 6460), expected);
+    }
+
+    // g.V().order().by(math("age * 2 - 50"), desc), where the software without ages sort last
+    #[test]
+    fn order_by_expression_test() {
+        let traversal = Graph::traversal().v().order_by_projection(by_expr("age * 2 - 50"), true);
+        let ids = run_ids(traversal, 6461);
+        assert_eq!(ids[..4].to_vec(), to_global_ids(vec![6, 4, 1, 2]));
+        let mut software = ids[4..].to_vec();
+        software.sort();
+        let mut expected = to_global_ids(vec![3, 5]);
+        expected.sort();
+        assert_eq!(software, expected);
+    }
+
+    // g.V().order().by(values("lang", "age")).groupCount().by(values("lang", "age")), where the
+    // software of the same lang without ages are grouped together
+    #[test]
+    fn order_and_group_by_composite_test() {
+        initialize();
+        let by = || by_keys(&["lang", "age"]);
+        let traversal =
+            Graph::traversal().v().order_by_projection(by(), false).group_count_by(by());
+        let mut groups: Vec<(Vec<Option<Object>>, u64)> = traversal
+            .run(job_conf(6462))
+            .map(|r| {
+                let pair = r.expect("traversal failed");
+                let key = try_downcast_group_key(&pair).expect("not a group pair");
+                let key = key_values(key.get_object().expect("key is not a value"));
+                let count = try_downcast_group_count_value(&pair).expect("not a count");
+                (key, count)
+            })
+            .collect();
+        groups.sort_by(|(left, _), (right, _)| {
+            let age = |key: &[Option<Object>]| key[1].as_ref().and_then(|a| a.as_i64().ok());
+            age(left).cmp(&age(right))
+        });
+        let expected = vec![
+            (vec![Some(Object::from("java")), None], 2),
+            (vec![None, Some(Object::from(27))], 1),
+            (vec![None, Some(Object::from(29))], 1),
+            (vec![None, Some(Object::from(32))], 1),
+            (vec![None, Some(Object::from(35))], 1),
+        ];
+        assert_eq!(groups, expected);
+    }
+
+    // g.V().dedup().by(values("lang")), where the persons without langs are duplicates
+    #[test]
+    fn dedup_by_composite_test() {
+        let mut ids = run_ids(Graph::traversal().v().dedup_by(by_keys(&["lang"])), 6463);
+        ids.sort();
+        assert_eq!(ids.len(), 2);
+        let software = to_global_ids(vec![3, 5]);
+        let persons = to_global_ids(vec![1, 2, 4, 6]);
+        assert_eq!(ids.iter().filter(|id| software.contains(id)).count(), 1);
+        assert_eq!(ids.iter().filter(|id| persons.contains(id)).count(), 1);
+    }
+}
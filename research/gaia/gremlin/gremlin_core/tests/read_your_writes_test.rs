@@ -0,0 +1,142 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use dyn_type::Object;
+    use graph_store::config::JsonConf;
+    use graph_store::ldbc::LDBCVertexParser;
+    use graph_store::prelude::{
+        DefaultId, GlobalStoreUpdate, GraphDBConfig, InternalId, LDBCGraphSchema, LargeGraphDB,
+        MutableGraphDB, Row, INVALID_LABEL_ID,
+    };
+    use gremlin_core::traversal::*;
+    use gremlin_core::{create_mutable_graph, register_named_graph, ID};
+    use pegasus_server::generated::protocol as server_pb;
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    const SCHEMA: &str = r#"
+    {
+      "vertex_type_map": { "person": 0 },
+      "edge_type_map": { "knows": 0 },
+      "vertex_prop": {
+        "person": [["id", "ID"], ["name", "String"]]
+      },
+      "edge_prop": {
+        "knows": [["start_id", "ID"], ["end_id", "ID"]]
+      }
+    }
+    "#;
+
+    fn person(id: usize) -> DefaultId {
+        LDBCVertexParser::to_global_id(id, 0.into())
+    }
+
+    fn named(name: &str) -> HashMap<String, Object> {
+        let mut properties = HashMap::new();
+        properties.insert("name".to_owned(), Object::from(name));
+        properties
+    }
+
+    // marko knows vadas
+    fn create_store() -> LargeGraphDB<DefaultId, InternalId> {
+        let mut graph: MutableGraphDB<DefaultId, InternalId> = GraphDBConfig::default().new();
+        for (id, name) in [(1, "marko"), (2, "vadas")] {
+            graph.add_vertex(person(id), [0.into(), INVALID_LABEL_ID]);
+            let row = Row::from(vec![Object::from(id as u64), Object::from(name)]);
+            graph.add_or_update_vertex_properties(person(id), row).unwrap();
+        }
+        graph.add_edge(person(1), person(2), 0.into());
+        graph.into_graph(LDBCGraphSchema::from_json(SCHEMA.to_owned()).unwrap())
+    }
+
+    fn job_conf(job_id: u64, min_version: u64, wait_ms: u64) -> server_pb::JobConfig {
+        server_pb::JobConfig {
+            job_id,
+            job_name: "read_your_writes_test".to_owned(),
+            workers: 1,
+            min_version,
+            min_version_wait_ms: wait_ms,
+            ..Default::default()
+        }
+    }
+
+    // the names of the persons marko knows
+    fn known_by_marko(graph: &str, conf: server_pb::JobConfig) -> Vec<String> {
+        let marko = person(1) as ID;
+        let traversal = Graph::traversal().v_ids(&[marko]).on_graph(graph).out(&[]);
+        let mut names: Vec<String> = traversal
+            .values(&["name"])
+            .run(conf)
+            .map(|r| r.expect("traversal failed").as_str().unwrap().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    // the job submitted right after the mutations on the graph they are made to reads them
+    #[test]
+    fn read_your_writes_primary_test() {
+        initialize();
+        let (graph, delta) = create_mutable_graph(create_store());
+        register_named_graph("ryw_primary", graph);
+        assert_eq!(delta.add_vertex(person(3), 0.into(), named("lop")), 1);
+        let version = delta.add_edge(person(1), person(3), 0.into(), HashMap::new());
+        assert_eq!(version, 2);
+        let names = known_by_marko("ryw_primary", job_conf(6416, version, 0));
+        assert_eq!(names, vec!["lop", "vadas"]);
+    }
+
+    // the job on a replica lagging behind the mutation fails once it waits beyond the timeout,
+    // while it reads the mutation if the replica catches up in time
+    #[test]
+    fn read_your_writes_replica_test() {
+        initialize();
+        let (primary, primary_delta) = create_mutable_graph(create_store());
+        let (replica, replica_delta) = create_mutable_graph(create_store());
+        register_named_graph("ryw_lagging_primary", primary);
+        register_named_graph("ryw_lagging_replica", replica);
+        let replicate = {
+            let replica_delta = replica_delta.clone();
+            move || {
+                replica_delta.add_vertex(person(3), 0.into(), named("lop"));
+                replica_delta.add_edge(person(1), person(3), 0.into(), HashMap::new())
+            }
+        };
+        primary_delta.add_vertex(person(3), 0.into(), named("lop"));
+        let version = primary_delta.add_edge(person(1), person(3), 0.into(), HashMap::new());
+
+        let traversal = Graph::traversal().v().on_graph("ryw_lagging_replica").count();
+        let results: Vec<_> = traversal.run(job_conf(6417, version, 50)).collect();
+        assert_eq!(results.len(), 1);
+        let err = results[0].as_ref().unwrap_err().to_string();
+        assert!(err.contains("timed out"), "unexpected error {}", err);
+        // the replica is never pinned by the job rejected
+        assert_eq!(replica_delta.oldest_pinned(), None);
+
+        let replicated = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            replicate()
+        });
+        let names = known_by_marko("ryw_lagging_replica", job_conf(6418, version, 10_000));
+        assert_eq!(replicated.join().unwrap(), version);
+        assert_eq!(names, vec!["lop", "vadas"]);
+        let names = known_by_marko("ryw_lagging_primary", job_conf(6419, version, 0));
+        assert_eq!(names, vec!["lop", "vadas"]);
+    }
+}
@@ -0,0 +1,139 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use gremlin_core::generated::common as common_pb;
+    use gremlin_core::generated::gremlin as pb;
+    use gremlin_core::ID;
+    use pegasus_server::generated::protocol as server_pb;
+    use pegasus_server::JobRequest;
+    use prost::Message;
+
+    fn encode_step(step: pb::gremlin_step::Step) -> Vec<u8> {
+        let step = pb::GremlinStep { tags: vec![], remove_tags: vec![], step: Some(step) };
+        let mut bytes = vec![];
+        step.encode(&mut bytes).expect("encode step failed");
+        bytes
+    }
+
+    fn repeat_loops_op(kind: pb::repeat_loops_step::Kind) -> server_pb::OperatorDef {
+        let step =
+            pb::gremlin_step::Step::RepeatLoopsStep(pb::RepeatLoopsStep { kind: kind as i32 });
+        server_pb::OperatorDef {
+            op_kind: Some(server_pb::operator_def::OpKind::Map(server_pb::Map {
+                resource: encode_step(step),
+            })),
+            ..Default::default()
+        }
+    }
+
+    // until(loops().is(cmp(n)))
+    fn loops_filter(cmp: pb::Compare, n: i32) -> server_pb::Filter {
+        let single = pb::FilterValueExp {
+            cmp: cmp as i32,
+            right: Some(common_pb::Value { item: Some(common_pb::value::Item::I32(n)) }),
+            epsilon: 0.0,
+        };
+        let step = pb::gremlin_step::Step::LoopsStep(pb::LoopsStep { single: Some(single) });
+        server_pb::Filter { resource: encode_step(step) }
+    }
+
+    // g.V(1).repeat(out()) with the given until condition, built from the source of g.V(1) and
+    // the plan of g.V().out() as the loop body, the same as compiled from the gremlin query
+    fn repeat_out_request(
+        until: server_pb::Filter, post_check: bool, emit: server_pb::iteration::EmitKind,
+    ) -> JobRequest {
+        let mut pb_request = read_pb_request(gen_path("out_step_test_01")).expect("read pb failed");
+        let src_request = read_pb_request(gen_path("source_test_02")).expect("read pb failed");
+        pb_request.source = src_request.source;
+        let plan = pb_request.plan.as_mut().expect("plan not found");
+        let mut body = std::mem::take(&mut plan.plan);
+        body.push(repeat_loops_op(pb::repeat_loops_step::Kind::Incr));
+        let iterate = server_pb::Iteration {
+            max_iters: 0,
+            until: Some(until),
+            body: Some(server_pb::TaskPlan { plan: body }),
+            post_check,
+            emit: emit as i32,
+        };
+        plan.plan.push(repeat_loops_op(pb::repeat_loops_step::Kind::Reset));
+        plan.plan.push(server_pb::OperatorDef {
+            op_kind: Some(server_pb::operator_def::OpKind::Iterate(iterate)),
+            ..Default::default()
+        });
+        pb_request
+    }
+
+    fn sorted_global_ids(ids: Vec<usize>) -> Vec<ID> {
+        let mut ids = to_global_ids(ids);
+        ids.sort();
+        ids
+    }
+
+    // g.V(1).repeat(out()).until(loops().is(2)), i.e., g.V(1).repeat(out()).times(2)
+    #[test]
+    fn repeat_until_loops_test_01() {
+        initialize();
+        let until = loops_filter(pb::Compare::Eq, 2);
+        let request = repeat_out_request(until, true, server_pb::iteration::EmitKind::EmitNone);
+        let test_job_factory = TestJobFactory::with_expect_ids(sorted_global_ids(vec![3, 5]));
+        run_test_with_job_id(test_job_factory, request, 5961, 1);
+    }
+
+    // g.V(1).repeat(out()).until(loops().is(gte(0))), each traverser goes through out() once
+    #[test]
+    fn repeat_until_post_check_test_01() {
+        initialize();
+        let until = loops_filter(pb::Compare::Ge, 0);
+        let request = repeat_out_request(until, true, server_pb::iteration::EmitKind::EmitNone);
+        let test_job_factory = TestJobFactory::with_expect_ids(sorted_global_ids(vec![2, 3, 4]));
+        run_test_with_job_id(test_job_factory, request, 5962, 1);
+    }
+
+    // g.V(1).until(loops().is(gte(0))).repeat(out()), the traverser leaves before the loop
+    #[test]
+    fn repeat_until_pre_check_test_01() {
+        initialize();
+        let until = loops_filter(pb::Compare::Ge, 0);
+        let request = repeat_out_request(until, false, server_pb::iteration::EmitKind::EmitNone);
+        let test_job_factory = TestJobFactory::with_expect_ids(sorted_global_ids(vec![1]));
+        run_test_with_job_id(test_job_factory, request, 5963, 1);
+    }
+
+    // g.V(1).repeat(out()).emit().times(2)
+    #[test]
+    fn repeat_emit_after_test_01() {
+        initialize();
+        let until = loops_filter(pb::Compare::Eq, 2);
+        let request = repeat_out_request(until, true, server_pb::iteration::EmitKind::EmitAfter);
+        let expected = sorted_global_ids(vec![2, 3, 4, 3, 5]);
+        let test_job_factory = TestJobFactory::with_expect_ids(expected);
+        run_test_with_job_id(test_job_factory, request, 5964, 1);
+    }
+
+    // g.V(1).emit().repeat(out()).times(1)
+    #[test]
+    fn repeat_emit_before_test_01() {
+        initialize();
+        let until = loops_filter(pb::Compare::Eq, 1);
+        let request = repeat_out_request(until, true, server_pb::iteration::EmitKind::EmitBefore);
+        let test_job_factory = TestJobFactory::with_expect_ids(sorted_global_ids(vec![1, 2, 3, 4]));
+        run_test_with_job_id(test_job_factory, request, 5965, 1);
+    }
+}
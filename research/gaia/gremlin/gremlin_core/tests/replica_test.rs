@@ -0,0 +1,74 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use graph_store::partition::{HashPartition, ReplicatedPartition};
+    use gremlin_core::process::metrics::{get_worker_counter, EXPAND_COUNTER};
+    use gremlin_core::{GraphPartition, GraphPartitioner};
+    use pegasus::api::function::ReplicaPolicy;
+    use std::sync::Arc;
+
+    // g.V().out() over two servers emulated by two workers, where the only partition is held by
+    // the servers given
+    fn run_out_on_servers(partition: Arc<dyn GraphPartition>, policy: ReplicaPolicy, job_id: u64) {
+        let mut expected = to_global_ids(vec![2, 3, 3, 3, 4, 5]);
+        expected.sort();
+        let mut test_job_factory = TestJobFactory::with_expect_ids(expected);
+        test_job_factory.set_servers(GraphPartitioner::new(partition, 2), 2, policy);
+        let pb_request = read_pb_request(gen_path("out_step_test_01")).expect("read pb failed");
+        run_test_with_job_id(test_job_factory, pb_request, job_id, 2);
+    }
+
+    #[test]
+    fn single_replica_test() {
+        initialize();
+        run_out_on_servers(Arc::new(HashPartition::new(1)), ReplicaPolicy::PreferLocal, 6260);
+        assert!(get_worker_counter(6260, 0, EXPAND_COUNTER) > 0);
+        assert_eq!(get_worker_counter(6260, 1, EXPAND_COUNTER), 0);
+    }
+
+    #[test]
+    fn replicas_prefer_local_test() {
+        initialize();
+        let partition = ReplicatedPartition::new(Arc::new(HashPartition::new(1)), 2);
+        run_out_on_servers(Arc::new(partition), ReplicaPolicy::PreferLocal, 6261);
+        assert!(get_worker_counter(6261, 0, EXPAND_COUNTER) > 0);
+        assert!(get_worker_counter(6261, 1, EXPAND_COUNTER) > 0);
+    }
+
+    #[test]
+    fn replicas_round_robin_test() {
+        initialize();
+        let partition = ReplicatedPartition::new(Arc::new(HashPartition::new(1)), 2);
+        run_out_on_servers(Arc::new(partition), ReplicaPolicy::RoundRobin, 6262);
+        assert!(get_worker_counter(6262, 0, EXPAND_COUNTER) > 0);
+        assert!(get_worker_counter(6262, 1, EXPAND_COUNTER) > 0);
+    }
+
+    // the server 0 is lost, so its replica serves all the lookups
+    #[test]
+    fn replica_of_lost_server_test() {
+        initialize();
+        let partition =
+            ReplicatedPartition::new(Arc::new(HashPartition::new(1)), 2).without_server(0);
+        run_out_on_servers(Arc::new(partition), ReplicaPolicy::RoundRobin, 6263);
+        assert_eq!(get_worker_counter(6263, 0, EXPAND_COUNTER), 0);
+        assert!(get_worker_counter(6263, 1, EXPAND_COUNTER) > 0);
+    }
+}
@@ -0,0 +1,112 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use gremlin_core::compiler::GremlinJobCompiler;
+    use gremlin_core::traversal::*;
+    use gremlin_core::{Detach, Partition};
+    use pegasus_server::generated::protocol as server_pb;
+    use pegasus_server::service::{Output, Service};
+    use pegasus_server::{JobResponse, JobResult};
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    #[derive(Clone, Default)]
+    struct CollectOutput {
+        results: Arc<Mutex<Vec<JobResult>>>,
+    }
+
+    impl Output for CollectOutput {
+        fn send(&self, res: JobResponse) {
+            if let Some(result) = res.result {
+                self.results.lock().unwrap().push(result);
+            }
+        }
+
+        fn close(&self) {}
+    }
+
+    // the frames of the results sent to the client, and the bytes the workers ship
+    fn run(traversal: GraphTraversal, job_id: u64, in_service: bool) -> (Vec<Vec<u8>>, u64) {
+        initialize();
+        let conf = server_pb::JobConfig {
+            job_id,
+            job_name: "result_encoding_test".to_owned(),
+            workers: 2,
+            encode_in_service: in_service,
+            ..Default::default()
+        };
+        let service = Service::new(GremlinJobCompiler::new(Partition { num_servers: 1 }, 1, 0));
+        let output = CollectOutput::default();
+        service.accept(traversal.to_request(conf), output.clone());
+        if let Some(guard) = service.job_guards.write().unwrap().get_mut(&job_id) {
+            guard.join().expect("job failed");
+        }
+        // the results encoded in the service may be sent after the workers end
+        let start = Instant::now();
+        loop {
+            let results = output.results.lock().unwrap().clone();
+            let stats = results.iter().find_map(|res| match res {
+                JobResult::Complete(complete) => complete.stats.clone(),
+                _ => None,
+            });
+            if let Some(stats) = stats {
+                let mut frames: Vec<Vec<u8>> = results
+                    .into_iter()
+                    .filter_map(|res| match res {
+                        JobResult::Data(data) => Some(data),
+                        _ => None,
+                    })
+                    .collect();
+                frames.sort();
+                return (frames, stats.result_bytes);
+            }
+            assert!(start.elapsed() < Duration::from_secs(10), "job {} not complete", job_id);
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    // g.V().dedup(), with all properties of the vertices, which runs on the workers, as the jobs of
+    // no operators but the source are read by the service instead
+    fn all_properties() -> GraphTraversal {
+        Graph::traversal().v().dedup().detach(Detach::Full)
+    }
+
+    #[test]
+    fn encode_in_service_test() {
+        let (on_workers, worker_bytes) = run(all_properties(), 6423, false);
+        let (in_service, service_bytes) = run(all_properties(), 6424, true);
+        // the client gets the same results wherever they are encoded
+        assert_eq!(on_workers, in_service);
+        assert!(!on_workers.is_empty());
+        // the workers ship the encoded results, or the records as they are
+        assert_eq!(worker_bytes, on_workers.iter().map(|frame| frame.len() as u64).sum::<u64>());
+        assert!(service_bytes > 0);
+    }
+
+    #[test]
+    fn worker_projection_test() {
+        let (_, full_bytes) = run(all_properties(), 6425, false);
+        // only the names are encoded on the workers, e.g. as the client needs no other property
+        let names = Graph::traversal().v().dedup().detach(Detach::Custom(vec!["name".to_owned()]));
+        let (frames, name_bytes) = run(names, 6426, false);
+        assert!(!frames.is_empty());
+        assert!(name_bytes < full_bytes, "{} vs {}", name_bytes, full_bytes);
+    }
+}
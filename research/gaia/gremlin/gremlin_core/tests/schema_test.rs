@@ -0,0 +1,140 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use graph_store::common::LabelId;
+    use graph_store::parser::DataType;
+    use gremlin_core::compiler::GremlinJobCompiler;
+    use gremlin_core::generated::gremlin as pb;
+    use gremlin_core::process::traversal::traverser::Traverser;
+    use gremlin_core::traversal::*;
+    use gremlin_core::validate::TypeCheck;
+    use gremlin_core::Partition;
+    use pegasus_server::generated::protocol as server_pb;
+    use pegasus_server::service::{Output, Service, INVALID_PLAN_ERR_CODE};
+    use pegasus_server::{JobRequest, JobResponse, JobResult};
+    use prost::Message;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct CollectOutput {
+        results: Arc<Mutex<Vec<JobResult>>>,
+    }
+
+    impl Output for CollectOutput {
+        fn send(&self, res: JobResponse) {
+            if let Some(result) = res.result {
+                self.results.lock().unwrap().push(result);
+            }
+        }
+
+        fn close(&self) {}
+    }
+
+    fn job_conf(job_id: u64) -> server_pb::JobConfig {
+        server_pb::JobConfig {
+            job_id,
+            job_name: "schema_test".to_owned(),
+            workers: 1,
+            ..Default::default()
+        }
+    }
+
+    fn service(type_check: TypeCheck) -> Service<Traverser> {
+        let compiler =
+            GremlinJobCompiler::new(Partition { num_servers: 1 }, 1, 0).with_type_check(type_check);
+        Service::new(compiler)
+    }
+
+    fn run(request: JobRequest, type_check: TypeCheck) -> Vec<JobResult> {
+        initialize();
+        let job_id = request.conf.as_ref().unwrap().job_id;
+        let service = service(type_check);
+        let output = CollectOutput::default();
+        service.accept(request, output.clone());
+        if let Some(guard) = service.job_guards.write().unwrap().get_mut(&job_id) {
+            guard.join().expect("job failed");
+        }
+        let results = output.results.lock().unwrap();
+        results.clone()
+    }
+
+    // the schema of the modern graph recorded while it is loaded
+    #[test]
+    fn graph_schema_test() {
+        initialize();
+        let schema = gremlin_core::get_graph().unwrap().get_schema().unwrap();
+        let names: Vec<&str> = schema.vertex_labels.iter().map(|l| l.name.as_str()).collect();
+        assert_eq!(names, vec!["person", "software"]);
+        let person = schema.get_vertex_label("person").unwrap();
+        assert_eq!(person.id, LabelId::from(0));
+        assert_eq!(person.get_property_type("name"), Some(&DataType::String));
+        assert_eq!(person.get_property_type("age"), Some(&DataType::Integer));
+        assert_eq!(person.get_property_type("lang"), None);
+        let software = schema.get_vertex_label("software").unwrap();
+        assert_eq!(software.get_property_type("lang"), Some(&DataType::String));
+        let created = schema.get_edge_label("created").unwrap();
+        assert_eq!(created.get_property_type("weight"), Some(&DataType::Double));
+        // knows links persons, and created links persons to software
+        let (person_id, software_id) = (LabelId::from(0), LabelId::from(1));
+        assert_eq!(schema.get_relations(0.into()), vec![(person_id, person_id)]);
+        assert_eq!(schema.get_relations(1.into()), vec![(person_id, software_id)]);
+        assert_eq!(schema.get_property_types("name"), vec![DataType::String]);
+    }
+
+    // the schema replied to the schema request of the job service
+    #[test]
+    fn schema_service_test() {
+        initialize();
+        let res = service(TypeCheck::Strict).schema();
+        let schema = pb::GraphSchema::decode(res.schema.as_slice()).unwrap();
+        assert_eq!(schema.vertex_labels.len(), 2);
+        assert_eq!(schema.edge_labels.len(), 2);
+        let person = schema.vertex_labels.iter().find(|l| l.name == "person").unwrap();
+        let age = person.properties.iter().find(|p| p.name == "age").unwrap();
+        assert_eq!(age.data_type, pb::graph_schema::PropertyType::Integer as i32);
+        let relations: Vec<(i32, i32, i32)> =
+            schema.relations.iter().map(|r| (r.edge_label, r.src_label, r.dst_label)).collect();
+        assert_eq!(relations, vec![(0, 0, 0), (1, 0, 1)]);
+    }
+
+    // g.V().has("age", "29"), which compares a string to an int property
+    #[test]
+    fn ill_typed_query_test() {
+        let request =
+            |job_id| Graph::traversal().v().has("age", eq("29")).to_request(job_conf(job_id));
+        let results = run(request(6230), TypeCheck::Strict);
+        match results.as_slice() {
+            [JobResult::Err(err)] => {
+                assert_eq!(err.err_code, INVALID_PLAN_ERR_CODE);
+                assert_eq!(err.kind, server_pb::QueryErrorKind::Validation as i32);
+                assert_eq!(err.worker, -1);
+                assert!(err.err_msg.contains("property age of type [Integer]"), "{}", err.err_msg);
+            }
+            _ => panic!("the ill-typed query is expected to be rejected, but got {:?}", results),
+        }
+        // which never matches if accepted
+        let results = run(request(6231), TypeCheck::Lenient);
+        assert!(!results.iter().any(|r| matches!(r, JobResult::Err(_))), "{:?}", results);
+        // the numbers are coerced to each other
+        let request = Graph::traversal().v().has("age", eq(29i64)).to_request(job_conf(6232));
+        let results = run(request, TypeCheck::Strict);
+        assert!(!results.iter().any(|r| matches!(r, JobResult::Err(_))), "{:?}", results);
+    }
+}
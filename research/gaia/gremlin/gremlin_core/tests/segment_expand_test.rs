@@ -0,0 +1,79 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use gremlin_core::structure::{Direction, Label, QueryParams, Vertex};
+    use gremlin_core::{Element, ID};
+
+    // the ids of the vertices adjacent to the vertex by the edges of the labels, expanded through
+    // the adjacency segments of the labels if any
+    fn expand_ids(id: usize, direction: Direction, labels: Vec<&str>) -> Vec<ID> {
+        let graph = gremlin_core::get_graph().expect("graph not registered");
+        let mut params = QueryParams::<Vertex>::new();
+        params.labels = labels.into_iter().map(|l| Label::Str(l.to_owned())).collect();
+        let stmt = graph.prepare_explore_vertex(direction, &params).expect("prepare failure");
+        let vertices = stmt.exec(to_global_id(id) as ID).expect("expand failure");
+        let mut ids: Vec<ID> = vertices.map(|v| v.unwrap().id()).collect();
+        ids.sort();
+        ids
+    }
+
+    // g.V(1).out('knows')
+    #[test]
+    fn expand_out_test() {
+        initialize();
+        assert_eq!(expand_ids(1, Direction::Out, vec!["knows"]), to_global_ids(vec![2, 4]));
+        assert_eq!(expand_ids(1, Direction::Out, vec!["created"]), to_global_ids(vec![3]));
+        assert_eq!(
+            expand_ids(1, Direction::Out, vec!["created", "knows", "created"]),
+            to_global_ids(vec![2, 4, 3])
+        );
+        assert!(expand_ids(2, Direction::Out, vec!["knows"]).is_empty());
+    }
+
+    // g.V(3).in('created')
+    #[test]
+    fn expand_in_test() {
+        initialize();
+        assert_eq!(expand_ids(3, Direction::In, vec!["created"]), to_global_ids(vec![1, 4, 6]));
+        assert!(expand_ids(3, Direction::In, vec!["knows"]).is_empty());
+    }
+
+    // g.V(4).both('knows', 'created')
+    #[test]
+    fn expand_both_test() {
+        initialize();
+        assert_eq!(
+            expand_ids(4, Direction::Both, vec!["knows", "created"]),
+            to_global_ids(vec![1, 3, 5])
+        );
+        assert_eq!(expand_ids(4, Direction::Both, vec!["knows"]), to_global_ids(vec![1]));
+    }
+
+    // the label absent from the schema is of no edge, while the other labels are still expanded
+    #[test]
+    fn expand_absent_label_test() {
+        initialize();
+        assert!(expand_ids(1, Direction::Out, vec!["likes"]).is_empty());
+        assert_eq!(
+            expand_ids(1, Direction::Out, vec!["likes", "knows"]),
+            to_global_ids(vec![2, 4])
+        );
+    }
+}
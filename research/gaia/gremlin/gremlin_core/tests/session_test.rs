@@ -0,0 +1,95 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use dyn_type::Object;
+    use gremlin_core::session::{close_session, get_session, open_session};
+    use gremlin_core::traversal::*;
+    use gremlin_core::DynResult;
+    use pegasus_server::generated::protocol as server_pb;
+    use std::time::Duration;
+
+    fn run_in_session(
+        traversal: GraphTraversal, job_id: u64, session_id: u64,
+    ) -> Vec<DynResult<Object>> {
+        initialize();
+        let conf = server_pb::JobConfig {
+            job_id,
+            job_name: "session_test".to_owned(),
+            workers: 2,
+            session_id,
+            ..Default::default()
+        };
+        traversal.run(conf).collect()
+    }
+
+    fn expect_error(results: Vec<DynResult<Object>>, msg: &str) {
+        let err = results.into_iter().find_map(|r| r.err()).expect("traversal should fail");
+        assert!(err.to_string().contains(msg), "unexpected error: {}", err);
+    }
+
+    fn expect_values(results: Vec<DynResult<Object>>) -> Vec<Object> {
+        let mut values =
+            results.into_iter().map(|r| r.expect("traversal failed")).collect::<Vec<_>>();
+        values.sort_by(|a, b| a.partial_cmp(b).expect("incomparable values"));
+        values
+    }
+
+    // g.V().out("knows").aggregate("x"), and then SessionRef("x").values("name")
+    #[test]
+    fn session_ref_test() {
+        let session = open_session(Duration::from_secs(60));
+        let traversal = Graph::traversal().session_ref("x").count();
+        expect_error(run_in_session(traversal, 6211, session), "referenced before stored");
+
+        let traversal = Graph::traversal().v().out(&[0]).aggregate("x");
+        assert_eq!(expect_values(run_in_session(traversal, 6212, session)).len(), 2);
+        let traversal = Graph::traversal().session_ref("x").values(&["name"]);
+        let names = expect_values(run_in_session(traversal, 6213, session));
+        assert_eq!(names, vec![Object::from("josh"), Object::from("vadas")]);
+        let traversal = Graph::traversal().session_ref("x").count();
+        assert_eq!(expect_values(run_in_session(traversal, 6214, session)), vec![2u64.into()]);
+
+        // all results of the session are released once it is closed
+        assert!(close_session(session));
+        let traversal = Graph::traversal().session_ref("x").count();
+        expect_error(run_in_session(traversal, 6215, session), "not found");
+    }
+
+    #[test]
+    fn session_ref_out_of_session_test() {
+        let traversal = Graph::traversal().session_ref("x").count();
+        expect_error(run_in_session(traversal, 6216, 0), "out of sessions");
+    }
+
+    #[test]
+    fn session_expire_test() {
+        let session = open_session(Duration::from_millis(200));
+        let traversal = Graph::traversal().v().out(&[0]).aggregate("x");
+        assert_eq!(expect_values(run_in_session(traversal, 6217, session)).len(), 2);
+        let session_state = get_session(session).expect("session expired too early");
+        assert!(session_state.get_result("x").is_some());
+        drop(session_state);
+        // idle for longer than the TTL
+        std::thread::sleep(Duration::from_millis(400));
+        assert!(get_session(session).is_none());
+        let traversal = Graph::traversal().session_ref("x").count();
+        expect_error(run_in_session(traversal, 6218, session), "not found");
+    }
+}
@@ -0,0 +1,116 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use gremlin_core::compiler::GremlinJobCompiler;
+    use gremlin_core::generated::common as common_pb;
+    use gremlin_core::generated::gremlin as pb;
+    use gremlin_core::process::side_store::{bind_job_side_store, get_job_side_store};
+    use gremlin_core::process::traversal::traverser::Traverser;
+    use gremlin_core::structure::{ElementFilter, TraverserFilter, TraverserFilterChain};
+    use gremlin_core::Partition;
+    use pegasus::api::{Filter, ResultSet, Sink};
+    use pegasus::JobConf;
+    use pegasus_server::factory::JobCompiler;
+    use prost::Message;
+    use std::sync::Arc;
+
+    // hasId(within(1, 2, .., ids))
+    fn has_id_within(ids: i64) -> Vec<u8> {
+        let exp = pb::FilterExp {
+            left: Some(common_pb::Key {
+                item: Some(common_pb::key::Item::Id(common_pb::IdKey {})),
+            }),
+            cmp: pb::Compare::Within as i32,
+            right: Some(common_pb::Value {
+                item: Some(common_pb::value::Item::I64Array(common_pb::I64Array {
+                    item: (1..=ids).collect(),
+                })),
+            }),
+            epsilon: 0.0,
+            predicate: String::new(),
+        };
+        let node = pb::FilterNode {
+            inner: Some(pb::filter_node::Inner::Single(exp)),
+            next: pb::Connect::And as i32,
+        };
+        let has_step = pb::HasStep { predicates: Some(pb::FilterChain { node: vec![node] }) };
+        let step = pb::GremlinStep {
+            tags: vec![],
+            remove_tags: vec![],
+            step: Some(pb::gremlin_step::Step::HasStep(has_step)),
+        };
+        let mut bytes = vec![];
+        step.encode(&mut bytes).expect("encode step failure");
+        bytes
+    }
+
+    // the address and the size of the set of ids the filter is within
+    fn within_ids(filter: &TraverserFilterChain) -> Option<(usize, usize)> {
+        let mut ids = None;
+        filter.for_each(&mut |p| {
+            if let TraverserFilter::HasHead(head) = p {
+                if let ElementFilter::ContainsId(contains) = head.get_filter() {
+                    ids = Some((Arc::as_ptr(&contains.expect) as usize, contains.expect.len()));
+                }
+            }
+        });
+        ids
+    }
+
+    // the filter of the has step is decoded once and shared by all workers of the job, with the
+    // same set of ids
+    #[test]
+    fn shared_within_filter_test() {
+        initialize();
+        let compiler = Arc::new(GremlinJobCompiler::new(Partition { num_servers: 1 }, 1, 0));
+        let res = has_id_within(2000);
+        let (tx, rx) = crossbeam_channel::unbounded();
+        let mut conf = JobConf::new(6269, "shared_filter_test", 4);
+        bind_job_side_store(&mut conf);
+        pegasus::run(conf, |worker| {
+            let tx = tx.clone();
+            let compiler = compiler.clone();
+            let res = res.clone();
+            worker.dataflow(move |dfb| {
+                let func = compiler.filter(&res)?;
+                let store = get_job_side_store().expect("side store not found");
+                let shared = store.find_filter(&res).expect("filter not shared");
+                tx.send((Arc::as_ptr(&shared) as usize, within_ids(&shared)))
+                    .expect("send filter failure");
+                dfb.input_from_iter(Vec::<Traverser>::new().into_iter())?
+                    .filter(func)?
+                    .sink_by(|_| |_, _: ResultSet<Traverser>| ())?;
+                Ok(())
+            })
+        })
+        .expect("submit job failure");
+
+        std::mem::drop(tx);
+        let filters: Vec<(usize, Option<(usize, usize)>)> = rx.iter().collect();
+        assert_eq!(filters.len(), 4);
+        let (filter, ids) = filters[0];
+        let (_, size) = ids.expect("within ids not found");
+        assert_eq!(size, 2000);
+        for (other_filter, other_ids) in filters {
+            assert_eq!(other_filter, filter);
+            assert_eq!(other_ids, ids);
+        }
+    }
+}
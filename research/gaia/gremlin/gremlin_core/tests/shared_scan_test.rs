@@ -0,0 +1,74 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use gremlin_core::get_vertex_scan_count;
+    use gremlin_core::process::shared_scan::{set_shared_scan, SharedScanConf};
+    use pegasus_server::JobRequest;
+    use std::time::Duration;
+
+    // g.V().hasLabel("PERSON"), whose source is read by the service, as it has no other operator
+    fn has_label_request(job_id: u64) -> JobRequest {
+        let mut pb_request = read_pb_request(gen_path("has_step_test_01")).expect("read pb failed");
+        pb_request.conf.as_mut().expect("no job_conf").job_id = job_id;
+        pb_request
+    }
+
+    // g.V().union(identity(),identity()).dedup().order().by(id), which runs on the workers
+    fn scan_request(job_id: u64) -> JobRequest {
+        let mut pb_request =
+            read_pb_request(gen_path("dedup_step_test_w2")).expect("read pb failed");
+        pb_request.conf.as_mut().expect("no job_conf").job_id = job_id;
+        pb_request
+    }
+
+    #[test]
+    fn shared_label_scan_test() {
+        initialize();
+        let conf = SharedScanConf { window: Duration::from_millis(200), ..Default::default() };
+        set_shared_scan(Some(conf));
+        // the results of all the 5 jobs are checked together
+        let mut expected = to_global_ids([1, 2, 4, 6].repeat(5));
+        expected.sort();
+        let requests = (6161..6166).map(has_label_request).collect();
+        let scans = get_vertex_scan_count();
+        run_sources_concurrently(TestJobFactory::with_expect_ids(expected), requests);
+        // the service scans once for all the 5 jobs, instead of once for each job
+        let scans = get_vertex_scan_count() - scans;
+        assert_eq!(scans, 1, "the storage is scanned {} times", scans);
+        set_shared_scan(None);
+    }
+
+    #[test]
+    fn shared_scan_test_w2() {
+        initialize();
+        let conf = SharedScanConf { window: Duration::from_millis(200), ..Default::default() };
+        set_shared_scan(Some(conf));
+        let mut expected = to_global_ids(vec![1, 2, 3, 4, 5, 6]);
+        expected.sort();
+        let requests = (6171..6176).map(scan_request).collect();
+        let scans = get_vertex_scan_count();
+        let results =
+            run_tests_concurrently(TestJobFactory::with_expect_ids(expected), requests, 2);
+        assert!(results.iter().all(|r| r.is_ok()), "{:?}", results);
+        // each of the two workers scans once for all the 5 jobs, instead of once for each job
+        let scans = get_vertex_scan_count() - scans;
+        assert_eq!(scans, 2, "the storage is scanned {} times", scans);
+        set_shared_scan(None);
+    }
+}
@@ -0,0 +1,166 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+mod common;
+use gremlin_core::graph_algo::{shortest_paths, PathRecord, ShortestPath};
+use gremlin_core::structure::{
+    DefaultDetails, Direction, DynDetails, Edge, Label, QueryParams, Statement, Vertex,
+};
+use gremlin_core::{register_graph, DynIter, DynResult, GraphProxy, ID};
+use pegasus::{Configuration, JobConf};
+use std::collections::HashMap;
+use std::sync::{Arc, Once};
+
+/// 1->2 (7), 1->3 (9), 1->6 (14), 2->3 (10), 2->4 (15), 3->4 (11), 3->6 (2), 4->5 (6), 6->5 (9)
+struct WeightedGraph {
+    edges: Vec<(ID, ID, f64)>,
+}
+
+fn vertex(id: ID) -> Vertex {
+    Vertex::new(id, Some(Label::Id(0.into())), DefaultDetails::new(id, Label::Id(0.into())))
+}
+
+fn edge(index: usize, (src, dst, weight): (ID, ID, f64)) -> Edge {
+    let id = index as ID;
+    let mut properties = HashMap::new();
+    properties.insert("weight".to_owned(), weight.into());
+    let details = DefaultDetails::new_with_prop(id, Label::Id(0.into()), properties);
+    Edge::new(id, Some(Label::Id(0.into())), src, dst, DynDetails::new(details))
+}
+
+impl GraphProxy for WeightedGraph {
+    fn scan_vertex(
+        &self, _: &QueryParams<Vertex>,
+    ) -> DynResult<Box<dyn Iterator<Item = Vertex> + Send>> {
+        Ok(Box::new((1..=6).map(vertex)))
+    }
+
+    fn get_vertex(
+        &self, ids: &[ID], _: &QueryParams<Vertex>,
+    ) -> DynResult<Box<dyn Iterator<Item = Vertex> + Send>> {
+        Ok(Box::new(ids.iter().map(|id| vertex(*id)).collect::<Vec<_>>().into_iter()))
+    }
+
+    fn prepare_explore_vertex(
+        &self, _: Direction, _: &QueryParams<Vertex>,
+    ) -> DynResult<Box<dyn Statement<ID, Vertex>>> {
+        unimplemented!()
+    }
+
+    fn prepare_explore_edge(
+        &self, direction: Direction, _: &QueryParams<Edge>,
+    ) -> DynResult<Box<dyn Statement<ID, Edge>>> {
+        let edges = self.edges.clone();
+        Ok(Box::new(move |v: ID| -> DynResult<DynIter<Edge>> {
+            let adjacent = edges
+                .iter()
+                .enumerate()
+                .filter(|(_, (src, dst, _))| match direction {
+                    Direction::Out => *src == v,
+                    Direction::In => *dst == v,
+                    Direction::Both => *src == v || *dst == v,
+                })
+                .map(|(i, e)| Ok(edge(i, *e)))
+                .collect::<Vec<_>>();
+            Ok(Box::new(adjacent.into_iter()))
+        }))
+    }
+}
+
+static INIT: Once = Once::new();
+
+fn initialize() {
+    INIT.call_once(|| {
+        pegasus_common::logs::init_log();
+        pegasus::startup(Configuration::singleton()).ok();
+        let edges = vec![
+            (1, 2, 7.0),
+            (1, 3, 9.0),
+            (1, 6, 14.0),
+            (2, 3, 10.0),
+            (2, 4, 15.0),
+            (3, 4, 11.0),
+            (3, 6, 2.0),
+            (4, 5, 6.0),
+            (6, 5, 9.0),
+        ];
+        register_graph(Arc::new(WeightedGraph { edges }));
+    });
+}
+
+fn record(vertex: ID, distance: f64, path: Vec<ID>) -> PathRecord {
+    PathRecord { vertex, distance, path: Some(path) }
+}
+
+#[test]
+fn weighted_shortest_path_test() {
+    initialize();
+    let conf = JobConf::new(6181, "weighted_shortest_path_test", 2);
+    let sp = ShortestPath::new(vec![1]).with_weight("weight");
+    let records = shortest_paths(conf, sp, None, true).unwrap();
+    let expected = vec![
+        record(1, 0.0, vec![1]),
+        record(2, 7.0, vec![1, 2]),
+        record(3, 9.0, vec![1, 3]),
+        record(4, 20.0, vec![1, 3, 4]),
+        record(5, 20.0, vec![1, 3, 6, 5]),
+        record(6, 11.0, vec![1, 3, 6]),
+    ];
+    assert_eq!(records, expected);
+}
+
+#[test]
+fn bounded_shortest_path_test() {
+    initialize();
+    let conf = JobConf::new(6182, "bounded_shortest_path_test", 2);
+    let sp = ShortestPath::new(vec![1]).with_weight("weight");
+    let records = shortest_paths(conf, sp, Some(1), false).unwrap();
+    let distances = records.into_iter().map(|r| (r.vertex, r.distance)).collect::<Vec<_>>();
+    // only the paths of one edge, where 1->6 is longer than 1->3->6
+    assert_eq!(distances, vec![(1, 0.0), (2, 7.0), (3, 9.0), (6, 14.0)]);
+}
+
+#[test]
+fn bfs_test() {
+    initialize();
+    let conf = JobConf::new(6183, "bfs_test", 2);
+    let records = shortest_paths(conf, ShortestPath::new(vec![1]), None, true).unwrap();
+    let expected = vec![
+        record(1, 0.0, vec![1]),
+        record(2, 1.0, vec![1, 2]),
+        record(3, 1.0, vec![1, 3]),
+        // ties of 1->2->4 and 1->3->4, and 1->3->6->5 is longer than 1->6->5
+        record(4, 2.0, vec![1, 2, 4]),
+        record(5, 2.0, vec![1, 6, 5]),
+        record(6, 1.0, vec![1, 6]),
+    ];
+    assert_eq!(records, expected);
+}
+
+#[test]
+fn bfs_from_many_sources_test() {
+    initialize();
+    let conf = JobConf::new(6184, "bfs_from_many_sources_test", 2);
+    let sp = ShortestPath::new(vec![2, 6]).with_direction(Direction::Both);
+    let records = shortest_paths(conf, sp, None, true).unwrap();
+    let expected = vec![
+        record(1, 1.0, vec![2, 1]),
+        record(2, 0.0, vec![2]),
+        record(3, 1.0, vec![2, 3]),
+        record(4, 1.0, vec![2, 4]),
+        record(5, 1.0, vec![6, 5]),
+        record(6, 0.0, vec![6]),
+    ];
+    assert_eq!(records, expected);
+}
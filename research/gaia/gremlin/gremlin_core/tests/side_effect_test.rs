@@ -0,0 +1,170 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use gremlin_core::generated::gremlin as pb;
+    use gremlin_core::ID;
+    use pegasus_server::generated::protocol as server_pb;
+    use pegasus_server::JobRequest;
+    use prost::Message;
+
+    // the plan of out(), taken from g.V().out()
+    fn out_plan() -> Vec<server_pb::OperatorDef> {
+        let mut pb_request = read_pb_request(gen_path("out_step_test_01")).expect("read pb failed");
+        pb_request.plan.take().expect("plan not found").plan
+    }
+
+    fn encode_step(step: pb::gremlin_step::Step) -> Vec<u8> {
+        let step = pb::GremlinStep { tags: vec![], remove_tags: vec![], step: Some(step) };
+        let mut bytes = vec![];
+        step.encode(&mut bytes).expect("encode step failed");
+        bytes
+    }
+
+    fn fold_op(
+        accum: server_pb::AccumKind, unfold: pb::gremlin_step::Step,
+    ) -> server_pb::OperatorDef {
+        let fold = server_pb::Fold {
+            range: server_pb::Range::Global as i32,
+            accum: accum as i32,
+            resource: vec![],
+            unfold: Some(server_pb::FlatMap { resource: encode_step(unfold) }),
+            ..Default::default()
+        };
+        server_pb::OperatorDef {
+            op_kind: Some(server_pb::operator_def::OpKind::Fold(fold)),
+            ..Default::default()
+        }
+    }
+
+    // aggregate("x")
+    fn aggregate_op(key: &str) -> server_pb::OperatorDef {
+        let step = pb::SideEffectStep {
+            kind: pb::side_effect_step::Kind::Aggregate as i32,
+            key: key.to_string(),
+            ..Default::default()
+        };
+        fold_op(server_pb::AccumKind::ToList, pb::gremlin_step::Step::SideEffectStep(step))
+    }
+
+    // store("x")
+    fn store_op(key: &str) -> server_pb::OperatorDef {
+        let step = pb::SideEffectStep {
+            kind: pb::side_effect_step::Kind::Store as i32,
+            key: key.to_string(),
+            ..Default::default()
+        };
+        let map =
+            server_pb::Map { resource: encode_step(pb::gremlin_step::Step::SideEffectStep(step)) };
+        server_pb::OperatorDef {
+            op_kind: Some(server_pb::operator_def::OpKind::Map(map)),
+            ..Default::default()
+        }
+    }
+
+    // cap("x").unfold(), to check the collection by the ids of its vertices
+    fn cap_unfold_ops(key: &str) -> Vec<server_pb::OperatorDef> {
+        let cap = pb::CapStep { key: key.to_string() };
+        let unfold = server_pb::FlatMap {
+            resource: encode_step(pb::gremlin_step::Step::UnfoldStep(pb::UnfoldStep {})),
+        };
+        vec![
+            fold_op(server_pb::AccumKind::Cnt, pb::gremlin_step::Step::CapStep(cap)),
+            server_pb::OperatorDef {
+                op_kind: Some(server_pb::operator_def::OpKind::FlatMap(unfold)),
+                ..Default::default()
+            },
+        ]
+    }
+
+    // where(within("x")) or where(without("x"))
+    fn within_op(key: &str, negate: bool) -> server_pb::OperatorDef {
+        let filter = server_pb::Filter {
+            resource: encode_step(pb::gremlin_step::Step::WithinSideStep(pb::WithinSideStep {
+                key: key.to_string(),
+                negate,
+            })),
+        };
+        server_pb::OperatorDef {
+            op_kind: Some(server_pb::operator_def::OpKind::Filter(filter)),
+            ..Default::default()
+        }
+    }
+
+    // g.V() followed by the given plan
+    fn side_effect_request(plan: Vec<server_pb::OperatorDef>) -> JobRequest {
+        let mut pb_request = read_pb_request(gen_path("out_step_test_01")).expect("read pb failed");
+        pb_request.plan = Some(server_pb::TaskPlan { plan });
+        pb_request
+    }
+
+    fn sorted_global_ids(ids: Vec<usize>) -> Vec<ID> {
+        let mut ids = to_global_ids(ids);
+        ids.sort();
+        ids
+    }
+
+    // g.V().out().aggregate("x").cap("x").unfold()
+    #[test]
+    fn aggregate_cap_test_01() {
+        initialize();
+        let mut plan = out_plan();
+        plan.push(aggregate_op("x"));
+        plan.extend(cap_unfold_ops("x"));
+        let expected = sorted_global_ids(vec![2, 3, 3, 3, 4, 5]);
+        let test_job_factory = TestJobFactory::with_expect_ids(expected);
+        run_test_with_job_id(test_job_factory, side_effect_request(plan), 6022, 2);
+    }
+
+    // g.V().out().store("x").cap("x").unfold()
+    #[test]
+    fn store_cap_test_01() {
+        initialize();
+        let mut plan = out_plan();
+        plan.push(store_op("x"));
+        plan.extend(cap_unfold_ops("x"));
+        let expected = sorted_global_ids(vec![2, 3, 3, 3, 4, 5]);
+        let test_job_factory = TestJobFactory::with_expect_ids(expected);
+        run_test_with_job_id(test_job_factory, side_effect_request(plan), 6023, 2);
+    }
+
+    // g.V().out().aggregate("x").out().where(within("x"))
+    #[test]
+    fn aggregate_within_test_01() {
+        initialize();
+        let mut plan = out_plan();
+        plan.push(aggregate_op("x"));
+        plan.extend(out_plan());
+        plan.push(within_op("x", false));
+        let test_job_factory = TestJobFactory::with_expect_ids(sorted_global_ids(vec![3, 5]));
+        run_test_with_job_id(test_job_factory, side_effect_request(plan), 6024, 2);
+    }
+
+    // g.V().out().aggregate("x").out().where(without("x"))
+    #[test]
+    fn aggregate_without_test_01() {
+        initialize();
+        let mut plan = out_plan();
+        plan.push(aggregate_op("x"));
+        plan.extend(out_plan());
+        plan.push(within_op("x", true));
+        let test_job_factory = TestJobFactory::with_expect_result_num(0);
+        run_test_with_job_id(test_job_factory, side_effect_request(plan), 6025, 2);
+    }
+}
@@ -0,0 +1,83 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use gremlin_core::traversal::*;
+    use pegasus_server::generated::protocol as server_pb;
+
+    fn job_conf(job_id: u64) -> server_pb::JobConfig {
+        server_pb::JobConfig {
+            job_id,
+            job_name: "simplify_test".to_owned(),
+            workers: 2,
+            ..Default::default()
+        }
+    }
+
+    fn names(traversal: GraphTraversal, job_id: u64) -> Vec<String> {
+        let results = traversal.run(job_conf(job_id));
+        let mut names: Vec<String> =
+            results.map(|r| r.expect("traversal failed").as_str().unwrap().into_owned()).collect();
+        names.sort();
+        names
+    }
+
+    fn kinds(plan: &server_pb::JobPlan) -> Vec<&str> {
+        plan.operators.iter().map(|op| op.kind.as_str()).collect()
+    }
+
+    // g.V().has("age", gt(10)).has("age", gt(30)).values("name"), whose has steps are merged into
+    // has("age", gt(30))
+    #[test]
+    fn merge_has_test() {
+        initialize();
+        let traversal =
+            || Graph::traversal().v().has("age", gt(10)).has("age", gt(30)).values(&["name"]);
+        let plan = traversal().explain(job_conf(6454)).expect("explain failed");
+        assert!(plan.simplified);
+        assert_eq!(kinds(&plan), vec!["source", "filter", "flat_map"]);
+        assert_eq!(names(traversal(), 6455), vec!["josh".to_owned(), "peter".to_owned()]);
+    }
+
+    // g.V().has("age", gt(0)).has("name", eq("josh")), whose has steps are kept as they are
+    #[test]
+    fn keep_has_test() {
+        initialize();
+        let traversal = || Graph::traversal().v().has("age", gt(0)).has("name", eq("josh"));
+        let plan = traversal().explain(job_conf(6456)).expect("explain failed");
+        assert!(!plan.simplified);
+        assert_eq!(kinds(&plan), vec!["source", "filter", "filter"]);
+    }
+
+    // g.V().out().has("age", gt(30)).has("age", lt(20)).values("name"), where nothing passes the
+    // has steps, so neither the source scans nor the vertices are expanded
+    #[test]
+    fn contradiction_test() {
+        initialize();
+        let traversal = || {
+            Graph::traversal().v().out(&[]).has("age", gt(30)).has("age", lt(20)).values(&["name"])
+        };
+        let plan = traversal().explain(job_conf(6457)).expect("explain failed");
+        assert!(plan.simplified);
+        assert_eq!(kinds(&plan), vec!["source", "filter", "flat_map"]);
+        assert_eq!(plan.operators[0].access, "vertex scan with predicates never true");
+        assert_eq!(plan.operators[0].estimated_records, 0);
+        assert!(names(traversal(), 6458).is_empty());
+    }
+}
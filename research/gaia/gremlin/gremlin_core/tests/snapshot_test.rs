@@ -0,0 +1,222 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use dyn_type::Object;
+    use graph_store::delta::{GraphDelta, SnapshotPin};
+    use graph_store::config::JsonConf;
+    use graph_store::ldbc::LDBCVertexParser;
+    use graph_store::prelude::{
+        DefaultId, GlobalStoreUpdate, GraphDBConfig, GraphSchemaInfo, GraphStatistics, InternalId,
+        LDBCGraphSchema, LargeGraphDB, MutableGraphDB, Row, INVALID_LABEL_ID,
+    };
+    use gremlin_core::structure::{Direction, Edge, Label, QueryParams, Statement, Vertex};
+    use gremlin_core::traversal::*;
+    use gremlin_core::{create_mutable_graph, register_named_graph, DynResult, GraphProxy, ID};
+    use pegasus_server::generated::protocol as server_pb;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::mpsc::{channel, Receiver, Sender};
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    const SCHEMA: &str = r#"
+    {
+      "vertex_type_map": { "person": 0 },
+      "edge_type_map": { "knows": 0 },
+      "vertex_prop": {
+        "person": [["id", "ID"], ["name", "String"]]
+      },
+      "edge_prop": {
+        "knows": [["start_id", "ID"], ["end_id", "ID"]]
+      }
+    }
+    "#;
+
+    fn person(id: usize) -> DefaultId {
+        LDBCVertexParser::to_global_id(id, 0.into())
+    }
+
+    fn named(name: &str) -> HashMap<String, Object> {
+        let mut properties = HashMap::new();
+        properties.insert("name".to_owned(), Object::from(name));
+        properties
+    }
+
+    // marko knows vadas and lop, while josh knows nobody
+    fn create_store() -> LargeGraphDB<DefaultId, InternalId> {
+        let mut graph: MutableGraphDB<DefaultId, InternalId> = GraphDBConfig::default().new();
+        for (id, name) in [(1, "marko"), (2, "vadas"), (3, "lop"), (4, "josh")] {
+            graph.add_vertex(person(id), [0.into(), INVALID_LABEL_ID]);
+            let row = Row::from(vec![Object::from(id as u64), Object::from(name)]);
+            graph.add_or_update_vertex_properties(person(id), row).unwrap();
+        }
+        graph.add_edge(person(1), person(2), 0.into());
+        graph.add_edge(person(1), person(3), 0.into());
+        graph.into_graph(LDBCGraphSchema::from_json(SCHEMA.to_owned()).unwrap())
+    }
+
+    /// Hold the first scan once it reads a vertex, until it is told to go on
+    struct Gate {
+        armed: AtomicBool,
+        started: Mutex<Sender<()>>,
+        resume: Mutex<Receiver<()>>,
+    }
+
+    impl Gate {
+        fn pass(&self) {
+            if self.armed.swap(false, Ordering::SeqCst) {
+                self.started.lock().unwrap().send(()).unwrap();
+                self.resume.lock().unwrap().recv().unwrap();
+            }
+        }
+    }
+
+    /// The graph whose first scan is held by the gate, as a slow scan
+    struct GatedGraph {
+        inner: Arc<dyn GraphProxy>,
+        gate: Arc<Gate>,
+    }
+
+    impl GraphProxy for GatedGraph {
+        fn scan_vertex(
+            &self, params: &QueryParams<Vertex>,
+        ) -> DynResult<Box<dyn Iterator<Item = Vertex> + Send>> {
+            let gate = self.gate.clone();
+            Ok(Box::new(self.inner.scan_vertex(params)?.inspect(move |_| gate.pass())))
+        }
+
+        fn get_vertex(
+            &self, ids: &[ID], params: &QueryParams<Vertex>,
+        ) -> DynResult<Box<dyn Iterator<Item = Vertex> + Send>> {
+            self.inner.get_vertex(ids, params)
+        }
+
+        fn get_edge(
+            &self, ids: &[ID], params: &QueryParams<Edge>,
+        ) -> DynResult<Box<dyn Iterator<Item = Edge> + Send>> {
+            self.inner.get_edge(ids, params)
+        }
+
+        fn prepare_explore_vertex(
+            &self, direction: Direction, params: &QueryParams<Vertex>,
+        ) -> DynResult<Box<dyn Statement<ID, Vertex>>> {
+            self.inner.prepare_explore_vertex(direction, params)
+        }
+
+        fn prepare_explore_edge(
+            &self, direction: Direction, params: &QueryParams<Edge>,
+        ) -> DynResult<Box<dyn Statement<ID, Edge>>> {
+            self.inner.prepare_explore_edge(direction, params)
+        }
+
+        fn count_adj_edges(
+            &self, vid: ID, direction: Direction, edge_labels: &[Label],
+        ) -> DynResult<usize> {
+            self.inner.count_adj_edges(vid, direction, edge_labels)
+        }
+
+        fn get_statistics(&self) -> Option<Arc<GraphStatistics>> {
+            self.inner.get_statistics()
+        }
+
+        fn has_exact_statistics(&self) -> bool {
+            self.inner.has_exact_statistics()
+        }
+
+        fn get_schema(&self) -> Option<Arc<GraphSchemaInfo>> {
+            self.inner.get_schema()
+        }
+
+        fn pin_snapshot(&self) -> Option<SnapshotPin> {
+            self.inner.pin_snapshot()
+        }
+    }
+
+    fn job_conf(job_id: u64) -> server_pb::JobConfig {
+        server_pb::JobConfig {
+            job_id,
+            job_name: "snapshot_test".to_owned(),
+            workers: 1,
+            ..Default::default()
+        }
+    }
+
+    // the names of the persons known by the persons
+    fn known_names(job_id: u64) -> Vec<String> {
+        let traversal = Graph::traversal().v().on_graph("snapshot").out(&[]).values(&["name"]);
+        let mut names: Vec<String> = traversal
+            .run(job_conf(job_id))
+            .map(|r| r.expect("traversal failed").as_str().unwrap().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    fn count_persons(job_id: u64) -> Vec<Object> {
+        let traversal = Graph::traversal().v().on_graph("snapshot").count();
+        traversal.run(job_conf(job_id)).map(|r| r.expect("traversal failed")).collect()
+    }
+
+    fn wait_unpinned(delta: &GraphDelta<DefaultId>) {
+        let start = Instant::now();
+        while delta.oldest_pinned().is_some() {
+            assert!(start.elapsed() < Duration::from_secs(10), "the snapshot is never unpinned");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    // a job scanning the graph slowly reads the graph as it is once submitted, by its scan, the
+    // adjacency and the properties, however the graph is mutated while it runs
+    #[test]
+    fn snapshot_isolation_test() {
+        initialize();
+        let (graph, delta) = create_mutable_graph(create_store());
+        let (started_tx, started) = channel();
+        let (resume, resume_rx) = channel();
+        let gate = Gate {
+            armed: AtomicBool::new(true),
+            started: Mutex::new(started_tx),
+            resume: Mutex::new(resume_rx),
+        };
+        register_named_graph(
+            "snapshot",
+            Arc::new(GatedGraph { inner: graph, gate: Arc::new(gate) }),
+        );
+
+        let slow = std::thread::spawn(|| known_names(6401));
+        started.recv().unwrap();
+        let pinned = delta.oldest_pinned();
+        assert_eq!(pinned, Some(0));
+        delta.add_vertex(person(2), 0.into(), named("vadas2"));
+        delta.add_vertex(person(2), 0.into(), named("vadas3"));
+        delta.add_edge(person(1), person(4), 0.into(), HashMap::new());
+        delta.add_vertex(person(5), 0.into(), named("zed"));
+        delta.add_edge(person(5), person(2), 0.into(), HashMap::new());
+        // the versions read by the slow job are kept by the compaction
+        assert_eq!(delta.compact(), 0);
+        resume.send(()).unwrap();
+        assert_eq!(slow.join().unwrap(), vec!["lop", "vadas"]);
+
+        wait_unpinned(&delta);
+        assert_eq!(delta.compact(), 1);
+        assert_eq!(known_names(6402), vec!["josh", "lop", "vadas3", "vadas3"]);
+        assert_eq!(count_persons(6403), vec![Object::from(5u64)]);
+    }
+}
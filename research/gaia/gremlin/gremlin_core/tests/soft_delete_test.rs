@@ -0,0 +1,142 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use dyn_type::Object;
+    use graph_store::config::JsonConf;
+    use graph_store::ldbc::LDBCVertexParser;
+    use graph_store::prelude::{
+        DefaultId, GlobalStoreUpdate, GraphDBConfig, InternalId, LDBCGraphSchema, LargeGraphDB,
+        MutableGraphDB, Row, INVALID_LABEL_ID,
+    };
+    use gremlin_core::traversal::*;
+    use gremlin_core::{create_mutable_graph, register_named_graph, ID};
+    use pegasus_server::generated::protocol as server_pb;
+    use std::collections::HashMap;
+
+    const SCHEMA: &str = r#"
+    {
+      "vertex_type_map": { "person": 0 },
+      "edge_type_map": { "knows": 0 },
+      "vertex_prop": {
+        "person": [["id", "ID"], ["name", "String"]]
+      },
+      "edge_prop": {
+        "knows": [["start_id", "ID"], ["end_id", "ID"]]
+      }
+    }
+    "#;
+
+    fn person(id: usize) -> DefaultId {
+        LDBCVertexParser::to_global_id(id, 0.into())
+    }
+
+    fn named(name: &str) -> HashMap<String, Object> {
+        let mut properties = HashMap::new();
+        properties.insert("name".to_owned(), Object::from(name));
+        properties
+    }
+
+    // marko knows vadas and lop, josh knows lop
+    fn create_store() -> LargeGraphDB<DefaultId, InternalId> {
+        let mut graph: MutableGraphDB<DefaultId, InternalId> = GraphDBConfig::default().new();
+        for (id, name) in [(1, "marko"), (2, "vadas"), (3, "lop"), (4, "josh")] {
+            graph.add_vertex(person(id), [0.into(), INVALID_LABEL_ID]);
+            let row = Row::from(vec![Object::from(id as u64), Object::from(name)]);
+            graph.add_or_update_vertex_properties(person(id), row).unwrap();
+        }
+        graph.add_edge(person(1), person(2), 0.into());
+        graph.add_edge(person(1), person(3), 0.into());
+        graph.add_edge(person(4), person(3), 0.into());
+        graph.into_graph(LDBCGraphSchema::from_json(SCHEMA.to_owned()).unwrap())
+    }
+
+    fn job_conf(job_id: u64) -> server_pb::JobConfig {
+        server_pb::JobConfig {
+            job_id,
+            job_name: "soft_delete_test".to_owned(),
+            workers: 1,
+            ..Default::default()
+        }
+    }
+
+    fn run(traversal: GraphTraversal, job_id: u64) -> Vec<Object> {
+        traversal.run(job_conf(job_id)).map(|r| r.expect("traversal failed")).collect()
+    }
+
+    fn names(traversal: GraphTraversal, job_id: u64) -> Vec<String> {
+        let mut names: Vec<String> = run(traversal.values(&["name"]), job_id)
+            .into_iter()
+            .map(|name| name.as_str().unwrap().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    fn v() -> GraphTraversal {
+        Graph::traversal().v().on_graph("soft_delete")
+    }
+
+    fn v_ids(ids: &[usize]) -> GraphTraversal {
+        let ids: Vec<ID> = ids.iter().map(|id| person(*id) as ID).collect();
+        Graph::traversal().v_ids(&ids).on_graph("soft_delete")
+    }
+
+    fn count(traversal: GraphTraversal, job_id: u64) -> u64 {
+        run(traversal.count(), job_id)[0].as_u64().unwrap()
+    }
+
+    // the vertices and edges deleted vanish from the scans, the lookups, the expansions and the
+    // degrees of the jobs submitted after, together with the edges of the vertices deleted
+    #[test]
+    fn soft_delete_test() {
+        initialize();
+        let (graph, delta) = create_mutable_graph(create_store());
+        register_named_graph("soft_delete", graph);
+        assert_eq!(names(v().out(&[]), 6464), vec!["lop", "lop", "vadas"]);
+
+        delta.delete_vertex(person(2));
+        assert_eq!(names(v(), 6465), vec!["josh", "lop", "marko"]);
+        assert_eq!(count(v(), 6466), 3);
+        assert!(run(v_ids(&[2]), 6467).is_empty());
+        assert_eq!(names(v().out(&[]), 6468), vec!["lop", "lop"]);
+        assert_eq!(count(v_ids(&[1]).out_e(&[]), 6469), 1);
+        assert_eq!(run(v_ids(&[1]).out_degree(&[]), 6470), vec![Object::from(1u64)]);
+
+        delta.delete_edge(person(1), person(3), 0.into());
+        assert_eq!(names(v().out(&[]), 6471), vec!["lop"]);
+        assert_eq!(names(v_ids(&[3]).in_(&[]), 6472), vec!["josh"]);
+        assert!(run(v_ids(&[1]).both_e(&[]), 6473).is_empty());
+        assert_eq!(run(v_ids(&[3]).in_degree(&[]), 6474), vec![Object::from(1u64)]);
+
+        // the vertex added back is seen again, but not by the edges deleted with it
+        delta.add_vertex(person(2), 0.into(), named("vadas2"));
+        assert_eq!(names(v_ids(&[2]), 6475), vec!["vadas2"]);
+        assert!(run(v_ids(&[2]).in_(&[]), 6476).is_empty());
+        delta.add_edge(person(4), person(2), 0.into(), HashMap::new());
+        assert_eq!(names(v_ids(&[4]).out(&[]), 6477), vec!["lop", "vadas2"]);
+
+        // the edges added are deleted with their vertices as well
+        delta.delete_vertex(person(4));
+        assert_eq!(names(v(), 6478), vec!["lop", "marko", "vadas2"]);
+        assert!(run(v_ids(&[2]).in_(&[]), 6479).is_empty());
+        assert!(run(v_ids(&[3]).in_e(&[]), 6480).is_empty());
+        assert_eq!(run(v_ids(&[3]).in_degree(&[]), 6481), vec![Object::from(0u64)]);
+    }
+}
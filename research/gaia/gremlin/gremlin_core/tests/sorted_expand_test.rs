@@ -0,0 +1,142 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use dyn_type::Object;
+    use graph_store::common::LabelId;
+    use graph_store::schema::AdjOrder;
+    use gremlin_core::compiler::GremlinJobCompiler;
+    use gremlin_core::process::metrics::{
+        get_worker_counter, EXPAND_COUNTER, SORTED_EXPAND_COUNTER,
+    };
+    use gremlin_core::process::sorted_expand::push_down_order;
+    use gremlin_core::traversal::*;
+    use gremlin_core::{GremlinStepPb, Partition};
+    use pegasus_server::generated::protocol as server_pb;
+    use pegasus_server::service::{Output, Service};
+    use pegasus_server::JobResponse;
+    use prost::Message;
+
+    const WORKERS: u32 = 2;
+
+    #[derive(Clone)]
+    struct DiscardOutput;
+
+    impl Output for DiscardOutput {
+        fn send(&self, _res: JobResponse) {}
+
+        fn close(&self) {}
+    }
+
+    fn job_conf(job_id: u64) -> server_pb::JobConfig {
+        server_pb::JobConfig {
+            job_id,
+            job_name: "sorted_expand_test".to_owned(),
+            workers: WORKERS,
+            ..Default::default()
+        }
+    }
+
+    // the limit of the order pushed into the edge step, where the knows edges, of label 0, are
+    // stored sorted by weight in descending order, as of the modern graph
+    fn pushed(traversal: &GraphTraversal) -> Option<usize> {
+        let plan = traversal.to_request(server_pb::JobConfig::default()).plan.unwrap().plan;
+        let adj_order = |label| {
+            if label == LabelId::from(0) {
+                Some(AdjOrder { property: "weight".to_owned(), descending: true })
+            } else {
+                None
+            }
+        };
+        push_down_order(&plan, |res| GremlinStepPb::decode(res).ok(), adj_order)
+            .map(|expand| expand.limit)
+    }
+
+    #[test]
+    fn push_down_order_test() {
+        let g = || Graph::traversal().v();
+        assert_eq!(pushed(&g().out_e(&[0]).order_by_key("weight", true).limit(1)), Some(1));
+        assert_eq!(pushed(&g().in_e(&[0]).order_by_key("weight", true).limit(10)), Some(10));
+        // the order must be by the property stored sorted, in the same direction, with a limit
+        assert_eq!(pushed(&g().out_e(&[0]).order_by_key("weight", false).limit(1)), None);
+        assert_eq!(pushed(&g().out_e(&[0]).order_by_key("name", true).limit(1)), None);
+        assert_eq!(pushed(&g().out_e(&[0]).order_by_key("weight", true)), None);
+        // the edge step must be of a single label stored sorted, in one direction
+        assert_eq!(pushed(&g().out_e(&[1]).order_by_key("weight", true).limit(1)), None);
+        assert_eq!(pushed(&g().out_e(&[]).order_by_key("weight", true).limit(1)), None);
+        assert_eq!(pushed(&g().out_e(&[0, 1]).order_by_key("weight", true).limit(1)), None);
+        assert_eq!(pushed(&g().both_e(&[0]).order_by_key("weight", true).limit(1)), None);
+        assert_eq!(pushed(&g().out(&[0]).order_by_key("weight", true).limit(1)), None);
+    }
+
+    fn run(traversal: GraphTraversal, job_id: u64) -> Vec<Object> {
+        traversal.run(job_conf(job_id)).map(|r| r.expect("traversal failed")).collect()
+    }
+
+    // the traversals with the order pushed down give the results of the profiled ones, whose
+    // steps are never fused
+    #[test]
+    fn sorted_expand_traversal_test() {
+        initialize();
+        let g = || Graph::traversal().v();
+        let top = |k: u32| g().out_e(&[0]).order_by_key("weight", true).limit(k);
+        let pushed = run(top(2).values(&["weight"]), 6446);
+        let generic = run(top(2).values(&["weight"]).profile(), 6447);
+        assert_eq!(pushed, vec![Object::from(1.0), Object::from(0.5)]);
+        assert_eq!(pushed, generic);
+        let pushed = run(top(5).values(&["weight"]), 6448);
+        let generic = run(top(5).values(&["weight"]).profile(), 6449);
+        assert_eq!(pushed, vec![Object::from(1.0), Object::from(0.5)]);
+        assert_eq!(pushed, generic);
+        let top_in = || g().in_e(&[0]).order_by_key("weight", true).limit(1).values(&["weight"]);
+        let pushed = run(top_in(), 6450);
+        assert_eq!(pushed, vec![Object::from(1.0)]);
+        assert_eq!(pushed, run(top_in().profile(), 6451));
+    }
+
+    // the counters summed over the workers of the job, run till the job is torn down
+    fn run_counted(traversal: GraphTraversal, job_id: u64) -> (u64, u64) {
+        initialize();
+        let service = Service::new(GremlinJobCompiler::new(Partition { num_servers: 1 }, 1, 0));
+        service.accept(traversal.to_request(job_conf(job_id)), DiscardOutput);
+        if let Some(guard) = service.job_guards.write().unwrap().get_mut(&job_id) {
+            guard.join().expect("job failed");
+        }
+        let sum = |name| (0..WORKERS).map(|index| get_worker_counter(job_id, index, name)).sum();
+        (sum(SORTED_EXPAND_COUNTER), sum(EXPAND_COUNTER))
+    }
+
+    // the edge step emits at most `k` edges of each vertex it expands, while the generic path
+    // emits all of them
+    #[test]
+    fn sorted_expand_metrics_test() {
+        let g = || Graph::traversal().v();
+        let (emitted, frontier) =
+            run_counted(g().out_e(&[0]).order_by_key("weight", true).limit(1), 6452);
+        assert_eq!(frontier, 6);
+        assert!(emitted > 0);
+        assert!(emitted <= frontier);
+        // v1 knows both v2 and v4, of which only the heavier one is read
+        assert_eq!(emitted, 1);
+        let (emitted, frontier) =
+            run_counted(g().out_e(&[0]).order_by_key("weight", false).limit(1), 6453);
+        assert_eq!(frontier, 6);
+        assert_eq!(emitted, 0);
+    }
+}
@@ -0,0 +1,74 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use dyn_type::Object;
+    use gremlin_core::process::subgraph::{get_subgraph, register_subgraph, remove_subgraph};
+    use gremlin_core::traversal::*;
+    use gremlin_core::{register_graph, ID};
+    use pegasus_server::generated::protocol as server_pb;
+    use std::sync::Arc;
+
+    fn job_conf(job_id: u64) -> server_pb::JobConfig {
+        server_pb::JobConfig {
+            job_id,
+            job_name: "subgraph_test".to_owned(),
+            workers: 2,
+            ..Default::default()
+        }
+    }
+
+    fn run_embedded(traversal: GraphTraversal, job_id: u64) -> Vec<Object> {
+        traversal.run(job_conf(job_id)).map(|r| r.expect("traversal failed")).collect()
+    }
+
+    // the tests run in order, as the graph is replaced by the subgraph at last
+    #[test]
+    fn subgraph_test() {
+        initialize();
+        // g.V().both("knows").outE("knows").subgraph("sg").cap("sg"), where the knows edges of
+        // marko are traversed twice, from vadas and josh
+        let traversal = Graph::traversal().v().both(&[0]).out_e(&[0]).subgraph("sg").cap("sg");
+        let handles = run_embedded(traversal, 6191);
+        assert_eq!(handles, vec![Object::from("sg@6191")]);
+        let subgraph = remove_subgraph("sg@6191").expect("subgraph not published");
+        assert_eq!(subgraph.edge_count(), 2);
+        let mut ids = subgraph.vertices().iter().map(|v| v.id).collect::<Vec<ID>>();
+        ids.sort();
+        let mut expected = to_global_ids(vec![1, 2, 4]);
+        expected.sort();
+        assert_eq!(ids, expected);
+
+        // the distinct edges exceed the cap
+        let traversal =
+            Graph::traversal().v().out_e(&[]).subgraph_with_max_edges("sg", 3).cap("sg");
+        let results = traversal.run(job_conf(6192)).collect::<Vec<_>>();
+        assert!(results.iter().any(|r| r.is_err()));
+        assert!(get_subgraph("sg@6192").is_none());
+
+        // g.V().count() and g.V().out().count() on the knows subgraph
+        register_subgraph("knows", subgraph);
+        let knows = get_subgraph("knows").expect("subgraph not registered");
+        register_graph(Arc::new(knows));
+        let count = run_embedded(Graph::traversal().v().count(), 6193);
+        assert_eq!(count, vec![Object::from(3u64)]);
+        let count = run_embedded(Graph::traversal().v().out(&[]).count(), 6194);
+        assert_eq!(count, vec![Object::from(2u64)]);
+    }
+}
@@ -0,0 +1,179 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use dyn_type::Object;
+    use gremlin_core::process::metrics::{get_worker_counter, EXPAND_COUNTER};
+    use gremlin_core::process::traversal::step::ProjectRecord;
+    use gremlin_core::structure::{
+        DefaultDetails, Direction, Edge, Label, QueryParams, Statement, Vertex,
+    };
+    use gremlin_core::traversal::*;
+    use gremlin_core::{register_named_graph, DynIter, DynResult, GraphProxy, ID};
+    use pegasus_server::generated::protocol as server_pb;
+    use std::sync::{Arc, Once};
+
+    static REGISTER: Once = Once::new();
+
+    // the hub reaches the million vertices in [FIRST, SINK), each 1000th of which reaches the sink
+    const HUB: ID = 1;
+    const FIRST: ID = 2;
+    const DEGREE: u64 = 1_000_000;
+    const SINK: ID = FIRST + DEGREE as ID;
+    const WORKERS: u32 = 4;
+
+    fn vertex(id: ID) -> Vertex {
+        Vertex::new(id, Some(Label::Id(0.into())), DefaultDetails::new(id, Label::Id(0.into())))
+    }
+
+    /// The graph of a super vertex, whose edges are generated as they are expanded
+    struct HubGraph;
+
+    fn adjacent(v: ID) -> Box<dyn Iterator<Item = ID> + Send> {
+        if v == HUB {
+            Box::new(FIRST..SINK)
+        } else if (FIRST..SINK).contains(&v) && v.is_multiple_of(1000) {
+            Box::new(std::iter::once(SINK))
+        } else {
+            Box::new(std::iter::empty())
+        }
+    }
+
+    impl GraphProxy for HubGraph {
+        fn scan_vertex(
+            &self, _: &QueryParams<Vertex>,
+        ) -> DynResult<Box<dyn Iterator<Item = Vertex> + Send>> {
+            Ok(Box::new((HUB..=SINK).map(vertex)))
+        }
+
+        fn get_vertex(
+            &self, ids: &[ID], _: &QueryParams<Vertex>,
+        ) -> DynResult<Box<dyn Iterator<Item = Vertex> + Send>> {
+            Ok(Box::new(ids.iter().map(|id| vertex(*id)).collect::<Vec<_>>().into_iter()))
+        }
+
+        fn prepare_explore_vertex(
+            &self, direction: Direction, _: &QueryParams<Vertex>,
+        ) -> DynResult<Box<dyn Statement<ID, Vertex>>> {
+            assert_eq!(direction, Direction::Out);
+            Ok(Box::new(|v: ID| -> DynResult<DynIter<Vertex>> {
+                Ok(Box::new(adjacent(v).map(|id| Ok(vertex(id)))))
+            }))
+        }
+
+        fn prepare_explore_edge(
+            &self, _: Direction, _: &QueryParams<Edge>,
+        ) -> DynResult<Box<dyn Statement<ID, Edge>>> {
+            unimplemented!()
+        }
+
+        fn count_adj_edges(&self, vid: ID, _: Direction, _: &[Label]) -> DynResult<usize> {
+            Ok(adjacent(vid).count())
+        }
+    }
+
+    fn register_hub_graph() {
+        initialize();
+        REGISTER.call_once(|| register_named_graph("hub", Arc::new(HubGraph)));
+    }
+
+    // the adjacency of the hub is split into 4 ranges, one for each worker, if `split`
+    fn job_conf(job_id: u64, split: bool) -> server_pb::JobConfig {
+        server_pb::JobConfig {
+            job_id,
+            job_name: "super_vertex_test".to_owned(),
+            workers: WORKERS,
+            split_degree: if split { DEGREE / WORKERS as u64 } else { 0 },
+            ..Default::default()
+        }
+    }
+
+    fn sorted_ids(traversal: GraphTraversal, conf: server_pb::JobConfig) -> Vec<u128> {
+        let mut ids: Vec<u128> = traversal
+            .run(conf)
+            .map(|r| r.expect("traversal failed").as_u128().expect("not an id"))
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    // g.V(hub).out()
+    #[test]
+    fn split_expand_test() {
+        register_hub_graph();
+        let out = || Graph::traversal().v_ids(&[HUB]).on_graph("hub").out(&[]);
+        let unsplit = sorted_ids(out(), job_conf(6410, false));
+        let split = sorted_ids(out(), job_conf(6411, true));
+        assert_eq!(unsplit.len(), DEGREE as usize);
+        assert_eq!(split, unsplit);
+    }
+
+    // g.V(hub).out().count(), where each worker expands a quarter of the edges of the hub
+    #[test]
+    fn split_workers_test() {
+        register_hub_graph();
+        let count = |job_id: u64, split: bool| {
+            let traversal = Graph::traversal().v_ids(&[HUB]).on_graph("hub").out(&[]).count();
+            let factory = TestJobFactory::with_expect_values(vec![Object::from(DEGREE)]);
+            let request = traversal.to_request(job_conf(job_id, split));
+            let ended = run_tests_concurrently(factory, vec![request], WORKERS);
+            assert_eq!(ended, vec![Ok(())]);
+            (0..WORKERS).map(|index| get_worker_counter(job_id, index, EXPAND_COUNTER)).collect()
+        };
+        let unsplit: Vec<u64> = count(6412, false);
+        let split: Vec<u64> = count(6413, true);
+        assert_eq!(unsplit.iter().sum::<u64>(), 1);
+        assert_eq!(split, vec![1; WORKERS as usize]);
+    }
+
+    // g.V(hub).out().out().count(), where the second step expands the vertices reached by all
+    // the ranges of the hub
+    #[test]
+    fn split_two_hops_test() {
+        register_hub_graph();
+        let traversal = Graph::traversal().v_ids(&[HUB]).on_graph("hub").out(&[]).out(&[]).count();
+        let results: Vec<_> = traversal.run(job_conf(6414, true)).collect();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].as_ref().expect("count failed").as_u64().unwrap(), DEGREE / 1000);
+    }
+
+    // g.V(hub, 1000, 2).project("degree").by(out().count()), where the counts of the ranges of
+    // the hub, expanded by the other workers, are still joined to the hub
+    #[test]
+    fn split_subtask_test() {
+        register_hub_graph();
+        let bys = vec![Graph::anonymous().out(&[]).count()];
+        let traversal = Graph::traversal().v_ids(&[HUB, 1000, FIRST]).on_graph("hub").project(
+            &["degree"],
+            bys,
+            false,
+        );
+        let mut degrees: Vec<i64> = traversal
+            .run(job_conf(6415, true))
+            .map(|r| match r.expect("traversal failed") {
+                Object::DynOwned(x) => {
+                    let record = x.try_downcast_ref::<ProjectRecord>().expect("not a record");
+                    record.get("degree").expect("no degree").as_i64().expect("not a count")
+                }
+                other => panic!("unexpected result {:?}", other),
+            })
+            .collect();
+        degrees.sort();
+        assert_eq!(degrees, vec![0, 1, DEGREE as i64]);
+    }
+}
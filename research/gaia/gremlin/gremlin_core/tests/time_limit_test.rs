@@ -0,0 +1,62 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use gremlin_core::traversal::*;
+    use gremlin_core::validate::validate_request;
+    use gremlin_core::ID;
+    use pegasus_server::generated::protocol as server_pb;
+
+    fn job_conf(job_id: u64) -> server_pb::JobConfig {
+        server_pb::JobConfig {
+            job_id,
+            job_name: "time_limit_test".to_owned(),
+            workers: 2,
+            ..Default::default()
+        }
+    }
+
+    fn run_ids(traversal: GraphTraversal, job_id: u64) -> Vec<ID> {
+        initialize();
+        let mut ids: Vec<ID> = traversal
+            .run(job_conf(job_id))
+            .map(|r| r.expect("traversal failed").as_u128().expect("not an id") as ID)
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    // g.V().out().timeLimit(60000), which is never reached on the modern graph
+    #[test]
+    fn time_limit_not_reached_test() {
+        let traversal = Graph::traversal().v().out(&[]).time_limit(60000);
+        let request = traversal.to_request(job_conf(6404));
+        assert!(request.features.contains(&TIME_LIMIT_FEATURE.to_owned()));
+        validate_request(&request).expect("valid time limit rejected");
+        let ids = run_ids(traversal, 6404);
+        assert_eq!(ids, run_ids(Graph::traversal().v().out(&[]), 6405));
+    }
+
+    // g.V().timeLimit(0)
+    #[test]
+    fn time_limit_zero_test() {
+        let request = Graph::traversal().v().time_limit(0).to_request(job_conf(6406));
+        assert!(validate_request(&request).is_err());
+    }
+}
@@ -0,0 +1,118 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use gremlin_core::generated::gremlin as pb;
+    use gremlin_core::ID;
+    use pegasus_server::generated::protocol as server_pb;
+    use pegasus_server::JobRequest;
+    use prost::Message;
+
+    // the plan of out(), taken from g.V().out()
+    fn out_plan() -> Vec<server_pb::OperatorDef> {
+        let mut pb_request = read_pb_request(gen_path("out_step_test_01")).expect("read pb failed");
+        pb_request.plan.take().expect("plan not found").plan
+    }
+
+    // the plan of in(), by flipping the direction of out()
+    fn in_plan() -> Vec<server_pb::OperatorDef> {
+        let mut plan = out_plan();
+        for op in plan.iter_mut() {
+            if let Some(server_pb::operator_def::OpKind::FlatMap(flat_map)) = op.op_kind.as_mut() {
+                let mut step = pb::GremlinStep::decode(flat_map.resource.as_slice())
+                    .expect("decode step failed");
+                if let Some(pb::gremlin_step::Step::VertexStep(vertex_step)) = step.step.as_mut() {
+                    vertex_step.direction = pb::Direction::In as i32;
+                }
+                flat_map.resource.clear();
+                step.encode(&mut flat_map.resource).expect("encode step failed");
+            }
+        }
+        plan
+    }
+
+    // where(traversal) or not(traversal), as compiled from the gremlin query
+    fn semi_join_op(
+        kind: server_pb::subtask::SemiJoin, body: Vec<server_pb::OperatorDef>,
+    ) -> server_pb::OperatorDef {
+        let subtask = server_pb::Subtask {
+            join: None,
+            task: Some(server_pb::TaskPlan { plan: body }),
+            semi_join: kind as i32,
+            optional: false,
+            first: false,
+        };
+        server_pb::OperatorDef {
+            op_kind: Some(server_pb::operator_def::OpKind::Subtask(subtask)),
+            ..Default::default()
+        }
+    }
+
+    // g.V() followed by the given predicates
+    fn where_request(predicates: Vec<server_pb::OperatorDef>) -> JobRequest {
+        let mut pb_request = read_pb_request(gen_path("out_step_test_01")).expect("read pb failed");
+        pb_request.plan = Some(server_pb::TaskPlan { plan: predicates });
+        pb_request
+    }
+
+    fn sorted_global_ids(ids: Vec<usize>) -> Vec<ID> {
+        let mut ids = to_global_ids(ids);
+        ids.sort();
+        ids
+    }
+
+    // g.V().where(out())
+    #[test]
+    fn where_test_01() {
+        initialize();
+        let where_out = semi_join_op(server_pb::subtask::SemiJoin::AnyExists, out_plan());
+        let test_job_factory = TestJobFactory::with_expect_ids(sorted_global_ids(vec![1, 4, 6]));
+        run_test_with_job_id(test_job_factory, where_request(vec![where_out]), 6001, 2);
+    }
+
+    // g.V().not(out())
+    #[test]
+    fn where_not_test_01() {
+        initialize();
+        let not_out = semi_join_op(server_pb::subtask::SemiJoin::NoneExists, out_plan());
+        let test_job_factory = TestJobFactory::with_expect_ids(sorted_global_ids(vec![2, 3, 5]));
+        run_test_with_job_id(test_job_factory, where_request(vec![not_out]), 6002, 2);
+    }
+
+    // g.V().and(out(), in())
+    #[test]
+    fn where_and_test_01() {
+        initialize();
+        let where_out = semi_join_op(server_pb::subtask::SemiJoin::AnyExists, out_plan());
+        let where_in = semi_join_op(server_pb::subtask::SemiJoin::AnyExists, in_plan());
+        let test_job_factory = TestJobFactory::with_expect_ids(sorted_global_ids(vec![4]));
+        run_test_with_job_id(test_job_factory, where_request(vec![where_out, where_in]), 6003, 2);
+    }
+
+    // g.V().where(out().where(out()))
+    #[test]
+    fn where_nested_test_01() {
+        initialize();
+        let mut body = out_plan();
+        body.push(semi_join_op(server_pb::subtask::SemiJoin::AnyExists, out_plan()));
+        let where_nested = semi_join_op(server_pb::subtask::SemiJoin::AnyExists, body);
+        let test_job_factory = TestJobFactory::with_expect_ids(sorted_global_ids(vec![1]));
+        run_test_with_job_id(test_job_factory, where_request(vec![where_nested]), 6004, 2);
+    }
+}
@@ -0,0 +1,80 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::test::*;
+    use gremlin_core::traversal::*;
+    use pegasus_server::generated::protocol as server_pb;
+
+    // each worker is expected to scan 2 vertices at most
+    const RECORDS_PER_WORKER: usize = 2;
+
+    fn auto_conf(job_id: u64, min: u32, max: u32) -> server_pb::JobConfig {
+        let worker_hint =
+            server_pb::WorkerHint { kind: server_pb::worker_hint::Kind::Auto as i32, min, max };
+        server_pb::JobConfig {
+            job_id,
+            job_name: "worker_hint_test".to_owned(),
+            workers: 1,
+            worker_hint: Some(worker_hint),
+            ..Default::default()
+        }
+    }
+
+    fn run_auto(
+        traversal: GraphTraversal, factory: TestJobFactory, job_id: u64, min: u32, max: u32,
+    ) {
+        initialize();
+        let request = traversal.to_request(auto_conf(job_id, min, max));
+        let factory = factory.with_records_per_worker(RECORDS_PER_WORKER);
+        run_test_with_job_id(factory, request, job_id, 1);
+    }
+
+    // g.V(1), which is tiny enough for a single worker
+    #[test]
+    fn auto_workers_tiny_input_test() {
+        let ids = to_global_ids(vec![1]);
+        let factory = TestJobFactory::with_expect_ids(ids.clone()).with_expect_peers(1);
+        run_auto(Graph::traversal().v_ids(&ids), factory, 6060, 1, 4);
+    }
+
+    // g.V(), which scans 6 vertices by 3 workers
+    #[test]
+    fn auto_workers_large_input_test() {
+        let mut ids = to_global_ids(vec![1, 2, 3, 4, 5, 6]);
+        ids.sort();
+        let factory = TestJobFactory::with_expect_ids(ids).with_expect_peers(3);
+        run_auto(Graph::traversal().v(), factory, 6061, 1, 4);
+    }
+
+    // g.V(), which is bounded by the max of the hint
+    #[test]
+    fn auto_workers_bounded_test() {
+        let mut ids = to_global_ids(vec![1, 2, 3, 4, 5, 6]);
+        ids.sort();
+        let factory = TestJobFactory::with_expect_ids(ids).with_expect_peers(2);
+        run_auto(Graph::traversal().v(), factory, 6062, 1, 2);
+    }
+
+    // g.V().count(), which aggregates the partial counts of the 3 workers
+    #[test]
+    fn auto_workers_count_test() {
+        let factory = TestJobFactory::with_expect_values(vec![6u64.into()]).with_expect_peers(3);
+        run_auto(Graph::traversal().v().count(), factory, 6063, 1, 4);
+    }
+}
@@ -18,7 +18,11 @@ package com.alibaba.pegasus;
 import com.alibaba.pegasus.common.StreamIterator;
 import com.alibaba.pegasus.intf.CloseableIterator;
 import com.alibaba.pegasus.service.protocol.JobServiceGrpc;
+import com.alibaba.pegasus.service.protocol.JobServiceGrpc.JobServiceBlockingStub;
 import com.alibaba.pegasus.service.protocol.JobServiceGrpc.JobServiceStub;
+import com.alibaba.pegasus.service.protocol.PegasusClient.CancelCursorRequest;
+import com.alibaba.pegasus.service.protocol.PegasusClient.FetchPageRequest;
+import com.alibaba.pegasus.service.protocol.PegasusClient.FetchPageResponse;
 import com.alibaba.pegasus.service.protocol.PegasusClient.JobResponse;
 import com.alibaba.pegasus.service.protocol.PegasusClient.JobRequest;
 import io.grpc.Status;
@@ -45,14 +49,39 @@ public class RpcClient {
         StreamIterator<JobResponse> responseIterator = new StreamIterator<>();
         AtomicInteger counter = new AtomicInteger(this.channels.size());
         AtomicBoolean finished = new AtomicBoolean(false);
+        // the profile of a profiled job is sent after the job is complete, until the stream ends
+        boolean waitProfile = jobRequest.getConf().getProfile();
         for (RpcChannel rpcChannel : channels) {
             JobServiceStub asyncStub = JobServiceGrpc.newStub(rpcChannel.getChannel());
             // TODO: fix timeout according to config
-            asyncStub.withDeadlineAfter(600000, TimeUnit.MILLISECONDS).submit(jobRequest, new JobResponseObserver(responseIterator, finished, counter));
+            asyncStub.withDeadlineAfter(600000, TimeUnit.MILLISECONDS).submit(jobRequest,
+                    new JobResponseObserver(responseIterator, finished, counter, waitProfile));
         }
         return responseIterator;
     }
 
+    /**
+     * Fetch a page of the results of a job submitted with a page size from the server of the given
+     * channel, from token 0 and then the next token of each page until the last page.
+     */
+    public FetchPageResponse fetchPage(int server, long jobId, long token) {
+        FetchPageRequest request = FetchPageRequest.newBuilder().setJobId(jobId).setToken(token).build();
+        return blockingStub(server).fetchPage(request);
+    }
+
+    /**
+     * Release the pages of the job not fetched yet on the server of the given channel, which also
+     * cancels the job if it is still running.
+     */
+    public boolean cancelCursor(int server, long jobId) {
+        CancelCursorRequest request = CancelCursorRequest.newBuilder().setJobId(jobId).build();
+        return blockingStub(server).cancelCursor(request).getCancelled();
+    }
+
+    private JobServiceBlockingStub blockingStub(int server) {
+        return JobServiceGrpc.newBlockingStub(channels.get(server).getChannel());
+    }
+
     public void shutdown() throws InterruptedException {
         for (RpcChannel rpcChannel : channels) {
             rpcChannel.shutdown();
@@ -63,11 +92,16 @@ public class RpcClient {
         private final StreamIterator<JobResponse> iterator;
         private final AtomicBoolean finished;
         private final AtomicInteger counter;
+        private final boolean waitProfile;
+        // set once the job is complete on the server, by the completion or the end of the stream
+        private final AtomicBoolean completed = new AtomicBoolean(false);
 
-        public JobResponseObserver(StreamIterator<JobResponse> iterator, AtomicBoolean finished, AtomicInteger counter) {
+        public JobResponseObserver(StreamIterator<JobResponse> iterator, AtomicBoolean finished, AtomicInteger counter,
+                                   boolean waitProfile) {
             this.iterator = iterator;
             this.finished = finished;
             this.counter = counter;
+            this.waitProfile = waitProfile;
         }
 
         @Override
@@ -82,7 +116,15 @@ public class RpcClient {
                     onError(new InterruptedException(msg));
                     return;
                 }
+                if (completed.get()) {
+                    return;
+                }
                 this.iterator.putData(jobResponse);
+                // the server tells the job is complete after all its results, ahead of tearing it down
+                if (jobResponse.hasComplete() && !waitProfile) {
+                    logger.info("job complete on one server: {}", jobResponse.getComplete().getStats());
+                    complete();
+                }
             } catch (InterruptedException e) {
                 onError(e);
             }
@@ -101,6 +143,13 @@ public class RpcClient {
         @Override
         public void onCompleted() {
             logger.info("finish get job response from one server");
+            complete();
+        }
+
+        private void complete() {
+            if (completed.getAndSet(true)) {
+                return;
+            }
             if (counter.decrementAndGet() == 0) {
                 logger.info("finish get job response from all servers");
                 try {
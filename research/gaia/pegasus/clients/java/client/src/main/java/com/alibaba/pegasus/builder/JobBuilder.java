@@ -17,9 +17,11 @@ package com.alibaba.pegasus.builder;
 
 import com.alibaba.pegasus.intf.NestedFunc;
 import com.alibaba.pegasus.service.protocol.PegasusClient.AccumKind;
+import com.alibaba.pegasus.service.protocol.PegasusClient.Iteration;
 import com.alibaba.pegasus.service.protocol.PegasusClient.JobConfig;
 import com.alibaba.pegasus.service.protocol.PegasusClient.JobRequest;
 import com.alibaba.pegasus.service.protocol.PegasusClient.Sink;
+import com.alibaba.pegasus.service.protocol.PegasusClient.Subtask;
 import com.google.protobuf.ByteString;
 import org.slf4j.Logger;
 import org.slf4j.LoggerFactory;
@@ -120,6 +122,11 @@ public class JobBuilder extends AbstractBuilder {
         return this;
     }
 
+    public JobBuilder repeatUntil(int times, ByteString until, boolean postCheck, Iteration.EmitKind emit, JobBuilder subPlan) {
+        this.plan.repeateUntil(times, until, postCheck, emit, subPlan.plan);
+        return this;
+    }
+
     public JobBuilder fork(JobBuilder subPlan) {
         this.plan.fork(subPlan.getPlan());
         return this;
@@ -140,6 +147,16 @@ public class JobBuilder extends AbstractBuilder {
         return this;
     }
 
+    public JobBuilder semiJoin(Subtask.SemiJoin kind, JobBuilder subPlan) {
+        this.plan.semiJoin(kind, subPlan.getPlan());
+        return this;
+    }
+
+    public JobBuilder semiJoin(Subtask.SemiJoin kind, NestedFunc func) {
+        this.plan.semiJoin(kind, func);
+        return this;
+    }
+
     public JobBuilder union(List<JobBuilder> subPlans) {
         List<Plan> plans = new ArrayList<>();
         subPlans.forEach(builder -> plans.add(builder.getPlan()));
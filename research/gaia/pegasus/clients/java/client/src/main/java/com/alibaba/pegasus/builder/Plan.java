@@ -413,6 +413,37 @@ public class Plan {
         this.plan.add(operatorDef);
     }
 
+    public void repeateUntil(int times, ByteString until, boolean postCheck, Iteration.EmitKind emit, Plan subPlan) {
+        TaskPlan taskPlan = TaskPlan
+                .newBuilder()
+                .addAllPlan(subPlan.getPlan())
+                .build();
+        Filter filterUntil = Filter.newBuilder()
+                .setResource(until)
+                .build();
+        Iteration iteration  = Iteration
+                .newBuilder()
+                .setMaxIters(times)
+                .setBody(taskPlan)
+                .setUntil(filterUntil)
+                .setPostCheck(postCheck)
+                .setEmit(emit)
+                .build();
+        Pipeline pipeline = Pipeline
+                .newBuilder()
+                .build();
+        ChannelDef channelDef = ChannelDef
+                .newBuilder()
+                .setToLocal(pipeline)
+                .build();
+        OperatorDef operatorDef = OperatorDef
+                .newBuilder()
+                .setCh(channelDef)
+                .setIterate(iteration)
+                .build();
+        this.plan.add(operatorDef);
+    }
+
     public void fork(Plan subPlan) {
         TaskPlan taskPlan = TaskPlan
                 .newBuilder()
@@ -523,6 +554,37 @@ public class Plan {
         this.plan.add(operatorDef);
     }
 
+    public void semiJoin(Subtask.SemiJoin kind, Plan subPlan) {
+        TaskPlan taskPlan = TaskPlan
+                .newBuilder()
+                .addAllPlan(subPlan.getPlan())
+                .build();
+        Subtask subtask = Subtask
+                .newBuilder()
+                .setTask(taskPlan)
+                .setSemiJoin(kind)
+                .build();
+        Pipeline pipeline = Pipeline
+                .newBuilder()
+                .build();
+        ChannelDef channelDef = ChannelDef
+                .newBuilder()
+                .setToLocal(pipeline)
+                .build();
+        OperatorDef operatorDef = OperatorDef
+                .newBuilder()
+                .setCh(channelDef)
+                .setSubtask(subtask)
+                .build();
+        this.plan.add(operatorDef);
+    }
+
+    public void semiJoin(Subtask.SemiJoin kind, NestedFunc func) {
+        Plan subPlan = new Plan();
+        func.nestedFunc(subPlan);
+        semiJoin(kind, subPlan);
+    }
+
     public void union(List<Plan> subPlans) {
         Pipeline pipeline = Pipeline
                 .newBuilder()
@@ -636,6 +698,19 @@ public class Plan {
         }
     }
 
+    /**
+     * Name the operators added since the index {@code start} which are not named yet, e.g. after the
+     * step they are compiled from, as shown in the profile of the job.
+     */
+    public void nameFrom(int start, String name) {
+        for (int i = Math.max(start, 0); i < plan.size(); ++i) {
+            OperatorDef op = plan.get(i);
+            if (op.getName().isEmpty()) {
+                plan.set(i, op.toBuilder().setName(name).build());
+            }
+        }
+    }
+
     public boolean endReduce() {
         int len = plan.size();
         if (len == 0) {
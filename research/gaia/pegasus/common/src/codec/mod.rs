@@ -334,13 +334,176 @@ impl<T: Decode> Decode for Option<T> {
     }
 }
 
+impl Encode for bool {
+    fn write_to<W: WriteExt>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_u8(*self as u8)
+    }
+}
+
+impl Decode for bool {
+    fn read_from<R: ReadExt>(reader: &mut R) -> io::Result<Self> {
+        Ok(reader.read_u8()? != 0)
+    }
+}
+
+impl Encode for char {
+    fn write_to<W: WriteExt>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_u32(*self as u32)
+    }
+}
+
+impl Decode for char {
+    fn read_from<R: ReadExt>(reader: &mut R) -> io::Result<Self> {
+        let code = reader.read_u32()?;
+        std::char::from_u32(code).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("invalid char {}", code))
+        })
+    }
+}
+
+// the sizes are always encoded as 64 bits, regardless of the platforms
+impl Encode for usize {
+    fn write_to<W: WriteExt>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_u64(*self as u64)
+    }
+}
+
+impl Decode for usize {
+    fn read_from<R: ReadExt>(reader: &mut R) -> io::Result<Self> {
+        Ok(reader.read_u64()? as usize)
+    }
+}
+
+impl Encode for isize {
+    fn write_to<W: WriteExt>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_i64(*self as i64)
+    }
+}
+
+impl Decode for isize {
+    fn read_from<R: ReadExt>(reader: &mut R) -> io::Result<Self> {
+        Ok(reader.read_i64()? as isize)
+    }
+}
+
+impl Encode for () {
+    fn write_to<W: WriteExt>(&self, _writer: &mut W) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Decode for () {
+    fn read_from<R: ReadExt>(_reader: &mut R) -> io::Result<Self> {
+        Ok(())
+    }
+}
+
+impl<T: Encode> Encode for Box<T> {
+    fn write_to<W: WriteExt>(&self, writer: &mut W) -> io::Result<()> {
+        self.as_ref().write_to(writer)
+    }
+}
+
+impl<T: Decode> Decode for Box<T> {
+    fn read_from<R: ReadExt>(reader: &mut R) -> io::Result<Self> {
+        Ok(Box::new(T::read_from(reader)?))
+    }
+}
+
+/// Encode the items of a collection prefixed by the number of them, as `Vec`
+macro_rules! serialize_collection {
+    ($ty: ident, $new: expr $(, $bound: path)*) => {
+        impl<T: Encode> Encode for $ty<T> {
+            fn write_to<W: WriteExt>(&self, writer: &mut W) -> io::Result<()> {
+                writer.write_u64(self.len() as u64)?;
+                for item in self.iter() {
+                    item.write_to(writer)?;
+                }
+                Ok(())
+            }
+        }
+
+        impl<T: Decode $(+ $bound)*> Decode for $ty<T> {
+            fn read_from<R: ReadExt>(reader: &mut R) -> io::Result<Self> {
+                let len = reader.read_u64()? as usize;
+                let new: fn(usize) -> $ty<T> = $new;
+                let mut collection = new(len);
+                for _i in 0..len {
+                    collection.extend(std::iter::once(T::read_from(reader)?));
+                }
+                Ok(collection)
+            }
+        }
+    };
+}
+
+serialize_collection!(HashSet, HashSet::with_capacity, Eq, Hash);
+serialize_collection!(BTreeSet, |_| BTreeSet::new(), Ord);
+serialize_collection!(VecDeque, VecDeque::with_capacity);
+
+impl<K: Encode, V: Encode> Encode for BTreeMap<K, V> {
+    fn write_to<W: WriteExt>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_u64(self.len() as u64)?;
+        for (k, v) in self {
+            k.write_to(writer)?;
+            v.write_to(writer)?;
+        }
+        Ok(())
+    }
+}
+
+impl<K: Decode + Ord, V: Decode> Decode for BTreeMap<K, V> {
+    fn read_from<R: ReadExt>(reader: &mut R) -> io::Result<Self> {
+        let len = reader.read_u64()? as usize;
+        let mut map = BTreeMap::new();
+        for _i in 0..len {
+            let k = K::read_from(reader)?;
+            let v = V::read_from(reader)?;
+            map.insert(k, v);
+        }
+        Ok(map)
+    }
+}
+
+impl<A: Array> Encode for SmallVec<A>
+where
+    A::Item: Encode,
+{
+    fn write_to<W: WriteExt>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_u64(self.len() as u64)?;
+        for item in self.iter() {
+            item.write_to(writer)?;
+        }
+        Ok(())
+    }
+}
+
+impl<A: Array> Decode for SmallVec<A>
+where
+    A::Item: Decode,
+{
+    fn read_from<R: ReadExt>(reader: &mut R) -> io::Result<Self> {
+        let len = reader.read_u64()? as usize;
+        let mut vec = SmallVec::with_capacity(len);
+        for _i in 0..len {
+            vec.push(A::Item::read_from(reader)?);
+        }
+        Ok(vec)
+    }
+}
+
 mod shade;
 mod third_party;
+mod versioned;
+pub use pegasus_derive::{Decode, Encode};
 pub use shade::{shade_codec, ShadeCodec};
-use std::collections::HashMap;
+use smallvec::{Array, SmallVec};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 use std::hash::Hash;
 #[cfg(feature = "serde")]
 pub use third_party::serde_bin as serde;
+#[doc(hidden)]
+pub use versioned::{read_or_default, read_versioned, write_versioned};
 
 #[cfg(test)]
 mod test {
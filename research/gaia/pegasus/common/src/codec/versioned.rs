@@ -0,0 +1,201 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The versioned blocks of the fields encoded by `#[derive(Encode, Decode)]`, see `pegasus_derive`.
+
+use super::{Decode, ReadExt, WriteExt};
+use std::io;
+
+/// Write the fields written by `write` as a block prefixed by its length, so that the readers of
+/// older versions can skip the trailing fields unknown to them
+pub fn write_versioned<W, F>(writer: &mut W, write: F) -> io::Result<()>
+where
+    W: WriteExt,
+    F: FnOnce(&mut Vec<u8>) -> io::Result<()>,
+{
+    let mut block = Vec::new();
+    write(&mut block)?;
+    writer.write_u32(block.len() as u32)?;
+    writer.write_all(&block)
+}
+
+/// Read the fields by `read` from a block written by `write_versioned`, where the trailing fields
+/// left unread, i.e. written by newer versions, are skipped
+pub fn read_versioned<R, T, F>(reader: &mut R, read: F) -> io::Result<T>
+where
+    R: ReadExt,
+    F: FnOnce(&mut &[u8]) -> io::Result<T>,
+{
+    let len = reader.read_u32()? as usize;
+    let mut block = vec![0u8; len];
+    reader.read_exact(&mut block)?;
+    read(&mut &block[..])
+}
+
+/// Read a field from the block, or the default if the block ends before it, i.e. written by an
+/// older version without the field
+pub fn read_or_default<T: Decode + Default>(block: &mut &[u8]) -> io::Result<T> {
+    if block.is_empty() {
+        Ok(T::default())
+    } else {
+        T::read_from(block)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::codec::{Decode, Encode};
+    use smallvec::SmallVec;
+    use std::collections::HashMap;
+    use std::fmt::Debug;
+
+    #[derive(Debug, Clone, PartialEq, Encode, Decode)]
+    struct Person {
+        name: String,
+        age: u16,
+        emails: Vec<String>,
+        phone: Option<u64>,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Encode, Decode)]
+    struct Pair(u32, Person);
+
+    #[derive(Debug, Clone, PartialEq, Encode, Decode)]
+    struct Unit;
+
+    #[derive(Debug, Clone, PartialEq, Encode, Decode)]
+    enum Shape {
+        Empty,
+        Circle(f64),
+        Rect { width: u32, height: u32 },
+        Labeled(String, Box<Shape>),
+    }
+
+    #[derive(Debug, Clone, PartialEq, Encode, Decode)]
+    struct Wrapper<T> {
+        items: Vec<T>,
+    }
+
+    fn round_trip<T: Encode + Decode + PartialEq + Debug>(item: T) {
+        let mut bytes = vec![];
+        item.write_to(&mut bytes).unwrap();
+        let mut reader = &bytes[..];
+        let decoded = T::read_from(&mut reader).unwrap();
+        assert_eq!(item, decoded);
+        assert!(reader.is_empty());
+    }
+
+    fn marko() -> Person {
+        Person {
+            name: "marko".to_owned(),
+            age: 29,
+            emails: vec!["marko@a.com".to_owned()],
+            phone: None,
+        }
+    }
+
+    #[test]
+    fn derive_struct_test() {
+        round_trip(marko());
+        round_trip(Pair(1, marko()));
+        round_trip(Unit);
+        round_trip(Wrapper { items: vec![marko(), marko()] });
+    }
+
+    #[test]
+    fn derive_enum_test() {
+        round_trip(Shape::Empty);
+        round_trip(Shape::Circle(1.5));
+        round_trip(Shape::Rect { width: 3, height: 4 });
+        round_trip(Shape::Labeled("a".to_owned(), Box::new(Shape::Rect { width: 1, height: 2 })));
+        round_trip(vec![Shape::Empty, Shape::Circle(0.5)]);
+        // the variants unknown to current version
+        let mut bytes = vec![];
+        bytes.extend_from_slice(&4u32.to_le_bytes());
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        let err = Shape::read_from(&mut &bytes[..]).unwrap_err();
+        assert!(err.to_string().contains("unknown variant 4 of Shape"));
+    }
+
+    mod v1 {
+        use crate::codec::{Decode, Encode};
+
+        #[derive(Debug, PartialEq, Encode, Decode)]
+        pub struct Person {
+            pub name: String,
+            pub age: u16,
+        }
+    }
+
+    mod v2 {
+        use crate::codec::{Decode, Encode};
+
+        #[derive(Debug, PartialEq, Encode, Decode)]
+        pub struct Person {
+            pub name: String,
+            pub age: u16,
+            #[codec(default)]
+            pub email: Option<String>,
+            #[codec(default)]
+            pub tags: Vec<String>,
+        }
+    }
+
+    #[test]
+    fn versioned_test() {
+        // the newer fields are skipped by the older version
+        let person = v2::Person {
+            name: "vadas".to_owned(),
+            age: 27,
+            email: Some("vadas@a.com".to_owned()),
+            tags: vec!["x".to_owned()],
+        };
+        let mut bytes = vec![];
+        (person, 1u32).write_to(&mut bytes).unwrap();
+        let (decoded, next) = <(v1::Person, u32)>::read_from(&mut &bytes[..]).unwrap();
+        assert_eq!(decoded, v1::Person { name: "vadas".to_owned(), age: 27 });
+        assert_eq!(next, 1);
+
+        // the newer fields are defaults if written by the older version
+        let mut bytes = vec![];
+        (v1::Person { name: "josh".to_owned(), age: 32 }, 2u32).write_to(&mut bytes).unwrap();
+        let (decoded, next) = <(v2::Person, u32)>::read_from(&mut &bytes[..]).unwrap();
+        let expected = v2::Person { name: "josh".to_owned(), age: 32, email: None, tags: vec![] };
+        assert_eq!(decoded, expected);
+        assert_eq!(next, 2);
+    }
+
+    #[test]
+    fn std_types_test() {
+        round_trip(true);
+        round_trip('x');
+        round_trip(42usize);
+        round_trip(-42isize);
+        round_trip(());
+        round_trip(Box::new(marko()));
+        round_trip((1u8, 2u16, 3u32, 4u64, 5i8, 6i16, "7".to_owned(), Some(8i64)));
+        let mut map = HashMap::new();
+        map.insert("marko".to_owned(), marko());
+        round_trip(map);
+        round_trip(vec![1u32, 2, 3].into_iter().collect::<std::collections::HashSet<_>>());
+        round_trip(
+            vec![(1u32, 'a'), (2, 'b')].into_iter().collect::<std::collections::BTreeMap<_, _>>(),
+        );
+        round_trip(vec![3u64, 1, 2].into_iter().collect::<std::collections::BTreeSet<_>>());
+        round_trip(vec![1u32, 2].into_iter().collect::<std::collections::VecDeque<_>>());
+        let small: SmallVec<[u32; 4]> = vec![1, 2, 3, 4, 5].into_iter().collect();
+        round_trip(small);
+    }
+}
@@ -220,6 +220,7 @@ impl WriteExt for std::io::Sink {}
 impl WriteExt for std::net::TcpStream {}
 impl WriteExt for &std::net::TcpStream {}
 impl WriteExt for Vec<u8> {}
+impl<W: Write> WriteExt for std::io::BufWriter<W> {}
 
 impl ReadExt for &[u8] {}
 impl ReadExt for std::fs::File {}
@@ -228,6 +229,7 @@ impl ReadExt for std::io::Empty {}
 impl ReadExt for std::net::TcpStream {}
 impl ReadExt for &std::net::TcpStream {}
 impl<T: AsRef<[u8]>> ReadExt for std::io::Cursor<T> {}
+impl<R: Read> ReadExt for std::io::BufReader<R> {}
 
 pub struct BytesRead {
     buf: Bytes,
@@ -15,6 +15,8 @@
 
 #[macro_use]
 extern crate log;
+// the code derived by `pegasus_derive` refers to `pegasus_common`, e.g. in the tests of codec
+extern crate self as pegasus_common;
 
 pub mod bytes;
 pub mod channel;
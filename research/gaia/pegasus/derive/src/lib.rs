@@ -0,0 +1,235 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The derive macros of `Encode` and `Decode` of `pegasus_common::codec`, for the user records
+//! sent through channels, which are re-exported by `pegasus_common::codec`, e.g.
+//!
+//! ```ignore
+//! use pegasus_common::codec::{Decode, Encode};
+//!
+//! #[derive(Encode, Decode)]
+//! struct Person {
+//!     name: String,
+//!     age: u16,
+//!     // added by a later version, which is decoded as default if written by older versions
+//!     #[codec(default)]
+//!     email: Option<String>,
+//! }
+//! ```
+//!
+//! The fields of a struct or an enum variant are encoded in order, into a block prefixed by its
+//! length, so that a reader skips the trailing fields unknown to it, i.e. added by newer
+//! versions, and decodes the fields marked by `#[codec(default)]` as default if the block ends
+//! before them, i.e. written by older versions. The variants of an enum are identified by their
+//! indices in declaration, so new variants should be added to the end only.
+//!
+//! The generated code refers to the crate `pegasus_common`, which should be a dependency of the
+//! crate deriving them.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::spanned::Spanned;
+use syn::{parse_macro_input, parse_quote, Data, DeriveInput, Fields, GenericParam, Generics};
+
+#[proc_macro_derive(Encode, attributes(codec))]
+pub fn derive_encode(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+    let generics = add_bounds(input.generics.clone(), quote!(pegasus_common::codec::Encode));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let (pattern, write) = write_fields(&data.fields);
+            quote! {
+                let #name #pattern = self;
+                #write
+            }
+        }
+        Data::Enum(data) => {
+            let arms = data.variants.iter().enumerate().map(|(index, variant)| {
+                let ident = &variant.ident;
+                let index = index as u32;
+                let (pattern, write) = write_fields(&variant.fields);
+                quote! {
+                    #name::#ident #pattern => {
+                        pegasus_common::codec::WriteExt::write_u32(writer, #index)?;
+                        #write
+                    }
+                }
+            });
+            quote! {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+        Data::Union(_) => {
+            return syn::Error::new(input.span(), "Encode can't be derived for unions")
+                .to_compile_error()
+                .into();
+        }
+    };
+    let expanded = quote! {
+        impl #impl_generics pegasus_common::codec::Encode for #name #ty_generics #where_clause {
+            fn write_to<W: pegasus_common::codec::WriteExt>(
+                &self, writer: &mut W,
+            ) -> std::io::Result<()> {
+                #body
+            }
+        }
+    };
+    expanded.into()
+}
+
+#[proc_macro_derive(Decode, attributes(codec))]
+pub fn derive_decode(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+    let generics = add_bounds(input.generics.clone(), quote!(pegasus_common::codec::Decode));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let body = match &input.data {
+        Data::Struct(data) => match read_fields(&data.fields) {
+            Ok(read) => {
+                let block = block_param(&data.fields);
+                quote! {
+                    pegasus_common::codec::read_versioned(reader, |#block| Ok(#name #read))
+                }
+            }
+            Err(err) => return err.to_compile_error().into(),
+        },
+        Data::Enum(data) => {
+            let mut arms = vec![];
+            for (index, variant) in data.variants.iter().enumerate() {
+                let ident = &variant.ident;
+                let index = index as u32;
+                let block = block_param(&variant.fields);
+                match read_fields(&variant.fields) {
+                    Ok(read) => arms.push(quote! {
+                        #index => pegasus_common::codec::read_versioned(
+                            reader, |#block| Ok(#name::#ident #read)
+                        ),
+                    }),
+                    Err(err) => return err.to_compile_error().into(),
+                }
+            }
+            let unknown = format!("unknown variant {{}} of {}", name);
+            quote! {
+                let index = pegasus_common::codec::ReadExt::read_u32(reader)?;
+                match index {
+                    #(#arms)*
+                    _ => Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!(#unknown, index),
+                    )),
+                }
+            }
+        }
+        Data::Union(_) => {
+            return syn::Error::new(input.span(), "Decode can't be derived for unions")
+                .to_compile_error()
+                .into();
+        }
+    };
+    let expanded = quote! {
+        impl #impl_generics pegasus_common::codec::Decode for #name #ty_generics #where_clause {
+            fn read_from<R: pegasus_common::codec::ReadExt>(
+                reader: &mut R,
+            ) -> std::io::Result<Self> {
+                #body
+            }
+        }
+    };
+    expanded.into()
+}
+
+/// Require all type parameters to implement the trait
+fn add_bounds(mut generics: Generics, bound: TokenStream2) -> Generics {
+    for param in generics.params.iter_mut() {
+        if let GenericParam::Type(ref mut param) = *param {
+            param.bounds.push(parse_quote!(#bound));
+        }
+    }
+    generics
+}
+
+/// The pattern binding the fields, and the code writing them as a block
+fn write_fields(fields: &Fields) -> (TokenStream2, TokenStream2) {
+    let bindings = (0..fields.len()).map(|i| format_ident!("__field_{}", i)).collect::<Vec<_>>();
+    let pattern = match fields {
+        Fields::Named(named) => {
+            let names = named.named.iter().map(|f| &f.ident);
+            quote!({ #(#names: #bindings),* })
+        }
+        Fields::Unnamed(_) => quote!(( #(#bindings),* )),
+        Fields::Unit => quote!(),
+    };
+    let block = block_param(fields);
+    let write = quote! {
+        pegasus_common::codec::write_versioned(writer, |#block| {
+            #(pegasus_common::codec::Encode::write_to(#bindings, block)?;)*
+            Ok(())
+        })
+    };
+    (pattern, write)
+}
+
+/// The code constructing the fields read from a block, e.g. `{ a: .., b: .. }`
+fn read_fields(fields: &Fields) -> syn::Result<TokenStream2> {
+    let mut reads = vec![];
+    for field in fields.iter() {
+        let ty = &field.ty;
+        let read = if is_default(field)? {
+            quote!(pegasus_common::codec::read_or_default::<#ty>(block)?)
+        } else {
+            quote!(<#ty as pegasus_common::codec::Decode>::read_from(block)?)
+        };
+        reads.push(read);
+    }
+    Ok(match fields {
+        Fields::Named(named) => {
+            let names = named.named.iter().map(|f| &f.ident);
+            quote!({ #(#names: #reads),* })
+        }
+        Fields::Unnamed(_) => quote!(( #(#reads),* )),
+        Fields::Unit => quote!(),
+    })
+}
+
+/// The parameter of the closure writing or reading the block of the fields, which is unused if
+/// there is no field
+fn block_param(fields: &Fields) -> TokenStream2 {
+    if fields.iter().next().is_none() {
+        quote!(_)
+    } else {
+        quote!(block)
+    }
+}
+
+/// Check if the field is marked by `#[codec(default)]`
+fn is_default(field: &syn::Field) -> syn::Result<bool> {
+    let mut default = false;
+    for attr in field.attrs.iter().filter(|attr| attr.path.is_ident("codec")) {
+        let ident: syn::Ident = attr.parse_args()?;
+        if ident == "default" {
+            default = true;
+        } else {
+            return Err(syn::Error::new(ident.span(), "expect #[codec(default)]"));
+        }
+    }
+    Ok(default)
+}
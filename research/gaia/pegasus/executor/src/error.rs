@@ -13,12 +13,21 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
+use std::any::Any;
 use std::error::Error;
 use std::fmt::{self, Debug, Display};
 
-pub trait TaskExecError: Error + Send + 'static {}
+pub trait TaskExecError: Error + Send + 'static {
+    /// Get the error as `Any`, to be downcast to its concrete type, e.g. to classify the error a
+    /// task fails with;
+    fn as_any_ref(&self) -> &dyn Any;
+}
 
-impl<E: Sized + TaskExecError> TaskExecError for Box<E> {}
+impl<E: Sized + TaskExecError> TaskExecError for Box<E> {
+    fn as_any_ref(&self) -> &dyn Any {
+        (**self).as_any_ref()
+    }
+}
 
 impl<E: Sized + TaskExecError> From<E> for Box<dyn TaskExecError> {
     fn from(raw: E) -> Self {
@@ -26,7 +35,11 @@ impl<E: Sized + TaskExecError> From<E> for Box<dyn TaskExecError> {
     }
 }
 
-impl TaskExecError for std::io::Error {}
+impl TaskExecError for std::io::Error {
+    fn as_any_ref(&self) -> &dyn Any {
+        self
+    }
+}
 
 pub struct RejectError<T>(pub T);
 
@@ -60,7 +73,11 @@ impl Display for TaskPanic {
 
 impl Error for TaskPanic {}
 
-impl TaskExecError for TaskPanic {}
+impl TaskExecError for TaskPanic {
+    fn as_any_ref(&self) -> &dyn Any {
+        self
+    }
+}
 
 pub enum ExecError {
     /// Errors occurred when executing task, it usually caused by incorrect computation in task.
@@ -29,7 +29,7 @@ use std::thread::ThreadId;
 mod error;
 mod pending;
 mod reactor;
-pub use error::{ExecError, RejectError, TaskExecError};
+pub use error::{ExecError, RejectError, TaskExecError, TaskPanic};
 pub use reactor::*;
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
@@ -224,8 +224,12 @@ impl Drop for GeneralTask {
 impl Task for GeneralTask {
     #[inline(always)]
     fn execute(&mut self) -> Result<TaskState, Box<dyn TaskExecError>> {
-        let state = self.inner.execute()?;
-        if let Some(err) = crate::check_error() {
+        let result = self.inner.execute();
+        // the error reported is taken even if the task fails, or it fails the next task on the
+        // thread instead;
+        let reported = crate::check_error();
+        let state = result?;
+        if let Some(err) = reported {
             Err(err)
         } else {
             Ok(state)
@@ -234,8 +238,10 @@ impl Task for GeneralTask {
 
     #[inline(always)]
     fn check_ready(&mut self) -> Result<TaskState, Box<dyn TaskExecError>> {
-        let state = self.inner.check_ready()?;
-        if let Some(err) = crate::check_error() {
+        let result = self.inner.check_ready();
+        let reported = crate::check_error();
+        let state = result?;
+        if let Some(err) = reported {
             Err(err)
         } else {
             Ok(state)
@@ -89,12 +89,17 @@ fn do_user_task(
     // 2. check task state:
     let tid = *task.get_thread_id();
     let seq = task.get_seq();
-    let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+    let result = std::panic::catch_unwind(AssertUnwindSafe(move || {
         match task.execute() {
-            // if finished or failed, sink result;
-            Ok(TaskState::Finished) => sink_task_result(tid, seq, None),
+            // if finished or failed, sink result once the task is dropped, which may still
+            // release the resources of its job;
+            Ok(TaskState::Finished) => {
+                std::mem::drop(task);
+                sink_task_result(tid, seq, None)
+            }
             Err(err) => {
                 error!("Task execution failure: {:?}", err);
+                std::mem::drop(task);
                 sink_task_result(tid, seq, Some(ExecError::Task(err)))
             }
             // otherwise push to not-ready queue;
@@ -136,15 +141,15 @@ fn do_select(mut select: SelectTask, re_active: &WorkStealQueue<RunTask>) {
                 }
                 Some(Ok(TaskState::Finished)) => {
                     let task = task.take().unwrap();
-                    sink_task_result(*task.get_thread_id(), task.get_seq(), None);
+                    let (tid, seq) = (*task.get_thread_id(), task.get_seq());
+                    std::mem::drop(task);
+                    sink_task_result(tid, seq, None);
                 }
                 Some(Err(err)) => {
                     let task = task.take().unwrap();
-                    sink_task_result(
-                        *task.get_thread_id(),
-                        task.get_seq(),
-                        Some(ExecError::Task(err)),
-                    );
+                    let (tid, seq) = (*task.get_thread_id(), task.get_seq());
+                    std::mem::drop(task);
+                    sink_task_result(tid, seq, Some(ExecError::Task(err)));
                 }
                 _ => {}
             }
@@ -214,6 +219,18 @@ fn work_loop(
             if !do_task(task, not_readies, re_active) {
                 break;
             }
+            // the new tasks are queued behind the ready ones, instead of waiting until no ready
+            // task is left, which is never if each of them runs over the select interval;
+            while let Ok(task) = new_task.try_recv() {
+                match task {
+                    TaskPackage::Single(task) => re_active.push(RunTask::Users(task)),
+                    TaskPackage::Batch(tasks) => {
+                        for t in tasks {
+                            re_active.push(RunTask::Users(t));
+                        }
+                    }
+                }
+            }
         }
 
         if !is_shutdown {
@@ -452,25 +469,40 @@ lazy_static! {
     static ref EXECUTOR: (Mutex<Option<ExecutorRuntime>>, ExecutorProxy) = init_executor();
     static ref THREAD_JOIN: Mutex<Option<JoinHandle<()>>> = Mutex::new(None);
     static ref EXECUTOR_GUARD: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+    // the max thread size of the started executor runtime;
+    static ref STARTED_CORE_SIZE: AtomicUsize = AtomicUsize::new(0);
 }
 
 /// Start the [`Executor`] runtime, this function will **block** current thread;
 /// The global executor runtime can only be started once, other invoking on this function will fail;
 pub fn start_executor() {
     if SHUTDOWN_HOOK.swap(false, Ordering::SeqCst) {
-        let mut lock = EXECUTOR.0.lock().expect("Executor lock poison");
-        if let Some(executor) = lock.take() {
-            executor.start();
-        } else {
-            error!("Global executor runtime is already started;");
-        }
+        run_executor();
     }
 }
 
+fn run_executor() {
+    // taken out of the lock, which is never released otherwise until the runtime exits;
+    let executor = EXECUTOR.0.lock().expect("Executor lock poison").take();
+    if let Some(executor) = executor {
+        STARTED_CORE_SIZE.store(executor.max_core, Ordering::SeqCst);
+        executor.start();
+    } else {
+        error!("Global executor runtime is already started;");
+    }
+}
+
+/// Start the [`Executor`] runtime in a new thread, which takes the tasks once this function
+/// returns, i.e. the tasks spawned before the runtime runs are queued instead of being rejected;
 pub fn start_executor_async() -> JoinHandle<()> {
+    let started = SHUTDOWN_HOOK.swap(false, Ordering::SeqCst);
     std::thread::Builder::new()
         .name("reactor 0".to_owned())
-        .spawn(|| start_executor())
+        .spawn(move || {
+            if started {
+                run_executor();
+            }
+        })
         .expect("start executor thread failure")
 }
 
@@ -528,6 +560,16 @@ pub fn set_core_pool_size(core: usize) {
     }
 }
 
+/// Get the max thread size the [`Executor`] can used, either started or not;
+pub fn get_core_pool_size() -> usize {
+    let lock = EXECUTOR.0.lock().expect("Executor lock poison");
+    if let Some(executor) = lock.as_ref() {
+        executor.max_core
+    } else {
+        STARTED_CORE_SIZE.load(Ordering::SeqCst)
+    }
+}
+
 /// Spawn a new task to the reactor [`Executor`];
 /// All tasks will be pushed to an unbound task queue, waiting for being executed, so no task will
 /// be rejected unless the executor had shutdown;
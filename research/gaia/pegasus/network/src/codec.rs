@@ -0,0 +1,467 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The codecs of the batches of data sent to other servers, selected by `NetworkConfig::codec`,
+//! which must be the same among all servers, as checked by the handshake of the connections;
+//! The data exchanged between the workers of a server are never encoded, so they are not
+//! affected by the codec;
+
+use pegasus_common::codec::{Decode, Encode};
+use pegasus_common::io::{ReadExt, WriteExt};
+use serde::Deserialize;
+use std::fmt::{Display, Formatter};
+use std::io::{self, Read, Write};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+static WIRE_CODEC: AtomicU32 = AtomicU32::new(0);
+
+/// The codec the batches of data are encoded by before they are sent to other servers;
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[derive(Default)]
+pub enum WireCodec {
+    /// The format used ever, where a batch is headed by an optional sequence and its length;
+    #[default]
+    Standard,
+    /// The integers of both the headers and the records are packed into varints, the signed ones
+    /// of which are zigzag encoded, so that the small ones take fewer bytes;
+    Compact,
+    /// The headers are written as fixed-width little-endian integers without the flag of the
+    /// sequence, so that every header of the same tag has the same size;
+    Fixed,
+}
+
+impl WireCodec {
+    pub const ALL: [WireCodec; 3] = [WireCodec::Standard, WireCodec::Compact, WireCodec::Fixed];
+
+    /// The id of the codec carried by the handshake of the connections;
+    pub fn id(&self) -> u32 {
+        match self {
+            WireCodec::Standard => 0,
+            WireCodec::Compact => 1,
+            WireCodec::Fixed => 2,
+        }
+    }
+
+    pub fn from_id(id: u32) -> Option<Self> {
+        WireCodec::ALL.iter().find(|codec| codec.id() == id).copied()
+    }
+}
+
+impl Display for WireCodec {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            WireCodec::Standard => write!(f, "standard"),
+            WireCodec::Compact => write!(f, "compact"),
+            WireCodec::Fixed => write!(f, "fixed"),
+        }
+    }
+}
+
+/// Set the codec of the batches sent to and received from other servers by current process;
+pub fn set_wire_codec(codec: WireCodec) {
+    WIRE_CODEC.store(codec.id(), Ordering::SeqCst);
+}
+
+/// The codec of the batches sent to and received from other servers by current process;
+pub fn wire_codec() -> WireCodec {
+    WireCodec::from_id(WIRE_CODEC.load(Ordering::SeqCst)).unwrap_or_default()
+}
+
+/// Encode and decode a batch of records, as its header, i.e. the optional sequence of
+/// (producer, seq) and the number of records, followed by the records;
+pub trait BatchCodec {
+    fn write_header<W: WriteExt>(
+        &self, seq: Option<(u32, u64)>, len: usize, writer: &mut W,
+    ) -> io::Result<()>;
+
+    fn read_header<R: ReadExt>(&self, reader: &mut R) -> io::Result<(Option<(u32, u64)>, usize)>;
+
+    fn write_record<T: Encode, W: WriteExt>(&self, record: &T, writer: &mut W) -> io::Result<()> {
+        record.write_to(writer)
+    }
+
+    fn read_record<T: Decode, R: ReadExt>(&self, reader: &mut R) -> io::Result<T> {
+        T::read_from(reader)
+    }
+}
+
+pub struct StandardCodec;
+
+impl BatchCodec for StandardCodec {
+    fn write_header<W: WriteExt>(
+        &self, seq: Option<(u32, u64)>, len: usize, writer: &mut W,
+    ) -> io::Result<()> {
+        if let Some((producer, seq)) = seq {
+            writer.write_u8(1)?;
+            writer.write_u32(producer)?;
+            writer.write_u64(seq)?;
+        } else {
+            writer.write_u8(0)?;
+        }
+        writer.write_u32(len as u32)
+    }
+
+    fn read_header<R: ReadExt>(&self, reader: &mut R) -> io::Result<(Option<(u32, u64)>, usize)> {
+        let seq = if reader.read_u8()? == 1 {
+            let producer = reader.read_u32()?;
+            Some((producer, reader.read_u64()?))
+        } else {
+            None
+        };
+        let len = reader.read_u32()? as usize;
+        Ok((seq, len))
+    }
+}
+
+pub struct CompactCodec;
+
+impl BatchCodec for CompactCodec {
+    fn write_header<W: WriteExt>(
+        &self, seq: Option<(u32, u64)>, len: usize, writer: &mut W,
+    ) -> io::Result<()> {
+        let mut writer = CompactWriter(writer);
+        StandardCodec.write_header(seq, len, &mut writer)
+    }
+
+    fn read_header<R: ReadExt>(&self, reader: &mut R) -> io::Result<(Option<(u32, u64)>, usize)> {
+        let mut reader = CompactReader(reader);
+        StandardCodec.read_header(&mut reader)
+    }
+
+    fn write_record<T: Encode, W: WriteExt>(&self, record: &T, writer: &mut W) -> io::Result<()> {
+        record.write_to(&mut CompactWriter(writer))
+    }
+
+    fn read_record<T: Decode, R: ReadExt>(&self, reader: &mut R) -> io::Result<T> {
+        T::read_from(&mut CompactReader(reader))
+    }
+}
+
+// the producer of a batch without sequence in the fixed header, which is never a worker index;
+const NO_PRODUCER: u32 = u32::MAX;
+
+pub struct FixedCodec;
+
+impl BatchCodec for FixedCodec {
+    fn write_header<W: WriteExt>(
+        &self, seq: Option<(u32, u64)>, len: usize, writer: &mut W,
+    ) -> io::Result<()> {
+        let (producer, seq) = seq.unwrap_or((NO_PRODUCER, 0));
+        writer.write_u32(producer)?;
+        writer.write_u64(seq)?;
+        writer.write_u64(len as u64)
+    }
+
+    fn read_header<R: ReadExt>(&self, reader: &mut R) -> io::Result<(Option<(u32, u64)>, usize)> {
+        let producer = reader.read_u32()?;
+        let seq = reader.read_u64()?;
+        let len = reader.read_u64()? as usize;
+        if producer == NO_PRODUCER {
+            Ok((None, len))
+        } else {
+            Ok((Some((producer, seq)), len))
+        }
+    }
+}
+
+impl BatchCodec for WireCodec {
+    fn write_header<W: WriteExt>(
+        &self, seq: Option<(u32, u64)>, len: usize, writer: &mut W,
+    ) -> io::Result<()> {
+        match self {
+            WireCodec::Standard => StandardCodec.write_header(seq, len, writer),
+            WireCodec::Compact => CompactCodec.write_header(seq, len, writer),
+            WireCodec::Fixed => FixedCodec.write_header(seq, len, writer),
+        }
+    }
+
+    fn read_header<R: ReadExt>(&self, reader: &mut R) -> io::Result<(Option<(u32, u64)>, usize)> {
+        match self {
+            WireCodec::Standard => StandardCodec.read_header(reader),
+            WireCodec::Compact => CompactCodec.read_header(reader),
+            WireCodec::Fixed => FixedCodec.read_header(reader),
+        }
+    }
+
+    fn write_record<T: Encode, W: WriteExt>(&self, record: &T, writer: &mut W) -> io::Result<()> {
+        match self {
+            WireCodec::Compact => CompactCodec.write_record(record, writer),
+            _ => record.write_to(writer),
+        }
+    }
+
+    fn read_record<T: Decode, R: ReadExt>(&self, reader: &mut R) -> io::Result<T> {
+        match self {
+            WireCodec::Compact => CompactCodec.read_record(reader),
+            _ => T::read_from(reader),
+        }
+    }
+}
+
+#[inline]
+fn write_varint<W: Write + ?Sized>(writer: &mut W, mut v: u128) -> io::Result<()> {
+    let mut buf = [0u8; 19];
+    let mut len = 0;
+    while v >= 0x80 {
+        buf[len] = (v as u8) | 0x80;
+        v >>= 7;
+        len += 1;
+    }
+    buf[len] = v as u8;
+    writer.write_all(&buf[0..len + 1])
+}
+
+#[inline]
+fn read_varint<R: Read + ?Sized>(reader: &mut R, bits: u32) -> io::Result<u128> {
+    let mut value = 0u128;
+    let mut shift = 0;
+    loop {
+        let mut byte = [0u8; 1];
+        reader.read_exact(&mut byte)?;
+        if shift >= bits {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "varint overflow"));
+        }
+        value |= ((byte[0] & 0x7f) as u128) << shift;
+        if byte[0] & 0x80 == 0 {
+            return if bits < 128 && value >> bits != 0 {
+                Err(io::Error::new(io::ErrorKind::InvalidData, "varint overflow"))
+            } else {
+                Ok(value)
+            };
+        }
+        shift += 7;
+    }
+}
+
+#[inline]
+fn zigzag(v: i128) -> u128 {
+    ((v << 1) ^ (v >> 127)) as u128
+}
+
+#[inline]
+fn unzigzag(v: u128) -> i128 {
+    ((v >> 1) as i128) ^ -((v & 1) as i128)
+}
+
+/// Write the integers as varints into the inner writer, and anything else as is;
+pub struct CompactWriter<'a, W: ?Sized>(pub &'a mut W);
+
+impl<'a, W: Write + ?Sized> Write for CompactWriter<'a, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.0.write_all(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+impl<'a, W: Write + ?Sized> WriteExt for CompactWriter<'a, W> {
+    fn write_u16(&mut self, v: u16) -> io::Result<()> {
+        write_varint(self.0, v as u128)
+    }
+
+    fn write_u32(&mut self, v: u32) -> io::Result<()> {
+        write_varint(self.0, v as u128)
+    }
+
+    fn write_u64(&mut self, v: u64) -> io::Result<()> {
+        write_varint(self.0, v as u128)
+    }
+
+    fn write_u128(&mut self, v: u128) -> io::Result<()> {
+        write_varint(self.0, v)
+    }
+
+    fn write_i16(&mut self, v: i16) -> io::Result<()> {
+        write_varint(self.0, zigzag(v as i128))
+    }
+
+    fn write_i32(&mut self, v: i32) -> io::Result<()> {
+        write_varint(self.0, zigzag(v as i128))
+    }
+
+    fn write_i64(&mut self, v: i64) -> io::Result<()> {
+        write_varint(self.0, zigzag(v as i128))
+    }
+
+    fn write_i128(&mut self, v: i128) -> io::Result<()> {
+        write_varint(self.0, zigzag(v))
+    }
+}
+
+/// Read the integers written by `CompactWriter` from the inner reader;
+pub struct CompactReader<'a, R: ?Sized>(pub &'a mut R);
+
+impl<'a, R: Read + ?Sized> Read for CompactReader<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        self.0.read_exact(buf)
+    }
+}
+
+impl<'a, R: Read + ?Sized> ReadExt for CompactReader<'a, R> {
+    fn read_u16(&mut self) -> io::Result<u16> {
+        Ok(read_varint(self.0, 16)? as u16)
+    }
+
+    fn read_u32(&mut self) -> io::Result<u32> {
+        Ok(read_varint(self.0, 32)? as u32)
+    }
+
+    fn read_u64(&mut self) -> io::Result<u64> {
+        Ok(read_varint(self.0, 64)? as u64)
+    }
+
+    fn read_u128(&mut self) -> io::Result<u128> {
+        read_varint(self.0, 128)
+    }
+
+    fn read_i16(&mut self) -> io::Result<i16> {
+        Ok(unzigzag(read_varint(self.0, 16)?) as i16)
+    }
+
+    fn read_i32(&mut self) -> io::Result<i32> {
+        Ok(unzigzag(read_varint(self.0, 32)?) as i32)
+    }
+
+    fn read_i64(&mut self) -> io::Result<i64> {
+        Ok(unzigzag(read_varint(self.0, 64)?) as i64)
+    }
+
+    fn read_i128(&mut self) -> io::Result<i128> {
+        Ok(unzigzag(read_varint(self.0, 128)?))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    type Record = (u64, i32, String, Vec<i64>, Option<u16>, (i128, f64, bool));
+
+    // xorshift, so that the records generated are the same in every run;
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        // mostly small numbers, which are packed the most, with the extreme ones now and then;
+        fn int(&mut self) -> u64 {
+            match self.next() % 4 {
+                0 => self.next() % 128,
+                1 => self.next() % 65536,
+                2 => self.next(),
+                _ => [0, 1, u64::MAX, u64::MAX >> 1, 1 << 63][(self.next() % 5) as usize],
+            }
+        }
+
+        fn record(&mut self) -> Record {
+            let name_len = self.next() % 16;
+            let name = (0..name_len).map(|_| (b'a' + (self.next() % 26) as u8) as char).collect();
+            let list_len = self.next() % 8;
+            let list = (0..list_len).map(|_| self.int() as i64).collect();
+            let opt = if self.next().is_multiple_of(2) { Some(self.int() as u16) } else { None };
+            let wide = ((self.int() as i128) << 64) | self.int() as i128;
+            (
+                self.int(),
+                self.int() as i32,
+                name,
+                list,
+                opt,
+                (wide, self.int() as f64, self.next().is_multiple_of(2)),
+            )
+        }
+    }
+
+    fn round_trip(codec: WireCodec, seq: Option<(u32, u64)>, records: &[Record]) -> usize {
+        let mut buf = vec![];
+        codec.write_header(seq, records.len(), &mut buf).unwrap();
+        for record in records.iter() {
+            codec.write_record(record, &mut buf).unwrap();
+        }
+        let mut reader = &buf[..];
+        let (seq_r, len) = codec.read_header(&mut reader).unwrap();
+        assert_eq!(seq_r, seq, "{}", codec);
+        assert_eq!(len, records.len(), "{}", codec);
+        for record in records.iter() {
+            let record_r: Record = codec.read_record(&mut reader).unwrap();
+            assert_eq!(&record_r, record, "{}", codec);
+        }
+        assert!(reader.is_empty(), "{} leaves {} bytes unread", codec, reader.len());
+        buf.len()
+    }
+
+    #[test]
+    fn codec_round_trip_test() {
+        for codec in WireCodec::ALL.iter() {
+            let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+            for _ in 0..500 {
+                let records = (0..rng.next() % 32).map(|_| rng.record()).collect::<Vec<_>>();
+                let seq = match rng.next() % 3 {
+                    0 => None,
+                    1 => Some((rng.int() as u32 % 1024, rng.int())),
+                    _ => Some((0, u64::MAX)),
+                };
+                round_trip(*codec, seq, &records);
+            }
+        }
+    }
+
+    #[test]
+    fn codec_compact_size_test() {
+        let records = (0..100u64)
+            .map(|i| (i, -(i as i32), String::new(), vec![i as i64], None, (0, 0.0, true)))
+            .collect::<Vec<Record>>();
+        let standard = round_trip(WireCodec::Standard, Some((1, 2)), &records);
+        let compact = round_trip(WireCodec::Compact, Some((1, 2)), &records);
+        let fixed = round_trip(WireCodec::Fixed, Some((1, 2)), &records);
+        assert!(compact < standard / 2, "compact {} vs standard {}", compact, standard);
+        // the fixed header drops the flag of sequence, but widens the length;
+        assert_eq!(fixed, standard + 3);
+    }
+
+    #[test]
+    fn codec_corrupt_varint_test() {
+        let mut reader = &[0xffu8, 0xff, 0xff, 0x7f][..];
+        assert!(CompactReader(&mut reader).read_u16().is_err());
+        let mut reader = &[0x80u8][..];
+        assert!(CompactReader(&mut reader).read_u64().is_err());
+        let mut reader = &[0xffu8, 0xff, 0x03][..];
+        assert_eq!(CompactReader(&mut reader).read_u16().unwrap(), u16::MAX);
+    }
+
+    #[test]
+    fn codec_id_test() {
+        for codec in WireCodec::ALL.iter() {
+            assert_eq!(WireCodec::from_id(codec.id()), Some(*codec));
+        }
+        assert_eq!(WireCodec::from_id(3), None);
+        assert_eq!(WireCodec::default(), WireCodec::Standard);
+    }
+}
@@ -13,6 +13,7 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
+use crate::codec::WireCodec;
 use crate::{NetError, Server};
 use serde::Deserialize;
 use std::net::SocketAddr;
@@ -23,6 +24,7 @@ pub const DEFAULT_HEARTBEAT_INTERVAL_SEC: usize = 5;
 pub const DEFAULT_SEND_BUFFER_SIZE: usize = 1440;
 pub const DEFAULT_WAIT_USER_DATA_MILLSEC: usize = 100;
 pub const DEFAULT_SLAB_SIZE: usize = 1 << 16;
+pub const DEFAULT_IO_THREADS: usize = 2;
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum BlockMode {
@@ -68,13 +70,27 @@ pub struct ConnectionParams {
     pub is_nonblocking: bool,
     write: WriteParams,
     read: ReadParams,
+    // the number of servers in the cluster, checked by the handshake, 0 if unknown;
+    servers: u32,
+    // the number of threads doing the IO of all the connections, 0 if each connection has its
+    // own threads;
+    io_threads: usize,
+    // the codec of the batches sent to other servers, checked by the handshake;
+    codec: WireCodec,
 }
 
 impl ConnectionParams {
     pub fn nonblocking() -> Self {
         let write = WriteParams::default();
         let read = ReadParams::default();
-        ConnectionParams { is_nonblocking: true, write, read }
+        ConnectionParams {
+            is_nonblocking: true,
+            write,
+            read,
+            servers: 0,
+            io_threads: DEFAULT_IO_THREADS,
+            codec: WireCodec::Standard,
+        }
     }
 
     pub fn blocking() -> Self {
@@ -82,7 +98,14 @@ impl ConnectionParams {
         write.mode = BlockMode::Blocking(None);
         let mut read = ReadParams::default();
         read.mode = BlockMode::Blocking(None);
-        ConnectionParams { is_nonblocking: false, write, read }
+        ConnectionParams {
+            is_nonblocking: false,
+            write,
+            read,
+            servers: 0,
+            io_threads: DEFAULT_IO_THREADS,
+            codec: WireCodec::Standard,
+        }
     }
 
     pub fn set_read_timeout(&mut self, timeout: Duration) {
@@ -117,6 +140,24 @@ impl ConnectionParams {
         self.write.heartbeat = interval;
     }
 
+    /// Set the number of servers in the cluster, which must be the same among all servers;
+    pub fn set_servers(&mut self, servers: u32) {
+        self.servers = servers;
+    }
+
+    /// Set the number of threads doing the IO of all the connections, apart from the threads of
+    /// the workers; The connections of a pool are always nonblocking, while 0 starts a reading
+    /// and a writing thread for each connection instead, which honour the blocking mode;
+    pub fn set_io_threads(&mut self, threads: usize) {
+        self.io_threads = threads;
+    }
+
+    /// Set the codec of the batches sent to other servers, which must be the same among all
+    /// servers, or the connections are refused by the handshake;
+    pub fn set_codec(&mut self, codec: WireCodec) {
+        self.codec = codec;
+    }
+
     pub(crate) fn get_write_params(&self) -> &WriteParams {
         &self.write
     }
@@ -128,6 +169,18 @@ impl ConnectionParams {
     pub(crate) fn get_hb_interval_sec(&self) -> u32 {
         self.write.heartbeat as u32
     }
+
+    pub(crate) fn get_servers(&self) -> u32 {
+        self.servers
+    }
+
+    pub(crate) fn get_io_threads(&self) -> usize {
+        self.io_threads
+    }
+
+    pub(crate) fn get_codec(&self) -> WireCodec {
+        self.codec
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -142,6 +195,8 @@ pub struct NetworkConfig {
     pub no_delay: Option<bool>,
     pub send_buffer: Option<u32>,
     pub heartbeat_sec: Option<u32>,
+    /// The codec of the batches sent to other servers, `standard` if not set, see `WireCodec`;
+    pub codec: Option<WireCodec>,
     pub peers: Option<Vec<PeerConfig>>,
 }
 
@@ -172,6 +227,7 @@ impl NetworkConfig {
             no_delay: None,
             send_buffer: None,
             heartbeat_sec: None,
+            codec: None,
             peers: Some(peers),
         }
     }
@@ -223,6 +279,14 @@ impl NetworkConfig {
             }
         }
 
+        if let Some(codec) = self.codec {
+            params.set_codec(codec);
+        }
+
+        if let Some(ref peers) = self.peers {
+            params.set_servers(peers.len() as u32);
+        }
+
         params
     }
 
@@ -256,6 +320,7 @@ mod test {
             nonblocking = false
             read_timeout_ms = 8
             write_timeout_ms = 8
+            codec = 'compact'
 
             [[peers]]
             server_id = 0
@@ -282,6 +347,7 @@ mod test {
         assert_eq!(wp.nodelay, false);
         assert_eq!(wp.buffer, DEFAULT_SEND_BUFFER_SIZE);
         assert_eq!(wp.heartbeat, DEFAULT_HEARTBEAT_INTERVAL_SEC);
+        assert_eq!(params.get_codec(), WireCodec::Compact);
         let peers = config.get_peers().unwrap().unwrap();
         assert_eq!(peers.len(), 2);
         assert_eq!(peers[0].id, 0);
@@ -13,6 +13,7 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
+use crate::codec::WireCodec;
 use std::error::Error;
 use std::fmt::Display;
 use std::net::{AddrParseError, SocketAddr};
@@ -30,6 +31,10 @@ pub enum NetError {
     AddrParseError(AddrParseError),
     HBAbnormal(SocketAddr),
     ChannelRxReset(u128),
+    /// (remote server id, local number of servers, remote number of servers)
+    InconsistentServers(u64, u32, u32),
+    /// (remote server id, local codec, remote codec id)
+    InconsistentCodec(u64, WireCodec, u32),
 }
 
 impl Display for NetError {
@@ -72,6 +77,19 @@ impl Display for NetError {
                     id
                 )
             }
+            NetError::InconsistentServers(id, local, remote) => {
+                write!(f, "server {} has {} servers configured, but {} locally;", id, remote, local)
+            }
+            NetError::InconsistentCodec(id, local, remote) => match WireCodec::from_id(*remote) {
+                Some(remote) => {
+                    write!(
+                        f,
+                        "server {} encodes data by codec {}, but {} locally;",
+                        id, remote, local
+                    )
+                }
+                None => write!(f, "server {} encodes data by unknown codec {};", id, remote),
+            },
         }
     }
 }
@@ -0,0 +1,190 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The pool of threads doing the IO of the connections, which write the encoded messages into the
+//! sockets, read and decode the frames from them, and deliver the messages into the inboxes of the
+//! channels. The pool is separated from the threads running the workers of the jobs, so that a
+//! worker busy computing never delays the data exchanged with other servers, which would hold
+//! back the workers there.
+//!
+//! Each connection is split into a task of writing and a task of reading, which are handed to the
+//! threads of the pool in turn through lock-free queues, and polled by them without blocking. Once
+//! the server shuts down, the tasks are drained before the threads exit, i.e. the messages queued
+//! by the workers are written and those arrived are delivered, within `DRAIN_TIMEOUT`;
+
+use crossbeam_queue::SegQueue;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::Thread;
+use std::time::{Duration, Instant};
+
+/// The longest time the threads of the pool wait for the tasks to be drained at shutdown;
+pub const DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+// how long a thread parks once none of its tasks has any IO ready, or it is unparked by a new task;
+const IDLE_PARK: Duration = Duration::from_micros(200);
+
+/// The state of a task after it is polled;
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum IoState {
+    /// some IO is done, and more may be ready;
+    Busy,
+    /// no IO is ready for now;
+    Idle,
+    /// the task is finished or failed, and is dropped from the pool;
+    Done,
+}
+
+pub(crate) trait IoTask: Send {
+    /// Do the IO ready now without blocking, where `draining` tells that the server is shutting
+    /// down, and the task is done once it has nothing more to do;
+    fn poll(&mut self, draining: bool) -> IoState;
+}
+
+type TaskQueue = Arc<SegQueue<Box<dyn IoTask>>>;
+
+pub(crate) struct IoPool {
+    threads: Vec<(TaskQueue, Thread)>,
+    next: AtomicUsize,
+}
+
+lazy_static! {
+    static ref IO_POOLS: Mutex<HashMap<u64, Arc<IoPool>>> = Mutex::new(HashMap::new());
+}
+
+impl IoPool {
+    /// Start the IO threads of the server, which exit after the server shuts down, and are joined
+    /// by `crate::await_termination`;
+    pub(crate) fn start(server_id: u64, threads: usize) -> Arc<IoPool> {
+        let mut handles = Vec::with_capacity(threads);
+        for index in 0..threads {
+            let queue: TaskQueue = Arc::new(SegQueue::new());
+            let tasks = queue.clone();
+            let guard = std::thread::Builder::new()
+                .name(format!("net-io-{}-{}", server_id, index))
+                .spawn(move || run_tasks(server_id, &tasks))
+                .expect("start network io thread failure;");
+            handles.push((queue, guard.thread().clone()));
+            crate::add_network_thread(server_id, guard);
+        }
+        let pool = Arc::new(IoPool { threads: handles, next: AtomicUsize::new(0) });
+        let mut pools = IO_POOLS.lock().expect("IO_POOLS lock poisoned");
+        pools.insert(server_id, pool.clone());
+        pool
+    }
+
+    /// Hand the task to a thread of the pool in turn;
+    pub(crate) fn submit(&self, task: Box<dyn IoTask>) {
+        let index = self.next.fetch_add(1, Ordering::Relaxed) % self.threads.len();
+        let (queue, thread) = &self.threads[index];
+        queue.push(task);
+        thread.unpark();
+    }
+}
+
+/// Get the IO pool of the server, or `None` if the connections have their own threads;
+pub(crate) fn get_io_pool(server_id: u64) -> Option<Arc<IoPool>> {
+    IO_POOLS.lock().ok().and_then(|pools| pools.get(&server_id).cloned())
+}
+
+pub(crate) fn remove_io_pool(server_id: u64) {
+    if let Ok(mut pools) = IO_POOLS.lock() {
+        pools.remove(&server_id);
+    }
+}
+
+fn run_tasks(server_id: u64, queue: &TaskQueue) {
+    let mut tasks: Vec<Box<dyn IoTask>> = vec![];
+    let mut drain_since: Option<Instant> = None;
+    loop {
+        while let Ok(task) = queue.pop() {
+            tasks.push(task);
+        }
+        let draining = crate::is_shutdown(server_id);
+        if draining && drain_since.is_none() {
+            drain_since = Some(Instant::now());
+        }
+        let mut busy = false;
+        let mut i = 0;
+        while i < tasks.len() {
+            match tasks[i].poll(draining) {
+                IoState::Busy => {
+                    busy = true;
+                    i += 1;
+                }
+                IoState::Idle => i += 1,
+                IoState::Done => {
+                    tasks.swap_remove(i);
+                }
+            }
+        }
+        if let Some(since) = drain_since {
+            if tasks.is_empty() && queue.is_empty() {
+                break;
+            }
+            if since.elapsed() > DRAIN_TIMEOUT {
+                warn!("drop {} network tasks not drained in {:?};", tasks.len(), DRAIN_TIMEOUT);
+                break;
+            }
+        }
+        if !busy {
+            std::thread::park_timeout(IDLE_PARK);
+        }
+    }
+    debug!("network io thread of server {} exit;", server_id);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::atomic::AtomicBool;
+
+    struct CountDown {
+        left: usize,
+        polled: Arc<AtomicUsize>,
+    }
+
+    impl IoTask for CountDown {
+        fn poll(&mut self, draining: bool) -> IoState {
+            self.polled.fetch_add(1, Ordering::SeqCst);
+            if self.left > 0 {
+                self.left -= 1;
+                IoState::Busy
+            } else if draining {
+                IoState::Done
+            } else {
+                IoState::Idle
+            }
+        }
+    }
+
+    #[test]
+    fn io_pool_drain_test() {
+        let server_id = 1 << 40;
+        crate::SHUTDOWN_HOOK.write().unwrap().insert(server_id, Arc::new(AtomicBool::new(false)));
+        let pool = IoPool::start(server_id, 2);
+        let polled = Arc::new(AtomicUsize::new(0));
+        for _ in 0..4 {
+            pool.submit(Box::new(CountDown { left: 100, polled: polled.clone() }));
+        }
+        assert!(get_io_pool(server_id).is_some());
+        crate::shutdown(server_id);
+        crate::await_termination(server_id);
+        // each task is polled until it is drained
+        assert!(polled.load(Ordering::SeqCst) >= 4 * 101);
+        assert!(get_io_pool(server_id).is_none());
+    }
+}
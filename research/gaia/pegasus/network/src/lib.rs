@@ -25,10 +25,10 @@ use crossbeam_utils::sync::ShardedLock;
 use pegasus_common::codec::*;
 use std::collections::HashMap;
 use std::net::{SocketAddr, ToSocketAddrs};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread::JoinHandle;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 lazy_static! {
     static ref SHUTDOWN_HOOK: ShardedLock<HashMap<u64, Arc<AtomicBool>>> =
@@ -37,10 +37,21 @@ lazy_static! {
         Mutex::new(HashMap::new());
 }
 
+// the bytes sent to and received from other servers, including the headers and heartbeats;
+static SENT_BYTES: AtomicU64 = AtomicU64::new(0);
+static RECEIVED_BYTES: AtomicU64 = AtomicU64::new(0);
+// the messages written to other servers, and the time from they are sent by the channels to they
+// are written into the connections, in microseconds;
+static SENT_MESSAGES: AtomicU64 = AtomicU64::new(0);
+static SEND_LATENCY_US: AtomicU64 = AtomicU64::new(0);
+static MAX_SEND_LATENCY_US: AtomicU64 = AtomicU64::new(0);
+
 pub fn start_up<D: ServerDetect + 'static, A: ToSocketAddrs>(
     server_id: u64, conf: ConnectionParams, addr: A, detect: D,
 ) -> Result<SocketAddr, NetError> {
     info!("start server {} ...", server_id);
+    let io_threads = conf.get_io_threads();
+    codec::set_wire_codec(conf.get_codec());
     let mut mgr = manager::ServerManager::new(server_id, conf, detect);
     {
         let mut lock = SHUTDOWN_HOOK.write().expect("SHUTDOWN_HOOK write lock failure;");
@@ -51,6 +62,9 @@ pub fn start_up<D: ServerDetect + 'static, A: ToSocketAddrs>(
         }
     }
 
+    if io_threads > 0 {
+        io_pool::IoPool::start(server_id, io_threads);
+    }
     let addr = mgr.bind(addr)?;
     let guard = std::thread::Builder::new()
         .name(format!("net-manager-{}", server_id))
@@ -98,6 +112,7 @@ pub fn await_termination(server_id: u64) {
         let mut lock = NETWORK_THREADS.lock().expect("fetch lock of NETWORK_THREADS failure;");
         lock.remove(&server_id)
     };
+    io_pool::remove_io_pool(server_id);
     if let Some(mut resources) = resources {
         debug!("wait {} resources terminate;", resources.len());
         for g in resources.drain(..) {
@@ -108,25 +123,88 @@ pub fn await_termination(server_id: u64) {
     }
 }
 
+/// The total bytes sent to other servers by current process;
+#[inline]
+pub fn get_sent_bytes() -> u64 {
+    SENT_BYTES.load(Ordering::Relaxed)
+}
+
+/// The total bytes received from other servers by current process;
+#[inline]
+pub fn get_received_bytes() -> u64 {
+    RECEIVED_BYTES.load(Ordering::Relaxed)
+}
+
+/// The latency of the messages sent to other servers by current process, from they are sent by the
+/// channels to they are written into the connections;
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SendLatency {
+    pub messages: u64,
+    pub total_us: u64,
+    pub max_us: u64,
+}
+
+impl SendLatency {
+    pub fn avg_us(&self) -> f64 {
+        if self.messages == 0 {
+            0.0
+        } else {
+            self.total_us as f64 / self.messages as f64
+        }
+    }
+}
+
+#[inline]
+pub fn get_send_latency() -> SendLatency {
+    SendLatency {
+        messages: SENT_MESSAGES.load(Ordering::Relaxed),
+        total_us: SEND_LATENCY_US.load(Ordering::Relaxed),
+        max_us: MAX_SEND_LATENCY_US.load(Ordering::Relaxed),
+    }
+}
+
+#[inline]
+pub(crate) fn add_send_latency(sent_at: Instant) {
+    let latency = sent_at.elapsed().as_micros() as u64;
+    SENT_MESSAGES.fetch_add(1, Ordering::Relaxed);
+    SEND_LATENCY_US.fetch_add(latency, Ordering::Relaxed);
+    MAX_SEND_LATENCY_US.fetch_max(latency, Ordering::Relaxed);
+}
+
+#[inline]
+pub(crate) fn add_sent_bytes(bytes: usize) {
+    SENT_BYTES.fetch_add(bytes as u64, Ordering::Relaxed);
+}
+
+#[inline]
+pub(crate) fn add_received_bytes(bytes: usize) {
+    RECEIVED_BYTES.fetch_add(bytes as u64, Ordering::Relaxed);
+}
+
 pub(crate) fn add_network_thread(server_id: u64, guard: JoinHandle<()>) {
     let mut lock = NETWORK_THREADS.lock().expect("fetch lock of NETWORK_THREADS failure;");
     lock.entry(server_id).or_insert_with(|| vec![]).push(guard);
 }
 
+pub mod codec;
 pub mod config;
 mod error;
+mod io_pool;
 mod manager;
 mod message;
 mod receive;
 mod send;
 mod state;
 mod transport;
+mod usage;
 
+pub use codec::{set_wire_codec, wire_codec, BatchCodec, WireCodec};
 pub use error::NetError;
 pub use manager::ServerDetect;
 pub use receive::IPCReceiver;
 pub use send::{check_has_network_error, IPCSender};
-pub use state::check_connect;
+pub use state::{check_connect, peer_states, PeerState};
+pub use usage::{get_job_bytes, get_job_total_bytes, track_job, untrack_job, PeerBytes};
 
 #[cfg(feature = "benchmark")]
 pub use message::{MessageHeader, MESSAGE_HEAD_SIZE};
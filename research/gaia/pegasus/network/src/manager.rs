@@ -35,8 +35,10 @@ pub(crate) struct ServerManager {
 
 impl ServerManager {
     pub fn new<D: ServerDetect + 'static>(
-        server_id: u64, conf: ConnectionParams, detect: D,
+        server_id: u64, conf: ConnectionParams, mut detect: D,
     ) -> Self {
+        let peers = detect.fetch().iter().map(|s| (s.id, s.addr)).collect();
+        crate::state::set_detected_peers(server_id, peers);
         ServerManager { server_id, peer_detect: Box::new(detect), conn_params: conf }
     }
 
@@ -46,7 +48,10 @@ impl ServerManager {
     }
 
     pub fn refresh(&mut self) {
-        for s in self.peer_detect.fetch() {
+        let servers = self.peer_detect.fetch();
+        let peers = servers.iter().map(|s| (s.id, s.addr)).collect();
+        crate::state::set_detected_peers(self.server_id, peers);
+        for s in servers {
             if s.id < self.server_id && !crate::state::is_connected(self.server_id, s.id) {
                 if let Err(e) =
                     crate::transport::block::connect(self.server_id, s.id, self.conn_params, s.addr)
@@ -27,6 +27,8 @@ use std::sync::Arc;
 mod decode;
 mod net_rx;
 use crate::config::{BlockMode::Blocking, ConnectionParams};
+use crate::io_pool::{IoState, IoTask};
+use decode::GeneralDecoder;
 pub use decode::{MessageDecoder, ReentrantDecoder, ReentrantSlabDecoder, SimpleBlockDecoder};
 use net_rx::{InboxRegister, NetReceiver};
 
@@ -86,21 +88,57 @@ pub fn register_remotes_receiver<T: Decode + 'static>(
     Ok(IPCReceiver::new(rx))
 }
 
+/// Read the messages from a connection in a thread of the IO pool, until the connection fails, or
+/// no more message is ready once the server shuts down;
+struct RecvTask {
+    local: u64,
+    remote: Server,
+    net_recv: NetReceiver<TcpStream, GeneralDecoder>,
+    disconnected: Arc<AtomicBool>,
+}
+
+impl IoTask for RecvTask {
+    fn poll(&mut self, draining: bool) -> IoState {
+        match self.net_recv.recv() {
+            Ok(true) => return IoState::Busy,
+            Ok(false) if !draining => return IoState::Idle,
+            Ok(false) => (),
+            Err(e) => {
+                error!("fail to read data from server {:?}, caused by {:?};", self.remote, e);
+            }
+        }
+        self.disconnected.store(true, Ordering::SeqCst);
+        remove_remote_register(self.local, self.remote.id);
+        info!("IPC receiver recv from {:?} exit;", self.remote);
+        IoState::Done
+    }
+}
+
 pub fn start_net_receiver(
     local: u64, remote: Server, hb_sec: u32, params: &ConnectionParams, state: &Arc<AtomicBool>,
     conn: TcpStream,
 ) {
+    let pool = crate::io_pool::get_io_pool(local);
     //    let decoder = DefaultBlockDecoder::new(conn);
-    if let Blocking(timeout) = params.get_read_params().mode {
+    if pool.is_some() {
+        conn.set_nonblocking(true).ok();
+    } else if let Blocking(timeout) = params.get_read_params().mode {
         conn.set_read_timeout(timeout).ok();
     }
 
     let slab_size = params.get_read_params().slab_size;
     let decoder = self::decode::get_reentrant_decoder(slab_size);
-    let mut net_recv = NetReceiver::new(hb_sec as u64, remote.addr, conn, decoder);
+    let mut net_recv = NetReceiver::new(hb_sec as u64, remote.addr, remote.id, conn, decoder);
+    if let Some(clock) = crate::state::get_heard_clock(local, remote.id) {
+        net_recv.set_heard_clock(clock);
+    }
     let register = net_recv.get_inbox_register();
     add_remote_register(local, remote.id, register);
     let disconnected = state.clone();
+    if let Some(pool) = pool {
+        pool.submit(Box::new(RecvTask { local, remote, net_recv, disconnected }));
+        return;
+    }
     let guard = std::thread::Builder::new()
         .name(format!("net-recv-{}-{}", remote.id, local))
         .spawn(move || {
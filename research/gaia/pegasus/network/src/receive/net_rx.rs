@@ -13,7 +13,7 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
-use crate::message::{Message, Payload};
+use crate::message::{Message, Payload, MESSAGE_HEAD_SIZE};
 use crate::receive::MessageDecoder;
 use crate::NetError;
 use crossbeam_queue::SegQueue;
@@ -23,7 +23,7 @@ use std::collections::HashMap;
 use std::io;
 use std::io::Read;
 use std::net::SocketAddr;
-use std::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Instant;
 
@@ -210,26 +210,39 @@ pub(crate) struct NetReceiver<R: Read, D: MessageDecoder> {
     hb_sec: u64,
     reader: R,
     addr: SocketAddr,
+    // the id of the server the messages are received from;
+    remote: u64,
     decoder: D,
     last_recv: Instant,
+    // stamped with the time a message is heard, see `crate::state::PeerState::last_heartbeat`;
+    heard: Option<Arc<AtomicU64>>,
     inbox_table: ReadOptInboxTable,
 }
 
 impl<R: Read, D: MessageDecoder> NetReceiver<R, D> {
-    pub fn new(hb_sec: u64, addr: SocketAddr, reader: R, decoder: D) -> Self {
+    pub fn new(hb_sec: u64, addr: SocketAddr, remote: u64, reader: R, decoder: D) -> Self {
         NetReceiver {
             hb_sec,
             reader,
             addr,
+            remote,
             decoder,
             last_recv: Instant::now(),
+            heard: None,
             inbox_table: ReadOptInboxTable::new(),
         }
     }
 
-    pub fn recv(&mut self) -> Result<(), NetError> {
+    /// Set the clock stamped with the time each message is heard from the remote;
+    pub fn set_heard_clock(&mut self, clock: Arc<AtomicU64>) {
+        self.heard = Some(clock);
+    }
+
+    /// Receive the next message if any, and tell whether a message is received;
+    pub fn recv(&mut self) -> Result<bool, NetError> {
         if let Some(msg) = decode_next(&mut self.reader, &mut self.decoder)? {
             let (header, payload) = msg.separate();
+            crate::add_received_bytes(MESSAGE_HEAD_SIZE + payload.len());
             if header.channel_id == 0 {
                 // This is a heartbeat signal;
             } else if header.sequence == 0 {
@@ -240,16 +253,22 @@ impl<R: Read, D: MessageDecoder> NetReceiver<R, D> {
                 );
                 self.inbox_table.close(header.channel_id);
             } else {
+                let bytes = MESSAGE_HEAD_SIZE + payload.len();
+                crate::usage::on_received(header.channel_id, self.remote, bytes);
                 self.inbox_table.dispatch(header.channel_id, payload);
             }
             self.last_recv = Instant::now();
+            if let Some(clock) = self.heard.as_ref() {
+                crate::state::stamp_heard(clock);
+            }
+            Ok(true)
         } else {
             let elapsed = self.last_recv.elapsed().as_secs();
             if elapsed > self.hb_sec * 2 {
                 return Err(NetError::HBAbnormal(self.addr));
             }
+            Ok(false)
         }
-        Ok(())
     }
 
     pub(crate) fn get_inbox_register(&self) -> InboxRegister {
@@ -385,7 +404,7 @@ mod test {
 
         let reader = &bin_stream[0..];
         //let decoder = SimpleBlockDecoder::new();
-        let mut net_rx = NetReceiver::new(5, "127.0.0.1:8080".parse().unwrap(), reader, decoder);
+        let mut net_rx = NetReceiver::new(5, "127.0.0.1:8080".parse().unwrap(), 1, reader, decoder);
         let register = net_rx.get_inbox_register();
         let mut user_rx = vec![None; 9];
 
@@ -455,7 +474,7 @@ mod test {
         header.sequence = 0;
         let reader = header.as_bytes();
         let reader = std::io::Read::chain(reader, MockReader);
-        let mut net_rx = NetReceiver::new(1, "127.0.0.1:8080".parse().unwrap(), reader, decoder);
+        let mut net_rx = NetReceiver::new(1, "127.0.0.1:8080".parse().unwrap(), 1, reader, decoder);
         let start = Instant::now();
         loop {
             if let Err(e) = net_rx.recv() {
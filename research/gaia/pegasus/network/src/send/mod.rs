@@ -14,6 +14,7 @@
 //! limitations under the License.
 
 use crate::config::{BlockMode, ConnectionParams, DEFAULT_SLAB_SIZE};
+use crate::io_pool::{IoPool, IoState, IoTask};
 use crate::message::MessageHeader;
 use crate::{NetError, Server};
 use crossbeam_channel::Sender;
@@ -26,7 +27,7 @@ use std::net::{SocketAddr, TcpStream};
 use std::ops::Deref;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex, Weak};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 mod encode;
 pub use encode::{GeneralEncoder, MessageEncoder, SimpleEncoder, SlabEncoder};
@@ -37,6 +38,8 @@ use net_tx::{NetData, NetSender};
 pub struct IPCSender<T: Encode> {
     pub target: SocketAddr,
     pub channel_id: u128,
+    // the id of the server the messages are sent to;
+    remote: u64,
     sequence: u64,
     encoder: GeneralEncoder<T>,
     outbox_tx: Sender<NetData>,
@@ -48,7 +51,8 @@ impl<T: Encode> IPCSender<T> {
         let mut header = MessageHeader::new(self.channel_id);
         header.sequence = self.sequence;
         let payload = self.encoder.encode(&mut header, msg)?;
-        self.outbox_tx.send(NetData::AppData(self.channel_id, payload)).map_err(|_| {
+        crate::usage::on_sent(self.channel_id, self.remote, payload.len());
+        self.outbox_tx.send(NetData::app(self.channel_id, payload)).map_err(|_| {
             error!("DefaultAppSender#send: network outbox disconnected;");
             io::Error::from(io::ErrorKind::BrokenPipe)
         })?;
@@ -60,22 +64,21 @@ impl<T: Encode> IPCSender<T> {
         if self.close_guard.fetch_sub(1, Ordering::SeqCst) == 1 {
             let mut header = MessageHeader::new(self.channel_id);
             header.sequence = 0;
-            self.outbox_tx.send(NetData::AppData(self.channel_id, header.into())).map_err(
-                |_| {
-                    error!("DefaultAppSender#close: network outbox disconnected;");
-                    io::Error::from(io::ErrorKind::BrokenPipe)
-                },
-            )?;
+            self.outbox_tx.send(NetData::app(self.channel_id, header.into())).map_err(|_| {
+                error!("DefaultAppSender#close: network outbox disconnected;");
+                io::Error::from(io::ErrorKind::BrokenPipe)
+            })?;
         }
         Ok(())
     }
 }
 
 impl<T: Encode + 'static> IPCSender<T> {
-    fn new(target: SocketAddr, channel_id: u128, outbox_tx: Sender<NetData>) -> Self {
+    fn new(target: SocketAddr, channel_id: u128, remote: u64, outbox_tx: Sender<NetData>) -> Self {
         IPCSender {
             target,
             channel_id,
+            remote,
             sequence: 1,
             encoder: SlabEncoder::new(DEFAULT_SLAB_SIZE).into(),
             outbox_tx,
@@ -98,6 +101,7 @@ impl<T: Encode + 'static> Clone for IPCSender<T> {
         IPCSender {
             target: self.target,
             channel_id: self.channel_id,
+            remote: self.remote,
             sequence: 1,
             encoder: self.encoder.clone(),
             outbox_tx: self.outbox_tx.clone(),
@@ -150,7 +154,7 @@ pub fn fetch_remote_sender<T: Encode + 'static>(
             if let Some((addr, tx)) = lock.get(&(local, *id)) {
                 if let Some(tx) = tx.upgrade() {
                     let tx = tx.deref().clone();
-                    let sender = IPCSender::<T>::new(*addr, channel_id, tx);
+                    let sender = IPCSender::<T>::new(*addr, channel_id, *id, tx);
                     app_senders.push(sender);
                 } else {
                     return Err(NetError::NotConnected(*id));
@@ -163,10 +167,106 @@ pub fn fetch_remote_sender<T: Encode + 'static>(
     Ok(app_senders)
 }
 
+/// Write the messages sent to a remote server in a thread of the IO pool, until the connection
+/// fails, or all the messages are written once the server shuts down;
+struct SendTask<W: Write> {
+    local: u64,
+    remote: Server,
+    net_tx: Option<NetSender<W>>,
+    heartbeat: Duration,
+    last_heartbeat: Instant,
+    disconnected: Arc<AtomicBool>,
+    // shutdown the writing half of the connection;
+    shutdown: fn(W),
+}
+
+impl<W: Write> SendTask<W> {
+    fn finish(&mut self) {
+        self.disconnected.store(true, Ordering::SeqCst);
+        if let Some(net_tx) = self.net_tx.take() {
+            (self.shutdown)(net_tx.take_writer());
+        }
+        info!("IPC sender to {:?} exit;", self.remote.id);
+        remove_remote_sender(self.local, self.remote.id);
+    }
+}
+
+impl<W: Write + Send> IoTask for SendTask<W> {
+    fn poll(&mut self, draining: bool) -> IoState {
+        let net_tx = match self.net_tx.as_mut() {
+            Some(net_tx) => net_tx,
+            None => return IoState::Done,
+        };
+        let written = net_tx.written();
+        let finished = match net_tx.try_send(0) {
+            Ok(true) => {
+                info!("finish sending all data to {:?}", self.remote.id);
+                true
+            }
+            Ok(false) => draining && net_tx.is_idle(),
+            Err(e) => {
+                error!("fail to send data to {:?}, caused by {};", self.remote.id, e);
+                true
+            }
+        };
+        if finished {
+            self.finish();
+            IoState::Done
+        } else {
+            if self.last_heartbeat.elapsed() >= self.heartbeat {
+                net_tx.send_heart_beat();
+                self.last_heartbeat = Instant::now();
+            }
+            if net_tx.written() > written {
+                IoState::Busy
+            } else {
+                IoState::Idle
+            }
+        }
+    }
+}
+
+fn submit_net_sender<W: Write + Send + 'static>(
+    pool: &IoPool, local: u64, remote: Server, net_tx: NetSender<W>, heartbeat: Duration,
+    state: &Arc<AtomicBool>, shutdown: fn(W),
+) {
+    let tx = net_tx.get_outbox_tx().as_ref().expect("");
+    add_remote_sender(local, &remote, tx);
+    let task = SendTask {
+        local,
+        remote,
+        net_tx: Some(net_tx),
+        heartbeat,
+        last_heartbeat: Instant::now(),
+        disconnected: state.clone(),
+        shutdown,
+    };
+    pool.submit(Box::new(task));
+}
+
 pub(crate) fn start_net_sender(
     local_id: u64, remote: Server, params: &ConnectionParams, state: &Arc<AtomicBool>,
     conn: TcpStream,
 ) {
+    if let Some(pool) = crate::io_pool::get_io_pool(local_id) {
+        let params = params.get_write_params();
+        conn.set_nonblocking(true).ok();
+        conn.set_nodelay(params.nodelay).ok();
+        let heartbeat = Duration::from_secs(params.heartbeat as u64);
+        if params.buffer > 0 {
+            let writer = std::io::BufWriter::with_capacity(params.buffer, conn);
+            let net_tx = NetSender::new(remote.addr, writer);
+            submit_net_sender(&pool, local_id, remote, net_tx, heartbeat, state, |w| {
+                w.get_ref().shutdown(std::net::Shutdown::Write).ok();
+            });
+        } else {
+            let net_tx = NetSender::new(remote.addr, conn);
+            submit_net_sender(&pool, local_id, remote, net_tx, heartbeat, state, |w| {
+                w.shutdown(std::net::Shutdown::Write).ok();
+            });
+        }
+        return;
+    }
     let mut is_block = !params.is_nonblocking;
     let params = params.get_write_params();
     match params.mode {
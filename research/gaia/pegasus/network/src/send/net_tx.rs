@@ -19,13 +19,20 @@ use std::io;
 use std::io::Write;
 use std::net::SocketAddr;
 use std::sync::{Arc, Weak};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 pub enum NetData {
-    AppData(u128, Payload),
+    /// the data of a channel, with the time it is sent by the channel;
+    AppData(u128, Payload, Instant),
     Heartbeat(Payload),
 }
 
+impl NetData {
+    pub fn app(channel_id: u128, payload: Payload) -> Self {
+        NetData::AppData(channel_id, payload, Instant::now())
+    }
+}
+
 #[allow(dead_code)]
 pub struct NetSender<W: Write> {
     addr: SocketAddr,
@@ -33,6 +40,8 @@ pub struct NetSender<W: Write> {
     outbox_tx: (Weak<Sender<NetData>>, Option<Arc<Sender<NetData>>>),
     conn: W,
     next: Option<NetData>,
+    // the messages written into the connection;
+    written: u64,
 }
 
 impl<W: Write> NetSender<W> {
@@ -45,6 +54,7 @@ impl<W: Write> NetSender<W> {
             outbox_tx: (Arc::downgrade(&outbox_tx), Some(outbox_tx)),
             conn,
             next: None,
+            written: 0,
         }
     }
 
@@ -66,6 +76,16 @@ impl<W: Write> NetSender<W> {
         }
     }
 
+    /// The messages written into the connection so far;
+    pub fn written(&self) -> u64 {
+        self.written
+    }
+
+    /// Whether all the messages in the outbox are written into the connection;
+    pub fn is_idle(&self) -> bool {
+        self.next.is_none() && self.outbox.is_empty()
+    }
+
     #[allow(dead_code)]
     pub fn try_send(&mut self, timeout: u64) -> io::Result<bool> {
         if let Some(msg) = self.next.take() {
@@ -130,12 +150,14 @@ impl<W: Write> NetSender<W> {
     #[inline]
     fn try_send_inner(&mut self, data: NetData) -> io::Result<Option<NetData>> {
         Ok(match data {
-            NetData::AppData(ch_id, mut p) => match self.try_write(&mut p) {
+            NetData::AppData(ch_id, mut p, sent_at) => match self.try_write(&mut p) {
                 Ok(finish) => {
                     if finish {
+                        crate::add_send_latency(sent_at);
+                        self.written += 1;
                         None
                     } else {
-                        Some(NetData::AppData(ch_id, p))
+                        Some(NetData::AppData(ch_id, p, sent_at))
                     }
                 }
                 Err(e) => {
@@ -147,6 +169,7 @@ impl<W: Write> NetSender<W> {
                 if !self.try_write(&mut p)? {
                     Some(NetData::Heartbeat(p))
                 } else {
+                    self.written += 1;
                     None
                 }
             }
@@ -225,14 +248,20 @@ impl<W: Write> NetSender<W> {
     #[inline]
     fn write(&mut self, data: NetData) -> io::Result<()> {
         match data {
-            NetData::AppData(ch_id, data) => {
+            NetData::AppData(ch_id, data, sent_at) => {
                 if let Err(e) = self.conn.write_all(data.as_ref()) {
                     super::report_network_error(ch_id, self.addr);
                     return Err(e);
                 }
+                crate::add_sent_bytes(data.len());
+                crate::add_send_latency(sent_at);
+            }
+            NetData::Heartbeat(data) => {
+                self.conn.write_all(data.as_ref())?;
+                crate::add_sent_bytes(data.len());
             }
-            NetData::Heartbeat(data) => self.conn.write_all(data.as_ref())?,
         }
+        self.written += 1;
         Ok(())
     }
 
@@ -273,14 +302,14 @@ mod test {
         let writer: Vec<u8> = Vec::with_capacity(1 << 20);
         let mut net_tx = NetSender::new("0.0.0.0:0".parse::<SocketAddr>().unwrap(), writer);
         let mailbox = net_tx.take_outbox_tx().unwrap();
-        mailbox.send(NetData::AppData(1, vec![1u8; 256].into())).unwrap();
-        mailbox.send(NetData::AppData(1, vec![2u8; 256].into())).unwrap();
-        mailbox.send(NetData::AppData(1, vec![3u8; 256].into())).unwrap();
-        mailbox.send(NetData::AppData(1, vec![4u8; 256].into())).unwrap();
-        mailbox.send(NetData::AppData(1, vec![5u8; 256].into())).unwrap();
-        mailbox.send(NetData::AppData(1, vec![6u8; 256].into())).unwrap();
-        mailbox.send(NetData::AppData(1, vec![7u8; 256].into())).unwrap();
-        mailbox.send(NetData::AppData(1, vec![8u8; 256].into())).unwrap();
+        mailbox.send(NetData::app(1, vec![1u8; 256].into())).unwrap();
+        mailbox.send(NetData::app(1, vec![2u8; 256].into())).unwrap();
+        mailbox.send(NetData::app(1, vec![3u8; 256].into())).unwrap();
+        mailbox.send(NetData::app(1, vec![4u8; 256].into())).unwrap();
+        mailbox.send(NetData::app(1, vec![5u8; 256].into())).unwrap();
+        mailbox.send(NetData::app(1, vec![6u8; 256].into())).unwrap();
+        mailbox.send(NetData::app(1, vec![7u8; 256].into())).unwrap();
+        mailbox.send(NetData::app(1, vec![8u8; 256].into())).unwrap();
 
         std::mem::drop(mailbox);
         if block {
@@ -290,6 +319,8 @@ mod test {
         }
 
         assert_eq!(net_tx.conn.len(), 256 * 8);
+        assert_eq!(net_tx.written(), 8);
+        assert!(net_tx.is_idle());
         let mut content = net_tx.conn.as_slice();
         for i in 1..9u8 {
             assert_eq!(&content[0..256], vec![i; 256].as_slice());
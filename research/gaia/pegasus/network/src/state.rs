@@ -16,14 +16,19 @@
 use crossbeam_utils::sync::ShardedLock;
 use std::collections::HashMap;
 use std::net::SocketAddr;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 struct ConnectionState {
     pub local_id: u64,
     pub remote_id: u64,
     addr: SocketAddr,
     disconnected: Arc<AtomicBool>,
+    // the millis since the epoch the last message is heard from the remote, 0 if none;
+    heard_ms: Arc<AtomicU64>,
+    // whether the sender and the receiver of the connection are started;
+    ready: bool,
 }
 
 impl ConnectionState {
@@ -36,13 +41,34 @@ lazy_static! {
     static ref CONNECTION_STATES: ShardedLock<HashMap<(u64, u64), ConnectionState>> =
         ShardedLock::new(HashMap::new());
     static ref ADDR_TO_ID: ShardedLock<HashMap<SocketAddr, u64>> = ShardedLock::new(HashMap::new());
+    /// local id -> the servers detected by the local server, connected or not;
+    static ref DETECTED_PEERS: ShardedLock<HashMap<u64, Vec<(u64, SocketAddr)>>> =
+        ShardedLock::new(HashMap::new());
+}
+
+/// The state of the connection with a peer server, as is seen by a local server;
+#[derive(Debug, Clone)]
+pub struct PeerState {
+    pub server_id: u64,
+    pub addr: SocketAddr,
+    pub connected: bool,
+    /// the last time any message, a heartbeat or data, is received from the peer, or `None` if
+    /// nothing is received yet;
+    pub last_heartbeat: Option<SystemTime>,
 }
 
 pub fn add_connection(local_id: u64, remote_id: u64, addr: SocketAddr) -> Option<Arc<AtomicBool>> {
     let disconnected = Arc::new(AtomicBool::new(false));
     {
         let mut states = CONNECTION_STATES.write().expect("lock poisoned");
-        let st = ConnectionState { local_id, remote_id, addr, disconnected: disconnected.clone() };
+        let st = ConnectionState {
+            local_id,
+            remote_id,
+            addr,
+            disconnected: disconnected.clone(),
+            heard_ms: Arc::new(AtomicU64::new(0)),
+            ready: false,
+        };
         if let Some(s) = states.get_mut(&(local_id, remote_id)) {
             if !s.is_connected() {
                 *s = st;
@@ -71,12 +97,72 @@ pub fn is_connected(local_id: u64, remote_id: u64) -> bool {
         || states.get(&(local_id, remote_id)).map(|s| s.is_connected()).unwrap_or(false)
 }
 
+/// Mark the connection ready once its sender and receiver are started, after which the channels
+/// of the jobs can be registered on it;
+pub(crate) fn set_ready(local_id: u64, remote_id: u64) {
+    let mut states = CONNECTION_STATES.write().expect("lock poisoned");
+    if let Some(s) = states.get_mut(&(local_id, remote_id)) {
+        s.ready = true;
+    }
+}
+
+/// Check whether the local server is connected with all the remotes, and the connections are
+/// ready to be used by the jobs;
 pub fn check_connect(local: u64, remotes: &[u64]) -> bool {
     let states = CONNECTION_STATES.read().expect("lock poisoned");
     for id in remotes {
-        if *id != local && !states.get(&(local, *id)).map(|s| s.is_connected()).unwrap_or(false) {
+        let ready = states.get(&(local, *id)).map(|s| s.ready && s.is_connected());
+        if *id != local && !ready.unwrap_or(false) {
             return false;
         }
     }
     true
 }
+
+/// Record the servers detected by the local server, which are the peers it is expected to be
+/// connected with;
+pub(crate) fn set_detected_peers(local_id: u64, peers: Vec<(u64, SocketAddr)>) {
+    let mut detected = DETECTED_PEERS.write().expect("lock poisoned");
+    detected.insert(local_id, peers);
+}
+
+/// Get the clock the receiver of the connection stamps with the time a message is heard;
+pub(crate) fn get_heard_clock(local_id: u64, remote_id: u64) -> Option<Arc<AtomicU64>> {
+    let states = CONNECTION_STATES.read().expect("lock poisoned");
+    states.get(&(local_id, remote_id)).map(|s| s.heard_ms.clone())
+}
+
+#[inline]
+pub(crate) fn stamp_heard(clock: &AtomicU64) {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    clock.store(now.as_millis() as u64, Ordering::Relaxed);
+}
+
+/// Get the states of the connections with all the peers of the local server, including the peers
+/// detected but not connected yet, in the order of the server ids;
+pub fn peer_states(local_id: u64) -> Vec<PeerState> {
+    let mut peers = HashMap::new();
+    if let Some(detected) = DETECTED_PEERS.read().expect("lock poisoned").get(&local_id) {
+        for (id, addr) in detected.iter().filter(|(id, _)| *id != local_id) {
+            let st =
+                PeerState { server_id: *id, addr: *addr, connected: false, last_heartbeat: None };
+            peers.insert(*id, st);
+        }
+    }
+    let states = CONNECTION_STATES.read().expect("lock poisoned");
+    for s in states.values().filter(|s| s.local_id == local_id) {
+        let heard_ms = s.heard_ms.load(Ordering::Relaxed);
+        let last_heartbeat =
+            if heard_ms > 0 { Some(UNIX_EPOCH + Duration::from_millis(heard_ms)) } else { None };
+        let st = PeerState {
+            server_id: s.remote_id,
+            addr: s.addr,
+            connected: s.is_connected(),
+            last_heartbeat,
+        };
+        peers.insert(s.remote_id, st);
+    }
+    let mut peers = peers.into_values().collect::<Vec<_>>();
+    peers.sort_by_key(|st| st.server_id);
+    peers
+}
@@ -29,21 +29,56 @@ pub fn listen_on<A: ToSocketAddrs>(
     info!("network listen on {:?}", bind_addr);
     listener.set_nonblocking(true).ok();
     let hb_sec = params.get_hb_interval_sec();
+    let servers = params.get_servers();
+    let codec = params.get_codec();
     let guard = std::thread::Builder::new()
         .name("network-listener".to_owned())
         .spawn(move || {
             while !crate::is_shutdown(server_id) {
                 match listener.accept() {
                     Ok((mut stream, addr)) => {
-                        if let Ok(Some((remote_id, hb))) = super::check_connection(&mut stream) {
+                        if let Ok(Some((remote_id, hb, remote_servers, remote_codec))) =
+                            super::check_connection(&mut stream)
+                        {
                             info!("accept new connection from server {} on {:?}", remote_id, addr);
-                            if !crate::state::is_connected(server_id, remote_id) {
+                            let inconsistent =
+                                if !super::is_consistent_servers(servers, remote_servers) {
+                                    Some(NetError::InconsistentServers(
+                                        remote_id,
+                                        servers,
+                                        remote_servers,
+                                    ))
+                                } else if !super::is_consistent_codec(codec, remote_codec) {
+                                    Some(NetError::InconsistentCodec(
+                                        remote_id,
+                                        codec,
+                                        remote_codec,
+                                    ))
+                                } else {
+                                    None
+                                };
+                            if let Some(err) = inconsistent {
+                                // reply the handshake, so that the remote server can report it;
+                                super::setup_connection(
+                                    server_id,
+                                    hb_sec,
+                                    servers,
+                                    codec,
+                                    &mut stream,
+                                )
+                                .ok();
+                                error!("refuse connection from {:?}, caused by {}", addr, err);
+                            } else if !crate::state::is_connected(server_id, remote_id) {
                                 // create network communication_old channel for lib user;
                                 let mut write_half =
                                     stream.try_clone().expect("clone tcp stream failure;");
-                                if let Err(e) =
-                                    super::setup_connection(server_id, hb_sec, &mut write_half)
-                                {
+                                if let Err(e) = super::setup_connection(
+                                    server_id,
+                                    hb_sec,
+                                    servers,
+                                    codec,
+                                    &mut write_half,
+                                ) {
                                     error!("write pass phrase to {:?} failure: {}", addr, e);
                                 } else {
                                     let hook =
@@ -58,6 +93,7 @@ pub fn listen_on<A: ToSocketAddrs>(
                                     start_net_receiver(
                                         server_id, remote, hb, &params, &hook, stream,
                                     );
+                                    crate::state::set_ready(server_id, remote_id);
                                 }
                             } else {
                                 warn!("server {} is connected and already in use;", remote_id);
@@ -100,10 +136,16 @@ pub fn connect<A: ToSocketAddrs>(
     let addr = conn.peer_addr()?;
     debug!("connect to server {:?};", addr);
     let hb_sec = params.get_hb_interval_sec();
-    super::setup_connection(local_id, hb_sec, &mut conn)?;
+    let servers = params.get_servers();
+    let codec = params.get_codec();
+    super::setup_connection(local_id, hb_sec, servers, codec, &mut conn)?;
     debug!("setup connection to {:?} success;", addr);
-    if let Some((id, hb_sec)) = super::check_connection(&mut conn)? {
-        if id == remote_id {
+    if let Some((id, hb_sec, remote_servers, remote_codec)) = super::check_connection(&mut conn)? {
+        if !super::is_consistent_servers(servers, remote_servers) {
+            return Err(NetError::InconsistentServers(remote_id, servers, remote_servers));
+        } else if !super::is_consistent_codec(codec, remote_codec) {
+            return Err(NetError::InconsistentCodec(remote_id, codec, remote_codec));
+        } else if id == remote_id {
             info!("connect server {} on {:?} success;", remote_id, addr);
             if let Some(state) = crate::state::add_connection(local_id, remote_id, addr) {
                 let remote = Server { id: remote_id, addr };
@@ -113,6 +155,7 @@ pub fn connect<A: ToSocketAddrs>(
                 let read_half = conn.try_clone().expect("clone tcp stream failure;");
                 start_net_sender(local_id, remote, &params, &state, conn);
                 start_net_receiver(local_id, remote, hb_sec, &params, &state, read_half);
+                crate::state::set_ready(local_id, remote_id);
             } else {
                 return Err(NetError::ConflictConnect(remote_id));
             }
@@ -13,6 +13,7 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
+use crate::codec::WireCodec;
 use crate::config::*;
 use pegasus_common::io::{ReadExt, WriteExt};
 
@@ -42,16 +43,38 @@ fn check_handshake(value: u128) -> Option<(u64, u32)> {
     }
 }
 
+/// Read the handshake of the remote server, as its id, heartbeat interval, the number of
+/// servers in the cluster it is configured with, which is 0 if unknown, and the id of its codec;
 #[inline]
-fn check_connection<R: ReadExt>(conn: &mut R) -> std::io::Result<Option<(u64, u32)>> {
+fn check_connection<R: ReadExt>(conn: &mut R) -> std::io::Result<Option<(u64, u32, u32, u32)>> {
     let handshake = conn.read_u128()?;
-    Ok(check_handshake(handshake))
+    let servers = conn.read_u32()?;
+    let codec = conn.read_u32()?;
+    Ok(check_handshake(handshake).map(|(server_id, hb)| (server_id, hb, servers, codec)))
 }
 
 #[inline]
-fn setup_connection<W: WriteExt>(server_id: u64, hb_sec: u32, conn: &mut W) -> std::io::Result<()> {
+fn setup_connection<W: WriteExt>(
+    server_id: u64, hb_sec: u32, servers: u32, codec: WireCodec, conn: &mut W,
+) -> std::io::Result<()> {
     let handshake = get_handshake(server_id, hb_sec);
-    conn.write_u128(handshake)
+    conn.write_u128(handshake)?;
+    conn.write_u32(servers)?;
+    conn.write_u32(codec.id())
+}
+
+/// Check if two servers agree on the number of servers in the cluster, which are mistaken
+/// otherwise, and would hang at the first exchange of data;
+#[inline]
+fn is_consistent_servers(local: u32, remote: u32) -> bool {
+    local == 0 || remote == 0 || local == remote
+}
+
+/// Check if the remote server encodes the data by the same codec, as the data of the other codecs
+/// can't be decoded, which would fail the jobs at the first exchange of data;
+#[inline]
+fn is_consistent_codec(local: WireCodec, remote: u32) -> bool {
+    local.id() == remote
 }
 
 #[cfg(test)]
@@ -75,4 +98,27 @@ mod test {
         }
         handshake(!0);
     }
+
+    #[test]
+    fn hand_shake_with_servers_test() {
+        let mut buf = vec![];
+        setup_connection(3, 5, 4, WireCodec::Standard, &mut buf).unwrap();
+        let mut reader = &buf[..];
+        assert_eq!(check_connection(&mut reader).unwrap(), Some((3, 5, 4, 0)));
+        assert!(is_consistent_servers(4, 4));
+        assert!(is_consistent_servers(0, 4));
+        assert!(!is_consistent_servers(3, 4));
+    }
+
+    #[test]
+    fn hand_shake_with_codec_test() {
+        let mut buf = vec![];
+        setup_connection(3, 5, 4, WireCodec::Compact, &mut buf).unwrap();
+        let mut reader = &buf[..];
+        let (_, _, _, codec) = check_connection(&mut reader).unwrap().unwrap();
+        assert!(is_consistent_codec(WireCodec::Compact, codec));
+        assert!(!is_consistent_codec(WireCodec::Standard, codec));
+        assert!(!is_consistent_codec(WireCodec::Fixed, codec));
+        assert!(!is_consistent_codec(WireCodec::Compact, 7));
+    }
 }
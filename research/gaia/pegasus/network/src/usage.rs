@@ -0,0 +1,184 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The bytes of the messages each job sends to and receives from each peer server, which are
+//! accounted by the connections with the job told by the upper 64 bits of the channel ids, i.e.
+//! the ids of the jobs the channels are of. The bytes include the headers of the messages, as they
+//! are written into and read from the sockets, while the heartbeats are of no job.
+//!
+//! A job is accounted since its first message, which may arrive before the job starts in current
+//! server and is tracked by `track_job`, until it is untracked by `untrack_job` once it ends, after
+//! which the messages of it arrived late, e.g. of a job cancelled, are not accounted. The messages
+//! are not compressed for now, so the bytes on the wire are the raw bytes of them;
+
+use crossbeam_utils::sync::ShardedLock;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// The bytes a job sends to and receives from a peer server;
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct PeerBytes {
+    pub sent: u64,
+    pub received: u64,
+}
+
+impl PeerBytes {
+    pub fn total(&self) -> u64 {
+        self.sent + self.received
+    }
+}
+
+#[derive(Default)]
+struct JobBytes {
+    peers: Mutex<HashMap<u64, PeerBytes>>,
+    // the bytes sent and received with all peers, read by the checks of the limit;
+    total: AtomicU64,
+}
+
+impl JobBytes {
+    fn add(&self, peer: u64, sent: usize, received: usize) {
+        if let Ok(mut peers) = self.peers.lock() {
+            let bytes = peers.entry(peer).or_insert_with(PeerBytes::default);
+            bytes.sent += sent as u64;
+            bytes.received += received as u64;
+        }
+        self.total.fetch_add((sent + received) as u64, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> Vec<(u64, PeerBytes)> {
+        let mut peers = match self.peers.lock() {
+            Ok(peers) => peers.iter().map(|(peer, bytes)| (*peer, *bytes)).collect(),
+            Err(_) => vec![],
+        };
+        peers.sort_by_key(|(peer, _)| *peer);
+        peers
+    }
+}
+
+/// The most jobs untracked lately, whose messages are no longer accounted;
+const MAX_UNTRACKED_JOBS: usize = 1024;
+
+lazy_static! {
+    static ref JOB_BYTES: ShardedLock<HashMap<u64, Arc<JobBytes>>> =
+        ShardedLock::new(HashMap::new());
+    static ref UNTRACKED_JOBS: Mutex<VecDeque<u64>> = Mutex::new(VecDeque::new());
+}
+
+#[inline]
+fn get_job(channel_id: u128) -> Option<Arc<JobBytes>> {
+    let job_id = (channel_id >> 64) as u64;
+    if let Some(job) = JOB_BYTES.read().ok().and_then(|jobs| jobs.get(&job_id).cloned()) {
+        return Some(job);
+    }
+    let untracked = UNTRACKED_JOBS.lock().ok()?;
+    if untracked.contains(&job_id) {
+        None
+    } else {
+        let mut jobs = JOB_BYTES.write().ok()?;
+        Some(jobs.entry(job_id).or_insert_with(Arc::default).clone())
+    }
+}
+
+/// Track the job started in current server, keeping the bytes of it accounted ahead if any;
+pub fn track_job(job_id: u64) {
+    if let Ok(mut untracked) = UNTRACKED_JOBS.lock() {
+        untracked.retain(|id| *id != job_id);
+    }
+    let mut jobs = JOB_BYTES.write().expect("JOB_BYTES write lock poisoned");
+    jobs.entry(job_id).or_default();
+}
+
+/// Stop to account the bytes of the job, and get the bytes of it with each peer, in the order of
+/// the ids of the peers, or `None` if nothing of the job is accounted;
+pub fn untrack_job(job_id: u64) -> Option<Vec<(u64, PeerBytes)>> {
+    if let Ok(mut untracked) = UNTRACKED_JOBS.lock() {
+        untracked.push_back(job_id);
+        while untracked.len() > MAX_UNTRACKED_JOBS {
+            untracked.pop_front();
+        }
+    }
+    let mut jobs = JOB_BYTES.write().expect("JOB_BYTES write lock poisoned");
+    jobs.remove(&job_id).map(|job| job.snapshot())
+}
+
+/// Get the bytes the job sends to and receives from each peer by now, in the order of the ids of
+/// the peers, or `None` if nothing of the job is accounted;
+pub fn get_job_bytes(job_id: u64) -> Option<Vec<(u64, PeerBytes)>> {
+    let job = JOB_BYTES.read().ok()?.get(&job_id).cloned()?;
+    Some(job.snapshot())
+}
+
+/// Get the bytes the job sends to and receives from all peers by now;
+#[inline]
+pub fn get_job_total_bytes(job_id: u64) -> u64 {
+    match JOB_BYTES.read() {
+        Ok(jobs) => jobs.get(&job_id).map(|job| job.total.load(Ordering::Relaxed)).unwrap_or(0),
+        Err(_) => 0,
+    }
+}
+
+#[inline]
+pub(crate) fn on_sent(channel_id: u128, peer: u64, bytes: usize) {
+    if let Some(job) = get_job(channel_id) {
+        job.add(peer, bytes, 0);
+    }
+}
+
+#[inline]
+pub(crate) fn on_received(channel_id: u128, peer: u64, bytes: usize) {
+    if let Some(job) = get_job(channel_id) {
+        job.add(peer, 0, bytes);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn account_job_bytes_test() {
+        let job_id = 1 << 40;
+        let channel_id = (job_id as u128) << 64 | 3;
+        assert!(get_job_bytes(job_id).is_none());
+        assert_eq!(get_job_total_bytes(job_id), 0);
+        // the messages arrived before the job starts are accounted;
+        on_received(channel_id, 1, 60);
+        track_job(job_id);
+        on_sent(channel_id, 2, 100);
+        on_sent(channel_id + 1, 1, 40);
+        on_received(((job_id + 1) as u128) << 64, 1, 1000);
+        let bytes = get_job_bytes(job_id).unwrap();
+        assert_eq!(
+            bytes,
+            vec![
+                (1, PeerBytes { sent: 40, received: 60 }),
+                (2, PeerBytes { sent: 100, received: 0 })
+            ]
+        );
+        assert_eq!(get_job_total_bytes(job_id), 200);
+        assert_eq!(untrack_job(job_id), Some(bytes));
+        // the messages arrived late are not;
+        on_received(channel_id, 2, 100);
+        assert!(get_job_bytes(job_id).is_none());
+        assert!(untrack_job(job_id).is_none());
+        // unless the job of the same id starts again;
+        track_job(job_id);
+        on_received(channel_id, 2, 100);
+        assert_eq!(get_job_total_bytes(job_id), 100);
+        untrack_job(job_id);
+        untrack_job(job_id + 1);
+    }
+}
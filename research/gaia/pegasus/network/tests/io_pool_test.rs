@@ -0,0 +1,104 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use pegasus_network::{config::ConnectionParams, Server, ServerDetect};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+// the threads spinning to keep all the cores busy, as the workers of the compute heavy jobs;
+const BUSY_THREADS: usize = 32;
+const ENTRIES: u64 = 1000;
+
+struct MockServerDetect {
+    servers: Vec<Server>,
+}
+
+impl ServerDetect for MockServerDetect {
+    fn fetch(&mut self) -> &[Server] {
+        self.servers.as_slice()
+    }
+}
+
+fn busy_loop(stop: Arc<AtomicBool>) -> std::thread::JoinHandle<u64> {
+    std::thread::spawn(move || {
+        let mut count = 0u64;
+        while !stop.load(Ordering::Relaxed) {
+            count = count.wrapping_add(1);
+        }
+        count
+    })
+}
+
+fn run_server(
+    id: u64, servers: Vec<Server>, conf: ConnectionParams,
+) -> std::thread::JoinHandle<u64> {
+    std::thread::Builder::new()
+        .name(format!("process-{}", id))
+        .spawn(move || {
+            let addr = servers[id as usize].addr;
+            let detector = MockServerDetect { servers };
+            pegasus_network::start_up(id, conf, addr, detector).unwrap();
+            let remotes = vec![1 - id];
+            while !pegasus_network::check_connect(id, &remotes) {
+                std::thread::sleep(Duration::from_millis(100));
+            }
+            let ipc_ch = pegasus_network::ipc_channel::<u64>(1, id, &remotes).unwrap();
+            let (mut sends, recv) = ipc_ch.take();
+            for i in 0..ENTRIES {
+                sends[0].send(&i).unwrap();
+            }
+            sends[0].close().unwrap();
+            let mut sum = 0;
+            loop {
+                match recv.recv() {
+                    Ok(Some(entry)) => sum += entry,
+                    Ok(None) => std::thread::yield_now(),
+                    Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => break,
+                    Err(e) => panic!("unexpected error {}", e),
+                }
+            }
+            pegasus_network::shutdown(id);
+            pegasus_network::await_termination(id);
+            sum
+        })
+        .unwrap()
+}
+
+#[test]
+fn io_pool_under_busy_compute_test() {
+    pegasus_common::logs::init_log();
+    let stop = Arc::new(AtomicBool::new(false));
+    let busy = (0..BUSY_THREADS).map(|_| busy_loop(stop.clone())).collect::<Vec<_>>();
+    let servers = vec![
+        Server { id: 0, addr: "127.0.0.1:1240".parse().unwrap() },
+        Server { id: 1, addr: "127.0.0.1:1241".parse().unwrap() },
+    ];
+    let mut conf = ConnectionParams::nonblocking();
+    conf.set_io_threads(1);
+    let g0 = run_server(0, servers.clone(), conf);
+    let g1 = run_server(1, servers, conf);
+    let expected = ENTRIES * (ENTRIES - 1) / 2;
+    assert_eq!(g0.join().unwrap(), expected);
+    assert_eq!(g1.join().unwrap(), expected);
+    stop.store(true, Ordering::SeqCst);
+    for g in busy {
+        g.join().unwrap();
+    }
+    let latency = pegasus_network::get_send_latency();
+    // the data and exhaust signals of both servers;
+    assert!(latency.messages >= 2 * (ENTRIES + 1));
+    assert!(latency.max_us < 1_000_000, "send latency {:?} is unbounded", latency);
+}
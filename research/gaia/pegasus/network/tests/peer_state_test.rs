@@ -0,0 +1,61 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use pegasus_network::{config::ConnectionParams, Server};
+use std::time::{Duration, Instant};
+
+fn wait_until<F: Fn() -> bool>(timeout: Duration, cond: F) -> bool {
+    let start = Instant::now();
+    while !cond() {
+        if start.elapsed() > timeout {
+            return false;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    true
+}
+
+#[test]
+fn peer_state_test() {
+    pegasus_common::logs::init_log();
+    let servers = vec![
+        Server { id: 0, addr: "127.0.0.1:1250".parse().unwrap() },
+        Server { id: 1, addr: "127.0.0.1:1251".parse().unwrap() },
+    ];
+    let mut conf = ConnectionParams::blocking();
+    conf.set_heartbeat_interval(1);
+    pegasus_network::start_up(0, conf, servers[0].addr, servers.clone()).unwrap();
+    // the server of smaller id is connected by the others, so it sees its peer before connected;
+    let peers = pegasus_network::peer_states(0);
+    assert_eq!(peers.len(), 1);
+    assert_eq!(peers[0].server_id, 1);
+    assert!(!peers[0].connected);
+    assert!(peers[0].last_heartbeat.is_none());
+
+    pegasus_network::start_up(1, conf, servers[1].addr, servers.clone()).unwrap();
+    let connected = |id: u64| pegasus_network::peer_states(id).iter().all(|p| p.connected);
+    assert!(wait_until(Duration::from_secs(10), || connected(0) && connected(1)));
+    let heard = |id: u64| pegasus_network::peer_states(id)[0].last_heartbeat.is_some();
+    assert!(wait_until(Duration::from_secs(10), || heard(0) && heard(1)));
+
+    let first = pegasus_network::peer_states(0)[0].last_heartbeat.unwrap();
+    let later = || pegasus_network::peer_states(0)[0].last_heartbeat.unwrap() > first;
+    assert!(wait_until(Duration::from_secs(10), later), "heartbeats are not heard");
+
+    pegasus_network::shutdown(0);
+    pegasus_network::shutdown(1);
+    pegasus_network::await_termination(0);
+    pegasus_network::await_termination(1);
+}
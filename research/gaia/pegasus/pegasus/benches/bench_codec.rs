@@ -0,0 +1,105 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+#![feature(test)]
+extern crate test;
+
+use pegasus::codec::{Decode, Encode};
+use pegasus_network::{BatchCodec, WireCodec};
+use std::io;
+use test::Bencher;
+
+/// cargo +nightly bench --bench bench_codec;
+
+// the table of the batches of each record shape encoded and decoded by each codec, where the
+// benches are named as `{encode|decode}_{shape}_{codec}`, and the bytes of each batch are
+// reported as the throughput;
+const BATCH_SIZE: usize = 1024;
+
+fn small_ints() -> Vec<u32> {
+    (0..BATCH_SIZE as u32).map(|i| i % 100).collect()
+}
+
+fn wide_ints() -> Vec<u64> {
+    (0..BATCH_SIZE as u64).map(|i| i.wrapping_mul(0x9e37_79b9_7f4a_7c15)).collect()
+}
+
+fn records() -> Vec<(u64, String, Vec<i32>)> {
+    (0..BATCH_SIZE as u64)
+        .map(|i| (i, format!("vertex-{}", i), (0..(i % 8) as i32).map(|j| j - 4).collect()))
+        .collect()
+}
+
+// encode the records as a batch sent to other servers, see `DataSet::write_with`;
+fn encode<T: Encode>(codec: WireCodec, records: &[T], buf: &mut Vec<u8>) -> io::Result<()> {
+    codec.write_header(Some((1, 1024)), records.len(), buf)?;
+    for record in records.iter() {
+        codec.write_record(record, buf)?;
+    }
+    Ok(())
+}
+
+fn decode<T: Decode>(codec: WireCodec, mut buf: &[u8]) -> io::Result<Vec<T>> {
+    let (_, len) = codec.read_header(&mut buf)?;
+    let mut records = Vec::with_capacity(len);
+    for _ in 0..len {
+        records.push(codec.read_record(&mut buf)?);
+    }
+    Ok(records)
+}
+
+macro_rules! bench_codec {
+    ($encode: ident, $decode: ident, $shape: ident, $codec: expr) => {
+        #[bench]
+        fn $encode(b: &mut Bencher) {
+            let records = $shape();
+            let mut buf = Vec::with_capacity(1 << 16);
+            encode($codec, &records, &mut buf).unwrap();
+            b.bytes = buf.len() as u64;
+            b.iter(|| {
+                buf.clear();
+                encode($codec, &records, &mut buf).unwrap();
+            });
+        }
+
+        #[bench]
+        fn $decode(b: &mut Bencher) {
+            let records = $shape();
+            let mut buf = Vec::with_capacity(1 << 16);
+            encode($codec, &records, &mut buf).unwrap();
+            b.bytes = buf.len() as u64;
+            b.iter(|| {
+                let decoded = decode($codec, &buf[..]).unwrap();
+                assert_eq!(decoded, records);
+                decoded
+            });
+        }
+    };
+}
+
+bench_codec!(
+    encode_small_ints_standard,
+    decode_small_ints_standard,
+    small_ints,
+    WireCodec::Standard
+);
+bench_codec!(encode_small_ints_compact, decode_small_ints_compact, small_ints, WireCodec::Compact);
+bench_codec!(encode_small_ints_fixed, decode_small_ints_fixed, small_ints, WireCodec::Fixed);
+bench_codec!(encode_wide_ints_standard, decode_wide_ints_standard, wide_ints, WireCodec::Standard);
+bench_codec!(encode_wide_ints_compact, decode_wide_ints_compact, wide_ints, WireCodec::Compact);
+bench_codec!(encode_wide_ints_fixed, decode_wide_ints_fixed, wide_ints, WireCodec::Fixed);
+bench_codec!(encode_records_standard, decode_records_standard, records, WireCodec::Standard);
+bench_codec!(encode_records_compact, decode_records_compact, records, WireCodec::Compact);
+bench_codec!(encode_records_fixed, decode_records_fixed, records, WireCodec::Fixed);
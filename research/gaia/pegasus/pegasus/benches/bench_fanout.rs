@@ -0,0 +1,74 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+#![feature(test)]
+extern crate test;
+
+use pegasus::api::{Map, ResultSet, Sink, SubTask};
+use pegasus::communication::Pipeline;
+use pegasus::{Configuration, JobConf, Tag};
+use std::sync::atomic::{AtomicU64, Ordering};
+use test::Bencher;
+
+/// cargo +nightly bench --bench bench_fanout;
+
+// the fork/join of the subtask tests, where each parent forks a subtask expanding it to 8, run with
+// and without the fanout hint on the expansion; The capacity stalls of the expansion in the last
+// run are printed beside the time;
+static JOB_ID: AtomicU64 = AtomicU64::new(1 << 20);
+
+fn fork_join(hint: bool) -> u64 {
+    let job_id = JOB_ID.fetch_add(1, Ordering::SeqCst);
+    let mut conf = JobConf::new(job_id, "bench_fanout", 1);
+    conf.output_capacity = 32;
+    conf.profile = true;
+    pegasus::run(conf, |worker| {
+        worker.dataflow(|builder| {
+            let src = builder.input_from_iter(0..10000u32)?;
+            let subtask = src.fork_subtask(|stream| {
+                let stream = if hint { stream.with_fanout_hint(8.0) } else { stream };
+                stream.named("expand", |s| {
+                    s.flat_map_with_fn(Pipeline, |item| {
+                        Ok(vec![item; 8].into_iter().map(|x| Ok(x)))
+                    })
+                })
+            })?;
+            src.join_subtask(subtask, |p, s| Some(s - *p))?
+                .sink_by(|_meta| |_t: &Tag, _result: ResultSet<u32>| ())?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;")
+    .expect("job not started;")
+    .join()
+    .expect("run job failure;");
+    let profile = pegasus::fetch_job_profile(job_id).expect("job not profiled;");
+    profile.get_operator("expand").map(|op| op.stalls.sum).unwrap_or(0)
+}
+
+fn bench_fork_join(b: &mut Bencher, hint: bool) {
+    pegasus::startup(Configuration::singleton()).ok();
+    b.iter(|| fork_join(hint));
+}
+
+#[bench]
+fn fork_join_without_hint(b: &mut Bencher) {
+    bench_fork_join(b, false)
+}
+
+#[bench]
+fn fork_join_with_hint(b: &mut Bencher) {
+    bench_fork_join(b, true)
+}
@@ -1,4 +1,4 @@
-use pegasus::preclude::{Filter, Map, Pipeline};
+use pegasus::preclude::{Filter, Map, Pipeline, ResultSet, Sink};
 use pegasus::{Configuration, JobConf};
 use std::time::Instant;
 use structopt::StructOpt;
@@ -25,7 +25,8 @@ fn main() {
         worker.dataflow(|builder| {
             let src = builder.input_from_iter(1..100_000u64)?;
             src.flat_map_with_fn(Pipeline, |i| Ok((0..i).into_iter().map(|i| Ok(i))))?
-                .filter_with_fn(|_| Ok(false))?;
+                .filter_with_fn(|_| Ok(false))?
+                .sink_by(|_| |_, _: ResultSet<u64>| ())?;
             Ok(())
         })
     })
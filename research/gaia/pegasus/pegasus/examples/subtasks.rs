@@ -50,7 +50,7 @@ fn main() {
                             tx.send(item).ok();
                         }
                     }
-                    ResultSet::End => {}
+                    ResultSet::ScopeEnd(_) | ResultSet::End => {}
                 }
             })?;
             Ok(())
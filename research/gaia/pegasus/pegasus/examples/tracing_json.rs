@@ -0,0 +1,40 @@
+// Print the spans of a job as json lines, e.g.
+// `RUST_LOG=trace cargo run --example tracing_json --features tracing`
+use pegasus::api::{Exchange, Map, ResultSet, Sink};
+use pegasus::communication::Pipeline;
+use pegasus::{Configuration, JobConf};
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::EnvFilter;
+
+fn main() {
+    tracing_subscriber::fmt()
+        .json()
+        .with_current_span(true)
+        .with_span_list(true)
+        .with_span_events(FmtSpan::CLOSE)
+        .with_env_filter(EnvFilter::from_default_env())
+        .init();
+    pegasus::startup(Configuration::singleton()).ok();
+    let conf = JobConf::new(1, "g.V().out().count()", 2);
+    let mut guard = pegasus::run(conf, |worker| {
+        worker.dataflow(|builder| {
+            builder
+                .input_from_iter(0..1000u64)?
+                .exchange_with_fn(|item| *item)?
+                .map_with_fn(Pipeline, |item| Ok(item + 1))?
+                .sink_by(|_| {
+                    |_, result| {
+                        if let ResultSet::Data(data) = result {
+                            tracing::info!(size = data.len(), "results");
+                        }
+                    }
+                })?;
+            Ok(())
+        })
+    })
+    .expect("build job failure")
+    .unwrap();
+
+    guard.join().expect("run job failure");
+    pegasus::shutdown_all();
+}
@@ -0,0 +1,88 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The failures of the jobs raised out of the functions of the operators, e.g. by the iterator of
+//! a source, or by the encoder of a sink, neither of which can return an error themselves. A job
+//! is aborted by `abort_current_job` from the worker running on current thread, and the first
+//! worker of the job in current server that ends a run after it fails with the error, which
+//! cancels the other workers of the job as any failure does.
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+lazy_static! {
+    static ref ABORTED_JOBS: Mutex<HashMap<u64, Box<dyn Error + Send>>> =
+        Mutex::new(HashMap::new());
+}
+
+// the jobs aborted but not failed yet, so that the workers don't lock the jobs after each run
+// unless some job is aborted;
+static ABORTING: AtomicUsize = AtomicUsize::new(0);
+
+/// Abort the job of the worker running on current thread with the error, which is ignored if
+/// current thread runs no worker, or the job has been aborted before;
+pub fn abort_current_job<E: Error + Send + 'static>(err: E) {
+    if let Some(worker) = crate::worker_id::get_current_worker() {
+        if let Ok(mut jobs) = ABORTED_JOBS.lock() {
+            if let Entry::Vacant(entry) = jobs.entry(worker.job_id) {
+                entry.insert(Box::new(err));
+                ABORTING.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+    }
+}
+
+/// Take the error the job is aborted with, if any, which is taken by one worker of the job only;
+#[inline]
+pub(crate) fn take_abort(job_id: u64) -> Option<Box<dyn Error + Send>> {
+    if ABORTING.load(Ordering::Relaxed) == 0 {
+        return None;
+    }
+    let err = ABORTED_JOBS.lock().ok()?.remove(&job_id)?;
+    ABORTING.fetch_sub(1, Ordering::SeqCst);
+    Some(err)
+}
+
+/// Drop the error the job is aborted with once all its workers in current server end, e.g. if it
+/// is aborted by its last run;
+pub(crate) fn on_job_finished(job_id: u64) {
+    take_abort(job_id);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::errors::JobExecError;
+    use crate::worker_id::WorkerId;
+
+    #[test]
+    fn abort_current_job_test() {
+        let job_id = 1 << 43;
+        let err = || std::io::Error::other("too many results");
+        abort_current_job(err());
+        assert!(take_abort(job_id).is_none());
+        {
+            let _g = crate::worker_id::guard(WorkerId::new(job_id, 1, 0, false));
+            abort_current_job(err());
+            abort_current_job(std::io::Error::other("ignored"));
+        }
+        let err = JobExecError::from_box(take_abort(job_id).unwrap());
+        assert!(err.to_string().contains("too many results"), "{}", err);
+        assert!(take_abort(job_id).is_none());
+    }
+}
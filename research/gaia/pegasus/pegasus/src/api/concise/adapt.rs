@@ -0,0 +1,54 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use crate::api::Branch;
+use crate::errors::BuildJobError;
+use crate::stream::Stream;
+use crate::Data;
+
+/// The alternative an adaptive part of the dataflow chooses for a scope, with the reason told in
+/// the profile of the job, e.g. the pass rates observed;
+#[derive(Clone, Debug, PartialEq)]
+pub struct Decision {
+    /// `Branch::Left` for the first alternative, `Branch::Right` for the second;
+    pub choice: Branch,
+    pub reason: String,
+}
+
+impl Decision {
+    pub fn new<S: Into<String>>(choice: Branch, reason: S) -> Self {
+        Decision { choice, reason: reason.into() }
+    }
+}
+
+/// Adapt a part of the dataflow to the data at runtime, by choosing between two alternatives of
+/// the part, e.g. two orders of the same filters, which must be semantically equivalent, as the
+/// scopes may go through either; The first `sample` data of each scope are held until `decide`
+/// chooses an alternative by them, and then the data of the scope go through the alternative
+/// chosen, including the ones held, for the remainder of the scope. A scope with fewer data is
+/// decided by all of its data once it ends;
+///
+/// Each decision is reported to the profile of the job if it is profiled, see
+/// `crate::profile::JobProfile::decisions`;
+pub trait Adapt<D: Data> {
+    fn adapt<O, P, A, B>(
+        &self, name: &str, sample: usize, decide: P, first: A, second: B,
+    ) -> Result<Stream<O>, BuildJobError>
+    where
+        O: Data,
+        P: Fn(&[D]) -> Decision + Send + 'static,
+        A: FnOnce(Stream<D>) -> Result<Stream<O>, BuildJobError>,
+        B: FnOnce(Stream<D>) -> Result<Stream<O>, BuildJobError>;
+}
@@ -13,10 +13,12 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
+use crate::api::function::FnResult;
 use crate::api::Range;
 use crate::stream::Stream;
 use crate::{BuildJobError, Data};
 use pegasus_common::collections::{CollectionFactory, Set};
+use std::hash::Hash;
 
 pub trait Dedup<D: Data + Eq> {
     fn dedup<S>(&self, range: Range) -> Result<Stream<D>, BuildJobError>
@@ -28,3 +30,149 @@ pub trait Dedup<D: Data + Eq> {
         S: CollectionFactory<D> + 'static,
         S::Target: Set<D>;
 }
+
+/// Dedup the data of each scope in bounded memory by a Bloom filter of each worker, see
+/// `BloomFilter`, which never emits a duplicate but may drop a distinct datum as a duplicate by
+/// the false positive rate; The data are exchanged to the workers by their hashes for the global
+/// range, so each worker keeps the filter of `expected_items / peers` items, while each worker or
+/// server keeps the filter of `expected_items` items for the local or per server range; The job
+/// fails to be built if the filters of a server need more memory than `JobConf::memory_limit`;
+pub trait ApproxDedup<D: Data> {
+    fn dedup_approx(
+        &self, range: Range, expected_items: u64, fp_rate: f64,
+    ) -> Result<Stream<D>, BuildJobError>
+    where
+        D: Hash;
+
+    /// Dedup the data by the stable hashes given by `hash`, which must be the same for the
+    /// duplicates in all workers, e.g. the hashes the data are partitioned by;
+    fn dedup_approx_by<H>(
+        &self, range: Range, expected_items: u64, fp_rate: f64, hash: H,
+    ) -> Result<Stream<D>, BuildJobError>
+    where
+        H: Fn(&D) -> FnResult<u64> + Send + Sync + 'static;
+}
+
+/// A partitioned Bloom filter of the hashes of the items, whose bits are split into one slice for
+/// each hash function, so the slices are filled evenly; It is sized for the expected items at the
+/// false positive rate, which grows once more items are inserted;
+#[derive(Clone, Debug)]
+pub struct BloomFilter {
+    slices: u32,
+    slice_bits: u64,
+    bits: Vec<u64>,
+    ones: u64,
+}
+
+impl BloomFilter {
+    /// Create an empty filter of `expected_items` at the false positive rate `fp_rate`, which
+    /// should be in `(0, 1)`;
+    pub fn with_rate(expected_items: u64, fp_rate: f64) -> Self {
+        let (slices, slice_bits) = Self::shape(expected_items, fp_rate);
+        let words = (slices as u64 * slice_bits).div_ceil(64);
+        BloomFilter { slices, slice_bits, bits: vec![0; words as usize], ones: 0 }
+    }
+
+    /// The bytes of the filter of `expected_items` at the false positive rate `fp_rate`;
+    pub fn memory_bytes(expected_items: u64, fp_rate: f64) -> u64 {
+        let (slices, slice_bits) = Self::shape(expected_items, fp_rate);
+        (slices as u64 * slice_bits).div_ceil(64) * 8
+    }
+
+    // `k = ceil(log2(1 / p))` slices, each filled by the ratio `p^(1/k)` once the expected items
+    // are inserted, so the false positive rate is `p`;
+    fn shape(expected_items: u64, fp_rate: f64) -> (u32, u64) {
+        assert!(
+            fp_rate > 0.0 && fp_rate < 1.0,
+            "the false positive rate of BloomFilter should be in (0, 1), but got {}",
+            fp_rate
+        );
+        let slices = (1.0 / fp_rate).log2().ceil().max(1.0);
+        let fill = fp_rate.powf(1.0 / slices);
+        let slice_bits = (-(expected_items.max(1) as f64) / (1.0 - fill).ln()).ceil();
+        (slices as u32, (slice_bits as u64).max(1))
+    }
+
+    /// Insert the hash of an item, and tell whether it is new, i.e. not in the filter before;
+    pub fn insert_hash(&mut self, hash: u64) -> bool {
+        let mut new = false;
+        for index in self.indexes(hash) {
+            let (word, bit) = ((index / 64) as usize, 1u64 << (index % 64));
+            if self.bits[word] & bit == 0 {
+                self.bits[word] |= bit;
+                self.ones += 1;
+                new = true;
+            }
+        }
+        new
+    }
+
+    pub fn contains_hash(&self, hash: u64) -> bool {
+        self.indexes(hash).all(|index| self.bits[(index / 64) as usize] & (1 << (index % 64)) != 0)
+    }
+
+    /// The ratio of the bits set, which is `p^(1/k)` once the expected items are inserted;
+    pub fn fill_ratio(&self) -> f64 {
+        self.ones as f64 / (self.slices as u64 * self.slice_bits) as f64
+    }
+
+    /// The bit of each slice by double hashing, where the hash is remixed as it may be the one the
+    /// items are exchanged by, whose low bits are the same in a worker;
+    fn indexes(&self, hash: u64) -> impl Iterator<Item = u64> {
+        let h1 = mix(hash);
+        let h2 = mix(h1 ^ 0x9e37_79b9_7f4a_7c15) | 1;
+        let slice_bits = self.slice_bits;
+        (0..self.slices as u64)
+            .map(move |i| i * slice_bits + h1.wrapping_add(i.wrapping_mul(h2)) % slice_bits)
+    }
+}
+
+/// The finalizer of splitmix64;
+#[inline]
+fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::Hasher;
+
+    fn hash(item: u64) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        item.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn bloom_false_positive_test() {
+        for &fp_rate in &[0.1, 0.01, 0.001] {
+            let n = 100_000;
+            let mut filter = BloomFilter::with_rate(n, fp_rate);
+            // the distinct items taken as duplicates while being inserted;
+            let dropped = (0..n).filter(|i| !filter.insert_hash(hash(*i))).count();
+            assert!((dropped as f64) < 2.0 * fp_rate * n as f64, "{} of {}", dropped, fp_rate);
+            // no false negative;
+            assert!((0..n).all(|i| filter.contains_hash(hash(i))));
+            assert!((0..n).all(|i| !filter.insert_hash(hash(i))));
+            let positives = (n..2 * n).filter(|i| filter.contains_hash(hash(*i))).count();
+            let rate = positives as f64 / n as f64;
+            assert!(rate < 2.0 * fp_rate, "{} of {}", rate, fp_rate);
+            let fill = filter.fill_ratio();
+            assert!((fill - fp_rate.powf(1.0 / filter.slices as f64)).abs() < 0.02, "{}", fill);
+        }
+    }
+
+    #[test]
+    fn bloom_memory_test() {
+        // about 1.44 * log2(1 / p) bits per item;
+        let bytes = BloomFilter::memory_bytes(1_000_000, 0.01);
+        assert!(bytes > 1_100_000 && bytes < 1_300_000, "{}", bytes);
+        let filter = BloomFilter::with_rate(1_000_000, 0.01);
+        assert_eq!(filter.bits.len() as u64 * 8, bytes);
+        assert_eq!(filter.fill_ratio(), 0.0);
+    }
+}
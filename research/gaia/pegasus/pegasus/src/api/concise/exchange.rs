@@ -14,6 +14,7 @@
 //! limitations under the License.
 
 use crate::api::function::RouteFunction;
+use crate::api::key::{KeySelector, StableKey};
 use crate::errors::BuildJobError;
 use crate::stream::Stream;
 use crate::Data;
@@ -26,4 +27,17 @@ pub trait Exchange<D: Data> {
     fn exchange_with_fn<R>(&self, func: R) -> Result<Stream<D>, BuildJobError>
     where
         R: Fn(&D) -> u64 + Send + 'static;
+
+    /// Exchange the data by the stable hashes of the keys selected by `keys!`, so the data of the
+    /// same key go to the same worker in all such exchanges of the job, e.g. the two inputs of a
+    /// join, see `co_partitioned_with`;
+    fn exchange_by<K, F>(&self, keys: KeySelector<D, K, F>) -> Result<Stream<D>, BuildJobError>
+    where
+        K: StableKey + 'static,
+        F: Fn(&D) -> K + Send + 'static;
+
+    /// Check if the stream and `other` are both the outputs of `exchange_by` with the keys of the
+    /// same type, so the data of the same key are on the same worker, e.g. before they are joined,
+    /// or fail the build;
+    fn co_partitioned_with<R: Data>(&self, other: &Stream<R>) -> Result<(), BuildJobError>;
 }
@@ -26,4 +26,10 @@ pub trait Filter<D: Data> {
     fn filter_with_fn<F>(&self, func: F) -> Result<Stream<D>, BuildJobError>
     where
         F: Fn(&D) -> FnResult<bool> + Send + 'static;
+
+    /// Filter the data as `filter_with_fn`, while the data rejected by the predicate go to the
+    /// second stream instead of being dropped, in the same scopes as the first one;
+    fn filter_with_sideoutput<F>(&self, func: F) -> Result<(Stream<D>, Stream<D>), BuildJobError>
+    where
+        F: Fn(&D) -> FnResult<bool> + Send + 'static;
 }
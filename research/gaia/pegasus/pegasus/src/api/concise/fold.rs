@@ -19,6 +19,7 @@ use crate::communication::Channel;
 use crate::errors::BuildJobError;
 use crate::stream::Stream;
 use crate::Data;
+use std::io;
 
 pub trait Fold<I: Data> {
     fn fold<O, C, F>(&self, seed: O, channel: C, func: F) -> Result<Stream<O>, BuildJobError>
@@ -33,4 +34,16 @@ pub trait Fold<I: Data> {
     where
         A: AccumFactory<I> + 'static,
         A::Target: Data;
+
+    /// Fold the data globally in two stages: each worker accumulates its own data first, and
+    /// only the partial accumulators are sent to one worker and combined by `combine`, which
+    /// should be associative; On more than one server of more than one worker each, the partial
+    /// accumulators of each server are combined within the server first, see `Range::PerServer`;
+    fn fold_with_combine<A, M>(
+        &self, accum_factory: A, combine: M,
+    ) -> Result<Stream<A::Target>, BuildJobError>
+    where
+        A: AccumFactory<I> + 'static,
+        A::Target: Data,
+        M: Fn(&mut A::Target, A::Target) -> Result<(), io::Error> + Send + 'static;
 }
@@ -18,7 +18,57 @@ use crate::communication::Channel;
 use crate::errors::BuildJobError;
 use crate::stream::Stream;
 use crate::Data;
+use pegasus_common::codec::*;
 use std::error::Error;
+use std::future::Future;
+use std::time::Duration;
+
+/// A datum failed to be mapped, along with the error message, e.g. output by
+/// `map_with_sideoutput` instead of failing the job;
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rejected<D> {
+    pub datum: D,
+    pub error: String,
+}
+
+impl<D: Encode> Encode for Rejected<D> {
+    fn write_to<W: WriteExt>(&self, writer: &mut W) -> std::io::Result<()> {
+        self.datum.write_to(writer)?;
+        self.error.write_to(writer)
+    }
+}
+
+impl<D: Decode> Decode for Rejected<D> {
+    fn read_from<R: ReadExt>(reader: &mut R) -> std::io::Result<Self> {
+        let datum = D::read_from(reader)?;
+        let error = String::read_from(reader)?;
+        Ok(Rejected { datum, error })
+    }
+}
+
+/// The policy of retrying the batches failed to be mapped by an idempotent function, set by
+/// `Stream::with_retry`;
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// The most attempts of mapping a batch, including the first one;
+    pub max_attempts: u32,
+    /// The delay before the first retry, which is doubled for each of the following retries, and
+    /// blocks the worker meanwhile;
+    pub backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// The delay before the given retry, counting from 1;
+    pub fn backoff_of(&self, retry: u32) -> Duration {
+        self.backoff.saturating_mul(1u32 << retry.saturating_sub(1).min(16))
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy { max_attempts: 3, backoff: Duration::from_millis(10) }
+    }
+}
 
 pub trait Map<I: Data> {
     fn map<O, C, F>(&self, channel: C, func: F) -> Result<Stream<O>, BuildJobError>
@@ -50,4 +100,29 @@ pub trait Map<I: Data> {
         C: Into<Channel<I>>,
         R: Iterator<Item = Result<O, Box<dyn Error + Send>>> + Send + 'static,
         F: Fn(I) -> FnResult<R> + Send + 'static;
+
+    /// Map the data as `map_with_fn`, while the data failed to be mapped go to the second stream
+    /// along with their errors, in the same scopes as the first one, instead of failing the job;
+    fn map_with_sideoutput<O, F>(
+        &self, func: F,
+    ) -> Result<(Stream<O>, Stream<Rejected<I>>), BuildJobError>
+    where
+        O: Data,
+        F: Fn(&I) -> Result<O, String> + Send + 'static;
+
+    /// Map each datum by the future the function returns, e.g. to look up an external service
+    /// without blocking the worker; At most `concurrency` futures of each scope are in flight on
+    /// each worker, which are polled by the worker between the other operators;
+    ///
+    /// The outputs of a scope are given in the order the futures complete, which may differ from
+    /// the order of the inputs, while the end of the scope is held back until all its futures
+    /// complete; Any error of the futures fails the job as that of `map_with_fn`;
+    fn map_async_with_fn<O, C, Fut, F>(
+        &self, channel: C, concurrency: usize, func: F,
+    ) -> Result<Stream<O>, BuildJobError>
+    where
+        O: Data,
+        C: Into<Channel<I>>,
+        Fut: Future<Output = FnResult<O>> + Send + 'static,
+        F: Fn(I) -> Fut + Send + 'static;
 }
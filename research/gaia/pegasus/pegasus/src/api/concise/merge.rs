@@ -0,0 +1,24 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use crate::errors::BuildJobError;
+use crate::stream::Stream;
+use crate::Data;
+
+pub trait Merge<D: Data> {
+    /// Merge the data of the other stream in the same scope into this one, e.g. the two streams
+    /// split by `branch_by`, where a scope of the merged stream ends once it ends in both;
+    fn merge(&self, other: &Stream<D>) -> Result<Stream<D>, BuildJobError>;
+}
@@ -13,6 +13,7 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
+pub mod adapt;
 pub mod dedup;
 pub mod exchange;
 pub mod filter;
@@ -20,4 +21,7 @@ pub mod fold;
 pub mod iteration;
 pub mod join;
 pub mod map;
+pub mod merge;
 pub mod reduce;
+pub mod sample;
+pub mod time_limit;
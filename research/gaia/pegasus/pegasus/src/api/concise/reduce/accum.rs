@@ -131,11 +131,11 @@ impl<D: Debug + Send + 'static> Accumulator<D> for ToList<D> {
 
 impl<D: 'static> AsAny for ToList<D> {
     fn as_any_mut(&mut self) -> &mut dyn Any {
-        unimplemented!()
+        self
     }
 
     fn as_any_ref(&self) -> &dyn Any {
-        unimplemented!()
+        self
     }
 }
 
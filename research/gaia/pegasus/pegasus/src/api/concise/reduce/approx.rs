@@ -0,0 +1,502 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The approximate aggregations, which summarize the data of a scope by a mergeable sketch of
+//! bounded size rather than keeping them all, e.g. to count the distinct data or find the quantiles
+//! of billions of data; The global ones are two-staged, i.e. each worker sketches its own data
+//! first, and only the sketches are sent to one worker to be merged.
+
+use crate::api::accum::Accumulator;
+use crate::api::concise::reduce::Range;
+use crate::codec::{Decode, Encode, ReadExt, WriteExt};
+use crate::errors::BuildJobError;
+use crate::stream::Stream;
+use crate::Data;
+use pegasus_common::downcast::*;
+use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeSet;
+use std::f64::consts::PI;
+use std::hash::{Hash, Hasher};
+use std::io;
+
+pub trait ApproxDistinct<D: Data + Hash> {
+    /// Count the distinct data of each scope approximately by HyperLogLog of the `precision`,
+    /// which should be in `[4, 18]`, see `HyperLogLog`;
+    fn count_approx_distinct(
+        &self, range: Range, precision: u8,
+    ) -> Result<Stream<u64>, BuildJobError>;
+}
+
+pub trait Quantiles<D: Data + Into<f64>> {
+    /// Estimate the quantiles `qs` of the data of each scope by t-digest, see `TDigest`, which are
+    /// emitted in the order of `qs` once a scope ends; Nothing is emitted for an empty scope;
+    fn quantiles(&self, range: Range, qs: &[f64]) -> Result<Stream<Vec<f64>>, BuildJobError>;
+}
+
+pub const MIN_PRECISION: u8 = 4;
+pub const MAX_PRECISION: u8 = 18;
+
+/// Count the distinct items approximately by HyperLogLog of `2^precision` one-byte registers, whose
+/// relative standard error is about `1.04 / sqrt(2^precision)`, e.g. 0.8% of precision 14.
+/// The hashes of the items are kept as they are until there are more than `2^precision / 8` of
+/// them, so a small number of distinct items are counted exactly unless their hashes collide.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HyperLogLog {
+    precision: u8,
+    // the hashes of the items before the sketch turns into registers;
+    sparse: Option<BTreeSet<u64>>,
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    /// Create an empty sketch, where the `precision` should be in `[4, 18]`
+    pub fn new(precision: u8) -> Self {
+        assert!(
+            (MIN_PRECISION..=MAX_PRECISION).contains(&precision),
+            "the precision of HyperLogLog should be in [{}, {}], but got {}",
+            MIN_PRECISION,
+            MAX_PRECISION,
+            precision
+        );
+        HyperLogLog { precision, sparse: Some(BTreeSet::new()), registers: vec![] }
+    }
+
+    pub fn precision(&self) -> u8 {
+        self.precision
+    }
+
+    pub fn add<T: Hash + ?Sized>(&mut self, item: &T) {
+        // the hasher of fixed keys, so that the same item has the same hash in all workers;
+        let mut hasher = DefaultHasher::new();
+        item.hash(&mut hasher);
+        self.add_hash(hasher.finish());
+    }
+
+    pub fn add_hash(&mut self, hash: u64) {
+        if let Some(sparse) = self.sparse.as_mut() {
+            sparse.insert(hash);
+            if sparse.len() > self.sparse_limit() {
+                self.densify();
+            }
+        } else {
+            self.update(hash);
+        }
+    }
+
+    /// Merge the sketch of another part of the data, which should be of the same precision
+    pub fn merge(&mut self, other: &HyperLogLog) -> io::Result<()> {
+        if self.precision != other.precision {
+            let msg = format!(
+                "can't merge HyperLogLog of precision {} into {}",
+                other.precision, self.precision
+            );
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, msg));
+        }
+        if let Some(hashes) = other.sparse.as_ref() {
+            for hash in hashes {
+                self.add_hash(*hash);
+            }
+        } else {
+            self.densify();
+            for (register, rank) in self.registers.iter_mut().zip(other.registers.iter()) {
+                if *register < *rank {
+                    *register = *rank;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Estimate the number of the distinct items
+    pub fn count(&self) -> u64 {
+        if let Some(sparse) = self.sparse.as_ref() {
+            return sparse.len() as u64;
+        }
+        let m = self.registers.len() as f64;
+        let mut sum = 0.0;
+        let mut zeros = 0;
+        for rank in self.registers.iter() {
+            sum += 1.0 / (1u64 << *rank) as f64;
+            if *rank == 0 {
+                zeros += 1;
+            }
+        }
+        let alpha = match self.registers.len() {
+            16 => 0.673,
+            32 => 0.697,
+            64 => 0.709,
+            _ => 0.7213 / (1.0 + 1.079 / m),
+        };
+        let estimate = alpha * m * m / sum;
+        if estimate <= 2.5 * m && zeros > 0 {
+            // linear counting is more accurate for the small cardinalities;
+            (m * (m / zeros as f64).ln()).round() as u64
+        } else {
+            estimate.round() as u64
+        }
+    }
+
+    #[inline]
+    fn sparse_limit(&self) -> usize {
+        (1usize << self.precision) / 8
+    }
+
+    fn densify(&mut self) {
+        if let Some(hashes) = self.sparse.take() {
+            self.registers = vec![0; 1 << self.precision];
+            for hash in hashes {
+                self.update(hash);
+            }
+        }
+    }
+
+    fn update(&mut self, hash: u64) {
+        let precision = self.precision as u32;
+        let index = (hash >> (64 - precision)) as usize;
+        // the position of the first 1-bit in the rest bits of the hash;
+        let rank = (hash << precision).leading_zeros().min(64 - precision) + 1;
+        if self.registers[index] < rank as u8 {
+            self.registers[index] = rank as u8;
+        }
+    }
+}
+
+impl<D: Hash> Accumulator<D> for HyperLogLog {
+    fn accum(&mut self, next: D) -> Result<(), io::Error> {
+        self.add(&next);
+        Ok(())
+    }
+}
+
+impl_as_any!(HyperLogLog);
+
+impl Encode for HyperLogLog {
+    fn write_to<W: WriteExt>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_u8(self.precision)?;
+        if let Some(hashes) = self.sparse.as_ref() {
+            writer.write_u8(1)?;
+            writer.write_u32(hashes.len() as u32)?;
+            for hash in hashes {
+                writer.write_u64(*hash)?;
+            }
+            Ok(())
+        } else {
+            writer.write_u8(0)?;
+            writer.write_all(&self.registers)
+        }
+    }
+}
+
+impl Decode for HyperLogLog {
+    fn read_from<R: ReadExt>(reader: &mut R) -> io::Result<Self> {
+        let precision = reader.read_u8()?;
+        if !(MIN_PRECISION..=MAX_PRECISION).contains(&precision) {
+            let msg = format!("invalid precision {} of HyperLogLog", precision);
+            return Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+        }
+        if reader.read_u8()? == 1 {
+            let len = reader.read_u32()? as usize;
+            let mut hashes = BTreeSet::new();
+            for _ in 0..len {
+                hashes.insert(reader.read_u64()?);
+            }
+            Ok(HyperLogLog { precision, sparse: Some(hashes), registers: vec![] })
+        } else {
+            let mut registers = vec![0; 1 << precision];
+            reader.read_exact(&mut registers)?;
+            Ok(HyperLogLog { precision, sparse: None, registers })
+        }
+    }
+}
+
+pub const DEFAULT_COMPRESSION: f64 = 100.0;
+
+/// Summarize the distribution of numbers by t-digest, which clusters the numbers into at most
+/// about `compression` centroids, and estimates a quantile by interpolating the centroids around
+/// it. The centroids are smaller towards the tails, where the estimations are more accurate, and
+/// the numbers are kept as they are while there are no more than about `compression / 2` of them,
+/// so the quantiles of a few numbers are exact, interpolated linearly between the closest ranks.
+/// NaN is ignored.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TDigest {
+    compression: f64,
+    // sorted by the means;
+    centroids: Vec<Centroid>,
+    // the numbers or the centroids of other digests not merged yet;
+    buffer: Vec<Centroid>,
+    count: u64,
+    min: f64,
+    max: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Centroid {
+    mean: f64,
+    weight: u64,
+}
+
+impl Centroid {
+    fn add(&mut self, other: Centroid) {
+        self.weight += other.weight;
+        self.mean += (other.mean - self.mean) * other.weight as f64 / self.weight as f64;
+    }
+}
+
+impl Default for TDigest {
+    fn default() -> Self {
+        TDigest::new(DEFAULT_COMPRESSION)
+    }
+}
+
+impl TDigest {
+    pub fn new(compression: f64) -> Self {
+        TDigest {
+            compression,
+            centroids: vec![],
+            buffer: vec![],
+            count: 0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn add(&mut self, value: f64) {
+        if !value.is_nan() {
+            self.add_centroid(Centroid { mean: value, weight: 1 });
+            self.min = self.min.min(value);
+            self.max = self.max.max(value);
+        }
+    }
+
+    /// Merge the digest of another part of the numbers
+    pub fn merge(&mut self, other: &TDigest) {
+        for centroid in other.centroids.iter().chain(other.buffer.iter()) {
+            self.add_centroid(*centroid);
+        }
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+    }
+
+    /// Estimate the quantile `q` in `[0, 1]`, or `None` if there is no number
+    pub fn quantile(&mut self, q: f64) -> Option<f64> {
+        self.compress();
+        if self.centroids.is_empty() {
+            return None;
+        }
+        // interpolate between the centers of the centroids, by the ranks counting from 0;
+        let q = if q.is_nan() { 0.0 } else { q.clamp(0.0, 1.0) };
+        let rank = q * (self.count - 1) as f64;
+        let mut left = (0.0, self.min);
+        let mut before = 0.0;
+        for centroid in self.centroids.iter() {
+            let center = before + (centroid.weight - 1) as f64 / 2.0;
+            if rank <= center {
+                return Some(interpolate(left, (center, centroid.mean), rank));
+            }
+            left = (center, centroid.mean);
+            before += centroid.weight as f64;
+        }
+        Some(interpolate(left, ((self.count - 1) as f64, self.max), rank))
+    }
+
+    /// Estimate the quantiles in the order of `qs`, or `None` if there is no number
+    pub fn quantiles(&mut self, qs: &[f64]) -> Option<Vec<f64>> {
+        qs.iter().map(|q| self.quantile(*q)).collect()
+    }
+
+    fn add_centroid(&mut self, centroid: Centroid) {
+        self.count += centroid.weight;
+        self.buffer.push(centroid);
+        if self.buffer.len() as f64 >= 5.0 * self.compression {
+            self.compress();
+        }
+    }
+
+    /// Merge the buffer into the centroids, where the adjacent centroids are merged as long as
+    /// the merged one is within the size limit by the k1 scale function of t-digest
+    fn compress(&mut self) {
+        if self.buffer.is_empty() {
+            return;
+        }
+        let mut all = std::mem::take(&mut self.centroids);
+        all.append(&mut self.buffer);
+        all.sort_by(|a, b| a.mean.partial_cmp(&b.mean).unwrap_or(Ordering::Equal));
+        let total = self.count as f64;
+        let mut merged = Vec::with_capacity(self.compression as usize);
+        let mut centroids = all.into_iter();
+        let mut current = centroids.next().expect("buffer is not empty");
+        let mut before = 0.0;
+        let mut q_limit = self.q_limit(0.0);
+        for next in centroids {
+            let q = (before + (current.weight + next.weight) as f64) / total;
+            if q <= q_limit {
+                current.add(next);
+            } else {
+                before += current.weight as f64;
+                merged.push(current);
+                q_limit = self.q_limit(before / total);
+                current = next;
+            }
+        }
+        merged.push(current);
+        self.centroids = merged;
+    }
+
+    /// The most quantile a centroid starting from `q` can reach, i.e. `k^-1(k(q) + 1)`
+    #[inline]
+    fn q_limit(&self, q: f64) -> f64 {
+        let k = self.compression / (2.0 * PI) * (2.0 * q - 1.0).asin() + 1.0;
+        ((k * 2.0 * PI / self.compression).sin() + 1.0) / 2.0
+    }
+}
+
+#[inline]
+fn interpolate(left: (f64, f64), right: (f64, f64), x: f64) -> f64 {
+    if right.0 <= left.0 {
+        right.1
+    } else {
+        left.1 + (right.1 - left.1) * (x - left.0) / (right.0 - left.0)
+    }
+}
+
+impl<D: Into<f64>> Accumulator<D> for TDigest {
+    fn accum(&mut self, next: D) -> Result<(), io::Error> {
+        self.add(next.into());
+        Ok(())
+    }
+}
+
+impl_as_any!(TDigest);
+
+impl Encode for TDigest {
+    fn write_to<W: WriteExt>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_f64(self.compression)?;
+        writer.write_f64(self.min)?;
+        writer.write_f64(self.max)?;
+        let len = self.centroids.len() + self.buffer.len();
+        writer.write_u32(len as u32)?;
+        for centroid in self.centroids.iter().chain(self.buffer.iter()) {
+            writer.write_f64(centroid.mean)?;
+            writer.write_u64(centroid.weight)?;
+        }
+        Ok(())
+    }
+}
+
+impl Decode for TDigest {
+    fn read_from<R: ReadExt>(reader: &mut R) -> io::Result<Self> {
+        let mut digest = TDigest::new(reader.read_f64()?);
+        digest.min = reader.read_f64()?;
+        digest.max = reader.read_f64()?;
+        let len = reader.read_u32()? as usize;
+        for _ in 0..len {
+            let mean = reader.read_f64()?;
+            let weight = reader.read_u64()?;
+            digest.count += weight;
+            digest.buffer.push(Centroid { mean, weight });
+        }
+        Ok(digest)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sketch(precision: u8, items: std::ops::Range<u64>) -> HyperLogLog {
+        let mut sketch = HyperLogLog::new(precision);
+        for item in items {
+            sketch.add(&item);
+        }
+        sketch
+    }
+
+    #[test]
+    fn distinct_error_bound_test() {
+        let count = sketch(14, 0..1_000_000).count() as f64;
+        assert!((count - 1_000_000.0).abs() / 1_000_000.0 < 0.02, "count {}", count);
+    }
+
+    #[test]
+    fn distinct_exact_test() {
+        let mut sketch = sketch(14, 0..100);
+        for item in 0..100u64 {
+            sketch.add(&item);
+        }
+        assert_eq!(sketch.count(), 100);
+        assert_eq!(HyperLogLog::new(4).count(), 0);
+    }
+
+    #[test]
+    fn distinct_merge_test() {
+        let whole = sketch(12, 0..100_000);
+        let mut merged = sketch(12, 0..10);
+        merged.merge(&sketch(12, 0..60_000)).unwrap();
+        merged.merge(&sketch(12, 40_000..100_000)).unwrap();
+        assert_eq!(merged, whole);
+        assert!(merged.merge(&HyperLogLog::new(14)).is_err());
+
+        let mut bytes = vec![];
+        merged.write_to(&mut bytes).unwrap();
+        assert_eq!(HyperLogLog::read_from(&mut &bytes[..]).unwrap(), whole);
+        let sparse = sketch(12, 0..10);
+        bytes.clear();
+        sparse.write_to(&mut bytes).unwrap();
+        assert_eq!(HyperLogLog::read_from(&mut &bytes[..]).unwrap(), sparse);
+    }
+
+    fn digest<I: IntoIterator<Item = u64>>(values: I) -> TDigest {
+        let mut digest = TDigest::default();
+        for value in values {
+            digest.add(value as f64);
+        }
+        digest
+    }
+
+    #[test]
+    fn quantile_error_bound_test() {
+        let n = 1_000_000u64;
+        // all numbers in [0, n) in a shuffled order;
+        let values = (0..n).map(|i| (i * 7919) % n);
+        let mut whole = digest(values.clone());
+        let mut merged = digest(values.clone().filter(|v| v % 3 == 0));
+        merged.merge(&digest(values.filter(|v| v % 3 != 0)));
+        for q in [0.0, 0.01, 0.25, 0.5, 0.75, 0.99, 1.0].iter() {
+            let exact = q * (n - 1) as f64;
+            let estimated = whole.quantile(*q).unwrap();
+            assert!((estimated - exact).abs() / (n as f64) < 0.01, "{} of {}", estimated, q);
+            let estimated = merged.quantile(*q).unwrap();
+            assert!((estimated - exact).abs() / (n as f64) < 0.01, "{} of {}", estimated, q);
+        }
+    }
+
+    #[test]
+    fn quantile_exact_test() {
+        let mut digest = digest(vec![7, 1, 3, 9, 5]);
+        assert_eq!(digest.quantiles(&[0.0, 0.5, 0.625, 1.0]), Some(vec![1.0, 5.0, 6.0, 9.0]));
+        assert_eq!(TDigest::default().quantile(0.5), None);
+
+        let mut bytes = vec![];
+        digest.write_to(&mut bytes).unwrap();
+        let mut decoded = TDigest::read_from(&mut &bytes[..]).unwrap();
+        assert_eq!(decoded.count(), 5);
+        assert_eq!(decoded.quantile(0.25), Some(3.0));
+    }
+}
@@ -27,4 +27,15 @@ pub trait Barrier<D: Data> {
     where
         C: CollectionFactory<D> + 'static,
         C::Target: Data;
+
+    /// Hold the data of each scope until the end of the scope has been received on all inputs,
+    /// and then release them in the same scope, where the held data are counted against the
+    /// memory limit of the job;
+    fn scope_barrier(&self) -> Result<Stream<D>, BuildJobError>;
+
+    /// Hold the data of each scope as `scope_barrier`, and release them after the function has
+    /// seen all of them, e.g. to normalize them by a factor computed from the whole scope;
+    fn scope_barrier_with<F>(&self, func: F) -> Result<Stream<D>, BuildJobError>
+    where
+        F: Fn(&mut Vec<D>) + Send + 'static;
 }
@@ -19,5 +19,7 @@ use crate::stream::Stream;
 use crate::Data;
 
 pub trait Count<D: Data> {
+    /// Count the data of each scope; The global count is two-staged, i.e. each worker counts its
+    /// own data first, and only the partial counts are sent to one worker to be summed up;
     fn count(&self, range: Range) -> Result<Stream<u64>, BuildJobError>;
 }
@@ -22,6 +22,7 @@ use pegasus_common::collections::MapFactory;
 use pegasus_common::downcast::AsAny;
 use std::collections::HashMap;
 use std::hash::Hash;
+use std::io;
 
 pub trait Group<D: Data + Keyed> {
     fn group_by(
@@ -47,6 +48,21 @@ pub trait Group<D: Data + Keyed> {
         A: AccumFactory<D::Value> + 'static,
         A::Target: Data + 'static,
         D::Key: Data + Hash + Eq + Partition;
+
+    /// Group the data globally in two stages: the values of each key are accumulated locally
+    /// first, and only the partial accumulators are exchanged by the keys and combined by
+    /// `combine`, so a hot key sends one record per worker instead of all of its values to one
+    /// worker; The `combine` should be associative, and give the same result as `group_with_accum`
+    /// with `Range::Global`.
+    #[allow(clippy::type_complexity)]
+    fn group_with_combine<A, M>(
+        &self, accum_factory: A, combine: M,
+    ) -> Result<Stream<HashMap<D::Key, A::Target>>, BuildJobError>
+    where
+        A: AccumFactory<D::Value> + 'static,
+        A::Target: Data + 'static,
+        D::Key: Data + Hash + Eq + Partition,
+        M: Fn(&mut A::Target, A::Target) -> Result<(), io::Error> + Send + 'static;
 }
 
 pub trait KeyBy<D: Data> {
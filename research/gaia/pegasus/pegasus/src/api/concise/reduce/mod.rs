@@ -16,6 +16,7 @@
 use pegasus_common::downcast::*;
 
 pub mod accum;
+pub mod approx;
 pub mod barrier;
 pub mod count;
 pub mod group;
@@ -25,12 +26,17 @@ pub mod order;
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum Range {
     Local,
+    /// Over the workers of each server, whose data are gathered by one worker of the server
+    /// without crossing the network;
+    PerServer,
     Global,
 }
 
 impl_as_any!(Range);
-pub const RANGES: [Range; 2] = [Range::Local, Range::Global];
+/// The ranges indexed by their values in the protocol of the server;
+pub const RANGES: [Range; 3] = [Range::Local, Range::Global, Range::PerServer];
 
+pub use approx::{ApproxDistinct, Quantiles};
 pub use barrier::Barrier;
 pub use count::Count;
 pub use group::{Group, KeyBy};
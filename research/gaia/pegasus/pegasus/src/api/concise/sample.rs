@@ -0,0 +1,33 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use crate::api::Range;
+use crate::stream::Stream;
+use crate::{BuildJobError, Data};
+
+/// Draw the data at random by the random numbers of the job, see `JobConf::rng_seed`, so that
+/// the same data are drawn in all runs of the job with the same seed, as long as the data come to
+/// each worker in the same order, while the workers draw apart;
+pub trait Sample<D: Data> {
+    /// Keep each datum by the probability `p` in `[0, 1]`, which is drawn by each worker from the
+    /// stream of each scope apart;
+    fn coin(&self, p: f64) -> Result<Stream<D>, BuildJobError>;
+
+    /// Draw `n` data of each scope uniformly without replacement, or all if fewer, in each worker,
+    /// each server, or all the workers of the range; Each datum is given a random key where it is,
+    /// and those of the `n` least keys are drawn, in the order of their keys, so the data drawn
+    /// don't depend on the order the data of the workers are gathered in;
+    fn sample(&self, range: Range, n: usize) -> Result<Stream<D>, BuildJobError>;
+}
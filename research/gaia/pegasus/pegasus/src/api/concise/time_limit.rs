@@ -0,0 +1,36 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use crate::errors::BuildJobError;
+use crate::stream::Stream;
+use crate::Data;
+use std::time::Duration;
+
+/// Bound a part of the dataflow by time, e.g. `timeLimit()` in Gremlin, where each scope is given
+/// the time limit since its first data enter the part. Once the deadline of a scope passes, the
+/// part stops consuming the data of the scope, whose data still in the part are discarded, while
+/// the outputs of the scope so far are kept as its results, followed by the end of the scope as
+/// usual;
+///
+/// The deadlines are checked cooperatively, i.e. between the firings of the operators, in each
+/// step of the scheduler, so an operator busy in a firing overruns the deadline until the firing
+/// ends;
+pub trait TimeLimit<D: Data> {
+    /// Build the part bounded by the time limit on the stream by `func`;
+    fn time_limit<O, F>(&self, limit: Duration, func: F) -> Result<Stream<O>, BuildJobError>
+    where
+        O: Data,
+        F: FnOnce(Stream<D>) -> Result<Stream<O>, BuildJobError>;
+}
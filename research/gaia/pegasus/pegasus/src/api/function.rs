@@ -14,6 +14,7 @@
 //! limitations under the License.
 
 use crate::Data;
+use std::cell::Cell;
 use std::cmp::Ordering;
 use std::sync::Arc;
 
@@ -34,6 +35,22 @@ pub trait MultiRouteFunction<D>: Send + 'static {
     fn route(&self, data: &D) -> FnResult<&[u64]>;
 }
 
+/// Give the workers that can serve the data, e.g. those holding the replicas of the partition of a
+/// vertex, with the primary first;
+pub trait ReplicaFunction<D>: Send + 'static {
+    fn replicas(&self, data: &D) -> FnResult<Vec<u64>>;
+}
+
+/// How the data are routed to one of the workers that can serve them;
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ReplicaPolicy {
+    /// rotate among the replicas datum by datum, to balance the load over them;
+    RoundRobin,
+    /// keep the data in current server if any replica is in it, to save the network, or rotate
+    /// among the replicas otherwise;
+    PreferLocal,
+}
+
 pub trait Partition {
     fn get_partition(&self) -> FnResult<u64>;
 }
@@ -139,12 +156,31 @@ pub trait CompareFunction<D>: Send + 'static {
 
 pub trait MapFunction<I, O>: Send + 'static {
     fn exec(&self, input: I) -> FnResult<O>;
+
+    /// Check if the function gives the same output on the same input without side effects, e.g.
+    /// a lookup of the storage, so that it can be called again on a failed input by retry;
+    fn is_idempotent(&self) -> bool {
+        false
+    }
 }
 
 pub trait FlatMapFunction<I, O>: Send + 'static {
     type Target: Iterator<Item = Result<O, DynError>> + Send + 'static;
 
     fn exec(&self, input: I) -> FnResult<Self::Target>;
+
+    /// Check if the function gives the same outputs on the same input without side effects, as
+    /// that of `MapFunction`;
+    fn is_idempotent(&self) -> bool {
+        false
+    }
+
+    /// The outputs the function expects to give of each input, e.g. the average degree of the
+    /// vertices it expands, which bounds the inputs its operator takes in each firing by the
+    /// capacity of its outputs, see `OperatorMeta::set_fanout`; `None` if unknown;
+    fn fanout(&self) -> Option<f64> {
+        None
+    }
 }
 
 pub trait FilterFunction<D>: Send + 'static {
@@ -153,10 +189,22 @@ pub trait FilterFunction<D>: Send + 'static {
 
 pub trait LeftJoinFunction<D>: Send + 'static {
     fn exec(&self, left: &D, right: D) -> Option<D>;
+
+    /// Join the left whose subtask outputs no result, in the joins keeping such ones, e.g.
+    /// `join_first_subtask`, which is dropped by default;
+    fn exec_empty(&self, _left: &D) -> Option<D> {
+        None
+    }
 }
 
 pub trait EncodeFunction<D>: Send + 'static {
     fn encode(&self, data: Vec<D>) -> Vec<u8>;
+
+    /// Encode the data as `encode`, but fail if any of them can't be encoded faithfully, e.g. a
+    /// value out of the range of the type in the wire format, instead of giving a wrong result;
+    fn try_encode(&self, data: Vec<D>) -> FnResult<Vec<u8>> {
+        Ok(self.encode(data))
+    }
 }
 
 ///
@@ -199,6 +247,10 @@ impl<I, O, M: MapFunction<I, O> + ?Sized> MapFunction<I, O> for Box<M> {
     fn exec(&self, input: I) -> FnResult<O> {
         (**self).exec(input)
     }
+
+    fn is_idempotent(&self) -> bool {
+        (**self).is_idempotent()
+    }
 }
 
 impl<I, O, M: FlatMapFunction<I, O> + ?Sized> FlatMapFunction<I, O> for Box<M> {
@@ -207,6 +259,14 @@ impl<I, O, M: FlatMapFunction<I, O> + ?Sized> FlatMapFunction<I, O> for Box<M> {
     fn exec(&self, input: I) -> FnResult<Self::Target> {
         (**self).exec(input)
     }
+
+    fn is_idempotent(&self) -> bool {
+        (**self).is_idempotent()
+    }
+
+    fn fanout(&self) -> Option<f64> {
+        (**self).fanout()
+    }
 }
 
 impl<D, F: FilterFunction<D> + ?Sized> FilterFunction<D> for Box<F> {
@@ -219,12 +279,20 @@ impl<D, L: LeftJoinFunction<D> + ?Sized> LeftJoinFunction<D> for Box<L> {
     fn exec(&self, left: &D, right: D) -> Option<D> {
         (**self).exec(left, right)
     }
+
+    fn exec_empty(&self, left: &D) -> Option<D> {
+        (**self).exec_empty(left)
+    }
 }
 
 impl<D, E: EncodeFunction<D> + ?Sized> EncodeFunction<D> for Box<E> {
     fn encode(&self, data: Vec<D>) -> Vec<u8> {
         (**self).encode(data)
     }
+
+    fn try_encode(&self, data: Vec<D>) -> FnResult<Vec<u8>> {
+        (**self).try_encode(data)
+    }
 }
 
 ///
@@ -235,6 +303,10 @@ impl<D: Data, T: LeftJoinFunction<D> + Sync + ?Sized> LeftJoinFunction<D> for Ar
     fn exec(&self, parent: &D, sub: D) -> Option<D> {
         (**self).exec(parent, sub)
     }
+
+    fn exec_empty(&self, parent: &D) -> Option<D> {
+        (**self).exec_empty(parent)
+    }
 }
 
 /// impl functions for closure;
@@ -263,6 +335,64 @@ where
     }
 }
 
+/// Route each datum to one of its replicas following the policy, where the same datum may go to
+/// different workers, so it fits the reads of immutable data, e.g. the lookups of the graph,
+/// rather than the data keyed by the route, e.g. those to be grouped;
+pub struct ReplicaRoute<D, F: ReplicaFunction<D>> {
+    func: F,
+    policy: ReplicaPolicy,
+    workers_per_server: u64,
+    // the server of current worker, if the route is built by a worker
+    local_server: Option<u64>,
+    next: Cell<usize>,
+    _ph: std::marker::PhantomData<D>,
+}
+
+impl<D, F: ReplicaFunction<D>> ReplicaRoute<D, F> {
+    pub fn new(func: F, policy: ReplicaPolicy, workers_per_server: u64) -> Self {
+        let workers_per_server = std::cmp::max(1, workers_per_server);
+        let local_server = crate::get_current_worker().map(|w| w.index as u64 / workers_per_server);
+        ReplicaRoute {
+            func,
+            policy,
+            workers_per_server,
+            local_server,
+            next: Cell::new(0),
+            _ph: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<D, F> RouteFunction<D> for ReplicaRoute<D, F>
+where
+    D: Send + 'static,
+    F: ReplicaFunction<D>,
+{
+    fn route(&self, data: &D) -> FnResult<u64> {
+        let replicas = self.func.replicas(data)?;
+        match replicas.len() {
+            0 => {
+                let err: Box<dyn std::error::Error + Send + Sync> = "no replica to route to".into();
+                Err(err)
+            }
+            1 => Ok(replicas[0]),
+            len => {
+                if self.policy == ReplicaPolicy::PreferLocal {
+                    if let Some(local) = self.local_server {
+                        let workers = self.workers_per_server;
+                        if let Some(r) = replicas.iter().find(|r| **r / workers == local) {
+                            return Ok(*r);
+                        }
+                    }
+                }
+                let next = self.next.get();
+                self.next.set(next.wrapping_add(1));
+                Ok(replicas[next % len])
+            }
+        }
+    }
+}
+
 #[macro_export]
 macro_rules! route {
     ($func: expr) => {
@@ -375,6 +505,36 @@ macro_rules! flat_map {
     };
 }
 
+/// Mark a map or flat_map function as idempotent, e.g. `Idempotent(map!(|id| lookup(id)))`, so
+/// that it can be retried by `Stream::with_retry`;
+pub struct Idempotent<F>(pub F);
+
+impl<I, O, F: MapFunction<I, O>> MapFunction<I, O> for Idempotent<F> {
+    fn exec(&self, input: I) -> FnResult<O> {
+        self.0.exec(input)
+    }
+
+    fn is_idempotent(&self) -> bool {
+        true
+    }
+}
+
+impl<I, O, F: FlatMapFunction<I, O>> FlatMapFunction<I, O> for Idempotent<F> {
+    type Target = F::Target;
+
+    fn exec(&self, input: I) -> FnResult<Self::Target> {
+        self.0.exec(input)
+    }
+
+    fn is_idempotent(&self) -> bool {
+        true
+    }
+
+    fn fanout(&self) -> Option<f64> {
+        self.0.fanout()
+    }
+}
+
 pub struct FilterClosure<D, F: Fn(&D) -> FnResult<bool>> {
     func: F,
     _ph: std::marker::PhantomData<D>,
@@ -16,7 +16,8 @@
 use crate::api::function::{FilterFunction, FnResult};
 use crate::errors::BuildJobError;
 use crate::stream::Stream;
-use crate::Data;
+use crate::{Data, Tag};
+use std::cell::RefCell;
 
 pub trait Iteration<D: Data> {
     fn iterate<F>(&self, max_iters: u32, func: F) -> Result<Stream<D>, BuildJobError>
@@ -28,26 +29,140 @@ pub trait Iteration<D: Data> {
     ) -> Result<Stream<D>, BuildJobError>
     where
         F: FnOnce(Stream<D>) -> Result<Stream<D>, BuildJobError>;
+
+    /// Same as `iterate`, but the body is also given the round of the loop, which can be moved
+    /// into the functions of the body to tell the round they are running in, instead of
+    /// `current_iteration`, even when they run in an inner loop of the body;
+    fn iterate_with_round<F>(&self, max_iters: u32, func: F) -> Result<Stream<D>, BuildJobError>
+    where
+        F: FnOnce(LoopRound, Stream<D>) -> Result<Stream<D>, BuildJobError>;
+}
+
+thread_local! {
+    // the (scope depth, round) of each loop the operator firing on current thread is in,
+    // outermost first;
+    static CURRENT_ROUNDS : RefCell<Vec<(usize, u32)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// The round of the innermost loop of `iterate` the calling function runs in, from 0 for the
+/// data entering the loop, or `None` outside any loop;
+pub fn current_iteration() -> Option<u32> {
+    CURRENT_ROUNDS.with(|rounds| rounds.borrow().last().map(|(_, round)| *round))
+}
+
+/// The rounds of all the loops the calling function runs in, outermost first, which is empty
+/// outside any loop;
+pub fn current_iterations() -> Vec<u32> {
+    CURRENT_ROUNDS.with(|rounds| rounds.borrow().iter().map(|(_, round)| *round).collect())
+}
+
+/// The round of a loop, given to the body of `iterate_with_round`;
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct LoopRound {
+    scope_depth: usize,
+}
+
+impl LoopRound {
+    pub(crate) fn new(scope_depth: usize) -> Self {
+        LoopRound { scope_depth }
+    }
+
+    /// The round of the loop the calling function runs in, from 0 for the data entering the
+    /// loop, or `None` if it runs outside the loop;
+    pub fn get(&self) -> Option<u32> {
+        CURRENT_ROUNDS.with(|rounds| {
+            rounds
+                .borrow()
+                .iter()
+                .find(|(depth, _)| *depth == self.scope_depth)
+                .map(|(_, round)| *round)
+        })
+    }
+}
+
+/// Tell the rounds of the loops of `depths` read from `tag` to the functions fired on it, until
+/// the guard is dropped;
+pub(crate) fn enter_rounds(depths: &[usize], tag: &Tag) -> RoundsGuard {
+    let cur = tag.as_slice();
+    // the notifications of the outer scopes have shorter tags, without the inner rounds;
+    let entered = depths
+        .iter()
+        .take_while(|depth| **depth <= cur.len())
+        .map(|depth| (*depth, cur[*depth - 1]))
+        .collect();
+    let outer = CURRENT_ROUNDS.with(|rounds| rounds.replace(entered));
+    RoundsGuard { outer }
+}
+
+/// Restore the rounds of the operator fired before, if any, when it is dropped;
+pub(crate) struct RoundsGuard {
+    outer: Vec<(usize, u32)>,
+}
+
+impl Drop for RoundsGuard {
+    fn drop(&mut self) {
+        let outer = std::mem::take(&mut self.outer);
+        CURRENT_ROUNDS.with(|rounds| rounds.replace(outer));
+    }
+}
+
+/// Which data of an iteration are also emitted out of the loop, besides the data that converge;
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EmitKind {
+    /// Only the converged data leave the loop;
+    Null,
+    /// Emit the data entering the loop, and the data after each round of iteration;
+    Before,
+    /// Emit the data after each round of iteration;
+    After,
 }
 
 pub struct LoopCondition<D> {
     pub max_iters: u32,
     until: Option<Box<dyn FilterFunction<D>>>,
+    /// If the until condition is checked on the data entering the loop, otherwise each datum
+    /// goes through the loop at least once;
+    pre_check: bool,
+    emit: EmitKind,
+}
+
+impl<D: 'static> Default for LoopCondition<D> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<D: 'static> LoopCondition<D> {
     pub fn new() -> Self {
-        LoopCondition { max_iters: !0u32, until: None }
+        LoopCondition { max_iters: !0u32, until: None, pre_check: true, emit: EmitKind::Null }
     }
 
     pub fn max_iters(max_iters: u32) -> Self {
-        LoopCondition { max_iters, until: None }
+        LoopCondition { max_iters, until: None, pre_check: true, emit: EmitKind::Null }
     }
 
     pub fn until(&mut self, func: Box<dyn FilterFunction<D>>) {
         self.until = Some(func);
     }
 
+    pub fn set_pre_check(&mut self, pre_check: bool) {
+        self.pre_check = pre_check;
+    }
+
+    pub fn set_emit(&mut self, emit: EmitKind) {
+        self.emit = emit;
+    }
+
+    #[inline]
+    pub fn is_pre_check(&self) -> bool {
+        self.pre_check
+    }
+
+    #[inline]
+    pub fn get_emit(&self) -> EmitKind {
+        self.emit
+    }
+
     #[inline]
     pub fn is_converge(&self, data: &D) -> FnResult<bool> {
         if let Some(cond) = self.until.as_ref() {
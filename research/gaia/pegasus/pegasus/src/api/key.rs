@@ -0,0 +1,263 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The keys selected from the data to exchange them by, which are hashed identically across
+//! platforms, processes and versions, so that the data of the same key go to the same worker in
+//! all exchanges of a job of the same peers, e.g. the two inputs of a join, without packing and
+//! hashing the fields by hand. The keys of multiple fields are given as tuples, e.g.
+//! `keys!(|e: &Edge| (e.src, e.dst))`, while a user struct implements `StableKey` by writing its
+//! fields one by one.
+
+use crate::api::function::{FnResult, RouteFunction};
+use std::marker::PhantomData;
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// The FNV-1a hasher of the bytes written, whose hash is mixed by the finalizer of SplitMix64,
+/// as the workers are picked by the low bits of the hashes;
+#[derive(Copy, Clone, Debug)]
+pub struct StableHasher {
+    state: u64,
+}
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        StableHasher { state: FNV_OFFSET }
+    }
+}
+
+impl StableHasher {
+    pub fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.state ^= *b as u64;
+            self.state = self.state.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    /// Write the integer in little endian, whatever the endian of the platform;
+    pub fn write_u64(&mut self, value: u64) {
+        self.write(&value.to_le_bytes());
+    }
+
+    pub fn finish(&self) -> u64 {
+        let mut h = self.state;
+        h = (h ^ (h >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        h = (h ^ (h >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        h ^ (h >> 31)
+    }
+}
+
+/// The keys hashed identically across platforms, processes and versions, where the integers of
+/// the same value are hashed the same whatever their widths, as are `String` and `&str`; The
+/// floats are not keys, as the same number may be of different bits, e.g. `0.0` and `-0.0`;
+pub trait StableKey {
+    fn write_stable(&self, hasher: &mut StableHasher);
+
+    fn stable_hash(&self) -> u64 {
+        let mut hasher = StableHasher::default();
+        self.write_stable(&mut hasher);
+        hasher.finish()
+    }
+}
+
+macro_rules! impl_stable_unsigned {
+    ($($ty: ty),*) => {
+        $(
+            impl StableKey for $ty {
+                fn write_stable(&self, hasher: &mut StableHasher) {
+                    hasher.write_u64(*self as u64);
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! impl_stable_signed {
+    ($($ty: ty),*) => {
+        $(
+            impl StableKey for $ty {
+                fn write_stable(&self, hasher: &mut StableHasher) {
+                    hasher.write_u64(*self as i64 as u64);
+                }
+            }
+        )*
+    };
+}
+
+impl_stable_unsigned!(u8, u16, u32, u64, usize);
+impl_stable_signed!(i8, i16, i32, i64, isize);
+
+impl StableKey for u128 {
+    fn write_stable(&self, hasher: &mut StableHasher) {
+        hasher.write(&self.to_le_bytes());
+    }
+}
+
+impl StableKey for i128 {
+    fn write_stable(&self, hasher: &mut StableHasher) {
+        hasher.write(&self.to_le_bytes());
+    }
+}
+
+impl StableKey for bool {
+    fn write_stable(&self, hasher: &mut StableHasher) {
+        hasher.write(&[*self as u8]);
+    }
+}
+
+impl StableKey for char {
+    fn write_stable(&self, hasher: &mut StableHasher) {
+        hasher.write_u64(*self as u64);
+    }
+}
+
+// the length goes first, so the fields of a tuple never run into each other
+impl StableKey for str {
+    fn write_stable(&self, hasher: &mut StableHasher) {
+        hasher.write_u64(self.len() as u64);
+        hasher.write(self.as_bytes());
+    }
+}
+
+impl StableKey for String {
+    fn write_stable(&self, hasher: &mut StableHasher) {
+        self.as_str().write_stable(hasher);
+    }
+}
+
+impl<T: StableKey> StableKey for [T] {
+    fn write_stable(&self, hasher: &mut StableHasher) {
+        hasher.write_u64(self.len() as u64);
+        for item in self {
+            item.write_stable(hasher);
+        }
+    }
+}
+
+impl<T: StableKey> StableKey for Vec<T> {
+    fn write_stable(&self, hasher: &mut StableHasher) {
+        self.as_slice().write_stable(hasher);
+    }
+}
+
+impl<T: StableKey> StableKey for Option<T> {
+    fn write_stable(&self, hasher: &mut StableHasher) {
+        match self {
+            Some(v) => {
+                hasher.write(&[1]);
+                v.write_stable(hasher);
+            }
+            None => hasher.write(&[0]),
+        }
+    }
+}
+
+impl<T: StableKey + ?Sized> StableKey for &T {
+    fn write_stable(&self, hasher: &mut StableHasher) {
+        (**self).write_stable(hasher);
+    }
+}
+
+impl<T: StableKey + ?Sized> StableKey for Box<T> {
+    fn write_stable(&self, hasher: &mut StableHasher) {
+        (**self).write_stable(hasher);
+    }
+}
+
+macro_rules! impl_stable_tuple {
+    ($($name: ident),*) => {
+        #[allow(non_snake_case)]
+        impl<$($name: StableKey),*> StableKey for ($($name,)*) {
+            fn write_stable(&self, hasher: &mut StableHasher) {
+                let ($($name,)*) = self;
+                $($name.write_stable(hasher);)*
+            }
+        }
+    };
+}
+
+impl_stable_tuple!(A);
+impl_stable_tuple!(A, B);
+impl_stable_tuple!(A, B, C);
+impl_stable_tuple!(A, B, C, D);
+impl_stable_tuple!(A, B, C, D, E);
+impl_stable_tuple!(A, B, C, D, E, F);
+
+/// Route the data by the stable hashes of the keys selected by `func`, built by `keys!`, which
+/// sends the same key to the same worker in all exchanges of a job of the same peers;
+pub struct KeySelector<D, K, F: Fn(&D) -> K> {
+    func: F,
+    _ph: PhantomData<fn(&D) -> K>,
+}
+
+impl<D, K, F: Fn(&D) -> K> KeySelector<D, K, F> {
+    pub fn new(func: F) -> Self {
+        KeySelector { func, _ph: PhantomData }
+    }
+
+    /// The type of the keys, which tells if two exchanges are co-partitioned;
+    pub fn key_type(&self) -> &'static str {
+        std::any::type_name::<K>()
+    }
+}
+
+impl<D, K, F> RouteFunction<D> for KeySelector<D, K, F>
+where
+    D: 'static,
+    K: StableKey + 'static,
+    F: Fn(&D) -> K + Send + 'static,
+{
+    fn route(&self, data: &D) -> FnResult<u64> {
+        Ok((self.func)(data).stable_hash())
+    }
+}
+
+/// Select the keys to exchange the data by, e.g. `keys!(|d: &Item| (d.label, d.bucket))`;
+#[macro_export]
+macro_rules! keys {
+    ($func: expr) => {
+        $crate::api::key::KeySelector::new($func)
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn stable_hash_test() {
+        // the hashes must never change, or the data are routed differently among versions
+        assert_eq!(7u32.stable_hash(), 0xae25_3598_b337_821e);
+        assert_eq!("marko".stable_hash(), 0xb97b_1742_9b37_af1c);
+        assert_eq!((1u32, "a").stable_hash(), 0xb8da_88a5_f246_48ae);
+        // the same whatever the widths of the integers, or the owned strings
+        assert_eq!(7u8.stable_hash(), 7u64.stable_hash());
+        assert_eq!((-7i32).stable_hash(), (-7i64).stable_hash());
+        assert_eq!("marko".to_owned().stable_hash(), "marko".stable_hash());
+        // the fields never run into each other
+        assert_ne!(("ab", "c").stable_hash(), ("a", "bc").stable_hash());
+        assert_ne!(Some(0u32).stable_hash(), None::<u32>.stable_hash());
+    }
+
+    #[test]
+    fn key_selector_test() {
+        let keys = keys!(|d: &(u32, u32, String)| (d.0, d.1));
+        let route = keys.route(&(1, 2, "x".to_owned())).unwrap();
+        assert_eq!(route, (1u32, 2u32).stable_hash());
+        assert_eq!(route, keys.route(&(1, 2, "y".to_owned())).unwrap());
+        assert_eq!(keys.key_type(), "(u32, u32)");
+    }
+}
@@ -14,8 +14,11 @@
 //! limitations under the License.
 
 use crate::communication::output::OutputDelta;
-use crate::{JobConf, Tag, WorkerId};
+use crate::rng::JobRng;
+use crate::schedule::TimeSlice;
+use crate::{JobConf, LatencyMode, Tag, WorkerId};
 use std::sync::Arc;
+use std::time::Duration;
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum OperatorKind {
@@ -54,10 +57,28 @@ pub struct OperatorMeta {
     pub(crate) delta: OutputDelta,
     pub(crate) batch_size: usize,
     pub(crate) capacity: usize,
+    pub(crate) flush_interval: Option<Duration>,
+    pub(crate) latency_mode: LatencyMode,
     pub(crate) mem_limit: u32,
     pub(crate) kind: OperatorKind,
     pub(crate) notifiable: bool,
     pub(crate) scope_order: ScopePrior,
+    pub(crate) profile: bool,
+    pub(crate) slice: TimeSlice,
+    // whether the outputs go back to the head of an iteration, closing the cycle of the loop
+    pub(crate) feedback: bool,
+    // whether the operator enters the scopes by `enter_scope`, which must be left on all paths
+    pub(crate) scope_entry: bool,
+    // whether the operator is asked for the scopes to cancel in each step, see `enable_tick`
+    pub(crate) ticked: bool,
+    // the depths of the scopes of the loops the operator is in, outermost first, whose rounds are
+    // told to the functions fired on the operator, see `current_iteration`
+    pub(crate) loop_depths: Vec<usize>,
+    // the outputs the operator expects of each input, which bounds the inputs it takes in each
+    // firing by the capacity of its outputs, see `set_fanout`
+    pub(crate) fanout: Option<f64>,
+    // the seed of the job, see `JobConf::rng_seed`
+    pub(crate) rng_seed: u64,
 }
 
 impl std::fmt::Debug for OperatorMeta {
@@ -73,13 +94,23 @@ impl OperatorMeta {
             name: name.to_owned(),
             index: 0,
             delta: OutputDelta::None,
-            batch_size: conf.batch_size as usize,
+            batch_size: conf.effective_batch_size() as usize,
             capacity: conf.output_capacity as usize,
+            flush_interval: conf.flush_interval(),
+            latency_mode: conf.latency_mode,
             scope_depth: 0,
             mem_limit: conf.memory_limit,
             kind: OperatorKind::Unknown,
             notifiable: false,
             scope_order: ScopePrior::None,
+            profile: conf.profile,
+            slice: TimeSlice::new(conf.slice_records, conf.slice_us),
+            feedback: false,
+            scope_entry: false,
+            ticked: false,
+            loop_depths: vec![],
+            fanout: None,
+            rng_seed: conf.rng_seed.unwrap_or(0),
         }
     }
 
@@ -103,6 +134,13 @@ impl OperatorMeta {
         self
     }
 
+    /// Ask the operator for the scopes to cancel in each step of the scheduler, even if it is not
+    /// fired, see `OperatorCore::on_tick`;
+    pub fn enable_tick(&mut self) -> &mut Self {
+        self.ticked = true;
+        self
+    }
+
     pub fn set_scope_order(&mut self, order: ScopePrior) -> &mut Self {
         self.scope_order = order;
         self
@@ -111,4 +149,31 @@ impl OperatorMeta {
     pub fn set_kind(&mut self, kind: OperatorKind) {
         self.kind = kind;
     }
+
+    /// Declare the outputs the operator expects of each input, e.g. 8 of a flat map expanding each
+    /// input into 8 outputs, so that it takes in each firing only the inputs whose outputs its
+    /// outputs have the capacity for, instead of overshooting the capacity;
+    pub fn set_fanout(&mut self, fanout: f64) -> &mut Self {
+        self.fanout = Some(fanout);
+        self
+    }
+
+    /// The stream of the random numbers of the operator in this worker, split of the seed of the
+    /// job by the index of the worker and the index of the operator, which is the same in all runs
+    /// of the job with the same seed, see `JobConf::rng_seed`;
+    pub fn rng(&self) -> JobRng {
+        JobRng::of_operator(self.rng_seed, self.worker_id.index, self.index)
+    }
+
+    /// Mark the operator as the feedback of an iteration, whose outputs are the only ones allowed
+    /// to close a cycle in the dataflow;
+    pub(crate) fn set_feedback(&mut self) {
+        self.feedback = true;
+    }
+
+    /// Mark the operator as entering the scopes by `enter_scope`, whose streams must be left by
+    /// `leave_scope` on all the paths to the sinks;
+    pub(crate) fn set_scope_entry(&mut self) {
+        self.scope_entry = true;
+    }
 }
@@ -17,6 +17,8 @@ pub(crate) mod concise;
 #[macro_use]
 pub mod function;
 pub(crate) mod iteration;
+#[macro_use]
+pub mod key;
 pub mod meta;
 pub(crate) mod multiplex;
 pub mod notify;
@@ -24,20 +26,29 @@ pub(crate) mod primitive;
 pub(crate) mod scope;
 pub mod state;
 
-pub use concise::dedup::Dedup;
+pub use concise::adapt::{Adapt, Decision};
+pub use concise::dedup::{ApproxDedup, BloomFilter, Dedup};
 pub use concise::exchange::Exchange;
 pub use concise::filter::Filter;
 pub use concise::fold::Fold;
-pub use concise::map::Map;
+pub use concise::map::{Map, Rejected, RetryPolicy};
+pub use concise::merge::Merge;
 pub use concise::reduce::*;
-pub use iteration::{Iteration, LoopCondition};
-pub use multiplex::subtask::{SubTask, SubtaskResult};
+pub use concise::sample::Sample;
+pub use concise::time_limit::TimeLimit;
+pub use iteration::{
+    current_iteration, current_iterations, EmitKind, Iteration, LoopCondition, LoopRound,
+};
+pub use multiplex::subtask::{SemiJoinKind, SubTask, SubtaskResult};
 pub use multiplex::Multiplexing;
 pub use primitive::binary::{Binary, BinaryInput, BinaryNotification, BinaryNotify, BinaryState};
 pub use primitive::branch::{Branch, Condition, IntoBranch};
 pub use primitive::sink::{ResultSet, Sink};
-pub use primitive::source::{ExternSource, FromStream, IntoStream, NonBlockReceiver};
+pub use primitive::source::{
+    DataSource, ExternSource, FileLines, FromStream, IntoStream, NonBlockReceiver, SourceConfig,
+};
 pub use primitive::unary::{LazyUnary, Unary, UnaryNotify, UnaryState};
 pub use scope::enter::complete;
-pub use scope::enter::{EnterScope, ScopeInput, ScopeInputEmitter};
+pub use scope::enter::{EnterScope, ScopeInput, ScopeInputEmitter, ScopePartition};
 pub use scope::leave::LeaveScope;
+pub use scope::window::{Sliding, Stamped, TimeWindow, Trigger, Tumbling, Window, WindowAssigner};
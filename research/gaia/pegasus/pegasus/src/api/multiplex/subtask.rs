@@ -21,6 +21,15 @@ use crate::{Data, JobConf};
 use pegasus_common::codec::*;
 use std::fmt::Debug;
 
+/// How the parents are filtered by the results of their subtasks in `semi_join_subtask`
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SemiJoinKind {
+    /// Keep the parents whose subtasks output any result, e.g. `where()` in Gremlin;
+    AnyExists,
+    /// Keep the parents whose subtasks output no result, e.g. `not()` in Gremlin;
+    NoneExists,
+}
+
 pub struct SubtaskResult<T> {
     pub seq: u32,
     result: ResultSet<T>,
@@ -46,6 +55,40 @@ pub trait SubTask<D: Data> {
         T: Data,
         R: Data,
         F: Fn(&D, T) -> Option<R> + Send + 'static;
+
+    /// Join each parent with the first result of its subtask, or with `None` if its subtask outputs
+    /// no result, so that no parent is dropped or repeated for its subtask, e.g. the columns of
+    /// `project()` in Gremlin;
+    fn join_first_subtask<T, R, F>(
+        &self, subtask: Stream<SubtaskResult<T>>, func: F,
+    ) -> Result<Stream<R>, BuildJobError>
+    where
+        T: Data,
+        R: Data,
+        F: Fn(&D, Option<T>) -> Option<R> + Send + 'static;
+
+    /// Keep the parents following whether their subtasks output any result or not, which
+    /// doesn't care about what the results are;
+    fn semi_join_subtask<T>(
+        &self, subtask: Stream<SubtaskResult<T>>, kind: SemiJoinKind,
+    ) -> Result<Stream<D>, BuildJobError>
+    where
+        T: Data;
+
+    /// Fork a subtask for each parent, which emits the results of its subtask if any, or the parent
+    /// itself otherwise, e.g. `optional()` in Gremlin;
+    fn optional_subtask<F>(&self, func: F) -> Result<Stream<D>, BuildJobError>
+    where
+        F: FnOnce(Stream<D>) -> Result<Stream<D>, BuildJobError> + Send;
+
+    /// Fork the subtasks of the branches in order, where a parent is forked into the subtask of a
+    /// branch only if its subtasks of all the branches before output no result, e.g. `coalesce()`
+    /// in Gremlin; Each parent emits the results of the first branch whose subtask outputs any,
+    /// tagged with the index of the branch, or nothing if no branch does;
+    fn coalesce_subtasks<F, T>(&self, branches: Vec<F>) -> Result<Stream<(u32, T)>, BuildJobError>
+    where
+        T: Data,
+        F: FnOnce(Stream<D>) -> Result<Stream<T>, BuildJobError> + Send;
 }
 
 impl<T: Data> Encode for SubtaskResult<T> {
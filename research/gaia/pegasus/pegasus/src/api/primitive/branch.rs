@@ -17,6 +17,7 @@ use crate::errors::BuildJobError;
 use crate::stream::Stream;
 use crate::Data;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Branch {
     Left,
     Right,
@@ -30,6 +31,13 @@ pub trait IntoBranch<D: Data> {
     fn branch<F: Condition<D> + 'static>(
         &self, name: &str, func: F,
     ) -> Result<(Stream<D>, Stream<D>), BuildJobError>;
+
+    /// Split the stream into the data satisfying the predicate and the others, by a single
+    /// operator evaluating the predicate once for each datum, where both streams carry the same
+    /// scopes and end each scope even if no data of the scope goes to them;
+    fn branch_by<F>(&self, pred: F) -> Result<(Stream<D>, Stream<D>), BuildJobError>
+    where
+        F: Fn(&D) -> bool + Send + 'static;
 }
 
 impl<D, F> Condition<D> for F
@@ -19,16 +19,50 @@ use crate::errors::BuildJobError;
 use crate::{Data, Tag};
 use std::io;
 
+/// The results delivered to the sink, along with the tag of their scope;
 pub enum ResultSet<D> {
     Data(Vec<D>),
+    /// The scope of the tag has ended on current worker, which is only delivered by the sinks
+    /// inside scopes, e.g. by `scope_by`; The results of the scope may still be delivered by the
+    /// sinks of other workers, and the scope ends globally once all workers delivered its end;
+    ScopeEnd(Tag),
+    /// The stream into the sink has ended;
     End,
 }
 
+/// Deliver the results of a stream to the function built by `construct` on each worker; A dataflow
+/// may have several sinks, e.g. one on each branch, or more than one on the same stream, each of
+/// which receives a copy of the stream and is told of its own end by `ResultSet::End`, while the
+/// job completes once all of them end; A dataflow without any sink fails to be built;
 pub trait Sink<D: Data> {
     fn sink_by<B, F>(&self, construct: B) -> Result<(), BuildJobError>
     where
         B: FnOnce(&OperatorMeta) -> F,
         F: Fn(&Tag, ResultSet<D>) + Send + 'static;
+
+    /// Deliver the results transformed by `map` to the function built by `construct` as `sink_by`
+    /// does, where `map` runs on the worker before the results leave it, e.g. to project each
+    /// result to the fields the client needs, or to serialize it into the wire format of the
+    /// client, so that the full results are never shipped;
+    fn sink_map_by<T, M, B, F>(&self, map: M, construct: B) -> Result<(), BuildJobError>
+    where
+        T: Send + 'static,
+        M: Fn(D) -> T + Send + 'static,
+        B: FnOnce(&OperatorMeta) -> F,
+        F: Fn(&Tag, ResultSet<T>) + Send + 'static,
+    {
+        self.sink_by(|meta| {
+            let func = construct(meta);
+            move |tag: &Tag, result: ResultSet<D>| {
+                let result = match result {
+                    ResultSet::Data(data) => ResultSet::Data(data.into_iter().map(&map).collect()),
+                    ResultSet::ScopeEnd(tag) => ResultSet::ScopeEnd(tag),
+                    ResultSet::End => ResultSet::End,
+                };
+                func(tag, result)
+            }
+        })
+    }
 }
 
 impl<D: Encode> Encode for ResultSet<D> {
@@ -41,6 +75,10 @@ impl<D: Encode> Encode for ResultSet<D> {
             ResultSet::End => {
                 writer.write_u8(1)?;
             }
+            ResultSet::ScopeEnd(tag) => {
+                writer.write_u8(2)?;
+                tag.write_to(writer)?;
+            }
         }
         Ok(())
     }
@@ -55,6 +93,10 @@ impl<D: Decode> Decode for ResultSet<D> {
                 Ok(ResultSet::Data(v))
             }
             1 => Ok(ResultSet::End),
+            2 => {
+                let tag = Tag::read_from(reader)?;
+                Ok(ResultSet::ScopeEnd(tag))
+            }
             _ => Err(io::Error::new(io::ErrorKind::Other, "unreachable")),
         }
     }
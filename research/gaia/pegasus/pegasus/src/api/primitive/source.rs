@@ -18,6 +18,9 @@ use crate::errors::{BuildJobError, IOError};
 use crate::stream::Stream;
 use crate::Data;
 use crossbeam_channel::{Receiver, TryRecvError};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
 
 pub trait IntoStream<D: Data> {
     fn into_stream(self, dfb: &DataflowBuilder) -> Result<Stream<D>, BuildJobError>;
@@ -31,6 +34,12 @@ pub trait ExternSource: Send {
     type Item;
 
     fn pull_next(&mut self) -> Result<Option<Self::Item>, IOError>;
+
+    /// The records the source expects to produce on current worker, told once the source is
+    /// built, to tell the progress of the job, see `pegasus::peek_progress`; `None` if unknown;
+    fn expected_size(&self) -> Option<u64> {
+        None
+    }
 }
 
 pub struct NonBlockReceiver<T> {
@@ -54,3 +63,72 @@ impl<T: Send> ExternSource for NonBlockReceiver<T> {
         }
     }
 }
+
+/// A source pulled in batches on demand, e.g. backed by a file or a socket, see
+/// `DataflowBuilder::input_from_source`; It is asked for a batch only if the output of the source
+/// has the capacity, i.e. the consumers have taken the batches pulled before, so the source
+/// never races ahead of them;
+pub trait DataSource: Send {
+    type Item;
+
+    /// Pull the next batch of at most `max` records, where an empty batch tells that nothing is
+    /// available for now, to be pulled again later, and `None` ends the input;
+    fn next_batch(&mut self, max: usize) -> Result<Option<Vec<Self::Item>>, IOError>;
+
+    /// The records the source expects to produce on current worker, as
+    /// `ExternSource::expected_size`;
+    fn expected_size(&self) -> Option<u64> {
+        None
+    }
+}
+
+/// The configuration of a source pulled by `DataflowBuilder::input_from_source_with`;
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SourceConfig {
+    /// the most records pulled each second on each worker, or 0 if unlimited;
+    pub records_per_second: u64,
+}
+
+impl SourceConfig {
+    pub fn rate_limit(records_per_second: u64) -> Self {
+        SourceConfig { records_per_second }
+    }
+}
+
+/// The lines of a file as a source, without the line endings;
+pub struct FileLines {
+    reader: BufReader<File>,
+}
+
+impl FileLines {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, IOError> {
+        let file = File::open(path)?;
+        Ok(FileLines { reader: BufReader::new(file) })
+    }
+}
+
+impl DataSource for FileLines {
+    type Item = String;
+
+    fn next_batch(&mut self, max: usize) -> Result<Option<Vec<String>>, IOError> {
+        let mut lines = Vec::with_capacity(max);
+        while lines.len() < max {
+            let mut line = String::new();
+            if self.reader.read_line(&mut line)? == 0 {
+                break;
+            }
+            if line.ends_with('\n') {
+                line.pop();
+                if line.ends_with('\r') {
+                    line.pop();
+                }
+            }
+            lines.push(line);
+        }
+        if lines.is_empty() && max > 0 {
+            Ok(None)
+        } else {
+            Ok(Some(lines))
+        }
+    }
+}
@@ -39,6 +39,29 @@ impl<D> ScopeInput<D> {
     }
 }
 
+/// The partition, i.e. the child scope, a datum enters by [`enter_scope`];
+///
+/// [`enter_scope`]: trait.EnterScope.html#tymethod.enter_scope
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ScopePartition {
+    /// The id of the partition among those of the same parent scope;
+    pub id: u32,
+    /// Indicates if the datum is the last one of the partition on current worker, which closes it;
+    pub closes: bool,
+}
+
+impl ScopePartition {
+    /// The datum enters the partition, which is still open for the data after it;
+    pub fn of(id: u32) -> Self {
+        ScopePartition { id, closes: false }
+    }
+
+    /// The datum is the last one to enter the partition, which is closed after it;
+    pub fn last(id: u32) -> Self {
+        ScopePartition { id, closes: true }
+    }
+}
+
 pub trait ScopeInputEmitter<D: Send>: Send + Clone {
     /// If a `ScopeInputEmitter` has no other peers(return `false`), it means that all the scopes
     /// this emitter emit data to are only visible to this emitter, these scopes' open or close states
@@ -84,6 +107,28 @@ pub trait EnterScope<D: Data> {
     where
         B: FnOnce(&OperatorMeta) -> F,
         F: ScopeInputEmitter<D> + 'static;
+
+    /// Enter the data into the child scopes partitioned by `func`, e.g. the windows of a batch or
+    /// the groups of a key, so that the operators after it process each partition in its own
+    /// scope, as the body of an iteration or a subtask does; A datum is dropped if `func` returns
+    /// `None` for it, and the data are tagged one level deeper in the scope, with the id of the
+    /// partition as the innermost, as those in an iteration or a subtask are;
+    ///
+    /// The scopes entered follow the invariants below:
+    /// * the end of a partition is generated once it is closed by [`ScopePartition::last`] on a
+    ///   worker, or once its parent scope ends if it is never closed;
+    /// * a datum entering a partition already closed fails the job by an error of kind
+    ///   [`IllegalScopeInput`];
+    /// * every stream in the scope must be left by [`leave_scope`] on all the paths to the sinks,
+    ///   or the job fails to be built, naming the operator which consumes a stream still in it;
+    ///
+    /// [`ScopePartition::last`]: struct.ScopePartition.html#method.last
+    /// [`IllegalScopeInput`]: ../errors/enum.ErrorKind.html
+    /// [`leave_scope`]: trait.LeaveScope.html#tymethod.leave_scope
+    ///
+    fn enter_scope<F>(&self, func: F) -> Result<Stream<D>, BuildJobError>
+    where
+        F: FnMut(&D) -> Option<ScopePartition> + Send + Clone + 'static;
 }
 
 thread_local! {
@@ -23,4 +23,9 @@ pub trait LeaveScope<D: Data> {
     fn leave(&self) -> Result<Stream<D>, BuildJobError>;
 
     fn owned_leave(self) -> Result<Stream<D>, BuildJobError>;
+
+    /// Leave the scope entered by `enter_scope` back to its parent scope, where the data of all
+    /// its partitions are merged; It fails to be built in any other scope, e.g. the body of an
+    /// iteration or a subtask, which is left by the operator entering it;
+    fn leave_scope(&self) -> Result<Stream<D>, BuildJobError>;
 }
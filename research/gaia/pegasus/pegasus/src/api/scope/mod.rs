@@ -15,3 +15,4 @@
 
 pub mod enter;
 pub mod leave;
+pub mod window;
@@ -0,0 +1,192 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use crate::api::function::RouteFunction;
+use crate::codec::{Decode, Encode, ReadExt, WriteExt};
+use crate::errors::BuildJobError;
+use crate::stream::Stream;
+use crate::Data;
+use std::io;
+
+/// An item of a stream in event time, i.e. a record or a watermark, which is windowed by
+/// [`window`];
+///
+/// [`window`]: trait.Window.html#tymethod.window
+#[derive(Clone, Debug, PartialEq)]
+pub enum Stamped<D> {
+    /// A record of the event time;
+    Record(u64, D),
+    /// No record of an event time earlier than it follows from the producer of the watermark;
+    Watermark(u64),
+}
+
+impl<D: Encode> Encode for Stamped<D> {
+    fn write_to<W: WriteExt>(&self, writer: &mut W) -> io::Result<()> {
+        match self {
+            Stamped::Record(ts, data) => {
+                writer.write_u8(0)?;
+                writer.write_u64(*ts)?;
+                data.write_to(writer)
+            }
+            Stamped::Watermark(ts) => {
+                writer.write_u8(1)?;
+                writer.write_u64(*ts)
+            }
+        }
+    }
+}
+
+impl<D: Decode> Decode for Stamped<D> {
+    fn read_from<R: ReadExt>(reader: &mut R) -> io::Result<Self> {
+        match reader.read_u8()? {
+            0 => {
+                let ts = reader.read_u64()?;
+                Ok(Stamped::Record(ts, D::read_from(reader)?))
+            }
+            1 => Ok(Stamped::Watermark(reader.read_u64()?)),
+            _ => Err(io::Error::other("unreachable")),
+        }
+    }
+}
+
+/// A window of the event times in `[start, end)`, whose records enter the scope of `id`;
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct TimeWindow {
+    pub id: u32,
+    pub start: u64,
+    pub end: u64,
+}
+
+/// Assign the records to the windows by their event times;
+pub trait WindowAssigner: Send + Clone + 'static {
+    /// The windows a record of the event time falls into, each of which gets a copy of it;
+    fn assign(&self, ts: u64) -> Vec<TimeWindow>;
+}
+
+/// The windows of `size` one after another, i.e. `[k * size, (k + 1) * size)` of id `k`;
+#[derive(Copy, Clone, Debug)]
+pub struct Tumbling {
+    pub size: u64,
+}
+
+impl WindowAssigner for Tumbling {
+    fn assign(&self, ts: u64) -> Vec<TimeWindow> {
+        let size = self.size.max(1);
+        let id = ts / size;
+        vec![TimeWindow { id: id as u32, start: id * size, end: (id + 1) * size }]
+    }
+}
+
+/// The windows of `size` starting every `slide`, i.e. `[k * slide, k * slide + size)` of id `k`,
+/// which overlap if `slide` is less than `size`;
+#[derive(Copy, Clone, Debug)]
+pub struct Sliding {
+    pub size: u64,
+    pub slide: u64,
+}
+
+impl WindowAssigner for Sliding {
+    fn assign(&self, ts: u64) -> Vec<TimeWindow> {
+        let slide = self.slide.max(1);
+        let first = if ts >= self.size { (ts - self.size) / slide + 1 } else { 0 };
+        (first..=ts / slide)
+            .map(|id| TimeWindow { id: id as u32, start: id * slide, end: id * slide + self.size })
+            .collect()
+    }
+}
+
+/// When a window emits its records and closes;
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Trigger {
+    /// once the watermark passes the end of the window;
+    Watermark,
+    /// once the watermark passes the end of the window by the lateness, so that the records
+    /// later than the watermark by no more than it still fall into the window;
+    Lateness(u64),
+}
+
+impl Trigger {
+    /// Check if the window of the end has fired as of the watermark;
+    pub fn fires(&self, end: u64, watermark: u64) -> bool {
+        match self {
+            Trigger::Watermark => watermark >= end,
+            Trigger::Lateness(lateness) => watermark >= end.saturating_add(*lateness),
+        }
+    }
+}
+
+pub trait Window<D: Data> {
+    /// Bucket the records into the windows of their event times by `assigner`, each of which is
+    /// a child scope as entered by [`enter_scope`], with the id of the window as that of the
+    /// partition; The records of a window are emitted into its scope all at once as `trigger`
+    /// fires on the watermark, after which the scope is closed, so that the operators after it,
+    /// e.g. a fold, give the aggregate of each window once; The streams in the windows must be
+    /// left by [`leave_scope`] as those entered by [`enter_scope`];
+    ///
+    /// The windows follow the invariants below:
+    /// * the records and the watermarks of each worker stay on it, which are windowed by the
+    ///   watermarks of the worker alone, see [`window_by`] for the records of all the workers;
+    /// * the watermarks of each producer only go forward, i.e. a watermark below the last one of
+    ///   the producer is ignored;
+    /// * the watermarks cross the exchanges in the windows as the ends of their scopes, i.e. a
+    ///   window closes after an exchange once it is closed on all the producers, as of the least
+    ///   of their watermarks;
+    /// * a record falling into a window already fired is late, and dropped;
+    /// * the windows still open once the input ends are emitted and closed in the order of their
+    ///   ends;
+    ///
+    /// [`enter_scope`]: trait.EnterScope.html#tymethod.enter_scope
+    /// [`leave_scope`]: trait.LeaveScope.html#tymethod.leave_scope
+    /// [`window_by`]: trait.Window.html#tymethod.window_by
+    ///
+    fn window<A: WindowAssigner>(
+        &self, assigner: A, trigger: Trigger,
+    ) -> Result<Stream<D>, BuildJobError>;
+
+    /// Bucket the records into the windows as [`window`] does, where the records are exchanged
+    /// to the workers by `route` ahead, while the watermarks go to all the workers; The watermark
+    /// of each worker is that of each of the producers, i.e. all the workers, tracked on its own,
+    /// and the windows fire as the least of them passes their ends, so that a window never fires
+    /// ahead of the records of a slower producer;
+    ///
+    /// [`window`]: trait.Window.html#tymethod.window
+    ///
+    fn window_by<A: WindowAssigner, R: RouteFunction<D>>(
+        &self, route: R, assigner: A, trigger: Trigger,
+    ) -> Result<Stream<D>, BuildJobError>;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn ids(windows: Vec<TimeWindow>) -> Vec<u32> {
+        windows.into_iter().map(|w| w.id).collect()
+    }
+
+    #[test]
+    fn assign_windows_test() {
+        let tumbling = Tumbling { size: 10 };
+        assert_eq!(tumbling.assign(0), vec![TimeWindow { id: 0, start: 0, end: 10 }]);
+        assert_eq!(tumbling.assign(25), vec![TimeWindow { id: 2, start: 20, end: 30 }]);
+        let sliding = Sliding { size: 10, slide: 5 };
+        assert_eq!(ids(sliding.assign(3)), vec![0]);
+        assert_eq!(ids(sliding.assign(12)), vec![1, 2]);
+        assert_eq!(sliding.assign(10)[0], TimeWindow { id: 1, start: 5, end: 15 });
+        assert!(Trigger::Watermark.fires(10, 10));
+        assert!(!Trigger::Lateness(5).fires(10, 14));
+        assert!(Trigger::Lateness(5).fires(10, 15));
+    }
+}
@@ -15,8 +15,10 @@
 
 use super::meta::OperatorMeta;
 use crate::Tag;
+use pegasus_common::codec::*;
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
+use std::hash::Hash;
 
 pub trait State: Send + Default + 'static {}
 
@@ -64,6 +66,96 @@ impl<S: State> Default for OperatorState<S> {
     }
 }
 
+/// The keyed state of an operator in one scope, used as the state of `unary_with_state`, which
+/// is created on the first data of the scope, and handed to `on_notify` once the scope ends.
+/// The memory it holds is counted against the memory limit of the job by the operator;
+///
+/// It can be checkpointed by encoding, if both the keys and values can be encoded;
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScopedState<K: Eq + Hash, V> {
+    map: HashMap<K, V>,
+}
+
+impl<K: Eq + Hash, V> Default for ScopedState<K, V> {
+    fn default() -> Self {
+        ScopedState { map: HashMap::new() }
+    }
+}
+
+impl<K: Eq + Hash, V> ScopedState<K, V> {
+    #[inline]
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.map.get(key)
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.map.get_mut(key)
+    }
+
+    /// Get the value of the key, or insert the one created by `default` if the key is absent;
+    #[inline]
+    pub fn get_or_insert_with<F: FnOnce() -> V>(&mut self, key: K, default: F) -> &mut V {
+        self.map.entry(key).or_insert_with(default)
+    }
+
+    #[inline]
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.map.insert(key, value)
+    }
+
+    #[inline]
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.map.remove(key)
+    }
+
+    #[inline]
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.map.iter()
+    }
+
+    #[inline]
+    pub fn into_map(self) -> HashMap<K, V> {
+        self.map
+    }
+}
+
+impl<K: Eq + Hash, V> IntoIterator for ScopedState<K, V> {
+    type Item = (K, V);
+    type IntoIter = std::collections::hash_map::IntoIter<K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.map.into_iter()
+    }
+}
+
+impl<K: Encode + Eq + Hash, V: Encode> Encode for ScopedState<K, V> {
+    fn write_to<W: WriteExt>(&self, writer: &mut W) -> std::io::Result<()> {
+        self.map.write_to(writer)
+    }
+}
+
+impl<K: Decode + Eq + Hash, V: Decode> Decode for ScopedState<K, V> {
+    fn read_from<R: ReadExt>(reader: &mut R) -> std::io::Result<Self> {
+        let map = <HashMap<K, V>>::read_from(reader)?;
+        Ok(ScopedState { map })
+    }
+}
+
 pub struct StateMap<V> {
     scope_depth: usize,
     map: HashMap<Tag, Option<V>>,
@@ -159,3 +251,29 @@ impl<'a, V> WrapEntry<'a, V> {
         self.inner.or_insert_with(|| Some(default())).as_mut().unwrap()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn scoped_state_test() {
+        let mut state = ScopedState::<u32, Vec<u32>>::default();
+        state.get_or_insert_with(1, Vec::new).push(10);
+        state.get_or_insert_with(1, Vec::new).push(11);
+        state.insert(2, vec![20]);
+        assert_eq!(state.len(), 2);
+        assert_eq!(state.get(&1), Some(&vec![10, 11]));
+
+        let mut bytes = vec![];
+        state.write_to(&mut bytes).unwrap();
+        let restored = ScopedState::<u32, Vec<u32>>::read_from(&mut bytes.as_slice()).unwrap();
+        assert_eq!(restored, state);
+
+        assert_eq!(state.remove(&2), Some(vec![20]));
+        assert!(!state.contains_key(&2));
+        let mut entries = state.into_iter().collect::<Vec<_>>();
+        entries.sort();
+        assert_eq!(entries, vec![(1, vec![10, 11])]);
+    }
+}
@@ -0,0 +1,395 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! A harness running the jobs of a test on a cluster of servers over the loopback TCP, so that
+//! the distributed behaviors, e.g. the exchanges across the servers and the aggregations per
+//! server, are tested as they are deployed. As the id of a server is set once per process, see
+//! `pegasus::startup`, each server is a child process running the same test alone, to which the
+//! configuration of the cluster is passed by the environment variables.
+//!
+//! The harness is only built for the tests, or by the feature `cluster` for the tests of other
+//! crates, e.g. the integration tests. A test runs its jobs by `Cluster::run`, where the name
+//! given to `Cluster::new` must be the name of the test itself, e.g.
+//!
+//! ```ignore
+//! use pegasus::cluster::Cluster;
+//!
+//! #[test]
+//! fn global_count_test() {
+//!     let cluster = Cluster::new("global_count_test", 2);
+//!     let conf = JobConf::new(1, "global_count_test", 2);
+//!     let counts = cluster.run(conf, |dfb| dfb.input_from_iter(0..100u32)?.count(Range::Global));
+//!     assert_eq!(counts.expect("run job failure;"), vec![400]);
+//! }
+//! ```
+//!
+//! The test process spawns the servers by the first run, which start up once the same run comes
+//! to them, and run the jobs of the test one by one. Each server writes its results of a job, or
+//! the error of the job, into a file shared by the processes, and all the files of the job are
+//! read back by every process, so that the test goes on the same in all the processes, with the
+//! results of the whole cluster. A job not done in time, or a server exited before the job is
+//! done, fails the run instead of hanging the test.
+
+use crate::api::{ResultSet, Sink};
+use crate::codec::{Decode, Encode, ReadExt, WriteExt};
+use crate::dataflow::DataflowBuilder;
+use crate::stream::Stream;
+use crate::{BuildJobError, Configuration, Data, JobConf, Tag};
+use std::cell::{Cell, RefCell};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// The id of the server a child process runs as;
+const SERVER_ENV: &str = "PEGASUS_CLUSTER_SERVER";
+/// The ports of all the servers on the loopback, separated by commas;
+const PORTS_ENV: &str = "PEGASUS_CLUSTER_PORTS";
+/// The directory of the results of the jobs and the logs of the servers;
+const DIR_ENV: &str = "PEGASUS_CLUSTER_DIR";
+
+/// The time a job is given to be done on all the servers by default;
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The results of a job on all the servers in the order of the servers, or the errors of the
+/// servers failed;
+pub type ClusterResult<D> = Result<Vec<D>, String>;
+
+enum Role {
+    /// the test process, which spawns the servers by the first run;
+    Test { children: RefCell<Vec<Child>> },
+    /// a server of the cluster, which starts up by the first run;
+    Server { server_id: u64, ports: Vec<u16>, started: RefCell<Option<Result<(), String>>> },
+}
+
+pub struct Cluster {
+    test: String,
+    servers: usize,
+    timeout: Duration,
+    dir: PathBuf,
+    role: Role,
+    // the jobs run so far, by which the files of the next job are named;
+    runs: Cell<usize>,
+}
+
+impl Cluster {
+    /// A cluster of `servers` servers running the test named `test`, which is the test process
+    /// itself or one of its servers, told by the environment;
+    pub fn new(test: &str, servers: usize) -> Self {
+        assert!(servers > 0, "a cluster has at least one server;");
+        let (dir, role) = match std::env::var(SERVER_ENV) {
+            Ok(server_id) => {
+                let server_id = server_id.parse().expect("invalid server id;");
+                let ports = std::env::var(PORTS_ENV)
+                    .expect("ports of servers not found;")
+                    .split(',')
+                    .map(|port| port.parse().expect("invalid port;"))
+                    .collect::<Vec<u16>>();
+                assert_eq!(ports.len(), servers, "servers of the cluster are inconsistent;");
+                let dir = PathBuf::from(std::env::var(DIR_ENV).expect("directory not found;"));
+                (dir, Role::Server { server_id, ports, started: RefCell::new(None) })
+            }
+            Err(_) => {
+                let dir = std::env::temp_dir().join(format!(
+                    "pegasus_cluster_{}_{}",
+                    test,
+                    std::process::id()
+                ));
+                (dir, Role::Test { children: RefCell::new(vec![]) })
+            }
+        };
+        Cluster {
+            test: test.to_owned(),
+            servers,
+            timeout: DEFAULT_TIMEOUT,
+            dir,
+            role,
+            runs: Cell::new(0),
+        }
+    }
+
+    /// Set the time each job is given to be done on all the servers;
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// The id of the server current process runs as, or `None` for the test process;
+    pub fn server_id(&self) -> Option<u64> {
+        match &self.role {
+            Role::Test { .. } => None,
+            Role::Server { server_id, .. } => Some(*server_id),
+        }
+    }
+
+    /// Run the job on all the servers, each of `conf.workers` workers, whose dataflow is built by
+    /// `func` on each worker, and collect the results of the stream it gives; The results of all
+    /// the servers are given in every process;
+    pub fn run<D, F>(&self, conf: JobConf, func: F) -> ClusterResult<D>
+    where
+        D: Data,
+        F: Fn(&DataflowBuilder) -> Result<Stream<D>, BuildJobError> + Send + Sync + 'static,
+    {
+        let run = self.runs.get();
+        self.runs.set(run + 1);
+        match &self.role {
+            Role::Test { children } => {
+                if run == 0 {
+                    let spawned = self.spawn()?;
+                    children.replace(spawned);
+                }
+            }
+            Role::Server { server_id, ports, started } => {
+                let started = started
+                    .borrow_mut()
+                    .get_or_insert_with(|| start_server(*server_id, ports, self.timeout))
+                    .clone();
+                let results = started.and_then(|_| self.run_job(conf, func));
+                if let Err(err) = &results {
+                    error!("run {} failed on server {}: {}", run, server_id, err);
+                }
+                write_results(&self.result_file(run, *server_id), &results)
+                    .expect("write results failure;");
+            }
+        }
+        self.gather(run)
+    }
+
+    fn spawn(&self) -> Result<Vec<Child>, String> {
+        if self.dir.exists() {
+            std::fs::remove_dir_all(&self.dir).map_err(|e| e.to_string())?;
+        }
+        std::fs::create_dir_all(&self.dir).map_err(|e| e.to_string())?;
+        let ports = free_ports(self.servers).map_err(|e| e.to_string())?;
+        let ports = ports.iter().map(|port| port.to_string()).collect::<Vec<_>>().join(",");
+        let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+        let mut children = Vec::with_capacity(self.servers);
+        for server_id in 0..self.servers as u64 {
+            let log = File::create(self.log_file(server_id)).map_err(|e| e.to_string())?;
+            let log_err = log.try_clone().map_err(|e| e.to_string())?;
+            let child = Command::new(&exe)
+                .args([self.test.as_str(), "--exact", "--nocapture", "--test-threads=1"])
+                .env(SERVER_ENV, server_id.to_string())
+                .env(PORTS_ENV, &ports)
+                .env(DIR_ENV, &self.dir)
+                .stdout(Stdio::from(log))
+                .stderr(Stdio::from(log_err))
+                .spawn()
+                .map_err(|e| format!("spawn server {} failure: {}", server_id, e))?;
+            children.push(child);
+        }
+        Ok(children)
+    }
+
+    fn run_job<D, F>(&self, mut conf: JobConf, func: F) -> ClusterResult<D>
+    where
+        D: Data,
+        F: Fn(&DataflowBuilder) -> Result<Stream<D>, BuildJobError> + Send + Sync + 'static,
+    {
+        let job_id = conf.job_id;
+        conf.add_servers(&(0..self.servers as u64).collect::<Vec<_>>());
+        let (tx, rx) = crossbeam_channel::unbounded();
+        let func = Arc::new(func);
+        // the job is joined aside, so that a job hanging is told by the timeout;
+        let job = std::thread::spawn(move || {
+            let guard = crate::run(conf, |worker| {
+                let tx = tx.clone();
+                let func = func.clone();
+                worker.dataflow(move |dfb| {
+                    (*func)(dfb)?.sink_by(move |_meta| {
+                        move |_t: &Tag, result: ResultSet<D>| {
+                            if let ResultSet::Data(data) = result {
+                                tx.send(data).ok();
+                            }
+                        }
+                    })
+                })
+            })
+            .map_err(|e| format!("submit job failure: {}", e))?;
+            if let Some(mut guard) = guard {
+                guard.join().map_err(|e| format!("run job failure: {}", e))?;
+            }
+            Ok(())
+        });
+        let deadline = Instant::now() + self.timeout;
+        let mut results = vec![];
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            match rx.recv_timeout(left) {
+                Ok(data) => results.extend(data),
+                Err(crossbeam_channel::RecvTimeoutError::Disconnected) => break,
+                Err(crossbeam_channel::RecvTimeoutError::Timeout) => {
+                    return Err(format!("job {} is not done in {:?};", job_id, self.timeout));
+                }
+            }
+        }
+        match job.join() {
+            Ok(Ok(())) => Ok(results),
+            Ok(Err(err)) => Err(err),
+            Err(_) => Err(format!("job {} panicked;", job_id)),
+        }
+    }
+
+    /// Read the results of the run of all the servers, in the order of the servers;
+    fn gather<D: Data>(&self, run: usize) -> ClusterResult<D> {
+        let deadline = Instant::now() + self.timeout;
+        let mut results = vec![];
+        let mut errors = vec![];
+        for server_id in 0..self.servers as u64 {
+            let path = self.result_file(run, server_id);
+            loop {
+                if path.exists() {
+                    match read_results(&path) {
+                        Ok(Ok(data)) => results.extend(data),
+                        Ok(Err(err)) => errors.push(format!("server {}: {}", server_id, err)),
+                        Err(err) => errors.push(format!("server {}: {}", server_id, err)),
+                    }
+                    break;
+                }
+                if let Some(exited) = self.exited(server_id) {
+                    errors.push(format!(
+                        "server {} exited by {} before run {} is done, see {:?};",
+                        server_id,
+                        exited,
+                        run,
+                        self.log_file(server_id)
+                    ));
+                    break;
+                }
+                if Instant::now() > deadline {
+                    errors.push(format!(
+                        "server {} is not done with run {} in {:?};",
+                        server_id, run, self.timeout
+                    ));
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(10));
+            }
+        }
+        if errors.is_empty() {
+            Ok(results)
+        } else {
+            Err(errors.join(" "))
+        }
+    }
+
+    /// The exit status of the server if it is a child of current process and has exited;
+    fn exited(&self, server_id: u64) -> Option<String> {
+        if let Role::Test { children } = &self.role {
+            let mut children = children.borrow_mut();
+            let child = children.get_mut(server_id as usize)?;
+            if let Ok(Some(status)) = child.try_wait() {
+                return Some(status.to_string());
+            }
+        }
+        None
+    }
+
+    fn result_file(&self, run: usize, server_id: u64) -> PathBuf {
+        self.dir.join(format!("run_{}_server_{}", run, server_id))
+    }
+
+    fn log_file(&self, server_id: u64) -> PathBuf {
+        self.dir.join(format!("server_{}.log", server_id))
+    }
+}
+
+impl Drop for Cluster {
+    fn drop(&mut self) {
+        if let Role::Test { children } = &self.role {
+            // the servers exit once they are done with the test, or are killed after the timeout;
+            let deadline = Instant::now() + self.timeout;
+            let mut succeed = true;
+            for child in children.borrow_mut().iter_mut() {
+                let status = loop {
+                    match child.try_wait() {
+                        Ok(Some(status)) => break Some(status),
+                        Ok(None) if Instant::now() < deadline => {
+                            std::thread::sleep(Duration::from_millis(10))
+                        }
+                        _ => {
+                            child.kill().ok();
+                            child.wait().ok();
+                            break None;
+                        }
+                    }
+                };
+                succeed &= status.map(|s| s.success()).unwrap_or(false);
+            }
+            if succeed {
+                std::fs::remove_dir_all(&self.dir).ok();
+            } else {
+                error!("servers of {} failed, see the logs in {:?};", self.test, self.dir);
+            }
+        }
+    }
+}
+
+fn start_server(server_id: u64, ports: &[u16], timeout: Duration) -> Result<(), String> {
+    let mut builder = Configuration::builder().server_id(server_id);
+    for (id, port) in ports.iter().enumerate() {
+        builder = builder.add_peer(id as u64, "127.0.0.1", *port);
+    }
+    let conf = builder.build().map_err(|e| e.to_string())?;
+    crate::startup(conf).map_err(|e| e.to_string())?;
+    // the jobs are only submitted once all the servers are connected;
+    let servers = (0..ports.len() as u64).collect::<Vec<_>>();
+    let deadline = Instant::now() + timeout;
+    while !pegasus_network::check_connect(server_id, &servers) {
+        if Instant::now() > deadline {
+            return Err(format!("server {} is not connected in {:?};", server_id, timeout));
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    Ok(())
+}
+
+/// Pick the ports free on the loopback, which are held until all are picked to be distinct;
+fn free_ports(n: usize) -> std::io::Result<Vec<u16>> {
+    let listeners =
+        (0..n).map(|_| TcpListener::bind("127.0.0.1:0")).collect::<std::io::Result<Vec<_>>>()?;
+    listeners.iter().map(|l| l.local_addr().map(|addr| addr.port())).collect()
+}
+
+fn write_results<D: Encode>(path: &Path, results: &ClusterResult<D>) -> std::io::Result<()> {
+    // written aside and renamed, so that the file is complete once it is seen;
+    let tmp = path.with_extension("tmp");
+    {
+        let mut writer = BufWriter::new(File::create(&tmp)?);
+        match results {
+            Ok(data) => {
+                writer.write_u8(0)?;
+                data.write_to(&mut writer)?;
+            }
+            Err(err) => {
+                writer.write_u8(1)?;
+                err.write_to(&mut writer)?;
+            }
+        }
+        writer.flush()?;
+    }
+    std::fs::rename(tmp, path)
+}
+
+fn read_results<D: Decode>(path: &Path) -> std::io::Result<ClusterResult<D>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    match reader.read_u8()? {
+        0 => Ok(Ok(Vec::<D>::read_from(&mut reader)?)),
+        _ => Ok(Err(String::read_from(&mut reader)?)),
+    }
+}
@@ -13,7 +13,7 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
-use crate::api::function::{MultiRouteFunction, RouteFunction};
+use crate::api::function::{MultiRouteFunction, RouteClosure, RouteFunction};
 use crate::channel_id::{ChannelId, SubChannelId};
 use crate::communication::decorator::{count::CountedPush, exchange::ExchangePush, DataPush};
 use crate::data::{Data, DataSet};
@@ -27,11 +27,14 @@ enum ChannelKind<T: Data> {
     Shuffle(Box<dyn RouteFunction<T>>),
     Broadcast(Option<Box<dyn MultiRouteFunction<T>>>),
     Aggregate(u64),
+    AggregateLocal,
 }
 
 pub struct Channel<T: Data> {
     kind: ChannelKind<T>,
     allow_cancel: bool,
+    order_preserving: bool,
+    spillable: bool,
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -41,6 +44,8 @@ pub(crate) struct ChannelMeta {
     pub push_peers: usize,
     pub forbid_cancel: bool,
     pub is_aggregate: bool,
+    pub order_preserving: bool,
+    pub spillable: bool,
 }
 
 impl Into<Edge> for ChannelMeta {
@@ -71,19 +76,48 @@ impl<T: Data> MaterializedChannel<T> {
 
 impl<T: Data> Channel<T> {
     fn new(kind: ChannelKind<T>, allow_cancel: bool) -> Self {
-        Channel { kind, allow_cancel }
+        Channel { kind, allow_cancel, order_preserving: false, spillable: false }
     }
 
     pub fn forbid_cancel(&mut self) {
         self.allow_cancel = false;
     }
 
+    /// Deliver the batches of each scope from each producer in the order they are sent, e.g.
+    /// of the data sorted by each worker, while the batches of an exchange may be delivered out
+    /// of order by the data plane; The batches are sequenced by the producers, and those
+    /// arriving ahead of others are held back by the consumers until the preceding ones arrive.
+    ///
+    /// A pipeline always keeps the order, while a broadcast by a multi-route function is not
+    /// supported, and fails to be built.
+    pub fn preserve_order(&mut self) {
+        self.order_preserving = true;
+    }
+
+    /// Spill the batches the consumers receive beyond their receive buffers into temp files,
+    /// instead of holding them in memory, e.g. of the batch analytics whose consumers are slow,
+    /// trading the latency for the completion; The batches are replayed in the order they arrive
+    /// once the consumers catch up, see `JobConf::exchange_spill_limit`.
+    pub fn spill_on_pressure(&mut self) {
+        self.spillable = true;
+    }
+
     pub(crate) fn materialize(
         self, dfb: &DataflowBuilder,
     ) -> Result<MaterializedChannel<T>, BuildJobError> {
+        if self.order_preserving {
+            if let ChannelKind::Broadcast(Some(_)) = self.kind {
+                // the route may send a record to a consumer more than once, whose order among
+                // the copies isn't defined;
+                return BuildJobError::unsupported(
+                    "order-preserving broadcast by a multi-route function",
+                );
+            }
+        }
         let index = dfb.next_channel_index();
         let ch_id =
             (ChannelId { job_seq: dfb.config.job_id as u64, index }, dfb.worker_id.index).into();
+        let batch_size = dfb.config.effective_batch_size() as usize;
         match self.kind {
             ChannelKind::Pipeline => {
                 let (tx, rx) = crate::data_plane::pipeline::<DataSet<T>>(ch_id);
@@ -93,6 +127,9 @@ impl<T: Data> Channel<T> {
                     push_peers: 1,
                     forbid_cancel: !self.allow_cancel,
                     is_aggregate: false,
+                    // the batches of a pipeline are never reordered;
+                    order_preserving: false,
+                    spillable: self.spillable,
                 };
                 let push = CountedPush::new(
                     ch_id,
@@ -111,10 +148,12 @@ impl<T: Data> Channel<T> {
                     push_peers: raw.len(),
                     forbid_cancel: !self.allow_cancel,
                     is_aggregate: false,
+                    order_preserving: self.order_preserving,
+                    spillable: self.spillable,
                 };
-                let pushes = decorate_to_count(ch_id, raw, &dfb);
-                let push =
-                    ExchangePush::exchange_to_one(dfb.config.batch_size as usize, ch_id, pushes, r);
+                let pushes = decorate_to_count(ch_id, raw, dfb, self.order_preserving);
+                let mut push = ExchangePush::exchange_to_one(batch_size, ch_id, pushes, r);
+                push.detect_skew(dfb.config.skew_factor);
                 Ok(MaterializedChannel { meta, push: DataPush::Exchange(push), pull: pull.into() })
             }
             ChannelKind::Broadcast(r) => {
@@ -125,15 +164,36 @@ impl<T: Data> Channel<T> {
                     push_peers: raw.len(),
                     forbid_cancel: !self.allow_cancel,
                     is_aggregate: false,
+                    order_preserving: self.order_preserving,
+                    spillable: self.spillable,
                 };
-                let pushes = decorate_to_count(ch_id, raw, &dfb);
+                let pushes = decorate_to_count(ch_id, raw, dfb, self.order_preserving);
                 let push = if let Some(r) = r {
-                    ExchangePush::exchange_to_some(dfb.config.batch_size as usize, ch_id, pushes, r)
+                    ExchangePush::exchange_to_some(batch_size, ch_id, pushes, r)
                 } else {
-                    ExchangePush::broadcast(dfb.config.batch_size as usize, ch_id, pushes)
+                    ExchangePush::broadcast(batch_size, ch_id, pushes)
                 };
                 Ok(MaterializedChannel { meta, push: DataPush::Exchange(push), pull: pull.into() })
             }
+            ChannelKind::AggregateLocal => {
+                let (raw, pull) = super::build_channel::<DataSet<T>>(index, &dfb.config)?.take();
+                // the scope ends are still sent to all peers, as by a shuffle, so the workers
+                // other than the leaders of the servers know that nothing is coming;
+                let meta = ChannelMeta {
+                    id: ch_id,
+                    is_local: false,
+                    push_peers: raw.len(),
+                    forbid_cancel: !self.allow_cancel,
+                    is_aggregate: false,
+                    order_preserving: self.order_preserving,
+                    spillable: self.spillable,
+                };
+                let leader = dfb.worker_id.server_leader() as u64;
+                let pushes = decorate_to_count(ch_id, raw, dfb, self.order_preserving);
+                let route: Box<dyn RouteFunction<T>> = box_route!(move |_: &T| leader);
+                let push = ExchangePush::exchange_to_one(batch_size, ch_id, pushes, route);
+                Ok(MaterializedChannel { meta, push: DataPush::Exchange(push), pull })
+            }
             ChannelKind::Aggregate(id) => {
                 let (mut raw, pull) =
                     super::build_channel::<DataSet<T>>(index, &dfb.config)?.take();
@@ -143,11 +203,16 @@ impl<T: Data> Channel<T> {
                     push_peers: raw.len(),
                     forbid_cancel: !self.allow_cancel,
                     is_aggregate: true,
+                    order_preserving: self.order_preserving,
+                    spillable: self.spillable,
                 };
                 let push = raw.swap_remove(id as usize);
+                // the server of the target is told by its index, rather than copied from the source;
                 let mut target = dfb.worker_id;
                 target.index = id as u32;
-                let push = CountedPush::new(ch_id, dfb.worker_id, target, push, &dfb.event_bus);
+                let target = target.on_servers(dfb.worker_id.local_peers);
+                let mut push = CountedPush::new(ch_id, dfb.worker_id, target, push, &dfb.event_bus);
+                decorate_order(&mut push, dfb, self.order_preserving);
                 for mut unused in raw {
                     unused.close().ok();
                 }
@@ -160,6 +225,7 @@ impl<T: Data> Channel<T> {
 #[inline]
 fn decorate_to_count<T: Data>(
     ch_id: SubChannelId, raw: Vec<GeneralPush<DataSet<T>>>, dfb: &DataflowBuilder,
+    order_preserving: bool,
 ) -> Vec<CountedPush<T>> {
     let mut counts = Vec::with_capacity(raw.len());
     let source = dfb.worker_id;
@@ -167,12 +233,29 @@ fn decorate_to_count<T: Data>(
     for (idx, p) in raw.into_iter().enumerate() {
         let mut target = source;
         target.index = idx as u32;
-        let push = CountedPush::new(ch_id, source, target, p, &dfb.event_bus);
+        let target = target.on_servers(source.local_peers);
+        let mut push = CountedPush::new(ch_id, source, target, p, &dfb.event_bus);
+        decorate_order(&mut push, dfb, order_preserving);
         counts.push(push);
     }
     counts
 }
 
+#[inline]
+fn decorate_order<T: Data>(
+    push: &mut CountedPush<T>, dfb: &DataflowBuilder, order_preserving: bool,
+) {
+    if order_preserving {
+        push.preserve_order();
+    }
+    if super::is_reordering_injected(dfb.config.job_id) {
+        push.swap_batches();
+    }
+    if dfb.config.profile {
+        push.measure_cross_server();
+    }
+}
+
 pub struct Pipeline;
 
 impl<T: Data> From<Pipeline> for Channel<T> {
@@ -212,6 +295,15 @@ impl<T: Data> From<Box<dyn MultiRouteFunction<T>>> for Channel<T> {
 
 pub struct Aggregate(pub u64);
 
+/// Aggregate the data of each server to the first worker of the server, see `Range::PerServer`;
+pub struct AggregateLocal;
+
+impl<T: Data> From<AggregateLocal> for Channel<T> {
+    fn from(_: AggregateLocal) -> Self {
+        Channel::new(ChannelKind::AggregateLocal, true)
+    }
+}
+
 impl<T: Data> From<Aggregate> for Channel<T> {
     fn from(a: Aggregate) -> Self {
         let kind = ChannelKind::Aggregate(a.0);
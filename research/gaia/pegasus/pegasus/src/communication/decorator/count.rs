@@ -14,10 +14,13 @@
 //! limitations under the License.
 
 use crate::channel_id::SubChannelId;
+use crate::codec::Encode;
 use crate::data::DataSet;
 use crate::data_plane::{GeneralPush, Push};
 use crate::errors::IOResult;
 use crate::event::{Event, EventBus, EventKind};
+use crate::scratch::{self, ScratchVec};
+use crate::{metrics, profile, span};
 use crate::{Data, Tag, WorkerId};
 use std::marker::PhantomData;
 
@@ -28,6 +31,13 @@ pub struct CountedPush<T: Data> {
     inner: GeneralPush<DataSet<T>>,
     event_bus: EventBus,
     current: Option<(Tag, usize)>,
+    // the sequence of the next batch if the channel is order-preserving;
+    next_seq: Option<u64>,
+    // push each batch after the one following it, if the reordering is injected;
+    swap: bool,
+    held: Option<DataSet<T>>,
+    // measure the bytes of the batches if they cross the servers, see `measure_cross_server`;
+    measure_bytes: bool,
     _ph: PhantomData<T>,
 }
 
@@ -43,12 +53,63 @@ impl<T: Data> CountedPush<T> {
             inner: push,
             event_bus: event_bus.clone(),
             current: None,
+            next_seq: None,
+            swap: false,
+            held: None,
+            measure_bytes: false,
             _ph: Default::default(),
         }
     }
 
+    /// Stamp the batches with their sequences, so that they are delivered in order by the
+    /// consumer, see `Channel::preserve_order`
+    pub fn preserve_order(&mut self) {
+        self.next_seq = Some(0);
+    }
+
+    /// Push each batch after the one following it, to test the consumers against the batches
+    /// delivered out of order, see `inject_reordering`
+    pub fn swap_batches(&mut self) {
+        self.swap = true;
+    }
+
+    /// Measure the encoded bytes of the batches into the profile of the job, if the target runs
+    /// on another server than the source
+    pub fn measure_cross_server(&mut self) {
+        self.measure_bytes = self.source.server_id != self.target.server_id;
+    }
+
+    #[inline]
+    fn push_inner(&mut self, mut msg: DataSet<T>) -> IOResult<()> {
+        // the batches forwarded from other channels may carry the sequences of them;
+        let producer = self.source.index;
+        msg.set_seq(self.next_seq.as_mut().map(|seq| {
+            *seq += 1;
+            (producer, *seq - 1)
+        }));
+        if !self.swap {
+            self.inner.push(msg)
+        } else if let Some(pre) = self.held.take() {
+            self.inner.push(msg)?;
+            self.inner.push(pre)
+        } else {
+            self.held = Some(msg);
+            Ok(())
+        }
+    }
+
+    #[inline]
+    fn release_held(&mut self) -> IOResult<()> {
+        if let Some(pre) = self.held.take() {
+            self.inner.push(pre)
+        } else {
+            Ok(())
+        }
+    }
+
     #[inline(always)]
     fn send_event(&mut self, t: Tag, size: usize) -> IOResult<()> {
+        self.release_held()?;
         self.inner.flush()?;
         let event = Event::new(t, self.ch_id.index(), EventKind::Pushed(size));
         self.event_bus.send_to(self.target, event)
@@ -70,7 +131,18 @@ impl<T: Data> Push<DataSet<T>> for CountedPush<T> {
         } else {
             self.current.replace((msg.tag.clone(), msg.len()));
         }
-        self.inner.push(msg)
+        span::on_send(self.ch_id.index(), &msg.tag, msg.len());
+        metrics::on_send(msg.len());
+        if self.measure_bytes {
+            // the batch is encoded into the scratch of the firing only to be measured;
+            scratch::with_context(|ctx| {
+                let mut buf = ScratchVec::new(ctx.scratch());
+                if msg.write_to(&mut buf).is_ok() {
+                    profile::on_cross_server(buf.len());
+                }
+            });
+        }
+        self.push_inner(msg)
     }
 
     fn flush(&mut self) -> IOResult<()> {
@@ -79,6 +151,7 @@ impl<T: Data> Push<DataSet<T>> for CountedPush<T> {
                 self.send_event(t, size)?;
             }
         }
+        self.release_held()?;
         self.inner.flush()
     }
 
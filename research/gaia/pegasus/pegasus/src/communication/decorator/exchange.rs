@@ -19,6 +19,7 @@ use crate::communication::decorator::count::CountedPush;
 use crate::data::DataSet;
 use crate::data_plane::Push;
 use crate::errors::{IOError, IOResult};
+use crate::scratch::{self, ScratchVec};
 use crate::{Data, Tag};
 use crossbeam_channel::{Receiver, Sender};
 
@@ -106,6 +107,27 @@ enum RoutingRule<D: Data> {
     ToAll,
 }
 
+/// Find the target receiving more than `factor` times the median of the records all targets
+/// receive, if any, ignoring the targets receiving no more than `least` records;
+fn find_skew(routed: &[u64], factor: u64, least: u64) -> Option<(usize, u64)> {
+    let (index, max) = routed.iter().enumerate().max_by_key(|(_, count)| **count)?;
+    if *max <= least {
+        return None;
+    }
+    // checked on each flush, so the counts are sorted in the scratch of the firing;
+    let median = scratch::with_context(|ctx| {
+        let mut sorted = ScratchVec::new(ctx.scratch());
+        sorted.extend_from_slice(routed);
+        sorted.sort_unstable();
+        sorted[sorted.len() / 2]
+    });
+    if *max > median.max(1).saturating_mul(factor) {
+        Some((index, median))
+    } else {
+        None
+    }
+}
+
 pub struct ExchangePush<D: Data> {
     pub batch_size: usize,
     ch_id: SubChannelId,
@@ -113,6 +135,11 @@ pub struct ExchangePush<D: Data> {
     current: Option<Tag>,
     routing: RoutingRule<D>,
     mask: Option<u64>,
+    // the number of records routed to each target;
+    routed: Vec<u64>,
+    // warn if one target receives more than this multiple of the median, 0 means no detection;
+    skew_factor: u64,
+    skew_reported: bool,
 }
 
 impl<D: Data> ExchangePush<D> {
@@ -138,7 +165,41 @@ impl<D: Data> ExchangePush<D> {
             });
         }
 
-        ExchangePush { batch_size, pushes: buffer_pushes, ch_id, current: None, routing, mask }
+        let routed = vec![0; buffer_pushes.len()];
+        ExchangePush {
+            batch_size,
+            pushes: buffer_pushes,
+            ch_id,
+            current: None,
+            routing,
+            mask,
+            routed,
+            skew_factor: 0,
+            skew_reported: false,
+        }
+    }
+
+    /// Warn once one target receives more than `factor` times the median of the records all
+    /// targets receive, e.g. the worker of a hot key;
+    pub fn detect_skew(&mut self, factor: u32) {
+        self.skew_factor = factor as u64;
+    }
+
+    fn check_skew(&mut self) {
+        if self.skew_factor > 0 && !self.skew_reported {
+            let least = self.batch_size as u64;
+            if let Some((index, median)) = find_skew(&self.routed, self.skew_factor, least) {
+                self.skew_reported = true;
+                warn_worker!(
+                    "channel {:?} is skewed: target {} receives {} records, over {} times the median {}",
+                    self.ch_id,
+                    index,
+                    self.routed[index],
+                    self.skew_factor,
+                    median
+                );
+            }
+        }
     }
 
     pub fn exchange_to_one(
@@ -164,6 +225,7 @@ impl<D: Data> ExchangePush<D> {
 
     #[inline]
     fn push_to(&mut self, data: D, index: usize) -> IOResult<()> {
+        self.routed[index] += 1;
         if self.pushes[index].push(data) {
             self.flush_buffer(index)?;
         }
@@ -305,6 +367,7 @@ impl<D: Data> Push<DataSet<D>> for ExchangePush<D> {
     }
 
     fn flush(&mut self) -> Result<(), IOError> {
+        self.check_skew();
         for i in 0..self.pushes.len() {
             if self.pushes[i].len() > 0 {
                 self.flush_buffer(i)?;
@@ -331,3 +394,18 @@ impl<D: Data> Push<DataSet<D>> for ExchangePush<D> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn find_skew_test() {
+        assert_eq!(find_skew(&[100, 120, 90, 110], 10, 64), None);
+        assert_eq!(find_skew(&[100, 1200, 90, 110], 10, 64), Some((1, 110)));
+        // too few records to tell
+        assert_eq!(find_skew(&[0, 50, 0, 0], 10, 64), None);
+        assert_eq!(find_skew(&[0, 500, 0, 0], 10, 64), Some((1, 0)));
+        assert_eq!(find_skew(&[], 10, 64), None);
+    }
+}
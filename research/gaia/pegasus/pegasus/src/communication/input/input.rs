@@ -20,24 +20,80 @@ use crate::data::DataSet;
 use crate::data_plane::{GeneralPull, Pull};
 use crate::errors::IOResult;
 use crate::event::{ChannelRxState, Event, EventBus, EventKind, Panel};
-use crate::{Data, Tag};
+use crate::metrics::SpillMetrics;
+use crate::schedule::slice;
+use crate::spill::{SpillAppender, SpillFile, SpillReader};
+use crate::{metrics, profile, span};
+use crate::{Data, JobConf, Tag, DEFAULT_EXCHANGE_SPILL_LIMIT};
 use pegasus_common::downcast::*;
 use pegasus_common::rc::RcPointer;
 use std::cell::{Cell, Ref, RefCell};
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 use std::time::Instant;
 
+/// The records the receive buffers of a channel hold in memory, beyond which the batches arriving
+/// are spilled into temp files, see `JobConf::exchange_spill_limit`;
+struct SpillBudget {
+    conf: Arc<JobConf>,
+    limit: usize,
+    in_memory: AtomicUsize,
+    metrics: SpillMetrics,
+}
+
+impl SpillBudget {
+    fn new(conf: Arc<JobConf>, limit: u64) -> Self {
+        let metrics = SpillMetrics::new(&conf);
+        SpillBudget { conf, limit: limit as usize, in_memory: AtomicUsize::new(0), metrics }
+    }
+
+    #[inline]
+    fn is_full(&self, len: usize) -> bool {
+        self.in_memory.load(Ordering::SeqCst) + len > self.limit
+    }
+
+    #[inline]
+    fn add(&self, len: usize) {
+        self.in_memory.fetch_add(len, Ordering::SeqCst);
+    }
+
+    #[inline]
+    fn sub(&self, len: usize) {
+        let _ = self.in_memory.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |in_memory| {
+            Some(in_memory.saturating_sub(len))
+        });
+    }
+}
+
+/// The batches of a scope spilled in the order they arrive, into the file being written at the
+/// back, and read back from the front;
+enum Spilled<D> {
+    Writing(SpillAppender<DataSet<D>>),
+    Reading(SpillReader<DataSet<D>>),
+}
+
 struct Stash<D> {
     one_shot: Option<DataSet<D>>,
     queued: Option<VecDeque<DataSet<D>>>,
+    // the batches arriving once the budget is full, which follow all those in memory, and are
+    // followed by all arriving later until they are replayed, to keep the order they arrive in;
+    spilled: VecDeque<Spilled<D>>,
+    budget: Option<Arc<SpillBudget>>,
 }
 
 impl<D: Data> Stash<D> {
-    pub fn new(data: DataSet<D>) -> Self {
-        Stash { one_shot: Some(data), queued: None }
+    pub fn new(budget: Option<Arc<SpillBudget>>) -> Self {
+        Stash { one_shot: None, queued: None, spilled: VecDeque::new(), budget }
     }
 
-    pub fn push(&mut self, data: DataSet<D>) {
+    pub fn push(&mut self, data: DataSet<D>) -> IOResult<()> {
+        if let Some(budget) = self.budget.clone() {
+            if !self.spilled.is_empty() || budget.is_full(data.len()) {
+                return self.spill(data, &budget);
+            }
+            budget.add(data.len());
+        }
         if let Some(ref mut queued) = self.queued {
             queued.push_back(data);
         } else if self.one_shot.is_none() {
@@ -47,27 +103,84 @@ impl<D: Data> Stash<D> {
             queue.push_back(data);
             self.queued = Some(queue);
         }
+        Ok(())
+    }
+
+    fn spill(&mut self, data: DataSet<D>, budget: &SpillBudget) -> IOResult<()> {
+        match self.spilled.back_mut() {
+            Some(Spilled::Writing(appender)) => appender.push(&data)?,
+            _ => {
+                let mut appender = SpillFile::append(&budget.conf)?;
+                appender.push(&data)?;
+                self.spilled.push_back(Spilled::Writing(appender));
+            }
+        }
+        Ok(())
     }
 
-    pub fn pop(&mut self) -> Option<DataSet<D>> {
-        if let Some(one_shot) = self.one_shot.take() {
+    pub fn pop(&mut self) -> IOResult<Option<DataSet<D>>> {
+        let data = if let Some(one_shot) = self.one_shot.take() {
             Some(one_shot)
         } else if let Some(ref mut queued) = self.queued {
             queued.pop_front()
         } else {
             None
+        };
+        if let Some(data) = data {
+            if let Some(budget) = self.budget.as_ref() {
+                budget.sub(data.len());
+            }
+            return Ok(Some(data));
         }
+        self.replay()
+    }
+
+    // read back the batches spilled once those in memory are all consumed;
+    fn replay(&mut self) -> IOResult<Option<DataSet<D>>> {
+        while let Some(spilled) = self.spilled.pop_front() {
+            let start = Instant::now();
+            let mut reader = match spilled {
+                Spilled::Reading(reader) => reader,
+                Spilled::Writing(appender) => {
+                    let (file, bytes) = appender.finish()?;
+                    if let Some(budget) = self.budget.as_ref() {
+                        budget.metrics.on_spilled(bytes);
+                    }
+                    file.into_reader()?
+                }
+            };
+            if let Some(data) = reader.next() {
+                let data = data?;
+                self.spilled.push_front(Spilled::Reading(reader));
+                // the consumer waits for the batch read back from the disk;
+                if let Some(budget) = self.budget.as_ref() {
+                    budget.metrics.on_replay_stall(start.elapsed());
+                }
+                return Ok(Some(data));
+            }
+            // the file is removed once its reader is dropped;
+        }
+        Ok(None)
     }
 
     pub fn clear(&mut self) {
+        if let (Some(budget), Some(one_shot)) = (self.budget.as_ref(), self.one_shot.as_ref()) {
+            budget.sub(one_shot.len());
+        }
         self.one_shot = None;
         if let Some(mut queued) = self.queued.take() {
+            if let Some(budget) = self.budget.as_ref() {
+                budget.sub(queued.iter().map(|data| data.len()).sum());
+            }
             queued.clear()
         }
+        self.spilled.clear();
     }
 
     pub fn is_empty(&self) -> bool {
-        self.one_shot.is_none() && self.queued.as_ref().map(|q| q.is_empty()).unwrap_or(true)
+        self.one_shot.is_none()
+            && self.queued.as_ref().map(|q| q.is_empty()).unwrap_or(true)
+            && self.spilled.is_empty()
     }
 }
 
@@ -80,40 +193,45 @@ struct StashedData<D> {
 }
 
 impl<D: Data> StashedData<D> {
-    pub fn new(tag: Tag, data: DataSet<D>, panel: Option<RcPointer<Panel>>) -> Self {
+    pub fn new(
+        tag: Tag, data: DataSet<D>, panel: Option<RcPointer<Panel>>,
+        budget: Option<Arc<SpillBudget>>,
+    ) -> IOResult<Self> {
         let len = data.len();
         // trace_worker!("stash {} data of {:?}", len, tag);
-        StashedData {
+        let mut stash = Stash::new(budget);
+        stash.push(data)?;
+        Ok(StashedData {
             tag,
-            stash: RefCell::new(Stash::new(data)),
+            stash: RefCell::new(stash),
             panel: RefCell::new(panel),
             abandoned: Cell::new(false),
             len: Cell::new(len),
-        }
+        })
     }
 
     #[inline]
-    pub fn pop(&self) -> Option<DataSet<D>> {
+    pub fn pop(&self) -> IOResult<Option<DataSet<D>>> {
         assert!(!self.abandoned.get());
-        if let Some(data) = self.stash.borrow_mut().pop() {
+        if let Some(data) = self.stash.borrow_mut().pop()? {
             self.len.set(self.len.get() - data.len());
-            Some(data)
+            Ok(Some(data))
         } else {
-            None
+            Ok(None)
         }
     }
 
     #[inline]
-    pub fn stash(&self, data: DataSet<D>) -> bool {
+    pub fn stash(&self, data: DataSet<D>) -> IOResult<bool> {
         if let Some(panel) = self.panel.borrow().as_ref() {
             if panel.is_skipped() {
-                return false;
+                return Ok(false);
             }
         }
         // trace_worker!("stash {} data of {:?}", data.len(), self.tag);
         self.len.set(self.len.get() + data.len());
-        self.stash.borrow_mut().push(data);
-        true
+        self.stash.borrow_mut().push(data)?;
+        Ok(true)
     }
 
     #[inline]
@@ -159,6 +277,47 @@ impl<D: Data> StashedData<D> {
     }
 }
 
+/// Hold back the batches of an order-preserving channel arriving ahead of those sent before them
+/// by the same producer, until the preceding ones arrive;
+struct Reorder<D> {
+    // the sequence of the next batch from each producer;
+    expected: HashMap<u32, u64>,
+    held: BTreeMap<(u32, u64), DataSet<D>>,
+    // the held batches whose preceding ones have arrived;
+    ready: VecDeque<DataSet<D>>,
+}
+
+impl<D: Data> Reorder<D> {
+    fn new() -> Self {
+        Reorder { expected: HashMap::new(), held: BTreeMap::new(), ready: VecDeque::new() }
+    }
+
+    /// Give back the batch pulled from the channel if it is the next one of its producer, or
+    /// hold it back otherwise;
+    fn accept(&mut self, data: DataSet<D>) -> Option<DataSet<D>> {
+        let (producer, seq) = match data.get_seq() {
+            Some(seq) => seq,
+            None => return Some(data),
+        };
+        let expected = self.expected.entry(producer).or_insert(0);
+        if seq > *expected {
+            self.held.insert((producer, seq), data);
+            return None;
+        }
+        *expected += 1;
+        while let Some(next) = self.held.remove(&(producer, *expected)) {
+            self.ready.push_back(next);
+            *expected += 1;
+        }
+        Some(data)
+    }
+
+    #[inline]
+    fn take_ready(&mut self) -> Option<DataSet<D>> {
+        self.ready.pop_front()
+    }
+}
+
 pub struct InboundChannel<D: Data> {
     pub ch_id: SubChannelId,
     pub peers: usize,
@@ -173,6 +332,8 @@ pub struct InboundChannel<D: Data> {
     state: RcPointer<ChannelRxState>,
     stash_cost: u128,
     skip_st: usize,
+    reorder: Option<Reorder<D>>,
+    budget: Option<Arc<SpillBudget>>,
 }
 
 struct Session {
@@ -199,6 +360,16 @@ impl<D: Data> InboundChannel<D> {
         let push_peers = meta.push_peers;
         let mut queue = VecDeque::new();
         queue.push_back(None);
+        // the exchanges spill once the job limits their receive buffers, and the channels opting in
+        // spill by the default limit unless the job limits them;
+        let budget = crate::get_current_job_conf().and_then(|conf| {
+            let limit = match conf.exchange_spill_limit {
+                0 if meta.spillable => DEFAULT_EXCHANGE_SPILL_LIMIT,
+                limit if limit > 0 && (meta.spillable || !meta.is_local) => limit,
+                _ => return None,
+            };
+            Some(Arc::new(SpillBudget::new(conf, limit)))
+        });
         InboundChannel {
             ch_id,
             peers: push_peers,
@@ -213,6 +384,8 @@ impl<D: Data> InboundChannel<D> {
             state: RcPointer::new(ChannelRxState::new(ch_id.index(), push_peers, scope_depth)),
             stash_cost: 0,
             skip_st: 0,
+            reorder: if meta.order_preserving { Some(Reorder::new()) } else { None },
+            budget,
         }
     }
 
@@ -261,12 +434,17 @@ impl<D: Data> InboundChannel<D> {
 
     pub fn pull_scope(&mut self, tag: &Tag) -> IOResult<Option<(DataSet<D>, bool)>> {
         assert_eq!(tag.len(), self.scope_depth);
+        let ch_index = self.ch_id.index();
         if let Some(st) = self.fetch_stash(tag) {
             st.set_panel_if_absent(&self.state);
             if let Some(panel) = st.get_panel().as_ref() {
                 if panel.has_outstanding() {
-                    if let Some(data) = st.pop() {
+                    if let Some(data) = st.pop()? {
                         panel.add_pulled(data.len());
+                        span::on_recv(ch_index, tag, data.len());
+                        metrics::on_recv(data.len());
+                        profile::on_recv(data.len());
+                        slice::on_records(data.len());
                         let has_more = panel.has_outstanding();
                         return Ok(Some((data, has_more)));
                     }
@@ -284,6 +462,10 @@ impl<D: Data> InboundChannel<D> {
             let start = Instant::now();
             let result = if let Some(data) = self.pull_until(tag)? {
                 panel.add_pulled(data.len());
+                span::on_recv(ch_index, tag, data.len());
+                metrics::on_recv(data.len());
+                profile::on_recv(data.len());
+                slice::on_records(data.len());
                 let has_more = panel.has_outstanding();
                 Some((data, has_more))
             } else {
@@ -296,16 +478,34 @@ impl<D: Data> InboundChannel<D> {
         }
     }
 
+    /// Pull the next batch from the channel, which is held back if it arrives ahead of the
+    /// preceding ones of an order-preserving channel;
+    fn pull_next(&mut self) -> IOResult<Option<DataSet<D>>> {
+        if let Some(reorder) = self.reorder.as_mut() {
+            if let Some(data) = reorder.take_ready() {
+                return Ok(Some(data));
+            }
+            while let Some(data) = self.pull.pull()? {
+                if let Some(data) = reorder.accept(data) {
+                    return Ok(Some(data));
+                }
+            }
+            Ok(None)
+        } else {
+            self.pull.pull()
+        }
+    }
+
     fn pull_until(&mut self, tag: &Tag) -> IOResult<Option<DataSet<D>>> {
         let mut limit = 8;
         while limit > 0 {
-            match self.pull.pull()? {
+            match self.pull_next()? {
                 Some(data) => {
                     // trace_worker!("pull data {:?} in ch: {}", data, self.ch_id.index());
                     if &data.tag == tag {
                         return Ok(Some(data));
                     } else {
-                        self.stash(data);
+                        self.stash(data)?;
                         limit -= 1;
                     }
                 }
@@ -326,10 +526,10 @@ impl<D: Data> InboundChannel<D> {
         }
 
         while !self.is_exhaust() {
-            match self.pull.pull()? {
+            match self.pull_next()? {
                 Some(data) => {
                     let tag = data.tag();
-                    if self.stash(data) {
+                    if self.stash(data)? {
                         return Ok(Some(tag));
                     }
                 }
@@ -341,13 +541,13 @@ impl<D: Data> InboundChannel<D> {
         Ok(None)
     }
 
-    fn stash(&mut self, data: DataSet<D>) -> bool {
+    fn stash(&mut self, data: DataSet<D>) -> IOResult<bool> {
         let tag = data.tag();
         let log_trace = crate::worker_id::is_in_trace();
         let mut has_stashed = true;
         if let Some(stashed) = self.get_stash(&tag) {
             let len = data.len();
-            if !stashed.stash(data) {
+            if !stashed.stash(data)? {
                 has_stashed = false;
                 self.skip_st += len;
                 if log_trace {
@@ -362,7 +562,8 @@ impl<D: Data> InboundChannel<D> {
         } else if let Some(panel) = self.state.get_panel(&tag) {
             if !panel.is_skipped() {
                 panel.set_seq(0);
-                let stashed = StashedData::new(tag.clone(), data, Some(panel));
+                let budget = self.budget.clone();
+                let stashed = StashedData::new(tag.clone(), data, Some(panel), budget)?;
                 self.stash_data.push(stashed);
                 self.stash_index.insert(tag.clone(), self.stash_data.len() - 1);
             } else {
@@ -380,7 +581,7 @@ impl<D: Data> InboundChannel<D> {
         } else {
             // the panel is absent,
             if !self.state.is_scope_skipped(&tag.to_parent_uncheck()) {
-                let stashed = StashedData::new(tag.clone(), data, None);
+                let stashed = StashedData::new(tag.clone(), data, None, self.budget.clone())?;
                 self.stash_data.push(stashed);
                 self.stash_index.insert(tag.clone(), self.stash_data.len() - 1);
             } else {
@@ -400,7 +601,7 @@ impl<D: Data> InboundChannel<D> {
         if has_stashed {
             self.stashed_scope.push_front(Some(tag));
         }
-        has_stashed
+        Ok(has_stashed)
     }
 
     pub fn cancel(&mut self, tag: &Tag) {
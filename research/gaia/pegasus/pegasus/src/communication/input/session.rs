@@ -17,6 +17,7 @@ use crate::api::notify::NotifySubscriber;
 use crate::communication::input::InboundChannel;
 use crate::data::DataSet;
 use crate::errors::JobExecError;
+use crate::schedule::slice;
 use crate::{Data, Tag};
 use std::cell::RefMut;
 
@@ -53,7 +54,8 @@ impl<'a, D: Data> InputSession<'a, D> {
                 if let Err(err) = func(&mut data) {
                     return if err.can_be_retried() { Ok(()) } else { Err(err) };
                 }
-                if !has_more {
+                // the rest batches of the scope are left outstanding for the later firings;
+                if !has_more || slice::is_input_exhausted() {
                     break;
                 }
             }
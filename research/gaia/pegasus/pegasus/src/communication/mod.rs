@@ -18,16 +18,16 @@ use crate::errors::{BuildJobError, IOError};
 use crate::{Data, JobConf};
 use std::any::Any;
 use std::cell::RefCell;
-use std::collections::{HashMap, LinkedList};
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet, LinkedList};
+use std::sync::{Arc, RwLock};
 
-mod channel;
-mod decorator;
+pub(crate) mod channel;
+pub(crate) mod decorator;
 pub(crate) mod input;
 pub(crate) mod output;
 
 use crate::channel_id::ChannelId;
-pub use channel::{Aggregate, Broadcast, Channel, Pipeline};
+pub use channel::{Aggregate, AggregateLocal, Broadcast, Channel, Pipeline};
 
 pub type IOResult<D> = Result<D, IOError>;
 pub type Input<'a, D> = input::InputSession<'a, D>;
@@ -37,6 +37,49 @@ thread_local! {
     static CHANNEL_RESOURCES : RefCell<HashMap<ChannelId, LinkedList<Box<dyn Any>>>> = RefCell::new(Default::default());
 }
 
+lazy_static! {
+    // the jobs whose exchanges deliver the batches out of order, see `inject_reordering`;
+    static ref REORDERED_JOBS: RwLock<HashSet<u64>> = RwLock::new(HashSet::new());
+    // the jobs whose workers are split into simulated servers, see `simulate_servers`;
+    static ref SIMULATED_SERVERS: RwLock<HashMap<u64, u32>> = RwLock::new(HashMap::new());
+}
+
+/// Push each batch of the exchanges of the job after the one following it, to test the
+/// operators against the batches delivered out of order by the data plane; It takes effect on
+/// the channels built afterwards.
+#[doc(hidden)]
+pub fn inject_reordering(job_id: u64, enabled: bool) {
+    if let Ok(mut jobs) = REORDERED_JOBS.write() {
+        if enabled {
+            jobs.insert(job_id);
+        } else {
+            jobs.remove(&job_id);
+        }
+    }
+}
+
+pub(crate) fn is_reordering_injected(job_id: u64) -> bool {
+    REORDERED_JOBS.read().map(|jobs| jobs.contains(&job_id)).unwrap_or(false)
+}
+
+/// Split the workers of the job run by a single server into `servers` simulated servers of the
+/// same number of workers, to test the per-server channels and ranges without a cluster; It takes
+/// effect on the jobs submitted afterwards, and `servers` of 0 or 1 stops the simulation.
+#[doc(hidden)]
+pub fn simulate_servers(job_id: u64, servers: u32) {
+    if let Ok(mut jobs) = SIMULATED_SERVERS.write() {
+        if servers > 1 {
+            jobs.insert(job_id, servers);
+        } else {
+            jobs.remove(&job_id);
+        }
+    }
+}
+
+pub(crate) fn get_simulated_servers(job_id: u64) -> Option<u32> {
+    SIMULATED_SERVERS.read().ok().and_then(|jobs| jobs.get(&job_id).copied())
+}
+
 pub(crate) fn build_channel<T: Data>(
     ch_index: u32, conf: &Arc<JobConf>,
 ) -> Result<ChannelResource<T>, BuildJobError> {
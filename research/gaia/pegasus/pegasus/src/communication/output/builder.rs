@@ -19,11 +19,12 @@ use crate::communication::output::tee::{ChannelPush, Tee};
 use crate::communication::output::{OutputBuilder, OutputDelta, OutputProxy};
 use crate::event::EventBus;
 use crate::graph::Port;
-use crate::Data;
+use crate::{Data, LatencyMode};
 use pegasus_common::downcast::*;
 use smallvec::SmallVec;
 use std::cell::{Cell, RefCell};
 use std::rc::Rc;
+use std::time::Duration;
 
 pub struct OutputEntry<D: Data> {
     pub ch_index: u32,
@@ -42,6 +43,8 @@ pub struct OutputBuilderImpl<D: Data> {
     pub delta: Rc<Cell<OutputDelta>>,
     pub batch_size: usize,
     pub capacity: u32,
+    pub flush_interval: Option<Duration>,
+    pub latency_mode: LatencyMode,
     pub scope_depth: usize,
     pub mem_limit: usize,
     shared: Rc<RefCell<SmallVec<[OutputEntry<D>; 2]>>>,
@@ -57,6 +60,8 @@ impl<D: Data> OutputBuilderImpl<D> {
             scope_depth: 0,
             mem_limit: (!0u32) as usize,
             capacity: 64,
+            flush_interval: None,
+            latency_mode: LatencyMode::Throughput,
             shared: Rc::new(RefCell::new(SmallVec::new())),
             event_bus: event_bus.clone(),
         }
@@ -108,6 +113,8 @@ impl<D: Data> Clone for OutputBuilderImpl<D> {
             delta: self.delta.clone(),
             batch_size: self.batch_size,
             capacity: self.capacity,
+            flush_interval: self.flush_interval,
+            latency_mode: self.latency_mode,
             scope_depth: self.scope_depth,
             mem_limit: self.mem_limit.clone(),
             shared: self.shared.clone(),
@@ -135,6 +142,8 @@ impl<D: Data> OutputBuilder for OutputBuilderImpl<D> {
             tee,
         );
         output.set_job_mem_limit(self.mem_limit * 1 << 20);
+        output.flush_interval = self.flush_interval;
+        output.latency_mode = self.latency_mode;
         Box::new(RefWrapOutput::wrap(output)) as Box<dyn OutputProxy>
     }
 }
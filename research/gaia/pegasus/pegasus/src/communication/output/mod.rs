@@ -48,6 +48,10 @@ pub trait OutputProxy: AsAny + Send {
     /// Reset the output capacity;
     fn reset_capacity(&self);
 
+    /// The batches this port can still output in current firing, which is negative once the
+    /// outputs overshoot the capacity;
+    fn remaining_capacity(&self) -> i64;
+
     fn batch_size(&self) -> usize;
 
     /// Notify this output that the scope with tag in parameter was closed in upstreams;
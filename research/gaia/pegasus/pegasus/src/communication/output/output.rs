@@ -19,6 +19,7 @@ use std::ops::Deref;
 use std::sync::atomic::AtomicI64;
 use std::sync::atomic::Ordering::SeqCst;
 use std::sync::Arc;
+use std::time::Duration;
 
 use crate::communication::output::tee::Tee;
 use crate::communication::output::{OutputDelta, OutputProxy};
@@ -27,7 +28,7 @@ use crate::errors::IOResult;
 use crate::event::EventKind;
 use crate::graph::Port;
 use crate::tag::tools::{BlockGuard, TagAntiChainSet, TagTree};
-use crate::{Data, Tag};
+use crate::{Data, LatencyMode, Tag};
 
 use crossbeam_channel::{Receiver, Sender};
 use pegasus_common::downcast::*;
@@ -37,6 +38,10 @@ pub struct OutputHandle<D: Data> {
     pub delta: OutputDelta,
     pub batch_size: usize,
     pub capacity: u32,
+    /// the most time the data wait in a partial batch before being flushed, if set;
+    pub flush_interval: Option<Duration>,
+    /// the eager mode flushes each record once output, see `LatencyMode`;
+    pub latency_mode: LatencyMode,
     pub scope_depth: usize,
     pub mem_limit: Option<usize>,
     pub recycle_hook: Sender<Vec<D>>,
@@ -62,6 +67,8 @@ impl<D: Data> OutputHandle<D> {
             delta,
             batch_size,
             capacity,
+            flush_interval: None,
+            latency_mode: LatencyMode::Throughput,
             scope_depth,
             mem_limit: None,
             tee: output,
@@ -289,6 +296,11 @@ impl<D: Data> OutputProxy for RefWrapOutput<D> {
         self.capacity.load(SeqCst) > 0
     }
 
+    #[inline]
+    fn remaining_capacity(&self) -> i64 {
+        self.capacity.load(SeqCst)
+    }
+
     #[inline]
     fn reset_capacity(&self) {
         let ca = self.output.borrow().capacity as i64;
@@ -16,12 +16,14 @@
 use crate::communication::output::output::OutputHandle;
 use crate::data::DataSet;
 use crate::errors::{IOError, IOResult, JobExecError};
-use crate::{Data, Tag};
+use crate::schedule::slice;
+use crate::{Data, LatencyMode, Tag};
 use std::cell::{Cell, RefMut};
 use std::error::Error;
 use std::sync::atomic::AtomicI64;
 use std::sync::atomic::Ordering::SeqCst;
 use std::sync::Arc;
+use std::time::Instant;
 
 pub struct OutputSession<'a, D: Data> {
     pub(crate) tag: Tag,
@@ -32,6 +34,9 @@ pub struct OutputSession<'a, D: Data> {
     is_skipped: bool,
     skip_st: usize,
     trace: bool,
+    // the time of the last flush, only if the output has a flush interval;
+    last_flush: Option<Instant>,
+    flushed_by_interval: bool,
 }
 
 impl<'a, D: Data> OutputSession<'a, D> {
@@ -40,6 +45,7 @@ impl<'a, D: Data> OutputSession<'a, D> {
     ) -> Self {
         let tag = output.evolve_output(in_tag);
         let is_skipped = output.is_skipped(&tag);
+        let last_flush = output.flush_interval.map(|_| Instant::now());
         OutputSession {
             output,
             capacity: capacity.clone(),
@@ -49,9 +55,18 @@ impl<'a, D: Data> OutputSession<'a, D> {
             is_skipped,
             skip_st: 0,
             trace: crate::worker_id::is_in_trace(),
+            last_flush,
+            flushed_by_interval: false,
         }
     }
 
+    /// Check if a partial batch has been flushed as the flush interval of the output elapsed, when
+    /// the operators producing data by themselves, e.g. the sources, should yield to let the data
+    /// go downstream;
+    pub fn has_flushed_by_interval(&self) -> bool {
+        self.flushed_by_interval
+    }
+
     pub fn advance(&mut self, to: u32) -> IOResult<()> {
         if let Some(cur) = self.tag.current() {
             if cur != to {
@@ -190,18 +205,37 @@ impl<'a, D: Data> OutputSession<'a, D> {
 
     fn push(&mut self, msg: D) -> IOResult<()> {
         self.buffer.push(msg);
-        if self.buffer.len() == self.output.batch_size {
+        // each batch of one record is full and due at once in the eager mode, which is flushed
+        // through the output as due instead of waiting in the channels as full;
+        let eager = self.output.latency_mode == LatencyMode::Eager;
+        if self.buffer.len() == self.output.batch_size && !eager {
             self.flush(false)?;
+        } else if self.is_flush_due() {
+            self.flushed_by_interval = true;
+            self.flush(false)?;
+            self.output.flush()?;
         }
         Ok(())
     }
 
+    #[inline]
+    fn is_flush_due(&self) -> bool {
+        match (self.last_flush, self.output.flush_interval) {
+            (Some(last_flush), Some(interval)) => last_flush.elapsed() >= interval,
+            _ => false,
+        }
+    }
+
     fn flush(&mut self, before_close: bool) -> IOResult<()> {
+        if let Some(last_flush) = self.last_flush.as_mut() {
+            *last_flush = Instant::now();
+        }
         let buffer = self.detach_buffer(before_close);
         if !buffer.is_empty() {
             self.output.push(self.tag.clone(), buffer)?;
             let sub = self.capacity.fetch_sub(1, SeqCst);
-            if sub <= 1 && self.allow_interrupt.get() {
+            // the iterators given are resumed from where they stop in the later firings;
+            if self.allow_interrupt.get() && (sub <= 1 || slice::is_exhausted()) {
                 return Err(IOError::new(std::io::ErrorKind::Interrupted));
             }
         }
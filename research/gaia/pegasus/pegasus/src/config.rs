@@ -14,14 +14,51 @@
 //! limitations under the License.
 
 use crate::errors::StartupError;
-use pegasus_network::config::NetworkConfig;
+use crate::health::Requirement;
+use crate::resource::JobResources;
+use crate::slow_query::SlowQueryConfig;
+use pegasus_network::config::{NetworkConfig, PeerConfig};
 use serde::Deserialize;
-use std::path::Path;
+use std::any::Any;
+use std::collections::HashSet;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// The least `JobConf::output_capacity` of any operator, below which the operator never has the
+/// capacity to be scheduled;
+pub const MIN_OUTPUT_CAPACITY: u32 = 1;
+/// The least `JobConf::output_capacity` of the operators with two inputs, e.g. merge and join,
+/// which give a batch for each input in a schedule, so that one input can't starve the other;
+pub const MIN_BINARY_OUTPUT_CAPACITY: u32 = 2;
+/// The `JobConf::exchange_spill_limit` of the channels opting in by `Channel::spill_on_pressure`
+/// in the jobs not limiting their receive buffers;
+pub const DEFAULT_EXCHANGE_SPILL_LIMIT: u64 = 1 << 20;
 
 #[derive(Debug, Deserialize)]
 pub struct Configuration {
     pub network: Option<NetworkConfig>,
     pub max_pool_size: Option<u32>,
+    /// the number of threads doing the IO of the connections to other servers apart from the
+    /// workers, 0 if each connection has its own threads, or
+    /// `pegasus_network::config::DEFAULT_IO_THREADS` if not set
+    pub io_pool_size: Option<u32>,
+    /// the address of the endpoint serving the metrics, e.g. '0.0.0.0:9091', which requires the
+    /// feature `metrics`
+    pub metrics_addr: Option<String>,
+    /// the slow query log, which is disabled if not set, see `crate::slow_query`
+    pub slow_query: Option<SlowQueryConfig>,
+    /// the most jobs running on current server at once, over which the new jobs are rejected,
+    /// and aborted on all their servers, see `crate::submit`; no limit if not set
+    pub max_jobs: Option<u32>,
+    /// the requirements of the readiness of current server, e.g. `['peers', 'graph:ldbc']`, see
+    /// `crate::health::Requirement`; all the peers, the executor and the components reported are
+    /// required if not set
+    pub readiness: Option<Vec<String>>,
+    /// the root of the temp files and directories of the jobs, see `crate::temp`, under which the
+    /// ones left by the crashed processes are removed once the engine starts; `pegasus_temp` in
+    /// the temp directory of the system if not set
+    pub temp_dir: Option<String>,
 }
 
 impl Configuration {
@@ -30,7 +67,54 @@ impl Configuration {
     }
 
     pub fn singleton() -> Self {
-        Configuration { network: None, max_pool_size: None }
+        Configuration {
+            network: None,
+            max_pool_size: None,
+            io_pool_size: None,
+            metrics_addr: None,
+            slow_query: None,
+            max_jobs: None,
+            readiness: None,
+            temp_dir: None,
+        }
+    }
+
+    pub fn builder() -> ConfigurationBuilder {
+        ConfigurationBuilder::default()
+    }
+
+    /// Read and validate the configuration from a toml file, e.g.
+    ///
+    /// ```toml
+    /// max_pool_size = 8
+    /// io_pool_size = 2
+    /// metrics_addr = '0.0.0.0:9091'
+    /// readiness = ['peers', 'graph:ldbc']
+    /// temp_dir = '/data/pegasus_temp'
+    ///
+    /// [slow_query]
+    /// threshold_ms = 1000
+    ///
+    /// [network]
+    /// server_id = 0
+    /// ip = '127.0.0.1'
+    /// port = 8080
+    /// codec = 'standard'
+    ///
+    /// [[network.peers]]
+    /// server_id = 0
+    /// ip = '127.0.0.1'
+    /// port = 8080
+    ///
+    /// [[network.peers]]
+    /// server_id = 1
+    /// ip = '127.0.0.1'
+    /// port = 8081
+    /// ```
+    pub fn from_toml<P: AsRef<Path>>(path: P) -> Result<Self, StartupError> {
+        let conf = read_from(path)?;
+        conf.validate()?;
+        Ok(conf)
     }
 
     pub fn server_id(&self) -> u64 {
@@ -44,6 +128,90 @@ impl Configuration {
     pub fn network_config(&self) -> Option<&NetworkConfig> {
         self.network.as_ref()
     }
+
+    /// Check the configuration before startup, and report all the violated constraints at once.
+    /// The number of servers is also checked by the handshake with other servers, as it may be
+    /// inconsistent among the servers.
+    pub fn validate(&self) -> Result<(), StartupError> {
+        let mut violations = vec![];
+        if self.max_pool_size == Some(0) {
+            violations.push("max_pool_size must be positive".to_owned());
+        }
+        if self.max_jobs == Some(0) {
+            violations.push("max_jobs must be positive".to_owned());
+        }
+        if self.slow_query.as_ref().map(|conf| conf.max_entries == Some(0)).unwrap_or(false) {
+            violations.push("max_entries of slow_query must be positive".to_owned());
+        }
+        for requirement in self.readiness.iter().flatten() {
+            if let Err(e) = requirement.parse::<Requirement>() {
+                violations.push(e);
+            }
+        }
+        if let Some(net_conf) = self.network.as_ref() {
+            validate_network(net_conf, &mut violations);
+        }
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(StartupError::InvalidConfig(violations))
+        }
+    }
+}
+
+fn validate_network(net_conf: &NetworkConfig, violations: &mut Vec<String>) {
+    let server_id = net_conf.server_id;
+    if net_conf.ip.parse::<IpAddr>().is_err() {
+        violations.push(format!("ip '{}' of server {} is invalid", net_conf.ip, server_id));
+    }
+    let peers = match net_conf.peers.as_ref() {
+        Some(peers) => peers,
+        // the servers may be detected instead, see `startup_with`;
+        None => return,
+    };
+    // the servers are identified by the consecutive integers from 0;
+    let servers = peers.len() as u64;
+    let mut ids = HashSet::new();
+    let mut addrs = HashSet::new();
+    for peer in peers {
+        if peer.server_id >= servers {
+            violations.push(format!(
+                "id of peer {} is out of the range [0, {}) of {} servers",
+                peer.server_id, servers, servers
+            ));
+        }
+        if !ids.insert(peer.server_id) {
+            violations.push(format!("peer {} is configured more than once", peer.server_id));
+        }
+        if peer.port == 0 {
+            violations.push(format!("port of peer {} must be positive", peer.server_id));
+        }
+        match peer.ip.parse::<IpAddr>() {
+            Ok(ip) => {
+                let addr = SocketAddr::new(ip, peer.port);
+                if peer.port != 0 && !addrs.insert(addr) {
+                    violations.push(format!(
+                        "address {} of peer {} is used by another peer",
+                        addr, peer.server_id
+                    ));
+                }
+            }
+            Err(_) => {
+                violations.push(format!("ip '{}' of peer {} is invalid", peer.ip, peer.server_id))
+            }
+        }
+    }
+    match peers.iter().find(|peer| peer.server_id == server_id) {
+        Some(local) => {
+            if net_conf.port != 0 && local.port != net_conf.port {
+                violations.push(format!(
+                    "port {} of server {} is different from {} in peers",
+                    net_conf.port, server_id, local.port
+                ));
+            }
+        }
+        None => violations.push(format!("server {} is not found in peers", server_id)),
+    }
 }
 
 pub fn read_from<P: AsRef<Path>>(path: P) -> Result<Configuration, StartupError> {
@@ -51,6 +219,193 @@ pub fn read_from<P: AsRef<Path>>(path: P) -> Result<Configuration, StartupError>
     Ok(Configuration::parse(&config_str)?)
 }
 
+/// Build the configuration of a server, which is validated once built, e.g. a server of two:
+///
+/// ```
+/// use pegasus::Configuration;
+///
+/// let conf = Configuration::builder()
+///     .server_id(1)
+///     .local_addr("127.0.0.1", 8081)
+///     .add_peer(0, "127.0.0.1", 8080)
+///     .add_peer(1, "127.0.0.1", 8081)
+///     .max_pool_size(8)
+///     .build()
+///     .unwrap();
+/// assert_eq!(conf.server_id(), 1);
+/// ```
+#[derive(Default)]
+pub struct ConfigurationBuilder {
+    server_id: Option<u64>,
+    addr: Option<(String, u16)>,
+    peers: Vec<PeerConfig>,
+    max_pool_size: Option<u32>,
+    io_pool_size: Option<u32>,
+    metrics_addr: Option<String>,
+    slow_query: Option<SlowQueryConfig>,
+    max_jobs: Option<u32>,
+    readiness: Option<Vec<String>>,
+    temp_dir: Option<String>,
+}
+
+impl ConfigurationBuilder {
+    pub fn server_id(mut self, server_id: u64) -> Self {
+        self.server_id = Some(server_id);
+        self
+    }
+
+    /// Set the address current server listens on, where port 0 means any available port
+    pub fn local_addr<S: Into<String>>(mut self, ip: S, port: u16) -> Self {
+        self.addr = Some((ip.into(), port));
+        self
+    }
+
+    /// Add a server of the cluster, including current server itself
+    pub fn add_peer<S: Into<String>>(mut self, server_id: u64, ip: S, port: u16) -> Self {
+        self.peers.push(PeerConfig { server_id, ip: ip.into(), port });
+        self
+    }
+
+    /// Set the number of threads to run the workers
+    pub fn max_pool_size(mut self, size: u32) -> Self {
+        self.max_pool_size = Some(size);
+        self
+    }
+
+    /// Set the number of threads doing the IO of the connections to other servers
+    pub fn io_pool_size(mut self, size: u32) -> Self {
+        self.io_pool_size = Some(size);
+        self
+    }
+
+    /// Set the address of the endpoint serving the metrics, where port 0 means any available port
+    pub fn metrics_addr<S: Into<String>>(mut self, addr: S) -> Self {
+        self.metrics_addr = Some(addr.into());
+        self
+    }
+
+    /// Enable the slow query log by the configuration
+    pub fn slow_query(mut self, conf: SlowQueryConfig) -> Self {
+        self.slow_query = Some(conf);
+        self
+    }
+
+    /// Set the most jobs running on current server at once
+    pub fn max_jobs(mut self, max_jobs: u32) -> Self {
+        self.max_jobs = Some(max_jobs);
+        self
+    }
+
+    /// Add a requirement of the readiness of current server, see `Configuration::readiness`
+    pub fn require_ready<S: Into<String>>(mut self, requirement: S) -> Self {
+        self.readiness.get_or_insert_with(Vec::new).push(requirement.into());
+        self
+    }
+
+    /// Set the root of the temp files and directories of the jobs, see `Configuration::temp_dir`
+    pub fn temp_dir<S: Into<String>>(mut self, dir: S) -> Self {
+        self.temp_dir = Some(dir.into());
+        self
+    }
+
+    pub fn build(self) -> Result<Configuration, StartupError> {
+        let ConfigurationBuilder {
+            server_id,
+            addr,
+            mut peers,
+            max_pool_size,
+            io_pool_size,
+            metrics_addr,
+            slow_query,
+            max_jobs,
+            readiness,
+            temp_dir,
+        } = self;
+        let network = if addr.is_none() && peers.is_empty() {
+            if let Some(server_id) = server_id {
+                let violation = format!("address of server {} is not set", server_id);
+                return Err(StartupError::InvalidConfig(vec![violation]));
+            }
+            None
+        } else {
+            let server_id = server_id.unwrap_or(0);
+            let (ip, port) = match addr {
+                Some(addr) => addr,
+                // listen on the address in peers by default
+                None => peers
+                    .iter()
+                    .find(|peer| peer.server_id == server_id)
+                    .map(|peer| (peer.ip.clone(), peer.port))
+                    .unwrap_or_else(|| ("127.0.0.1".to_owned(), 0)),
+            };
+            peers.sort_by_key(|peer| peer.server_id);
+            let mut net_conf = NetworkConfig::with_default_config(server_id, ip, port, peers);
+            if net_conf.peers.as_ref().map(|peers| peers.is_empty()).unwrap_or(true) {
+                // the servers are detected instead, see `startup_with`;
+                net_conf.peers = None;
+            }
+            Some(net_conf)
+        };
+        let conf = Configuration {
+            network,
+            max_pool_size,
+            io_pool_size,
+            metrics_addr,
+            slow_query,
+            max_jobs,
+            readiness,
+            temp_dir,
+        };
+        conf.validate()?;
+        Ok(conf)
+    }
+}
+
+/// How many workers per server a job runs with
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WorkerHint {
+    /// exactly the given number of workers;
+    Exact(u32),
+    /// a worker for each thread of the executor;
+    PerCore,
+    /// decided by the estimated input size of the job before submission, within `[min, max]`,
+    /// or `max` if the size is unknown;
+    Auto { min: u32, max: u32 },
+}
+
+/// What to do if a count or sum of a job overflows
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum OverflowPolicy {
+    /// stick to the bound of the type, e.g. `u64::MAX` of a count;
+    Saturate,
+    /// fail the job with an error naming the overflowed step;
+    #[default]
+    Error,
+}
+
+/// What a job trades for, the time of its first results or its throughput
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum LatencyMode {
+    /// the data wait in the partial batches of the outputs and the channels until the batches are
+    /// full, flushed by `JobConf::batch_flush_interval_ms`, or the operators yield;
+    #[default]
+    Throughput,
+    /// each record goes downstream as soon as it is output, in a batch of its own, and the sources
+    /// yield after each record, e.g. for a `limit(1)` query waiting for its first result only;
+    /// the scopes and their ends are as they are;
+    Eager,
+}
+
+impl OverflowPolicy {
+    /// Add up two counts by the policy, or `None` if it overflows while the policy is `Error`;
+    pub fn add_count(&self, left: u64, right: u64) -> Option<u64> {
+        match self {
+            OverflowPolicy::Saturate => Some(left.saturating_add(right)),
+            OverflowPolicy::Error => left.checked_add(right),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct JobConf {
     /// unique identifier of the job;
@@ -63,8 +418,18 @@ pub struct JobConf {
     pub time_limit: u64,
     /// the size used to batching streaming data;
     pub batch_size: u32,
-    /// the size used to limit each operator's output size per-schedule;
+    /// the size used to limit each operator's output size per-schedule, i.e. the most batches in
+    /// flight of each output port, which is at least `MIN_OUTPUT_CAPACITY`, or
+    /// `MIN_BINARY_OUTPUT_CAPACITY` if the job has any merge or join, or the job fails to build;
     pub output_capacity: u32,
+    /// the most milliseconds the data wait in a partial batch of an output before being flushed,
+    /// when the sources also yield to let the flushed data go downstream, e.g. a small interval
+    /// for the interactive queries to give their first results quickly; 0 means the partial
+    /// batches are only flushed once the operators yield by themselves;
+    pub batch_flush_interval_ms: u64,
+    /// what the job trades for, where the eager mode overrides the `batch_size` and the
+    /// `batch_flush_interval_ms`, see `LatencyMode`;
+    pub latency_mode: LatencyMode,
     /// the most memory(MB) this job can use in each server;
     pub memory_limit: u32,
     /// set to print runtime dataflow plan before running;
@@ -73,6 +438,99 @@ pub struct JobConf {
     servers: Vec<u64>,
     /// set enable trace job run progress;
     pub trace_enable: bool,
+    /// set to record the events each worker of this job sends to and receives from the others,
+    /// e.g. the ends of the scopes, which are dumped by `pegasus::dump_job_trace` to debug the
+    /// job hanging, or into the log once the job exceeds its time limit;
+    pub wire_trace: bool,
+    /// set to capture the logs of the workers of this job, which are fetched by
+    /// `pegasus::fetch_job_logs` and attached to the errors of the job;
+    pub capture_logs: bool,
+    /// set to measure the wall time and the records received of each operator of this job, which
+    /// are fetched by `pegasus::fetch_job_profile` once the job ends;
+    pub profile: bool,
+    /// the most bytes each worker of this job can use to cache the data read from the graph,
+    /// e.g. the adjacency lists, 0 means no cache;
+    pub cache_limit: u64,
+    /// the most milliseconds a property value read from the graph is served from the property
+    /// cache of the worker after it is read, i.e. how stale the values this job reads may be;
+    /// 0 means no cache;
+    pub property_cache_ttl_ms: u64,
+    /// set to always read the property values from the graph, bypassing the property cache,
+    /// whose values are refreshed by the reads of this job for the later ones;
+    pub fresh_reads: bool,
+    /// set to merge the equal data into one with a bulk, after they are exchanged between workers;
+    pub bulking: bool,
+    /// the most distinct results the sink of the job merges into (value, count) pairs in each
+    /// scope, emitted once the scope ends, beyond which the results pass through as they are;
+    /// 0 means no consolidation;
+    pub sink_consolidation: u64,
+    /// the most outputs a flat map expands of one datum on one worker, e.g. the edges of a vertex
+    /// expanded by a step of a graph, beyond which the rest are split into ranges expanded by the
+    /// other workers; 0 means never split;
+    pub split_degree: u64,
+    /// set to split the outputs only among the workers of the same server, instead of all the
+    /// workers of the job, which saves the network at the cost of less parallelism;
+    pub split_local: bool,
+    /// the version of a mutation of the graph the job must read, e.g. one the client has just
+    /// made, whose snapshot is pinned at this version at least; 0 means any version;
+    pub min_version: u64,
+    /// the most milliseconds the job waits for the graph to catch up with `min_version`, e.g. a
+    /// replica lagging behind the mutation, before it fails;
+    pub min_version_wait_ms: u64,
+    /// the most vertices, edges and results the job can access or return in each server, which
+    /// are checked by the operators themselves, 0 means following the default of the server;
+    pub vertex_limit: u64,
+    pub edge_limit: u64,
+    pub result_limit: u64,
+    /// the most bytes the job sends to and receives from the other servers in each server,
+    /// beyond which the job fails with `NetworkLimitExceeded`, see `pegasus::net_usage`; 0 means
+    /// no limit;
+    pub network_byte_limit: u64,
+    /// what to do if a count or sum of the job overflows;
+    pub overflow: OverflowPolicy,
+    /// warn once a worker receives more than this multiple of the median of the records all
+    /// workers receive through an exchange, which suggests to aggregate the skewed keys in two
+    /// stages, e.g. by `group_with_combine`; 0 means no detection;
+    pub skew_factor: u32,
+    /// the session the job runs in, whose named results are shared with the other jobs of the
+    /// session, 0 means no session;
+    pub session_id: u64,
+    /// the most records each sort of the job holds in memory for a scope, beyond which they are
+    /// sorted and spilled into a temp file as a run, to be merged with the other runs on emission;
+    /// 0 means never spill;
+    pub sort_spill_limit: u64,
+    /// the most records the receive buffers of each input of the job hold in memory in each worker,
+    /// i.e. the batches stashed for the scopes not consumed yet, beyond which the batches arriving
+    /// from the exchanges are spilled into temp files, and replayed in the order they arrive once
+    /// the consumer catches up; 0 means never spill, except the channels opting in by
+    /// `Channel::spill_on_pressure`, which spill beyond `DEFAULT_EXCHANGE_SPILL_LIMIT`;
+    pub exchange_spill_limit: u64,
+    /// the directory the job spills into, or the temp directory of the system if `None`, where
+    /// the files of the job are removed once all its workers in current server end;
+    pub spill_dir: Option<PathBuf>,
+    /// the most records an operator receives in each firing, after which it yields to let the
+    /// other operators and jobs on the thread go, and is fired again for the rest of its input
+    /// later; 0 means no limit;
+    pub slice_records: u64,
+    /// the most microseconds an operator runs in each firing before yielding as above, e.g. a
+    /// small slice for the batch jobs sharing the threads with the interactive ones; 0 means no
+    /// limit;
+    pub slice_us: u64,
+    /// the hash of the plan of this job, e.g. set by the server from the plan it receives, which
+    /// tells the runs of the same query in the slow query log; 0 means unknown;
+    pub plan_hash: u64,
+    /// the most milliseconds the first server of this job waits for the others to prepare it, if
+    /// it runs on more than one server, after which it is aborted on all, see `crate::submit`;
+    pub submit_timeout_ms: u64,
+    /// the seed the random numbers of this job are drawn from, e.g. by `sample` and `coin`, where
+    /// each operator of each worker draws from its own stream of the seed, see `pegasus::rng`;
+    /// `None` means a seed drawn from the entropy in each server, which is reported by
+    /// `pegasus::fetch_job_rng_seed` to reproduce the job;
+    pub rng_seed: Option<u64>,
+    /// how the number of workers is decided, see `JobConf::workers`;
+    worker_hint: WorkerHint,
+    /// the read-only data shared with all operators of the job, see `JobConf::set_user_data`;
+    pub(crate) resources: JobResources,
 }
 
 impl JobConf {
@@ -80,10 +538,65 @@ impl JobConf {
         let mut conf = JobConf::default();
         conf.job_id = job_id;
         conf.job_name = name.into();
-        conf.workers = workers;
+        conf.workers(WorkerHint::Exact(workers));
         conf
     }
 
+    /// Set the number of workers per server by the hint, where `Auto` is finally decided by
+    /// `resolve_workers` with the estimated input size.
+    pub fn workers(&mut self, hint: WorkerHint) -> &mut Self {
+        self.worker_hint = hint;
+        self.workers = match hint {
+            WorkerHint::Exact(workers) => workers,
+            WorkerHint::PerCore => pegasus_executor::get_core_pool_size().max(1) as u32,
+            WorkerHint::Auto { max, .. } => max,
+        };
+        self
+    }
+
+    pub fn get_worker_hint(&self) -> WorkerHint {
+        self.worker_hint
+    }
+
+    /// Share the value of the key with all operators of the job on all workers, which is got by
+    /// `pegasus::get_job_resource` inside the functions of the operators, and dropped once the job
+    /// ends; The value of a key set before is replaced;
+    pub fn set_user_data<K: Into<String>, T: Any + Send + Sync>(
+        &mut self, key: K, value: T,
+    ) -> &mut Self {
+        self.resources.insert(key.into(), value);
+        self
+    }
+
+    /// Decide the number of workers of `Auto` by the number of workers the input deserves, which
+    /// is estimated by the size of input, or keep the `max` if `None`.
+    pub fn resolve_workers(&mut self, estimated: Option<u32>) {
+        if let WorkerHint::Auto { min, max } = self.worker_hint {
+            let max = std::cmp::max(min, max);
+            self.workers = estimated.map(|w| w.max(min).min(max)).unwrap_or(max);
+        }
+    }
+
+    /// The size of the batches of the outputs and the channels, which is 1 in the eager mode;
+    pub fn effective_batch_size(&self) -> u32 {
+        match self.latency_mode {
+            LatencyMode::Throughput => self.batch_size,
+            LatencyMode::Eager => 1,
+        }
+    }
+
+    /// The interval of flushing the partial batches, which is zero in the eager mode, i.e. each
+    /// record is flushed once output, or `None` if not set;
+    pub fn flush_interval(&self) -> Option<Duration> {
+        if self.latency_mode == LatencyMode::Eager {
+            Some(Duration::from_millis(0))
+        } else if self.batch_flush_interval_ms > 0 {
+            Some(Duration::from_millis(self.batch_flush_interval_ms))
+        } else {
+            None
+        }
+    }
+
     pub fn servers(&self) -> &[u64] {
         &self.servers
     }
@@ -92,6 +605,11 @@ impl JobConf {
         self.servers.extend_from_slice(servers);
     }
 
+    /// Run on current server only, e.g. to build the dataflow of the job without any other server;
+    pub(crate) fn clear_servers(&mut self) {
+        self.servers.clear();
+    }
+
     pub fn total_workers(&self) -> usize {
         if self.servers.is_empty() {
             return self.workers as usize;
@@ -110,10 +628,200 @@ impl Default for JobConf {
             time_limit: !0,
             batch_size: 1024,
             output_capacity: 64,
+            batch_flush_interval_ms: 0,
+            latency_mode: LatencyMode::Throughput,
             memory_limit: !0u32,
             plan_print: false,
             servers: vec![],
             trace_enable: false,
+            wire_trace: false,
+            capture_logs: false,
+            profile: false,
+            cache_limit: 0,
+            property_cache_ttl_ms: 0,
+            fresh_reads: false,
+            bulking: false,
+            sink_consolidation: 0,
+            split_degree: 0,
+            split_local: false,
+            min_version: 0,
+            min_version_wait_ms: 1000,
+            vertex_limit: 0,
+            edge_limit: 0,
+            result_limit: 0,
+            network_byte_limit: 0,
+            overflow: OverflowPolicy::Error,
+            skew_factor: 0,
+            session_id: 0,
+            sort_spill_limit: 0,
+            exchange_spill_limit: 0,
+            spill_dir: None,
+            slice_records: 0,
+            slice_us: 0,
+            plan_hash: 0,
+            submit_timeout_ms: 10_000,
+            rng_seed: None,
+            worker_hint: WorkerHint::Exact(1),
+            resources: JobResources::default(),
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn violations(result: Result<Configuration, StartupError>) -> Vec<String> {
+        match result {
+            Err(StartupError::InvalidConfig(violations)) => violations,
+            Err(e) => panic!("unexpected error {}", e),
+            Ok(_) => panic!("the configuration is expected to be invalid"),
+        }
+    }
+
+    #[test]
+    fn build_config_test() {
+        let conf = Configuration::builder().max_pool_size(4).io_pool_size(1).build().unwrap();
+        assert!(conf.network.is_none());
+        assert_eq!(conf.max_pool_size, Some(4));
+        assert_eq!(conf.io_pool_size, Some(1));
+        let conf = Configuration::builder()
+            .server_id(1)
+            .add_peer(1, "127.0.0.1", 8081)
+            .add_peer(0, "127.0.0.1", 8080)
+            .build()
+            .unwrap();
+        let net_conf = conf.network_config().unwrap();
+        assert_eq!(net_conf.port, 8081);
+        let peers = net_conf.get_peers().unwrap().unwrap();
+        assert_eq!(peers.iter().map(|p| p.id).collect::<Vec<_>>(), vec![0, 1]);
+    }
+
+    #[test]
+    fn invalid_pool_size_test() {
+        let violations = violations(Configuration::builder().max_pool_size(0).build());
+        assert_eq!(violations, vec!["max_pool_size must be positive".to_owned()]);
+    }
+
+    #[test]
+    fn server_out_of_peers_test() {
+        let result = Configuration::builder()
+            .server_id(2)
+            .local_addr("127.0.0.1", 8082)
+            .add_peer(0, "127.0.0.1", 8080)
+            .add_peer(1, "127.0.0.1", 8081)
+            .build();
+        assert_eq!(violations(result), vec!["server 2 is not found in peers".to_owned()]);
+    }
+
+    #[test]
+    fn invalid_peers_test() {
+        let result = Configuration::builder()
+            .server_id(0)
+            .local_addr("localhost", 8080)
+            .add_peer(0, "127.0.0.1", 8080)
+            .add_peer(5, "127.0.0.1", 8085)
+            .add_peer(1, "127.0.0.1", 8080)
+            .add_peer(1, "127.0.0", 0)
+            .max_pool_size(0)
+            .build();
+        // all violations are reported at once
+        assert_eq!(
+            violations(result),
+            vec![
+                "max_pool_size must be positive".to_owned(),
+                "ip 'localhost' of server 0 is invalid".to_owned(),
+                "address 127.0.0.1:8080 of peer 1 is used by another peer".to_owned(),
+                "peer 1 is configured more than once".to_owned(),
+                "port of peer 1 must be positive".to_owned(),
+                "ip '127.0.0' of peer 1 is invalid".to_owned(),
+                "id of peer 5 is out of the range [0, 4) of 4 servers".to_owned(),
+            ]
+        );
+    }
+
+    #[test]
+    fn inconsistent_port_test() {
+        let result = Configuration::builder()
+            .server_id(0)
+            .local_addr("127.0.0.1", 9090)
+            .add_peer(0, "127.0.0.1", 8080)
+            .build();
+        assert_eq!(
+            violations(result),
+            vec!["port 9090 of server 0 is different from 8080 in peers".to_owned()]
+        );
+    }
+
+    #[test]
+    fn missing_addr_test() {
+        let violations = violations(Configuration::builder().server_id(1).build());
+        assert_eq!(violations, vec!["address of server 1 is not set".to_owned()]);
+    }
+
+    #[test]
+    fn from_toml_test() {
+        let content = r#"
+            max_pool_size = 8
+
+            [network]
+            server_id = 1
+            ip = '127.0.0.1'
+            port = 8081
+
+            [[network.peers]]
+            server_id = 0
+            ip = '127.0.0.1'
+            port = 8080
+
+            [[network.peers]]
+            server_id = 1
+            ip = '127.0.0.1'
+            port = 8081
+        "#;
+        let path = std::env::temp_dir().join("pegasus_from_toml_test.toml");
+        std::fs::write(&path, content).unwrap();
+        let conf = Configuration::from_toml(&path).unwrap();
+        assert_eq!(conf.server_id(), 1);
+        assert_eq!(conf.max_pool_size, Some(8));
+        // the id of the local server only, which is ahead of the peers;
+        let content = content.replacen("server_id = 1", "server_id = 3", 1);
+        std::fs::write(&path, content).unwrap();
+        match Configuration::from_toml(&path) {
+            Err(StartupError::InvalidConfig(violations)) => {
+                assert_eq!(violations, vec!["server 3 is not found in peers".to_owned()]);
+            }
+            _ => panic!("the configuration is expected to be invalid"),
+        }
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn worker_hint_test() {
+        let mut conf = JobConf::new(1, "worker_hint_test", 2);
+        assert_eq!(conf.get_worker_hint(), WorkerHint::Exact(2));
+        conf.resolve_workers(Some(8));
+        assert_eq!(conf.workers, 2);
+        conf.workers(WorkerHint::Auto { min: 2, max: 4 });
+        assert_eq!(conf.workers, 4);
+        conf.resolve_workers(Some(1));
+        assert_eq!(conf.workers, 2);
+        conf.resolve_workers(Some(3));
+        assert_eq!(conf.workers, 3);
+        conf.resolve_workers(Some(100));
+        assert_eq!(conf.workers, 4);
+        conf.resolve_workers(None);
+        assert_eq!(conf.workers, 4);
+        conf.workers(WorkerHint::PerCore);
+        assert!(conf.workers >= 1);
+    }
+
+    #[test]
+    fn overflow_policy_test() {
+        let near_max = u64::MAX - 1;
+        assert_eq!(OverflowPolicy::Error.add_count(near_max, 1), Some(u64::MAX));
+        assert_eq!(OverflowPolicy::Error.add_count(near_max, 2), None);
+        assert_eq!(OverflowPolicy::Saturate.add_count(near_max, 2), Some(u64::MAX));
+        assert_eq!(OverflowPolicy::Saturate.add_count(u64::MAX, u64::MAX), Some(u64::MAX));
+    }
+}
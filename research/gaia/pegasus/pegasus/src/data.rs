@@ -17,6 +17,7 @@ use crate::tag::Tag;
 use crossbeam_channel::Sender;
 use pegasus_common::codec::{Decode, Encode};
 use pegasus_common::io::{ReadExt, WriteExt};
+use pegasus_network::BatchCodec;
 use std::fmt::Debug;
 
 pub trait Data: Clone + Send + Debug + Encode + Decode + 'static {}
@@ -26,22 +27,24 @@ pub struct DataSet<T> {
     pub tag: Tag,
     data: Vec<T>,
     recycle_hook: Option<Sender<Vec<T>>>,
+    // the producer and the sequence of the batch in an order-preserving channel;
+    seq: Option<(u32, u64)>,
 }
 
 impl<D> DataSet<D> {
     #[inline]
     pub fn empty() -> Self {
-        DataSet { tag: crate::tag::ROOT.clone(), data: Vec::new(), recycle_hook: None }
+        DataSet { tag: crate::tag::ROOT.clone(), data: Vec::new(), recycle_hook: None, seq: None }
     }
 
     pub fn new<T: Into<Tag>>(tag: T, data: Vec<D>) -> Self {
         let tag: Tag = tag.into();
-        DataSet { tag, data, recycle_hook: None }
+        DataSet { tag, data, recycle_hook: None, seq: None }
     }
 
     pub fn with_hook<T: Into<Tag>>(tag: T, data: Vec<D>, creator_hook: &Sender<Vec<D>>) -> Self {
         let tag: Tag = tag.into();
-        DataSet { tag, data, recycle_hook: Some(creator_hook.clone()) }
+        DataSet { tag, data, recycle_hook: Some(creator_hook.clone()), seq: None }
     }
 
     #[inline]
@@ -54,6 +57,18 @@ impl<D> DataSet<D> {
         &mut self.data
     }
 
+    /// Get the producer and the sequence of the batch if it is sent by an order-preserving
+    /// channel, see `Channel::preserve_order`
+    #[inline]
+    pub fn get_seq(&self) -> Option<(u32, u64)> {
+        self.seq
+    }
+
+    #[inline]
+    pub(crate) fn set_seq(&mut self, seq: Option<(u32, u64)>) {
+        self.seq = seq;
+    }
+
     #[inline]
     pub fn drain_into(&mut self) -> DataSetIter<D> {
         DataSetIter {
@@ -101,27 +116,47 @@ impl<T> AsRef<Tag> for DataSet<T> {
     }
 }
 
-impl<D: Data> Encode for DataSet<D> {
-    fn write_to<W: WriteExt>(&self, writer: &mut W) -> std::io::Result<()> {
+impl<D: Data> DataSet<D> {
+    /// Encode the batch by the codec, as the tag followed by the header and the records written
+    /// by the codec;
+    pub fn write_with<C: BatchCodec, W: WriteExt>(
+        &self, codec: &C, writer: &mut W,
+    ) -> std::io::Result<()> {
         self.tag.write_to(writer)?;
-        writer.write_u32(self.data.len() as u32)?;
+        codec.write_header(self.seq, self.data.len(), writer)?;
         for item in self.data.iter() {
-            item.write_to(writer)?;
+            codec.write_record(item, writer)?;
         }
         Ok(())
     }
-}
 
-impl<D: Data> Decode for DataSet<D> {
-    fn read_from<R: ReadExt>(reader: &mut R) -> ::std::io::Result<Self> {
+    /// Decode the batch encoded by `write_with` of the same codec;
+    pub fn read_with<C: BatchCodec, R: ReadExt>(
+        codec: &C, reader: &mut R,
+    ) -> std::io::Result<Self> {
         let tag = Tag::read_from(reader)?;
-        let len = reader.read_u32()? as usize;
+        let (seq, len) = codec.read_header(reader)?;
         let mut data = Vec::with_capacity(len);
         for _ in 0..len {
-            let item = D::read_from(reader)?;
+            let item = codec.read_record(reader)?;
             data.push(item);
         }
-        Ok(DataSet::new(tag, data))
+        let mut dataset = DataSet::new(tag, data);
+        dataset.seq = seq;
+        Ok(dataset)
+    }
+}
+
+// the batches are only encoded to be sent to other servers, by the codec of current process;
+impl<D: Data> Encode for DataSet<D> {
+    fn write_to<W: WriteExt>(&self, writer: &mut W) -> std::io::Result<()> {
+        self.write_with(&pegasus_network::wire_codec(), writer)
+    }
+}
+
+impl<D: Data> Decode for DataSet<D> {
+    fn read_from<R: ReadExt>(reader: &mut R) -> ::std::io::Result<Self> {
+        DataSet::read_with(&pegasus_network::wire_codec(), reader)
     }
 }
 
@@ -131,6 +166,7 @@ impl<D: Data> Clone for DataSet<D> {
             tag: self.tag.clone(),
             data: self.data.clone(),
             recycle_hook: self.recycle_hook.clone(),
+            seq: self.seq,
         }
     }
 }
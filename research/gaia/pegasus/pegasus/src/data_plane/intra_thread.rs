@@ -1,12 +1,12 @@
 //
 //! Copyright 2020 Alibaba Group Holding Limited.
-//! 
+//!
 //! Licensed under the Apache License, Version 2.0 (the "License");
 //! you may not use this file except in compliance with the License.
 //! You may obtain a copy of the License at
-//! 
+//!
 //! http://www.apache.org/licenses/LICENSE-2.0
-//! 
+//!
 //! Unless required by applicable law or agreed to in writing, software
 //! distributed under the License is distributed on an "AS IS" BASIS,
 //! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
@@ -16,6 +16,7 @@
 use crate::channel_id::SubChannelId;
 use crate::data_plane::{Pull, Push};
 use crate::errors::{IOError, IOErrorKind};
+use crate::warm::WarmHandle;
 use crossbeam_utils::CachePadded;
 use std::collections::VecDeque;
 use std::io;
@@ -63,20 +64,25 @@ impl<T: Send> Push<T> for ThreadPush<T> {
     }
 }
 
+/// The warm set a queue is returned to, and how it is returned;
+type Recycle<T> = (WarmHandle, fn(&WarmHandle, VecDeque<T>));
+
 pub struct ThreadPull<T> {
     pub id: SubChannelId,
     ptr: NonNull<VecDeque<T>>,
     exhaust: Arc<CachePadded<AtomicBool>>,
     exhaust_local: bool,
     closed: Arc<CachePadded<AtomicBool>>,
+    // the warm set the queue is returned to once the pull is dropped, see `crate::warm`;
+    recycle: Option<Recycle<T>>,
 }
 
 impl<T> ThreadPull<T> {
     fn new(
         id: SubChannelId, ptr: NonNull<VecDeque<T>>, exhaust: Arc<CachePadded<AtomicBool>>,
-        closed: Arc<CachePadded<AtomicBool>>,
+        closed: Arc<CachePadded<AtomicBool>>, recycle: Option<Recycle<T>>,
     ) -> Self {
-        ThreadPull { id, ptr, exhaust, exhaust_local: false, closed }
+        ThreadPull { id, ptr, exhaust, exhaust_local: false, closed, recycle }
     }
 }
 
@@ -113,21 +119,27 @@ impl<T: Send> Pull<T> for ThreadPull<T> {
 impl<T> Drop for ThreadPull<T> {
     fn drop(&mut self) {
         self.closed.store(true, Ordering::SeqCst);
-        unsafe {
-            std::ptr::drop_in_place(self.ptr.as_ptr());
+        let queue = unsafe { Box::from_raw(self.ptr.as_ptr()) };
+        if let Some((handle, give)) = self.recycle.take() {
+            give(&handle, *queue);
         }
     }
 }
 
 unsafe impl<T: Send> Send for ThreadPull<T> {}
 
-pub fn pipeline<T>(id: SubChannelId) -> (ThreadPush<T>, ThreadPull<T>) {
-    let queue = Box::new(VecDeque::new());
-    let ptr =
-        NonNull::new(Box::into_raw(queue)).expect("inter thread communication_old init failure;");
+/// Create a pipeline whose queue is taken from the warm set of the worker being built if any,
+/// and returned to it once the pull is dropped;
+pub fn pipeline<T: Send + 'static>(id: SubChannelId) -> (ThreadPush<T>, ThreadPull<T>) {
+    let (queue, handle) = crate::warm::take_queue::<T>();
+    let recycle =
+        handle.map(|handle| (handle, crate::warm::give_queue::<T> as fn(&WarmHandle, VecDeque<T>)));
+    let ptr = NonNull::new(Box::into_raw(Box::new(queue)))
+        .expect("inter thread communication_old init failure;");
     let exhaust = Arc::new(CachePadded::new(AtomicBool::new(false)));
     let closed = Arc::new(CachePadded::new(AtomicBool::new(false)));
-    (ThreadPush::new(id, ptr, &exhaust, &closed), ThreadPull::new(id, ptr, exhaust, closed))
+    let push = ThreadPush::new(id, ptr, &exhaust, &closed);
+    (push, ThreadPull::new(id, ptr, exhaust, closed, recycle))
 }
 
 #[cfg(test)]
@@ -13,7 +13,7 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
-use crate::api::meta::{OperatorMeta, ScopePrior};
+use crate::api::meta::{OperatorKind, OperatorMeta, ScopePrior};
 use crate::errors::BuildJobError;
 use crate::event::EventBus;
 use crate::graph::{Edge, LogicalGraph};
@@ -21,6 +21,7 @@ use crate::operator::{OperatorBuilder, OperatorCore};
 use crate::schedule::OpRuntime;
 use crate::{JobConf, WorkerId};
 use std::cell::{RefCell, RefMut};
+use std::collections::HashSet;
 use std::fmt::Write;
 use std::rc::Rc;
 use std::sync::Arc;
@@ -32,6 +33,8 @@ pub struct DataflowBuilder {
     ch_index: Rc<RefCell<u32>>,
     operators: Rc<RefCell<Vec<OperatorBuilder>>>,
     edges: Rc<RefCell<Vec<Edge>>>,
+    // the names given to the operators being constructed, innermost last, see `named`
+    names: Rc<RefCell<Vec<String>>>,
 }
 
 impl DataflowBuilder {
@@ -43,6 +46,7 @@ impl DataflowBuilder {
             edges: Rc::new(RefCell::new(vec![])),
             event_bus: event_bus.clone(),
             ch_index: Rc::new(RefCell::new(1)),
+            names: Rc::new(RefCell::new(vec![])),
         }
     }
 
@@ -64,6 +68,15 @@ impl DataflowBuilder {
         *idx - 1
     }
 
+    /// Name the operators constructed by `func` after `name` instead of their builtin names,
+    /// e.g. after the step they are compiled from, except those named by a nested call;
+    pub fn named<T, F: FnOnce() -> T>(&self, name: &str, func: F) -> T {
+        self.names.borrow_mut().push(name.to_owned());
+        let result = func();
+        self.names.borrow_mut().pop();
+        result
+    }
+
     pub fn construct_operator<F>(
         &self, name: &str, scope_depth: usize, order: ScopePrior, construct: F,
     ) -> OperatorRef
@@ -71,7 +84,8 @@ impl DataflowBuilder {
         F: FnOnce(&mut OperatorMeta) -> Box<dyn OperatorCore>,
     {
         let index = self.operators.borrow().len();
-        let mut meta = OperatorMeta::new(name, self.worker_id, &self.config);
+        let name = self.names.borrow().last().cloned().unwrap_or_else(|| name.to_owned());
+        let mut meta = OperatorMeta::new(&name, self.worker_id, &self.config);
         meta.set_scope_depth(scope_depth).set_scope_order(order.clone()).set_index(index);
         let core = construct(&mut meta);
         let op_b = OperatorBuilder::new(meta, core, &self.event_bus);
@@ -85,6 +99,32 @@ impl DataflowBuilder {
         self.edges.borrow_mut().push(edge);
     }
 
+    /// Describe the operators and the channels constructed by now, in the order of the operators;
+    pub(crate) fn describe(&self) -> PlanDesc {
+        let mut operators: Vec<OperatorDesc> = self
+            .operators
+            .borrow()
+            .iter()
+            .map(|op| OperatorDesc {
+                index: op.index(),
+                name: op.meta.name.clone(),
+                scope_depth: op.meta.scope_depth,
+            })
+            .collect();
+        operators.sort_by_key(|op| op.index);
+        let channels = self
+            .edges
+            .borrow()
+            .iter()
+            .map(|e| ChannelDesc {
+                source: e.source.index,
+                target: e.target.index,
+                is_local: e.is_local,
+            })
+            .collect();
+        PlanDesc { operators, channels }
+    }
+
     pub(crate) fn build(self) -> Result<Dataflow, BuildJobError> {
         let report =
             self.worker_id.index == 0 && (self.config.plan_print || self.config.trace_enable);
@@ -97,6 +137,14 @@ impl DataflowBuilder {
 
         let mut builds = self.operators.replace(vec![]);
         builds.sort_by_key(|op| op.index());
+        let edges = self.edges.replace(vec![]);
+        let shapes: Vec<OperatorShape> = builds.iter().map(OperatorShape::of).collect();
+        let warnings = validate_topology(&shapes, &edges)?;
+        if self.worker_id.index == 0 {
+            for warning in warnings {
+                warn_worker!("{}", warning);
+            }
+        }
         let mut operators = Vec::with_capacity(builds.len());
         for (i, op_b) in builds.drain(..).enumerate() {
             assert_eq!(i, op_b.index());
@@ -106,7 +154,6 @@ impl DataflowBuilder {
             }
             operators.push(Some(OpRuntime::new(op)));
         }
-        let edges = self.edges.replace(vec![]);
         if report {
             writeln!(plan_desc, "Channels ").ok();
             for e in edges.iter() {
@@ -123,6 +170,186 @@ impl DataflowBuilder {
     }
 }
 
+/// An operator of the dataflow described by `PlanDesc`;
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OperatorDesc {
+    pub index: usize,
+    pub name: String,
+    /// The depth of the scope the operator runs in, e.g. 1 in the body of an iteration;
+    pub scope_depth: usize,
+}
+
+/// A channel of the dataflow described by `PlanDesc`, from the operator of index `source` to that
+/// of `target`;
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChannelDesc {
+    pub source: usize,
+    pub target: usize,
+    /// Whether the records stay on the worker, i.e. by a pipeline, instead of being exchanged;
+    pub is_local: bool,
+}
+
+/// The dataflow of a job as built on a worker, which is told by `pegasus::explain` without
+/// running the job;
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PlanDesc {
+    pub operators: Vec<OperatorDesc>,
+    pub channels: Vec<ChannelDesc>,
+}
+
+/// What the topology of a dataflow is validated by of an operator;
+struct OperatorShape {
+    index: usize,
+    name: String,
+    scope_depth: usize,
+    outputs: usize,
+    feedback: bool,
+    enters_scope: bool,
+    sink: bool,
+}
+
+impl OperatorShape {
+    fn of(op: &OperatorBuilder) -> Self {
+        OperatorShape {
+            index: op.index(),
+            name: op.meta.name.clone(),
+            scope_depth: op.meta.scope_depth,
+            outputs: op.output_ports(),
+            feedback: op.meta.feedback,
+            enters_scope: op.meta.scope_entry,
+            sink: op.meta.kind == OperatorKind::Sink,
+        }
+    }
+}
+
+impl std::fmt::Display for OperatorShape {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}[{}]", self.name, self.index)
+    }
+}
+
+/// Validate the topology of a dataflow once it is constructed, which may hang the job otherwise:
+/// a cycle of the operators not closed by the feedback of an iteration never terminates, nor
+/// does a stream consumed in a scope other than the one it is produced in, i.e. without entering
+/// or leaving the scope, nor does a scope entered by `enter_scope` but not left on a path, whose
+/// data never leave it, nor is a dataflow without any sink, whose results go nowhere; Either
+/// fails the build naming the operators, while the outputs consumed by no operator, whose records
+/// are dropped, are returned as warnings;
+fn validate_topology(
+    operators: &[OperatorShape], edges: &[Edge],
+) -> Result<Vec<String>, BuildJobError> {
+    for edge in edges.iter() {
+        let (source, target) = (&operators[edge.source.index], &operators[edge.target.index]);
+        if edge.scope_depth != target.scope_depth {
+            return BuildJobError::unsupported(format!(
+                "operator {} in scope depth {} consumes the stream of operator {} in scope \
+                 depth {} without entering or leaving the scope;",
+                target, target.scope_depth, source, edge.scope_depth
+            ));
+        }
+    }
+
+    let mut next = vec![vec![]; operators.len()];
+    for edge in edges.iter().filter(|e| !operators[e.source.index].feedback) {
+        next[edge.source.index].push(edge.target.index);
+    }
+    if let Some(cycle) = find_cycle(&next) {
+        let cycle: Vec<String> = cycle.iter().map(|i| operators[*i].to_string()).collect();
+        return BuildJobError::unsupported(format!(
+            "operators {} form a cycle not closed by an iteration, which never terminates;",
+            cycle.join(" -> ")
+        ));
+    }
+
+    let mut outgoing = vec![vec![]; operators.len()];
+    for edge in edges.iter() {
+        outgoing[edge.source.index].push(edge);
+    }
+    for enter in operators.iter().filter(|op| op.enters_scope) {
+        if let Some(end) = find_unleft_path(enter.index, enter.scope_depth + 1, &outgoing) {
+            return BuildJobError::unsupported(format!(
+                "the scope entered by operator {} is not left by leave_scope before operator {};",
+                enter, operators[end]
+            ));
+        }
+    }
+
+    if !operators.iter().any(|op| op.sink) {
+        return BuildJobError::unsupported(
+            "the dataflow has no sink, whose results are delivered nowhere; add one by sink_by;",
+        );
+    }
+
+    let consumed: HashSet<(usize, usize)> =
+        edges.iter().map(|e| (e.source.index, e.source.port)).collect();
+    let mut warnings = vec![];
+    for op in operators.iter() {
+        for port in 0..op.outputs {
+            if !consumed.contains(&(op.index, port)) {
+                warnings
+                    .push(format!("output {} of operator {} is consumed by nothing;", port, op));
+            }
+        }
+    }
+    Ok(warnings)
+}
+
+/// Find a cycle of the graph given by the successors of each vertex, as the vertices along it,
+/// starting and ending with the same one;
+fn find_cycle(next: &[Vec<usize>]) -> Option<Vec<usize>> {
+    // 0 for the vertices unvisited, 1 for those on the path, 2 for those done
+    let mut states = vec![0u8; next.len()];
+    let mut path = vec![];
+    (0..next.len()).find_map(|v| {
+        if states[v] == 0 {
+            visit(v, next, &mut states, &mut path)
+        } else {
+            None
+        }
+    })
+}
+
+/// Find the operator ending a path from the operator `enter` with the stream still in the scope of
+/// depth `depth`, i.e. consumed by no operator, whereas a path leaving the scope is done;
+fn find_unleft_path(enter: usize, depth: usize, outgoing: &[Vec<&Edge>]) -> Option<usize> {
+    let mut visited = HashSet::new();
+    let mut stack = vec![enter];
+    visited.insert(enter);
+    while let Some(op) = stack.pop() {
+        if outgoing[op].is_empty() {
+            return Some(op);
+        }
+        for edge in outgoing[op].iter().filter(|e| e.scope_depth >= depth) {
+            if visited.insert(edge.target.index) {
+                stack.push(edge.target.index);
+            }
+        }
+    }
+    None
+}
+
+fn visit(
+    v: usize, next: &[Vec<usize>], states: &mut [u8], path: &mut Vec<usize>,
+) -> Option<Vec<usize>> {
+    states[v] = 1;
+    path.push(v);
+    for n in next[v].iter() {
+        if states[*n] == 1 {
+            let start = path.iter().position(|p| p == n).unwrap_or(0);
+            let mut cycle = path[start..].to_vec();
+            cycle.push(*n);
+            return Some(cycle);
+        } else if states[*n] == 0 {
+            if let Some(cycle) = visit(*n, next, states, path) {
+                return Some(cycle);
+            }
+        }
+    }
+    path.pop();
+    states[v] = 2;
+    None
+}
+
 impl Clone for DataflowBuilder {
     fn clone(&self) -> Self {
         DataflowBuilder {
@@ -132,6 +359,7 @@ impl Clone for DataflowBuilder {
             edges: self.edges.clone(),
             event_bus: self.event_bus.clone(),
             ch_index: self.ch_index.clone(),
+            names: self.names.clone(),
         }
     }
 }
@@ -199,3 +427,201 @@ impl Dataflow {
         true
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::graph::Port;
+
+    fn op(index: usize, name: &str, scope_depth: usize, outputs: usize) -> OperatorShape {
+        OperatorShape {
+            index,
+            name: name.to_owned(),
+            scope_depth,
+            outputs,
+            feedback: false,
+            enters_scope: false,
+            sink: name == "sink",
+        }
+    }
+
+    fn edge(id: usize, source: (usize, usize), target: (usize, usize), scope_depth: usize) -> Edge {
+        Edge {
+            id,
+            source: Port::new(source.0, source.1),
+            target: Port::new(target.0, target.1),
+            scope_depth,
+            ..Default::default()
+        }
+    }
+
+    fn error_of(result: Result<Vec<String>, BuildJobError>) -> String {
+        result.expect_err("the topology is expected to be invalid").to_string()
+    }
+
+    #[test]
+    fn valid_iteration_test() {
+        // source -> enter -> merge_switch -> map -> feedback -> merge_switch, merge_switch -> sink
+        let mut ops = vec![
+            op(0, "source", 0, 1),
+            op(1, "enter", 0, 1),
+            op(2, "merge_switch", 1, 2),
+            op(3, "map", 1, 1),
+            op(4, "feedback", 1, 1),
+            op(5, "sink", 1, 0),
+        ];
+        ops[4].feedback = true;
+        let edges = vec![
+            edge(1, (0, 0), (1, 0), 0),
+            edge(2, (1, 0), (2, 0), 1),
+            edge(3, (2, 1), (3, 0), 1),
+            edge(4, (3, 0), (4, 0), 1),
+            edge(5, (4, 0), (2, 1), 1),
+            edge(6, (2, 0), (5, 0), 1),
+        ];
+        assert_eq!(validate_topology(&ops, &edges).unwrap(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn cycle_without_iteration_test() {
+        // source -> merge -> map -> merge
+        let ops = vec![op(0, "source", 0, 1), op(1, "merge", 0, 1), op(2, "map", 0, 1)];
+        let edges = vec![
+            edge(1, (0, 0), (1, 0), 0),
+            edge(2, (1, 0), (2, 0), 0),
+            edge(3, (2, 0), (1, 1), 0),
+        ];
+        let err = error_of(validate_topology(&ops, &edges));
+        assert!(
+            err.contains(
+                "operators merge[1] -> map[2] -> merge[1] form a cycle not closed by an iteration"
+            ),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn stream_across_scopes_test() {
+        // the stream of the map inside the scope is consumed by the sink outside without leaving
+        let ops = vec![
+            op(0, "source", 0, 1),
+            op(1, "enter", 0, 1),
+            op(2, "map", 1, 1),
+            op(3, "sink", 0, 0),
+        ];
+        let edges = vec![
+            edge(1, (0, 0), (1, 0), 0),
+            edge(2, (1, 0), (2, 0), 1),
+            edge(3, (2, 0), (3, 0), 1),
+        ];
+        let err = error_of(validate_topology(&ops, &edges));
+        assert!(
+            err.contains(
+                "operator sink[3] in scope depth 0 consumes the stream of operator map[2] in scope \
+                 depth 1 without entering or leaving the scope"
+            ),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn custom_scope_left_test() {
+        // source -> enter_scope -> branch, whose outputs are left by the leave and the iteration
+        // nested in the scope, which goes on to the sink after its own leave and the leave_scope
+        let mut ops = vec![
+            op(0, "source", 0, 1),
+            op(1, "enter_scope", 0, 1),
+            op(2, "branch", 1, 2),
+            op(3, "leave_scope", 1, 1),
+            op(4, "enter", 1, 1),
+            op(5, "map", 2, 1),
+            op(6, "leave", 2, 1),
+            op(7, "leave_scope", 1, 1),
+            op(8, "sink", 0, 0),
+        ];
+        ops[1].enters_scope = true;
+        let edges = vec![
+            edge(1, (0, 0), (1, 0), 0),
+            edge(2, (1, 0), (2, 0), 1),
+            edge(3, (2, 0), (3, 0), 1),
+            edge(4, (2, 1), (4, 0), 1),
+            edge(5, (4, 0), (5, 0), 2),
+            edge(6, (5, 0), (6, 0), 2),
+            edge(7, (6, 0), (7, 0), 1),
+            edge(8, (7, 0), (8, 0), 0),
+            edge(9, (3, 0), (8, 1), 0),
+        ];
+        assert_eq!(validate_topology(&ops, &edges).unwrap(), Vec::<String>::new());
+
+        // the second output of the branch goes to a sink inside the scope instead
+        let mut ops = vec![
+            op(0, "source", 0, 1),
+            op(1, "enter_scope", 0, 1),
+            op(2, "branch", 1, 2),
+            op(3, "leave_scope", 1, 1),
+            op(4, "sink", 0, 0),
+            op(5, "sink", 1, 0),
+        ];
+        ops[1].enters_scope = true;
+        let edges = vec![
+            edge(1, (0, 0), (1, 0), 0),
+            edge(2, (1, 0), (2, 0), 1),
+            edge(3, (2, 0), (3, 0), 1),
+            edge(4, (3, 0), (4, 0), 0),
+            edge(5, (2, 1), (5, 0), 1),
+        ];
+        let err = error_of(validate_topology(&ops, &edges));
+        assert!(
+            err.contains(
+                "the scope entered by operator enter_scope[1] is not left by leave_scope before \
+                 operator sink[5]"
+            ),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn output_consumed_by_nothing_test() {
+        // the second output of the branch is left alone
+        let ops = vec![op(0, "source", 0, 1), op(1, "branch", 0, 2), op(2, "sink", 0, 0)];
+        let edges = vec![edge(1, (0, 0), (1, 0), 0), edge(2, (1, 0), (2, 0), 0)];
+        let warnings = validate_topology(&ops, &edges).unwrap();
+        assert_eq!(warnings, vec!["output 1 of operator branch[1] is consumed by nothing;"]);
+    }
+
+    #[test]
+    fn sinks_test() {
+        // the stream is sunk twice, once as it is and once after a count
+        let ops = vec![
+            op(0, "source", 0, 1),
+            op(1, "sink", 0, 0),
+            op(2, "count", 0, 1),
+            op(3, "sink", 0, 0),
+        ];
+        let edges = vec![
+            edge(1, (0, 0), (1, 0), 0),
+            edge(2, (0, 0), (2, 0), 0),
+            edge(3, (2, 0), (3, 0), 0),
+        ];
+        assert_eq!(validate_topology(&ops, &edges).unwrap(), Vec::<String>::new());
+
+        let ops = vec![op(0, "source", 0, 1), op(1, "map", 0, 1)];
+        let edges = vec![edge(1, (0, 0), (1, 0), 0)];
+        let err = error_of(validate_topology(&ops, &edges));
+        assert!(err.contains("the dataflow has no sink"), "{}", err);
+        let err = error_of(validate_topology(&[], &[]));
+        assert!(err.contains("the dataflow has no sink"), "{}", err);
+    }
+
+    #[test]
+    fn find_cycle_test() {
+        assert_eq!(find_cycle(&[vec![1], vec![2], vec![]]), None);
+        // a diamond is no cycle
+        assert_eq!(find_cycle(&[vec![1, 2], vec![3], vec![3], vec![]]), None);
+        assert_eq!(find_cycle(&[vec![1], vec![2], vec![1]]), Some(vec![1, 2, 1]));
+        assert_eq!(find_cycle(&[vec![0]]), Some(vec![0, 0]));
+    }
+}
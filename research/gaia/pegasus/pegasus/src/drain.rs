@@ -0,0 +1,113 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Drain the jobs of current server before it is shut down, e.g. during a rolling restart, where
+//! new jobs are rejected, and the running jobs are waited for until a deadline, after which the
+//! remaining ones are canceled.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+lazy_static! {
+    /// job id -> the job running in current server, which is done once all of its workers are
+    /// dropped
+    static ref RUNNING_JOBS: Mutex<HashMap<u64, RunningJob>> = Mutex::new(HashMap::new());
+}
+
+static DRAINING: AtomicBool = AtomicBool::new(false);
+
+/// How often the running jobs are checked while draining
+const DRAIN_CHECK_INTERVAL: Duration = Duration::from_millis(10);
+
+struct RunningJob {
+    // held by the workers of the job
+    workers: Weak<AtomicUsize>,
+    cancel_hook: Arc<AtomicBool>,
+}
+
+impl RunningJob {
+    fn is_done(&self) -> bool {
+        self.workers.strong_count() == 0
+    }
+}
+
+/// The jobs running when the draining starts, by how they end
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DrainReport {
+    /// the jobs completed before the deadline
+    pub completed: Vec<u64>,
+    /// the jobs canceled at the deadline
+    pub cancelled: Vec<u64>,
+}
+
+/// Check if current server is draining, when new jobs are rejected
+pub fn is_draining() -> bool {
+    DRAINING.load(Ordering::SeqCst)
+}
+
+pub(crate) fn add_running_job(
+    job_id: u64, workers: &Arc<AtomicUsize>, cancel_hook: &Arc<AtomicBool>,
+) {
+    let mut jobs = RUNNING_JOBS.lock().expect("lock poisoned");
+    jobs.retain(|_, job| !job.is_done());
+    let job = RunningJob { workers: Arc::downgrade(workers), cancel_hook: cancel_hook.clone() };
+    jobs.insert(job_id, job);
+}
+
+/// The number of the jobs running in current server
+pub(crate) fn running_jobs() -> usize {
+    let mut jobs = RUNNING_JOBS.lock().expect("lock poisoned");
+    jobs.retain(|_, job| !job.is_done());
+    jobs.len()
+}
+
+/// Stop accepting new jobs, which are rejected by `JobSubmitError::Draining` from now on, and
+/// wait for the running jobs to complete until the deadline, after which the remaining ones are
+/// canceled, as by `JobGuard::cancel_execute`. The server keeps rejecting new jobs after drained,
+/// until it is shut down.
+pub fn drain(deadline: Duration) -> DrainReport {
+    DRAINING.store(true, Ordering::SeqCst);
+    let start = Instant::now();
+    let mut report = DrainReport::default();
+    let mut running = {
+        let mut jobs = RUNNING_JOBS.lock().expect("lock poisoned");
+        jobs.drain().filter(|(_, job)| !job.is_done()).collect::<Vec<_>>()
+    };
+    info!("start draining {} running jobs, in {:?} at most;", running.len(), deadline);
+    loop {
+        running.retain(|(job_id, job)| {
+            // checked once, or the job done in between is neither completed nor canceled
+            let done = job.is_done();
+            if done {
+                report.completed.push(*job_id);
+            }
+            !done
+        });
+        if running.is_empty() || start.elapsed() >= deadline {
+            break;
+        }
+        std::thread::sleep(DRAIN_CHECK_INTERVAL);
+    }
+    for (job_id, job) in running {
+        warn!("job {} is canceled as not completed before the deadline of draining;", job_id);
+        job.cancel_hook.store(true, Ordering::SeqCst);
+        report.cancelled.push(job_id);
+    }
+    report.completed.sort();
+    report.cancelled.sort();
+    report
+}
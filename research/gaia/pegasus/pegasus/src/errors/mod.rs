@@ -14,7 +14,9 @@
 //! limitations under the License.
 
 use pegasus_executor::TaskExecError;
+pub use pegasus_executor::{ExecError, TaskPanic};
 use pegasus_network::NetError;
+use std::any::Any;
 use std::error::Error;
 use std::fmt::{self, Debug, Display};
 use std::io;
@@ -48,24 +50,52 @@ pub struct JobExecError {
     pub kind: ErrorKind,
     pub is_system: bool,
     cause: Box<dyn Error + Send>,
+    // the logs captured of the job by the failure, see `JobConf::capture_logs`
+    logs: Vec<String>,
+    // the index of the worker failed by the error, which is set once the worker fails
+    worker: Option<u32>,
 }
 
 impl JobExecError {
     pub fn new<E: Error + Send + 'static>(kind: ErrorKind, cause: E) -> Self {
-        JobExecError { kind, is_system: false, cause: Box::new(cause) }
+        JobExecError { kind, is_system: false, cause: Box::new(cause), logs: vec![], worker: None }
     }
 
     pub(crate) fn from_box(err: Box<dyn Error + Send>) -> Self {
         if let Some(e) = err.downcast_ref::<JobExecError>() {
-            JobExecError { kind: e.kind, is_system: e.is_system, cause: err }
+            JobExecError {
+                kind: e.kind,
+                is_system: e.is_system,
+                cause: err,
+                logs: vec![],
+                worker: None,
+            }
         } else if let Some(e) = err.downcast_ref::<IOError>() {
             if e.is_interrupted() || e.is_would_block() || e.is_source_exhaust() {
-                JobExecError { kind: ErrorKind::RetryLater, is_system: true, cause: err }
+                JobExecError {
+                    kind: ErrorKind::RetryLater,
+                    is_system: true,
+                    cause: err,
+                    logs: vec![],
+                    worker: None,
+                }
             } else {
-                JobExecError { kind: ErrorKind::IOError, is_system: true, cause: err }
+                JobExecError {
+                    kind: ErrorKind::IOError,
+                    is_system: true,
+                    cause: err,
+                    logs: vec![],
+                    worker: None,
+                }
             }
         } else {
-            JobExecError { kind: ErrorKind::Others, is_system: false, cause: err }
+            JobExecError {
+                kind: ErrorKind::Others,
+                is_system: false,
+                cause: err,
+                logs: vec![],
+                worker: None,
+            }
         }
     }
 
@@ -89,6 +119,25 @@ impl JobExecError {
     pub fn as_ref<E: Error + 'static>(&self) -> Option<&E> {
         self.cause.downcast_ref::<E>()
     }
+
+    /// Get the logs captured of the job by the failure, which are empty unless the job captures
+    /// its logs;
+    pub fn get_logs(&self) -> &[String] {
+        &self.logs
+    }
+
+    pub(crate) fn attach_logs(&mut self, logs: Vec<String>) {
+        self.logs = logs;
+    }
+
+    /// Get the index of the worker failed by the error, or `None` if it hasn't failed any worker;
+    pub fn get_worker(&self) -> Option<u32> {
+        self.worker
+    }
+
+    pub(crate) fn set_worker(&mut self, index: u32) {
+        self.worker = Some(index);
+    }
 }
 
 impl Debug for JobExecError {
@@ -98,7 +147,14 @@ impl Debug for JobExecError {
         } else {
             write!(f, "user error: ")?;
         }
-        write!(f, "kind({:?}), caused by:{}", self.kind, self.cause)
+        write!(f, "kind({:?}), caused by:{}", self.kind, self.cause)?;
+        if !self.logs.is_empty() {
+            write!(f, "\ncaptured logs:")?;
+            for line in self.logs.iter() {
+                write!(f, "\n{}", line)?;
+            }
+        }
+        Ok(())
     }
 }
 
@@ -117,9 +173,21 @@ impl Error for JobExecError {
 impl From<IOError> for JobExecError {
     fn from(err: IOError) -> Self {
         if err.is_interrupted() || err.is_would_block() || err.is_source_exhaust() {
-            JobExecError { kind: ErrorKind::RetryLater, is_system: true, cause: Box::new(err) }
+            JobExecError {
+                kind: ErrorKind::RetryLater,
+                is_system: true,
+                cause: Box::new(err),
+                logs: vec![],
+                worker: None,
+            }
         } else {
-            JobExecError { kind: ErrorKind::IOError, is_system: true, cause: Box::new(err) }
+            JobExecError {
+                kind: ErrorKind::IOError,
+                is_system: true,
+                cause: Box::new(err),
+                logs: vec![],
+                worker: None,
+            }
         }
     }
 }
@@ -147,10 +215,20 @@ impl From<String> for JobExecError {
 impl From<io::Error> for JobExecError {
     fn from(err: io::Error) -> Self {
         match err.kind() {
-            io::ErrorKind::WouldBlock | io::ErrorKind::Interrupted => {
-                JobExecError { kind: ErrorKind::RetryLater, is_system: true, cause: Box::new(err) }
-            }
-            _ => JobExecError { kind: ErrorKind::IOError, is_system: true, cause: Box::new(err) },
+            io::ErrorKind::WouldBlock | io::ErrorKind::Interrupted => JobExecError {
+                kind: ErrorKind::RetryLater,
+                is_system: true,
+                cause: Box::new(err),
+                logs: vec![],
+                worker: None,
+            },
+            _ => JobExecError {
+                kind: ErrorKind::IOError,
+                is_system: true,
+                cause: Box::new(err),
+                logs: vec![],
+                worker: None,
+            },
         }
     }
 }
@@ -166,7 +244,11 @@ macro_rules! throw_user_error {
     }};
 }
 
-impl TaskExecError for JobExecError {}
+impl TaskExecError for JobExecError {
+    fn as_any_ref(&self) -> &dyn Any {
+        self
+    }
+}
 
 // TODO: Make build error enumerate.;
 pub enum BuildJobError {
@@ -191,7 +273,14 @@ impl Display for BuildJobError {
     }
 }
 
-impl Error for BuildJobError {}
+impl Error for BuildJobError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            BuildJobError::Unsupported(_) => None,
+            BuildJobError::ServerError(e) | BuildJobError::UserError(e) => Some(e.as_ref()),
+        }
+    }
+}
 
 impl BuildJobError {
     pub(crate) fn unsupported<T, S: Into<String>>(msg: S) -> Result<T, Self> {
@@ -224,6 +313,60 @@ impl From<&str> for BuildJobError {
     }
 }
 
+/// The failure to get a resource of the job by `pegasus::get_job_resource`;
+#[derive(Debug, Clone, PartialEq)]
+pub enum JobResourceError {
+    /// current thread runs no worker of any job;
+    OutOfJob,
+    NotFound {
+        job_id: u64,
+        key: String,
+    },
+    /// the resource of the key is not of the type asked for;
+    TypeMismatch {
+        job_id: u64,
+        key: String,
+    },
+}
+
+impl Display for JobResourceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JobResourceError::OutOfJob => write!(f, "job resource is got out of any job;"),
+            JobResourceError::NotFound { job_id, key } => {
+                write!(f, "resource {} of job {} not found;", key, job_id)
+            }
+            JobResourceError::TypeMismatch { job_id, key } => {
+                write!(f, "resource {} of job {} is not of the type asked for;", key, job_id)
+            }
+        }
+    }
+}
+
+impl Error for JobResourceError {}
+
+/// The job sends and receives more bytes with the other servers than its
+/// `JobConf::network_byte_limit` in current server, which fails and cancels the job;
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkLimitExceeded {
+    pub job_id: u64,
+    pub limit: u64,
+    /// the bytes sent and received by the job once the limit is found exceeded;
+    pub bytes: u64,
+}
+
+impl Display for NetworkLimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "job {} exceeds the network byte limit {} by {} bytes sent and received;",
+            self.job_id, self.limit, self.bytes
+        )
+    }
+}
+
+impl Error for NetworkLimitExceeded {}
+
 pub struct SpawnJobError(pub String);
 
 impl Debug for SpawnJobError {
@@ -250,6 +393,13 @@ impl From<&str> for SpawnJobError {
 pub enum JobSubmitError {
     Build(BuildJobError),
     Spawn(SpawnJobError),
+    /// the server is draining, and accepts no new jobs
+    Draining,
+    /// the server runs as many jobs as its `Configuration::max_jobs`, and accepts no more;
+    OverJobLimit(u32),
+    /// the job is aborted on all its servers, as some of them failed to prepare it, by their
+    /// errors, see `crate::submit`;
+    Aborted(String),
 }
 
 impl Display for JobSubmitError {
@@ -257,11 +407,26 @@ impl Display for JobSubmitError {
         match self {
             JobSubmitError::Build(err) => write!(f, "Build job failure: {}", err),
             JobSubmitError::Spawn(err) => write!(f, "Spawn job failure: {}", err),
+            JobSubmitError::Draining => write!(f, "Server is draining, and rejects new jobs;"),
+            JobSubmitError::OverJobLimit(limit) => {
+                write!(f, "Server runs {} jobs at most, and rejects new jobs;", limit)
+            }
+            JobSubmitError::Aborted(err) => write!(f, "Job aborted on all servers: {}", err),
         }
     }
 }
 
-impl Error for JobSubmitError {}
+impl Error for JobSubmitError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            JobSubmitError::Build(e) => Some(e),
+            JobSubmitError::Spawn(e) => Some(e),
+            JobSubmitError::Draining
+            | JobSubmitError::OverJobLimit(_)
+            | JobSubmitError::Aborted(_) => None,
+        }
+    }
+}
 
 impl From<BuildJobError> for JobSubmitError {
     fn from(err: BuildJobError) -> Self {
@@ -282,6 +447,12 @@ pub enum StartupError {
     CannotFindServers,
     Network(NetError),
     AlreadyStarted(u64),
+    /// the constraints violated by the configuration
+    InvalidConfig(Vec<String>),
+    /// fail to start the endpoint of metrics
+    MetricsEndpoint(std::io::Error),
+    /// fail to open the file of the slow query log
+    SlowQueryLog(std::io::Error),
 }
 
 impl Display for StartupError {
@@ -296,6 +467,19 @@ impl Display for StartupError {
                 write!(f, "startup failure, caused by network error: {:?}", e)
             }
             StartupError::AlreadyStarted(id) => write!(f, "server {} has already started;", id),
+            StartupError::InvalidConfig(violations) => {
+                write!(f, "invalid configuration:")?;
+                for (i, violation) in violations.iter().enumerate() {
+                    write!(f, " {}) {};", i + 1, violation)?;
+                }
+                Ok(())
+            }
+            StartupError::MetricsEndpoint(e) => {
+                write!(f, "start metrics endpoint failure, caused by {};", e)
+            }
+            StartupError::SlowQueryLog(e) => {
+                write!(f, "open slow query log failure, caused by {};", e)
+            }
         }
     }
 }
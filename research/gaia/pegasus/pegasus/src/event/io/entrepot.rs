@@ -15,8 +15,9 @@
 
 use crate::data_plane::{GeneralPull, GeneralPush, Pull, Push};
 use crate::errors::{BuildJobError, IOResult};
-use crate::event::io::{EventBatch, EventBus, Events};
+use crate::event::io::{EventBatch, EventBus, Events, WireStamp};
 use crate::event::Event;
+use crate::wire_trace::WireTracer;
 use crate::{JobConf, WorkerId};
 use crossbeam_channel::{Receiver, TryRecvError};
 use pegasus_common::rc::RcPointer;
@@ -30,28 +31,47 @@ pub struct EventPush {
     pub(crate) target: WorkerId,
     inner: GeneralPush<Events>,
     buffer: Option<EventBatch>,
+    // the tracer with the sequence of the next event if the job is traced on the wire;
+    trace: Option<(WireTracer, u64)>,
 }
 
 impl EventPush {
     pub fn new(source: WorkerId, target: WorkerId, push: GeneralPush<Events>) -> Self {
-        if push.is_local() {
-            EventPush { source, target, inner: push, buffer: None }
-        } else {
-            EventPush { source, target, inner: push, buffer: Some(EventBatch::new()) }
-        }
+        let buffer = if push.is_local() { None } else { Some(EventBatch::new()) };
+        EventPush { source, target, inner: push, buffer, trace: None }
+    }
+
+    /// Record the events sent by the tracer, stamped with their sequences;
+    pub(crate) fn trace(&mut self, tracer: WireTracer) {
+        self.trace = Some((tracer, 0));
+    }
+
+    #[inline]
+    fn stamp(&mut self, msg: &Event) -> Option<WireStamp> {
+        let (tracer, next_seq) = self.trace.as_mut()?;
+        let seq = *next_seq;
+        *next_seq += 1;
+        tracer.on_send(self.target.index, seq, msg);
+        Some((self.source.index, seq))
     }
 }
 
 impl Push<Event> for EventPush {
     #[inline]
     fn push(&mut self, msg: Event) -> IOResult<()> {
+        let stamp = self.stamp(&msg);
         if let Some(mut buffer) = self.buffer.take() {
+            if buffer.is_empty() {
+                buffer.stamp = stamp;
+            }
             buffer.push(msg);
             if buffer.len() == 64 {
                 let batch = std::mem::replace(&mut buffer, EventBatch::new());
                 self.inner.push(Events::Batched(batch))?;
             }
             self.buffer.replace(buffer);
+        } else if let Some(stamp) = stamp {
+            self.inner.push(Events::Stamped(msg, stamp))?;
         } else {
             self.inner.push(Events::Single(msg))?;
         }
@@ -83,6 +103,7 @@ pub struct EventEntrepot {
     pushes: Vec<EventPush>,
     pull: GeneralPull<Events>,
     received: RcPointer<RefCell<VecDeque<Event>>>,
+    tracer: Option<WireTracer>,
 }
 
 impl EventEntrepot {
@@ -93,10 +114,23 @@ impl EventEntrepot {
         let EventBus { worker_id, tx: _, internal } = event_bus;
         let ch_res = crate::communication::build_channel::<Events>(0, conf)?;
         let (pushes, pull) = ch_res.take();
+        let tracer =
+            if conf.wire_trace { WireTracer::of(conf.job_id, worker_id.index) } else { None };
         for (push, target) in pushes.into_iter().zip(worker_id.all_peers()) {
-            event_pushes.push(EventPush::new(worker_id, target, push));
+            let mut push = EventPush::new(worker_id, target, push);
+            if let Some(tracer) = tracer.as_ref() {
+                push.trace(tracer.clone());
+            }
+            event_pushes.push(push);
         }
-        Ok(EventEntrepot { worker_id, recv, pushes: event_pushes, pull, received: internal })
+        Ok(EventEntrepot {
+            worker_id,
+            recv,
+            pushes: event_pushes,
+            pull,
+            received: internal,
+            tracer,
+        })
     }
 
     pub fn classify(&mut self) -> IOResult<()> {
@@ -134,8 +168,18 @@ impl EventEntrepot {
                 Ok(Some(Events::Single(e))) => {
                     received.push_back(e);
                 }
+                Ok(Some(Events::Stamped(e, (source, seq)))) => {
+                    if let Some(tracer) = self.tracer.as_ref() {
+                        tracer.on_receive(source, seq, &e);
+                    }
+                    received.push_back(e);
+                }
                 Ok(Some(Events::Batched(mut ec))) => {
-                    for e in ec.drain(..) {
+                    let stamp = ec.stamp.take();
+                    for (i, e) in ec.drain(..).enumerate() {
+                        if let (Some(tracer), Some((source, seq))) = (self.tracer.as_ref(), stamp) {
+                            tracer.on_receive(source, seq + i as u64, &e);
+                        }
                         received.push_back(e);
                     }
                 }
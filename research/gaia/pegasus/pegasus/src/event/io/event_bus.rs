@@ -34,6 +34,14 @@ impl EventBus {
         EventBus { worker_id, tx: sender, internal: RcPointer::new(RefCell::new(VecDeque::new())) }
     }
 
+    /// Create the event bus whose events sent to the worker itself are queued in `queue`, e.g.
+    /// one taken from the warm set of the worker;
+    pub(crate) fn with_queue(
+        worker_id: WorkerId, sender: Sender<(WorkerId, Event)>, queue: VecDeque<Event>,
+    ) -> Self {
+        EventBus { worker_id, tx: sender, internal: RcPointer::new(RefCell::new(queue)) }
+    }
+
     #[inline]
     pub fn send_self(&self, event: Event) -> IOResult<()> {
         self.internal.borrow_mut().push_back(event);
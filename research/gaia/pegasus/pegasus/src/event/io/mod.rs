@@ -22,14 +22,29 @@ lazy_static! {
     static ref EVENT_BATCH_RECYCLE: Arc<SegQueue<Vec<Event>>> = Arc::new(SegQueue::new());
 }
 
+// set on the length of a batch followed by its stamp, which never reaches this bit;
+const STAMPED: u32 = 1 << 31;
+
+/// The sender of an event and the sequence of it among those the sender sends to the receiver,
+/// stamped on the events of the jobs traced on the wire, see `crate::wire_trace`;
+pub type WireStamp = (u32, u64);
+
 #[derive(Clone, Debug)]
 pub struct EventBatch {
     batch: Vec<Event>,
+    /// the stamp of the first event of the batch, whose followers are of the sequences after it;
+    pub stamp: Option<WireStamp>,
 }
 
 impl Encode for EventBatch {
     fn write_to<W: WriteExt>(&self, writer: &mut W) -> std::io::Result<()> {
-        writer.write_u32(self.batch.len() as u32)?;
+        if let Some((source, seq)) = self.stamp {
+            writer.write_u32(self.batch.len() as u32 | STAMPED)?;
+            writer.write_u32(source)?;
+            writer.write_u64(seq)?;
+        } else {
+            writer.write_u32(self.batch.len() as u32)?;
+        }
         for e in self.batch.iter() {
             e.write_to(writer)?;
         }
@@ -39,14 +54,21 @@ impl Encode for EventBatch {
 
 impl Decode for EventBatch {
     fn read_from<R: ReadExt>(reader: &mut R) -> std::io::Result<Self> {
-        let len = reader.read_u32()? as usize;
+        let len = reader.read_u32()?;
+        let stamp = if len & STAMPED != 0 {
+            let source = reader.read_u32()?;
+            Some((source, reader.read_u64()?))
+        } else {
+            None
+        };
+        let len = (len & !STAMPED) as usize;
         let mut batch: Vec<Event> =
             EVENT_BATCH_RECYCLE.pop().unwrap_or_else(|_| Vec::with_capacity(len));
         for _ in 0..len {
             let event = Event::read_from(reader)?;
             batch.push(event);
         }
-        Ok(EventBatch { batch })
+        Ok(EventBatch { batch, stamp })
     }
 }
 
@@ -54,7 +76,7 @@ impl EventBatch {
     #[inline]
     pub fn new() -> Self {
         let batch = EVENT_BATCH_RECYCLE.pop().unwrap_or_else(|_| Vec::with_capacity(64));
-        EventBatch { batch }
+        EventBatch { batch, stamp: None }
     }
 }
 
@@ -85,6 +107,8 @@ impl Drop for EventBatch {
 #[derive(Clone, Debug)]
 pub enum Events {
     Single(Event),
+    /// a single event stamped as it is traced, which is never encoded as `Single`;
+    Stamped(Event, WireStamp),
     Batched(EventBatch),
 }
 
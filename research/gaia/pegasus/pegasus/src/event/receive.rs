@@ -153,6 +153,8 @@ pub struct ChannelRxState {
     scope_data: RefCell<HashMap<Tag, RcPointer<Panel>>>,
     parent_scope_skipped: RefCell<HashSet<Tag>>,
     notifications: RefCell<TagAntiChainSet>,
+    // the scopes ended on all workers by one peer, see `give_scope_end_all`;
+    global_ends: RefCell<HashSet<Tag>>,
     seq_gen: Cell<usize>,
     is_source_exhaust: Cell<bool>,
 }
@@ -167,6 +169,7 @@ impl ChannelRxState {
             scope_data: RefCell::new(HashMap::new()),
             parent_scope_skipped: RefCell::new(HashSet::new()),
             notifications: RefCell::new(TagAntiChainSet::new()),
+            global_ends: RefCell::new(HashSet::new()),
             seq_gen: Cell::new(0),
             is_source_exhaust: Cell::new(false),
         }
@@ -289,11 +292,24 @@ impl ChannelRxState {
         }
     }
 
+    /// The scope ends on all workers, e.g. a scope only the worker sending its end ever enters,
+    /// which is still so after the channel if it is of one peer, whose data never leave the
+    /// worker; Once the scope crosses an exchange, each worker ends its own part of the scope;
     #[inline]
     pub fn give_scope_end_all(&self, tag: Tag) {
+        if self.tx_peers == 1 {
+            self.global_ends.borrow_mut().insert(tag.clone());
+        }
         self.notifications.borrow_mut().push(tag);
     }
 
+    /// Whether the notification of the scope is its end on all workers, see `give_scope_end_all`;
+    #[inline]
+    pub fn take_global_end(&self, tag: &Tag) -> bool {
+        let mut global_ends = self.global_ends.borrow_mut();
+        !global_ends.is_empty() && global_ends.remove(tag)
+    }
+
     pub fn for_each_outstanding<F: FnMut(&Tag)>(&self, mut func: F) {
         let scope_data = self.scope_data.borrow();
         for (t, panel) in scope_data.iter() {
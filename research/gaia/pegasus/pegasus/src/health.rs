@@ -0,0 +1,250 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The health of current server, for the probes of the deployments, e.g. Kubernetes, to tell
+//! whether the server is alive, and whether it is ready to take jobs, which is decided by the
+//! requirements configured by `Configuration::readiness`, e.g. `["peers", "graph:ldbc"]` to be
+//! ready only once all the peers are connected and the graph 'ldbc' is loaded.
+//!
+//! The components of the applications, e.g. the graphs being loaded, report their states by
+//! `set_component_state`, and the network connections and the executor are watched by the
+//! engine itself. Both probes are served by the endpoint of metrics on `/live` and `/ready`, see
+//! `crate::metrics`, or checked by `health` in process.
+
+use pegasus_network::PeerState;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::RwLock;
+use std::time::SystemTime;
+
+lazy_static! {
+    /// name -> the state of the component reported by the application
+    static ref COMPONENTS: RwLock<BTreeMap<String, ComponentHealth>> =
+        RwLock::new(BTreeMap::new());
+    /// the requirements of readiness, or `None` to require everything watched
+    static ref REQUIREMENTS: RwLock<Option<Vec<Requirement>>> = RwLock::new(None);
+}
+
+/// The state of a component reported by the application
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ComponentState {
+    /// being prepared, e.g. a graph being loaded
+    Loading,
+    Ready,
+    /// failed to be prepared with the reason
+    Failed(String),
+}
+
+#[derive(Clone, Debug)]
+pub struct ComponentHealth {
+    pub name: String,
+    pub state: ComponentState,
+    /// the time the component comes into its state
+    pub since: SystemTime,
+}
+
+/// The liveness of the threads running the workers
+#[derive(Clone, Debug)]
+pub struct PoolHealth {
+    /// whether the executor is started and not shut down
+    pub alive: bool,
+    /// the number of threads of the executor
+    pub threads: usize,
+    /// the sets of queues claimed by the workers alive, see `crate::warm`
+    pub claimed: usize,
+    /// the idle sets of queues kept warm for the next workers
+    pub idle: usize,
+}
+
+/// A condition current server must meet to be ready
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Requirement {
+    /// connected with all the peers detected, written as "peers"
+    AllPeersConnected,
+    /// the executor is alive, written as "pool"
+    PoolAlive,
+    /// the component of the name is ready, written as its name, e.g. "graph:ldbc"
+    ComponentReady(String),
+}
+
+impl FromStr for Requirement {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "" => Err("requirement of readiness must not be empty".to_owned()),
+            "peers" => Ok(Requirement::AllPeersConnected),
+            "pool" => Ok(Requirement::PoolAlive),
+            name => Ok(Requirement::ComponentReady(name.to_owned())),
+        }
+    }
+}
+
+impl fmt::Display for Requirement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Requirement::AllPeersConnected => write!(f, "peers"),
+            Requirement::PoolAlive => write!(f, "pool"),
+            Requirement::ComponentReady(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+/// The health of current server at a moment
+#[derive(Clone, Debug)]
+pub struct HealthReport {
+    pub server_id: u64,
+    /// whether the server is alive, i.e. its executor is alive
+    pub live: bool,
+    /// whether all the requirements are met, and the server is not draining
+    pub ready: bool,
+    /// the requirements not met, or "draining" if the server is draining, see `crate::drain`
+    pub unmet: Vec<String>,
+    pub peers: Vec<PeerState>,
+    pub components: Vec<ComponentHealth>,
+    pub pool: PoolHealth,
+}
+
+impl fmt::Display for HealthReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "server {}: live={}, ready={}", self.server_id, self.live, self.ready)?;
+        for unmet in self.unmet.iter() {
+            writeln!(f, "unmet {}", unmet)?;
+        }
+        for peer in self.peers.iter() {
+            let heard = peer.last_heartbeat.and_then(|t| t.elapsed().ok());
+            writeln!(
+                f,
+                "peer {} at {}: connected={}, last heartbeat {}",
+                peer.server_id,
+                peer.addr,
+                peer.connected,
+                heard.map(|d| format!("{}ms ago", d.as_millis())).unwrap_or("never".to_owned())
+            )?;
+        }
+        for component in self.components.iter() {
+            writeln!(f, "component {}: {:?}", component.name, component.state)?;
+        }
+        writeln!(
+            f,
+            "pool: alive={}, threads={}, claimed={}, idle={}",
+            self.pool.alive, self.pool.threads, self.pool.claimed, self.pool.idle
+        )
+    }
+}
+
+/// Report the state of a component of the application, e.g. `Loading` once a graph starts to be
+/// loaded, and `Ready` once it is loaded
+pub fn set_component_state(name: &str, state: ComponentState) {
+    let mut components = COMPONENTS.write().expect("lock poisoned");
+    if components.get(name).map(|c| c.state == state).unwrap_or(false) {
+        return;
+    }
+    let health = ComponentHealth { name: name.to_owned(), state, since: SystemTime::now() };
+    components.insert(name.to_owned(), health);
+}
+
+/// Remove a component no longer watched, which tells whether it is found
+pub fn remove_component(name: &str) -> bool {
+    COMPONENTS.write().expect("lock poisoned").remove(name).is_some()
+}
+
+/// Set the requirements of readiness, replacing the ones configured at startup
+pub fn set_readiness(requirements: Vec<Requirement>) {
+    *REQUIREMENTS.write().expect("lock poisoned") = Some(requirements);
+}
+
+/// Check whether current server is alive, i.e. its executor is started and not shut down
+pub fn is_live() -> bool {
+    !pegasus_executor::is_shutdown()
+}
+
+/// Check whether current server is ready, see `HealthReport::ready`
+pub fn is_ready() -> bool {
+    health().ready
+}
+
+/// Collect the health of current server; Without any requirement configured, the server is ready
+/// once all the peers are connected, the executor is alive, and all the components are ready
+pub fn health() -> HealthReport {
+    let server_id = crate::server_id().unwrap_or(0);
+    let peers = pegasus_network::peer_states(server_id);
+    let components =
+        COMPONENTS.read().expect("lock poisoned").values().cloned().collect::<Vec<_>>();
+    let warm = crate::warm::warm_stats();
+    let pool = PoolHealth {
+        alive: is_live(),
+        threads: pegasus_executor::get_core_pool_size(),
+        claimed: warm.claimed,
+        idle: warm.idle,
+    };
+    let requirements = REQUIREMENTS.read().expect("lock poisoned").clone().unwrap_or_else(|| {
+        let mut all = vec![Requirement::AllPeersConnected, Requirement::PoolAlive];
+        all.extend(components.iter().map(|c| Requirement::ComponentReady(c.name.clone())));
+        all
+    });
+    let mut unmet = requirements
+        .iter()
+        .filter(|requirement| !is_met(requirement, &peers, &components, &pool))
+        .map(|requirement| requirement.to_string())
+        .collect::<Vec<_>>();
+    if crate::is_draining() {
+        unmet.push("draining".to_owned());
+    }
+    HealthReport {
+        server_id,
+        live: pool.alive,
+        ready: unmet.is_empty(),
+        unmet,
+        peers,
+        components,
+        pool,
+    }
+}
+
+fn is_met(
+    requirement: &Requirement, peers: &[PeerState], components: &[ComponentHealth],
+    pool: &PoolHealth,
+) -> bool {
+    match requirement {
+        Requirement::AllPeersConnected => peers.iter().all(|peer| peer.connected),
+        Requirement::PoolAlive => pool.alive,
+        Requirement::ComponentReady(name) => {
+            components.iter().any(|c| &c.name == name && c.state == ComponentState::Ready)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_requirement_test() {
+        let conf = ["peers", " pool", "graph:ldbc"];
+        let requirements = conf.iter().map(|s| s.parse().unwrap()).collect::<Vec<Requirement>>();
+        assert_eq!(
+            requirements,
+            vec![
+                Requirement::AllPeersConnected,
+                Requirement::PoolAlive,
+                Requirement::ComponentReady("graph:ldbc".to_owned())
+            ]
+        );
+        assert!("".parse::<Requirement>().is_err());
+        assert_eq!(requirements[2].to_string(), "graph:ldbc");
+    }
+}
@@ -0,0 +1,153 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The logs of `info_worker!`, `warn_worker!`, `error_worker!` and so on captured per job, for the
+//! jobs with `JobConf::capture_logs`, so that the logs of a misbehaving job can be told apart from
+//! those of the other jobs running concurrently. The lines of a job are kept in a ring buffer of
+//! at most `MAX_LINES_PER_JOB` lines, which drops the oldest lines once full, and the buffers of
+//! at most `MAX_CAPTURED_JOBS` jobs are kept after the jobs end, to be fetched by
+//! `fetch_job_logs`, where the buffers of the earliest jobs are dropped first.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Arguments;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+
+/// The most lines kept for each job;
+pub const MAX_LINES_PER_JOB: usize = 1024;
+/// The most jobs whose lines are kept;
+pub const MAX_CAPTURED_JOBS: usize = 64;
+
+#[derive(Default)]
+struct JobLogs {
+    lines: VecDeque<String>,
+    // the number of lines dropped as the buffer is full
+    dropped: u64,
+}
+
+impl JobLogs {
+    fn push(&mut self, line: String) {
+        if self.lines.len() >= MAX_LINES_PER_JOB {
+            self.lines.pop_front();
+            self.dropped += 1;
+        }
+        self.lines.push_back(line);
+    }
+
+    fn to_vec(&self) -> Vec<String> {
+        let mut lines = Vec::with_capacity(self.lines.len() + 1);
+        if self.dropped > 0 {
+            lines.push(format!("... {} earlier lines dropped", self.dropped));
+        }
+        lines.extend(self.lines.iter().cloned());
+        lines
+    }
+}
+
+#[derive(Default)]
+struct CapturedLogs {
+    jobs: HashMap<u64, Arc<Mutex<JobLogs>>>,
+    // the jobs in the order they are registered, to drop the earliest ones
+    order: VecDeque<u64>,
+}
+
+lazy_static! {
+    static ref CAPTURED_LOGS: RwLock<CapturedLogs> = RwLock::new(CapturedLogs::default());
+}
+
+// the number of jobs whose logs are captured, to skip looking up the buffers if there is none
+static CAPTURED_JOBS: AtomicUsize = AtomicUsize::new(0);
+
+/// Start to capture the logs of the job, which drops the lines captured by the previous job of
+/// the same id if any;
+pub(crate) fn register(job_id: u64) {
+    let mut captured = CAPTURED_LOGS.write().expect("lock poisoned");
+    if captured.jobs.insert(job_id, Arc::new(Mutex::new(JobLogs::default()))).is_some() {
+        captured.order.retain(|id| *id != job_id);
+    }
+    captured.order.push_back(job_id);
+    while captured.order.len() > MAX_CAPTURED_JOBS {
+        if let Some(earliest) = captured.order.pop_front() {
+            captured.jobs.remove(&earliest);
+        }
+    }
+    CAPTURED_JOBS.store(captured.jobs.len(), Ordering::SeqCst);
+}
+
+/// Capture a line logged by the current worker if its job captures logs, where the lines of
+/// `Debug` and `Trace` are only captured if they are enabled by the logger;
+#[doc(hidden)]
+#[inline]
+pub fn capture(lvl: log::Level, args: Arguments) {
+    if CAPTURED_JOBS.load(Ordering::Relaxed) == 0 {
+        return;
+    }
+    if lvl > log::Level::Info && !log_enabled!(lvl) {
+        return;
+    }
+    if let Some(worker) = crate::worker_id::get_current_worker() {
+        let logs = CAPTURED_LOGS.read().ok().and_then(|c| c.jobs.get(&worker.job_id).cloned());
+        if let Some(logs) = logs {
+            let line = format!("{} {:?}: {}", lvl, worker, args);
+            if let Ok(mut logs) = logs.lock() {
+                logs.push(line);
+            }
+        }
+    }
+}
+
+/// Get the lines captured of the job in current server, in the order they are logged, or `None`
+/// if the logs of the job are not captured, or have been dropped for the later jobs;
+pub fn fetch_job_logs(job_id: u64) -> Option<Vec<String>> {
+    let logs = CAPTURED_LOGS.read().ok()?.jobs.get(&job_id).cloned()?;
+    let lines = logs.lock().ok()?.to_vec();
+    Some(lines)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ring_buffer_test() {
+        let mut logs = JobLogs::default();
+        for i in 0..MAX_LINES_PER_JOB + 2 {
+            logs.push(i.to_string());
+        }
+        let lines = logs.to_vec();
+        assert_eq!(lines.len(), MAX_LINES_PER_JOB + 1);
+        assert_eq!(lines[0], "... 2 earlier lines dropped");
+        assert_eq!(lines[1], "2");
+        assert_eq!(lines.last().unwrap(), &(MAX_LINES_PER_JOB + 1).to_string());
+    }
+
+    #[test]
+    fn capture_test() {
+        let base = 1 << 40;
+        register(base);
+        let _g = crate::worker_id::guard(crate::WorkerId::new(base, 2, 1, false));
+        capture(log::Level::Info, format_args!("hello {}", 1));
+        assert_eq!(
+            fetch_job_logs(base).unwrap(),
+            vec!["INFO [worker_1(1099511627776-2)]: hello 1"]
+        );
+        // the earliest jobs are dropped once too many jobs are captured
+        for i in 1..=MAX_CAPTURED_JOBS as u64 {
+            register(base + i);
+        }
+        assert!(fetch_job_logs(base).is_none());
+        assert_eq!(fetch_job_logs(base + 1).unwrap(), Vec::<String>::new());
+    }
+}
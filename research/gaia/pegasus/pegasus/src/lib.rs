@@ -25,9 +25,11 @@ extern crate enum_dispatch;
 extern crate pegasus_common;
 
 use std::cell::{Cell, RefCell};
+use std::path::Path;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 
+mod abort;
 mod config;
 mod graph;
 pub mod preclude;
@@ -35,6 +37,8 @@ mod tag;
 #[macro_use]
 mod worker_id;
 mod channel_id;
+#[cfg(any(test, feature = "cluster"))]
+pub mod cluster;
 #[macro_use]
 pub mod errors;
 #[macro_use]
@@ -43,24 +47,63 @@ pub mod communication;
 mod data;
 mod data_plane;
 pub mod dataflow;
+mod drain;
 mod event;
+pub mod health;
+pub mod job_log;
+pub mod metrics;
+pub mod net_usage;
 mod operator;
+pub mod profile;
+pub mod progress;
+mod resource;
+pub mod result;
+pub mod rng;
 mod schedule;
+pub mod scratch;
+pub mod slow_query;
+pub mod span;
+mod spill;
 pub mod stream;
+mod submit;
+pub mod temp;
+pub mod warm;
+pub mod wire_trace;
 mod worker;
 
-pub use crate::errors::{BuildJobError, JobSubmitError, SpawnJobError, StartupError};
+pub use crate::abort::abort_current_job;
+pub use crate::api::{current_iteration, current_iterations};
+pub use crate::errors::{
+    BuildJobError, JobResourceError, JobSubmitError, SpawnJobError, StartupError,
+};
 pub use crate::operator::{never_clone, NeverClone};
 use crate::worker_id::WorkerIdIter;
-pub use config::{read_from, Configuration, JobConf};
+pub use config::{
+    read_from, Configuration, ConfigurationBuilder, JobConf, LatencyMode, OverflowPolicy,
+    WorkerHint, DEFAULT_EXCHANGE_SPILL_LIMIT, MIN_BINARY_OUTPUT_CAPACITY, MIN_OUTPUT_CAPACITY,
+};
 pub use data::Data;
+pub use dataflow::{ChannelDesc, OperatorDesc, PlanDesc};
+pub use drain::{drain, is_draining, DrainReport};
+pub use health::{health, HealthReport};
+pub use job_log::fetch_job_logs;
+pub use net_usage::{fetch_job_net_usage, JobNetUsage};
 pub use pegasus_common::codec;
 use pegasus_executor::{ExecError, TaskGuard};
 pub use pegasus_memory::alloc::check_current_task_memory;
+use pegasus_network::config::{ConnectionParams, NetworkConfig};
 pub use pegasus_network::ServerDetect;
+pub use profile::{fetch_job_profile, AdaptiveDecision, JobProfile, OperatorProfile};
+pub use progress::{peek_progress, JobProgress, SourceProgress};
+pub use resource::get_job_resource;
+pub use rng::{fetch_job_rng_seed, JobRng};
+pub use slow_query::{fetch_slow_queries, JobStatus, SlowQueryConfig, SlowQueryRecord};
+pub use submit::{admit, Admission};
 pub use tag::Tag;
+pub use warm::{warm_stats, WarmStats};
+pub use wire_trace::{dump_job_trace, JobWireTrace, WireRecord};
 pub use worker::Worker;
-pub use worker_id::{get_current_worker, WorkerId};
+pub use worker_id::{get_current_job_conf, get_current_worker, WorkerId};
 
 lazy_static! {
     static ref SERVER_ID: Mutex<Option<u64>> = Mutex::new(None);
@@ -100,6 +143,7 @@ fn set_server_id(server_id: u64) -> Option<u64> {
 }
 
 pub fn startup(conf: Configuration) -> Result<(), StartupError> {
+    conf.validate()?;
     let server_id = conf.server_id();
     if let Some(id) = set_server_id(server_id) {
         return Err(StartupError::AlreadyStarted(id));
@@ -107,7 +151,7 @@ pub fn startup(conf: Configuration) -> Result<(), StartupError> {
     if let Some(net_conf) = conf.network_config() {
         if let Some(peers) = net_conf.get_peers()? {
             let addr = net_conf.local_addr()?;
-            let conn_conf = net_conf.get_connection_param();
+            let conn_conf = connection_param(&conf, net_conf);
             let addr = pegasus_network::start_up(server_id, conn_conf, addr, peers)?;
             info!("server {} start on {:?}", server_id, addr);
         } else {
@@ -117,6 +161,19 @@ pub fn startup(conf: Configuration) -> Result<(), StartupError> {
     if let Some(pool_size) = conf.max_pool_size {
         pegasus_executor::set_core_pool_size(pool_size as usize);
     }
+    if let Some(addr) = conf.metrics_addr.as_ref() {
+        metrics::start_endpoint(addr)?;
+    }
+    if let Some(slow_query) = conf.slow_query.as_ref() {
+        slow_query::enable(slow_query)?;
+    }
+    if let Some(max_jobs) = conf.max_jobs {
+        submit::set_max_jobs(max_jobs);
+    }
+    if let Some(readiness) = conf.readiness.as_ref() {
+        health::set_readiness(readiness.iter().filter_map(|r| r.parse().ok()).collect());
+    }
+    scavenge_temp(&conf);
     pegasus_executor::try_start_executor_async();
     Ok(())
 }
@@ -124,6 +181,7 @@ pub fn startup(conf: Configuration) -> Result<(), StartupError> {
 pub fn startup_with<D: ServerDetect + 'static>(
     conf: Configuration, detect: D,
 ) -> Result<(), StartupError> {
+    conf.validate()?;
     let server_id = conf.server_id();
     if let Some(id) = set_server_id(server_id) {
         return Err(StartupError::AlreadyStarted(id));
@@ -131,7 +189,7 @@ pub fn startup_with<D: ServerDetect + 'static>(
 
     if let Some(net_conf) = conf.network_config() {
         let addr = net_conf.local_addr()?;
-        let conn_conf = net_conf.get_connection_param();
+        let conn_conf = connection_param(&conf, net_conf);
         let addr = pegasus_network::start_up(server_id, conn_conf, addr, detect)?;
         info!("server {} start on {:?}", server_id, addr);
     }
@@ -139,46 +197,122 @@ pub fn startup_with<D: ServerDetect + 'static>(
     if let Some(pool_size) = conf.max_pool_size {
         pegasus_executor::set_core_pool_size(pool_size as usize);
     }
+    if let Some(addr) = conf.metrics_addr.as_ref() {
+        metrics::start_endpoint(addr)?;
+    }
+    if let Some(slow_query) = conf.slow_query.as_ref() {
+        slow_query::enable(slow_query)?;
+    }
+    if let Some(max_jobs) = conf.max_jobs {
+        submit::set_max_jobs(max_jobs);
+    }
+    if let Some(readiness) = conf.readiness.as_ref() {
+        health::set_readiness(readiness.iter().filter_map(|r| r.parse().ok()).collect());
+    }
+    scavenge_temp(&conf);
     pegasus_executor::try_start_executor_async();
     Ok(())
 }
 
+// remove the temp directories left by the crashed processes before any job runs
+fn scavenge_temp(conf: &Configuration) {
+    match temp::init(conf.temp_dir.as_ref().map(Path::new)) {
+        Ok(removed) if removed > 0 => {
+            info!("remove {} temp directories left by the crashed processes", removed)
+        }
+        Ok(_) => {}
+        Err(e) => warn!("fail to scavenge the temp directories: {}", e),
+    }
+}
+
+fn connection_param(conf: &Configuration, net_conf: &NetworkConfig) -> ConnectionParams {
+    let mut params = net_conf.get_connection_param();
+    if let Some(size) = conf.io_pool_size {
+        params.set_io_threads(size as usize);
+    }
+    params
+}
+
 pub fn shutdown_all() {
     pegasus_executor::try_shutdown();
     if let Some(server_id) = server_id() {
+        // the network threads write the data sent before and deliver the data arrived before they
+        // exit, which is drained ahead of the teardown of the threads running the workers;
         pegasus_network::shutdown(server_id);
         pegasus_network::await_termination(server_id);
     }
     pegasus_executor::await_termination();
 }
 
-pub fn run<F>(conf: JobConf, logic: F) -> Result<Option<JobGuard>, JobSubmitError>
+pub fn run<F>(mut conf: JobConf, logic: F) -> Result<Option<JobGuard>, JobSubmitError>
 where
     F: Fn(&mut Worker) -> Result<(), BuildJobError>,
 {
+    if drain::is_draining() {
+        return Err(JobSubmitError::Draining);
+    }
     let cancel_hook = Arc::new(AtomicBool::new(false));
     let peer_guard = Arc::new(AtomicUsize::new(0));
+    // traced with the configuration submitted, before the profile is enabled for the log;
+    let trace = slow_query::trace(&conf);
+    if trace.is_some() {
+        conf.profile = true;
+    }
+    // held by the registry only, to be dropped once the workers end;
+    let resources = std::mem::take(&mut conf.resources);
+    conf.rng_seed = Some(rng::resolve_seed(conf.job_id, conf.rng_seed));
     let conf = Arc::new(conf);
+    let job_span = span::job_span(&conf);
+    if conf.capture_logs {
+        job_log::register(conf.job_id);
+    }
+    if conf.profile {
+        profile::register(conf.job_id);
+    }
+    if conf.wire_trace {
+        wire_trace::register(conf.job_id);
+    }
 
     let workers = allocate_worker(&conf)?;
     if workers.is_none() {
         return Ok(None);
     }
     let worker_ids = workers.unwrap();
+    progress::register(conf.job_id, conf.workers);
+    resource::register(conf.job_id, resources);
     let mut workers = WOKER_POOL.with(|pool| pool.replace(vec![]));
-    for id in worker_ids {
-        let mut worker = Worker::new(&conf, id, &peer_guard, &cancel_hook);
-        logic(&mut worker)?;
-        workers.push(worker);
-    }
+    // the job is prepared by reserving its slot and building its workers, which are started only
+    // if it is prepared on all its servers;
+    let prepared = submit::reserve(conf.job_id).and_then(|reservation| {
+        for id in worker_ids {
+            let mut worker = Worker::new(&conf, id, &peer_guard, &cancel_hook, &job_span, &trace);
+            logic(&mut worker)?;
+            workers.push(worker);
+        }
+        Ok(reservation)
+    });
+    let my_id = server_id().unwrap_or(0);
+    let _reservation = match submit::decide(&conf, my_id, prepared) {
+        Ok(reservation) => reservation,
+        Err(err) => {
+            workers.clear();
+            resource::unregister(conf.job_id);
+            WOKER_POOL.with(|pool| pool.replace(workers));
+            return Err(err);
+        }
+    };
 
     if workers.is_empty() {
+        resource::unregister(conf.job_id);
         WOKER_POOL.with(|pool| pool.replace(workers));
         return Ok(None);
     }
 
     let result = match pegasus_executor::spawn_batch(&mut workers.drain(..)) {
-        Ok(guards) => Ok(Some(JobGuard::new(conf.job_id, guards, &cancel_hook))),
+        Ok(guards) => {
+            drain::add_running_job(conf.job_id, &peer_guard, &cancel_hook);
+            Ok(Some(JobGuard::new(conf.job_id, guards, &cancel_hook)))
+        }
         Err(e) => {
             if pegasus_executor::is_shutdown() {
                 Err(SpawnJobError("Executor has shutdown;".into()))?
@@ -192,12 +326,43 @@ where
     result
 }
 
+/// Build the dataflow of the job by `logic` as `run` does, but without running it, to tell what
+/// the job would run, e.g. the operators fused or named after the steps they are compiled from;
+/// The dataflow is of the same shape on any worker, so it is built on a single worker of current
+/// server, whose channels are all local, and dropped once described; It fails as `run` would for
+/// the dataflow failed to be built, e.g. of an invalid topology;
+pub fn explain<F>(mut conf: JobConf, logic: F) -> Result<PlanDesc, BuildJobError>
+where
+    F: FnOnce(&mut Worker) -> Result<(), BuildJobError>,
+{
+    conf.workers = 1;
+    conf.clear_servers();
+    conf.profile = false;
+    // the functions of the operators may get the resources of the job while being built;
+    let resources = std::mem::take(&mut conf.resources);
+    let conf = Arc::new(conf);
+    resource::register(conf.job_id, resources);
+    let peer_guard = Arc::new(AtomicUsize::new(0));
+    let cancel_hook = Arc::new(AtomicBool::new(false));
+    let job_span = span::job_span(&conf);
+    let id = WorkerId::new(conf.job_id, 1, 0, false);
+    // the resources are unregistered once the worker is dropped;
+    let mut worker = Worker::new(&conf, id, &peer_guard, &cancel_hook, &job_span, &None);
+    worker.explaining();
+    logic(&mut worker)?;
+    worker.take_plan().ok_or_else(|| BuildJobError::from("no dataflow is built for the job;"))
+}
+
 #[inline]
 fn allocate_worker(conf: &Arc<JobConf>) -> Result<Option<WorkerIdIter>, BuildJobError> {
     if let Some(my_id) = server_id() {
         let servers = conf.servers();
         if servers.is_empty() || (servers.len() == 1 && servers[0] == my_id) {
-            Ok(Some(WorkerIdIter::new(conf.job_id, conf.workers, 0, conf.workers)))
+            let mut workers = WorkerIdIter::new(conf.job_id, conf.workers, 0, conf.workers);
+            if let Some(servers) = communication::get_simulated_servers(conf.job_id) {
+                workers.split_servers(servers);
+            }
+            Ok(Some(workers))
         } else {
             let mut my_index = -1;
             for (index, id) in servers.iter().enumerate() {
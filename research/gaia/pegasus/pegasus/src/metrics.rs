@@ -0,0 +1,564 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The metrics of the engine exported to prometheus with the feature `metrics`, including the
+//! running jobs, the records processed and the busy time of workers, the backlog of channels, the
+//! bytes of network, the batches retried by operators, the fill ratios of the Bloom filters of
+//! `dedup_approx`, and the hits of caches reported by the applications, e.g. the adjacency cache
+//! of gremlin, the network bytes of each job with each peer server once the job ends, see
+//! `crate::net_usage`, the temp resources alive on the disk, see `crate::temp`, and the bytes the
+//! receive buffers spill along with the stalls replaying them, see `JobConf::exchange_spill_limit`,
+//! which are served in the text format by the endpoint started at `Configuration::metrics_addr`.
+//! Without the feature, all of these are no-ops.
+//!
+//! The records are counted into thread local counters by the channels without any
+//! synchronization, which are flushed into the metrics of the worker after each run of it.
+//! The jobs are labeled by their names, which may be the query texts, so at most
+//! `MAX_JOB_NAMES` names are labeled, and the jobs of other names are labeled as "other".
+
+use crate::errors::StartupError;
+use crate::JobConf;
+use pegasus_network::PeerBytes;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+/// The most job names labeled in the metrics, beyond which the jobs are labeled as "other"
+pub const MAX_JOB_NAMES: usize = 64;
+
+#[cfg(feature = "metrics")]
+pub use self::prom::*;
+
+#[cfg(feature = "metrics")]
+mod prom {
+    use super::*;
+    use prometheus::{
+        Counter, CounterVec, Encoder, Histogram, HistogramOpts, HistogramVec, IntCounter,
+        IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry, TextEncoder,
+    };
+    use std::cell::Cell;
+    use std::collections::HashSet;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::Mutex;
+    use std::time::Instant;
+
+    struct Metrics {
+        running_jobs: IntGauge,
+        records: IntCounterVec,
+        backlog: IntGaugeVec,
+        busy: CounterVec,
+        net_sent: IntCounter,
+        net_received: IntCounter,
+        job_net_sent: IntCounterVec,
+        job_net_received: IntCounterVec,
+        cache_hits: IntCounterVec,
+        cache_misses: IntCounterVec,
+        retries: IntCounterVec,
+        filter_fill: HistogramVec,
+        temp_resources: IntGauge,
+        temp_scavenged: IntCounter,
+        spilled_bytes: IntCounterVec,
+        replay_stalls: IntCounterVec,
+        replay_stall_seconds: CounterVec,
+    }
+
+    impl Metrics {
+        fn register(registry: &Registry) -> prometheus::Result<Self> {
+            let job_worker = &["job", "worker"];
+            let metrics = Metrics {
+                running_jobs: IntGauge::new("pegasus_running_jobs", "The jobs running")?,
+                records: IntCounterVec::new(
+                    Opts::new("pegasus_records_processed_total", "The records received"),
+                    job_worker,
+                )?,
+                backlog: IntGaugeVec::new(
+                    Opts::new("pegasus_channel_backlog", "The records sent but not received"),
+                    &["job"],
+                )?,
+                busy: CounterVec::new(
+                    Opts::new("pegasus_worker_busy_seconds_total", "The time of running workers"),
+                    job_worker,
+                )?,
+                net_sent: IntCounter::new("pegasus_network_sent_bytes_total", "The bytes sent")?,
+                net_received: IntCounter::new(
+                    "pegasus_network_received_bytes_total",
+                    "The bytes received",
+                )?,
+                job_net_sent: IntCounterVec::new(
+                    Opts::new("pegasus_job_network_sent_bytes_total", "The bytes sent by jobs"),
+                    &["job", "peer"],
+                )?,
+                job_net_received: IntCounterVec::new(
+                    Opts::new(
+                        "pegasus_job_network_received_bytes_total",
+                        "The bytes received by jobs",
+                    ),
+                    &["job", "peer"],
+                )?,
+                cache_hits: IntCounterVec::new(
+                    Opts::new("pegasus_cache_hits_total", "The accesses hitting caches"),
+                    &["cache"],
+                )?,
+                cache_misses: IntCounterVec::new(
+                    Opts::new("pegasus_cache_misses_total", "The accesses missing caches"),
+                    &["cache"],
+                )?,
+                retries: IntCounterVec::new(
+                    Opts::new("pegasus_operator_retries_total", "The batches retried by operators"),
+                    &["job", "operator"],
+                )?,
+                filter_fill: HistogramVec::new(
+                    HistogramOpts::new(
+                        "pegasus_filter_fill_ratio",
+                        "The ratio of the bits set in the filter of each scope once it ends",
+                    )
+                    .buckets(prometheus::linear_buckets(0.1, 0.1, 10)?),
+                    &["job", "operator"],
+                )?,
+                temp_resources: IntGauge::new(
+                    "pegasus_temp_resources",
+                    "The temp files and directories of the jobs alive",
+                )?,
+                temp_scavenged: IntCounter::new(
+                    "pegasus_temp_scavenged_total",
+                    "The temp directories of the crashed processes removed",
+                )?,
+                spilled_bytes: IntCounterVec::new(
+                    Opts::new(
+                        "pegasus_exchange_spilled_bytes_total",
+                        "The bytes spilled by the receive buffers of the channels",
+                    ),
+                    &["job"],
+                )?,
+                replay_stalls: IntCounterVec::new(
+                    Opts::new(
+                        "pegasus_exchange_replay_stalls_total",
+                        "The batches the consumers wait for to be read back from the disk",
+                    ),
+                    &["job"],
+                )?,
+                replay_stall_seconds: CounterVec::new(
+                    Opts::new(
+                        "pegasus_exchange_replay_stall_seconds_total",
+                        "The time the consumers wait for the batches read back from the disk",
+                    ),
+                    &["job"],
+                )?,
+            };
+            registry.register(Box::new(metrics.running_jobs.clone()))?;
+            registry.register(Box::new(metrics.records.clone()))?;
+            registry.register(Box::new(metrics.backlog.clone()))?;
+            registry.register(Box::new(metrics.busy.clone()))?;
+            registry.register(Box::new(metrics.net_sent.clone()))?;
+            registry.register(Box::new(metrics.net_received.clone()))?;
+            registry.register(Box::new(metrics.job_net_sent.clone()))?;
+            registry.register(Box::new(metrics.job_net_received.clone()))?;
+            registry.register(Box::new(metrics.cache_hits.clone()))?;
+            registry.register(Box::new(metrics.cache_misses.clone()))?;
+            registry.register(Box::new(metrics.retries.clone()))?;
+            registry.register(Box::new(metrics.filter_fill.clone()))?;
+            registry.register(Box::new(metrics.temp_resources.clone()))?;
+            registry.register(Box::new(metrics.temp_scavenged.clone()))?;
+            registry.register(Box::new(metrics.spilled_bytes.clone()))?;
+            registry.register(Box::new(metrics.replay_stalls.clone()))?;
+            registry.register(Box::new(metrics.replay_stall_seconds.clone()))?;
+            Ok(metrics)
+        }
+    }
+
+    lazy_static! {
+        static ref REGISTRY: Registry = Registry::new();
+        static ref METRICS: Metrics =
+            Metrics::register(&REGISTRY).expect("register metrics failure");
+        static ref JOB_NAMES: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+        static ref ENDPOINT: Mutex<Option<SocketAddr>> = Mutex::new(None);
+        // the lock of gathering, to update the counters of network bytes one by one
+        static ref GATHER_LOCK: Mutex<()> = Mutex::new(());
+    }
+
+    thread_local! {
+        // the records (sent, received) by the worker running on current thread
+        static RECORDS: Cell<(u64, u64)> = const { Cell::new((0, 0)) };
+    }
+
+    /// The registry of the metrics of the engine, where the applications may register theirs
+    pub fn registry() -> &'static Registry {
+        &REGISTRY
+    }
+
+    /// Gather all metrics in the text format of prometheus
+    pub fn gather() -> String {
+        let _lock = GATHER_LOCK.lock().expect("lock poisoned");
+        METRICS.net_sent.inc_by(pegasus_network::get_sent_bytes() - METRICS.net_sent.get());
+        METRICS
+            .net_received
+            .inc_by(pegasus_network::get_received_bytes() - METRICS.net_received.get());
+        let mut buf = vec![];
+        if let Err(e) = TextEncoder::new().encode(&REGISTRY.gather(), &mut buf) {
+            error!("encode metrics failure: {}", e);
+        }
+        String::from_utf8(buf).unwrap_or_default()
+    }
+
+    /// Start the endpoint serving the metrics by `GET /metrics`, and the probes of the health by
+    /// `GET /live` and `GET /ready`, see `crate::health`, in a thread of the process
+    pub fn start_endpoint(addr: &str) -> Result<SocketAddr, StartupError> {
+        let listener = TcpListener::bind(addr).map_err(StartupError::MetricsEndpoint)?;
+        let addr = listener.local_addr().map_err(StartupError::MetricsEndpoint)?;
+        std::thread::Builder::new().name("metrics-endpoint".to_owned()).spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        if let Err(e) = serve(stream) {
+                            warn!("serve metrics failure: {}", e);
+                        }
+                    }
+                    Err(e) => warn!("accept metrics request failure: {}", e),
+                }
+            }
+        })?;
+        info!("metrics endpoint started on {}", addr);
+        ENDPOINT.lock().expect("lock poisoned").replace(addr);
+        Ok(addr)
+    }
+
+    /// The address of the endpoint serving the metrics, if started
+    pub fn endpoint_addr() -> Option<SocketAddr> {
+        ENDPOINT.lock().ok().and_then(|addr| *addr)
+    }
+
+    fn serve(mut stream: std::net::TcpStream) -> std::io::Result<()> {
+        let mut request = String::new();
+        let mut reader = BufReader::new(stream.try_clone()?);
+        reader.read_line(&mut request)?;
+        // skip the headers
+        let mut line = String::new();
+        while reader.read_line(&mut line)? > 2 {
+            line.clear();
+        }
+        let mut parts = request.split_whitespace();
+        let response = match (parts.next(), parts.next()) {
+            (Some("GET"), Some("/metrics")) => {
+                let body = gather();
+                format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\n\
+                     Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                )
+            }
+            (Some("GET"), Some("/live")) => probe(crate::health::is_live(), String::new()),
+            (Some("GET"), Some("/ready")) => {
+                let report = crate::health::health();
+                probe(report.ready, report.to_string())
+            }
+            _ => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                .to_owned(),
+        };
+        stream.write_all(response.as_bytes())?;
+        stream.flush()
+    }
+
+    // 200 if the probe passes, or 503 otherwise, which fails the probes of Kubernetes
+    fn probe(pass: bool, body: String) -> String {
+        let status = if pass { "200 OK" } else { "503 Service Unavailable" };
+        format!(
+            "HTTP/1.1 {}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\n\
+             Connection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        )
+    }
+
+    /// Report the accesses of a cache of the application, e.g. once the cache is dropped
+    pub fn add_cache_accesses(cache: &str, hits: u64, misses: u64) {
+        METRICS.cache_hits.with_label_values(&[cache]).inc_by(hits);
+        METRICS.cache_misses.with_label_values(&[cache]).inc_by(misses);
+    }
+
+    /// Report the bytes the job sends to and receives from each peer server, once the job ends
+    pub(crate) fn add_job_network_bytes(conf: &JobConf, peers: &[(u64, PeerBytes)]) {
+        let job = job_label(conf);
+        for (peer, bytes) in peers {
+            let peer = peer.to_string();
+            let labels = [job.as_str(), peer.as_str()];
+            METRICS.job_net_sent.with_label_values(&labels).inc_by(bytes.sent);
+            METRICS.job_net_received.with_label_values(&labels).inc_by(bytes.received);
+        }
+    }
+
+    fn job_label(conf: &JobConf) -> String {
+        let mut names = JOB_NAMES.lock().expect("lock poisoned");
+        if names.contains(&conf.job_name) {
+            conf.job_name.clone()
+        } else if names.len() < MAX_JOB_NAMES {
+            names.insert(conf.job_name.clone());
+            conf.job_name.clone()
+        } else {
+            "other".to_owned()
+        }
+    }
+
+    /// The metrics of a worker, with the labels resolved once it is created
+    pub(crate) struct WorkerMetrics {
+        records: IntCounter,
+        backlog: IntGauge,
+        busy: Counter,
+    }
+
+    impl WorkerMetrics {
+        pub(crate) fn new(conf: &JobConf, index: u32) -> Self {
+            let job = job_label(conf);
+            let index = index.to_string();
+            WorkerMetrics {
+                records: METRICS.records.with_label_values(&[job.as_str(), index.as_str()]),
+                backlog: METRICS.backlog.with_label_values(&[job.as_str()]),
+                busy: METRICS.busy.with_label_values(&[job.as_str(), index.as_str()]),
+            }
+        }
+
+        /// Measure a run of the worker, until the guard is dropped
+        pub(crate) fn measure(&self) -> RunGuard<'_> {
+            RECORDS.with(|r| r.set((0, 0)));
+            RunGuard { metrics: self, start: Instant::now() }
+        }
+    }
+
+    /// The retries of an operator, with the labels resolved once the operator is built
+    pub(crate) struct RetryMetrics {
+        retries: IntCounter,
+    }
+
+    impl RetryMetrics {
+        /// Resolve the labels by the job whose dataflow is being built by current thread
+        pub(crate) fn new(operator: &str) -> Self {
+            let job = crate::get_current_job_conf()
+                .map(|conf| job_label(&conf))
+                .unwrap_or_else(|| "other".to_owned());
+            RetryMetrics { retries: METRICS.retries.with_label_values(&[job.as_str(), operator]) }
+        }
+
+        pub(crate) fn on_retry(&self) {
+            self.retries.inc();
+        }
+    }
+
+    /// The fill ratios of the Bloom filters of an operator, with the labels resolved once the
+    /// operator is built
+    pub(crate) struct FilterMetrics {
+        fill: Histogram,
+    }
+
+    impl FilterMetrics {
+        /// Resolve the labels by the job whose dataflow is being built by current thread
+        pub(crate) fn new(operator: &str) -> Self {
+            let job = crate::get_current_job_conf()
+                .map(|conf| job_label(&conf))
+                .unwrap_or_else(|| "other".to_owned());
+            let fill = METRICS.filter_fill.with_label_values(&[job.as_str(), operator]);
+            FilterMetrics { fill }
+        }
+
+        pub(crate) fn on_fill(&self, ratio: f64) {
+            self.fill.observe(ratio);
+        }
+    }
+
+    /// The spills of the receive buffers of a channel, with the labels resolved once the channel
+    /// is built
+    pub(crate) struct SpillMetrics {
+        spilled_bytes: IntCounter,
+        replay_stalls: IntCounter,
+        replay_stall_seconds: Counter,
+    }
+
+    impl SpillMetrics {
+        pub(crate) fn new(conf: &JobConf) -> Self {
+            let job = job_label(conf);
+            SpillMetrics {
+                spilled_bytes: METRICS.spilled_bytes.with_label_values(&[job.as_str()]),
+                replay_stalls: METRICS.replay_stalls.with_label_values(&[job.as_str()]),
+                replay_stall_seconds: METRICS
+                    .replay_stall_seconds
+                    .with_label_values(&[job.as_str()]),
+            }
+        }
+
+        pub(crate) fn on_spilled(&self, bytes: u64) {
+            self.spilled_bytes.inc_by(bytes);
+        }
+
+        pub(crate) fn on_replay_stall(&self, stall: Duration) {
+            self.replay_stalls.inc();
+            self.replay_stall_seconds.inc_by(stall.as_secs_f64());
+        }
+    }
+
+    pub(crate) struct RunGuard<'a> {
+        metrics: &'a WorkerMetrics,
+        start: Instant,
+    }
+
+    impl<'a> Drop for RunGuard<'a> {
+        fn drop(&mut self) {
+            let (sent, received) = RECORDS.with(|r| r.replace((0, 0)));
+            self.metrics.records.inc_by(received);
+            self.metrics.backlog.add(sent as i64 - received as i64);
+            self.metrics.busy.inc_by(self.start.elapsed().as_secs_f64());
+        }
+    }
+
+    #[inline]
+    pub(crate) fn on_send(size: usize) {
+        RECORDS.with(|r| {
+            let (sent, received) = r.get();
+            r.set((sent + size as u64, received))
+        });
+    }
+
+    #[inline]
+    pub(crate) fn on_recv(size: usize) {
+        RECORDS.with(|r| {
+            let (sent, received) = r.get();
+            r.set((sent, received + size as u64))
+        });
+    }
+
+    pub(crate) fn job_started() {
+        METRICS.running_jobs.inc();
+    }
+
+    pub(crate) fn job_finished() {
+        METRICS.running_jobs.dec();
+    }
+
+    pub(crate) fn on_temp_created() {
+        METRICS.temp_resources.inc();
+    }
+
+    pub(crate) fn on_temp_removed() {
+        METRICS.temp_resources.dec();
+    }
+
+    pub(crate) fn on_temp_scavenged(dirs: usize) {
+        METRICS.temp_scavenged.inc_by(dirs as u64);
+    }
+}
+
+#[cfg(not(feature = "metrics"))]
+pub use self::noop::*;
+
+#[cfg(not(feature = "metrics"))]
+mod noop {
+    use super::*;
+
+    /// Gather all metrics in the text format of prometheus, which is empty without the feature
+    pub fn gather() -> String {
+        String::new()
+    }
+
+    /// The endpoint is never started without the feature
+    pub fn start_endpoint(addr: &str) -> Result<SocketAddr, StartupError> {
+        let violation = format!("metrics_addr {} requires the feature 'metrics' of pegasus", addr);
+        Err(StartupError::InvalidConfig(vec![violation]))
+    }
+
+    pub fn endpoint_addr() -> Option<SocketAddr> {
+        None
+    }
+
+    #[inline]
+    pub fn add_cache_accesses(_cache: &str, _hits: u64, _misses: u64) {}
+
+    #[inline]
+    pub(crate) fn add_job_network_bytes(_conf: &JobConf, _peers: &[(u64, PeerBytes)]) {}
+
+    pub(crate) struct WorkerMetrics;
+
+    impl WorkerMetrics {
+        #[inline]
+        pub(crate) fn new(_conf: &JobConf, _index: u32) -> Self {
+            WorkerMetrics
+        }
+
+        #[inline]
+        pub(crate) fn measure(&self) -> RunGuard {
+            RunGuard
+        }
+    }
+
+    pub(crate) struct RunGuard;
+
+    pub(crate) struct SpillMetrics;
+
+    impl SpillMetrics {
+        #[inline]
+        pub(crate) fn new(_conf: &JobConf) -> Self {
+            SpillMetrics
+        }
+
+        #[inline]
+        pub(crate) fn on_spilled(&self, _bytes: u64) {}
+
+        #[inline]
+        pub(crate) fn on_replay_stall(&self, _stall: Duration) {}
+    }
+
+    pub(crate) struct RetryMetrics;
+
+    impl RetryMetrics {
+        #[inline]
+        pub(crate) fn new(_operator: &str) -> Self {
+            RetryMetrics
+        }
+
+        #[inline]
+        pub(crate) fn on_retry(&self) {}
+    }
+
+    pub(crate) struct FilterMetrics;
+
+    impl FilterMetrics {
+        #[inline]
+        pub(crate) fn new(_operator: &str) -> Self {
+            FilterMetrics
+        }
+
+        #[inline]
+        pub(crate) fn on_fill(&self, _ratio: f64) {}
+    }
+
+    #[inline]
+    pub(crate) fn on_send(_size: usize) {}
+
+    #[inline]
+    pub(crate) fn on_recv(_size: usize) {}
+
+    #[inline]
+    pub(crate) fn job_started() {}
+
+    #[inline]
+    pub(crate) fn job_finished() {}
+
+    #[inline]
+    pub(crate) fn on_temp_created() {}
+
+    #[inline]
+    pub(crate) fn on_temp_removed() {}
+
+    #[inline]
+    pub(crate) fn on_temp_scavenged(_dirs: usize) {}
+}
@@ -0,0 +1,128 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The bytes each job sends to and receives from each of the other servers, which are accounted
+//! by the connections of the network, see `pegasus_network::track_job`. A job is tracked once its
+//! first worker in current server is created, and untracked once its last worker is dropped, when
+//! its bytes are reported into the metrics labeled by the job and the peer, and kept for at most
+//! `MAX_FINISHED_JOBS` jobs, to be fetched by `fetch_job_net_usage` as those of the running jobs.
+//!
+//! The bytes of a job of `JobConf::network_byte_limit` are checked by each of its workers after
+//! each run, and the worker finding them beyond the limit fails with `NetworkLimitExceeded`, which
+//! cancels the other workers of the job as any failure does.
+
+use crate::errors::NetworkLimitExceeded;
+use crate::JobConf;
+pub use pegasus_network::PeerBytes;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// The most jobs ended whose bytes are kept;
+pub const MAX_FINISHED_JOBS: usize = 64;
+
+/// The bytes a job sends to and receives from the other servers in current server;
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct JobNetUsage {
+    pub job_id: u64,
+    /// the bytes with each of the other servers, in the order of the ids of the servers;
+    pub peers: Vec<(u64, PeerBytes)>,
+    /// whether the job has ended in current server, after which its bytes no longer change;
+    pub finished: bool,
+}
+
+impl JobNetUsage {
+    pub fn sent(&self) -> u64 {
+        self.peers.iter().map(|(_, bytes)| bytes.sent).sum()
+    }
+
+    pub fn received(&self) -> u64 {
+        self.peers.iter().map(|(_, bytes)| bytes.received).sum()
+    }
+
+    pub fn total(&self) -> u64 {
+        self.sent() + self.received()
+    }
+}
+
+lazy_static! {
+    static ref FINISHED_JOBS: Mutex<VecDeque<JobNetUsage>> = Mutex::new(VecDeque::new());
+}
+
+/// Get the bytes of the job in current server, either running or ended lately, or `None` if the
+/// job is unknown, or its bytes have been dropped for the later jobs;
+pub fn fetch_job_net_usage(job_id: u64) -> Option<JobNetUsage> {
+    if let Some(peers) = pegasus_network::get_job_bytes(job_id) {
+        return Some(JobNetUsage { job_id, peers, finished: false });
+    }
+    let finished = FINISHED_JOBS.lock().ok()?;
+    finished.iter().rev().find(|usage| usage.job_id == job_id).cloned()
+}
+
+pub(crate) fn on_job_started(job_id: u64) {
+    pegasus_network::track_job(job_id);
+}
+
+pub(crate) fn on_job_finished(conf: &JobConf) {
+    let peers = pegasus_network::untrack_job(conf.job_id).unwrap_or_default();
+    crate::metrics::add_job_network_bytes(conf, &peers);
+    if let Ok(mut finished) = FINISHED_JOBS.lock() {
+        finished.retain(|usage| usage.job_id != conf.job_id);
+        finished.push_back(JobNetUsage { job_id: conf.job_id, peers, finished: true });
+        while finished.len() > MAX_FINISHED_JOBS {
+            finished.pop_front();
+        }
+    }
+}
+
+/// Check the bytes of the job against its `network_byte_limit`, if any;
+#[inline]
+pub(crate) fn check_limit(conf: &JobConf) -> Result<(), NetworkLimitExceeded> {
+    let limit = conf.network_byte_limit;
+    if limit > 0 {
+        let bytes = pegasus_network::get_job_total_bytes(conf.job_id);
+        if bytes > limit {
+            return Err(NetworkLimitExceeded { job_id: conf.job_id, limit, bytes });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn job_net_usage_test() {
+        let job_id = 1 << 42;
+        assert!(fetch_job_net_usage(job_id).is_none());
+        on_job_started(job_id);
+        let usage = fetch_job_net_usage(job_id).unwrap();
+        assert_eq!(usage, JobNetUsage { job_id, peers: vec![], finished: false });
+        let mut conf = JobConf::new(job_id, "job_net_usage_test", 1);
+        conf.network_byte_limit = 1;
+        assert!(check_limit(&conf).is_ok());
+        on_job_finished(&conf);
+        let usage = fetch_job_net_usage(job_id).unwrap();
+        assert!(usage.finished);
+        assert_eq!(usage.total(), 0);
+
+        for id in 1..=MAX_FINISHED_JOBS as u64 {
+            on_job_started(job_id + id);
+            on_job_finished(&JobConf::new(job_id + id, "job_net_usage_test", 1));
+        }
+        assert!(fetch_job_net_usage(job_id).is_none());
+        assert!(fetch_job_net_usage(job_id + 1).is_some());
+    }
+}
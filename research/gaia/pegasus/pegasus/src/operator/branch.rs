@@ -13,6 +13,7 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
+use crate::api::function::FnResult;
 use crate::api::meta::OperatorKind;
 use crate::api::{Branch, Condition, IntoBranch};
 use crate::communication::input::{new_input_session, InputProxy};
@@ -52,6 +53,61 @@ impl<D: Data, F: Condition<D> + 'static> OperatorCore for BranchOperator<D, F> {
     }
 }
 
+/// Where a datum goes among the two outputs of a branch-like operator, e.g. the data rejected by
+/// a filter go to the right one;
+pub(crate) enum Routed<L, R> {
+    Left(L),
+    Right(R),
+}
+
+struct RouteOperator<I, L, R, F> {
+    func: F,
+    _ph: std::marker::PhantomData<(I, L, R)>,
+}
+
+impl<I, L, R, F> OperatorCore for RouteOperator<I, L, R, F>
+where
+    I: Data,
+    L: Data,
+    R: Data,
+    F: Fn(I) -> FnResult<Routed<L, R>> + Send + 'static,
+{
+    fn on_receive(
+        &mut self, tag: &Tag, inputs: &[Box<dyn InputProxy>], outputs: &[Box<dyn OutputProxy>],
+    ) -> Result<FiredState, JobExecError> {
+        let mut input = new_input_session::<I>(&inputs[0], tag);
+        let mut left = new_output_session::<L>(&outputs[0], tag);
+        let mut right = new_output_session::<R>(&outputs[1], tag);
+        input.for_each_batch(|dataset| {
+            for item in dataset.drain(..) {
+                match (self.func)(item)? {
+                    Routed::Left(item) => left.give(item)?,
+                    Routed::Right(item) => right.give(item)?,
+                }
+            }
+            Ok(())
+        })?;
+        Ok(FiredState::Idle)
+    }
+}
+
+/// Route each datum of the stream to either of the two output streams by the function, where
+/// both streams carry the same scopes as the input;
+pub(crate) fn route<I, L, R, F>(
+    stream: &Stream<I>, name: &str, func: F,
+) -> Result<(Stream<L>, Stream<R>), BuildJobError>
+where
+    I: Data,
+    L: Data,
+    R: Data,
+    F: Fn(I) -> FnResult<Routed<L, R>> + Send + 'static,
+{
+    stream.make_branch(name, Pipeline, |meta| {
+        meta.set_kind(OperatorKind::Map);
+        Box::new(RouteOperator { func, _ph: std::marker::PhantomData })
+    })
+}
+
 impl<D: Data> IntoBranch<D> for Stream<D> {
     fn branch<F: Condition<D> + 'static>(
         &self, name: &str, func: F,
@@ -61,4 +117,11 @@ impl<D: Data> IntoBranch<D> for Stream<D> {
             Box::new(BranchOperator { condition: func, _ph: std::marker::PhantomData })
         })
     }
+
+    fn branch_by<F>(&self, pred: F) -> Result<(Stream<D>, Stream<D>), BuildJobError>
+    where
+        F: Fn(&D) -> bool + Send + 'static,
+    {
+        self.branch("branch", move |item: &D| if pred(item) { Branch::Left } else { Branch::Right })
+    }
 }
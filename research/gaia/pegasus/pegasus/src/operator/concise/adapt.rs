@@ -0,0 +1,151 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use crate::api::notify::Notification;
+use crate::api::{Adapt, Branch, Decision, Merge};
+use crate::communication::input::{new_input_session, InputProxy};
+use crate::communication::output::{new_output_session, OutputProxy};
+use crate::communication::Pipeline;
+use crate::errors::{BuildJobError, JobExecError};
+use crate::operator::{FiredState, OperatorCore};
+use crate::profile::AdaptiveDecision;
+use crate::stream::Stream;
+use crate::{Data, Tag};
+use std::collections::HashMap;
+
+/// The state of a scope on the decision of an adaptive part;
+enum ScopeState<D> {
+    /// the data held to be decided by, before any alternative is chosen;
+    Sampling(Vec<D>),
+    Decided(Branch),
+}
+
+/// The head of an adaptive part, which routes the data of each scope to the alternative chosen
+/// by the first data of the scope;
+struct DecisionOperator<D, P> {
+    name: String,
+    sample: usize,
+    decide: P,
+    // the job to report the decisions to, if it is profiled;
+    profiled: Option<u64>,
+    scopes: HashMap<Tag, ScopeState<D>>,
+}
+
+impl<D: Data, P: Fn(&[D]) -> Decision + Send + 'static> DecisionOperator<D, P> {
+    fn decide(&self, tag: &Tag, held: &[D]) -> Branch {
+        let decision = (self.decide)(held);
+        debug_worker!(
+            "{} chooses {:?} for scope {:?} by {} data: {}",
+            self.name,
+            decision.choice,
+            tag,
+            held.len(),
+            decision.reason
+        );
+        if let Some(job_id) = self.profiled {
+            let worker = crate::get_current_worker().map(|w| w.index).unwrap_or(0);
+            let decision = AdaptiveDecision {
+                name: self.name.clone(),
+                worker,
+                scope: format!("{:?}", tag),
+                choice: decision.choice,
+                sampled: held.len(),
+                reason: decision.reason,
+            };
+            crate::profile::add_decision(job_id, decision);
+        }
+        decision.choice
+    }
+}
+
+impl<D: Data, P: Fn(&[D]) -> Decision + Send + 'static> OperatorCore for DecisionOperator<D, P> {
+    fn on_receive(
+        &mut self, tag: &Tag, inputs: &[Box<dyn InputProxy>], outputs: &[Box<dyn OutputProxy>],
+    ) -> Result<FiredState, JobExecError> {
+        let mut input = new_input_session::<D>(&inputs[0], tag);
+        let mut first = new_output_session::<D>(&outputs[0], tag);
+        let mut second = new_output_session::<D>(&outputs[1], tag);
+        let mut state =
+            self.scopes.remove(tag).unwrap_or_else(|| ScopeState::Sampling(Vec::new()));
+        let result = input.for_each_batch(|dataset| {
+            if let ScopeState::Sampling(held) = &mut state {
+                let take = std::cmp::min(self.sample - held.len(), dataset.len());
+                held.extend(dataset.drain(..take));
+                if held.len() < self.sample {
+                    return Ok(());
+                }
+                let choice = self.decide(tag, held);
+                let held = std::mem::take(held);
+                match choice {
+                    Branch::Left => first.give_entire_iter(held)?,
+                    Branch::Right => second.give_entire_iter(held)?,
+                }
+                state = ScopeState::Decided(choice);
+            }
+            match state {
+                ScopeState::Decided(Branch::Left) => first.forward(dataset)?,
+                ScopeState::Decided(Branch::Right) => second.forward(dataset)?,
+                ScopeState::Sampling(_) => unreachable!("scope {:?} is decided", tag),
+            }
+            Ok(())
+        });
+        self.scopes.insert(tag.clone(), state);
+        result?;
+        Ok(FiredState::Idle)
+    }
+
+    fn on_notify(
+        &mut self, n: Notification, outputs: &[Box<dyn OutputProxy>],
+    ) -> Result<(), JobExecError> {
+        // a scope of fewer data than the sample is decided by all of its data as it ends;
+        if let Some(ScopeState::Sampling(held)) = self.scopes.remove(&n.tag) {
+            if !held.is_empty() {
+                let port = match self.decide(&n.tag, &held) {
+                    Branch::Left => 0,
+                    Branch::Right => 1,
+                };
+                new_output_session::<D>(&outputs[port], &n.tag).give_entire_iter(held)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<D: Data> Adapt<D> for Stream<D> {
+    fn adapt<O, P, A, B>(
+        &self, name: &str, sample: usize, decide: P, first: A, second: B,
+    ) -> Result<Stream<O>, BuildJobError>
+    where
+        O: Data,
+        P: Fn(&[D]) -> Decision + Send + 'static,
+        A: FnOnce(Stream<D>) -> Result<Stream<O>, BuildJobError>,
+        B: FnOnce(Stream<D>) -> Result<Stream<O>, BuildJobError>,
+    {
+        let op_name = name.to_owned();
+        let (left, right) = self.make_branch(name, Pipeline, move |meta| {
+            meta.enable_notify();
+            let profiled = if meta.profile { Some(meta.worker_id.job_id) } else { None };
+            Box::new(DecisionOperator::<D, P> {
+                name: op_name,
+                // at least a datum is needed to decide by
+                sample: std::cmp::max(1, sample),
+                decide,
+                profiled,
+                scopes: HashMap::new(),
+            })
+        })?;
+        first(left)?.merge(&second(right)?)
+    }
+}
@@ -13,40 +13,45 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
-use crate::api::notify::Notification;
-use crate::api::state::StateMap;
-use crate::api::{Dedup, Range, Unary, UnaryNotify};
-use crate::communication::{Aggregate, Input, Output, Pipeline};
+use crate::api::function::{FnResult, RouteFunction};
+use crate::api::state::OperatorState;
+use crate::api::{ApproxDedup, BloomFilter, Dedup, Range, Unary, UnaryState};
+use crate::communication::{Channel, Input, Output};
 use crate::errors::JobExecError;
+use crate::metrics::FilterMetrics;
+use crate::operator::concise::gather;
 use crate::stream::Stream;
 use crate::{BuildJobError, Data};
 use pegasus_common::collections::{Collection, CollectionFactory, DefaultCollectionFactory, Set};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
 
-struct DedupHandle<D: Data + Eq, C: CollectionFactory<D>> {
+struct DedupHandle<D, C> {
     factory: C,
-    container: StateMap<C::Target>,
     _ph: std::marker::PhantomData<D>,
 }
 
-impl<D: Data + Eq, C: CollectionFactory<D>> DedupHandle<D, C> {
-    pub fn new(factory: C, container: StateMap<C::Target>) -> Self {
-        DedupHandle { factory, container, _ph: std::marker::PhantomData }
+impl<D, C> DedupHandle<D, C> {
+    pub fn new(factory: C) -> Self {
+        DedupHandle { factory, _ph: std::marker::PhantomData }
     }
 }
 
-impl<D: Data + Eq, C: CollectionFactory<D> + 'static> UnaryNotify<D, D> for DedupHandle<D, C>
+// the set is created by the factory on the first data of a scope, and dropped once the scope ends;
+impl<D: Data + Eq, C: CollectionFactory<D> + 'static> UnaryState<D, D, Option<C::Target>>
+    for DedupHandle<D, C>
 where
-    C::Target: Set<D>,
+    C::Target: Set<D> + 'static,
 {
-    type NotifyResult = Vec<D>;
+    type NotifyResult = Option<D>;
 
     fn on_receive(
-        &mut self, input: &mut Input<D>, output: &mut Output<D>,
+        &self, input: &mut Input<D>, output: &mut Output<D>,
+        state: &mut OperatorState<Option<C::Target>>,
     ) -> Result<(), JobExecError> {
-        input.subscribe_notify();
         let factory = &self.factory;
-        let container = self.container.entry(&input.tag).or_insert_with(|| factory.create());
-
+        let container = state.get_or_insert_with(|| factory.create());
         input.for_each_batch(|data| {
             for datum in data.drain(..) {
                 if !container.contains(&datum) {
@@ -55,17 +60,11 @@ where
                 }
             }
             Ok(())
-        })?;
-
-        Ok(())
+        })
     }
 
-    fn on_notify(&mut self, n: &Notification) -> Self::NotifyResult {
-        self.container.notify(n);
-        let notified = self.container.extract_notified();
-        assert_eq!(notified.len(), 1);
-        notified.remove(0).1;
-        vec![]
+    fn on_notify(&self, _: Option<C::Target>) -> Self::NotifyResult {
+        None
     }
 }
 
@@ -74,17 +73,8 @@ impl<D: Data + Eq> Dedup<D> for Stream<D> {
     where
         S: Set<D> + Default + 'static,
     {
-        let factory = DefaultCollectionFactory::new();
-        match range {
-            Range::Local => self.unary_with_notify("dedup", Pipeline, |meta| {
-                let state = StateMap::new(meta);
-                DedupHandle::<D, DefaultCollectionFactory<D, S>>::new(factory, state)
-            }),
-            Range::Global => self.unary_with_notify("dedup", Aggregate(0), |meta| {
-                let state = StateMap::new(meta);
-                DedupHandle::<D, DefaultCollectionFactory<D, S>>::new(factory, state)
-            }),
-        }
+        let factory = DefaultCollectionFactory::<D, S>::new();
+        self.dedup_with(range, factory)
     }
 
     fn dedup_with<S>(&self, range: Range, factory: S) -> Result<Stream<D>, BuildJobError>
@@ -92,15 +82,124 @@ impl<D: Data + Eq> Dedup<D> for Stream<D> {
         S: CollectionFactory<D> + 'static,
         S::Target: Set<D>,
     {
-        match range {
-            Range::Local => self.unary_with_notify("dedup", Pipeline, |meta| {
-                let state = StateMap::new(meta);
-                DedupHandle::<D, S>::new(factory, state)
-            }),
-            Range::Global => self.unary_with_notify("dedup", Aggregate(0), |meta| {
-                let state = StateMap::new(meta);
-                DedupHandle::<D, S>::new(factory, state)
-            }),
+        self.unary_with_state("dedup", gather(range), |_| DedupHandle::<D, S>::new(factory))
+    }
+}
+
+struct HashRoute<H> {
+    hash: Arc<H>,
+}
+
+impl<D, H> RouteFunction<D> for HashRoute<H>
+where
+    H: Fn(&D) -> FnResult<u64> + Send + Sync + 'static,
+{
+    fn route(&self, data: &D) -> FnResult<u64> {
+        (self.hash)(data)
+    }
+}
+
+struct ApproxDedupHandle<H> {
+    hash: Arc<H>,
+    expected_items: u64,
+    fp_rate: f64,
+    metrics: FilterMetrics,
+}
+
+// the filter is created on the first data of a scope, whose fill ratio is reported once the scope
+// ends;
+impl<D, H> UnaryState<D, D, Option<BloomFilter>> for ApproxDedupHandle<H>
+where
+    D: Data,
+    H: Fn(&D) -> FnResult<u64> + Send + Sync + 'static,
+{
+    type NotifyResult = Option<D>;
+
+    fn on_receive(
+        &self, input: &mut Input<D>, output: &mut Output<D>,
+        state: &mut OperatorState<Option<BloomFilter>>,
+    ) -> Result<(), JobExecError> {
+        let (expected_items, fp_rate) = (self.expected_items, self.fp_rate);
+        let filter = state.get_or_insert_with(|| BloomFilter::with_rate(expected_items, fp_rate));
+        input.for_each_batch(|data| {
+            for datum in data.drain(..) {
+                if filter.insert_hash((self.hash)(&datum)?) {
+                    output.give(datum)?;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn on_notify(&self, filter: Option<BloomFilter>) -> Self::NotifyResult {
+        if let Some(filter) = filter {
+            self.metrics.on_fill(filter.fill_ratio());
+        }
+        None
+    }
+}
+
+impl<D: Data> ApproxDedup<D> for Stream<D> {
+    fn dedup_approx(
+        &self, range: Range, expected_items: u64, fp_rate: f64,
+    ) -> Result<Stream<D>, BuildJobError>
+    where
+        D: Hash,
+    {
+        self.dedup_approx_by(range, expected_items, fp_rate, |datum: &D| {
+            // the hasher of fixed keys, so that the duplicates have the same hash in all workers;
+            let mut hasher = DefaultHasher::new();
+            datum.hash(&mut hasher);
+            Ok(hasher.finish())
+        })
+    }
+
+    fn dedup_approx_by<H>(
+        &self, range: Range, expected_items: u64, fp_rate: f64, hash: H,
+    ) -> Result<Stream<D>, BuildJobError>
+    where
+        H: Fn(&D) -> FnResult<u64> + Send + Sync + 'static,
+    {
+        if expected_items == 0 || !(fp_rate > 0.0 && fp_rate < 1.0) {
+            let msg = format!(
+                "dedup_approx of {} expected items at false positive rate {}, which should be \
+                 positive and in (0, 1);",
+                expected_items, fp_rate
+            );
+            return BuildJobError::unsupported(msg);
+        }
+        let local_peers = self.local_peers() as u64;
+        // the items and the filters of each worker in a server;
+        let (expected_items, filters) = match range {
+            Range::Local => (expected_items, local_peers),
+            Range::PerServer => (expected_items, 1),
+            Range::Global => {
+                let peers = self.peers() as u64;
+                (expected_items.div_ceil(peers), local_peers)
+            }
+        };
+        let bytes = BloomFilter::memory_bytes(expected_items, fp_rate) * filters;
+        if let Some(conf) = crate::get_current_job_conf() {
+            let limit = conf.memory_limit;
+            if limit > 0 && limit != !0u32 && bytes > (limit as u64) << 20 {
+                let msg = format!(
+                    "dedup_approx needs {} bytes of the filters of each scope in each server, \
+                     beyond the memory_limit {}MB of the job;",
+                    bytes, limit
+                );
+                return BuildJobError::unsupported(msg);
+            }
         }
+        let hash = Arc::new(hash);
+        let channel: Channel<D> = match range {
+            Range::Global => Box::new(HashRoute { hash: hash.clone() }).into(),
+            _ => gather(range),
+        };
+        self.unary_with_state("dedup_approx", channel, move |_| ApproxDedupHandle {
+            hash,
+            expected_items,
+            fp_rate,
+            metrics: FilterMetrics::new("dedup_approx"),
+        })
     }
 }
@@ -14,6 +14,7 @@
 //! limitations under the License.
 
 use crate::api::function::*;
+use crate::api::key::{KeySelector, StableKey};
 use crate::api::meta::OperatorKind;
 use crate::api::Exchange;
 use crate::api::Unary;
@@ -43,4 +44,28 @@ impl<D: Data> Exchange<D> for Stream<D> {
     {
         self.exchange(route!(func))
     }
+
+    fn exchange_by<K, F>(&self, keys: KeySelector<D, K, F>) -> Result<Stream<D>, BuildJobError>
+    where
+        K: StableKey + 'static,
+        F: Fn(&D) -> K + Send + 'static,
+    {
+        let key_type = keys.key_type();
+        let mut stream = self.exchange(keys)?;
+        stream.partitioned_by = Some(key_type);
+        Ok(stream)
+    }
+
+    fn co_partitioned_with<R: Data>(&self, other: &Stream<R>) -> Result<(), BuildJobError> {
+        match (self.partitioned_by, other.partitioned_by) {
+            (Some(left), Some(right)) if left == right => Ok(()),
+            (left, right) => BuildJobError::unsupported(format!(
+                "the streams are not co-partitioned, as they are exchanged by the keys of {} and \
+                 {}, while both should be exchanged by `exchange_by` with the keys of the same \
+                 type;",
+                left.unwrap_or("none"),
+                right.unwrap_or("none")
+            )),
+        }
+    }
 }
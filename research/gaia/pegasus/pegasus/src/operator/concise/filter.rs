@@ -18,6 +18,7 @@ use crate::api::meta::OperatorKind;
 use crate::api::{Filter, Unary};
 use crate::communication::Pipeline;
 use crate::errors::BuildJobError;
+use crate::operator::branch::{route, Routed};
 use crate::stream::Stream;
 use crate::Data;
 
@@ -57,4 +58,17 @@ impl<D: Data> Filter<D> for Stream<D> {
     {
         self.filter(filter!(func))
     }
+
+    fn filter_with_sideoutput<F>(&self, func: F) -> Result<(Stream<D>, Stream<D>), BuildJobError>
+    where
+        F: Fn(&D) -> FnResult<bool> + Send + 'static,
+    {
+        route(self, "filter_with_sideoutput", move |item: D| {
+            if func(&item)? {
+                Ok(Routed::Left(item))
+            } else {
+                Ok(Routed::Right(item))
+            }
+        })
+    }
 }
@@ -16,12 +16,16 @@
 use crate::api::accum::{AccumFactory, Accumulator};
 use crate::api::meta::OperatorKind;
 use crate::api::notify::Notification;
-use crate::api::{Fold, Range, Unary, UnaryNotify};
-use crate::communication::{Aggregate, Channel, Input, Output, Pipeline};
+use crate::api::state::OperatorState;
+use crate::api::{Fold, Range, Unary, UnaryNotify, UnaryState};
+use crate::communication::{Channel, Input, Output};
 use crate::errors::{BuildJobError, JobExecError};
+use crate::operator::concise::gather;
 use crate::stream::Stream;
 use crate::{Data, Tag};
 use std::collections::HashMap;
+use std::io;
+use std::sync::{Arc, Mutex};
 
 struct FoldHandle<I, O, F> {
     seed: O,
@@ -121,6 +125,37 @@ where
     }
 }
 
+/// Combine the partial accumulators of each scope;
+struct FoldCombineHandle<M> {
+    combine: M,
+}
+
+impl<O: Data, M> UnaryState<O, O, Option<O>> for FoldCombineHandle<M>
+where
+    M: Fn(&mut O, O) -> Result<(), io::Error> + Send + 'static,
+{
+    type NotifyResult = Option<O>;
+
+    fn on_receive(
+        &self, input: &mut Input<O>, _: &mut Output<O>, state: &mut OperatorState<Option<O>>,
+    ) -> Result<(), JobExecError> {
+        input.for_each_batch(|dataset| {
+            for partial in dataset.drain(..) {
+                if let Some(pre) = state.as_mut() {
+                    (self.combine)(pre, partial)?;
+                } else {
+                    **state = Some(partial);
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn on_notify(&self, state: Option<O>) -> Self::NotifyResult {
+        state
+    }
+}
+
 impl<I: Data> Fold<I> for Stream<I> {
     fn fold<O, C, F>(&self, seed: O, channel: C, func: F) -> Result<Stream<O>, BuildJobError>
     where
@@ -141,15 +176,58 @@ impl<I: Data> Fold<I> for Stream<I> {
         A: AccumFactory<I> + 'static,
         A::Target: Data,
     {
-        match range {
-            Range::Local => self.unary_with_notify("fold_with_accum", Pipeline, |meta| {
-                meta.set_kind(OperatorKind::Clip);
-                FoldAccumHandle::new(accum_factory)
-            }),
-            Range::Global => self.unary_with_notify("fold_with_accum", Aggregate(0), |meta| {
-                meta.set_kind(OperatorKind::Clip);
-                FoldAccumHandle::new(accum_factory)
-            }),
+        self.unary_with_notify("fold_with_accum", gather(range), |meta| {
+            meta.set_kind(OperatorKind::Clip);
+            FoldAccumHandle::new(accum_factory)
+        })
+    }
+
+    fn fold_with_combine<A, M>(
+        &self, accum_factory: A, combine: M,
+    ) -> Result<Stream<A::Target>, BuildJobError>
+    where
+        A: AccumFactory<I> + 'static,
+        A::Target: Data,
+        M: Fn(&mut A::Target, A::Target) -> Result<(), io::Error> + Send + 'static,
+    {
+        let partial = self.fold_with_accum(Range::Local, accum_factory)?;
+        if partial.gather_per_server() {
+            // the partial accumulators of each server are combined first, whose results are
+            // combined again across the servers;
+            let combine = Arc::new(Mutex::new(combine));
+            let partial = combine_partial(&partial, Range::PerServer, share(&combine))?;
+            combine_partial(&partial, Range::Global, share(&combine))
+        } else {
+            combine_partial(&partial, Range::Global, combine)
         }
     }
 }
+
+/// Share the combine function between the operators of a worker, which are fired one by one;
+fn share<O, M>(
+    combine: &Arc<Mutex<M>>,
+) -> impl Fn(&mut O, O) -> Result<(), io::Error> + Send + 'static
+where
+    O: Data,
+    M: Fn(&mut O, O) -> Result<(), io::Error> + Send + 'static,
+{
+    let combine = combine.clone();
+    move |pre, partial| match combine.lock() {
+        Ok(combine) => (*combine)(pre, partial),
+        Err(_) => Err(io::Error::other("combine function poisoned")),
+    }
+}
+
+#[inline]
+fn combine_partial<O, M>(
+    partial: &Stream<O>, range: Range, combine: M,
+) -> Result<Stream<O>, BuildJobError>
+where
+    O: Data,
+    M: Fn(&mut O, O) -> Result<(), io::Error> + Send + 'static,
+{
+    partial.unary_with_state("fold_with_combine", gather(range), |meta| {
+        meta.set_kind(OperatorKind::Clip);
+        FoldCombineHandle { combine }
+    })
+}
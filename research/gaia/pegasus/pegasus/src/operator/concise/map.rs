@@ -15,12 +15,16 @@
 
 use crate::api::function::*;
 use crate::api::meta::OperatorKind;
-use crate::api::{LazyUnary, Map, Unary};
+use crate::api::{LazyUnary, Map, Rejected, Unary};
 use crate::communication::Channel;
 use crate::errors::BuildJobError;
+use crate::operator::branch::{route, Routed};
+use crate::operator::concise::map_async::AsyncMapOperator;
+use crate::operator::concise::retry::BatchRetry;
 use crate::stream::Stream;
 use crate::Data;
 use std::error::Error;
+use std::future::Future;
 
 impl<I: Data> Map<I> for Stream<I> {
     fn map<O, C, F>(&self, channel: C, func: F) -> Result<Stream<O>, BuildJobError>
@@ -29,6 +33,24 @@ impl<I: Data> Map<I> for Stream<I> {
         C: Into<Channel<I>>,
         F: MapFunction<I, O>,
     {
+        if let Some(policy) = self.retry {
+            check_idempotent("map", func.is_idempotent())?;
+            return self.unary("map", channel, |meta| {
+                meta.set_kind(OperatorKind::Map);
+                let retry = BatchRetry::new("map", policy);
+                move |input, output| {
+                    input.for_each_batch(|dataset| {
+                        let outputs = retry.exec(dataset, |datum, outputs| {
+                            outputs.push(func.exec(datum)?);
+                            Ok(())
+                        })?;
+                        dataset.clear();
+                        output.give_entire_iter(outputs)?;
+                        Ok(())
+                    })
+                }
+            });
+        }
         self.unary("map", channel, |meta| {
             meta.set_kind(OperatorKind::Map);
             move |input, output| {
@@ -77,6 +99,30 @@ impl<I: Data> Map<I> for Stream<I> {
         C: Into<Channel<I>>,
         F: FlatMapFunction<I, O>,
     {
+        if let Some(policy) = self.retry {
+            check_idempotent("flat_map", func.is_idempotent())?;
+            // the outputs of a batch are collected before being given, instead of lazily
+            return self.unary("flat_map", channel, |meta| {
+                meta.set_kind(OperatorKind::Expand);
+                if let (None, Some(fanout)) = (meta.fanout, func.fanout()) {
+                    meta.set_fanout(fanout);
+                }
+                let retry = BatchRetry::new("flat_map", policy);
+                move |input, output| {
+                    input.for_each_batch(|dataset| {
+                        let outputs = retry.exec(dataset, |datum, outputs| {
+                            for item in func.exec(datum)? {
+                                outputs.push(item?);
+                            }
+                            Ok(())
+                        })?;
+                        dataset.clear();
+                        output.give_entire_iter(outputs)?;
+                        Ok(())
+                    })
+                }
+            });
+        }
         self.lazy_unary("flat_map", channel, move |_| func)
     }
 
@@ -89,4 +135,43 @@ impl<I: Data> Map<I> for Stream<I> {
     {
         self.flat_map(channel, flat_map!(func))
     }
+
+    fn map_with_sideoutput<O, F>(
+        &self, func: F,
+    ) -> Result<(Stream<O>, Stream<Rejected<I>>), BuildJobError>
+    where
+        O: Data,
+        F: Fn(&I) -> Result<O, String> + Send + 'static,
+    {
+        route(self, "map_with_sideoutput", move |datum: I| match func(&datum) {
+            Ok(item) => Ok(Routed::Left(item)),
+            Err(error) => Ok(Routed::Right(Rejected { datum, error })),
+        })
+    }
+
+    fn map_async_with_fn<O, C, Fut, F>(
+        &self, channel: C, concurrency: usize, func: F,
+    ) -> Result<Stream<O>, BuildJobError>
+    where
+        O: Data,
+        C: Into<Channel<I>>,
+        Fut: Future<Output = FnResult<O>> + Send + 'static,
+        F: Fn(I) -> Fut + Send + 'static,
+    {
+        self.concat("map_async", channel, |meta| {
+            meta.set_kind(OperatorKind::Map);
+            Box::new(AsyncMapOperator::new(concurrency, func))
+        })
+    }
+}
+
+fn check_idempotent(name: &str, is_idempotent: bool) -> Result<(), BuildJobError> {
+    if is_idempotent {
+        Ok(())
+    } else {
+        BuildJobError::unsupported(format!(
+            "{} can't be retried as its function isn't marked as idempotent;",
+            name
+        ))
+    }
 }
@@ -0,0 +1,188 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use crate::api::function::FnResult;
+use crate::api::notify::Notification;
+use crate::communication::input::{new_input_session, InputProxy};
+use crate::communication::output::{new_output_session, OutputProxy};
+use crate::errors::JobExecError;
+use crate::operator::{FiredState, OperatorCore};
+use crate::{Data, Tag};
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+
+/// Mark the future to be polled again once it is woken;
+struct TaskWaker {
+    woken: AtomicBool,
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.woken.store(true, Ordering::SeqCst);
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.woken.store(true, Ordering::SeqCst);
+    }
+}
+
+struct Task<Fut> {
+    future: Pin<Box<Fut>>,
+    waker: Arc<TaskWaker>,
+}
+
+impl<Fut: Future> Task<Fut> {
+    fn new(future: Fut) -> Self {
+        let waker = Arc::new(TaskWaker { woken: AtomicBool::new(true) });
+        Task { future: Box::pin(future), waker }
+    }
+
+    /// Poll the future only if it has been woken since the last poll;
+    fn poll(&mut self) -> Poll<Fut::Output> {
+        if !self.waker.woken.swap(false, Ordering::SeqCst) {
+            return Poll::Pending;
+        }
+        let waker = Waker::from(self.waker.clone());
+        let mut cx = Context::from_waker(&waker);
+        self.future.as_mut().poll(&mut cx)
+    }
+}
+
+/// The data of a scope waiting for, or being mapped by futures;
+struct ScopeTasks<I, Fut> {
+    pending: VecDeque<I>,
+    running: Vec<Task<Fut>>,
+}
+
+impl<I, Fut> Default for ScopeTasks<I, Fut> {
+    fn default() -> Self {
+        ScopeTasks { pending: VecDeque::new(), running: Vec::new() }
+    }
+}
+
+impl<I, Fut> ScopeTasks<I, Fut> {
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.pending.is_empty() && self.running.is_empty()
+    }
+}
+
+/// Map data by futures, which are driven by the event loop of the worker through the actives of
+/// the operator, with at most `concurrency` futures of each scope in flight. The scope stays
+/// active until all its futures complete, which holds back its end;
+pub(crate) struct AsyncMapOperator<I, O, F, Fut> {
+    func: F,
+    concurrency: usize,
+    scopes: HashMap<Tag, ScopeTasks<I, Fut>>,
+    _ph: std::marker::PhantomData<O>,
+}
+
+impl<I, O, F, Fut> AsyncMapOperator<I, O, F, Fut> {
+    pub fn new(concurrency: usize, func: F) -> Self {
+        AsyncMapOperator {
+            func,
+            concurrency: std::cmp::max(concurrency, 1),
+            scopes: HashMap::new(),
+            _ph: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<I, O, F, Fut> AsyncMapOperator<I, O, F, Fut>
+where
+    I: Data,
+    O: Data,
+    F: Fn(I) -> Fut + Send + 'static,
+    Fut: Future<Output = FnResult<O>> + Send + 'static,
+{
+    fn drive(
+        &mut self, tag: &Tag, outputs: &[Box<dyn OutputProxy>],
+    ) -> Result<FiredState, JobExecError> {
+        let finished = if let Some(tasks) = self.scopes.get_mut(tag) {
+            let mut output = new_output_session::<O>(&outputs[0], tag);
+            loop {
+                while tasks.running.len() < self.concurrency {
+                    if let Some(datum) = tasks.pending.pop_front() {
+                        tasks.running.push(Task::new((self.func)(datum)));
+                    } else {
+                        break;
+                    }
+                }
+                let mut completed = 0;
+                let mut i = 0;
+                while i < tasks.running.len() {
+                    if let Poll::Ready(result) = tasks.running[i].poll() {
+                        tasks.running.swap_remove(i);
+                        output.give(result?)?;
+                        completed += 1;
+                    } else {
+                        i += 1;
+                    }
+                }
+                if completed == 0 || tasks.is_empty() {
+                    break;
+                }
+            }
+            tasks.is_empty()
+        } else {
+            true
+        };
+
+        if finished {
+            self.scopes.remove(tag);
+            Ok(FiredState::Idle)
+        } else {
+            Ok(FiredState::Active)
+        }
+    }
+}
+
+impl<I, O, F, Fut> OperatorCore for AsyncMapOperator<I, O, F, Fut>
+where
+    I: Data,
+    O: Data,
+    F: Fn(I) -> Fut + Send + 'static,
+    Fut: Future<Output = FnResult<O>> + Send + 'static,
+{
+    fn on_receive(
+        &mut self, tag: &Tag, inputs: &[Box<dyn InputProxy>], outputs: &[Box<dyn OutputProxy>],
+    ) -> Result<FiredState, JobExecError> {
+        let mut input = new_input_session::<I>(&inputs[0], tag);
+        let tasks = self.scopes.entry(tag.clone()).or_default();
+        input.for_each_batch(|dataset| {
+            tasks.pending.extend(dataset.drain(..));
+            Ok(())
+        })?;
+        self.drive(tag, outputs)
+    }
+
+    fn on_active(
+        &mut self, active: &Tag, outputs: &[Box<dyn OutputProxy>],
+    ) -> Result<FiredState, JobExecError> {
+        self.drive(active, outputs)
+    }
+
+    fn on_notify(
+        &mut self, n: Notification, _: &[Box<dyn OutputProxy>],
+    ) -> Result<(), JobExecError> {
+        // the futures of a canceled scope are dropped without being completed;
+        self.scopes.remove(&n.tag);
+        Ok(())
+    }
+}
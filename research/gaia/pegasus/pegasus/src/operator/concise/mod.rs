@@ -14,19 +14,39 @@
 //! limitations under the License.
 
 use crate::api::function::{FnResult, Partition};
+use crate::api::Range;
 use crate::codec::{Decode, Encode};
+use crate::communication::{Aggregate, AggregateLocal, Channel, Pipeline};
+use crate::Data;
 use pegasus_common::collections::{Collection, Map};
 use pegasus_common::io::{ReadExt, WriteExt};
 use std::fmt::Debug;
 use std::io;
 use std::ops::{Deref, DerefMut};
 
+mod adapt;
 mod dedup;
 mod exchange;
 mod filter;
 mod fold;
 mod map;
+mod map_async;
+mod merge;
 mod reduce;
+mod retry;
+mod sample;
+mod time_limit;
+
+/// The channel gathering the data of each scope over the range, i.e. to current worker, to the
+/// first worker of current server, or to the first worker of all;
+#[inline]
+fn gather<D: Data>(range: Range) -> Channel<D> {
+    match range {
+        Range::Local => Pipeline.into(),
+        Range::PerServer => AggregateLocal.into(),
+        Range::Global => Aggregate(0).into(),
+    }
+}
 
 #[inline]
 pub fn never_clone<T>(raw: T) -> NeverClone<T> {
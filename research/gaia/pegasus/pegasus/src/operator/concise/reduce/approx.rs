@@ -0,0 +1,97 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use crate::api::accum::AccumFactory;
+use crate::api::approx::{HyperLogLog, TDigest, MAX_PRECISION, MIN_PRECISION};
+use crate::api::{ApproxDistinct, Fold, Map, Quantiles, Range};
+use crate::communication::Pipeline;
+use crate::stream::Stream;
+use crate::{BuildJobError, Data};
+use std::hash::Hash;
+
+struct DistinctSketchFactory {
+    precision: u8,
+}
+
+impl<D: Data + Hash> AccumFactory<D> for DistinctSketchFactory {
+    type Target = HyperLogLog;
+
+    fn create(&self) -> Self::Target {
+        HyperLogLog::new(self.precision)
+    }
+
+    fn is_associative(&self) -> bool {
+        true
+    }
+}
+
+struct DigestFactory;
+
+impl<D: Data + Into<f64>> AccumFactory<D> for DigestFactory {
+    type Target = TDigest;
+
+    fn create(&self) -> Self::Target {
+        TDigest::default()
+    }
+
+    fn is_associative(&self) -> bool {
+        true
+    }
+}
+
+impl<D: Data + Hash> ApproxDistinct<D> for Stream<D> {
+    fn count_approx_distinct(
+        &self, range: Range, precision: u8,
+    ) -> Result<Stream<u64>, BuildJobError> {
+        if !(MIN_PRECISION..=MAX_PRECISION).contains(&precision) {
+            let msg = format!(
+                "precision {} of count_approx_distinct, which should be in [{}, {}]",
+                precision, MIN_PRECISION, MAX_PRECISION
+            );
+            return BuildJobError::unsupported(msg);
+        }
+        let factory = DistinctSketchFactory { precision };
+        let sketches = match range {
+            Range::Local | Range::PerServer => self.fold_with_accum(range, factory)?,
+            Range::Global => self
+                .fold_with_combine(factory, |sketch: &mut HyperLogLog, other| {
+                    sketch.merge(&other)
+                })?,
+        };
+        sketches.map_with_fn(Pipeline, |sketch: HyperLogLog| Ok(sketch.count()))
+    }
+}
+
+impl<D: Data + Into<f64>> Quantiles<D> for Stream<D> {
+    fn quantiles(&self, range: Range, qs: &[f64]) -> Result<Stream<Vec<f64>>, BuildJobError> {
+        if qs.is_empty() || qs.iter().any(|q| !(0.0..=1.0).contains(q)) {
+            let msg = format!("quantiles {:?}, which should be in [0, 1]", qs);
+            return BuildJobError::unsupported(msg);
+        }
+        let qs = qs.to_vec();
+        let digests = match range {
+            Range::Local | Range::PerServer => self.fold_with_accum(range, DigestFactory)?,
+            Range::Global => {
+                self.fold_with_combine(DigestFactory, |digest: &mut TDigest, other| {
+                    digest.merge(&other);
+                    Ok(())
+                })?
+            }
+        };
+        digests.flat_map_with_fn(Pipeline, move |mut digest: TDigest| {
+            Ok(digest.quantiles(&qs).into_iter().map(Ok))
+        })
+    }
+}
@@ -14,11 +14,13 @@
 //! limitations under the License.
 
 use crate::api::concise::reduce::barrier::Barrier;
+use crate::api::meta::OperatorKind;
 use crate::api::notify::Notification;
-use crate::api::state::StateMap;
-use crate::api::{Range, Unary, UnaryNotify};
-use crate::communication::{Aggregate, Input, Output, Pipeline};
+use crate::api::state::{OperatorState, StateMap};
+use crate::api::{Range, Unary, UnaryNotify, UnaryState};
+use crate::communication::{Input, Output, Pipeline};
 use crate::errors::JobExecError;
+use crate::operator::concise::gather;
 use crate::stream::Stream;
 use crate::{BuildJobError, Data};
 use pegasus_common::collections::{Collection, CollectionFactory, DefaultCollectionFactory};
@@ -67,25 +69,40 @@ where
     }
 }
 
+struct ScopeBarrierHandle<F> {
+    func: F,
+}
+
+impl<D: Data, F: Fn(&mut Vec<D>) + Send + 'static> UnaryState<D, D, Vec<D>>
+    for ScopeBarrierHandle<F>
+{
+    type NotifyResult = Vec<D>;
+
+    fn on_receive(
+        &self, input: &mut Input<D>, _: &mut Output<D>, state: &mut OperatorState<Vec<D>>,
+    ) -> Result<(), JobExecError> {
+        input.for_each_batch(|data| {
+            state.extend(data.drain(..));
+            Ok(())
+        })
+    }
+
+    fn on_notify(&self, mut state: Vec<D>) -> Self::NotifyResult {
+        (self.func)(&mut state);
+        state
+    }
+}
+
 impl<D: Data> Barrier<D> for Stream<D> {
     fn barrier<C: Collection<D> + Data + Default>(
         &self, range: Range,
     ) -> Result<Stream<C>, BuildJobError> {
-        match range {
-            Range::Local => self.unary_with_notify("barrier", Pipeline, |meta| {
-                let state = StateMap::new(meta);
-                let factory = DefaultCollectionFactory::new();
-                BarrierHandle::<D, DefaultCollectionFactory<D, C>>::new(factory, state)
-            }),
-            Range::Global => {
-                // TODO: change aggregate to worker 0 into aggregate by tag;
-                self.unary_with_notify("barrier", Aggregate(0), |meta| {
-                    let state = StateMap::new(meta);
-                    let factory = DefaultCollectionFactory::new();
-                    BarrierHandle::<D, DefaultCollectionFactory<D, C>>::new(factory, state)
-                })
-            }
-        }
+        // TODO: change aggregate to worker 0 into aggregate by tag;
+        self.unary_with_notify("barrier", gather(range), |meta| {
+            let state = StateMap::new(meta);
+            let factory = DefaultCollectionFactory::new();
+            BarrierHandle::<D, DefaultCollectionFactory<D, C>>::new(factory, state)
+        })
     }
 
     fn barrier_with<C>(&self, range: Range, factory: C) -> Result<Stream<C::Target>, BuildJobError>
@@ -93,18 +110,24 @@ impl<D: Data> Barrier<D> for Stream<D> {
         C: CollectionFactory<D> + 'static,
         C::Target: Data,
     {
-        match range {
-            Range::Local => self.unary_with_notify("barrier", Pipeline, |meta| {
-                let state = StateMap::new(meta);
-                BarrierHandle::<D, C>::new(factory, state)
-            }),
-            Range::Global => {
-                // TODO: change aggregate to worker 0 into aggregate by tag;
-                self.unary_with_notify("barrier", Aggregate(0), |meta| {
-                    let state = StateMap::new(meta);
-                    BarrierHandle::<D, C>::new(factory, state)
-                })
-            }
-        }
+        // TODO: change aggregate to worker 0 into aggregate by tag;
+        self.unary_with_notify("barrier", gather(range), |meta| {
+            let state = StateMap::new(meta);
+            BarrierHandle::<D, C>::new(factory, state)
+        })
+    }
+
+    fn scope_barrier(&self) -> Result<Stream<D>, BuildJobError> {
+        self.scope_barrier_with(|_| ())
+    }
+
+    fn scope_barrier_with<F>(&self, func: F) -> Result<Stream<D>, BuildJobError>
+    where
+        F: Fn(&mut Vec<D>) + Send + 'static,
+    {
+        self.unary_with_state("scope_barrier", Pipeline, |meta| {
+            meta.set_kind(OperatorKind::Clip);
+            ScopeBarrierHandle { func }
+        })
     }
 }
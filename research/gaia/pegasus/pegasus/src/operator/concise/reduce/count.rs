@@ -14,19 +14,158 @@
 //! limitations under the License.
 
 use crate::api::concise::reduce::Range;
-use crate::api::{Count, Fold};
-use crate::communication::{Aggregate, Pipeline};
-use crate::errors::BuildJobError;
+use crate::api::meta::OperatorKind;
+use crate::api::state::OperatorState;
+use crate::api::{Count, Unary, UnaryState};
+use crate::communication::{Input, Output, Pipeline};
+use crate::errors::{BuildJobError, JobExecError};
+use crate::operator::concise::gather;
 use crate::stream::Stream;
 use crate::Data;
 
+/// Count the data of each scope;
+struct CountHandle;
+
+impl<D: Data> UnaryState<D, u64, u64> for CountHandle {
+    type NotifyResult = Option<u64>;
+
+    fn on_receive(
+        &self, input: &mut Input<D>, _: &mut Output<u64>, state: &mut OperatorState<u64>,
+    ) -> Result<(), JobExecError> {
+        input.for_each_batch(|dataset| {
+            **state += dataset.len() as u64;
+            Ok(())
+        })
+    }
+
+    fn on_notify(&self, state: u64) -> Self::NotifyResult {
+        Some(state)
+    }
+}
+
+/// Sum up the partial counts of each scope;
+struct SumCountHandle;
+
+impl UnaryState<u64, u64, u64> for SumCountHandle {
+    type NotifyResult = Option<u64>;
+
+    fn on_receive(
+        &self, input: &mut Input<u64>, _: &mut Output<u64>, state: &mut OperatorState<u64>,
+    ) -> Result<(), JobExecError> {
+        input.for_each_batch(|dataset| {
+            for count in dataset.drain(..) {
+                **state += count;
+            }
+            Ok(())
+        })
+    }
+
+    fn on_notify(&self, state: u64) -> Self::NotifyResult {
+        Some(state)
+    }
+}
+
 impl<D: Data> Count<D> for Stream<D> {
     fn count(&self, range: Range) -> Result<Stream<u64>, BuildJobError> {
+        let local = self.unary_with_state("count", Pipeline, |meta| {
+            meta.set_kind(OperatorKind::Clip);
+            CountHandle
+        })?;
         match range {
-            Range::Local => self.fold(0u64, Pipeline, |s, _| *s += 1),
-            Range::Global => {
-                self.fold(0u64, Pipeline, |s, _| *s += 1)?.fold(0u64, Aggregate(0), |s, u| *s += u)
+            Range::Local => Ok(local),
+            Range::PerServer => sum_counts(&local, Range::PerServer),
+            Range::Global if local.gather_per_server() => {
+                sum_counts(&sum_counts(&local, Range::PerServer)?, Range::Global)
             }
+            Range::Global => sum_counts(&local, Range::Global),
         }
     }
 }
+
+#[inline]
+fn sum_counts(partial: &Stream<u64>, range: Range) -> Result<Stream<u64>, BuildJobError> {
+    partial.unary_with_state("count", gather(range), |meta| {
+        meta.set_kind(OperatorKind::Clip);
+        SumCountHandle
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::operator::harness::{HarnessBuilder, MockInput, MockOutput, OperatorHarness};
+    use crate::operator::unary::UnaryStateOperator;
+    use crate::Tag;
+
+    fn count_harness() -> (OperatorHarness, MockInput<u32>, MockOutput<u64>) {
+        let mut builder = HarnessBuilder::new("count", 1);
+        let input = builder.input::<u32>();
+        let output = builder.output::<u64>();
+        let harness = builder.build(|meta| {
+            meta.enable_notify();
+            Box::new(UnaryStateOperator::<u32, u64, u64, _>::new(meta, CountHandle))
+        });
+        (harness, input, output)
+    }
+
+    #[test]
+    fn count_end_before_data_test() {
+        let (mut harness, mut input, mut output) = count_harness();
+        // the end of the scope arrives while its batch is still in flight;
+        input.announce(Tag::new(0), 3);
+        input.end(Tag::new(0));
+        harness.step().unwrap();
+        assert!(output.take().is_empty());
+        assert!(output.take_ends().is_empty());
+
+        input.deliver(Tag::new(0), vec![1, 2, 3]);
+        harness.step().unwrap();
+        assert_eq!(output.take(), vec![(Tag::new(0), vec![3])]);
+        assert_eq!(output.take_ends(), vec![Tag::new(0)]);
+    }
+
+    #[test]
+    fn count_empty_scope_test() {
+        let (mut harness, input, mut output) = count_harness();
+        // a scope of no data has no state, whose end is passed on without a count;
+        input.end(Tag::new(0));
+        harness.step().unwrap();
+        assert!(output.take().is_empty());
+        assert_eq!(output.take_ends(), vec![Tag::new(0)]);
+    }
+
+    #[test]
+    fn count_interleaved_scopes_test() {
+        let (mut harness, mut input, mut output) = count_harness();
+        input.give(Tag::new(0), vec![1, 2]);
+        input.give(Tag::new(1), vec![1]);
+        input.give(Tag::new(0), vec![3]);
+        input.end(Tag::new(1));
+        harness.step().unwrap();
+        assert_eq!(output.take(), vec![(Tag::new(1), vec![1])]);
+        assert_eq!(output.take_ends(), vec![Tag::new(1)]);
+
+        input.give(Tag::new(0), vec![4]);
+        input.end(Tag::new(0));
+        harness.step().unwrap();
+        assert_eq!(output.take(), vec![(Tag::new(0), vec![4])]);
+        assert_eq!(output.take_ends(), vec![Tag::new(0)]);
+    }
+
+    #[test]
+    fn count_backpressure_test() {
+        let (mut harness, mut input, mut output) = count_harness();
+        harness.set_capacity(&output, 0);
+        input.give(Tag::new(0), vec![1, 2]);
+        input.end(Tag::new(0));
+        // the data are left outstanding while the output is full, which hold the end back;
+        harness.step().unwrap();
+        assert!(output.take().is_empty());
+        assert!(output.take_ends().is_empty());
+
+        harness.set_capacity(&output, 1);
+        harness.step().unwrap();
+        assert_eq!(output.take(), vec![(Tag::new(0), vec![2])]);
+        assert_eq!(output.take_ends(), vec![Tag::new(0)]);
+    }
+}
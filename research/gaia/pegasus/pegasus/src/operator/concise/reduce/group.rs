@@ -16,10 +16,8 @@
 use crate::api::accum::{AccumFactory, Accumulator, ToVecAccum};
 use crate::api::function::*;
 use crate::api::group::KeyBy;
-use crate::api::meta::OperatorMeta;
-use crate::api::notify::Notification;
-use crate::api::state::StateMap;
-use crate::api::{Group, Map, Range, Unary, UnaryNotify};
+use crate::api::state::{OperatorState, ScopedState};
+use crate::api::{Group, Map, Range, Unary, UnaryState};
 use crate::communication::{Input, Output, Pipeline};
 use crate::errors::JobExecError;
 use crate::stream::Stream;
@@ -28,6 +26,7 @@ use pegasus_common::collections::{Map as MapContainer, MapFactory};
 use pegasus_common::downcast::AsAny;
 use std::collections::HashMap;
 use std::hash::Hash;
+use std::io;
 
 impl<D: Data + Keyed> Group<D> for Stream<D> {
     fn group_by(
@@ -50,20 +49,12 @@ impl<D: Data + Keyed> Group<D> for Stream<D> {
         F::Target: Data,
     {
         match range {
-            Range::Local => self.unary_with_notify("group_by", Pipeline, |meta| {
-                GroupByHandler::new(meta, map_factory)
-            }),
-            Range::Global => {
-                let route = box_route!(move |t: &D| {
-                    if let Ok(k) = t.get_key() {
-                        k.get_partition().unwrap_or(0)
-                    } else {
-                        0
-                    }
-                });
-                self.unary_with_notify("group_by", route, |meta| {
-                    GroupByHandler::new(meta, map_factory)
-                })
+            Range::Local => {
+                self.unary_with_state("group_by", Pipeline, |_| GroupByHandler::new(map_factory))
+            }
+            Range::PerServer | Range::Global => {
+                let route = key_route(self, range);
+                self.unary_with_state("group_by", route, |_| GroupByHandler::new(map_factory))
             }
         }
     }
@@ -77,23 +68,53 @@ impl<D: Data + Keyed> Group<D> for Stream<D> {
         D::Key: Data + Hash + Eq + Partition,
     {
         match range {
-            Range::Local => self.unary_with_notify("group_with_accum", Pipeline, |meta| {
-                GroupAccumHandler::new(meta, accum_factory)
+            Range::Local => self.unary_with_state("group_with_accum", Pipeline, |_| {
+                GroupAccumHandler::new(accum_factory)
             }),
-            Range::Global => {
-                let route = box_route!(move |t: &D| {
-                    if let Ok(k) = t.get_key() {
-                        k.get_partition().unwrap_or(0)
-                    } else {
-                        0
-                    }
-                });
-                self.unary_with_notify("group_with_accum", route, |meta| {
-                    GroupAccumHandler::new(meta, accum_factory)
+            Range::PerServer | Range::Global => {
+                let route = key_route(self, range);
+                self.unary_with_state("group_with_accum", route, |_| {
+                    GroupAccumHandler::new(accum_factory)
                 })
             }
         }
     }
+
+    fn group_with_combine<A, M>(
+        &self, accum_factory: A, combine: M,
+    ) -> Result<Stream<HashMap<D::Key, A::Target>>, BuildJobError>
+    where
+        A: AccumFactory<D::Value> + 'static,
+        A::Target: Data + 'static,
+        D::Key: Data + Hash + Eq + Partition,
+        M: Fn(&mut A::Target, A::Target) -> Result<(), io::Error> + Send + 'static,
+    {
+        let partial = self
+            .group_with_accum(Range::Local, accum_factory)?
+            .flat_map_with_fn(Pipeline, |map| Ok(map.into_iter().map(Ok)))?;
+        let route = box_route!(move |(k, _): &(D::Key, A::Target)| k.get_partition().unwrap_or(0));
+        partial.unary_with_state("group_with_combine", route, |_| CombineHandler { combine })
+    }
+}
+
+/// Route the data by the partitions of their keys, over all workers if the range is global, or
+/// over the workers of current server;
+fn key_route<D>(stream: &Stream<D>, range: Range) -> Box<dyn RouteFunction<D>>
+where
+    D: Data + Keyed,
+    D::Key: Partition,
+{
+    let (first, peers) = if range == Range::PerServer {
+        let local_peers = stream.local_peers();
+        (stream.server_id() * local_peers, local_peers)
+    } else {
+        (0, stream.peers())
+    };
+    let (first, peers) = (first as u64, peers.max(1) as u64);
+    box_route!(move |t: &D| {
+        let partition = if let Ok(k) = t.get_key() { k.get_partition().unwrap_or(0) } else { 0 };
+        first + partition % peers
+    })
 }
 
 impl<D: Data> KeyBy<D> for Stream<D> {
@@ -109,112 +130,118 @@ impl<D: Data> KeyBy<D> for Stream<D> {
     }
 }
 
-struct GroupByHandler<I: Keyed, F, M> {
+struct GroupByHandler<I, F> {
     map_factory: F,
-    multi_states: StateMap<M>,
     _ph: std::marker::PhantomData<I>,
 }
 
-impl<I: Keyed, F, M> GroupByHandler<I, F, M> {
-    pub fn new(meta: &OperatorMeta, map_factory: F) -> Self {
-        GroupByHandler {
-            map_factory,
-            multi_states: StateMap::new(meta),
-            _ph: std::marker::PhantomData,
-        }
+impl<I, F> GroupByHandler<I, F> {
+    pub fn new(map_factory: F) -> Self {
+        GroupByHandler { map_factory, _ph: std::marker::PhantomData }
     }
 }
 
-impl<I: Data + Keyed, F> UnaryNotify<I, F::Target> for GroupByHandler<I, F, F::Target>
+// the map is created by the factory on the first data of a scope;
+impl<I: Data + Keyed, F> UnaryState<I, F::Target, Option<F::Target>> for GroupByHandler<I, F>
 where
     I::Key: Eq + Send,
     I::Value: Send,
     F: MapFactory<I::Key, I::Value> + 'static,
     F::Target: Data,
 {
-    type NotifyResult = Vec<F::Target>;
+    type NotifyResult = Option<F::Target>;
 
     fn on_receive(
-        &mut self, input: &mut Input<I>, _: &mut Output<F::Target>,
+        &self, input: &mut Input<I>, _: &mut Output<F::Target>,
+        state: &mut OperatorState<Option<F::Target>>,
     ) -> Result<(), JobExecError> {
-        input.subscribe_notify();
         let map_factory = &self.map_factory;
-        let mut multi_states = std::mem::replace(&mut self.multi_states, StateMap::default());
-        let state = multi_states.entry(&input.tag).or_insert_with(|| map_factory.create());
-        let result = input.for_each_batch(|data_set| {
+        let map = state.get_or_insert_with(|| map_factory.create());
+        input.for_each_batch(|data_set| {
             for mut data in data_set.drain(..) {
                 let key = data.take_key()?;
                 let value = data.take_value()?;
-                state.insert(key, value);
+                map.insert(key, value);
             }
             Ok(())
-        });
-        self.multi_states = multi_states;
-        result
+        })
     }
 
-    fn on_notify(&mut self, n: &Notification) -> Self::NotifyResult {
-        self.multi_states.notify(n);
-        let notified = self.multi_states.extract_notified();
-        assert_eq!(notified.len(), 1);
-        let result = notified.remove(0).1;
-        vec![result]
+    fn on_notify(&self, state: Option<F::Target>) -> Self::NotifyResult {
+        state
     }
 }
 
-struct GroupAccumHandler<I: Keyed, A: AccumFactory<I::Value>, M> {
+struct GroupAccumHandler<I, A> {
     accum_factory: A,
-    multi_states: StateMap<M>,
     _ph: std::marker::PhantomData<I>,
 }
 
-impl<I: Keyed, A: AccumFactory<I::Value>, M> GroupAccumHandler<I, A, M> {
-    pub fn new(meta: &OperatorMeta, accum_factory: A) -> Self {
-        GroupAccumHandler {
-            accum_factory,
-            multi_states: StateMap::new(meta),
-            _ph: std::marker::PhantomData,
-        }
+impl<I, A> GroupAccumHandler<I, A> {
+    pub fn new(accum_factory: A) -> Self {
+        GroupAccumHandler { accum_factory, _ph: std::marker::PhantomData }
     }
 }
 
 impl<I: Data + Keyed, A: AccumFactory<I::Value> + 'static>
-    UnaryNotify<I, HashMap<I::Key, A::Target>>
-    for GroupAccumHandler<I, A, HashMap<I::Key, A::Target>>
+    UnaryState<I, HashMap<I::Key, A::Target>, ScopedState<I::Key, A::Target>>
+    for GroupAccumHandler<I, A>
 where
     I::Key: Hash + Eq + Data,
     A::Target: Data,
 {
-    type NotifyResult = Vec<HashMap<I::Key, A::Target>>;
+    type NotifyResult = Option<HashMap<I::Key, A::Target>>;
 
     fn on_receive(
-        &mut self, input: &mut Input<I>, _: &mut Output<HashMap<I::Key, A::Target>>,
+        &self, input: &mut Input<I>, _: &mut Output<HashMap<I::Key, A::Target>>,
+        state: &mut OperatorState<ScopedState<I::Key, A::Target>>,
     ) -> Result<(), JobExecError> {
-        input.subscribe_notify();
-        let mut multi_states = std::mem::replace(&mut self.multi_states, StateMap::default());
-        let state = multi_states.entry(&input.tag).or_insert_with(HashMap::new);
-        let result = input.for_each_batch(|data_set| {
+        let accum_factory = &self.accum_factory;
+        input.for_each_batch(|data_set| {
             for mut data in data_set.drain(..) {
                 let key = data.take_key()?;
-                if let Some(accum) = state.get_mut(&key) {
-                    accum.accum(data.take_value()?)?;
+                let accum = state.get_or_insert_with(key, || accum_factory.create());
+                accum.accum(data.take_value()?)?;
+            }
+            Ok(())
+        })
+    }
+
+    fn on_notify(&self, state: ScopedState<I::Key, A::Target>) -> Self::NotifyResult {
+        Some(state.into_map())
+    }
+}
+
+/// Combine the partial accumulators of each key exchanged from all workers;
+struct CombineHandler<M> {
+    combine: M,
+}
+
+impl<K, V, M> UnaryState<(K, V), HashMap<K, V>, ScopedState<K, V>> for CombineHandler<M>
+where
+    K: Data + Hash + Eq,
+    V: Data,
+    M: Fn(&mut V, V) -> Result<(), io::Error> + Send + 'static,
+{
+    type NotifyResult = Option<HashMap<K, V>>;
+
+    fn on_receive(
+        &self, input: &mut Input<(K, V)>, _: &mut Output<HashMap<K, V>>,
+        state: &mut OperatorState<ScopedState<K, V>>,
+    ) -> Result<(), JobExecError> {
+        input.for_each_batch(|data_set| {
+            for (key, partial) in data_set.drain(..) {
+                if let Some(pre) = state.get_mut(&key) {
+                    (self.combine)(pre, partial)?;
                 } else {
-                    let mut accum = self.accum_factory.create();
-                    accum.accum(data.take_value()?)?;
-                    state.insert(key, accum);
+                    state.insert(key, partial);
                 }
             }
             Ok(())
-        });
-        self.multi_states = multi_states;
-        result
+        })
     }
 
-    fn on_notify(&mut self, n: &Notification) -> Self::NotifyResult {
-        self.multi_states.notify(n);
-        let notified = self.multi_states.extract_notified();
-        assert_eq!(notified.len(), 1);
-        let result = notified.remove(0).1;
-        vec![result]
+    fn on_notify(&self, state: ScopedState<K, V>) -> Self::NotifyResult {
+        Some(state.into_map())
     }
 }
@@ -17,8 +17,9 @@ use crate::api::concise::reduce::Range;
 use crate::api::meta::OperatorKind;
 use crate::api::state::OperatorState;
 use crate::api::{Limit, Unary, UnaryState};
-use crate::communication::{Aggregate, Input, Output, Pipeline};
+use crate::communication::{AggregateLocal, Input, Output, Pipeline};
 use crate::errors::JobExecError;
+use crate::operator::concise::gather;
 use crate::stream::Stream;
 use crate::{BuildJobError, Data};
 
@@ -64,19 +65,26 @@ impl<D: Data> Limit<D> for Stream<D> {
     fn limit(&self, range: Range, size: u32) -> Result<Stream<D>, BuildJobError> {
         let limit = LimitHandle::new(size as u64);
         match range {
-            Range::Global => self
-                .unary_with_state("limit_local", Pipeline, |meta| {
-                    meta.set_kind(OperatorKind::Clip);
-                    limit
-                })?
-                .unary_with_state("limit", Aggregate(0), |meta| {
-                    meta.set_kind(OperatorKind::Clip);
-                    limit
-                }),
             Range::Local => self.unary_with_state("limit", Pipeline, |meta| {
                 meta.set_kind(OperatorKind::Clip);
                 limit
             }),
+            Range::PerServer | Range::Global => {
+                let mut stream = self.unary_with_state("limit_local", Pipeline, |meta| {
+                    meta.set_kind(OperatorKind::Clip);
+                    limit
+                })?;
+                if range == Range::Global && stream.gather_per_server() {
+                    stream = stream.unary_with_state("limit_server", AggregateLocal, |meta| {
+                        meta.set_kind(OperatorKind::Clip);
+                        limit
+                    })?;
+                }
+                stream.unary_with_state("limit", gather(range), |meta| {
+                    meta.set_kind(OperatorKind::Clip);
+                    limit
+                })
+            }
         }
     }
 }
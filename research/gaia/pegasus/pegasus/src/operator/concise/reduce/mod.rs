@@ -13,8 +13,10 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
+mod approx;
 mod barrier;
 mod count;
 mod group;
 mod limit;
 mod order;
+mod sort;
@@ -19,6 +19,7 @@ use crate::api::function::*;
 use crate::api::{Map, OrderBy, Range};
 use crate::codec::{shade_codec, ShadeCodec};
 use crate::communication::Pipeline;
+use crate::operator::concise::reduce::sort::SortFactory;
 use crate::operator::concise::{never_clone, NeverClone};
 use crate::stream::Stream;
 use crate::{BuildJobError, Data};
@@ -35,14 +36,7 @@ use std::sync::Arc;
 
 impl<D: Data + Ord> Order<D> for Stream<D> {
     fn sort(&self, range: Range, order: OrderDirect) -> Result<Stream<D>, BuildJobError> {
-        let barrier = self.barrier::<Vec<D>>(range)?;
-        barrier.flat_map_with_fn(Pipeline, move |mut input| {
-            match order {
-                OrderDirect::Asc => input.sort(),
-                OrderDirect::Desc => input.sort_by(|a, b| b.cmp(a)),
-            }
-            Ok(input.into_iter().map(|item| Ok(item)))
-        })
+        self.sort_by(range, DirectCompare(order))
     }
 
     fn top(
@@ -54,10 +48,14 @@ impl<D: Data + Ord> Order<D> for Stream<D> {
         let limit = limit as usize;
 
         let stream = get_top(&self, Range::Local, order, limit)?;
-        if Range::Global == range {
-            get_top(&stream, Range::Global, order, limit)
-        } else {
-            Ok(stream)
+        match range {
+            Range::Local => Ok(stream),
+            Range::PerServer => get_top(&stream, Range::PerServer, order, limit),
+            Range::Global if stream.gather_per_server() => {
+                let stream = get_top(&stream, Range::PerServer, order, limit)?;
+                get_top(&stream, Range::Global, order, limit)
+            }
+            Range::Global => get_top(&stream, Range::Global, order, limit),
         }
     }
 }
@@ -67,10 +65,12 @@ impl<D: Data> OrderBy<D> for Stream<D> {
     where
         F: CompareFunction<D> + 'static,
     {
-        let barrier = self.barrier::<Vec<D>>(range)?;
-        barrier.flat_map_with_fn(Pipeline, move |mut input| {
-            input.sort_by(|a, b| cmp.compare(a, b));
-            Ok(input.into_iter().map(|item| Ok(item)))
+        // the records beyond the spill limit of the job are spilled in sorted runs;
+        let factory = SortFactory::new(OrdParam::new(0, Box::new(cmp)));
+        let barrier = self.barrier_with(range, factory)?;
+        barrier.flat_map_with_fn(Pipeline, move |input| {
+            let sorted = input.take().take().into_sorted().map_err(|e| Box::new(e) as DynError)?;
+            Ok(sorted.map(|item| item.map_err(|e| Box::new(e) as DynError)))
         })
     }
 
@@ -83,22 +83,43 @@ impl<D: Data> OrderBy<D> for Stream<D> {
         }
 
         let param = OrdParam::<D, F>::new(limit as usize, Box::new(cmp));
-        let factory = CustomOrdQueueFactory::new(param.clone());
-        let barrier = self.barrier_with(Range::Local, factory)?;
-        let stream = barrier.flat_map_with_fn(Pipeline, move |input| {
-            let input = input.take().take();
-            Ok(input.into_iter().map(|item| Ok(item)))
-        });
-
+        let stream = top_in(self, Range::Local, &param)?;
         match range {
-            Range::Local => stream,
-            Range::Global => {
-                let factory = CustomOrdQueueFactory::new(param.clone());
-                stream?.barrier_with(range, factory)?.flat_map_with_fn(Pipeline, move |input| {
-                    let input = input.take().take();
-                    Ok(input.into_iter().map(|item| Ok(item)))
-                })
+            Range::Local => Ok(stream),
+            Range::PerServer => top_in(&stream, Range::PerServer, &param),
+            Range::Global if stream.gather_per_server() => {
+                let stream = top_in(&stream, Range::PerServer, &param)?;
+                top_in(&stream, Range::Global, &param)
             }
+            Range::Global => top_in(&stream, Range::Global, &param),
+        }
+    }
+}
+
+/// Keep the top records of each scope over the range by the order;
+#[inline]
+fn top_in<D, F>(
+    stream: &Stream<D>, range: Range, param: &OrdParam<D, F>,
+) -> Result<Stream<D>, BuildJobError>
+where
+    D: Data,
+    F: CompareFunction<D> + 'static,
+{
+    let factory = CustomOrdQueueFactory::new(param.clone());
+    stream.barrier_with(range, factory)?.flat_map_with_fn(Pipeline, move |input| {
+        let input = input.take().take();
+        Ok(input.into_iter().map(|item| Ok(item)))
+    })
+}
+
+/// Compare the records by their natural order in the direction;
+struct DirectCompare(OrderDirect);
+
+impl<D: Ord + Send + 'static> CompareFunction<D> for DirectCompare {
+    fn compare(&self, left: &D, right: &D) -> Ordering {
+        match self.0 {
+            OrderDirect::Asc => left.cmp(right),
+            OrderDirect::Desc => right.cmp(left),
         }
     }
 }
@@ -291,9 +312,9 @@ impl<D: Ord + Send> CollectionFactory<D> for LargeHeapFactory<D> {
     }
 }
 
-struct Item<D, C: CompareFunction<D>> {
-    inner: D,
-    cmp: NonNull<C>,
+pub(super) struct Item<D, C: CompareFunction<D>> {
+    pub inner: D,
+    pub cmp: NonNull<C>,
 }
 
 impl<D: Debug, C: CompareFunction<D>> Debug for Item<D, C> {
@@ -341,9 +362,9 @@ impl<D, C: CompareFunction<D>> PartialOrd<D> for Item<D, C> {
 
 unsafe impl<D: Send, C: CompareFunction<D>> Send for Item<D, C> {}
 
-struct OrdParam<D, C: CompareFunction<D>> {
+pub(super) struct OrdParam<D, C: CompareFunction<D>> {
     limit: usize,
-    cmp: NonNull<C>,
+    pub cmp: NonNull<C>,
     ref_count: Arc<AtomicUsize>,
     _ph: std::marker::PhantomData<D>,
 }
@@ -358,6 +379,11 @@ impl<D, C: CompareFunction<D>> OrdParam<D, C> {
             _ph: std::marker::PhantomData,
         }
     }
+
+    #[inline]
+    pub fn compare(&self, left: &D, right: &D) -> Ordering {
+        unsafe { self.cmp.as_ref().compare(left, right) }
+    }
 }
 
 impl<D, C: CompareFunction<D>> Clone for OrdParam<D, C> {
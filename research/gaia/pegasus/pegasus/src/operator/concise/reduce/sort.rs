@@ -0,0 +1,203 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use super::order::{Item, OrdParam};
+use crate::api::function::CompareFunction;
+use crate::codec::{shade_codec, ShadeCodec};
+use crate::operator::concise::{never_clone, NeverClone};
+use crate::spill::SpillFile;
+use crate::{Data, JobConf};
+use pegasus_common::collections::{Collection, CollectionFactory};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fmt::Debug;
+use std::io;
+use std::sync::Arc;
+
+/// Sort the records of a scope in memory, until there are more than the spill limit of the job,
+/// when they are sorted and spilled into a temp file as a run. All runs are merged along with
+/// the records left in memory on emission, which reads each run by streaming.
+pub(super) struct ExternalSorter<D: Data, C: CompareFunction<D>> {
+    buffer: Vec<D>,
+    runs: Vec<SpillFile<D>>,
+    conf: Arc<JobConf>,
+    param: OrdParam<D, C>,
+}
+
+impl<D: Data, C: CompareFunction<D>> ExternalSorter<D, C> {
+    fn spill(&mut self) -> io::Result<()> {
+        self.sort_buffer();
+        let run = SpillFile::write(&self.conf, self.buffer.drain(..))?;
+        self.runs.push(run);
+        Ok(())
+    }
+
+    fn sort_buffer(&mut self) {
+        let param = &self.param;
+        self.buffer.sort_by(|a, b| param.compare(a, b));
+    }
+
+    /// Merge the spilled runs and the records in memory into a sorted iterator, where the equal
+    /// records are in the order they are added;
+    pub fn into_sorted(mut self) -> io::Result<SortedRuns<D, C>> {
+        self.sort_buffer();
+        let buffer = std::mem::take(&mut self.buffer);
+        let mut sources: Vec<Box<dyn Iterator<Item = io::Result<D>> + Send>> = vec![];
+        for run in self.runs.drain(..) {
+            sources.push(Box::new(run.into_reader()?));
+        }
+        sources.push(Box::new(buffer.into_iter().map(Ok::<D, io::Error>)));
+        let mut merged =
+            SortedRuns { heads: BinaryHeap::new(), sources, param: self.param.clone() };
+        for index in 0..merged.sources.len() {
+            merged.fetch(index)?;
+        }
+        Ok(merged)
+    }
+}
+
+impl<D: Data, C: CompareFunction<D>> Collection<D> for ExternalSorter<D, C> {
+    fn add(&mut self, item: D) -> Result<(), io::Error> {
+        self.buffer.push(item);
+        let limit = self.conf.sort_spill_limit;
+        if limit > 0 && self.buffer.len() as u64 >= limit {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self) {
+        self.buffer.clear();
+        self.runs.clear();
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn len(&self) -> usize {
+        self.buffer.len() + self.runs.iter().map(|run| run.len()).sum::<usize>()
+    }
+}
+
+impl<D: Data, C: CompareFunction<D>> Debug for ExternalSorter<D, C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "sort {} records in memory, {} runs spilled", self.buffer.len(), self.runs.len())
+    }
+}
+
+/// The k-way merge of the sorted runs, which removes the spilled files once dropped;
+pub(super) struct SortedRuns<D, C: CompareFunction<D>> {
+    // the head of each run, ordered by the run index for the equal ones;
+    heads: BinaryHeap<Reverse<(Item<D, C>, usize)>>,
+    sources: Vec<Box<dyn Iterator<Item = io::Result<D>> + Send>>,
+    // hold the compare function the heads refer to;
+    param: OrdParam<D, C>,
+}
+
+impl<D: Send, C: CompareFunction<D>> SortedRuns<D, C> {
+    fn fetch(&mut self, index: usize) -> io::Result<()> {
+        if let Some(next) = self.sources[index].next() {
+            let item = Item { inner: next?, cmp: self.param.cmp };
+            self.heads.push(Reverse((item, index)));
+        }
+        Ok(())
+    }
+}
+
+impl<D: Send, C: CompareFunction<D>> Iterator for SortedRuns<D, C> {
+    type Item = io::Result<D>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Reverse((head, index)) = self.heads.pop()?;
+        if let Err(e) = self.fetch(index) {
+            return Some(Err(e));
+        }
+        Some(Ok(head.inner))
+    }
+}
+
+pub(super) struct SortFactory<D, C: CompareFunction<D>> {
+    conf: Arc<JobConf>,
+    param: OrdParam<D, C>,
+}
+
+impl<D, C: CompareFunction<D>> SortFactory<D, C> {
+    /// Create the sorters following the spill limit of the job being built
+    pub fn new(param: OrdParam<D, C>) -> Self {
+        let conf = crate::get_current_job_conf().unwrap_or_default();
+        SortFactory { conf, param }
+    }
+}
+
+impl<D: Data, C: CompareFunction<D>> CollectionFactory<D> for SortFactory<D, C> {
+    type Target = NeverClone<ShadeCodec<ExternalSorter<D, C>>>;
+
+    fn create(&self) -> Self::Target {
+        let sorter = ExternalSorter {
+            buffer: vec![],
+            runs: vec![],
+            conf: self.conf.clone(),
+            param: self.param.clone(),
+        };
+        never_clone(shade_codec(sorter))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::api::function::CompareClosure;
+
+    fn sort(limit: u64, records: Vec<u32>) -> (Vec<u32>, usize) {
+        let mut conf = JobConf::new(1, "external_sort_test", 1);
+        conf.sort_spill_limit = limit;
+        conf.spill_dir =
+            Some(std::env::temp_dir().join(format!("external_sort_test_{}", std::process::id())));
+        let cmp = CompareClosure::new(|a: &u32, b: &u32| a.cmp(b));
+        let factory = SortFactory { conf: Arc::new(conf), param: OrdParam::new(0, Box::new(cmp)) };
+        let mut sorter = factory.create().take().take();
+        for record in records {
+            sorter.add(record).unwrap();
+        }
+        let runs = sorter.runs.len();
+        let sorted = sorter.into_sorted().unwrap().collect::<io::Result<Vec<_>>>().unwrap();
+        // the runs are removed once merged;
+        let dir = crate::spill::job_spill_dir(&factory.conf);
+        assert!(!dir.exists() || std::fs::read_dir(&dir).unwrap().next().is_none());
+        crate::spill::remove_job_spill_dir(&factory.conf);
+        if let Some(root) = factory.conf.spill_dir.as_ref() {
+            std::fs::remove_dir(root).ok();
+        }
+        (sorted, runs)
+    }
+
+    #[test]
+    fn in_memory_sort_test() {
+        let (sorted, runs) = sort(0, vec![5, 3, 9, 1, 3]);
+        assert_eq!(runs, 0);
+        assert_eq!(sorted, vec![1, 3, 3, 5, 9]);
+    }
+
+    #[test]
+    fn spilled_sort_test() {
+        let records = (0..100u32).map(|i| (i * 37) % 101).collect::<Vec<_>>();
+        let (sorted, runs) = sort(8, records.clone());
+        assert_eq!(runs, 12);
+        let mut expected = records;
+        expected.sort();
+        assert_eq!(sorted, expected);
+    }
+}
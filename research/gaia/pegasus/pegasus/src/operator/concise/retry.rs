@@ -0,0 +1,124 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use crate::api::function::FnResult;
+use crate::api::RetryPolicy;
+use crate::metrics::RetryMetrics;
+
+/// Map the batches of an operator by an idempotent function, retrying a failed batch by the policy;
+pub(crate) struct BatchRetry {
+    name: String,
+    policy: RetryPolicy,
+    metrics: RetryMetrics,
+}
+
+impl BatchRetry {
+    pub(crate) fn new(name: &str, policy: RetryPolicy) -> Self {
+        BatchRetry { name: name.to_owned(), policy, metrics: RetryMetrics::new(name) }
+    }
+
+    /// Map each datum of the batch into the outputs, which are given back only if all the data are
+    /// mapped, as the outputs of a failed attempt are discarded before the next one; As the data
+    /// may be mapped more than once, each of them is cloned before being mapped;
+    pub(crate) fn exec<I, O, F>(&self, batch: &[I], mut func: F) -> FnResult<Vec<O>>
+    where
+        I: Clone,
+        F: FnMut(I, &mut Vec<O>) -> FnResult<()>,
+    {
+        let mut attempt = 1;
+        loop {
+            let mut outputs = Vec::with_capacity(batch.len());
+            match batch.iter().try_for_each(|datum| func(datum.clone(), &mut outputs)) {
+                Ok(()) => return Ok(outputs),
+                Err(err) if attempt < self.policy.max_attempts => {
+                    let backoff = self.policy.backoff_of(attempt);
+                    warn_worker!(
+                        "{} failed on attempt {}/{} of a batch of {} data, retry in {:?}: {}",
+                        self.name,
+                        attempt,
+                        self.policy.max_attempts,
+                        batch.len(),
+                        backoff,
+                        err
+                    );
+                    self.metrics.on_retry();
+                    if !backoff.is_zero() {
+                        std::thread::sleep(backoff);
+                    }
+                    attempt += 1;
+                }
+                Err(err) => {
+                    if attempt > 1 {
+                        warn_worker!("{} failed on all {} attempts: {}", self.name, attempt, err);
+                    }
+                    return Err(err);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::cell::Cell;
+    use std::time::Duration;
+
+    fn failure(msg: &str) -> FnResult<()> {
+        let err: Box<dyn std::error::Error + Send + Sync> = msg.into();
+        Err(err)
+    }
+
+    fn retry(max_attempts: u32) -> BatchRetry {
+        BatchRetry::new("test", RetryPolicy { max_attempts, backoff: Duration::from_millis(0) })
+    }
+
+    #[test]
+    fn retry_batch_test() {
+        let calls = Cell::new(0);
+        // fail on the last datum of the first two attempts
+        let outputs = retry(3)
+            .exec(&[1, 2, 3], |datum: u32, outputs| {
+                calls.set(calls.get() + 1);
+                if datum == 3 && calls.get() < 9 {
+                    failure("transient failure")
+                } else {
+                    outputs.push(datum * 10);
+                    Ok(())
+                }
+            })
+            .unwrap();
+        assert_eq!(outputs, vec![10, 20, 30]);
+        assert_eq!(calls.get(), 9);
+    }
+
+    #[test]
+    fn retry_exhausted_test() {
+        let calls = Cell::new(0);
+        let result = retry(2).exec(&[1, 2], |_: u32, _: &mut Vec<u32>| {
+            calls.set(calls.get() + 1);
+            failure("permanent failure")
+        });
+        assert!(result.is_err());
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[test]
+    fn backoff_test() {
+        let policy = RetryPolicy { max_attempts: 4, backoff: Duration::from_millis(10) };
+        assert_eq!(policy.backoff_of(1), Duration::from_millis(10));
+        assert_eq!(policy.backoff_of(3), Duration::from_millis(40));
+    }
+}
@@ -0,0 +1,158 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use crate::api::meta::OperatorKind;
+use crate::api::state::OperatorState;
+use crate::api::{Range, Sample, Unary, UnaryState};
+use crate::communication::{Input, Output, Pipeline};
+use crate::errors::JobExecError;
+use crate::operator::concise::gather;
+use crate::rng::JobRng;
+use crate::stream::Stream;
+use crate::{BuildJobError, Data};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+// the stream of the operator is split by the tag of each scope, whose draws don't depend on the
+// other scopes interleaved with it;
+struct CoinHandle {
+    rng: JobRng,
+    p: f64,
+}
+
+impl<D: Data> UnaryState<D, D, Option<JobRng>> for CoinHandle {
+    type NotifyResult = Option<D>;
+
+    fn on_receive(
+        &self, input: &mut Input<D>, output: &mut Output<D>,
+        state: &mut OperatorState<Option<JobRng>>,
+    ) -> Result<(), JobExecError> {
+        let (base, p) = (&self.rng, self.p);
+        let rng = state.get_or_insert_with(|| base.split_by_tag(&input.tag));
+        input.for_each_batch(|data| {
+            data.retain(|_| rng.gen_bool(p));
+            if !data.is_empty() {
+                output.forward(data)?;
+            }
+            Ok(())
+        })
+    }
+
+    fn on_notify(&self, _: Option<JobRng>) -> Self::NotifyResult {
+        None
+    }
+}
+
+struct SampleKeyHandle {
+    rng: JobRng,
+}
+
+impl<D: Data> UnaryState<D, (u64, D), Option<JobRng>> for SampleKeyHandle {
+    type NotifyResult = Option<(u64, D)>;
+
+    fn on_receive(
+        &self, input: &mut Input<D>, output: &mut Output<(u64, D)>,
+        state: &mut OperatorState<Option<JobRng>>,
+    ) -> Result<(), JobExecError> {
+        let base = &self.rng;
+        let rng = state.get_or_insert_with(|| base.split_by_tag(&input.tag));
+        input.for_each_batch(|data| {
+            for datum in data.drain(..) {
+                output.give((rng.next_u64(), datum))?;
+            }
+            Ok(())
+        })
+    }
+
+    fn on_notify(&self, _: Option<JobRng>) -> Self::NotifyResult {
+        None
+    }
+}
+
+// a datum with its random key, ordered by the key only;
+struct Keyed<D>(u64, D);
+
+impl<D> PartialEq for Keyed<D> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<D> Eq for Keyed<D> {}
+
+impl<D> PartialOrd for Keyed<D> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<D> Ord for Keyed<D> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+// the data of the least keys of each scope so far, whose greatest key is on the top of the heap;
+struct SampleHandle {
+    n: usize,
+}
+
+impl<D: Data> UnaryState<(u64, D), D, BinaryHeap<Keyed<D>>> for SampleHandle {
+    type NotifyResult = Vec<D>;
+
+    fn on_receive(
+        &self, input: &mut Input<(u64, D)>, _: &mut Output<D>,
+        state: &mut OperatorState<BinaryHeap<Keyed<D>>>,
+    ) -> Result<(), JobExecError> {
+        let n = self.n;
+        input.for_each_batch(|data| {
+            for (key, datum) in data.drain(..) {
+                if state.len() < n {
+                    state.push(Keyed(key, datum));
+                } else if state.peek().map(|top| key < top.0).unwrap_or(false) {
+                    state.pop();
+                    state.push(Keyed(key, datum));
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn on_notify(&self, state: BinaryHeap<Keyed<D>>) -> Self::NotifyResult {
+        state.into_sorted_vec().into_iter().map(|keyed| keyed.1).collect()
+    }
+}
+
+impl<D: Data> Sample<D> for Stream<D> {
+    fn coin(&self, p: f64) -> Result<Stream<D>, BuildJobError> {
+        if !(0.0..=1.0).contains(&p) {
+            let msg = format!("coin of probability {}, which should be in [0, 1];", p);
+            return BuildJobError::unsupported(msg);
+        }
+        self.unary_with_state("coin", Pipeline, |meta| {
+            meta.set_kind(OperatorKind::Clip);
+            CoinHandle { rng: meta.rng(), p }
+        })
+    }
+
+    fn sample(&self, range: Range, n: usize) -> Result<Stream<D>, BuildJobError> {
+        if n == 0 {
+            return BuildJobError::unsupported("sample of 0 data;");
+        }
+        let keyed = self
+            .unary_with_state("sample_key", Pipeline, |meta| SampleKeyHandle { rng: meta.rng() })?;
+        keyed.unary_with_state("sample", gather(range), |_| SampleHandle { n })
+    }
+}
@@ -0,0 +1,145 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use crate::api::notify::Notification;
+use crate::api::{TimeLimit, Unary};
+use crate::communication::input::{new_input_session, InputProxy};
+use crate::communication::output::{new_output_session, OutputProxy};
+use crate::communication::Pipeline;
+use crate::errors::{BuildJobError, JobExecError};
+use crate::operator::{FiredState, OperatorCore};
+use crate::stream::Stream;
+use crate::{Data, Tag};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// The deadlines of the scopes in the part bounded by time on current worker;
+type Deadlines = Arc<Mutex<HashMap<Tag, Instant>>>;
+
+/// The end of the part bounded by time, which passes the data of each scope until its deadline,
+/// and cancels the scope after;
+struct TimeLimitOperator<D> {
+    limit: Duration,
+    deadlines: Deadlines,
+    // the scopes whose deadlines have passed, whose data are discarded until they end;
+    expired: HashSet<Tag>,
+    _ph: std::marker::PhantomData<D>,
+}
+
+impl<D> TimeLimitOperator<D> {
+    /// The deadline of the scope, which starts here if none of its data entered the part on
+    /// current worker, e.g. of the data exchanged from others in the part;
+    fn deadline(&self, tag: &Tag) -> Instant {
+        let limit = self.limit;
+        match self.deadlines.lock() {
+            Ok(mut deadlines) => {
+                *deadlines.entry(tag.clone()).or_insert_with(|| Instant::now() + limit)
+            }
+            Err(_) => Instant::now() + limit,
+        }
+    }
+}
+
+impl<D: Data> OperatorCore for TimeLimitOperator<D> {
+    fn on_receive(
+        &mut self, tag: &Tag, inputs: &[Box<dyn InputProxy>], outputs: &[Box<dyn OutputProxy>],
+    ) -> Result<FiredState, JobExecError> {
+        let mut input = new_input_session::<D>(&inputs[0], tag);
+        if self.expired.contains(tag) {
+            input.for_each_batch(|dataset| {
+                dataset.clear();
+                Ok(())
+            })?;
+            return Ok(FiredState::Idle);
+        }
+        let deadline = self.deadline(tag);
+        let mut output = new_output_session::<D>(&outputs[0], tag);
+        let mut expired = false;
+        input.for_each_batch(|dataset| {
+            if expired || Instant::now() >= deadline {
+                expired = true;
+                dataset.clear();
+            } else {
+                output.forward(dataset)?;
+            }
+            Ok(())
+        })?;
+        if expired {
+            debug_worker!("scope {:?} expires after {:?}", tag, self.limit);
+            self.expired.insert(tag.clone());
+            input.cancel_scope();
+        }
+        Ok(FiredState::Idle)
+    }
+
+    fn on_notify(
+        &mut self, n: Notification, _: &[Box<dyn OutputProxy>],
+    ) -> Result<(), JobExecError> {
+        self.expired.remove(&n.tag);
+        if let Ok(mut deadlines) = self.deadlines.lock() {
+            deadlines.remove(&n.tag);
+        }
+        Ok(())
+    }
+
+    fn on_tick(&mut self) -> Vec<Tag> {
+        let now = Instant::now();
+        let mut expired = vec![];
+        if let Ok(deadlines) = self.deadlines.lock() {
+            for (tag, deadline) in deadlines.iter() {
+                if *deadline <= now && self.expired.insert(tag.clone()) {
+                    debug_worker!("scope {:?} expires after {:?}", tag, self.limit);
+                    expired.push(tag.clone());
+                }
+            }
+        }
+        expired
+    }
+}
+
+impl<D: Data> TimeLimit<D> for Stream<D> {
+    fn time_limit<O, F>(&self, limit: Duration, func: F) -> Result<Stream<O>, BuildJobError>
+    where
+        O: Data,
+        F: FnOnce(Stream<D>) -> Result<Stream<O>, BuildJobError>,
+    {
+        let deadlines: Deadlines = Arc::new(Mutex::new(HashMap::new()));
+        let entered = deadlines.clone();
+        let start = self.unary("time_limit_enter", Pipeline, move |_| {
+            move |input, output| {
+                // the deadline of a scope starts once its first data enter the part
+                if let Ok(mut deadlines) = entered.lock() {
+                    deadlines.entry(input.tag.clone()).or_insert_with(|| Instant::now() + limit);
+                }
+                input.for_each_batch(|dataset| {
+                    output.forward(dataset)?;
+                    Ok(())
+                })
+            }
+        })?;
+        func(start)?.concat("time_limit", Pipeline, move |meta| {
+            meta.enable_notify();
+            meta.enable_tick();
+            let expired = HashSet::new();
+            Box::new(TimeLimitOperator::<O> {
+                limit,
+                deadlines,
+                expired,
+                _ph: std::marker::PhantomData,
+            })
+        })
+    }
+}
@@ -0,0 +1,231 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! A harness to test an operator outside of the runtime, whose inputs are fed with the batches,
+//! the pushed events and the scope ends given by hand in any order, which is fired step by step as
+//! the scheduler does, and whose outputs capture the batches and the scope ends it gives. It makes
+//! the orders of the signals rare in the runtime, e.g. the end of a scope arriving before its data,
+//! deterministic to test.
+
+use crate::api::meta::OperatorMeta;
+use crate::channel_id::SubChannelId;
+use crate::communication::channel::ChannelMeta;
+use crate::communication::decorator::count::CountedPush;
+use crate::communication::decorator::DataPush;
+use crate::communication::input::{new_input, InputProxy};
+use crate::communication::output::{OutputEntry, RefWrapOutput};
+use crate::data::DataSet;
+use crate::data_plane::{Pull, Push, ThreadPull, ThreadPush};
+use crate::errors::JobExecError;
+use crate::event::{ChannelRxState, Event, EventBus, EventKind};
+use crate::operator::{OperatorBuilder, OperatorCore};
+use crate::schedule::OpRuntime;
+use crate::worker_id::CurWorkerGuard;
+use crate::{Data, JobConf, Tag, WorkerId};
+use crossbeam_channel::Receiver;
+use pegasus_common::rc::RcPointer;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// The events the operator sends, which are taken by the outputs of their channels;
+struct EventLog {
+    rx: Receiver<(WorkerId, Event)>,
+    events: RefCell<Vec<Event>>,
+}
+
+impl EventLog {
+    fn take(&self, ch_index: u32) -> Vec<Event> {
+        let mut events = self.events.borrow_mut();
+        events.extend(self.rx.try_iter().map(|(_, event)| event));
+        let (taken, rest): (Vec<Event>, Vec<Event>) =
+            events.drain(..).partition(|event| event.ch == ch_index);
+        *events = rest;
+        taken
+    }
+}
+
+// adds an output of the operator to the builder, once the operator is built;
+type OutputBuilder = Box<dyn FnOnce(&mut OperatorBuilder)>;
+
+pub(crate) struct HarnessBuilder {
+    worker_id: WorkerId,
+    meta: OperatorMeta,
+    event_bus: EventBus,
+    log: Rc<EventLog>,
+    inputs: Vec<Box<dyn InputProxy>>,
+    outputs: Vec<OutputBuilder>,
+    next_ch: u32,
+    guard: CurWorkerGuard,
+}
+
+impl HarnessBuilder {
+    /// Build the operator of `name` in the scopes of `scope_depth`, on the only worker of a job;
+    pub fn new(name: &str, scope_depth: usize) -> Self {
+        let worker_id = WorkerId::new(0, 1, 0, false);
+        let guard = crate::worker_id::guard(worker_id);
+        let mut meta = OperatorMeta::new(name, worker_id, &Arc::new(JobConf::default()));
+        meta.scope_depth = scope_depth;
+        let (tx, rx) = crossbeam_channel::unbounded();
+        HarnessBuilder {
+            worker_id,
+            meta,
+            event_bus: EventBus::new(worker_id, tx),
+            log: Rc::new(EventLog { rx, events: RefCell::new(vec![]) }),
+            inputs: vec![],
+            outputs: vec![],
+            next_ch: 1,
+            guard,
+        }
+    }
+
+    fn next_channel(&mut self) -> SubChannelId {
+        let index = self.next_ch as usize;
+        self.next_ch += 1;
+        [0, self.worker_id.index as usize, index].into()
+    }
+
+    /// Add the next input of the operator, which is a pipeline from the worker itself;
+    pub fn input<D: Data>(&mut self) -> MockInput<D> {
+        let ch_id = self.next_channel();
+        let meta = ChannelMeta {
+            id: ch_id,
+            is_local: true,
+            push_peers: 1,
+            forbid_cancel: false,
+            is_aggregate: false,
+            order_preserving: false,
+            spillable: false,
+        };
+        let (push, pull) = crate::data_plane::pipeline::<DataSet<D>>(ch_id);
+        let input = new_input(meta, self.meta.scope_depth, &self.event_bus, pull.into());
+        let state = input.get_state().clone();
+        self.inputs.push(input);
+        MockInput { push, state }
+    }
+
+    /// Add the next output port of the operator, which is a pipeline to the worker itself;
+    pub fn output<D: Data>(&mut self) -> MockOutput<D> {
+        let ch_id = self.next_channel();
+        let (push, pull) = crate::data_plane::pipeline::<DataSet<D>>(ch_id);
+        let (worker_id, event_bus) = (self.worker_id, self.event_bus.clone());
+        self.outputs.push(Box::new(move |op: &mut OperatorBuilder| {
+            let push = CountedPush::new(ch_id, worker_id, worker_id, push.into(), &event_bus);
+            let entry = OutputEntry::new(ch_id.index(), true, DataPush::Count(push));
+            op.new_output::<D>().add_push(entry);
+        }));
+        let port = self.outputs.len() - 1;
+        MockOutput { port, ch_index: ch_id.index(), pull, log: self.log.clone() }
+    }
+
+    /// Build the operator of the core constructed by `construct` on the inputs and outputs added;
+    pub fn build<F>(mut self, construct: F) -> OperatorHarness
+    where
+        F: FnOnce(&mut OperatorMeta) -> Box<dyn OperatorCore>,
+    {
+        let core = construct(&mut self.meta);
+        let mut builder = OperatorBuilder::new(self.meta, core, &self.event_bus);
+        for input in self.inputs {
+            builder.add_input(input);
+        }
+        for output in self.outputs {
+            output(&mut builder);
+        }
+        OperatorHarness { runtime: OpRuntime::new(builder.build()), _guard: self.guard }
+    }
+}
+
+/// An input of the operator, through which the batches and the signals of the scopes are given;
+pub(crate) struct MockInput<D: Data> {
+    push: ThreadPush<DataSet<D>>,
+    state: RcPointer<ChannelRxState>,
+}
+
+impl<D: Data> MockInput<D> {
+    /// Give a batch of the scope of `tag` along with the event of its size, as the channels do;
+    pub fn give(&mut self, tag: Tag, data: Vec<D>) {
+        self.announce(tag.clone(), data.len());
+        self.deliver(tag, data);
+    }
+
+    /// Give the event of a batch of `len` data pushed into the scope, ahead of the batch itself;
+    pub fn announce(&self, tag: Tag, len: usize) {
+        self.state.pushed(tag, len);
+    }
+
+    /// Give a batch of the scope whose event is given by `announce`;
+    pub fn deliver(&mut self, tag: Tag, data: Vec<D>) {
+        self.push.push(DataSet::new(tag, data)).expect("push into harness failure");
+    }
+
+    /// End the scope of `tag` on the input, or all the scopes in it if it is of a parent scope;
+    pub fn end(&self, tag: Tag) {
+        self.state.give_scope_end_of(tag, 0);
+    }
+}
+
+/// An output of the operator, which captures the batches and the scope ends given by it;
+pub(crate) struct MockOutput<D: Data> {
+    port: usize,
+    ch_index: u32,
+    pull: ThreadPull<DataSet<D>>,
+    log: Rc<EventLog>,
+}
+
+impl<D: Data> MockOutput<D> {
+    /// Take the batches output so far as the tags and the data of them, in the order output;
+    pub fn take(&mut self) -> Vec<(Tag, Vec<D>)> {
+        let mut batches = vec![];
+        while let Ok(Some(mut batch)) = self.pull.pull() {
+            let data = std::mem::take(batch.data());
+            batches.push((batch.tag(), data));
+        }
+        batches
+    }
+
+    /// Take the scopes ended on the output so far, in the order ended;
+    pub fn take_ends(&self) -> Vec<Tag> {
+        self.log
+            .take(self.ch_index)
+            .into_iter()
+            .filter(|event| matches!(event.kind, EventKind::EOS(_)))
+            .map(|event| event.tag)
+            .collect()
+    }
+}
+
+pub(crate) struct OperatorHarness {
+    runtime: OpRuntime,
+    _guard: CurWorkerGuard,
+}
+
+impl OperatorHarness {
+    /// Fire the operator once as the scheduler does if it is ready, i.e. take the outstanding
+    /// inputs if its outputs have capacity, and give the scope ends notified;
+    pub fn step(&mut self) -> Result<(), JobExecError> {
+        if self.runtime.check_ready() {
+            self.runtime.fire()?;
+        }
+        Ok(())
+    }
+
+    /// Set the batches `output` can take in each firing, where the operator takes no input once
+    /// any of its outputs is out of capacity;
+    pub fn set_capacity<D: Data>(&self, output: &MockOutput<D>, capacity: u32) {
+        let proxy = &self.runtime.outputs()[output.port];
+        RefWrapOutput::<D>::downcast(proxy).borrow_mut().capacity = capacity;
+        proxy.reset_capacity();
+    }
+}
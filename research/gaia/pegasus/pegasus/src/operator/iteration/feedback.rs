@@ -1,12 +1,12 @@
 //
 //! Copyright 2020 Alibaba Group Holding Limited.
-//! 
+//!
 //! Licensed under the Apache License, Version 2.0 (the "License");
 //! you may not use this file except in compliance with the License.
 //! You may obtain a copy of the License at
-//! 
+//!
 //! http://www.apache.org/licenses/LICENSE-2.0
-//! 
+//!
 //! Unless required by applicable law or agreed to in writing, software
 //! distributed under the License is distributed on an "AS IS" BASIS,
 //! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
@@ -89,10 +89,11 @@ impl<D: Data> OperatorCore for Feedback<D> {
                         Self::try_to_vote(&outputs[1], &n.tag)?;
                     }
                 } else {
-                    // iteration data of scope `p` has never appear in this worker's loop stream, but it's
-                    // data may had appeared in other workers' loop stream;
-                    // self.in_loop.insert(p, n.stream_end.current_uncheck());
-                    // Self::try_to_vote(&outputs[0], self.worker_id.index, &n.stream_end)?;
+                    // iteration data of scope `p` has never appeared in this worker's loop stream,
+                    // e.g. all left the loop before entering it, so none enters the next iteration
+                    // here, though it may have appeared in other workers' loop stream;
+                    trace_worker!("try to vote iteration terminate {:?}", &n.tag);
+                    Self::try_to_vote(&outputs[1], &n.tag)?;
                 }
             }
         } else if n.tag.len() + 1 == self.scope_depth {
@@ -1,12 +1,12 @@
 //
 //! Copyright 2020 Alibaba Group Holding Limited.
-//! 
+//!
 //! Licensed under the Apache License, Version 2.0 (the "License");
 //! you may not use this file except in compliance with the License.
 //! You may obtain a copy of the License at
-//! 
+//!
 //! http://www.apache.org/licenses/LICENSE-2.0
-//! 
+//!
 //! Unless required by applicable law or agreed to in writing, software
 //! distributed under the License is distributed on an "AS IS" BASIS,
 //! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
@@ -15,7 +15,7 @@
 
 use crate::api::meta::OperatorMeta;
 use crate::api::notify::Notification;
-use crate::api::LoopCondition;
+use crate::api::{EmitKind, LoopCondition};
 use crate::communication::input::{new_input_session, InputProxy};
 use crate::communication::output::{new_output_session, OutputProxy};
 use crate::errors::JobExecError;
@@ -138,33 +138,34 @@ impl<D: Data> OperatorCore for MergeSwitch<D> {
             }
 
             let condition = &self.condition;
+            let check_until = condition.has_until_cond() && condition.is_pre_check();
+            let emit_before = condition.get_emit() == EmitKind::Before;
             let mut input = new_input_session::<IterationSync<D>>(&inputs[0], tag);
-            if condition.has_until_cond() {
+            if check_until || emit_before {
                 input.for_each_batch(|data_set| {
                     for data in data_set.drain(..) {
-                        match data {
-                            IterationSync::Data(mut data) => {
-                                for datum in data.drain(..) {
-                                    if condition.is_converge(&datum)? {
-                                        output_leave.give(datum)?;
-                                    } else {
-                                        has_data_into_iter |= true;
-                                        output_loop.give(datum)?;
+                        if let IterationSync::Data(mut data) = data {
+                            for datum in data.drain(..) {
+                                if check_until && condition.is_converge(&datum)? {
+                                    output_leave.give(datum)?;
+                                } else {
+                                    if emit_before {
+                                        output_leave.give(datum.clone())?;
                                     }
+                                    has_data_into_iter |= true;
+                                    output_loop.give(datum)?;
                                 }
                             }
-                            _ => (),
                         }
                     }
                     Ok(())
                 })?;
             } else {
-                has_data_into_iter |= true;
                 input.for_each_batch(|data_set| {
                     for data in data_set.drain(..) {
-                        match data {
-                            IterationSync::Data(mut data) => output_loop.forward(&mut data)?,
-                            _ => (),
+                        if let IterationSync::Data(mut data) = data {
+                            has_data_into_iter |= !data.is_empty();
+                            output_loop.forward(&mut data)?
                         }
                     }
                     Ok(())
@@ -173,13 +174,24 @@ impl<D: Data> OperatorCore for MergeSwitch<D> {
         }
         self.extern_exhaust |= inputs[0].is_exhaust();
 
+        if round == 0 && !has_data_into_iter && self.in_loops.contains_key(&p) {
+            std::mem::drop(output_loop);
+            std::mem::drop(output_leave);
+            // nothing enters the loop by now, e.g. all data leave before the first iteration, so
+            // the loop ends once no worker has data entering it either;
+            self.check_termination(tag, p, round, &inputs[2], outputs)?;
+            return Ok(FiredState::Idle);
+        }
+
         if round > 0 {
             let mut feedback = new_input_session::<D>(&inputs[1], tag);
-            if round >= self.condition.max_iters {
+            let condition = &self.condition;
+            let emit = condition.get_emit() != EmitKind::Null;
+            if round >= condition.max_iters {
                 feedback.for_each_batch(|data_set| {
-                    if self.condition.has_until_cond() {
+                    if condition.has_until_cond() && !emit {
                         for datum in data_set.drain(..) {
-                            if self.condition.is_converge(&datum)? {
+                            if condition.is_converge(&datum)? {
                                 output_leave.give(datum)?;
                             } else {
                                 // discard forever;
@@ -192,11 +204,14 @@ impl<D: Data> OperatorCore for MergeSwitch<D> {
                 })?;
             } else {
                 feedback.for_each_batch(|data_set| {
-                    if self.condition.has_until_cond() {
+                    if condition.has_until_cond() || emit {
                         for datum in data_set.drain(..) {
-                            if self.condition.is_converge(&datum)? {
+                            if condition.is_converge(&datum)? {
                                 output_leave.give(datum)?;
                             } else {
+                                if emit {
+                                    output_leave.give(datum.clone())?;
+                                }
                                 has_data_into_iter |= true;
                                 output_loop.give(datum)?;
                             }
@@ -240,6 +255,16 @@ impl<D: Data> OperatorCore for MergeSwitch<D> {
                     true
                 }
             });
+
+            // the loops nested in another one may all end before the end of their input, which
+            // fires no more data to tell it;
+            if n.tag.is_root() {
+                self.extern_exhaust = true;
+                if self.in_loops.is_empty() {
+                    outputs[0].close()?;
+                    outputs[1].close()?;
+                }
+            }
         } else if n.port == 1 {
             if n.tag.len() == self.scope_depth {
                 // End notifications of each iteration;
@@ -15,7 +15,7 @@
 
 use crate::api::meta::{OperatorKind, Priority, ScopePrior};
 use crate::api::notify::Notification;
-use crate::api::{EnterScope, Iteration, LeaveScope, LoopCondition, Unary, UnaryNotify};
+use crate::api::{EnterScope, Iteration, LeaveScope, LoopCondition, LoopRound, Unary, UnaryNotify};
 use crate::communication::output::{OutputDelta, OutputProxy};
 use crate::communication::{Broadcast, Channel, Pipeline};
 use crate::communication::{Input, Output};
@@ -44,6 +44,16 @@ impl<D: Data> Iteration<D> for Stream<D> {
         self.iterate_until(until, func)
     }
 
+    fn iterate_with_round<F>(&self, max_iters: u32, func: F) -> Result<Stream<D>, BuildJobError>
+    where
+        F: FnOnce(LoopRound, Stream<D>) -> Result<Stream<D>, BuildJobError>,
+    {
+        self.iterate(max_iters, |into_loop| {
+            let round = LoopRound::new(into_loop.scope_depth);
+            func(round, into_loop)
+        })
+    }
+
     fn iterate_until<F>(&self, until: LoopCondition<D>, func: F) -> Result<Stream<D>, BuildJobError>
     where
         F: FnOnce(Stream<D>) -> Result<Stream<D>, BuildJobError>,
@@ -69,7 +79,7 @@ impl<D: Data> Iteration<D> for Stream<D> {
 
             ms.set_cancel_guard(LoopCancelGuard::new());
             let leave_loop = enter.spawn::<D>(&mut ms);
-            let into_loop = enter.spawn::<D>(&mut ms);
+            let into_loop = enter.spawn::<D>(&mut ms).into_loop_scope();
             (leave_loop, into_loop, ms.get_index())
         };
 
@@ -77,6 +87,7 @@ impl<D: Data> Iteration<D> for Stream<D> {
         let (fb_data, fb_vote) = {
             let mut feedback = after_loop.add_operator("feedback", Pipeline, |meta| {
                 meta.set_kind(OperatorKind::Map);
+                meta.set_feedback();
                 meta.enable_notify();
                 meta.set_output_delta(OutputDelta::Advance);
                 Box::new(Feedback::<D>::new(meta.scope_depth, max_iters))
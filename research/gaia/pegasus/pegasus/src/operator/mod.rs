@@ -13,6 +13,7 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
+use crate::api::iteration::{enter_rounds, RoundsGuard};
 use crate::api::meta::OperatorMeta;
 use crate::api::notify::Notification;
 use crate::communication::input::InputProxy;
@@ -20,6 +21,9 @@ use crate::communication::output::{OutputBuilder, OutputBuilderImpl, OutputProxy
 use crate::errors::JobExecError;
 use crate::event::EventBus;
 use crate::graph::Port;
+use crate::profile::OperatorProfiler;
+use crate::schedule::slice;
+use crate::{scratch, span};
 use crate::{Data, Tag};
 use std::collections::HashMap;
 
@@ -50,6 +54,13 @@ pub trait OperatorCore: Send {
     ) -> Result<(), JobExecError> {
         Ok(())
     }
+
+    /// Get the scopes to cancel, e.g. as their deadlines pass, whose inputs are discarded and
+    /// canceled upstream; It is asked in each step of the scheduler for the operators of
+    /// `OperatorMeta::enable_tick` only;
+    fn on_tick(&mut self) -> Vec<Tag> {
+        vec![]
+    }
 }
 
 mod cancel;
@@ -59,12 +70,25 @@ use cancel::DefaultCancelGuard;
 
 struct Active {
     notified_ports: Vec<usize>,
+    // whether the end of a parent scope is retained until it is idle, see `Operator::notify`;
+    retains_parent: bool,
     state: FiredState,
 }
 
 impl Default for Active {
     fn default() -> Self {
-        Active { notified_ports: Vec::new(), state: FiredState::Active }
+        Active { notified_ports: Vec::new(), retains_parent: false, state: FiredState::Active }
+    }
+}
+
+/// Tell the rounds of the loops of the depths, as of `tag`, to the functions fired on an operator
+/// in the loops, which borrows only the depths so that the operator fires meanwhile;
+#[inline]
+fn enter_loop_rounds(loop_depths: &[usize], tag: &Tag) -> Option<RoundsGuard> {
+    if loop_depths.is_empty() {
+        None
+    } else {
+        Some(enter_rounds(loop_depths, tag))
     }
 }
 
@@ -75,6 +99,8 @@ pub struct Operator {
     core: Box<dyn OperatorCore>,
     actives: HashMap<Tag, Active>,
     cancel: Box<dyn CancelGuard>,
+    // the measures of the operator, if its job is profiled
+    profiler: Option<OperatorProfiler>,
 }
 
 impl Operator {
@@ -123,6 +149,22 @@ impl Operator {
         }
     }
 
+    /// The batches all outputs can still output in current firing, i.e. the least of them;
+    pub fn output_capacity_left(&self) -> i64 {
+        self.outputs
+            .iter()
+            .map(|o| o.remaining_capacity())
+            .min()
+            .unwrap_or(self.meta.capacity as i64)
+    }
+
+    /// Count a firing using up the capacity of the outputs, see `OperatorProfile::stalls`;
+    pub(crate) fn on_stall(&mut self) {
+        if let Some(profiler) = self.profiler.as_mut() {
+            profiler.on_stall();
+        }
+    }
+
     #[inline]
     pub fn has_notifications(&self) -> bool {
         let len = self.inputs.len();
@@ -145,11 +187,22 @@ impl Operator {
     }
 
     pub fn fire_actives(&mut self) -> Result<(), JobExecError> {
+        let _scratch = scratch::guard();
+        let _measure = self.profiler.as_mut().map(|p| p.measure());
         let mut actives = std::mem::replace(&mut self.actives, HashMap::new());
         for (tag, active) in actives.iter_mut() {
+            // the rest actives are resumed in the later firings once the slice is used up;
+            if slice::is_exhausted() {
+                break;
+            }
+            let _span = span::operator_span(&self.meta, tag).entered();
+            let _rounds = enter_loop_rounds(&self.meta.loop_depths, tag);
             trace_worker!("fire operator {:?} on actives {:?};", self.meta, tag);
             if FiredState::Idle == self.core.on_active(tag, &self.outputs)? {
                 active.state = FiredState::Idle;
+                if active.retains_parent {
+                    self.outputs.iter().for_each(|o| o.drop_retain(tag));
+                }
                 for p in active.notified_ports.drain(..) {
                     self.outputs.iter().for_each(|o| o.drop_retain(tag));
                     let notification = Notification::new(p, tag.clone());
@@ -167,6 +220,10 @@ impl Operator {
         if self.actives.contains_key(tag) {
             Ok(())
         } else {
+            let _scratch = scratch::guard();
+            let _span = span::operator_span(&self.meta, tag).entered();
+            let _rounds = enter_loop_rounds(&self.meta.loop_depths, tag);
+            let _measure = self.profiler.as_mut().map(|p| p.measure());
             trace_worker!("fire operator {:?} on receive {:?};", self.meta, tag);
             if FiredState::Active == self.core.on_receive(tag, &self.inputs, &self.outputs)? {
                 let active = Active::default();
@@ -177,13 +234,34 @@ impl Operator {
     }
 
     pub fn notify(&mut self) -> Result<(), JobExecError> {
+        let _scratch = scratch::guard();
         for (port, input) in self.inputs.iter().enumerate() {
             for n in input.get_state().notifications().drain(..) {
-                self.outputs.iter().for_each(|o| o.scope_end(n.clone()));
+                // an end on all workers passes an operator of one input as is, while it can't
+                // tell the ends of the other inputs;
+                if input.get_state().take_global_end(&n) && self.inputs.len() == 1 {
+                    self.outputs.iter().for_each(|o| o.global_scope_end(n.clone()));
+                } else {
+                    self.outputs.iter().for_each(|o| o.scope_end(n.clone()));
+                }
                 if let Some(active) = self.actives.get_mut(&n) {
                     active.notified_ports.push(port);
                     self.outputs.iter().for_each(|o| o.retain(&n));
-                } else if self.meta.notifiable {
+                    continue;
+                }
+                // the ends of the scopes still active within, which are folded into the end of
+                // their parent, e.g. those of the subtasks, never come on their own, so the end of
+                // the parent is retained by them instead;
+                for (tag, active) in self.actives.iter_mut() {
+                    if !active.retains_parent && n.is_parent_of(tag) {
+                        active.retains_parent = true;
+                        self.outputs.iter().for_each(|o| o.retain(tag));
+                    }
+                }
+                if self.meta.notifiable {
+                    let _span = span::operator_span(&self.meta, &n).entered();
+                    let _rounds = enter_loop_rounds(&self.meta.loop_depths, &n);
+                    let _measure = self.profiler.as_mut().map(|p| p.measure());
                     let n = Notification::new(port, n);
                     trace_worker!("fire operator {:?} on notify {:?};", self.meta, n);
                     self.core.on_notify(n, &self.outputs)?;
@@ -193,6 +271,18 @@ impl Operator {
         Ok(())
     }
 
+    /// Cancel the inputs of the scopes told by `OperatorCore::on_tick`;
+    pub fn tick(&mut self) {
+        if self.meta.ticked {
+            for tag in self.core.on_tick() {
+                debug_worker!("operator {:?} cancels scope {:?} on tick", self.meta, tag);
+                for input in self.inputs.iter() {
+                    input.cancel(&tag);
+                }
+            }
+        }
+    }
+
     pub fn cancel(&mut self, port: usize, ch_index: u32, tag: Tag) -> Result<(), JobExecError> {
         assert!(port < self.outputs.len(), "{:?} : output port {:?} not exist;", self.meta, port);
         let signal = CancelSignal { port, ch_index, tag };
@@ -203,10 +293,19 @@ impl Operator {
                 input.cancel(&tag);
             }
             if let Some(v) = self.actives.remove(&tag) {
+                if v.retains_parent {
+                    self.outputs.iter().for_each(|o| o.drop_retain(&tag));
+                }
+                if self.inputs.is_empty() {
+                    // a source ends its scope once exhausted, which it is never fired to be if
+                    // canceled ahead;
+                    self.outputs.iter().for_each(|o| o.scope_end(tag.clone()));
+                }
                 for p in v.notified_ports {
                     for output in self.outputs.iter() {
                         output.drop_retain(&tag);
                     }
+                    let _rounds = enter_loop_rounds(&self.meta.loop_depths, &tag);
                     let n = Notification::new(p, tag.clone());
                     self.core.on_notify(n, &self.outputs)?;
                 }
@@ -241,6 +340,11 @@ impl OperatorBuilder {
         self.meta.index
     }
 
+    /// The number of output ports of the operator;
+    pub(crate) fn output_ports(&self) -> usize {
+        self.outputs.len()
+    }
+
     pub fn set_cancel_guard<G: CancelGuard>(&mut self, guard: G) {
         self.cancel = Some(Box::new(guard));
     }
@@ -257,6 +361,8 @@ impl OperatorBuilder {
         output.batch_size = self.meta.batch_size;
         output.mem_limit = self.meta.mem_limit as usize;
         output.capacity = self.meta.capacity as u32;
+        output.flush_interval = self.meta.flush_interval;
+        output.latency_mode = self.meta.latency_mode;
         self.outputs.push(Box::new(output.clone()));
         output
     }
@@ -273,13 +379,25 @@ impl OperatorBuilder {
         let cancel =
             self.cancel.take().unwrap_or_else(|| Box::new(DefaultCancelGuard::new(outputs.len())));
 
-        Operator { meta: self.meta, inputs: self.inputs, outputs, core: self.core, actives, cancel }
+        let profiler =
+            if self.meta.profile { Some(OperatorProfiler::new(&self.meta)) } else { None };
+        Operator {
+            meta: self.meta,
+            inputs: self.inputs,
+            outputs,
+            core: self.core,
+            actives,
+            cancel,
+            profiler,
+        }
     }
 }
 
 mod binary;
 mod branch;
 mod concise;
+#[cfg(test)]
+pub(crate) mod harness;
 mod iteration;
 mod multiplex;
 mod scope;
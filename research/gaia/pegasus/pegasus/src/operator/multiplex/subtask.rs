@@ -18,13 +18,14 @@ use crate::api::meta::OperatorMeta;
 use crate::api::notify::Notification;
 use crate::api::state::StateMap;
 use crate::api::{
-    Binary, BinaryInput, BinaryNotification, BinaryNotify, Exchange, LeaveScope, Multiplexing,
-    ResultSet, SubTask, SubtaskResult,
+    Binary, BinaryInput, BinaryNotification, BinaryNotify, Exchange, LeaveScope, Map, Merge,
+    Multiplexing, ResultSet, SemiJoinKind, SubTask, SubtaskResult,
 };
 use crate::communication::input::{new_input_session, InputProxy};
 use crate::communication::output::{new_output_session, OutputProxy};
 use crate::communication::{Output, Pipeline};
 use crate::errors::{BuildJobError, JobExecError};
+use crate::operator::branch::{route, Routed};
 use crate::operator::{FiredState, OperatorCore};
 use crate::stream::Stream;
 use crate::{Data, JobConf, Tag};
@@ -38,9 +39,15 @@ impl<D: Data> SubTask<D> for Stream<D> {
     {
         let m = self.scope_by_size(1)?;
         let sub = func(m)?;
+        // the results are gathered to the worker forking the subtask before it leaves its scope,
+        // whose end there tells all the results have arrived, even if the subtask exchanges data;
         sub.concat("subtask_sink", Pipeline, |meta| Box::new(SubtaskSink::<T>::new(meta)))?
-            .owned_leave()?
-            .exchange(route!(|item: &SubtaskResult<T>| item.seq as u64))
+            .exchange(route!(|item: &SubtaskResult<T>| item.seq as u64))?
+            .concat("subtask_end", Pipeline, |meta| {
+                meta.enable_notify();
+                Box::new(SubtaskEnd::<T>::new(meta))
+            })?
+            .owned_leave()
     }
 
     fn fork_detached_subtask<F, T>(
@@ -65,37 +72,155 @@ impl<D: Data> SubTask<D> for Stream<D> {
             SubtaskJoin::new(meta, func)
         })
     }
+
+    fn join_first_subtask<T, R, F>(
+        &self, subtask: Stream<SubtaskResult<T>>, func: F,
+    ) -> Result<Stream<R>, BuildJobError>
+    where
+        T: Data,
+        R: Data,
+        F: Fn(&D, Option<T>) -> Option<R> + Send + 'static,
+    {
+        self.binary_notify("join_first_subtask", &subtask, Pipeline, Pipeline, |meta| {
+            SubtaskJoinFirst::new(meta, func)
+        })
+    }
+
+    fn semi_join_subtask<T>(
+        &self, subtask: Stream<SubtaskResult<T>>, kind: SemiJoinKind,
+    ) -> Result<Stream<D>, BuildJobError>
+    where
+        T: Data,
+    {
+        self.binary_notify("semi_join_subtask", &subtask, Pipeline, Pipeline, |meta| {
+            SubtaskSemiJoin::new(meta, kind)
+        })
+    }
+
+    fn optional_subtask<F>(&self, func: F) -> Result<Stream<D>, BuildJobError>
+    where
+        F: FnOnce(Stream<D>) -> Result<Stream<D>, BuildJobError> + Send,
+    {
+        let subtask = self.fork_subtask(func)?;
+        self.binary_notify("optional_subtask", &subtask, Pipeline, Pipeline, |meta| {
+            SubtaskSplit::new(meta)
+        })?
+        .flat_map_with_fn(Pipeline, |(r, p)| Ok(r.or(p).into_iter().map(|d| Ok(d))))
+    }
+
+    fn coalesce_subtasks<F, T>(&self, branches: Vec<F>) -> Result<Stream<(u32, T)>, BuildJobError>
+    where
+        T: Data,
+        F: FnOnce(Stream<D>) -> Result<Stream<T>, BuildJobError> + Send,
+    {
+        if branches.is_empty() {
+            return BuildJobError::unsupported("coalesce subtasks of no branch;");
+        }
+        let last = branches.len() - 1;
+        // the parents whose subtasks of all the branches so far output no result;
+        let mut parents: Option<Stream<D>> = None;
+        let mut results: Option<Stream<(u32, T)>> = None;
+        for (i, func) in branches.into_iter().enumerate() {
+            let index = i as u32;
+            let current = parents.as_ref().unwrap_or(self);
+            let subtask = current.fork_subtask(func)?;
+            let found = if i == last {
+                current.join_subtask(subtask, move |_, r| Some((index, r)))?
+            } else {
+                let split = current.binary_notify(
+                    "coalesce_subtask",
+                    &subtask,
+                    Pipeline,
+                    Pipeline,
+                    |meta| SubtaskSplit::new(meta),
+                )?;
+                let (found, empty) = route(&split, "coalesce_route", move |split| match split {
+                    (Some(r), _) => Ok(Routed::Left((index, r))),
+                    (None, Some(p)) => Ok(Routed::Right(p)),
+                    (None, None) => {
+                        let err = std::io::Error::other("empty split");
+                        Err(Box::new(err) as DynError)
+                    }
+                })?;
+                parents = Some(empty);
+                found
+            };
+            results = Some(match results.take() {
+                Some(results) => results.merge(&found)?,
+                None => found,
+            });
+        }
+        Ok(results.expect("at least one branch"))
+    }
 }
 
+/// Wrap the results of each subtask with its sequence, to be routed to the worker forking it;
 struct SubtaskSink<D: Data> {
+    _ph: std::marker::PhantomData<D>,
+}
+
+impl<D: Data> SubtaskSink<D> {
+    fn new(_meta: &OperatorMeta) -> Self {
+        SubtaskSink { _ph: std::marker::PhantomData }
+    }
+}
+
+impl<D: Data> OperatorCore for SubtaskSink<D> {
+    fn on_receive(
+        &mut self, tag: &Tag, inputs: &[Box<dyn InputProxy>], outputs: &[Box<dyn OutputProxy>],
+    ) -> Result<FiredState, JobExecError> {
+        let mut input = new_input_session::<D>(&inputs[0], tag);
+        let mut output = new_output_session::<SubtaskResult<D>>(&outputs[0], tag);
+        let seq = tag.current_uncheck();
+        input.for_each_batch(|dataset| {
+            if !dataset.is_empty() {
+                let data = std::mem::take(dataset.data());
+                output.give(SubtaskResult::new(seq, ResultSet::Data(data)))?;
+            }
+            Ok(())
+        })?;
+        Ok(FiredState::Idle)
+    }
+}
+
+/// Forward the results of the subtasks gathered to the workers forking them, and end each subtask
+/// on the worker forking it once its scope ends there, i.e. once the results of the subtask from
+/// all the workers have arrived; The scope of a subtask ends on every worker once it crosses an
+/// exchange, where only the worker forking it gives its end;
+struct SubtaskEnd<D: Data> {
     scope_depth: usize,
+    index: u32,
+    peers: u32,
     state: StateMap<()>,
     _ph: std::marker::PhantomData<D>,
 }
 
-impl<D: Data> SubtaskSink<D> {
+impl<D: Data> SubtaskEnd<D> {
     fn new(meta: &OperatorMeta) -> Self {
-        SubtaskSink {
+        SubtaskEnd {
             scope_depth: meta.scope_depth,
+            index: meta.worker_id.index,
+            peers: meta.worker_id.peers,
             state: StateMap::new(meta),
             _ph: std::marker::PhantomData,
         }
     }
+
+    #[inline]
+    fn is_forked_here(&self, tag: &Tag) -> bool {
+        tag.current_uncheck() % self.peers == self.index
+    }
 }
 
-impl<D: Data> OperatorCore for SubtaskSink<D> {
+impl<D: Data> OperatorCore for SubtaskEnd<D> {
     fn on_receive(
         &mut self, tag: &Tag, inputs: &[Box<dyn InputProxy>], outputs: &[Box<dyn OutputProxy>],
     ) -> Result<FiredState, JobExecError> {
-        let mut input = new_input_session::<D>(&inputs[0], tag);
+        let mut input = new_input_session::<SubtaskResult<D>>(&inputs[0], tag);
         let mut output = new_output_session::<SubtaskResult<D>>(&outputs[0], tag);
         self.state.entry(tag).or_insert(());
-        let seq = tag.current_uncheck();
         input.for_each_batch(|dataset| {
-            if !dataset.is_empty() {
-                let data = std::mem::replace(dataset.data(), vec![]);
-                output.give(SubtaskResult::new(seq, ResultSet::Data(data)))?;
-            }
+            output.forward(dataset)?;
             Ok(())
         })?;
         Ok(FiredState::Idle)
@@ -104,7 +229,7 @@ impl<D: Data> OperatorCore for SubtaskSink<D> {
     fn on_notify(
         &mut self, n: Notification, outputs: &[Box<dyn OutputProxy>],
     ) -> Result<(), JobExecError> {
-        if n.tag.len() == self.scope_depth {
+        if n.tag.len() == self.scope_depth && self.is_forked_here(&n.tag) {
             self.state.insert(n.tag.clone(), ());
         }
         self.state.notify(&n);
@@ -117,21 +242,79 @@ impl<D: Data> OperatorCore for SubtaskSink<D> {
     }
 }
 
-struct SubtaskJoin<L, R, O, F> {
+/// The parents of the subtasks forked in each scope, in the order they are forked, which the
+/// results of a subtask find by the sequence of the subtask; The parents left in a scope are
+/// taken out once the scope ends on the results, e.g. as their subtask ends are folded into it;
+struct ScopedParents<P> {
     peers: u32,
-    parent_data: HashMap<Tag, Vec<Option<L>>>,
+    scopes: HashMap<Tag, Vec<Option<P>>>,
+}
+
+impl<P> ScopedParents<P> {
+    fn new(meta: &OperatorMeta) -> Self {
+        ScopedParents { peers: meta.worker_id.peers, scopes: HashMap::new() }
+    }
+
+    /// Keep the parents of the scope of the input by `wrap`, and give each result of their
+    /// subtasks to `on_result` along with the sequence and the parent of its subtask, where the
+    /// join is told by `name` in the errors;
+    fn receive<L, R, W, F>(
+        &mut self, name: &str, input: &mut BinaryInput<L, SubtaskResult<R>>, wrap: W,
+        mut on_result: F,
+    ) -> Result<(), JobExecError>
+    where
+        L: Data,
+        R: Data,
+        W: Fn(L) -> P,
+        F: FnMut(u32, &mut Option<P>, ResultSet<R>) -> Result<(), JobExecError>,
+    {
+        input.subscribe_left_notify();
+        input.subscribe_right_notify();
+
+        let peers = self.peers;
+        let parents = self.scopes.entry(input.tag().clone()).or_default();
+        input.left_for_each(|dataset| {
+            parents.extend(dataset.drain(..).map(|item| Some(wrap(item))));
+            Ok(())
+        })?;
+        input.right_for_each(|dataset| {
+            for data in dataset.drain(..) {
+                let seq = data.seq;
+                match parents.get_mut((seq / peers) as usize) {
+                    Some(parent) => on_result(seq, parent, data.take())?,
+                    None => Err(format!("{} subtask={} error: parent lost;", name, seq))?,
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// The parents left in the scope once it ends on the results;
+    fn on_notify(&mut self, n: BinaryNotification) -> Vec<P> {
+        match n {
+            BinaryNotification::Left(t) => {
+                if let Some(parents) = self.scopes.get_mut(&t) {
+                    parents.shrink_to_fit();
+                }
+                vec![]
+            }
+            BinaryNotification::Right(t) => {
+                let parents = self.scopes.remove(&t).unwrap_or_default();
+                parents.into_iter().flatten().collect()
+            }
+        }
+    }
+}
+
+struct SubtaskJoin<L, R, O, F> {
+    parents: ScopedParents<L>,
     func: F,
     _ph: std::marker::PhantomData<(R, O)>,
 }
 
 impl<L, R, O, F> SubtaskJoin<L, R, O, F> {
     pub fn new(meta: &OperatorMeta, func: F) -> Self {
-        SubtaskJoin {
-            peers: meta.worker_id.peers,
-            parent_data: HashMap::new(),
-            func,
-            _ph: std::marker::PhantomData,
-        }
+        SubtaskJoin { parents: ScopedParents::new(meta), func, _ph: std::marker::PhantomData }
     }
 }
 
@@ -147,57 +330,358 @@ where
     fn on_receive(
         &mut self, input: &mut BinaryInput<L, SubtaskResult<R>>, output: &mut Output<O>,
     ) -> Result<(), JobExecError> {
-        input.subscribe_left_notify();
-        input.subscribe_right_notify();
+        let func = &self.func;
+        self.parents.receive(
+            "join",
+            input,
+            |item| item,
+            |seq, parent, result| {
+                let p = match parent.as_ref() {
+                    Some(p) => p,
+                    None => Err(format!("join subtask={} error: internal;", seq))?,
+                };
+                match result {
+                    ResultSet::Data(s_data) => {
+                        for r in s_data {
+                            if let Some(join) = func(p, r) {
+                                output.give(join)?;
+                            }
+                        }
+                    }
+                    ResultSet::ScopeEnd(_) | ResultSet::End => {
+                        parent.take();
+                    }
+                }
+                Ok(())
+            },
+        )
+    }
+
+    fn on_notify(&mut self, n: BinaryNotification) -> Self::NotifyResult {
+        self.parents.on_notify(n);
+        vec![]
+    }
+}
 
-        let mut p = std::mem::replace(&mut self.parent_data, HashMap::new());
-        let parent_data = p.entry(input.tag().clone()).or_insert_with(|| vec![]);
+/// Join each parent with the first result of its subtask once it arrives, or with `None` once its
+/// subtask ends without any result, where the later results of the subtask are ignored;
+struct SubtaskJoinFirst<L, R, O, F> {
+    // the parents waiting for the first results of their subtasks, which are taken once joined
+    parents: ScopedParents<L>,
+    func: F,
+    _ph: std::marker::PhantomData<(R, O)>,
+}
 
-        input.left_for_each(|dataset| {
-            for item in dataset.drain(..) {
-                parent_data.push(Some(item));
-            }
-            Ok(())
-        })?;
+impl<L, R, O, F> SubtaskJoinFirst<L, R, O, F> {
+    pub fn new(meta: &OperatorMeta, func: F) -> Self {
+        SubtaskJoinFirst { parents: ScopedParents::new(meta), func, _ph: std::marker::PhantomData }
+    }
+}
 
-        input.right_for_each(|dataset| {
-            for data in dataset.drain(..) {
-                let offset = (data.seq / self.peers) as usize;
-                if let Some(parent) = parent_data.get_mut(offset) {
-                    if let Some(p) = parent.take() {
-                        match data.take() {
-                            ResultSet::Data(s_data) => {
-                                for r in s_data {
-                                    if let Some(join) = (self.func)(&p, r) {
-                                        output.give(join)?;
-                                    }
+impl<L, R, O, F> BinaryNotify<L, SubtaskResult<R>, O> for SubtaskJoinFirst<L, R, O, F>
+where
+    L: Data,
+    R: Data,
+    O: Data,
+    F: Fn(&L, Option<R>) -> Option<O> + Send + 'static,
+{
+    type NotifyResult = Vec<O>;
+
+    fn on_receive(
+        &mut self, input: &mut BinaryInput<L, SubtaskResult<R>>, output: &mut Output<O>,
+    ) -> Result<(), JobExecError> {
+        let func = &self.func;
+        self.parents.receive(
+            "join first",
+            input,
+            |item| item,
+            |_, parent, result| {
+                let joined = match result {
+                    ResultSet::Data(s_data) => match s_data.into_iter().next() {
+                        Some(first) => parent.take().map(|p| func(&p, Some(first))),
+                        None => None,
+                    },
+                    ResultSet::ScopeEnd(_) => None,
+                    ResultSet::End => parent.take().map(|p| func(&p, None)),
+                };
+                if let Some(Some(join)) = joined {
+                    output.give(join)?;
+                }
+                Ok(())
+            },
+        )
+    }
+
+    fn on_notify(&mut self, n: BinaryNotification) -> Self::NotifyResult {
+        // the parents still waiting are of no result, whose subtask ends are folded into the end
+        // of the parent scope, see `SubtaskSemiJoin`;
+        let parents = self.parents.on_notify(n);
+        let func = &self.func;
+        parents.into_iter().filter_map(|p| func(&p, None)).collect()
+    }
+}
+
+struct SubtaskSemiJoin<L, R> {
+    kind: SemiJoinKind,
+    // the parents waiting for the results of their subtasks, and whether any result is found
+    parents: ScopedParents<(L, bool)>,
+    _ph: std::marker::PhantomData<R>,
+}
+
+impl<L, R> SubtaskSemiJoin<L, R> {
+    pub fn new(meta: &OperatorMeta, kind: SemiJoinKind) -> Self {
+        SubtaskSemiJoin { kind, parents: ScopedParents::new(meta), _ph: std::marker::PhantomData }
+    }
+}
+
+impl<L, R> BinaryNotify<L, SubtaskResult<R>, L> for SubtaskSemiJoin<L, R>
+where
+    L: Data,
+    R: Data,
+{
+    type NotifyResult = Vec<L>;
+
+    fn on_receive(
+        &mut self, input: &mut BinaryInput<L, SubtaskResult<R>>, output: &mut Output<L>,
+    ) -> Result<(), JobExecError> {
+        let kind = self.kind;
+        self.parents.receive(
+            "semi join",
+            input,
+            |item| (item, false),
+            |_, parent, result| {
+                match result {
+                    ResultSet::Data(s_data) => {
+                        if let Some((p, found)) = parent.as_mut() {
+                            if !*found && !s_data.is_empty() {
+                                *found = true;
+                                // the parent qualifies once any result is found;
+                                if kind == SemiJoinKind::AnyExists {
+                                    output.give(p.clone())?;
                                 }
-                                parent.replace(p);
                             }
-                            ResultSet::End => (),
                         }
-                    } else {
-                        Err(format!("join subtask={} error: internal;", data.seq))?;
                     }
-                } else {
-                    Err(format!("join subtask={} error: parent lost;", data.seq))?;
+                    ResultSet::ScopeEnd(_) => (),
+                    ResultSet::End => {
+                        if let Some((p, found)) = parent.take() {
+                            if !found && kind == SemiJoinKind::NoneExists {
+                                output.give(p)?;
+                            }
+                        }
+                    }
                 }
-            }
-            Ok(())
-        })?;
-        self.parent_data = p;
-        Ok(())
+                Ok(())
+            },
+        )
     }
 
     fn on_notify(&mut self, n: BinaryNotification) -> Self::NotifyResult {
-        match n {
-            BinaryNotification::Left(t) => {
-                self.parent_data.get_mut(&t).map(|p| p.shrink_to_fit());
-            }
-            BinaryNotification::Right(t) => {
-                self.parent_data.remove(&t);
+        // the subtasks of no result whose ends are folded into the end of the parent scope, e.g.
+        // as they end along with it, are never ended one by one;
+        let parents = self.parents.on_notify(n);
+        if self.kind == SemiJoinKind::NoneExists {
+            parents.into_iter().filter(|(_, found)| !found).map(|(p, _)| p).collect()
+        } else {
+            vec![]
+        }
+    }
+}
+
+/// Split the results of the subtasks from the parents whose subtasks output no result, as
+/// `(Some(result), None)` and `(None, Some(parent))` respectively, for the next branch of
+/// `coalesce_subtasks` to fork the parents of no result only, or to pass them through by
+/// `optional_subtask`;
+struct SubtaskSplit<L, R> {
+    // the parents waiting for the results of their subtasks, and whether any result is found
+    parents: ScopedParents<(L, bool)>,
+    _ph: std::marker::PhantomData<R>,
+}
+
+impl<L, R> SubtaskSplit<L, R> {
+    pub fn new(meta: &OperatorMeta) -> Self {
+        SubtaskSplit { parents: ScopedParents::new(meta), _ph: std::marker::PhantomData }
+    }
+}
+
+impl<L, R> BinaryNotify<L, SubtaskResult<R>, (Option<R>, Option<L>)> for SubtaskSplit<L, R>
+where
+    L: Data,
+    R: Data,
+{
+    type NotifyResult = Vec<(Option<R>, Option<L>)>;
+
+    fn on_receive(
+        &mut self, input: &mut BinaryInput<L, SubtaskResult<R>>,
+        output: &mut Output<(Option<R>, Option<L>)>,
+    ) -> Result<(), JobExecError> {
+        self.parents.receive(
+            "split",
+            input,
+            |item| (item, false),
+            |_, parent, result| {
+                match result {
+                    ResultSet::Data(s_data) => {
+                        if let Some((_, found)) = parent.as_mut() {
+                            *found |= !s_data.is_empty();
+                        }
+                        for r in s_data {
+                            output.give((Some(r), None))?;
+                        }
+                    }
+                    ResultSet::ScopeEnd(_) => (),
+                    ResultSet::End => {
+                        if let Some((p, found)) = parent.take() {
+                            if !found {
+                                output.give((None, Some(p)))?;
+                            }
+                        }
+                    }
+                }
+                Ok(())
+            },
+        )
+    }
+
+    fn on_notify(&mut self, n: BinaryNotification) -> Self::NotifyResult {
+        // the parents of no result whose subtask ends are folded into the end of the parent
+        // scope, see `SubtaskSemiJoin`;
+        let parents = self.parents.on_notify(n);
+        parents.into_iter().filter(|(_, found)| !found).map(|(p, _)| (None, Some(p))).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::operator::binary::BinaryNotifyOperator;
+    use crate::operator::harness::{HarnessBuilder, MockInput, MockOutput, OperatorHarness};
+
+    type Joined = (u32, u32);
+
+    struct JoinHarness {
+        harness: OperatorHarness,
+        parents: MockInput<u32>,
+        results: MockInput<SubtaskResult<u32>>,
+        output: MockOutput<Joined>,
+    }
+
+    impl JoinHarness {
+        fn new() -> Self {
+            let mut builder = HarnessBuilder::new("join_subtask", 1);
+            let parents = builder.input::<u32>();
+            let results = builder.input::<SubtaskResult<u32>>();
+            let output = builder.output::<Joined>();
+            let harness = builder.build(|meta| {
+                meta.enable_notify();
+                let join =
+                    SubtaskJoin::<u32, u32, Joined, _>::new(meta, |p: &u32, r| Some((*p, r)));
+                Box::new(BinaryNotifyOperator::<u32, SubtaskResult<u32>, Joined, _>::new(
+                    meta, join,
+                ))
+            });
+            JoinHarness { harness, parents, results, output }
+        }
+
+        /// The data joined so far along with their scopes, sorted as the join gives no order;
+        fn take_joined(&mut self) -> Vec<(Tag, Joined)> {
+            let mut joined = vec![];
+            for (tag, data) in self.output.take() {
+                joined.extend(data.into_iter().map(|d| (tag.clone(), d)));
             }
+            joined.sort_by_key(|a| a.1);
+            joined
         }
-        vec![]
+    }
+
+    fn result(seq: u32, data: Vec<u32>) -> SubtaskResult<u32> {
+        SubtaskResult::new(seq, ResultSet::Data(data))
+    }
+
+    #[test]
+    fn join_results_after_parents_test() {
+        let mut join = JoinHarness::new();
+        join.parents.give(Tag::new(0), vec![10, 20]);
+        join.harness.step().unwrap();
+        assert!(join.take_joined().is_empty());
+
+        join.results.give(Tag::new(0), vec![result(1, vec![7]), result(0, vec![5, 6])]);
+        join.harness.step().unwrap();
+        let tag = Tag::new(0);
+        let expected = vec![(tag.clone(), (10, 5)), (tag.clone(), (10, 6)), (tag, (20, 7))];
+        assert_eq!(join.take_joined(), expected);
+
+        // the scope is held open until the results of its subtasks end;
+        join.parents.end(Tag::new(0));
+        join.harness.step().unwrap();
+        assert!(join.output.take_ends().is_empty());
+        join.results.end(Tag::new(0));
+        join.harness.step().unwrap();
+        assert_eq!(join.output.take_ends(), vec![Tag::new(0)]);
+    }
+
+    #[test]
+    fn join_end_before_results_test() {
+        let mut join = JoinHarness::new();
+        join.parents.give(Tag::new(0), vec![10]);
+        join.parents.end(Tag::new(0));
+        // the end of the results arrives while the last of them is still in flight;
+        join.results.announce(Tag::new(0), 1);
+        join.results.end(Tag::new(0));
+        join.harness.step().unwrap();
+        assert!(join.take_joined().is_empty());
+        assert!(join.output.take_ends().is_empty());
+
+        join.results.deliver(Tag::new(0), vec![result(0, vec![5])]);
+        join.harness.step().unwrap();
+        assert_eq!(join.take_joined(), vec![(Tag::new(0), (10, 5))]);
+        assert_eq!(join.output.take_ends(), vec![Tag::new(0)]);
+    }
+
+    #[test]
+    fn join_interleaved_scopes_test() {
+        let mut join = JoinHarness::new();
+        join.parents.give(Tag::new(0), vec![10]);
+        join.parents.give(Tag::new(1), vec![20]);
+        join.results.give(Tag::new(1), vec![result(0, vec![6])]);
+        join.parents.end(Tag::new(1));
+        join.results.end(Tag::new(1));
+        join.harness.step().unwrap();
+        assert_eq!(join.take_joined(), vec![(Tag::new(1), (20, 6))]);
+        assert_eq!(join.output.take_ends(), vec![Tag::new(1)]);
+
+        join.results.give(Tag::new(0), vec![result(0, vec![5])]);
+        join.parents.end(Tag::new(0));
+        join.results.end(Tag::new(0));
+        join.harness.step().unwrap();
+        assert_eq!(join.take_joined(), vec![(Tag::new(0), (10, 5))]);
+        assert_eq!(join.output.take_ends(), vec![Tag::new(0)]);
+    }
+
+    #[test]
+    fn join_empty_scope_test() {
+        let mut join = JoinHarness::new();
+        // the subtasks of the parents give no result;
+        join.parents.give(Tag::new(0), vec![10]);
+        join.parents.end(Tag::new(0));
+        join.results.end(Tag::new(0));
+        join.harness.step().unwrap();
+        assert!(join.take_joined().is_empty());
+        assert_eq!(join.output.take_ends(), vec![Tag::new(0)]);
+
+        // the scope of no parent at all;
+        join.parents.end(Tag::new(1));
+        join.results.end(Tag::new(1));
+        join.harness.step().unwrap();
+        assert!(join.take_joined().is_empty());
+        assert_eq!(join.output.take_ends(), vec![Tag::new(1)]);
+    }
+
+    #[test]
+    fn join_result_before_parent_test() {
+        let mut join = JoinHarness::new();
+        join.results.give(Tag::new(0), vec![result(0, vec![5])]);
+        let err = join.harness.step().expect_err("the parent of the result is not arrived");
+        assert!(err.to_string().contains("parent lost"), "{}", err);
     }
 }
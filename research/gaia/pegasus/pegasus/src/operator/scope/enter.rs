@@ -16,7 +16,7 @@
 use crate::api::meta::{OperatorKind, OperatorMeta};
 use crate::api::notify::Notification;
 use crate::api::scope::enter::{CURRENT_SCOPE, EXTRA_COMPLETES};
-use crate::api::{EnterScope, ScopeInput, ScopeInputEmitter};
+use crate::api::{EnterScope, ScopeInput, ScopeInputEmitter, ScopePartition};
 use crate::communication::input::{new_input_session, InputProxy};
 use crate::communication::output::{new_output_session, OutputDelta, OutputProxy};
 use crate::communication::Pipeline;
@@ -144,7 +144,7 @@ impl<D: Data> EnterScope<D> for Stream<D> {
                 meta.set_output_delta(OutputDelta::ToChild);
                 Box::new(default_enter::<D>())
             })?
-            .enter_scope())
+            .into_child_scope())
     }
 
     fn dyn_enter<B, F>(&self, builder: B) -> Result<Stream<D>, BuildJobError>
@@ -160,7 +160,49 @@ impl<D: Data> EnterScope<D> for Stream<D> {
                 let func = builder(meta);
                 Box::new(EnterScopeOperator::<D, F>::new(Some(func)))
             })?
-            .enter_scope())
+            .into_child_scope())
+    }
+
+    fn enter_scope<F>(&self, func: F) -> Result<Stream<D>, BuildJobError>
+    where
+        F: FnMut(&D) -> Option<ScopePartition> + Send + Clone + 'static,
+    {
+        Ok(self
+            .concat("enter_scope", Pipeline, |meta| {
+                meta.set_kind(OperatorKind::Map);
+                meta.set_output_delta(OutputDelta::ToChild);
+                meta.enable_notify();
+                meta.set_scope_entry();
+                Box::new(EnterScopeOperator::new(Some(PartitionEmitter::<D, F>::new(func))))
+            })?
+            .into_custom_scope())
+    }
+}
+
+struct PartitionEmitter<D, F> {
+    func: F,
+    _ph: std::marker::PhantomData<D>,
+}
+
+impl<D, F> PartitionEmitter<D, F> {
+    fn new(func: F) -> Self {
+        PartitionEmitter { func, _ph: std::marker::PhantomData }
+    }
+}
+
+impl<D, F: Clone> Clone for PartitionEmitter<D, F> {
+    fn clone(&self) -> Self {
+        PartitionEmitter { func: self.func.clone(), _ph: std::marker::PhantomData }
+    }
+}
+
+impl<D, F> ScopeInputEmitter<D> for PartitionEmitter<D, F>
+where
+    D: Data,
+    F: FnMut(&D) -> Option<ScopePartition> + Send + Clone + 'static,
+{
+    fn get_scope(&mut self, data: D) -> Option<ScopeInput<D>> {
+        (self.func)(&data).map(|partition| ScopeInput::new(partition.id, partition.closes, data))
     }
 }
 
@@ -53,12 +53,14 @@ impl<D: Data> LeaveScope<D> for Stream<D> {
         if self.scope_depth == 0 {
             return BuildJobError::unsupported("can't leave root scope;");
         }
+        // the tags of the output are cut back to the depth of the parent scope;
+        let parent_depth = self.scope_depth as u32 - 1;
         let after_leave = self.concat("leave", Pipeline, |meta| {
-            meta.set_output_delta(OutputDelta::ToParent(1));
+            meta.set_output_delta(OutputDelta::ToParent(parent_depth));
             meta.set_kind(OperatorKind::Map);
             Box::new(LeaveOperator::<D>::new())
         })?;
-        Ok(after_leave.leave_scope())
+        Ok(after_leave.into_parent_scope())
     }
 
     fn owned_leave(self) -> Result<Stream<D>, BuildJobError> {
@@ -70,7 +72,7 @@ impl<D: Data> LeaveScope<D> for Stream<D> {
         if output.output_size() == 0 {
             if output.leave() {
                 let after_leave = Stream::inherit(&self, output);
-                Ok(after_leave.leave_scope())
+                Ok(after_leave.into_parent_scope())
             } else {
                 BuildJobError::unsupported("can't leave root scope;")
             }
@@ -80,4 +82,14 @@ impl<D: Data> LeaveScope<D> for Stream<D> {
             )
         }
     }
+
+    fn leave_scope(&self) -> Result<Stream<D>, BuildJobError> {
+        if !self.in_custom_scope() {
+            return BuildJobError::unsupported(
+                "can't leave the scope not entered by enter_scope, which is left by the operator \
+                 entering it;",
+            );
+        }
+        self.leave()
+    }
 }
@@ -15,3 +15,4 @@
 
 mod enter;
 mod leave;
+mod window;
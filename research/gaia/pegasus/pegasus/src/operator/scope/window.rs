@@ -0,0 +1,264 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use crate::api::function::{FnResult, MultiRouteFunction, RouteFunction};
+use crate::api::meta::OperatorKind;
+use crate::api::notify::Notification;
+use crate::api::{Map, Stamped, Trigger, Window, WindowAssigner};
+use crate::codec::{Decode, Encode, ReadExt, WriteExt};
+use crate::communication::input::{new_input_session, InputProxy};
+use crate::communication::output::{new_output_session, OutputDelta, OutputProxy};
+use crate::communication::{Channel, Pipeline};
+use crate::errors::{BuildJobError, IOResult, JobExecError};
+use crate::operator::{FiredState, OperatorCore};
+use crate::stream::Stream;
+use crate::{Data, Tag};
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+
+/// The stamped records into the windows, where each watermark carries the index of the worker
+/// producing it, so that the windows track the watermark of each producer on its own;
+#[derive(Clone, Debug)]
+enum Envelope<D> {
+    Record(u64, D),
+    Watermark(u32, u64),
+}
+
+impl<D> Envelope<D> {
+    fn from_stamped(producer: u32, stamped: Stamped<D>) -> Self {
+        match stamped {
+            Stamped::Record(ts, data) => Envelope::Record(ts, data),
+            Stamped::Watermark(ts) => Envelope::Watermark(producer, ts),
+        }
+    }
+}
+
+impl<D: Encode> Encode for Envelope<D> {
+    fn write_to<W: WriteExt>(&self, writer: &mut W) -> io::Result<()> {
+        match self {
+            Envelope::Record(ts, data) => {
+                writer.write_u8(0)?;
+                writer.write_u64(*ts)?;
+                data.write_to(writer)
+            }
+            Envelope::Watermark(producer, ts) => {
+                writer.write_u8(1)?;
+                writer.write_u32(*producer)?;
+                writer.write_u64(*ts)
+            }
+        }
+    }
+}
+
+impl<D: Decode> Decode for Envelope<D> {
+    fn read_from<R: ReadExt>(reader: &mut R) -> io::Result<Self> {
+        match reader.read_u8()? {
+            0 => {
+                let ts = reader.read_u64()?;
+                Ok(Envelope::Record(ts, D::read_from(reader)?))
+            }
+            1 => {
+                let producer = reader.read_u32()?;
+                Ok(Envelope::Watermark(producer, reader.read_u64()?))
+            }
+            _ => Err(io::Error::other("unreachable")),
+        }
+    }
+}
+
+/// Route the records by `route`, and broadcast the watermarks to all the workers;
+struct EnvelopeRoute<D, R> {
+    route: R,
+    peers: Vec<u64>,
+    _ph: std::marker::PhantomData<D>,
+}
+
+impl<D, R> EnvelopeRoute<D, R> {
+    fn new(route: R, len: u32) -> Self {
+        let peers = (0..len as u64).collect();
+        EnvelopeRoute { route, peers, _ph: std::marker::PhantomData }
+    }
+}
+
+impl<D: Data, R: RouteFunction<D>> MultiRouteFunction<Envelope<D>> for EnvelopeRoute<D, R> {
+    fn route(&self, data: &Envelope<D>) -> FnResult<&[u64]> {
+        Ok(match data {
+            Envelope::Record(_, data) => {
+                let index = (self.route.route(data)? % self.peers.len() as u64) as usize;
+                &self.peers[index..index + 1]
+            }
+            Envelope::Watermark(..) => &self.peers[..],
+        })
+    }
+}
+
+/// The windows of a scope of the input on current worker;
+struct WindowState<D> {
+    // the last watermark of each producer, of which the least is that of the windows;
+    watermarks: Vec<u64>,
+    watermark: u64,
+    // the records of the open windows by their ends and ids, i.e. in the order they fire;
+    open: BTreeMap<(u64, u32), Vec<D>>,
+    late: u64,
+}
+
+impl<D> WindowState<D> {
+    fn new(producers: usize) -> Self {
+        WindowState { watermarks: vec![0; producers], watermark: 0, open: BTreeMap::new(), late: 0 }
+    }
+
+    /// Update the watermark of the producer, and give the watermark of the windows if it goes
+    /// forward with the update;
+    fn advance(&mut self, producer: u32, ts: u64) -> Option<u64> {
+        let slot = producer as usize % self.watermarks.len();
+        if ts <= self.watermarks[slot] {
+            return None;
+        }
+        self.watermarks[slot] = ts;
+        let least = self.watermarks.iter().copied().min().unwrap_or(ts);
+        if least > self.watermark {
+            self.watermark = least;
+            Some(least)
+        } else {
+            None
+        }
+    }
+}
+
+struct WindowOperator<D, A> {
+    assigner: A,
+    trigger: Trigger,
+    producers: usize,
+    states: HashMap<Tag, WindowState<D>>,
+}
+
+/// Emit the records of the window into its scope by the first output, and close the scope;
+fn emit_window<D: Data>(
+    tag: &Tag, id: u32, mut records: Vec<D>, outputs: &[Box<dyn OutputProxy>],
+) -> IOResult<()> {
+    let output = &outputs[0];
+    let mut session = new_output_session::<D>(output, tag);
+    session.advance(id)?;
+    session.give_batch(&mut records)?;
+    let window = session.tag.clone();
+    std::mem::drop(session);
+    output.scope_end(window);
+    Ok(())
+}
+
+impl<D: Data, A: WindowAssigner> OperatorCore for WindowOperator<D, A> {
+    fn on_receive(
+        &mut self, tag: &Tag, inputs: &[Box<dyn InputProxy>], outputs: &[Box<dyn OutputProxy>],
+    ) -> Result<FiredState, JobExecError> {
+        let mut input = new_input_session::<Envelope<D>>(&inputs[0], tag);
+        let WindowOperator { assigner, trigger, producers, states } = self;
+        let state = states.entry(tag.clone()).or_insert_with(|| WindowState::new(*producers));
+        input.for_each_batch(|dataset| {
+            for item in dataset.drain(..) {
+                match item {
+                    Envelope::Record(ts, data) => {
+                        for window in assigner.assign(ts) {
+                            if trigger.fires(window.end, state.watermark) {
+                                state.late += 1;
+                            } else {
+                                let entry = state.open.entry((window.end, window.id));
+                                entry.or_insert_with(Vec::new).push(data.clone());
+                            }
+                        }
+                    }
+                    Envelope::Watermark(producer, ts) => {
+                        if let Some(watermark) = state.advance(producer, ts) {
+                            while let Some((end, id)) = state.open.keys().next().copied() {
+                                if !trigger.fires(end, watermark) {
+                                    break;
+                                }
+                                let records = state.open.remove(&(end, id)).unwrap_or_default();
+                                emit_window(tag, id, records, outputs)?;
+                            }
+                        }
+                    }
+                }
+            }
+            Ok(())
+        })?;
+        Ok(FiredState::Idle)
+    }
+
+    fn on_notify(
+        &mut self, n: Notification, outputs: &[Box<dyn OutputProxy>],
+    ) -> Result<(), JobExecError> {
+        let ended: Vec<Tag> = self
+            .states
+            .keys()
+            .filter(|tag| &n.tag == *tag || n.tag.is_parent_of(tag))
+            .cloned()
+            .collect();
+        for tag in ended {
+            if let Some(state) = self.states.remove(&tag) {
+                if state.late > 0 {
+                    info_worker!("window dropped {} late records in scope {:?};", state.late, tag);
+                }
+                for ((_, id), records) in state.open {
+                    emit_window(&tag, id, records, outputs)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Bucket the envelopes from the channel into the windows, watching the watermarks of
+/// `producers` producers;
+fn window_envelopes<D: Data, A: WindowAssigner, C: Into<Channel<Envelope<D>>>>(
+    stream: Stream<Envelope<D>>, channel: C, producers: usize, assigner: A, trigger: Trigger,
+) -> Result<Stream<D>, BuildJobError> {
+    Ok(stream
+        .concat("window", channel, |meta| {
+            meta.set_kind(OperatorKind::Map);
+            meta.set_output_delta(OutputDelta::ToChild);
+            meta.enable_notify();
+            meta.set_scope_entry();
+            Box::new(WindowOperator::<D, A> {
+                assigner,
+                trigger,
+                producers,
+                states: HashMap::new(),
+            })
+        })?
+        .into_custom_scope())
+}
+
+impl<D: Data> Window<D> for Stream<Stamped<D>> {
+    fn window<A: WindowAssigner>(
+        &self, assigner: A, trigger: Trigger,
+    ) -> Result<Stream<D>, BuildJobError> {
+        let producer = self.index();
+        let stream =
+            self.map_with_fn(Pipeline, move |item| Ok(Envelope::from_stamped(producer, item)))?;
+        window_envelopes(stream, Pipeline, 1, assigner, trigger)
+    }
+
+    fn window_by<A: WindowAssigner, R: RouteFunction<D>>(
+        &self, route: R, assigner: A, trigger: Trigger,
+    ) -> Result<Stream<D>, BuildJobError> {
+        let producer = self.index();
+        let peers = self.peers();
+        let stream =
+            self.map_with_fn(Pipeline, move |item| Ok(Envelope::from_stamped(producer, item)))?;
+        let route =
+            Box::new(EnvelopeRoute::new(route, peers)) as Box<dyn MultiRouteFunction<Envelope<D>>>;
+        window_envelopes(stream, route, peers as usize, assigner, trigger)
+    }
+}
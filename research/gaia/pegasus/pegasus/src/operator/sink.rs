@@ -71,7 +71,15 @@ where
         }
         self.state.notify(&n);
         for (t, _) in self.state.extract_notified().drain(..) {
-            (self.func)(&t, ResultSet::End)
+            if t.is_root() {
+                (self.func)(&t, ResultSet::End)
+            } else {
+                (self.func)(&t, ResultSet::ScopeEnd(t.clone()))
+            }
+        }
+        // the sink inside scopes also tells when the whole stream ends;
+        if n.tag.is_root() && self.scope_depth > 0 {
+            (self.func)(&n.tag, ResultSet::End)
         }
         Ok(())
     }
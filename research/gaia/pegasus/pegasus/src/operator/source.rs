@@ -14,25 +14,23 @@
 //! limitations under the License.
 
 use crate::api::meta::{OperatorKind, ScopePrior};
-use crate::api::{ExternSource, IntoStream};
+use crate::api::{DataSource, ExternSource, IntoStream, SourceConfig};
 use crate::communication::input::InputProxy;
 use crate::communication::output::{new_output_session, OutputProxy};
 use crate::dataflow::DataflowBuilder;
 use crate::errors::{BuildJobError, IOError, JobExecError};
 use crate::operator::{FiredState, OperatorCore};
+use crate::progress::SourceTracker;
+use crate::schedule::slice;
 use crate::stream::Stream;
-use crate::{Data, Tag};
+use crate::{Data, LatencyMode, Tag};
 use std::iter::FusedIterator;
+use std::time::Instant;
 
 struct SourceOperator<D, E: ExternSource<Item = D>> {
     src: E,
     is_exhaust: bool,
-}
-
-impl<D, E: ExternSource<Item = D>> SourceOperator<D, E> {
-    pub fn new(src: E) -> Self {
-        SourceOperator { src, is_exhaust: false }
-    }
+    progress: SourceTracker,
 }
 
 impl<D: Data, E: ExternSource<Item = D>> OperatorCore for SourceOperator<D, E> {
@@ -49,7 +47,15 @@ impl<D: Data, E: ExternSource<Item = D>> OperatorCore for SourceOperator<D, E> {
         let mut session = new_output_session::<D>(&outputs[0], active);
         loop {
             match self.src.pull_next() {
-                Ok(Some(data)) => session.give(data)?,
+                Ok(Some(data)) => {
+                    session.give(data)?;
+                    slice::on_records(1);
+                    self.progress.on_produced(1);
+                    // yield to let the flushed data go downstream, or the other operators go
+                    if session.has_flushed_by_interval() || slice::is_exhausted() {
+                        break;
+                    }
+                }
                 Ok(None) => break,
                 Err(err) => {
                     if err.is_source_exhaust() {
@@ -65,23 +71,126 @@ impl<D: Data, E: ExternSource<Item = D>> OperatorCore for SourceOperator<D, E> {
         if self.is_exhaust {
             std::mem::drop(session);
             outputs[0].scope_end(active.clone());
+            self.progress.on_exhausted();
             info_worker!("source has been exhausted;");
             Ok(FiredState::Idle)
         } else {
+            self.progress.flush();
             Ok(FiredState::Active)
         }
     }
 }
 
+/// Pull the records from a `DataSource` in batches, one for each unit of the output capacity, so
+/// it goes no faster than the consumers take the batches, nor than the rate limit if any;
+struct PullSourceOperator<S> {
+    src: S,
+    batch_size: usize,
+    // whether to yield after each batch, i.e. each record in the eager mode, see `LatencyMode`
+    eager: bool,
+    limiter: Option<RateLimiter>,
+    progress: SourceTracker,
+}
+
+impl<D: Data, S: DataSource<Item = D>> OperatorCore for PullSourceOperator<S> {
+    fn on_receive(
+        &mut self, _: &Tag, _: &[Box<dyn InputProxy>], _: &[Box<dyn OutputProxy>],
+    ) -> Result<FiredState, JobExecError> {
+        unreachable!("source operator on receive");
+    }
+
+    fn on_active(
+        &mut self, active: &Tag, outputs: &[Box<dyn OutputProxy>],
+    ) -> Result<FiredState, JobExecError> {
+        assert!(active.is_root());
+        let mut session = new_output_session::<D>(&outputs[0], active);
+        let mut is_exhaust = false;
+        while session.has_capacity() && !slice::is_exhausted() {
+            let max = match self.limiter.as_ref() {
+                Some(limiter) => limiter.permits(self.batch_size),
+                None => self.batch_size,
+            };
+            if max == 0 {
+                break;
+            }
+            match self.src.next_batch(max)? {
+                Some(mut batch) => {
+                    if batch.is_empty() {
+                        // nothing available for now;
+                        break;
+                    }
+                    if let Some(limiter) = self.limiter.as_mut() {
+                        limiter.grant(batch.len());
+                    }
+                    slice::on_records(batch.len());
+                    self.progress.on_produced(batch.len());
+                    session.give_batch(&mut batch)?;
+                    if self.eager {
+                        break;
+                    }
+                }
+                None => {
+                    is_exhaust = true;
+                    break;
+                }
+            }
+        }
+
+        if is_exhaust {
+            std::mem::drop(session);
+            outputs[0].scope_end(active.clone());
+            self.progress.on_exhausted();
+            info_worker!("source has been exhausted;");
+            Ok(FiredState::Idle)
+        } else {
+            self.progress.flush();
+            Ok(FiredState::Active)
+        }
+    }
+}
+
+/// Limit the records pulled to a rate since the first pull;
+struct RateLimiter {
+    records_per_second: u64,
+    start: Option<Instant>,
+    granted: u64,
+}
+
+impl RateLimiter {
+    fn new(records_per_second: u64) -> Self {
+        RateLimiter { records_per_second, start: None, granted: 0 }
+    }
+
+    /// The most records allowed to be pulled now, up to `max`;
+    fn permits(&self, max: usize) -> usize {
+        let allowed = match self.start {
+            // the first batch is pulled at once;
+            None => return max.min(self.records_per_second as usize).max(1),
+            Some(start) => {
+                let elapsed = start.elapsed().as_micros();
+                (elapsed * self.records_per_second as u128 / 1_000_000) as u64
+            }
+        };
+        allowed.saturating_sub(self.granted).min(max as u64) as usize
+    }
+
+    fn grant(&mut self, records: usize) {
+        if self.start.is_none() {
+            self.start = Some(Instant::now());
+        }
+        self.granted += records as u64;
+    }
+}
+
 impl<E: ExternSource + 'static> IntoStream<E::Item> for E
 where
     E::Item: Data,
 {
     fn into_stream(self, dfb: &DataflowBuilder) -> Result<Stream<E::Item>, BuildJobError> {
-        let src = SourceOperator::new(self);
         let mut op = dfb.construct_operator("source", 0, ScopePrior::None, move |meta| {
             meta.set_kind(OperatorKind::Source);
-            Box::new(src)
+            let progress = SourceTracker::new(meta, self.expected_size());
+            Box::new(SourceOperator { src: self, is_exhaust: false, progress })
         });
         let output = op.new_output::<E::Item>();
         Ok(Stream::new(output, dfb))
@@ -95,10 +204,61 @@ impl DataflowBuilder {
     where
         <I as Iterator>::Item: Data,
     {
-        let source = WrapIterator::new(iter);
+        // the size is only expected if the iterator tells it exactly
+        let expected = match iter.size_hint() {
+            (lower, Some(upper)) if lower == upper => Some(upper as u64),
+            _ => None,
+        };
+        self.input_from_iter_sized(iter, expected)
+    }
+
+    /// Read the input of the job from the iterator as `input_from_iter`, which expects to produce
+    /// the records of the size on current worker, e.g. by the metadata of the partition the
+    /// iterator scans, to tell the progress of the job, see `pegasus::peek_progress`;
+    pub fn input_from_iter_sized<I: FusedIterator + Send + 'static>(
+        &self, iter: I, expected: Option<u64>,
+    ) -> Result<Stream<<I as Iterator>::Item>, BuildJobError>
+    where
+        <I as Iterator>::Item: Data,
+    {
+        let source = WrapIterator { iter, expected, _ph: std::marker::PhantomData };
         source.into_stream(self)
     }
 
+    /// Read the input of the job from the source, which is pulled on demand by the output capacity
+    /// of the source operator, and ends once the source returns `None`;
+    pub fn input_from_source<S: DataSource + 'static>(
+        &self, src: S,
+    ) -> Result<Stream<S::Item>, BuildJobError>
+    where
+        S::Item: Data,
+    {
+        self.input_from_source_with(src, SourceConfig::default())
+    }
+
+    /// Read the input of the job from the source as `input_from_source`, limited by the config;
+    pub fn input_from_source_with<S: DataSource + 'static>(
+        &self, src: S, config: SourceConfig,
+    ) -> Result<Stream<S::Item>, BuildJobError>
+    where
+        S::Item: Data,
+    {
+        let limiter = if config.records_per_second > 0 {
+            Some(RateLimiter::new(config.records_per_second))
+        } else {
+            None
+        };
+        let batch_size = self.config.effective_batch_size().max(1) as usize;
+        let eager = self.config.latency_mode == LatencyMode::Eager;
+        let mut op = self.construct_operator("source", 0, ScopePrior::None, move |meta| {
+            meta.set_kind(OperatorKind::Source);
+            let progress = SourceTracker::new(meta, src.expected_size());
+            Box::new(PullSourceOperator { src, batch_size, eager, limiter, progress })
+        });
+        let output = op.new_output::<S::Item>();
+        Ok(Stream::new(output, self))
+    }
+
     pub fn input_from<E: ExternSource + 'static>(
         &self, extern_src: E,
     ) -> Result<Stream<E::Item>, BuildJobError>
@@ -111,6 +271,7 @@ impl DataflowBuilder {
 
 struct WrapIterator<D, I: FusedIterator<Item = D>> {
     iter: I,
+    expected: Option<u64>,
     _ph: std::marker::PhantomData<D>,
 }
 
@@ -123,10 +284,8 @@ impl<D: Send, I: FusedIterator<Item = D> + Send> ExternSource for WrapIterator<D
             None => Err(IOError::source_exhaust()),
         }
     }
-}
 
-impl<D, I: FusedIterator<Item = D>> WrapIterator<D, I> {
-    pub fn new(iter: I) -> Self {
-        WrapIterator { iter, _ph: std::marker::PhantomData }
+    fn expected_size(&self) -> Option<u64> {
+        self.expected
     }
 }
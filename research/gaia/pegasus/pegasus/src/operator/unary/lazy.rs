@@ -157,6 +157,9 @@ impl<I: Data> LazyUnary<I> for Stream<I> {
         self.concat(name, channel, |meta| {
             meta.set_kind(OperatorKind::Expand);
             let func = construct(meta);
+            if let (None, Some(fanout)) = (meta.fanout, func.fanout()) {
+                meta.set_fanout(fanout);
+            }
             Box::new(LazyUnaryOperator::new(func))
         })
     }
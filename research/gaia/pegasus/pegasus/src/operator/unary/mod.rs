@@ -99,8 +99,10 @@ where
     }
 }
 
-struct UnaryStateOperator<I, O, S: State, F> {
+pub(crate) struct UnaryStateOperator<I, O, S: State, F> {
     name: String,
+    // the memory limit of the job in bytes, if any;
+    mem_limit: Option<usize>,
     func: F,
     state: StateMap<OperatorState<S>>,
     _ph: std::marker::PhantomData<(I, O)>,
@@ -109,7 +111,34 @@ struct UnaryStateOperator<I, O, S: State, F> {
 impl<I, O, S: State, F> UnaryStateOperator<I, O, S, F> {
     pub fn new(meta: &OperatorMeta, func: F) -> Self {
         let name = format!("{}_{}", meta.name, meta.index);
-        UnaryStateOperator { name, func, state: StateMap::new(meta), _ph: std::marker::PhantomData }
+        let mem_limit = if meta.mem_limit > 0 && meta.mem_limit != !0u32 {
+            Some((meta.mem_limit as usize) << 20)
+        } else {
+            None
+        };
+        UnaryStateOperator {
+            name,
+            mem_limit,
+            func,
+            state: StateMap::new(meta),
+            _ph: std::marker::PhantomData,
+        }
+    }
+
+    /// Fail the job rather than keep growing the states once the job runs out of its memory;
+    fn check_memory(&self, tag: &Tag) -> Result<(), JobExecError> {
+        if let Some(limit) = self.mem_limit {
+            if let Some(used) = pegasus_memory::alloc::check_current_task_memory() {
+                if used >= limit {
+                    let msg = format!(
+                        "{} holds the state of scope {:?} over the memory limit: {}/{} bytes",
+                        self.name, tag, used, limit
+                    );
+                    return Err(JobExecError::from(msg));
+                }
+            }
+        }
+        Ok(())
     }
 }
 
@@ -131,6 +160,7 @@ where
             debug_worker!("trigger cancel on scope {:?} by operator {:?}", tag, self.name);
             input.cancel_scope();
         }
+        self.check_memory(tag)?;
         Ok(FiredState::Idle)
     }
 
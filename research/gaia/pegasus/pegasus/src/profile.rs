@@ -0,0 +1,327 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The profiles of the jobs with `JobConf::profile`, which tell the wall time each operator is
+//! fired and the records it receives on each worker, aggregated across the workers of the job in
+//! current server into the min, max and average of each operator. Each operator reports what it
+//! measures once it is dropped, so the profile of a job is complete once all its workers end, to
+//! be fetched by `fetch_job_profile`. The profiles of at most `MAX_PROFILED_JOBS` jobs are kept,
+//! where those of the earliest jobs are dropped first.
+//!
+//! The records are counted into a thread local counter by the inputs, which is read before and
+//! after each firing of an operator, as the operators of a worker are fired one by one. So are the
+//! bytes of the batches an operator sends to the workers on other servers, whose encoded sizes
+//! are measured for the profiled jobs only.
+//!
+//! The profile also tells the alternatives the adaptive parts of the job choose for each scope on
+//! each worker, see `crate::api::Adapt`.
+
+use crate::api::meta::OperatorMeta;
+use crate::api::Branch;
+use std::cell::Cell;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// The most jobs whose profiles are kept;
+pub const MAX_PROFILED_JOBS: usize = 64;
+
+/// The min, max and sum of a measure of an operator over the workers;
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Summary {
+    pub min: u64,
+    pub max: u64,
+    pub sum: u64,
+    /// the number of workers measured;
+    pub count: u32,
+}
+
+impl Summary {
+    fn add(&mut self, value: u64) {
+        if self.count == 0 {
+            self.min = value;
+            self.max = value;
+        } else {
+            self.min = self.min.min(value);
+            self.max = self.max.max(value);
+        }
+        self.sum = self.sum.saturating_add(value);
+        self.count += 1;
+    }
+
+    pub fn avg(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.sum as f64 / self.count as f64
+        }
+    }
+}
+
+/// The profile of an operator over the workers of the job in current server;
+#[derive(Clone, Debug)]
+pub struct OperatorProfile {
+    /// the index of the operator in the dataflow;
+    pub index: usize,
+    /// the name of the operator, e.g. the step it is compiled from, see `Stream::named`;
+    pub name: String,
+    /// the wall time the operator is fired on each worker, in microseconds;
+    pub elapsed_us: Summary,
+    /// the records the operator receives on each worker;
+    pub records: Summary,
+    /// the encoded bytes the operator sends to the workers on other servers from each worker;
+    pub cross_server_bytes: Summary,
+    /// the firings of the operator on each worker using up the capacity of its outputs, after
+    /// which its outputs are parked or its inputs wait for the later firings;
+    pub stalls: Summary,
+}
+
+/// The alternative an adaptive part of the job chooses for a scope on a worker;
+#[derive(Clone, Debug, PartialEq)]
+pub struct AdaptiveDecision {
+    /// the name of the adaptive part, see `Adapt::adapt`;
+    pub name: String,
+    /// the index of the worker;
+    pub worker: u32,
+    /// the tag of the scope;
+    pub scope: String,
+    /// `Branch::Left` for the first alternative, `Branch::Right` for the second;
+    pub choice: Branch,
+    /// the number of the data the choice is made by;
+    pub sampled: usize,
+    pub reason: String,
+}
+
+/// The profile of a job in current server, with the operators in the order of their indexes;
+#[derive(Clone, Debug, Default)]
+pub struct JobProfile {
+    pub job_id: u64,
+    pub operators: Vec<OperatorProfile>,
+    /// the decisions of the adaptive parts, in the order of the workers;
+    pub decisions: Vec<AdaptiveDecision>,
+    /// the seed the random numbers of the job are drawn from in current server, see
+    /// `JobConf::rng_seed`;
+    pub rng_seed: Option<u64>,
+}
+
+impl JobProfile {
+    /// Get the profile of the first operator of the name;
+    pub fn get_operator(&self, name: &str) -> Option<&OperatorProfile> {
+        self.operators.iter().find(|op| op.name == name)
+    }
+
+    /// The encoded bytes sent across the servers by all the operators;
+    pub fn cross_server_bytes(&self) -> u64 {
+        self.operators.iter().map(|op| op.cross_server_bytes.sum).sum()
+    }
+}
+
+#[derive(Default)]
+struct ProfiledJob {
+    operators: BTreeMap<usize, OperatorProfile>,
+    decisions: Vec<AdaptiveDecision>,
+}
+
+#[derive(Default)]
+struct ProfiledJobs {
+    jobs: HashMap<u64, Arc<Mutex<ProfiledJob>>>,
+    // the jobs in the order they are registered, to drop the earliest ones
+    order: VecDeque<u64>,
+}
+
+lazy_static! {
+    static ref PROFILED_JOBS: Mutex<ProfiledJobs> = Mutex::new(ProfiledJobs::default());
+}
+
+thread_local! {
+    // the records received by the worker running on current thread
+    static RECORDS: Cell<u64> = const { Cell::new(0) };
+    // the bytes sent to other servers by the worker running on current thread
+    static CROSS_SERVER_BYTES: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Start to profile the job, which drops the profile of the previous job of the same id if any;
+pub(crate) fn register(job_id: u64) {
+    let mut profiled = PROFILED_JOBS.lock().expect("lock poisoned");
+    if profiled.jobs.insert(job_id, Arc::new(Mutex::new(ProfiledJob::default()))).is_some() {
+        profiled.order.retain(|id| *id != job_id);
+    }
+    profiled.order.push_back(job_id);
+    while profiled.order.len() > MAX_PROFILED_JOBS {
+        if let Some(earliest) = profiled.order.pop_front() {
+            profiled.jobs.remove(&earliest);
+        }
+    }
+}
+
+/// Get the profile of the job in current server, or `None` if the job is not profiled, or its
+/// profile has been dropped for the later jobs;
+pub fn fetch_job_profile(job_id: u64) -> Option<JobProfile> {
+    let job = PROFILED_JOBS.lock().ok()?.jobs.get(&job_id).cloned()?;
+    let job = job.lock().ok()?;
+    let operators = job.operators.values().cloned().collect();
+    let mut decisions = job.decisions.clone();
+    decisions.sort_by_key(|d| d.worker);
+    let rng_seed = crate::rng::fetch_job_rng_seed(job_id);
+    Some(JobProfile { job_id, operators, decisions, rng_seed })
+}
+
+/// Report a decision of an adaptive part of the job, if the job is profiled;
+pub(crate) fn add_decision(job_id: u64, decision: AdaptiveDecision) {
+    let job = match PROFILED_JOBS.lock() {
+        Ok(profiled) => profiled.jobs.get(&job_id).cloned(),
+        Err(_) => None,
+    };
+    let locked = job.as_ref().map(|job| job.lock());
+    if let Some(Ok(mut job)) = locked {
+        job.decisions.push(decision);
+    }
+}
+
+#[inline]
+pub(crate) fn on_recv(size: usize) {
+    RECORDS.with(|r| r.set(r.get() + size as u64));
+}
+
+#[inline]
+pub(crate) fn on_cross_server(bytes: usize) {
+    CROSS_SERVER_BYTES.with(|b| b.set(b.get() + bytes as u64));
+}
+
+/// What an operator of a worker measures, which is reported once the operator is dropped;
+pub(crate) struct OperatorProfiler {
+    job_id: u64,
+    index: usize,
+    name: String,
+    elapsed: Duration,
+    records: u64,
+    cross_server_bytes: u64,
+    stalls: u64,
+}
+
+impl OperatorProfiler {
+    pub(crate) fn new(meta: &OperatorMeta) -> Self {
+        OperatorProfiler {
+            job_id: meta.worker_id.job_id,
+            index: meta.index,
+            name: meta.name.clone(),
+            elapsed: Duration::default(),
+            records: 0,
+            cross_server_bytes: 0,
+            stalls: 0,
+        }
+    }
+
+    pub(crate) fn on_stall(&mut self) {
+        self.stalls += 1;
+    }
+
+    /// Measure a firing of the operator, until the guard is dropped;
+    pub(crate) fn measure(&mut self) -> MeasureGuard<'_> {
+        let records = RECORDS.with(|r| r.get());
+        let cross_server_bytes = CROSS_SERVER_BYTES.with(|b| b.get());
+        MeasureGuard { profiler: self, start: Instant::now(), records, cross_server_bytes }
+    }
+}
+
+impl Drop for OperatorProfiler {
+    fn drop(&mut self) {
+        let job = match PROFILED_JOBS.lock() {
+            Ok(profiled) => profiled.jobs.get(&self.job_id).cloned(),
+            Err(_) => None,
+        };
+        let locked = job.as_ref().map(|job| job.lock());
+        if let Some(Ok(mut job)) = locked {
+            let profile = job.operators.entry(self.index).or_insert_with(|| OperatorProfile {
+                index: self.index,
+                name: self.name.clone(),
+                elapsed_us: Summary::default(),
+                records: Summary::default(),
+                cross_server_bytes: Summary::default(),
+                stalls: Summary::default(),
+            });
+            profile.elapsed_us.add(self.elapsed.as_micros() as u64);
+            profile.records.add(self.records);
+            profile.cross_server_bytes.add(self.cross_server_bytes);
+            profile.stalls.add(self.stalls);
+        }
+    }
+}
+
+pub(crate) struct MeasureGuard<'a> {
+    profiler: &'a mut OperatorProfiler,
+    start: Instant,
+    records: u64,
+    cross_server_bytes: u64,
+}
+
+impl<'a> Drop for MeasureGuard<'a> {
+    fn drop(&mut self) {
+        self.profiler.elapsed += self.start.elapsed();
+        let records = RECORDS.with(|r| r.get());
+        self.profiler.records += records.wrapping_sub(self.records);
+        let bytes = CROSS_SERVER_BYTES.with(|b| b.get());
+        self.profiler.cross_server_bytes += bytes.wrapping_sub(self.cross_server_bytes);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn summary_test() {
+        let mut summary = Summary::default();
+        assert_eq!(summary.avg(), 0.0);
+        for value in [3, 1, 5] {
+            summary.add(value);
+        }
+        assert_eq!(summary, Summary { min: 1, max: 5, sum: 9, count: 3 });
+        assert_eq!(summary.avg(), 3.0);
+    }
+
+    #[test]
+    fn profile_operators_test() {
+        let job_id = 1 << 41;
+        register(job_id);
+        for worker in 0..2u32 {
+            let mut profiler = OperatorProfiler {
+                job_id,
+                index: 1,
+                name: "has".to_owned(),
+                elapsed: Duration::default(),
+                records: 0,
+                cross_server_bytes: 0,
+                stalls: 0,
+            };
+            {
+                let _guard = profiler.measure();
+                on_recv(worker as usize + 1);
+                on_cross_server(8);
+            }
+            // the records received out of the firings are not counted
+            on_recv(10);
+            on_cross_server(10);
+        }
+        let profile = fetch_job_profile(job_id).unwrap();
+        assert_eq!(profile.operators.len(), 1);
+        let has = profile.get_operator("has").unwrap();
+        assert_eq!(has.records, Summary { min: 1, max: 2, sum: 3, count: 2 });
+        assert_eq!(has.elapsed_us.count, 2);
+        assert_eq!(has.cross_server_bytes, Summary { min: 8, max: 8, sum: 16, count: 2 });
+        assert_eq!(profile.cross_server_bytes(), 16);
+        assert!(fetch_job_profile(job_id + 1).is_none());
+    }
+}
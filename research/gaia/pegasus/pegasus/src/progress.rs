@@ -0,0 +1,251 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The progress of the running jobs in current server, told by their sources, each of which
+//! reports the records it expects to produce on each worker once it is built, e.g. by the
+//! metadata of the partition it scans, and the records it has produced so far once it is fired.
+//! The fraction of a source is the records it has produced over those it expects, or 1.0 once it
+//! is exhausted on all the workers, and is unknown if it expects no size on any worker. The
+//! fraction of a job is the least of its sources, which is peeked by `peek_progress`. The progress
+//! of at most `MAX_TRACKED_JOBS` jobs is kept, where that of the earliest jobs is dropped first.
+
+use crate::api::meta::OperatorMeta;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// The most jobs whose progress is kept;
+pub const MAX_TRACKED_JOBS: usize = 64;
+
+/// The progress of a source operator over the workers of the job in current server;
+#[derive(Clone, Debug, PartialEq)]
+pub struct SourceProgress {
+    /// the index of the source operator in the dataflow;
+    pub index: usize,
+    /// the name of the source operator;
+    pub name: String,
+    /// the records the source has produced on all the workers;
+    pub consumed: u64,
+    /// the records the source expects to produce on all the workers, if all of them can tell;
+    pub expected: Option<u64>,
+    /// the fraction of the source done, between 0.0 and 1.0, or `None` if unknown;
+    pub fraction: Option<f64>,
+}
+
+/// The progress of a job in current server, with the sources in the order of their indexes;
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct JobProgress {
+    pub job_id: u64,
+    pub sources: Vec<SourceProgress>,
+}
+
+impl JobProgress {
+    /// The fraction of the job done, i.e. the least fraction of its sources, or `None` if any
+    /// source is unknown, or no source has been built yet;
+    pub fn fraction(&self) -> Option<f64> {
+        let mut least: Option<f64> = None;
+        for source in self.sources.iter() {
+            let fraction = source.fraction?;
+            least = Some(least.map_or(fraction, |l| l.min(fraction)));
+        }
+        least
+    }
+}
+
+#[derive(Default)]
+struct SourceMeta {
+    // the workers which have built the source
+    built: u32,
+    // the sum of the sizes expected by the workers, or `None` once any of them can't tell
+    expected: Option<u64>,
+    // the workers on which the source has been exhausted
+    exhausted: u32,
+}
+
+struct SourceState {
+    name: String,
+    consumed: AtomicU64,
+    meta: Mutex<SourceMeta>,
+}
+
+struct JobSources {
+    // the workers of the job in current server
+    workers: u32,
+    sources: Mutex<BTreeMap<usize, Arc<SourceState>>>,
+}
+
+impl JobSources {
+    fn progress(&self, job_id: u64) -> JobProgress {
+        let sources = self.sources.lock().expect("lock poisoned");
+        let sources = sources
+            .iter()
+            .map(|(index, state)| {
+                // read ahead of the meta, so that a source exhausted in between is never told of
+                // fewer records than before
+                let consumed = state.consumed.load(Ordering::SeqCst);
+                let meta = state.meta.lock().expect("lock poisoned");
+                let fraction = if meta.exhausted >= self.workers {
+                    Some(1.0)
+                } else if meta.built < self.workers {
+                    None
+                } else {
+                    meta.expected
+                        .map(|expected| (consumed as f64 / expected.max(1) as f64).min(1.0))
+                };
+                SourceProgress {
+                    index: *index,
+                    name: state.name.clone(),
+                    consumed,
+                    expected: meta.expected,
+                    fraction,
+                }
+            })
+            .collect();
+        JobProgress { job_id, sources }
+    }
+}
+
+#[derive(Default)]
+struct TrackedJobs {
+    jobs: HashMap<u64, Arc<JobSources>>,
+    // the jobs in the order they are registered, to drop the earliest ones
+    order: VecDeque<u64>,
+}
+
+lazy_static! {
+    static ref TRACKED_JOBS: Mutex<TrackedJobs> = Mutex::new(TrackedJobs::default());
+}
+
+/// Start to track the progress of the job of the workers in current server, which drops the
+/// progress of the previous job of the same id if any;
+pub(crate) fn register(job_id: u64, workers: u32) {
+    let sources = Arc::new(JobSources { workers, sources: Mutex::new(BTreeMap::new()) });
+    let mut tracked = TRACKED_JOBS.lock().expect("lock poisoned");
+    if tracked.jobs.insert(job_id, sources).is_some() {
+        tracked.order.retain(|id| *id != job_id);
+    }
+    tracked.order.push_back(job_id);
+    while tracked.order.len() > MAX_TRACKED_JOBS {
+        if let Some(earliest) = tracked.order.pop_front() {
+            tracked.jobs.remove(&earliest);
+        }
+    }
+}
+
+/// Peek the progress of the job in current server, or `None` if the job is unknown, or its
+/// progress has been dropped for the later jobs;
+pub fn peek_progress(job_id: u64) -> Option<JobProgress> {
+    let sources = TRACKED_JOBS.lock().ok()?.jobs.get(&job_id).cloned()?;
+    Some(sources.progress(job_id))
+}
+
+/// What a source operator of a worker reports of its progress;
+pub(crate) struct SourceTracker {
+    state: Option<Arc<SourceState>>,
+    // the records produced but not reported yet
+    pending: u64,
+}
+
+impl SourceTracker {
+    /// Report the records the source expects to produce on the worker once it is built, or `None`
+    /// if it can't tell;
+    pub(crate) fn new(meta: &OperatorMeta, expected: Option<u64>) -> Self {
+        let sources = match TRACKED_JOBS.lock() {
+            Ok(tracked) => tracked.jobs.get(&meta.worker_id.job_id).cloned(),
+            Err(_) => None,
+        };
+        let state = sources.and_then(|sources| {
+            let mut sources = sources.sources.lock().ok()?;
+            let state = sources.entry(meta.index).or_insert_with(|| {
+                Arc::new(SourceState {
+                    name: meta.name.clone(),
+                    consumed: AtomicU64::new(0),
+                    meta: Mutex::new(SourceMeta { expected: Some(0), ..Default::default() }),
+                })
+            });
+            if let Ok(mut source) = state.meta.lock() {
+                source.built += 1;
+                source.expected = source.expected.and_then(|sum| Some(sum + expected?));
+            }
+            Some(state.clone())
+        });
+        SourceTracker { state, pending: 0 }
+    }
+
+    #[inline]
+    pub(crate) fn on_produced(&mut self, records: usize) {
+        self.pending += records as u64;
+    }
+
+    /// Report the records produced since the last report, e.g. at the end of each firing;
+    pub(crate) fn flush(&mut self) {
+        if self.pending > 0 {
+            if let Some(state) = self.state.as_ref() {
+                state.consumed.fetch_add(self.pending, Ordering::SeqCst);
+            }
+            self.pending = 0;
+        }
+    }
+
+    pub(crate) fn on_exhausted(&mut self) {
+        self.flush();
+        if let Some(Ok(mut meta)) = self.state.as_ref().map(|state| state.meta.lock()) {
+            meta.exhausted += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn source(
+        expected: Option<u64>, built: u32, exhausted: u32, consumed: u64,
+    ) -> Arc<SourceState> {
+        Arc::new(SourceState {
+            name: "source".to_owned(),
+            consumed: AtomicU64::new(consumed),
+            meta: Mutex::new(SourceMeta { built, expected, exhausted }),
+        })
+    }
+
+    #[test]
+    fn job_fraction_test() {
+        let job = JobSources { workers: 2, sources: Mutex::new(BTreeMap::new()) };
+        assert_eq!(job.progress(1).fraction(), None);
+        {
+            let mut sources = job.sources.lock().unwrap();
+            sources.insert(0, source(Some(100), 2, 0, 50));
+            sources.insert(3, source(Some(10), 2, 0, 8));
+        }
+        let progress = job.progress(1);
+        assert_eq!(progress.sources[0].fraction, Some(0.5));
+        assert_eq!(progress.sources[1].fraction, Some(0.8));
+        assert_eq!(progress.fraction(), Some(0.5));
+        // more records than expected are never beyond 1.0
+        job.sources.lock().unwrap().insert(0, source(Some(100), 2, 0, 120));
+        assert_eq!(job.progress(1).fraction(), Some(0.8));
+        // a source not built by all the workers, or expecting no size, is unknown
+        job.sources.lock().unwrap().insert(5, source(Some(10), 1, 0, 0));
+        assert_eq!(job.progress(1).sources[2].fraction, None);
+        assert_eq!(job.progress(1).fraction(), None);
+        job.sources.lock().unwrap().insert(5, source(None, 2, 1, 0));
+        assert_eq!(job.progress(1).fraction(), None);
+        // until it is exhausted on all the workers
+        job.sources.lock().unwrap().insert(5, source(None, 2, 2, 7));
+        assert_eq!(job.progress(1).sources[2].fraction, Some(1.0));
+        assert_eq!(job.progress(1).fraction(), Some(0.8));
+    }
+}
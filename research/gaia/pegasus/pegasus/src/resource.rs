@@ -0,0 +1,123 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The read-only data a job shares with all its operators, e.g. the parameters of a query, the
+//! user requesting it, or a timestamp for `now()` to be evaluated the same on all workers. They
+//! are set by `JobConf::set_user_data` before the job is submitted, and retrieved inside the
+//! functions of the operators by `get_job_resource`, which finds the job by the worker running on
+//! current thread. The resources of a job are dropped once all its workers in current server end.
+
+use crate::errors::JobResourceError;
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+/// The resources of a job by their keys;
+#[derive(Clone, Default)]
+pub struct JobResources {
+    resources: HashMap<String, Arc<dyn Any + Send + Sync>>,
+}
+
+impl JobResources {
+    pub fn insert<T: Any + Send + Sync>(&mut self, key: String, value: T) {
+        self.resources.insert(key, Arc::new(value));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.resources.is_empty()
+    }
+
+    fn get<T: Any + Send + Sync>(
+        &self, job_id: u64, key: &str,
+    ) -> Result<Arc<T>, JobResourceError> {
+        let resource = self
+            .resources
+            .get(key)
+            .ok_or_else(|| JobResourceError::NotFound { job_id, key: key.to_owned() })?;
+        resource
+            .clone()
+            .downcast::<T>()
+            .map_err(|_| JobResourceError::TypeMismatch { job_id, key: key.to_owned() })
+    }
+}
+
+impl fmt::Debug for JobResources {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.resources.keys()).finish()
+    }
+}
+
+lazy_static! {
+    static ref JOB_RESOURCES: RwLock<HashMap<u64, Arc<JobResources>>> = RwLock::new(HashMap::new());
+}
+
+/// Share the resources with the workers of the job in current server;
+pub(crate) fn register(job_id: u64, resources: JobResources) {
+    if !resources.is_empty() {
+        if let Ok(mut jobs) = JOB_RESOURCES.write() {
+            jobs.insert(job_id, Arc::new(resources));
+        }
+    }
+}
+
+/// Drop the resources of the job, once all its workers in current server end;
+pub(crate) fn unregister(job_id: u64) {
+    if let Ok(mut jobs) = JOB_RESOURCES.write() {
+        jobs.remove(&job_id);
+    }
+}
+
+/// Get the resource of the key set for the job of the worker running on current thread, which
+/// fails if current thread runs no worker, e.g. out of the functions of the operators, or the job
+/// has no resource of the key and type;
+pub fn get_job_resource<T: Any + Send + Sync>(key: &str) -> Result<Arc<T>, JobResourceError> {
+    let job_id = crate::worker_id::get_current_worker().ok_or(JobResourceError::OutOfJob)?.job_id;
+    let resources = JOB_RESOURCES
+        .read()
+        .ok()
+        .and_then(|jobs| jobs.get(&job_id).cloned())
+        .ok_or_else(|| JobResourceError::NotFound { job_id, key: key.to_owned() })?;
+    resources.get(job_id, key)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::worker_id::WorkerId;
+
+    #[test]
+    fn get_job_resource_test() {
+        let job_id = 1 << 42;
+        let mut resources = JobResources::default();
+        resources.insert("user".to_owned(), "alice".to_owned());
+        register(job_id, resources);
+        assert_eq!(get_job_resource::<String>("user"), Err(JobResourceError::OutOfJob));
+        {
+            let _g = crate::worker_id::guard(WorkerId::new(job_id, 1, 0, false));
+            assert_eq!(get_job_resource::<String>("user").unwrap().as_str(), "alice");
+            assert_eq!(
+                get_job_resource::<u64>("user"),
+                Err(JobResourceError::TypeMismatch { job_id, key: "user".to_owned() })
+            );
+            assert_eq!(
+                get_job_resource::<String>("now"),
+                Err(JobResourceError::NotFound { job_id, key: "now".to_owned() })
+            );
+            unregister(job_id);
+            assert!(get_job_resource::<String>("user").is_err());
+        }
+    }
+}
@@ -0,0 +1,163 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Collect the results of a job from its sinks as a typed iterator, instead of matching each
+//! `ResultSet` received. The sinks send what they receive by `sink_to` to a channel, whose
+//! receiving side is wrapped by `ResultCollector`, which yields the data one by one and ends once
+//! all the sinks are dropped, i.e. once the job ends on current server, with the failure of the
+//! job if it fails. The channel is a plain `crossbeam_channel`, which is still available to be
+//! received directly if the ends of the scopes matter.
+
+use crate::api::ResultSet;
+use crate::errors::ExecError;
+use crate::{JobGuard, Tag};
+use crossbeam_channel::{Receiver, RecvTimeoutError, Sender};
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::fmt::{self, Debug, Display};
+use std::hash::Hash;
+use std::time::Duration;
+
+/// The errors of collecting the results of a job;
+pub enum JobError {
+    /// The job failed on current server;
+    Exec(ExecError),
+    /// No result arrived in the time, e.g. as the job hangs;
+    Timeout(Duration),
+}
+
+impl Debug for JobError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JobError::Exec(err) => write!(f, "job failure: {}", err),
+            JobError::Timeout(timeout) => write!(f, "no result in {:?}", timeout),
+        }
+    }
+}
+
+impl Display for JobError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
+impl Error for JobError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            JobError::Exec(err) => Some(err),
+            JobError::Timeout(_) => None,
+        }
+    }
+}
+
+/// The function of `Sink::sink_by` sending all it receives to the channel, to be collected by
+/// `ResultCollector`;
+pub fn sink_to<D: Send + 'static>(
+    tx: Sender<ResultSet<D>>,
+) -> impl Fn(&Tag, ResultSet<D>) + Send + 'static {
+    move |_, result| {
+        // the collector may be dropped early, while the rest results are of no use;
+        tx.send(result).ok();
+    }
+}
+
+/// The iterator of the data the sinks of a job send to the channel of `rx`, e.g. by `sink_to`,
+/// which ends once all the senders are dropped, after which the failure of the job is yielded
+/// if it is joined by `with_guard`;
+pub struct ResultCollector<T> {
+    rx: Receiver<ResultSet<T>>,
+    buffer: VecDeque<T>,
+    guard: Option<JobGuard>,
+    is_done: bool,
+}
+
+impl<T> ResultCollector<T> {
+    pub fn new(rx: Receiver<ResultSet<T>>) -> Self {
+        ResultCollector { rx, buffer: VecDeque::new(), guard: None, is_done: false }
+    }
+
+    /// Join the job by its guard once all the results are collected, to yield its failure, e.g.
+    /// the guard of `pegasus::run`, which is none if the job has no worker on current server;
+    pub fn with_guard(mut self, guard: Option<JobGuard>) -> Self {
+        self.guard = guard;
+        self
+    }
+
+    /// Get the next datum, waiting at most `timeout` for it, or `JobError::Timeout` after;
+    pub fn next_timeout(&mut self, timeout: Duration) -> Option<Result<T, JobError>> {
+        self.poll(Some(timeout))
+    }
+
+    /// Collect all the data, or the first error;
+    pub fn collect_vec(self) -> Result<Vec<T>, JobError> {
+        self.collect()
+    }
+
+    /// Collect all the data grouped by the keys of `key_fn`, or the first error;
+    pub fn collect_map<K, F>(self, key_fn: F) -> Result<HashMap<K, Vec<T>>, JobError>
+    where
+        K: Eq + Hash,
+        F: Fn(&T) -> K,
+    {
+        let mut groups = HashMap::new();
+        for datum in self {
+            let datum = datum?;
+            groups.entry(key_fn(&datum)).or_insert_with(Vec::new).push(datum);
+        }
+        Ok(groups)
+    }
+
+    fn poll(&mut self, timeout: Option<Duration>) -> Option<Result<T, JobError>> {
+        loop {
+            if let Some(datum) = self.buffer.pop_front() {
+                return Some(Ok(datum));
+            }
+            if self.is_done {
+                return None;
+            }
+            let received = match timeout {
+                Some(timeout) => match self.rx.recv_timeout(timeout) {
+                    Ok(result) => Some(result),
+                    Err(RecvTimeoutError::Timeout) => {
+                        return Some(Err(JobError::Timeout(timeout)));
+                    }
+                    Err(RecvTimeoutError::Disconnected) => None,
+                },
+                None => self.rx.recv().ok(),
+            };
+            match received {
+                Some(ResultSet::Data(data)) => self.buffer.extend(data),
+                // the ends of the scopes and of each sink tell nothing of the data
+                Some(ResultSet::ScopeEnd(_)) | Some(ResultSet::End) => (),
+                None => return self.finish(),
+            }
+        }
+    }
+
+    /// End the iterator as all the senders are dropped, with the failure of the job if any;
+    fn finish(&mut self) -> Option<Result<T, JobError>> {
+        self.is_done = true;
+        let mut guard = self.guard.take()?;
+        guard.join().err().map(|err| Err(JobError::Exec(err)))
+    }
+}
+
+impl<T> Iterator for ResultCollector<T> {
+    type Item = Result<T, JobError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.poll(None)
+    }
+}
@@ -0,0 +1,231 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The random numbers of the jobs, which are drawn from the streams derived of the seed of each
+//! job, see `JobConf::rng_seed`. Each operator of each worker draws from its own stream, split of
+//! the seed by the index of the worker and the index of the operator, got by `OperatorMeta::rng`,
+//! so the random choices of a job with a fixed seed are the same in all runs, as long as the data
+//! come to the operators in the same order, while those of different workers are independent.
+//!
+//! The seed of a job without `rng_seed` is drawn from the entropy of the system once the job is
+//! submitted in each server, which is logged and kept for at most `MAX_SEEDED_JOBS` jobs, to be
+//! fetched by `fetch_job_rng_seed` and set as the `rng_seed` of a later run to reproduce the job.
+//! Note that the servers of such a job draw their seeds apart, which are fetched in each server.
+
+use crate::Tag;
+use std::collections::hash_map::RandomState;
+use std::collections::VecDeque;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// The most jobs whose seeds are kept;
+pub const MAX_SEEDED_JOBS: usize = 64;
+
+const GOLDEN_GAMMA: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// A splittable generator of the random numbers by SplitMix64, which is cheap to create and to
+/// split into the independent generators keyed by the indexes of the workers, the operators, or
+/// the scopes;
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct JobRng {
+    state: u64,
+}
+
+impl JobRng {
+    pub fn new(seed: u64) -> Self {
+        JobRng { state: mix(seed) }
+    }
+
+    /// The stream of the operator of the index in the worker of the index, of the job seed;
+    pub fn of_operator(seed: u64, worker: u32, operator: usize) -> Self {
+        JobRng::new(seed).split(worker as u64).split(operator as u64)
+    }
+
+    /// A generator independent of this one and of those split by the other keys, which leaves
+    /// this one as it is;
+    pub fn split(&self, key: u64) -> Self {
+        JobRng { state: mix(self.state ^ mix(key.wrapping_add(GOLDEN_GAMMA))) }
+    }
+
+    /// A generator split by the tag of a scope, e.g. of an operator drawing for each scope apart,
+    /// whose draws of a scope don't depend on the other scopes coming before;
+    pub fn split_by_tag(&self, tag: &Tag) -> Self {
+        let mut rng = self.split(tag.len() as u64);
+        for cur in tag.as_slice() {
+            rng = rng.split(*cur as u64);
+        }
+        rng
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(GOLDEN_GAMMA);
+        mix(self.state)
+    }
+
+    /// A float uniformly drawn in `[0, 1)`;
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+    }
+
+    /// An integer uniformly drawn in `[0, bound)`, by rejecting the draws of the biased tail;
+    /// `bound` should be positive;
+    pub fn gen_below(&mut self, bound: u64) -> u64 {
+        assert!(bound > 0, "the bound of JobRng::gen_below should be positive");
+        let zone = u64::MAX - u64::MAX % bound;
+        loop {
+            let draw = self.next_u64();
+            if draw < zone {
+                return draw % bound;
+            }
+        }
+    }
+
+    /// True by the probability `p`, which is clamped into `[0, 1]`, where a NaN is taken as 0;
+    pub fn gen_bool(&mut self, p: f64) -> bool {
+        let p = if p.is_nan() { 0.0 } else { p.clamp(0.0, 1.0) };
+        self.next_f64() < p
+    }
+}
+
+/// The finalizer of splitmix64;
+#[inline]
+fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+/// A seed drawn from the random keys the hash maps of the system are seeded by, mixed with the
+/// time;
+pub fn entropy_seed() -> u64 {
+    let mut hasher = RandomState::new().build_hasher();
+    let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default();
+    hasher.write_u128(now.as_nanos());
+    hasher.finish()
+}
+
+/// The seeds of the latest jobs, at most `MAX_SEEDED_JOBS`, in the order they are kept;
+#[derive(Default)]
+struct SeededJobs {
+    seeds: VecDeque<(u64, u64)>,
+}
+
+impl SeededJobs {
+    fn get(&self, job_id: u64) -> Option<u64> {
+        self.seeds.iter().rev().find(|(id, _)| *id == job_id).map(|(_, seed)| *seed)
+    }
+
+    /// Keep the seed of the job, dropping the seeds of the earliest jobs beyond the limit;
+    fn keep(&mut self, job_id: u64, seed: u64) {
+        self.seeds.retain(|(id, _)| *id != job_id);
+        self.seeds.push_back((job_id, seed));
+        while self.seeds.len() > MAX_SEEDED_JOBS {
+            self.seeds.pop_front();
+        }
+    }
+}
+
+lazy_static! {
+    static ref SEEDED_JOBS: Mutex<SeededJobs> = Mutex::new(SeededJobs::default());
+}
+
+/// Get the seed the job runs with in current server, either set by `JobConf::rng_seed` or drawn
+/// from the entropy, or `None` if the job is unknown, or its seed has been dropped for the later
+/// jobs;
+pub fn fetch_job_rng_seed(job_id: u64) -> Option<u64> {
+    SEEDED_JOBS.lock().ok()?.get(job_id)
+}
+
+/// Decide the seed of the job, which is the `rng_seed` if set, and keep it for the job;
+pub(crate) fn resolve_seed(job_id: u64, rng_seed: Option<u64>) -> u64 {
+    let seed = rng_seed.unwrap_or_else(|| {
+        let seed = entropy_seed();
+        info!("job {} draws the random numbers of rng_seed {};", job_id, seed);
+        seed
+    });
+    if let Ok(mut seeded) = SEEDED_JOBS.lock() {
+        seeded.keep(job_id, seed);
+    }
+    seed
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn job_rng_stream_test() {
+        let draws = |mut rng: JobRng| (0..16).map(|_| rng.next_u64()).collect::<Vec<_>>();
+        assert_eq!(draws(JobRng::of_operator(7, 1, 2)), draws(JobRng::of_operator(7, 1, 2)));
+        assert_ne!(draws(JobRng::of_operator(7, 1, 2)), draws(JobRng::of_operator(7, 2, 2)));
+        assert_ne!(draws(JobRng::of_operator(7, 1, 2)), draws(JobRng::of_operator(7, 1, 3)));
+        assert_ne!(draws(JobRng::of_operator(7, 1, 2)), draws(JobRng::of_operator(8, 1, 2)));
+        // the worker and the operator are not interchangeable;
+        assert_ne!(draws(JobRng::of_operator(7, 1, 2)), draws(JobRng::of_operator(7, 2, 1)));
+        let rng = JobRng::new(7);
+        let tag = Tag::from_vec(vec![1, 2]);
+        assert_eq!(draws(rng.split_by_tag(&tag)), draws(rng.split_by_tag(&tag)));
+        assert_ne!(draws(rng.split_by_tag(&tag)), draws(rng.split_by_tag(&Tag::new(1))));
+    }
+
+    #[test]
+    fn job_rng_uniform_test() {
+        let mut rng = JobRng::new(42);
+        let n = 100_000;
+        let mut buckets = [0u32; 10];
+        for _ in 0..n {
+            let draw = rng.gen_below(10);
+            buckets[draw as usize] += 1;
+        }
+        assert!(buckets.iter().all(|b| (*b as i64 - n / 10).abs() < n / 100), "{:?}", buckets);
+        let heads = (0..n).filter(|_| rng.gen_bool(0.3)).count() as f64 / n as f64;
+        assert!((heads - 0.3).abs() < 0.01, "{}", heads);
+        assert!((0..n).map(|_| rng.next_f64()).all(|f| (0.0..1.0).contains(&f)));
+        assert!(!(0..1000).any(|_| rng.gen_bool(0.0)));
+        assert!((0..1000).all(|_| rng.gen_bool(1.0)));
+        // clamped into [0, 1];
+        assert!(!(0..1000).any(|_| rng.gen_bool(-0.5) || rng.gen_bool(f64::NAN)));
+        assert!((0..1000).all(|_| rng.gen_bool(1.5)));
+    }
+
+    #[test]
+    fn job_rng_seed_test() {
+        // the job ids are far beyond those of the other tests, which share the seeds kept;
+        let job_id = 1 << 43;
+        assert!(fetch_job_rng_seed(job_id).is_none());
+        assert_eq!(resolve_seed(job_id, Some(5)), 5);
+        assert_eq!(fetch_job_rng_seed(job_id), Some(5));
+        let seed = resolve_seed(job_id + 1, None);
+        assert_eq!(fetch_job_rng_seed(job_id + 1), Some(seed));
+        assert_ne!(entropy_seed(), entropy_seed());
+    }
+
+    #[test]
+    fn seeded_jobs_limit_test() {
+        // apart from the seeds kept by the jobs of the other tests;
+        let mut seeded = SeededJobs::default();
+        for id in 0..=MAX_SEEDED_JOBS as u64 {
+            seeded.keep(id, id + 100);
+        }
+        assert!(seeded.get(0).is_none());
+        assert_eq!(seeded.get(1), Some(101));
+        // kept again, which is then the latest;
+        seeded.keep(1, 7);
+        seeded.keep(MAX_SEEDED_JOBS as u64 + 1, 0);
+        assert_eq!(seeded.get(1), Some(7));
+        assert!(seeded.get(2).is_none());
+    }
+}
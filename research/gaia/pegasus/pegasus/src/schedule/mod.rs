@@ -19,7 +19,9 @@ use crate::event::EventManager;
 use std::time::Instant;
 
 mod op_runtime;
+pub(crate) mod slice;
 pub(crate) use op_runtime::OpRuntime;
+pub(crate) use slice::TimeSlice;
 
 pub struct Schedule {
     pub step_count: usize,
@@ -106,6 +108,7 @@ impl Schedule {
                 op.cancel(port.port, ch, skip)?;
             }
         }
+        op.tick();
         let mut is_finished = false;
         if op.check_ready() {
             match op.fire() {
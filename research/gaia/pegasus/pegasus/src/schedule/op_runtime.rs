@@ -16,6 +16,7 @@
 use crate::api::meta::ScopePrior;
 use crate::errors::JobExecError;
 use crate::operator::Operator;
+use crate::schedule::slice;
 use crate::Tag;
 use std::collections::HashSet;
 use std::ops::{Deref, DerefMut};
@@ -47,9 +48,11 @@ impl OpRuntime {
     }
 
     pub fn fire(&mut self) -> Result<bool, JobExecError> {
+        let _slice = slice::begin(self.meta.slice);
         let start = Instant::now();
         self.op.fire_actives()?;
         self.elapse[0] += start.elapsed().as_micros();
+        self.bound_inputs();
 
         let len = self.inputs().len();
         if len > 0 {
@@ -66,7 +69,9 @@ impl OpRuntime {
                             });
                         }
                         let mut x = (1u64 << len) - 1;
-                        while x > 0 && !dedup.is_empty() {
+                        // the scopes not fired once the slice or the input quota is used up stay
+                        // outstanding;
+                        while x > 0 && !dedup.is_empty() && !slice::is_input_exhausted() {
                             for i in 0..len {
                                 let mask = 1u64 << i;
                                 if x & mask > 0 {
@@ -106,6 +111,9 @@ impl OpRuntime {
                         }
                         receives.sort_by(|t1, t2| priority.compare(t1, t2));
                         for tag in receives.drain(..) {
+                            if slice::is_input_exhausted() {
+                                break;
+                            }
                             self.op.fire_on_receive(&tag)?;
                         }
                     }
@@ -121,6 +129,9 @@ impl OpRuntime {
             self.elapse[2] += start.elapsed().as_micros();
         }
 
+        if !self.op.has_output_capacity() {
+            self.op.on_stall();
+        }
         for output in self.op.outputs() {
             output.close_scopes()?;
             output.reset_capacity();
@@ -129,6 +140,18 @@ impl OpRuntime {
         Ok(self.is_finished())
     }
 
+    /// Bound the inputs the operator takes in the firing by the batches its outputs still have the
+    /// capacity for, as expected by the fanout it declares, see `OperatorMeta::fanout`; The
+    /// operators of no fanout above 1, e.g. the filters, take as many inputs as the slice allows;
+    fn bound_inputs(&self) {
+        if let Some(fanout) = self.meta.fanout.filter(|fanout| *fanout > 1.0) {
+            // the output giving the last batch of its capacity interrupts the operator, so one
+            // batch less is left for the inputs, while one batch of inputs is always taken
+            let batches = (self.op.output_capacity_left() - 1).max(1) as u64;
+            slice::set_input_quota(batches, fanout, self.meta.batch_size);
+        }
+    }
+
     pub fn close(&mut self) {
         if !self.is_closed {
             self.is_closed = true;
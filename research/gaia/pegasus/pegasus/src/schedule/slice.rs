@@ -0,0 +1,224 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The time slices of the operators, which bound the records and the time an operator runs in a
+//! firing, so that a large batch job yields the thread to the other jobs on it in time, e.g. the
+//! interactive ones, rather than holding it until its inputs are drained.
+//!
+//! A slice begins once an operator is fired, and is used up by the records it receives, or pulls
+//! from the source, and the time elapsed since then. The operator is never cut within a batch:
+//! the inputs stop giving the batches of a scope, the sources and the lazy iterators stop being
+//! pulled, and the rest of the scopes are left outstanding, all of which are resumed in the later
+//! firings as if the output capacity is used up, so the worker stays ready and is scheduled again
+//! after the other tasks on the thread.
+//!
+//! The inputs of an operator declaring its fanout, i.e. the outputs it expects of each input, are
+//! bounded further in each firing by the quota of the batches its outputs have the capacity for,
+//! see `set_input_quota`: the inputs stop giving batches once another batch is expected to
+//! overshoot the capacity, so a flat map of a large fanout takes fewer
+//! inputs in a firing than a filter does. Unlike the slice, the quota never cuts the outputs.
+
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+/// The most records and time an operator runs in a firing, where 0 means no limit;
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TimeSlice {
+    pub records: u64,
+    pub micros: u64,
+}
+
+impl TimeSlice {
+    pub fn new(records: u64, micros: u64) -> Self {
+        TimeSlice { records, micros }
+    }
+
+    #[inline]
+    pub fn is_unlimited(&self) -> bool {
+        self.records == 0 && self.micros == 0
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Budget {
+    slice: TimeSlice,
+    start: Instant,
+    records: u64,
+    quota: Option<Quota>,
+}
+
+impl Budget {
+    fn is_exhausted(&self) -> bool {
+        (self.slice.records > 0 && self.records >= self.slice.records)
+            || (self.slice.micros > 0
+                && self.start.elapsed() >= Duration::from_micros(self.slice.micros))
+    }
+
+    fn is_input_exhausted(&self) -> bool {
+        self.is_exhausted() || self.quota.map(|q| q.is_used_up()).unwrap_or(false)
+    }
+}
+
+/// The batches the outputs of an operator have the capacity for in a firing, and those expected
+/// of the inputs taken so far by the fanout of the operator;
+#[derive(Clone, Copy)]
+struct Quota {
+    batches: u64,
+    fanout: f64,
+    batch_size: usize,
+    expected: u64,
+    // the batches expected of the last batch of inputs taken
+    last: u64,
+}
+
+impl Quota {
+    /// Whether another batch of inputs like the last one would overshoot the quota;
+    fn is_used_up(&self) -> bool {
+        self.expected + self.last > self.batches
+    }
+}
+
+/// The batches of `batch_size` expected of a batch of `records` inputs by the fanout, where the
+/// outputs of a batch take one batch at least, as they are flushed once the batch is done;
+#[inline]
+pub(crate) fn expected_batches(records: usize, fanout: f64, batch_size: usize) -> u64 {
+    let outputs = records as f64 * fanout;
+    std::cmp::max(1, (outputs / batch_size.max(1) as f64).ceil() as u64)
+}
+
+thread_local! {
+    // the slice of the operator being fired on current thread, if it is limited
+    static BUDGET: Cell<Option<Budget>> = const { Cell::new(None) };
+}
+
+/// Begin the slice of an operator firing, which ends once the guard is dropped;
+pub(crate) fn begin(slice: TimeSlice) -> SliceGuard {
+    if !slice.is_unlimited() {
+        let budget = Budget { slice, start: Instant::now(), records: 0, quota: None };
+        BUDGET.with(|b| b.set(Some(budget)));
+    }
+    SliceGuard { _private: () }
+}
+
+/// Bound the inputs of the operator being fired by the `batches` its outputs have the capacity
+/// for, as expected by its `fanout`, which lasts until the slice ends;
+pub(crate) fn set_input_quota(batches: u64, fanout: f64, batch_size: usize) {
+    let quota = Quota { batches, fanout, batch_size, expected: 0, last: 0 };
+    BUDGET.with(|b| {
+        let budget = match b.get() {
+            Some(budget) => Budget { quota: Some(quota), ..budget },
+            None => Budget {
+                slice: TimeSlice::default(),
+                start: Instant::now(),
+                records: 0,
+                quota: Some(quota),
+            },
+        };
+        b.set(Some(budget));
+    })
+}
+
+/// Count the records the operator being fired processes, i.e. those received from its inputs, or
+/// pulled from the source;
+#[inline]
+pub(crate) fn on_records(size: usize) {
+    BUDGET.with(|b| {
+        if let Some(mut budget) = b.get() {
+            budget.records += size as u64;
+            if let Some(quota) = budget.quota.as_mut() {
+                quota.last = expected_batches(size, quota.fanout, quota.batch_size);
+                quota.expected += quota.last;
+            }
+            b.set(Some(budget));
+        }
+    })
+}
+
+/// Tell if the operator being fired has used up its slice, and should yield once it comes to a
+/// point it can be resumed from;
+#[inline]
+pub(crate) fn is_exhausted() -> bool {
+    BUDGET.with(|b| b.get().map(|budget| budget.is_exhausted()).unwrap_or(false))
+}
+
+/// Tell if the inputs of the operator being fired should stop giving batches, as its slice is
+/// used up, or another batch like the last one is expected to overshoot the capacity of its
+/// outputs, see `set_input_quota`;
+#[inline]
+pub(crate) fn is_input_exhausted() -> bool {
+    BUDGET.with(|b| b.get().map(|budget| budget.is_input_exhausted()).unwrap_or(false))
+}
+
+pub(crate) struct SliceGuard {
+    _private: (),
+}
+
+impl Drop for SliceGuard {
+    fn drop(&mut self) {
+        BUDGET.with(|b| b.set(None));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn slice_records_test() {
+        {
+            let _guard = begin(TimeSlice::new(10, 0));
+            on_records(6);
+            assert!(!is_exhausted());
+            on_records(4);
+            assert!(is_exhausted());
+        }
+        // out of a firing nothing is limited
+        assert!(!is_exhausted());
+        let _guard = begin(TimeSlice::default());
+        on_records(100);
+        assert!(!is_exhausted());
+    }
+
+    #[test]
+    fn input_quota_test() {
+        {
+            let _guard = begin(TimeSlice::default());
+            // the outputs have the capacity for 4 batches of 10, where each input expands to 8
+            set_input_quota(4, 8.0, 10);
+            assert!(!is_input_exhausted());
+            // 2 batches are expected, where 2 more still fit
+            on_records(2);
+            assert!(!is_input_exhausted());
+            on_records(1);
+            assert!(!is_input_exhausted());
+            on_records(1);
+            assert!(is_input_exhausted());
+            // the outputs are never cut by the quota
+            assert!(!is_exhausted());
+        }
+        assert!(!is_input_exhausted());
+        // a scope of a single input still takes a batch of the outputs
+        assert_eq!(expected_batches(1, 8.0, 1024), 1);
+        assert_eq!(expected_batches(1024, 8.0, 1024), 8);
+    }
+
+    #[test]
+    fn slice_time_test() {
+        let _guard = begin(TimeSlice::new(0, 1000));
+        assert!(!is_exhausted());
+        std::thread::sleep(Duration::from_millis(2));
+        assert!(is_exhausted());
+    }
+}
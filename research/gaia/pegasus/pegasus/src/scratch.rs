@@ -0,0 +1,473 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The scratch memory of the temporary data the functions of the operators need for one firing
+//! only, e.g. a small adjacency list to be sorted, or the fields of a key to be hashed, which are
+//! bumped from the chunks kept by the thread running the worker instead of being allocated and
+//! freed one by one. As a thread fires the operators of one worker at a time, the scratch is
+//! reset once each firing ends, so the chunks are reused by the next firing without allocation.
+//!
+//! The scratch is reached by `with_context`, whose handle lives in the closure only, so nothing
+//! borrowed from the scratch outlives the firing it is taken in, e.g.
+//!
+//! ```
+//! use pegasus::scratch::{with_context, ScratchVec};
+//!
+//! let median = with_context(|ctx| {
+//!     let mut neighbors = ScratchVec::new(ctx.scratch());
+//!     neighbors.extend(vec![5u32, 1, 3].into_iter());
+//!     neighbors.sort_unstable();
+//!     neighbors[neighbors.len() / 2]
+//! });
+//! assert_eq!(median, 3);
+//! ```
+//!
+//! while the scratch vector can't be kept across the firings:
+//!
+//! ```compile_fail
+//! use pegasus::scratch::{with_context, ScratchVec};
+//!
+//! let kept = with_context(|ctx| ScratchVec::<u32>::new(ctx.scratch()));
+//! ```
+
+use crate::worker_id::WorkerId;
+use std::alloc::{self, Layout};
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::io::Write;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::ptr::NonNull;
+
+/// The bytes of the first chunk of a thread;
+pub const MIN_CHUNK_BYTES: usize = 4 << 10;
+/// The most bytes of the chunks kept by a thread once a firing ends, beyond which the chunks are
+/// freed, e.g. after an unusual firing of huge temporary data;
+pub const MAX_RETAINED_BYTES: usize = 1 << 20;
+// the alignment of the chunks, while the data of larger alignments are aligned in the chunks;
+const CHUNK_ALIGN: usize = 16;
+
+struct Chunk {
+    ptr: NonNull<u8>,
+    size: usize,
+}
+
+impl Chunk {
+    fn new(size: usize) -> Self {
+        let layout = Layout::from_size_align(size, CHUNK_ALIGN).expect("invalid chunk size");
+        // the size of a chunk is never zero;
+        let ptr = unsafe { alloc::alloc(layout) };
+        let ptr = NonNull::new(ptr).unwrap_or_else(|| alloc::handle_alloc_error(layout));
+        Chunk { ptr, size }
+    }
+
+    /// Bump the memory of the layout from `offset` of the chunk, and give the memory and the
+    /// offset after it, or `None` if the chunk has no room left;
+    fn bump(&self, offset: usize, layout: Layout) -> Option<(NonNull<u8>, usize)> {
+        let base = self.ptr.as_ptr() as usize;
+        let start = (base + offset).checked_add(layout.align() - 1)? & !(layout.align() - 1);
+        let end = start.checked_add(layout.size())?;
+        if end <= base + self.size {
+            let ptr = unsafe { self.ptr.as_ptr().add(start - base) };
+            Some((unsafe { NonNull::new_unchecked(ptr) }, end - base))
+        } else {
+            None
+        }
+    }
+}
+
+impl Drop for Chunk {
+    fn drop(&mut self) {
+        let layout = Layout::from_size_align(self.size, CHUNK_ALIGN).expect("invalid chunk size");
+        unsafe { alloc::dealloc(self.ptr.as_ptr(), layout) }
+    }
+}
+
+/// The bump allocator of the scratch memory, which frees nothing until it is reset once the
+/// firing ends, see the module doc;
+#[derive(Default)]
+pub struct Scratch {
+    chunks: RefCell<Vec<Chunk>>,
+    // the chunk being bumped, and the offset in it;
+    current: Cell<usize>,
+    offset: Cell<usize>,
+    // the bytes bumped since the scratch is reset;
+    bumped: Cell<usize>,
+}
+
+impl Scratch {
+    /// Allocate the memory of the layout, which is valid until the scratch is reset;
+    pub fn alloc(&self, layout: Layout) -> NonNull<u8> {
+        if layout.size() == 0 {
+            // a dangling pointer of the alignment, as no memory is needed;
+            return unsafe { NonNull::new_unchecked(layout.align() as *mut u8) };
+        }
+        self.bumped.set(self.bumped.get() + layout.size());
+        let mut chunks = self.chunks.borrow_mut();
+        while let Some(chunk) = chunks.get(self.current.get()) {
+            if let Some((ptr, offset)) = chunk.bump(self.offset.get(), layout) {
+                self.offset.set(offset);
+                return ptr;
+            }
+            self.current.set(self.current.get() + 1);
+            self.offset.set(0);
+        }
+        // a chunk of twice the last one, with the room to align the layout;
+        let last = chunks.last().map(|c| c.size).unwrap_or(MIN_CHUNK_BYTES / 2);
+        let size = (last * 2).max(layout.size() + layout.align());
+        chunks.push(Chunk::new(size));
+        self.current.set(chunks.len() - 1);
+        let (ptr, offset) = chunks[chunks.len() - 1].bump(0, layout).expect("chunk too small");
+        self.offset.set(offset);
+        ptr
+    }
+
+    /// The bytes allocated since the scratch is reset;
+    pub fn allocated_bytes(&self) -> usize {
+        self.bumped.get()
+    }
+
+    /// The bytes of the chunks kept;
+    pub fn capacity(&self) -> usize {
+        self.chunks.borrow().iter().map(|c| c.size).sum()
+    }
+
+    /// Reuse all chunks, where the memory allocated before is invalidated, so it takes `&mut`;
+    /// The chunks of a firing needing more than one are merged into one of their total bytes, up
+    /// to `MAX_RETAINED_BYTES`, so the next firing like it bumps one chunk only;
+    pub fn reset(&mut self) {
+        let chunks = self.chunks.get_mut();
+        if chunks.len() > 1 {
+            let total: usize = chunks.iter().map(|c| c.size).sum();
+            chunks.clear();
+            if total <= MAX_RETAINED_BYTES {
+                chunks.push(Chunk::new(total));
+            }
+        } else if chunks.iter().any(|c| c.size > MAX_RETAINED_BYTES) {
+            chunks.clear();
+        }
+        self.current.set(0);
+        self.offset.set(0);
+        self.bumped.set(0);
+    }
+}
+
+impl fmt::Debug for Scratch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Scratch")
+            .field("allocated", &self.allocated_bytes())
+            .field("capacity", &self.capacity())
+            .finish()
+    }
+}
+
+thread_local! {
+    static SCRATCH: RefCell<Scratch> = RefCell::new(Scratch::default());
+}
+
+/// The handle given to the functions of the operators by `with_context`, which borrows what the
+/// current firing owns;
+pub struct OperatorContext<'a> {
+    scratch: &'a Scratch,
+    worker: Option<WorkerId>,
+}
+
+impl<'a> OperatorContext<'a> {
+    /// The scratch memory of current firing, see `ScratchVec`;
+    pub fn scratch(&self) -> &'a Scratch {
+        self.scratch
+    }
+
+    /// The worker firing the operator, or `None` out of the workers;
+    pub fn worker_id(&self) -> Option<WorkerId> {
+        self.worker
+    }
+}
+
+/// Run `func` with the context of the firing of current thread, where nothing borrowed from the
+/// context can be returned; It may be called out of the workers too, where the scratch is reset
+/// once the outermost call returns instead of the firing;
+pub fn with_context<R, F: FnOnce(&OperatorContext) -> R>(func: F) -> R {
+    let (result, out_of_worker) = SCRATCH.with(|scratch| {
+        let scratch_ref = scratch.borrow();
+        let worker = crate::worker_id::get_current_worker();
+        let ctx = OperatorContext { scratch: &scratch_ref, worker };
+        let result = func(&ctx);
+        (result, worker.is_none())
+    });
+    if out_of_worker {
+        reset();
+    }
+    result
+}
+
+/// Reset the scratch of current thread unless it is borrowed, e.g. by the `with_context` the
+/// firing runs in;
+pub(crate) fn reset() {
+    SCRATCH.with(|scratch| {
+        if let Ok(mut scratch) = scratch.try_borrow_mut() {
+            scratch.reset();
+        }
+    });
+}
+
+/// The guard resetting the scratch once a firing of an operator ends;
+pub(crate) struct ResetGuard;
+
+impl Drop for ResetGuard {
+    fn drop(&mut self) {
+        reset();
+    }
+}
+
+#[inline]
+pub(crate) fn guard() -> ResetGuard {
+    ResetGuard
+}
+
+/// A vector in the scratch memory, which grows by copying its data to a larger part of the
+/// scratch, and drops its data but frees nothing once it is dropped;
+pub struct ScratchVec<'a, T> {
+    ptr: NonNull<T>,
+    len: usize,
+    cap: usize,
+    scratch: &'a Scratch,
+    _ph: PhantomData<T>,
+}
+
+impl<'a, T> ScratchVec<'a, T> {
+    pub fn new(scratch: &'a Scratch) -> Self {
+        let cap = if std::mem::size_of::<T>() == 0 { usize::MAX } else { 0 };
+        ScratchVec { ptr: NonNull::dangling(), len: 0, cap, scratch, _ph: PhantomData }
+    }
+
+    pub fn with_capacity(scratch: &'a Scratch, capacity: usize) -> Self {
+        let mut vec = ScratchVec::new(scratch);
+        vec.reserve(capacity);
+        vec
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.cap
+    }
+
+    pub fn reserve(&mut self, additional: usize) {
+        let needed = self.len.checked_add(additional).expect("capacity overflow");
+        if needed > self.cap {
+            self.grow_to(needed.max(self.cap * 2).max(4));
+        }
+    }
+
+    pub fn push(&mut self, value: T) {
+        if self.len == self.cap {
+            self.reserve(1);
+        }
+        unsafe { self.ptr.as_ptr().add(self.len).write(value) };
+        self.len += 1;
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            None
+        } else {
+            self.len -= 1;
+            Some(unsafe { self.ptr.as_ptr().add(self.len).read() })
+        }
+    }
+
+    pub fn clear(&mut self) {
+        let len = self.len;
+        // the length goes first, so a panicking drop leaks the rest instead of dropping twice;
+        self.len = 0;
+        unsafe {
+            std::ptr::drop_in_place(std::ptr::slice_from_raw_parts_mut(self.ptr.as_ptr(), len))
+        }
+    }
+
+    fn grow_to(&mut self, cap: usize) {
+        let layout = Layout::array::<T>(cap).expect("capacity overflow");
+        let ptr = self.scratch.alloc(layout).cast::<T>();
+        // the old memory is left in the scratch until it is reset;
+        unsafe { std::ptr::copy_nonoverlapping(self.ptr.as_ptr(), ptr.as_ptr(), self.len) };
+        self.ptr = ptr;
+        self.cap = cap;
+    }
+}
+
+impl<'a, T: Clone> ScratchVec<'a, T> {
+    pub fn extend_from_slice(&mut self, values: &[T]) {
+        self.reserve(values.len());
+        for value in values {
+            self.push(value.clone());
+        }
+    }
+}
+
+impl<'a, T> Deref for ScratchVec<'a, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<'a, T> DerefMut for ScratchVec<'a, T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        unsafe { std::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<'a, T> Extend<T> for ScratchVec<'a, T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for value in iter {
+            self.push(value);
+        }
+    }
+}
+
+impl<'a, T> Drop for ScratchVec<'a, T> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl<'a, T: fmt::Debug> fmt::Debug for ScratchVec<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// The bytes encoded into the scratch, e.g. to measure the encoded size of a batch;
+impl<'a> Write for ScratchVec<'a, u8> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> pegasus_common::io::WriteExt for ScratchVec<'a, u8> {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::rc::Rc;
+
+    #[test]
+    fn scratch_vec_test() {
+        let scratch = Scratch::default();
+        let mut vec = ScratchVec::new(&scratch);
+        for i in (0..100u64).rev() {
+            vec.push(i);
+        }
+        vec.sort_unstable();
+        assert_eq!(vec.len(), 100);
+        assert!(vec.iter().enumerate().all(|(i, v)| i as u64 == *v));
+        assert_eq!(vec.pop(), Some(99));
+        let mut bytes = ScratchVec::with_capacity(&scratch, 3);
+        bytes.write_all(b"abcdef").unwrap();
+        assert_eq!(&bytes[..], b"abcdef");
+        // the units are never allocated;
+        let mut units = ScratchVec::new(&scratch);
+        units.extend(std::iter::repeat_n((), 10));
+        assert_eq!(units.len(), 10);
+        assert!(scratch.allocated_bytes() >= 100 * 8 + 6);
+    }
+
+    #[test]
+    fn scratch_drop_test() {
+        let scratch = Scratch::default();
+        let counter = Rc::new(());
+        {
+            let mut vec = ScratchVec::new(&scratch);
+            for _ in 0..10 {
+                vec.push(counter.clone());
+            }
+            assert_eq!(Rc::strong_count(&counter), 11);
+            vec.clear();
+            assert_eq!(Rc::strong_count(&counter), 1);
+            vec.extend(std::iter::repeat_n(counter.clone(), 3));
+            assert_eq!(Rc::strong_count(&counter), 4);
+        }
+        assert_eq!(Rc::strong_count(&counter), 1);
+    }
+
+    #[test]
+    fn scratch_reset_test() {
+        let mut scratch = Scratch::default();
+        {
+            let mut vec = ScratchVec::new(&scratch);
+            for i in 0..10_000u64 {
+                vec.push(i);
+            }
+        }
+        assert!(scratch.chunks.borrow().len() > 1);
+        scratch.reset();
+        // the chunks of the firing are merged, to be bumped by the next firing without allocation
+        assert_eq!(scratch.chunks.borrow().len(), 1);
+        let capacity = scratch.capacity();
+        {
+            let mut vec = ScratchVec::new(&scratch);
+            for i in 0..10_000u64 {
+                vec.push(i);
+            }
+        }
+        assert_eq!(scratch.chunks.borrow().len(), 1);
+        assert_eq!(scratch.capacity(), capacity);
+        // the chunks beyond the limit are freed
+        {
+            let _ = ScratchVec::<u8>::with_capacity(&scratch, MAX_RETAINED_BYTES * 2);
+        }
+        scratch.reset();
+        assert_eq!(scratch.capacity(), 0);
+    }
+
+    #[test]
+    fn scratch_align_test() {
+        #[repr(align(64))]
+        struct Aligned(u8);
+        let scratch = Scratch::default();
+        let mut bytes = ScratchVec::new(&scratch);
+        bytes.push(1u8);
+        let mut aligned = ScratchVec::new(&scratch);
+        aligned.push(Aligned(2));
+        assert_eq!(aligned.as_ptr() as usize % 64, 0);
+        assert_eq!(aligned[0].0, 2);
+    }
+
+    #[test]
+    fn with_context_test() {
+        let sum = with_context(|ctx| {
+            assert!(ctx.worker_id().is_none());
+            let mut outer = ScratchVec::new(ctx.scratch());
+            outer.extend(1..4u32);
+            // the nested context shares the scratch, which is not reset until the outer returns
+            let inner = with_context(|ctx| {
+                let mut inner = ScratchVec::new(ctx.scratch());
+                inner.extend(4..6u32);
+                inner.iter().sum::<u32>()
+            });
+            reset();
+            outer.iter().sum::<u32>() + inner
+        });
+        assert_eq!(sum, 15);
+        SCRATCH.with(|scratch| assert_eq!(scratch.borrow().allocated_bytes(), 0));
+    }
+}
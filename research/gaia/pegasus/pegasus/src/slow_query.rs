@@ -0,0 +1,386 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The slow query log of the engine, which records the jobs running at least
+//! `SlowQueryConfig::threshold_ms` in current server, with what is needed to reproduce them: the
+//! hash of the plan, the effective `JobConf`, the profile of each operator and the peak memory of
+//! the job, together with how the job ends, e.g. failed or cancelled after running that long.
+//! The jobs are all profiled while the log is enabled, as which of them are slow is unknown until
+//! they end. A job is recorded once all its workers in current server end, where at most
+//! `max_entries` records are kept, to be fetched by `fetch_slow_queries`, and the records of the
+//! earliest jobs are dropped first. Each record is also appended to the file of `log_path` if
+//! set, as a line of json.
+
+use crate::errors::StartupError;
+use crate::profile::OperatorProfile;
+use crate::JobConf;
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::fmt::Write as FmtWrite;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// The most records kept if `SlowQueryConfig::max_entries` is not set;
+pub const DEFAULT_MAX_ENTRIES: usize = 128;
+
+/// The configuration of the slow query log, e.g. in the toml of `Configuration`:
+///
+/// ```toml
+/// [slow_query]
+/// threshold_ms = 1000
+/// max_entries = 64
+/// log_path = '/var/log/pegasus/slow_query.log'
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct SlowQueryConfig {
+    /// the jobs running at least this milliseconds are recorded;
+    pub threshold_ms: u64,
+    /// the most records kept, or `DEFAULT_MAX_ENTRIES` if not set;
+    pub max_entries: Option<usize>,
+    /// the file the records are appended to as json lines, if set;
+    pub log_path: Option<PathBuf>,
+}
+
+/// How a recorded job ends;
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JobStatus {
+    Completed,
+    /// an operator fails, which cancels the other workers of the job;
+    Failed,
+    /// cancelled by its guard, e.g. as its client goes away;
+    Cancelled,
+    /// cancelled as it runs beyond `JobConf::time_limit`;
+    TimedOut,
+}
+
+impl JobStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobStatus::Completed => "completed",
+            JobStatus::Failed => "failed",
+            JobStatus::Cancelled => "cancelled",
+            JobStatus::TimedOut => "timed_out",
+        }
+    }
+}
+
+/// A job recorded by the slow query log;
+#[derive(Debug, Clone)]
+pub struct SlowQueryRecord {
+    pub job_id: u64,
+    pub job_name: String,
+    /// the hash of the plan of the job, see `JobConf::plan_hash`;
+    pub plan_hash: u64,
+    /// the configuration the job runs with;
+    pub conf: JobConf,
+    pub status: JobStatus,
+    /// the error failing the job, if failed;
+    pub error: Option<String>,
+    /// the wall time from the job is submitted until all its workers in current server end;
+    pub elapsed_ms: u64,
+    /// the most bytes the job holds at once in current server, which is 0 if the memory is not
+    /// traced, i.e. without the feature `mem`;
+    pub peak_memory: u64,
+    /// the profiles of the operators, in the order of their indexes;
+    pub operators: Vec<OperatorProfile>,
+    /// when the job ends, in milliseconds since the unix epoch;
+    pub finished_at_ms: u64,
+}
+
+impl SlowQueryRecord {
+    /// The configuration of the job as a json object;
+    pub fn conf_json(&self) -> String {
+        let conf = &self.conf;
+        let mut json = String::new();
+        write!(
+            json,
+            "{{\"job_id\":{},\"job_name\":{},\"workers\":{},\"servers\":{:?},\"time_limit\":{},\
+             \"batch_size\":{},\"output_capacity\":{},\"batch_flush_interval_ms\":{},\
+             \"memory_limit\":{},\"cache_limit\":{},\"bulking\":{},\"vertex_limit\":{},\
+             \"edge_limit\":{},\"result_limit\":{},\"overflow\":\"{:?}\",\"skew_factor\":{},\
+             \"session_id\":{},\"sort_spill_limit\":{},\"slice_records\":{},\"slice_us\":{},\
+             \"profile\":{},\"worker_hint\":{}}}",
+            conf.job_id,
+            json_str(&conf.job_name),
+            conf.workers,
+            conf.servers(),
+            conf.time_limit,
+            conf.batch_size,
+            conf.output_capacity,
+            conf.batch_flush_interval_ms,
+            conf.memory_limit,
+            conf.cache_limit,
+            conf.bulking,
+            conf.vertex_limit,
+            conf.edge_limit,
+            conf.result_limit,
+            conf.overflow,
+            conf.skew_factor,
+            conf.session_id,
+            conf.sort_spill_limit,
+            conf.slice_records,
+            conf.slice_us,
+            conf.profile,
+            json_str(&format!("{:?}", conf.get_worker_hint())),
+        )
+        .expect("write json failure");
+        json
+    }
+
+    /// The record as a line of json, without the line break;
+    pub fn to_json(&self) -> String {
+        let mut json = String::new();
+        write!(
+            json,
+            "{{\"job_id\":{},\"job_name\":{},\"plan_hash\":{},\"status\":\"{}\",\"error\":{},\
+             \"elapsed_ms\":{},\"peak_memory\":{},\"finished_at_ms\":{},\"conf\":{},\
+             \"operators\":[",
+            self.job_id,
+            json_str(&self.job_name),
+            self.plan_hash,
+            self.status.as_str(),
+            self.error.as_ref().map(|e| json_str(e)).unwrap_or_else(|| "null".to_owned()),
+            self.elapsed_ms,
+            self.peak_memory,
+            self.finished_at_ms,
+            self.conf_json(),
+        )
+        .expect("write json failure");
+        for (i, op) in self.operators.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            write!(
+                json,
+                "{{\"index\":{},\"name\":{},\"workers\":{},\"elapsed_us\":{{\"min\":{},\"max\":{},\
+                 \"avg\":{}}},\"records\":{{\"min\":{},\"max\":{},\"sum\":{}}},\
+                 \"cross_server_bytes\":{},\"stalls\":{}}}",
+                op.index,
+                json_str(&op.name),
+                op.records.count,
+                op.elapsed_us.min,
+                op.elapsed_us.max,
+                op.elapsed_us.avg(),
+                op.records.min,
+                op.records.max,
+                op.records.sum,
+                op.cross_server_bytes.sum,
+                op.stalls.sum,
+            )
+            .expect("write json failure");
+        }
+        json.push_str("]}");
+        json
+    }
+}
+
+fn json_str(s: &str) -> String {
+    let mut json = String::with_capacity(s.len() + 2);
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                write!(json, "\\u{:04x}", c as u32).expect("write json failure");
+            }
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+struct SlowQueryLog {
+    threshold_ms: u64,
+    max_entries: usize,
+    records: VecDeque<SlowQueryRecord>,
+    sink: Option<File>,
+}
+
+impl SlowQueryLog {
+    fn push(&mut self, record: SlowQueryRecord) {
+        if let Some(sink) = self.sink.as_mut() {
+            // written at once, so the lines of the records never interleave;
+            let line = format!("{}\n", record.to_json());
+            if let Err(e) = sink.write_all(line.as_bytes()) {
+                warn!("write slow query of job {} failure: {}", record.job_id, e);
+            }
+        }
+        if self.records.len() >= self.max_entries {
+            self.records.pop_front();
+        }
+        self.records.push_back(record);
+    }
+}
+
+lazy_static! {
+    static ref SLOW_QUERY_LOG: Mutex<Option<SlowQueryLog>> = Mutex::new(None);
+}
+
+// set once the log is enabled, to skip tracing the jobs if it is not
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Enable the slow query log by the configuration, which drops the records kept before if any,
+/// e.g. by `startup` with `Configuration::slow_query`;
+pub fn enable(config: &SlowQueryConfig) -> Result<(), StartupError> {
+    let sink = match config.log_path.as_ref() {
+        Some(path) => Some(
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map_err(StartupError::SlowQueryLog)?,
+        ),
+        None => None,
+    };
+    let log = SlowQueryLog {
+        threshold_ms: config.threshold_ms,
+        max_entries: config.max_entries.unwrap_or(DEFAULT_MAX_ENTRIES),
+        records: VecDeque::new(),
+        sink,
+    };
+    SLOW_QUERY_LOG.lock().expect("lock poisoned").replace(log);
+    ENABLED.store(true, Ordering::SeqCst);
+    Ok(())
+}
+
+#[inline]
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Get the records of the slow jobs kept in current server, from the earliest to the latest;
+pub fn fetch_slow_queries() -> Vec<SlowQueryRecord> {
+    match SLOW_QUERY_LOG.lock() {
+        Ok(log) => {
+            log.as_ref().map(|log| log.records.iter().cloned().collect()).unwrap_or_default()
+        }
+        Err(_) => vec![],
+    }
+}
+
+/// What is traced of a job in current server while it runs, shared by its workers;
+pub(crate) struct JobTrace {
+    conf: JobConf,
+    start: Instant,
+    // how the job ends, where the first of the workers to tell wins;
+    status: Mutex<Option<(JobStatus, Option<String>)>>,
+    peak_memory: AtomicUsize,
+}
+
+impl JobTrace {
+    pub(crate) fn on_memory(&self, usage: usize) {
+        self.peak_memory.fetch_max(usage, Ordering::Relaxed);
+    }
+
+    pub(crate) fn on_end(&self, status: JobStatus, error: Option<String>) {
+        if let Ok(mut end) = self.status.lock() {
+            if end.is_none() {
+                end.replace((status, error));
+            }
+        }
+    }
+}
+
+/// Start to trace the job if the slow query log is enabled;
+pub(crate) fn trace(conf: &JobConf) -> Option<Arc<JobTrace>> {
+    if is_enabled() {
+        Some(Arc::new(JobTrace {
+            conf: conf.clone(),
+            start: Instant::now(),
+            status: Mutex::new(None),
+            peak_memory: AtomicUsize::new(0),
+        }))
+    } else {
+        None
+    }
+}
+
+/// Record the job if it is slow, once all its workers in current server end, after which the
+/// profiles of all its operators have been reported;
+pub(crate) fn on_job_end(trace: &JobTrace) {
+    let elapsed_ms = trace.start.elapsed().as_millis() as u64;
+    let mut log = SLOW_QUERY_LOG.lock().expect("lock poisoned");
+    let log = match log.as_mut() {
+        Some(log) if elapsed_ms >= log.threshold_ms => log,
+        _ => return,
+    };
+    let (status, error) = trace
+        .status
+        .lock()
+        .ok()
+        .and_then(|end| end.clone())
+        .unwrap_or((JobStatus::Completed, None));
+    let conf = &trace.conf;
+    let operators =
+        crate::fetch_job_profile(conf.job_id).map(|profile| profile.operators).unwrap_or_default();
+    let finished_at_ms =
+        SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0);
+    warn!("job {} is slow, {} after {} ms;", conf.job_id, status.as_str(), elapsed_ms);
+    log.push(SlowQueryRecord {
+        job_id: conf.job_id,
+        job_name: conf.job_name.clone(),
+        plan_hash: conf.plan_hash,
+        conf: conf.clone(),
+        status,
+        error,
+        elapsed_ms,
+        peak_memory: trace.peak_memory.load(Ordering::Relaxed) as u64,
+        operators,
+        finished_at_ms,
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn json_str_test() {
+        assert_eq!(json_str("g.V()"), "\"g.V()\"");
+        assert_eq!(json_str("has(\"name\",'a\\b')\n"), "\"has(\\\"name\\\",'a\\\\b')\\n\"");
+        assert_eq!(json_str("\u{1}"), "\"\\u0001\"");
+    }
+
+    #[test]
+    fn record_to_json_test() {
+        let record = SlowQueryRecord {
+            job_id: 1,
+            job_name: "g.V().count()".to_owned(),
+            plan_hash: 42,
+            conf: JobConf::new(1, "g.V().count()", 2),
+            status: JobStatus::Failed,
+            error: Some("vertex limit exceeded".to_owned()),
+            elapsed_ms: 1500,
+            peak_memory: 0,
+            operators: vec![],
+            finished_at_ms: 0,
+        };
+        let json = record.to_json();
+        assert!(json.starts_with("{\"job_id\":1,\"job_name\":\"g.V().count()\",\"plan_hash\":42,"));
+        assert!(json.contains("\"status\":\"failed\",\"error\":\"vertex limit exceeded\""));
+        assert!(json.contains("\"conf\":{\"job_id\":1,"));
+        assert!(json.contains("\"workers\":2,"));
+        assert!(json.ends_with("\"operators\":[]}"));
+    }
+}
@@ -0,0 +1,116 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The structured spans of job execution, emitted into the `tracing` crate with the feature
+//! `tracing`, where a job has a root span of its id and the hash of its query, under which
+//! each firing of an operator is a span of the operator name, worker index and scope tag, and the
+//! batches sent and received by channels are events of their sizes. Without the feature,
+//! all of these are no-ops.
+
+use crate::api::meta::OperatorMeta;
+use crate::{JobConf, Tag};
+
+#[cfg(feature = "tracing")]
+pub use tracing::Span;
+
+/// The no-op span without the feature `tracing`
+#[cfg(not(feature = "tracing"))]
+#[derive(Clone, Debug, Default)]
+pub struct Span;
+
+#[cfg(not(feature = "tracing"))]
+impl Span {
+    #[inline]
+    pub fn entered(self) -> Self {
+        self
+    }
+}
+
+/// Check if the spans and the logs of workers are emitted into `tracing`
+#[inline]
+pub const fn is_enabled() -> bool {
+    cfg!(feature = "tracing")
+}
+
+/// The hash of the query of a job, by its name, which is the query text for the queries submitted
+/// by the gremlin compiler
+pub fn query_hash(conf: &JobConf) -> u64 {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    conf.job_name.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The root span of a job in current server
+#[cfg(feature = "tracing")]
+pub fn job_span(conf: &JobConf) -> Span {
+    tracing::info_span!("job", job_id = conf.job_id, query_hash = query_hash(conf))
+}
+
+#[cfg(not(feature = "tracing"))]
+#[inline]
+pub fn job_span(_conf: &JobConf) -> Span {
+    Span
+}
+
+/// The span of an operator being fired on the scope of the tag, under the span of its job
+#[cfg(feature = "tracing")]
+pub fn operator_span(meta: &OperatorMeta, tag: &Tag) -> Span {
+    let worker = crate::get_current_worker().map(|w| w.index);
+    tracing::trace_span!(
+        "operator",
+        operator = meta.name.as_str(),
+        index = meta.index,
+        worker = ?worker,
+        tag = ?tag
+    )
+}
+
+#[cfg(not(feature = "tracing"))]
+#[inline]
+pub fn operator_span(_meta: &OperatorMeta, _tag: &Tag) -> Span {
+    Span
+}
+
+/// A batch of `size` data of the scope of the tag is sent into the channel
+#[inline]
+pub fn on_send(_ch_index: u32, _tag: &Tag, _size: usize) {
+    #[cfg(feature = "tracing")]
+    tracing::trace!(ch = _ch_index, tag = ?_tag, batch_size = _size, "send");
+}
+
+/// A batch of `size` data of the scope of the tag is received from the channel
+#[inline]
+pub fn on_recv(_ch_index: u32, _tag: &Tag, _size: usize) {
+    #[cfg(feature = "tracing")]
+    tracing::trace!(ch = _ch_index, tag = ?_tag, batch_size = _size, "recv");
+}
+
+/// Forward the logs of `info_worker!` and so on into `tracing`, along with the current worker
+#[doc(hidden)]
+#[inline]
+pub fn forward_log(_lvl: log::Level, _args: std::fmt::Arguments) {
+    #[cfg(feature = "tracing")]
+    {
+        let worker = crate::get_current_worker().map(|w| w.index);
+        match _lvl {
+            log::Level::Error => tracing::error!(worker = ?worker, "{}", _args),
+            log::Level::Warn => tracing::warn!(worker = ?worker, "{}", _args),
+            log::Level::Info => tracing::info!(worker = ?worker, "{}", _args),
+            log::Level::Debug => tracing::debug!(worker = ?worker, "{}", _args),
+            log::Level::Trace => tracing::trace!(worker = ?worker, "{}", _args),
+        }
+    }
+}
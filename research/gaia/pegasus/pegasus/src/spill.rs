@@ -0,0 +1,177 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The temp files an operator spills its data into once it holds too much data in memory, e.g. the
+//! sorted runs of a sort, or the batches received by an input beyond its receive buffers, which
+//! are appended one by one as they arrive, see `SpillAppender`. Each file is registered with the
+//! temp resources of the job, see `crate::temp`, and removed once it is dropped, and the directory
+//! of a job is removed once all workers of the job in current server end, no matter the job is
+//! completed, canceled or failed.
+
+use crate::codec::{Decode, Encode};
+use crate::temp::TempPath;
+use crate::JobConf;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+lazy_static! {
+    static ref SPILL_FILE_SEQ: AtomicU64 = AtomicU64::new(0);
+}
+
+/// The directory the job spills into, which is the directory of the job in the temp space of
+/// current process unless the job spills into its own directory
+pub(crate) fn job_spill_dir(conf: &JobConf) -> PathBuf {
+    match conf.spill_dir.as_ref() {
+        Some(root) => root.join(format!("job_{}", conf.job_id)),
+        None => crate::temp::instance().job_dir(conf.job_id),
+    }
+}
+
+/// Remove the directory of the job along with the files left in it
+pub(crate) fn remove_job_spill_dir(conf: &JobConf) {
+    crate::temp::instance().release_job(conf.job_id);
+    let dir = job_spill_dir(conf);
+    if dir.exists() {
+        if let Err(e) = fs::remove_dir_all(&dir) {
+            warn!("fail to remove the spill directory {:?} of job {}: {}", dir, conf.job_id, e);
+        }
+    }
+}
+
+/// A temp file of encoded records, which is removed once dropped
+pub(crate) struct SpillFile<D> {
+    file: TempPath,
+    len: usize,
+    _ph: PhantomData<D>,
+}
+
+impl<D: Encode + Decode> SpillFile<D> {
+    /// Spill the records into a new file in the directory of the job
+    pub fn write<I: IntoIterator<Item = D>>(conf: &JobConf, records: I) -> io::Result<Self> {
+        let dir = job_spill_dir(conf);
+        fs::create_dir_all(&dir)?;
+        let seq = SPILL_FILE_SEQ.fetch_add(1, Ordering::Relaxed);
+        let path = dir.join(format!("{}.spill", seq));
+        let file = crate::temp::instance().register(conf.job_id, path);
+        let mut file = SpillFile { file, len: 0, _ph: PhantomData };
+        // the file is removed by drop if it fails to be written;
+        let mut writer = BufWriter::new(File::create(file.file.path())?);
+        for record in records {
+            record.write_to(&mut writer)?;
+            file.len += 1;
+        }
+        writer.flush()?;
+        Ok(file)
+    }
+
+    /// Create a new file in the directory of the job, which the records are appended to one by
+    /// one before they are read back
+    pub fn append(conf: &JobConf) -> io::Result<SpillAppender<D>> {
+        let dir = job_spill_dir(conf);
+        fs::create_dir_all(&dir)?;
+        let seq = SPILL_FILE_SEQ.fetch_add(1, Ordering::Relaxed);
+        let path = dir.join(format!("{}.spill", seq));
+        let file = crate::temp::instance().register(conf.job_id, path);
+        let writer = BufWriter::new(File::create(file.path())?);
+        Ok(SpillAppender { file: SpillFile { file, len: 0, _ph: PhantomData }, writer })
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Read the records back in the order they were written
+    pub fn into_reader(self) -> io::Result<SpillReader<D>> {
+        let reader = BufReader::new(File::open(self.file.path())?);
+        Ok(SpillReader { remaining: self.len, reader, _file: self })
+    }
+}
+
+/// A spill file being written, whose records are read back once it is finished
+pub(crate) struct SpillAppender<D> {
+    file: SpillFile<D>,
+    writer: BufWriter<File>,
+}
+
+impl<D: Encode + Decode> SpillAppender<D> {
+    pub fn push(&mut self, record: &D) -> io::Result<()> {
+        record.write_to(&mut self.writer)?;
+        self.file.len += 1;
+        Ok(())
+    }
+
+    /// Flush the records appended, which returns the file along with its size in bytes
+    pub fn finish(mut self) -> io::Result<(SpillFile<D>, u64)> {
+        self.writer.flush()?;
+        let bytes = self.writer.get_ref().metadata()?.len();
+        Ok((self.file, bytes))
+    }
+}
+
+pub(crate) struct SpillReader<D> {
+    remaining: usize,
+    reader: BufReader<File>,
+    _file: SpillFile<D>,
+}
+
+impl<D: Decode> Iterator for SpillReader<D> {
+    type Item = io::Result<D>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            None
+        } else {
+            self.remaining -= 1;
+            Some(D::read_from(&mut self.reader))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn spill_file_test() {
+        let mut conf = JobConf::new(1, "spill_file_test", 1);
+        conf.spill_dir =
+            Some(std::env::temp_dir().join(format!("spill_file_test_{}", std::process::id())));
+        let file = SpillFile::write(&conf, vec![3u64, 1, 2]).unwrap();
+        assert_eq!(file.len(), 3);
+        let path = file.file.path().to_path_buf();
+        assert!(path.exists());
+        let records = file.into_reader().unwrap().collect::<io::Result<Vec<u64>>>().unwrap();
+        assert_eq!(records, vec![3, 1, 2]);
+        assert!(!path.exists());
+
+        let mut appender = SpillFile::append(&conf).unwrap();
+        for record in [5u64, 4] {
+            appender.push(&record).unwrap();
+        }
+        let (file, bytes) = appender.finish().unwrap();
+        assert!(bytes > 0);
+        let records = file.into_reader().unwrap().collect::<io::Result<Vec<u64>>>().unwrap();
+        assert_eq!(records, vec![5, 4]);
+
+        let file = SpillFile::write(&conf, vec![1u64]).unwrap();
+        remove_job_spill_dir(&conf);
+        assert!(!job_spill_dir(&conf).exists());
+        drop(file);
+        fs::remove_dir_all(conf.spill_dir.unwrap()).ok();
+    }
+}
@@ -14,13 +14,14 @@
 //! limitations under the License.
 
 use crate::api::meta::{OperatorMeta, ScopePrior};
+use crate::api::RetryPolicy;
 use crate::communication::output::{OutputBuilderImpl, OutputEntry};
 use crate::communication::Channel;
 use crate::dataflow::{DataflowBuilder, OperatorIndex, OperatorRef};
 use crate::errors::BuildJobError;
 use crate::graph::{Edge, Port};
 use crate::operator::{OperatorBuilder, OperatorCore};
-use crate::Data;
+use crate::{Data, MIN_BINARY_OUTPUT_CAPACITY, MIN_OUTPUT_CAPACITY};
 
 pub struct Stream<D: Data> {
     pub(crate) source: Port,
@@ -28,6 +29,17 @@ pub struct Stream<D: Data> {
     pub(crate) scope_order: Vec<ScopePrior>,
     outputs: OutputBuilderImpl<D>,
     dfb: DataflowBuilder,
+    // the retry policy of the next map or flat_map on the stream, set by `with_retry`;
+    pub(crate) retry: Option<RetryPolicy>,
+    // the fanout of the next operator built on the stream, set by `with_fanout_hint`;
+    fanout: Option<f64>,
+    // the type of the keys the stream is exchanged by, if it is the output of `exchange_by`;
+    pub(crate) partitioned_by: Option<&'static str>,
+    // whether each scope the stream is in, innermost last, is entered by `enter_scope`, as only
+    // those are left by `leave_scope`;
+    custom_scopes: Vec<bool>,
+    // whether each scope the stream is in, innermost last, is the body of a loop of `iterate`;
+    loop_scopes: Vec<bool>,
 }
 
 impl<D: Data> Stream<D> {
@@ -38,6 +50,11 @@ impl<D: Data> Stream<D> {
             scope_order: vec![ScopePrior::None],
             outputs,
             dfb: dfb.clone(),
+            retry: None,
+            fanout: None,
+            partitioned_by: None,
+            custom_scopes: vec![],
+            loop_scopes: vec![],
         }
     }
 
@@ -48,6 +65,11 @@ impl<D: Data> Stream<D> {
             scope_order: parent.scope_order.clone(),
             outputs,
             dfb: parent.dfb.clone(),
+            retry: None,
+            fanout: None,
+            partitioned_by: None,
+            custom_scopes: parent.custom_scopes.clone(),
+            loop_scopes: parent.loop_scopes.clone(),
         }
     }
 
@@ -59,6 +81,24 @@ impl<D: Data> Stream<D> {
         self.dfb.worker_id.index
     }
 
+    /// The index of the server current worker runs on, see `WorkerId::server_id`;
+    pub fn server_id(&self) -> u32 {
+        self.dfb.worker_id.server_id
+    }
+
+    /// The number of workers on each server;
+    pub fn local_peers(&self) -> u32 {
+        self.dfb.worker_id.local_peers
+    }
+
+    /// Whether a global aggregation is better to gather the data of each server first, i.e. the
+    /// job runs on more than one server, each of more than one worker, whose partial results cross
+    /// the network instead of those of every worker;
+    pub fn gather_per_server(&self) -> bool {
+        let id = &self.dfb.worker_id;
+        id.local_peers > 1 && id.servers() > 1
+    }
+
     pub fn spawn<O: Data>(&self, op: &mut OperatorBuilder) -> Stream<O> {
         let outputs = op.new_output::<O>();
         Stream::inherit(self, outputs)
@@ -75,11 +115,18 @@ impl<D: Data> Stream<D> {
         C: Into<Channel<D>>,
         F: FnOnce(&mut OperatorMeta) -> Box<dyn OperatorCore>,
     {
+        self.check_output_capacity(name, MIN_OUTPUT_CAPACITY)?;
+        let loop_depths = self.loop_depths();
+        let fanout = self.fanout;
         let mut op = self.dfb.construct_operator(
             name,
             self.scope_depth,
             self.scope_order().clone(),
-            op_builder,
+            |meta| {
+                meta.loop_depths = loop_depths;
+                meta.fanout = fanout;
+                op_builder(meta)
+            },
         );
         self.connect(&mut op, channel.into())?;
         Ok(op)
@@ -123,6 +170,7 @@ impl<D: Data> Stream<D> {
             BuildJobError::unsupported(format!("Build {} operator failure, left and right stream are in different scope hierarchy({}/{});",
                         name, self.scope_depth, other.scope_depth))
         } else {
+            self.check_output_capacity(name, MIN_BINARY_OUTPUT_CAPACITY)?;
             let mut op = self.add_operator(name, ch_left, op_builder)?;
             other.connect(&mut op, ch_right.into())?;
             let output = op.new_output::<O>();
@@ -147,18 +195,99 @@ impl<D: Data> Stream<D> {
         Ok((left, right))
     }
 
-    pub fn enter_scope(mut self) -> Self {
+    /// Retry the next `map` or `flat_map` on the stream by the policy, whose function must be
+    /// marked as idempotent by `Idempotent`, or the operator fails to be built; A batch failed to
+    /// be mapped is mapped again from its first datum, until it succeeds or all attempts fail,
+    /// when the error of the last attempt fails the job;
+    pub fn with_retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = Some(policy);
+        self
+    }
+
+    /// Hint the outputs the next operator built on the stream expects of each input, e.g. of a
+    /// `flat_map` by a closure, which bounds the inputs it takes in each firing by the capacity of
+    /// its outputs, see `OperatorMeta::set_fanout`; It overrides the fanout the function declares
+    /// by `FlatMapFunction::fanout`;
+    pub fn with_fanout_hint(mut self, fanout: f64) -> Self {
+        self.fanout = Some(fanout);
+        self
+    }
+
+    /// Name the operators built on the stream by `func` after `name`, e.g. after the step they are
+    /// compiled from, as shown in the printed plan and the profile of the job;
+    pub fn named<O, F>(&self, name: &str, func: F) -> Result<Stream<O>, BuildJobError>
+    where
+        O: Data,
+        F: FnOnce(&Stream<D>) -> Result<Stream<O>, BuildJobError>,
+    {
+        self.dfb.named(name, || func(self))
+    }
+
+    /// Reject the output capacity of the job which may deadlock the operator, as it never has the
+    /// capacity to be scheduled for all its inputs;
+    fn check_output_capacity(&self, name: &str, least: u32) -> Result<(), BuildJobError> {
+        let capacity = self.dfb.config.output_capacity;
+        if capacity < least {
+            BuildJobError::unsupported(format!(
+                "output_capacity {} is below {} needed by operator {} to be scheduled for all its \
+                 inputs, which may deadlock the job;",
+                capacity, least, name
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Move the stream into a child scope, whose data are tagged one level deeper;
+    pub(crate) fn into_child_scope(mut self) -> Self {
         self.scope_depth += 1;
         self.scope_order.push(ScopePrior::None);
+        self.custom_scopes.push(false);
+        self.loop_scopes.push(false);
         self
     }
 
-    pub fn leave_scope(mut self) -> Self {
+    /// Move the stream into a child scope entered by `enter_scope`;
+    pub(crate) fn into_custom_scope(self) -> Self {
+        let mut stream = self.into_child_scope();
+        stream.custom_scopes.pop();
+        stream.custom_scopes.push(true);
+        stream
+    }
+
+    /// Mark the current scope of the stream as the body of a loop, whose rounds are told to the
+    /// functions of the operators in it;
+    pub(crate) fn into_loop_scope(mut self) -> Self {
+        if let Some(is_loop) = self.loop_scopes.last_mut() {
+            *is_loop = true;
+        }
+        self
+    }
+
+    /// Move the stream back to the parent scope of its current one;
+    pub(crate) fn into_parent_scope(mut self) -> Self {
         self.scope_depth -= 1;
         self.scope_order.pop();
+        self.custom_scopes.pop();
+        self.loop_scopes.pop();
         self
     }
 
+    /// The depths of the scopes of loops the stream is in, outermost first;
+    fn loop_depths(&self) -> Vec<usize> {
+        self.loop_scopes
+            .iter()
+            .enumerate()
+            .filter(|(_, is_loop)| **is_loop)
+            .map(|(i, _)| i + 1)
+            .collect()
+    }
+
+    /// Whether the current scope of the stream is entered by `enter_scope`;
+    pub(crate) fn in_custom_scope(&self) -> bool {
+        self.custom_scopes.last().copied().unwrap_or(false)
+    }
+
     pub fn connect_to(
         &self, op_index: OperatorIndex, channel: Channel<D>,
     ) -> Result<(), BuildJobError> {
@@ -0,0 +1,419 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Submit a job to all its servers by two phases, so that the job starts on either all or none of
+//! them, instead of its workers started on some servers waiting for the peers never started on
+//! the others, e.g. where the job fails to be built or is over the job limit of a server.
+//!
+//! Each server prepares the job with the plan it receives, by reserving a slot of the job limit,
+//! see `Configuration::max_jobs`, and building the workers of the job. The coordinator, i.e. the
+//! first server of the job, asks all the others to vote once it is prepared itself, and each of
+//! them votes the result of its preparation in reply. The coordinator commits the job only if all
+//! the servers are prepared in `JobConf::submit_timeout_ms`, or aborts it with the errors of all
+//! the servers failed, or of those not voted in time, and tells the decision to all the others,
+//! which start the workers of the job only once committed. The coordinator failed to prepare the
+//! job aborts it at once without asking for the votes.
+//!
+//! A server not asked to vote in the timeout, or not told of the decision in twice the timeout
+//! after voting, aborts the job by itself, and tells the coordinator so, which aborts the job on
+//! all the servers if it is not decided yet. The coordinator decides in the timeout after asking,
+//! so the abort of a server not asked in time always comes before the decision, unless it is
+//! delayed over the timeout. The messages of the submission go through a channel of the job apart
+//! from its data, whose index is never taken by the channels of the dataflow.
+//!
+//! The slots of a batch of jobs may be reserved all at once ahead by `admit`, e.g. of the queries
+//! a client submits together, so that the jobs are either all admitted or none, instead of those
+//! submitted later rejected by the limit; Each job admitted takes its slot as it is submitted.
+
+use crate::errors::{BuildJobError, JobSubmitError};
+use crate::JobConf;
+use pegasus_common::codec::{Decode, Encode};
+use pegasus_common::io::{ReadExt, WriteExt};
+use pegasus_network::{IPCReceiver, IPCSender};
+use std::collections::HashSet;
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How often the messages of the submission are checked while waiting;
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// The most jobs running on current server at once, 0 means no limit;
+static MAX_JOBS: AtomicUsize = AtomicUsize::new(0);
+
+lazy_static! {
+    /// The jobs prepared but not started yet on current server, which are counted as running;
+    static ref RESERVED: Mutex<usize> = Mutex::new(0);
+    /// The jobs admitted whose slots are reserved but not taken yet, which are locked after
+    /// `RESERVED`;
+    static ref ADMITTED: Mutex<HashSet<u64>> = Mutex::new(HashSet::new());
+}
+
+pub(crate) fn set_max_jobs(max_jobs: u32) {
+    MAX_JOBS.store(max_jobs as usize, Ordering::SeqCst);
+}
+
+/// The slot of a job reserved in the job limit of current server, which is released once the job
+/// starts, when it is counted among the running jobs instead, or is aborted;
+pub(crate) struct Reservation {
+    _private: (),
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        let mut reserved = RESERVED.lock().expect("lock poisoned");
+        *reserved -= 1;
+    }
+}
+
+/// Reserve a slot of the job in the job limit of current server, or take the slot reserved for it
+/// if it is admitted ahead;
+pub(crate) fn reserve(job_id: u64) -> Result<Reservation, JobSubmitError> {
+    let max_jobs = MAX_JOBS.load(Ordering::SeqCst);
+    let mut reserved = RESERVED.lock().expect("lock poisoned");
+    if ADMITTED.lock().expect("lock poisoned").remove(&job_id) {
+        return Ok(Reservation { _private: () });
+    }
+    if max_jobs > 0 && *reserved + crate::drain::running_jobs() >= max_jobs {
+        return Err(JobSubmitError::OverJobLimit(max_jobs as u32));
+    }
+    *reserved += 1;
+    Ok(Reservation { _private: () })
+}
+
+/// The slots reserved for the jobs admitted together by `admit`, where those not taken by the jobs
+/// are released once it is dropped, e.g. of the jobs rejected before submitted;
+pub struct Admission {
+    job_ids: Vec<u64>,
+}
+
+impl Drop for Admission {
+    fn drop(&mut self) {
+        let mut reserved = RESERVED.lock().expect("lock poisoned");
+        let mut admitted = ADMITTED.lock().expect("lock poisoned");
+        for job_id in self.job_ids.iter() {
+            if admitted.remove(job_id) {
+                *reserved -= 1;
+            }
+        }
+    }
+}
+
+/// Admit the jobs of the ids all at once, by reserving their slots in the job limit of current
+/// server, see `Configuration::max_jobs`, which are taken by the jobs as they are submitted by
+/// `crate::run`; None is admitted if the server is draining, or has not as many slots left;
+pub fn admit(job_ids: &[u64]) -> Result<Admission, JobSubmitError> {
+    if crate::drain::is_draining() {
+        return Err(JobSubmitError::Draining);
+    }
+    let job_ids: Vec<u64> = job_ids.iter().copied().collect::<HashSet<_>>().into_iter().collect();
+    let max_jobs = MAX_JOBS.load(Ordering::SeqCst);
+    let mut reserved = RESERVED.lock().expect("lock poisoned");
+    if max_jobs > 0 && *reserved + crate::drain::running_jobs() + job_ids.len() > max_jobs {
+        return Err(JobSubmitError::OverJobLimit(max_jobs as u32));
+    }
+    let mut admitted = ADMITTED.lock().expect("lock poisoned");
+    if let Some(job_id) = job_ids.iter().find(|id| admitted.contains(id)) {
+        let err = format!("job {} is admitted already", job_id);
+        return Err(JobSubmitError::Build(BuildJobError::from(err)));
+    }
+    admitted.extend(job_ids.iter().copied());
+    *reserved += job_ids.len();
+    Ok(Admission { job_ids })
+}
+
+/// The messages of the submission between the servers of a job;
+enum SubmitMessage {
+    /// the coordinator asks the others to vote, once it prepares the job;
+    Prepare,
+    /// the vote of a server to the coordinator, with the error it fails to prepare the job by;
+    Vote { server_id: u64, error: Option<String> },
+    /// a server tells the coordinator that it aborts the job by the error, e.g. as it is not
+    /// asked to vote in time;
+    Abort { server_id: u64, error: String },
+    /// the decision of the coordinator, which aborts the job by the error if any;
+    Decide { error: Option<String> },
+}
+
+fn write_error<W: WriteExt>(error: &Option<String>, writer: &mut W) -> io::Result<()> {
+    match error {
+        Some(error) => {
+            writer.write_u8(1)?;
+            error.write_to(writer)
+        }
+        None => writer.write_u8(0),
+    }
+}
+
+fn read_error<R: ReadExt>(reader: &mut R) -> io::Result<Option<String>> {
+    match reader.read_u8()? {
+        0 => Ok(None),
+        _ => Ok(Some(String::read_from(reader)?)),
+    }
+}
+
+impl Encode for SubmitMessage {
+    fn write_to<W: WriteExt>(&self, writer: &mut W) -> io::Result<()> {
+        match self {
+            SubmitMessage::Vote { server_id, error } => {
+                writer.write_u8(0)?;
+                writer.write_u64(*server_id)?;
+                write_error(error, writer)
+            }
+            SubmitMessage::Decide { error } => {
+                writer.write_u8(1)?;
+                write_error(error, writer)
+            }
+            SubmitMessage::Prepare => writer.write_u8(2),
+            SubmitMessage::Abort { server_id, error } => {
+                writer.write_u8(3)?;
+                writer.write_u64(*server_id)?;
+                error.write_to(writer)
+            }
+        }
+    }
+}
+
+impl Decode for SubmitMessage {
+    fn read_from<R: ReadExt>(reader: &mut R) -> io::Result<Self> {
+        match reader.read_u8()? {
+            0 => {
+                let server_id = reader.read_u64()?;
+                let error = read_error(reader)?;
+                Ok(SubmitMessage::Vote { server_id, error })
+            }
+            1 => Ok(SubmitMessage::Decide { error: read_error(reader)? }),
+            2 => Ok(SubmitMessage::Prepare),
+            3 => {
+                let server_id = reader.read_u64()?;
+                let error = String::read_from(reader)?;
+                Ok(SubmitMessage::Abort { server_id, error })
+            }
+            kind => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unknown submit message {}", kind),
+            )),
+        }
+    }
+}
+
+/// The id of the channel of the submission of the job, whose index is out of those of the
+/// dataflow channels, see `data_plane::encode_channel_id`;
+fn submit_channel_id(job_id: u64) -> u128 {
+    ((job_id as u128) << 64) | ((u32::MAX as u128) << 32)
+}
+
+/// Decide whether to start the job prepared on current server as `prepared`, which is started
+/// only if it is prepared on all its servers, or the job is aborted with the errors of the
+/// servers failed; A job of a single server is decided by its own preparation;
+pub(crate) fn decide<T>(
+    conf: &JobConf, server_id: u64, prepared: Result<T, JobSubmitError>,
+) -> Result<T, JobSubmitError> {
+    let servers = conf.servers();
+    if servers.len() <= 1 {
+        return prepared;
+    }
+    let error = prepared.as_ref().err().map(|err| format!("server {}: {}", server_id, err));
+    let channel_id = submit_channel_id(conf.job_id);
+    let recv = pegasus_network::ipc_channel_recv::<SubmitMessage>(channel_id, server_id, servers)
+        .map_err(|e| JobSubmitError::Aborted(e.to_string()))?;
+    let timeout = Duration::from_millis(conf.submit_timeout_ms);
+    let coordinator = servers[0];
+    let decision = if server_id == coordinator {
+        let others = servers.iter().copied().filter(|id| *id != server_id).collect::<Vec<_>>();
+        let mut senders = senders(channel_id, server_id, &others)?;
+        let mut errors = error.into_iter().collect::<Vec<_>>();
+        if errors.is_empty() {
+            let mut asked = vec![];
+            for (id, sender) in others.iter().zip(senders.iter_mut()) {
+                match sender.send(&SubmitMessage::Prepare) {
+                    Ok(()) => asked.push(*id),
+                    Err(err) => errors.push(format!("server {}: not asked to vote: {}", id, err)),
+                }
+            }
+            let mut voted = vec![];
+            let deadline = Instant::now() + timeout;
+            while voted.len() < asked.len() {
+                match poll(&recv, deadline)? {
+                    Some(SubmitMessage::Vote { server_id, error }) => {
+                        voted.push(server_id);
+                        errors.extend(error);
+                    }
+                    Some(SubmitMessage::Abort { server_id, error }) => {
+                        voted.push(server_id);
+                        errors.push(format!("server {}: {}", server_id, error));
+                    }
+                    Some(_) => (),
+                    None => break,
+                }
+            }
+            for id in asked.iter().filter(|id| !voted.contains(id)) {
+                errors.push(format!("server {}: not prepared in {:?}", id, timeout));
+            }
+        }
+        let error = if errors.is_empty() { None } else { Some(errors.join("; ")) };
+        let decision = SubmitMessage::Decide { error: error.clone() };
+        for sender in senders.iter_mut() {
+            // the servers not told abort the job by themselves once the decision is overdue
+            if let Err(err) = sender.send(&decision).and_then(|_| sender.close()) {
+                warn!("tell the decision of job {} failure: {};", conf.job_id, err);
+            }
+        }
+        error
+    } else {
+        let mut senders = senders(channel_id, server_id, &[coordinator])?;
+        // the vote is only sent once asked, before which the coordinator may abort the job
+        let deadline = Instant::now() + timeout;
+        let asked = loop {
+            match poll(&recv, deadline)? {
+                Some(SubmitMessage::Prepare) => break Ok(()),
+                Some(SubmitMessage::Decide { error }) => break Err(error),
+                Some(_) => (),
+                None => break Err(None),
+            }
+        };
+        let decision = match asked {
+            Ok(()) => {
+                tell(&mut senders, coordinator, SubmitMessage::Vote { server_id, error })?;
+                let deadline = Instant::now() + timeout * 2;
+                loop {
+                    match poll(&recv, deadline)? {
+                        Some(SubmitMessage::Decide { error }) => break error,
+                        Some(_) => (),
+                        None => {
+                            let error = format!(
+                                "no decision from server {} in {:?}",
+                                coordinator,
+                                timeout * 2
+                            );
+                            let abort = SubmitMessage::Abort { server_id, error: error.clone() };
+                            tell(&mut senders, coordinator, abort)?;
+                            break Some(error);
+                        }
+                    }
+                }
+            }
+            Err(Some(error)) => Some(error),
+            Err(None) => {
+                let error = format!("not asked to vote by server {} in {:?}", coordinator, timeout);
+                let abort = SubmitMessage::Abort { server_id, error: error.clone() };
+                tell(&mut senders, coordinator, abort)?;
+                Some(error)
+            }
+        };
+        for sender in senders.iter_mut() {
+            if let Err(err) = sender.close() {
+                warn!("close the submission of job {} failure: {};", conf.job_id, err);
+            }
+        }
+        decision
+    };
+    match decision {
+        None => prepared,
+        // the error of current server is reported as it is, which is among the decision
+        Some(_) if prepared.is_err() => prepared,
+        Some(error) => {
+            info!("job {} is aborted on server {}: {};", conf.job_id, server_id, error);
+            Err(JobSubmitError::Aborted(error))
+        }
+    }
+}
+
+/// Send the message of a server to the coordinator;
+fn tell(
+    senders: &mut [IPCSender<SubmitMessage>], coordinator: u64, msg: SubmitMessage,
+) -> Result<(), JobSubmitError> {
+    for sender in senders.iter_mut() {
+        sender.send(&msg).map_err(|e| {
+            JobSubmitError::Aborted(format!("send to server {} failure: {}", coordinator, e))
+        })?;
+    }
+    Ok(())
+}
+
+fn senders(
+    channel_id: u128, server_id: u64, remotes: &[u64],
+) -> Result<Vec<IPCSender<SubmitMessage>>, JobSubmitError> {
+    let mut senders = pegasus_network::ipc_channel_send(channel_id, server_id, remotes)
+        .map_err(|e| JobSubmitError::Aborted(e.to_string()))?;
+    for sender in senders.iter_mut() {
+        // each message is sent as it is, instead of being batched into a slab
+        sender.reset_slab_size(0);
+    }
+    Ok(senders)
+}
+
+/// Wait for the next message until the deadline, or `None` after;
+fn poll(
+    recv: &IPCReceiver<SubmitMessage>, deadline: Instant,
+) -> Result<Option<SubmitMessage>, JobSubmitError> {
+    loop {
+        match recv.recv() {
+            Ok(Some(msg)) => return Ok(Some(msg)),
+            Ok(None) if Instant::now() < deadline => std::thread::sleep(POLL_INTERVAL),
+            Ok(None) => return Ok(None),
+            Err(e) => return Err(JobSubmitError::Aborted(format!("receive failure: {}", e))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn submit_message_codec_test() {
+        let vote = SubmitMessage::Vote { server_id: 3, error: Some("over limit".to_owned()) };
+        let mut bytes = vec![];
+        vote.write_to(&mut bytes).unwrap();
+        match SubmitMessage::read_from(&mut bytes.as_slice()).unwrap() {
+            SubmitMessage::Vote { server_id, error } => {
+                assert_eq!(server_id, 3);
+                assert_eq!(error.as_deref(), Some("over limit"));
+            }
+            _ => panic!("vote decoded as decision"),
+        }
+        let mut bytes = vec![];
+        SubmitMessage::Decide { error: None }.write_to(&mut bytes).unwrap();
+        match SubmitMessage::read_from(&mut bytes.as_slice()).unwrap() {
+            SubmitMessage::Decide { error } => assert!(error.is_none()),
+            _ => panic!("decision decoded as vote"),
+        }
+        let mut bytes = vec![];
+        SubmitMessage::Prepare.write_to(&mut bytes).unwrap();
+        assert!(matches!(
+            SubmitMessage::read_from(&mut bytes.as_slice()),
+            Ok(SubmitMessage::Prepare)
+        ));
+        let mut bytes = vec![];
+        let abort = SubmitMessage::Abort { server_id: 2, error: "timeout".to_owned() };
+        abort.write_to(&mut bytes).unwrap();
+        match SubmitMessage::read_from(&mut bytes.as_slice()).unwrap() {
+            SubmitMessage::Abort { server_id, error } => {
+                assert_eq!(server_id, 2);
+                assert_eq!(error, "timeout");
+            }
+            _ => panic!("abort decoded as other message"),
+        }
+    }
+
+    #[test]
+    fn submit_channel_id_test() {
+        // the index of the channel is never taken by the dataflow
+        assert_eq!((submit_channel_id(7) >> 32) as u32, u32::MAX);
+        assert_eq!((submit_channel_id(7) >> 64) as u64, 7);
+    }
+}
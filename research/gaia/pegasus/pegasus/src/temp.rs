@@ -0,0 +1,387 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The temp files and directories the jobs create on the disk, e.g. the sorted runs spilled by
+//! the operators, or the pages of the results spilled by the server. Each of them is registered
+//! with the temp resources of its job once created, by `TempSpace::register`, and removed once
+//! its handle is dropped. The directory of a job is removed along with the resources left in it
+//! once all workers of the job in current server end, no matter the job is completed, canceled
+//! or failed, so the live resources of a job, see `live_resources`, drop to zero by then unless
+//! they are kept by others on purpose, e.g. the pages not fetched yet.
+//!
+//! The resources of current process live in the directory of its instance under the temp root,
+//! e.g. `/tmp/pegasus_temp/<pid>_<nanos>/job_<id>/`, along with the liveness marker `LIVE` the
+//! instance refreshes every `HEARTBEAT_INTERVAL`. A process crashed leaves its instance behind,
+//! which is removed by the scavenger once the engine starts again, see `TempSpace::scavenge`,
+//! as the marker is not refreshed for `STALE_AFTER`, or the process of the marker is gone.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The name of the liveness marker in the directory of each instance
+pub const LIVE_MARKER: &str = "LIVE";
+/// How often the instance of current process refreshes its liveness marker
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+/// How long the marker of an instance is not refreshed before the instance is taken as gone
+pub const STALE_AFTER: Duration = Duration::from_secs(60);
+
+lazy_static! {
+    static ref TEMP_ROOT: RwLock<Option<PathBuf>> = RwLock::new(None);
+    static ref INSTANCE: Arc<TempSpace> = open_instance();
+}
+
+/// The temp root configured, or `pegasus_temp` in the temp directory of the system
+fn temp_root() -> PathBuf {
+    TEMP_ROOT
+        .read()
+        .ok()
+        .and_then(|root| root.clone())
+        .unwrap_or_else(|| std::env::temp_dir().join("pegasus_temp"))
+}
+
+fn open_instance() -> Arc<TempSpace> {
+    let root = temp_root();
+    let space = TempSpace::open(&root).unwrap_or_else(|e| {
+        // the resources fail to be created later, each with its own error
+        warn!("fail to create the temp directory under {:?}: {}", root, e);
+        Arc::new(TempSpace::new(&root))
+    });
+    let heartbeat = space.clone();
+    let spawned =
+        std::thread::Builder::new().name("pegasus-temp-heartbeat".to_owned()).spawn(move || loop {
+            std::thread::sleep(HEARTBEAT_INTERVAL);
+            if let Err(e) = heartbeat.touch() {
+                warn!("fail to refresh the liveness marker in {:?}: {}", heartbeat.dir, e);
+            }
+        });
+    if let Err(e) = spawned {
+        warn!("fail to start the heartbeat of the temp directory: {}", e);
+    }
+    space
+}
+
+/// Set the temp root of current process, and remove the instances of the crashed processes left
+/// under it, as the engine starts; It returns the number of the instances removed
+pub(crate) fn init(root: Option<&Path>) -> io::Result<usize> {
+    if let Some(root) = root {
+        if let Ok(mut temp_root) = TEMP_ROOT.write() {
+            *temp_root = Some(root.to_path_buf());
+        }
+    }
+    let space = instance();
+    if root.map(|root| !space.dir.starts_with(root)).unwrap_or(false) {
+        warn!("the temp directory {:?} has been used before the engine starts", space.dir);
+    }
+    let removed = space.scavenge(STALE_AFTER)?;
+    crate::metrics::on_temp_scavenged(removed);
+    Ok(removed)
+}
+
+/// The temp space of current process
+pub fn instance() -> Arc<TempSpace> {
+    INSTANCE.clone()
+}
+
+/// The temp resources of the job alive in current process
+pub fn live_resources(job_id: u64) -> usize {
+    INSTANCE.live_resources(job_id)
+}
+
+/// The temp resources of the jobs of an instance, in the directory of the instance
+pub struct TempSpace {
+    dir: PathBuf,
+    // the paths registered by each job
+    jobs: Mutex<HashMap<u64, HashSet<PathBuf>>>,
+}
+
+impl TempSpace {
+    fn new(root: &Path) -> Self {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+        let dir = root.join(format!("{}_{}", std::process::id(), nanos));
+        TempSpace { dir, jobs: Mutex::new(HashMap::new()) }
+    }
+
+    /// Create the directory of a new instance under `root`, along with its liveness marker
+    pub fn open(root: &Path) -> io::Result<Arc<Self>> {
+        let space = TempSpace::new(root);
+        space.touch()?;
+        Ok(Arc::new(space))
+    }
+
+    /// The directory of the instance
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The directory of the job in the instance, which is removed once the job ends
+    pub fn job_dir(&self, job_id: u64) -> PathBuf {
+        self.dir.join(format!("job_{}", job_id))
+    }
+
+    /// Register the file or directory at `path` created by the job, or to be created right after,
+    /// which is removed once the handle is dropped
+    pub fn register(self: &Arc<Self>, job_id: u64, path: PathBuf) -> TempPath {
+        let mut jobs = self.jobs.lock().expect("lock poisoned");
+        if jobs.entry(job_id).or_default().insert(path.clone()) {
+            crate::metrics::on_temp_created();
+        }
+        TempPath { space: self.clone(), job_id, path }
+    }
+
+    /// The resources registered by the job and not removed yet
+    pub fn live_resources(&self, job_id: u64) -> usize {
+        let jobs = self.jobs.lock().expect("lock poisoned");
+        jobs.get(&job_id).map(|paths| paths.len()).unwrap_or(0)
+    }
+
+    /// Remove the resources of the job left in its directory, along with the directory, once
+    /// the job ends, e.g. of the handles leaked by the operators
+    pub fn release_job(&self, job_id: u64) {
+        let dir = self.job_dir(job_id);
+        let left = {
+            let mut jobs = self.jobs.lock().expect("lock poisoned");
+            match jobs.get_mut(&job_id) {
+                Some(paths) => {
+                    let left: Vec<PathBuf> =
+                        paths.iter().filter(|path| path.starts_with(&dir)).cloned().collect();
+                    for path in left.iter() {
+                        paths.remove(path);
+                        crate::metrics::on_temp_removed();
+                    }
+                    if paths.is_empty() {
+                        jobs.remove(&job_id);
+                    }
+                    left
+                }
+                None => vec![],
+            }
+        };
+        if !left.is_empty() {
+            warn!("remove {} temp resources left by job {}", left.len(), job_id);
+        }
+        remove_path(&dir);
+    }
+
+    /// Remove the directories of the other instances under the same root which are gone, i.e.
+    /// whose liveness markers are not refreshed for `stale_after`, or whose processes have exited,
+    /// or whose markers are missing for `stale_after` since created; It returns the number of the
+    /// instances removed
+    pub fn scavenge(&self, stale_after: Duration) -> io::Result<usize> {
+        let root = match self.dir.parent() {
+            Some(root) => root,
+            None => return Ok(0),
+        };
+        let mut removed = 0;
+        for entry in fs::read_dir(root)? {
+            let path = entry?.path();
+            if path == self.dir || !is_instance_dir(&path) {
+                continue;
+            }
+            if is_gone(&path, stale_after) {
+                info!("remove the temp directory {:?} left by a gone process", path);
+                if let Err(e) = fs::remove_dir_all(&path) {
+                    warn!("fail to remove the temp directory {:?}: {}", path, e);
+                } else {
+                    removed += 1;
+                }
+            }
+        }
+        Ok(removed)
+    }
+
+    // write the liveness marker, by renaming so that it is never read half written
+    fn touch(&self) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let tmp = self.dir.join(format!("{}.tmp", LIVE_MARKER));
+        let mut file = fs::File::create(&tmp)?;
+        write!(file, "{} {}", std::process::id(), now_millis())?;
+        file.sync_all()?;
+        fs::rename(&tmp, self.dir.join(LIVE_MARKER))
+    }
+
+    fn unregister(&self, job_id: u64, path: &Path) {
+        let mut jobs = self.jobs.lock().expect("lock poisoned");
+        if let Some(paths) = jobs.get_mut(&job_id) {
+            if paths.remove(path) {
+                crate::metrics::on_temp_removed();
+            }
+            if paths.is_empty() {
+                jobs.remove(&job_id);
+            }
+        }
+    }
+}
+
+/// A temp file or directory registered with the temp resources of a job, which is removed once
+/// dropped, unless it has been removed along with the directory of the job
+pub struct TempPath {
+    space: Arc<TempSpace>,
+    job_id: u64,
+    path: PathBuf,
+}
+
+impl TempPath {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        remove_path(&self.path);
+        self.space.unregister(self.job_id, &self.path);
+    }
+}
+
+fn remove_path(path: &Path) {
+    let removed = match fs::symlink_metadata(path) {
+        Ok(meta) if meta.is_dir() => fs::remove_dir_all(path),
+        Ok(_) => fs::remove_file(path),
+        Err(e) => Err(e),
+    };
+    if let Err(e) = removed {
+        if e.kind() != io::ErrorKind::NotFound {
+            warn!("fail to remove the temp resource {:?}: {}", path, e);
+        }
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64
+}
+
+// the directory of an instance is named by the process id and the creation time, e.g.
+// `1234_1620000000000000000`, so nothing else under the root is ever removed
+fn is_instance_dir(path: &Path) -> bool {
+    let name = match path.file_name().and_then(|name| name.to_str()) {
+        Some(name) => name,
+        None => return false,
+    };
+    let mut parts = name.splitn(2, '_');
+    let is_number = |part: Option<&str>| {
+        part.map(|p| !p.is_empty() && p.bytes().all(|b| b.is_ascii_digit())).unwrap_or(false)
+    };
+    path.is_dir() && is_number(parts.next()) && is_number(parts.next())
+}
+
+fn is_gone(dir: &Path, stale_after: Duration) -> bool {
+    let stale_after = stale_after.as_millis() as u64;
+    match fs::read_to_string(dir.join(LIVE_MARKER)) {
+        Ok(marker) => {
+            let mut fields = marker.split_whitespace().map(|field| field.parse::<u64>().ok());
+            match (fields.next().flatten(), fields.next().flatten()) {
+                (Some(pid), Some(beat)) => {
+                    !is_process_alive(pid) || now_millis().saturating_sub(beat) >= stale_after
+                }
+                // never written by an instance
+                _ => true,
+            }
+        }
+        // the instance may be just created, whose marker is not written yet
+        Err(_) => {
+            let created = fs::metadata(dir).and_then(|meta| meta.modified());
+            let age = created.ok().and_then(|created| created.elapsed().ok()).unwrap_or_default();
+            age.as_millis() as u64 >= stale_after
+        }
+    }
+}
+
+// whether the process may be still alive, which is only told for sure on linux
+fn is_process_alive(pid: u64) -> bool {
+    if cfg!(target_os = "linux") {
+        pid == std::process::id() as u64 || Path::new("/proc").join(pid.to_string()).exists()
+    } else {
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn test_root(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("{}_{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn temp_path_test() {
+        let root = test_root("temp_path_test");
+        let space = TempSpace::open(&root).unwrap();
+        assert!(space.dir().join(LIVE_MARKER).exists());
+        let dir = space.job_dir(1);
+        fs::create_dir_all(&dir).unwrap();
+        let file = space.register(1, dir.join("0.spill"));
+        fs::write(file.path(), b"spilled").unwrap();
+        let left = space.register(1, dir.join("1.spill"));
+        fs::write(left.path(), b"spilled").unwrap();
+        let pages = space.register(1, space.dir().join("pages_1"));
+        fs::create_dir_all(pages.path()).unwrap();
+        assert_eq!(space.live_resources(1), 3);
+        drop(file);
+        assert_eq!(space.live_resources(1), 2);
+        assert!(!dir.join("0.spill").exists());
+        // the resources outside the directory of the job outlive the job
+        std::mem::forget(left);
+        space.release_job(1);
+        assert!(!dir.exists());
+        assert_eq!(space.live_resources(1), 1);
+        drop(pages);
+        assert_eq!(space.live_resources(1), 0);
+        assert!(!space.dir().join("pages_1").exists());
+        fs::remove_dir_all(&root).ok();
+    }
+
+    // the instance of a crashed process, which never cleans up, is removed once another instance
+    // scavenges after its marker is stale, while the live instances are kept
+    #[test]
+    fn scavenge_test() {
+        let root = test_root("scavenge_test");
+        let crashed = TempSpace::open(&root).unwrap();
+        let dir = crashed.job_dir(2);
+        fs::create_dir_all(&dir).unwrap();
+        let run = crashed.register(2, dir.join("0.spill"));
+        fs::write(run.path(), b"spilled").unwrap();
+        std::mem::forget(run);
+        let crashed_dir = crashed.dir().to_path_buf();
+        drop(crashed);
+        fs::create_dir_all(root.join("not_an_instance")).unwrap();
+
+        let restarted = TempSpace::open(&root).unwrap();
+        assert_eq!(restarted.scavenge(STALE_AFTER).unwrap(), 0);
+        assert!(crashed_dir.exists());
+        assert_eq!(restarted.scavenge(Duration::from_millis(0)).unwrap(), 1);
+        assert!(!crashed_dir.exists());
+        assert!(restarted.dir().join(LIVE_MARKER).exists());
+        assert!(root.join("not_an_instance").exists());
+        fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn liveness_marker_test() {
+        let root = test_root("liveness_marker_test");
+        let space = TempSpace::open(&root).unwrap();
+        assert!(!is_gone(space.dir(), STALE_AFTER));
+        // the process of the marker has exited, e.g. of a pid never used
+        fs::write(space.dir().join(LIVE_MARKER), format!("{} {}", u32::MAX, now_millis())).unwrap();
+        assert_eq!(is_gone(space.dir(), STALE_AFTER), cfg!(target_os = "linux"));
+        fs::write(space.dir().join(LIVE_MARKER), "broken").unwrap();
+        assert!(is_gone(space.dir(), STALE_AFTER));
+        fs::remove_dir_all(&root).ok();
+    }
+}
@@ -0,0 +1,212 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The warm pool of the structures a worker allocates for each job, i.e. the queues of the
+//! pipelines between its operators and the queue of the events it sends to itself, which cost a
+//! noticeable part of the latency of a short job, e.g. a point query. A worker claims a set of
+//! them once it is created for a job, from which its dataflow takes the queues it builds, and
+//! returns the set with the queues once it is dropped, to be claimed by a worker of a later job.
+//!
+//! At most `MAX_WARM_SETS` idle sets are kept, each of at most `MAX_QUEUES_PER_TYPE` queues of
+//! each type of data, and a queue grown beyond `MAX_QUEUE_CAPACITY` is dropped instead of being
+//! returned, so what a large job allocates is not held after it ends.
+
+use crate::event::Event;
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// The most idle sets kept;
+pub const MAX_WARM_SETS: usize = 64;
+/// The most queues of each type of data kept in a set;
+pub const MAX_QUEUES_PER_TYPE: usize = 64;
+/// The largest capacity of a queue kept;
+pub const MAX_QUEUE_CAPACITY: usize = 1024;
+
+#[derive(Default)]
+pub(crate) struct WarmSet {
+    events: Option<VecDeque<Event>>,
+    // the queues by the type of their data, each of which is a boxed `VecDeque<T>`
+    queues: HashMap<TypeId, Vec<Box<dyn Any + Send>>>,
+}
+
+impl WarmSet {
+    fn pooled_queues(&self) -> usize {
+        self.queues.values().map(|queues| queues.len()).sum::<usize>()
+            + self.events.is_some() as usize
+    }
+}
+
+/// The set claimed by a worker, shared with the pipelines it builds to return their queues;
+pub(crate) type WarmHandle = Arc<Mutex<WarmSet>>;
+
+lazy_static! {
+    static ref IDLE_SETS: Mutex<Vec<WarmSet>> = Mutex::new(Vec::new());
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+static CLAIMED: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    // the set of the worker whose dataflow is being built on current thread, see `enter`
+    static CURRENT: RefCell<Option<WarmHandle>> = const { RefCell::new(None) };
+}
+
+/// Enable or disable the warm pool, which is enabled by default; The idle sets are dropped once
+/// it is disabled, and the workers allocate all they need for each job from then on;
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::SeqCst);
+    if !enabled {
+        IDLE_SETS.lock().expect("lock poisoned").clear();
+    }
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::SeqCst)
+}
+
+/// The sets of the warm pool in current server;
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct WarmStats {
+    /// the sets claimed by the workers alive;
+    pub claimed: usize,
+    /// the idle sets to be claimed;
+    pub idle: usize,
+    /// the queues kept in the idle sets;
+    pub pooled_queues: usize,
+}
+
+pub fn warm_stats() -> WarmStats {
+    let idle = IDLE_SETS.lock().expect("lock poisoned");
+    WarmStats {
+        claimed: CLAIMED.load(Ordering::SeqCst),
+        idle: idle.len(),
+        pooled_queues: idle.iter().map(|set| set.pooled_queues()).sum(),
+    }
+}
+
+/// Claim an idle set for a worker being created, or a new one if there is none;
+pub(crate) fn claim() -> WarmHandle {
+    CLAIMED.fetch_add(1, Ordering::SeqCst);
+    let set = if is_enabled() { IDLE_SETS.lock().expect("lock poisoned").pop() } else { None };
+    Arc::new(Mutex::new(set.unwrap_or_default()))
+}
+
+/// Return the set claimed by a worker being dropped, after its dataflow is dropped, so the queues
+/// of its pipelines are returned to the set; The queues returned to the handle from then on are
+/// dropped with it;
+pub(crate) fn release(handle: &WarmHandle) {
+    CLAIMED.fetch_sub(1, Ordering::SeqCst);
+    let set = match handle.lock() {
+        Ok(mut set) => std::mem::take(&mut *set),
+        Err(_) => return,
+    };
+    if is_enabled() {
+        let mut idle = IDLE_SETS.lock().expect("lock poisoned");
+        if idle.len() < MAX_WARM_SETS {
+            idle.push(set);
+        }
+    }
+}
+
+/// Make the set the one the pipelines built on current thread take their queues from, until the
+/// guard is dropped;
+pub(crate) fn enter(handle: &WarmHandle) -> CurrentGuard {
+    let prev = CURRENT.with(|current| current.replace(Some(handle.clone())));
+    CurrentGuard { prev }
+}
+
+pub(crate) struct CurrentGuard {
+    prev: Option<WarmHandle>,
+}
+
+impl Drop for CurrentGuard {
+    fn drop(&mut self) {
+        let prev = self.prev.take();
+        CURRENT.with(|current| current.replace(prev));
+    }
+}
+
+/// Take a queue for a pipeline from the set of current thread, with the set it is returned to by
+/// `give_queue` once the pipeline is dropped;
+pub(crate) fn take_queue<T: Send + 'static>() -> (VecDeque<T>, Option<WarmHandle>) {
+    let handle = CURRENT.with(|current| current.borrow().clone());
+    let queue = handle.as_ref().and_then(|handle| {
+        let mut set = handle.lock().ok()?;
+        let queue = set.queues.get_mut(&TypeId::of::<T>())?.pop()?;
+        queue.downcast::<VecDeque<T>>().ok().map(|queue| *queue)
+    });
+    (queue.unwrap_or_default(), handle)
+}
+
+pub(crate) fn give_queue<T: Send + 'static>(handle: &WarmHandle, mut queue: VecDeque<T>) {
+    queue.clear();
+    if queue.capacity() > MAX_QUEUE_CAPACITY {
+        return;
+    }
+    if let Ok(mut set) = handle.lock() {
+        let queues = set.queues.entry(TypeId::of::<T>()).or_insert_with(Vec::new);
+        if queues.len() < MAX_QUEUES_PER_TYPE {
+            queues.push(Box::new(queue));
+        }
+    }
+}
+
+/// Take the queue of the events of a worker from its set;
+pub(crate) fn take_events(handle: &WarmHandle) -> VecDeque<Event> {
+    handle.lock().ok().and_then(|mut set| set.events.take()).unwrap_or_default()
+}
+
+pub(crate) fn give_events(handle: &WarmHandle, mut events: VecDeque<Event>) {
+    events.clear();
+    if events.capacity() <= MAX_QUEUE_CAPACITY {
+        if let Ok(mut set) = handle.lock() {
+            set.events = Some(events);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reuse_queue_test() {
+        let handle = Arc::new(Mutex::new(WarmSet::default()));
+        {
+            let _guard = enter(&handle);
+            let (mut queue, returned) = take_queue::<u64>();
+            assert!(returned.is_some());
+            queue.extend(0..10);
+            give_queue(&handle, queue);
+            // the queues are reused by the type of their data
+            take_queue::<u32>();
+            assert_eq!(handle.lock().unwrap().pooled_queues(), 1);
+            let (mut queue, _) = take_queue::<u64>();
+            assert!(queue.is_empty());
+            assert!(queue.capacity() >= 10);
+            // a queue grown too large is dropped instead of being returned
+            queue.extend(0..MAX_QUEUE_CAPACITY as u64 * 2);
+            give_queue(&handle, queue);
+            assert_eq!(handle.lock().unwrap().pooled_queues(), 0);
+            give_queue(&handle, VecDeque::<u64>::with_capacity(16));
+        }
+        // no queue is taken from the set out of the guard
+        assert!(take_queue::<u64>().1.is_none());
+        assert_eq!(handle.lock().unwrap().pooled_queues(), 1);
+    }
+}
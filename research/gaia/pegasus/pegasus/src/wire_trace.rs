@@ -0,0 +1,382 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The wire trace of the jobs with `JobConf::wire_trace`, to debug the jobs hanging across the
+//! workers, e.g. by a scope missing an end signal on some channel. Each event one worker sends to
+//! another, i.e. the records pushed into a scope of a channel, the end of a scope or the discard
+//! of it, is recorded by the sender once it is sent and by the receiver once it is received, into
+//! a ring buffer of each worker keeping at most `MAX_RECORDS_PER_WORKER` records. The events of
+//! the traced jobs are stamped on the wire with the sender and a sequence counted for each pair of
+//! workers, by which the records of both sides are correlated. The data themselves are not
+//! recorded, but are told by the pushed events following them.
+//!
+//! The trace of a job is dumped by `dump_job_trace` on demand, or into the log once the job
+//! exceeds its time limit. The traces of at most `MAX_TRACED_JOBS` jobs are kept after the jobs
+//! end, where those of the earliest jobs are dropped first. The events of the jobs not traced are
+//! neither stamped nor recorded.
+
+use crate::event::{EndOfStream, Event, EventKind};
+use crate::Tag;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::SystemTime;
+
+/// The most records kept for each worker;
+pub const MAX_RECORDS_PER_WORKER: usize = 4096;
+/// The most jobs whose traces are kept;
+pub const MAX_TRACED_JOBS: usize = 16;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Send,
+    Receive,
+}
+
+/// An event sent from one worker to another, as recorded by either of them;
+#[derive(Clone, Debug, PartialEq)]
+pub struct WireRecord {
+    /// the worker recording it;
+    pub worker: u32,
+    /// the worker it is sent to if it is sent, or received from otherwise;
+    pub peer: u32,
+    pub direction: Direction,
+    /// the sequence of the event among those the sender sends to the receiver;
+    pub seq: u64,
+    /// the index of the channel the event is of;
+    pub ch: u32,
+    pub tag: Tag,
+    /// the number of records pushed into the scope, 0 for an end or a discard;
+    pub size: usize,
+    /// whether it tells the end of the scope;
+    pub is_end: bool,
+    /// when it is sent or received;
+    pub at: SystemTime,
+}
+
+impl WireRecord {
+    /// The sender and the receiver of the event, with its sequence between them;
+    pub fn key(&self) -> (u32, u32, u64) {
+        match self.direction {
+            Direction::Send => (self.worker, self.peer, self.seq),
+            Direction::Receive => (self.peer, self.worker, self.seq),
+        }
+    }
+}
+
+impl fmt::Display for WireRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (arrow, action) = match self.direction {
+            Direction::Send => ("->", "sent"),
+            Direction::Receive => ("<-", "received"),
+        };
+        let what = if self.is_end {
+            "end".to_owned()
+        } else if self.size == 0 {
+            "discard".to_owned()
+        } else {
+            format!("{} records", self.size)
+        };
+        let at = self.at.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default();
+        write!(
+            f,
+            "worker[{}] {} worker[{}] #{}: {} of {:?} on channel {}, {} at {:?}",
+            self.worker, arrow, self.peer, self.seq, what, self.tag, self.ch, action, at
+        )
+    }
+}
+
+/// The records of a job kept by the workers in current server, see `dump_job_trace`;
+#[derive(Clone, Debug, Default)]
+pub struct JobWireTrace {
+    pub job_id: u64,
+    /// worker index -> the records of the worker in the order they are recorded;
+    pub workers: BTreeMap<u32, Vec<WireRecord>>,
+    /// the number of records dropped as the buffers are full;
+    pub dropped: u64,
+}
+
+impl JobWireTrace {
+    /// Add the records of the workers in another server, dumped of the same job there, so that
+    /// the events between the servers are correlated as well;
+    pub fn merge(&mut self, other: JobWireTrace) {
+        for (worker, records) in other.workers {
+            self.workers.entry(worker).or_default().extend(records);
+        }
+        self.dropped += other.dropped;
+    }
+
+    /// The records of the events both sent and received, as (send, receive) pairs;
+    pub fn pairs(&self) -> Vec<(&WireRecord, &WireRecord)> {
+        let received: HashMap<(u32, u32, u64), &WireRecord> =
+            self.records(Direction::Receive).map(|r| (r.key(), r)).collect();
+        self.records(Direction::Send)
+            .filter_map(|send| received.get(&send.key()).map(|receive| (send, *receive)))
+            .collect()
+    }
+
+    /// The records of the events only one side of which is recorded, e.g. the events sent but
+    /// never received, or those the other side of which is dropped or recorded in another server;
+    pub fn unmatched(&self) -> Vec<&WireRecord> {
+        let mut sides: HashMap<(u32, u32, u64), Vec<&WireRecord>> = HashMap::new();
+        for record in self.workers.values().flatten() {
+            sides.entry(record.key()).or_default().push(record);
+        }
+        let mut unmatched: Vec<&WireRecord> = sides
+            .into_iter()
+            .filter(|(_, records)| records.len() == 1)
+            .map(|(_, records)| records[0])
+            .collect();
+        unmatched.sort_by_key(|record| record.at);
+        unmatched
+    }
+
+    /// (receiver, channel, scope) -> the workers each end received is from, in the order they
+    /// are received;
+    pub fn ends_received(&self) -> HashMap<(u32, u32, Tag), Vec<u32>> {
+        let mut ends = HashMap::new();
+        for record in self.records(Direction::Receive).filter(|record| record.is_end) {
+            let key = (record.worker, record.ch, record.tag.clone());
+            ends.entry(key).or_insert_with(Vec::new).push(record.peer);
+        }
+        ends
+    }
+
+    fn records(&self, direction: Direction) -> impl Iterator<Item = &WireRecord> {
+        self.workers.values().flatten().filter(move |record| record.direction == direction)
+    }
+}
+
+impl fmt::Display for JobWireTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let records: usize = self.workers.values().map(|records| records.len()).sum();
+        writeln!(
+            f,
+            "wire trace of job {}: {} records of {} workers, {} dropped;",
+            self.job_id,
+            records,
+            self.workers.len(),
+            self.dropped
+        )?;
+        for record in self.unmatched() {
+            writeln!(f, "unmatched: {}", record)?;
+        }
+        let mut ends: Vec<_> = self.ends_received().into_iter().collect();
+        ends.sort_by_key(|((worker, ch, _), _)| (*worker, *ch));
+        for ((worker, ch, tag), sources) in ends {
+            writeln!(
+                f,
+                "worker[{}] received {} ends of {:?} on channel {} from {:?}",
+                worker,
+                sources.len(),
+                tag,
+                ch,
+                sources
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct WireRing {
+    records: VecDeque<WireRecord>,
+    // the number of records dropped as the buffer is full
+    dropped: u64,
+}
+
+impl WireRing {
+    fn push(&mut self, record: WireRecord) {
+        if self.records.len() >= MAX_RECORDS_PER_WORKER {
+            self.records.pop_front();
+            self.dropped += 1;
+        }
+        self.records.push_back(record);
+    }
+}
+
+#[derive(Default)]
+struct JobRings {
+    workers: Mutex<BTreeMap<u32, Arc<Mutex<WireRing>>>>,
+    // set once the trace is dumped into the log, to dump it once for all the workers
+    logged: AtomicBool,
+}
+
+#[derive(Default)]
+struct TracedJobs {
+    jobs: HashMap<u64, Arc<JobRings>>,
+    // the jobs in the order they are registered, to drop the earliest ones
+    order: VecDeque<u64>,
+}
+
+lazy_static! {
+    static ref TRACED_JOBS: RwLock<TracedJobs> = RwLock::new(TracedJobs::default());
+}
+
+/// Start to trace the job, which drops the records of the previous job of the same id if any;
+pub(crate) fn register(job_id: u64) {
+    let mut traced = TRACED_JOBS.write().expect("lock poisoned");
+    if traced.jobs.insert(job_id, Arc::new(JobRings::default())).is_some() {
+        traced.order.retain(|id| *id != job_id);
+    }
+    traced.order.push_back(job_id);
+    while traced.order.len() > MAX_TRACED_JOBS {
+        if let Some(earliest) = traced.order.pop_front() {
+            traced.jobs.remove(&earliest);
+        }
+    }
+}
+
+fn get_job(job_id: u64) -> Option<Arc<JobRings>> {
+    TRACED_JOBS.read().ok()?.jobs.get(&job_id).cloned()
+}
+
+/// Records the events a worker sends to and receives from the other workers;
+#[derive(Clone)]
+pub(crate) struct WireTracer {
+    worker: u32,
+    ring: Arc<Mutex<WireRing>>,
+}
+
+impl WireTracer {
+    /// The tracer of the worker, or `None` if the job is not traced;
+    pub(crate) fn of(job_id: u64, worker: u32) -> Option<Self> {
+        let job = get_job(job_id)?;
+        let mut workers = job.workers.lock().ok()?;
+        let ring = workers.entry(worker).or_insert_with(Default::default).clone();
+        Some(WireTracer { worker, ring })
+    }
+
+    #[inline]
+    pub(crate) fn on_send(&self, target: u32, seq: u64, event: &Event) {
+        self.record(target, Direction::Send, seq, event)
+    }
+
+    #[inline]
+    pub(crate) fn on_receive(&self, source: u32, seq: u64, event: &Event) {
+        self.record(source, Direction::Receive, seq, event)
+    }
+
+    fn record(&self, peer: u32, direction: Direction, seq: u64, event: &Event) {
+        let (size, is_end) = match event.kind {
+            EventKind::Pushed(size) => (size, false),
+            EventKind::EOS(EndOfStream::All) | EventKind::EOS(EndOfStream::OneOf(_)) => (0, true),
+            EventKind::Discard(_) => (0, false),
+        };
+        let record = WireRecord {
+            worker: self.worker,
+            peer,
+            direction,
+            seq,
+            ch: event.ch,
+            tag: event.tag.clone(),
+            size,
+            is_end,
+            at: SystemTime::now(),
+        };
+        if let Ok(mut ring) = self.ring.lock() {
+            ring.push(record);
+        }
+    }
+}
+
+/// Dump the records of the job kept by the workers in current server, or `None` if the job is
+/// not traced, or its trace has been dropped for the later jobs;
+pub fn dump_job_trace(job_id: u64) -> Option<JobWireTrace> {
+    let job = get_job(job_id)?;
+    let workers = job.workers.lock().ok()?;
+    let mut trace = JobWireTrace { job_id, ..Default::default() };
+    for (worker, ring) in workers.iter() {
+        let ring = ring.lock().ok()?;
+        trace.workers.insert(*worker, ring.records.iter().cloned().collect());
+        trace.dropped += ring.dropped;
+    }
+    Some(trace)
+}
+
+/// Dump the trace of the job into the log once it exceeds its time limit, which may suggest that
+/// it hangs, where the trace is dumped once by the first worker of it timed out;
+pub(crate) fn on_timeout(job_id: u64) {
+    if let Some(job) = get_job(job_id) {
+        if !job.logged.swap(true, Ordering::SeqCst) {
+            if let Some(trace) = dump_job_trace(job_id) {
+                error_worker!("{}", trace);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn record(worker: u32, peer: u32, direction: Direction, seq: u64, is_end: bool) -> WireRecord {
+        WireRecord {
+            worker,
+            peer,
+            direction,
+            seq,
+            ch: 1,
+            tag: Tag::root(),
+            size: if is_end { 0 } else { 8 },
+            is_end,
+            at: SystemTime::now(),
+        }
+    }
+
+    #[test]
+    fn ring_buffer_test() {
+        let mut ring = WireRing::default();
+        for seq in 0..MAX_RECORDS_PER_WORKER as u64 + 2 {
+            ring.push(record(0, 1, Direction::Send, seq, false));
+        }
+        assert_eq!(ring.records.len(), MAX_RECORDS_PER_WORKER);
+        assert_eq!(ring.dropped, 2);
+        assert_eq!(ring.records.front().map(|r| r.seq), Some(2));
+    }
+
+    #[test]
+    fn correlate_test() {
+        let mut trace = JobWireTrace::default();
+        trace.workers.insert(
+            0,
+            vec![
+                record(0, 1, Direction::Send, 0, false),
+                record(0, 1, Direction::Send, 1, true),
+                record(0, 1, Direction::Receive, 0, true),
+            ],
+        );
+        let mut other = JobWireTrace::default();
+        other.workers.insert(
+            1,
+            vec![
+                record(1, 0, Direction::Receive, 0, false),
+                record(1, 0, Direction::Send, 0, true),
+            ],
+        );
+        trace.merge(other);
+        let pairs = trace.pairs();
+        assert_eq!(pairs.len(), 2);
+        assert!(pairs.iter().all(|(send, receive)| send.key() == receive.key()));
+        // the end worker 0 sends to worker 1 is never received;
+        let unmatched = trace.unmatched();
+        assert_eq!(unmatched.len(), 1);
+        assert_eq!(unmatched[0].key(), (0, 1, 1));
+        let ends = trace.ends_received();
+        assert_eq!(ends.get(&(0, 1, Tag::root())), Some(&vec![1]));
+        assert_eq!(ends.get(&(1, 1, Tag::root())), None);
+    }
+}
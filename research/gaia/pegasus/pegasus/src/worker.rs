@@ -13,13 +13,20 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
-use crate::dataflow::{Dataflow, DataflowBuilder};
-use crate::errors::{BuildJobError, JobExecError};
-use crate::event::{EventBus, EventEntrepot, EventManager};
+use crate::dataflow::{Dataflow, DataflowBuilder, PlanDesc};
+use crate::errors::{BuildJobError, ErrorKind, JobExecError};
+use crate::event::{Event, EventBus, EventEntrepot, EventManager};
+use crate::metrics::WorkerMetrics;
 use crate::schedule::Schedule;
+use crate::slow_query::{JobStatus, JobTrace};
+use crate::span::Span;
+use crate::warm::WarmHandle;
 use crate::{JobConf, WorkerId};
+use pegasus_common::rc::RcPointer;
 use pegasus_executor::{Task, TaskExecError, TaskState};
 use std::any::Any;
+use std::cell::RefCell;
+use std::collections::VecDeque;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::Instant;
@@ -31,15 +38,28 @@ pub struct Worker {
     peer_guard: Arc<AtomicUsize>,
     start: Instant,
     cancel_hook: Arc<AtomicBool>,
+    // the root span of the job, entered by each run of the worker
+    span: Span,
+    metrics: WorkerMetrics,
+    // the warm set claimed for the job, returned once the worker is dropped, see `crate::warm`;
+    warm: WarmHandle,
+    // what is traced of the job for the slow query log, if enabled;
+    trace: Option<Arc<JobTrace>>,
+    events: Option<RcPointer<RefCell<VecDeque<Event>>>>,
+    // set to describe the dataflow once built instead of preparing it to run, see `explain`;
+    explaining: bool,
+    plan: Option<PlanDesc>,
 }
 
 impl Worker {
     pub(crate) fn new(
         conf: &Arc<JobConf>, id: WorkerId, peer_guard: &Arc<AtomicUsize>,
-        cancel_hook: &Arc<AtomicBool>,
+        cancel_hook: &Arc<AtomicBool>, span: &Span, trace: &Option<Arc<JobTrace>>,
     ) -> Self {
         if peer_guard.fetch_add(1, Ordering::SeqCst) == 0 {
             pegasus_memory::alloc::new_task(conf.job_id as usize);
+            crate::metrics::job_started();
+            crate::net_usage::on_job_started(conf.job_id);
         }
         Worker {
             conf: conf.clone(),
@@ -48,6 +68,13 @@ impl Worker {
             peer_guard: peer_guard.clone(),
             start: Instant::now(),
             cancel_hook: cancel_hook.clone(),
+            span: span.clone(),
+            metrics: WorkerMetrics::new(conf, id.index),
+            warm: crate::warm::claim(),
+            trace: trace.clone(),
+            events: None,
+            explaining: false,
+            plan: None,
         }
     }
 
@@ -57,10 +84,21 @@ impl Worker {
     {
         // set current worker's id into tls variable to make it accessible at anywhere;
         let _g = crate::worker_id::guard(self.id);
+        let _c = crate::worker_id::CurJobConfGuard::new(&self.conf);
+        let _w = crate::warm::enter(&self.warm);
         let (tx, rx) = crossbeam_channel::unbounded();
-        let event_bus = EventBus::new(self.id, tx);
+        let events = crate::warm::take_events(&self.warm);
+        let event_bus = EventBus::with_queue(self.id, tx, events);
+        self.events = Some(event_bus.internal.clone());
         let dfb = DataflowBuilder::new(self.id, &self.conf, &event_bus);
         func(&dfb)?;
+        if self.explaining {
+            let plan = dfb.describe();
+            // the topology is validated as that of a job to run;
+            dfb.build()?;
+            self.plan = Some(plan);
+            return Ok(());
+        }
         let df = dfb.build()?;
         let entrepot = EventEntrepot::new(event_bus, rx, &self.conf)?;
         let event_manager = EventManager::new(entrepot, &df)?;
@@ -69,50 +107,97 @@ impl Worker {
         Ok(())
     }
 
+    /// Describe the dataflow built by `dataflow` instead of preparing it to run;
+    pub(crate) fn explaining(&mut self) {
+        self.explaining = true;
+    }
+
+    /// The dataflow described once built, if the worker is explaining;
+    pub(crate) fn take_plan(&mut self) -> Option<PlanDesc> {
+        self.plan.take()
+    }
+
     pub fn run(&mut self) -> Result<TaskState, JobExecError> {
+        let _span = self.span.clone().entered();
+        let _metrics = self.metrics.measure();
         if let Some((mut task, mut schedule)) = self.task.take() {
             let is_active = schedule.step(&mut task)?;
-            if is_active {
+            if let Err(e) = crate::net_usage::check_limit(&self.conf) {
+                return Err(JobExecError::new(ErrorKind::Others, e));
+            }
+            if let Some(e) = crate::abort::take_abort(self.id.job_id) {
+                return Err(JobExecError::from_box(e));
+            }
+            if let Some(trace) = self.trace.as_ref() {
+                let job_id = self.id.job_id as usize;
+                if let Some(usage) = pegasus_memory::alloc::check_task_memory(job_id) {
+                    trace.on_memory(usage);
+                }
+            }
+            if !is_active && task.check_finish() {
+                if let Err(e) = schedule.close() {
+                    warn_worker!("error occurred when close schedule after task finished: {}", e);
+                }
+                debug_worker!("finished;");
+                Ok(TaskState::Finished)
+            } else if self.check_cancel() {
+                // checked in the active steps too, e.g. of an endless source, which are never
+                // checked for being ready;
+                schedule.close().ok();
+                for op in task.operators.iter_mut().flatten() {
+                    op.close();
+                }
+                debug_worker!("be canceled;");
+                Ok(TaskState::Finished)
+            } else if is_active {
                 self.task = Some((task, schedule));
                 Ok(TaskState::Ready)
             } else {
-                if task.check_finish() {
-                    if let Err(e) = schedule.close() {
-                        warn_worker!(
-                            "error occurred when close schedule after task finished: {}",
-                            e
-                        );
-                    }
-                    debug_worker!("finished;");
-                    Ok(TaskState::Finished)
-                } else if self.check_cancel() {
-                    schedule.close().ok();
-                    for op in task.operators.iter_mut() {
-                        if let Some(op) = op {
-                            op.close();
-                        }
-                    }
-                    debug_worker!("be canceled;");
-                    Ok(TaskState::Finished)
-                } else {
-                    self.task = Some((task, schedule));
-                    Ok(TaskState::NotReady)
-                }
+                self.task = Some((task, schedule));
+                Ok(TaskState::NotReady)
             }
         } else {
             Ok(TaskState::Finished)
         }
     }
 
+    /// Cancel the other workers of the job once this worker fails, so that they stop as if the
+    /// job is canceled, rather than running until the failure is found by the job guard; The logs
+    /// captured of the job by now are attached to the error if the job captures its logs;
+    fn cancel_peers(&self, mut err: JobExecError) -> JobExecError {
+        error_worker!("execute failure, cancel the job: {}", err);
+        err.set_worker(self.id.index);
+        // told ahead of the cancellation, which the other workers tell otherwise;
+        if let Some(trace) = self.trace.as_ref() {
+            trace.on_end(JobStatus::Failed, Some(err.to_string()));
+        }
+        self.cancel_hook.store(true, Ordering::SeqCst);
+        if self.conf.capture_logs {
+            if let Some(logs) = crate::job_log::fetch_job_logs(self.id.job_id) {
+                err.attach_logs(logs);
+            }
+        }
+        err
+    }
+
     fn check_cancel(&self) -> bool {
         if self.cancel_hook.load(Ordering::Relaxed) {
             error_worker!("has been canceled.");
+            if let Some(trace) = self.trace.as_ref() {
+                trace.on_end(JobStatus::Cancelled, None);
+            }
             return true;
         }
         let elapsed = self.start.elapsed().as_millis();
         let is_timeout = (self.conf.time_limit as u128) < elapsed;
         if is_timeout {
             error_worker!("execute timeout, take {} millis", elapsed);
+            if let Some(trace) = self.trace.as_ref() {
+                trace.on_end(JobStatus::TimedOut, None);
+            }
+            if self.conf.wire_trace {
+                crate::wire_trace::on_timeout(self.id.job_id);
+            }
         }
         is_timeout
     }
@@ -170,20 +255,38 @@ impl Task for Worker {
     fn execute(&mut self) -> Result<TaskState, Box<dyn TaskExecError>> {
         let _c = WorkerContext::new(self.id);
         let _g = crate::worker_id::guard(self.id);
-        Ok(self.run()?)
+        let result = self.run().map_err(|err| self.cancel_peers(err));
+        Ok(result?)
     }
 
     fn check_ready(&mut self) -> Result<TaskState, Box<dyn TaskExecError>> {
         let _c = WorkerContext::new(self.id);
         let _g = crate::worker_id::guard(self.id);
-        Ok(Worker::check_ready(self)?)
+        let result = Worker::check_ready(self).map_err(|err| self.cancel_peers(err));
+        Ok(result?)
     }
 }
 
 impl Drop for Worker {
     fn drop(&mut self) {
+        // drop the dataflow first, whose pipelines return their queues to the warm set;
+        self.task.take();
+        if let Some(events) = self.events.take() {
+            let events = std::mem::take(&mut *events.borrow_mut());
+            crate::warm::give_events(&self.warm, events);
+        }
+        crate::warm::release(&self.warm);
         if self.peer_guard.fetch_sub(1, Ordering::SeqCst) == 1 {
             pegasus_memory::alloc::remove_task(self.id.job_id as usize);
+            crate::spill::remove_job_spill_dir(&self.conf);
+            crate::resource::unregister(self.id.job_id);
+            crate::metrics::job_finished();
+            crate::net_usage::on_job_finished(&self.conf);
+            crate::abort::on_job_finished(self.id.job_id);
+            // the operators of all workers have been dropped with their profiles reported;
+            if let Some(trace) = self.trace.as_ref() {
+                crate::slow_query::on_job_end(trace);
+            }
         }
     }
 }
@@ -14,8 +14,9 @@
 //! limitations under the License.
 
 use crate::JobConf;
-use std::cell::Cell;
+use std::cell::{Cell, RefCell};
 use std::fmt::Debug;
+use std::sync::Arc;
 
 #[derive(Copy, Clone, Hash)]
 pub struct WorkerId {
@@ -27,17 +28,41 @@ pub struct WorkerId {
     pub index: u32,
     /// Indicates that if trace is enabled;
     pub trace_enable: bool,
+    /// The index of the server this worker runs on among the servers of the job, whose workers
+    /// are `[server_id * local_peers, (server_id + 1) * local_peers)`;
+    pub server_id: u32,
+    /// The number of worker peers on each server;
+    pub local_peers: u32,
 }
 
 impl WorkerId {
     pub fn new(job_id: u64, peers: u32, index: u32, trace: bool) -> Self {
-        WorkerId { job_id, peers, index, trace_enable: trace }
+        WorkerId { job_id, peers, index, trace_enable: trace, server_id: 0, local_peers: peers }
+    }
+
+    /// Place the worker on the servers of `local_peers` workers each;
+    pub fn on_servers(mut self, local_peers: u32) -> Self {
+        let local_peers = local_peers.max(1);
+        self.local_peers = local_peers;
+        self.server_id = self.index / local_peers;
+        self
+    }
+
+    /// The number of servers the job runs on;
+    pub fn servers(&self) -> u32 {
+        self.peers / self.local_peers.max(1)
+    }
+
+    /// The index of the first worker on the server of this worker;
+    pub fn server_leader(&self) -> u32 {
+        self.server_id * self.local_peers
     }
 
     pub fn all_peers(&self) -> WorkerIdIter {
         WorkerIdIter {
             job_id: self.job_id,
             peers: self.peers,
+            local_peers: self.local_peers,
             cursor: 0,
             trace_enable: self.trace_enable,
             last: self.peers,
@@ -58,6 +83,8 @@ impl std::convert::From<&JobConf> for WorkerId {
             peers: job_conf.total_workers() as u32,
             index: 0,
             trace_enable: job_conf.trace_enable,
+            server_id: 0,
+            local_peers: job_conf.workers,
         }
     }
 }
@@ -73,6 +100,7 @@ impl Eq for WorkerId {}
 pub struct WorkerIdIter {
     job_id: u64,
     peers: u32,
+    local_peers: u32,
     trace_enable: bool,
     cursor: u32,
     last: u32,
@@ -80,7 +108,16 @@ pub struct WorkerIdIter {
 
 impl WorkerIdIter {
     pub fn new(job_id: u64, peers: u32, start: u32, last: u32) -> Self {
-        WorkerIdIter { job_id, peers, trace_enable: false, cursor: start, last }
+        let local_peers = if last > start { last - start } else { peers };
+        WorkerIdIter { job_id, peers, local_peers, trace_enable: false, cursor: start, last }
+    }
+
+    /// Split the workers into `servers` simulated servers, as if each of them runs on a server of
+    /// its own, see `simulate_servers`;
+    pub fn split_servers(&mut self, servers: u32) {
+        if servers > 1 && self.local_peers.is_multiple_of(servers) {
+            self.local_peers /= servers;
+        }
     }
 
     pub fn enable_trace(&mut self) {
@@ -95,7 +132,8 @@ impl Iterator for WorkerIdIter {
         if self.cursor == self.last {
             None
         } else {
-            let next = WorkerId::new(self.job_id, self.peers, self.cursor, self.trace_enable);
+            let next = WorkerId::new(self.job_id, self.peers, self.cursor, self.trace_enable)
+                .on_servers(self.local_peers);
             self.cursor += 1;
             Some(next)
         }
@@ -141,6 +179,32 @@ pub fn get_current_worker_uncheck() -> WorkerId {
     CURRENT_WORKER.with(|w| w.get()).expect("current worker lost;")
 }
 
+thread_local! {
+    static CURRENT_JOB_CONF : RefCell<Option<Arc<JobConf>>> = const { RefCell::new(None) }
+}
+
+pub struct CurJobConfGuard;
+
+impl CurJobConfGuard {
+    pub fn new(conf: &Arc<JobConf>) -> Self {
+        CURRENT_JOB_CONF.with(|c| c.replace(Some(conf.clone())));
+        CurJobConfGuard
+    }
+}
+
+impl Drop for CurJobConfGuard {
+    fn drop(&mut self) {
+        CURRENT_JOB_CONF.with(|c| c.replace(None));
+    }
+}
+
+/// Get the configuration of the job whose dataflow is being built by current thread, which is
+/// only available while building the dataflow, e.g. when compiling the user functions;
+#[inline]
+pub fn get_current_job_conf() -> Option<Arc<JobConf>> {
+    CURRENT_JOB_CONF.with(|c| c.borrow().clone())
+}
+
 #[inline]
 pub fn is_in_trace() -> bool {
     CURRENT_WORKER.with(|w| w.get().map(|w| w.trace_enable)).unwrap_or(false)
@@ -148,7 +212,11 @@ pub fn is_in_trace() -> bool {
 }
 
 macro_rules! inspect_worker {
-    ($lvl:expr, $arg0: expr) => (
+    ($lvl:expr, $arg0: expr) => ({
+        if $crate::span::is_enabled() {
+            $crate::span::forward_log($lvl, format_args!($arg0));
+        }
+        $crate::job_log::capture($lvl, format_args!($arg0));
         if log_enabled!($lvl) {
             if let Some(id) = $crate::worker_id::get_current_worker() {
                 log!($lvl, concat!("{:?}: ", $arg0), id);
@@ -162,8 +230,12 @@ macro_rules! inspect_worker {
                 println!($arg0);
             }
         }
-    );
-    ($lvl: expr, $arg0: expr, $($arg:tt)*) => (
+    });
+    ($lvl: expr, $arg0: expr, $($arg:tt)*) => ({
+        if $crate::span::is_enabled() {
+            $crate::span::forward_log($lvl, format_args!($arg0, $($arg)*));
+        }
+        $crate::job_log::capture($lvl, format_args!($arg0, $($arg)*));
         if log_enabled!($lvl) {
             if let Some(id) = $crate::worker_id::get_current_worker() {
                 log!($lvl, concat!("{:?}: ", $arg0), id, $($arg)*);
@@ -177,7 +249,7 @@ macro_rules! inspect_worker {
                 println!($arg0, $($arg)*);
             }
         }
-    )
+    })
 }
 
 #[macro_export]
@@ -211,7 +283,11 @@ macro_rules! trace_worker {
 }
 
 macro_rules! inspect_worker_error {
-     ($lvl:expr, $arg0: expr) => (
+     ($lvl:expr, $arg0: expr) => ({
+        if $crate::span::is_enabled() {
+            $crate::span::forward_log($lvl, format_args!($arg0));
+        }
+        $crate::job_log::capture($lvl, format_args!($arg0));
         if log_enabled!($lvl) {
             if let Some(id) = $crate::worker_id::get_current_worker() {
                 log!($lvl, concat!("{:?}: ", $arg0), id);
@@ -225,8 +301,12 @@ macro_rules! inspect_worker_error {
                 eprintln!($arg0);
             }
         }
-    );
-    ($lvl: expr, $arg0: expr, $($arg:tt)*) => (
+    });
+    ($lvl: expr, $arg0: expr, $($arg:tt)*) => ({
+        if $crate::span::is_enabled() {
+            $crate::span::forward_log($lvl, format_args!($arg0, $($arg)*));
+        }
+        $crate::job_log::capture($lvl, format_args!($arg0, $($arg)*));
          if log_enabled!($lvl) {
             if let Some(id) = $crate::worker_id::get_current_worker() {
                 log!($lvl, concat!("{:?}: ", $arg0), id, $($arg)*);
@@ -240,7 +320,7 @@ macro_rules! inspect_worker_error {
                 eprintln!($arg0, $($arg)*);
             }
          }
-    )
+    })
 }
 
 #[macro_export]
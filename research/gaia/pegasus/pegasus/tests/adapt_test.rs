@@ -0,0 +1,99 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use pegasus::api::{Adapt, Branch, Decision, Map, ResultSet, Sink};
+use pegasus::communication::Pipeline;
+use pegasus::{Configuration, JobConf, JobProfile};
+
+/// Run the data of the worker 0 of both odd and even numbers, and of the worker 1 of even numbers
+/// only from 1000, through the alternative of the first for the samples of any odd number, and of
+/// the second otherwise, which tag the data by 0 and 1 respectively;
+fn run_adaptive(job_id: u64, sample: usize) -> (Vec<(u32, u64)>, JobProfile) {
+    let mut conf = JobConf::new(job_id, "adapt_test", 2);
+    conf.profile = true;
+    let (tx, rx) = crossbeam_channel::unbounded();
+    pegasus::run(conf, |worker| {
+        let index = worker.id.index;
+        let tx = tx.clone();
+        worker.dataflow(move |dfb| {
+            let source = if index == 0 {
+                dfb.input_from_iter(0..100u64)?
+            } else {
+                dfb.input_from_iter((0..100u64).map(|i| 1000 + i * 2))?
+            };
+            let decide = |sampled: &[u64]| {
+                let odds = sampled.iter().filter(|i| *i % 2 == 1).count();
+                let choice = if odds > 0 { Branch::Left } else { Branch::Right };
+                Decision::new(choice, format!("{} odds in {}", odds, sampled.len()))
+            };
+            source
+                .adapt(
+                    "adapt",
+                    sample,
+                    decide,
+                    |s| s.map_with_fn(Pipeline, |i| Ok((0u32, i))),
+                    |s| s.map_with_fn(Pipeline, |i| Ok((1u32, i))),
+                )?
+                .sink_by(|_meta| {
+                    move |_tag, result| {
+                        if let ResultSet::Data(data) = result {
+                            tx.send(data).expect("send error");
+                        }
+                    }
+                })?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;")
+    .expect("job not started;")
+    .join()
+    .expect("run job failure;");
+    std::mem::drop(tx);
+    let mut results: Vec<(u32, u64)> = rx.iter().flatten().collect();
+    results.sort();
+    let profile = pegasus::fetch_job_profile(job_id).expect("job not profiled;");
+    (results, profile)
+}
+
+fn assert_routed(results: &[(u32, u64)]) {
+    assert_eq!(results.len(), 200);
+    // the data held as the sample go through the alternative chosen as well
+    for (branch, i) in results {
+        assert_eq!(*branch, if *i < 1000 { 0 } else { 1 }, "{} goes through {}", i, branch);
+    }
+}
+
+#[test]
+fn adapt_by_sample_test() {
+    pegasus::startup(Configuration::singleton()).ok();
+    let (results, profile) = run_adaptive(155, 10);
+    assert_routed(&results);
+    assert_eq!(profile.decisions.len(), 2);
+    let (first, second) = (&profile.decisions[0], &profile.decisions[1]);
+    assert_eq!((first.worker, first.choice, first.sampled), (0, Branch::Left, 10));
+    assert_eq!(first.reason, "5 odds in 10");
+    assert_eq!((second.worker, second.choice, second.sampled), (1, Branch::Right, 10));
+    assert_eq!(second.name, "adapt");
+}
+
+#[test]
+fn adapt_scope_end_test() {
+    pegasus::startup(Configuration::singleton()).ok();
+    // the scopes end before the samples are full, which are decided by all of their data
+    let (results, profile) = run_adaptive(156, 1000);
+    assert_routed(&results);
+    assert_eq!(profile.decisions.len(), 2);
+    assert!(profile.decisions.iter().all(|d| d.sampled == 100));
+}
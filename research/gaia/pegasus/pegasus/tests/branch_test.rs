@@ -13,7 +13,8 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
-use pegasus::api::{Branch, Exchange, IntoBranch, ResultSet, Sink};
+use pegasus::api::{Branch, Exchange, Filter, IntoBranch, Map, Merge, Rejected, ResultSet, Sink};
+use pegasus::communication::Pipeline;
 use pegasus::{Configuration, JobConf};
 
 #[test]
@@ -84,3 +85,183 @@ fn branch_test() {
     assert_eq!(count[1], 555);
     pegasus::shutdown_all();
 }
+
+fn run_branch_merge<F>(job_id: u64, pred: F) -> Vec<u32>
+where
+    F: Fn(&u32) -> bool + Send + Sync + Copy + 'static,
+{
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let conf = JobConf::new(job_id, "branch_merge_test", 2);
+
+    let (tx, rx) = crossbeam_channel::unbounded();
+    pegasus::run(conf, |worker| {
+        let index = worker.id.index;
+        let tx = tx.clone();
+        worker.dataflow(move |builder| {
+            let source = if index == 0 {
+                builder.input_from_iter(0..100u32)
+            } else {
+                builder.input_from_iter(100..200u32)
+            }?;
+            let (left, right) = source.branch_by(pred)?;
+            let left = left.map_with_fn(Pipeline, |item| Ok(item * 10))?;
+            let right = right.map_with_fn(Pipeline, |item| Ok(item + 1000))?;
+            left.merge(&right)?.sink_by(|_| {
+                move |_, result| {
+                    if let ResultSet::Data(data) = result {
+                        tx.send(data).unwrap();
+                    }
+                }
+            })?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;");
+
+    std::mem::drop(tx);
+    let mut result = vec![];
+    while let Ok(data) = rx.recv() {
+        result.extend(data);
+    }
+    result.sort();
+    result
+}
+
+#[test]
+fn branch_by_merge_test() {
+    let result = run_branch_merge(10, |item: &u32| (*item).is_multiple_of(2));
+    assert_eq!(result.len(), 200);
+    let mut expected: Vec<u32> =
+        (0..200u32).map(|i| if i % 2 == 0 { i * 10 } else { i + 1000 }).collect();
+    expected.sort();
+    assert_eq!(result, expected);
+    assert_eq!(result.iter().filter(|i| **i >= 1000 && **i % 2 == 1).count(), 100);
+}
+
+// the merge still ends while one side of the branch gets no data
+#[test]
+fn branch_by_one_side_test() {
+    let result = run_branch_merge(11, |_: &u32| true);
+    let expected: Vec<u32> = (0..200u32).map(|i| i * 10).collect();
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn filter_with_sideoutput_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let conf = JobConf::new(12, "filter_with_sideoutput_test", 2);
+
+    let (tx, rx) = crossbeam_channel::unbounded();
+    pegasus::run(conf, |worker| {
+        let index = worker.id.index;
+        let tx = tx.clone();
+        worker.dataflow(move |builder| {
+            let source = if index == 0 {
+                builder.input_from_iter(0..1000u32)
+            } else {
+                builder.input_from_iter(1000..2000u32)
+            }?;
+            let (kept, dropped) = source.filter_with_sideoutput(|item| Ok(*item % 3 == 0))?;
+            let tx_kept = tx.clone();
+            kept.sink_by(|_| {
+                move |_, result| {
+                    if let ResultSet::Data(data) = result {
+                        tx_kept.send((0, data)).unwrap();
+                    }
+                }
+            })?;
+            dropped.sink_by(|_| {
+                move |_, result| {
+                    if let ResultSet::Data(data) = result {
+                        tx.send((1, data)).unwrap();
+                    }
+                }
+            })?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;");
+
+    std::mem::drop(tx);
+    let mut kept = vec![];
+    let mut dropped = vec![];
+    while let Ok((flag, data)) = rx.recv() {
+        if flag == 0 {
+            kept.extend(data);
+        } else {
+            dropped.extend(data);
+        }
+    }
+    assert!(kept.iter().all(|item| *item % 3 == 0));
+    assert!(dropped.iter().all(|item| *item % 3 != 0));
+    let mut all = kept;
+    all.extend(dropped);
+    all.sort();
+    assert_eq!(all, (0..2000u32).collect::<Vec<_>>());
+}
+
+#[test]
+fn map_with_sideoutput_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let conf = JobConf::new(13, "map_with_sideoutput_test", 2);
+
+    let (tx, rx) = crossbeam_channel::unbounded();
+    pegasus::run(conf, |worker| {
+        let index = worker.id.index;
+        let tx = tx.clone();
+        worker.dataflow(move |builder| {
+            let source = if index == 0 {
+                builder.input_from_iter(0..1000u32)
+            } else {
+                builder.input_from_iter(1000..2000u32)
+            }?;
+            let (mapped, rejected) = source.map_with_sideoutput(|item| {
+                if *item % 7 == 0 {
+                    Err(format!("{} is divisible by 7", item))
+                } else {
+                    Ok(*item as u64 * 2)
+                }
+            })?;
+            let tx_mapped = tx.clone();
+            mapped.sink_by(|_| {
+                move |_, result| {
+                    if let ResultSet::Data(data) = result {
+                        tx_mapped.send(Ok(data)).unwrap();
+                    }
+                }
+            })?;
+            rejected.sink_by(|_| {
+                move |_, result| {
+                    if let ResultSet::Data(data) = result {
+                        tx.send(Err(data)).unwrap();
+                    }
+                }
+            })?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;");
+
+    std::mem::drop(tx);
+    let mut mapped = vec![];
+    let mut rejected: Vec<Rejected<u32>> = vec![];
+    while let Ok(data) = rx.recv() {
+        match data {
+            Ok(data) => mapped.extend(data),
+            Err(data) => rejected.extend(data),
+        }
+    }
+    assert_eq!(rejected.len(), 286);
+    for r in rejected.iter() {
+        assert_eq!(r.datum % 7, 0);
+        assert_eq!(r.error, format!("{} is divisible by 7", r.datum));
+    }
+    let mut all: Vec<u32> = mapped.into_iter().map(|item| (item / 2) as u32).collect();
+    assert!(all.iter().all(|item| *item % 7 != 0));
+    all.extend(rejected.into_iter().map(|r| r.datum));
+    all.sort();
+    assert_eq!(all, (0..2000u32).collect::<Vec<_>>());
+}
@@ -0,0 +1,65 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use pegasus::api::{Map, Sink};
+use pegasus::communication::Pipeline;
+use pegasus::{Configuration, JobConf, JobGuard, JobSubmitError};
+use std::time::Duration;
+
+fn run_sleeping_job(job_id: u64, items: u64) -> Result<Option<JobGuard>, JobSubmitError> {
+    let mut conf = JobConf::new(job_id, "drain_test", 1);
+    // check the cancellation after each item, which the operators yield in between
+    conf.batch_size = 1;
+    conf.slice_records = 1;
+    pegasus::run(conf, |worker| {
+        worker.dataflow(move |builder| {
+            builder
+                .input_from_iter(0..items)?
+                .map_with_fn(Pipeline, |item| {
+                    std::thread::sleep(Duration::from_millis(10));
+                    Ok(item)
+                })?
+                .sink_by(|_| |_, _| ())?;
+            Ok(())
+        })
+    })
+}
+
+// draining is not undone in the process, so it is the only test here;
+#[test]
+fn drain_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    // the job running for days, which is canceled at the deadline
+    let mut long_job = run_sleeping_job(1, u64::MAX).expect("submit job failure;").unwrap();
+    // the job completed soon after the draining starts
+    let mut short_job = run_sleeping_job(2, 10).expect("submit job failure;").unwrap();
+    assert!(!pegasus::is_draining());
+
+    let report = pegasus::drain(Duration::from_millis(500));
+    assert!(pegasus::is_draining());
+    assert_eq!(report.completed, vec![2]);
+    assert_eq!(report.cancelled, vec![1]);
+    short_job.join().expect("job failure;");
+    // the canceled job ends without failure
+    long_job.join().expect("job failure;");
+
+    match run_sleeping_job(3, 10) {
+        Err(JobSubmitError::Draining) => (),
+        Err(err) => panic!("unexpected error: {}", err),
+        Ok(_) => panic!("new job is accepted while draining"),
+    }
+    pegasus::shutdown_all();
+}
@@ -0,0 +1,168 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use pegasus::api::{Binary, Exchange, Map, ResultSet, Sink};
+use pegasus::cluster::Cluster;
+use pegasus::communication::Pipeline;
+use pegasus::keys;
+use pegasus::{Configuration, JobConf};
+use std::collections::HashMap;
+
+#[test]
+fn exchange_by_co_located_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let conf = JobConf::new(70, "exchange_by_co_located_test", 4);
+    let (tx, rx) = crossbeam_channel::unbounded();
+    let _guard = pegasus::run(conf, |worker| {
+        let index = worker.id.index;
+        let tx = tx.clone();
+        worker.dataflow(move |builder| {
+            let range = index * 100..(index + 1) * 100;
+            let left = builder
+                .input_from_iter(range.clone().map(|i| ((i % 10, i % 3), i)))?
+                .exchange_by(keys!(|item: &((u32, u32), u32)| item.0))?;
+            let right = builder
+                .input_from_iter(range.map(|i| ((i % 7, i % 3), i.to_string())))?
+                .exchange_by(keys!(|item: &((u32, u32), String)| item.0))?;
+            left.co_partitioned_with(&right)?;
+            let left = left.map_with_fn(Pipeline, move |item| Ok((index, item.0, true)))?;
+            let right = right.map_with_fn(Pipeline, move |item| Ok((index, item.0, false)))?;
+            left.binary("merge", &right, Pipeline, Pipeline, |_meta| {
+                |input, output| {
+                    input.left_for_each(|dataset| {
+                        output.forward(dataset)?;
+                        Ok(())
+                    })?;
+                    input.right_for_each(|dataset| {
+                        output.forward(dataset)?;
+                        Ok(())
+                    })
+                }
+            })?
+            .sink_by(|_meta| {
+                move |_, result| {
+                    if let ResultSet::Data(data) = result {
+                        tx.send(data).unwrap()
+                    }
+                }
+            })?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;");
+
+    ::std::mem::drop(tx);
+    let mut workers = HashMap::new();
+    let mut count = 0;
+    while let Ok(data) = rx.recv() {
+        for (index, key, _) in data {
+            let worker = *workers.entry(key).or_insert(index);
+            assert_eq!(worker, index, "key {:?} is on more than one worker", key);
+            count += 1;
+        }
+    }
+    assert_eq!(count, 800);
+    // the 21 keys of the right side are all among the 30 keys of the left side;
+    assert_eq!(workers.len(), 30);
+}
+
+/// Run on 2 servers, each of 2 workers, where the data of the same key from both sides are
+/// exchanged to the same worker, even across the servers;
+#[test]
+fn exchange_by_co_located_cluster_test() {
+    pegasus_common::logs::init_log();
+    let cluster = Cluster::new("exchange_by_co_located_cluster_test", 2);
+    let conf = JobConf::new(170, "exchange_by_co_located_cluster_test", 2);
+    let results = cluster
+        .run(conf, |builder| {
+            let index = builder.worker_id.index;
+            let range = index * 100..(index + 1) * 100;
+            let left = builder
+                .input_from_iter(range.clone().map(|i| ((i % 10, i % 3), i)))?
+                .exchange_by(keys!(|item: &((u32, u32), u32)| item.0))?;
+            let right = builder
+                .input_from_iter(range.map(|i| ((i % 7, i % 3), i.to_string())))?
+                .exchange_by(keys!(|item: &((u32, u32), String)| item.0))?;
+            left.co_partitioned_with(&right)?;
+            let left = left.map_with_fn(Pipeline, move |item| Ok((index, item.0, true)))?;
+            let right = right.map_with_fn(Pipeline, move |item| Ok((index, item.0, false)))?;
+            left.binary("merge", &right, Pipeline, Pipeline, |_meta| {
+                |input, output| {
+                    input.left_for_each(|dataset| {
+                        output.forward(dataset)?;
+                        Ok(())
+                    })?;
+                    input.right_for_each(|dataset| {
+                        output.forward(dataset)?;
+                        Ok(())
+                    })
+                }
+            })
+        })
+        .expect("run job failure;");
+
+    let mut workers = HashMap::new();
+    for (index, key, _) in results.iter() {
+        let worker = *workers.entry(*key).or_insert(*index);
+        assert_eq!(worker, *index, "key {:?} is on more than one worker", key);
+    }
+    assert_eq!(results.len(), 800);
+    // the 21 keys of the right side are all among the 30 keys of the left side;
+    assert_eq!(workers.len(), 30);
+    // the keys are owned by the workers of both servers;
+    assert!(workers.values().any(|index| *index < 2));
+    assert!(workers.values().any(|index| *index >= 2));
+}
+
+#[test]
+fn exchange_by_mismatched_keys_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let conf = JobConf::new(71, "exchange_by_mismatched_keys_test", 2);
+    let result = pegasus::run(conf, |worker| {
+        worker.dataflow(|builder| {
+            let left = builder
+                .input_from_iter((0..10u32).map(|i| (i, i as u64)))?
+                .exchange_by(keys!(|item: &(u32, u64)| item.0))?;
+            let right = builder
+                .input_from_iter((0..10u32).map(|i| (i, i as u64)))?
+                .exchange_by(keys!(|item: &(u32, u64)| item.1))?;
+            left.co_partitioned_with(&right)?;
+            left.sink_by(|_| |_, _| ())?;
+            Ok(())
+        })
+    });
+    let err = result.err().expect("build job should fail;");
+    assert!(err.to_string().contains("not co-partitioned"), "{}", err);
+}
+
+#[test]
+fn exchange_with_fn_not_co_partitioned_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let conf = JobConf::new(72, "exchange_with_fn_not_co_partitioned_test", 2);
+    let result = pegasus::run(conf, |worker| {
+        worker.dataflow(|builder| {
+            let left = builder.input_from_iter(0..10u32)?.exchange_by(keys!(|item: &u32| *item))?;
+            let right = builder.input_from_iter(0..10u32)?.exchange_with_fn(|item| *item as u64)?;
+            left.co_partitioned_with(&right)?;
+            left.sink_by(|_| |_, _| ())?;
+            Ok(())
+        })
+    });
+    let err = result.err().expect("build job should fail;");
+    assert!(err.to_string().contains("not co-partitioned"), "{}", err);
+}
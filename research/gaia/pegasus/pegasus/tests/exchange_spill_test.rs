@@ -0,0 +1,107 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use pegasus::api::{EnterScope, LeaveScope, ScopePartition, Sink, Unary};
+use pegasus::communication::{Aggregate, Channel};
+use pegasus::{Configuration, JobConf};
+
+const WINDOW: u32 = 16;
+
+// send (worker, seq) from each of the 2 workers to worker 0 in the scopes of the windows of seq,
+// where worker 0 consumes each batch slowly, so that the batches of the other scopes stash up
+// behind it; returns what worker 0 received in order, and the most temp files live at once;
+fn run_spilling(job_id: u64, spill_limit: u64) -> (Vec<(u32, u32)>, usize) {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let mut conf = JobConf::new(job_id, "exchange_spill_test", 2);
+    conf.batch_size = 4;
+    conf.exchange_spill_limit = spill_limit;
+    pegasus::communication::inject_reordering(job_id, true);
+    let spilled = Arc::new(AtomicUsize::new(0));
+    let (tx, rx) = crossbeam_channel::unbounded();
+    let mut guard = pegasus::run(conf, |worker| {
+        let index = worker.id.index;
+        let tx = tx.clone();
+        let spilled = spilled.clone();
+        worker.dataflow(move |builder| {
+            let mut channel: Channel<(u32, u32)> = Aggregate(0).into();
+            channel.preserve_order();
+            builder
+                .input_from_iter((0..256u32).map(move |seq| (index, seq)))?
+                .enter_scope(|item: &(u32, u32)| Some(ScopePartition::of(item.1 / WINDOW)))?
+                .unary("slow_forward", channel, move |_meta| {
+                    move |input, output| {
+                        input.for_each_batch(|dataset| {
+                            // the spilled batches not replayed yet are live temp files of the job;
+                            let live = pegasus::temp::live_resources(job_id);
+                            spilled.fetch_max(live, Ordering::SeqCst);
+                            std::thread::sleep(Duration::from_millis(2));
+                            tx.send(dataset.to_vec()).expect("send error");
+                            output.forward(dataset)?;
+                            Ok(())
+                        })
+                    }
+                })?
+                .leave_scope()?
+                .sink_by(|_meta| |_tag, _result| {})?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure")
+    .expect("job not started");
+
+    std::mem::drop(tx);
+    let mut received = vec![];
+    while let Ok(data) = rx.recv() {
+        received.extend(data);
+    }
+    guard.join().expect("run job failure");
+    pegasus::communication::inject_reordering(job_id, false);
+    assert_eq!(pegasus::temp::live_resources(job_id), 0);
+    assert!(!pegasus::temp::instance().job_dir(job_id).exists());
+    (received, spilled.load(Ordering::SeqCst))
+}
+
+fn is_ordered_per_window(received: &[(u32, u32)]) -> bool {
+    let mut last = std::collections::HashMap::new();
+    for (worker, seq) in received {
+        let last = last.entry((*worker, *seq / WINDOW)).or_insert(None);
+        if matches!(*last, Some(pre) if pre >= *seq) {
+            return false;
+        }
+        *last = Some(*seq);
+    }
+    true
+}
+
+#[test]
+fn exchange_spill_test() {
+    let (received, spilled) = run_spilling(1, 4);
+    assert_eq!(received.len(), 512);
+    assert!(is_ordered_per_window(&received), "out of order: {:?}", received);
+    assert!(spilled > 0, "nothing spilled");
+}
+
+#[test]
+fn exchange_no_spill_test() {
+    let (received, spilled) = run_spilling(2, 0);
+    assert_eq!(received.len(), 512);
+    assert!(is_ordered_per_window(&received));
+    assert_eq!(spilled, 0);
+}
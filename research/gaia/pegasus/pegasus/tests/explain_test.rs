@@ -0,0 +1,82 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use pegasus::api::{Exchange, Map, ResultSet, Sink};
+use pegasus::communication::Pipeline;
+use pegasus::{Configuration, JobConf, Tag};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+// an iterator setting the flag once read, which is never read by a dataflow explained
+struct Watched {
+    read: Arc<AtomicBool>,
+    inner: std::ops::Range<u32>,
+}
+
+impl Iterator for Watched {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        self.read.store(true, Ordering::SeqCst);
+        self.inner.next()
+    }
+}
+
+impl std::iter::FusedIterator for Watched {}
+
+#[test]
+fn explain_without_running_test() {
+    pegasus::startup(Configuration::singleton()).ok();
+    let read = Arc::new(AtomicBool::new(false));
+    let mut conf = JobConf::new(152, "explain_test", 4);
+    conf.add_servers(&[0, 1]);
+    let watched = Watched { read: read.clone(), inner: 0..100 };
+    let plan = pegasus::explain(conf, |worker| {
+        worker.dataflow(move |builder| {
+            builder
+                .input_from_iter(watched)?
+                .named("plus_one", |s| s.map_with_fn(Pipeline, |x| Ok(x + 1)))?
+                .exchange_with_fn(|x: &u32| *x as u64)?
+                .sink_by(|_meta| |_t: &Tag, _result: ResultSet<u32>| ())?;
+            Ok(())
+        })
+    })
+    .expect("explain failure;");
+    assert!(!read.load(Ordering::SeqCst));
+    let names: Vec<&str> = plan.operators.iter().map(|op| op.name.as_str()).collect();
+    assert_eq!(names.first(), Some(&"source"));
+    assert!(names.contains(&"plus_one"), "{:?}", names);
+    assert_eq!(names.last(), Some(&"sink"));
+    assert!(plan.operators.iter().all(|op| op.scope_depth == 0));
+    // the records are exchanged into the operator of the exchange only
+    let exchanged: Vec<_> = plan.channels.iter().filter(|ch| !ch.is_local).collect();
+    assert_eq!(exchanged.len(), 1);
+    assert_eq!(plan.operators[exchanged[0].target].name, "exchange");
+}
+
+#[test]
+fn explain_invalid_dataflow_test() {
+    pegasus::startup(Configuration::singleton()).ok();
+    let conf = JobConf::new(153, "explain_invalid_test", 1);
+    // the results of a dataflow without any sink go nowhere
+    let result = pegasus::explain(conf, |worker| {
+        worker.dataflow(|builder| {
+            builder.input_from_iter(0..10u32)?.map_with_fn(Pipeline, |x| Ok(x + 1))?;
+            Ok(())
+        })
+    });
+    let err = result.expect_err("explain a dataflow without sink");
+    assert!(err.to_string().contains("no sink"), "{}", err);
+}
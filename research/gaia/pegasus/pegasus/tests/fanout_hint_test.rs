@@ -0,0 +1,110 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use pegasus::api::{Map, ResultSet, Sink, SubTask};
+use pegasus::communication::Pipeline;
+use pegasus::errors::BuildJobError;
+use pegasus::stream::Stream;
+use pegasus::{Configuration, JobConf, Tag};
+
+// the outputs are of 32 batches of 16 in each firing, where each batch of inputs expands to 8
+fn job_conf(job_id: u64, name: &str) -> JobConf {
+    let mut conf = JobConf::new(job_id, name, 1);
+    conf.batch_size = 16;
+    conf.output_capacity = 32;
+    conf.profile = true;
+    conf
+}
+
+fn expand(stream: Stream<u32>, hint: bool) -> Result<Stream<u32>, BuildJobError> {
+    let stream = if hint { stream.with_fanout_hint(8.0) } else { stream };
+    stream.named("expand", |s| {
+        s.flat_map_with_fn(Pipeline, |item| Ok(vec![item; 8].into_iter().map(Ok)))
+    })
+}
+
+fn run_flat_map(job_id: u64, hint: bool) -> u64 {
+    let (tx, rx) = crossbeam_channel::unbounded();
+    pegasus::run(job_conf(job_id, "fanout_flat_map"), |worker| {
+        let tx = tx.clone();
+        worker.dataflow(move |builder| {
+            let src = builder.input_from_iter(0..4096u32)?;
+            expand(src, hint)?.sink_by(|_meta| count_to(tx))?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;")
+    .expect("job not started;")
+    .join()
+    .expect("run job failure;");
+    std::mem::drop(tx);
+    assert_eq!(rx.iter().sum::<usize>(), 8 * 4096);
+    stalls_of(job_id)
+}
+
+fn run_fork_join(job_id: u64, hint: bool) -> u64 {
+    let (tx, rx) = crossbeam_channel::unbounded();
+    pegasus::run(job_conf(job_id, "fanout_fork_join"), |worker| {
+        let tx = tx.clone();
+        worker.dataflow(move |builder| {
+            let src = builder.input_from_iter(0..1024u32)?;
+            let subtask = src.fork_subtask(|stream| expand(stream, hint))?;
+            src.join_subtask(subtask, |p, s| Some(s - *p))?.sink_by(|_meta| count_to(tx))?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;")
+    .expect("job not started;")
+    .join()
+    .expect("run job failure;");
+    std::mem::drop(tx);
+    assert_eq!(rx.iter().sum::<usize>(), 8 * 1024);
+    stalls_of(job_id)
+}
+
+fn count_to(
+    tx: crossbeam_channel::Sender<usize>,
+) -> impl Fn(&Tag, ResultSet<u32>) + Send + 'static {
+    move |_t: &Tag, result: ResultSet<u32>| {
+        if let ResultSet::Data(data) = result {
+            tx.send(data.len()).ok();
+        }
+    }
+}
+
+fn stalls_of(job_id: u64) -> u64 {
+    let profile = pegasus::fetch_job_profile(job_id).expect("job not profiled;");
+    let expand = profile.get_operator("expand").expect("operator expand not found;");
+    expand.stalls.sum
+}
+
+#[test]
+fn fanout_hint_flat_map_test() {
+    pegasus::startup(Configuration::singleton()).ok();
+    let stalls = run_flat_map(148, false);
+    let hinted = run_flat_map(149, true);
+    // without the hint, the flat map takes all the batches of the source in a firing, of which
+    // the outputs overshoot the capacity
+    assert!(stalls > 0);
+    assert!(hinted < stalls, "{} stalls with the hint, {} without;", hinted, stalls);
+}
+
+#[test]
+fn fanout_hint_fork_join_test() {
+    pegasus::startup(Configuration::singleton()).ok();
+    let stalls = run_fork_join(150, false);
+    let hinted = run_fork_join(151, true);
+    assert!(hinted <= stalls, "{} stalls with the hint, {} without;", hinted, stalls);
+}
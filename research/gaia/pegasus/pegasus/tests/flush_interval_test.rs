@@ -0,0 +1,133 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use pegasus::api::{Map, Merge, ResultSet, Sink};
+use pegasus::communication::Pipeline;
+use pegasus::{Configuration, JobConf};
+use std::time::{Duration, Instant};
+
+/// A source giving an item every 5 milliseconds;
+struct SlowSource {
+    next: u32,
+    end: u32,
+}
+
+impl Iterator for SlowSource {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        if self.next < self.end {
+            std::thread::sleep(Duration::from_millis(5));
+            self.next += 1;
+            Some(self.next - 1)
+        } else {
+            None
+        }
+    }
+}
+
+impl std::iter::FusedIterator for SlowSource {}
+
+/// Run a job on the slow source, and get the time of its first result and the total results;
+fn first_result_time(job_id: u64, flush_interval_ms: u64) -> (Duration, usize) {
+    let mut conf = JobConf::new(job_id, "flush_interval_test", 1);
+    conf.batch_flush_interval_ms = flush_interval_ms;
+    let (tx, rx) = crossbeam_channel::unbounded();
+    let start = Instant::now();
+    let mut guard = pegasus::run(conf, |worker| {
+        let tx = tx.clone();
+        worker.dataflow(move |builder| {
+            builder
+                .input_from_iter(SlowSource { next: 0, end: 100 })?
+                .map_with_fn(Pipeline, |item| Ok(item + 1))?
+                .sink_by(|_| {
+                    move |_, result| {
+                        if let ResultSet::Data(data) = result {
+                            tx.send((Instant::now(), data.len())).unwrap();
+                        }
+                    }
+                })?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;")
+    .expect("job not started;");
+    guard.join().expect("run job failure;");
+    drop(tx);
+    let results: Vec<(Instant, usize)> = rx.iter().collect();
+    let first = results.first().expect("no result;").0.duration_since(start);
+    (first, results.iter().map(|(_, len)| *len).sum())
+}
+
+// the 100 items of the source fit in a batch, which is flushed only after the source is
+// exhausted without the flush interval
+#[test]
+fn flush_interval_latency_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let (slow, total) = first_result_time(1, 0);
+    assert_eq!(total, 100);
+    let (fast, total) = first_result_time(2, 1);
+    assert_eq!(total, 100);
+    assert!(slow >= Duration::from_millis(400), "first result without interval in {:?}", slow);
+    assert!(fast * 4 < slow, "first result in {:?} with interval, {:?} without", fast, slow);
+}
+
+#[test]
+fn output_capacity_validation_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    // the capacity is enough for the operators of one input, but not for merge
+    let mut conf = JobConf::new(3, "output_capacity_validation_test", 1);
+    conf.output_capacity = pegasus::MIN_BINARY_OUTPUT_CAPACITY - 1;
+    let result = pegasus::run(conf.clone(), |worker| {
+        worker.dataflow(|builder| {
+            let stream = builder.input_from_iter(0..10u32)?;
+            let plus = stream.map_with_fn(Pipeline, |item| Ok(item + 1))?;
+            stream.merge(&plus)?.sink_by(|_| |_, _| ())?;
+            Ok(())
+        })
+    });
+    let err = result.err().expect("build job should fail;");
+    assert!(err.to_string().contains("is below 2 needed by operator merge"), "{}", err);
+
+    conf.job_id = 4;
+    let mut guard = pegasus::run(conf.clone(), |worker| {
+        worker.dataflow(|builder| {
+            builder
+                .input_from_iter(0..10u32)?
+                .map_with_fn(Pipeline, |item| Ok(item + 1))?
+                .sink_by(|_| |_, _| ())?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;")
+    .expect("job not started;");
+    guard.join().expect("run job failure;");
+
+    conf.job_id = 5;
+    conf.output_capacity = 0;
+    let result = pegasus::run(conf, |worker| {
+        worker.dataflow(|builder| {
+            builder
+                .input_from_iter(0..10u32)?
+                .map_with_fn(Pipeline, |item| Ok(item + 1))?
+                .sink_by(|_| |_, _| ())?;
+            Ok(())
+        })
+    });
+    let err = result.err().expect("build job should fail;");
+    assert!(err.to_string().contains("output_capacity 0 is below 1"), "{}", err);
+}
@@ -0,0 +1,86 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use pegasus::health::{ComponentState, Requirement};
+use pegasus::Configuration;
+use pegasus_network::config::ConnectionParams;
+use pegasus_network::Server;
+use std::time::{Duration, Instant};
+
+fn wait_until<F: Fn() -> bool>(timeout: Duration, cond: F) -> bool {
+    let start = Instant::now();
+    while !cond() {
+        if start.elapsed() > timeout {
+            return false;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    true
+}
+
+/// The server 0 runs the engine, and the server 1 only joins the network in the same process, to
+/// be connected with the server 0 once it starts up;
+#[test]
+fn readiness_test() {
+    pegasus_common::logs::init_log();
+    let conf = Configuration::builder()
+        .server_id(0)
+        .add_peer(0, "127.0.0.1", 1270)
+        .add_peer(1, "127.0.0.1", 1271)
+        .require_ready("peers")
+        .require_ready("pool")
+        .require_ready("graph:ldbc")
+        .build()
+        .unwrap();
+    pegasus::startup(conf).expect("startup failure;");
+    assert!(wait_until(Duration::from_secs(10), pegasus::health::is_live));
+
+    let report = pegasus::health();
+    assert!(!report.ready);
+    assert_eq!(report.unmet, vec!["peers".to_owned(), "graph:ldbc".to_owned()]);
+    assert_eq!(report.peers.len(), 1);
+    assert!(!report.peers[0].connected);
+
+    // a graph being loaded is not ready yet;
+    pegasus::health::set_component_state("graph:ldbc", ComponentState::Loading);
+    assert!(!pegasus::health().ready);
+    pegasus::health::set_component_state("graph:ldbc", ComponentState::Ready);
+    let report = pegasus::health();
+    assert!(!report.ready);
+    assert_eq!(report.unmet, vec!["peers".to_owned()]);
+
+    let servers = vec![
+        Server { id: 0, addr: "127.0.0.1:1270".parse().unwrap() },
+        Server { id: 1, addr: "127.0.0.1:1271".parse().unwrap() },
+    ];
+    let mut params = ConnectionParams::blocking();
+    params.set_heartbeat_interval(1);
+    pegasus_network::start_up(1, params, servers[1].addr, servers.clone()).unwrap();
+    assert!(wait_until(Duration::from_secs(10), pegasus::health::is_ready));
+    let report = pegasus::health();
+    assert!(report.unmet.is_empty());
+    assert!(report.peers[0].connected);
+    assert_eq!(report.components[0].name, "graph:ldbc");
+
+    // a failed component fails the readiness again, and the requirements can be replaced;
+    pegasus::health::set_component_state("graph:ldbc", ComponentState::Failed("io".to_owned()));
+    assert!(!pegasus::health::is_ready());
+    pegasus::health::set_readiness(vec![Requirement::AllPeersConnected]);
+    assert!(pegasus::health::is_ready());
+
+    pegasus_network::shutdown(1);
+    pegasus_network::await_termination(1);
+    pegasus::shutdown_all();
+}
@@ -15,8 +15,8 @@
 
 use pegasus::api::function::*;
 use pegasus::api::{
-    complete, Exchange, Iteration, LoopCondition, Map, Multiplexing, NonBlockReceiver, ResultSet,
-    Sink,
+    complete, EmitKind, Exchange, Iteration, LoopCondition, Map, Multiplexing, NonBlockReceiver,
+    ResultSet, Sink,
 };
 use pegasus::communication::Pipeline;
 use pegasus::filter;
@@ -121,6 +121,61 @@ fn ping_pong_test_02() {
     pegasus::shutdown_all();
 }
 
+#[test]
+fn ping_pong_test_04() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let conf = JobConf::new(64, "ping_pong_test_04", 2);
+    let (tx, rx) = crossbeam_channel::unbounded();
+    let _guard = pegasus::run(conf, |worker| {
+        let tx = tx.clone();
+        let index = worker.id.index;
+        worker.dataflow(move |builder| {
+            let source = if index == 0 {
+                builder.input_from_iter(0..5u32)
+            } else {
+                builder.input_from_iter(5..10u32)
+            }?
+            .map_with_fn(Pipeline, |item| Ok((0u32, item)))?;
+            // each datum goes through the loop at least once, and is emitted after each round;
+            let mut condition = LoopCondition::new();
+            condition.until(Box::new(filter!(|item: &(u32, u32)| Ok(item.1 >= 5))));
+            condition.set_pre_check(false);
+            condition.set_emit(EmitKind::After);
+            source
+                .iterate_until(condition, |start| {
+                    start
+                        .exchange_with_fn(|item: &(u32, u32)| item.1 as u64)?
+                        .map_with_fn(Pipeline, |(index, item)| Ok((index + 1, item + 1)))
+                })?
+                .sink_by(|_| {
+                    move |_, result| {
+                        if let ResultSet::Data(data) = result {
+                            tx.send(data).unwrap();
+                        }
+                    }
+                })?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure");
+
+    std::mem::drop(tx);
+    let mut count = 0;
+    let mut sum = 0;
+    while let Ok(data) = rx.recv() {
+        count += data.len();
+        for (x, y) in data {
+            assert!(x >= 1);
+            sum += y;
+        }
+    }
+    // 0..5 emit [1..=5], [2..=5], .. [5], and 5..10 leave after one round as 6..=10;
+    assert_eq!(count, 20);
+    assert_eq!(sum, 55 + 40);
+    pegasus::shutdown_all();
+}
+
 #[test]
 fn ping_pong_test_03() {
     pegasus_common::logs::init_log();
@@ -161,9 +216,9 @@ fn ping_pong_test_03() {
                 })?
                 .sink_by(|_| {
                     move |t, result| {
-                        let t = t.current_uncheck();
+                        // the end of the whole stream is told by the root tag;
                         if let ResultSet::Data(data) = result {
-                            tx.send((t, data)).unwrap();
+                            tx.send((t.current_uncheck(), data)).unwrap();
                         }
                     }
                 })?;
@@ -194,3 +249,150 @@ fn ping_pong_test_03() {
     assert_eq!(count, vec![1023, 1024, 1025]);
     pegasus::shutdown_all();
 }
+
+#[test]
+fn iteration_round_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let conf = JobConf::new(145, "iteration_round_test", 2);
+    let (tx, rx) = crossbeam_channel::unbounded();
+    let guard = pegasus::run(conf, |worker| {
+        let tx = tx.clone();
+        worker.dataflow(move |builder| {
+            builder
+                .input_from_iter((0..10u32).map(|_| vec![]))?
+                .iterate(3, |start| {
+                    start.exchange_with_fn(|rounds: &Vec<u32>| rounds.len() as u64)?.map_with_fn(
+                        Pipeline,
+                        |mut rounds| {
+                            rounds.push(pegasus::current_iteration().unwrap_or(99));
+                            Ok(rounds)
+                        },
+                    )
+                })?
+                .map_with_fn(Pipeline, |rounds| Ok((rounds, pegasus::current_iteration())))?
+                .sink_by(|_| {
+                    move |_, result| {
+                        if let ResultSet::Data(data) = result {
+                            tx.send(data).unwrap();
+                        }
+                    }
+                })?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure");
+
+    std::mem::drop(tx);
+    let mut count = 0;
+    while let Ok(data) = rx.recv() {
+        for (rounds, outside) in data {
+            assert_eq!(rounds, vec![0, 1, 2]);
+            assert_eq!(outside, None);
+            count += 1;
+        }
+    }
+    guard.unwrap().join().expect("run job failure;");
+    assert_eq!(count, 20);
+    pegasus::shutdown_all();
+}
+
+#[test]
+fn nested_iteration_round_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let conf = JobConf::new(146, "nested_iteration_round_test", 2);
+    let (tx, rx) = crossbeam_channel::unbounded();
+    let guard = pegasus::run(conf, |worker| {
+        let tx = tx.clone();
+        worker.dataflow(move |builder| {
+            builder
+                .input_from_iter((0..10u32).map(|_| vec![]))?
+                .iterate(2, |outer| {
+                    outer
+                        .map_with_fn(Pipeline, |mut rounds: Vec<u32>| {
+                            // the rounds of the outer loop, apart from the inner one;
+                            rounds.push(100 + pegasus::current_iteration().unwrap_or(99));
+                            Ok(rounds)
+                        })?
+                        .iterate(3, |inner| {
+                            inner.map_with_fn(Pipeline, |mut rounds| {
+                                let cur = pegasus::current_iterations();
+                                assert_eq!(pegasus::current_iteration(), cur.last().copied());
+                                rounds.push(cur[0] * 10 + cur[1]);
+                                Ok(rounds)
+                            })
+                        })
+                })?
+                .sink_by(|_| {
+                    move |_, result| {
+                        if let ResultSet::Data(data) = result {
+                            tx.send(data).unwrap();
+                        }
+                    }
+                })?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure");
+
+    std::mem::drop(tx);
+    let mut count = 0;
+    while let Ok(data) = rx.recv() {
+        for rounds in data {
+            assert_eq!(rounds, vec![100, 0, 1, 2, 101, 10, 11, 12]);
+            count += 1;
+        }
+    }
+    guard.unwrap().join().expect("run job failure;");
+    assert_eq!(count, 20);
+    pegasus::shutdown_all();
+}
+
+#[test]
+fn iterate_with_round_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let conf = JobConf::new(147, "iterate_with_round_test", 2);
+    let (tx, rx) = crossbeam_channel::unbounded();
+    let guard = pegasus::run(conf, |worker| {
+        let tx = tx.clone();
+        worker.dataflow(move |builder| {
+            builder
+                .input_from_iter((0..10u32).map(|_| vec![]))?
+                .iterate_with_round(2, |outer_round, outer| {
+                    outer.iterate_with_round(3, move |inner_round, inner| {
+                        inner
+                            .exchange_with_fn(|rounds: &Vec<u32>| rounds.len() as u64)?
+                            .map_with_fn(Pipeline, move |mut rounds| {
+                                let outer = outer_round.get().unwrap_or(99);
+                                let inner = inner_round.get().unwrap_or(99);
+                                rounds.push(outer * 10 + inner);
+                                Ok(rounds)
+                            })
+                    })
+                })?
+                .sink_by(|_| {
+                    move |_, result| {
+                        if let ResultSet::Data(data) = result {
+                            tx.send(data).unwrap();
+                        }
+                    }
+                })?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure");
+
+    std::mem::drop(tx);
+    let mut count = 0;
+    while let Ok(data) = rx.recv() {
+        for rounds in data {
+            assert_eq!(rounds, vec![0, 1, 2, 10, 11, 12]);
+            count += 1;
+        }
+    }
+    guard.unwrap().join().expect("run job failure;");
+    assert_eq!(count, 20);
+    pegasus::shutdown_all();
+}
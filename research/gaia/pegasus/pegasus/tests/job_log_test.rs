@@ -0,0 +1,82 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use pegasus::api::{Map, ResultSet, Sink};
+use pegasus::communication::Pipeline;
+use pegasus::{Configuration, JobConf, JobGuard, Tag};
+use std::io;
+
+fn submit(job_id: u64, capture_logs: bool, fail: bool) -> JobGuard {
+    let mut conf = JobConf::new(job_id, "job_log_test", 2);
+    conf.capture_logs = capture_logs;
+    pegasus::run(conf, move |worker| {
+        worker.dataflow(move |builder| {
+            builder
+                .input_from_iter(0..1000u32)?
+                .map_with_fn(Pipeline, move |item| {
+                    if fail && item == 500 {
+                        Err(Box::new(io::Error::other("fail on purpose")))
+                    } else {
+                        Ok(item)
+                    }
+                })?
+                .sink_by(|_meta| |_t: &Tag, _result: ResultSet<u32>| ())?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;")
+    .expect("job not started;")
+}
+
+#[test]
+fn concurrent_jobs_test() {
+    pegasus::startup(Configuration::singleton()).ok();
+    let mut guards = [submit(101, true, false), submit(102, true, false)];
+    let mut not_captured = submit(103, false, false);
+    for guard in guards.iter_mut() {
+        guard.join().expect("run job failure;");
+    }
+    not_captured.join().expect("run job failure;");
+
+    for (job_id, other) in &[(101, 102), (102, 101)] {
+        let logs = pegasus::fetch_job_logs(*job_id).expect("logs not captured;");
+        assert!(!logs.is_empty());
+        // all lines are of the workers of this job only
+        for line in logs.iter() {
+            assert!(line.contains(&format!("({}-2)]", job_id)), "{}", line);
+            assert!(!line.contains(&format!("({}-2)]", other)), "{}", line);
+        }
+        // e.g. "INFO [worker_0(101-2)]: source has been exhausted;"
+        for index in 0..2 {
+            let exhausted =
+                format!("INFO [worker_{}({}-2)]: source has been exhausted;", index, job_id);
+            assert!(logs.contains(&exhausted), "{:?}", logs);
+        }
+    }
+    assert!(pegasus::fetch_job_logs(103).is_none());
+}
+
+#[test]
+fn attach_to_error_test() {
+    pegasus::startup(Configuration::singleton()).ok();
+    let err = submit(104, true, true).join().expect_err("job should fail;");
+    let msg = err.to_string();
+    assert!(msg.contains("fail on purpose"), "{}", msg);
+    assert!(msg.contains("captured logs:"), "{}", msg);
+    assert!(msg.contains("ERROR [worker_"), "{}", msg);
+    assert!(msg.contains("(104-2)]: execute failure, cancel the job"), "{}", msg);
+    let logs = pegasus::fetch_job_logs(104).expect("logs not captured;");
+    assert!(logs.iter().any(|line| line.contains("execute failure")));
+}
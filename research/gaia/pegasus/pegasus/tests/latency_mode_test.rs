@@ -0,0 +1,121 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use pegasus::api::{Limit, Map, Range, ResultSet, Sink};
+use pegasus::communication::Pipeline;
+use pegasus::{Configuration, JobConf, LatencyMode};
+use std::time::{Duration, Instant};
+
+/// A source giving an item every 5 milliseconds;
+struct SlowSource {
+    next: u32,
+    end: u32,
+}
+
+impl Iterator for SlowSource {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        if self.next < self.end {
+            std::thread::sleep(Duration::from_millis(5));
+            self.next += 1;
+            Some(self.next - 1)
+        } else {
+            None
+        }
+    }
+}
+
+impl std::iter::FusedIterator for SlowSource {}
+
+/// Run `limit(1)` over the slow sources of 2 workers, gathered by the limit, and get the time of
+/// its first result and the total results;
+fn first_result_time(job_id: u64, mode: LatencyMode) -> (Duration, usize) {
+    let mut conf = JobConf::new(job_id, "latency_mode_test", 2);
+    conf.latency_mode = mode;
+    let (tx, rx) = crossbeam_channel::unbounded();
+    let start = Instant::now();
+    let mut guard = pegasus::run(conf, |worker| {
+        let tx = tx.clone();
+        worker.dataflow(move |builder| {
+            builder
+                .input_from_iter(SlowSource { next: 0, end: 100 })?
+                .map_with_fn(Pipeline, |item| Ok(item + 1))?
+                .limit(Range::Global, 1)?
+                .sink_by(|_| {
+                    move |_, result| {
+                        if let ResultSet::Data(data) = result {
+                            tx.send((Instant::now(), data.len())).unwrap();
+                        }
+                    }
+                })?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;")
+    .expect("job not started;");
+    guard.join().expect("run job failure;");
+    drop(tx);
+    let results: Vec<(Instant, usize)> = rx.iter().collect();
+    let first = results.first().expect("no result;").0.duration_since(start);
+    (first, results.iter().map(|(_, len)| *len).sum())
+}
+
+// the 100 items of each source fit in a batch, which waits for the source to be exhausted in the
+// throughput mode, while the first item goes through at once in the eager mode
+#[test]
+fn eager_first_result_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let (slow, total) = first_result_time(1, LatencyMode::Throughput);
+    assert_eq!(total, 1);
+    let (fast, total) = first_result_time(2, LatencyMode::Eager);
+    assert_eq!(total, 1);
+    assert!(slow >= Duration::from_millis(400), "first result in throughput mode in {:?}", slow);
+    assert!(fast * 10 < slow, "first result in {:?} in eager mode, {:?} otherwise", fast, slow);
+}
+
+// the records go in batches of their own in the eager mode, none of which is lost
+#[test]
+fn eager_single_record_batches_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let mut conf = JobConf::new(3, "eager_single_record_batches_test", 2);
+    conf.latency_mode = LatencyMode::Eager;
+    let (tx, rx) = crossbeam_channel::unbounded();
+    let mut guard = pegasus::run(conf, |worker| {
+        let tx = tx.clone();
+        worker.dataflow(move |builder| {
+            builder
+                .input_from_iter(0..1000u32)?
+                .map_with_fn(Pipeline, |item| Ok(item + 1))?
+                .sink_by(|_| {
+                    move |_, result| {
+                        if let ResultSet::Data(data) = result {
+                            tx.send(data.len()).unwrap();
+                        }
+                    }
+                })?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;")
+    .expect("job not started;");
+    guard.join().expect("run job failure;");
+    drop(tx);
+    let batches: Vec<usize> = rx.iter().collect();
+    assert_eq!(batches.len(), 2000);
+    assert!(batches.iter().all(|len| *len == 1));
+}
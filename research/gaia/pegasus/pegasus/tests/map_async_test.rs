@@ -0,0 +1,166 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use pegasus::api::function::FnResult;
+use pegasus::api::{Count, Map, Range, ResultSet, Sink};
+use pegasus::communication::Pipeline;
+use pegasus::{Configuration, JobConf};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+/// A mock of the async sleep without any runtime, which is woken by a timer thread;
+struct Delay {
+    deadline: Instant,
+    started: bool,
+}
+
+impl Delay {
+    fn new(duration: Duration) -> Self {
+        Delay { deadline: Instant::now() + duration, started: false }
+    }
+}
+
+impl Future for Delay {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let now = Instant::now();
+        if now >= self.deadline {
+            Poll::Ready(())
+        } else {
+            if !self.started {
+                self.started = true;
+                let waker = cx.waker().clone();
+                let duration = self.deadline - now;
+                std::thread::spawn(move || {
+                    std::thread::sleep(duration);
+                    waker.wake();
+                });
+            }
+            Poll::Pending
+        }
+    }
+}
+
+/// A mock external service counting the lookups in flight;
+#[derive(Clone, Default)]
+struct MockService {
+    in_flight: Arc<AtomicUsize>,
+    max_in_flight: Arc<AtomicUsize>,
+}
+
+impl MockService {
+    async fn lookup(self, key: u32) -> FnResult<u32> {
+        let current = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        self.max_in_flight.fetch_max(current, Ordering::SeqCst);
+        Delay::new(Duration::from_millis((key % 5) as u64)).await;
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+        Ok(key * 2)
+    }
+}
+
+async fn delay_echo(item: u32) -> FnResult<u32> {
+    Delay::new(Duration::from_millis(1)).await;
+    Ok(item)
+}
+
+#[test]
+fn map_async_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let conf = JobConf::new(1, "map_async_test", 2);
+    let service = MockService::default();
+
+    let (tx, rx) = crossbeam_channel::unbounded();
+    let service_cloned = service.clone();
+    pegasus::run(conf, |worker| {
+        let index = worker.id.index;
+        let tx = tx.clone();
+        let service = service_cloned.clone();
+        worker.dataflow(move |builder| {
+            let source = if index == 0 {
+                builder.input_from_iter(0..100u32)
+            } else {
+                builder.input_from_iter(100..200u32)
+            }?;
+            source
+                .map_async_with_fn(Pipeline, 4, move |item| service.clone().lookup(item))?
+                .sink_by(|_| {
+                    move |_, result| {
+                        if let ResultSet::Data(data) = result {
+                            tx.send(data).unwrap();
+                        }
+                    }
+                })?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;");
+
+    std::mem::drop(tx);
+    let mut result = vec![];
+    while let Ok(data) = rx.recv() {
+        result.extend(data);
+    }
+    // the outputs may be out of order;
+    result.sort();
+    assert_eq!(result, (0..200u32).map(|i| i * 2).collect::<Vec<_>>());
+    // at most 4 lookups in flight on each of the 2 workers;
+    assert!(service.max_in_flight.load(Ordering::SeqCst) <= 8);
+    assert_eq!(service.in_flight.load(Ordering::SeqCst), 0);
+}
+
+// the end of the scope is held back until all lookups complete, so nothing is missed by count;
+#[test]
+fn map_async_count_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let conf = JobConf::new(2, "map_async_count_test", 2);
+
+    let (tx, rx) = crossbeam_channel::unbounded();
+    pegasus::run(conf, |worker| {
+        let index = worker.id.index;
+        let tx = tx.clone();
+        worker.dataflow(move |builder| {
+            let source = if index == 0 {
+                builder.input_from_iter(0..100u32)
+            } else {
+                builder.input_from_iter(100..200u32)
+            }?;
+            source.map_async_with_fn(Pipeline, 2, delay_echo)?.count(Range::Global)?.sink_by(
+                |_| {
+                    move |_, result| {
+                        if let ResultSet::Data(data) = result {
+                            tx.send(data).unwrap();
+                        }
+                    }
+                },
+            )?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;");
+
+    std::mem::drop(tx);
+    let mut counts = vec![];
+    while let Ok(data) = rx.recv() {
+        counts.extend(data);
+    }
+    assert_eq!(counts, vec![200]);
+}
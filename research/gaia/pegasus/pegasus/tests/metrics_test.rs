@@ -0,0 +1,123 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use pegasus::api::function::{FnResult, Idempotent, MapClosure};
+use pegasus::api::{ApproxDedup, Exchange, Map, Range, RetryPolicy, Sink};
+use pegasus::communication::Pipeline;
+use pegasus::health::ComponentState;
+use pegasus::{Configuration, JobConf};
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+static FAILED: AtomicBool = AtomicBool::new(false);
+
+/// Fail on the first datum only, which is retried once;
+fn fail_once(item: u64) -> FnResult<u64> {
+    if FAILED.swap(true, Ordering::SeqCst) {
+        Ok(item)
+    } else {
+        let err: Box<dyn std::error::Error + Send + Sync> = "fail once".into();
+        Err(err)
+    }
+}
+
+fn get(path: &str) -> String {
+    let addr = pegasus::metrics::endpoint_addr().expect("metrics endpoint not started;");
+    let mut stream = TcpStream::connect(addr).expect("connect metrics endpoint failure;");
+    write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
+
+fn scrape() -> String {
+    let response = get("/metrics");
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+    response
+}
+
+/// Get the value of the metric of given name and labels, e.g. `name{job="x",worker="0"}`
+fn get_value(metrics: &str, name_with_labels: &str) -> Option<f64> {
+    metrics
+        .lines()
+        .find(|line| line.starts_with(name_with_labels))
+        .and_then(|line| line[name_with_labels.len()..].trim().parse().ok())
+}
+
+#[test]
+fn metrics_test() {
+    pegasus_common::logs::init_log();
+    let conf = Configuration::builder().metrics_addr("127.0.0.1:0").build().unwrap();
+    pegasus::startup(conf).expect("startup failure;");
+
+    let conf = JobConf::new(1, "metrics_test", 2);
+    let mut guard = pegasus::run(conf, |worker| {
+        worker.dataflow(move |builder| {
+            builder
+                .input_from_iter(0..100u64)?
+                .exchange_with_fn(|item| *item)?
+                .map_with_fn(Pipeline, |item| Ok(item + 1))?
+                .with_retry(RetryPolicy { max_attempts: 2, backoff: Duration::from_millis(1) })
+                .map(Pipeline, Idempotent(MapClosure::new(fail_once)))?
+                .dedup_approx(Range::Global, 1000, 0.01)?
+                .sink_by(|_| |_, _| ())?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;")
+    .unwrap();
+    guard.join().expect("run job failure;");
+    // the workers are released after the job guard is joined
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    pegasus::metrics::add_cache_accesses("test_cache", 3, 1);
+
+    let metrics = scrape();
+    assert_eq!(get_value(&metrics, "pegasus_running_jobs"), Some(0.0));
+    // the 200 records are received by the two maps, and then by the sink
+    let mut records = 0.0;
+    for index in 0..2 {
+        let name =
+            format!("pegasus_records_processed_total{{job=\"metrics_test\",worker=\"{}\"}}", index);
+        records += get_value(&metrics, &name).expect("records of worker lost;");
+        let name = format!(
+            "pegasus_worker_busy_seconds_total{{job=\"metrics_test\",worker=\"{}\"}}",
+            index
+        );
+        assert!(get_value(&metrics, &name).expect("busy time of worker lost;") > 0.0);
+    }
+    assert!(records >= 600.0, "records: {}", records);
+    let retries = "pegasus_operator_retries_total{job=\"metrics_test\",operator=\"map\"}";
+    assert_eq!(get_value(&metrics, retries), Some(1.0));
+    // the filter of each worker is reported once its only scope ends
+    let fill = "pegasus_filter_fill_ratio_count{job=\"metrics_test\",operator=\"dedup_approx\"}";
+    assert_eq!(get_value(&metrics, fill), Some(2.0));
+    assert_eq!(get_value(&metrics, "pegasus_channel_backlog{job=\"metrics_test\"}"), Some(0.0));
+    assert_eq!(get_value(&metrics, "pegasus_cache_hits_total{cache=\"test_cache\"}"), Some(3.0));
+    assert_eq!(get_value(&metrics, "pegasus_cache_misses_total{cache=\"test_cache\"}"), Some(1.0));
+    assert!(metrics.contains("pegasus_network_sent_bytes_total"));
+
+    // the probes of the health share the endpoint, where a component loading fails the readiness
+    assert!(get("/live").starts_with("HTTP/1.1 200 OK"));
+    assert!(get("/ready").starts_with("HTTP/1.1 200 OK"));
+    pegasus::health::set_component_state("graph:test", ComponentState::Loading);
+    let response = get("/ready");
+    assert!(response.starts_with("HTTP/1.1 503"), "{}", response);
+    assert!(response.contains("unmet graph:test"), "{}", response);
+    pegasus::health::set_component_state("graph:test", ComponentState::Ready);
+    assert!(get("/ready").starts_with("HTTP/1.1 200 OK"));
+    pegasus::shutdown_all();
+}
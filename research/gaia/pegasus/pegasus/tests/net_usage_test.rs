@@ -0,0 +1,87 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use pegasus::api::{Exchange, Map};
+use pegasus::cluster::Cluster;
+use pegasus::JobConf;
+use std::time::Duration;
+
+/// The records each worker sends, each of an id and a payload of 100 bytes;
+const RECORDS: u64 = 1000;
+const PAYLOAD: usize = 100;
+
+/// Run on 2 servers, each of the workers of `conf`, where each worker exchanges its records by
+/// their ids, so that half of them cross the servers;
+fn shuffle_job(cluster: &Cluster, conf: JobConf) -> Result<Vec<u64>, String> {
+    cluster.run(conf, |builder| {
+        let index = builder.worker_id.index as u64;
+        let range = index * RECORDS..(index + 1) * RECORDS;
+        builder
+            .input_from_iter(range.map(|id| (id, "x".repeat(PAYLOAD))))?
+            .exchange_with_fn(|item: &(u64, String)| item.0)?
+            .map_with_fn(pegasus::communication::Pipeline, |item| Ok(item.0))
+    })
+}
+
+#[test]
+fn net_usage_counts_test() {
+    pegasus_common::logs::init_log();
+    let cluster = Cluster::new("net_usage_counts_test", 2);
+    let conf = JobConf::new(129, "net_usage_counts_test", 2);
+    let ids = shuffle_job(&cluster, conf).expect("run job failure;");
+    assert_eq!(ids.len() as u64, 4 * RECORDS);
+
+    // the bytes of the job with the other server, told by the leader of each server;
+    let conf = JobConf::new(130, "net_usage_counts_test", 2);
+    let usages = cluster
+        .run(conf, |builder| {
+            let id = builder.worker_id;
+            let usage = if id.index == id.server_leader() {
+                let usage = pegasus::fetch_job_net_usage(129).expect("net usage lost;");
+                vec![(id.server_id as u64, usage.sent(), usage.received())]
+            } else {
+                vec![]
+            };
+            builder.input_from_iter(usage.into_iter())
+        })
+        .expect("run job failure;");
+    assert_eq!(usages.len(), 2);
+    // each server sends half of the records of its 2 workers to the other, each encoded as the id
+    // and the length and bytes of the payload, besides the headers of the messages and batches;
+    let expected = RECORDS * (8 + 4 + PAYLOAD as u64);
+    for (server, sent, received) in usages.iter() {
+        for bytes in [*sent, *received] {
+            assert!(
+                bytes >= expected && bytes < expected * 5 / 4,
+                "server {} sends and receives {} and {} bytes, expected about {}",
+                server,
+                sent,
+                received,
+                expected
+            );
+        }
+    }
+}
+
+#[test]
+fn net_usage_limit_test() {
+    pegasus_common::logs::init_log();
+    let cluster = Cluster::new("net_usage_limit_test", 2).timeout(Duration::from_secs(30));
+    // a worker of each server, whose failure is not hidden by the aborts of the others;
+    let mut conf = JobConf::new(131, "net_usage_limit_test", 1);
+    conf.network_byte_limit = 1024;
+    let err = shuffle_job(&cluster, conf).expect_err("job should fail;");
+    assert!(err.contains("exceeds the network byte limit 1024"), "{}", err);
+}
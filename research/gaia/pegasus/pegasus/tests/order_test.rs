@@ -0,0 +1,135 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use pegasus::api::function::{FnResult, MultiRouteFunction};
+use pegasus::api::{ResultSet, Sink, Unary};
+use pegasus::communication::{Aggregate, Channel};
+use pegasus::{Configuration, JobConf, JobSubmitError};
+
+// send (worker, seq) from each of the 2 workers to worker 0 by batches of 4, where each batch is
+// delivered after the one following it, and collect them in the order worker 0 receives them;
+fn run_reordered(job_id: u64, preserve_order: bool) -> Vec<(u32, u32)> {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let mut conf = JobConf::new(job_id, "order_test", 2);
+    conf.batch_size = 4;
+    pegasus::communication::inject_reordering(job_id, true);
+    let (tx, rx) = crossbeam_channel::unbounded();
+    let mut guard = pegasus::run(conf, |worker| {
+        let index = worker.id.index;
+        let tx = tx.clone();
+        worker.dataflow(move |builder| {
+            let mut channel: Channel<(u32, u32)> = Aggregate(0).into();
+            if preserve_order {
+                channel.preserve_order();
+            }
+            builder
+                .input_from_iter((0..256u32).map(move |seq| (index, seq)))?
+                .unary("forward", channel, |_meta| {
+                    |input, output| {
+                        input.for_each_batch(|dataset| {
+                            output.forward(dataset)?;
+                            Ok(())
+                        })
+                    }
+                })?
+                .sink_by(move |_meta| {
+                    move |_tag, result| {
+                        if let ResultSet::Data(data) = result {
+                            tx.send(data).expect("send error");
+                        }
+                    }
+                })?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure")
+    .expect("job not started");
+
+    std::mem::drop(tx);
+    let mut received = vec![];
+    while let Ok(data) = rx.recv() {
+        received.extend(data);
+    }
+    guard.join().expect("run job failure");
+    pegasus::communication::inject_reordering(job_id, false);
+    received
+}
+
+fn is_ordered_per_worker(received: &[(u32, u32)]) -> bool {
+    let mut last = [None; 2];
+    for (worker, seq) in received {
+        let last = &mut last[*worker as usize];
+        if last.map(|pre| pre >= *seq).unwrap_or(false) {
+            return false;
+        }
+        *last = Some(*seq);
+    }
+    true
+}
+
+#[test]
+fn order_preserving_test() {
+    let received = run_reordered(1, true);
+    assert_eq!(received.len(), 512);
+    assert!(is_ordered_per_worker(&received), "out of order: {:?}", received);
+}
+
+#[test]
+fn reordered_without_preserving_test() {
+    let received = run_reordered(2, false);
+    assert_eq!(received.len(), 512);
+    assert!(!is_ordered_per_worker(&received));
+}
+
+struct ToAll([u64; 2]);
+
+impl MultiRouteFunction<u32> for ToAll {
+    fn route(&self, _data: &u32) -> FnResult<&[u64]> {
+        Ok(&self.0)
+    }
+}
+
+#[test]
+fn order_preserving_multi_route_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let conf = JobConf::new(3, "order_preserving_multi_route_test", 2);
+    let result = pegasus::run(conf, |worker| {
+        worker.dataflow(|builder| {
+            let route = Box::new(ToAll([0, 1])) as Box<dyn MultiRouteFunction<u32>>;
+            let mut channel: Channel<u32> = route.into();
+            channel.preserve_order();
+            builder
+                .input_from_iter(0..16u32)?
+                .unary("forward", channel, |_meta| {
+                    |input, output| {
+                        input.for_each_batch(|dataset| {
+                            output.forward(dataset)?;
+                            Ok(())
+                        })
+                    }
+                })?
+                .sink_by(|_meta| |_tag, _result: ResultSet<u32>| ())?;
+            Ok(())
+        })
+    });
+    match result {
+        Err(JobSubmitError::Build(err)) => {
+            assert!(err.to_string().contains("order-preserving broadcast"), "{}", err)
+        }
+        _ => panic!("the order-preserving multi-route broadcast is expected to be rejected"),
+    }
+}
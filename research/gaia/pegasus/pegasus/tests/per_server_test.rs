@@ -0,0 +1,114 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use pegasus::api::accum::{Count as CountAccumulated, CountAccum};
+use pegasus::api::{Count, Fold, Map, Range, ResultSet, Sink};
+use pegasus::communication::{Aggregate, Pipeline};
+use pegasus::stream::Stream;
+use pegasus::{BuildJobError, Configuration, JobConf, Tag};
+
+/// Run the job on 4 workers split into 2 simulated servers, where each worker reads 100 records
+/// of its own, and collect the results with the workers they are produced on;
+fn run_on_two_servers<F>(job_id: u64, profile: bool, func: F) -> Vec<(u32, u64)>
+where
+    F: Fn(&Stream<u32>) -> Result<Stream<u64>, BuildJobError> + Send + Sync + 'static,
+{
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    pegasus::communication::simulate_servers(job_id, 2);
+    let mut conf = JobConf::new(job_id, "per_server_test", 4);
+    conf.profile = profile;
+    let (tx, rx) = crossbeam_channel::unbounded();
+    let func = std::sync::Arc::new(func);
+    pegasus::run(conf, |worker| {
+        let tx = tx.clone();
+        let func = func.clone();
+        worker.dataflow(move |dfb| {
+            let src = dfb.input_from_iter(0..100u32)?;
+            (*func)(&src)?.sink_by(move |_meta| {
+                move |_t: &Tag, result: ResultSet<u64>| {
+                    if let ResultSet::Data(data) = result {
+                        let worker = pegasus::get_current_worker().expect("worker lost").index;
+                        for value in data {
+                            tx.send((worker, value)).expect("send error");
+                        }
+                    }
+                }
+            })?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;")
+    .expect("job not started;")
+    .join()
+    .expect("run job failure;");
+    std::mem::drop(tx);
+    pegasus::communication::simulate_servers(job_id, 0);
+    let mut results: Vec<(u32, u64)> = rx.iter().collect();
+    results.sort();
+    results
+}
+
+fn fold_count(src: &Stream<u32>, range: Range) -> Result<Stream<u64>, BuildJobError> {
+    src.fold_with_accum(range, CountAccum::new())?
+        .map_with_fn(Pipeline, |c: CountAccumulated<u32>| Ok(c.value))
+}
+
+#[test]
+fn count_over_ranges_test() {
+    let local = run_on_two_servers(70, false, |src| src.count(Range::Local));
+    assert_eq!(local, vec![(0, 100), (1, 100), (2, 100), (3, 100)]);
+    // the counts of each server are gathered by its first worker;
+    let per_server = run_on_two_servers(71, false, |src| src.count(Range::PerServer));
+    assert_eq!(per_server, vec![(0, 200), (2, 200)]);
+    let global = run_on_two_servers(72, false, |src| src.count(Range::Global));
+    assert_eq!(global, vec![(0, 400)]);
+}
+
+#[test]
+fn fold_over_ranges_test() {
+    let local = run_on_two_servers(73, false, |src| fold_count(src, Range::Local));
+    assert_eq!(local, vec![(0, 100), (1, 100), (2, 100), (3, 100)]);
+    let per_server = run_on_two_servers(74, false, |src| fold_count(src, Range::PerServer));
+    assert_eq!(per_server, vec![(0, 200), (2, 200)]);
+    let global = run_on_two_servers(75, false, |src| fold_count(src, Range::Global));
+    assert_eq!(global, vec![(0, 400)]);
+    let combined = run_on_two_servers(76, false, |src| {
+        src.fold_with_combine(CountAccum::new(), |pre, other| {
+            pre.value += other.value;
+            Ok(())
+        })?
+        .map_with_fn(Pipeline, |c: CountAccumulated<u32>| Ok(c.value))
+    });
+    assert_eq!(combined, vec![(0, 400)]);
+}
+
+#[test]
+fn global_count_cross_server_bytes_test() {
+    // the partial counts of all workers are sent to worker 0 at once;
+    let one_level = run_on_two_servers(77, true, |src| {
+        src.count(Range::Local)?.fold(0u64, Aggregate(0), |sum, c| *sum += c)
+    });
+    assert_eq!(one_level, vec![(0, 400)]);
+    // only the counts of each server cross the network;
+    let two_level = run_on_two_servers(78, true, |src| src.count(Range::Global));
+    assert_eq!(two_level, vec![(0, 400)]);
+
+    let one_level = pegasus::fetch_job_profile(77).expect("job not profiled;").cross_server_bytes();
+    let two_level = pegasus::fetch_job_profile(78).expect("job not profiled;").cross_server_bytes();
+    assert!(two_level > 0);
+    assert!(two_level < one_level, "two level {} vs one level {}", two_level, one_level);
+    pegasus::shutdown_all();
+}
@@ -0,0 +1,58 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use pegasus::api::{Filter, Map, ResultSet, Sink};
+use pegasus::communication::Pipeline;
+use pegasus::{Configuration, JobConf, Tag};
+
+fn run_job(job_id: u64, profile: bool) {
+    let mut conf = JobConf::new(job_id, "profile_test", 2);
+    conf.profile = profile;
+    pegasus::run(conf, |worker| {
+        worker.dataflow(|builder| {
+            builder
+                .input_from_iter(0..1000u32)?
+                .named("is_even", |s| s.filter_with_fn(|item| Ok(*item % 2 == 0)))?
+                .named("double", |s| s.map_with_fn(Pipeline, |item| Ok(item * 2)))?
+                .sink_by(|_meta| |_t: &Tag, _result: ResultSet<u32>| ())?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;")
+    .expect("job not started;")
+    .join()
+    .expect("run job failure;");
+}
+
+#[test]
+fn profile_named_operators_test() {
+    pegasus::startup(Configuration::singleton()).ok();
+    run_job(111, true);
+    let profile = pegasus::fetch_job_profile(111).expect("job not profiled;");
+    let is_even = profile.get_operator("is_even").expect("operator is_even not found;");
+    // each of the 2 workers reads 1000 records from its own source
+    assert_eq!(is_even.records.count, 2);
+    assert_eq!(is_even.records.sum, 2000);
+    assert_eq!(is_even.records.avg(), 1000.0);
+    let double = profile.get_operator("double").expect("operator double not found;");
+    assert_eq!(double.records.sum, 1000);
+    assert!(double.records.min <= double.records.max);
+    assert!(double.index > is_even.index);
+    let sink = profile.get_operator("sink").expect("operator sink not found;");
+    assert_eq!(sink.records.sum, 1000);
+
+    run_job(112, false);
+    assert!(pegasus::fetch_job_profile(112).is_none());
+}
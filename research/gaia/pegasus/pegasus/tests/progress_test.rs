@@ -0,0 +1,117 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use pegasus::api::{DataSource, Filter, ResultSet, Sink, SourceConfig};
+use pegasus::errors::IOError;
+use pegasus::{Configuration, JobConf, Tag};
+use std::time::Duration;
+
+/// The numbers below `total`, which expects to produce all of them;
+struct Numbers {
+    next: u32,
+    total: u32,
+}
+
+impl DataSource for Numbers {
+    type Item = u32;
+
+    fn next_batch(&mut self, max: usize) -> Result<Option<Vec<u32>>, IOError> {
+        if self.next >= self.total {
+            return Ok(None);
+        }
+        let end = self.total.min(self.next + max as u32);
+        let batch: Vec<u32> = (self.next..end).collect();
+        self.next = end;
+        Ok(Some(batch))
+    }
+
+    fn expected_size(&self) -> Option<u64> {
+        Some(self.total as u64)
+    }
+}
+
+#[test]
+fn progress_of_slow_scan_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let mut conf = JobConf::new(121, "progress_test", 2);
+    conf.batch_size = 16;
+    let mut guard = pegasus::run(conf, |worker| {
+        worker.dataflow(|builder| {
+            // each worker scans 1000 numbers in about half a second
+            let numbers = Numbers { next: 0, total: 1000 };
+            builder
+                .input_from_source_with(numbers, SourceConfig::rate_limit(2000))?
+                .sink_by(|_meta| |_t: &Tag, _result: ResultSet<u32>| ())?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;")
+    .expect("job not started;");
+
+    let mut fractions = vec![];
+    loop {
+        let progress = pegasus::peek_progress(121).expect("job not tracked;");
+        if let Some(fraction) = progress.fraction() {
+            assert_eq!(progress.sources.len(), 1);
+            assert_eq!(progress.sources[0].expected, Some(2000));
+            fractions.push(fraction);
+            if fraction >= 1.0 {
+                break;
+            }
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    guard.join().expect("run job failure;");
+
+    for pair in fractions.windows(2) {
+        assert!(pair[0] <= pair[1], "progress goes back from {} to {}", pair[0], pair[1]);
+    }
+    assert!(fractions.iter().any(|f| *f > 0.0 && *f < 1.0), "no progress in between");
+    assert_eq!(fractions.last(), Some(&1.0));
+    let progress = pegasus::peek_progress(121).unwrap();
+    assert_eq!(progress.sources[0].consumed, 2000);
+    assert_eq!(progress.fraction(), Some(1.0));
+}
+
+#[test]
+fn progress_of_unknown_size_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let conf = JobConf::new(122, "progress_test", 1);
+    pegasus::run(conf, |worker| {
+        worker.dataflow(|builder| {
+            // a filtered iterator can't tell its size exactly
+            let odds = (0..100u32).filter(|i| i % 2 == 1);
+            builder
+                .input_from_iter(odds)?
+                .filter_with_fn(|_| Ok(true))?
+                .sink_by(|_meta| |_t: &Tag, _result: ResultSet<u32>| ())?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;")
+    .expect("job not started;")
+    .join()
+    .expect("run job failure;");
+
+    // the source is unknown until it is exhausted
+    let progress = pegasus::peek_progress(122).expect("job not tracked;");
+    assert_eq!(progress.sources.len(), 1);
+    assert_eq!(progress.sources[0].expected, None);
+    assert_eq!(progress.sources[0].consumed, 50);
+    assert_eq!(progress.fraction(), Some(1.0));
+    assert!(pegasus::peek_progress(123).is_none());
+}
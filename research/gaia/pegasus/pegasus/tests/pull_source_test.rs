@@ -0,0 +1,183 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use pegasus::api::{DataSource, FileLines, Map, ResultSet, Sink, SourceConfig};
+use pegasus::communication::Pipeline;
+use pegasus::dataflow::DataflowBuilder;
+use pegasus::errors::IOError;
+use pegasus::stream::Stream;
+use pegasus::{BuildJobError, Configuration, JobConf};
+use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// The numbers below `total`, counting the numbers pulled;
+struct Numbers {
+    next: u32,
+    total: u32,
+    pulled: Arc<AtomicUsize>,
+}
+
+impl DataSource for Numbers {
+    type Item = u32;
+
+    fn next_batch(&mut self, max: usize) -> Result<Option<Vec<u32>>, IOError> {
+        if self.next >= self.total {
+            return Ok(None);
+        }
+        let end = self.total.min(self.next + max as u32);
+        let batch: Vec<u32> = (self.next..end).collect();
+        self.next = end;
+        self.pulled.fetch_add(batch.len(), Ordering::SeqCst);
+        Ok(Some(batch))
+    }
+}
+
+/// Run the job on a worker of small batches and output capacity, and get the most records pulled
+/// ahead of those consumed by the map following the source, with the records consumed;
+fn run_with_lag<F>(job_id: u64, source: F) -> (usize, usize)
+where
+    F: Fn(&DataflowBuilder, Arc<AtomicUsize>) -> Result<Stream<u32>, BuildJobError>
+        + Send
+        + Sync
+        + 'static,
+{
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let mut conf = JobConf::new(job_id, "pull_source_test", 1);
+    conf.batch_size = 10;
+    conf.output_capacity = 2;
+    let pulled = Arc::new(AtomicUsize::new(0));
+    let consumed = Arc::new(AtomicUsize::new(0));
+    let max_lag = Arc::new(AtomicUsize::new(0));
+    let (p, c, l) = (pulled.clone(), consumed.clone(), max_lag.clone());
+    let source = Arc::new(source);
+    pegasus::run(conf, move |worker| {
+        let (pulled, consumed, max_lag) = (p.clone(), c.clone(), l.clone());
+        let source = source.clone();
+        worker.dataflow(move |dfb| {
+            (*source)(dfb, pulled.clone())?
+                .map_with_fn(Pipeline, move |item| {
+                    let consumed = consumed.fetch_add(1, Ordering::SeqCst) + 1;
+                    let lag = pulled.load(Ordering::SeqCst) - consumed;
+                    max_lag.fetch_max(lag, Ordering::SeqCst);
+                    Ok(item)
+                })?
+                .sink_by(|_| |_, _: ResultSet<u32>| ())?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;")
+    .expect("job not started;")
+    .join()
+    .expect("run job failure;");
+    (max_lag.load(Ordering::SeqCst), consumed.load(Ordering::SeqCst))
+}
+
+#[test]
+fn pull_by_consumption_test() {
+    let (lag, consumed) = run_with_lag(1, |dfb, pulled| {
+        dfb.input_from_source(Numbers { next: 0, total: 1000, pulled })
+    });
+    assert_eq!(consumed, 1000);
+    // at most a few batches of the output capacity are pulled ahead of the map;
+    assert!(lag <= 40, "pulled {} records ahead", lag);
+
+    // while the iterator is drained at once;
+    let (lag, consumed) = run_with_lag(2, |dfb, pulled| {
+        let numbers = (0..1000u32).inspect(move |_| {
+            pulled.fetch_add(1, Ordering::SeqCst);
+        });
+        dfb.input_from_iter(numbers)
+    });
+    assert_eq!(consumed, 1000);
+    assert!(lag > 500, "pulled {} records ahead", lag);
+}
+
+#[test]
+fn rate_limit_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let (tx, rx) = crossbeam_channel::unbounded();
+    let start = Instant::now();
+    pegasus::run(JobConf::new(3, "rate_limit_test", 1), |worker| {
+        let tx = tx.clone();
+        worker.dataflow(move |dfb| {
+            let numbers = Numbers { next: 0, total: 300, pulled: Arc::new(AtomicUsize::new(0)) };
+            dfb.input_from_source_with(numbers, SourceConfig::rate_limit(1000))?.sink_by(
+                move |_| {
+                    move |_, result| {
+                        if let ResultSet::Data(data) = result {
+                            tx.send(data.len()).unwrap();
+                        }
+                    }
+                },
+            )?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;")
+    .expect("job not started;")
+    .join()
+    .expect("run job failure;");
+    let elapsed = start.elapsed();
+    drop(tx);
+    assert_eq!(rx.iter().sum::<usize>(), 300);
+    // all but the first batch wait for the rate;
+    assert!(elapsed >= Duration::from_millis(250), "300 records pulled in {:?}", elapsed);
+}
+
+#[test]
+fn file_lines_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let path = std::env::temp_dir().join(format!("pull_source_test_{}.txt", std::process::id()));
+    {
+        let mut file = std::fs::File::create(&path).expect("create file failure;");
+        for i in 0..100 {
+            writeln!(file, "line {}", i).expect("write file failure;");
+        }
+    }
+    let (tx, rx) = crossbeam_channel::unbounded();
+    let lines_path = path.clone();
+    pegasus::run(JobConf::new(4, "file_lines_test", 1), |worker| {
+        let tx = tx.clone();
+        let path = lines_path.clone();
+        worker.dataflow(move |dfb| {
+            let lines = FileLines::open(&path).map_err(|e| format!("open failure: {}", e))?;
+            dfb.input_from_source(lines)?.sink_by(move |_| {
+                move |_, result| {
+                    if let ResultSet::Data(data) = result {
+                        for line in data {
+                            tx.send(line).unwrap();
+                        }
+                    }
+                }
+            })?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;")
+    .expect("job not started;")
+    .join()
+    .expect("run job failure;");
+    drop(tx);
+    let lines: Vec<String> = rx.iter().collect();
+    std::fs::remove_file(&path).ok();
+    assert_eq!(lines.len(), 100);
+    assert_eq!(lines[0], "line 0");
+    assert_eq!(lines[99], "line 99");
+}
@@ -16,11 +16,12 @@
 use pegasus::api::accum::{Count, CountAccum};
 use pegasus::api::function::*;
 use pegasus::api::{
-    Barrier, Dedup, Exchange, Fold, Group, Map, Order, OrderBy, OrderDirect, Range, ResultSet, Sink,
+    ApproxDedup, ApproxDistinct, Barrier, Dedup, Exchange, Fold, Group, Iteration, Map, Order,
+    OrderBy, OrderDirect, Quantiles, Range, ResultSet, Sink, SubTask,
 };
 use pegasus::communication::Pipeline;
 use pegasus::compare;
-use pegasus::{Configuration, JobConf, Tag};
+use pegasus::{Configuration, JobConf, JobSubmitError, Tag};
 use pegasus_common::codec::{Decode, Encode, ReadExt, WriteExt};
 use pegasus_common::collections::{Collection, Set};
 use std::collections::HashSet;
@@ -307,6 +308,51 @@ fn sort_test() {
     pegasus::shutdown_all();
 }
 
+#[test]
+fn spilled_sort_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let spill_dir = std::env::temp_dir().join(format!("spilled_sort_test_{}", std::process::id()));
+    let (tx, rx) = crossbeam_channel::unbounded();
+    let mut conf = JobConf::new(1, "spilled_sort_test", 2);
+    // spill every 4 records, which makes dozens of runs in the worker sorting all records;
+    conf.sort_spill_limit = 4;
+    conf.spill_dir = Some(spill_dir.clone());
+    pegasus::run(conf, |worker| {
+        let tx = tx.clone();
+        worker.dataflow(|dfb| {
+            let src = (0..100u32).map(|i| (i * 37) % 101);
+            dfb.input_from_iter(src)?
+                .exchange_with_fn(|item: &u32| *item as u64)?
+                .sort(Range::Global, OrderDirect::Asc)?
+                .sink_by(move |_meta| {
+                    move |_t: &Tag, result: ResultSet<u32>| {
+                        if let ResultSet::Data(data) = result {
+                            tx.send(data).expect("send error");
+                        }
+                    }
+                })?;
+            Ok(())
+        })
+    })
+    .expect("");
+    std::mem::drop(tx);
+
+    let mut result = Vec::new();
+    while let Ok(data) = rx.recv() {
+        result.extend(data);
+    }
+    let mut expected =
+        (0..100u32).map(|i| (i * 37) % 101).flat_map(|i| vec![i, i]).collect::<Vec<_>>();
+    expected.sort();
+    assert_eq!(expected, result);
+    // the spilled runs are removed along with the directory of the job;
+    let left = std::fs::read_dir(&spill_dir).map(|dir| dir.count()).unwrap_or(0);
+    assert_eq!(0, left);
+    std::fs::remove_dir_all(&spill_dir).ok();
+    pegasus::shutdown_all();
+}
+
 #[test]
 fn top_test() {
     pegasus_common::logs::init_log();
@@ -378,3 +424,324 @@ fn custom_top_test() {
     assert_eq!(vec![8, 8, 7, 7, 6], result);
     pegasus::shutdown_all();
 }
+
+#[test]
+fn scope_barrier_in_subtask_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let (tx, rx) = crossbeam_channel::unbounded();
+    let (held_tx, held_rx) = crossbeam_channel::unbounded();
+    let conf = JobConf::new(2, "scope_barrier_in_subtask_test", 2);
+    pegasus::run(conf, |worker| {
+        let tx = tx.clone();
+        let held_tx = held_tx.clone();
+        worker.dataflow(|dfb| {
+            let subtask = dfb.input_from_iter(0..10u32)?.fork_subtask(|stream| {
+                stream
+                    .flat_map_with_fn(
+                        Pipeline,
+                        |item| Ok((0..8u32).map(move |i| Ok(item * 8 + i))),
+                    )?
+                    .scope_barrier_with(move |all: &mut Vec<u32>| {
+                        held_tx.send(all.len()).expect("send error");
+                        // normalize by the min of the subtask, which is known only if all held
+                        let min = all.iter().min().cloned().unwrap_or(0);
+                        for item in all.iter_mut() {
+                            *item -= min;
+                        }
+                    })
+            })?;
+            subtask.sink_by(move |_meta| {
+                move |_t: &Tag, result| {
+                    if let ResultSet::Data(data) = result {
+                        for d in data {
+                            if let ResultSet::Data(data) = d.take() {
+                                tx.send(data).expect("send error");
+                            }
+                        }
+                    }
+                }
+            })?;
+            Ok(())
+        })
+    })
+    .expect("");
+    std::mem::drop(tx);
+    std::mem::drop(held_tx);
+
+    let mut result = Vec::new();
+    while let Ok(data) = rx.recv() {
+        result.extend(data);
+    }
+    // nothing is released before all the 8 records of each subtask are held
+    let held: Vec<usize> = held_rx.iter().collect();
+    assert_eq!(held, vec![8; 20]);
+    assert_eq!(160, result.len());
+    result.sort();
+    let expected: Vec<u32> = (0..8u32).flat_map(|i| vec![i; 20]).collect();
+    assert_eq!(expected, result);
+    pegasus::shutdown_all();
+}
+
+#[test]
+fn scope_barrier_in_iteration_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let (tx, rx) = crossbeam_channel::unbounded();
+    let (held_tx, held_rx) = crossbeam_channel::unbounded();
+    let conf = JobConf::new(3, "scope_barrier_in_iteration_test", 2);
+    pegasus::run(conf, |worker| {
+        let tx = tx.clone();
+        let held_tx = held_tx.clone();
+        worker.dataflow(|dfb| {
+            dfb.input_from_iter(0..10u32)?
+                .iterate(3, |start| {
+                    start
+                        .flat_map_with_fn(Pipeline, |item| {
+                            Ok(vec![item, item].into_iter().map(Ok))
+                        })?
+                        .scope_barrier_with(move |all: &mut Vec<u32>| {
+                            held_tx.send(all.len()).expect("send error");
+                        })
+                })?
+                .sink_by(move |_meta| {
+                    move |_t: &Tag, result: ResultSet<u32>| {
+                        if let ResultSet::Data(data) = result {
+                            tx.send(data).expect("send error");
+                        }
+                    }
+                })?;
+            Ok(())
+        })
+    })
+    .expect("");
+    std::mem::drop(tx);
+    std::mem::drop(held_tx);
+
+    let mut result = Vec::new();
+    while let Ok(data) = rx.recv() {
+        result.extend(data);
+    }
+    // each round of each worker is released as a whole once the round ends
+    let mut held: Vec<usize> = held_rx.iter().collect();
+    held.sort();
+    assert_eq!(held, vec![20, 20, 40, 40, 80, 80]);
+    assert_eq!(160, result.len());
+    pegasus::shutdown_all();
+}
+
+// zipf-like keys, the key k appears 1000 / k times;
+fn skewed_keys() -> Vec<u32> {
+    (1..=20u32).flat_map(|k| std::iter::repeat_n(k, (1000 / k) as usize)).collect()
+}
+
+#[test]
+fn group_with_combine_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let (tx, rx) = crossbeam_channel::unbounded();
+    let mut conf = JobConf::new(4, "group_with_combine_test", 2);
+    conf.skew_factor = 4;
+    pegasus::run(conf, |worker| {
+        let tx = tx.clone();
+        worker.dataflow(|dfb| {
+            dfb.input_from_iter(skewed_keys().into_iter())?
+                .group_with_combine(CountAccum::new(), |pre: &mut Count<u32>, partial| {
+                    pre.value += partial.value;
+                    Ok(())
+                })?
+                .sink_by(move |_meta| {
+                    move |_t: &Tag, result| {
+                        if let ResultSet::Data(data) = result {
+                            tx.send(data).expect("send error");
+                        }
+                    }
+                })?;
+            Ok(())
+        })
+    })
+    .expect("");
+    std::mem::drop(tx);
+
+    let mut result = Vec::new();
+    while let Ok(data) = rx.recv() {
+        for map in data {
+            for (k, count) in map {
+                result.push((k, count.value));
+            }
+        }
+    }
+    result.sort();
+    let expected = (1..=20u32).map(|k| (k, 2 * (1000 / k) as u64)).collect::<Vec<_>>();
+    assert_eq!(result, expected);
+    pegasus::shutdown_all();
+}
+
+#[test]
+fn fold_with_combine_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let (tx, rx) = crossbeam_channel::unbounded();
+    let conf = JobConf::new(5, "fold_with_combine_test", 2);
+    pegasus::run(conf, |worker| {
+        let tx = tx.clone();
+        worker.dataflow(|dfb| {
+            dfb.input_from_iter(skewed_keys().into_iter())?
+                .fold_with_combine(CountAccum::new(), |pre: &mut Count<u32>, partial| {
+                    pre.value += partial.value;
+                    Ok(())
+                })?
+                .sink_by(move |_meta| {
+                    move |_t: &Tag, result: ResultSet<Count<u32>>| {
+                        if let ResultSet::Data(data) = result {
+                            tx.send(data).expect("send error");
+                        }
+                    }
+                })?;
+            Ok(())
+        })
+    })
+    .expect("");
+    std::mem::drop(tx);
+
+    let mut result = Vec::new();
+    while let Ok(data) = rx.recv() {
+        result.extend(data);
+    }
+    assert_eq!(1, result.len());
+    assert_eq!(2 * skewed_keys().len() as u64, result[0].value);
+    pegasus::shutdown_all();
+}
+
+#[test]
+fn count_approx_distinct_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let (tx, rx) = crossbeam_channel::unbounded();
+    let conf = JobConf::new(1, "count_approx_distinct_test", 2);
+    pegasus::run(conf, |worker| {
+        let tx = tx.clone();
+        worker.dataflow(|dfb| {
+            // each worker produces the same 1000 distinct numbers;
+            dfb.input_from_iter(0..1000u32)?.count_approx_distinct(Range::Global, 14)?.sink_by(
+                move |_meta| {
+                    move |_t: &Tag, result: ResultSet<u64>| {
+                        if let ResultSet::Data(data) = result {
+                            tx.send(data).expect("send error");
+                        }
+                    }
+                },
+            )?;
+            Ok(())
+        })
+    })
+    .expect("");
+    std::mem::drop(tx);
+
+    let mut result = Vec::new();
+    while let Ok(data) = rx.recv() {
+        result.extend(data);
+    }
+    // the sketches of a few items are exact;
+    assert_eq!(vec![1000], result);
+    pegasus::shutdown_all();
+}
+
+#[test]
+fn quantiles_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let (tx, rx) = crossbeam_channel::unbounded();
+    let conf = JobConf::new(1, "quantiles_test", 2);
+    pegasus::run(conf, |worker| {
+        let tx = tx.clone();
+        worker.dataflow(|dfb| {
+            dfb.input_from_iter(vec![3u32, 9, 0, 5, 1, 8, 2, 7, 4, 6].into_iter())?
+                .quantiles(Range::Global, &[0.0, 0.25, 0.5, 1.0])?
+                .sink_by(move |_meta| {
+                    move |_t: &Tag, result: ResultSet<Vec<f64>>| {
+                        if let ResultSet::Data(data) = result {
+                            tx.send(data).expect("send error");
+                        }
+                    }
+                })?;
+            Ok(())
+        })
+    })
+    .expect("");
+    std::mem::drop(tx);
+
+    let mut result = Vec::new();
+    while let Ok(data) = rx.recv() {
+        result.extend(data);
+    }
+    // the quantiles of [0, 0, 1, 1, .., 9, 9] from both workers;
+    assert_eq!(vec![vec![0.0, 2.0, 4.5, 9.0]], result);
+    pegasus::shutdown_all();
+}
+
+#[test]
+fn dedup_approx_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let (tx, rx) = crossbeam_channel::unbounded();
+    let conf = JobConf::new(132, "dedup_approx_test", 2);
+    let distinct = 20_000u64;
+    let fp_rate = 0.01;
+    pegasus::run(conf, |worker| {
+        let tx = tx.clone();
+        worker.dataflow(move |dfb| {
+            // each worker produces each number three times;
+            let src = (0..3 * distinct).map(move |item| item % distinct);
+            dfb.input_from_iter(src)?.dedup_approx(Range::Global, distinct, fp_rate)?.sink_by(
+                move |_meta| {
+                    move |_t: &Tag, result: ResultSet<u64>| {
+                        if let ResultSet::Data(data) = result {
+                            tx.send(data).expect("send error");
+                        }
+                    }
+                },
+            )?;
+            Ok(())
+        })
+    })
+    .expect("");
+    std::mem::drop(tx);
+
+    let mut result = Vec::new();
+    while let Ok(data) = rx.recv() {
+        result.extend(data);
+    }
+    // no duplicate is emitted, while a few distinct numbers may be dropped as duplicates;
+    let emitted = result.iter().collect::<HashSet<_>>();
+    assert_eq!(emitted.len(), result.len());
+    assert!(result.iter().all(|item| *item < distinct));
+    let dropped = distinct - result.len() as u64;
+    assert!((dropped as f64) < 2.0 * fp_rate * distinct as f64, "dropped {}", dropped);
+    pegasus::shutdown_all();
+}
+
+#[test]
+fn dedup_approx_memory_limit_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let mut conf = JobConf::new(133, "dedup_approx_memory_limit_test", 2);
+    conf.memory_limit = 1;
+    let result = pegasus::run(conf, |worker| {
+        worker.dataflow(|dfb| {
+            // the filter of 10 million items at 1% takes about 12MB;
+            dfb.input_from_iter(0..10u32)?
+                .dedup_approx(Range::PerServer, 10_000_000, 0.01)?
+                .sink_by(|_meta| |_t: &Tag, _result: ResultSet<u32>| {})?;
+            Ok(())
+        })
+    });
+    match result {
+        Err(JobSubmitError::Build(err)) => {
+            let err = err.to_string();
+            assert!(err.contains("beyond the memory_limit 1MB"), "{}", err);
+        }
+        _ => panic!("the job is expected to fail to be built;"),
+    }
+    pegasus::shutdown_all();
+}
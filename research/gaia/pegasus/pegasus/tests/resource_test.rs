@@ -0,0 +1,97 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use pegasus::api::function::DynError;
+use pegasus::api::{Exchange, Map, ResultSet, Sink};
+use pegasus::communication::Pipeline;
+use pegasus::{Configuration, JobConf, JobGuard, JobResourceError};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// the parameters of a query, which are alive as long as any reference to `alive` is kept
+struct Params {
+    offset: u32,
+    _alive: Arc<()>,
+}
+
+fn run_with_params(
+    job_id: u64, offset: u32, alive: &Arc<()>, tx: &crossbeam_channel::Sender<Vec<u32>>,
+) -> Option<JobGuard> {
+    let mut conf = JobConf::new(job_id, "job_resource_test", 2);
+    conf.set_user_data("params", Params { offset, _alive: alive.clone() });
+    pegasus::run(conf, |worker| {
+        let tx = tx.clone();
+        worker.dataflow(move |builder| {
+            // each of the two workers reads its own half of the source;
+            let index = builder.worker_id.index;
+            builder
+                .input_from_iter(index * 500..(index + 1) * 500)?
+                .exchange_with_fn(|item: &u32| *item as u64)?
+                .map_with_fn(Pipeline, |item| {
+                    let params = pegasus::get_job_resource::<Params>("params")
+                        .map_err(|e| Box::new(e) as DynError)?;
+                    Ok(item + params.offset)
+                })?
+                .sink_by(|_meta| {
+                    move |_, result| {
+                        if let ResultSet::Data(data) = result {
+                            tx.send(data).unwrap()
+                        }
+                    }
+                })?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;")
+}
+
+#[test]
+fn job_resource_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let alive = Arc::new(());
+    let (tx_a, rx_a) = crossbeam_channel::unbounded();
+    let (tx_b, rx_b) = crossbeam_channel::unbounded();
+    // the two jobs run at the same time, each of its own parameters
+    let guard_a = run_with_params(79, 10000, &alive, &tx_a);
+    let guard_b = run_with_params(80, 20000, &alive, &tx_b);
+    std::mem::drop(guard_a);
+    std::mem::drop(guard_b);
+    std::mem::drop(tx_a);
+    std::mem::drop(tx_b);
+
+    for (rx, offset) in [(rx_a, 10000), (rx_b, 20000)] {
+        let mut result = vec![];
+        while let Ok(data) = rx.recv() {
+            result.extend(data);
+        }
+        result.sort();
+        assert_eq!(result, (0..1000u32).map(|i| i + offset).collect::<Vec<_>>());
+    }
+
+    // the parameters of both jobs are dropped once their workers end
+    let start = Instant::now();
+    while Arc::strong_count(&alive) > 1 && start.elapsed() < Duration::from_secs(5) {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(Arc::strong_count(&alive), 1);
+    pegasus::shutdown_all();
+}
+
+#[test]
+fn job_resource_out_of_job_test() {
+    let err = pegasus::get_job_resource::<u32>("params").err();
+    assert_eq!(err, Some(JobResourceError::OutOfJob));
+}
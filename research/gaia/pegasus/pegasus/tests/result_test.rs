@@ -0,0 +1,70 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use pegasus::api::{Map, ResultSet, Sink};
+use pegasus::communication::Pipeline;
+use pegasus::result::{sink_to, JobError, ResultCollector};
+use pegasus::{Configuration, JobConf};
+use std::io;
+use std::time::Duration;
+
+#[test]
+fn collect_failure_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let conf = JobConf::new(139, "collect_failure_test", 1);
+    let (tx, rx) = crossbeam_channel::unbounded();
+    let guard = pegasus::run(conf, |worker| {
+        let tx = tx.clone();
+        worker.dataflow(|dfb| {
+            dfb.input_from_iter(0..10u32)?
+                .map_with_fn(Pipeline, |item| {
+                    if item == 7 {
+                        Err(Box::new(io::Error::other(format!("failure on {}", item))))
+                    } else {
+                        Ok(item)
+                    }
+                })?
+                .sink_by(|_| sink_to(tx))?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;");
+
+    std::mem::drop(tx);
+    match ResultCollector::new(rx).with_guard(guard).collect_vec() {
+        Err(JobError::Exec(err)) => assert!(err.to_string().contains("failure on 7"), "{}", err),
+        Err(err) => panic!("unexpected error {}", err),
+        Ok(result) => panic!("job should fail, but get {:?}", result),
+    }
+}
+
+#[test]
+fn next_timeout_test() {
+    let (tx, rx) = crossbeam_channel::unbounded();
+    tx.send(ResultSet::Data(vec![1u32, 2])).unwrap();
+    let mut collector = ResultCollector::new(rx);
+    let timeout = Duration::from_millis(10);
+    assert_eq!(collector.next_timeout(timeout).map(|r| r.ok()), Some(Some(1)));
+    assert_eq!(collector.next_timeout(timeout).map(|r| r.ok()), Some(Some(2)));
+    // the sender is alive but sends nothing
+    match collector.next_timeout(timeout) {
+        Some(Err(JobError::Timeout(t))) => assert_eq!(t, timeout),
+        _ => panic!("no timeout"),
+    }
+    tx.send(ResultSet::End).unwrap();
+    std::mem::drop(tx);
+    assert!(collector.next_timeout(timeout).is_none());
+}
@@ -0,0 +1,200 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use pegasus::api::function::{DynError, FlatMapClosure, FnResult, Idempotent, MapClosure};
+use pegasus::api::{Map, ResultSet, RetryPolicy, Sink};
+use pegasus::communication::Pipeline;
+use pegasus::{Configuration, JobConf, JobGuard, JobSubmitError};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// The invocations of the function on each datum;
+#[derive(Clone, Default)]
+struct Invocations {
+    counts: Arc<Mutex<HashMap<u32, u32>>>,
+}
+
+impl Invocations {
+    /// Count an invocation on the datum, which fails if it is one of the first `failures` ones;
+    fn invoke(&self, datum: u32, failures: u32) -> FnResult<u32> {
+        let mut counts = self.counts.lock().unwrap();
+        let count = counts.entry(datum).or_insert(0);
+        *count += 1;
+        if *count <= failures {
+            let err: Box<dyn std::error::Error + Send + Sync> =
+                format!("transient failure on {}", datum).into();
+            Err(err)
+        } else {
+            Ok(datum * 2)
+        }
+    }
+
+    fn count_of(&self, datum: u32) -> u32 {
+        self.counts.lock().unwrap().get(&datum).copied().unwrap_or(0)
+    }
+}
+
+fn policy(max_attempts: u32) -> RetryPolicy {
+    RetryPolicy { max_attempts, backoff: Duration::from_millis(1) }
+}
+
+fn collect(mut guard: JobGuard, rx: crossbeam_channel::Receiver<Vec<u32>>) -> Vec<u32> {
+    guard.join().expect("run job failure;");
+    let mut result = vec![];
+    while let Ok(data) = rx.try_recv() {
+        result.extend(data);
+    }
+    result.sort();
+    result
+}
+
+fn submit_map(
+    conf: JobConf, invocations: Invocations, failures: u32, max_attempts: u32,
+    tx: crossbeam_channel::Sender<Vec<u32>>,
+) -> Result<Option<JobGuard>, JobSubmitError> {
+    pegasus::run(conf, |worker| {
+        let index = worker.id.index;
+        let invocations = invocations.clone();
+        let tx = tx.clone();
+        worker.dataflow(move |builder| {
+            let invocations = invocations.clone();
+            builder
+                .input_from_iter(index * 50..(index + 1) * 50)?
+                .with_retry(policy(max_attempts))
+                .map(
+                    Pipeline,
+                    Idempotent(MapClosure::new(move |item| invocations.invoke(item, failures))),
+                )?
+                .sink_by(|_| {
+                    move |_, result| {
+                        if let ResultSet::Data(data) = result {
+                            tx.send(data).unwrap();
+                        }
+                    }
+                })?;
+            Ok(())
+        })
+    })
+}
+
+// each batch of a single datum fails on its first two attempts
+#[test]
+fn retry_map_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let mut conf = JobConf::new(1, "retry_map_test", 2);
+    conf.batch_size = 1;
+    conf.capture_logs = true;
+    let invocations = Invocations::default();
+    let (tx, rx) = crossbeam_channel::unbounded();
+    let guard = submit_map(conf, invocations.clone(), 2, 3, tx)
+        .expect("submit job failure;")
+        .expect("job not started;");
+    let result = collect(guard, rx);
+    assert_eq!(result, (0..100u32).map(|i| i * 2).collect::<Vec<_>>());
+    for datum in 0..100 {
+        assert_eq!(invocations.count_of(datum), 3, "attempts of {}", datum);
+    }
+    let logs = pegasus::fetch_job_logs(1).expect("logs not captured;");
+    let retries = logs.iter().filter(|line| line.contains("WARN") && line.contains("retry in"));
+    assert_eq!(retries.count(), 200, "{:?}", logs);
+    assert!(logs
+        .iter()
+        .any(|line| line.contains("map failed on attempt 2/3 of a batch of 1 data")));
+}
+
+// the whole batch is mapped again on the failure of any datum in it
+#[test]
+fn retry_flat_map_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let conf = JobConf::new(2, "retry_flat_map_test", 1);
+    let invocations = Invocations::default();
+    let (tx, rx) = crossbeam_channel::unbounded();
+    let invocations_cloned = invocations.clone();
+    let guard = pegasus::run(conf, |worker| {
+        let invocations = invocations_cloned.clone();
+        let tx = tx.clone();
+        worker.dataflow(move |builder| {
+            let invocations = invocations.clone();
+            builder
+                .input_from_iter(0..10u32)?
+                .with_retry(policy(3))
+                .flat_map(
+                    Pipeline,
+                    Idempotent(FlatMapClosure::new(move |item| {
+                        let failures = if item == 7 { 2 } else { 0 };
+                        let doubled = invocations.invoke(item, failures)?;
+                        let outputs: Vec<Result<u32, DynError>> = vec![Ok(item), Ok(doubled)];
+                        Ok(outputs.into_iter())
+                    })),
+                )?
+                .sink_by(|_| {
+                    move |_, result| {
+                        if let ResultSet::Data(data) = result {
+                            tx.send(data).unwrap();
+                        }
+                    }
+                })?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;")
+    .expect("job not started;");
+    drop(tx);
+    let result = collect(guard, rx);
+    let mut expected = (0..10u32).flat_map(|i| vec![i, i * 2]).collect::<Vec<_>>();
+    expected.sort();
+    assert_eq!(result, expected);
+    assert_eq!(invocations.count_of(7), 3);
+    // the data before 7 in its batch are mapped again by each attempt
+    assert_eq!(invocations.count_of(0), 3);
+}
+
+#[test]
+fn retry_exhausted_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    // a single worker, or the failure is told by the peers as their aborted connections
+    let conf = JobConf::new(3, "retry_exhausted_test", 1);
+    let invocations = Invocations::default();
+    let (tx, _rx) = crossbeam_channel::unbounded();
+    let mut guard = submit_map(conf, invocations.clone(), 3, 3, tx)
+        .expect("submit job failure;")
+        .expect("job not started;");
+    let err = guard.join().expect_err("job should fail;");
+    assert!(err.to_string().contains("transient failure"), "{}", err);
+    assert!((0..100).all(|datum| invocations.count_of(datum) <= 3));
+}
+
+#[test]
+fn retry_not_idempotent_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let conf = JobConf::new(4, "retry_not_idempotent_test", 1);
+    let result = pegasus::run(conf, |worker| {
+        worker.dataflow(|builder| {
+            builder
+                .input_from_iter(0..10u32)?
+                .with_retry(policy(3))
+                .map_with_fn(Pipeline, |item| Ok(item + 1))?
+                .sink_by(|_| |_, _| ())?;
+            Ok(())
+        })
+    });
+    let err = result.err().expect("build job should fail;");
+    assert!(err.to_string().contains("isn't marked as idempotent"), "{}", err);
+}
@@ -0,0 +1,117 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use pegasus::api::{Range, ResultSet, Sample, Sink};
+use pegasus::{Configuration, JobConf};
+
+/// Run the numbers 0..1000 on each of 4 workers through a coin of 0.5, and sample 10 of those in
+/// the range, which are grouped by the workers drawing them;
+fn run_sampled(job_id: u64, rng_seed: Option<u64>, range: Range) -> Vec<Vec<u64>> {
+    let mut conf = JobConf::new(job_id, "rng_test", 4);
+    conf.rng_seed = rng_seed;
+    let (tx, rx) = crossbeam_channel::unbounded();
+    pegasus::run(conf, |worker| {
+        let index = worker.id.index;
+        let tx = tx.clone();
+        worker.dataflow(move |dfb| {
+            dfb.input_from_iter(0..1000u64)?.coin(0.5)?.sample(range, 10)?.sink_by(|_meta| {
+                move |_tag, result| {
+                    if let ResultSet::Data(data) = result {
+                        tx.send((index, data)).expect("send error");
+                    }
+                }
+            })?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;")
+    .expect("job not started;")
+    .join()
+    .expect("run job failure;");
+    std::mem::drop(tx);
+    let mut samples = vec![vec![]; 4];
+    for (index, data) in rx.iter() {
+        samples[index as usize].extend(data);
+    }
+    samples
+}
+
+#[test]
+fn sample_with_seed_test() {
+    pegasus::startup(Configuration::singleton()).ok();
+    let first = run_sampled(1, Some(42), Range::Local);
+    assert!(first.iter().all(|data| data.len() == 10), "{:?}", first);
+    // the same data are drawn in all runs with the same seed;
+    for job_id in 2..5 {
+        assert_eq!(run_sampled(job_id, Some(42), Range::Local), first);
+    }
+    assert_eq!(pegasus::fetch_job_rng_seed(4), Some(42));
+    // while the workers draw apart from the same data;
+    for i in 0..4 {
+        for j in i + 1..4 {
+            assert_ne!(first[i], first[j], "worker {} and {} draw the same", i, j);
+        }
+    }
+    assert_ne!(run_sampled(5, Some(43), Range::Local), first);
+}
+
+#[test]
+fn global_sample_with_seed_test() {
+    pegasus::startup(Configuration::singleton()).ok();
+    let first = run_sampled(31, Some(42), Range::Global);
+    // all drawn by the worker the data are gathered to;
+    assert_eq!(first.iter().map(|data| data.len()).sum::<usize>(), 10);
+    assert_eq!(first[0].len(), 10);
+    // the same data are drawn however the data of the workers are interleaved;
+    for job_id in 32..35 {
+        assert_eq!(run_sampled(job_id, Some(42), Range::Global), first);
+    }
+}
+
+#[test]
+fn sample_with_entropy_test() {
+    pegasus::startup(Configuration::singleton()).ok();
+    let first = run_sampled(11, None, Range::Local);
+    let seed = pegasus::fetch_job_rng_seed(11).expect("seed not reported;");
+    // the job is reproduced by the seed reported;
+    assert_eq!(run_sampled(12, Some(seed), Range::Local), first);
+}
+
+#[test]
+fn coin_probability_test() {
+    pegasus::startup(Configuration::singleton()).ok();
+    let conf = JobConf::new(21, "rng_test", 1);
+    let (tx, rx) = crossbeam_channel::unbounded();
+    pegasus::run(conf, |worker| {
+        let tx = tx.clone();
+        worker.dataflow(move |dfb| {
+            dfb.input_from_iter(0..10000u64)?.coin(0.2)?.sink_by(|_meta| {
+                move |_tag, result| {
+                    if let ResultSet::Data(data) = result {
+                        tx.send(data.len()).expect("send error");
+                    }
+                }
+            })?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;")
+    .expect("job not started;")
+    .join()
+    .expect("run job failure;");
+    std::mem::drop(tx);
+    let kept: usize = rx.iter().sum();
+    assert!(kept > 1800 && kept < 2200, "{} kept", kept);
+}
@@ -0,0 +1,133 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use pegasus::api::{EnterScope, Fold, Iteration, LeaveScope, Map, ResultSet, ScopePartition, Sink};
+use pegasus::communication::{Aggregate, Pipeline};
+use pegasus::stream::Stream;
+use pegasus::{BuildJobError, Configuration, JobConf, JobSubmitError, Tag};
+
+const WINDOW: u32 = 100;
+
+/// Sum the data of each group in each window, and pick the group of the largest sum of each
+/// window, as (window, group, sum), by the windows and the groups nested in them as the scopes;
+fn max_group_of_windows(src: Stream<u32>) -> Result<Stream<(u32, u32, u64)>, BuildJobError> {
+    src.enter_scope(|item: &u32| {
+        let window = *item / WINDOW;
+        // the data of a worker are in order, whose last one in a window closes it
+        if *item % WINDOW >= WINDOW - 2 {
+            Some(ScopePartition::last(window))
+        } else {
+            Some(ScopePartition::of(window))
+        }
+    })?
+    // the groups are never closed, which end with their windows
+    .enter_scope(|item: &u32| Some(ScopePartition::of(*item % 3)))?
+    .fold((0u32, 0u32, 0u64), Aggregate(0), |sum, item| {
+        *sum = (item / WINDOW, item % 3, sum.2 + item as u64);
+    })?
+    .leave_scope()?
+    .fold((0u32, 0u32, 0u64), Aggregate(0), |max, sum| {
+        if sum.2 > max.2 {
+            *max = sum;
+        }
+    })?
+    .leave_scope()
+}
+
+#[test]
+fn two_level_scope_aggregation_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let conf = JobConf::new(124, "two_level_scope_aggregation_test", 2);
+    let (tx, rx) = crossbeam_channel::unbounded();
+    pegasus::run(conf, |worker| {
+        let index = worker.id.index;
+        let tx = tx.clone();
+        worker.dataflow(move |dfb| {
+            let src = dfb.input_from_iter((0..10 * WINDOW).filter(move |i| i % 2 == index))?;
+            max_group_of_windows(src)?.sink_by(move |_meta| {
+                move |_t: &Tag, result: ResultSet<(u32, u32, u64)>| {
+                    if let ResultSet::Data(data) = result {
+                        for max in data {
+                            tx.send(max).expect("send error");
+                        }
+                    }
+                }
+            })?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;")
+    .expect("job not started;")
+    .join()
+    .expect("run job failure;");
+    std::mem::drop(tx);
+    let mut results: Vec<(u32, u32, u64)> = rx.iter().collect();
+    results.sort();
+
+    // the flat reference, computed without any scope
+    let mut expected = vec![];
+    for window in 0..10 {
+        let mut sums = [0u64; 3];
+        for item in window * WINDOW..(window + 1) * WINDOW {
+            sums[(item % 3) as usize] += item as u64;
+        }
+        let max = (0..3).max_by_key(|g| sums[*g as usize]).unwrap();
+        expected.push((window, max, sums[max as usize]));
+    }
+    assert_eq!(results, expected);
+    pegasus::shutdown_all();
+}
+
+fn build_error<F>(job_id: u64, func: F) -> String
+where
+    F: Fn(Stream<u32>) -> Result<Stream<u32>, BuildJobError> + Send + Sync + 'static,
+{
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let conf = JobConf::new(job_id, "scope_build_error_test", 1);
+    let func = std::sync::Arc::new(func);
+    let result = pegasus::run(conf, |worker| {
+        let func = func.clone();
+        worker.dataflow(move |dfb| {
+            let src = dfb.input_from_iter(0..10u32)?;
+            (*func)(src)?.sink_by(|_meta| |_t: &Tag, _result: ResultSet<u32>| {})?;
+            Ok(())
+        })
+    });
+    match result {
+        Err(JobSubmitError::Build(err)) => err.to_string(),
+        _ => panic!("the job is expected to fail to be built;"),
+    }
+}
+
+#[test]
+fn scope_not_left_test() {
+    let err = build_error(125, |src| {
+        src.enter_scope(|item: &u32| Some(ScopePartition::of(*item % 2)))?
+            .map_with_fn(Pipeline, |item| Ok(item + 1))
+    });
+    assert!(
+        err.contains("the scope entered by operator enter_scope[1] is not left by leave_scope"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn leave_scope_of_iteration_test() {
+    let err = build_error(126, |src| src.iterate(2, |body| body.leave_scope()));
+    assert!(err.contains("can't leave the scope not entered by enter_scope"), "{}", err);
+}
@@ -0,0 +1,117 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use pegasus::api::{Map, ResultSet, Sink, SubTask};
+use pegasus::communication::Pipeline;
+use pegasus::scratch::{with_context, ScratchVec};
+use pegasus::{Configuration, JobConf, Tag};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Count the allocations of all threads, including the reallocations;
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+const RECORDS: u32 = 10_000;
+
+/// The median of the small adjacency list of the item, built and sorted for the item only;
+fn median_by_vec(item: u32) -> u32 {
+    let mut neighbors = Vec::new();
+    for i in 0..8 {
+        neighbors.push(item.wrapping_mul(31).wrapping_add(i * 17) % 101);
+    }
+    neighbors.sort_unstable();
+    neighbors[4]
+}
+
+fn median_by_scratch(item: u32) -> u32 {
+    with_context(|ctx| {
+        let mut neighbors = ScratchVec::new(ctx.scratch());
+        for i in 0..8 {
+            neighbors.push(item.wrapping_mul(31).wrapping_add(i * 17) % 101);
+        }
+        neighbors.sort_unstable();
+        neighbors[4]
+    })
+}
+
+/// Fork a subtask of each item computing the median of its adjacency list, which is joined with
+/// the item, and give the sum of the medians and the allocations per record of the job;
+fn fork_join(job_id: u64, median: fn(u32) -> u32) -> (u64, f64) {
+    let (tx, rx) = crossbeam_channel::unbounded();
+    let conf = JobConf::new(job_id, "scratch_test", 2);
+    let before = ALLOCATIONS.load(Ordering::SeqCst);
+    pegasus::run(conf, |worker| {
+        let tx = tx.clone();
+        worker.dataflow(move |dfb| {
+            let src = dfb.input_from_iter(0..RECORDS)?;
+            let subtask = src.fork_subtask(move |stream| {
+                stream.map_with_fn(Pipeline, move |item| Ok(median(item)))
+            })?;
+            src.join_subtask(subtask, |_item, median| Some(median as u64))?.sink_by(
+                move |_meta| {
+                    move |_t: &Tag, result: ResultSet<u64>| {
+                        if let ResultSet::Data(data) = result {
+                            tx.send(data.into_iter().sum::<u64>()).expect("send error");
+                        }
+                    }
+                },
+            )?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;")
+    .expect("no job guard;")
+    .join()
+    .expect("run job failure;");
+    let allocations = ALLOCATIONS.load(Ordering::SeqCst) - before;
+    std::mem::drop(tx);
+    let sum = rx.iter().sum();
+    (sum, allocations as f64 / (2 * RECORDS) as f64)
+}
+
+#[test]
+fn scratch_allocations_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    // warm up the engine, e.g. its pools of workers and buffers;
+    fork_join(134, median_by_vec);
+    let (expected, by_vec) = fork_join(135, median_by_vec);
+    let (sum, by_scratch) = fork_join(136, median_by_scratch);
+    assert_eq!(sum, expected);
+    // the vector of each record allocates twice as it grows, while the scratch is reused;
+    assert!(by_scratch + 1.5 < by_vec, "{} by vec, {} by scratch", by_vec, by_scratch);
+    pegasus::shutdown_all();
+}
@@ -0,0 +1,126 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use pegasus::api::{Count, Map, Range, ResultSet, Sink};
+use pegasus::communication::Pipeline;
+use pegasus::{Configuration, JobConf, JobSubmitError, Tag};
+
+/// Collect what a sink delivers, as the data and the number of ends it is told;
+fn collect(rx: crossbeam_channel::Receiver<Option<Vec<u64>>>) -> (Vec<u64>, usize) {
+    let mut data = vec![];
+    let mut ends = 0;
+    for result in rx.iter() {
+        match result {
+            Some(batch) => data.extend(batch),
+            None => ends += 1,
+        }
+    }
+    data.sort();
+    (data, ends)
+}
+
+fn sender(
+    tx: crossbeam_channel::Sender<Option<Vec<u64>>>,
+) -> impl Fn(&Tag, ResultSet<u64>) + Send + 'static {
+    move |_tag, result| match result {
+        ResultSet::Data(data) => tx.send(Some(data)).expect("send error"),
+        ResultSet::End => tx.send(None).expect("send error"),
+        ResultSet::ScopeEnd(_) => {}
+    }
+}
+
+#[test]
+fn multiple_sinks_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let conf = JobConf::new(127, "multiple_sinks_test", 2);
+    let (full_tx, full_rx) = crossbeam_channel::unbounded();
+    let (count_tx, count_rx) = crossbeam_channel::unbounded();
+    pegasus::run(conf, |worker| {
+        let index = worker.id.index as u64;
+        let full_tx = full_tx.clone();
+        let count_tx = count_tx.clone();
+        worker.dataflow(move |dfb| {
+            let stream = dfb
+                .input_from_iter((0..1000u64).filter(move |i| i % 2 == index))?
+                .map_with_fn(Pipeline, |item| Ok(item * 2))?;
+            // the full results to one sink, and only their count to another
+            stream.sink_by(|_meta| sender(full_tx))?;
+            stream.count(Range::Global)?.sink_by(|_meta| sender(count_tx))?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;")
+    .expect("job not started;")
+    .join()
+    .expect("run job failure;");
+    std::mem::drop(full_tx);
+    std::mem::drop(count_tx);
+
+    // the job completes after both sinks of both workers end
+    let (full, full_ends) = collect(full_rx);
+    assert_eq!(full, (0..1000u64).map(|i| i * 2).collect::<Vec<_>>());
+    assert_eq!(full_ends, 2);
+    let (counts, count_ends) = collect(count_rx);
+    assert_eq!(counts.iter().sum::<u64>(), 1000);
+    assert_eq!(count_ends, 2);
+}
+
+#[test]
+fn sink_map_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let conf = JobConf::new(154, "sink_map_test", 2);
+    let (tx, rx) = crossbeam_channel::unbounded();
+    pegasus::run(conf, |worker| {
+        let index = worker.id.index as u64;
+        let tx = tx.clone();
+        worker.dataflow(move |dfb| {
+            let records = (0..100u64).filter(move |i| i % 2 == index).map(|i| (i, "x".repeat(64)));
+            // only the keys of the records are delivered, projected on the workers
+            dfb.input_from_iter(records)?
+                .sink_map_by(|(key, _payload): (u64, String)| key, |_meta| sender(tx))?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;")
+    .expect("job not started;")
+    .join()
+    .expect("run job failure;");
+    std::mem::drop(tx);
+
+    let (keys, ends) = collect(rx);
+    assert_eq!(keys, (0..100u64).collect::<Vec<_>>());
+    assert_eq!(ends, 2);
+}
+
+#[test]
+fn no_sink_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let conf = JobConf::new(128, "no_sink_test", 1);
+    let result = pegasus::run(conf, |worker| {
+        worker.dataflow(|dfb| {
+            dfb.input_from_iter(0..10u64)?.map_with_fn(Pipeline, |item| Ok(item + 1))?;
+            Ok(())
+        })
+    });
+    match result {
+        Err(JobSubmitError::Build(err)) => {
+            assert!(err.to_string().contains("the dataflow has no sink"), "{}", err)
+        }
+        _ => panic!("the job without sink is expected to fail to be built;"),
+    }
+}
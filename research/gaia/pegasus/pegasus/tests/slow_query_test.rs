@@ -0,0 +1,128 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use pegasus::api::{Map, ResultSet, Sink};
+use pegasus::communication::Pipeline;
+use pegasus::{Configuration, JobConf, JobGuard, JobStatus, SlowQueryConfig, SlowQueryRecord, Tag};
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+const THRESHOLD_MS: u64 = 200;
+
+fn log_path() -> PathBuf {
+    std::env::temp_dir().join(format!("slow_query_test_{}.log", std::process::id()))
+}
+
+fn setup() {
+    let slow_query = SlowQueryConfig {
+        threshold_ms: THRESHOLD_MS,
+        max_entries: Some(16),
+        log_path: Some(log_path()),
+    };
+    let conf = Configuration::builder().slow_query(slow_query).build().unwrap();
+    pegasus::startup(conf).ok();
+}
+
+// sleeps on the 500th record if `sleep`, after which fails if `fail`;
+fn submit(job_id: u64, sleep: bool, fail: bool) -> JobGuard {
+    let mut conf = JobConf::new(job_id, "slow_query_test", 2);
+    conf.plan_hash = job_id * 10;
+    pegasus::run(conf, move |worker| {
+        worker.dataflow(move |builder| {
+            builder
+                .input_from_iter(0..1000u32)?
+                .named("sleep", |s| {
+                    s.map_with_fn(Pipeline, move |item| {
+                        if sleep && item == 500 {
+                            std::thread::sleep(Duration::from_millis(THRESHOLD_MS * 2));
+                            if fail {
+                                return Err(Box::new(io::Error::other("fail on purpose")));
+                            }
+                        }
+                        Ok(item)
+                    })
+                })?
+                .sink_by(|_meta| |_t: &Tag, _result: ResultSet<u32>| ())?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;")
+    .expect("job not started;")
+}
+
+// the job is recorded once all its workers are dropped, which may be after the guard is joined;
+fn wait_record(job_id: u64) -> SlowQueryRecord {
+    let start = Instant::now();
+    loop {
+        let records = pegasus::fetch_slow_queries();
+        if let Some(record) = records.into_iter().find(|r| r.job_id == job_id) {
+            return record;
+        }
+        assert!(start.elapsed() < Duration::from_secs(10), "job {} not recorded;", job_id);
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+fn read_lines(job_id: u64) -> Vec<String> {
+    let content = std::fs::read_to_string(log_path()).expect("read slow query log failure;");
+    let job = format!("{{\"job_id\":{},", job_id);
+    content.lines().filter(|line| line.starts_with(&job)).map(|line| line.to_owned()).collect()
+}
+
+#[test]
+fn slow_job_test() {
+    setup();
+    submit(74, false, false).join().expect("run job failure;");
+    submit(75, true, false).join().expect("run job failure;");
+    let record = wait_record(75);
+    assert_eq!(record.status, JobStatus::Completed);
+    assert_eq!(record.plan_hash, 750);
+    assert_eq!(record.conf.workers, 2);
+    assert!(record.error.is_none());
+    assert!(record.elapsed_ms >= THRESHOLD_MS);
+    let sleep = record.operators.iter().find(|op| op.name == "sleep").expect("no profile;");
+    assert_eq!(sleep.records.sum, 2000);
+
+    // the fast job ends ahead of the slow one, so it would have been recorded by now
+    let records = pegasus::fetch_slow_queries();
+    let jobs: Vec<u64> = records.iter().map(|r| r.job_id).filter(|id| *id < 76).collect();
+    assert_eq!(jobs, vec![75]);
+    let lines = read_lines(75);
+    assert_eq!(lines.len(), 1);
+    let line = &lines[0];
+    assert!(line.ends_with("]}"), "{}", line);
+    assert!(
+        line.contains("\"plan_hash\":750,\"status\":\"completed\",\"error\":null,"),
+        "{}",
+        line
+    );
+    assert!(line.contains("\"conf\":{\"job_id\":75,\"job_name\":\"slow_query_test\","), "{}", line);
+    assert!(line.contains("\"name\":\"sleep\",\"workers\":2,"), "{}", line);
+    assert!(read_lines(74).is_empty());
+}
+
+#[test]
+fn slow_failed_job_test() {
+    setup();
+    assert!(submit(76, true, true).join().is_err());
+    let record = wait_record(76);
+    assert_eq!(record.status, JobStatus::Failed);
+    assert!(record.error.as_ref().unwrap().contains("fail on purpose"));
+    assert!(record.elapsed_ms >= THRESHOLD_MS);
+    let lines = read_lines(76);
+    assert_eq!(lines.len(), 1);
+    assert!(lines[0].contains("\"status\":\"failed\",\"error\":\""), "{}", lines[0]);
+}
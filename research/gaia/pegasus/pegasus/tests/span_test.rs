@@ -0,0 +1,125 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use pegasus::api::{Exchange, Map, ResultSet, Sink};
+use pegasus::communication::Pipeline;
+use pegasus::{Configuration, JobConf};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+
+type Fields = HashMap<String, String>;
+
+/// Capture the spans by their names, and the events, with their fields;
+#[derive(Clone, Default)]
+struct Capture {
+    spans: Arc<Mutex<Vec<(String, Fields)>>>,
+    events: Arc<Mutex<Vec<Fields>>>,
+}
+
+struct FieldVisitor<'a>(&'a mut Fields);
+
+impl<'a> Visit for FieldVisitor<'a> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_owned(), value.to_owned());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.0.insert(field.name().to_owned(), format!("{:?}", value));
+    }
+}
+
+impl<S: Subscriber> Layer<S> for Capture {
+    fn on_new_span(&self, attrs: &Attributes<'_>, _id: &Id, _ctx: Context<'_, S>) {
+        let mut fields = Fields::new();
+        attrs.record(&mut FieldVisitor(&mut fields));
+        let name = attrs.metadata().name().to_owned();
+        self.spans.lock().unwrap().push((name, fields));
+    }
+
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut fields = Fields::new();
+        event.record(&mut FieldVisitor(&mut fields));
+        self.events.lock().unwrap().push(fields);
+    }
+}
+
+#[test]
+fn span_test() {
+    let capture = Capture::default();
+    let subscriber = tracing_subscriber::registry().with(capture.clone());
+    tracing::subscriber::set_global_default(subscriber).expect("set subscriber failure;");
+    pegasus::startup(Configuration::singleton()).ok();
+
+    let conf = JobConf::new(1, "span_test", 2);
+    let query_hash = pegasus::span::query_hash(&conf);
+    let (tx, rx) = crossbeam_channel::unbounded();
+    let mut guard = pegasus::run(conf, |worker| {
+        let tx = tx.clone();
+        worker.dataflow(move |builder| {
+            builder
+                .input_from_iter(0..100u64)?
+                .exchange_with_fn(|item| *item)?
+                .map_with_fn(Pipeline, |item| Ok(item + 1))?
+                .sink_by(|_| {
+                    move |_, result| {
+                        if let ResultSet::Data(data) = result {
+                            tx.send(data.len()).unwrap();
+                        }
+                    }
+                })?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;")
+    .unwrap();
+    guard.join().expect("run job failure;");
+    std::mem::drop(tx);
+    assert_eq!(rx.iter().sum::<usize>(), 200);
+
+    let spans = capture.spans.lock().unwrap().clone();
+    let jobs = spans.iter().filter(|(name, _)| name == "job").collect::<Vec<_>>();
+    assert_eq!(jobs.len(), 1);
+    assert_eq!(jobs[0].1["job_id"], "1");
+    assert_eq!(jobs[0].1["query_hash"], query_hash.to_string());
+
+    let operators = spans.iter().filter(|(name, _)| name == "operator").collect::<Vec<_>>();
+    assert!(!operators.is_empty());
+    for (_, fields) in operators.iter() {
+        assert!(!fields["operator"].is_empty());
+        assert!(fields.contains_key("index"));
+        assert!(fields.contains_key("tag"));
+    }
+    for index in 0..2 {
+        let worker = format!("Some({})", index);
+        assert!(operators.iter().any(|(_, fields)| fields["worker"] == worker));
+    }
+
+    let events = capture.events.lock().unwrap().clone();
+    // the batches of the 200 results are sent and received
+    for kind in &["send", "recv"] {
+        let sizes = events
+            .iter()
+            .filter(|fields| fields.get("message").map(|m| m == kind).unwrap_or(false))
+            .map(|fields| fields["batch_size"].parse::<usize>().unwrap())
+            .collect::<Vec<_>>();
+        assert!(sizes.iter().sum::<usize>() >= 200, "missing {} events", kind);
+    }
+    pegasus::shutdown_all();
+}
@@ -0,0 +1,68 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use pegasus::api::{Map, ResultSet, Sink};
+use pegasus::communication::Pipeline;
+use pegasus::{Configuration, JobConf};
+use std::time::{Duration, Instant};
+
+/// The end-to-end latency of a one-worker job looking up a single vertex, as `g.V(x)` does;
+fn point_query(job_id: u64, x: u64) -> Duration {
+    let start = Instant::now();
+    let conf = JobConf::new(job_id, "point_query", 1);
+    let (tx, rx) = crossbeam_channel::unbounded();
+    let _guard = pegasus::run(conf, |worker| {
+        let tx = tx.clone();
+        worker.dataflow(move |builder| {
+            builder
+                .input_from_iter(std::iter::once(x))?
+                .map_with_fn(Pipeline, |item| Ok(item + 1))?
+                .sink_by(|_meta| {
+                    move |_, result| {
+                        if let ResultSet::Data(data) = result {
+                            tx.send(data).unwrap();
+                        }
+                    }
+                })?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;");
+    ::std::mem::drop(tx);
+    let result = rx.iter().flatten().collect::<Vec<_>>();
+    assert_eq!(result, vec![x + 1]);
+    start.elapsed()
+}
+
+fn p50_latency(first_job_id: u64) -> Duration {
+    for i in 0..20 {
+        point_query(first_job_id + i, i);
+    }
+    let mut latencies = (20..220).map(|i| point_query(first_job_id + i, i)).collect::<Vec<_>>();
+    latencies.sort();
+    latencies[latencies.len() / 2]
+}
+
+#[test]
+fn startup_latency_bench() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    pegasus::warm::set_enabled(false);
+    let cold = p50_latency(1000);
+    pegasus::warm::set_enabled(true);
+    let warm = p50_latency(2000);
+    // loosely bounded, as the latencies vary with the machine the test runs on;
+    assert!(warm <= cold * 2, "p50 latency {:?} without the warm pool, {:?} with it", cold, warm);
+}
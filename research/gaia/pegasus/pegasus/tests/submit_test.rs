@@ -0,0 +1,146 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use pegasus::api::{Map, Sink};
+use pegasus::cluster::Cluster;
+use pegasus::communication::Pipeline;
+use pegasus::{Configuration, JobConf, JobGuard, JobSubmitError};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// Whether any worker of the aborted job ever started on current server;
+static STARTED: AtomicBool = AtomicBool::new(false);
+
+/// Run on 2 servers, where the job fails to be built on the second one only, which is aborted on
+/// both before any worker starts;
+#[test]
+fn abort_on_prepare_failure_test() {
+    pegasus_common::logs::init_log();
+    let cluster = Cluster::new("abort_on_prepare_failure_test", 2);
+    let conf = JobConf::new(140, "abort_on_prepare_failure_test", 2);
+    let result = cluster.run(conf, |dfb| {
+        if dfb.worker_id.server_id == 1 {
+            return Err("rejected by server 1".into());
+        }
+        dfb.input_from_iter(0..100u32)?.map_with_fn(Pipeline, |item| {
+            STARTED.store(true, Ordering::SeqCst);
+            Ok(item)
+        })
+    });
+    let err = result.expect_err("job should be aborted;");
+    assert!(err.contains("Job aborted on all servers"), "{}", err);
+    // the error is reported by both servers
+    assert_eq!(err.matches("rejected by server 1").count(), 2, "{}", err);
+
+    let conf = JobConf::new(141, "abort_on_prepare_failure_test", 1);
+    let started = cluster
+        .run(conf, |dfb| dfb.input_from_iter(std::iter::once(STARTED.load(Ordering::SeqCst))))
+        .expect("run job failure;");
+    assert_eq!(started, vec![false, false]);
+}
+
+/// Run on 2 servers, where the first one prepares the job slower than the timeout, so the second
+/// one is not asked to vote in time, which aborts the job on both by telling the first one;
+#[test]
+fn abort_on_prepare_timeout_test() {
+    pegasus_common::logs::init_log();
+    let cluster = Cluster::new("abort_on_prepare_timeout_test", 2);
+    let mut conf = JobConf::new(145, "abort_on_prepare_timeout_test", 1);
+    conf.submit_timeout_ms = 100;
+    let result = cluster.run(conf, |dfb| {
+        if dfb.worker_id.server_id == 0 {
+            std::thread::sleep(Duration::from_millis(500));
+        }
+        dfb.input_from_iter(0..100u32)?.map_with_fn(Pipeline, |item| {
+            STARTED.store(true, Ordering::SeqCst);
+            Ok(item)
+        })
+    });
+    let err = result.expect_err("job should be aborted;");
+    // the abort of the second server is honoured by the first one
+    assert_eq!(err.matches("not asked to vote").count(), 2, "{}", err);
+
+    let conf = JobConf::new(146, "abort_on_prepare_timeout_test", 1);
+    let started = cluster
+        .run(conf, |dfb| dfb.input_from_iter(std::iter::once(STARTED.load(Ordering::SeqCst))))
+        .expect("run job failure;");
+    assert_eq!(started, vec![false, false]);
+}
+
+fn submit_range(job_id: u64) -> Result<Option<JobGuard>, JobSubmitError> {
+    pegasus::run(JobConf::new(job_id, "job_limit_test", 1), |worker| {
+        worker.dataflow(|dfb| dfb.input_from_iter(0..10u32)?.sink_by(|_| |_, _| ()))
+    })
+}
+
+#[test]
+fn job_limit_test() {
+    pegasus_common::logs::init_log();
+    let conf = Configuration::builder().max_jobs(1).build().expect("invalid configuration;");
+    pegasus::startup(conf).ok();
+    // the first job holds the only slot until it is released
+    let (release, hold) = crossbeam_channel::unbounded::<u32>();
+    let mut first = pegasus::run(JobConf::new(142, "job_limit_test", 1), |worker| {
+        let hold = hold.clone();
+        worker.dataflow(move |dfb| dfb.input_from_iter(hold.into_iter())?.sink_by(|_| |_, _| ()))
+    })
+    .expect("submit job failure;")
+    .expect("job not started;");
+    match submit_range(143).err() {
+        Some(JobSubmitError::OverJobLimit(limit)) => assert_eq!(limit, 1),
+        Some(err) => panic!("unexpected error {}", err),
+        None => panic!("job over the limit is accepted"),
+    }
+
+    std::mem::drop(release);
+    first.join().expect("run job failure;");
+    // the slot is free once all workers of the first job are dropped
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        match submit_range(144) {
+            Ok(guard) => {
+                guard.expect("job not started;").join().expect("run job failure;");
+                break;
+            }
+            Err(JobSubmitError::OverJobLimit(_)) if Instant::now() < deadline => {
+                std::thread::sleep(Duration::from_millis(10))
+            }
+            Err(err) => panic!("submit job failure: {}", err),
+        }
+    }
+
+    // the jobs admitted together are admitted all at once or none
+    match pegasus::admit(&[157, 158]).err() {
+        Some(JobSubmitError::OverJobLimit(limit)) => assert_eq!(limit, 1),
+        Some(err) => panic!("unexpected error {}", err),
+        None => panic!("jobs over the limit are admitted"),
+    }
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let admission = loop {
+        match pegasus::admit(&[157]) {
+            Ok(admission) => break admission,
+            Err(JobSubmitError::OverJobLimit(_)) if Instant::now() < deadline => {
+                std::thread::sleep(Duration::from_millis(10))
+            }
+            Err(err) => panic!("admit job failure: {}", err),
+        }
+    };
+    // the slot is reserved for the job admitted, which takes it as it is submitted
+    assert!(matches!(submit_range(158), Err(JobSubmitError::OverJobLimit(_))));
+    let guard = submit_range(157).expect("submit admitted job failure;");
+    guard.expect("job not started;").join().expect("run job failure;");
+    std::mem::drop(admission);
+    pegasus::shutdown_all();
+}
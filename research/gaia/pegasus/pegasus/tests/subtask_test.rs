@@ -13,10 +13,17 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
-use pegasus::api::{Count, Exchange, Iteration, Map, Range, ResultSet, Sink, SubTask};
+use pegasus::api::{
+    Count, Exchange, Iteration, Map, Multiplexing, Range, ResultSet, SemiJoinKind, Sink, SubTask,
+};
+use pegasus::cluster::Cluster;
 use pegasus::communication::Pipeline;
+use pegasus::errors::BuildJobError;
+use pegasus::result::{sink_to, ResultCollector};
+use pegasus::stream::Stream;
 use pegasus::{Configuration, JobConf};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
 
 #[test]
 fn test_subtask_fork() {
@@ -24,7 +31,7 @@ fn test_subtask_fork() {
     pegasus::startup(Configuration::singleton()).ok();
     let conf = JobConf::new(50, "test_subtask_fork", 2);
     let (tx, rx) = crossbeam_channel::unbounded();
-    pegasus::run(conf, |worker| {
+    let guard = pegasus::run(conf, |worker| {
         let tx = tx.clone();
         worker.dataflow(|dfb| {
             let src = if dfb.worker_id.index == 0 {
@@ -39,40 +46,27 @@ fn test_subtask_fork() {
                     Ok(vec![item + 1; 8].into_iter().map(|x| Ok(x)))
                 })
             })?;
-            subtask.sink_by(|_meta| {
-                move |_, r| match r {
-                    ResultSet::Data(data) => {
-                        for d in data {
-                            tx.send(d).expect("sink result failure")
-                        }
-                    }
-                    _ => (),
-                }
-            })?;
+            subtask.sink_by(|_meta| sink_to(tx))?;
             Ok(())
         })
     })
     .expect("submit job failure;");
 
     std::mem::drop(tx);
-    let mut count = 0;
+    let results =
+        ResultCollector::new(rx).with_guard(guard).collect_vec().expect("run job failure;");
     let mut res_map = HashMap::new();
-    while let Ok(r) = rx.recv() {
-        match r.take() {
-            ResultSet::Data(data) => {
-                count += data.len();
-                for d in data {
-                    let group_count = res_map.entry(d).or_insert(0);
-                    *group_count += 1;
-                }
+    for r in results {
+        if let ResultSet::Data(data) = r.take() {
+            for d in data {
+                res_map.entry(d).or_insert_with(Vec::new).push(d);
             }
-            _ => (),
         }
     }
-    assert_eq!(count, 80);
+    assert_eq!(res_map.values().map(|group| group.len()).sum::<usize>(), 80);
     assert_eq!(res_map.len(), 10);
     for i in 1..11 {
-        let r = res_map.get(&i).map(|i| *i);
+        let r = res_map.get(&i).map(|group| group.len());
         assert_eq!(r, Some(8))
     }
     pegasus::shutdown_all();
@@ -84,7 +78,7 @@ fn test_subtask_fork_join() {
     pegasus::startup(Configuration::singleton()).ok();
     let conf = JobConf::new(51, "test_subtask_fork_join", 2);
     let (tx, rx) = crossbeam_channel::unbounded();
-    pegasus::run(conf, |worker| {
+    let guard = pegasus::run(conf, |worker| {
         let tx = tx.clone();
         worker.dataflow(|dfb| {
             let src = if dfb.worker_id.index == 0 {
@@ -100,38 +94,53 @@ fn test_subtask_fork_join() {
                 })
             })?;
             let join = p.join_subtask(subtask, move |p, s| Some(s - *p))?;
-            join.sink_by(|_| {
-                move |_, r| match r {
-                    ResultSet::Data(data) => {
-                        tx.send(data).expect("sink data failure;");
-                    }
-                    _ => (),
-                }
-            })?;
+            join.sink_by(|_| sink_to(tx))?;
             Ok(())
         })
     })
     .expect("submit job failure;");
 
     std::mem::drop(tx);
-    let mut count = 0;
-    while let Ok(r) = rx.recv() {
-        count += r.len();
-        for d in r {
-            assert_eq!(d, 1);
-        }
-    }
-    assert_eq!(count, 8 * 2000);
+    let results =
+        ResultCollector::new(rx).with_guard(guard).collect_vec().expect("run job failure;");
+    assert_eq!(results.len(), 8 * 2000);
+    assert!(results.iter().all(|d| *d == 1));
     pegasus::shutdown_all();
 }
 
+/// Run on 2 servers, each of a worker, where the parents are exchanged to the other server;
+#[test]
+fn test_subtask_fork_join_cluster() {
+    pegasus_common::logs::init_log();
+    let cluster = Cluster::new("test_subtask_fork_join_cluster", 2);
+    let conf = JobConf::new(151, "test_subtask_fork_join_cluster", 1);
+    let results = cluster
+        .run(conf, |dfb| {
+            let src = if dfb.worker_id.index == 0 {
+                let vec = (0..2000).collect::<Vec<u32>>();
+                dfb.input_from_iter(vec.into_iter())
+            } else {
+                dfb.input_from_iter(Vec::<u32>::new().into_iter())
+            }?;
+            let p = src.exchange_with_fn(|item: &u32| *item as u64)?;
+            let subtask = p.fork_subtask(|stream| {
+                stream.flat_map_with_fn(Pipeline, |item| Ok(vec![item + 1; 8].into_iter().map(Ok)))
+            })?;
+            p.join_subtask(subtask, move |p, s| Some(s - *p))
+        })
+        .expect("run job failure;");
+
+    assert_eq!(results.len(), 8 * 2000);
+    assert!(results.iter().all(|d| *d == 1));
+}
+
 #[test]
 fn test_subtask_fork_count_join() {
     pegasus_common::logs::init_log();
     pegasus::startup(Configuration::singleton()).ok();
     let conf = JobConf::new(52, "test_subtask_count_fork_join", 2);
     let (tx, rx) = crossbeam_channel::unbounded();
-    pegasus::run(conf, |worker| {
+    let guard = pegasus::run(conf, |worker| {
         let tx = tx.clone();
         worker.dataflow(|dfb| {
             let src = if dfb.worker_id.index == 0 {
@@ -152,25 +161,21 @@ fn test_subtask_fork_count_join() {
             })?;
 
             let join = p.join_subtask(subtask, move |p, s| Some((*p, s)))?;
-            join.sink_by(|_| {
-                move |_, r| match r {
-                    ResultSet::Data(data) => {
-                        tx.send(data).expect("sink data failure;");
-                    }
-                    _ => (),
-                }
-            })?;
+            join.sink_by(|_| sink_to(tx))?;
             Ok(())
         })
     })
     .expect("submit job failure;");
 
     std::mem::drop(tx);
-    while let Ok(r) = rx.recv() {
-        for (i, count) in r {
-            assert_eq!(i + 1, count as u32);
-        }
+    let mut collector = ResultCollector::new(rx).with_guard(guard);
+    let mut joined = 0;
+    while let Some(r) = collector.next_timeout(Duration::from_secs(60)) {
+        let (i, count) = r.expect("run job failure;");
+        assert_eq!(i + 1, count as u32);
+        joined += 1;
     }
+    assert_eq!(joined, 10);
     pegasus::shutdown_all();
 }
 
@@ -181,7 +186,7 @@ fn test_subtask_in_iteration() {
     let conf = JobConf::new(52, "test_subtask_count_fork_join", 2);
     //conf.plan_print = true;
     let (tx, rx) = crossbeam_channel::unbounded();
-    pegasus::run(conf, |worker| {
+    let guard = pegasus::run(conf, |worker| {
         let tx = tx.clone();
         worker.dataflow(|dfb| {
             let src = if dfb.worker_id.index == 0 {
@@ -201,12 +206,222 @@ fn test_subtask_in_iteration() {
 
                 parent.join_subtask(sub, |p, s| Some(*p + s))
             })?
+            .sink_by(|_| sink_to(tx))?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;");
+
+    std::mem::drop(tx);
+    let vec = ResultCollector::new(rx).with_guard(guard).collect_vec().expect("run job failure;");
+    assert_eq!(80, vec.len());
+    pegasus::shutdown_all();
+}
+
+fn semi_join_subtask(job_id: u64, kind: SemiJoinKind) -> Vec<u32> {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let conf = JobConf::new(job_id, "test_subtask_semi_join", 2);
+    let (tx, rx) = crossbeam_channel::unbounded();
+    let guard = pegasus::run(conf, |worker| {
+        let tx = tx.clone();
+        worker.dataflow(move |dfb| {
+            let src = if dfb.worker_id.index == 0 {
+                let vec = (0..100).collect::<Vec<u32>>();
+                dfb.input_from_iter(vec.into_iter())
+            } else {
+                dfb.input_from_iter(Vec::<u32>::new().into_iter())
+            }?;
+            let p = src.exchange_with_fn(|item: &u32| *item as u64)?;
+            // the subtask of each multiple of 3 outputs nothing
+            let subtask = p.fork_subtask(|stream| {
+                stream.flat_map_with_fn(Pipeline, |item| {
+                    let size = (item % 3) as usize;
+                    Ok(vec![item; size].into_iter().map(Ok))
+                })
+            })?;
+            let join = p.semi_join_subtask(subtask, kind)?;
+            join.sink_by(|_| sink_to(tx))?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;");
+
+    std::mem::drop(tx);
+    let mut result =
+        ResultCollector::new(rx).with_guard(guard).collect_vec().expect("run job failure;");
+    result.sort();
+    result
+}
+
+#[test]
+fn test_subtask_semi_join() {
+    let result = semi_join_subtask(53, SemiJoinKind::AnyExists);
+    // each parent is kept once, even if its subtask outputs more than one result
+    assert_eq!(result, (0..100).filter(|i| i % 3 != 0).collect::<Vec<u32>>());
+    let result = semi_join_subtask(54, SemiJoinKind::NoneExists);
+    assert_eq!(result, (0..100).filter(|i| i % 3 == 0).collect::<Vec<u32>>());
+    pegasus::shutdown_all();
+}
+
+#[test]
+fn test_scope_end_per_parent() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let conf = JobConf::new(55, "test_scope_end_per_parent", 2);
+    let (tx, rx) = crossbeam_channel::unbounded();
+    pegasus::run(conf, |worker| {
+        let tx = tx.clone();
+        worker.dataflow(|dfb| {
+            let index = dfb.worker_id.index;
+            let src = if index == 0 {
+                let vec = (0..10u32).flat_map(|p| vec![Some(p), None]).collect::<Vec<_>>();
+                dfb.input_from_iter(vec.into_iter())
+            } else {
+                dfb.input_from_iter(Vec::<Option<u32>>::new().into_iter())
+            }?;
+            // each parent is in its own scope, whose children are spread over both workers;
+            let mut scope_id = 0u32;
+            src.scope_by(move |item| {
+                if item.is_some() {
+                    Some(scope_id)
+                } else {
+                    scope_id += 1;
+                    None
+                }
+            })?
+            .flat_map_with_fn(Pipeline, |item| {
+                let parent = item.unwrap();
+                Ok((0..8u32).map(move |i| Ok(parent * 10 + i)))
+            })?
+            .exchange_with_fn(|item: &u32| *item as u64)?
             .sink_by(|_| {
-                move |_, r| match r {
+                move |tag, r| match r {
                     ResultSet::Data(data) => {
+                        tx.send((index, tag.current_uncheck(), Some(data))).unwrap();
+                    }
+                    ResultSet::ScopeEnd(end) => {
+                        assert_eq!(&end, tag);
+                        tx.send((index, end.current_uncheck(), None)).unwrap();
+                    }
+                    ResultSet::End => {
+                        tx.send((index, u32::MAX, None)).unwrap();
+                    }
+                }
+            })?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;");
+
+    std::mem::drop(tx);
+    let mut ended = vec![HashSet::new(), HashSet::new()];
+    let mut stream_ends = vec![0, 0];
+    let mut children = 0;
+    while let Ok((index, scope, r)) = rx.recv() {
+        match r {
+            Some(data) => {
+                assert!(!ended[index as usize].contains(&scope), "data after the end of scope");
+                for d in data {
+                    assert_eq!(d / 10, scope);
+                    children += 1;
+                }
+            }
+            None if scope == u32::MAX => stream_ends[index as usize] += 1,
+            None => assert!(ended[index as usize].insert(scope), "scope {} ends twice", scope),
+        }
+    }
+    assert_eq!(children, 80);
+    assert_eq!(stream_ends, vec![1, 1]);
+    for scopes in ended {
+        assert_eq!(scopes, (0..10u32).collect::<HashSet<_>>());
+    }
+    pegasus::shutdown_all();
+}
+
+#[test]
+fn test_coalesce_subtasks() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let conf = JobConf::new(56, "test_coalesce_subtasks", 2);
+    let (tx, rx) = crossbeam_channel::unbounded();
+    let guard = pegasus::run(conf, |worker| {
+        let tx = tx.clone();
+        worker.dataflow(|dfb| {
+            let src = if dfb.worker_id.index == 0 {
+                let vec = (0..100).collect::<Vec<u32>>();
+                dfb.input_from_iter(vec.into_iter())
+            } else {
+                dfb.input_from_iter(Vec::<u32>::new().into_iter())
+            }?;
+            let p = src.exchange_with_fn(|item: &u32| *item as u64)?;
+            type Branch = Box<dyn FnOnce(Stream<u32>) -> Result<Stream<u32>, BuildJobError> + Send>;
+            // the multiples of 3 match the first branch twice, the numbers of remainder 1 match
+            // the second only, and the others match neither;
+            let first: Branch = Box::new(|stream| {
+                stream.flat_map_with_fn(Pipeline, |item| {
+                    let size = if item % 3 == 0 { 2 } else { 0 };
+                    Ok(vec![item; size].into_iter().map(Ok))
+                })
+            });
+            let second: Branch = Box::new(|stream| {
+                stream.flat_map_with_fn(Pipeline, |item| {
+                    // each multiple of 3 would match the second branch too, if it were forked;
+                    let size = if item % 3 == 2 { 0 } else { 1 };
+                    Ok(vec![item; size].into_iter().map(Ok))
+                })
+            });
+            p.coalesce_subtasks(vec![first, second])?.sink_by(|_| sink_to(tx))?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;");
+
+    std::mem::drop(tx);
+    let mut result =
+        ResultCollector::new(rx).with_guard(guard).collect_vec().expect("run job failure;");
+    result.sort();
+    let mut expected = vec![];
+    for i in 0..100u32 {
+        match i % 3 {
+            0 => expected.extend(vec![(0, i); 2]),
+            1 => expected.push((1, i)),
+            _ => (),
+        }
+    }
+    expected.sort();
+    assert_eq!(result, expected);
+    pegasus::shutdown_all();
+}
+
+#[test]
+fn test_optional_subtask() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let conf = JobConf::new(57, "test_optional_subtask", 2);
+    let (tx, rx) = crossbeam_channel::unbounded();
+    pegasus::run(conf, |worker| {
+        let tx = tx.clone();
+        worker.dataflow(|dfb| {
+            let src = if dfb.worker_id.index == 0 {
+                let vec = (0..100).collect::<Vec<u32>>();
+                dfb.input_from_iter(vec.into_iter())
+            } else {
+                dfb.input_from_iter(Vec::<u32>::new().into_iter())
+            }?;
+            let p = src.exchange_with_fn(|item: &u32| *item as u64)?;
+            // the even parents are mapped into two results each, and the odd ones pass through
+            p.optional_subtask(|stream| {
+                stream.flat_map_with_fn(Pipeline, |item| {
+                    let size = if item % 2 == 0 { 2 } else { 0 };
+                    Ok(vec![item + 1000; size].into_iter().map(Ok))
+                })
+            })?
+            .sink_by(|_| {
+                move |_, r| {
+                    if let ResultSet::Data(data) = r {
                         tx.send(data).expect("sink data failure;");
                     }
-                    _ => (),
                 }
             })?;
             Ok(())
@@ -215,11 +430,205 @@ fn test_subtask_in_iteration() {
     .expect("submit job failure;");
 
     std::mem::drop(tx);
-    let mut vec = Vec::new();
+    let mut result = vec![];
     while let Ok(r) = rx.recv() {
-        vec.extend(r);
+        result.extend(r);
     }
-    println!("get result {:?}", vec);
-    assert_eq!(80, vec.len());
+    result.sort();
+    let mut expected = vec![];
+    for i in 0..100u32 {
+        if i % 2 == 0 {
+            expected.extend(vec![i + 1000; 2]);
+        } else {
+            expected.push(i);
+        }
+    }
+    expected.sort();
+    assert_eq!(result, expected);
+    pegasus::shutdown_all();
+}
+
+#[test]
+fn test_join_first_subtask() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let conf = JobConf::new(73, "test_join_first_subtask", 2);
+    let (tx, rx) = crossbeam_channel::unbounded();
+    pegasus::run(conf, |worker| {
+        let tx = tx.clone();
+        worker.dataflow(|dfb| {
+            let src = if dfb.worker_id.index == 0 {
+                let vec = (0..100).collect::<Vec<u32>>();
+                dfb.input_from_iter(vec.into_iter())
+            } else {
+                dfb.input_from_iter(Vec::<u32>::new().into_iter())
+            }?;
+            let p = src.exchange_with_fn(|item: &u32| *item as u64)?;
+            // the subtask of each multiple of 3 outputs nothing, and the others output the item
+            // plus 1000 followed by more results, which are ignored
+            let subtask = p.fork_subtask(|stream| {
+                stream.flat_map_with_fn(Pipeline, |item| {
+                    let results = if item % 3 == 0 { vec![] } else { vec![item + 1000, item] };
+                    Ok(results.into_iter().map(Ok))
+                })
+            })?;
+            p.join_first_subtask(subtask, |p, first| Some((*p, first)))?.sink_by(|_| {
+                move |_, r| {
+                    if let ResultSet::Data(data) = r {
+                        tx.send(data).expect("sink data failure;");
+                    }
+                }
+            })?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;");
+
+    std::mem::drop(tx);
+    let mut result = vec![];
+    while let Ok(r) = rx.recv() {
+        result.extend(r);
+    }
+    result.sort();
+    let expected = (0..100u32)
+        .map(|i| if i % 3 == 0 { (i, None) } else { (i, Some(i + 1000)) })
+        .collect::<Vec<_>>();
+    assert_eq!(result, expected);
+    pegasus::shutdown_all();
+}
+
+#[test]
+fn test_subtask_fork_exchange_join() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let conf = JobConf::new(77, "test_subtask_fork_exchange_join", 4);
+    let (tx, rx) = crossbeam_channel::unbounded();
+    pegasus::run(conf, |worker| {
+        let tx = tx.clone();
+        worker.dataflow(|dfb| {
+            let src = if dfb.worker_id.index == 0 {
+                let vec = (0..2000).collect::<Vec<u32>>();
+                dfb.input_from_iter(vec.into_iter())
+            } else {
+                dfb.input_from_iter(Vec::<u32>::new().into_iter())
+            }?;
+            let p = src.exchange_with_fn(|item: &u32| *item as u64)?;
+            // the data of each subtask are exchanged to the other workers twice, as an expansion
+            // routed to the owners of the vertices, before they are joined back to their parents
+            let subtask = p.fork_subtask(|stream| {
+                stream
+                    .exchange_with_fn(|item: &u32| *item as u64 + 1)?
+                    .flat_map_with_fn(Pipeline, |item| {
+                        Ok((0..8u32).map(move |i| Ok((item + 1) * 8 + i)))
+                    })?
+                    .exchange_with_fn(|item: &u32| *item as u64)?
+                    .map_with_fn(Pipeline, |item| Ok(item / 8))
+            })?;
+            let join = p.join_subtask(subtask, move |p, s| Some(s - *p))?;
+            join.sink_by(|_| {
+                move |_, r| {
+                    if let ResultSet::Data(data) = r {
+                        tx.send(data).expect("sink data failure;");
+                    }
+                }
+            })?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;");
+
+    std::mem::drop(tx);
+    let mut count = 0;
+    while let Ok(r) = rx.recv() {
+        count += r.len();
+        for d in r {
+            assert_eq!(d, 1);
+        }
+    }
+    assert_eq!(count, 8 * 2000);
+    pegasus::shutdown_all();
+}
+
+/// Run on 2 servers, each of 2 workers, where the data of the subtasks cross the servers;
+#[test]
+fn test_subtask_fork_exchange_join_cluster() {
+    pegasus_common::logs::init_log();
+    let cluster = Cluster::new("test_subtask_fork_exchange_join_cluster", 2);
+    let conf = JobConf::new(177, "test_subtask_fork_exchange_join_cluster", 2);
+    let results = cluster
+        .run(conf, |dfb| {
+            let src = if dfb.worker_id.index == 0 {
+                let vec = (0..2000).collect::<Vec<u32>>();
+                dfb.input_from_iter(vec.into_iter())
+            } else {
+                dfb.input_from_iter(Vec::<u32>::new().into_iter())
+            }?;
+            let p = src.exchange_with_fn(|item: &u32| *item as u64)?;
+            // the data of each subtask are exchanged to the other workers twice, as an expansion
+            // routed to the owners of the vertices, before they are joined back to their parents
+            let subtask = p.fork_subtask(|stream| {
+                stream
+                    .exchange_with_fn(|item: &u32| *item as u64 + 1)?
+                    .flat_map_with_fn(Pipeline, |item| {
+                        Ok((0..8u32).map(move |i| Ok((item + 1) * 8 + i)))
+                    })?
+                    .exchange_with_fn(|item: &u32| *item as u64)?
+                    .map_with_fn(Pipeline, |item| Ok(item / 8))
+            })?;
+            p.join_subtask(subtask, move |p, s| Some(s - *p))
+        })
+        .expect("run job failure;");
+
+    assert_eq!(results.len(), 8 * 2000);
+    assert!(results.iter().all(|d| *d == 1));
+}
+
+#[test]
+fn test_semi_join_exchanged_subtask() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let conf = JobConf::new(78, "test_semi_join_exchanged_subtask", 4);
+    let (tx, rx) = crossbeam_channel::unbounded();
+    pegasus::run(conf, |worker| {
+        let tx = tx.clone();
+        worker.dataflow(|dfb| {
+            let src = if dfb.worker_id.index == 0 {
+                let vec = (0..400).collect::<Vec<u32>>();
+                dfb.input_from_iter(vec.into_iter())
+            } else {
+                dfb.input_from_iter(Vec::<u32>::new().into_iter())
+            }?;
+            let p = src.exchange_with_fn(|item: &u32| *item as u64)?;
+            // the subtasks of the odd items find results on the other workers only, which must
+            // arrive before the subtasks end, or the odd items are taken as of no result
+            let subtask = p.fork_subtask(|stream| {
+                stream.exchange_with_fn(|item: &u32| *item as u64 + 1)?.flat_map_with_fn(
+                    Pipeline,
+                    |item| {
+                        let results = if item % 2 == 1 { vec![item; 4] } else { vec![] };
+                        Ok(results.into_iter().map(Ok))
+                    },
+                )
+            })?;
+            p.semi_join_subtask(subtask, SemiJoinKind::NoneExists)?.sink_by(|_| {
+                move |_, r| {
+                    if let ResultSet::Data(data) = r {
+                        tx.send(data).expect("sink data failure;");
+                    }
+                }
+            })?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;");
+
+    std::mem::drop(tx);
+    let mut result = vec![];
+    while let Ok(r) = rx.recv() {
+        result.extend(r);
+    }
+    result.sort();
+    let expected = (0..400u32).filter(|i| i % 2 == 0).collect::<Vec<_>>();
+    assert_eq!(result, expected);
     pegasus::shutdown_all();
 }
@@ -0,0 +1,100 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use pegasus::api::{Map, Order, OrderDirect, Range, ResultSet, Sink};
+use pegasus::communication::Pipeline;
+use pegasus::{Configuration, JobConf, Tag};
+use std::time::{Duration, Instant};
+
+fn spilling_conf(job_id: u64) -> JobConf {
+    let mut conf = JobConf::new(job_id, "temp_test", 1);
+    // spill every 4 records into the temp space of current process;
+    conf.sort_spill_limit = 4;
+    conf.batch_size = 1;
+    conf
+}
+
+// the spilled runs are all removed once the job completes;
+#[test]
+fn spill_completed_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let (tx, rx) = crossbeam_channel::unbounded();
+    let mut guard = pegasus::run(spilling_conf(159), |worker| {
+        let tx = tx.clone();
+        worker.dataflow(|dfb| {
+            dfb.input_from_iter((0..100u32).map(|i| (i * 37) % 101))?
+                .sort(Range::Global, OrderDirect::Asc)?
+                .sink_by(move |_meta| {
+                    move |_t: &Tag, result: ResultSet<u32>| {
+                        if let ResultSet::Data(data) = result {
+                            tx.send(data).expect("send error");
+                        }
+                    }
+                })?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;")
+    .unwrap();
+    std::mem::drop(tx);
+
+    let result = rx.iter().flatten().collect::<Vec<_>>();
+    guard.join().expect("run job failure;");
+    let mut expected = (0..100u32).map(|i| (i * 37) % 101).collect::<Vec<_>>();
+    expected.sort();
+    assert_eq!(result, expected);
+    assert_eq!(pegasus::temp::live_resources(159), 0);
+    assert!(!pegasus::temp::instance().job_dir(159).exists());
+}
+
+// the job canceled while it is spilling, as its source never ends, leaves no spilled runs behind;
+#[test]
+fn spill_canceled_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let mut conf = spilling_conf(160);
+    // the source yields after each record, so the worker is checked for the cancellation
+    conf.slice_records = 1;
+    let mut guard = pegasus::run(conf, |worker| {
+        worker.dataflow(|dfb| {
+            dfb.input_from_iter(0..u64::MAX)?
+                .map_with_fn(Pipeline, |item| {
+                    std::thread::sleep(Duration::from_millis(1));
+                    Ok(item)
+                })?
+                .sort(Range::Global, OrderDirect::Asc)?
+                .sink_by(|_| |_, _| ())?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;")
+    .unwrap();
+
+    let start = Instant::now();
+    while pegasus::temp::live_resources(160) < 2 {
+        assert!(start.elapsed() < Duration::from_secs(10), "nothing spilled");
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    let dir = pegasus::temp::instance().job_dir(160);
+    assert!(dir.exists());
+    guard.cancel_execute();
+    // the worker ends once it sees the cancellation, as it is not joined by the guard any more;
+    let start = Instant::now();
+    while pegasus::temp::live_resources(160) > 0 || dir.exists() {
+        assert!(start.elapsed() < Duration::from_secs(10), "spilled runs left after canceled");
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
@@ -0,0 +1,77 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use pegasus::api::{Map, ResultSet, Sink, TimeLimit};
+use pegasus::communication::Pipeline;
+use pegasus::{Configuration, JobConf};
+use std::time::Duration;
+
+const RECORDS: u32 = 200;
+
+/// Map each record slowly in the part bounded by the time limit, and give the results of the job;
+fn run_slow_map(job_id: u64, limit: Duration, delay: Duration) -> Vec<u32> {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let mut conf = JobConf::new(job_id, "time_limit_test", 1);
+    conf.batch_size = 1;
+    // each firing maps a few records only, so the deadline is checked between them
+    conf.slice_records = 4;
+    let (tx, rx) = crossbeam_channel::unbounded();
+    let mut guard = pegasus::run(conf, |worker| {
+        let tx = tx.clone();
+        worker.dataflow(move |dfb| {
+            dfb.input_from_iter(0..RECORDS)?
+                .time_limit(limit, |start| {
+                    start.map_with_fn(Pipeline, move |item| {
+                        std::thread::sleep(delay);
+                        Ok(item)
+                    })
+                })?
+                .sink_by(|_| {
+                    move |_, result| {
+                        if let ResultSet::Data(data) = result {
+                            tx.send(data).unwrap();
+                        }
+                    }
+                })?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;")
+    .expect("job not started;");
+    guard.join().expect("run job failure;");
+    let mut result = vec![];
+    while let Ok(data) = rx.try_recv() {
+        result.extend(data);
+    }
+    result.sort();
+    result
+}
+
+#[test]
+fn time_limit_partial_result_test() {
+    let result = run_slow_map(137, Duration::from_millis(100), Duration::from_millis(5));
+    assert!(!result.is_empty(), "no result in the time limit;");
+    assert!((result.len() as u32) < RECORDS, "{} results over the time limit;", result.len());
+    // the results so far are kept in the order of the input
+    let expected = (0..result.len() as u32).collect::<Vec<_>>();
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn time_limit_not_reached_test() {
+    let result = run_slow_map(138, Duration::from_secs(60), Duration::from_micros(1));
+    assert_eq!(result, (0..RECORDS).collect::<Vec<_>>());
+}
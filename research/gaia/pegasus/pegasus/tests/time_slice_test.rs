@@ -0,0 +1,77 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use pegasus::api::{Map, ResultSet, Sink};
+use pegasus::communication::Pipeline;
+use pegasus::{Configuration, JobConf};
+use std::time::{Duration, Instant};
+
+// a batch job taking seconds and an interactive job of a few records share the only thread of
+// the executor, where the interactive one waits for the batch one at most a few of its slices
+// in each step, rather than until the batch one drains its inputs
+#[test]
+fn interactive_job_latency_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::builder().max_pool_size(1).build().unwrap()).ok();
+    let mut conf = JobConf::new(1, "batch_job", 1);
+    conf.batch_size = 16;
+    conf.slice_us = 2000;
+    let start = Instant::now();
+    let mut batch = pegasus::run(conf, |worker| {
+        worker.dataflow(|builder| {
+            builder
+                .input_from_iter(0..20_000u64)?
+                .map_with_fn(Pipeline, |item| {
+                    std::thread::sleep(Duration::from_micros(100));
+                    Ok(item + 1)
+                })?
+                .sink_by(|_| |_, _| ())?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;")
+    .expect("job not started;");
+
+    std::thread::sleep(Duration::from_millis(100));
+    let submitted = Instant::now();
+    let (tx, rx) = crossbeam_channel::unbounded();
+    let mut interactive = pegasus::run(JobConf::new(2, "interactive_job", 1), |worker| {
+        let tx = tx.clone();
+        worker.dataflow(move |builder| {
+            builder
+                .input_from_iter(0..10u32)?
+                .map_with_fn(Pipeline, |item| Ok(item + 1))?
+                .sink_by(|_| {
+                    move |_, result| {
+                        if let ResultSet::Data(data) = result {
+                            tx.send(data.len()).unwrap();
+                        }
+                    }
+                })?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;")
+    .expect("job not started;");
+    interactive.join().expect("run job failure;");
+    let latency = submitted.elapsed();
+    drop(tx);
+    assert_eq!(rx.iter().sum::<usize>(), 10);
+
+    batch.join().expect("run job failure;");
+    let total = start.elapsed();
+    assert!(total >= Duration::from_secs(2), "batch job ends in {:?}", total);
+    assert!(latency < Duration::from_millis(100), "interactive job ends in {:?}", latency);
+}
@@ -15,7 +15,7 @@
 
 use pegasus::api::function::*;
 use pegasus::api::notify::Notification;
-use pegasus::api::state::OperatorState;
+use pegasus::api::state::{OperatorState, ScopedState};
 use pegasus::api::Range::Global;
 use pegasus::api::{
     Exchange, Filter, Limit, Map, Multiplexing, NonBlockReceiver, Unary, UnaryNotify, UnaryState,
@@ -523,3 +523,75 @@ fn unary_test_state_01() {
     }
     pegasus::shutdown_all();
 }
+
+#[test]
+fn unary_test_keyed_state_top2() {
+    // a user-defined operator keeping the two largest values of each key in each scope;
+    struct Top2PerKey;
+
+    impl UnaryState<(u32, u32), (u32, Vec<u32>), ScopedState<u32, Vec<u32>>> for Top2PerKey {
+        type NotifyResult = Vec<(u32, Vec<u32>)>;
+
+        fn on_receive(
+            &self, input: &mut Input<(u32, u32)>, _: &mut Output<(u32, Vec<u32>)>,
+            state: &mut OperatorState<ScopedState<u32, Vec<u32>>>,
+        ) -> Result<(), JobExecError> {
+            input.for_each_batch(|dataset| {
+                for (key, value) in dataset.drain(..) {
+                    let top = state.get_or_insert_with(key, Vec::new);
+                    top.push(value);
+                    top.sort_by(|a, b| b.cmp(a));
+                    top.truncate(2);
+                }
+                Ok(())
+            })
+        }
+
+        fn on_notify(&self, state: ScopedState<u32, Vec<u32>>) -> Self::NotifyResult {
+            state.into_iter().collect()
+        }
+    }
+
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let conf = JobConf::new(7, "unary_test_keyed_state_top2", 2);
+
+    let (tx, rx) = crossbeam_channel::unbounded();
+    pegasus::run(conf, |worker| {
+        let index = worker.id.index;
+        let tx = tx.clone();
+        worker.dataflow(move |builder| {
+            let source = if index == 0 {
+                builder.input_from_iter(0..500u32)
+            } else {
+                builder.input_from_iter(500..1000u32)
+            }?;
+            source
+                .map_with_fn(Pipeline, |item| Ok((item % 10, item)))?
+                .exchange_with_fn(|item: &(u32, u32)| item.0 as u64)?
+                .unary_with_state("top2_per_key", Pipeline, |_| Top2PerKey)?
+                .sink_by(|_| {
+                    move |_, result| {
+                        if let ResultSet::Data(data) = result {
+                            tx.send(data).unwrap();
+                        }
+                    }
+                })?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;");
+
+    std::mem::drop(tx);
+    let mut result = HashMap::new();
+    while let Ok(data) = rx.recv() {
+        for (key, top) in data {
+            assert!(result.insert(key, top).is_none(), "key {} is output twice", key);
+        }
+    }
+    assert_eq!(result.len(), 10);
+    for (key, top) in result {
+        assert_eq!(top, vec![990 + key, 980 + key]);
+    }
+    pegasus::shutdown_all();
+}
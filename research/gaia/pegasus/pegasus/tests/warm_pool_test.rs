@@ -0,0 +1,83 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use pegasus::api::{Exchange, Map, ResultSet, Sink};
+use pegasus::communication::Pipeline;
+use pegasus::{warm_stats, Configuration, JobConf, WarmStats};
+use std::time::{Duration, Instant};
+
+fn run_job(job_id: u64, workers: u32) {
+    let conf = JobConf::new(job_id, "warm_pool_test", workers);
+    let (tx, rx) = crossbeam_channel::unbounded();
+    let mut guard = pegasus::run(conf, |worker| {
+        let tx = tx.clone();
+        worker.dataflow(move |builder| {
+            builder
+                .input_from_iter(0..100u32)?
+                .map_with_fn(Pipeline, |item| Ok(item + 1))?
+                .exchange_with_fn(|item| *item as u64)?
+                .map_with_fn(Pipeline, |item| Ok(item as u64))?
+                .sink_by(|_meta| {
+                    move |_, result| {
+                        if let ResultSet::Data(data) = result {
+                            tx.send(data.len()).unwrap();
+                        }
+                    }
+                })?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;")
+    .expect("job not started;");
+    guard.join().expect("job failure;");
+    ::std::mem::drop(tx);
+    assert_eq!(rx.iter().sum::<usize>(), 100 * workers as usize);
+}
+
+/// Wait until the workers of the jobs are all dropped, which may be a little later than the jobs
+/// are joined;
+fn await_released() -> WarmStats {
+    let start = Instant::now();
+    loop {
+        let stats = warm_stats();
+        if stats.claimed == 0 || start.elapsed() > Duration::from_secs(5) {
+            return stats;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn warm_pool_leak_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    run_job(1, 1);
+    let first = await_released();
+    assert_eq!(first.claimed, 0);
+    assert_eq!(first.idle, 1);
+    assert!(first.pooled_queues > 0);
+    // the queues of a job are all reused by the next one and returned again, with none leaked;
+    for job_id in 2..20 {
+        run_job(job_id, 1);
+        assert_eq!(await_released(), first);
+    }
+
+    for job_id in 20..30 {
+        run_job(job_id, 4);
+        let stats = await_released();
+        assert_eq!(stats.claimed, 0);
+        assert!(stats.idle <= 4, "{:?}", stats);
+    }
+}
@@ -0,0 +1,142 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use pegasus::api::function::RouteClosure;
+use pegasus::api::{Fold, LeaveScope, ResultSet, Sink, Stamped, Trigger, Tumbling, Window};
+use pegasus::communication::Aggregate;
+use pegasus::{Configuration, JobConf, Tag};
+
+const SIZE: u32 = 20;
+
+/// The records of `index` of the 2 workers in `[0, 200)` stamped by themselves, reversed in each
+/// chunk of 8, with a watermark after each chunk once all the records before it are given;
+fn out_of_order(index: u32) -> Vec<Stamped<u32>> {
+    let records: Vec<u32> = (0..200u32).filter(|i| i % 2 == index).collect();
+    let mut stamped = vec![];
+    for chunk in records.chunks(8) {
+        for item in chunk.iter().rev() {
+            stamped.push(Stamped::Record(*item as u64, *item));
+        }
+        stamped.push(Stamped::Watermark(*chunk.last().unwrap() as u64 + 1));
+    }
+    if index == 0 {
+        // late for the first window, which has fired;
+        stamped.push(Stamped::Record(4, 4));
+    }
+    stamped
+}
+
+/// All the records of `index` of the 2 workers in order, with a single watermark after them
+/// all, i.e. the watermark of the worker runs ahead of those of the other;
+fn in_order(index: u32) -> Vec<Stamped<u32>> {
+    let mut stamped: Vec<Stamped<u32>> =
+        (0..200u32).filter(|i| i % 2 == index).map(|i| Stamped::Record(i as u64, i)).collect();
+    stamped.push(Stamped::Watermark(200));
+    stamped
+}
+
+fn collect_windows(rx: crossbeam_channel::Receiver<Vec<u32>>) -> Vec<Vec<u32>> {
+    let mut windows: Vec<Vec<u32>> = rx
+        .iter()
+        .map(|mut records: Vec<u32>| {
+            records.sort();
+            records
+        })
+        .collect();
+    windows.sort();
+    windows
+}
+
+#[test]
+fn tumbling_window_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let conf = JobConf::new(1, "tumbling_window_test", 2);
+    let (tx, rx) = crossbeam_channel::unbounded();
+    pegasus::run(conf, |worker| {
+        let index = worker.id.index;
+        let tx = tx.clone();
+        worker.dataflow(move |dfb| {
+            dfb.input_from_iter(out_of_order(index).into_iter())?
+                .window(Tumbling { size: SIZE as u64 }, Trigger::Watermark)?
+                .fold(Vec::new(), Aggregate(0), |records: &mut Vec<u32>, item| records.push(item))?
+                .leave_scope()?
+                .sink_by(move |_meta| {
+                    move |_t: &Tag, result: ResultSet<Vec<u32>>| {
+                        if let ResultSet::Data(data) = result {
+                            for records in data {
+                                tx.send(records).expect("send error");
+                            }
+                        }
+                    }
+                })?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;")
+    .expect("job not started;")
+    .join()
+    .expect("run job failure;");
+    std::mem::drop(tx);
+    let windows = collect_windows(rx);
+    // each window once, with exactly its records, and without the late one;
+    let expected: Vec<Vec<u32>> =
+        (0..200 / SIZE).map(|w| (w * SIZE..(w + 1) * SIZE).collect()).collect();
+    assert_eq!(windows, expected);
+}
+
+#[test]
+fn exchanged_window_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    let conf = JobConf::new(2, "exchanged_window_test", 2);
+    let (tx, rx) = crossbeam_channel::unbounded();
+    pegasus::run(conf, |worker| {
+        let index = worker.id.index;
+        let tx = tx.clone();
+        worker.dataflow(move |dfb| {
+            let stamped = if index == 0 { in_order(0) } else { out_of_order(1) };
+            dfb.input_from_iter(stamped.into_iter())?
+                .window_by(
+                    RouteClosure::new(|_: &u32| 0),
+                    Tumbling { size: SIZE as u64 },
+                    Trigger::Watermark,
+                )?
+                .fold(Vec::new(), Aggregate(0), |records: &mut Vec<u32>, item| records.push(item))?
+                .leave_scope()?
+                .sink_by(move |_meta| {
+                    move |_t: &Tag, result: ResultSet<Vec<u32>>| {
+                        if let ResultSet::Data(data) = result {
+                            for records in data {
+                                tx.send(records).expect("send error");
+                            }
+                        }
+                    }
+                })?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;")
+    .expect("job not started;")
+    .join()
+    .expect("run job failure;");
+    std::mem::drop(tx);
+    let windows = collect_windows(rx);
+    // the watermark of worker 0 runs ahead, yet no window fires before the records of worker 1,
+    // so each window has the records of both the workers;
+    let expected: Vec<Vec<u32>> =
+        (0..200 / SIZE).map(|w| (w * SIZE..(w + 1) * SIZE).collect()).collect();
+    assert_eq!(windows, expected);
+}
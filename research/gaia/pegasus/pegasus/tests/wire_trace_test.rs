@@ -0,0 +1,95 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use pegasus::api::{Count, Exchange, Map, Range, Sink, SubTask};
+use pegasus::communication::Pipeline;
+use pegasus::result::{sink_to, ResultCollector};
+use pegasus::wire_trace::Direction;
+use pegasus::{Configuration, JobConf};
+use std::collections::HashSet;
+use std::time::Duration;
+
+fn fork_join(job_id: u64, wire_trace: bool) {
+    let mut conf = JobConf::new(job_id, "wire_trace_test", 2);
+    conf.wire_trace = wire_trace;
+    let (tx, rx) = crossbeam_channel::unbounded();
+    let guard = pegasus::run(conf, |worker| {
+        let tx = tx.clone();
+        worker.dataflow(|dfb| {
+            let src = if dfb.worker_id.index == 0 {
+                dfb.input_from_iter(0..10u32)
+            } else {
+                dfb.input_from_iter(Vec::<u32>::new().into_iter())
+            }?;
+            let p = src.exchange_with_fn(|item: &u32| *item as u64)?;
+            let subtask = p.fork_subtask(|stream| {
+                stream
+                    .flat_map_with_fn(Pipeline, |item| {
+                        Ok(vec![item; item as usize + 1].into_iter().map(Ok))
+                    })?
+                    .count(Range::Local)
+            })?;
+            p.join_subtask(subtask, move |p, s| Some((*p, s)))?.sink_by(|_| sink_to(tx))?;
+            Ok(())
+        })
+    })
+    .expect("submit job failure;");
+
+    std::mem::drop(tx);
+    let mut collector = ResultCollector::new(rx).with_guard(guard);
+    let mut joined = 0;
+    while let Some(r) = collector.next_timeout(Duration::from_secs(60)) {
+        let (i, count) = r.expect("run job failure;");
+        assert_eq!(i + 1, count as u32);
+        joined += 1;
+    }
+    assert_eq!(joined, 10);
+}
+
+#[test]
+fn fork_join_trace_test() {
+    pegasus_common::logs::init_log();
+    pegasus::startup(Configuration::singleton()).ok();
+    fork_join(1, true);
+    fork_join(2, false);
+
+    let trace = pegasus::dump_job_trace(1).expect("job not traced;");
+    assert_eq!(trace.workers.keys().copied().collect::<Vec<_>>(), vec![0, 1]);
+    assert_eq!(trace.dropped, 0);
+    // each event received is sent as it is received, at or before it is received;
+    let pairs = trace.pairs();
+    assert!(!pairs.is_empty());
+    for (send, receive) in pairs.iter() {
+        assert_eq!((send.worker, send.peer), (receive.peer, receive.worker));
+        assert_eq!((send.ch, &send.tag), (receive.ch, &receive.tag));
+        assert_eq!((send.size, send.is_end), (receive.size, receive.is_end));
+        assert!(send.at <= receive.at, "{} received before {}", receive, send);
+    }
+    for record in trace.unmatched() {
+        assert_eq!(record.direction, Direction::Send, "{} never sent", record);
+    }
+    // each worker receives the end of each scope of each channel from the other worker once;
+    let ends = trace.ends_received();
+    assert!(!ends.is_empty());
+    for ((worker, ch, tag), sources) in ends.iter() {
+        assert_eq!(sources, &vec![1 - *worker], "ends of {:?} on channel {}", tag, ch);
+    }
+    let roots: HashSet<u32> =
+        ends.keys().filter(|(_, _, tag)| tag.is_root()).map(|(worker, _, _)| *worker).collect();
+    assert_eq!(roots.len(), 2);
+
+    assert!(pegasus::dump_job_trace(2).is_none());
+    pegasus::shutdown_all();
+}
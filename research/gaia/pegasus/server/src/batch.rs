@@ -0,0 +1,148 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The queries submitted at once as a batch, see `Service::accept_batch`, each compiled and run as
+//! a job of its own, while their responses are multiplexed over one output of the batch, framed
+//! by the indexes of the queries in the batch. The admission of the batch is sent first, then the
+//! responses of the queries as they are sent, interleaved, each followed by the end of its query
+//! once the query has sent all its responses, i.e. once all clones of its output are dropped.
+
+use crate::generated::protocol as pb;
+use crate::service::Output;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Where the frames of a batch are sent, e.g. the stream of the rpc;
+pub trait BatchOutput: Send + Sync + 'static {
+    fn send(&self, res: pb::BatchResponse);
+}
+
+/// The jobs of the queries of a batch, by which they are cancelled all at once, see
+/// `Service::cancel_batch`, e.g. as the client of the batch goes away;
+#[derive(Clone)]
+pub struct BatchJobs {
+    job_ids: Arc<Vec<u64>>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl BatchJobs {
+    pub fn new(batch: &pb::BatchRequest) -> Self {
+        let job_ids = batch.requests.iter().filter_map(|req| req.conf.as_ref());
+        BatchJobs {
+            job_ids: Arc::new(job_ids.map(|conf| conf.job_id).collect()),
+            cancelled: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn job_ids(&self) -> &[u64] {
+        &self.job_ids
+    }
+
+    /// Tell the queries not submitted yet never to be submitted, which ends them as cancelled;
+    pub(crate) fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+/// Check if the batch is well-formed as a whole, i.e. each query has its config, of a job id not
+/// taken by any other query of the batch, or by a job running, told by `running`;
+pub(crate) fn check_batch<F: Fn(u64) -> bool>(
+    batch: &pb::BatchRequest, running: F,
+) -> Result<(), String> {
+    let mut job_ids = HashSet::new();
+    for (index, req) in batch.requests.iter().enumerate() {
+        let job_id = match req.conf.as_ref() {
+            Some(conf) => conf.job_id,
+            None => return Err(format!("job conf of query {} not specified", index)),
+        };
+        if !job_ids.insert(job_id) || running(job_id) {
+            return Err(format!("job id {} of query {} is taken", job_id, index));
+        }
+    }
+    Ok(())
+}
+
+/// Frame the admission of the batch;
+pub(crate) fn admission_frame(
+    admitted: bool, invalid: Vec<u32>, err: Option<pb::JobError>,
+) -> pb::BatchResponse {
+    let admission = pb::BatchAdmission { admitted, invalid, err };
+    pb::BatchResponse { index: 0, frame: Some(pb::batch_response::Frame::Admission(admission)) }
+}
+
+struct QueryFrame<B: BatchOutput> {
+    index: u32,
+    output: Arc<B>,
+    // the status of the query by the errors sent, if any;
+    failed: Mutex<Option<pb::JobStatus>>,
+    jobs: BatchJobs,
+}
+
+impl<B: BatchOutput> Drop for QueryFrame<B> {
+    fn drop(&mut self) {
+        let failed = self.failed.lock().ok().and_then(|failed| *failed);
+        let status = match failed {
+            Some(status) => status,
+            None if self.jobs.is_cancelled() => pb::JobStatus::Cancelled,
+            None => pb::JobStatus::Completed,
+        };
+        let end = pb::QueryEnd { status: status as i32 };
+        let frame = Some(pb::batch_response::Frame::End(end));
+        self.output.send(pb::BatchResponse { index: self.index, frame });
+    }
+}
+
+/// The output of a query of the batch, which frames each response of the query by its index, and
+/// sends the end of the query once all its clones are dropped;
+pub struct QueryOutput<B: BatchOutput> {
+    frame: Arc<QueryFrame<B>>,
+}
+
+impl<B: BatchOutput> QueryOutput<B> {
+    pub(crate) fn new(index: u32, output: Arc<B>, jobs: &BatchJobs) -> Self {
+        let frame = QueryFrame { index, output, failed: Mutex::new(None), jobs: jobs.clone() };
+        QueryOutput { frame: Arc::new(frame) }
+    }
+}
+
+impl<B: BatchOutput> Clone for QueryOutput<B> {
+    fn clone(&self) -> Self {
+        QueryOutput { frame: self.frame.clone() }
+    }
+}
+
+impl<B: BatchOutput> Output for QueryOutput<B> {
+    fn send(&self, res: pb::JobResponse) {
+        if let Some(pb::job_response::Result::Err(err)) = res.result.as_ref() {
+            let status = match pb::QueryErrorKind::from_i32(err.kind) {
+                Some(pb::QueryErrorKind::Cancelled) => pb::JobStatus::Cancelled,
+                Some(pb::QueryErrorKind::Timeout) => pb::JobStatus::TimedOut,
+                _ => pb::JobStatus::Failed,
+            };
+            if let Ok(mut failed) = self.frame.failed.lock() {
+                failed.get_or_insert(status);
+            }
+        }
+        let frame = Some(pb::batch_response::Frame::Response(res));
+        self.frame.output.send(pb::BatchResponse { index: self.frame.index, frame });
+    }
+
+    fn close(&self) {}
+}
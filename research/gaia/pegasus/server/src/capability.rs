@@ -0,0 +1,280 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The versions of the plans and the features they require. The plans are decoded leniently as
+//! documented by `JobRequest` in the proto, where the unknown fields are skipped, so a plan is
+//! checked before it is submitted, to reject the features it requires but unsupported by the
+//! engine by name, instead of running a plan the engine doesn't fully understand.
+
+use crate::factory::PlanError;
+use crate::generated::protocol as pb;
+
+/// The latest version of the plans the engine is aware of; The plans of version 0, i.e. of the
+/// clients before the plans are versioned, are the same as version 1.
+pub const PLAN_VERSION: u32 = 1;
+
+/// The features supported by the engine, which may be required by the plans besides the
+/// operators of version 1
+pub const FEATURES: &[&str] = &[
+    "iterate.post_check",
+    "iterate.emit",
+    "subtask.semi_join",
+    "subtask.optional",
+    "subtask.join_first",
+    "coalesce",
+    "time_limit",
+    "fold.mean",
+    "fold.approx",
+    "dedup.approx",
+    "sink.scope_end",
+    "job.worker_hint",
+    "job.overflow",
+    "job.skew_factor",
+    "job.session",
+    "job.flush_interval",
+    "job.profile",
+    "job.paging",
+    "job.latency_mode",
+    "job.depends_on",
+];
+
+/// Get the version and the features supported by the engine, with the `extra` features of the
+/// compiler, see `JobCompiler::features`
+pub fn capabilities(extra: &[String]) -> pb::CapabilitiesResponse {
+    let mut features: Vec<String> = FEATURES.iter().map(|f| f.to_string()).collect();
+    features.extend(extra.iter().cloned());
+    pb::CapabilitiesResponse { plan_version: PLAN_VERSION, features }
+}
+
+/// Check if the engine supports all features the request requires, including the operators
+/// and enum values of its plan, which are unknown if the plan is of a newer version.
+pub fn check_request(req: &pb::JobRequest, extra: &[String]) -> Result<(), PlanError> {
+    let unsupported: Vec<&str> = req
+        .features
+        .iter()
+        .filter(|f| !FEATURES.contains(&f.as_str()) && !extra.contains(f))
+        .map(|f| f.as_str())
+        .collect();
+    if !unsupported.is_empty() {
+        let msg = format!(
+            "the plan of version {} requires features unsupported by the engine of version {}: {}",
+            req.plan_version,
+            PLAN_VERSION,
+            unsupported.join(", ")
+        );
+        return Err(PlanError::new(vec![], msg));
+    }
+    let checker = FeatureChecker { plan_version: req.plan_version };
+    if let Some(plan) = req.plan.as_ref() {
+        checker.check_plan(&plan.plan, &mut vec![])?;
+    }
+    if let Some(pb::sink::Sinker::Fold(fold)) = req.sink.as_ref().and_then(|s| s.sinker.as_ref()) {
+        checker.check_fold(fold, &[])?;
+    }
+    if let Some(pb::sink::Sinker::Group(group)) = req.sink.as_ref().and_then(|s| s.sinker.as_ref())
+    {
+        checker.check_enum(group.range, pb::Range::from_i32, "range", &[])?;
+    }
+    Ok(())
+}
+
+struct FeatureChecker {
+    plan_version: u32,
+}
+
+impl FeatureChecker {
+    fn check_plan(
+        &self, plan: &[pb::OperatorDef], index: &mut Vec<usize>,
+    ) -> Result<(), PlanError> {
+        for (i, op) in plan.iter().enumerate() {
+            index.push(i);
+            self.check_op(op, index)?;
+            index.pop();
+        }
+        Ok(())
+    }
+
+    fn check_op(&self, op: &pb::OperatorDef, index: &mut Vec<usize>) -> Result<(), PlanError> {
+        use pb::operator_def::OpKind;
+        match op.op_kind.as_ref() {
+            // the operators unknown to prost are skipped, which leaves nothing
+            None => Err(self.unsupported(index, "unknown operator")),
+            Some(OpKind::Limit(limit)) => {
+                self.check_enum(limit.range, pb::Range::from_i32, "range", index)
+            }
+            Some(OpKind::Order(order)) => {
+                self.check_enum(order.range, pb::Range::from_i32, "range", index)
+            }
+            Some(OpKind::Dedup(dedup)) => {
+                self.check_enum(dedup.range, pb::Range::from_i32, "range", index)
+            }
+            Some(OpKind::Group(group)) => {
+                self.check_enum(group.range, pb::Range::from_i32, "range", index)
+            }
+            Some(OpKind::Fold(fold)) => self.check_fold(fold, index),
+            Some(OpKind::Iterate(iter)) => {
+                self.check_enum(iter.emit, pb::iteration::EmitKind::from_i32, "emit kind", index)?;
+                match iter.body.as_ref() {
+                    Some(body) => self.check_plan(&body.plan, index),
+                    None => Ok(()),
+                }
+            }
+            Some(OpKind::Subtask(subtask)) => {
+                let semi_join = pb::subtask::SemiJoin::from_i32;
+                self.check_enum(subtask.semi_join, semi_join, "semi join", index)?;
+                match subtask.task.as_ref() {
+                    Some(task) => self.check_plan(&task.plan, index),
+                    None => Ok(()),
+                }
+            }
+            Some(OpKind::TimeLimit(time_limit)) => match time_limit.task.as_ref() {
+                Some(task) => self.check_plan(&task.plan, index),
+                None => Ok(()),
+            },
+            Some(OpKind::Union(union)) => self.check_branches(&union.branches, index),
+            Some(OpKind::Coalesce(coalesce)) => self.check_branches(&coalesce.branches, index),
+            Some(OpKind::Shuffle(_))
+            | Some(OpKind::Map(_))
+            | Some(OpKind::FlatMap(_))
+            | Some(OpKind::Filter(_)) => Ok(()),
+        }
+    }
+
+    fn check_branches(
+        &self, branches: &[pb::TaskPlan], index: &mut Vec<usize>,
+    ) -> Result<(), PlanError> {
+        for (i, branch) in branches.iter().enumerate() {
+            index.push(i);
+            self.check_plan(&branch.plan, index)?;
+            index.pop();
+        }
+        Ok(())
+    }
+
+    fn check_fold(&self, fold: &pb::Fold, index: &[usize]) -> Result<(), PlanError> {
+        self.check_enum(fold.range, pb::Range::from_i32, "range", index)?;
+        self.check_enum(fold.accum, pb::AccumKind::from_i32, "accum kind", index)
+    }
+
+    fn check_enum<T>(
+        &self, value: i32, from_i32: fn(i32) -> Option<T>, name: &str, index: &[usize],
+    ) -> Result<(), PlanError> {
+        match from_i32(value) {
+            Some(_) => Ok(()),
+            None => Err(self.unsupported(index, &format!("unknown {} {}", name, value))),
+        }
+    }
+
+    fn unsupported(&self, index: &[usize], what: &str) -> PlanError {
+        let msg = format!(
+            "{} of the plan of version {}, unsupported by the engine of version {}",
+            what, self.plan_version, PLAN_VERSION
+        );
+        PlanError::new(index.to_vec(), msg)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use prost::Message;
+
+    fn map_op() -> pb::OperatorDef {
+        pb::OperatorDef {
+            ch: None,
+            op_kind: Some(pb::operator_def::OpKind::Map(pb::Map { resource: vec![] })),
+            ..Default::default()
+        }
+    }
+
+    fn fold_op(accum: i32) -> pb::OperatorDef {
+        let fold = pb::Fold { range: pb::Range::Global as i32, accum, ..Default::default() };
+        let op_kind = Some(pb::operator_def::OpKind::Fold(fold));
+        pb::OperatorDef { op_kind, ..Default::default() }
+    }
+
+    fn request(plan: Vec<pb::OperatorDef>) -> pb::JobRequest {
+        pb::JobRequest {
+            conf: None,
+            source: Some(pb::Source { resource: vec![] }),
+            plan: Some(pb::TaskPlan { plan }),
+            sink: None,
+            plan_version: PLAN_VERSION,
+            features: vec![],
+            graph: String::new(),
+        }
+    }
+
+    // g.V().map().window(5) of plan version 2, where the window operator of code 14 is unknown
+    fn future_request(features: Vec<&str>) -> pb::JobRequest {
+        let mut req = request(vec![map_op()]);
+        req.plan_version = 2;
+        req.features = features.into_iter().map(|f| f.to_owned()).collect();
+        let mut bytes = vec![];
+        req.encode(&mut bytes).unwrap();
+        // the plan field again, with an operator of the window of size 5, which is merged into
+        // the plan as its second operator
+        bytes.extend_from_slice(&[0x1a, 6, 0x0a, 4, 0x72, 2, 0x08, 0x05]);
+        pb::JobRequest::decode(bytes.as_slice()).unwrap()
+    }
+
+    #[test]
+    fn supported_plan_test() {
+        let req = request(vec![map_op(), fold_op(pb::AccumKind::Mean as i32)]);
+        assert!(check_request(&req, &[]).is_ok());
+        // the plans before versioning
+        let mut req = request(vec![map_op()]);
+        req.plan_version = 0;
+        assert!(check_request(&req, &[]).is_ok());
+        req.features = vec!["iterate.emit".to_owned(), "gremlin.subgraph".to_owned()];
+        assert!(check_request(&req, &["gremlin.subgraph".to_owned()]).is_ok());
+    }
+
+    #[test]
+    fn future_plan_test() {
+        let req = future_request(vec!["op.window"]);
+        assert_eq!(req.plan.as_ref().unwrap().plan.len(), 2);
+        let err = check_request(&req, &[]).unwrap_err();
+        assert!(err.op_index.is_empty());
+        assert!(err.msg.contains("unsupported by the engine of version 1: op.window"), "{}", err);
+        // the undeclared operator is still rejected, instead of being skipped
+        let err = check_request(&future_request(vec![]), &[]).unwrap_err();
+        assert_eq!(err.op_index, vec![1]);
+        assert!(err.msg.contains("unknown operator of the plan of version 2"), "{}", err);
+    }
+
+    #[test]
+    fn unknown_enum_test() {
+        let union = pb::Union {
+            branches: vec![
+                pb::TaskPlan { plan: vec![map_op()] },
+                pb::TaskPlan { plan: vec![map_op(), fold_op(10)] },
+            ],
+        };
+        let op_kind = Some(pb::operator_def::OpKind::Union(union));
+        let union = pb::OperatorDef { op_kind, ..Default::default() };
+        let err = check_request(&request(vec![map_op(), union]), &[]).unwrap_err();
+        assert_eq!(err.op_index, vec![1, 1, 1]);
+        assert!(err.msg.contains("unknown accum kind 10"), "{}", err);
+    }
+
+    #[test]
+    fn capabilities_test() {
+        let caps = capabilities(&["gremlin.subgraph".to_owned()]);
+        assert_eq!(caps.plan_version, PLAN_VERSION);
+        assert!(caps.features.iter().any(|f| f == "iterate.emit"));
+        assert_eq!(caps.features.last().unwrap(), "gremlin.subgraph");
+    }
+}
@@ -13,8 +13,9 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
-use pegasus::{Configuration, StartupError};
+use pegasus::{Configuration, SlowQueryConfig, StartupError};
 use pegasus_network::config::{NetworkConfig, PeerConfig};
+use pegasus_network::WireCodec;
 use serde::Deserialize;
 use std::fmt::Debug;
 use std::path::Path;
@@ -38,6 +39,7 @@ impl HostsConfig {
 #[derive(Debug, Deserialize)]
 pub struct CommonConfig {
     pub max_pool_size: Option<u32>,
+    pub io_pool_size: Option<u32>,
     pub nonblocking: Option<bool>,
     pub read_timeout_ms: Option<u32>,
     pub write_timeout_ms: Option<u32>,
@@ -45,6 +47,12 @@ pub struct CommonConfig {
     pub no_delay: Option<bool>,
     pub send_buffer: Option<u32>,
     pub heartbeat_sec: Option<u32>,
+    pub codec: Option<WireCodec>,
+    pub metrics_addr: Option<String>,
+    pub slow_query: Option<SlowQueryConfig>,
+    pub max_jobs: Option<u32>,
+    pub readiness: Option<Vec<String>>,
+    pub temp_dir: Option<String>,
 }
 
 impl CommonConfig {
@@ -62,9 +70,13 @@ pub fn combine_config(
     server_id: u64, host_config: Option<HostsConfig>, common_config: Option<CommonConfig>,
 ) -> Option<Configuration> {
     if let Some(host_config) = host_config {
-        let local_host = &host_config.peers[server_id as usize];
-        let ip = local_host.ip.to_owned();
-        let port = local_host.port;
+        // the server missing in hosts is reported by the validation at startup;
+        let (ip, port) = host_config
+            .peers
+            .iter()
+            .find(|peer| peer.server_id == server_id)
+            .map(|peer| (peer.ip.to_owned(), peer.port))
+            .unwrap_or_else(|| (String::new(), 0));
         let config = if let Some(common_config) = common_config {
             let network_config = NetworkConfig {
                 server_id,
@@ -77,21 +89,46 @@ pub fn combine_config(
                 no_delay: common_config.no_delay,
                 send_buffer: common_config.send_buffer,
                 heartbeat_sec: common_config.heartbeat_sec,
+                codec: common_config.codec,
                 peers: Some(host_config.peers),
             };
             Configuration {
                 network: Some(network_config),
                 max_pool_size: common_config.max_pool_size,
+                io_pool_size: common_config.io_pool_size,
+                metrics_addr: common_config.metrics_addr,
+                slow_query: common_config.slow_query,
+                max_jobs: common_config.max_jobs,
+                readiness: common_config.readiness,
+                temp_dir: common_config.temp_dir,
             }
         } else {
             let network_config =
                 NetworkConfig::with_default_config(server_id, ip, port, host_config.peers);
-            Configuration { network: Some(network_config), max_pool_size: None }
+            Configuration {
+                network: Some(network_config),
+                max_pool_size: None,
+                io_pool_size: None,
+                metrics_addr: None,
+                slow_query: None,
+                max_jobs: None,
+                readiness: None,
+                temp_dir: None,
+            }
         };
         Some(config)
     } else {
         if let Some(common_config) = common_config {
-            Some(Configuration { network: None, max_pool_size: common_config.max_pool_size })
+            Some(Configuration {
+                network: None,
+                max_pool_size: common_config.max_pool_size,
+                io_pool_size: common_config.io_pool_size,
+                metrics_addr: common_config.metrics_addr,
+                slow_query: common_config.slow_query,
+                max_jobs: common_config.max_jobs,
+                readiness: common_config.readiness,
+                temp_dir: common_config.temp_dir,
+            })
         } else {
             None
         }
@@ -0,0 +1,341 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The jobs depending on the outputs of others, e.g. to run a query over the results of another
+//! query kept on the servers, without the results going through the client. A job keeping its
+//! output, see `JobConfig.keep_output`, adds the results of each of its workers into the outputs
+//! kept by current server, and a job depending on it, see `JobConfig.depends_on`, is queued until
+//! it completes, and then reads its output as the source instead of `Source.resource`:
+//! - partitioned, the output stays on the server it is kept by, where the results of each worker
+//!   of the job depended on are read by one of the workers of the dependent job on the server;
+//! - broadcast, each worker of the dependent job reads all the output kept by its server;
+//!
+//! The dependent job fails by `QueryError::Dependency` if the job it depends on fails, or doesn't
+//! complete in time, where the job may be submitted after the jobs depending on it. A job closing
+//! a cycle of the jobs queued, e.g. depending on itself, is rejected instead of waiting forever.
+//!
+//! The output of a job is kept for `OUTPUT_TTL` since the job ends, which is read by any number of
+//! the jobs depending on it meanwhile.
+
+use crate::error::QueryError;
+use crate::generated::protocol as pb;
+use pegasus::WorkerId;
+use std::any::Any;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// How long the output of a job is kept since the job ends;
+pub const OUTPUT_TTL: Duration = Duration::from_secs(600);
+/// How long a job is queued for the job it depends on, if not given;
+pub const DEFAULT_DEPENDENCY_WAIT: Duration = Duration::from_secs(60);
+
+lazy_static! {
+    static ref OUTPUTS: JobOutputs = JobOutputs::default();
+}
+
+/// The outputs kept by current server, shared by all its services;
+pub fn job_outputs() -> &'static JobOutputs {
+    &OUTPUTS
+}
+
+/// The job a job depends on, and how its output is read, see `JobConfig.depends_on`;
+#[derive(Clone, Debug, PartialEq)]
+pub struct Dependency {
+    pub job_id: u64,
+    pub broadcast: bool,
+    pub wait: Duration,
+}
+
+impl Dependency {
+    /// The dependency of the job, or `None` if it depends on no job;
+    pub fn of(conf: &pb::JobConfig) -> Option<Self> {
+        if conf.depends_on == 0 {
+            return None;
+        }
+        let wait = if conf.dependency_wait_ms == 0 {
+            DEFAULT_DEPENDENCY_WAIT
+        } else {
+            Duration::from_millis(conf.dependency_wait_ms)
+        };
+        let broadcast = conf.dependency_input == pb::DependencyInput::Broadcast as i32;
+        Some(Dependency { job_id: conf.depends_on, broadcast, wait })
+    }
+}
+
+/// The output of a job read by a job depending on it, of the results of each worker of the job
+/// on current server;
+pub struct JobOutput<D> {
+    parts: Vec<(u32, Vec<D>)>,
+    broadcast: bool,
+}
+
+impl<D: Clone> JobOutput<D> {
+    /// The number of the results of the output;
+    pub fn len(&self) -> usize {
+        self.parts.iter().map(|(_, part)| part.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The results read by the worker of the dependent job, which are all the results if
+    /// broadcast, or else those of the workers of the job depended on whose positions among the
+    /// workers on current server are of the same position modulo the local peers of the worker;
+    pub fn read(&self, worker: &WorkerId) -> Vec<D> {
+        let local_peers = worker.local_peers.max(1) as usize;
+        let position = worker.index as usize % local_peers;
+        self.parts
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| self.broadcast || i % local_peers == position)
+            .flat_map(|(_, (_, part))| part.iter().cloned())
+            .collect()
+    }
+}
+
+enum JobState {
+    Running,
+    Succeeded,
+    Failed(String),
+}
+
+struct KeptOutput {
+    state: JobState,
+    // the results of each worker of the job, as a `Vec` of the data of the job;
+    parts: BTreeMap<u32, Box<dyn Any + Send>>,
+    // the output is released at, once the job ends;
+    expires_at: Option<Instant>,
+}
+
+#[derive(Default)]
+struct Registry {
+    outputs: HashMap<u64, KeptOutput>,
+    // the job each of the queued jobs depends on;
+    queued: HashMap<u64, u64>,
+}
+
+/// The outputs of the jobs kept by current server, and the jobs queued for them, see the module
+/// docs;
+#[derive(Default)]
+pub struct JobOutputs {
+    registry: Mutex<Registry>,
+    ended: Condvar,
+}
+
+impl JobOutputs {
+    /// Keep the output of the job, which is running, replacing that of the former job of the same
+    /// id if any;
+    pub fn keep(&self, job_id: u64) {
+        let output =
+            KeptOutput { state: JobState::Running, parts: BTreeMap::new(), expires_at: None };
+        self.sweep().outputs.insert(job_id, output);
+    }
+
+    /// Add the results of the worker into the output of the job, if it is kept and running;
+    pub fn push<D: Send + 'static>(&self, job_id: u64, worker: u32, data: Vec<D>) {
+        let mut registry = self.registry.lock().expect("lock poisoned");
+        if let Some(output) = registry.outputs.get_mut(&job_id) {
+            if let JobState::Running = output.state {
+                let part = output.parts.entry(worker).or_insert_with(|| Box::new(Vec::<D>::new()));
+                if let Some(part) = part.downcast_mut::<Vec<D>>() {
+                    part.extend(data);
+                }
+            }
+        }
+    }
+
+    /// The job ends, successfully or by the error, which wakes up the jobs queued for it; The
+    /// results of a failed job are released at once;
+    pub fn end(&self, job_id: u64, error: Option<String>) {
+        let mut registry = self.registry.lock().expect("lock poisoned");
+        if let Some(output) = registry.outputs.get_mut(&job_id) {
+            if let JobState::Running = output.state {
+                output.state = match error {
+                    Some(msg) => {
+                        output.parts.clear();
+                        JobState::Failed(msg)
+                    }
+                    None => JobState::Succeeded,
+                };
+                output.expires_at = Some(Instant::now() + OUTPUT_TTL);
+            }
+        }
+        drop(registry);
+        self.ended.notify_all();
+    }
+
+    /// Queue the job for the job it depends on, which fails if the job closes a cycle of the jobs
+    /// queued, e.g. depending on itself;
+    pub fn depend(&self, job_id: u64, depends_on: u64) -> Result<(), QueryError> {
+        let mut registry = self.sweep();
+        // the jobs queued never form a cycle, so the chain from any job ends
+        let mut chain = vec![job_id];
+        let mut next = Some(depends_on);
+        while let Some(upstream) = next {
+            chain.push(upstream);
+            if upstream == job_id {
+                let chain: Vec<String> = chain.iter().map(|id| id.to_string()).collect();
+                let msg = format!("cyclic dependency of the jobs {}", chain.join(" -> "));
+                return Err(QueryError::Validation { op_index: vec![], msg, cause: None });
+            }
+            next = registry.queued.get(&upstream).copied();
+        }
+        registry.queued.insert(job_id, depends_on);
+        Ok(())
+    }
+
+    /// Wait for the job the queued job depends on to end, at most `dependency.wait`, and read its
+    /// output, or fail by `QueryError::Dependency` if the job fails, or doesn't complete in time;
+    /// The job is no longer queued after;
+    pub fn wait<D: Clone + 'static>(
+        &self, job_id: u64, dependency: &Dependency,
+    ) -> Result<JobOutput<D>, QueryError> {
+        let depends_on = dependency.job_id;
+        let deadline = Instant::now() + dependency.wait;
+        let mut registry = self.registry.lock().expect("lock poisoned");
+        let result = loop {
+            match registry.outputs.get(&depends_on).map(|output| (&output.state, output)) {
+                Some((JobState::Succeeded, output)) => {
+                    break read_output(output, dependency.broadcast);
+                }
+                Some((JobState::Failed(msg), _)) => break Err(msg.clone()),
+                _ => (),
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                break Err(format!(
+                    "not completed within {} ms, or its output is not kept",
+                    dependency.wait.as_millis()
+                ));
+            }
+            registry = self.ended.wait_timeout(registry, deadline - now).expect("lock poisoned").0;
+        };
+        registry.queued.remove(&job_id);
+        result.map_err(|msg| QueryError::Dependency { job_id: depends_on, msg })
+    }
+
+    /// Remove the job from the queue without waiting, e.g. as it fails to be queued;
+    pub fn dequeue(&self, job_id: u64) {
+        self.registry.lock().expect("lock poisoned").queued.remove(&job_id);
+    }
+
+    /// Release the output of the job, and return if it is kept;
+    pub fn release(&self, job_id: u64) -> bool {
+        self.sweep().outputs.remove(&job_id).is_some()
+    }
+
+    fn sweep(&self) -> MutexGuard<'_, Registry> {
+        let mut registry = self.registry.lock().expect("lock poisoned");
+        let now = Instant::now();
+        registry.outputs.retain(|job_id, output| match output.expires_at {
+            Some(expires_at) if expires_at <= now => {
+                info!("output of job {} expired;", job_id);
+                false
+            }
+            _ => true,
+        });
+        registry
+    }
+}
+
+fn read_output<D: Clone + 'static>(
+    output: &KeptOutput, broadcast: bool,
+) -> Result<JobOutput<D>, String> {
+    let mut parts = Vec::with_capacity(output.parts.len());
+    for (worker, part) in output.parts.iter() {
+        match part.downcast_ref::<Vec<D>>() {
+            Some(part) => parts.push((*worker, part.clone())),
+            None => return Err("its output is of another kind of data".to_owned()),
+        }
+    }
+    Ok(JobOutput { parts, broadcast })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn dependency(job_id: u64, broadcast: bool) -> Dependency {
+        Dependency { job_id, broadcast, wait: Duration::from_millis(500) }
+    }
+
+    #[test]
+    fn read_output_test() {
+        let outputs = JobOutputs::default();
+        outputs.keep(1);
+        outputs.push(1, 0, vec![1u64, 2]);
+        outputs.push(1, 1, vec![3u64]);
+        outputs.push(1, 0, vec![4u64]);
+        outputs.end(1, None);
+        outputs.depend(2, 1).unwrap();
+        let output = outputs.wait::<u64>(2, &dependency(1, false)).unwrap();
+        assert_eq!(output.len(), 4);
+        assert_eq!(output.read(&WorkerId::new(2, 2, 0, false)), vec![1, 2, 4]);
+        assert_eq!(output.read(&WorkerId::new(2, 2, 1, false)), vec![3]);
+        assert_eq!(output.read(&WorkerId::new(2, 1, 0, false)), vec![1, 2, 4, 3]);
+        let output = outputs.wait::<u64>(3, &dependency(1, true)).unwrap();
+        assert_eq!(output.read(&WorkerId::new(3, 2, 1, false)), vec![1, 2, 4, 3]);
+        assert!(outputs.wait::<String>(4, &dependency(1, false)).is_err());
+    }
+
+    #[test]
+    fn wait_for_job_test() {
+        let outputs = std::sync::Arc::new(JobOutputs::default());
+        outputs.depend(2, 1).unwrap();
+        let waiting = outputs.clone();
+        let dependent = std::thread::spawn(move || {
+            let dependency =
+                Dependency { job_id: 1, broadcast: false, wait: Duration::from_secs(5) };
+            waiting.wait::<u64>(2, &dependency).map(|output| output.len())
+        });
+        // the job depended on is submitted after the job depending on it
+        outputs.keep(1);
+        outputs.push(1, 0, vec![7u64]);
+        outputs.end(1, None);
+        assert_eq!(dependent.join().unwrap(), Ok(1));
+    }
+
+    #[test]
+    fn failed_dependency_test() {
+        let outputs = JobOutputs::default();
+        outputs.keep(1);
+        outputs.push(1, 0, vec![1u64]);
+        outputs.end(1, Some("internal error: oops".to_owned()));
+        // the job has failed already, which is never overwritten by the completion after
+        outputs.end(1, None);
+        outputs.depend(2, 1).unwrap();
+        let err = outputs.wait::<u64>(2, &dependency(1, false)).err().unwrap();
+        assert_eq!(err.kind(), pb::QueryErrorKind::Dependency);
+        assert_eq!(err.to_string(), "dependency failed: job 1: internal error: oops");
+
+        let err = outputs.wait::<u64>(3, &dependency(9, false)).err().unwrap();
+        assert_eq!(err.kind(), pb::QueryErrorKind::Dependency);
+    }
+
+    #[test]
+    fn cyclic_dependency_test() {
+        let outputs = JobOutputs::default();
+        let err = outputs.depend(1, 1).err().unwrap();
+        assert_eq!(err.kind(), pb::QueryErrorKind::Validation);
+        outputs.depend(1, 2).unwrap();
+        outputs.depend(2, 3).unwrap();
+        let err = outputs.depend(3, 1).err().unwrap();
+        assert_eq!(err.to_string(), "invalid plan: cyclic dependency of the jobs 3 -> 1 -> 2 -> 3");
+        outputs.dequeue(2);
+        outputs.depend(3, 1).unwrap();
+    }
+}
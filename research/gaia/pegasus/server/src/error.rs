@@ -0,0 +1,500 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The errors of the queries told to the clients, classified by what they are of, so that a
+//! client could tell an invalid query, which fails wherever it runs, from an engine that is
+//! overloaded or failed, where the query may succeed later or on other servers. The errors of
+//! the submission and the execution of the jobs are converted into `QueryError` once they leave
+//! the engine, which keeps the messages of their sources for the logs.
+
+use crate::factory::PlanError;
+use crate::generated::protocol as pb;
+use crate::service::{DRAINING_ERR_CODE, INVALID_PLAN_ERR_CODE, UNSUPPORTED_PLAN_ERR_CODE};
+use pegasus::errors::{ExecError, JobExecError, NetworkLimitExceeded, TaskPanic};
+use pegasus::{BuildJobError, JobSubmitError, SpawnJobError};
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// The limit of the jobs rejected as the server is draining;
+pub const DRAINING_LIMIT: &str = "draining";
+/// The limit of the jobs timed out, i.e. the `time_limit` of the job;
+pub const TIME_LIMIT: &str = "time_limit";
+/// The limit of the jobs sending and receiving too many bytes, i.e. the `network_byte_limit`;
+pub const NETWORK_BYTE_LIMIT: &str = "network_byte_limit";
+
+/// The source chain of an error, copied as the messages of the sources, which is kept by the
+/// `QueryError` converted from the error, as the sources of the errors of the engine are not
+/// always shareable across threads;
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ErrorCause {
+    msg: String,
+    source: Option<Box<ErrorCause>>,
+}
+
+impl ErrorCause {
+    pub fn of(err: &dyn Error) -> Self {
+        ErrorCause { msg: err.to_string(), source: err.source().map(|s| Box::new(Self::of(s))) }
+    }
+}
+
+impl fmt::Display for ErrorCause {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.msg)
+    }
+}
+
+impl Error for ErrorCause {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source.as_ref().map(|s| s.as_ref() as &(dyn Error + 'static))
+    }
+}
+
+/// The error of a query, where `op_index` follows that of `PlanError`, and `worker` is the index
+/// of the worker failed by the error, or `None` if it fails no worker, e.g. a plan rejected ahead;
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum QueryError {
+    /// the plan or a step of it fails to be decoded;
+    Parse { op_index: Vec<usize>, msg: String, cause: Option<ErrorCause> },
+    /// the plan is decoded but invalid, e.g. ill-typed, found before or while the job is built;
+    Validation { op_index: Vec<usize>, msg: String, cause: Option<ErrorCause> },
+    /// the plan requires what the engine doesn't support;
+    Unsupported { op_index: Vec<usize>, msg: String, cause: Option<ErrorCause> },
+    /// the job exceeds the limit of the name, e.g. of the edges it visits, or the server can't
+    /// take it now, e.g. of `DRAINING_LIMIT`;
+    ResourceLimit { limit: String, worker: Option<u32>, msg: String, cause: Option<ErrorCause> },
+    /// the job is cancelled, e.g. by its client;
+    Cancelled { worker: Option<u32>, reason: String },
+    /// the job runs beyond its time limit of milliseconds;
+    Timeout { limit_ms: u64, worker: Option<u32> },
+    /// the engine fails the job otherwise, e.g. a worker panics, or the network fails;
+    Internal { worker: Option<u32>, msg: String, cause: Option<ErrorCause> },
+    /// the job of the id the job depends on fails, or doesn't complete in time;
+    Dependency { job_id: u64, msg: String },
+}
+
+impl QueryError {
+    pub fn internal<S: Into<String>>(msg: S) -> Self {
+        QueryError::Internal { worker: None, msg: msg.into(), cause: None }
+    }
+
+    /// The error of the plan requiring the features the engine doesn't support, see
+    /// `capability::check_request`;
+    pub fn unsupported_plan(err: PlanError) -> Self {
+        QueryError::Unsupported { op_index: err.op_index, msg: err.msg, cause: None }
+    }
+
+    pub fn kind(&self) -> pb::QueryErrorKind {
+        match self {
+            QueryError::Parse { .. } => pb::QueryErrorKind::Parse,
+            QueryError::Validation { .. } => pb::QueryErrorKind::Validation,
+            QueryError::Unsupported { .. } => pb::QueryErrorKind::Unsupported,
+            QueryError::ResourceLimit { .. } => pb::QueryErrorKind::ResourceLimit,
+            QueryError::Cancelled { .. } => pb::QueryErrorKind::Cancelled,
+            QueryError::Timeout { .. } => pb::QueryErrorKind::Timeout,
+            QueryError::Internal { .. } => pb::QueryErrorKind::Internal,
+            QueryError::Dependency { .. } => pb::QueryErrorKind::Dependency,
+        }
+    }
+
+    /// The index of the operator the error is of, which is empty if of no operator;
+    pub fn op_index(&self) -> &[usize] {
+        match self {
+            QueryError::Parse { op_index, .. }
+            | QueryError::Validation { op_index, .. }
+            | QueryError::Unsupported { op_index, .. } => op_index.as_slice(),
+            _ => &[],
+        }
+    }
+
+    /// The index of the worker failed by the error, if any;
+    pub fn worker(&self) -> Option<u32> {
+        match self {
+            QueryError::ResourceLimit { worker, .. }
+            | QueryError::Cancelled { worker, .. }
+            | QueryError::Timeout { worker, .. }
+            | QueryError::Internal { worker, .. } => *worker,
+            _ => None,
+        }
+    }
+
+    /// Tell the worker failed by the error, unless it is told already;
+    pub fn with_worker(mut self, index: Option<u32>) -> Self {
+        match &mut self {
+            QueryError::ResourceLimit { worker, .. }
+            | QueryError::Cancelled { worker, .. }
+            | QueryError::Timeout { worker, .. }
+            | QueryError::Internal { worker, .. }
+                if worker.is_none() =>
+            {
+                *worker = index;
+            }
+            _ => (),
+        }
+        self
+    }
+
+    /// Classify the error by the `QueryError` it is or is caused by if any, or else as an
+    /// internal error;
+    pub fn classify(err: &(dyn Error + 'static)) -> Self {
+        find_query_error(err).unwrap_or_else(|| QueryError::Internal {
+            worker: None,
+            msg: err.to_string(),
+            cause: source_cause(err),
+        })
+    }
+
+    /// The error code of the `JobError` before the errors are classified, which the clients
+    /// unaware of the kinds still tell the errors by;
+    pub fn err_code(&self) -> i32 {
+        match self {
+            QueryError::Parse { .. } | QueryError::Validation { .. } => INVALID_PLAN_ERR_CODE,
+            QueryError::Unsupported { .. } => UNSUPPORTED_PLAN_ERR_CODE,
+            QueryError::ResourceLimit { limit, .. } if limit == DRAINING_LIMIT => DRAINING_ERR_CODE,
+            _ => 0,
+        }
+    }
+
+    /// The messages of the errors causing this one, the direct cause first;
+    pub fn causes(&self) -> Vec<String> {
+        let mut causes = vec![];
+        let mut next = self.source();
+        while let Some(cause) = next {
+            causes.push(cause.to_string());
+            next = cause.source();
+        }
+        causes
+    }
+
+    /// The error with the messages of its causes, as written to the logs;
+    pub fn report(&self) -> String {
+        let mut report = self.to_string();
+        for cause in self.causes() {
+            report.push_str("\n  caused by: ");
+            report.push_str(&cause);
+        }
+        report
+    }
+
+    pub fn to_pb(&self) -> pb::JobError {
+        let limit = match self {
+            QueryError::ResourceLimit { limit, .. } => limit.clone(),
+            QueryError::Timeout { .. } => TIME_LIMIT.to_owned(),
+            _ => String::new(),
+        };
+        pb::JobError {
+            err_code: self.err_code(),
+            err_msg: self.to_string(),
+            kind: self.kind() as i32,
+            op_index: self.op_index().iter().map(|i| *i as u32).collect(),
+            limit,
+            worker: self.worker().map(|w| w as i32).unwrap_or(-1),
+            causes: self.causes(),
+        }
+    }
+}
+
+fn write_op_index(f: &mut fmt::Formatter<'_>, op_index: &[usize]) -> fmt::Result {
+    if !op_index.is_empty() {
+        let index: Vec<String> = op_index.iter().map(|i| i.to_string()).collect();
+        write!(f, " at operator {}", index.join("."))?;
+    }
+    Ok(())
+}
+
+fn write_worker(f: &mut fmt::Formatter<'_>, worker: &Option<u32>) -> fmt::Result {
+    if let Some(worker) = worker {
+        write!(f, " on worker {}", worker)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueryError::Parse { op_index, msg, .. } => {
+                write!(f, "parse error")?;
+                write_op_index(f, op_index)?;
+                write!(f, ": {}", msg)
+            }
+            QueryError::Validation { op_index, msg, .. } => {
+                write!(f, "invalid plan")?;
+                write_op_index(f, op_index)?;
+                write!(f, ": {}", msg)
+            }
+            QueryError::Unsupported { op_index, msg, .. } => {
+                write!(f, "unsupported")?;
+                write_op_index(f, op_index)?;
+                write!(f, ": {}", msg)
+            }
+            QueryError::ResourceLimit { limit, worker, msg, .. } => {
+                write!(f, "{} limit exceeded", limit)?;
+                write_worker(f, worker)?;
+                write!(f, ": {}", msg)
+            }
+            QueryError::Cancelled { worker, reason } => {
+                write!(f, "cancelled")?;
+                write_worker(f, worker)?;
+                write!(f, ": {}", reason)
+            }
+            QueryError::Timeout { limit_ms, worker } => {
+                write!(f, "timed out")?;
+                write_worker(f, worker)?;
+                write!(f, ": beyond the time limit of {} ms", limit_ms)
+            }
+            QueryError::Internal { worker, msg, .. } => {
+                write!(f, "internal error")?;
+                write_worker(f, worker)?;
+                write!(f, ": {}", msg)
+            }
+            QueryError::Dependency { job_id, msg } => {
+                write!(f, "dependency failed: job {}: {}", job_id, msg)
+            }
+        }
+    }
+}
+
+impl Error for QueryError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        let cause = match self {
+            QueryError::Parse { cause, .. }
+            | QueryError::Validation { cause, .. }
+            | QueryError::Unsupported { cause, .. }
+            | QueryError::ResourceLimit { cause, .. }
+            | QueryError::Internal { cause, .. } => cause.as_ref(),
+            _ => None,
+        };
+        cause.map(|c| c as &(dyn Error + 'static))
+    }
+}
+
+/// Find the `QueryError` the error is or is caused by, e.g. raised by a function of the job and
+/// wrapped by the engine, including those carried by the `io::Error`s;
+fn find_query_error(err: &(dyn Error + 'static)) -> Option<QueryError> {
+    let mut next = Some(err);
+    while let Some(err) = next {
+        if let Some(err) = err.downcast_ref::<QueryError>() {
+            return Some(err.clone());
+        }
+        if let Some(err) = err.downcast_ref::<NetworkLimitExceeded>() {
+            return Some(QueryError::ResourceLimit {
+                limit: NETWORK_BYTE_LIMIT.to_owned(),
+                worker: None,
+                msg: err.to_string(),
+                cause: None,
+            });
+        }
+        if let Some(inner) = err.downcast_ref::<io::Error>().and_then(|e| e.get_ref()) {
+            if let Some(err) = find_query_error(inner) {
+                return Some(err);
+            }
+        }
+        next = err.source();
+    }
+    None
+}
+
+fn source_cause(err: &dyn Error) -> Option<ErrorCause> {
+    err.source().map(ErrorCause::of)
+}
+
+impl From<PlanError> for QueryError {
+    fn from(err: PlanError) -> Self {
+        QueryError::Validation { op_index: err.op_index, msg: err.msg, cause: None }
+    }
+}
+
+impl From<BuildJobError> for QueryError {
+    fn from(err: BuildJobError) -> Self {
+        if let Some(err) = find_query_error(&err) {
+            return err;
+        }
+        match &err {
+            BuildJobError::Unsupported(msg) => {
+                QueryError::Unsupported { op_index: vec![], msg: msg.clone(), cause: None }
+            }
+            BuildJobError::UserError(e) => QueryError::Validation {
+                op_index: vec![],
+                msg: e.to_string(),
+                cause: source_cause(&**e),
+            },
+            BuildJobError::ServerError(e) => {
+                QueryError::Internal { worker: None, msg: e.to_string(), cause: source_cause(&**e) }
+            }
+        }
+    }
+}
+
+impl From<SpawnJobError> for QueryError {
+    fn from(err: SpawnJobError) -> Self {
+        QueryError::internal(err.to_string())
+    }
+}
+
+impl From<JobSubmitError> for QueryError {
+    fn from(err: JobSubmitError) -> Self {
+        match err {
+            JobSubmitError::Build(err) => QueryError::from(err),
+            JobSubmitError::Spawn(err) => QueryError::from(err),
+            JobSubmitError::Draining => QueryError::ResourceLimit {
+                limit: DRAINING_LIMIT.to_owned(),
+                worker: None,
+                msg: JobSubmitError::Draining.to_string(),
+                cause: None,
+            },
+            JobSubmitError::OverJobLimit(limit) => QueryError::ResourceLimit {
+                limit: "max_jobs".to_owned(),
+                worker: None,
+                msg: JobSubmitError::OverJobLimit(limit).to_string(),
+                cause: None,
+            },
+            JobSubmitError::Aborted(err) => QueryError::internal(format!("job aborted: {}", err)),
+        }
+    }
+}
+
+impl From<&JobExecError> for QueryError {
+    fn from(err: &JobExecError) -> Self {
+        QueryError::classify(&**err.get_cause()).with_worker(err.get_worker())
+    }
+}
+
+impl From<ExecError> for QueryError {
+    fn from(err: ExecError) -> Self {
+        match &err {
+            ExecError::Task(task_err) => {
+                let task_err = task_err.as_any_ref();
+                if let Some(err) = task_err.downcast_ref::<JobExecError>() {
+                    QueryError::from(err)
+                } else if task_err.is::<TaskPanic>() {
+                    QueryError::internal("a worker of the job panicked")
+                } else {
+                    QueryError::internal(err.to_string())
+                }
+            }
+            ExecError::Executor(msg) => QueryError::internal(msg.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn plan_error_test() {
+        let err = QueryError::from(PlanError::new(vec![2, 0], "unknown step"));
+        assert_eq!(err.kind(), pb::QueryErrorKind::Validation);
+        assert_eq!(err.to_string(), "invalid plan at operator 2.0: unknown step");
+        let res = err.to_pb();
+        assert_eq!(res.err_code, INVALID_PLAN_ERR_CODE);
+        assert_eq!(res.op_index, vec![2, 0]);
+        assert_eq!(res.worker, -1);
+
+        let err = QueryError::unsupported_plan(PlanError::new(vec![], "op.window"));
+        assert_eq!(err.kind(), pb::QueryErrorKind::Unsupported);
+        assert_eq!(err.err_code(), UNSUPPORTED_PLAN_ERR_CODE);
+    }
+
+    #[test]
+    fn build_error_test() {
+        let err = QueryError::from(BuildJobError::Unsupported("iterate.until".to_owned()));
+        assert_eq!(err.kind(), pb::QueryErrorKind::Unsupported);
+        let err = QueryError::from(BuildJobError::from("unknown accum kind 10"));
+        assert_eq!(err.kind(), pb::QueryErrorKind::Validation);
+        let io_err = io::Error::new(io::ErrorKind::ConnectionRefused, "server 2 is down");
+        let err = QueryError::from(BuildJobError::ServerError(Box::new(io_err)));
+        assert_eq!(err.kind(), pb::QueryErrorKind::Internal);
+        assert!(err.to_string().contains("server 2 is down"), "{}", err);
+
+        // classified by the compiler, which is kept through the build error
+        let parse_err = QueryError::Parse {
+            op_index: vec![1],
+            msg: "malformed predicate".to_owned(),
+            cause: Some(ErrorCause::of(&io::Error::other("bad varint"))),
+        };
+        let err = QueryError::from(BuildJobError::UserError(Box::new(parse_err.clone())));
+        assert_eq!(err, parse_err);
+        assert_eq!(err.causes(), vec!["bad varint".to_owned()]);
+        assert!(err.report().ends_with("caused by: bad varint"), "{}", err.report());
+        let err = QueryError::from(JobSubmitError::Build(BuildJobError::UserError(Box::new(
+            parse_err.clone(),
+        ))));
+        assert_eq!(err, parse_err);
+    }
+
+    #[test]
+    fn draining_error_test() {
+        let err = QueryError::from(JobSubmitError::Draining);
+        assert_eq!(err.kind(), pb::QueryErrorKind::ResourceLimit);
+        let res = err.to_pb();
+        assert_eq!(res.err_code, DRAINING_ERR_CODE);
+        assert_eq!(res.limit, DRAINING_LIMIT);
+    }
+
+    #[test]
+    fn exec_error_test() {
+        let limit = QueryError::ResourceLimit {
+            limit: "edge".to_owned(),
+            worker: None,
+            msg: "the edge limit 3 is exceeded by out()".to_owned(),
+            cause: None,
+        };
+        let err = JobExecError::from(Box::new(limit.clone()) as Box<dyn Error + Send>);
+        let classified = QueryError::from(&err);
+        assert_eq!(classified, limit);
+
+        // carried by an io error, e.g. raised by an accumulator
+        let io_err = io::Error::other(limit.clone());
+        let err = JobExecError::from(io_err);
+        assert_eq!(QueryError::from(&err), limit);
+
+        let err = JobExecError::from("division by zero".to_owned());
+        let classified = QueryError::from(&err);
+        assert_eq!(classified.kind(), pb::QueryErrorKind::Internal);
+        assert!(classified.to_string().contains("division by zero"), "{}", classified);
+
+        let err = QueryError::from(ExecError::Task(Box::new(TaskPanic)));
+        assert_eq!(err.kind(), pb::QueryErrorKind::Internal);
+        let err = QueryError::from(ExecError::Task(Box::new(JobExecError::from(Box::new(
+            limit.clone(),
+        )
+            as Box<dyn Error + Send>))));
+        assert_eq!(err, limit);
+    }
+
+    #[test]
+    fn network_limit_test() {
+        let exceeded = NetworkLimitExceeded { job_id: 1, limit: 1024, bytes: 4096 };
+        let err = JobExecError::new(pegasus::errors::ErrorKind::Others, exceeded);
+        let classified = QueryError::from(&err);
+        assert_eq!(classified.kind(), pb::QueryErrorKind::ResourceLimit);
+        assert_eq!(classified.to_pb().limit, NETWORK_BYTE_LIMIT);
+        assert!(classified.to_string().starts_with("network_byte_limit limit exceeded"));
+    }
+
+    #[test]
+    fn worker_test() {
+        let err = QueryError::internal("channel closed").with_worker(Some(3));
+        assert_eq!(err.worker(), Some(3));
+        assert_eq!(err.to_string(), "internal error on worker 3: channel closed");
+        assert_eq!(err.to_pb().worker, 3);
+        // the worker told first is kept
+        assert_eq!(err.with_worker(Some(1)).worker(), Some(3));
+        let err = QueryError::Timeout { limit_ms: 100, worker: None };
+        assert_eq!(err.to_pb().limit, TIME_LIMIT);
+        assert_eq!(err.to_pb().err_code, 0);
+    }
+}
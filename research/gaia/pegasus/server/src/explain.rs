@@ -0,0 +1,197 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The plans of the jobs explained instead of being run, see `JobConfig.explain`, which list the
+//! operators of the plans with how the records are sent to them, how the source reads the data and
+//! the records each operator is estimated to output by the compiler, along with the dataflow built
+//! of the plan by `pegasus::explain`;
+
+use crate::factory::JobCompiler;
+use crate::generated::protocol as pb;
+use crate::AnyData;
+use pb::operator_def::OpKind;
+use pegasus::PlanDesc;
+
+/// List the operators of the plan, the source first, each followed by the operators nested in it,
+/// whose indices follow those of `PlanError`;
+pub fn explain_plan<D: AnyData>(
+    factory: &dyn JobCompiler<D>, graph: &str, source: &pb::Source, task: &Option<pb::TaskPlan>,
+) -> pb::JobPlan {
+    let access = factory.explain_source(graph, &source.resource);
+    let estimated = access.as_ref().and_then(|access| access.estimated_records);
+    let mut explainer = Explainer { factory, graph, op_index: vec![], operators: vec![] };
+    explainer.operators.push(pb::PlanOperator {
+        op_index: vec![],
+        name: "source".to_owned(),
+        kind: "source".to_owned(),
+        channel: String::new(),
+        access: access.map(|access| access.access).unwrap_or_default(),
+        estimated_records: to_pb_estimate(estimated),
+    });
+    if let Some(task) = task {
+        explainer.explain(&task.plan, estimated);
+    }
+    pb::JobPlan { operators: explainer.operators, ..Default::default() }
+}
+
+/// Add the dataflow described by `pegasus::explain` to the plan;
+pub fn add_dataflow(desc: &PlanDesc, plan: &mut pb::JobPlan) {
+    plan.dataflow = desc
+        .operators
+        .iter()
+        .map(|op| pb::DataflowOperator {
+            index: op.index as u32,
+            name: op.name.clone(),
+            scope_depth: op.scope_depth as u32,
+        })
+        .collect();
+    plan.channels = desc
+        .channels
+        .iter()
+        .map(|ch| pb::DataflowChannel {
+            source: ch.source as u32,
+            target: ch.target as u32,
+            exchange: !ch.is_local,
+        })
+        .collect();
+}
+
+struct Explainer<'a, D: AnyData> {
+    factory: &'a dyn JobCompiler<D>,
+    graph: &'a str,
+    op_index: Vec<u32>,
+    operators: Vec<pb::PlanOperator>,
+}
+
+impl<'a, D: AnyData> Explainer<'a, D> {
+    // list the operators of the plan of the `input` records, and get the records it outputs
+    fn explain(&mut self, plan: &[pb::OperatorDef], mut input: Option<u64>) -> Option<u64> {
+        for (i, op) in plan.iter().enumerate() {
+            self.op_index.push(i as u32);
+            let slot = self.operators.len();
+            self.operators.push(pb::PlanOperator {
+                op_index: self.op_index.clone(),
+                name: op.name.clone(),
+                kind: String::new(),
+                channel: channel_name(op.ch.as_ref()).to_owned(),
+                access: String::new(),
+                estimated_records: -1,
+            });
+            let (kind, output) = self.explain_op(op, input);
+            self.operators[slot].kind = kind.to_owned();
+            self.operators[slot].estimated_records = to_pb_estimate(output);
+            self.op_index.pop();
+            input = output;
+        }
+        input
+    }
+
+    // list the operators of a nested plan, with the index `i` appended if given, e.g. of a branch
+    fn explain_nested(
+        &mut self, plan: &[pb::OperatorDef], i: Option<usize>, input: Option<u64>,
+    ) -> Option<u64> {
+        if let Some(i) = i {
+            self.op_index.push(i as u32);
+        }
+        let output = self.explain(plan, input);
+        if i.is_some() {
+            self.op_index.pop();
+        }
+        output
+    }
+
+    fn explain_op(
+        &mut self, op: &pb::OperatorDef, input: Option<u64>,
+    ) -> (&'static str, Option<u64>) {
+        let global = |range: i32| range == pb::Range::Global as i32;
+        match op.op_kind.as_ref() {
+            Some(OpKind::Shuffle(_)) => ("shuffle", input),
+            Some(OpKind::Map(_)) => ("map", input),
+            Some(OpKind::FlatMap(flat_map)) => {
+                ("flat_map", self.estimate(&flat_map.resource, input))
+            }
+            Some(OpKind::Filter(filter)) => ("filter", self.estimate(&filter.resource, input)),
+            Some(OpKind::Limit(limit)) if global(limit.range) => {
+                ("limit", Some(input.map_or(limit.limit as u64, |n| n.min(limit.limit as u64))))
+            }
+            Some(OpKind::Limit(_)) => ("limit", input),
+            Some(OpKind::Order(order)) if global(order.range) && order.limit > 0 => {
+                let limit = order.limit as u64;
+                ("order", Some(input.map_or(limit, |n| n.min(limit))))
+            }
+            Some(OpKind::Order(_)) => ("order", input),
+            Some(OpKind::Fold(fold)) if global(fold.range) => {
+                let output = match (fold.unfold.as_ref(), pb::AccumKind::from_i32(fold.accum)) {
+                    (None, _) => Some(1),
+                    (Some(_), Some(pb::AccumKind::ToList)) => input,
+                    (Some(_), _) => None,
+                };
+                ("fold", output)
+            }
+            Some(OpKind::Fold(_)) => ("fold", None),
+            Some(OpKind::Group(_)) => ("group", None),
+            Some(OpKind::Dedup(_)) => ("dedup", None),
+            Some(OpKind::Union(union)) => {
+                let mut output = Some(0);
+                for (i, branch) in union.branches.iter().enumerate() {
+                    let branch = self.explain_nested(&branch.plan, Some(i), input);
+                    output = output.and_then(|n| branch.map(|m| n + m));
+                }
+                ("union", output)
+            }
+            Some(OpKind::Coalesce(coalesce)) => {
+                for (i, branch) in coalesce.branches.iter().enumerate() {
+                    self.explain_nested(&branch.plan, Some(i), input);
+                }
+                ("coalesce", None)
+            }
+            Some(OpKind::Iterate(iteration)) => {
+                self.explain_nested(plan_of(&iteration.body), None, input);
+                ("iterate", None)
+            }
+            Some(OpKind::Subtask(subtask)) => {
+                self.explain_nested(plan_of(&subtask.task), None, input);
+                ("subtask", None)
+            }
+            Some(OpKind::TimeLimit(time_limit)) => {
+                // the data pass the empty task as they are
+                ("time_limit", self.explain_nested(plan_of(&time_limit.task), None, input))
+            }
+            None => ("unknown", None),
+        }
+    }
+
+    fn estimate(&self, res: &[u8], input: Option<u64>) -> Option<u64> {
+        input.and_then(|n| self.factory.estimate_output(self.graph, res, n))
+    }
+}
+
+// how the records are sent to the operator of the channel
+fn channel_name(ch: Option<&pb::ChannelDef>) -> &'static str {
+    match ch.and_then(|ch| ch.ch_kind.as_ref()) {
+        None | Some(pb::channel_def::ChKind::ToLocal(_)) => "pipeline",
+        Some(pb::channel_def::ChKind::ToAnother(_)) => "shuffle",
+        Some(pb::channel_def::ChKind::ToOthers(_)) => "broadcast",
+        Some(pb::channel_def::ChKind::ToOne(_)) => "aggregate",
+    }
+}
+
+fn plan_of(task: &Option<pb::TaskPlan>) -> &[pb::OperatorDef] {
+    task.as_ref().map(|task| task.plan.as_slice()).unwrap_or(&[])
+}
+
+fn to_pb_estimate(estimated: Option<u64>) -> i64 {
+    estimated.map_or(-1, |n| n.min(i64::MAX as u64) as i64)
+}
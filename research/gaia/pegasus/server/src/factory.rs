@@ -13,18 +13,77 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
+use crate::error::QueryError;
+use crate::generated::protocol as pb;
 use crate::AnyData;
 use pegasus::api::accum::{AccumFactory, Accumulator};
 use pegasus::api::function::*;
+use pegasus::api::Decision;
 use pegasus::codec::{Decode, Encode, ReadExt, WriteExt};
-use pegasus::BuildJobError;
+use pegasus::{BuildJobError, JobConf};
 use pegasus_common::collections::{Collection, CollectionFactory, Map, MapFactory, Set};
-use std::fmt::Debug;
+use pegasus_common::downcast::{Any, AsAny};
+use std::fmt::{self, Debug};
 use std::hash::Hash;
 use std::ops::Deref;
 
 pub type CompileResult<T> = Result<T, BuildJobError>;
 
+/// A malformed plan found by `JobCompiler::validate` before the job is submitted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlanError {
+    /// The index of the malformed operator in its plan, following the indices of the operators
+    /// it is nested in from the outermost plan, where the branches of union and coalesce are
+    /// indexed as well;
+    /// It is empty if the error is not of any operator, e.g. of the source;
+    pub op_index: Vec<usize>,
+    pub msg: String,
+}
+
+impl PlanError {
+    pub fn new<S: Into<String>>(op_index: Vec<usize>, msg: S) -> Self {
+        PlanError { op_index, msg: msg.into() }
+    }
+}
+
+impl fmt::Display for PlanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.op_index.is_empty() {
+            write!(f, "invalid plan: {}", self.msg)
+        } else {
+            let index: Vec<String> = self.op_index.iter().map(|i| i.to_string()).collect();
+            write!(f, "invalid plan at operator {}: {}", index.join("."), self.msg)
+        }
+    }
+}
+
+impl std::error::Error for PlanError {}
+
+/// How the source of a job reads the data, told by `JobCompiler::explain_source` to explain the
+/// plan of the job, e.g. "vertex lookup by 2 ids" or "vertex scan";
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceAccess {
+    pub access: String,
+    /// The records the source is estimated to produce over all servers, `None` if unknown;
+    pub estimated_records: Option<u64>,
+}
+
+/// A designated point of the plan where the job decides between two alternatives at runtime,
+/// told by `JobCompiler::adaptive`; The data of each scope are sampled by `decide`, which picks
+/// the alternative the rest of the scope goes through, see `pegasus::api::Adapt`, so both
+/// alternatives must produce the same results of the same data;
+pub struct AdaptivePoint<D> {
+    /// The number of the leading operators of the plan the alternatives replace;
+    pub covers: usize,
+    /// The name of the decision in the profile of the job;
+    pub name: String,
+    /// The number of the records of a scope sampled before the decision;
+    pub sample: usize,
+    pub first: Vec<pb::OperatorDef>,
+    pub second: Vec<pb::OperatorDef>,
+    pub decide: DecideFn<D>,
+}
+
 pub type DynMap<T> = Box<dyn Map<T, T, Target = Box<dyn Iterator<Item = (T, T)> + Send>>>;
 
 pub type DynMapFactory<T> = Box<dyn MapFactory<T, T, Target = DynMap<T>>>;
@@ -33,6 +92,13 @@ pub type DynGroupUnfold<T> = Box<dyn FlatMapFunction<DynMap<T>, T, Target = DynI
 
 pub type DynGroupSink<T> = Box<dyn EncodeFunction<DynMap<T>>>;
 
+pub type DynSource<T> = Box<dyn Iterator<Item = T> + Send>;
+
+pub type DynFlatMap<T> = Box<dyn FlatMapFunction<T, T, Target = DynIter<T>>>;
+
+/// The decision of an adaptive point by the records sampled, see `AdaptivePoint`;
+pub type DecideFn<D> = Box<dyn Fn(&[D]) -> Decision + Send>;
+
 pub trait GroupFunction<D>: Send + 'static {
     fn key(&self) -> CompileResult<Box<dyn KeyFunction<D, Key = D>>>;
 
@@ -92,6 +158,129 @@ pub trait JobCompiler<D: AnyData>: Send + Sync + 'static {
     ) -> CompileResult<Box<dyn CollectionFactory<D, Target = Box<dyn Set<D>>>>>;
 
     fn sink(&self, res: &[u8]) -> CompileResult<Box<dyn EncodeFunction<D>>>;
+
+    /// Check if the request is well-formed before the job is submitted, so that a malformed plan
+    /// is rejected with where and why it is malformed, instead of failing the job while the
+    /// operators are being built;
+    fn validate(&self, _req: &pb::JobRequest) -> Result<(), PlanError> {
+        Ok(())
+    }
+
+    /// Simplify the plan of the request validated by `validate` before the job is built, e.g. to
+    /// merge the filters on the same key, keeping the results of the job as they are; returns
+    /// whether the request is rewritten, which the explained plan tells, while the error rejects
+    /// the job as that of `validate`;
+    fn simplify(&self, _req: &mut pb::JobRequest) -> Result<bool, PlanError> {
+        Ok(false)
+    }
+
+    /// Estimate how many workers per server the job deserves by the resource of its source, e.g.
+    /// the number of vertices to scan in the `graph` named by the request, which decides the
+    /// workers of jobs with the `Auto` worker hint; `None` if it can't be estimated;
+    fn estimate_workers(&self, _graph: &str, _src: &[u8]) -> Option<u32> {
+        None
+    }
+
+    /// The records the source of the resource expects to produce on current worker, e.g. by the
+    /// metadata of the partition it scans, asked once the source is built, to tell the progress
+    /// of the job by `pegasus::peek_progress`; `None` if it can't be told;
+    fn source_size(&self, _src: &[u8]) -> Option<u64> {
+        None
+    }
+
+    /// How the source of the resource reads the data of the `graph` named by the request, e.g. by
+    /// an index of the graph or by a scan, with the records it is estimated to produce by the
+    /// statistics of the graph, to explain the plan of the job; `None` if it can't be told;
+    fn explain_source(&self, _graph: &str, _src: &[u8]) -> Option<SourceAccess> {
+        None
+    }
+
+    /// Estimate how many records the flat map or filter of the resource outputs of the `input`
+    /// records, e.g. by the average degree of the graph for an expansion, to explain the plan of
+    /// the job; `None` if it can't be estimated;
+    fn estimate_output(&self, _graph: &str, _res: &[u8], _input: u64) -> Option<u64> {
+        None
+    }
+
+    /// The features supported by the compiler besides those of the engine, e.g. of the resources
+    /// it compiles, which may be required by the plans, see `capability::FEATURES`;
+    fn features(&self) -> Vec<String> {
+        vec![]
+    }
+
+    /// The results of the request told by the compiler without running the job, e.g. a count of
+    /// the scan answered by the statistics of the graph, which are encoded by the sink of the
+    /// request as those of the job; `None` if the job must run. It is only asked for the requests
+    /// sinking by resource;
+    fn shortcut(&self, _req: &pb::JobRequest) -> Option<Vec<D>> {
+        None
+    }
+
+    /// The hash of the query template of the request, i.e. of its plan with the literals
+    /// stripped, which the jobs of the same query share whatever their literals, and which their
+    /// latencies are recorded by, see `crate::latency`; `None` to tell the templates by the plans
+    /// as they are, i.e. by `JobConf::plan_hash`;
+    fn template_hash(&self, _req: &pb::JobRequest) -> Option<u64> {
+        None
+    }
+
+    /// The schema of the data the jobs run on, encoded for the clients to introspect, e.g. the
+    /// labels and properties of a graph; `None` if the data is schema-free;
+    fn schema(&self) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// Prepare the configuration of a job before it runs, e.g. to share the read-only data its
+    /// functions use on all workers by `JobConf::set_user_data`, which they get by
+    /// `pegasus::get_job_resource` while the job is running, such as the `graph` named by the
+    /// request, empty for the default one; The error rejects the job, e.g. a `Timeout` waiting for
+    /// the graph to catch up with `JobConf::min_version`;
+    fn prepare(&self, _graph: &str, _conf: &mut JobConf) -> Result<(), QueryError> {
+        Ok(())
+    }
+
+    /// Build the source along with the leading operators of the plan fused into it, e.g. the
+    /// filters evaluated over the columns of the batches the source scans, before the records
+    /// are produced; returns the source and the number of the operators it fuses, which are not
+    /// materialized, or `None` to start from `source` with all the operators of the plan; It is
+    /// not asked for the profiled jobs, whose operators are measured one by one;
+    fn fused_source(
+        &self, _src: &[u8], _plan: &[pb::OperatorDef],
+    ) -> CompileResult<Option<(DynSource<D>, usize)>> {
+        Ok(None)
+    }
+
+    /// Build the flat map of the first operator of the plan along with the operators following
+    /// it fused into it, e.g. the consecutive expansions of a graph, where the second explores
+    /// each record the first produces as soon as it is produced, so the records in between are
+    /// never collected into batches; returns the function, built with the channel of the first
+    /// operator, and the number of the operators it fuses, including the first, or `None` to
+    /// build the first operator alone; It is not asked for the profiled jobs;
+    fn fused_flat_map(
+        &self, _plan: &[pb::OperatorDef],
+    ) -> CompileResult<Option<(DynFlatMap<D>, usize)>> {
+        Ok(None)
+    }
+
+    /// The adaptive point at the head of the plan, whose alternatives replace its leading
+    /// operators, e.g. the consecutive filters whose order is decided by their pass rates over
+    /// the data of each scope; `None` to build the operators as they are; Unlike the fused
+    /// operators, it is asked for the profiled jobs as well, which log the decisions;
+    fn adaptive(&self, _plan: &[pb::OperatorDef]) -> CompileResult<Option<AdaptivePoint<D>>> {
+        Ok(None)
+    }
+
+    /// Whether the flat map of the resource may output the continuations of the data of too many
+    /// outputs, see `AnyData::continuation_route`, which are routed to the other workers to be
+    /// expanded by the flat map of the same resource; It is only asked if `JobConf::split_degree`
+    /// is set;
+    fn may_split(&self, _res: &[u8]) -> bool {
+        false
+    }
+
+    /// Called once the server is drained, i.e. no more jobs running, before it shuts down, e.g.
+    /// to persist the states the compiler warms up with as the server restarts;
+    fn on_drain(&self) {}
     // others undefined;
 }
 
@@ -154,3 +343,30 @@ impl<T> Deref for HashKey<T> {
         &self.value
     }
 }
+
+/// The quantiles estimated by the `QUANTILES` fold, in the order of the quantiles of the fold,
+/// which are unfolded or sunk by the `FoldFunction` like the accumulators;
+#[derive(Clone, Debug, PartialEq)]
+pub struct QuantileValues {
+    pub values: Vec<f64>,
+}
+
+impl<D> Accumulator<D> for QuantileValues {
+    fn accum(&mut self, _: D) -> std::io::Result<()> {
+        Err(std::io::Error::other("the estimated quantiles accumulate nothing"))
+    }
+}
+
+impl_as_any!(QuantileValues);
+
+impl Encode for QuantileValues {
+    fn write_to<W: WriteExt>(&self, writer: &mut W) -> std::io::Result<()> {
+        self.values.write_to(writer)
+    }
+}
+
+impl Decode for QuantileValues {
+    fn read_from<R: ReadExt>(reader: &mut R) -> std::io::Result<Self> {
+        Ok(QuantileValues { values: Vec::<f64>::read_from(reader)? })
+    }
+}
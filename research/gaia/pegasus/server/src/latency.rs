@@ -0,0 +1,458 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The latencies of the jobs of each query template, i.e. of the same plan whatever its literals,
+//! told by `JobCompiler::template_hash`, to tell the tail latencies of each kind of query which
+//! the averages hide. Each job completed records the time from it is accepted until it
+//! completes, and the time until its first results are sent, into the histograms of its template.
+//!
+//! The histograms are of the HDR style, where the latencies in microseconds are counted into the
+//! buckets of `SUB_BUCKETS` linear sub buckets for each power of two, so that a quantile is told
+//! within about 3% of the latencies recorded, whatever their magnitudes, by a few thousand buckets
+//! at most. The histograms of the same template are merged bucket by bucket, e.g. those of all the
+//! servers sent by `LatencyTemplates`.
+//!
+//! At most `DEFAULT_MAX_TEMPLATES` templates are kept by the recorder of the server, beyond which
+//! the least recently run template is dropped. With the feature `metrics`, the quantiles of the
+//! templates kept are exported as the summaries of the metrics endpoint, see `pegasus::metrics`.
+
+use crate::generated::protocol as pb;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// The most query templates the latencies are kept of, beyond which the least recently run
+/// template is dropped;
+pub const DEFAULT_MAX_TEMPLATES: usize = 256;
+/// The quantiles reported of each template;
+pub const QUANTILES: [f64; 3] = [0.5, 0.95, 0.99];
+
+// the bits of the sub buckets of each power of two;
+const SUB_BITS: u32 = 5;
+const SUB_BUCKETS: u64 = 1 << SUB_BITS;
+
+/// The index of the bucket of the latency, where a latency below `SUB_BUCKETS` is of its own
+/// bucket, and the others of `[2^e, 2^(e+1))` are split into `SUB_BUCKETS` buckets of equal width;
+pub fn bucket_index(us: u64) -> usize {
+    if us < SUB_BUCKETS {
+        us as usize
+    } else {
+        let shift = 63 - us.leading_zeros() - SUB_BITS;
+        (((shift + 1) as usize) << SUB_BITS) + ((us >> shift) - SUB_BUCKETS) as usize
+    }
+}
+
+/// The highest latency of the bucket of the index;
+pub fn bucket_upper(index: usize) -> u64 {
+    if (index as u64) < SUB_BUCKETS {
+        index as u64
+    } else {
+        let shift = (index >> SUB_BITS) as u32 - 1;
+        let sub = (index as u64 & (SUB_BUCKETS - 1)) + SUB_BUCKETS;
+        // the bucket of the highest latencies ends at `u64::MAX`;
+        ((sub + 1) << shift).wrapping_sub(1)
+    }
+}
+
+/// A histogram of the latencies in microseconds, see the module docs;
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LatencyHistogram {
+    // the number of the latencies of each bucket, grown to the highest bucket recorded;
+    counts: Vec<u64>,
+    count: u64,
+    sum: u64,
+    max: u64,
+}
+
+impl LatencyHistogram {
+    pub fn record(&mut self, us: u64) {
+        let index = bucket_index(us);
+        if index >= self.counts.len() {
+            self.counts.resize(index + 1, 0);
+        }
+        self.counts[index] += 1;
+        self.count += 1;
+        self.sum = self.sum.saturating_add(us);
+        self.max = self.max.max(us);
+    }
+
+    /// The number of the latencies recorded;
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// The sum of the latencies recorded, in microseconds;
+    pub fn sum(&self) -> u64 {
+        self.sum
+    }
+
+    pub fn max(&self) -> u64 {
+        self.max
+    }
+
+    /// The latency in microseconds which the quantile `q` of the latencies recorded are at most,
+    /// i.e. the highest latency of the bucket the quantile falls into, or the highest latency
+    /// recorded if lower; 0 if nothing is recorded;
+    pub fn quantile(&self, q: f64) -> u64 {
+        if self.count == 0 {
+            return 0;
+        }
+        let rank = ((q.clamp(0.0, 1.0) * self.count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (index, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return bucket_upper(index).min(self.max);
+            }
+        }
+        self.max
+    }
+
+    /// Add the latencies recorded by the other histogram, e.g. of another server;
+    pub fn merge(&mut self, other: &LatencyHistogram) {
+        if other.counts.len() > self.counts.len() {
+            self.counts.resize(other.counts.len(), 0);
+        }
+        for (index, count) in other.counts.iter().enumerate() {
+            self.counts[index] += count;
+        }
+        self.count += other.count;
+        self.sum = self.sum.saturating_add(other.sum);
+        self.max = self.max.max(other.max);
+    }
+
+    pub fn to_pb(&self) -> pb::LatencyHistogram {
+        let mut buckets = vec![];
+        let mut counts = vec![];
+        for (index, count) in self.counts.iter().enumerate().filter(|(_, count)| **count > 0) {
+            buckets.push(index as u32);
+            counts.push(*count);
+        }
+        pb::LatencyHistogram { buckets, counts, sum_us: self.sum, max_us: self.max }
+    }
+
+    pub fn from_pb(histogram: &pb::LatencyHistogram) -> Self {
+        let mut counts = vec![];
+        for (index, count) in histogram.buckets.iter().zip(histogram.counts.iter()) {
+            let index = *index as usize;
+            if index >= counts.len() {
+                counts.resize(index + 1, 0);
+            }
+            counts[index] += count;
+        }
+        LatencyHistogram {
+            count: counts.iter().sum(),
+            counts,
+            sum: histogram.sum_us,
+            max: histogram.max_us,
+        }
+    }
+}
+
+struct TemplateEntry {
+    job_name: String,
+    latency: LatencyHistogram,
+    first_result: LatencyHistogram,
+    // the tick of the last record;
+    tick: u64,
+}
+
+impl TemplateEntry {
+    fn to_pb(&self, template_hash: u64) -> pb::TemplateLatency {
+        let (latency, first_result) = (&self.latency, &self.first_result);
+        pb::TemplateLatency {
+            template_hash,
+            job_name: self.job_name.clone(),
+            count: latency.count(),
+            p50_us: latency.quantile(QUANTILES[0]),
+            p95_us: latency.quantile(QUANTILES[1]),
+            p99_us: latency.quantile(QUANTILES[2]),
+            first_result_p50_us: first_result.quantile(QUANTILES[0]),
+            first_result_p95_us: first_result.quantile(QUANTILES[1]),
+            first_result_p99_us: first_result.quantile(QUANTILES[2]),
+            latency: Some(latency.to_pb()),
+            first_result: Some(first_result.to_pb()),
+        }
+    }
+}
+
+struct Templates {
+    entries: HashMap<u64, TemplateEntry>,
+    // tick of the last record -> template, the least recently run template comes first;
+    order: BTreeMap<u64, u64>,
+    tick: u64,
+    capacity: usize,
+}
+
+impl Templates {
+    /// The entry of the template, which is created if absent, and becomes the most recently run;
+    fn touch(&mut self, template: u64, job_name: &str) -> &mut TemplateEntry {
+        self.tick += 1;
+        let tick = self.tick;
+        if let Some(entry) = self.entries.get(&template) {
+            self.order.remove(&entry.tick);
+        } else {
+            while self.entries.len() >= self.capacity.max(1) {
+                let lru = *self.order.keys().next().expect("lru order lost");
+                let lru_template = self.order.remove(&lru).expect("lru order lost");
+                self.entries.remove(&lru_template);
+            }
+        }
+        self.order.insert(tick, template);
+        let entry = self.entries.entry(template).or_insert_with(|| TemplateEntry {
+            job_name: String::new(),
+            latency: LatencyHistogram::default(),
+            first_result: LatencyHistogram::default(),
+            tick,
+        });
+        entry.tick = tick;
+        if entry.job_name != job_name {
+            entry.job_name = job_name.to_owned();
+        }
+        entry
+    }
+}
+
+/// The histograms of the latencies of the query templates run most recently, see the module docs;
+pub struct LatencyRecorder {
+    templates: Mutex<Templates>,
+}
+
+impl LatencyRecorder {
+    /// Create a recorder keeping the latencies of `capacity` templates at most;
+    pub fn new(capacity: usize) -> Self {
+        let templates =
+            Templates { entries: HashMap::new(), order: BTreeMap::new(), tick: 0, capacity };
+        LatencyRecorder { templates: Mutex::new(templates) }
+    }
+
+    /// Record the latencies of a job of the template completed, i.e. from it is accepted until it
+    /// completes, and until its first results are sent;
+    pub fn record(&self, template: u64, job_name: &str, latency: Duration, first_result: Duration) {
+        if let Ok(mut templates) = self.templates.lock() {
+            let entry = templates.touch(template, job_name);
+            entry.latency.record(latency.as_micros() as u64);
+            entry.first_result.record(first_result.as_micros() as u64);
+        }
+    }
+
+    /// Merge the latencies of the template recorded by another recorder, e.g. of another server;
+    pub fn merge(&self, other: &pb::TemplateLatency) {
+        if let Ok(mut templates) = self.templates.lock() {
+            let entry = templates.touch(other.template_hash, &other.job_name);
+            if let Some(latency) = other.latency.as_ref() {
+                entry.latency.merge(&LatencyHistogram::from_pb(latency));
+            }
+            if let Some(first_result) = other.first_result.as_ref() {
+                entry.first_result.merge(&LatencyHistogram::from_pb(first_result));
+            }
+        }
+    }
+
+    /// The latencies of the template, or `None` if it is never run or dropped;
+    pub fn get(&self, template: u64) -> Option<pb::TemplateLatency> {
+        let templates = self.templates.lock().ok()?;
+        templates.entries.get(&template).map(|entry| entry.to_pb(template))
+    }
+
+    /// The latencies of the `top` templates of the highest p99 latencies, or of all the templates
+    /// kept if `top` is 0, from the highest p99 latency to the lowest;
+    pub fn top_by_p99(&self, top: usize) -> Vec<pb::TemplateLatency> {
+        let mut latencies: Vec<pb::TemplateLatency> = match self.templates.lock() {
+            Ok(templates) => templates.entries.iter().map(|(t, entry)| entry.to_pb(*t)).collect(),
+            Err(_) => vec![],
+        };
+        latencies.sort_by_key(|l| std::cmp::Reverse(l.p99_us));
+        if top > 0 {
+            latencies.truncate(top);
+        }
+        latencies
+    }
+
+    /// The number of the templates kept;
+    pub fn len(&self) -> usize {
+        self.templates.lock().map(|templates| templates.entries.len()).unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+lazy_static! {
+    static ref RECORDER: LatencyRecorder = LatencyRecorder::new(DEFAULT_MAX_TEMPLATES);
+}
+
+/// The recorder of the latencies of the jobs run on current server, shared by all its services;
+pub fn latency_recorder() -> &'static LatencyRecorder {
+    #[cfg(feature = "metrics")]
+    prom::register();
+    &RECORDER
+}
+
+#[cfg(feature = "metrics")]
+mod prom {
+    use super::*;
+    use prometheus::core::{Collector, Desc};
+    use prometheus::proto;
+    use std::sync::Once;
+
+    static REGISTER: Once = Once::new();
+
+    /// Export the quantiles of the templates kept as the summaries of the metrics endpoint, each
+    /// labeled by its template in hex;
+    struct LatencyCollector {
+        descs: Vec<Desc>,
+    }
+
+    const METRICS: [(&str, &str); 2] = [
+        ("pegasus_query_latency_seconds", "The time of the jobs of each query template"),
+        ("pegasus_query_first_result_seconds", "The time until the first results of each template"),
+    ];
+
+    impl Collector for LatencyCollector {
+        fn desc(&self) -> Vec<&Desc> {
+            self.descs.iter().collect()
+        }
+
+        fn collect(&self) -> Vec<proto::MetricFamily> {
+            let templates = RECORDER.top_by_p99(0);
+            let mut families = vec![];
+            for (i, (name, help)) in METRICS.iter().enumerate() {
+                let mut family = proto::MetricFamily::default();
+                family.set_name(name.to_string());
+                family.set_help(help.to_string());
+                family.set_field_type(proto::MetricType::SUMMARY);
+                for template in templates.iter() {
+                    let histogram = if i == 0 { &template.latency } else { &template.first_result };
+                    let histogram = LatencyHistogram::from_pb(histogram.as_ref().unwrap());
+                    let mut summary = proto::Summary::default();
+                    summary.set_sample_count(histogram.count());
+                    summary.set_sample_sum(histogram.sum() as f64 / 1e6);
+                    let quantiles = QUANTILES
+                        .iter()
+                        .map(|q| {
+                            let mut quantile = proto::Quantile::default();
+                            quantile.set_quantile(*q);
+                            quantile.set_value(histogram.quantile(*q) as f64 / 1e6);
+                            quantile
+                        })
+                        .collect::<Vec<_>>();
+                    summary.set_quantile(quantiles.into());
+                    let mut label = proto::LabelPair::default();
+                    label.set_name("template".to_owned());
+                    label.set_value(format!("{:016x}", template.template_hash));
+                    let mut metric = proto::Metric::default();
+                    metric.set_label(vec![label].into());
+                    metric.set_summary(summary);
+                    family.mut_metric().push(metric);
+                }
+                families.push(family);
+            }
+            families
+        }
+    }
+
+    pub(super) fn register() {
+        REGISTER.call_once(|| {
+            let descs = METRICS
+                .iter()
+                .filter_map(|(name, help)| {
+                    let labels = vec!["template".to_owned()];
+                    Desc::new(name.to_string(), help.to_string(), labels, HashMap::new()).ok()
+                })
+                .collect();
+            let collector = LatencyCollector { descs };
+            if let Err(e) = pegasus::metrics::registry().register(Box::new(collector)) {
+                warn!("register the latency metrics failure: {}", e);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn bucket_bounds_test() {
+        for us in (0..100_000u64).chain(vec![u64::MAX / 3, u64::MAX - 1, u64::MAX]) {
+            let index = bucket_index(us);
+            assert!(us <= bucket_upper(index), "{} above its bucket {}", us, index);
+            if index > 0 {
+                assert!(us > bucket_upper(index - 1), "{} below its bucket {}", us, index);
+            }
+            // the width of a bucket is at most 1/32 of its latencies
+            let lower = if index > 0 { bucket_upper(index - 1) + 1 } else { 0 };
+            assert!(bucket_upper(index) - lower <= lower / SUB_BUCKETS);
+        }
+        assert_eq!(bucket_upper(bucket_index(u64::MAX)), u64::MAX);
+    }
+
+    #[test]
+    fn quantile_test() {
+        let mut histogram = LatencyHistogram::default();
+        assert_eq!(histogram.quantile(0.99), 0);
+        for us in 1..=1000 {
+            histogram.record(us * 100);
+        }
+        for (q, expected) in [(0.5, 50_000.0), (0.95, 95_000.0), (0.99, 99_000.0)] {
+            let told = histogram.quantile(q) as f64;
+            assert!(told >= expected && told <= expected * 1.04, "p{} told {}", q, told);
+        }
+        assert_eq!(histogram.quantile(1.0), 100_000);
+    }
+
+    #[test]
+    fn merge_by_pb_test() {
+        let mut a = LatencyHistogram::default();
+        let mut b = LatencyHistogram::default();
+        let mut all = LatencyHistogram::default();
+        for us in 0..500u64 {
+            if us % 3 == 0 {
+                a.record(us * us);
+            } else {
+                b.record(us * us);
+            }
+            all.record(us * us);
+        }
+        let mut merged = LatencyHistogram::from_pb(&a.to_pb());
+        merged.merge(&LatencyHistogram::from_pb(&b.to_pb()));
+        assert_eq!(merged, all);
+    }
+
+    #[test]
+    fn lru_templates_test() {
+        let recorder = LatencyRecorder::new(2);
+        let ms = Duration::from_millis;
+        recorder.record(1, "q1", ms(10), ms(1));
+        recorder.record(2, "q2", ms(30), ms(2));
+        recorder.record(1, "q1", ms(20), ms(1));
+        recorder.record(3, "q3", ms(5), ms(5));
+        // the template 2 is the least recently run
+        assert!(recorder.get(2).is_none());
+        assert_eq!(recorder.len(), 2);
+        let top: Vec<u64> = recorder.top_by_p99(0).iter().map(|t| t.template_hash).collect();
+        assert_eq!(top, vec![1, 3]);
+        assert_eq!(recorder.top_by_p99(1).len(), 1);
+
+        let other = LatencyRecorder::new(2);
+        other.merge(&recorder.get(1).unwrap());
+        other.merge(&recorder.get(1).unwrap());
+        let merged = other.get(1).unwrap();
+        assert_eq!(merged.count, 4);
+        assert_eq!(merged.job_name, "q1");
+    }
+}
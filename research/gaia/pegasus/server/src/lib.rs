@@ -16,31 +16,76 @@
 #[macro_use]
 extern crate pegasus;
 #[macro_use]
+extern crate pegasus_common;
+#[macro_use]
 extern crate log;
+#[macro_use]
+extern crate lazy_static;
 
 pub use config::{CommonConfig, HostsConfig};
 use pegasus::api::function::Partition;
 use pegasus::Data;
 
 #[cfg(not(feature = "gcip"))]
-mod generated {
+pub mod generated {
     pub mod protocol {
         tonic::include_proto!("protocol");
     }
 }
 
 #[cfg(feature = "gcip")]
-mod generated {
+pub mod generated {
     #[path = "protocol.rs"]
     pub mod protocol;
 }
 
-pub trait AnyData: Data + Eq + Partition {}
+/// The data flowing through the jobs. If `JobConf::bulking` is set, the equal data are merged
+/// into one datum standing for all of them, i.e. with a bulk, after they are exchanged between
+/// workers. The data that are never merged keep the default implementations.
+pub trait AnyData: Data + Eq + Partition {
+    /// Get the number of data this datum stands for
+    fn get_bulk(&self) -> u64 {
+        1
+    }
+
+    fn set_bulk(&mut self, _bulk: u64) {}
+
+    /// Get the key used to find the data that may be merged with this datum, or `None` if it
+    /// must not be merged with any other one
+    fn bulk_key(&self) -> Option<u64> {
+        None
+    }
+
+    /// Try to merge `other` into this datum, and return `other` back if they can't be merged
+    fn try_merge(&mut self, other: Self) -> Option<Self> {
+        Some(other)
+    }
+
+    /// Get the numeric value of this datum, e.g. to estimate the quantiles, or `None` if it is
+    /// not a number
+    fn as_f64(&self) -> Option<f64> {
+        None
+    }
+
+    /// Get the index of the worker to go on expanding this datum, if it is the continuation of a
+    /// flat map, i.e. a datum standing for a range of the outputs of a datum of too many outputs,
+    /// see `JobConf::split_degree`, or `None` if it is an output as usual
+    fn continuation_route(&self) -> Option<u64> {
+        None
+    }
+}
 
 // pub mod client;
+pub mod batch;
+pub mod capability;
 pub mod config;
+pub mod dependency;
+pub mod error;
+mod explain;
 pub mod factory;
+pub mod latency;
 mod materialize;
+pub mod paging;
 pub mod rpc;
 pub mod service;
 
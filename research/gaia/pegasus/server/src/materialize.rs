@@ -13,40 +13,166 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
-use crate::factory::JobCompiler;
+use crate::factory::{JobCompiler, QuantileValues};
 use crate::generated::protocol as pb;
 use crate::generated::protocol::AccumKind;
 use crate::AnyData;
-use pegasus::api::accum::ToListAccum;
+use pegasus::api::accum::{AccumFactory, Accumulator, ToListAccum};
 use pegasus::api::function::*;
+use pegasus::api::state::OperatorState;
 use pegasus::api::{
-    Binary, Count, Dedup, Exchange, Filter, Fold, Group, Iteration, KeyBy, Limit, LoopCondition,
-    Map, OrderBy, ResultSet, SubTask, SubtaskResult, RANGES,
+    Adapt, ApproxDedup, ApproxDistinct, Binary, Count, Dedup, EmitKind, Exchange, Filter, Fold,
+    Group, IntoBranch, Iteration, KeyBy, Limit, LoopCondition, Map, Merge, OrderBy, Quantiles,
+    Range, ResultSet, SemiJoinKind, SubTask, SubtaskResult, TimeLimit, Unary, UnaryState, RANGES,
 };
-use pegasus::codec::{shade_codec, ShadeCodec};
-use pegasus::communication::{Aggregate, Broadcast, Channel, Pipeline};
+use pegasus::codec::{shade_codec, Decode, Encode, ReadExt, ShadeCodec, WriteExt};
+use pegasus::communication::{
+    Aggregate, AggregateLocal, Broadcast, Channel, Input, Output, Pipeline,
+};
+use pegasus::errors::JobExecError;
 use pegasus::stream::Stream;
-use pegasus::{never_clone, BuildJobError, NeverClone};
+use pegasus::{never_clone, BuildJobError, NeverClone, OverflowPolicy};
 use pegasus_common::collections::MapFactory;
+use pegasus_common::downcast::{Any, AsAny};
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 
 pub fn exec<D: AnyData>(
     stream: &Stream<D>, plan: &[pb::OperatorDef], factory: &Arc<dyn JobCompiler<D>>,
+) -> Result<Stream<D>, BuildJobError> {
+    exec_plan(stream, plan, factory, true)
+}
+
+/// Install the operators of the plan, where the adaptive points told by the factory are built if
+/// `adaptive`, see `JobCompiler::adaptive`; The alternatives of an adaptive point are installed
+/// without, so they are never adapted again;
+fn exec_plan<D: AnyData>(
+    stream: &Stream<D>, plan: &[pb::OperatorDef], factory: &Arc<dyn JobCompiler<D>>, adaptive: bool,
 ) -> Result<Stream<D>, BuildJobError> {
     if plan.is_empty() {
         Err("should be unreachable, plan length = 0;")?
     }
-    let mut owned_stream = install(stream, &plan[0], factory)?;
-    for op in &plan[1..] {
-        owned_stream = install(&owned_stream, op, factory)?;
+    let (mut owned_stream, mut installed) = install_next(stream, plan, factory, adaptive)?;
+    while installed < plan.len() {
+        let (next, fused) = install_next(&owned_stream, &plan[installed..], factory, adaptive)?;
+        owned_stream = next;
+        installed += fused;
     }
     Ok(owned_stream)
 }
 
+/// Install the adaptive point at the head of the plan if any and `adaptive`, or else the first
+/// operator of the plan by `install_fused`; returns the stream and the number of the operators
+/// installed;
+fn install_next<D: AnyData>(
+    stream: &Stream<D>, plan: &[pb::OperatorDef], factory: &Arc<dyn JobCompiler<D>>, adaptive: bool,
+) -> Result<(Stream<D>, usize), BuildJobError> {
+    let point = if adaptive { factory.adaptive(plan)? } else { None };
+    let point = match point {
+        Some(point) => point,
+        None => return install_fused(stream, plan, factory),
+    };
+    if point.covers == 0 || point.covers > plan.len() {
+        Err(format!(
+            "{} operators adapted out of the {} left in the plan",
+            point.covers,
+            plan.len()
+        ))?
+    }
+    if point.first.is_empty() || point.second.is_empty() {
+        Err(format!("empty alternative of the adaptive point {}", point.name))?
+    }
+    let (first, second) = (point.first, point.second);
+    let stream = stream.adapt(
+        &point.name,
+        point.sample,
+        point.decide,
+        |s| exec_plan(&s, &first, factory, false),
+        |s| exec_plan(&s, &second, factory, false),
+    )?;
+    Ok((stream, point.covers))
+}
+
+/// Install the first operator of the plan, along with the operators following it fused into it
+/// by the factory if it is a flat map, see `JobCompiler::fused_flat_map`, as one operator named
+/// after all of them; returns the stream and the number of the operators installed;
+fn install_fused<D: AnyData>(
+    stream: &Stream<D>, plan: &[pb::OperatorDef], factory: &Arc<dyn JobCompiler<D>>,
+) -> Result<(Stream<D>, usize), BuildJobError> {
+    let op = &plan[0];
+    let fused = match &op.op_kind {
+        Some(pb::operator_def::OpKind::FlatMap(_)) if is_fusible() => {
+            factory.fused_flat_map(plan)?
+        }
+        _ => None,
+    };
+    let (func, fused) = match fused {
+        Some(fused) => fused,
+        None => return Ok((install(stream, op, factory)?, 1)),
+    };
+    if fused == 0 || fused > plan.len() {
+        Err(format!("{} operators fused out of the {} left in the plan", fused, plan.len()))?
+    }
+    let build = |s: &Stream<D>| with_channel(s, op, factory, |s, ch| s.flat_map(ch, func));
+    let names: Vec<&str> =
+        plan[..fused].iter().map(|op| op.name.as_str()).filter(|n| !n.is_empty()).collect();
+    let stream =
+        if names.is_empty() { build(stream)? } else { stream.named(&names.join("."), build)? };
+    Ok((stream, fused))
+}
+
 fn install<D: AnyData>(
     stream: &Stream<D>, op: &pb::OperatorDef, factory: &Arc<dyn JobCompiler<D>>,
 ) -> Result<Stream<D>, BuildJobError> {
-    let ch = gen_channel(op.ch.as_ref(), factory)?;
+    if op.name.is_empty() {
+        install_with_channel(stream, op, factory)
+    } else {
+        stream.named(&op.name, |s| install_with_channel(s, op, factory))
+    }
+}
+
+fn install_with_channel<D: AnyData>(
+    stream: &Stream<D>, op: &pb::OperatorDef, factory: &Arc<dyn JobCompiler<D>>,
+) -> Result<Stream<D>, BuildJobError> {
+    with_channel(stream, op, factory, |s, ch| install_op(s, op, ch, factory))
+}
+
+/// Build the operator by `func` on the stream with the channel of `op`, or with a pipeline after
+/// the exchange of the channel if bulking;
+fn with_channel<D, F>(
+    stream: &Stream<D>, op: &pb::OperatorDef, factory: &Arc<dyn JobCompiler<D>>, func: F,
+) -> Result<Stream<D>, BuildJobError>
+where
+    D: AnyData,
+    F: FnOnce(&Stream<D>, Channel<D>) -> Result<Stream<D>, BuildJobError>,
+{
+    let route = op.ch.as_ref().and_then(|ch| match &ch.ch_kind {
+        Some(pb::channel_def::ChKind::ToAnother(route)) => Some(route),
+        _ => None,
+    });
+    match route {
+        Some(route) if is_bulking() => {
+            // exchange ahead of the operator, so that the data can be merged after being
+            // exchanged, before the operator processes them;
+            let route = factory.shuffle(&route.resource)?;
+            let bulked = bulking(&stream.exchange(route)?)?;
+            if let Some(pb::operator_def::OpKind::Shuffle(_)) = &op.op_kind {
+                Ok(bulked)
+            } else {
+                func(&bulked, Pipeline.into())
+            }
+        }
+        _ => {
+            let ch = gen_channel(op.ch.as_ref(), factory)?;
+            func(stream, ch)
+        }
+    }
+}
+
+fn install_op<D: AnyData>(
+    stream: &Stream<D>, op: &pb::OperatorDef, ch: Channel<D>, factory: &Arc<dyn JobCompiler<D>>,
+) -> Result<Stream<D>, BuildJobError> {
     match &op.op_kind {
         Some(pb::operator_def::OpKind::Shuffle(_)) => match &op.ch {
             Some(ch) => match &ch.ch_kind {
@@ -64,7 +190,12 @@ fn install<D: AnyData>(
         }
         Some(pb::operator_def::OpKind::FlatMap(flatmap)) => {
             let func = factory.flat_map(&flatmap.resource)?;
-            stream.flat_map(ch, func)
+            if is_splitting() && factory.may_split(&flatmap.resource) {
+                let rest = factory.flat_map(&flatmap.resource)?;
+                split_flat_map(stream, ch, func, rest)
+            } else {
+                stream.flat_map(ch, func)
+            }
         }
         Some(pb::operator_def::OpKind::Filter(filter)) => {
             let func = factory.filter(&filter.resource)?;
@@ -72,20 +203,23 @@ fn install<D: AnyData>(
         }
         Some(pb::operator_def::OpKind::Limit(limit)) => {
             let range = RANGES[limit.range as usize];
-            stream.limit(range, limit.limit)
+            with_unbulked(stream, |s| s.limit(range, limit.limit))
         }
         Some(pb::operator_def::OpKind::Order(order)) => {
             let range = RANGES[order.range as usize];
             let func = factory.compare(&order.compare)?;
-            if order.limit > 0 {
-                stream.top_by(order.limit as u32, range, func)
-            } else {
-                stream.sort_by(range, func)
-            }
+            with_unbulked(stream, |s| {
+                if order.limit > 0 {
+                    s.top_by(order.limit as u32, range, func)
+                } else {
+                    s.sort_by(range, func)
+                }
+            })
         }
         Some(pb::operator_def::OpKind::Fold(fold)) => {
             let range = RANGES[fold.range as usize];
-            let accum_kind: pb::AccumKind = unsafe { std::mem::transmute(fold.accum) };
+            let accum_kind = pb::AccumKind::from_i32(fold.accum)
+                .ok_or_else(|| format!("unknown accum kind {}", fold.accum))?;
             let unfold_res = if let Some(flat_map) = &fold.unfold {
                 &flat_map.resource
             } else {
@@ -95,17 +229,41 @@ fn install<D: AnyData>(
                 AccumKind::Cnt => {
                     let funcs = factory.fold(&vec![], unfold_res, &vec![])?;
                     let unfold_func = funcs.fold_unfold()?;
-                    stream
-                        .count(range)?
+                    count(stream, range)?
                         .flat_map_with_fn(Pipeline, move |c| unfold_func.exec(Box::new(c)))
                 }
                 AccumKind::ToList => {
                     let funcs = factory.fold(&vec![], unfold_res, &vec![])?;
                     let unfold_func = funcs.fold_unfold()?;
-                    stream
-                        .fold_with_accum(range, ToListAccum::new())?
+                    with_unbulked(stream, |s| s.fold_with_accum(range, ToListAccum::new()))?
                         .flat_map_with_fn(Pipeline, move |l| unfold_func.exec(Box::new(l)))
                 }
+                AccumKind::Sum
+                | AccumKind::Max
+                | AccumKind::Min
+                | AccumKind::Mean
+                | AccumKind::Custom => {
+                    let funcs = factory.fold(&fold.resource, unfold_res, &[])?;
+                    let accum = ShadeAccumFactory::new(funcs.accumulate()?);
+                    let unfold_func = funcs.fold_unfold()?;
+                    stream
+                        .fold_with_accum(range, accum)?
+                        .flat_map_with_fn(Pipeline, move |a| unfold_func.exec(a.take()))
+                }
+                AccumKind::ApproxDistinct => {
+                    let funcs = factory.fold(&[], unfold_res, &[])?;
+                    let unfold_func = funcs.fold_unfold()?;
+                    approx_distinct(stream, range, fold.precision)?
+                        .flat_map_with_fn(Pipeline, move |c| unfold_func.exec(Box::new(c)))
+                }
+                AccumKind::Quantiles => {
+                    let funcs = factory.fold(&[], unfold_res, &[])?;
+                    let unfold_func = funcs.fold_unfold()?;
+                    quantiles(stream, range, &fold.quantiles)?
+                        .flat_map_with_fn(Pipeline, move |values| {
+                            unfold_func.exec(Box::new(QuantileValues { values }))
+                        })
+                }
                 _ => unimplemented!(),
             }
         }
@@ -121,28 +279,56 @@ fn install<D: AnyData>(
             let map_factory = funcs.map_factory()?;
             let shade_map = ShadeMapFactory { inner: map_factory, _ph: std::marker::PhantomData };
             let unfold_func = funcs.unfold()?;
-            stream
-                .key_by(key_func)?
-                .group_with_map(range, shade_map)?
+            with_unbulked(stream, |s| s.key_by(key_func)?.group_with_map(range, shade_map))?
                 .flat_map_with_fn(Pipeline, move |shade| unfold_func.exec(shade.take().take()))
         }
         Some(pb::operator_def::OpKind::Iterate(iter)) => {
-            let mut cond = LoopCondition::max_iters(iter.max_iters);
+            let mut cond = if iter.max_iters == 0 && iter.until.is_some() {
+                LoopCondition::new()
+            } else {
+                LoopCondition::max_iters(iter.max_iters)
+            };
             if let Some(ref until) = iter.until {
                 let until = factory.filter(&until.resource)?;
                 cond.until(until);
             }
+            cond.set_pre_check(!iter.post_check);
+            let emit = match pb::iteration::EmitKind::from_i32(iter.emit) {
+                Some(pb::iteration::EmitKind::EmitBefore) => EmitKind::Before,
+                Some(pb::iteration::EmitKind::EmitAfter) => EmitKind::After,
+                _ => EmitKind::Null,
+            };
+            cond.set_emit(emit);
             let body = iter.body.as_ref().ok_or("iteration body not found")?;
             stream
                 .iterate_until(cond, |start| crate::materialize::exec(&start, &body.plan, factory))
         }
         Some(pb::operator_def::OpKind::Subtask(subtask)) => {
             let body = subtask.task.as_ref().ok_or("subtask body not found")?;
+            if subtask.optional {
+                return stream.optional_subtask(|start| {
+                    crate::materialize::exec(&start, &body.plan, factory)
+                });
+            }
             let forked = stream
                 .fork_subtask(|start| crate::materialize::exec(&start, &body.plan, factory))?;
-            if let Some(ref joiner) = subtask.join {
+            let semi_join = match pb::subtask::SemiJoin::from_i32(subtask.semi_join) {
+                Some(pb::subtask::SemiJoin::AnyExists) => Some(SemiJoinKind::AnyExists),
+                Some(pb::subtask::SemiJoin::NoneExists) => Some(SemiJoinKind::NoneExists),
+                _ => None,
+            };
+            if let Some(kind) = semi_join {
+                stream.semi_join_subtask(forked, kind)
+            } else if let Some(ref joiner) = subtask.join {
                 let func = factory.left_join(&joiner.resource)?;
-                stream.join_subtask(forked, move |p, s| func.exec(p, s))
+                if subtask.first {
+                    stream.join_first_subtask(forked, move |p, s| match s {
+                        Some(s) => func.exec(p, s),
+                        None => func.exec_empty(p),
+                    })
+                } else {
+                    stream.join_subtask(forked, move |p, s| func.exec(p, s))
+                }
             } else {
                 forked.flat_map(
                     Pipeline,
@@ -179,16 +365,284 @@ fn install<D: AnyData>(
             }
             Ok(s)
         }
+        Some(pb::operator_def::OpKind::Coalesce(coalesce)) => {
+            if coalesce.branches.is_empty() {
+                Err("no branch in coalesce")?;
+            }
+            let branches = coalesce
+                .branches
+                .iter()
+                .map(|task| {
+                    move |start: Stream<D>| crate::materialize::exec(&start, &task.plan, factory)
+                })
+                .collect();
+            stream.coalesce_subtasks(branches)?.map_with_fn(Pipeline, |(_, r)| Ok(r))
+        }
+        Some(pb::operator_def::OpKind::TimeLimit(time_limit)) => {
+            if time_limit.ms == 0 {
+                Err("time limit of 0 ms")?;
+            }
+            let limit = Duration::from_millis(time_limit.ms);
+            let task = time_limit.task.as_ref().ok_or("time limit task not found")?;
+            stream.time_limit(limit, |start| {
+                if task.plan.is_empty() {
+                    Ok(start)
+                } else {
+                    crate::materialize::exec(&start, &task.plan, factory)
+                }
+            })
+        }
         Some(pb::operator_def::OpKind::Dedup(dedup)) => {
             let range = RANGES[dedup.range as usize];
-            let set_factory = factory.set_factory(&dedup.set)?;
-            stream.dedup_with(range, set_factory)
+            let dedup = if dedup.expected_items > 0 {
+                let (expected_items, fp_rate) = (dedup.expected_items, dedup.fp_rate);
+                stream.dedup_approx_by(range, expected_items, fp_rate, |d| d.get_partition())?
+            } else {
+                let set_factory = factory.set_factory(&dedup.set)?;
+                stream.dedup_with(range, set_factory)?
+            };
+            if is_bulking() {
+                dedup.map_in_place(Pipeline, |d| d.set_bulk(1))
+            } else {
+                Ok(dedup)
+            }
+        }
+        None => Err("unknown operator")?,
+    }
+}
+
+#[inline]
+fn is_bulking() -> bool {
+    pegasus::get_current_job_conf().map(|conf| conf.bulking).unwrap_or(false)
+}
+
+/// Whether the operators can be fused, i.e. not of a profiled job, whose operators are measured
+/// one by one, nor of a job splitting the flat maps, whose fused flat maps are never split;
+#[inline]
+fn is_fusible() -> bool {
+    pegasus::get_current_job_conf()
+        .map(|conf| !conf.profile && conf.split_degree == 0)
+        .unwrap_or(true)
+}
+
+/// Whether the flat maps of the data of too many outputs are split among the workers;
+#[inline]
+fn is_splitting() -> bool {
+    pegasus::get_current_job_conf().map(|conf| conf.split_degree > 0).unwrap_or(false)
+}
+
+/// Flat map the data by `func`, which may leave some outputs as continuations of the data of too
+/// many outputs, see `AnyData::continuation_route`; The continuations are routed to the workers
+/// they name, flat mapped there by `rest` of the same resource, and merged into the outputs;
+fn split_flat_map<D: AnyData>(
+    stream: &Stream<D>, ch: Channel<D>, func: Box<dyn FlatMapFunction<D, D, Target = DynIter<D>>>,
+    rest: Box<dyn FlatMapFunction<D, D, Target = DynIter<D>>>,
+) -> Result<Stream<D>, BuildJobError> {
+    let (continued, expanded) =
+        stream.flat_map(ch, func)?.branch_by(|d: &D| d.continuation_route().is_some())?;
+    let continued = continued
+        .exchange_with_fn(|d: &D| d.continuation_route().unwrap_or(0))?
+        .flat_map(Pipeline, rest)?;
+    expanded.merge(&continued)
+}
+
+/// Merge the data of each batch that can be merged, i.e. with the same `bulk_key()`, into one
+/// datum with their bulks summed up;
+fn bulking<D: AnyData>(stream: &Stream<D>) -> Result<Stream<D>, BuildJobError> {
+    stream.unary("bulking", Pipeline, |_| {
+        |input, output| {
+            input.for_each_batch(|dataset| {
+                let mut merged: Vec<D> = Vec::with_capacity(dataset.len());
+                let mut positions: HashMap<u64, Vec<usize>> = HashMap::new();
+                for datum in dataset.drain(..) {
+                    if let Some(key) = datum.bulk_key() {
+                        merge_by_key(&mut merged, &mut positions, key, datum);
+                    } else {
+                        merged.push(datum);
+                    }
+                }
+                output.give_iterator(&mut merged.into_iter())?;
+                Ok(())
+            })
+        }
+    })
+}
+
+/// Merge the datum into the first one in `merged` of the same key it can be merged with, or
+/// append it to `merged` if there is none, where `positions` are those of the data of each key;
+fn merge_by_key<D: AnyData>(
+    merged: &mut Vec<D>, positions: &mut HashMap<u64, Vec<usize>>, key: u64, datum: D,
+) {
+    let candidates = positions.entry(key).or_default();
+    let mut rest = Some(datum);
+    for pos in candidates.iter() {
+        if let Some(d) = rest.take() {
+            rest = merged[*pos].try_merge(d);
         }
+    }
+    if let Some(d) = rest {
+        candidates.push(merged.len());
+        merged.push(d);
+    }
+}
+
+/// Build the operators by `func` on the stream whose data with bulks are split into as many
+/// data if bulking, which is required by the operators relying on the number of data, e.g.
+/// limit, order and group;
+pub(crate) fn with_unbulked<D, O, F>(stream: &Stream<D>, func: F) -> Result<O, BuildJobError>
+where
+    D: AnyData,
+    F: FnOnce(&Stream<D>) -> Result<O, BuildJobError>,
+{
+    if is_bulking() {
+        let unbulked = stream.flat_map_with_fn(Pipeline, |mut datum: D| {
+            let bulk = datum.get_bulk();
+            datum.set_bulk(1);
+            Ok(std::iter::repeat_n(datum, bulk as usize).map(|d| Ok(d)))
+        })?;
+        func(&unbulked)
+    } else {
+        func(stream)
+    }
+}
+
+/// Build the sink by `func` on the stream whose equal results are merged into ones with bulks
+/// in each scope, if `JobConf::sink_consolidation` is set;
+pub(crate) fn with_consolidated<D, O, F>(stream: &Stream<D>, func: F) -> Result<O, BuildJobError>
+where
+    D: AnyData,
+    F: FnOnce(&Stream<D>) -> Result<O, BuildJobError>,
+{
+    let limit = pegasus::get_current_job_conf().map(|conf| conf.sink_consolidation).unwrap_or(0);
+    if limit > 0 {
+        let consolidated = stream.unary_with_state("consolidate", Pipeline, |_| {
+            ConsolidateHandle { limit: limit as usize, _ph: std::marker::PhantomData }
+        })?;
+        func(&consolidated)
+    } else {
+        func(stream)
+    }
+}
 
-        _ => unimplemented!(),
+/// The results of a scope held by the sink to be merged, see `with_consolidated`;
+struct Consolidated<D> {
+    merged: Vec<D>,
+    positions: HashMap<u64, Vec<usize>>,
+    // set once the distinct results exceed the limit, after which the results pass through;
+    pass_through: bool,
+}
+
+impl<D> Default for Consolidated<D> {
+    fn default() -> Self {
+        Consolidated { merged: vec![], positions: HashMap::new(), pass_through: false }
     }
 }
 
+struct ConsolidateHandle<D> {
+    limit: usize,
+    _ph: std::marker::PhantomData<D>,
+}
+
+// the merged results are emitted once the scope ends, unless they are too many distinct ones to
+// be worth holding, when they are flushed at once and the rest of the scope passes through;
+impl<D: AnyData> UnaryState<D, D, Consolidated<D>> for ConsolidateHandle<D> {
+    type NotifyResult = Vec<D>;
+
+    fn on_receive(
+        &self, input: &mut Input<D>, output: &mut Output<D>,
+        state: &mut OperatorState<Consolidated<D>>,
+    ) -> Result<(), JobExecError> {
+        let limit = self.limit;
+        let consolidated: &mut Consolidated<D> = state;
+        input.for_each_batch(|dataset| {
+            for datum in dataset.drain(..) {
+                match datum.bulk_key() {
+                    Some(key) if !consolidated.pass_through => {
+                        let Consolidated { merged, positions, .. } = &mut *consolidated;
+                        merge_by_key(merged, positions, key, datum);
+                        if merged.len() > limit {
+                            consolidated.pass_through = true;
+                            consolidated.positions.clear();
+                            output.give_iterator(&mut consolidated.merged.drain(..))?;
+                        }
+                    }
+                    _ => output.give(datum)?,
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn on_notify(&self, state: Consolidated<D>) -> Self::NotifyResult {
+        state.merged
+    }
+}
+
+fn overflow_policy() -> OverflowPolicy {
+    pegasus::get_current_job_conf().map(|conf| conf.overflow).unwrap_or_default()
+}
+
+/// Count the distinct data approximately by the hashes of the data, i.e. their partition keys,
+/// where the bulks are irrelevant;
+pub(crate) fn approx_distinct<D: AnyData>(
+    stream: &Stream<D>, range: Range, precision: u32,
+) -> Result<Stream<u64>, BuildJobError> {
+    let precision = if precision > u8::MAX as u32 { u8::MAX } else { precision as u8 };
+    stream
+        .map_with_fn(Pipeline, |datum: D| datum.get_partition())?
+        .count_approx_distinct(range, precision)
+}
+
+/// Estimate the quantiles of the numeric data, taking their bulks into account, which fails the
+/// job on any datum that is not a number;
+pub(crate) fn quantiles<D: AnyData>(
+    stream: &Stream<D>, range: Range, qs: &[f64],
+) -> Result<Stream<Vec<f64>>, BuildJobError> {
+    let numbers = with_unbulked(stream, |s| {
+        s.map_with_fn(Pipeline, |datum: D| {
+            datum.as_f64().ok_or_else(|| {
+                let err: Box<dyn std::error::Error + Send + Sync> =
+                    format!("quantiles() requires numeric values, but got {:?}", datum).into();
+                err as Box<dyn std::error::Error + Send>
+            })
+        })
+    })?;
+    numbers.quantiles(range, qs)
+}
+
+/// Count the data, taking their bulks into account, where the count overflowing u64, e.g. by
+/// huge bulks, either saturates or fails the job by the overflow policy of the job;
+pub(crate) fn count<D: AnyData>(
+    stream: &Stream<D>, range: Range,
+) -> Result<Stream<u64>, BuildJobError> {
+    if !is_bulking() {
+        return stream.count(range);
+    }
+    let policy = overflow_policy();
+    // the count turns into `None` once it overflows;
+    let local = stream.fold(Some(0u64), Pipeline, move |s, d| {
+        *s = s.and_then(|c| policy.add_count(c, d.get_bulk()))
+    })?;
+    let sum = move |s: &mut Option<u64>, u: Option<u64>| {
+        *s = s.and_then(|c| u.and_then(|u| policy.add_count(c, u)))
+    };
+    let count = match range {
+        Range::Local => local,
+        Range::PerServer => local.fold(Some(0u64), AggregateLocal, sum)?,
+        Range::Global if local.gather_per_server() => {
+            local.fold(Some(0u64), AggregateLocal, sum)?.fold(Some(0u64), Aggregate(0), sum)?
+        }
+        Range::Global => local.fold(Some(0u64), Aggregate(0), sum)?,
+    };
+    count.map_with_fn(Pipeline, |c: Option<u64>| {
+        c.ok_or_else(|| {
+            let err: Box<dyn std::error::Error + Send + Sync> =
+                "count() overflows u64, or set the overflow policy to saturate".into();
+            err as Box<dyn std::error::Error + Send>
+        })
+    })
+}
+
 #[inline]
 fn gen_channel<D: AnyData>(
     ch: Option<&pb::ChannelDef>, factory: &Arc<dyn JobCompiler<D>>,
@@ -214,6 +668,68 @@ fn gen_channel<D: AnyData>(
     })
 }
 
+/// Accumulators compiled from user's resource, which never leave the worker they are created on;
+pub struct ShadeAccumFactory<D: AnyData> {
+    inner: Box<dyn AccumFactory<D, Target = Box<dyn Accumulator<D>>>>,
+}
+
+impl<D: AnyData> ShadeAccumFactory<D> {
+    pub fn new(inner: Box<dyn AccumFactory<D, Target = Box<dyn Accumulator<D>>>>) -> Self {
+        ShadeAccumFactory { inner }
+    }
+}
+
+impl<D: AnyData> AccumFactory<D> for ShadeAccumFactory<D> {
+    type Target = ShadeAccum<D>;
+
+    fn create(&self) -> Self::Target {
+        ShadeAccum { inner: never_clone(shade_codec(self.inner.create())) }
+    }
+
+    fn is_associative(&self) -> bool {
+        self.inner.is_associative()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ShadeAccum<D: AnyData> {
+    inner: NeverClone<ShadeCodec<Box<dyn Accumulator<D>>>>,
+}
+
+impl<D: AnyData> ShadeAccum<D> {
+    pub fn take(self) -> Box<dyn Accumulator<D>> {
+        self.inner.take().take()
+    }
+}
+
+impl<D: AnyData> Accumulator<D> for ShadeAccum<D> {
+    fn accum(&mut self, next: D) -> Result<(), std::io::Error> {
+        (**self.inner).accum(next)
+    }
+}
+
+impl<D: AnyData> AsAny for ShadeAccum<D> {
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        (**self.inner).as_any_mut()
+    }
+
+    fn as_any_ref(&self) -> &dyn Any {
+        (**self.inner).as_any_ref()
+    }
+}
+
+impl<D: AnyData> Encode for ShadeAccum<D> {
+    fn write_to<W: WriteExt>(&self, writer: &mut W) -> std::io::Result<()> {
+        self.inner.write_to(writer)
+    }
+}
+
+impl<D: AnyData> Decode for ShadeAccum<D> {
+    fn read_from<R: ReadExt>(reader: &mut R) -> std::io::Result<Self> {
+        Ok(ShadeAccum { inner: NeverClone::read_from(reader)? })
+    }
+}
+
 pub struct ShadeMapFactory<D: Send + Eq, F: MapFactory<D, D>> {
     inner: F,
     _ph: std::marker::PhantomData<D>,
@@ -0,0 +1,449 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The results of the jobs submitted with a page size, which are kept by current server in pages
+//! of that many rows instead of being streamed to the client, and fetched by the client page by
+//! page with the token of the next page given by the former fetch. The job completes and releases
+//! its workers as soon as its results are all paged, whether fetched or not.
+//!
+//! At most `MEMORY_PAGES` pages of a job are kept in memory, while the later ones are spilled into
+//! files until fetched, under a directory registered with the temp resources of the job, see
+//! `pegasus::temp`, which outlives the job until the pages are released. The pages before a
+//! fetched one are released, as the client has received them, and all pages of a cursor are
+//! released once it is cancelled, or it expires as it hasn't been fetched for its ttl.
+
+use crate::generated::protocol as pb;
+use pegasus::api::function::{DynError, EncodeFunction, FnResult};
+use pegasus::temp::TempPath;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// The pages of a job kept in memory, beyond which the pages are spilled into files;
+pub const MEMORY_PAGES: usize = 4;
+/// How long the pages are kept since the cursor is opened or last fetched, if not given;
+pub const DEFAULT_PAGE_TTL: Duration = Duration::from_secs(600);
+/// How long a fetch waits for the page while the job is running, if not given;
+pub const DEFAULT_FETCH_WAIT: Duration = Duration::from_secs(30);
+
+/// A page fetched by its token;
+#[derive(Debug, Default, PartialEq)]
+pub struct FetchedPage {
+    /// the encoded results of the page, as sent by the sink of the job without paging;
+    pub data: Vec<Vec<u8>>,
+    /// the token of the next page, which is the same token if the page isn't ready before the
+    /// fetch gives up waiting, to be fetched again;
+    pub next_token: u64,
+    /// no more pages after this one, e.g. of a token past the end;
+    pub last: bool,
+}
+
+enum Page {
+    Memory(Vec<Vec<u8>>),
+    Spilled(PathBuf),
+    Released,
+}
+
+struct PageState {
+    // the full pages in order, where page `i` is of token `i`;
+    pages: Vec<Page>,
+    // the page being filled, with the rows in it;
+    filling: Vec<Vec<u8>>,
+    filling_rows: usize,
+    memory_pages: usize,
+    complete: bool,
+    error: Option<String>,
+    released: bool,
+    expires_at: Instant,
+    // the directory of the spilled pages, registered once the first page is spilled;
+    spilled: Option<TempPath>,
+}
+
+/// The pages of the results of a job in current server;
+pub struct PageStore {
+    job_id: u64,
+    page_size: usize,
+    ttl: Duration,
+    spill_dir: PathBuf,
+    state: Mutex<PageState>,
+    ready: Condvar,
+}
+
+impl PageStore {
+    fn new(job_id: u64, page_size: u32, ttl: Duration) -> Self {
+        let spill_dir = pegasus::temp::instance().dir().join(format!("pages_{}", job_id));
+        let state = PageState {
+            pages: vec![],
+            filling: vec![],
+            filling_rows: 0,
+            memory_pages: 0,
+            complete: false,
+            error: None,
+            released: false,
+            expires_at: Instant::now() + ttl,
+            spilled: None,
+        };
+        PageStore {
+            job_id,
+            page_size: std::cmp::max(page_size, 1) as usize,
+            ttl,
+            spill_dir,
+            state: Mutex::new(state),
+            ready: Condvar::new(),
+        }
+    }
+
+    pub fn page_size(&self) -> u32 {
+        self.page_size as u32
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Add the results into the pages, which are encoded by the page, so that each page has exactly
+    /// `page_size` rows except the last one; The results are dropped if the cursor is released;
+    pub fn push<D: 'static>(&self, mut data: Vec<D>, ec: &dyn EncodeFunction<D>) -> FnResult<()> {
+        // the results of the workers are paged one by one, to keep the pages full
+        let mut state = self.lock();
+        while !data.is_empty() && !state.released {
+            let rows = std::cmp::min(self.page_size - state.filling_rows, data.len());
+            let rest = data.split_off(rows);
+            state.filling.push(ec.try_encode(data)?);
+            state.filling_rows += rows;
+            if state.filling_rows == self.page_size {
+                self.seal(&mut state).map_err(|e| Box::new(e) as DynError)?;
+            }
+            data = rest;
+        }
+        Ok(())
+    }
+
+    /// Fail the cursor with the error of the job, which is told by the fetches of the pages not
+    /// fetched yet;
+    pub fn fail(&self, err_msg: &str) {
+        let mut state = self.lock();
+        if state.error.is_none() {
+            state.error = Some(err_msg.to_owned());
+        }
+        self.ready.notify_all();
+    }
+
+    /// All results are paged once the job ends;
+    pub fn finish(&self) {
+        let mut state = self.lock();
+        if !state.filling.is_empty() {
+            if let Err(err) = self.seal(&mut state) {
+                state.error.get_or_insert_with(|| err.to_string());
+            }
+        }
+        state.complete = true;
+        self.ready.notify_all();
+    }
+
+    /// Fetch the page of the token, waiting at most `wait` for it while the job is running, and
+    /// release the pages before it;
+    pub fn fetch(&self, token: u64, wait: Duration) -> Result<FetchedPage, String> {
+        let deadline = Instant::now() + wait;
+        let mut state = self.lock();
+        let index = token as usize;
+        while !state.released && !state.complete && state.error.is_none() {
+            if index < state.pages.len() {
+                break;
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(FetchedPage { data: vec![], next_token: token, last: false });
+            }
+            state = match self.ready.wait_timeout(state, deadline - now) {
+                Ok((state, _)) => state,
+                Err(_) => return Err("lock poisoned".to_owned()),
+            };
+        }
+        if state.released {
+            return Err(format!("cursor of job {} has been released", self.job_id));
+        }
+        if let Some(err) = state.error.as_ref() {
+            return Err(err.clone());
+        }
+        let state = &mut *state;
+        state.expires_at = Instant::now() + self.ttl;
+        for page in state.pages.iter_mut().take(index) {
+            if self.release_page(page) {
+                state.memory_pages -= 1;
+            }
+        }
+        let last = state.complete && index + 1 >= state.pages.len();
+        let data = match state.pages.get(index) {
+            Some(Page::Memory(data)) => data.clone(),
+            Some(Page::Spilled(path)) => read_page(path).map_err(|e| {
+                format!("fail to read the spilled page {} of job {}: {}", token, self.job_id, e)
+            })?,
+            Some(Page::Released) => {
+                return Err(format!("page {} of job {} has been released", token, self.job_id))
+            }
+            // past the end of the results
+            None => vec![],
+        };
+        let next_token = if index < state.pages.len() { token + 1 } else { token };
+        Ok(FetchedPage { data, next_token, last })
+    }
+
+    /// Release all pages, and drop the results paged later;
+    pub fn release(&self) {
+        let mut state = self.lock();
+        state.released = true;
+        state.filling.clear();
+        let mut pages = std::mem::take(&mut state.pages);
+        for page in pages.iter_mut() {
+            self.release_page(page);
+        }
+        state.memory_pages = 0;
+        // the directory is removed along with the files left in it;
+        state.spilled.take();
+        self.ready.notify_all();
+    }
+
+    fn is_expired(&self, now: Instant) -> bool {
+        self.lock().expires_at <= now
+    }
+
+    fn seal(&self, state: &mut PageState) -> io::Result<()> {
+        let data = std::mem::take(&mut state.filling);
+        state.filling_rows = 0;
+        let page = if state.memory_pages < MEMORY_PAGES {
+            state.memory_pages += 1;
+            Page::Memory(data)
+        } else {
+            if state.spilled.is_none() {
+                let temp = pegasus::temp::instance();
+                state.spilled = Some(temp.register(self.job_id, self.spill_dir.clone()));
+            }
+            let path = self.spill_dir.join(format!("page_{}", state.pages.len()));
+            write_page(&path, &data)?;
+            Page::Spilled(path)
+        };
+        state.pages.push(page);
+        self.ready.notify_all();
+        Ok(())
+    }
+
+    // release the page, and tell if it was kept in memory
+    fn release_page(&self, page: &mut Page) -> bool {
+        match std::mem::replace(page, Page::Released) {
+            Page::Memory(_) => true,
+            Page::Spilled(path) => {
+                if let Err(err) = fs::remove_file(&path) {
+                    warn!("fail to remove the page {:?} of job {}: {}", path, self.job_id, err);
+                }
+                false
+            }
+            Page::Released => false,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, PageState> {
+        self.state.lock().expect("lock poisoned")
+    }
+}
+
+// a page is spilled as the length of each encoded result followed by it
+fn write_page(path: &Path, data: &[Vec<u8>]) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut writer = BufWriter::new(File::create(path)?);
+    for bytes in data {
+        writer.write_all(&(bytes.len() as u32).to_le_bytes())?;
+        writer.write_all(bytes)?;
+    }
+    writer.flush()
+}
+
+fn read_page(path: &Path) -> io::Result<Vec<Vec<u8>>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut data = vec![];
+    let mut len = [0u8; 4];
+    loop {
+        match reader.read_exact(&mut len) {
+            Ok(()) => {}
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(err) => return Err(err),
+        }
+        let mut bytes = vec![0u8; u32::from_le_bytes(len) as usize];
+        reader.read_exact(&mut bytes)?;
+        data.push(bytes);
+    }
+    Ok(data)
+}
+
+/// The cursors of the paged jobs in current server;
+#[derive(Default)]
+pub struct PageCursors {
+    stores: Mutex<HashMap<u64, Arc<PageStore>>>,
+}
+
+impl PageCursors {
+    /// Open the cursor of the job, which replaces that of the former job of the same id if any;
+    pub fn open(&self, conf: &pb::JobConfig) -> Arc<PageStore> {
+        let ttl = if conf.page_ttl_ms == 0 {
+            DEFAULT_PAGE_TTL
+        } else {
+            Duration::from_millis(conf.page_ttl_ms)
+        };
+        let store = Arc::new(PageStore::new(conf.job_id, conf.page_size, ttl));
+        let mut stores = self.sweep();
+        if let Some(former) = stores.insert(conf.job_id, store.clone()) {
+            former.release();
+        }
+        store
+    }
+
+    /// Fetch the page of the token of the job, or an error if the cursor of the job isn't found,
+    /// e.g. it has expired;
+    pub fn fetch(&self, job_id: u64, token: u64, wait: Duration) -> Result<FetchedPage, String> {
+        let store = self.sweep().get(&job_id).cloned().ok_or_else(|| {
+            format!("cursor of job {} not found, which may have expired or been cancelled", job_id)
+        })?;
+        store.fetch(token, wait)
+    }
+
+    /// Cancel the cursor of the job and release its pages, and return if it is found;
+    pub fn cancel(&self, job_id: u64) -> bool {
+        match self.sweep().remove(&job_id) {
+            Some(store) => {
+                store.release();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Check if the cursor of the job is open;
+    pub fn is_open(&self, job_id: u64) -> bool {
+        self.sweep().contains_key(&job_id)
+    }
+
+    // release the expired cursors
+    fn sweep(&self) -> MutexGuard<'_, HashMap<u64, Arc<PageStore>>> {
+        let mut stores = self.stores.lock().expect("lock poisoned");
+        let now = Instant::now();
+        stores.retain(|job_id, store| {
+            if store.is_expired(now) {
+                info!("cursor of job {} expired;", job_id);
+                store.release();
+                false
+            } else {
+                true
+            }
+        });
+        stores
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct RowsEncoder;
+
+    impl EncodeFunction<u32> for RowsEncoder {
+        fn encode(&self, data: Vec<u32>) -> Vec<u8> {
+            data.into_iter().map(|d| d as u8).collect()
+        }
+    }
+
+    fn rows(page: &FetchedPage) -> Vec<u8> {
+        page.data.iter().flatten().cloned().collect()
+    }
+
+    fn conf(job_id: u64, page_size: u32) -> pb::JobConfig {
+        pb::JobConfig { job_id, page_size, ..Default::default() }
+    }
+
+    #[test]
+    fn fetch_pages_test() {
+        let cursors = PageCursors::default();
+        let store = cursors.open(&conf(1, 3));
+        store.push((0..5).collect(), &RowsEncoder).unwrap();
+        store.push((5..7).collect(), &RowsEncoder).unwrap();
+        let wait = Duration::from_millis(10);
+        let page = cursors.fetch(1, 0, wait).unwrap();
+        assert_eq!((rows(&page), page.next_token, page.last), (vec![0, 1, 2], 1, false));
+        let page = cursors.fetch(1, 1, wait).unwrap();
+        assert_eq!((rows(&page), page.next_token, page.last), (vec![3, 4, 5], 2, false));
+        // the last partial page is not ready until the job ends
+        let page = cursors.fetch(1, 2, wait).unwrap();
+        assert_eq!((rows(&page), page.next_token, page.last), (vec![], 2, false));
+        store.finish();
+        let page = cursors.fetch(1, 2, wait).unwrap();
+        assert_eq!((rows(&page), page.next_token, page.last), (vec![6], 3, true));
+        // past the end
+        let page = cursors.fetch(1, 3, wait).unwrap();
+        assert_eq!((rows(&page), page.next_token, page.last), (vec![], 3, true));
+        // the pages before a fetched one are released
+        assert!(cursors.fetch(1, 0, wait).is_err());
+    }
+
+    #[test]
+    fn spill_pages_test() {
+        let cursors = PageCursors::default();
+        let store = cursors.open(&conf(2, 1));
+        let pages = MEMORY_PAGES as u32 + 2;
+        store.push((0..pages).collect(), &RowsEncoder).unwrap();
+        store.finish();
+        assert!(store.spill_dir.join(format!("page_{}", MEMORY_PAGES)).exists());
+        assert_eq!(pegasus::temp::live_resources(2), 1);
+        for token in 0..pages as u64 {
+            let page = cursors.fetch(2, token, Duration::from_millis(10)).unwrap();
+            assert_eq!(rows(&page), vec![token as u8]);
+        }
+        // the spilled pages are released as they are fetched, or once canceled
+        assert!(!store.spill_dir.join(format!("page_{}", MEMORY_PAGES)).exists());
+        assert!(cursors.cancel(2));
+        assert!(!store.spill_dir.exists());
+        assert_eq!(pegasus::temp::live_resources(2), 0);
+        assert!(!cursors.is_open(2));
+        assert!(cursors.fetch(2, 0, Duration::from_millis(10)).is_err());
+    }
+
+    #[test]
+    fn expire_cursor_test() {
+        let cursors = PageCursors::default();
+        let store = cursors.open(&pb::JobConfig {
+            job_id: 3,
+            page_size: 1,
+            page_ttl_ms: 1,
+            ..Default::default()
+        });
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(!cursors.is_open(3));
+        // the results of the job are dropped once its cursor expires
+        store.push(vec![1], &RowsEncoder).unwrap();
+        assert!(store.lock().pages.is_empty());
+    }
+
+    #[test]
+    fn failed_job_test() {
+        let cursors = PageCursors::default();
+        let store = cursors.open(&conf(4, 2));
+        store.fail("job failed");
+        let err = cursors.fetch(4, 0, Duration::from_millis(10)).unwrap_err();
+        assert_eq!(err, "job failed");
+    }
+}
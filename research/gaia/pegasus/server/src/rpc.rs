@@ -13,40 +13,120 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
+use crate::batch::{BatchJobs, BatchOutput};
 use crate::generated::protocol as pb;
 use crate::service::{Output, Service};
 use crate::AnyData;
 use prost::Message;
 use std::io::Write;
 use std::net::SocketAddr;
-use tokio::sync::mpsc::{self, UnboundedSender};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::mpsc::{self, Sender};
 use tokio::time::Instant;
-use tokio_stream::wrappers::UnboundedReceiverStream;
+use tokio_stream::wrappers::ReceiverStream;
 use tonic::transport::Server;
 use tonic::{Request, Response, Status};
 
+/// The most responses of a job queued for the client, beyond which the sinks of the job wait for
+/// the client to drain the queue, which holds back the workers of the job instead of buffering
+/// the results in the server;
+pub const RESULT_QUEUE_CAPACITY: usize = 64;
+/// How long the sinks of a job wait for the client to drain the full queue at most, beyond which
+/// the client is taken as stalled and the job is cancelled;
+pub const STALL_DEADLINE: Duration = Duration::from_secs(60);
+// how often the full queue is checked by the waiting sink;
+const QUEUE_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// The output of a job into the stream of the rpc, or of a batch of jobs by the frames of the
+/// batch, see `crate::batch`;
 #[derive(Clone)]
-pub struct RpcOutput {
-    tx: UnboundedSender<Result<pb::JobResponse, Status>>,
+pub struct RpcOutput<T = pb::JobResponse> {
+    tx: Sender<Result<T, Status>>,
     timer: Option<Instant>,
     job_id: u64,
+    stall_deadline: Duration,
+    // cancels the job once its client stalls or goes away;
+    on_stall: Option<Arc<dyn Fn() + Send + Sync>>,
+    // set once the client stalls or goes away, after which the responses are dropped;
+    abandoned: Arc<AtomicBool>,
 }
 
-impl RpcOutput {
-    pub fn new(tx: UnboundedSender<Result<pb::JobResponse, Status>>, job_id: u64) -> Self {
-        RpcOutput { tx, timer: None, job_id }
+impl<T: Send + 'static> RpcOutput<T> {
+    pub fn new(tx: Sender<Result<T, Status>>, job_id: u64) -> Self {
+        RpcOutput {
+            tx,
+            timer: None,
+            job_id,
+            stall_deadline: STALL_DEADLINE,
+            on_stall: None,
+            abandoned: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn with_timer(tx: Sender<Result<T, Status>>, job_id: u64) -> Self {
+        let mut output = RpcOutput::new(tx, job_id);
+        output.timer = Some(Instant::now());
+        output
+    }
+
+    /// Call `on_stall` once the client leaves the queue full beyond `deadline`, or goes away,
+    /// which is to cancel the job;
+    pub fn with_stall_deadline<F>(mut self, deadline: Duration, on_stall: F) -> Self
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        self.stall_deadline = deadline;
+        self.on_stall = Some(Arc::new(on_stall));
+        self
+    }
+
+    fn abandon(&self) {
+        if !self.abandoned.swap(true, Ordering::SeqCst) {
+            if let Some(on_stall) = self.on_stall.as_ref() {
+                on_stall();
+            }
+        }
     }
 
-    pub fn with_timer(tx: UnboundedSender<Result<pb::JobResponse, Status>>, job_id: u64) -> Self {
-        RpcOutput { tx, timer: Some(Instant::now()), job_id }
+    /// Queue the response for the client, which blocks the calling worker while the queue is
+    /// full, at most the stall deadline;
+    fn queue(&self, res: T) {
+        if self.abandoned.load(Ordering::SeqCst) {
+            return;
+        }
+        let deadline = Instant::now() + self.stall_deadline;
+        let mut res = Ok(res);
+        loop {
+            match self.tx.try_send(res) {
+                Ok(()) => return,
+                Err(TrySendError::Full(back)) => {
+                    if Instant::now() >= deadline {
+                        error!(
+                            "Job[{}]: client stalls beyond {:?}, cancel the job;",
+                            self.job_id, self.stall_deadline
+                        );
+                        self.abandon();
+                        return;
+                    }
+                    res = back;
+                    std::thread::sleep(QUEUE_POLL_INTERVAL);
+                }
+                Err(TrySendError::Closed(_)) => {
+                    error!("Job[{}]: send result into rpc output failure;", self.job_id);
+                    self.abandon();
+                    return;
+                }
+            }
+        }
     }
 }
 
 impl Output for RpcOutput {
     fn send(&self, res: pb::JobResponse) {
-        if let Err(_) = self.tx.send(Ok(res)) {
-            error!("Job[{}]: send result into rpc output failure;", self.job_id);
-        }
+        self.queue(res)
     }
 
     fn close(&self) {
@@ -58,6 +138,48 @@ impl Output for RpcOutput {
     }
 }
 
+/// Accept the job with its responses streamed to the client through a bounded queue, where the
+/// job is cancelled once the client stalls beyond `STALL_DEADLINE`;
+fn stream_job<D: AnyData>(
+    service: &Service<D>, req: pb::JobRequest, job_id: u64, report: bool,
+) -> ReceiverStream<Result<pb::JobResponse, Status>> {
+    let (tx, rx) = mpsc::channel(RESULT_QUEUE_CAPACITY);
+    let output =
+        if report { RpcOutput::with_timer(tx, job_id) } else { RpcOutput::new(tx, job_id) };
+    let canceller = service.clone();
+    let output = output.with_stall_deadline(STALL_DEADLINE, move || {
+        canceller.cancel_job(job_id);
+    });
+    let service = service.clone();
+    // the output waiting for the client to drain the queue blocks current thread
+    tokio::task::spawn_blocking(move || service.accept(req, output));
+    ReceiverStream::new(rx)
+}
+
+impl BatchOutput for RpcOutput<pb::BatchResponse> {
+    fn send(&self, res: pb::BatchResponse) {
+        self.queue(res)
+    }
+}
+
+/// Accept the queries of the batch with their frames streamed to the client through a bounded
+/// queue, where all the jobs of the batch are cancelled once the client stalls or goes away;
+fn stream_batch<D: AnyData>(
+    service: &Service<D>, batch: pb::BatchRequest,
+) -> ReceiverStream<Result<pb::BatchResponse, Status>> {
+    let (tx, rx) = mpsc::channel(RESULT_QUEUE_CAPACITY);
+    let jobs = BatchJobs::new(&batch);
+    let first_job = jobs.job_ids().first().copied().unwrap_or_default();
+    let canceller = service.clone();
+    let cancelled = jobs.clone();
+    let output = RpcOutput::new(tx, first_job).with_stall_deadline(STALL_DEADLINE, move || {
+        canceller.cancel_batch(&cancelled);
+    });
+    let service = service.clone();
+    tokio::task::spawn_blocking(move || service.accept_batch(batch, &jobs, output));
+    ReceiverStream::new(rx)
+}
+
 #[derive(Clone)]
 pub struct RpcService<D: AnyData> {
     inner: Service<D>,
@@ -72,7 +194,8 @@ pub struct DebugRpcService<D: AnyData> {
 
 #[tonic::async_trait]
 impl<D: AnyData> pb::job_service_server::JobService for RpcService<D> {
-    type SubmitStream = UnboundedReceiverStream<Result<pb::JobResponse, Status>>;
+    type SubmitStream = ReceiverStream<Result<pb::JobResponse, Status>>;
+    type SubmitBatchStream = ReceiverStream<Result<pb::BatchResponse, Status>>;
 
     async fn submit(
         &self, req: Request<pb::JobRequest>,
@@ -83,22 +206,62 @@ impl<D: AnyData> pb::job_service_server::JobService for RpcService<D> {
         } else {
             return Err(Status::invalid_argument("job conf not specified;"));
         };
-        let (tx, rx) = mpsc::unbounded_channel();
-        let output = if self.report {
-            // let _g = report_memory(job_id);
-            RpcOutput::with_timer(tx, job_id)
-        } else {
-            RpcOutput::new(tx, job_id)
-        };
-        self.inner.accept(job_req, output);
-        let rx = UnboundedReceiverStream::new(rx);
-        Ok(Response::new(rx))
+        Ok(Response::new(stream_job(&self.inner, job_req, job_id, self.report)))
+    }
+
+    async fn submit_batch(
+        &self, req: Request<pb::BatchRequest>,
+    ) -> Result<Response<Self::SubmitBatchStream>, Status> {
+        Ok(Response::new(stream_batch(&self.inner, req.into_inner())))
+    }
+
+    async fn drain(
+        &self, req: Request<pb::DrainRequest>,
+    ) -> Result<Response<pb::DrainResponse>, Status> {
+        drain_jobs(&self.inner, req.into_inner()).await
+    }
+
+    async fn capabilities(
+        &self, _req: Request<pb::CapabilitiesRequest>,
+    ) -> Result<Response<pb::CapabilitiesResponse>, Status> {
+        Ok(Response::new(self.inner.capabilities()))
+    }
+
+    async fn schema(
+        &self, _req: Request<pb::SchemaRequest>,
+    ) -> Result<Response<pb::SchemaResponse>, Status> {
+        Ok(Response::new(self.inner.schema()))
+    }
+
+    async fn fetch_page(
+        &self, req: Request<pb::FetchPageRequest>,
+    ) -> Result<Response<pb::FetchPageResponse>, Status> {
+        fetch_page(&self.inner, req.into_inner()).await
+    }
+
+    async fn cancel_cursor(
+        &self, req: Request<pb::CancelCursorRequest>,
+    ) -> Result<Response<pb::CancelCursorResponse>, Status> {
+        Ok(Response::new(self.inner.cancel_cursor(req.into_inner())))
+    }
+
+    async fn slow_queries(
+        &self, req: Request<pb::SlowQueriesRequest>,
+    ) -> Result<Response<pb::SlowQueriesResponse>, Status> {
+        Ok(Response::new(self.inner.slow_queries(req.into_inner())))
+    }
+
+    async fn latency_templates(
+        &self, req: Request<pb::LatencyTemplatesRequest>,
+    ) -> Result<Response<pb::LatencyTemplatesResponse>, Status> {
+        Ok(Response::new(self.inner.latency_templates(req.into_inner())))
     }
 }
 
 #[tonic::async_trait]
 impl<D: AnyData> pb::job_service_server::JobService for DebugRpcService<D> {
-    type SubmitStream = UnboundedReceiverStream<Result<pb::JobResponse, Status>>;
+    type SubmitStream = ReceiverStream<Result<pb::JobResponse, Status>>;
+    type SubmitBatchStream = ReceiverStream<Result<pb::BatchResponse, Status>>;
 
     async fn submit(
         &self, req: Request<pb::JobRequest>,
@@ -143,17 +306,90 @@ impl<D: AnyData> pb::job_service_server::JobService for DebugRpcService<D> {
         } else {
             return Err(Status::invalid_argument("job conf not specified;"));
         }
-        let (tx, rx) = mpsc::unbounded_channel();
-        let output = if self.report {
-            // let _g = report_memory(job_id);
-            RpcOutput::with_timer(tx, job_id)
-        } else {
-            RpcOutput::new(tx, job_id)
-        };
-        self.inner.accept(job_req, output);
-        let rx = UnboundedReceiverStream::new(rx);
-        Ok(Response::new(rx))
+        Ok(Response::new(stream_job(&self.inner, job_req, job_id, self.report)))
+    }
+
+    async fn submit_batch(
+        &self, req: Request<pb::BatchRequest>,
+    ) -> Result<Response<Self::SubmitBatchStream>, Status> {
+        Ok(Response::new(stream_batch(&self.inner, req.into_inner())))
+    }
+
+    async fn drain(
+        &self, req: Request<pb::DrainRequest>,
+    ) -> Result<Response<pb::DrainResponse>, Status> {
+        drain_jobs(&self.inner, req.into_inner()).await
+    }
+
+    async fn capabilities(
+        &self, _req: Request<pb::CapabilitiesRequest>,
+    ) -> Result<Response<pb::CapabilitiesResponse>, Status> {
+        Ok(Response::new(self.inner.capabilities()))
+    }
+
+    async fn schema(
+        &self, _req: Request<pb::SchemaRequest>,
+    ) -> Result<Response<pb::SchemaResponse>, Status> {
+        Ok(Response::new(self.inner.schema()))
+    }
+
+    async fn fetch_page(
+        &self, req: Request<pb::FetchPageRequest>,
+    ) -> Result<Response<pb::FetchPageResponse>, Status> {
+        fetch_page(&self.inner, req.into_inner()).await
     }
+
+    async fn cancel_cursor(
+        &self, req: Request<pb::CancelCursorRequest>,
+    ) -> Result<Response<pb::CancelCursorResponse>, Status> {
+        Ok(Response::new(self.inner.cancel_cursor(req.into_inner())))
+    }
+
+    async fn slow_queries(
+        &self, req: Request<pb::SlowQueriesRequest>,
+    ) -> Result<Response<pb::SlowQueriesResponse>, Status> {
+        Ok(Response::new(self.inner.slow_queries(req.into_inner())))
+    }
+
+    async fn latency_templates(
+        &self, req: Request<pb::LatencyTemplatesRequest>,
+    ) -> Result<Response<pb::LatencyTemplatesResponse>, Status> {
+        Ok(Response::new(self.inner.latency_templates(req.into_inner())))
+    }
+}
+
+async fn drain_jobs<D: AnyData>(
+    service: &Service<D>, req: pb::DrainRequest,
+) -> Result<Response<pb::DrainResponse>, Status> {
+    let deadline = Duration::from_millis(req.deadline_ms);
+    let service = service.clone();
+    // waiting for the running jobs blocks current thread, as may the compiler told of the drain
+    let report = tokio::task::spawn_blocking(move || {
+        let report = pegasus::drain(deadline);
+        service.on_drain();
+        report
+    })
+    .await
+    .map_err(|e| Status::internal(format!("drain failure: {}", e)))?;
+    info!(
+        "drained with jobs {:?} completed, and {:?} canceled;",
+        report.completed, report.cancelled
+    );
+    Ok(Response::new(pb::DrainResponse {
+        completed: report.completed,
+        cancelled: report.cancelled,
+    }))
+}
+
+async fn fetch_page<D: AnyData>(
+    service: &Service<D>, req: pb::FetchPageRequest,
+) -> Result<Response<pb::FetchPageResponse>, Status> {
+    let service = service.clone();
+    // waiting for the page blocks current thread
+    let res = tokio::task::spawn_blocking(move || service.fetch_page(req))
+        .await
+        .map_err(|e| Status::internal(format!("fetch page failure: {}", e)))?;
+    Ok(Response::new(res))
 }
 
 pub struct RpcServer<S: pb::job_service_server::JobService> {
@@ -194,3 +430,98 @@ impl<S: pb::job_service_server::JobService> RpcServer<S> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pegasus::api::{ResultSet, Sink};
+    use pegasus::{Configuration, JobConf, JobGuard};
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Mutex;
+
+    // sends a response for each of the `total` records into the output, counting those sent;
+    fn run_job(job_id: u64, total: u32, output: RpcOutput, sent: &Arc<AtomicUsize>) -> JobGuard {
+        pegasus::startup(Configuration::singleton()).ok();
+        let mut conf = JobConf::new(job_id, "rpc_output_test", 1);
+        // the sink yields in small slices, so that the cancellation of the job is seen before
+        // all the records are sent
+        conf.batch_size = 16;
+        conf.slice_records = 16;
+        pegasus::run(conf, |worker| {
+            let output = output.clone();
+            let sent = sent.clone();
+            worker.dataflow(move |builder| {
+                let src = builder.input_from_iter(0..total)?;
+                src.sink_by(|_meta| {
+                    move |_tag, result| {
+                        if let ResultSet::Data(data) = result {
+                            for d in data {
+                                let result = Some(pb::job_response::Result::Data(vec![d as u8]));
+                                output.send(pb::JobResponse { job_id, result });
+                                sent.fetch_add(1, Ordering::SeqCst);
+                            }
+                        }
+                    }
+                })
+            })
+        })
+        .expect("submit job failure")
+        .expect("job not run")
+    }
+
+    #[test]
+    fn slow_consumer_test() {
+        let (tx, mut rx) = mpsc::channel(4);
+        let stalled = Arc::new(AtomicBool::new(false));
+        let stall = stalled.clone();
+        let output = RpcOutput::new(tx, 101)
+            .with_stall_deadline(Duration::from_secs(10), move || {
+                stall.store(true, Ordering::SeqCst)
+            });
+        let sent = Arc::new(AtomicUsize::new(0));
+        let mut guard = run_job(101, 200, output, &sent);
+        let mut received = 0;
+        while let Some(res) = rx.blocking_recv() {
+            assert!(res.is_ok());
+            received += 1;
+            // the job is held back by the client instead of queueing more than the capacity
+            assert!(sent.load(Ordering::SeqCst) <= received + 4);
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(received, 200);
+        assert!(guard.join().is_ok());
+        assert!(!stalled.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn stalled_consumer_test() {
+        let (tx, mut rx) = mpsc::channel(4);
+        let stalled = Arc::new(AtomicBool::new(false));
+        let guards: Arc<Mutex<Option<JobGuard>>> = Arc::new(Mutex::new(None));
+        let stall = stalled.clone();
+        let cancel = guards.clone();
+        let output =
+            RpcOutput::new(tx, 102).with_stall_deadline(Duration::from_millis(200), move || {
+                stall.store(true, Ordering::SeqCst);
+                if let Some(mut guard) = cancel.lock().unwrap().take() {
+                    guard.cancel_execute();
+                }
+            });
+        let sent = Arc::new(AtomicUsize::new(0));
+        let guard = run_job(102, 10000, output, &sent);
+        guards.lock().unwrap().replace(guard);
+        // the client never reads, until the responses are no more sent
+        let start = std::time::Instant::now();
+        while !stalled.load(Ordering::SeqCst) {
+            assert!(start.elapsed() < Duration::from_secs(10), "job not cancelled");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        let mut received = 0;
+        while rx.blocking_recv().is_some() {
+            received += 1;
+        }
+        // only the responses queued before the client stalls are sent
+        assert_eq!(received, 4);
+        assert!(sent.load(Ordering::SeqCst) < 10000);
+    }
+}
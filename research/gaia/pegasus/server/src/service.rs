@@ -13,22 +13,58 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
-use crate::factory::JobCompiler;
+use crate::batch::{self, BatchJobs, BatchOutput, QueryOutput};
+use crate::capability;
+use crate::dependency::{self, Dependency, JobOutput};
+use crate::error::QueryError;
+use crate::factory::{JobCompiler, QuantileValues};
 use crate::generated::protocol as pb;
-use crate::materialize::ShadeMapFactory;
+use crate::latency;
+use crate::materialize::{
+    approx_distinct, count, quantiles, with_consolidated, with_unbulked, ShadeAccumFactory,
+    ShadeMapFactory,
+};
+use crate::paging::{PageCursors, PageStore, DEFAULT_FETCH_WAIT};
 use crate::AnyData;
 use crossbeam_utils::sync::ShardedLock;
 use pegasus::api::accum::{Accumulator, ToListAccum};
-use pegasus::api::function::EncodeFunction;
-use pegasus::api::{Count, Fold, Group, KeyBy, ResultSet, Sink, RANGES};
-use pegasus::codec::ShadeCodec;
+use pegasus::api::function::{EncodeFunction, FnResult};
+use pegasus::api::{Branch, Fold, Group, KeyBy, Map, ResultSet, Sink, RANGES};
+use pegasus::codec::{Decode, Encode, ShadeCodec};
+use pegasus::communication::Pipeline;
+use pegasus::dataflow::DataflowBuilder;
 use pegasus::stream::Stream;
-use pegasus::{BuildJobError, Data, JobConf, JobGuard, NeverClone};
+use pegasus::{
+    BuildJobError, Data, JobConf, JobGuard, JobProfile, JobStatus, LatencyMode, NeverClone,
+    OperatorProfile, OverflowPolicy, SlowQueryRecord, Tag, WorkerHint,
+};
+use prost::Message;
+use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
 use std::fmt::Debug;
-use std::sync::Arc;
+use std::hash::Hasher;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
 
-pub trait Output: Send + 'static {
+/// The error code of the jobs rejected for their malformed plans, see `JobCompiler::validate`,
+/// or failed to be built by them, while the failures of the jobs are of code 0; The errors are
+/// told apart by their kinds instead since they are classified, see `QueryError`
+pub const INVALID_PLAN_ERR_CODE: i32 = 1;
+/// The error code of the jobs rejected as the server is draining, which could be submitted to
+/// other servers instead
+pub const DRAINING_ERR_CODE: i32 = 2;
+/// The error code of the jobs rejected for the features of their plans unsupported by current
+/// server, e.g. of newer plan versions, see `capability::check_request`
+pub const UNSUPPORTED_PLAN_ERR_CODE: i32 = 3;
+/// The error code of the pages failed to be fetched, e.g. of a cursor expired or cancelled, while
+/// the pages of a failed job are failed with the error of the job
+pub const CURSOR_ERR_CODE: i32 = 4;
+/// The largest limit at the end of a plan, under which the job runs in the eager mode if its
+/// latency mode is `AUTO`, see `pegasus::LatencyMode`
+pub const EAGER_LIMIT: u32 = 16;
+
+pub trait Output: Send + Sync + 'static {
     fn send(&self, res: pb::JobResponse);
 
     fn close(&self);
@@ -37,48 +73,429 @@ pub trait Output: Send + 'static {
 pub struct JobResultSink<O: Output> {
     job_id: u64,
     output: O,
+    // the number of workers of the job in current server;
+    workers: u32,
+    // the scopes ended on part of the workers, with the number of the workers;
+    scope_ends: Arc<Mutex<HashMap<Tag, u32>>>,
+    // sends the profile of the job once all clones of the sink are dropped, if it is profiled;
+    profile: Option<Arc<ProfileReporter<O>>>,
+    // keeps the results in pages instead of sending them, if the job is paged;
+    pages: Option<Arc<PageWriter>>,
+    // tells the client the job is complete once the sinks of all workers have ended;
+    completion: Arc<Completion>,
+    // the results are shipped by the workers as they are, and encoded in the service;
+    in_service: bool,
+    // the query template and the name of the job, whose latencies are recorded once it completes;
+    template: Option<(u64, Arc<str>)>,
+    // the results are kept for the jobs depending on the job, see `crate::dependency`;
+    kept: bool,
+}
+
+struct Completion {
+    // the workers whose sinks have ended;
+    ended: AtomicU32,
+    // the responses of the results sent;
+    batches: AtomicU64,
+    // the bytes of the results shipped by the workers;
+    result_bytes: AtomicU64,
+    // set once an error is sent, after which the job never completes;
+    failed: AtomicBool,
+    // set once the completion is sent;
+    sent: AtomicBool,
+    start: Instant,
+    // the microseconds from the start until the first results are sent, `u64::MAX` if none;
+    first_result_us: AtomicU64,
+}
+
+impl Completion {
+    fn new() -> Self {
+        Completion {
+            ended: AtomicU32::new(0),
+            batches: AtomicU64::new(0),
+            result_bytes: AtomicU64::new(0),
+            failed: AtomicBool::new(false),
+            sent: AtomicBool::new(false),
+            start: Instant::now(),
+            first_result_us: AtomicU64::new(u64::MAX),
+        }
+    }
 }
 
 impl<O: Output> JobResultSink<O> {
     pub fn new(job_id: u64, output: O) -> Self {
-        JobResultSink { job_id, output }
+        JobResultSink::with_workers(job_id, 1, output)
+    }
+
+    pub fn with_workers(job_id: u64, workers: u32, output: O) -> Self {
+        let scope_ends = Arc::new(Mutex::new(HashMap::new()));
+        let completion = Arc::new(Completion::new());
+        JobResultSink {
+            job_id,
+            output,
+            workers,
+            scope_ends,
+            profile: None,
+            pages: None,
+            completion,
+            in_service: false,
+            template: None,
+            kept: false,
+        }
+    }
+
+    /// Send the profile of the job after its results, once the sinks of all its workers are
+    /// dropped, which are built after the other operators of the workers and so dropped after them;
+    pub fn with_profile(mut self) -> Self
+    where
+        O: Clone,
+    {
+        let reporter = ProfileReporter { job_id: self.job_id, output: self.output.clone() };
+        self.profile = Some(Arc::new(reporter));
+        self
+    }
+
+    /// Keep the results in the pages of the store instead of sending them, which are complete
+    /// once the sinks of all workers are dropped;
+    pub fn with_pages(mut self, store: Arc<PageStore>) -> Self {
+        self.pages = Some(Arc::new(PageWriter { store }));
+        self
+    }
+
+    /// Encode the results in the service instead of on the workers, which ship the records as they
+    /// are, see `JobConfig.encode_in_service`;
+    pub fn with_service_encoding(mut self) -> Self {
+        self.in_service = true;
+        self
+    }
+
+    /// Record the latencies of the job into those of the query template once the job completes,
+    /// see `crate::latency`;
+    pub fn with_template(mut self, template: u64, job_name: &str) -> Self {
+        self.template = Some((template, job_name.into()));
+        self
+    }
+
+    /// Keep the results of the job for the jobs depending on it besides sending them, which are
+    /// told of the end of the job as it completes or fails, see `crate::dependency`;
+    pub fn with_kept_output(mut self) -> Self {
+        dependency::job_outputs().keep(self.job_id);
+        self.kept = true;
+        self
+    }
+
+    /// Keep the results of the worker for the jobs depending on the job, if its output is kept;
+    fn keep_results<D: Data>(&self, worker: u32, data: &[D]) {
+        if self.kept && !data.is_empty() {
+            dependency::job_outputs().push(self.job_id, worker, data.to_vec());
+        }
+    }
+
+    /// Send the results encoded by `ec`, or keep them in pages if the job is paged;
+    pub fn on_results<D: 'static>(&self, data: Vec<D>, ec: &dyn EncodeFunction<D>) {
+        if let Some(pages) = self.pages.as_ref() {
+            self.on_first_result();
+            if let Err(err) = pages.store.push(data, ec) {
+                self.on_error(err.as_ref());
+            }
+        } else {
+            self.on_encoded(ec.try_encode(data));
+        }
+    }
+
+    /// Tell the client the results are kept in pages to be fetched by the cursor;
+    pub fn on_cursor(&self, cursor: pb::PageCursor) {
+        let result = Some(pb::job_response::Result::Cursor(cursor));
+        let res = pb::JobResponse { job_id: self.job_id, result };
+        self.output.send(res);
+    }
+
+    /// Send the plan of the job explained instead of running it;
+    pub fn on_plan(&self, plan: pb::JobPlan) {
+        let result = Some(pb::job_response::Result::Plan(plan));
+        let res = pb::JobResponse { job_id: self.job_id, result };
+        self.output.send(res);
     }
 
     pub fn on_next(&self, data: Vec<u8>) {
+        self.on_first_result();
+        self.completion.batches.fetch_add(1, Ordering::SeqCst);
+        if !self.in_service {
+            self.on_shipped(data.len());
+        }
         let result = Some(pb::job_response::Result::Data(data));
         let res = pb::JobResponse { job_id: self.job_id, result };
         self.output.send(res);
     }
 
-    pub fn on_error(&self, err: &dyn std::error::Error) {
-        error!("job[{}] get error {}", self.job_id, err);
-        let err_msg = format!("{}", err);
-        self.on_err_msg(0, err_msg);
+    /// Send the encoded results, or the error if they fail to be encoded;
+    pub fn on_encoded(&self, encoded: FnResult<Vec<u8>>) {
+        match encoded {
+            Ok(data) => self.on_next(data),
+            Err(err) => self.on_error(err.as_ref()),
+        }
     }
 
-    pub fn on_err_msg(&self, err_code: i32, err_msg: impl Into<String>) {
-        let err_msg = err_msg.into();
-        error!("job[{}] get error {}", self.job_id, err_msg);
-        let result = Some(pb::job_response::Result::Err(pb::JobError { err_code, err_msg }));
+    /// Send the error, classified as `QueryError::Internal` unless it is a `QueryError` or is
+    /// caused by one;
+    pub fn on_error(&self, err: &(dyn std::error::Error + 'static)) {
+        self.on_query_error(&QueryError::classify(err));
+    }
+
+    /// Send the error, which fails the job, with the messages of its causes written to the logs;
+    pub fn on_query_error(&self, err: &QueryError) {
+        error!("job[{}] get error {}", self.job_id, err.report());
+        self.completion.failed.store(true, Ordering::SeqCst);
+        if self.kept {
+            dependency::job_outputs().end(self.job_id, Some(err.to_string()));
+        }
+        if let Some(pages) = self.pages.as_ref() {
+            pages.store.fail(&err.to_string());
+        }
+        let result = Some(pb::job_response::Result::Err(err.to_pb()));
         let res = pb::JobResponse { job_id: self.job_id, result };
         self.output.send(res);
     }
 
+    // the time of the first results, which is only read once the job completes;
+    fn on_first_result(&self) {
+        let first_result_us = &self.completion.first_result_us;
+        if self.template.is_some() && first_result_us.load(Ordering::Relaxed) == u64::MAX {
+            let elapsed = self.completion.start.elapsed().as_micros() as u64;
+            let _ = first_result_us.compare_exchange(
+                u64::MAX,
+                elapsed,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            );
+        }
+    }
+
+    // the bytes of the results shipped by a worker, encoded or as they are;
+    fn on_shipped(&self, bytes: usize) {
+        self.completion.result_bytes.fetch_add(bytes as u64, Ordering::SeqCst);
+    }
+
+    /// Send the end of the scope as a marker of the result stream, once the scope has ended on
+    /// all workers of the job in current server;
+    pub fn on_scope_end(&self, tag: Tag) {
+        let ended = {
+            let mut scope_ends = self.scope_ends.lock().expect("lock poisoned");
+            let count = scope_ends.entry(tag.clone()).or_insert(0);
+            *count += 1;
+            if *count >= self.workers {
+                scope_ends.remove(&tag);
+                true
+            } else {
+                false
+            }
+        };
+        if ended {
+            let scope_end = pb::ScopeEnd { tag: tag.as_slice().to_vec() };
+            let result = Some(pb::job_response::Result::ScopeEnd(scope_end));
+            let res = pb::JobResponse { job_id: self.job_id, result };
+            self.output.send(res);
+        }
+    }
+
+    /// The results of a worker of the job have all been sent, e.g. as its sink has ended, which
+    /// completes the job once those of all workers of the job in current server have been sent;
+    /// The results of each worker are sent before it ends, so the completion never goes ahead of
+    /// any result;
     pub fn close(&self) {
+        let ended = self.completion.ended.fetch_add(1, Ordering::SeqCst) + 1;
+        if ended == self.workers {
+            self.on_complete();
+        }
+        self.output.close();
+    }
+
+    /// All results of the job have been sent at once, e.g. of the job answered without running;
+    pub fn finish(&self) {
+        self.on_complete();
         self.output.close();
     }
+
+    /// Tell the client the job is complete ahead of tearing it down, unless it has failed, or its
+    /// results are kept in pages whose end is told by the last page instead; The latencies of the
+    /// job completed are recorded, where the first results of a job without any are taken as
+    /// sent once it completes;
+    fn on_complete(&self) {
+        let completion = &self.completion;
+        if completion.failed.load(Ordering::SeqCst) || completion.sent.swap(true, Ordering::SeqCst)
+        {
+            return;
+        }
+        if self.kept {
+            dependency::job_outputs().end(self.job_id, None);
+        }
+        let elapsed = completion.start.elapsed();
+        if let Some((template, job_name)) = self.template.as_ref() {
+            let first_result = match completion.first_result_us.load(Ordering::SeqCst) {
+                u64::MAX => elapsed,
+                us => Duration::from_micros(us),
+            };
+            latency::latency_recorder().record(*template, job_name, elapsed, first_result);
+        }
+        if self.pages.is_some() {
+            return;
+        }
+        let stats = pb::JobStats {
+            workers: self.workers,
+            batches: completion.batches.load(Ordering::SeqCst),
+            elapsed_us: elapsed.as_micros() as u64,
+            result_bytes: completion.result_bytes.load(Ordering::SeqCst),
+        };
+        let complete = pb::JobComplete { stats: Some(stats) };
+        let result = Some(pb::job_response::Result::Complete(complete));
+        self.output.send(pb::JobResponse { job_id: self.job_id, result });
+    }
 }
 
 impl<O: Output + Clone> Clone for JobResultSink<O> {
     fn clone(&self) -> Self {
-        JobResultSink { job_id: self.job_id, output: self.output.clone() }
+        JobResultSink {
+            job_id: self.job_id,
+            output: self.output.clone(),
+            workers: self.workers,
+            scope_ends: self.scope_ends.clone(),
+            profile: self.profile.clone(),
+            pages: self.pages.clone(),
+            completion: self.completion.clone(),
+            in_service: self.in_service,
+            template: self.template.clone(),
+            kept: self.kept,
+        }
+    }
+}
+
+struct PageWriter {
+    store: Arc<PageStore>,
+}
+
+impl Drop for PageWriter {
+    fn drop(&mut self) {
+        self.store.finish();
+    }
+}
+
+struct ProfileReporter<O: Output> {
+    job_id: u64,
+    output: O,
+}
+
+impl<O: Output> Drop for ProfileReporter<O> {
+    fn drop(&mut self) {
+        if let Some(profile) = pegasus::fetch_job_profile(self.job_id) {
+            let result = Some(pb::job_response::Result::Profile(profile_to_pb(&profile)));
+            self.output.send(pb::JobResponse { job_id: self.job_id, result });
+        }
+    }
+}
+
+/// Convert the profile of a job in current server into the `JobProfile` sent to the client;
+pub fn profile_to_pb(profile: &JobProfile) -> pb::JobProfile {
+    let mut pb_profile = operators_to_pb(&profile.operators);
+    pb_profile.decisions = profile
+        .decisions
+        .iter()
+        .map(|d| pb::AdaptiveDecision {
+            name: d.name.clone(),
+            worker: d.worker,
+            scope: d.scope.clone(),
+            choice: if d.choice == Branch::Left { 0 } else { 1 },
+            sampled: d.sampled as u64,
+            reason: d.reason.clone(),
+        })
+        .collect();
+    pb_profile.rng_seed = profile.rng_seed.unwrap_or(0);
+    pb_profile
+}
+
+fn operators_to_pb(operators: &[OperatorProfile]) -> pb::JobProfile {
+    let operators = operators
+        .iter()
+        .map(|op| pb::OperatorProfile {
+            index: op.index as u32,
+            name: op.name.clone(),
+            workers: op.records.count,
+            min_elapsed_us: op.elapsed_us.min,
+            max_elapsed_us: op.elapsed_us.max,
+            avg_elapsed_us: op.elapsed_us.avg(),
+            min_records: op.records.min,
+            max_records: op.records.max,
+            avg_records: op.records.avg(),
+            total_records: op.records.sum,
+            cross_server_bytes: op.cross_server_bytes.sum,
+            stalls: op.stalls.sum,
+        })
+        .collect();
+    pb::JobProfile { operators, decisions: vec![], rng_seed: 0 }
+}
+
+/// Convert a record of the slow query log into the `SlowQuery` sent to the client;
+pub fn slow_query_to_pb(record: &SlowQueryRecord) -> pb::SlowQuery {
+    let status = match record.status {
+        JobStatus::Completed => pb::JobStatus::Completed,
+        JobStatus::Failed => pb::JobStatus::Failed,
+        JobStatus::Cancelled => pb::JobStatus::Cancelled,
+        JobStatus::TimedOut => pb::JobStatus::TimedOut,
+    };
+    pb::SlowQuery {
+        job_id: record.job_id,
+        job_name: record.job_name.clone(),
+        plan_hash: record.plan_hash,
+        conf: record.conf_json(),
+        status: status as i32,
+        error: record.error.clone().unwrap_or_default(),
+        elapsed_ms: record.elapsed_ms,
+        peak_memory: record.peak_memory,
+        profile: Some(operators_to_pb(&record.operators)),
+        finished_at_ms: record.finished_at_ms,
+    }
+}
+
+/// The hash of what the job runs, which is the same for the runs of the same query, to tell them
+/// in the slow query log;
+fn plan_hash(
+    source: &Option<pb::Source>, plan: &Option<pb::TaskPlan>, sink: &Option<pb::Sink>,
+) -> u64 {
+    // the encoding never fails as the buffer grows for the messages;
+    let mut buf = vec![];
+    if let Some(source) = source {
+        source.encode(&mut buf).ok();
+    }
+    if let Some(plan) = plan {
+        plan.encode(&mut buf).ok();
+    }
+    if let Some(sink) = sink {
+        sink.encode(&mut buf).ok();
     }
+    let mut hasher = DefaultHasher::new();
+    hasher.write(&buf);
+    hasher.finish()
+}
+
+/// Whether the plan ends with a small limit, i.e. of no more than `EAGER_LIMIT`, followed by the
+/// maps and filters only, so the job waits for its first results only, and runs in the eager mode
+/// unless its latency mode is told;
+fn ends_with_small_limit(plan: &[pb::OperatorDef]) -> bool {
+    use pb::operator_def::OpKind;
+    for op in plan.iter().rev() {
+        match op.op_kind.as_ref() {
+            Some(OpKind::Map(_)) | Some(OpKind::Filter(_)) => (),
+            Some(OpKind::Limit(limit)) => return limit.limit <= EAGER_LIMIT,
+            _ => return false,
+        }
+    }
+    false
 }
 
 #[derive(Clone)]
 pub struct Service<D: AnyData> {
     factory: Arc<dyn JobCompiler<D>>,
     pub job_guards: Arc<ShardedLock<HashMap<u64, JobGuard>>>,
+    // the pages of the results of the paged jobs;
+    cursors: Arc<PageCursors>,
 }
 
 impl<D: AnyData> Service<D> {
@@ -86,26 +503,315 @@ impl<D: AnyData> Service<D> {
         Service {
             factory: Arc::new(factory),
             job_guards: Arc::new(ShardedLock::new(HashMap::new())),
+            cursors: Arc::new(PageCursors::default()),
+        }
+    }
+
+    /// Fetch a page of the results of a job submitted with a page size, which waits for the page
+    /// while the job is running, at most `wait_ms` or `DEFAULT_FETCH_WAIT` if 0;
+    pub fn fetch_page(&self, req: pb::FetchPageRequest) -> pb::FetchPageResponse {
+        let wait =
+            if req.wait_ms == 0 { DEFAULT_FETCH_WAIT } else { Duration::from_millis(req.wait_ms) };
+        let mut res = pb::FetchPageResponse { job_id: req.job_id, ..Default::default() };
+        match self.cursors.fetch(req.job_id, req.token, wait) {
+            Ok(page) => {
+                res.data = page.data;
+                res.next_token = page.next_token;
+                res.last = page.last;
+            }
+            Err(err_msg) => {
+                res.next_token = req.token;
+                res.err = Some(pb::JobError {
+                    err_code: CURSOR_ERR_CODE,
+                    err_msg,
+                    worker: -1,
+                    ..Default::default()
+                });
+            }
+        }
+        res
+    }
+
+    /// Cancel the cursor of a paged job, which releases the pages not fetched yet, and cancels
+    /// the job if it is still running;
+    pub fn cancel_cursor(&self, req: pb::CancelCursorRequest) -> pb::CancelCursorResponse {
+        let cancelled = self.cursors.cancel(req.job_id);
+        if cancelled {
+            self.cancel_job(req.job_id);
+        }
+        pb::CancelCursorResponse { cancelled }
+    }
+
+    /// Cancel the job if it is still running, e.g. as its client stalls, which tells whether the
+    /// job is found;
+    pub fn cancel_job(&self, job_id: u64) -> bool {
+        let guard = self.job_guards.write().ok().and_then(|mut guards| guards.remove(&job_id));
+        if let Some(mut guard) = guard {
+            guard.cancel_execute();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// The plan version and the features supported by current server
+    pub fn capabilities(&self) -> pb::CapabilitiesResponse {
+        capability::capabilities(&self.factory.features())
+    }
+
+    /// The schema of the data the jobs run on, told by the compiler
+    pub fn schema(&self) -> pb::SchemaResponse {
+        pb::SchemaResponse { schema: self.factory.schema().unwrap_or_default() }
+    }
+
+    /// Tell the compiler that current server is drained, see `JobCompiler::on_drain`;
+    pub fn on_drain(&self) {
+        self.factory.on_drain()
+    }
+
+    /// The latest records of the slow query log of current server, which is empty if the log is
+    /// not enabled, see `pegasus::slow_query`
+    pub fn slow_queries(&self, req: pb::SlowQueriesRequest) -> pb::SlowQueriesResponse {
+        let records = pegasus::fetch_slow_queries();
+        let skip =
+            if req.limit == 0 { 0 } else { records.len().saturating_sub(req.limit as usize) };
+        let queries = records[skip..].iter().map(slow_query_to_pb).collect();
+        pb::SlowQueriesResponse { queries }
+    }
+
+    /// The latencies of the query templates run on current server, see `crate::latency`;
+    pub fn latency_templates(
+        &self, req: pb::LatencyTemplatesRequest,
+    ) -> pb::LatencyTemplatesResponse {
+        let templates = latency::latency_recorder().top_by_p99(req.top as usize);
+        pb::LatencyTemplatesResponse { templates }
+    }
+
+    pub fn accept<O: Output + Clone>(&self, mut req: pb::JobRequest, output: O) {
+        let checked = self.check(&mut req);
+        self.accept_checked(req, checked, |_| {}, output)
+    }
+
+    /// Accept the queries of the batch, each as a job of its own, whose responses are framed by
+    /// the indexes of the queries into the output, see `crate::batch`; The queries failing
+    /// validation are rejected on their own, while the others are admitted all at once by
+    /// `pegasus::admit`, or the batch is rejected as a whole with the queries failed listed;
+    /// The queries not submitted yet as the batch is cancelled by `cancel_batch` end as cancelled;
+    pub fn accept_batch<B: BatchOutput>(
+        &self, mut batch: pb::BatchRequest, jobs: &BatchJobs, output: B,
+    ) {
+        let running =
+            |job_id| self.job_guards.read().map(|g| g.contains_key(&job_id)).unwrap_or(false);
+        if let Err(msg) = batch::check_batch(&batch, running) {
+            let err = QueryError::Validation { op_index: vec![], msg, cause: None };
+            output.send(batch::admission_frame(false, vec![], Some(err.to_pb())));
+            return;
+        }
+        let checked: Vec<Result<bool, QueryError>> =
+            batch.requests.iter_mut().map(|req| self.check(req)).collect();
+        let invalid: Vec<u32> = checked
+            .iter()
+            .enumerate()
+            .filter(|(_, checked)| checked.is_err())
+            .map(|(index, _)| index as u32)
+            .collect();
+        let admitted: Vec<u64> = batch
+            .requests
+            .iter()
+            .zip(checked.iter())
+            .filter(|(_, checked)| checked.is_ok())
+            .filter_map(|(req, _)| req.conf.as_ref().map(|conf| conf.job_id))
+            .collect();
+        // the slots not taken by the queries, e.g. those cancelled, are released at last
+        let _admission = match pegasus::admit(&admitted) {
+            Ok(admission) => admission,
+            Err(err) => {
+                let err = QueryError::from(err);
+                output.send(batch::admission_frame(false, invalid, Some(err.to_pb())));
+                return;
+            }
+        };
+        output.send(batch::admission_frame(true, invalid, None));
+        let output = Arc::new(output);
+        for (index, (req, checked)) in batch.requests.into_iter().zip(checked).enumerate() {
+            let job_id = req.conf.as_ref().map(|conf| conf.job_id).unwrap_or_default();
+            let checked = match checked {
+                Ok(_) if jobs.is_cancelled() => Err(QueryError::Cancelled {
+                    worker: None,
+                    reason: "batch cancelled".to_owned(),
+                }),
+                checked => checked,
+            };
+            let query = QueryOutput::new(index as u32, output.clone(), jobs);
+            self.accept_checked(req, checked, |_| {}, query);
+            // the job submitted as the batch is being cancelled, which may miss the cancellation
+            if jobs.is_cancelled() {
+                self.cancel_job(job_id);
+            }
         }
     }
 
-    pub fn accept<O: Output + Clone>(&self, req: pb::JobRequest, output: O) {
-        // validate request;
+    /// Cancel all the queries of the batch, including those not submitted yet;
+    pub fn cancel_batch(&self, jobs: &BatchJobs) {
+        jobs.cancel();
+        for job_id in jobs.job_ids() {
+            self.cancel_job(*job_id);
+        }
+    }
+
+    /// Check if the request is supported and valid, and then simplify its plan, see
+    /// `JobCompiler::simplify`; returns whether the plan is simplified, or the error rejecting it;
+    fn check(&self, req: &mut pb::JobRequest) -> Result<bool, QueryError> {
+        // the unsupported features are checked ahead, which the validation is unaware of;
+        capability::check_request(req, &self.factory.features())
+            .map_err(QueryError::unsupported_plan)?;
+        self.factory.validate(req)?;
+        Ok(self.factory.simplify(req)?)
+    }
+
+    /// Accept the request checked and validated ahead, e.g. a query prepared once to be submitted
+    /// again and again with only its parameters changed, which is neither checked nor validated
+    /// again; `bind` sets the resources of the job prepared for the request, e.g. its parameters
+    /// bound to the decoded plan, after those set by `JobCompiler::prepare`;
+    pub fn accept_prepared<O, F>(&self, req: pb::JobRequest, bind: F, output: O)
+    where
+        O: Output + Clone,
+        F: FnOnce(&mut JobConf),
+    {
+        self.accept_checked(req, Ok(false), bind, output)
+    }
+
+    fn accept_checked<O, F>(
+        &self, req: pb::JobRequest, checked: Result<bool, QueryError>, bind: F, output: O,
+    ) where
+        O: Output + Clone,
+        F: FnOnce(&mut JobConf),
+    {
+        let (rejected, simplified) = match checked {
+            Ok(simplified) => (None, simplified),
+            Err(err) => (Some(err), false),
+        };
+        let dependency = req.conf.as_ref().and_then(Dependency::of);
+        // the results told without running the job are sent as those of the job, unless the job
+        // reads the output of another job instead of its source;
+        let shortcut = match req.sink.as_ref().and_then(|sink| sink.sinker.as_ref()) {
+            None | Some(pb::sink::Sinker::Resource(_))
+                if rejected.is_none() && dependency.is_none() =>
+            {
+                self.factory.shortcut(&req)
+            }
+            _ => None,
+        };
+        // the rejected and the explained jobs never run, whose latencies are not recorded;
+        let timed =
+            rejected.is_none() && !req.conf.as_ref().map(|conf| conf.explain).unwrap_or(true);
+        let template = if timed { self.factory.template_hash(&req) } else { None };
         // check if job conf lost;
-        let pb::JobRequest { conf, source, plan, sink } = req;
+        let pb::JobRequest { conf, source, plan, sink, graph, .. } = req;
         if let Some(conf) = conf {
-            let conf = parse_job_conf(conf);
-            let output = JobResultSink::new(conf.job_id, output);
+            let explain = conf.explain;
+            let encode_in_service = conf.encode_in_service;
+            // the explained jobs never run, which have no output to be kept;
+            let keep_output = conf.keep_output && !explain;
+            let page_size = conf.page_size;
+            // the explained jobs send their plans instead of any result to be paged;
+            let cursor = if page_size > 0 && rejected.is_none() && !explain {
+                Some(self.cursors.open(&conf))
+            } else {
+                None
+            };
+            let auto_latency = conf.latency_mode == pb::LatencyMode::Auto as i32;
+            let mut conf = parse_job_conf(conf);
+            conf.plan_hash = plan_hash(&source, &plan, &sink);
+            if auto_latency
+                && plan.as_ref().map(|p| ends_with_small_limit(&p.plan)).unwrap_or(false)
+            {
+                conf.latency_mode = LatencyMode::Eager;
+            }
+            if let (WorkerHint::Auto { .. }, Some(source)) = (conf.get_worker_hint(), &source) {
+                conf.resolve_workers(self.factory.estimate_workers(&graph, &source.resource));
+            }
+            // only the results of the sinks by resource are of the data the jobs read;
+            let rejected = match sink.as_ref().and_then(|sink| sink.sinker.as_ref()) {
+                Some(pb::sink::Sinker::Fold(_)) | Some(pb::sink::Sinker::Group(_))
+                    if keep_output && rejected.is_none() =>
+                {
+                    let msg = "only the results of the sinks by resource can be kept".to_owned();
+                    Some(QueryError::Validation { op_index: vec![], msg, cause: None })
+                }
+                _ => rejected,
+            };
+            // the rejected jobs are never prepared, as they never run;
+            let rejected = match rejected {
+                Some(err) => Some(err),
+                None => self.factory.prepare(&graph, &mut conf).err(),
+            };
+            bind(&mut conf);
+            let mut output = JobResultSink::with_workers(conf.job_id, conf.workers, output);
+            if encode_in_service {
+                output = output.with_service_encoding();
+            }
+            if timed {
+                output = output.with_template(template.unwrap_or(conf.plan_hash), &conf.job_name);
+            }
+            // kept ahead of any rejection, which fails the jobs depending on the job;
+            if keep_output {
+                output = output.with_kept_output();
+            }
+            if let Some(err) = rejected {
+                output.on_query_error(&err);
+                output.close();
+                return;
+            }
+            if let (true, Some(source)) = (explain, source.as_ref()) {
+                let flags =
+                    pb::JobPlan { shortcut: shortcut.is_some(), simplified, ..Default::default() };
+                self.explain(conf, &graph, source, &plan, &sink, flags, output);
+                return;
+            }
+            if let Some(store) = cursor {
+                output.on_cursor(pb::PageCursor {
+                    page_size,
+                    ttl_ms: store.ttl().as_millis() as u64,
+                });
+                output = output.with_pages(store);
+            }
+            if let Some(results) = shortcut {
+                let res = match sink.and_then(|sink| sink.sinker) {
+                    Some(pb::sink::Sinker::Resource(res)) => res,
+                    _ => vec![],
+                };
+                match self.factory.sink(&res) {
+                    Ok(ec) => {
+                        if !results.is_empty() {
+                            output.keep_results(0, &results);
+                            output.on_results(results, &ec);
+                        }
+                    }
+                    Err(err) => output.on_query_error(&err.into()),
+                }
+                output.finish();
+                return;
+            }
             if let Some(source) = source {
-                if plan.is_some() && !plan.as_ref().unwrap().plan.is_empty() {
-                    self.submit(conf, source, plan, sink, output);
+                if let Some(dependency) = dependency {
+                    self.submit_dependent(conf, dependency, source, plan, sink, output);
+                } else if plan.is_some() && !plan.as_ref().unwrap().plan.is_empty() {
+                    self.submit(conf, source, plan, sink, None, output);
+                } else if !graph.is_empty()
+                    || matches!(conf.get_worker_hint(), WorkerHint::Auto { .. })
+                {
+                    // the sources of the named graphs are read by the workers, as only the jobs
+                    // are bound to the graphs their requests name, and so are those of the jobs
+                    // hinting their workers by the size of the sources, which they are split by
+                    self.submit(conf, source, None, sink, None, output);
                 } else {
                     let ec = if let Some(sink) = sink {
                         match sink.sinker {
                             None => self.factory.sink(&vec![]),
                             Some(pb::sink::Sinker::Resource(res)) => self.factory.sink(&res),
                             _ => {
-                                self.submit(conf, source, None, Some(sink), output);
+                                self.submit(conf, source, None, Some(sink), None, output);
                                 return;
                             }
                         }
@@ -115,23 +821,39 @@ impl<D: AnyData> Service<D> {
                     match self.factory.source(&source.resource) {
                         Ok(src) => match ec {
                             Ok(ec) => {
-                                let mut vec = Vec::new();
-                                vec.extend(src);
-                                if !vec.is_empty() {
-                                    let result = ec.encode(vec);
-                                    output.on_next(result);
+                                // sent in batches as read instead of all at once, as the output
+                                // may wait for the client to drain the results sent before;
+                                let batch_size = conf.batch_size.max(1) as usize;
+                                let mut batch = Vec::with_capacity(batch_size);
+                                for item in src {
+                                    batch.push(item);
+                                    if batch.len() >= batch_size {
+                                        let full = std::mem::replace(
+                                            &mut batch,
+                                            Vec::with_capacity(batch_size),
+                                        );
+                                        output.keep_results(0, &full);
+                                        output.on_results(full, &ec);
+                                    }
+                                }
+                                if !batch.is_empty() {
+                                    output.keep_results(0, &batch);
+                                    output.on_results(batch, &ec);
                                 }
                             }
-                            Err(err) => output.on_error(&err),
+                            Err(err) => output.on_query_error(&err.into()),
                         },
-                        Err(e) => {
-                            output.on_error(&e);
-                        }
+                        Err(err) => output.on_query_error(&err.into()),
                     }
-                    output.close();
+                    output.finish();
                 }
             } else {
-                output.on_err_msg(0, "source of job not found;");
+                let msg = "source of job not found;".to_owned();
+                output.on_query_error(&QueryError::Validation {
+                    op_index: vec![],
+                    msg,
+                    cause: None,
+                });
                 output.close();
             }
         } else {
@@ -140,10 +862,91 @@ impl<D: AnyData> Service<D> {
         }
     }
 
+    /// Send the plan of the job instead of running it, as it would be run, see `JobConfig.explain`,
+    /// or the error failing its dataflow to be built; `flags` tells how the request is answered
+    /// or rewritten before explained, i.e. `JobPlan.shortcut` and `JobPlan.simplified`;
+    #[allow(clippy::too_many_arguments)]
+    fn explain<O: Output + Clone>(
+        &self, conf: JobConf, graph: &str, source: &pb::Source, task: &Option<pb::TaskPlan>,
+        sink: &Option<pb::Sink>, flags: pb::JobPlan, output: JobResultSink<O>,
+    ) {
+        let mut plan = crate::explain::explain_plan(self.factory.as_ref(), graph, source, task);
+        plan.workers = conf.workers;
+        plan.servers = conf.servers().len().max(1) as u32;
+        plan.shortcut = flags.shortcut;
+        plan.simplified = flags.simplified;
+        let fusible = !conf.profile;
+        let source = source.clone();
+        let task = task.clone();
+        let sink = sink.clone();
+        let factory = self.factory.clone();
+        let sink_output = output.clone();
+        let desc = pegasus::explain(conf, |worker| {
+            worker.dataflow(move |builder| {
+                build_dataflow(
+                    builder,
+                    &factory,
+                    &source,
+                    &task,
+                    &sink,
+                    &None,
+                    sink_output,
+                    fusible,
+                )
+            })
+        });
+        match desc {
+            Ok(desc) => {
+                crate::explain::add_dataflow(&desc, &mut plan);
+                output.on_plan(plan);
+                output.finish();
+            }
+            Err(err) => {
+                output.on_query_error(&err.into());
+                output.close();
+            }
+        }
+    }
+
+    /// Queue the job for the job it depends on, which is submitted reading the output of that job
+    /// as its source once that job completes, or fails if that job fails, see `crate::dependency`;
+    fn submit_dependent<O: Output + Clone>(
+        &self, conf: JobConf, dependency: Dependency, source: pb::Source,
+        task: Option<pb::TaskPlan>, sink: Option<pb::Sink>, output: JobResultSink<O>,
+    ) {
+        let job_id = conf.job_id;
+        if let Err(err) = dependency::job_outputs().depend(job_id, dependency.job_id) {
+            output.on_query_error(&err);
+            output.close();
+            return;
+        }
+        let service = self.clone();
+        let queued = output.clone();
+        let spawned = std::thread::Builder::new().name(format!("dependent-job-{}", job_id)).spawn(
+            move || match dependency::job_outputs().wait::<D>(job_id, &dependency) {
+                Ok(input) => {
+                    service.submit(conf, source, task, sink, Some(Arc::new(input)), queued)
+                }
+                Err(err) => {
+                    queued.on_query_error(&err);
+                    queued.close();
+                }
+            },
+        );
+        if let Err(err) = spawned {
+            dependency::job_outputs().dequeue(job_id);
+            output.on_query_error(&QueryError::internal(format!("fail to queue the job: {}", err)));
+            output.close();
+        }
+    }
+
     fn submit<O: Output + Clone>(
         &self, conf: JobConf, source: pb::Source, task: Option<pb::TaskPlan>,
-        sink: Option<pb::Sink>, output: JobResultSink<O>,
+        sink: Option<pb::Sink>, input: Option<Arc<JobOutput<D>>>, output: JobResultSink<O>,
     ) {
+        let output = if conf.profile { output.with_profile() } else { output };
+        // each operator of a profiled job is materialized to be measured on its own
+        let fusible = !conf.profile;
         let task = Arc::new(task);
         let source = Arc::new(source);
         let sink = Arc::new(sink);
@@ -151,63 +954,11 @@ impl<D: AnyData> Service<D> {
             let source = source.clone();
             let task = task.clone();
             let sink = sink.clone();
+            let input = input.clone();
             let factory = self.factory.clone();
             let output = output.clone();
             worker.dataflow(move |builder| {
-                let src = factory.source(&source.resource)?.fuse();
-                let source = builder.input_from_iter(src)?;
-                let stream = if let Some(task) = task.as_ref() {
-                    crate::materialize::exec(&source, &task.plan, &factory)?
-                } else {
-                    source
-                };
-
-                if let Some(sink) = sink.as_ref() {
-                    match &sink.sinker {
-                        Some(pb::sink::Sinker::Fold(fold)) => {
-                            let range = RANGES[fold.range as usize];
-                            let accum_kind: pb::AccumKind =
-                                unsafe { std::mem::transmute(fold.accum) };
-                            match accum_kind {
-                                pb::AccumKind::Cnt => {
-                                    let funcs = factory.fold(&vec![], &vec![], &vec![])?;
-                                    let ec = funcs.fold_sink()?;
-                                    let s = stream.count(range)?;
-                                    sink_fold(&s, ec, output)?;
-                                }
-                                pb::AccumKind::ToList => {
-                                    let funcs = factory.fold(&vec![], &vec![], &vec![])?;
-                                    let ec = funcs.fold_sink()?;
-                                    let s = stream.fold_with_accum(range, ToListAccum::new())?;
-                                    sink_fold(&s, ec, output)?;
-                                }
-                                _ => unimplemented!(),
-                            }
-                        }
-                        Some(pb::sink::Sinker::Group(group)) => {
-                            let range = RANGES[group.range as usize];
-                            let funcs = factory.group(&group.map, &vec![], &vec![])?;
-                            let key_func = funcs.key()?;
-                            let map_factory = funcs.map_factory()?;
-                            let ec = funcs.sink()?;
-                            let shade_map = ShadeMapFactory::new(map_factory);
-                            let s = stream.key_by(key_func)?.group_with_map(range, shade_map)?;
-                            sink_shade(&s, ec, output)?;
-                        }
-                        Some(pb::sink::Sinker::Resource(res)) => {
-                            let ec = factory.sink(&res)?;
-                            sink_with_encoder(&stream, ec, output)?;
-                        }
-                        None => {
-                            let ec = factory.sink(&vec![])?;
-                            sink_with_encoder(&stream, ec, output)?;
-                        }
-                    }
-                } else {
-                    let ec = factory.sink(&vec![])?;
-                    sink_with_encoder(&stream, ec, output)?;
-                }
-                Ok(())
+                build_dataflow(builder, &factory, &source, &task, &sink, &input, output, fusible)
             })
         });
 
@@ -216,23 +967,153 @@ impl<D: AnyData> Service<D> {
                 let mut w = self.job_guards.write().expect("fetch write lock failure;");
                 w.insert(guard.job_id, guard);
             }
-            Err(err) => {
-                output.on_error(&err);
-            }
+            Err(err) => output.on_query_error(&err.into()),
             _ => (),
         }
     }
 }
 
+/// Build the dataflow of the job on a worker, from the source through the operators of the plan
+/// into the sink, where the leading operators may be fused into the source if `fusible`; The
+/// source is the `input` instead if any, i.e. the output of the job the job depends on;
+#[allow(clippy::too_many_arguments)]
+fn build_dataflow<D: AnyData, O: Output + Clone>(
+    builder: &DataflowBuilder, factory: &Arc<dyn JobCompiler<D>>, source: &pb::Source,
+    task: &Option<pb::TaskPlan>, sink: &Option<pb::Sink>, input: &Option<Arc<JobOutput<D>>>,
+    output: JobResultSink<O>, fusible: bool,
+) -> Result<(), BuildJobError> {
+    let plan = task.as_ref().map(|t| t.plan.as_slice()).unwrap_or(&[]);
+    let fused_source = match input {
+        None if fusible => factory.fused_source(&source.resource, plan)?,
+        _ => None,
+    };
+    let (src, fused) = match (fused_source, input) {
+        (Some((src, fused)), _) => (src, fused),
+        (None, Some(input)) => {
+            let worker = pegasus::get_current_worker()
+                .unwrap_or_else(|| pegasus::WorkerId::new(0, 1, 0, false));
+            let src: Box<dyn Iterator<Item = D> + Send> = Box::new(input.read(&worker).into_iter());
+            (src, 0)
+        }
+        (None, None) => (factory.source(&source.resource)?, 0),
+    };
+    // the size of the scan doesn't tell how many records pass the fused operators
+    let size = match input {
+        None if fused == 0 => factory.source_size(&source.resource),
+        _ => None,
+    };
+    let src = src.fuse();
+    let source = match size {
+        Some(size) => builder.input_from_iter_sized(src, Some(size))?,
+        None => builder.input_from_iter(src)?,
+    };
+    let stream = match task {
+        Some(_) if fused > 0 && fused == plan.len() => source,
+        Some(_) => crate::materialize::exec(&source, &plan[fused..], factory)?,
+        None => source,
+    };
+
+    if let Some(sink) = sink.as_ref() {
+        match &sink.sinker {
+            Some(pb::sink::Sinker::Fold(fold)) => {
+                let range = RANGES[fold.range as usize];
+                let accum_kind = pb::AccumKind::from_i32(fold.accum).ok_or_else(|| {
+                    BuildJobError::Unsupported(format!("unknown accum kind {}", fold.accum))
+                })?;
+                // the unfold of the sink fold may carry side effects, e.g. `cap("x")`
+                let unfold_res =
+                    fold.unfold.as_ref().map(|f| f.resource.clone()).unwrap_or_default();
+                match accum_kind {
+                    pb::AccumKind::Cnt => {
+                        let funcs = factory.fold(&[], &unfold_res, &[])?;
+                        let ec = funcs.fold_sink()?;
+                        let s = count(&stream, range)?;
+                        sink_fold(&s, ec, output)?;
+                    }
+                    pb::AccumKind::ToList => {
+                        let funcs = factory.fold(&[], &unfold_res, &[])?;
+                        let ec = funcs.fold_sink()?;
+                        let s = with_unbulked(&stream, |s| {
+                            s.fold_with_accum(range, ToListAccum::new())
+                        })?;
+                        sink_fold(&s, ec, output)?;
+                    }
+                    pb::AccumKind::Sum
+                    | pb::AccumKind::Max
+                    | pb::AccumKind::Min
+                    | pb::AccumKind::Mean
+                    | pb::AccumKind::Custom => {
+                        let funcs = factory.fold(&fold.resource, &[], &[])?;
+                        let accum = ShadeAccumFactory::new(funcs.accumulate()?);
+                        let ec = funcs.fold_sink()?;
+                        let s = stream.fold_with_accum(range, accum)?;
+                        sink_fold(&s, ec, output)?;
+                    }
+                    pb::AccumKind::ApproxDistinct => {
+                        let funcs = factory.fold(&[], &unfold_res, &[])?;
+                        let ec = funcs.fold_sink()?;
+                        let s = approx_distinct(&stream, range, fold.precision)?;
+                        sink_fold(&s, ec, output)?;
+                    }
+                    pb::AccumKind::Quantiles => {
+                        let funcs = factory.fold(&[], &unfold_res, &[])?;
+                        let ec = funcs.fold_sink()?;
+                        let s = quantiles(&stream, range, &fold.quantiles)?
+                            .map_with_fn(Pipeline, |values: Vec<f64>| {
+                                Ok(QuantileValues { values })
+                            })?;
+                        sink_fold(&s, ec, output)?;
+                    }
+                    other => {
+                        let msg = format!("accum kind {:?} of the sink fold", other);
+                        return Err(BuildJobError::Unsupported(msg));
+                    }
+                }
+            }
+            Some(pb::sink::Sinker::Group(group)) => {
+                let range = RANGES[group.range as usize];
+                let funcs = factory.group(&group.map, &[], &[])?;
+                let key_func = funcs.key()?;
+                let map_factory = funcs.map_factory()?;
+                let ec = funcs.sink()?;
+                let shade_map = ShadeMapFactory::new(map_factory);
+                let s = with_unbulked(&stream, |s| {
+                    s.key_by(key_func)?.group_with_map(range, shade_map)
+                })?;
+                sink_shade(&s, ec, output)?;
+            }
+            Some(pb::sink::Sinker::Resource(res)) => {
+                let ec = factory.sink(res)?;
+                with_consolidated(&stream, |s| sink_with_encoder(s, ec, output))?;
+            }
+            None => {
+                let ec = factory.sink(&[])?;
+                with_consolidated(&stream, |s| sink_with_encoder(s, ec, output))?;
+            }
+        }
+    } else {
+        let ec = factory.sink(&[])?;
+        with_consolidated(&stream, |s| sink_with_encoder(s, ec, output))?;
+    }
+    Ok(())
+}
+
 #[inline]
 fn sink_with_encoder<D: Data, O: Output + Clone>(
     stream: &Stream<D>, ec: Box<dyn EncodeFunction<D>>, output: JobResultSink<O>,
 ) -> Result<(), BuildJobError> {
+    if output.in_service {
+        return sink_to_service(stream, ec, output);
+    }
+    let worker = pegasus::get_current_worker().map(|w| w.index).unwrap_or(0);
     stream.sink_by(|_meta| {
         move |_tag, result| match result {
             ResultSet::Data(data) => {
-                let bytes = ec.encode(data);
-                output.on_next(bytes);
+                output.keep_results(worker, &data);
+                output.on_results(data, &ec);
+            }
+            ResultSet::ScopeEnd(tag) => {
+                output.on_scope_end(tag);
             }
             ResultSet::End => {
                 output.close();
@@ -241,20 +1122,71 @@ fn sink_with_encoder<D: Data, O: Output + Clone>(
     })
 }
 
+/// Ship the results of a worker to the service in the codec of the records, where they are encoded
+/// by `ec` on a thread of the service instead of the worker, in the order they are shipped;
+fn sink_to_service<D: Data, O: Output + Clone>(
+    stream: &Stream<D>, ec: Box<dyn EncodeFunction<D>>, output: JobResultSink<O>,
+) -> Result<(), BuildJobError> {
+    let (tx, rx) = mpsc::channel::<ResultSet<u8>>();
+    let encoder = output.clone();
+    let worker = pegasus::get_current_worker().map(|w| w.index).unwrap_or(0);
+    std::thread::Builder::new()
+        .name(format!("result-encoder-{}", output.job_id))
+        .spawn(move || encode_in_service(rx, ec, encoder))
+        .map_err(|e| format!("fail to start the result encoder: {}", e))?;
+    stream.sink_by(|_meta| {
+        move |_tag, result| {
+            let shipped = match result {
+                ResultSet::Data(data) => {
+                    output.keep_results(worker, &data);
+                    let mut bytes = vec![];
+                    if let Err(err) = data.write_to(&mut bytes) {
+                        output.on_error(&err);
+                        return;
+                    }
+                    output.on_shipped(bytes.len());
+                    ResultSet::Data(bytes)
+                }
+                ResultSet::ScopeEnd(tag) => ResultSet::ScopeEnd(tag),
+                ResultSet::End => ResultSet::End,
+            };
+            if tx.send(shipped).is_err() {
+                error!("job[{}] the result encoder has stopped", output.job_id);
+            }
+        }
+    })
+}
+
+// encode the results shipped by a worker until the worker drops its sink
+fn encode_in_service<D: Data, O: Output>(
+    rx: mpsc::Receiver<ResultSet<u8>>, ec: Box<dyn EncodeFunction<D>>,
+    output: JobResultSink<O>,
+) {
+    for result in rx.iter() {
+        match result {
+            ResultSet::Data(bytes) => match Vec::<D>::read_from(&mut bytes.as_slice()) {
+                Ok(data) => output.on_results(data, &ec),
+                Err(err) => output.on_error(&err),
+            },
+            ResultSet::ScopeEnd(tag) => output.on_scope_end(tag),
+            ResultSet::End => output.close(),
+        }
+    }
+}
+
 #[inline]
 fn sink_fold<D: Data + Accumulator<A>, O: Output + Clone, A: 'static>(
     stream: &Stream<D>, ec: Box<dyn EncodeFunction<Box<dyn Accumulator<A>>>>,
     output: JobResultSink<O>,
 ) -> Result<(), BuildJobError> {
-    stream.sink_by(|_meta| {
+    let boxed = |fold: D| Box::new(fold) as Box<dyn Accumulator<A>>;
+    stream.sink_map_by(boxed, |_meta| {
         move |_tag, result| match result {
             ResultSet::Data(data) => {
-                let bytes = ec.encode(
-                    data.into_iter()
-                        .map(|fold| Box::new(fold) as Box<dyn Accumulator<A>>)
-                        .collect(),
-                );
-                output.on_next(bytes);
+                output.on_results(data, &ec);
+            }
+            ResultSet::ScopeEnd(tag) => {
+                output.on_scope_end(tag);
             }
             ResultSet::End => {
                 output.close();
@@ -268,11 +1200,14 @@ fn sink_shade<D: Send + Debug + 'static, O: Output + Clone>(
     stream: &Stream<NeverClone<ShadeCodec<D>>>, ec: Box<dyn EncodeFunction<D>>,
     output: JobResultSink<O>,
 ) -> Result<(), BuildJobError> {
-    stream.sink_by(|_meta| {
+    let unshaded = |shade: NeverClone<ShadeCodec<D>>| shade.take().take();
+    stream.sink_map_by(unshaded, |_meta| {
         move |_tag, result| match result {
             ResultSet::Data(data) => {
-                let bytes = ec.encode(data.into_iter().map(|shade| shade.take().take()).collect());
-                output.on_next(bytes);
+                output.on_results(data, &ec);
+            }
+            ResultSet::ScopeEnd(tag) => {
+                output.on_scope_end(tag);
             }
             ResultSet::End => {
                 output.close();
@@ -293,12 +1228,53 @@ fn parse_job_conf(conf: pb::JobConfig) -> JobConf {
     if conf.output_capacity != 0 {
         job_conf.output_capacity = conf.output_capacity;
     }
+    job_conf.batch_flush_interval_ms = conf.batch_flush_interval_ms;
     if conf.memory_limit != 0 {
         job_conf.memory_limit = conf.memory_limit;
     }
     job_conf.plan_print = conf.plan_print;
+    job_conf.cache_limit = conf.cache_limit;
+    job_conf.property_cache_ttl_ms = conf.property_cache_ttl_ms;
+    job_conf.fresh_reads = conf.fresh_reads;
+    job_conf.bulking = conf.bulking;
+    job_conf.sink_consolidation = conf.sink_consolidation;
+    job_conf.split_degree = conf.split_degree;
+    job_conf.split_local = conf.split_local;
+    job_conf.min_version = conf.min_version;
+    if conf.min_version_wait_ms != 0 {
+        job_conf.min_version_wait_ms = conf.min_version_wait_ms;
+    }
+    job_conf.vertex_limit = conf.vertex_limit;
+    job_conf.edge_limit = conf.edge_limit;
+    job_conf.result_limit = conf.result_limit;
+    job_conf.network_byte_limit = conf.network_byte_limit;
+    job_conf.skew_factor = conf.skew_factor;
+    job_conf.session_id = conf.session_id;
+    job_conf.profile = conf.profile;
+    job_conf.slice_records = conf.slice_records;
+    job_conf.slice_us = conf.slice_us;
+    if conf.has_rng_seed {
+        job_conf.rng_seed = Some(conf.rng_seed);
+    }
+    if conf.latency_mode == pb::LatencyMode::Eager as i32 {
+        job_conf.latency_mode = LatencyMode::Eager;
+    }
+    if conf.overflow == pb::OverflowPolicy::Saturate as i32 {
+        job_conf.overflow = OverflowPolicy::Saturate;
+    }
     if !conf.servers.is_empty() {
         job_conf.add_servers(&conf.servers);
     }
+    if let Some(hint) = conf.worker_hint {
+        match pb::worker_hint::Kind::from_i32(hint.kind) {
+            Some(pb::worker_hint::Kind::PerCore) => {
+                job_conf.workers(WorkerHint::PerCore);
+            }
+            Some(pb::worker_hint::Kind::Auto) => {
+                job_conf.workers(WorkerHint::Auto { min: hint.min.max(1), max: hint.max });
+            }
+            _ => (),
+        }
+    }
     job_conf
 }